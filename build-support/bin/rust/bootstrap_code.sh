@@ -12,6 +12,7 @@ source "${REPO_ROOT}/build-support/common.sh"
 # + NATIVE_ROOT: The Rust code directory, ie: src/rust/engine.
 # + MODE: Whether to run in debug or release mode.
 # + MODE_FLAG: The string to pass to Cargo to determine if we're in debug or release mode.
+# + ENGINE_FEATURES: Additional features of the engine to build with.
 # Exposes:
 # + calculate_current_hash: Generate a stable hash to determine if we need to rebuild the engine.
 # shellcheck source=build-support/bin/rust/calculate_engine_hash.sh
@@ -40,7 +41,7 @@ function _build_native_code() {
   banner "Building native code..."
   # NB: See Cargo.toml with regard to the `extension-module` features.
   "${REPO_ROOT}/cargo" build \
-    --features=extension-module \
+    --features="extension-module,${ENGINE_FEATURES}" \
     ${MODE_FLAG} \
     -p engine \
     -p client || die
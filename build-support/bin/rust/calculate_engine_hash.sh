@@ -19,6 +19,10 @@ case "$MODE" in
   *) MODE_FLAG="--release" ;;
 esac
 
+# N.B. Set $ENGINE_FEATURES to a comma separated list of optional features of the engine to build
+# with, such as "tokio-console".
+readonly ENGINE_FEATURES="${ENGINE_FEATURES:-}"
+
 function calculate_current_hash() {
   # Cached and unstaged files, with ignored files excluded.
   # NB: We fork a subshell because one or both of `ls-files`/`hash-object` are
@@ -31,6 +35,7 @@ function calculate_current_hash() {
     cd "${REPO_ROOT}" || exit 1
     (
       echo "${MODE_FLAG}"
+      echo "${ENGINE_FEATURES}"
       uname -mps
       # the engine only depends on the implementation and major.minor version, not the patch
       "${PY}" -c 'import sys; print(sys.implementation.name, sys.version_info.major, sys.version_info.minor)'
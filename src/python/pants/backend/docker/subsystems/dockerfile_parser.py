@@ -6,7 +6,9 @@ from __future__ import annotations
 import json
 from dataclasses import dataclass
 from pathlib import PurePath
+from typing import Any
 
+from pants.backend.docker.subsystems.dockerfile_wrapper_script import valid_address
 from pants.backend.docker.target_types import DockerImageSourceField
 from pants.backend.docker.util_rules.docker_build_args import DockerBuildArgs
 from pants.backend.python.subsystems.python_tool_base import PythonToolRequirementsBase
@@ -15,6 +17,8 @@ from pants.backend.python.util_rules import pex
 from pants.backend.python.util_rules.pex import PexRequest, VenvPex, VenvPexProcess
 from pants.engine.addresses import Address
 from pants.engine.fs import CreateDigest, Digest, FileContent
+from pants.engine.internals.native_dep_inference import NativeParsedDockerfileDependencies
+from pants.engine.internals.native_engine import NativeDependenciesRequest
 from pants.engine.process import Process, ProcessResult
 from pants.engine.rules import Get, collect_rules, rule
 from pants.engine.target import (
@@ -24,8 +28,10 @@ from pants.engine.target import (
     WrappedTarget,
     WrappedTargetRequest,
 )
+from pants.option.option_types import BoolOption
 from pants.util.logging import LogLevel
 from pants.util.resources import read_resource
+from pants.util.strutil import softwrap
 
 _DOCKERFILE_SANDBOX_TOOL = "dockerfile_wrapper_script.py"
 _DOCKERFILE_PACKAGE = "pants.backend.docker.subsystems"
@@ -41,6 +47,20 @@ class DockerfileParser(PythonToolRequirementsBase):
 
     default_lockfile_resource = (_DOCKERFILE_PACKAGE, "dockerfile.lock")
 
+    use_rust_parser = BoolOption(
+        default=False,
+        help=softwrap(
+            """
+            Use the Rust-based, in-process parser instead of running the `dockerfile` Python
+            package in a separate process.
+
+            The Rust-based parser also resolves `ARG`s in base image references, and infers the
+            source paths of `ADD` instructions.
+            """
+        ),
+        advanced=True,
+    )
+
 
 @dataclass(frozen=True)
 class ParserSetup:
@@ -120,7 +140,9 @@ class DockerfileInfoRequest:
 
 
 @rule
-async def parse_dockerfile(request: DockerfileInfoRequest) -> DockerfileInfo:
+async def parse_dockerfile(
+    request: DockerfileInfoRequest, dockerfile_parser: DockerfileParser
+) -> DockerfileInfo:
     wrapped_target = await Get(
         WrappedTarget, WrappedTargetRequest(request.address, description_of_origin="<infallible>")
     )
@@ -140,25 +162,46 @@ async def parse_dockerfile(request: DockerfileInfoRequest) -> DockerfileInfo:
         f"got: {dockerfiles}."
     )
 
-    result = await Get(
-        ProcessResult,
-        DockerfileParseRequest(
-            sources.snapshot.digest,
-            dockerfiles,
-        ),
-    )
+    if dockerfile_parser.use_rust_parser:
+        parsed = await Get(
+            NativeParsedDockerfileDependencies,
+            NativeDependenciesRequest(sources.snapshot.digest, None),
+        )
+        # The same output as `dockerfile_wrapper_script.py`.
+        info: dict[str, Any] = {
+            "source": dockerfiles[0],
+            "build_args": parsed.build_args,
+            "copy_source_paths": parsed.copy_source_paths,
+            "copy_build_args": parsed.copy_build_args,
+            # Only the build args which may refer to other `docker_image` targets.
+            "from_image_build_args": [
+                build_arg
+                for build_arg in parsed.from_image_build_args
+                if valid_address(build_arg.partition("=")[2])
+            ],
+            "version_tags": parsed.version_tags,
+        }
+    else:
+        result = await Get(
+            ProcessResult,
+            DockerfileParseRequest(
+                sources.snapshot.digest,
+                dockerfiles,
+            ),
+        )
 
-    try:
-        raw_output = result.stdout.decode("utf-8")
-        outputs = json.loads(raw_output)
-        assert len(outputs) == len(dockerfiles)
-    except Exception as e:
-        raise DockerfileInfoError(
-            f"Unexpected failure to parse Dockerfiles: {', '.join(dockerfiles)}, "
-            f"for the {request.address} target: {e}\nDockerfile parser output:\n{raw_output}"
-        ) from e
+        try:
+            raw_output = result.stdout.decode("utf-8")
+            outputs = json.loads(raw_output)
+            assert len(outputs) == len(dockerfiles)
+        except Exception as e:
+            raise DockerfileInfoError(
+                f"Unexpected failure to parse Dockerfiles: {', '.join(dockerfiles)}, "
+                f"for the {request.address} target: {e}\nDockerfile parser output:\n{raw_output}"
+            ) from e
+
+        info = outputs[0]
 
-    info = outputs[0]
     try:
         return DockerfileInfo(
             address=request.address,
@@ -19,8 +19,8 @@ from pants.testutil.pants_integration_test import run_pants
 from pants.testutil.rule_runner import QueryRule, RuleRunner
 
 
-@pytest.fixture
-def rule_runner() -> RuleRunner:
+@pytest.fixture(params=[False, True], ids=["python_parser", "rust_parser"])
+def rule_runner(request: pytest.FixtureRequest) -> RuleRunner:
     rule_runner = RuleRunner(
         rules=[
             *dockerfile_rules(),
@@ -31,7 +31,7 @@ def rule_runner() -> RuleRunner:
         target_types=[DockerImageTarget, PexBinary],
     )
     rule_runner.set_options(
-        [],
+        [f"--dockerfile-parser-use-rust-parser={request.param}"],
         env_inherit={"PATH", "PYENV_ROOT", "HOME"},
     )
     return rule_runner
@@ -193,6 +193,29 @@ def test_copy_source_references(rule_runner: RuleRunner) -> None:
     assert info.copy_source_paths == ("a", "b", "c/d", "e/f/g", "j", "k")
 
 
+def test_rust_parser_copy_source_references(rule_runner: RuleRunner) -> None:
+    rule_runner.set_options(
+        ["--dockerfile-parser-use-rust-parser"], env_inherit={"PATH", "PYENV_ROOT", "HOME"}
+    )
+    rule_runner.write_files(
+        {
+            "test/BUILD": "docker_image()",
+            "test/Dockerfile": dedent(
+                """\
+                ARG DIR=src
+                FROM base
+                COPY ["with space.txt", "/"]
+                COPY $DIR/app.py /app/
+                ADD archive.tar.gz https://example.com/file.txt /
+                """
+            ),
+        }
+    )
+
+    info = rule_runner.request(DockerfileInfo, [DockerfileInfoRequest(Address("test"))])
+    assert info.copy_source_paths == ("with space.txt", "src/app.py", "archive.tar.gz")
+
+
 def test_baseimage_tags(rule_runner: RuleRunner) -> None:
     rule_runner.write_files(
         {
@@ -243,6 +243,21 @@ class GolangSubsystem(Subsystem):
         ),
     )
 
+    native_dependency_inference = BoolOption(
+        default=False,
+        help=softwrap(
+            """
+            If true, infer the dependencies of first-party packages by parsing their imports and
+            build constraints in-process with a native parser, instead of running the Go package
+            analyzer for each package. The parsed imports are cached per file, including remotely.
+
+            Build constraints are evaluated against the GOOS and GOARCH of the Go SDK, the Go
+            release, and whether Cgo is enabled.
+            """
+        ),
+        advanced=True,
+    )
+
     asdf_tool_name = StrOption(
         default="go-sdk",
         help=softwrap(
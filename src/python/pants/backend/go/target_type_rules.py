@@ -22,7 +22,8 @@ from pants.backend.go.target_types import (
     GoThirdPartyPackageDependenciesField,
     GoThirdPartyPackageTarget,
 )
-from pants.backend.go.util_rules import build_opts, first_party_pkg, import_analysis
+from pants.backend.go.subsystems.golang import GolangSubsystem
+from pants.backend.go.util_rules import build_opts, first_party_pkg, import_analysis, native_imports
 from pants.backend.go.util_rules.build_opts import GoBuildOptions, GoBuildOptionsFromTargetRequest
 from pants.backend.go.util_rules.first_party_pkg import (
     FallibleFirstPartyPkgAnalysis,
@@ -37,6 +38,7 @@ from pants.backend.go.util_rules.go_mod import (
     OwningGoModRequest,
 )
 from pants.backend.go.util_rules.import_analysis import GoStdLibPackages, GoStdLibPackagesRequest
+from pants.backend.go.util_rules.native_imports import NativeGoImports, NativeGoImportsRequest
 from pants.backend.go.util_rules.third_party_pkg import (
     AllThirdPartyPackages,
     AllThirdPartyPackagesRequest,
@@ -216,7 +218,7 @@ class InferGoPackageDependenciesRequest(InferDependenciesRequest):
 
 @rule(desc="Infer dependencies for first-party Go packages", level=LogLevel.DEBUG)
 async def infer_go_dependencies(
-    request: InferGoPackageDependenciesRequest,
+    request: InferGoPackageDependenciesRequest, golang: GolangSubsystem
 ) -> InferredDependencies:
     go_mod_addr = await Get(OwningGoMod, OwningGoModRequest(request.field_set.address))
     package_mapping, build_opts = await MultiGet(
@@ -225,32 +227,35 @@ async def infer_go_dependencies(
     )
 
     addr = request.field_set.address
-    maybe_pkg_analysis, stdlib_packages = await MultiGet(
-        Get(
+    if golang.native_dependency_inference:
+        import_path_info, native_pkg_imports = await MultiGet(
+            Get(FirstPartyPkgImportPath, FirstPartyPkgImportPathRequest(addr)),
+            Get(NativeGoImports, NativeGoImportsRequest(addr, build_opts)),
+        )
+        pkg_import_path = import_path_info.import_path
+        pkg_imports: tuple[str, ...] = native_pkg_imports.imports
+    else:
+        maybe_pkg_analysis = await Get(
             FallibleFirstPartyPkgAnalysis, FirstPartyPkgAnalysisRequest(addr, build_opts=build_opts)
-        ),
-        Get(
-            GoStdLibPackages,
-            GoStdLibPackagesRequest(with_race_detector=build_opts.with_race_detector),
-        ),
-    )
-
-    if maybe_pkg_analysis.analysis is None:
-        logger.error(
-            f"Failed to analyze {maybe_pkg_analysis.import_path} for dependency inference:\n"
-            f"{maybe_pkg_analysis.stderr}"
         )
-        return InferredDependencies([])
-    pkg_analysis = maybe_pkg_analysis.analysis
+        if maybe_pkg_analysis.analysis is None:
+            logger.error(
+                f"Failed to analyze {maybe_pkg_analysis.import_path} for dependency inference:\n"
+                f"{maybe_pkg_analysis.stderr}"
+            )
+            return InferredDependencies([])
+        pkg_analysis = maybe_pkg_analysis.analysis
+        pkg_import_path = pkg_analysis.import_path
+        pkg_imports = (
+            *pkg_analysis.imports,
+            *pkg_analysis.test_imports,
+            *pkg_analysis.xtest_imports,
+        )
 
     inferred_dependencies: list[Address] = []
-    for import_path in (
-        *pkg_analysis.imports,
-        *pkg_analysis.test_imports,
-        *pkg_analysis.xtest_imports,
-    ):
+    for import_path in pkg_imports:
         # Avoid a dependency cycle caused by external test imports of this package (i.e., "xtest").
-        if import_path == pkg_analysis.import_path:
+        if import_path == pkg_import_path:
             continue
         candidate_packages = package_mapping.mapping.get(import_path)
         if candidate_packages:
@@ -415,6 +420,7 @@ def rules():
         *build_opts.rules(),
         *first_party_pkg.rules(),
         *import_analysis.rules(),
+        *native_imports.rules(),
         UnionRule(InferDependenciesRequest, InferGoPackageDependenciesRequest),
         UnionRule(InferDependenciesRequest, InferGoThirdPartyPackageDependenciesRequest),
         UnionRule(GenerateTargetsRequest, GenerateTargetsFromGoModRequest),
@@ -161,6 +161,51 @@ def test_go_package_dependency_inference(rule_runner: RuleRunner) -> None:
     assert not get_deps(Address("foo/bad"))
 
 
+def test_go_package_native_dependency_inference(rule_runner: RuleRunner) -> None:
+    rule_runner.set_options(["--golang-native-dependency-inference"], env_inherit={"PATH"})
+    rule_runner.write_files(
+        {
+            "foo/BUILD": "go_mod()",
+            "foo/go.mod": "module go.example.com/foo\ngo 1.17\n",
+            "foo/pkg/foo.go": "package pkg\n",
+            "foo/pkg/BUILD": "go_package()",
+            "foo/other/other.go": "package other\n",
+            "foo/other/BUILD": "go_package()",
+            "foo/cmd/main.go": dedent(
+                """\
+                package main
+
+                import (
+                    "fmt"
+                    _ "go.example.com/foo/other" // pants: no-infer-dep
+                    lib "go.example.com/foo/pkg"
+                )
+                """
+            ),
+            "foo/cmd/ignored.go": dedent(
+                """\
+                //go:build ignore
+
+                package main
+
+                import "go.example.com/foo/other"
+                """
+            ),
+            "foo/cmd/main_test.go": dedent(
+                """\
+                package main_test
+
+                import "go.example.com/foo/cmd"
+                """
+            ),
+            "foo/cmd/BUILD": "go_package()",
+        }
+    )
+    tgt = rule_runner.get_target(Address("foo/cmd"))
+    deps = rule_runner.request(Addresses, [DependenciesRequest(tgt[Dependencies])])
+    assert set(deps) == {Address("foo/pkg")}
+
+
 # -----------------------------------------------------------------------------------------------
 # `go_package` validation
 # -----------------------------------------------------------------------------------------------
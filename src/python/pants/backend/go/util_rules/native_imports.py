@@ -0,0 +1,125 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+import os
+from dataclasses import dataclass
+
+from pants.backend.go.target_types import GoPackageSourcesField
+from pants.backend.go.util_rules.build_opts import GoBuildOptions
+from pants.backend.go.util_rules.goroot import GoRoot
+from pants.build_graph.address import Address
+from pants.engine.engine_aware import EngineAwareParameter
+from pants.engine.fs import Digest, DigestSubset, PathGlobs
+from pants.engine.internals.native_dep_inference import NativeParsedGoDependencies
+from pants.engine.internals.native_engine import InferenceMetadata, NativeDependenciesRequest
+from pants.engine.rules import Get, MultiGet, collect_rules, rule
+from pants.engine.target import (
+    HydratedSources,
+    HydrateSourcesRequest,
+    WrappedTarget,
+    WrappedTargetRequest,
+)
+
+# The operating systems which satisfy the `unix` build tag, per Go's `internal/syslist`.
+_UNIX_GOOS = frozenset(
+    (
+        "aix",
+        "android",
+        "darwin",
+        "dragonfly",
+        "freebsd",
+        "hurd",
+        "illumos",
+        "ios",
+        "linux",
+        "netbsd",
+        "openbsd",
+        "solaris",
+    )
+)
+
+# Operating systems which also satisfy the build tag of another one.
+_IMPLIED_GOOS = {"android": "linux", "illumos": "solaris", "ios": "darwin"}
+
+
+def go_build_tags(
+    goroot: GoRoot, build_opts: GoBuildOptions, extra_build_tags: tuple[str, ...] = ()
+) -> tuple[str, ...]:
+    """The build tags which `go build` would satisfy for the given SDK and options."""
+    tags = {goroot.goos, goroot.goarch, "gc", *extra_build_tags}
+    if goroot.goos in _IMPLIED_GOOS:
+        tags.add(_IMPLIED_GOOS[goroot.goos])
+    if goroot.goos in _UNIX_GOOS:
+        tags.add("unix")
+    if build_opts.cgo_enabled:
+        tags.add("cgo")
+    major, minor = goroot.version.split(".")[:2]
+    tags.update(f"go{major}.{release}" for release in range(1, int(minor) + 1))
+    return tuple(sorted(tags))
+
+
+@dataclass(frozen=True)
+class NativeGoImportsRequest(EngineAwareParameter):
+    address: Address
+    build_opts: GoBuildOptions
+    extra_build_tags: tuple[str, ...] = ()
+
+    def debug_hint(self) -> str:
+        return self.address.spec
+
+
+@dataclass(frozen=True)
+class NativeGoImports:
+    """The import paths of the files of a first-party Go package which match the build tags.
+
+    Unlike `FirstPartyPkgAnalysis`, this is computed in-process (and cached per file) without
+    invoking the Go package analyzer.
+    """
+
+    imports: tuple[str, ...]
+
+
+@rule
+async def parse_go_imports_natively(
+    request: NativeGoImportsRequest, goroot: GoRoot
+) -> NativeGoImports:
+    wrapped_target = await Get(
+        WrappedTarget,
+        WrappedTargetRequest(request.address, description_of_origin="<go native imports>"),
+    )
+    pkg_sources = await Get(
+        HydratedSources,
+        HydrateSourcesRequest(wrapped_target.target[GoPackageSourcesField]),
+    )
+    # Like `go build`, ignore files whose names start with `_` or `.`.
+    go_files = [
+        path
+        for path in pkg_sources.snapshot.files
+        if path.endswith(".go") and not os.path.basename(path).startswith(("_", "."))
+    ]
+    digests = await MultiGet(
+        Get(Digest, DigestSubset(pkg_sources.snapshot.digest, PathGlobs([path])))
+        for path in go_files
+    )
+    metadata = InferenceMetadata.go(
+        go_build_tags(goroot, request.build_opts, request.extra_build_tags)
+    )
+    parsed_files = await MultiGet(
+        Get(NativeParsedGoDependencies, NativeDependenciesRequest(digest, metadata))
+        for digest in digests
+    )
+    imports = {
+        import_path
+        for parsed in parsed_files
+        if parsed.matches_build_tags
+        for import_path, _ in parsed.imports
+        # The pseudo-package for cgo.
+        if import_path != "C"
+    }
+    return NativeGoImports(tuple(sorted(imports)))
+
+
+def rules():
+    return collect_rules()
@@ -0,0 +1,40 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+from pants.backend.go.util_rules.build_opts import GoBuildOptions
+from pants.backend.go.util_rules.goroot import GoRoot
+from pants.backend.go.util_rules.native_imports import go_build_tags
+from pants.util.frozendict import FrozenDict
+
+
+def make_goroot(goos: str, goarch: str, version: str = "1.3") -> GoRoot:
+    return GoRoot(
+        path="/goroot",
+        version=version,
+        _raw_metadata=FrozenDict({"GOOS": goos, "GOARCH": goarch}),
+    )
+
+
+def test_go_build_tags() -> None:
+    assert go_build_tags(make_goroot("linux", "amd64"), GoBuildOptions()) == (
+        "amd64",
+        "cgo",
+        "gc",
+        "go1.1",
+        "go1.2",
+        "go1.3",
+        "linux",
+        "unix",
+    )
+    assert go_build_tags(
+        make_goroot("windows", "arm64"), GoBuildOptions(cgo_enabled=False), ("generate",)
+    ) == ("arm64", "gc", "generate", "go1.1", "go1.2", "go1.3", "windows")
+
+
+def test_implied_goos_tags() -> None:
+    tags = go_build_tags(make_goroot("android", "arm64"), GoBuildOptions())
+    assert {"android", "linux", "unix"} <= set(tags)
+    tags = go_build_tags(make_goroot("ios", "arm64"), GoBuildOptions())
+    assert {"ios", "darwin", "unix"} <= set(tags)
@@ -11,10 +11,16 @@ from dataclasses import dataclass
 import pkg_resources
 
 from pants.backend.java.dependency_inference.types import JavaSourceDependencyAnalysis
+from pants.backend.java.subsystems.java_infer import JavaInferSubsystem
 from pants.core.goals.generate_lockfiles import DEFAULT_TOOL_LOCKFILE, GenerateToolLockfileSentinel
 from pants.core.util_rules.source_files import SourceFiles
 from pants.engine.fs import AddPrefix, CreateDigest, Digest, DigestContents, Directory, FileContent
-from pants.engine.internals.native_engine import MergeDigests, RemovePrefix
+from pants.engine.internals.native_dep_inference import NativeParsedJvmDependencies
+from pants.engine.internals.native_engine import (
+    MergeDigests,
+    NativeDependenciesRequest,
+    RemovePrefix,
+)
 from pants.engine.process import FallibleProcessResult, ProcessResult, ProductDescription
 from pants.engine.rules import Get, MultiGet, collect_rules, rule
 from pants.engine.unions import UnionRule
@@ -51,8 +57,18 @@ class JavaParserCompiledClassfiles:
 
 @rule(level=LogLevel.DEBUG)
 async def resolve_fallible_result_to_analysis(
-    fallible_result: FallibleJavaSourceDependencyAnalysisResult,
+    request: JavaSourceDependencyAnalysisRequest, java_infer_subsystem: JavaInferSubsystem
 ) -> JavaSourceDependencyAnalysis:
+    if java_infer_subsystem.use_rust_parser:
+        parsed = await Get(
+            NativeParsedJvmDependencies,
+            NativeDependenciesRequest(request.source_files.snapshot.digest, None),
+        )
+        return JavaSourceDependencyAnalysis.from_native(parsed)
+
+    fallible_result = await Get(
+        FallibleJavaSourceDependencyAnalysisResult, JavaSourceDependencyAnalysisRequest, request
+    )
     desc = ProductDescription("Java source dependency analysis failed.")
     result = await Get(
         ProcessResult,
@@ -309,3 +309,51 @@ def test_java_parser_consumed_types(rule_runner: RuleRunner) -> None:
         "String",
         "provider",  # note: false positive on a variable identifier
     ]
+
+
+def test_rust_parser_analysis(rule_runner: RuleRunner) -> None:
+    rule_runner.set_options(["--java-infer-use-rust-parser"], env_inherit=PYTHON_BOOTSTRAP_ENV)
+    rule_runner.write_files(
+        {
+            "BUILD": "java_source(name='source', source='Source.java')",
+            "Source.java": dedent(
+                """\
+                package org.pantsbuild.example;
+
+                import java.util.List;
+                import static org.junit.Assert.*;
+
+                @AutoService(Processor.class)
+                public class Source extends Base {
+                    public List<Widget> widgets(Input input) {
+                        return Helper.widgets(input);
+                    }
+                }
+                """
+            ),
+        }
+    )
+
+    target = rule_runner.get_target(address=Address(spec_path="", target_name="source"))
+    source_files = rule_runner.request(
+        SourceFiles,
+        [SourceFilesRequest((target.get(SourcesField),), for_sources_types=(JavaSourceField,))],
+    )
+
+    analysis = rule_runner.request(JavaSourceDependencyAnalysis, [source_files])
+    assert analysis.declared_package == "org.pantsbuild.example"
+    assert analysis.imports == (
+        JavaImport(name="java.util.List"),
+        JavaImport(name="org.junit.Assert", is_static=True, is_asterisk=True),
+    )
+    assert analysis.top_level_types == ("org.pantsbuild.example.Source",)
+    assert analysis.consumed_types == (
+        "AutoService",
+        "Base",
+        "Helper",
+        "Input",
+        "List",
+        "Processor",
+        "Widget",
+    )
+    assert analysis.export_types == ("Base", "Input", "List", "Widget")
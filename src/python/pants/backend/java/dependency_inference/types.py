@@ -6,6 +6,8 @@ from __future__ import annotations
 from dataclasses import dataclass
 from typing import Any, Sequence
 
+from pants.engine.internals.native_dep_inference import NativeParsedJvmDependencies
+
 
 @dataclass(frozen=True)
 class JavaImport:
@@ -47,6 +49,20 @@ class JavaSourceDependencyAnalysis:
             export_types=tuple(analysis["exportTypes"]),
         )
 
+    @classmethod
+    def from_native(cls, parsed: NativeParsedJvmDependencies) -> JavaSourceDependencyAnalysis:
+        return cls(
+            declared_package=parsed.package,
+            imports=tuple(
+                JavaImport(name=name, is_static=is_static, is_asterisk=is_wildcard)
+                for name, is_static, is_wildcard, _ in parsed.imports
+            ),
+            top_level_types=parsed.declared_symbols,
+            # Annotation processors need the classes referenced by annotations on the classpath.
+            consumed_types=tuple(sorted(parsed.consumed_symbols | parsed.annotation_references)),
+            export_types=tuple(sorted(parsed.export_types)),
+        )
+
     def to_debug_json_dict(self) -> dict[str, Any]:
         return {
             "declared_package": self.declared_package,
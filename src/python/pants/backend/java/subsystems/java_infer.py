@@ -19,6 +19,19 @@ class JavaInferSubsystem(Subsystem):
         default=True,
         help="Infer a target's dependencies by parsing consumed types from sources.",
     )
+    use_rust_parser = BoolOption(
+        default=False,
+        help=softwrap(
+            """
+            Use the Rust-based, in-process dependency parser instead of running a JVM process for
+            each source file.
+
+            The Rust-based parser extracts the same imports and declared types, but only an
+            approximation of the consumed types, which are inferred syntactically.
+            """
+        ),
+        advanced=True,
+    )
     # TODO: Move to `coursier` or a generic `jvm` subsystem.
     third_party_import_mapping = DictOption[Any](
         help=softwrap(
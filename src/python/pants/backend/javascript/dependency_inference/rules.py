@@ -9,19 +9,23 @@ from typing import Iterable
 
 from pants.backend.javascript import package_json
 from pants.backend.javascript.package_json import (
+    ConditionalSubpath,
     FirstPartyNodePackageTargets,
     NodePackageDependenciesField,
     NodePackageNameField,
     OwningNodePackage,
     OwningNodePackageRequest,
+    PackageJson,
     PackageJsonEntryPoints,
     PackageJsonImports,
     PackageJsonSourceField,
+    conditional_exports,
 )
 from pants.backend.javascript.subsystems.nodejs_infer import NodeJSInfer
 from pants.backend.javascript.target_types import JSDependenciesField, JSSourceField
 from pants.build_graph.address import Address
 from pants.engine.addresses import Addresses
+from pants.engine.fs import DigestContents, PathGlobs, Paths
 from pants.engine.internals.graph import Owners, OwnersRequest
 from pants.engine.internals.native_dep_inference import NativeParsedJavascriptDependencies
 from pants.engine.internals.native_engine import InferenceMetadata, NativeDependenciesRequest
@@ -101,18 +105,59 @@ async def map_candidate_node_packages(
     )
 
 
+class TSConfigFiles(FrozenDict[str, str]):
+    """The content of all `tsconfig*.json` files in the repository, by path.
+
+    The native parser selects the nearest `tsconfig.json` for each file, and resolves its
+    `extends` chain, in order to apply `compilerOptions.paths` and `compilerOptions.baseUrl`.
+    """
+
+
 @rule
-async def prepare_inference_metadata(imports: PackageJsonImports) -> InferenceMetadata:
+async def find_tsconfig_files() -> TSConfigFiles:
+    contents = await Get(DigestContents, PathGlobs(["**/tsconfig*.json"]))
+    return TSConfigFiles(
+        (file_content.path, file_content.content.decode("utf-8")) for file_content in contents
+    )
+
+
+def _replacements(
+    subpaths: FrozenDict[str, tuple[ConditionalSubpath, ...]]
+) -> dict[str, list[str | ConditionalSubpath]]:
+    return {pattern: list(replacements) for pattern, replacements in subpaths.items()}
+
+
+@rule
+async def prepare_inference_metadata(
+    source_field: PackageJsonSourceField,
+    nodejs_infer: NodeJSInfer,
+    tsconfig_files: TSConfigFiles,
+) -> InferenceMetadata:
+    pkg_json = await Get(PackageJson, PackageJsonSourceField, source_field)
+    imports = await Get(PackageJsonImports, PackageJsonSourceField, source_field)
     return InferenceMetadata.javascript(
         imports.root_dir,
-        {pattern: list(replacements) for pattern, replacements in imports.imports.items()},
+        _replacements(imports.conditional_imports),
+        dict(tsconfig_files),
+        list(nodejs_infer.conditions),
+        pkg_json.name,
+        _replacements(conditional_exports(pkg_json)),
+        list(nodejs_infer.resolve_extensions),
     )
 
 
-async def _prepare_inference_metadata(address: Address) -> InferenceMetadata:
+async def _prepare_inference_metadata(
+    address: Address, nodejs_infer: NodeJSInfer, tsconfig_files: TSConfigFiles
+) -> InferenceMetadata:
     owning_pkg = await Get(OwningNodePackage, OwningNodePackageRequest(address))
     if not owning_pkg.target:
-        return InferenceMetadata.javascript(address.spec_path, {})
+        return InferenceMetadata.javascript(
+            address.spec_path,
+            {},
+            dict(tsconfig_files),
+            list(nodejs_infer.conditions),
+            resolve_extensions=list(nodejs_infer.resolve_extensions),
+        )
     return await Get(
         InferenceMetadata, PackageJsonSourceField, owning_pkg.target[PackageJsonSourceField]
     )
@@ -122,6 +167,7 @@ async def _prepare_inference_metadata(address: Address) -> InferenceMetadata:
 async def infer_js_source_dependencies(
     request: InferJSDependenciesRequest,
     nodejs_infer: NodeJSInfer,
+    tsconfig_files: TSConfigFiles,
 ) -> InferredDependencies:
     source: JSSourceField = request.field_set.source
     if not nodejs_infer.imports:
@@ -130,14 +176,21 @@ async def infer_js_source_dependencies(
     sources = await Get(
         HydratedSources, HydrateSourcesRequest(source, for_sources_types=[JSSourceField])
     )
-    metadata = await _prepare_inference_metadata(request.field_set.address)
+    metadata = await _prepare_inference_metadata(
+        request.field_set.address, nodejs_infer, tsconfig_files
+    )
 
     import_strings = await Get(
         NativeParsedJavascriptDependencies,
         NativeDependenciesRequest(sources.snapshot.digest, metadata),
     )
 
-    owners = await Get(Owners, OwnersRequest(tuple(import_strings.file_imports)))
+    file_imports = set(import_strings.file_imports)
+    if nodejs_infer.dynamic_import_patterns and import_strings.pattern_imports:
+        pattern_matches = await Get(Paths, PathGlobs(import_strings.pattern_imports))
+        file_imports.update(pattern_matches.files)
+
+    owners = await Get(Owners, OwnersRequest(tuple(file_imports)))
     owning_targets = await Get(Targets, Addresses(owners))
 
     non_path_string_bases = FrozenOrderedSet(
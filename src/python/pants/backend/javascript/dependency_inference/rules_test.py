@@ -106,6 +106,41 @@ def test_infers_esmodule_js_dependencies_from_ancestor_files(rule_runner: RuleRu
     assert set(addresses) == {Address("src/js", relative_file_path="xes.mjs")}
 
 
+def test_infers_js_dependencies_of_extensionless_imports(rule_runner: RuleRunner) -> None:
+    rule_runner.write_files(
+        {
+            "src/js/BUILD": "javascript_sources()",
+            "src/js/index.mjs": dedent(
+                """\
+                import { x } from "./xes";
+                import { y } from "./lib";
+                """
+            ),
+            "src/js/xes.mjs": "",
+            "src/js/lib/BUILD": "javascript_sources()",
+            "src/js/lib/index.js": "",
+        }
+    )
+
+    index_tgt = rule_runner.get_target(Address("src/js", relative_file_path="index.mjs"))
+
+    def infer_dependencies() -> set[Address]:
+        return set(
+            rule_runner.request(
+                InferredDependencies,
+                [InferJSDependenciesRequest(JSSourceInferenceFieldSet.create(index_tgt))],
+            ).include
+        )
+
+    assert infer_dependencies() == {
+        Address("src/js", relative_file_path="xes.mjs"),
+        Address("src/js/lib", relative_file_path="index.js"),
+    }
+
+    rule_runner.set_options(["--nodejs-infer-resolve-extensions=[]"], env_inherit={"PATH"})
+    assert infer_dependencies() == set()
+
+
 def test_infers_commonjs_js_dependencies_from_ancestor_files(rule_runner: RuleRunner) -> None:
     rule_runner.write_files(
         {
@@ -436,3 +471,141 @@ def test_infers_first_party_package_json_field_js_source_dependency_with_starred
     ).include
 
     assert set(addresses) == {Address("src/js/b", generated_name="spam")}
+
+
+def test_infers_js_dependencies_from_tsconfig_paths_of_extended_config(
+    rule_runner: RuleRunner,
+) -> None:
+    rule_runner.write_files(
+        {
+            "tsconfig.base.json": json.dumps(
+                {"compilerOptions": {"baseUrl": ".", "paths": {"@shared/*": ["src/shared/*"]}}}
+            ),
+            "src/js/tsconfig.json": dedent(
+                """\
+                {
+                  // Only the output directory differs from the base config.
+                  "extends": "../../tsconfig.base.json",
+                  "compilerOptions": {"outDir": "dist"},
+                }
+                """
+            ),
+            "src/js/BUILD": "javascript_sources()",
+            "src/js/index.mjs": 'import { x } from "@shared/xes.mjs";',
+            "src/shared/BUILD": "javascript_sources()",
+            "src/shared/xes.mjs": "",
+        }
+    )
+
+    index_tgt = rule_runner.get_target(Address("src/js", relative_file_path="index.mjs"))
+    addresses = rule_runner.request(
+        InferredDependencies,
+        [InferJSDependenciesRequest(JSSourceInferenceFieldSet.create(index_tgt))],
+    ).include
+
+    assert set(addresses) == {Address("src/shared", relative_file_path="xes.mjs")}
+
+
+@pytest.mark.parametrize(
+    "conditions, expected",
+    [
+        ([], {"esm.mjs", "cjs.cjs"}),
+        (["import"], {"esm.mjs"}),
+        (["browser"], {"cjs.cjs"}),
+    ],
+)
+def test_infers_js_dependencies_from_conditional_import_subpaths(
+    rule_runner: RuleRunner, conditions: list[str], expected: set[str]
+) -> None:
+    rule_runner.write_files(
+        {
+            "src/js/BUILD": "package_json()",
+            "src/js/package.json": json.dumps(
+                {
+                    "name": "ham",
+                    "version": "0.0.1",
+                    "imports": {"#lib": {"import": "./lib/esm.mjs", "default": "./lib/cjs.cjs"}},
+                }
+            ),
+            "src/js/lib/BUILD": "javascript_sources()",
+            "src/js/lib/index.js": 'import lib from "#lib";',
+            "src/js/lib/esm.mjs": "",
+            "src/js/lib/cjs.cjs": "",
+        }
+    )
+    rule_runner.set_options([f"--nodejs-infer-conditions={conditions!r}"], env_inherit={"PATH"})
+
+    tgt = rule_runner.get_target(Address("src/js/lib", relative_file_path="index.js"))
+    addresses = rule_runner.request(
+        InferredDependencies,
+        [InferJSDependenciesRequest(JSSourceInferenceFieldSet.create(tgt))],
+    ).include
+
+    assert set(addresses) == {
+        Address("src/js/lib", relative_file_path=file_name) for file_name in expected
+    }
+
+
+def test_infers_js_dependencies_from_conditional_exports_self_reference(
+    rule_runner: RuleRunner,
+) -> None:
+    rule_runner.write_files(
+        {
+            "src/js/BUILD": "package_json()",
+            "src/js/package.json": json.dumps(
+                {
+                    "name": "ham",
+                    "version": "0.0.1",
+                    "exports": {".": {"import": "./lib/esm.mjs", "require": "./lib/cjs.cjs"}},
+                }
+            ),
+            "src/js/lib/BUILD": "javascript_sources()",
+            "src/js/lib/index.js": 'const ham = require("ham");',
+            "src/js/lib/esm.mjs": "",
+            "src/js/lib/cjs.cjs": "",
+        }
+    )
+    rule_runner.set_options(["--nodejs-infer-conditions=['require']"], env_inherit={"PATH"})
+
+    tgt = rule_runner.get_target(Address("src/js/lib", relative_file_path="index.js"))
+    addresses = rule_runner.request(
+        InferredDependencies,
+        [InferJSDependenciesRequest(JSSourceInferenceFieldSet.create(tgt))],
+    ).include
+
+    assert set(addresses) == {Address("src/js/lib", relative_file_path="cjs.cjs")}
+
+
+@pytest.mark.parametrize(
+    "enabled, expected",
+    [
+        pytest.param(False, set(), id="disabled"),
+        pytest.param(True, {"home.js", "about.js"}, id="enabled"),
+    ],
+)
+def test_infers_js_dependencies_from_dynamic_import_patterns(
+    rule_runner: RuleRunner, enabled: bool, expected: set[str]
+) -> None:
+    rule_runner.write_files(
+        {
+            "src/js/BUILD": "javascript_sources()",
+            "src/js/index.js": "const page = await import(`./pages/${name}.js`);",
+            "src/js/pages/BUILD": "javascript_sources()",
+            "src/js/pages/home.js": "",
+            "src/js/pages/about.js": "",
+            "src/js/pages/styles.css": "",
+        }
+    )
+    rule_runner.set_options(
+        [f"--nodejs-infer-dynamic-import-patterns={enabled}"], env_inherit={"PATH"}
+    )
+
+    tgt = rule_runner.get_target(Address("src/js", relative_file_path="index.js"))
+    addresses = rule_runner.request(
+        InferredDependencies,
+        [InferJSDependenciesRequest(JSSourceInferenceFieldSet.create(tgt))],
+    ).include
+
+    assert set(addresses) == {
+        Address("src/js/pages", relative_file_path=file_name) for file_name in expected
+    }
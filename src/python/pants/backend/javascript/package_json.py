@@ -8,7 +8,7 @@ import logging
 import os.path
 from abc import ABC
 from dataclasses import dataclass, field
-from typing import Any, ClassVar, Iterable, Literal, Mapping, Optional, Tuple
+from typing import Any, ClassVar, Iterable, Iterator, Literal, Mapping, Optional, Sequence, Tuple
 
 import yaml
 
@@ -482,18 +482,56 @@ class NodeBuildScriptTarget(Target):
     )
 
 
+ConditionalSubpath = Tuple[Tuple[str, ...], str]
+
+
+def conditional_subpaths(
+    value: str | Sequence[Any] | Mapping[str, Any], conditions: tuple[str, ...] = ()
+) -> Iterator[ConditionalSubpath]:
+    """The subpaths of an "exports" or "imports" entry in declaration order, each with the
+    (possibly nested) conditions under which it applies.
+
+    See https://nodejs.org/api/packages.html#conditional-exports.
+    """
+    if isinstance(value, str):
+        yield conditions, value
+    elif isinstance(value, Mapping):
+        for condition, v in value.items():
+            yield from conditional_subpaths(v, (*conditions, condition))
+    elif isinstance(value, Sequence):
+        for v in value:
+            yield from conditional_subpaths(v, conditions)
+
+
+def conditional_exports(pkg_json: PackageJson) -> FrozenDict[str, tuple[ConditionalSubpath, ...]]:
+    """The subpaths of the "exports" of a package by subpath pattern (such as `.` or `./lib/*`)."""
+    exports: str | Sequence[Any] | Mapping[str, Any] | None = pkg_json.content.get("exports")
+    if not exports:
+        return FrozenDict()
+    if not isinstance(exports, Mapping) or not all(key.startswith(".") for key in exports):
+        exports = {".": exports}
+    return FrozenDict(
+        {key: tuple(conditional_subpaths(subpath)) for key, subpath in exports.items()}
+    )
+
+
 @dataclass(frozen=True)
 class PackageJsonImports:
     """https://nodejs.org/api/packages.html#subpath-imports."""
 
     imports: FrozenDict[str, tuple[str, ...]]
     root_dir: str
+    conditional_imports: FrozenDict[str, tuple[ConditionalSubpath, ...]] = FrozenDict()
 
     @classmethod
     def from_package_json(cls, pkg_json: PackageJson) -> PackageJsonImports:
+        imports: Mapping[str, Any] = pkg_json.content.get("imports") or {}
         return cls(
             imports=cls._import_from_package_json(pkg_json),
             root_dir=pkg_json.root_dir,
+            conditional_imports=FrozenDict(
+                {key: tuple(conditional_subpaths(subpath)) for key, subpath in imports.items()}
+            ),
         )
 
     @staticmethod
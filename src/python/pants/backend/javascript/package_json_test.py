@@ -313,3 +313,8 @@ def test_parses_subpath_imports(
             "#d/module/js/*.js": ("./module/*.js",),
         }
     )
+    assert imports.conditional_imports["#c"] == (
+        (("node",), "polyfill"),
+        (("default",), "./polyfill.js"),
+    )
+    assert imports.conditional_imports["#a"] == (((), "./yep.js"),)
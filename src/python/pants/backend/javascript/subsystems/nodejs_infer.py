@@ -4,7 +4,7 @@
 from __future__ import annotations
 
 from pants.backend.javascript.package_json import PackageJsonEntryPoints
-from pants.option.option_types import BoolOption
+from pants.option.option_types import BoolOption, StrListOption
 from pants.option.subsystem import Subsystem
 from pants.util.strutil import softwrap
 
@@ -38,3 +38,49 @@ class NodeJSInfer(Subsystem):
             """
         ),
     )
+
+    dynamic_import_patterns = BoolOption(
+        default=False,
+        advanced=True,
+        help=softwrap(
+            """
+            Infer dependencies on the files which dynamic imports of template literals might
+            refer to, e.g. every `.js` file in `pages/` for ``import(`./pages/${name}.js`)``.
+
+            Only relative templates with a static prefix or suffix are considered: each
+            substitution is treated as a `*` glob within a single directory.
+            """
+        ),
+    )
+
+    conditions = StrListOption(
+        default=[],
+        advanced=True,
+        help=softwrap(
+            """
+            The conditions (e.g. `import`, `require` or `types`) under which conditional
+            `"imports"` and `"exports"` of a `package.json` are resolved when inferring
+            dependencies. Each condition is resolved separately, falling back to `default`.
+
+            If empty, the subpaths of all conditions are inferred as dependencies.
+
+            See https://nodejs.org/api/packages.html#conditional-exports.
+            """
+        ),
+    )
+
+    resolve_extensions = StrListOption(
+        default=[".js", ".mjs", ".cjs", ".jsx", ".ts", ".tsx", ".mts", ".cts", "/index.*"],
+        advanced=True,
+        help=softwrap(
+            """
+            The extensions which are tried for imports of files without one of them, as Node and
+            TypeScript resolve them: e.g. `import './util'` infers a dependency on `util.js` or
+            `util.ts`, whichever exists.
+
+            Entries which start with a `/` are files within a directory of the imported name, and
+            a `*` is replaced with each of the other extensions: e.g. `/index.*` infers a
+            dependency on `lib/index.js` for `import './lib'`.
+            """
+        ),
+    )
@@ -121,3 +121,45 @@ def test_parser_simple(rule_runner: RuleRunner) -> None:
         "org.pantsbuild.backend.kotlin.Foo",
         "org.pantsbuild.backend.kotlin.Bar",
     }
+
+
+def test_rust_parser(rule_runner: RuleRunner) -> None:
+    rule_runner.set_options(["--kotlin-infer-use-rust-parser"], env_inherit=PYTHON_BOOTSTRAP_ENV)
+    analysis = _analyze(
+        rule_runner,
+        textwrap.dedent(
+            """\
+            package org.pantsbuild.backend.kotlin
+
+            import java.io.File
+            import org.pantsbuild.lib.*
+            import org.pantsbuild.other.Thing as OtherThing
+
+            @AutoService(Processor::class)
+            class Foo(val file: File) : Base()
+
+            fun main(args: Array<String>) {
+                Helper.run(args)
+            }
+            """
+        ),
+    )
+
+    assert analysis.package == "org.pantsbuild.backend.kotlin"
+    assert analysis.imports == {
+        KotlinImport(name="java.io.File", alias=None, is_wildcard=False),
+        KotlinImport(name="org.pantsbuild.lib", alias=None, is_wildcard=True),
+        KotlinImport(name="org.pantsbuild.other.Thing", alias="OtherThing", is_wildcard=False),
+    }
+    assert analysis.named_declarations == {
+        "org.pantsbuild.backend.kotlin.Foo",
+        "org.pantsbuild.backend.kotlin.main",
+    }
+    assert analysis.consumed_symbols_by_scope == FrozenDict(
+        {
+            "org.pantsbuild.backend.kotlin": frozenset(
+                {"Array", "AutoService", "Base", "File", "Helper", "Processor", "String"}
+            ),
+        }
+    )
+    assert analysis.scopes == {"org.pantsbuild.backend.kotlin"}
@@ -2,6 +2,7 @@
 # Licensed under the Apache License, Version 2.0 (see LICENSE).
 from pants.option.option_types import BoolOption
 from pants.option.subsystem import Subsystem
+from pants.util.strutil import softwrap
 
 
 class KotlinInferSubsystem(Subsystem):
@@ -17,3 +18,17 @@ class KotlinInferSubsystem(Subsystem):
         default=True,
         help="Infer a target's dependencies by parsing consumed types from sources.",
     )
+
+    use_rust_parser = BoolOption(
+        default=False,
+        help=softwrap(
+            """
+            Use the Rust-based, in-process dependency parser instead of running a JVM process for
+            each source file.
+
+            The Rust-based parser extracts the same imports and declarations, but only an
+            approximation of the consumed symbols, which it does not attribute to scopes.
+            """
+        ),
+        advanced=True,
+    )
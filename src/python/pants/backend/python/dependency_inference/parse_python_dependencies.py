@@ -4,7 +4,8 @@ from __future__ import annotations
 
 import logging
 import os
-from dataclasses import dataclass
+from dataclasses import dataclass, field
+from enum import Enum
 from typing import Iterable
 
 from pants.backend.python.dependency_inference.subsystem import PythonInferSubsystem
@@ -14,7 +15,7 @@ from pants.core.util_rules.source_files import SourceFilesRequest
 from pants.core.util_rules.stripped_source_files import StrippedSourceFiles
 from pants.engine.collection import DeduplicatedCollection
 from pants.engine.fs import CreateDigest, Digest, FileContent
-from pants.engine.internals.native_dep_inference import NativeParsedPythonDependencies
+from pants.engine.internals.native_dep_inference import NativeParsedPythonDependencies, SourceSpan
 from pants.engine.internals.native_engine import NativeDependenciesRequest
 from pants.engine.rules import Get, collect_rules, rule
 from pants.util.frozendict import FrozenDict
@@ -24,6 +25,16 @@ from pants.util.resources import read_resource
 logger = logging.getLogger(__name__)
 
 
+class ImportConfidence(str, Enum):
+    """How certain it is that an import refers to a module."""
+
+    # An import statement, or a call such as `importlib.import_module("a.b")`.
+    strong = "strong"
+    # A string which looks like a module path. These are only suggestions: they are used when they
+    # resolve to an owner, but don't warn when they are ambiguous.
+    weak = "weak"
+
+
 @dataclass(frozen=True, order=True)
 class ParsedPythonImportInfo:
     lineno: int
@@ -32,6 +43,10 @@ class ParsedPythonImportInfo:
     # Examples of "weak" imports include string imports (if enabled) or those inside a try block
     # which has a handler catching ImportError.
     weak: bool
+    confidence: ImportConfidence = ImportConfidence.strong
+    # The exact location of the import, for reporting. Like the line, it is the location of the
+    # first import of the module.
+    span: SourceSpan | None = field(default=None, compare=False)
 
 
 class ParsedPythonImports(FrozenDict[str, ParsedPythonImportInfo]):
@@ -111,27 +126,25 @@ async def parse_python_dependencies(
         NativeParsedPythonDependencies,
         NativeDependenciesRequest(stripped_sources.snapshot.digest),
     )
-    imports = dict(native_result.imports)
-    assets = set()
+    imports = {}
+    for name, (line, weak, confidence) in native_result.imports.items():
+        if confidence == ImportConfidence.weak and not (
+            python_infer_subsystem.string_imports
+            and name.count(".") >= python_infer_subsystem.string_imports_min_dots
+        ):
+            continue
+        imports[name] = ParsedPythonImportInfo(
+            line, weak, ImportConfidence(confidence), native_result.import_spans.get(name)
+        )
 
-    if python_infer_subsystem.string_imports or python_infer_subsystem.assets:
-        for string, line in native_result.string_candidates.items():
-            if (
-                python_infer_subsystem.string_imports
-                and string.count(".") >= python_infer_subsystem.string_imports_min_dots
-                and all(part.isidentifier() for part in string.split("."))
-            ):
-                imports.setdefault(string, (line, True))
-            if (
-                python_infer_subsystem.assets
-                and string.count("/") >= python_infer_subsystem.assets_min_slashes
-            ):
+    assets = set()
+    if python_infer_subsystem.assets:
+        for string in native_result.string_candidates:
+            if string.count("/") >= python_infer_subsystem.assets_min_slashes:
                 assets.add(string)
 
     return ParsedPythonDependencies(
-        ParsedPythonImports(
-            (key, ParsedPythonImportInfo(*value)) for key, value in imports.items()
-        ),
+        ParsedPythonImports(imports),
         ParsedPythonAssetPaths(sorted(assets)),
     )
 
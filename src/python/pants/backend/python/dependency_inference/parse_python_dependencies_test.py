@@ -9,6 +9,7 @@ import pytest
 
 from pants.backend.python.dependency_inference import parse_python_dependencies
 from pants.backend.python.dependency_inference.parse_python_dependencies import (
+    ImportConfidence,
     ParsedPythonDependencies,
 )
 from pants.backend.python.dependency_inference.parse_python_dependencies import (
@@ -22,6 +23,7 @@ from pants.backend.python.util_rules import pex
 from pants.backend.python.util_rules.interpreter_constraints import InterpreterConstraints
 from pants.core.util_rules import stripped_source_files
 from pants.engine.addresses import Address
+from pants.engine.internals.native_dep_inference import SourceSpan
 from pants.testutil.python_interpreter_selection import (
     skip_unless_python27_present,
     skip_unless_python38_present,
@@ -29,6 +31,8 @@ from pants.testutil.python_interpreter_selection import (
 )
 from pants.testutil.rule_runner import QueryRule, RuleRunner
 
+WEAK = ImportConfidence.weak
+
 
 @pytest.fixture
 def rule_runner() -> RuleRunner:
@@ -355,17 +359,17 @@ def test_imports_from_strings(rule_runner: RuleRunner, min_dots: int) -> None:
     )
 
     potentially_valid = {
-        "a.b": ImpInfo(lineno=3, weak=True),
-        "a.Foo": ImpInfo(lineno=4, weak=True),
-        "a.b.d": ImpInfo(lineno=5, weak=True),
-        "a.b2.d": ImpInfo(lineno=6, weak=True),
-        "a.b.c.Foo": ImpInfo(lineno=7, weak=True),
-        "a.b.c.d.Foo": ImpInfo(lineno=8, weak=True),
-        "a.b.c.d.FooBar": ImpInfo(lineno=9, weak=True),
-        "a.b.c.d.e.f.g.Baz": ImpInfo(lineno=10, weak=True),
-        "a.b_c.d._bar": ImpInfo(lineno=11, weak=True),
-        "a.b2.c.D": ImpInfo(lineno=12, weak=True),
-        "a.b.c_狗": ImpInfo(lineno=13, weak=True),
+        "a.b": ImpInfo(lineno=3, weak=True, confidence=WEAK),
+        "a.Foo": ImpInfo(lineno=4, weak=True, confidence=WEAK),
+        "a.b.d": ImpInfo(lineno=5, weak=True, confidence=WEAK),
+        "a.b2.d": ImpInfo(lineno=6, weak=True, confidence=WEAK),
+        "a.b.c.Foo": ImpInfo(lineno=7, weak=True, confidence=WEAK),
+        "a.b.c.d.Foo": ImpInfo(lineno=8, weak=True, confidence=WEAK),
+        "a.b.c.d.FooBar": ImpInfo(lineno=9, weak=True, confidence=WEAK),
+        "a.b.c.d.e.f.g.Baz": ImpInfo(lineno=10, weak=True, confidence=WEAK),
+        "a.b_c.d._bar": ImpInfo(lineno=11, weak=True, confidence=WEAK),
+        "a.b2.c.D": ImpInfo(lineno=12, weak=True, confidence=WEAK),
+        "a.b.c_狗": ImpInfo(lineno=13, weak=True, confidence=WEAK),
     }
     expected = {sym: info for sym, info in potentially_valid.items() if sym.count(".") >= min_dots}
 
@@ -389,6 +393,24 @@ def test_real_import_beats_string_import(rule_runner: RuleRunner) -> None:
     )
 
 
+def test_string_import_confidence(rule_runner: RuleRunner) -> None:
+    assert_deps_parsed(
+        rule_runner,
+        dedent(
+            """\
+            import a.b.c
+            importlib.import_module("d.e.f")
+            PLUGINS = ["g.h.I", "j.k"]
+            """
+        ),
+        expected_imports={
+            "a.b.c": ImpInfo(lineno=1, weak=False),
+            "d.e.f": ImpInfo(lineno=2, weak=False),
+            "g.h.I": ImpInfo(lineno=3, weak=True, confidence=WEAK),
+        },
+    )
+
+
 def test_real_import_beats_tryexcept_import(rule_runner: RuleRunner) -> None:
     assert_deps_parsed(
         rule_runner,
@@ -474,9 +496,9 @@ def test_works_with_python2(rule_runner: RuleRunner) -> None:
             "project.demo.Demo": ImpInfo(lineno=5, weak=False),
             "pkg_resources": ImpInfo(lineno=7, weak=False),
             "treat.as.a.regular.import.not.a.string.import": ImpInfo(lineno=8, weak=False),
-            "dep.from.bytes": ImpInfo(lineno=11, weak=True),
-            "dep.from.str": ImpInfo(lineno=12, weak=True),
-            "dep.from.str_狗": ImpInfo(lineno=13, weak=True),
+            "dep.from.bytes": ImpInfo(lineno=11, weak=False),
+            "dep.from.str": ImpInfo(lineno=12, weak=False),
+            "dep.from.str_狗": ImpInfo(lineno=13, weak=False),
             "weak1": ImpInfo(lineno=17, weak=True),
             "strong1": ImpInfo(lineno=18, weak=False),
             "strong2": ImpInfo(lineno=19, weak=False),
@@ -511,7 +533,7 @@ def test_works_with_python38(rule_runner: RuleRunner) -> None:
             "project.demo.Demo": ImpInfo(lineno=6, weak=False),
             "pkg_resources": ImpInfo(lineno=8, weak=False),
             "treat.as.a.regular.import.not.a.string.import": ImpInfo(lineno=9, weak=False),
-            "dep.from.str": ImpInfo(lineno=11, weak=True),
+            "dep.from.str": ImpInfo(lineno=11, weak=False),
         },
     )
 
@@ -544,7 +566,7 @@ def test_works_with_python39(rule_runner: RuleRunner) -> None:
             "project.demo.Demo": ImpInfo(lineno=8, weak=False),
             "pkg_resources": ImpInfo(lineno=10, weak=False),
             "treat.as.a.regular.import.not.a.string.import": ImpInfo(lineno=11, weak=False),
-            "dep.from.str": ImpInfo(lineno=13, weak=True),
+            "dep.from.str": ImpInfo(lineno=13, weak=False),
         },
         expected_assets=["/dev/null"],
     )
@@ -606,3 +628,27 @@ def test_assets(rule_runner: RuleRunner, min_slashes: int) -> None:
         rule_runner, content, expected_assets=expected, assets_min_slashes=min_slashes
     )
     assert_deps_parsed(rule_runner, content, assets=False, expected_assets=[])
+
+
+def test_import_spans(rule_runner: RuleRunner) -> None:
+    rule_runner.set_options(["--python-infer-use-rust-parser"], env_inherit={"PATH"})
+    rule_runner.write_files(
+        {
+            "BUILD": "python_source(name='t', source='project/foo.py')",
+            "project/foo.py": "import os\nfrom typing import (\n    Any,\n)\n",
+        }
+    )
+    tgt = rule_runner.get_target(Address("", target_name="t"))
+    result = rule_runner.request(
+        ParsedPythonDependencies,
+        [
+            ParsePythonDependenciesRequest(
+                tgt[PythonSourceField], InterpreterConstraints([">=3.6"])
+            )
+        ],
+    )
+    assert {name: info.span for name, info in result.imports.items()} == {
+        "os": SourceSpan(7, 9, 1, 7, 1, 9),
+        # The most specific name of a `from` import.
+        "typing.Any": SourceSpan(35, 38, 3, 4, 3, 7),
+    }
@@ -20,8 +20,10 @@ from pants.backend.python.dependency_inference.module_mapper import (
     ResolveName,
 )
 from pants.backend.python.dependency_inference.parse_python_dependencies import (
+    ImportConfidence,
     ParsedPythonAssetPaths,
     ParsedPythonDependencies,
+    ParsedPythonImportInfo,
     ParsedPythonImports,
     ParsePythonDependenciesRequest,
 )
@@ -162,12 +164,14 @@ def _get_imports_info(
         if owners.unambiguous:
             return ImportResolveResult(ImportOwnerStatus.unambiguous, owners.unambiguous)
 
-        explicitly_provided_deps.maybe_warn_of_ambiguous_dependency_inference(
-            owners.ambiguous,
-            address,
-            import_reference="module",
-            context=f"The target {address} imports `{import_name}`",
-        )
+        # Weak confidence imports are only suggestions, so their ambiguity isn't worth a warning.
+        if parsed_imports[import_name].confidence == ImportConfidence.strong:
+            explicitly_provided_deps.maybe_warn_of_ambiguous_dependency_inference(
+                owners.ambiguous,
+                address,
+                import_reference="module",
+                context=f"The target {address} imports `{import_name}`",
+            )
         maybe_disambiguated = explicitly_provided_deps.disambiguated(owners.ambiguous)
         if maybe_disambiguated:
             return ImportResolveResult(ImportOwnerStatus.disambiguated, (maybe_disambiguated,))
@@ -298,6 +302,13 @@ async def find_other_owners_for_unowned_import(
     return UnownedImportPossibleOwners(other_owners)
 
 
+def _import_location(import_info: ParsedPythonImportInfo) -> str:
+    if import_info.span is None:
+        return f"line: {import_info.lineno}"
+    # Columns are shown 1-based, as editors do.
+    return f"line: {import_info.lineno}, column: {import_info.span.start_column + 1}"
+
+
 async def _handle_unowned_imports(
     address: Address,
     unowned_dependency_behavior: UnownedDependencyUsage,
@@ -334,7 +345,7 @@ async def _handle_unowned_imports(
             )
 
     unowned_imports_with_lines = [
-        f"{module_name} (line: {parsed_imports[module_name].lineno})"
+        f"{module_name} ({_import_location(parsed_imports[module_name])})"
         for module_name in sorted(unowned_imports)
     ]
 
@@ -11,6 +11,7 @@ import pytest
 from pants.backend.python import target_types_rules
 from pants.backend.python.dependency_inference.module_mapper import PythonModuleOwners
 from pants.backend.python.dependency_inference.parse_python_dependencies import (
+    ImportConfidence,
     ParsedPythonImportInfo,
     ParsedPythonImports,
 )
@@ -628,7 +629,7 @@ def test_infer_python_strict(imports_rule_runner: PythonRuleRunner, caplog) -> N
     assert_owners_not_found_error(
         target="src/python/cheesey.py",
         not_found=[
-            "  * venezuelan_beaver_cheese (line: 1)",
+            "  * venezuelan_beaver_cheese (line: 1, column: 8)",
         ],
         found=[
             "japanese.sage.derby",
@@ -844,6 +845,16 @@ class TestCategoriseImportsInfo:
             ParsedPythonImportInfo(0, False),
             PythonModuleOwners(tuple()),
         ),
+        "weak_confidence_ambiguous": (
+            ParsedPythonImportInfo(0, True, ImportConfidence.weak),
+            PythonModuleOwners(
+                tuple(),
+                (
+                    Address("ambiguous_disambiguatable", target_name="bad0"),
+                    Address("ambiguous_disambiguatable", target_name="bad1"),
+                ),
+            ),
+        ),
     }
 
     def filter_case(self, case_name: str, cases=None):
@@ -921,6 +932,12 @@ class TestCategoriseImportsInfo:
         resolved = self.do_test(case_name, ImportOwnerStatus.unowned)
         assert resolved.address == ()
 
+    def test_weak_confidence_ambiguous(self, caplog):
+        case_name = "weak_confidence_ambiguous"
+        resolved = self.do_test(case_name, ImportOwnerStatus.weak_ignore)
+        assert resolved.address == ()
+        assert not caplog.records
+
 
 class TestFindOtherOwners:
     missing_import_name = "missing"
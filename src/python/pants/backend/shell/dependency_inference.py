@@ -5,6 +5,7 @@ from __future__ import annotations
 
 import json
 import logging
+import os
 import re
 from collections import defaultdict
 from dataclasses import dataclass
@@ -17,6 +18,8 @@ from pants.core.util_rules.external_tool import DownloadedExternalTool, External
 from pants.engine.addresses import Address
 from pants.engine.collection import DeduplicatedCollection
 from pants.engine.fs import Digest, MergeDigests
+from pants.engine.internals.native_dep_inference import NativeParsedShellDependencies
+from pants.engine.internals.native_engine import NativeDependenciesRequest
 from pants.engine.platform import Platform
 from pants.engine.process import FallibleProcessResult, Process, ProcessCacheScope
 from pants.engine.rules import Get, MultiGet, collect_rules, rule
@@ -94,8 +97,23 @@ PATH_FROM_SHELLCHECK_ERROR = re.compile(r"Not following: (.+) was not specified
 
 @rule
 async def parse_shell_imports(
-    request: ParseShellImportsRequest, shellcheck: Shellcheck, platform: Platform
+    request: ParseShellImportsRequest,
+    shellcheck: Shellcheck,
+    shell_setup: ShellSetup,
+    platform: Platform,
 ) -> ParsedShellImports:
+    if shell_setup.use_rust_parser:
+        parsed = await Get(
+            NativeParsedShellDependencies, NativeDependenciesRequest(request.digest, None)
+        )
+        # Scripts which are invoked by their (relative) path, e.g. `./scripts/build.sh`.
+        invoked_scripts = (
+            os.path.normpath(command)
+            for command in parsed.commands
+            if "/" in command and not os.path.isabs(command)
+        )
+        return ParsedShellImports([*parsed.file_imports, *invoked_scripts])
+
     # We use Shellcheck to parse for us by running it against each file in isolation, which means
     # that all `source` statements will error. Then, we can extract the problematic paths from the
     # JSON output.
@@ -27,9 +27,9 @@ from pants.testutil.rule_runner import QueryRule, RuleRunner
 from pants.util.frozendict import FrozenDict
 
 
-@pytest.fixture
-def rule_runner() -> RuleRunner:
-    return RuleRunner(
+@pytest.fixture(params=[False, True], ids=["shellcheck_parser", "rust_parser"])
+def rule_runner(request: pytest.FixtureRequest) -> RuleRunner:
+    rule_runner = RuleRunner(
         rules=[
             *dependency_inference.rules(),
             *external_tool.rules(),
@@ -40,6 +40,8 @@ def rule_runner() -> RuleRunner:
         ],
         target_types=[ShellSourcesGeneratorTarget, Shunit2TestsGeneratorTarget],
     )
+    rule_runner.set_options([f"--shell-setup-use-rust-parser={request.param}"])
+    return rule_runner
 
 
 def test_shell_mapping(rule_runner: RuleRunner) -> None:
@@ -95,6 +97,27 @@ def test_parse_imports(rule_runner: RuleRunner) -> None:
     assert parse("# shellcheck source=a/b.sh\nsource ${FOO}") == {"a/b.sh"}
 
 
+def test_parse_imports_rust_parser(rule_runner: RuleRunner) -> None:
+    rule_runner.set_options(["--shell-setup-use-rust-parser"])
+    snapshot = rule_runner.make_snapshot(
+        {
+            "subdir/f.sh": dedent(
+                """\
+                source a/b.sh
+                source c/d.sh  # pants: no-infer-dep
+                ./scripts/build.sh --release
+                /usr/bin/env true
+                echo done
+                """
+            )
+        }
+    )
+    result = rule_runner.request(
+        ParsedShellImports, [ParseShellImportsRequest(snapshot.digest, "subdir/f.sh")]
+    )
+    assert set(result) == {"a/b.sh", "scripts/build.sh"}
+
+
 def test_dependency_inference(rule_runner: RuleRunner, caplog) -> None:
     rule_runner.write_files(
         {
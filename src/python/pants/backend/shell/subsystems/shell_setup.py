@@ -18,6 +18,19 @@ class ShellSetup(Subsystem):
         help="Infer Shell dependencies on other Shell files by analyzing `source` statements.",
         advanced=True,
     )
+    use_rust_parser = BoolOption(
+        default=False,
+        help=softwrap(
+            """
+            Use the Rust-based, in-process parser to find `source` statements for dependency
+            inference, instead of running Shellcheck on each file.
+
+            The Rust-based parser also infers dependencies on the scripts which are invoked by
+            their path, e.g. `./scripts/build.sh`.
+            """
+        ),
+        advanced=True,
+    )
     tailor = BoolOption(
         default=True,
         help=softwrap("If true, add `shell_sources` targets with the `tailor` goal."),
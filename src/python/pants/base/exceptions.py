@@ -9,7 +9,16 @@ from pants.engine.internals.native_engine import EngineError as EngineError  # n
 from pants.engine.internals.native_engine import (  # noqa: F401
     IncorrectProductError as IncorrectProductError,
 )
+from pants.engine.internals.native_engine import (  # noqa: F401
+    InfrastructureError as InfrastructureError,
+)
+from pants.engine.internals.native_engine import InternalError as InternalError  # noqa: F401
 from pants.engine.internals.native_engine import IntrinsicError as IntrinsicError  # noqa: F401
+from pants.engine.internals.native_engine import RootCancelled as RootCancelled  # noqa: F401
+from pants.engine.internals.native_engine import (  # noqa: F401
+    RunBudgetExceeded as RunBudgetExceeded,
+)
+from pants.engine.internals.native_engine import UserError as UserError  # noqa: F401
 
 if TYPE_CHECKING:
     from pants.engine.internals.native_engine import PyFailure
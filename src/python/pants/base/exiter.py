@@ -6,3 +6,9 @@ ExitCode = int
 # Centralize integer return codes for the pants process.
 PANTS_SUCCEEDED_EXIT_CODE: ExitCode = 0
 PANTS_FAILED_EXIT_CODE: ExitCode = 1
+# NB: Matches the exit code of coreutils' `timeout`.
+PANTS_RUN_BUDGET_EXCEEDED_EXIT_CODE: ExitCode = 124
+# NB: Match `EX_SOFTWARE` and `EX_TEMPFAIL` from sysexits.h: used when a run fails only due to
+# errors which the engine classified as internal or infrastructure errors, respectively.
+PANTS_INTERNAL_ERROR_EXIT_CODE: ExitCode = 70
+PANTS_INFRA_FAILED_EXIT_CODE: ExitCode = 75
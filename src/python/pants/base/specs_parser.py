@@ -84,21 +84,22 @@ class SpecsParser:
         """Parse the given spec string and also return `true` if it's an ignore.
 
         :raises: CmdLineSpecParser.BadSpecError if the address selector could not be parsed.
+        :raises: AddressParseException if the spec is syntactically invalid.
         """
-        is_ignore = False
-        if spec.startswith("-"):
-            is_ignore = True
-            spec = spec[1:]
-
         (
+            is_ignore,
             (
-                path_component,
-                target_component,
-                generated_component,
-                parameters,
+                (
+                    path_component,
+                    target_component,
+                    generated_component,
+                    parameters,
+                ),
+                wildcard,
             ),
-            wildcard,
-        ) = native_engine.address_spec_parse(spec)
+        ) = native_engine.command_line_spec_parse(spec)
+        if is_ignore:
+            spec = spec[1:]
 
         if wildcard == "::":
             return RecursiveGlobSpec(directory=self._normalize_spec_path(path_component)), is_ignore
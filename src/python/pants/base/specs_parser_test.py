@@ -18,6 +18,7 @@ from pants.base.specs import (
     Spec,
 )
 from pants.base.specs_parser import SpecsParser
+from pants.engine.internals.native_engine import AddressParseException
 from pants.util.frozendict import FrozenDict
 
 
@@ -269,3 +270,29 @@ def test_invalid_working_dir(tmp_path: Path) -> None:
         assert_spec_parsed(
             tmp_path, "test_invalid_working_dir0/foo", dir_literal("foo"), str(tmp_path / "..")
         )
+
+
+@pytest.mark.parametrize(
+    "spec,message",
+    [
+        (
+            "dir:tgt::",
+            "the wildcard `::` may not follow a target name, generated target name or "
+            "parameters.\n\n  dir:tgt::\n         ^^",
+        ),
+        (
+            "-dir:tg!t",
+            "the character `!` is not allowed in a target name.\n\n  -dir:tg!t\n         ^",
+        ),
+        (
+            "dir:tgt#",
+            "expected a non-empty generated target name to follow a `#`."
+            "\n\n  dir:tgt#\n          ^",
+        ),
+    ],
+)
+def test_invalid_specs(tmp_path: Path, spec: str, message: str) -> None:
+    parser = SpecsParser(root_dir=str(tmp_path))
+    with pytest.raises(AddressParseException) as exc:
+        parser.parse_spec(spec)
+    assert str(exc.value) == f"Failed to parse address spec `{spec}`: {message}"
@@ -6,8 +6,8 @@ import os
 import sys
 import time
 from contextlib import contextmanager
-from threading import Lock
-from typing import Dict, Tuple
+from threading import Lock, Thread
+from typing import Dict, Optional, Tuple
 
 from pants.base.exiter import PANTS_FAILED_EXIT_CODE, ExitCode
 from pants.bin.local_pants_runner import LocalPantsRunner
@@ -50,6 +50,10 @@ class DaemonPantsRunner:
         Periodically prints a message on the given stderr_fileno while exclusive access cannot be
         acquired.
 
+        If the run completes without raising, exclusive access is held until `_post_run` has
+        completed in the background, which allows the run's exit code to be sent to the client
+        without waiting for it.
+
         TODO: This method will be removed as part of #7654, so it currently polls the lock and
         cancellation latch rather than waiting for both of them asynchronously, which would be a bit
         cleaner.
@@ -79,9 +83,11 @@ class DaemonPantsRunner:
             if acquired:
                 try:
                     yield
-                    break
-                finally:
+                except BaseException:
                     self._run_lock.release()
+                    raise
+                Thread(target=self._post_run, name="pantsd-post-run", daemon=True).start()
+                break
             elif should_keep_polling(now):
                 if now > render_deadline:
                     self._send_stderr(
@@ -95,6 +101,17 @@ class DaemonPantsRunner:
                     "Timed out while waiting for another pants invocation to finish."
                 )
 
+    def _post_run(self) -> None:
+        """Runs between runs while holding exclusive access, and then releases it."""
+        try:
+            # NB: Memory usage is checked while holding the run lock, so that no other run can
+            # observe the caches being cleared. A failure to check must not affect later runs.
+            self._core.check_memory_usage()
+        except Exception as e:
+            logger.exception(f"Failed to check memory usage: {e}")
+        finally:
+            self._run_lock.release()
+
     def single_daemonized_run(
         self,
         args: Tuple[str, ...],
@@ -157,6 +174,7 @@ class DaemonPantsRunner:
         stdin_fileno: int,
         stdout_fileno: int,
         stderr_fileno: int,
+        events_fileno: Optional[int] = None,
     ) -> ExitCode:
         request_timeout = float(env.get("PANTSD_REQUEST_TIMEOUT_LIMIT", -1))
         # NB: Order matters: we acquire a lock before mutating either `sys.std*`, `os.environ`, etc.
@@ -173,9 +191,11 @@ class DaemonPantsRunner:
                     stdin_fileno=stdin_fileno,
                     stdout_fileno=stdout_fileno,
                     stderr_fileno=stderr_fileno,
+                    events_fileno=events_fileno,
                 ):
-                    return self.single_daemonized_run(
+                    exit_code = self.single_daemonized_run(
                         ((command,) + args), env, working_dir, cancellation_latch
                     )
             finally:
                 logger.info(f"request completed: `{' '.join(args)}`")
+        return exit_code
@@ -0,0 +1,53 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+import sys
+from threading import Event
+
+import pytest
+
+from pants.bin.daemon_pants_runner import DaemonPantsRunner
+from pants.engine.internals.native_engine import PySessionCancellationLatch
+
+
+class BlockingCore:
+    def __init__(self) -> None:
+        self.checking = Event()
+        self.proceed = Event()
+
+    def check_memory_usage(self) -> None:
+        self.checking.set()
+        assert self.proceed.wait(timeout=10)
+
+
+def test_memory_checked_after_run_while_locked() -> None:
+    core = BlockingCore()
+    runner = DaemonPantsRunner(core)  # type: ignore[arg-type]
+
+    with runner._one_run_at_a_time(
+        sys.stderr.fileno(), cancellation_latch=PySessionCancellationLatch(), timeout=-1
+    ):
+        assert not core.checking.is_set()
+
+    # The run has completed (and so its exit code can be returned) before memory has been checked,
+    # but the next run cannot begin until memory has been checked.
+    assert core.checking.wait(timeout=10)
+    assert runner._run_lock.locked()
+    core.proceed.set()
+    assert runner._run_lock.acquire(timeout=10)
+
+
+def test_lock_released_if_run_raises() -> None:
+    core = BlockingCore()
+    runner = DaemonPantsRunner(core)  # type: ignore[arg-type]
+
+    with pytest.raises(ValueError):
+        with runner._one_run_at_a_time(
+            sys.stderr.fileno(), cancellation_latch=PySessionCancellationLatch(), timeout=-1
+        ):
+            raise ValueError()
+
+    assert not runner._run_lock.locked()
+    assert not core.checking.is_set()
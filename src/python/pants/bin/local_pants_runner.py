@@ -4,10 +4,21 @@
 from __future__ import annotations
 
 import logging
+import os
 import sys
+import time
 from dataclasses import dataclass
 
-from pants.base.exiter import PANTS_FAILED_EXIT_CODE, PANTS_SUCCEEDED_EXIT_CODE, ExitCode
+from pants.base.build_environment import get_buildroot
+from pants.base.exceptions import InfrastructureError, InternalError
+from pants.base.exiter import (
+    PANTS_FAILED_EXIT_CODE,
+    PANTS_INFRA_FAILED_EXIT_CODE,
+    PANTS_INTERNAL_ERROR_EXIT_CODE,
+    PANTS_RUN_BUDGET_EXCEEDED_EXIT_CODE,
+    PANTS_SUCCEEDED_EXIT_CODE,
+    ExitCode,
+)
 from pants.base.specs import Specs
 from pants.base.specs_parser import SpecsParser
 from pants.build_graph.build_configuration import BuildConfiguration
@@ -19,6 +30,7 @@ from pants.engine.internals.native_engine import PyExecutor, PySessionCancellati
 from pants.engine.internals.scheduler import ExecutionError
 from pants.engine.internals.selectors import Params
 from pants.engine.internals.session import SessionValues
+from pants.engine.markup import MarkupStyle
 from pants.engine.streaming_workunit_handler import (
     StreamingWorkunitHandler,
     WorkunitsCallback,
@@ -28,7 +40,11 @@ from pants.engine.unions import UnionMembership
 from pants.goal.builtin_goal import BuiltinGoal
 from pants.goal.run_tracker import RunTracker
 from pants.init.engine_initializer import EngineInitializer, GraphScheduler, GraphSession
-from pants.init.logging import stdio_destination_use_color
+from pants.init.logging import (
+    stdio_destination_emit_event,
+    stdio_destination_log_levels_by_target,
+    stdio_destination_use_color,
+)
 from pants.init.options_initializer import OptionsInitializer
 from pants.init.specs_calculator import calculate_specs
 from pants.option.global_options import DynamicRemoteOptions, DynamicUIRenderer, GlobalOptions
@@ -38,6 +54,26 @@ from pants.util.logging import LogLevel
 
 logger = logging.getLogger(__name__)
 
+# The maximum number of rules to include in the output stability report.
+_OUTPUT_STABILITY_REPORT_MAX_RULES = 20
+
+
+def _exit_code_for_failure(e: Exception) -> ExitCode:
+    """Determine the exit code for a run which failed with the given exception.
+
+    A run which failed only due to infrastructure errors (which might succeed if retried) or only
+    due to internal errors exits with a distinct code. Any other failure exits with the generic
+    failure code.
+    """
+    failures = e.wrapped_exceptions if isinstance(e, ExecutionError) else ()
+    if not failures:
+        failures = (e,)
+    if all(isinstance(f, InfrastructureError) for f in failures):
+        return PANTS_INFRA_FAILED_EXIT_CODE
+    if all(isinstance(f, InternalError) for f in failures):
+        return PANTS_INTERNAL_ERROR_EXIT_CODE
+    return PANTS_FAILED_EXIT_CODE
+
 
 @dataclass
 class LocalPantsRunner:
@@ -80,6 +116,7 @@ class LocalPantsRunner:
         :param options_bootstrapper: The OptionsBootstrapper instance to reuse.
         :param scheduler: If being called from the daemon, a warmed scheduler to use.
         """
+        stdio_destination_emit_event("phase", phase="initializing")
         global_bootstrap_options = options_bootstrapper.bootstrap_options.for_global_scope()
         executor = (
             scheduler.scheduler.py_executor
@@ -96,6 +133,7 @@ class LocalPantsRunner:
             options_bootstrapper, env, build_config, union_membership, raise_=True
         )
         stdio_destination_use_color(options.for_global_scope().colors)
+        stdio_destination_log_levels_by_target(options.for_global_scope())
 
         run_tracker = RunTracker(options_bootstrapper.args, options)
         native_engine.maybe_set_panic_handler()
@@ -148,6 +186,33 @@ class LocalPantsRunner:
                 }
             ),
             cancellation_latch=cancellation_latch,
+            chrome_trace_file=(
+                os.path.join(get_buildroot(), global_options.chrome_trace_file)
+                if global_options.chrome_trace_file
+                else None
+            ),
+            record_critical_path=(
+                global_options.record_critical_path or global_options.build_stats_summary
+            ),
+            stream_process_output=global_options.stream_process_output,
+            run_budget_seconds=global_options.run_budget,
+            workunit_sampling_threshold=global_options.streaming_workunits_sampling_threshold,
+            workunit_sampling_interval=global_options.streaming_workunits_sampling_interval,
+            cache_miss_records_dir=(
+                os.path.join(global_options.pants_workdir, "cache_misses")
+                if global_options.record_cache_misses
+                else None
+            ),
+            # NB: The style is detected using the environment of the client, which differs from
+            # that of this process when running in pantsd.
+            markup_style=MarkupStyle.detect(
+                env,
+                isatty=global_options.get("colors", True) and sys.stdout.isatty(),
+            ),
+        )
+
+        graph_session.scheduler_session.scheduler.set_output_stability_tracking(
+            global_options.output_stability_report
         )
 
         specs = calculate_specs(
@@ -225,6 +290,19 @@ class LocalPantsRunner:
             union_membership=self.union_membership,
         )
 
+    def _log_output_stability_report(self) -> None:
+        report = self.graph_session.scheduler_session.scheduler.output_stability_report()
+        if not report:
+            logger.info("Output stability: no rules re-ran with identical outputs.")
+            return
+        lines = [
+            "Output stability: rules which re-ran after invalidation with identical outputs:"
+        ]
+        for wasted in report[:_OUTPUT_STABILITY_REPORT_MAX_RULES]:
+            trigger = " -> ".join(wasted["trigger"])
+            lines.append(f"  {wasted['count']:>6}  {wasted['kind']} (last triggered by: {trigger})")
+        logger.info("\n".join(lines))
+
     def _run_inner(self) -> ExitCode:
         if self.options.builtin_goal:
             return self._run_builtin_goal(self.options.builtin_goal)
@@ -237,7 +315,7 @@ class LocalPantsRunner:
             return self._perform_run(goals)
         except Exception as e:
             logger.error(e)
-            return PANTS_FAILED_EXIT_CODE
+            return _exit_code_for_failure(e)
         except KeyboardInterrupt:
             print("Interrupted by user.\n", file=sys.stderr)
             return PANTS_FAILED_EXIT_CODE
@@ -251,6 +329,12 @@ class LocalPantsRunner:
 
         self.run_tracker.start(run_start_time=start_time, specs=specs)
         global_options = self.options.for_global_scope()
+        stdio_destination_emit_event(
+            "phase",
+            phase="running",
+            run_id=self.run_tracker.run_id,
+            goals=[self.options.builtin_goal] if self.options.builtin_goal else self.options.goals,
+        )
 
         streaming_reporter = StreamingWorkunitHandler(
             self.graph_session.scheduler_session,
@@ -258,7 +342,6 @@ class LocalPantsRunner:
             specs=self.specs,
             options_bootstrapper=self.options_bootstrapper,
             callbacks=self._get_workunits_callbacks(),
-            report_interval_seconds=global_options.streaming_workunits_report_interval,
             allow_async_completion=(
                 global_options.pantsd and global_options.streaming_workunits_complete_async
             ),
@@ -269,13 +352,31 @@ class LocalPantsRunner:
                 engine_result = PANTS_FAILED_EXIT_CODE
                 try:
                     engine_result = self._run_inner()
+                    if self.graph_session.scheduler_session.run_budget_exceeded():
+                        engine_result = PANTS_RUN_BUDGET_EXCEEDED_EXIT_CODE
                 finally:
+                    stdio_destination_emit_event("phase", phase="finishing")
                     self.graph_session.scheduler_session.wait_for_tail_tasks(
                         self.session_end_tasks_timeout
                     )
+                    self.graph_session.scheduler_session.write_chrome_trace()
                     metrics = self.graph_session.scheduler_session.metrics()
                     self.run_tracker.set_pantsd_scheduler_metrics(metrics)
+                    stdio_destination_emit_event("workunit_summary", counters=metrics)
+                    if global_options.build_stats_summary:
+                        logger.info(
+                            "Build stats:\n"
+                            + self.graph_session.scheduler_session.render_build_stats()
+                        )
+                    if global_options.output_stability_report:
+                        self._log_output_stability_report()
                     self.run_tracker.end_run(engine_result)
+                    stdio_destination_emit_event(
+                        "exit",
+                        exit_code=engine_result,
+                        run_id=self.run_tracker.run_id,
+                        elapsed_secs=time.time() - start_time,
+                    )
 
                 return engine_result
         finally:
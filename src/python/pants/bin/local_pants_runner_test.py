@@ -0,0 +1,88 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+import pytest
+
+from pants.base.exceptions import InfrastructureError, InternalError, UserError
+from pants.base.exiter import (
+    PANTS_FAILED_EXIT_CODE,
+    PANTS_INFRA_FAILED_EXIT_CODE,
+    PANTS_INTERNAL_ERROR_EXIT_CODE,
+)
+from pants.bin.local_pants_runner import _exit_code_for_failure
+from pants.engine.fs import AddPrefix, CreateDigest, Digest, FileContent
+from pants.engine.internals.scheduler import ExecutionError
+from pants.engine.process import Process, ProcessResult
+from pants.engine.rules import Get, rule
+from pants.testutil.rule_runner import QueryRule, RuleRunner
+from pants.util.frozendict import FrozenDict
+
+
+def create_outlined_get() -> Get[int]:
+    return Get(int, str, "hello")
+
+
+@rule
+async def uses_outlined_get() -> int:
+    return await create_outlined_get()
+
+
+@pytest.fixture
+def rule_runner() -> RuleRunner:
+    return RuleRunner(
+        rules=[
+            uses_outlined_get,
+            QueryRule(int, []),
+            QueryRule(ProcessResult, [Process]),
+        ],
+    )
+
+
+def test_user_error(rule_runner: RuleRunner) -> None:
+    digest = rule_runner.request(Digest, [CreateDigest([FileContent("main.ext", b"")])])
+    with pytest.raises(ExecutionError) as exc:
+        rule_runner.request(Digest, [AddPrefix(digest, "../something")])
+
+    (error,) = exc.value.wrapped_exceptions
+    assert isinstance(error, UserError)
+    assert error.code == "invalid_digest_prefix"
+    assert error.category == "user"
+    # Invalid inputs are the common case, and so use the generic failure exit code.
+    assert _exit_code_for_failure(exc.value) == PANTS_FAILED_EXIT_CODE
+
+
+def test_infrastructure_error(rule_runner: RuleRunner) -> None:
+    process = Process(
+        argv=("/does/not/exist",),
+        env=FrozenDict(),
+        description="a binary which does not exist",
+    )
+    with pytest.raises(ExecutionError) as exc:
+        rule_runner.request(ProcessResult, [process])
+
+    (error,) = exc.value.wrapped_exceptions
+    assert isinstance(error, InfrastructureError)
+    assert error.code == "process_execution_failed"
+    assert error.category == "infra"
+    assert _exit_code_for_failure(exc.value) == PANTS_INFRA_FAILED_EXIT_CODE
+
+
+def test_internal_error(rule_runner: RuleRunner) -> None:
+    # Fails because the creation of the `Get` was out-of-lined into a separate function.
+    with pytest.raises(ExecutionError) as exc:
+        rule_runner.request(int, [])
+
+    (error,) = exc.value.wrapped_exceptions
+    assert isinstance(error, InternalError)
+    assert error.code == "undetected_rule_call"
+    assert error.category == "internal"
+    assert _exit_code_for_failure(exc.value) == PANTS_INTERNAL_ERROR_EXIT_CODE
+
+
+def test_mixed_errors_use_generic_exit_code() -> None:
+    errors = (InfrastructureError("unavailable"), InternalError("bug"))
+    assert _exit_code_for_failure(ExecutionError("failed", wrapped_exceptions=errors)) == (
+        PANTS_FAILED_EXIT_CODE
+    )
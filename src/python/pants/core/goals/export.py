@@ -21,14 +21,14 @@ from pants.engine.collection import Collection
 from pants.engine.console import Console
 from pants.engine.env_vars import EnvironmentVars, EnvironmentVarsRequest
 from pants.engine.environment import EnvironmentName
-from pants.engine.fs import EMPTY_DIGEST, AddPrefix, Digest, MergeDigests, Workspace
+from pants.engine.fs import EMPTY_DIGEST, AddPrefix, Digest, ExportMode, MergeDigests, Workspace
 from pants.engine.goal import Goal, GoalSubsystem
 from pants.engine.internals.selectors import Effect, Get, MultiGet
 from pants.engine.process import InteractiveProcess, InteractiveProcessResult
 from pants.engine.rules import collect_rules, goal_rule
 from pants.engine.target import FilteredTargets, Target
 from pants.engine.unions import UnionMembership, union
-from pants.option.option_types import StrListOption
+from pants.option.option_types import EnumOption, StrListOption
 from pants.util.dirutil import safe_rmtree
 from pants.util.frozendict import FrozenDict
 from pants.util.strutil import softwrap
@@ -128,6 +128,20 @@ class ExportSubsystem(GoalSubsystem):
         help="Export the specified resolve(s). The export format is backend-specific, "
         "e.g., Python resolves are exported as virtualenvs.",
     )
+    materialization = EnumOption(
+        default=ExportMode.copy,
+        help=softwrap(
+            """
+            How to materialize the files of exports which are not post-processed (for example,
+            by creating a virtualenv).
+
+            Such exports are updated incrementally: only files which have changed since the
+            previous export are written, and files which are no longer exported are removed.
+            With `symlink` or `hardlink`, large files are linked into the local store rather than
+            being copied, and will be read-only.
+            """
+        ),
+    )
 
 
 class Export(Goal):
@@ -152,11 +166,22 @@ async def export(
 
     await _warn_on_non_local_environments(targets, "the `export` goal")
 
-    prefixed_digests = await MultiGet(
-        Get(Digest, AddPrefix(result.digest, result.reldir)) for result in flattened_results
-    )
     output_dir = os.path.join(str(dist_dir.relpath), "export")
+    # Results which are post-processed are cleared and rewritten in full, since their commands may
+    # write arbitrary files to their directories. Other results are exported incrementally.
+    post_processed_results = [result for result in flattened_results if result.post_processing_cmds]
     for result in flattened_results:
+        if not result.post_processing_cmds:
+            workspace.export_digest(
+                result.digest,
+                path_prefix=os.path.join(output_dir, result.reldir),
+                mode=export_subsys.materialization,
+            )
+
+    prefixed_digests = await MultiGet(
+        Get(Digest, AddPrefix(result.digest, result.reldir)) for result in post_processed_results
+    )
+    for result in post_processed_results:
         digest_root = os.path.join(build_root.path, output_dir, result.reldir)
         safe_rmtree(digest_root)
     merged_digest = await Get(Digest, MergeDigests(prefixed_digests))
@@ -31,7 +31,15 @@ from pants.core.util_rules.environments import (
 )
 from pants.engine.addresses import Address
 from pants.engine.env_vars import EnvironmentVars, EnvironmentVarsRequest
-from pants.engine.fs import AddPrefix, CreateDigest, Digest, FileContent, MergeDigests, Workspace
+from pants.engine.fs import (
+    AddPrefix,
+    CreateDigest,
+    Digest,
+    ExportMode,
+    FileContent,
+    MergeDigests,
+    Workspace,
+)
 from pants.engine.process import InteractiveProcess, InteractiveProcessResult
 from pants.engine.rules import QueryRule
 from pants.engine.target import Target, Targets
@@ -86,7 +94,19 @@ def _mock_run(rule_runner: RuleRunner, ip: InteractiveProcess) -> InteractivePro
     return InteractiveProcessResult(0)
 
 
-def run_export_rule(rule_runner: RuleRunner, targets: List[Target]) -> Tuple[int, str]:
+_COPY_TO_BAR1_AND_BAR2 = (
+    PostProcessingCommand(["cp", "{digest_root}/foo/bar", "{digest_root}/foo/bar1"]),
+    PostProcessingCommand(["cp", "{digest_root}/foo/bar", "{digest_root}/foo/bar2"]),
+)
+
+
+def run_export_rule(
+    rule_runner: RuleRunner,
+    targets: List[Target],
+    *,
+    post_processing_cmds: tuple[PostProcessingCommand, ...] = _COPY_TO_BAR1_AND_BAR2,
+    materialization: ExportMode = ExportMode.copy,
+) -> Tuple[int, str]:
     union_membership = UnionMembership({ExportRequest: [MockExportRequest]})
     with open(os.path.join(rule_runner.build_root, "somefile"), "wb") as fp:
         fp.write(b"SOMEFILE")
@@ -101,27 +121,14 @@ def run_export_rule(rule_runner: RuleRunner, targets: List[Target]) -> Tuple[int
                 union_membership,
                 BuildRoot(),
                 DistDir(relpath=Path("dist")),
-                create_subsystem(ExportSubsystem, resolve=[]),
+                create_subsystem(ExportSubsystem, resolve=[], materialization=materialization),
             ],
             mock_gets=[
                 MockGet(
                     output_type=ExportResults,
                     input_types=(ExportRequest,),
                     mock=lambda req: ExportResults(
-                        (
-                            mock_export(
-                                req,
-                                digest,
-                                (
-                                    PostProcessingCommand(
-                                        ["cp", "{digest_root}/foo/bar", "{digest_root}/foo/bar1"]
-                                    ),
-                                    PostProcessingCommand(
-                                        ["cp", "{digest_root}/foo/bar", "{digest_root}/foo/bar2"]
-                                    ),
-                                ),
-                            ),
-                        )
+                        (mock_export(req, digest, post_processing_cmds),)
                     ),
                 ),
                 MockGet(
@@ -166,6 +173,34 @@ def test_run_export_rule() -> None:
             assert fp.read() == b"BAR"
 
 
+@pytest.mark.parametrize("materialization", list(ExportMode))
+def test_run_export_rule_incrementally(materialization: ExportMode) -> None:
+    rule_runner = RuleRunner(
+        rules=[
+            UnionRule(ExportRequest, MockExportRequest),
+            QueryRule(Digest, [CreateDigest]),
+            QueryRule(EnvironmentVars, [EnvironmentVarsRequest]),
+        ],
+        target_types=[MockTarget],
+    )
+    export_dir = Path(rule_runner.build_root, "dist", "export", "mock")
+    export_dir.mkdir(parents=True)
+    (export_dir / "unrelated").write_text("UNRELATED")
+
+    for _ in range(2):
+        exit_code, stdout = run_export_rule(
+            rule_runner,
+            [make_target("foo/bar", "baz")],
+            post_processing_cmds=(),
+            materialization=materialization,
+        )
+        assert exit_code == 0
+        assert "Wrote mock export for foo/bar:baz to dist/export/mock" in stdout
+        assert (export_dir / "foo" / "bar").read_bytes() == b"BAR"
+        # Exports which are not post-processed are not cleared before being written.
+        assert (export_dir / "unrelated").read_text() == "UNRELATED"
+
+
 def _e(path, env):
     return make_target(path, path, env)
 
@@ -234,7 +269,9 @@ def test_warnings_for_non_local_target_environments(
                 union_membership,
                 BuildRoot(),
                 DistDir(relpath=Path("dist")),
-                create_subsystem(ExportSubsystem, resolve=[]),
+                create_subsystem(
+                    ExportSubsystem, resolve=[], materialization=ExportMode.copy
+                ),
             ],
             mock_gets=[
                 MockGet(
@@ -37,6 +37,8 @@ from pants.engine.engine_aware import EngineAwareReturnType
 from pants.engine.env_vars import EnvironmentVars, EnvironmentVarsRequest
 from pants.engine.fs import EMPTY_FILE_DIGEST, Digest, FileDigest, MergeDigests, Snapshot, Workspace
 from pants.engine.goal import Goal, GoalSubsystem
+from pants.engine.internals.native_engine import AggregateTestResultsRequest
+from pants.engine.internals.native_test_results import TestResultsSummary
 from pants.engine.internals.session import RunId
 from pants.engine.process import (
     FallibleProcessResult,
@@ -270,7 +272,10 @@ class TestResult(EngineAwareReturnType):
         return f"{message}{output}"
 
     def metadata(self) -> dict[str, Any]:
-        return {"addresses": [address.spec for address in self.addresses]}
+        return {
+            "addresses": [address.spec for address in self.addresses],
+            "exit_code": self.exit_code,
+        }
 
     def cacheable(self) -> bool:
         """Is marked uncacheable to ensure that it always renders."""
@@ -969,12 +974,13 @@ async def run_tests(
 
     if test_subsystem.report:
         report_dir = test_subsystem.report_dir(distdir)
-        merged_reports = await Get(
-            Digest,
-            MergeDigests(result.xml_results.digest for result in results if result.xml_results),
-        )
+        report_digests = [result.xml_results.digest for result in results if result.xml_results]
+        merged_reports = await Get(Digest, MergeDigests(report_digests))
         workspace.write_digest(merged_reports, path_prefix=str(report_dir))
         console.print_stderr(f"\nWrote test reports to {report_dir}")
+        report_summary = await Get(TestResultsSummary, AggregateTestResultsRequest(report_digests))
+        if report_summary.total:
+            console.print_stderr(_format_report_summary(report_summary))
 
     if test_subsystem.use_coverage:
         # NB: We must pre-sort the data for itertools.groupby() to work properly, using the same
@@ -1090,6 +1096,22 @@ def _format_test_summary(result: TestResult, run_id: RunId, console: Console) ->
     return f"{sigil} {result.description} {status}{attempt_msg} {elapsed_print}{source_desc}."
 
 
+def _format_report_summary(summary: TestResultsSummary) -> str:
+    """Format the totals of the test cases in the test reports."""
+    counts = [
+        f"{count} {label}"
+        for count, label in (
+            (summary.passed, "passed"),
+            (summary.failed, "failed"),
+            (summary.errored, "errored"),
+            (summary.skipped, "skipped"),
+            (summary.flaky, "flaky"),
+        )
+        if count
+    ]
+    return f"Ran {summary.total} test cases in {summary.duration_secs:.2f}s: {', '.join(counts)}."
+
+
 def _format_test_rerun_command(results: Iterable[TestResult]) -> None | str:
     failures = [result for result in results if result.exit_code not in (None, 0)]
     if not failures:
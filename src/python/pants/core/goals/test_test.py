@@ -33,6 +33,7 @@ from pants.core.goals.test import (
     TestResult,
     TestSubsystem,
     TestTimeoutField,
+    _format_report_summary,
     _format_test_rerun_command,
     _format_test_summary,
     build_runtime_package_dependencies,
@@ -58,6 +59,8 @@ from pants.engine.fs import (
     Snapshot,
     Workspace,
 )
+from pants.engine.internals.native_engine import AggregateTestResultsRequest
+from pants.engine.internals.native_test_results import TestResultsSummary
 from pants.engine.internals.session import RunId
 from pants.engine.platform import Platform
 from pants.engine.process import (
@@ -341,6 +344,17 @@ def run_test_rule(
         )
         return CoverageReports(reports=(console_report,))
 
+    def mock_aggregate_test_results(_: AggregateTestResultsRequest) -> TestResultsSummary:
+        return TestResultsSummary(
+            [("tests", "test_ok", "passed", 0.5, None, 1, False)],
+            passed=1,
+            failed=0,
+            errored=0,
+            skipped=0,
+            flaky=0,
+            duration_secs=0.5,
+        )
+
     with mock_console(rule_runner.options_bootstrapper) as (console, stdio_reader):
         result: Test = run_rule_with_mocks(
             run_tests,
@@ -391,6 +405,11 @@ def run_test_rule(
                     input_types=(MergeDigests,),
                     mock=lambda _: EMPTY_DIGEST,
                 ),
+                MockGet(
+                    output_type=TestResultsSummary,
+                    input_types=(AggregateTestResultsRequest,),
+                    mock=mock_aggregate_test_results,
+                ),
                 MockGet(
                     output_type=CoverageReports,
                     input_types=(CoverageDataCollection, EnvironmentName),
@@ -621,6 +640,29 @@ def test_report(rule_runner: PythonRuleRunner) -> None:
     )
     assert exit_code == 0
     assert "Wrote test reports to dist/test/reports" in stderr
+    assert "Ran 1 test cases in 0.50s: 1 passed." in stderr
+
+
+def test_format_report_summary() -> None:
+    summary = TestResultsSummary(
+        [
+            ("tests", "test_ok", "passed", 0.5, None, 1, False),
+            ("tests", "test_flaky", "passed", 1.0, "assert 1 == 2", 2, True),
+            ("tests", "test_fails", "failed", 0.25, "assert 1 == 2", 1, False),
+        ],
+        passed=2,
+        failed=1,
+        errored=0,
+        skipped=0,
+        flaky=1,
+        duration_secs=1.75,
+    )
+    assert summary.cases[1].flaky
+    assert not summary.succeeded
+    assert (
+        _format_report_summary(summary)
+        == "Ran 3 test cases in 1.75s: 2 passed, 1 failed, 1 flaky."
+    )
 
 
 def test_report_dir(rule_runner: PythonRuleRunner) -> None:
@@ -59,7 +59,7 @@ from pants.core.util_rules.environments import (
 )
 from pants.core.util_rules.wrap_source import wrap_source_rule_and_target
 from pants.engine.internals.parametrize import Parametrize
-from pants.goal import anonymous_telemetry, stats_aggregator
+from pants.goal import anonymous_telemetry, build_event_protocol, provenance, stats_aggregator
 from pants.source import source_root
 from pants.vcs import git
 from pants.version import PANTS_SEMVER
@@ -91,10 +91,12 @@ def rules():
         *adhoc_binaries.rules(),
         *anonymous_telemetry.rules(),
         *archive.rules(),
+        *build_event_protocol.rules(),
         *config_files.rules(),
         *environments.rules(),
         *external_tool.rules(),
         *git.rules(),
+        *provenance.rules(),
         *source_files.rules(),
         *source_root.rules(),
         *stats_aggregator.rules(),
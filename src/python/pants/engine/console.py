@@ -2,13 +2,17 @@
 # Licensed under the Apache License, Version 2.0 (see LICENSE).
 from __future__ import annotations
 
+import json
+import os
 import sys
 from typing import Callable, TextIO
 
 from colors import blue, cyan, green, magenta, red, yellow
 
 from pants.engine.engine_aware import SideEffecting
+from pants.engine.internals import native_engine
 from pants.engine.internals.scheduler import SchedulerSession
+from pants.engine.markup import Markup, MarkupStyle, markup_to_json, render_markup
 
 
 class Console(SideEffecting):
@@ -25,11 +29,13 @@ class Console(SideEffecting):
         stderr: TextIO | None = None,
         use_colors: bool = True,
         session: SchedulerSession | None = None,
+        markup_style: MarkupStyle | None = None,
     ):
         self._stdin = stdin or sys.stdin
         self._stdout = stdout or sys.stdout
         self._stderr = stderr or sys.stderr
         self._use_colors = use_colors
+        self._markup_style = markup_style
         self._session = session
         self._enforce_effects = self._session is not None
 
@@ -72,6 +78,20 @@ class Console(SideEffecting):
     def print_stderr(self, payload: str, end: str = "\n") -> None:
         self.write_stderr(f"{payload}{end}")
 
+    def print_markup(self, *elements: Markup) -> None:
+        """Render the given semantic elements to stdout, in the style of this Console.
+
+        The elements are also sent to the client as a structured `markup` event, if it requested
+        events.
+        """
+        self.write_stdout(render_markup(elements, self.markup_style))
+        native_engine.stdio_thread_console_event_write(
+            json.dumps(
+                {"type": "markup", "elements": [markup_to_json(e) for e in elements]},
+                sort_keys=True,
+            )
+        )
+
     def flush(self) -> None:
         self._stdout.flush()
         self._stderr.flush()
@@ -96,6 +116,19 @@ class Console(SideEffecting):
     def use_colors(self):
         return self._use_colors
 
+    @property
+    def markup_style(self) -> MarkupStyle:
+        """The style in which `print_markup` renders elements.
+
+        If a style was not set explicitly, it is detected from the process environment, and from
+        whether stdout is a TTY and colors are enabled.
+        """
+        if self._markup_style is None:
+            self._markup_style = MarkupStyle.detect(
+                os.environ, isatty=self._use_colors and self._stdout.isatty()
+            )
+        return self._markup_style
+
     def _safe_color(self, text: str, color: Callable[[str], str]) -> str:
         """We should only output color when the global flag --colors is enabled."""
         return color(text) if self._use_colors else text
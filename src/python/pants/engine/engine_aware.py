@@ -3,14 +3,14 @@
 
 from __future__ import annotations
 
-from abc import ABC
+from abc import ABC, abstractmethod
 from typing import TYPE_CHECKING, Any
 
 from pants.engine.internals import native_engine
 from pants.util.logging import LogLevel
 
 if TYPE_CHECKING:
-    from pants.engine.fs import FileDigest, Snapshot
+    from pants.engine.fs import Digest, FileDigest, Snapshot
 
 
 class EngineAwareParameter(ABC):
@@ -111,3 +111,29 @@ class SideEffecting(ABC):
         # become async instead, which would avoid the need for a thread/task-local.
         if self._enforce_effects:
             native_engine.task_side_effected()
+
+
+class PersistableValue(ABC):
+    """Marks a type as serializable, so that the results of `@rule(persist=True)` rules may be
+    persisted in the local cache and survive restarts of pantsd.
+
+    The results of a persisted rule are keyed by the persisted forms of its parameters (which must
+    also be `PersistableValue`s), and by the source code of the modules which define the rule and
+    the rules which it transitively depends on.
+    Because the rule will not re-run for the same parameters, it must be a pure function of them:
+    in particular, it must not read files or environment variables which are not captured by its
+    parameters.
+    """
+
+    @abstractmethod
+    def persisted_form(self) -> tuple[bytes, tuple[Digest, ...]]:
+        """Serializes this value to bytes, along with any Digests which it references.
+
+        The referenced Digests are persisted in the local Store alongside the value, and the value
+        is only restored if they are all still present.
+        """
+
+    @classmethod
+    @abstractmethod
+    def from_persisted_form(cls, data: bytes) -> PersistableValue:
+        """Deserializes a value which was serialized by `persisted_form`."""
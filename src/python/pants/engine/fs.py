@@ -42,6 +42,24 @@ class Paths:
     dirs: Tuple[str, ...]
 
 
+@dataclass(frozen=True)
+class PathDigests:
+    """A collection of sorted file paths and dir paths, along with the digest of each file.
+
+    PathDigests is like Paths, but the files are fingerprinted during the same walk of the
+    filesystem, reusing the engine's memoized file digests. Prefer it to requesting Paths and then
+    a Snapshot of the same files when the digests (or sizes) of the files are needed.
+    """
+
+    files: Tuple[str, ...]
+    # The digest of each entry in `files`, in the same order.
+    file_digests: Tuple[FileDigest, ...]
+    dirs: Tuple[str, ...]
+
+    def digests_by_path(self) -> dict[str, FileDigest]:
+        return dict(zip(self.files, self.file_digests))
+
+
 @dataclass(frozen=True)
 class FileContent:
     """The content of a file.
@@ -153,12 +171,33 @@ class GlobExpansionConjunction(Enum):
     all_match = "all_match"
 
 
+class MergeConflictPolicy(Enum):
+    """How `MergeDigests` resolves a path which is present in more than one of its digests.
+
+    Directories are always merged recursively, so the policy applies to the files and symlinks
+    within them.
+
+    NB: this object is interpreted from within the native `MergeDigests` constructor -- that method
+    will need to be aware of any changes to this object's definition.
+    """
+
+    # Fail if a file is present more than once, even with identical content.
+    error = "error"
+    # Fail if a file is present more than once with differing content.
+    error_unless_identical = "error_unless_identical"
+    # Use the file from the earliest digest which contains the path.
+    prefer_left = "prefer_left"
+    # Use the file from the latest digest which contains the path.
+    prefer_right = "prefer_right"
+
+
 @dataclass(frozen=True)
 class PathGlobs:
     globs: Tuple[str, ...]
     glob_match_error_behavior: GlobMatchErrorBehavior
     conjunction: GlobExpansionConjunction
     description_of_origin: str | None
+    preserve_empty_dirs: bool
 
     def __init__(
         self,
@@ -166,6 +205,7 @@ class PathGlobs:
         glob_match_error_behavior: GlobMatchErrorBehavior = GlobMatchErrorBehavior.ignore,
         conjunction: GlobExpansionConjunction = GlobExpansionConjunction.any_match,
         description_of_origin: str | None = None,
+        preserve_empty_dirs: bool = False,
     ) -> None:
         """A request to find files given a set of globs.
 
@@ -179,6 +219,10 @@ class PathGlobs:
         :param description_of_origin: a human-friendly description of where this PathGlobs request
             is coming from, used to improve the error message for unmatched globs. For example,
             this might be the text string "the option `--isort-config`".
+        :param preserve_empty_dirs: whether to match empty directories which match a directory
+            component of a glob, even though they contain nothing which matches the rest of the
+            glob. For example, with `src/**/*.java`, an empty `src/main/resources` directory will
+            be matched.
         """
 
         # NB: this object is interpreted from within Snapshot::lift_path_globs() -- that method
@@ -187,6 +231,7 @@ class PathGlobs:
         object.__setattr__(self, "glob_match_error_behavior", glob_match_error_behavior)
         object.__setattr__(self, "conjunction", conjunction)
         object.__setattr__(self, "description_of_origin", description_of_origin)
+        object.__setattr__(self, "preserve_empty_dirs", preserve_empty_dirs)
         self.__post_init__()
 
     def __post_init__(self) -> None:
@@ -241,6 +286,120 @@ class DigestSubset:
     globs: PathGlobs
 
 
+@dataclass(frozen=True)
+class Relocation:
+    """Moves each path in a digest which matches the `src` glob to the path rendered from `dest`.
+
+    The `dest` template may use the placeholders `{path}`, `{dir}` and `{name}`, which are computed
+    for each matched path relative to the literal (wildcard-free) parent directories of `src`. A
+    `dest` without placeholders is treated as a directory, and is equivalent to `{dest}/{path}`.
+
+    For example, `Relocation("src/py/**", "/")` moves the content of `src/py` to the root, while
+    `Relocation("src/py/**/*.py", "{name}")` flattens the Python files beneath it into the root.
+    """
+
+    src: str
+    dest: str
+
+
+@dataclass(frozen=True)
+class RelocateDigest:
+    """A request to move paths within a digest.
+
+    Each file, symlink, and empty directory in the digest is moved by the first `Relocation` which
+    matches it, and is otherwise left in place. All of the relocations are applied in a single pass,
+    which is cheaper than chaining `DigestSubset`, `RemovePrefix`, `AddPrefix` and `MergeDigests`.
+    It is an error for two paths to be relocated to the same destination.
+
+    Example:
+
+        result = await Get(
+            Digest, RelocateDigest(original_digest, [Relocation("src/py/**", "/")])
+        )
+    """
+
+    digest: Digest
+    relocations: Tuple[Relocation, ...]
+
+    def __init__(self, digest: Digest, relocations: Iterable[Relocation]) -> None:
+        # NB: this object is interpreted from within relocate_digest_to_digest() -- that method
+        # will need to be aware of any changes to this object's definition.
+        object.__setattr__(self, "digest", digest)
+        object.__setattr__(self, "relocations", tuple(relocations))
+
+
+class LineEnding(Enum):
+    """The line ending that `NormalizeLineEndings` rewrites each line ending to."""
+
+    lf = "lf"
+    crlf = "crlf"
+
+
+@dataclass(frozen=True)
+class NormalizeLineEndings:
+    """Rewrites each line ending (either `\\r\\n` or `\\n`) in a file to the given line ending."""
+
+    line_ending: LineEnding = LineEnding.lf
+
+
+@dataclass(frozen=True)
+class SubstitutePlaceholders:
+    """Replaces each occurrence of a literal placeholder in a file with its value.
+
+    The placeholders are replaced in a single pass, so values are never themselves searched for
+    placeholders, and the longest placeholder wins when more than one begins at the same position.
+    """
+
+    placeholders: FrozenDict[str, str]
+
+    def __init__(self, placeholders: Mapping[str, str]) -> None:
+        object.__setattr__(self, "placeholders", FrozenDict(placeholders))
+
+
+@dataclass(frozen=True)
+class SetExecutable:
+    """Sets whether a file is executable."""
+
+    is_executable: bool = True
+
+
+FileTransform = Union[NormalizeLineEndings, SubstitutePlaceholders, SetExecutable]
+
+
+@dataclass(frozen=True)
+class TransformDigest:
+    """A request to apply deterministic transforms to the files in a digest which match `globs`.
+
+    The transforms are applied in order, natively and in parallel, which is much cheaper than
+    running a `Process` to (for example) stamp a version string into a file. Globs prefixed with
+    `!` exclude files. Symlinks are not followed.
+
+    Example:
+
+        result = await Get(
+            Digest,
+            TransformDigest(
+                original_digest,
+                ["src/**/version.py"],
+                [SubstitutePlaceholders({"@VERSION@": "1.2.3"})],
+            ),
+        )
+    """
+
+    digest: Digest
+    globs: Tuple[str, ...]
+    transforms: Tuple[FileTransform, ...]
+
+    def __init__(
+        self, digest: Digest, globs: Iterable[str], transforms: Iterable[FileTransform]
+    ) -> None:
+        # NB: this object is interpreted from within transform_digest_to_digest() -- that method
+        # will need to be aware of any changes to this object's definition.
+        object.__setattr__(self, "digest", digest)
+        object.__setattr__(self, "globs", tuple(globs))
+        object.__setattr__(self, "transforms", tuple(transforms))
+
+
 @dataclass(frozen=True)
 class DownloadFile:
     """Retrieve the contents of a file via an HTTP GET request or directly for local file:// URLs.
@@ -281,6 +440,32 @@ class NativeDownloadFile:
         object.__setattr__(self, "auth_headers", FrozenDict(auth_headers or {}))
 
 
+class ExportMode(Enum):
+    """How `Workspace.export_digest` materializes the files of a digest.
+
+    NB: this object is interpreted from within the native `export_digest` function -- that function
+    will need to be aware of any changes to this object's definition.
+    """
+
+    # Copy all files, which will be writable.
+    copy = "copy"
+    # Symlink large files into the local store (where they will be read-only), and copy others.
+    symlink = "symlink"
+    # Hardlink (or on macOS, clone) large files from the local store if it is on the same
+    # filesystem as the destination, and copy others.
+    hardlink = "hardlink"
+
+
+@dataclass(frozen=True)
+class ExportSummary:
+    """The number of files which were written, or left unchanged, by `Workspace.export_digest`,
+    and the number of stale entries which were removed."""
+
+    written: int
+    unchanged: int
+    removed: int
+
+
 @dataclass(frozen=True)
 class Workspace(SideEffecting):
     """A handle for operations that mutate the local filesystem."""
@@ -308,6 +493,22 @@ class Workspace(SideEffecting):
             self.side_effected()
         self._scheduler.write_digest(digest, path_prefix=path_prefix, clear_paths=clear_paths)
 
+    def export_digest(
+        self, digest: Digest, *, path_prefix: str, mode: ExportMode = ExportMode.copy
+    ) -> ExportSummary:
+        """Export a digest to a directory relative to the build root, for consumption by users.
+
+        Unlike `write_digest`, the directory is owned by the export: a manifest of the exported
+        entries is recorded in it, so that re-exporting only writes the entries which have changed,
+        and removes entries which are no longer present. Other files in the directory are left
+        alone.
+        """
+        self.side_effected()
+        written, unchanged, removed = self._scheduler.export_digest(
+            digest, path_prefix=path_prefix, mode=mode.value
+        )
+        return ExportSummary(written=written, unchanged=unchanged, removed=removed)
+
 
 @dataclass(frozen=True)
 class SpecsPaths(Paths):
@@ -361,6 +562,8 @@ def rules():
         QueryRule(Digest, (NativeDownloadFile,)),
         QueryRule(Digest, (MergeDigests,)),
         QueryRule(Digest, (DigestSubset,)),
+        QueryRule(Digest, (RelocateDigest,)),
+        QueryRule(Digest, (TransformDigest,)),
         QueryRule(DigestContents, (Digest,)),
         QueryRule(Snapshot, (Digest,)),
         QueryRule(Paths, (PathGlobs,)),
@@ -11,6 +11,8 @@ import socket
 import ssl
 import tarfile
 import time
+import urllib.error
+import urllib.request
 from dataclasses import dataclass
 from http.server import BaseHTTPRequestHandler
 from io import BytesIO
@@ -19,6 +21,7 @@ from typing import Any, Callable, Dict, Iterable, Optional, Set, Union
 
 import pytest
 
+from pants.base.exceptions import UserError
 from pants.engine.console import Console
 from pants.engine.fs import (
     EMPTY_DIGEST,
@@ -35,25 +38,37 @@ from pants.engine.fs import (
     FileDigest,
     FileEntry,
     GlobMatchErrorBehavior,
+    LineEnding,
+    MergeConflictPolicy,
     MergeDigests,
+    NormalizeLineEndings,
+    PathDigests,
     PathGlobs,
     PathGlobsAndRoot,
     PathMetadataRequest,
     PathMetadataResult,
+    Paths,
+    RelocateDigest,
+    Relocation,
     RemovePrefix,
+    SetExecutable,
     Snapshot,
     SnapshotDiff,
+    SubstitutePlaceholders,
     SymlinkEntry,
+    TransformDigest,
     Workspace,
 )
 from pants.engine.goal import Goal, GoalSubsystem
 from pants.engine.internals.native_engine import PathMetadata, PathMetadataKind
-from pants.engine.internals.scheduler import ExecutionError
+from pants.engine.internals.nodes import Return
+from pants.engine.internals.scheduler import ExecutionError, ExecutionTimeoutError
 from pants.engine.rules import Get, goal_rule, rule
 from pants.testutil.rule_runner import QueryRule, RuleRunner
 from pants.util.collections import assert_single_element
 from pants.util.contextutil import http_server, temporary_dir
 from pants.util.dirutil import relative_symlink, safe_file_dump
+from pants.util.logging import LogLevel
 
 
 @pytest.fixture
@@ -61,12 +76,15 @@ def rule_runner() -> RuleRunner:
     return RuleRunner(
         rules=[
             QueryRule(Digest, [CreateDigest]),
+            QueryRule(Digest, [TransformDigest]),
             QueryRule(DigestContents, [PathGlobs]),
             QueryRule(DigestEntries, [Digest]),
             QueryRule(DigestEntries, [PathGlobs]),
             QueryRule(Snapshot, [CreateDigest]),
             QueryRule(Snapshot, [DigestSubset]),
+            QueryRule(Snapshot, [RelocateDigest]),
             QueryRule(Snapshot, [PathGlobs]),
+            QueryRule(PathDigests, [PathGlobs]),
             QueryRule(PathMetadataResult, [PathMetadataRequest]),
         ],
         isolated_local_store=True,
@@ -399,6 +417,30 @@ def test_path_globs_symlink_loop(rule_runner: RuleRunner) -> None:
         assert_path_globs(rule_runner, ["**"], expected_files=[], expected_dirs=[])
 
 
+def test_path_globs_preserve_empty_dirs(rule_runner: RuleRunner) -> None:
+    rule_runner.write_files({"src/main/java/A.java": ""})
+    Path(rule_runner.build_root, "src/main/resources").mkdir()
+    java_dirs = ("src", "src/main", "src/main/java")
+
+    def globs(preserve_empty_dirs: bool) -> PathGlobs:
+        return PathGlobs(["src/**/*.java"], preserve_empty_dirs=preserve_empty_dirs)
+
+    assert rule_runner.request(Snapshot, [globs(False)]).dirs == java_dirs
+    snapshot = rule_runner.request(Snapshot, [globs(True)])
+    assert snapshot.dirs == (*java_dirs, "src/main/resources")
+
+    # The empty directory survives merges, subsets and materialization.
+    other = rule_runner.request(Digest, [CreateDigest([FileContent("other.txt", b"")])])
+    merged = rule_runner.request(Digest, [MergeDigests((snapshot.digest, other))])
+    assert Directory("src/main/resources") in rule_runner.request(DigestEntries, [merged])
+    subset = rule_runner.request(Snapshot, [DigestSubset(merged, globs(True))])
+    assert subset.digest == snapshot.digest
+    assert rule_runner.request(Snapshot, [DigestSubset(merged, globs(False))]).dirs == java_dirs
+
+    rule_runner.scheduler.write_digest(merged, path_prefix="out/")
+    assert Path(rule_runner.build_root, "out/src/main/resources").is_dir()
+
+
 def test_path_globs_to_digest_contents(rule_runner: RuleRunner) -> None:
     setup_fs_test_tar(rule_runner)
 
@@ -444,6 +486,20 @@ def test_path_globs_to_digest_entries(rule_runner: RuleRunner) -> None:
     assert get_entries(["c.ln"]) == {Directory("c.ln")}
 
 
+def test_path_globs_to_path_digests(rule_runner: RuleRunner) -> None:
+    setup_fs_test_tar(rule_runner)
+
+    path_digests = rule_runner.request(PathDigests, [PathGlobs(["4.txt", "a/4.txt.ln", "a/b"])])
+    assert path_digests.files == ("4.txt", "a/4.txt.ln")
+    assert path_digests.dirs == ("a/b",)
+    four = FileDigest("ab929fcd5594037960792ea0b98caf5fdaf6b60645e4ef248c28db74260f393e", 5)
+    assert path_digests.digests_by_path() == {"4.txt": four, "a/4.txt.ln": four}
+
+    # The digests match those of a Snapshot of the same files.
+    entries = rule_runner.request(DigestEntries, [PathGlobs(["4.txt"])])
+    assert entries == DigestEntries([FileEntry("4.txt", four)])
+
+
 def test_digest_entries_handles_empty_directory(rule_runner: RuleRunner) -> None:
     digest = rule_runner.request(
         Digest, [CreateDigest([Directory("a/b"), FileContent("a/foo.txt", b"four\n")])]
@@ -690,6 +746,15 @@ def test_create_digest_with_file_entries(rule_runner: RuleRunner) -> None:
     assert snapshot.digest != EMPTY_DIGEST
 
 
+def test_create_digest_invalid_path(rule_runner: RuleRunner) -> None:
+    with pytest.raises(Exception) as exc:
+        rule_runner.request(
+            Digest, [CreateDigest([FileContent("ok.txt", b""), FileContent("../escape.txt", b"")])]
+        )
+    assert "Failed to convert item 1 of CreateDigest" in str(exc.value)
+    assert "Relative paths that escape the root are not allowed" in str(exc.value)
+
+
 # -----------------------------------------------------------------------------------------------
 # `MergeDigests`
 # -----------------------------------------------------------------------------------------------
@@ -727,6 +792,36 @@ def test_merge_digests(rule_runner: RuleRunner) -> None:
     assert both_snapshot.digest == both_merged
 
 
+def test_merge_digests_conflict_policy(rule_runner: RuleRunner) -> None:
+    defaults, overrides = (
+        rule_runner.request(
+            Digest,
+            [CreateDigest([FileContent("config.toml", content), FileContent(f"{name}.txt", b"")])],
+        )
+        for name, content in (("defaults", b"a = 1"), ("overrides", b"a = 2"))
+    )
+
+    def merged_config(conflict_policy: MergeConflictPolicy) -> bytes:
+        digest = rule_runner.request(Digest, [MergeDigests((defaults, overrides), conflict_policy)])
+        contents = rule_runner.request(DigestContents, [digest])
+        assert {fc.path for fc in contents} == {"config.toml", "defaults.txt", "overrides.txt"}
+        return next(fc.content for fc in contents if fc.path == "config.toml")
+
+    assert merged_config(MergeConflictPolicy.prefer_left) == b"a = 1"
+    assert merged_config(MergeConflictPolicy.prefer_right) == b"a = 2"
+
+    for conflict_policy in (MergeConflictPolicy.error, MergeConflictPolicy.error_unless_identical):
+        with pytest.raises(ExecutionError, match="config.toml"):
+            merged_config(conflict_policy)
+
+    # Identical files are only a conflict for `MergeConflictPolicy.error`.
+    rule_runner.request(Digest, [MergeDigests((defaults, defaults))])
+    with pytest.raises(ExecutionError, match="defaults.txt"):
+        rule_runner.request(
+            Digest, [MergeDigests((defaults, defaults), MergeConflictPolicy.error)]
+        )
+
+
 # -----------------------------------------------------------------------------------------------
 # `DigestSubset`
 # -----------------------------------------------------------------------------------------------
@@ -835,6 +930,88 @@ def test_digest_subset_nonexistent_filename_globs(rule_runner: RuleRunner) -> No
     #     )
 
 
+# -----------------------------------------------------------------------------------------------
+# `RelocateDigest`
+# -----------------------------------------------------------------------------------------------
+
+
+def test_relocate_digest(rule_runner: RuleRunner) -> None:
+    def relocate(*relocations: Relocation) -> tuple[str, ...]:
+        return rule_runner.request(
+            Snapshot, [RelocateDigest(generate_original_digest(rule_runner), relocations)]
+        ).files
+
+    assert relocate(Relocation("subdir2/**", "moved")) == (
+        "a.txt",
+        "b.txt",
+        "c.txt",
+        "moved/a.txt",
+        "moved/nested_subdir/x.txt",
+        "subdir/a.txt",
+        "subdir/b.txt",
+    )
+    assert relocate(
+        Relocation("subdir2/**/*.txt", "flat/{name}"),
+        Relocation("*.txt", "root/{path}"),
+    ) == (
+        "flat/a.txt",
+        "flat/x.txt",
+        "root/a.txt",
+        "root/b.txt",
+        "root/c.txt",
+        "subdir/a.txt",
+        "subdir/b.txt",
+    )
+
+    with pytest.raises(ExecutionError, match="Cannot relocate both `a.txt` and `subdir/a.txt`"):
+        relocate(Relocation("**/a.txt", "{name}"))
+
+
+# -----------------------------------------------------------------------------------------------
+# `TransformDigest`
+# -----------------------------------------------------------------------------------------------
+
+
+def test_transform_digest(rule_runner: RuleRunner) -> None:
+    digest = rule_runner.request(
+        Digest,
+        [
+            CreateDigest(
+                [
+                    FileContent("version.py", b"VERSION = '@VERSION@'\r\n"),
+                    FileContent("run.sh", b"echo @VERSION@\r\n"),
+                    FileContent("README.md", b"@VERSION@\r\n"),
+                ]
+            )
+        ],
+    )
+    transformed = rule_runner.request(
+        Digest,
+        [
+            TransformDigest(
+                digest,
+                ["*", "!README.md"],
+                [
+                    NormalizeLineEndings(LineEnding.lf),
+                    SubstitutePlaceholders({"@VERSION@": "1.2.3"}),
+                ],
+            )
+        ],
+    )
+    transformed = rule_runner.request(
+        Digest, [TransformDigest(transformed, ["*.sh"], [SetExecutable()])]
+    )
+    assert set(rule_runner.request(DigestContents, [transformed])) == {
+        FileContent("README.md", b"@VERSION@\r\n"),
+        FileContent("run.sh", b"echo 1.2.3\n", is_executable=True),
+        FileContent("version.py", b"VERSION = '1.2.3'\n"),
+    }
+
+    # If no files match, the digest is unchanged.
+    unchanged = rule_runner.request(Digest, [TransformDigest(digest, ["*.txt"], [SetExecutable()])])
+    assert unchanged == digest
+
+
 # -----------------------------------------------------------------------------------------------
 # `Digest` -> `Snapshot`
 # -----------------------------------------------------------------------------------------------
@@ -889,8 +1066,12 @@ def test_add_prefix(rule_runner: RuleRunner) -> None:
     assert digest == output_digest
 
     # Illegal.
-    with pytest.raises(Exception, match=r"The `prefix` must be relative."):
+    with pytest.raises(ExecutionError, match=r"The `prefix` must be relative.") as exc:
         rule_runner.request(Digest, [AddPrefix(digest, "../something")])
+    error = assert_single_element(exc.value.wrapped_exceptions)
+    assert isinstance(error, UserError)
+    assert error.code == "invalid_digest_prefix"
+    assert error.category == "user"
 
 
 def test_remove_prefix(rule_runner: RuleRunner) -> None:
@@ -1174,6 +1355,14 @@ def test_write_digest_scheduler(rule_runner: RuleRunner) -> None:
     assert path.read_text() == "European Burmese"
 
 
+def test_store_bundle_roundtrip(rule_runner: RuleRunner, tmp_path: Path) -> None:
+    prime_store_with_roland_digest(rule_runner)
+
+    bundle = str(tmp_path / "bundle.gz")
+    rule_runner.scheduler.export_store_bundle([ROLAND_DIGEST], bundle)
+    assert rule_runner.scheduler.import_store_bundle(bundle) == [ROLAND_DIGEST]
+
+
 def test_write_digest_workspace(rule_runner: RuleRunner) -> None:
     workspace = Workspace(rule_runner.scheduler, _enforce_effects=False)
     digest = rule_runner.request(
@@ -1297,6 +1486,34 @@ def test_workspace_in_goal_rule() -> None:
     assert Path(rule_runner.build_root, "a.txt").read_text() == "hello"
 
 
+def test_serve_digest(rule_runner: RuleRunner) -> None:
+    digest = rule_runner.request(
+        Digest,
+        [
+            CreateDigest(
+                [
+                    FileContent("index.html", b"<html></html>"),
+                    FileContent("docs/a.txt", b"hello world"),
+                ]
+            )
+        ],
+    )
+    url = rule_runner.scheduler.serve_digest(digest)
+
+    with urllib.request.urlopen(url) as response:
+        assert response.read() == b"<html></html>"
+        assert response.headers["Content-Type"] == "text/html; charset=utf-8"
+
+    request = urllib.request.Request(f"{url}docs/a.txt", headers={"Range": "bytes=6-"})
+    with urllib.request.urlopen(request) as response:
+        assert response.status == 206
+        assert response.read() == b"world"
+
+    with pytest.raises(urllib.error.HTTPError) as e:
+        urllib.request.urlopen(f"{url}docs/missing.txt")
+    assert e.value.code == 404
+
+
 # -----------------------------------------------------------------------------------------------
 # Invalidation of the FS
 # -----------------------------------------------------------------------------------------------
@@ -1319,6 +1536,31 @@ def test_invalidated_after_rewrite(rule_runner: RuleRunner) -> None:
     assert try_with_backoff(lambda: read_file() == new_value)
 
 
+def test_subscription_after_rewrite(rule_runner: RuleRunner) -> None:
+    """Test that a subscription observes new values after files are updated."""
+    setup_fs_test_tar(rule_runner)
+    session = rule_runner.scheduler
+    globs = PathGlobs(["4.txt"])
+    subscription = session.subscribe([(DigestContents, globs)], debounce=0.01)
+
+    def next_content() -> str:
+        ((root, state),) = session.next_subscription_results(subscription, timeout=30)
+        assert root == (DigestContents, globs)
+        assert isinstance(state, Return)
+        return state.value[0].content.decode()
+
+    # The first results contain the initial value.
+    assert next_content() == "four\n"
+
+    # A zero timeout does not wait for a change.
+    with pytest.raises(ExecutionTimeoutError):
+        session.next_subscription_results(subscription, timeout=0)
+
+    # And the next results are produced once the file has changed.
+    Path(rule_runner.build_root, "4.txt").write_text("cuatro\n")
+    assert next_content() == "cuatro\n"
+
+
 def test_invalidated_after_parent_deletion(rule_runner: RuleRunner) -> None:
     """Test that FileContent is invalidated after deleting the parent directory."""
     setup_fs_test_tar(rule_runner)
@@ -1375,6 +1617,64 @@ def test_invalidated_after_new_child(rule_runner: RuleRunner) -> None:
     assert try_with_backoff(is_changed_snapshot)
 
 
+def test_paths_memoized_until_invalidated() -> None:
+    """Test that glob expansions are reused across runs until the watcher observes a change."""
+    rule_runner = RuleRunner(
+        rules=[QueryRule(Paths, [PathGlobs])], max_workunit_verbosity=LogLevel.TRACE
+    )
+    setup_fs_test_tar(rule_runner)
+
+    def expand_globs() -> tuple[tuple[str, ...], int]:
+        paths = rule_runner.request(Paths, [PathGlobs(["a/*"])])
+        completed = rule_runner.scheduler.poll_workunits(LogLevel.TRACE)["completed"]
+        return paths.files, sum(1 for workunit in completed if workunit["name"] == "paths")
+
+    # The first run expands the globs.
+    assert expand_globs() == (("a/3.txt", "a/4.txt.ln"), 1)
+
+    # A following run against an unchanged directory reuses the expansion.
+    rule_runner.new_session("second")
+    assert expand_globs() == (("a/3.txt", "a/4.txt.ln"), 0)
+
+    # But once the watcher observes a change, the globs are expanded again.
+    Path(rule_runner.build_root, "a/new_file.txt").write_text("new file")
+    assert try_with_backoff(
+        lambda: expand_globs() == (("a/3.txt", "a/4.txt.ln", "a/new_file.txt"), 1)
+    )
+
+
+def test_paths_reexpanded_after_edit() -> None:
+    """Test that edits beneath a memoized glob expansion are observed by consumers of it."""
+    rule_runner = RuleRunner(
+        rules=[QueryRule(Paths, [PathGlobs]), QueryRule(DigestContents, [PathGlobs])],
+        max_workunit_verbosity=LogLevel.TRACE,
+    )
+    setup_fs_test_tar(rule_runner)
+    globs = PathGlobs(["a/**/*.txt"])
+
+    def expand_globs() -> tuple[tuple[str, ...], int]:
+        paths = rule_runner.request(Paths, [globs])
+        completed = rule_runner.scheduler.poll_workunits(LogLevel.TRACE)["completed"]
+        return paths.files, sum(1 for workunit in completed if workunit["name"] == "paths")
+
+    def read_files() -> dict[str, str]:
+        digest_contents = rule_runner.request(DigestContents, [globs])
+        return {fc.path: fc.content.decode() for fc in digest_contents}
+
+    assert expand_globs() == (("a/3.txt", "a/b/1.txt"), 1)
+    assert read_files() == {"a/3.txt": "three\n", "a/b/1.txt": "one\n"}
+
+    # Renaming a matched file changes the expansion, and so the globs are expanded again.
+    Path(rule_runner.build_root, "a/3.txt").rename(Path(rule_runner.build_root, "a/b/3.txt"))
+    assert try_with_backoff(lambda: expand_globs() == (("a/b/1.txt", "a/b/3.txt"), 1))
+
+    # Editing the content of a matched file is observed by consumers of the expansion.
+    Path(rule_runner.build_root, "a/b/1.txt").write_text("uno\n")
+    assert try_with_backoff(
+        lambda: read_files() == {"a/b/1.txt": "uno\n", "a/b/3.txt": "three\n"}
+    )
+
+
 # -----------------------------------------------------------------------------------------------
 # Native types
 # -----------------------------------------------------------------------------------------------
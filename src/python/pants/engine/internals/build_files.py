@@ -34,6 +34,7 @@ from pants.engine.internals.dep_rules import (
     MaybeBuildFileDependencyRulesImplementation,
 )
 from pants.engine.internals.mapper import AddressFamily, AddressMap
+from pants.engine.internals.native_dep_inference import NativeParsedBuildFile
 from pants.engine.internals.parser import (
     BuildFilePreludeSymbols,
     BuildFileSymbolsInfo,
@@ -64,8 +65,13 @@ logger = logging.getLogger(__name__)
 class BuildFileSyntaxError(SyntaxError):
     """An error parsing a BUILD file."""
 
-    def from_syntax_error(error: SyntaxError) -> BuildFileSyntaxError:
-        return BuildFileSyntaxError(
+    # The lines of any further syntax errors, as located by the native BUILD file pre-parser.
+    additional_lines: tuple[int, ...] = ()
+
+    def from_syntax_error(
+        error: SyntaxError, additional_lines: Sequence[int] = ()
+    ) -> BuildFileSyntaxError:
+        result = BuildFileSyntaxError(
             error.msg,
             (
                 error.filename,
@@ -74,16 +80,21 @@ class BuildFileSyntaxError(SyntaxError):
                 error.text,
             ),
         )
+        result.additional_lines = tuple(additional_lines)
+        return result
 
     def __str__(self) -> str:
-        first_line = f"Error parsing BUILD file {self.filename}:{self.lineno}: {self.msg}"
+        message = f"Error parsing BUILD file {self.filename}:{self.lineno}: {self.msg}"
         # These two fields are optional per the spec, so we can't rely on them being set.
         if self.text is not None and self.offset is not None:
             second_line = f"  {self.text.rstrip()}"
             third_line = f"  {' ' * (self.offset - 1)}^"
-            return f"{first_line}\n{second_line}\n{third_line}"
+            message = f"{message}\n{second_line}\n{third_line}"
+        if self.additional_lines:
+            lines = ", ".join(str(line) for line in self.additional_lines)
+            message = f"{message}\n\nFurther syntax errors at lines: {lines}"
 
-        return first_line
+        return message
 
 
 @dataclass(frozen=True)
@@ -91,6 +102,7 @@ class BuildFileOptions:
     patterns: tuple[str, ...]
     ignores: tuple[str, ...] = ()
     prelude_globs: tuple[str, ...] = ()
+    native_preparse: bool = False
 
 
 @rule
@@ -104,6 +116,7 @@ def extract_build_file_options(
         prelude_globs=(
             () if bootstrap_status.in_progress else global_options.build_file_prelude_globs
         ),
+        native_preparse=global_options.build_file_native_preparse,
     )
 
 
@@ -225,6 +238,28 @@ async def ensure_address_family(request: OptionalAddressFamily) -> AddressFamily
     return request.ensure()
 
 
+def _preparse_build_file(file_content: FileContent) -> NativeParsedBuildFile:
+    """Pre-parse a BUILD file natively, raising its syntax errors with all of their locations."""
+    preparsed = NativeParsedBuildFile.parse(file_content.content.decode())
+    if preparsed.syntax_errors:
+        # The Python parser describes the first error best, but stops there. If it succeeds though,
+        # the file uses syntax which the pre-parser doesn't support, and is evaluated as usual.
+        try:
+            ast.parse(file_content.content, file_content.path)
+        except SyntaxError as e:
+            additional_lines = sorted(
+                {
+                    span.start_line
+                    for span in preparsed.syntax_errors
+                    if span.start_line > (e.lineno or 0)
+                }
+            )
+            raise BuildFileSyntaxError.from_syntax_error(e, additional_lines).with_traceback(
+                e.__traceback__
+            )
+    return preparsed
+
+
 class BUILDFileEnvVarExtractor(ast.NodeVisitor):
     def __init__(self, filename: str):
         super().__init__()
@@ -339,10 +374,21 @@ async def parse_address_family(
         dependencies_rules_parser_state = None
 
     def _extract_env_vars(
-        file_content: FileContent, extra_env: Sequence[str], env: CompleteEnvironmentVars
+        file_content: FileContent,
+        preparsed: NativeParsedBuildFile | None,
+        extra_env: Sequence[str],
+        env: CompleteEnvironmentVars,
     ) -> Get[EnvironmentVars]:
-        """For BUILD file env vars, we only ever consult the local systems env."""
-        env_vars = (*BUILDFileEnvVarExtractor.get_env_vars(file_content), *extra_env)
+        """For BUILD file env vars, we only ever consult the local systems env.
+
+        Static BUILD files can't reference any env vars, so they don't need to be inspected.
+        """
+        referenced_env_vars = (
+            ()
+            if preparsed is not None and preparsed.is_static
+            else BUILDFileEnvVarExtractor.get_env_vars(file_content)
+        )
+        env_vars = (*referenced_env_vars, *extra_env)
         return Get(
             EnvironmentVars,
             {
@@ -351,11 +397,18 @@ async def parse_address_family(
             },
         )
 
+    preparsed_build_files = [
+        _preparse_build_file(fc) if build_file_options.native_preparse else None
+        for fc in digest_contents
+    ]
     all_env_vars = await MultiGet(
         _extract_env_vars(
-            fc, prelude_symbols.referenced_env_vars, session_values[CompleteEnvironmentVars]
+            fc,
+            preparsed,
+            prelude_symbols.referenced_env_vars,
+            session_values[CompleteEnvironmentVars],
         )
-        for fc in digest_contents
+        for fc, preparsed in zip(digest_contents, preparsed_build_files)
     )
 
     address_maps = [
@@ -369,8 +422,9 @@ async def parse_address_family(
             defaults_parser_state,
             dependents_rules_parser_state,
             dependencies_rules_parser_state,
+            preparsed,
         )
-        for fc, env_vars in zip(digest_contents, all_env_vars)
+        for fc, env_vars, preparsed in zip(digest_contents, all_env_vars, preparsed_build_files)
     ]
 
     # Freeze defaults and dependency rules
@@ -22,6 +22,7 @@ from pants.engine.internals.build_files import (
     BuildFileOptions,
     BuildFileSyntaxError,
     OptionalAddressFamily,
+    _preparse_build_file,
     evaluate_preludes,
     parse_address_family,
 )
@@ -1131,3 +1132,16 @@ def test_build_file_syntax_error(filename, contents, expect_failure, expected_me
 
     else:
         BUILDFileEnvVarExtractor.get_env_vars(MockFileContent(filename, contents))
+
+
+def test_preparsed_build_file_syntax_errors() -> None:
+    with pytest.raises(BuildFileSyntaxError) as e:
+        _preparse_build_file(
+            FileContent("foo/BUILD", b"data()\nqwe asd\ndata()\ndata(name='x',, sources=[])\n")
+        )
+    assert str(e.value) == (
+        "Error parsing BUILD file foo/BUILD:2: invalid syntax\n  qwe asd\n      ^\n\n"
+        "Further syntax errors at lines: 4"
+    )
+
+    assert _preparse_build_file(FileContent("foo/BUILD", b"data(name='x')")).is_static
@@ -11,6 +11,7 @@ from typing import List, Optional, Tuple
 import pytest
 
 from pants.backend.python.target_types import PythonSourcesGeneratorTarget
+from pants.base.deprecated import warn_or_error
 from pants.base.exceptions import IntrinsicError
 from pants.base.specs import Specs
 from pants.base.specs_parser import SpecsParser
@@ -978,6 +979,27 @@ def test_streaming_workunits_expanded_specs(run_tracker: RunTracker) -> None:
         rule_runner.request(ProcessResult, [stdout_process])
 
 
+def test_report_interval_seconds_is_deprecated(
+    rule_runner: RuleRunner, run_tracker: RunTracker, caplog
+) -> None:
+    # The warning is memoized, and so will already have been logged by other tests in this module.
+    warn_or_error.clear()  # type: ignore[attr-defined]
+    StreamingWorkunitHandler(
+        rule_runner.scheduler,
+        run_tracker=run_tracker,
+        callbacks=[WorkunitTracker()],
+        report_interval_seconds=0.01,
+        max_workunit_verbosity=LogLevel.INFO,
+        specs=Specs.empty(),
+        options_bootstrapper=create_options_bootstrapper([]),
+        allow_async_completion=False,
+    )
+    assert (
+        "DEPRECATED: the `report_interval_seconds` argument to `StreamingWorkunitHandler` is "
+        "scheduled to be removed in version 2.25.0.dev0."
+    ) in caplog.text
+
+
 @union
 class Union:
     pass
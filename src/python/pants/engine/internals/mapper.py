@@ -18,6 +18,7 @@ from pants.engine.internals.dep_rules import (
     BuildFileDependencyRules,
     BuildFileDependencyRulesParserState,
 )
+from pants.engine.internals.native_dep_inference import NativeParsedBuildFile
 from pants.engine.internals.parser import BuildFilePreludeSymbols, Parser
 from pants.engine.internals.target_adaptor import TargetAdaptor
 from pants.engine.target import RegisteredTargetTypes, Tags, Target
@@ -51,6 +52,7 @@ class AddressMap:
         defaults: BuildFileDefaultsParserState,
         dependents_rules: BuildFileDependencyRulesParserState | None,
         dependencies_rules: BuildFileDependencyRulesParserState | None,
+        preparsed: NativeParsedBuildFile | None = None,
     ) -> AddressMap:
         """Parses a source for targets.
 
@@ -67,6 +69,7 @@ class AddressMap:
                 defaults,
                 dependents_rules,
                 dependencies_rules,
+                preparsed,
             )
         except Exception as e:
             raise MappingError(f"Failed to parse ./{filepath}:\n{type(e).__name__}: {e}")
@@ -4,25 +4,208 @@
 from __future__ import annotations
 
 from dataclasses import dataclass
+from typing import Any
 
+from pants.engine.internals import native_engine
 from pants.util.frozendict import FrozenDict
 
 
+@dataclass(frozen=True, order=True)
+class SourceSpan:
+    """The location of an import in a source file."""
+
+    start_byte: int
+    end_byte: int
+    # Lines are 1-based, as editors show them, and columns are 0-based byte offsets.
+    start_line: int
+    start_column: int
+    end_line: int
+    end_column: int
+
+
+def _source_spans(
+    import_spans: dict[str, tuple[int, int, int, int, int, int]]
+) -> FrozenDict[str, SourceSpan]:
+    return FrozenDict((name, SourceSpan(*span)) for name, span in import_spans.items())
+
+
 @dataclass(frozen=True)
 class NativeParsedPythonDependencies:
-    imports: FrozenDict[str, tuple[int, bool]]
+    # The line, weakness and confidence (`strong` or `weak`) of each import.
+    imports: FrozenDict[str, tuple[int, bool, str]]
     string_candidates: FrozenDict[str, int]
+    import_spans: FrozenDict[str, SourceSpan]
 
-    def __init__(self, imports: dict[str, tuple[int, bool]], string_candidates: dict[str, int]):
+    def __init__(
+        self,
+        imports: dict[str, tuple[int, bool, str]],
+        string_candidates: dict[str, int],
+        import_spans: dict[str, tuple[int, int, int, int, int, int]],
+    ):
         object.__setattr__(self, "imports", FrozenDict(imports))
         object.__setattr__(self, "string_candidates", FrozenDict(string_candidates))
+        object.__setattr__(self, "import_spans", _source_spans(import_spans))
 
 
 @dataclass(frozen=True)
 class NativeParsedJavascriptDependencies:
     file_imports: frozenset[str]
     package_imports: frozenset[str]
+    pattern_imports: frozenset[str]
+    # The location of the first import which resolved to each file, package or pattern import.
+    import_spans: FrozenDict[str, SourceSpan]
+
+    def __init__(
+        self,
+        file_imports: set[str],
+        package_imports: set[str],
+        pattern_imports: set[str],
+        import_spans: dict[str, tuple[int, int, int, int, int, int]],
+    ):
+        object.__setattr__(self, "file_imports", file_imports)
+        object.__setattr__(self, "package_imports", package_imports)
+        object.__setattr__(self, "pattern_imports", pattern_imports)
+        object.__setattr__(self, "import_spans", _source_spans(import_spans))
+
+
+@dataclass(frozen=True)
+class NativeParsedCssDependencies:
+    file_imports: frozenset[str]
+    package_imports: frozenset[str]
 
     def __init__(self, file_imports: set[str], package_imports: set[str]):
         object.__setattr__(self, "file_imports", file_imports)
         object.__setattr__(self, "package_imports", package_imports)
+
+
+@dataclass(frozen=True)
+class NativeParsedGoDependencies:
+    package_name: str
+    # Each imported path, along with its alias: `_` for blank imports and `.` for dot imports.
+    imports: tuple[tuple[str, str | None], ...]
+    build_constraint: str | None
+    matches_build_tags: bool
+
+    def __init__(
+        self,
+        package_name: str,
+        imports: list[tuple[str, str | None]],
+        build_constraint: str | None,
+        matches_build_tags: bool,
+    ):
+        object.__setattr__(self, "package_name", package_name)
+        object.__setattr__(self, "imports", tuple(imports))
+        object.__setattr__(self, "build_constraint", build_constraint)
+        object.__setattr__(self, "matches_build_tags", matches_build_tags)
+
+
+@dataclass(frozen=True)
+class NativeParsedJvmDependencies:
+    package: str | None
+    # The name, staticness, wildcardness and alias of each import.
+    imports: tuple[tuple[str, bool, bool, str | None], ...]
+    declared_symbols: tuple[str, ...]
+    consumed_symbols: frozenset[str]
+    export_types: frozenset[str]
+    annotation_references: frozenset[str]
+
+    def __init__(
+        self,
+        package: str | None,
+        imports: list[tuple[str, bool, bool, str | None]],
+        declared_symbols: list[str],
+        consumed_symbols: set[str],
+        export_types: set[str],
+        annotation_references: set[str],
+    ):
+        object.__setattr__(self, "package", package)
+        object.__setattr__(self, "imports", tuple(imports))
+        object.__setattr__(self, "declared_symbols", tuple(declared_symbols))
+        object.__setattr__(self, "consumed_symbols", frozenset(consumed_symbols))
+        object.__setattr__(self, "export_types", frozenset(export_types))
+        object.__setattr__(self, "annotation_references", frozenset(annotation_references))
+
+
+@dataclass(frozen=True)
+class NativeParsedDockerfileDependencies:
+    build_args: tuple[str, ...]
+    # The alias, image and resolved image of each stage.
+    stages: tuple[tuple[str | None, str, str | None], ...]
+    image_references: tuple[str, ...]
+    copy_source_paths: tuple[str, ...]
+    copy_build_args: tuple[str, ...]
+    from_image_build_args: tuple[str, ...]
+    version_tags: tuple[str, ...]
+
+    def __init__(
+        self,
+        build_args: list[str],
+        stages: list[tuple[str | None, str, str | None]],
+        image_references: list[str],
+        copy_source_paths: list[str],
+        copy_build_args: list[str],
+        from_image_build_args: list[str],
+        version_tags: list[str],
+    ):
+        object.__setattr__(self, "build_args", tuple(build_args))
+        object.__setattr__(self, "stages", tuple(stages))
+        object.__setattr__(self, "image_references", tuple(image_references))
+        object.__setattr__(self, "copy_source_paths", tuple(copy_source_paths))
+        object.__setattr__(self, "copy_build_args", tuple(copy_build_args))
+        object.__setattr__(self, "from_image_build_args", tuple(from_image_build_args))
+        object.__setattr__(self, "version_tags", tuple(version_tags))
+
+
+@dataclass(frozen=True)
+class NativeParsedShellDependencies:
+    file_imports: frozenset[str]
+    commands: frozenset[str]
+
+    def __init__(self, file_imports: set[str], commands: set[str]):
+        object.__setattr__(self, "file_imports", frozenset(file_imports))
+        object.__setattr__(self, "commands", frozenset(commands))
+
+
+@dataclass(frozen=True)
+class NativeParsedDependenciesBatch:
+    # The parsed dependencies of each file, by path: e.g. a `NativeParsedPythonDependencies` for
+    # each file of a batch of Python files.
+    results: FrozenDict[str, Any]
+
+    def __init__(self, results: dict[str, Any]):
+        object.__setattr__(self, "results", FrozenDict(results))
+
+
+@dataclass(frozen=True)
+class NativeBuildFileTarget:
+    type_alias: str
+    span: SourceSpan
+    # The fields with literal values, and the names of the fields which must be evaluated.
+    fields: FrozenDict[str, Any]
+    dynamic_fields: tuple[str, ...]
+
+
+@dataclass(frozen=True)
+class NativeParsedBuildFile:
+    """The targets of a BUILD file, as extracted without evaluating it.
+
+    If the file `is_static`, its targets were extracted entirely: otherwise it must be evaluated.
+    """
+
+    targets: tuple[NativeBuildFileTarget, ...]
+    syntax_errors: tuple[SourceSpan, ...]
+    is_static: bool
+
+    @classmethod
+    def parse(cls, content: str) -> NativeParsedBuildFile:
+        targets, syntax_errors, is_static = native_engine.parse_build_file(content)
+        return cls(
+            targets=tuple(
+                NativeBuildFileTarget(
+                    type_alias, SourceSpan(*span), FrozenDict(fields), tuple(dynamic_fields)
+                )
+                for type_alias, span, fields, dynamic_fields in targets
+            ),
+            syntax_errors=tuple(SourceSpan(*span) for span in syntax_errors),
+            is_static=is_static,
+        )
@@ -29,17 +29,29 @@ from pants.engine.fs import (
     DigestContents,
     DigestEntries,
     DigestSubset,
+    MergeConflictPolicy,
     NativeDownloadFile,
+    PathDigests,
     PathGlobs,
     PathMetadataRequest,
     PathMetadataResult,
     Paths,
+    RelocateDigest,
+    TransformDigest,
 )
 from pants.engine.internals.docker import DockerResolveImageRequest, DockerResolveImageResult
 from pants.engine.internals.native_dep_inference import (
+    NativeParsedCssDependencies,
+    NativeParsedDependenciesBatch,
+    NativeParsedDockerfileDependencies,
+    NativeParsedGoDependencies,
     NativeParsedJavascriptDependencies,
+    NativeParsedJvmDependencies,
     NativeParsedPythonDependencies,
+    NativeParsedShellDependencies,
 )
+from pants.engine.internals.native_specs import AddressFamilyDirs, AddressFamilyDirsRequest
+from pants.engine.internals.native_test_results import TestResultsSummary
 from pants.engine.internals.scheduler import Workunit, _PathGlobsAndRootCollection
 from pants.engine.internals.session import RunId, SessionValues
 from pants.engine.process import (
@@ -47,6 +59,8 @@ from pants.engine.process import (
     InteractiveProcess,
     InteractiveProcessResult,
     Process,
+    ShardedProcess,
+    ShardedProcessResult,
 )
 
 # TODO: black and flake8 disagree about the content of this file:
@@ -72,6 +86,14 @@ def address_spec_parse(
     spec: str,
 ) -> tuple[tuple[str, str | None, str | None, tuple[tuple[str, str], ...]], str | None]: ...
 
+# Like `address_spec_parse`, but also returns whether the spec is an ignore (i.e. is prefixed with
+# `-`), and validates the names and parameters of the spec.
+def command_line_spec_parse(
+    spec: str,
+) -> tuple[
+    bool, tuple[tuple[str, str | None, str | None, tuple[tuple[str, str], ...]], str | None]
+]: ...
+
 class AddressParseException(Exception):
     pass
 
@@ -271,7 +293,13 @@ class Address:
 # ------------------------------------------------------------------------------
 
 class PyExecutor:
-    def __init__(self, core_threads: int, max_threads: int) -> None: ...
+    def __init__(
+        self,
+        core_threads: int,
+        max_threads: int,
+        io_threads: int | None = None,
+        fs_blocking_threads: int | None = None,
+    ) -> None: ...
     def to_borrowed(self) -> PyExecutor: ...
     def shutdown(self, duration_secs: float) -> None: ...
 
@@ -440,15 +468,24 @@ class Snapshot:
 class MergeDigests:
     """A request to merge several digests into one single digest.
 
-    This will fail if there are any conflicting changes, such as two digests having the same
-    file but with different content.
+    By default, this will fail if there are any conflicting changes, such as two digests having the
+    same file but with different content. Pass a `conflict_policy` to instead prefer the file from
+    the earliest or latest digest (e.g. to layer configuration files), or to fail for any duplicate
+    file.
 
     Example:
 
         result = await Get(Digest, MergeDigests([digest1, digest2])
+        layered = await Get(
+            Digest, MergeDigests([defaults, overrides], MergeConflictPolicy.prefer_right)
+        )
     """
 
-    def __init__(self, digests: Iterable[Digest]) -> None: ...
+    def __init__(
+        self,
+        digests: Iterable[Digest],
+        conflict_policy: MergeConflictPolicy = MergeConflictPolicy.error_unless_identical,
+    ) -> None: ...
     def __eq__(self, other: MergeDigests | Any) -> bool: ...
     def __hash__(self) -> int: ...
     def __repr__(self) -> str: ...
@@ -533,6 +570,9 @@ async def path_globs_to_digest(
 async def path_globs_to_paths(
     path_globs: PathGlobs,
 ) -> Paths: ...
+async def path_globs_to_path_digests(
+    path_globs: PathGlobs,
+) -> PathDigests: ...
 async def download_file_to_digest(
     native_download_file: NativeDownloadFile,
 ) -> Digest: ...
@@ -545,7 +585,12 @@ async def add_prefix_request_to_digest(add_prefix: AddPrefix) -> Digest: ...
 async def process_request_to_process_result(
     process: Process, process_execution_environment: ProcessExecutionEnvironment
 ) -> FallibleProcessResult: ...
+async def sharded_process_to_sharded_process_result(
+    sharded_process: ShardedProcess, process_execution_environment: ProcessExecutionEnvironment
+) -> ShardedProcessResult: ...
 async def digest_subset_to_digest(digest_subset: DigestSubset) -> Digest: ...
+async def relocate_digest_to_digest(relocate_digest: RelocateDigest) -> Digest: ...
+async def transform_digest_to_digest(transform_digest: TransformDigest) -> Digest: ...
 async def session_values() -> SessionValues: ...
 async def run_id() -> RunId: ...
 async def interactive_process(
@@ -558,12 +603,34 @@ async def parse_python_deps(
 async def parse_javascript_deps(
     deps_request: NativeDependenciesRequest,
 ) -> NativeParsedJavascriptDependencies: ...
+async def parse_css_deps(
+    deps_request: NativeDependenciesRequest,
+) -> NativeParsedCssDependencies: ...
+async def parse_go_deps(
+    deps_request: NativeDependenciesRequest,
+) -> NativeParsedGoDependencies: ...
+async def parse_jvm_deps(
+    deps_request: NativeDependenciesRequest,
+) -> NativeParsedJvmDependencies: ...
+async def parse_dockerfile_deps(
+    deps_request: NativeDependenciesRequest,
+) -> NativeParsedDockerfileDependencies: ...
+async def parse_shell_deps(
+    deps_request: NativeDependenciesRequest,
+) -> NativeParsedShellDependencies: ...
+async def parse_deps_batch(
+    deps_request: NativeDependenciesBatchRequest,
+) -> NativeParsedDependenciesBatch: ...
+async def address_family_dirs(request: AddressFamilyDirsRequest) -> AddressFamilyDirs: ...
+async def aggregate_test_results(request: AggregateTestResultsRequest) -> TestResultsSummary: ...
 async def path_metadata_request(request: PathMetadataRequest) -> PathMetadataResult: ...
 
 # ------------------------------------------------------------------------------
 # `pantsd`
 # ------------------------------------------------------------------------------
 
+PANTSD_CLIENT_IDEMPOTENT_GOALS: tuple[str, ...]
+
 def pantsd_fingerprint_compute(expected_option_names: set[str]) -> str: ...
 
 # ------------------------------------------------------------------------------
@@ -623,6 +690,56 @@ class PantsdConnectionException(Exception):
 class PantsdClientException(Exception):
     pass
 
+# ------------------------------------------------------------------------------
+# Build Event Protocol
+# ------------------------------------------------------------------------------
+
+class PyBuildEventPublisher:
+    def __init__(
+        self,
+        py_executor: PyExecutor,
+        address: str,
+        headers: dict[str, str],
+        build_id: str,
+        invocation_id: str,
+        project_id: str,
+        keywords: list[str],
+        root_ca_certs_path: str | None,
+    ) -> None: ...
+    def started(
+        self,
+        uuid: str,
+        command: str,
+        build_tool_version: str,
+        workspace_directory: str,
+        start_time_secs: float,
+    ) -> None: ...
+    def target_configured(self, label: str, target_kind: str, tags: list[str]) -> None: ...
+    def target_completed(self, label: str, success: bool, tags: list[str]) -> None: ...
+    def action_executed(
+        self,
+        id: str,
+        label: str | None,
+        mnemonic: str,
+        argv: list[str],
+        exit_code: int,
+        stdout: FileDigest | None,
+        stderr: FileDigest | None,
+        start_time_secs: float,
+        end_time_secs: float,
+    ) -> None: ...
+    def test_result(
+        self,
+        label: str,
+        exit_code: int | None,
+        cached_locally: bool,
+        start_time_secs: float,
+        duration_secs: float,
+        stdout: FileDigest | None,
+        stderr: FileDigest | None,
+    ) -> None: ...
+    def finished(self, exit_code: int, finish_time_secs: float) -> None: ...
+
 # ------------------------------------------------------------------------------
 # Options
 # ------------------------------------------------------------------------------
@@ -666,6 +783,17 @@ class PyOptionParser:
     def get_dict(self, option_id: PyOptionId, default: dict[str, Any]) -> OptionDictValue: ...
     def get_passthrough_args(self) -> Optional[list[str]]: ...
 
+def interpolate_config_value(value: str, replacements: dict[str, str]) -> str: ...
+
+# Renders the (scope, is_goal, description, options) of the registered scopes as JSON, where each
+# option is a (name, type, choices, default, help) tuple.
+def render_option_schema(
+    scopes: Sequence[tuple[str, bool, str, Sequence[tuple[str, str, Sequence[str], Any, str]]]]
+) -> str: ...
+
+# Generates a bash, zsh or fish completion script from a schema rendered by `render_option_schema`.
+def generate_completion_script(shell: str, schema_json: str) -> str: ...
+
 # ------------------------------------------------------------------------------
 # Testutil
 # ------------------------------------------------------------------------------
@@ -690,8 +818,28 @@ class PyStubCAS:
 class InferenceMetadata:
     @staticmethod
     def javascript(
-        package_root: str, import_patterns: dict[str, list[str]]
-    ) -> InferenceMetadata: ...
+        package_root: str,
+        import_patterns: dict[str, list[str | tuple[tuple[str, ...], str]]],
+        config_files: dict[str, str] | None = None,
+        conditions: Sequence[str] | None = None,
+        package_name: str | None = None,
+        export_patterns: dict[str, list[str | tuple[tuple[str, ...], str]]] | None = None,
+        resolve_extensions: Sequence[str] | None = None,
+    ) -> InferenceMetadata:
+        """Replacements are either unconditional, or a tuple of (nested) conditions and the
+        replacement which applies under them.
+
+        If `conditions` are given, only the conditional replacements which apply under them are
+        used.
+
+        Imports of files without one of the `resolve_extensions` (e.g. `.js`) are resolved to the
+        candidates with each of them appended, where an entry like `/index.*` is expanded with each
+        of the extensions.
+        """
+    @staticmethod
+    def css(package_root: str) -> InferenceMetadata: ...
+    @staticmethod
+    def go(build_tags: Iterable[str]) -> InferenceMetadata: ...
     def __eq__(self, other: InferenceMetadata | Any) -> bool: ...
     def __hash__(self) -> int: ...
     def __repr__(self) -> str: ...
@@ -714,6 +862,62 @@ class NativeDependenciesRequest:
     def __hash__(self) -> int: ...
     def __repr__(self) -> str: ...
 
+class NativeDependenciesBatchRequest:
+    """A request to parse the dependencies of every file in a digest.
+
+    * The files are all parsed as the given `language`: one of `python`, `javascript`, `css`,
+      `go`, `jvm`, `dockerfile` or `shell`.
+    * The `metadata` is shared by all of the files, as with a `NativeDependenciesRequest`.
+    * The files are parsed in parallel, and each file shares its cache entry with a
+      `NativeDependenciesRequest` for it.
+
+    Example:
+        batch = await Get(
+            NativeParsedDependenciesBatch,
+            NativeDependenciesBatchRequest(sources_digest, "python", None),
+        )
+        result = batch.results["src/app.py"]  # A `NativeParsedPythonDependencies`.
+    """
+
+    def __init__(
+        self, digest: Digest, language: str, metadata: InferenceMetadata | None = None
+    ) -> None: ...
+    def __eq__(self, other: NativeDependenciesBatchRequest | Any) -> bool: ...
+    def __hash__(self) -> int: ...
+    def __repr__(self) -> str: ...
+
+class AggregateTestResultsRequest:
+    """A request to parse and merge the test result files in some digests.
+
+    * Files ending in `.xml` are parsed as JUnit XML reports, and files ending in `.json` as jest
+      JSON reports. Other files are ignored.
+    * The digests may contain the results of different shards of a run, and of multiple attempts
+      of a test: they should be ordered from the oldest to the most recent attempt. A test case
+      which failed in some attempts and passed in others is reported as flaky.
+
+    Example:
+        summary = await Get(
+            TestResultsSummary,
+            AggregateTestResultsRequest([result.xml_results.digest for result in results]),
+        )
+    """
+
+    def __init__(self, digests: Iterable[Digest]) -> None: ...
+    def __eq__(self, other: AggregateTestResultsRequest | Any) -> bool: ...
+    def __hash__(self) -> int: ...
+    def __repr__(self) -> str: ...
+
+# Pre-parses a BUILD file without evaluating it, returning its top-level calls (with their type
+# alias, span, literal fields and the names of fields which must be evaluated), the spans of its
+# syntax errors, and whether it is static: i.e. whether its calls were extracted entirely.
+def parse_build_file(
+    content: str,
+) -> tuple[
+    list[tuple[str, tuple[int, int, int, int, int, int], dict[str, Any], list[str]]],
+    list[tuple[int, int, int, int, int, int]],
+    bool,
+]: ...
+
 # ------------------------------------------------------------------------------
 # (etc.)
 # ------------------------------------------------------------------------------
@@ -740,6 +944,10 @@ def ensure_remote_has_recursive(
     scheduler: PyScheduler, digests: list[Digest | FileDigest]
 ) -> None: ...
 def ensure_directory_digest_persisted(scheduler: PyScheduler, digest: Digest) -> None: ...
+def export_store_bundle(
+    scheduler: PyScheduler, digests: list[Digest | FileDigest], path: str
+) -> None: ...
+def import_store_bundle(scheduler: PyScheduler, path: str) -> list[Digest | FileDigest]: ...
 def single_file_digests_to_bytes(
     scheduler: PyScheduler, digests: list[FileDigest]
 ) -> list[bytes]: ...
@@ -750,6 +958,13 @@ def write_digest(
     path_prefix: str,
     clear_paths: Sequence[str],
 ) -> None: ...
+def export_digest(
+    scheduler: PyScheduler,
+    session: PySession,
+    digest: Digest,
+    path_prefix: str,
+    mode: str,
+) -> tuple[int, int, int]: ...
 def write_log(msg: str, level: int, target: str) -> None: ...
 def flush_log() -> None: ...
 def set_per_run_log_path(path: str | None) -> None: ...
@@ -762,12 +977,17 @@ def stdio_initialize(
     literal_filters: tuple[str, ...],
     regex_filters: tuple[str, ...],
     log_file: str,
+    json_per_run_logs: bool,
+    json_log_fd: int | None,
 ) -> tuple[RawIOBase, TextIO, TextIO]: ...
 def stdio_thread_get_destination() -> PyStdioDestination: ...
 def stdio_thread_set_destination(destination: PyStdioDestination) -> None: ...
 def stdio_thread_console_set(stdin_fileno: int, stdout_fileno: int, stderr_fileno: int) -> None: ...
 def stdio_thread_console_color_mode_set(use_color: bool) -> None: ...
 def stdio_thread_console_clear() -> None: ...
+def stdio_thread_console_log_filters_set(log_levels_by_target: dict[str, int]) -> None: ...
+def stdio_thread_console_events_set(events_fileno: int | None) -> None: ...
+def stdio_thread_console_event_write(event: str) -> None: ...
 def stdio_write_stdout(msg: str) -> None: ...
 def stdio_write_stderr(msg: str) -> None: ...
 def task_side_effected() -> None: ...
@@ -781,6 +1001,7 @@ def tasks_task_begin(
     side_effecting: bool,
     engine_aware_return_type: bool,
     cacheable: bool,
+    persistent_cache_salt: str | None,
     name: str,
     desc: str,
     level: int,
@@ -800,6 +1021,8 @@ def execution_add_root_select(
     param_vals: Sequence,
     product: type,
 ) -> None: ...
+def metrics_server_create(scheduler: PyScheduler, port: int) -> PyMetricsServer: ...
+def metrics_server_shutdown(server: PyMetricsServer) -> None: ...
 def nailgun_server_await_shutdown(server: PyNailgunServer) -> None: ...
 def nailgun_server_create(
     executor: PyExecutor, port: int, runner: RawFdRunner
@@ -822,11 +1045,35 @@ def scheduler_create(
 def scheduler_execute(
     scheduler: PyScheduler, session: PySession, execution_request: PyExecutionRequest
 ) -> list: ...
+def scheduler_subscribe(
+    scheduler: PyScheduler, execution_request: PyExecutionRequest, debounce_in_ms: int
+) -> PySubscription: ...
+def scheduler_subscription_next(
+    scheduler: PyScheduler,
+    session: PySession,
+    subscription: PySubscription,
+    timeout_in_ms: int | None,
+) -> list[tuple[int, Any]]: ...
 def scheduler_metrics(scheduler: PyScheduler, session: PySession) -> dict[str, int]: ...
 def scheduler_live_items(
     scheduler: PyScheduler, session: PySession
 ) -> tuple[list[Any], dict[str, tuple[int, int]]]: ...
-def scheduler_shutdown(scheduler: PyScheduler, timeout_secs: int) -> None: ...
+def scheduler_check_memory(
+    scheduler: PyScheduler,
+    session: PySession,
+    max_memory_usage: int,
+    clear_caches_fraction: float,
+    max_graph_size: int | None,
+) -> bool: ...
+def scheduler_memory_usage(
+    scheduler: PyScheduler, session: PySession, include_python_values: bool
+) -> list[tuple[str, str | None, int, int, int | None]]: ...
+def scheduler_publish_to_remote_cache(
+    scheduler: PyScheduler, session: PySession, execution_request: PyExecutionRequest
+) -> int: ...
+def scheduler_shutdown(
+    scheduler: PyScheduler, timeout_secs: int, drain_timeout_secs: int
+) -> None: ...
 def session_new_run_id(session: PySession) -> None: ...
 def session_poll_workunits(
     scheduler: PyScheduler, session: PySession, max_log_verbosity_level: int
@@ -838,14 +1085,30 @@ def session_get_metrics(session: PySession) -> dict[str, int]: ...
 def session_get_observation_histograms(
     scheduler: PyScheduler, session: PySession
 ) -> dict[str, Any]: ...
+def session_get_build_stats(session: PySession) -> dict[str, int | float | None]: ...
+def session_render_build_stats(session: PySession) -> str: ...
+def session_get_critical_path(session: PySession) -> list[dict[str, Any]]: ...
+def session_get_running_workunits(session: PySession) -> list[dict[str, Any]]: ...
+def session_render_running_workunits(session: PySession) -> str: ...
+def session_get_sampled_workunits(session: PySession) -> list[dict[str, Any]]: ...
+def session_explain_cache_misses(
+    session: PySession, before: str | None, after: str | None
+) -> dict[str, Any]: ...
 def session_record_test_observation(
     scheduler: PyScheduler, session: PySession, value: int
 ) -> None: ...
 def session_isolated_shallow_clone(session: PySession, build_id: str) -> PySession: ...
+def session_cancel_roots(
+    scheduler: PyScheduler, session: PySession, execution_request: PyExecutionRequest
+) -> int: ...
+def session_serve_digest(scheduler: PyScheduler, session: PySession, digest: Digest) -> str: ...
 def session_wait_for_tail_tasks(
     scheduler: PyScheduler, session: PySession, timeout: float
 ) -> None: ...
+def session_write_chrome_trace(session: PySession) -> None: ...
 def graph_len(scheduler: PyScheduler) -> int: ...
+def graph_set_output_stability_tracking(scheduler: PyScheduler, enabled: bool) -> None: ...
+def graph_output_stability_report(scheduler: PyScheduler) -> list[dict[str, Any]]: ...
 def graph_visualize(scheduler: PyScheduler, session: PySession, path: str) -> None: ...
 def graph_invalidate_paths(scheduler: PyScheduler, paths: Iterable[str]) -> int: ...
 def graph_invalidate_all_paths(scheduler: PyScheduler) -> int: ...
@@ -863,6 +1126,7 @@ def rule_subgraph_visualize(
     scheduler: PyScheduler, param_types: Sequence[type], product_type: type, path: str
 ) -> None: ...
 def garbage_collect_store(scheduler: PyScheduler, target_size_bytes: int) -> None: ...
+def scrub_store(scheduler: PyScheduler, sample_fraction: float) -> tuple[int, int, int]: ...
 def lease_files_in_graph(scheduler: PyScheduler, session: PySession) -> None: ...
 def strongly_connected_components(
     adjacency_lists: Sequence[Tuple[Any, Sequence[Any]]]
@@ -942,9 +1206,15 @@ class PyExecutionRequest:
         self, *, poll: bool, poll_delay_in_ms: int | None, timeout_in_ms: int | None
     ) -> None: ...
 
+class PySubscription:
+    pass
+
 class PyExecutionStrategyOptions:
     def __init__(self, **kwargs: Any) -> None: ...
 
+class PyMetricsServer:
+    def port(self) -> int: ...
+
 class PyNailgunServer:
     def port(self) -> int: ...
 
@@ -968,15 +1238,33 @@ class PySession:
         build_id: str,
         session_values: SessionValues,
         cancellation_latch: PySessionCancellationLatch,
+        chrome_trace_file: str | None = None,
+        record_critical_path: bool = False,
+        stream_process_output: bool = False,
+        run_budget_seconds: float | None = None,
+        workunit_sampling_threshold: int | None = None,
+        workunit_sampling_interval: int = 0,
+        cache_miss_records_dir: str | None = None,
     ) -> None: ...
     def cancel(self) -> None: ...
     def is_cancelled(self) -> bool: ...
+    def run_budget_exceeded(self) -> bool: ...
     @property
     def session_values(self) -> SessionValues: ...
 
 class PySessionCancellationLatch:
     def __init__(self) -> None: ...
 
+class PyWorkunitStream:
+    def __init__(
+        self, scheduler: PyScheduler, session: PySession, max_log_verbosity_level: int
+    ) -> None: ...
+    def run(
+        self,
+        callback: Callable[[tuple[Workunit, ...], tuple[Workunit, ...]], None],
+    ) -> None: ...
+    def close(self) -> None: ...
+
 class PyTasks:
     def __init__(self) -> None: ...
 
@@ -1004,3 +1292,34 @@ class IntrinsicError(EngineError):
 
 class IncorrectProductError(EngineError):
     """Exceptions raised when a rule's return value doesn't match its declared type."""
+
+class RootCancelled(EngineError):
+    """The result of a root which was cancelled while it was executing."""
+
+class RunBudgetExceeded(RootCancelled):
+    """The result of a root which was cancelled because its Session's run budget elapsed."""
+
+# NB: Errors which the engine has classified have a stable, machine-readable `code`, a `category`
+# (one of "user", "infra", or "internal"), and the `context` in which they occurred, outermost
+# first.
+
+class UserError(IntrinsicError):
+    """An error caused by invalid inputs, which will fail in the same way if retried."""
+
+    code: str
+    category: str
+    context: list[str]
+
+class InfrastructureError(IntrinsicError):
+    """An error in a dependency of the engine, such as a tool or remote service."""
+
+    code: str
+    category: str
+    context: list[str]
+
+class InternalError(IntrinsicError):
+    """An error caused by a bug in Pants."""
+
+    code: str
+    category: str
+    context: list[str]
@@ -1,10 +1,22 @@
 # Copyright 2023 Pants project contributors (see CONTRIBUTORS.md).
 # Licensed under the Apache License, Version 2.0 (see LICENSE).
+import pytest
+
+from pants.engine.internals.native_dep_inference import (
+    NativeParsedDependenciesBatch,
+    NativeParsedShellDependencies,
+)
 from pants.engine.internals.native_engine import (
     EMPTY_DIGEST,
+    AggregateTestResultsRequest,
     InferenceMetadata,
+    NativeDependenciesBatchRequest,
     NativeDependenciesRequest,
 )
+from pants.engine.internals.native_test_results import TestCaseResult, TestResultsSummary
+from pants.engine.internals.scheduler import ExecutionError
+from pants.testutil.rule_runner import QueryRule, RuleRunner
+from pants.util.frozendict import FrozenDict
 
 
 def test_can_construct_javascript_metadata() -> None:
@@ -18,3 +30,84 @@ def test_can_construct_native_dependencies_request() -> None:
     NativeDependenciesRequest(
         EMPTY_DIGEST, InferenceMetadata.javascript(package_root="some/dir", import_patterns={})
     )
+
+
+def test_can_construct_native_dependencies_batch_request() -> None:
+    NativeDependenciesBatchRequest(EMPTY_DIGEST, "python", None)
+    assert NativeDependenciesBatchRequest(
+        EMPTY_DIGEST, "go", InferenceMetadata.go(build_tags=["linux"])
+    ) == NativeDependenciesBatchRequest(
+        EMPTY_DIGEST, "go", InferenceMetadata.go(build_tags=["linux"])
+    )
+
+
+def test_parse_deps_batch() -> None:
+    rule_runner = RuleRunner(
+        rules=[QueryRule(NativeParsedDependenciesBatch, [NativeDependenciesBatchRequest])]
+    )
+    snapshot = rule_runner.make_snapshot(
+        {"a.sh": "source lib/a.sh\n", "b/c.sh": "./run.sh --fast\n", "b/empty.sh": ""}
+    )
+    result = rule_runner.request(
+        NativeParsedDependenciesBatch,
+        [NativeDependenciesBatchRequest(snapshot.digest, "shell", None)],
+    )
+    assert result.results == FrozenDict(
+        {
+            "a.sh": NativeParsedShellDependencies({"lib/a.sh"}, set()),
+            "b/c.sh": NativeParsedShellDependencies(set(), {"./run.sh"}),
+            "b/empty.sh": NativeParsedShellDependencies(set(), set()),
+        }
+    )
+
+    with pytest.raises(ExecutionError, match="Unknown language for dependency inference"):
+        rule_runner.request(
+            NativeParsedDependenciesBatch,
+            [NativeDependenciesBatchRequest(snapshot.digest, "cobol", None)],
+        )
+
+
+def test_aggregate_test_results() -> None:
+    rule_runner = RuleRunner(rules=[QueryRule(TestResultsSummary, [AggregateTestResultsRequest])])
+    first_attempt = rule_runner.make_snapshot(
+        {
+            "tests.test_app.xml": (
+                '<testsuite name="pytest">'
+                '<testcase classname="tests.test_app" name="test_ok" time="0.5" />'
+                '<testcase classname="tests.test_app" name="test_flaky" time="1.0">'
+                '<failure message="assert 1 == 2" /></testcase>'
+                "</testsuite>"
+            ),
+            "jest.json": (
+                '{"testResults": [{"name": "/tmp/pants-sandbox-abc/app.test.js", '
+                '"status": "passed", "assertionResults": [{"ancestorTitles": ["app"], '
+                '"title": "works", "status": "passed", "duration": 250}]}]}'
+            ),
+            "ignored.txt": "",
+        }
+    ).digest
+    second_attempt = rule_runner.make_snapshot(
+        {
+            "tests.test_app.xml": (
+                '<testsuite name="pytest">'
+                '<testcase classname="tests.test_app" name="test_flaky" time="1.0" />'
+                "</testsuite>"
+            ),
+        }
+    ).digest
+
+    request = AggregateTestResultsRequest([first_attempt, second_attempt])
+    assert request == AggregateTestResultsRequest([first_attempt, second_attempt])
+    summary = rule_runner.request(TestResultsSummary, [request])
+    assert (summary.passed, summary.failed, summary.errored, summary.skipped) == (3, 0, 0, 0)
+    assert summary.flaky == 1
+    assert summary.duration_secs == pytest.approx(2.75)
+    assert summary.cases == (
+        TestCaseResult("app.test.js", "app works", "passed", 0.25, None, 1, False),
+        TestCaseResult("tests.test_app", "test_flaky", "passed", 2.0, "assert 1 == 2", 2, True),
+        TestCaseResult("tests.test_app", "test_ok", "passed", 0.5, None, 1, False),
+    )
+
+    invalid = rule_runner.make_snapshot({"broken.xml": "<testsuite><testcase></testsuite>"})
+    with pytest.raises(ExecutionError, match="Failed to parse test results from broken.xml"):
+        rule_runner.request(TestResultsSummary, [AggregateTestResultsRequest([invalid.digest])])
@@ -0,0 +1,27 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+from dataclasses import dataclass
+
+
+@dataclass(frozen=True)
+class AddressFamilyDirsRequest:
+    """Find the directories whose BUILD files may define targets residing in `dirs`, or in and
+    below `recursive_dirs`.
+
+    This includes the ancestors of each directory, since their BUILD files may generate targets
+    into it.
+    """
+
+    dirs: tuple[str, ...]
+    recursive_dirs: tuple[str, ...]
+    build_patterns: tuple[str, ...]
+    build_ignore_patterns: tuple[str, ...]
+
+
+@dataclass(frozen=True)
+class AddressFamilyDirs:
+    # Sorted, and relative to the build root (which is the empty string).
+    dirs: tuple[str, ...]
@@ -0,0 +1,68 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+from dataclasses import dataclass
+
+
+@dataclass(frozen=True)
+class TestCaseResult:
+    """The result of a test case, merged across the shards and attempts in which it ran."""
+
+    # Prevent this class from being detected by pytest as a test class.
+    __test__ = False
+
+    suite: str
+    name: str
+    # One of `passed`, `failed`, `errored` or `skipped`.
+    outcome: str
+    # The total duration of all attempts.
+    duration_secs: float
+    # The message of the most recent attempt which did not pass, if any.
+    message: str | None
+    attempts: int
+    # True if the test case both failed and passed in different attempts.
+    flaky: bool
+
+
+@dataclass(frozen=True)
+class TestResultsSummary:
+    """The consolidated results of parsing and merging test result files natively."""
+
+    # Prevent this class from being detected by pytest as a test class.
+    __test__ = False
+
+    cases: tuple[TestCaseResult, ...]
+    passed: int
+    failed: int
+    errored: int
+    skipped: int
+    flaky: int
+    duration_secs: float
+
+    def __init__(
+        self,
+        cases: list[tuple[str, str, str, float, str | None, int, bool]],
+        passed: int,
+        failed: int,
+        errored: int,
+        skipped: int,
+        flaky: int,
+        duration_secs: float,
+    ):
+        object.__setattr__(self, "cases", tuple(TestCaseResult(*case) for case in cases))
+        object.__setattr__(self, "passed", passed)
+        object.__setattr__(self, "failed", failed)
+        object.__setattr__(self, "errored", errored)
+        object.__setattr__(self, "skipped", skipped)
+        object.__setattr__(self, "flaky", flaky)
+        object.__setattr__(self, "duration_secs", duration_secs)
+
+    @property
+    def total(self) -> int:
+        return self.passed + self.failed + self.errored + self.skipped
+
+    @property
+    def succeeded(self) -> bool:
+        return self.failed == 0 and self.errored == 0
@@ -26,6 +26,7 @@ from pants.build_graph.build_file_aliases import BuildFileAliases
 from pants.engine.env_vars import EnvironmentVars
 from pants.engine.internals.defaults import BuildFileDefaultsParserState, SetDefaultsT
 from pants.engine.internals.dep_rules import BuildFileDependencyRulesParserState
+from pants.engine.internals.native_dep_inference import NativeParsedBuildFile
 from pants.engine.internals.target_adaptor import TargetAdaptor
 from pants.engine.target import Field, ImmutableValue, RegisteredTargetTypes
 from pants.engine.unions import UnionMembership
@@ -302,6 +303,11 @@ class Registrar:
         return self._type_alias
 
     def __call__(self, **kwargs: Any) -> TargetAdaptor:
+        frame = inspect.currentframe()
+        source_line = frame.f_back.f_lineno if frame and frame.f_back else "??"
+        return self._create(source_line, kwargs)
+
+    def _create(self, source_line: int | str, kwargs: dict[str, Any]) -> TargetAdaptor:
         if self._parse_state.is_bootstrap and any(
             isinstance(v, _UnrecognizedSymbol) for v in kwargs.values()
         ):
@@ -317,8 +323,6 @@ class Registrar:
                 )
             kwargs["name"] = None
 
-        frame = inspect.currentframe()
-        source_line = frame.f_back.f_lineno if frame and frame.f_back else "??"
         kwargs["__description_of_origin__"] = f"{self._parse_state.filepath()}:{source_line}"
         raw_values = dict(self._parse_state.defaults.get(self._type_alias))
         raw_values.update(kwargs)
@@ -417,6 +421,7 @@ class Parser:
         defaults: BuildFileDefaultsParserState,
         dependents_rules: BuildFileDependencyRulesParserState | None,
         dependencies_rules: BuildFileDependencyRulesParserState | None,
+        preparsed: NativeParsedBuildFile | None = None,
     ) -> list[TargetAdaptor]:
         self._parse_state.reset(
             filepath=filepath,
@@ -427,6 +432,11 @@ class Parser:
             env_vars=env_vars,
         )
 
+        if preparsed is not None and preparsed.is_static:
+            target_adaptors = self._create_preparsed_targets(preparsed, extra_symbols)
+            if target_adaptors is not None:
+                return target_adaptors
+
         global_symbols: dict[str, Any] = {
             **self.symbols,
             **extra_symbols.symbols,
@@ -495,6 +505,25 @@ class Parser:
         error_on_imports(build_file_content, filepath)
         return self._parse_state.parsed_targets()
 
+    def _create_preparsed_targets(
+        self, preparsed: NativeParsedBuildFile, extra_symbols: BuildFilePreludeSymbols
+    ) -> list[TargetAdaptor] | None:
+        """Create the targets of a static BUILD file without evaluating it.
+
+        Returns None if the file calls anything other than target types, in which case it must be
+        evaluated instead.
+        """
+        registrars = []
+        for target in preparsed.targets:
+            registrar = self.symbols.get(target.type_alias)
+            if not isinstance(registrar, Registrar) or target.type_alias in extra_symbols.symbols:
+                return None
+            registrars.append(registrar)
+
+        for registrar, target in zip(registrars, preparsed.targets):
+            registrar._create(target.span.start_line, dict(target.fields))
+        return self._parse_state.parsed_targets()
+
 
 def error_on_imports(build_file_content: str, filepath: str) -> None:
     # This is poor sandboxing; there are many ways to get around this. But it's sufficient to tell
@@ -14,6 +14,7 @@ from pants.core.target_types import GenericTarget
 from pants.engine.addresses import Address
 from pants.engine.env_vars import EnvironmentVars
 from pants.engine.internals.defaults import BuildFileDefaults, BuildFileDefaultsParserState
+from pants.engine.internals.native_dep_inference import NativeParsedBuildFile
 from pants.engine.internals.parser import (
     BuildFilePreludeSymbols,
     ParseError,
@@ -193,6 +194,51 @@ def test_unknown_target_for_defaults_during_bootstrap_issue_19445(
     )
 
 
+@pytest.mark.parametrize(
+    "build_file_content, prelude, preparsed",
+    [
+        ("tgt(name='a', tags=['x'])\n\ntgt(\n    name='b',\n    description=\"b\",\n)", {}, True),
+        ("tgt(name='a', tags=['x' + 'y'])", {}, False),
+        ("TAGS = ['x']\ntgt(name='a', tags=TAGS)", {}, False),
+        ("tgt(name='a')\nmacro(name='b')", {"macro": lambda **kw: None}, False),
+        ("tgt(name='a')", {"tgt": lambda **kw: None}, False),
+    ],
+)
+def test_preparsed_build_file(
+    defaults_parser_state: BuildFileDefaultsParserState,
+    build_file_content: str,
+    prelude: dict[str, Any],
+    preparsed: bool,
+) -> None:
+    parser = Parser(
+        build_root="",
+        registered_target_types=RegisteredTargetTypes({"tgt": GenericTarget}),
+        union_membership=UnionMembership({}),
+        object_aliases=BuildFileAliases(),
+        ignore_unrecognized_symbols=False,
+    )
+    prelude_symbols = BuildFilePreludeSymbols.create(prelude, ())
+
+    def parse(native: NativeParsedBuildFile | None) -> list[tuple[str, str]]:
+        target_adaptors = parser.parse(
+            "dir/BUILD",
+            build_file_content,
+            prelude_symbols,
+            EnvironmentVars({}),
+            False,
+            defaults_parser_state,
+            dependents_rules=None,
+            dependencies_rules=None,
+            preparsed=native,
+        )
+        return [(repr(t), repr(t.kwargs)) for t in target_adaptors]
+
+    native = NativeParsedBuildFile.parse(build_file_content)
+    assert native.is_static == preparsed
+    # Targets are the same whether or not they were created without evaluating the BUILD file.
+    assert parse(native) == parse(None)
+
+
 @pytest.mark.parametrize("symbol", ["a", "bad", "BAD", "a___b_c", "a231", "áç"])
 def test_extract_symbol_from_name_error(symbol: str) -> None:
     assert _extract_symbol_from_name_error(NameError(f"name '{symbol}' is not defined")) == symbol
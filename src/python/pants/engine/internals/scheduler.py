@@ -3,8 +3,11 @@
 
 from __future__ import annotations
 
+import hashlib
+import inspect
 import logging
 import os
+import sys
 import time
 from dataclasses import dataclass
 from pathlib import PurePath
@@ -15,6 +18,7 @@ from typing_extensions import TypedDict
 
 from pants.engine.collection import Collection
 from pants.engine.engine_aware import EngineAwareParameter, EngineAwareReturnType, SideEffecting
+from pants.engine.env_vars import CompleteEnvironmentVars
 from pants.engine.fs import (
     CreateDigest,
     Digest,
@@ -26,6 +30,7 @@ from pants.engine.fs import (
     FileDigest,
     FileEntry,
     NativeDownloadFile,
+    PathDigests,
     PathGlobs,
     PathGlobsAndRoot,
     PathMetadataRequest,
@@ -38,8 +43,14 @@ from pants.engine.goal import CurrentExecutingGoals, Goal
 from pants.engine.internals import native_engine
 from pants.engine.internals.docker import DockerResolveImageRequest, DockerResolveImageResult
 from pants.engine.internals.native_dep_inference import (
+    NativeParsedCssDependencies,
+    NativeParsedDependenciesBatch,
+    NativeParsedDockerfileDependencies,
+    NativeParsedGoDependencies,
     NativeParsedJavascriptDependencies,
+    NativeParsedJvmDependencies,
     NativeParsedPythonDependencies,
+    NativeParsedShellDependencies,
 )
 from pants.engine.internals.native_engine import (
     PyExecutionRequest,
@@ -50,9 +61,13 @@ from pants.engine.internals.native_engine import (
     PyScheduler,
     PySession,
     PySessionCancellationLatch,
+    PySubscription,
     PyTasks,
     PyTypes,
+    PyWorkunitStream,
 )
+from pants.engine.internals.native_specs import AddressFamilyDirs
+from pants.engine.internals.native_test_results import TestResultsSummary
 from pants.engine.internals.nodes import Return, Throw
 from pants.engine.internals.selectors import Params
 from pants.engine.internals.session import RunId, SessionValues
@@ -63,6 +78,7 @@ from pants.engine.process import (
     InteractiveProcessResult,
     Process,
     ProcessResultMetadata,
+    ShardedProcessResult,
 )
 from pants.engine.rules import Rule, RuleIndex, TaskRule
 from pants.engine.unions import UnionMembership, is_union, union_in_scope_types
@@ -74,6 +90,7 @@ from pants.option.global_options import (
 from pants.util.contextutil import temporary_file_path
 from pants.util.logging import LogLevel
 from pants.util.strutil import pluralize
+from pants.version import VERSION
 
 logger = logging.getLogger(__name__)
 
@@ -86,6 +103,33 @@ class PolledWorkunits(TypedDict):
     completed: tuple[Workunit, ...]
 
 
+class WorkunitStream:
+    """Delivers batches of workunits to a callback as they are recorded.
+
+    See `SchedulerSession.stream_workunits`.
+    """
+
+    def __init__(
+        self, native: PyWorkunitStream, callback: Callable[[PolledWorkunits], None]
+    ) -> None:
+        self._native = native
+        self._callback = callback
+
+    def run(self) -> None:
+        """Call the callback with each batch of workunits until `close` is called.
+
+        Blocks the calling thread, but does not hold the GIL while waiting for workunits. Workunits
+        which are recorded while the callback is running are delivered together in the next batch.
+        """
+        self._native.run(
+            lambda started, completed: self._callback({"started": started, "completed": completed})
+        )
+
+    def close(self) -> None:
+        """Cause `run` to return once any in-progress call to the callback has completed."""
+        self._native.close()
+
+
 @dataclass(frozen=True)
 class ExecutionRequest:
     """Holds the roots for an execution, which might have been requested by a user.
@@ -97,6 +141,35 @@ class ExecutionRequest:
     native: PyExecutionRequest
 
 
+@dataclass(frozen=True)
+class Subscription:
+    """A long-lived subscription to the values of some roots.
+
+    To create a Subscription, see `SchedulerSession.subscribe`.
+    """
+
+    roots: tuple[tuple[type, Any | Params], ...]
+    native: PySubscription
+
+
+@dataclass(frozen=True)
+class NodeMemoryUsage:
+    """The memory used by the nodes in the graph which share a type and (for tasks) a rule.
+
+    See `SchedulerSession.memory_usage`.
+    """
+
+    node_type: str
+    rule: str | None
+    count: int
+    rust_bytes: int
+    python_bytes: int | None
+
+    @property
+    def total_bytes(self) -> int:
+        return self.rust_bytes + (self.python_bytes or 0)
+
+
 class ExecutionError(Exception):
     def __init__(self, message, wrapped_exceptions=None):
         super().__init__(message)
@@ -154,6 +227,7 @@ class Scheduler:
         # Create the native Scheduler and Session.
         types = PyTypes(
             paths=Paths,
+            path_digests=PathDigests,
             path_metadata_request=PathMetadataRequest,
             path_metadata_result=PathMetadataResult,
             file_content=FileContent,
@@ -170,8 +244,10 @@ class Scheduler:
             process=Process,
             process_result=FallibleProcessResult,
             process_result_metadata=ProcessResultMetadata,
+            sharded_process_result=ShardedProcessResult,
             coroutine=CoroutineType,
             session_values=SessionValues,
+            complete_environment_vars=CompleteEnvironmentVars,
             run_id=RunId,
             interactive_process=InteractiveProcess,
             interactive_process_result=InteractiveProcessResult,
@@ -180,6 +256,14 @@ class Scheduler:
             docker_resolve_image_result=DockerResolveImageResult,
             parsed_python_deps_result=NativeParsedPythonDependencies,
             parsed_javascript_deps_result=NativeParsedJavascriptDependencies,
+            parsed_css_deps_result=NativeParsedCssDependencies,
+            parsed_go_deps_result=NativeParsedGoDependencies,
+            parsed_jvm_deps_result=NativeParsedJvmDependencies,
+            parsed_dockerfile_deps_result=NativeParsedDockerfileDependencies,
+            parsed_shell_deps_result=NativeParsedShellDependencies,
+            parsed_deps_batch_result=NativeParsedDependenciesBatch,
+            address_family_dirs=AddressFamilyDirs,
+            test_results_summary=TestResultsSummary,
         )
         remoting_options = PyRemotingOptions(
             provider=execution_options.remote_provider.value,
@@ -189,14 +273,22 @@ class Scheduler:
             store_rpc_retries=execution_options.remote_store_rpc_retries,
             store_rpc_concurrency=execution_options.remote_store_rpc_concurrency,
             store_rpc_timeout_millis=execution_options.remote_store_rpc_timeout_millis,
+            store_streaming_rpc_timeout_millis=execution_options.remote_store_streaming_rpc_timeout_millis,
             store_batch_api_size_limit=execution_options.remote_store_batch_api_size_limit,
+            asset_fetch=execution_options.remote_asset_fetch,
             cache_warnings_behavior=execution_options.remote_cache_warnings.value,
             cache_content_behavior=execution_options.cache_content_behavior.value,
             cache_rpc_concurrency=execution_options.remote_cache_rpc_concurrency,
             cache_rpc_timeout_millis=execution_options.remote_cache_rpc_timeout_millis,
+            cache_write_behind=execution_options.remote_cache_write_behind,
+            cache_write_behind_rate_limit=execution_options.remote_cache_write_behind_rate_limit,
+            cache_circuit_breaker_threshold=execution_options.remote_cache_circuit_breaker_threshold,
+            cache_circuit_breaker_cooldown_secs=execution_options.remote_cache_circuit_breaker_cooldown_secs,
             execution_headers=execution_options.remote_execution_headers,
             execution_overall_deadline_secs=execution_options.remote_execution_overall_deadline_secs,
             execution_rpc_concurrency=execution_options.remote_execution_rpc_concurrency,
+            execution_priority=execution_options.remote_execution_priority,
+            results_cache_priority=execution_options.remote_results_cache_priority,
             store_address=execution_options.remote_store_address,
             execution_address=execution_options.remote_execution_address,
             execution_process_cache_namespace=execution_options.process_execution_cache_namespace,
@@ -205,6 +297,11 @@ class Scheduler:
             client_certs_path=execution_options.remote_client_certs_path,
             client_key_path=execution_options.remote_client_key_path,
             append_only_caches_base_path=execution_options.remote_execution_append_only_caches_base_path,
+            http_proxy=execution_options.http_proxy,
+            https_proxy=execution_options.https_proxy,
+            no_proxy=execution_options.no_proxy,
+            store_upload_rate_limit=execution_options.remote_store_upload_rate_limit,
+            store_download_rate_limit=execution_options.remote_store_download_rate_limit,
         )
         py_local_store_options = PyLocalStoreOptions(
             store_dir=local_store_options.store_dir,
@@ -213,6 +310,9 @@ class Scheduler:
             directories_max_size_bytes=local_store_options.directories_max_size_bytes,
             lease_time_millis=LOCAL_STORE_LEASE_TIME_SECS * 1000,
             shard_count=local_store_options.shard_count,
+            server_port=local_store_options.server_port,
+            materialize_concurrency=local_store_options.materialize_concurrency,
+            clone_files=local_store_options.clone_files,
         )
         exec_strategy_opts = PyExecutionStrategyOptions(
             local_cache=execution_options.local_cache,
@@ -225,6 +325,17 @@ class Scheduler:
             child_max_memory=execution_options.process_total_child_memory_usage or 0,
             child_default_memory=execution_options.process_per_child_memory_usage,
             graceful_shutdown_timeout=execution_options.process_execution_graceful_shutdown_timeout,
+            cache_max_age_secs=execution_options.process_cache_max_age,
+            verify_determinism=list(execution_options.process_verify_determinism),
+            determinism_report_path=execution_options.process_determinism_report,
+            session_tmpdir=execution_options.session_tmpdir,
+            sandboxer_socket=execution_options.sandboxer_socket,
+            local_sandbox_exec=execution_options.process_execution_local_sandbox_exec.value,
+            local_sandbox_exec_allowed_paths=list(
+                execution_options.process_execution_local_sandbox_exec_allowed_paths
+            ),
+            stall_threshold_secs=execution_options.stall_threshold,
+            retry_stalled_processes=execution_options.stall_retry_processes,
         )
 
         self._py_executor = executor
@@ -311,6 +422,23 @@ class Scheduler:
     def graph_len(self) -> int:
         return native_engine.graph_len(self.py_scheduler)
 
+    def set_output_stability_tracking(self, enabled: bool) -> None:
+        """Enable or disable tracking of rules which re-run after invalidation with equal output.
+
+        Disabling tracking discards the report: see `output_stability_report`.
+        """
+        native_engine.graph_set_output_stability_tracking(self.py_scheduler, enabled)
+
+    def output_stability_report(self) -> list[dict[str, Any]]:
+        """Return the "wasted recomputes" of each kind of node since tracking was enabled.
+
+        Each entry has the `kind` of node (generally the name of a rule), the `count` of times that
+        nodes of that kind re-ran after being invalidated but produced identical values, and the
+        most recent `trigger`: the path of invalidation from the node which was invalidated to the
+        node which re-ran.
+        """
+        return native_engine.graph_output_stability_report(self.py_scheduler)
+
     def execution_add_root_select(
         self, execution_request: PyExecutionRequest, subject_or_params: Any | Params, product: type
     ) -> None:
@@ -326,6 +454,14 @@ class Scheduler:
     def garbage_collect_store(self, target_size_bytes: int) -> None:
         native_engine.garbage_collect_store(self.py_scheduler, target_size_bytes)
 
+    def scrub_store(self, sample_fraction: float = 1.0) -> tuple[int, int, int]:
+        """Re-hash (a sample of) the entries in the local store, removing any which are corrupted.
+
+        Corrupted entries are re-fetched from the remote store if one is configured. Returns the
+        number of entries which were checked, corrupted, and repaired.
+        """
+        return native_engine.scrub_store(self.py_scheduler, sample_fraction)
+
     def new_session(
         self,
         build_id: str,
@@ -334,8 +470,29 @@ class Scheduler:
         max_workunit_level: LogLevel = LogLevel.DEBUG,
         session_values: SessionValues | None = None,
         cancellation_latch: PySessionCancellationLatch | None = None,
+        chrome_trace_file: str | None = None,
+        record_critical_path: bool = False,
+        stream_process_output: bool = False,
+        run_budget_seconds: float | None = None,
+        workunit_sampling_threshold: int | None = None,
+        workunit_sampling_interval: int = 0,
+        cache_miss_records_dir: str | None = None,
     ) -> SchedulerSession:
-        """Creates a new SchedulerSession for this Scheduler."""
+        """Creates a new SchedulerSession for this Scheduler.
+
+        If `run_budget_seconds` is set, work which is still running in the Session when the budget
+        has elapsed is cancelled: see `SchedulerSession.run_budget_exceeded`.
+
+        If `workunit_sampling_threshold` is set, workunits with a single name beyond the threshold
+        are only recorded individually once per `workunit_sampling_interval`, and are otherwise
+        aggregated: see `SchedulerSession.get_sampled_workunits`.
+
+        If `record_critical_path` is set, the workunits which complete in the Session are recorded
+        in order to compute its critical path: see `SchedulerSession.get_critical_path`.
+
+        If `cache_miss_records_dir` is set, the processes requested in the Session are recorded to
+        it when the Session ends: see `SchedulerSession.explain_cache_misses`.
+        """
         return SchedulerSession(
             self,
             PySession(
@@ -346,11 +503,23 @@ class Scheduler:
                 build_id=build_id,
                 session_values=session_values or SessionValues(),
                 cancellation_latch=cancellation_latch or PySessionCancellationLatch(),
+                chrome_trace_file=chrome_trace_file,
+                record_critical_path=record_critical_path,
+                stream_process_output=stream_process_output,
+                run_budget_seconds=run_budget_seconds,
+                workunit_sampling_threshold=workunit_sampling_threshold,
+                workunit_sampling_interval=workunit_sampling_interval,
+                cache_miss_records_dir=cache_miss_records_dir,
             ),
         )
 
-    def shutdown(self, timeout_secs: int = 60) -> None:
-        native_engine.scheduler_shutdown(self.py_scheduler, timeout_secs)
+    def shutdown(self, timeout_secs: int = 60, drain_timeout_secs: int = 0) -> None:
+        """Shut down the Scheduler.
+
+        If `drain_timeout_secs` is non-zero, no new work is started, and work which is already
+        running is given up to that long to complete before it is canceled.
+        """
+        native_engine.scheduler_shutdown(self.py_scheduler, timeout_secs, drain_timeout_secs)
 
 
 class _PathGlobsAndRootCollection(Collection[PathGlobsAndRoot]):
@@ -393,6 +562,17 @@ class SchedulerSession:
         )
         return {"started": result[0], "completed": result[1]}
 
+    def stream_workunits(
+        self, max_log_verbosity: LogLevel, callback: Callable[[PolledWorkunits], None]
+    ) -> WorkunitStream:
+        """Create a stream which calls `callback` with batches of workunits as they are recorded.
+
+        Workunits which have not been delivered when the stream is closed may be collected with
+        `poll_workunits`.
+        """
+        native = PyWorkunitStream(self.py_scheduler, self.py_session, max_log_verbosity.level)
+        return WorkunitStream(native, callback)
+
     def new_run_id(self) -> None:
         """Assigns a new "run id" to this Session, without creating a new Session.
 
@@ -459,6 +639,52 @@ class SchedulerSession:
         """Return all Python objects held by the Scheduler."""
         return native_engine.scheduler_live_items(self.py_scheduler, self.py_session)
 
+    def check_memory(
+        self,
+        *,
+        max_memory_usage: int,
+        clear_caches_fraction: float,
+        max_graph_size: int | None,
+    ) -> bool:
+        """Check memory usage, clearing in-memory caches if they exceed the given limits.
+
+        Should only be called between runs. Returns True if the process should be restarted in order
+        to reclaim memory.
+        """
+        return native_engine.scheduler_check_memory(
+            self.py_scheduler,
+            self.py_session,
+            max_memory_usage,
+            clear_caches_fraction,
+            max_graph_size,
+        )
+
+    def memory_usage(self, *, include_python_values: bool = True) -> list[NodeMemoryUsage]:
+        """Return the memory used by the graph, grouped by node type and rule, largest first.
+
+        Estimating the size of Python values is much slower than computing the size of the
+        engine's own structures, so it may be skipped with `include_python_values=False`.
+        """
+        return [
+            NodeMemoryUsage(*usage)
+            for usage in native_engine.scheduler_memory_usage(
+                self.py_scheduler, self.py_session, include_python_values
+            )
+        ]
+
+    def publish_to_remote_cache(self, execution_request: ExecutionRequest) -> int:
+        """Upload the locally produced process results for the given request to the remote cache.
+
+        The request must already have been executed in this session. Results are uploaded (along
+        with their outputs) even if remote cache writes were disabled while they were produced, so
+        that a build may run quickly and then publish its cache entries as a separate step.
+
+        Returns the number of results which were published.
+        """
+        return native_engine.scheduler_publish_to_remote_cache(
+            self.py_scheduler, self.py_session, execution_request.native
+        )
+
     def _maybe_visualize(self) -> None:
         if self._scheduler.visualize_to_dir is not None:
             # TODO: This increment-and-get is racey.
@@ -483,16 +709,7 @@ class SchedulerSession:
         except native_engine.PollTimeout:
             raise ExecutionTimeoutError("Timed out")
 
-        states = [
-            Throw(
-                raw_root.result,
-                python_traceback=raw_root.python_traceback,
-                engine_traceback=raw_root.engine_traceback,
-            )
-            if raw_root.is_throw
-            else Return(raw_root.result)
-            for raw_root in raw_roots
-        ]
+        states = [self._state(raw_root) for raw_root in raw_roots]
 
         roots = list(zip(execution_request.roots, states))
 
@@ -508,6 +725,63 @@ class SchedulerSession:
         throws = tuple((root, state) for root, state in roots if isinstance(state, Throw))
         return returns, throws
 
+    @staticmethod
+    def _state(raw_root: Any) -> Return | Throw:
+        if raw_root.is_throw:
+            return Throw(
+                raw_root.result,
+                python_traceback=raw_root.python_traceback,
+                engine_traceback=raw_root.engine_traceback,
+            )
+        return Return(raw_root.result)
+
+    def subscribe(
+        self, requests: Sequence[tuple[type, Any | Params]], debounce: float = 0.1
+    ) -> Subscription:
+        """Subscribe to the values of the given (product, subject) pairs in this SchedulerSession.
+
+        See `next_subscription_results`.
+
+        :param requests: A sequence of product types to request for subjects.
+        :param debounce: A delay (in seconds) to wait after observing that a root has been
+          invalidated, so that a burst of changes is coalesced into a single re-computation.
+        """
+        execution_request = self.execution_request(requests)
+        return Subscription(
+            execution_request.roots,
+            native_engine.scheduler_subscribe(
+                self.py_scheduler, execution_request.native, int(debounce * 1000)
+            ),
+        )
+
+    def next_subscription_results(
+        self, subscription: Subscription, timeout: float | None = None
+    ) -> tuple[tuple[tuple[type, Any | Params], Return | Throw], ...]:
+        """Wait for the roots of the given Subscription to change, and return their new values.
+
+        The first call for a Subscription computes and returns all of its roots. Each subsequent
+        call blocks until at least one root has been invalidated and re-computed to a new value,
+        and then returns only the roots whose values changed.
+
+        :param timeout: An optional timeout to wait for a change (in seconds). If no root has
+          changed before the timeout has elapsed, ExecutionTimeoutError is raised. A timeout of
+          zero does not wait for a change, while None waits indefinitely.
+        """
+        if timeout is not None and timeout < 0:
+            raise ValueError(f"The timeout must not be negative: got {timeout}.")
+        try:
+            raw_roots = native_engine.scheduler_subscription_next(
+                self.py_scheduler,
+                self.py_session,
+                subscription.native,
+                int(timeout * 1000) if timeout is not None else None,
+            )
+        except native_engine.PollTimeout:
+            raise ExecutionTimeoutError("Timed out")
+        return tuple(
+            (subscription.roots[index], self._state(raw_root)) for index, raw_root in raw_roots
+        )
+
     def _raise_on_error(self, throws: list[Throw]) -> NoReturn:
         exception_noun = pluralize(len(throws), "Exception")
         others_msg = f"\n(and {len(throws) - 1} more)\n" if len(throws) > 1 else ""
@@ -622,6 +896,18 @@ class SchedulerSession:
     def ensure_directory_digest_persisted(self, digest: Digest) -> None:
         native_engine.ensure_directory_digest_persisted(self.py_scheduler, digest)
 
+    def export_store_bundle(self, digests: Sequence[Digest | FileDigest], path: str) -> None:
+        """Write a compressed bundle of the given digests (and all of their content) to `path`.
+
+        The bundle may be moved to another machine (or attached to a bug report), and loaded into
+        its store with `import_store_bundle`.
+        """
+        native_engine.export_store_bundle(self.py_scheduler, list(digests), path)
+
+    def import_store_bundle(self, path: str) -> list[Digest | FileDigest]:
+        """Load a bundle written by `export_store_bundle` into the store, and return its roots."""
+        return native_engine.import_store_bundle(self.py_scheduler, path)
+
     def write_digest(
         self, digest: Digest, *, path_prefix: str | None = None, clear_paths: Sequence[str] = ()
     ) -> None:
@@ -635,18 +921,78 @@ class SchedulerSession:
             self.py_scheduler, self.py_session, digest, path_prefix or "", clear_paths
         )
 
+    def export_digest(self, digest: Digest, *, path_prefix: str, mode: str) -> tuple[int, int, int]:
+        """Export a digest to a directory relative to the build root: see `Workspace`."""
+        if PurePath(path_prefix).is_absolute():
+            raise ValueError(
+                f"The `path_prefix` {path_prefix} must be a relative path, as the engine exports "
+                "the digest relative to the build root."
+            )
+        return native_engine.export_digest(
+            self.py_scheduler, self.py_session, digest, path_prefix, mode
+        )
+
+    def serve_digest(self, digest: Digest) -> str:
+        """Serve the contents of the given Digest over HTTP on localhost until this Session ends.
+
+        Files are served at content-addressed URLs beneath the returned URL, and a request for a
+        directory serves its `index.html` (if any).
+
+        :returns: The URL of the root directory of the Digest.
+        """
+        return native_engine.session_serve_digest(self.py_scheduler, self.py_session, digest)
+
     def lease_files_in_graph(self) -> None:
         native_engine.lease_files_in_graph(self.py_scheduler, self.py_session)
 
     def garbage_collect_store(self, target_size_bytes: int) -> None:
         self._scheduler.garbage_collect_store(target_size_bytes)
 
+    def scrub_store(self, sample_fraction: float = 1.0) -> tuple[int, int, int]:
+        return self._scheduler.scrub_store(sample_fraction)
+
     def get_metrics(self) -> dict[str, int]:
         return native_engine.session_get_metrics(self.py_session)
 
     def get_observation_histograms(self) -> dict[str, Any]:
         return native_engine.session_get_observation_histograms(self.py_scheduler, self.py_session)
 
+    def get_build_stats(self) -> dict[str, int | float | None]:
+        """Returns an aggregated summary of cache hit rates, transfer volumes and process counts."""
+        return native_engine.session_get_build_stats(self.py_session)
+
+    def render_build_stats(self) -> str:
+        return native_engine.session_render_build_stats(self.py_session)
+
+    def get_critical_path(self) -> list[dict[str, Any]]:
+        return native_engine.session_get_critical_path(self.py_session)
+
+    def get_running_workunits(self) -> list[dict[str, Any]]:
+        """Returns the visible workunits which are currently running, longest running first.
+
+        Each includes what it is waiting on (if anything): one of `dependencies`, `semaphore`,
+        `gil`, `process_execution`, or `remote_rpc`.
+        """
+        return native_engine.session_get_running_workunits(self.py_session)
+
+    def render_running_workunits(self) -> str:
+        return native_engine.session_render_running_workunits(self.py_session)
+
+    def get_sampled_workunits(self) -> list[dict[str, Any]]:
+        return native_engine.session_get_sampled_workunits(self.py_session)
+
+    def explain_cache_misses(
+        self, before: str | None = None, after: str | None = None
+    ) -> dict[str, Any]:
+        """Explain the cache misses of a recorded run relative to an earlier recorded run.
+
+        Runs are identified by their build ids: if `after` is not given the most recent recorded run
+        is used, and if `before` is not given the run preceding `after` is used. Returns a dict with
+        the compared `before` and `after` build ids, and a list of `misses`, each of which has a
+        `description`, an `action_digest`, and a list of human readable `changes`.
+        """
+        return native_engine.session_explain_cache_misses(self.py_session, before, after)
+
     def record_test_observation(self, value: int) -> None:
         native_engine.session_record_test_observation(self.py_scheduler, self.py_session, value)
 
@@ -657,9 +1003,45 @@ class SchedulerSession:
     def cancel(self) -> None:
         self.py_session.cancel()
 
+    def run_budget_exceeded(self) -> bool:
+        """Return true if the run budget of this Session elapsed while work was in flight.
+
+        When the budget elapses, roots which have not completed fail with `RunBudgetExceeded`,
+        while the results of roots which had already completed are returned as usual.
+        """
+        return self.py_session.run_budget_exceeded()
+
+    def cancel_roots(self, requests: Sequence[tuple[type, Any | Params]]) -> int:
+        """Cancel in-flight executions of the given (product, subject) pairs in this Session.
+
+        Other roots which are executing in this Session are unaffected, and continue to completion.
+        A cancelled root completes with a `RootCancelled` error.
+
+        :returns: The number of in-flight roots which were cancelled.
+        """
+        return native_engine.session_cancel_roots(
+            self.py_scheduler, self.py_session, self.execution_request(requests).native
+        )
+
     def wait_for_tail_tasks(self, timeout: float) -> None:
         native_engine.session_wait_for_tail_tasks(self.py_scheduler, self.py_session, timeout)
 
+    def write_chrome_trace(self) -> None:
+        """If this Session has a `chrome_trace_file`, write its completed workunits to it."""
+        native_engine.session_write_chrome_trace(self.py_session)
+
+
+def _persistent_cache_salt(rule: TaskRule) -> str:
+    """A salt for the persisted results of a rule, which changes when its implementation might.
+
+    The source of the module which defines the rule and the version of Pants are used. The engine
+    additionally salts with the source of the modules of the rules which it transitively depends on
+    in the rule graph.
+    """
+    hasher = hashlib.sha256(VERSION.encode())
+    hasher.update(inspect.getsource(sys.modules[rule.func.__module__]).encode())
+    return hasher.hexdigest()
+
 
 def register_rules(rule_index: RuleIndex, union_membership: UnionMembership) -> PyTasks:
     """Create a native Tasks object loaded with given RuleIndex."""
@@ -675,6 +1057,7 @@ def register_rules(rule_index: RuleIndex, union_membership: UnionMembership) ->
             side_effecting=any(issubclass(t, SideEffecting) for t in rule.parameters.values()),
             engine_aware_return_type=issubclass(rule.output_type, EngineAwareReturnType),
             cacheable=rule.cacheable,
+            persistent_cache_salt=_persistent_cache_salt(rule) if rule.persist else None,
             name=rule.canonical_name,
             desc=rule.desc or "",
             level=rule.level.level,
@@ -2,6 +2,7 @@
 # Licensed under the Apache License, Version 2.0 (see LICENSE).
 
 import re
+import threading
 from abc import ABC, abstractmethod
 from dataclasses import dataclass
 from textwrap import dedent
@@ -9,7 +10,8 @@ from typing import Any
 
 import pytest
 
-from pants.base.exceptions import IncorrectProductError
+from pants.base.exceptions import IncorrectProductError, RootCancelled, RunBudgetExceeded
+from pants.engine.engine_aware import PersistableValue
 from pants.engine.internals.scheduler import ExecutionError
 from pants.engine.rules import Get, MultiGet, implicitly, rule
 from pants.engine.unions import UnionRule, union
@@ -546,3 +548,144 @@ def test_unhashable_types_failure() -> None:
     # Fail if the `input` in a `Get` is not hashable.
     with pytest.raises(ExecutionError, match="unhashable type: 'list'"):
         rule_runner.request(C, [])
+
+
+# -----------------------------------------------------------------------------------------------
+# Test root cancellation
+# -----------------------------------------------------------------------------------------------
+
+
+@dataclass(frozen=True)
+class BlockingRequest:
+    block: bool
+
+
+_blocked = threading.Event()
+_unblock = threading.Event()
+
+
+@rule
+def maybe_block(request: BlockingRequest) -> str:
+    if request.block:
+        _blocked.set()
+        _unblock.wait(timeout=30)
+        return "unblocked"
+    return "done"
+
+
+def test_cancel_roots() -> None:
+    rule_runner = RuleRunner(
+        rules=[maybe_block, QueryRule(str, [BlockingRequest])],
+        inherent_environment=None,
+    )
+    session = rule_runner.scheduler
+    blocking = BlockingRequest(block=True)
+    nonblocking = BlockingRequest(block=False)
+
+    # Roots which are not in flight are not cancelled.
+    assert session.cancel_roots([(str, blocking)]) == 0
+
+    def cancel() -> None:
+        assert _blocked.wait(timeout=30)
+        assert session.cancel_roots([(str, blocking)]) == 1
+
+    canceller = threading.Thread(target=cancel)
+    canceller.start()
+    try:
+        returns, throws = session._execute(
+            session.execution_request([(str, blocking), (str, nonblocking)])
+        )
+    finally:
+        _unblock.set()
+        canceller.join()
+
+    # Only the blocking root was cancelled: the other root ran to completion.
+    assert [state.value for _, state in returns] == ["done"]
+    ((root, throw),) = throws
+    assert root == (str, blocking)
+    assert isinstance(throw.exc, RootCancelled)
+
+
+@dataclass(frozen=True)
+class BudgetedRequest:
+    block: bool
+
+
+_budget_unblock = threading.Event()
+
+
+@rule
+def maybe_block_on_budget(request: BudgetedRequest) -> str:
+    if request.block:
+        _budget_unblock.wait(timeout=30)
+        return "unblocked"
+    return "done"
+
+
+def test_run_budget_exceeded() -> None:
+    rule_runner = RuleRunner(
+        rules=[maybe_block_on_budget, QueryRule(str, [BudgetedRequest])],
+        inherent_environment=None,
+    )
+    session = rule_runner.scheduler.scheduler.new_session(
+        build_id="run_budget_exceeded", run_budget_seconds=0.5
+    )
+    blocking = BudgetedRequest(block=True)
+    nonblocking = BudgetedRequest(block=False)
+    try:
+        returns, throws = session._execute(
+            session.execution_request([(str, blocking), (str, nonblocking)])
+        )
+    finally:
+        _budget_unblock.set()
+
+    # The root which completed within the budget is reported, while the other was cancelled.
+    assert [state.value for _, state in returns] == ["done"]
+    ((root, throw),) = throws
+    assert root == (str, blocking)
+    assert isinstance(throw.exc, RunBudgetExceeded)
+    assert session.run_budget_exceeded()
+
+
+# -----------------------------------------------------------------------------------------------
+# Test persisted rules
+# -----------------------------------------------------------------------------------------------
+
+
+@dataclass(frozen=True)
+class PersistedText(PersistableValue):
+    text: str
+
+    def persisted_form(self):
+        return self.text.encode(), ()
+
+    @classmethod
+    def from_persisted_form(cls, data: bytes) -> "PersistedText":
+        return cls(data.decode())
+
+
+_persisted_rule_runs: list[str] = []
+
+
+@rule(persist=True)
+async def persisted_upper(text: PersistedText) -> PersistedText:
+    _persisted_rule_runs.append(text.text)
+    return PersistedText(text.text.upper())
+
+
+def test_persisted_rule_restored_after_restart(tmp_path) -> None:
+    def run() -> PersistedText:
+        # A new RuleRunner has a new Scheduler (and so an empty graph), as if after a restart.
+        rule_runner = RuleRunner(
+            rules=[persisted_upper, QueryRule(PersistedText, [PersistedText])],
+            bootstrap_args=[f"--local-store-dir={tmp_path}"],
+            inherent_environment=None,
+        )
+        return rule_runner.request(PersistedText, [PersistedText("hello")])
+
+    assert run() == PersistedText("HELLO")
+    assert _persisted_rule_runs == ["hello"]
+
+    # The result is restored from the local cache, rather than by re-running the rule.
+    assert run() == PersistedText("HELLO")
+    assert _persisted_rule_runs == ["hello"]
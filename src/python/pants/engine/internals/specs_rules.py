@@ -28,6 +28,7 @@ from pants.engine.fs import PathGlobs, Paths, SpecsPaths
 from pants.engine.internals.build_files import AddressFamilyDir, BuildFileOptions
 from pants.engine.internals.graph import Owners, OwnersRequest
 from pants.engine.internals.mapper import AddressFamilies, AddressFamily, SpecsFilter
+from pants.engine.internals.native_specs import AddressFamilyDirs, AddressFamilyDirsRequest
 from pants.engine.internals.parametrize import (
     _TargetParametrizations,
     _TargetParametrizationsRequest,
@@ -134,13 +135,25 @@ async def address_families_from_raw_specs_without_file_owners(
 ) -> AddressFamilies:
     if not (specs.dir_literals or specs.dir_globs or specs.recursive_globs or specs.ancestor_globs):
         return AddressFamilies()
-    # Resolve all globs.
-    build_file_globs, validation_globs = specs.to_build_file_path_globs_tuple(
+    # The BUILD files are found natively, and the globs are only used to validate that the
+    # directories exist.
+    _, validation_globs = specs.to_build_file_path_globs_tuple(
         build_patterns=build_file_options.patterns,
         build_ignore_patterns=build_file_options.ignores,
     )
-    build_file_paths, _ = await MultiGet(
-        Get(Paths, PathGlobs, build_file_globs),
+    family_dirs, _ = await MultiGet(
+        Get(
+            AddressFamilyDirs,
+            AddressFamilyDirsRequest(
+                dirs=tuple(
+                    spec.directory
+                    for spec in (*specs.dir_literals, *specs.dir_globs, *specs.ancestor_globs)
+                ),
+                recursive_dirs=tuple(spec.directory for spec in specs.recursive_globs),
+                build_patterns=tuple(build_file_options.patterns),
+                build_ignore_patterns=tuple(build_file_options.ignores),
+            ),
+        ),
         Get(Paths, PathGlobs, validation_globs),
     )
     dirnames = set(
@@ -148,7 +161,7 @@ async def address_families_from_raw_specs_without_file_owners(
             SyntheticTargetsSpecPaths, SyntheticTargetsSpecPathsRequest(tuple(specs.glob_specs()))
         )
     )
-    dirnames.update(os.path.dirname(f) for f in build_file_paths.files)
+    dirnames.update(family_dirs.dirs)
     return AddressFamilies(
         await MultiGet(Get(AddressFamily, AddressFamilyDir(d)) for d in dirnames)
     )
@@ -28,6 +28,7 @@ from pants.base.specs_parser import SpecsParser
 from pants.build_graph.address import Address, ResolveError
 from pants.engine.addresses import Addresses
 from pants.engine.fs import SpecsPaths
+from pants.engine.internals.native_specs import AddressFamilyDirs, AddressFamilyDirsRequest
 from pants.engine.internals.parametrize import Parametrize
 from pants.engine.internals.scheduler import ExecutionError
 from pants.engine.internals.specs_rules import NoApplicableTargetsException
@@ -131,6 +132,7 @@ def rule_runner() -> RuleRunner:
             QueryRule(FilteredTargets, [Addresses]),
             QueryRule(Addresses, [Specs]),
             QueryRule(SpecsPaths, [Specs]),
+            QueryRule(AddressFamilyDirs, [AddressFamilyDirsRequest]),
         ],
         objects={"parametrize": Parametrize},
         target_types=[MockTarget, MockFileTargetGenerator, MockNonfileTargetGenerator],
@@ -262,6 +264,44 @@ def test_raw_specs_without_file_owners_deduplication(rule_runner: RuleRunner) ->
     ]
 
 
+def test_address_family_dirs(rule_runner: RuleRunner) -> None:
+    rule_runner.write_files(
+        {
+            "BUILD": "",
+            "a/BUILD": "",
+            "a/b/BUILD.pants": "",
+            "a/b/c/BUILD": "",
+            "a/b/c/d/BUILD": "",
+            "a/b/ignored/BUILD": "",
+            "a/no_build_file/f.txt": "",
+            "other/BUILD": "",
+        }
+    )
+
+    def family_dirs(dirs: tuple[str, ...], recursive_dirs: tuple[str, ...] = ()) -> tuple[str, ...]:
+        request = AddressFamilyDirsRequest(
+            dirs=dirs,
+            recursive_dirs=recursive_dirs,
+            build_patterns=("BUILD", "BUILD.*"),
+            build_ignore_patterns=("a/b/ignored",),
+        )
+        return rule_runner.request(AddressFamilyDirs, [request]).dirs
+
+    # The BUILD files of ancestors may generate targets into a directory.
+    assert family_dirs(("a/b/c",)) == ("", "a", "a/b", "a/b/c")
+    assert family_dirs(("a/no_build_file", "other")) == ("", "a", "other")
+    assert family_dirs((), recursive_dirs=("a/b",)) == ("", "a", "a/b", "a/b/c", "a/b/c/d")
+    assert family_dirs((), recursive_dirs=("",)) == (
+        "",
+        "a",
+        "a/b",
+        "a/b/c",
+        "a/b/c/d",
+        "other",
+    )
+    assert family_dirs(("does_not_exist",)) == ("",)
+
+
 def test_raw_specs_without_file_owners_filter_by_tag(rule_runner: RuleRunner) -> None:
     rule_runner.set_options(["--tag=+integration"])
     all_integration_tgts = [
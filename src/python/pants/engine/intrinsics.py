@@ -12,20 +12,35 @@ from pants.engine.fs import (
     DigestSubset,
     MergeDigests,
     NativeDownloadFile,
+    PathDigests,
     PathGlobs,
     PathMetadataRequest,
     PathMetadataResult,
     Paths,
+    RelocateDigest,
     RemovePrefix,
     Snapshot,
+    TransformDigest,
 )
 from pants.engine.internals import native_engine
 from pants.engine.internals.docker import DockerResolveImageRequest, DockerResolveImageResult
 from pants.engine.internals.native_dep_inference import (
+    NativeParsedCssDependencies,
+    NativeParsedDependenciesBatch,
+    NativeParsedDockerfileDependencies,
+    NativeParsedGoDependencies,
     NativeParsedJavascriptDependencies,
+    NativeParsedJvmDependencies,
     NativeParsedPythonDependencies,
+    NativeParsedShellDependencies,
 )
-from pants.engine.internals.native_engine import NativeDependenciesRequest
+from pants.engine.internals.native_engine import (
+    AggregateTestResultsRequest,
+    NativeDependenciesBatchRequest,
+    NativeDependenciesRequest,
+)
+from pants.engine.internals.native_specs import AddressFamilyDirs, AddressFamilyDirsRequest
+from pants.engine.internals.native_test_results import TestResultsSummary
 from pants.engine.internals.session import RunId, SessionValues
 from pants.engine.process import (
     FallibleProcessResult,
@@ -33,6 +48,8 @@ from pants.engine.process import (
     InteractiveProcessResult,
     Process,
     ProcessExecutionEnvironment,
+    ShardedProcess,
+    ShardedProcessResult,
 )
 from pants.engine.rules import _uncacheable_rule, collect_rules, rule
 
@@ -58,6 +75,13 @@ async def path_globs_to_paths(
     return await native_engine.path_globs_to_paths(path_globs)
 
 
+@rule
+async def path_globs_to_path_digests(
+    path_globs: PathGlobs,
+) -> PathDigests:
+    return await native_engine.path_globs_to_path_digests(path_globs)
+
+
 @rule
 async def download_file_to_digest(
     native_download_file: NativeDownloadFile,
@@ -104,11 +128,30 @@ async def process_request_to_process_result(
     )
 
 
+@rule
+async def sharded_process_to_sharded_process_result(
+    sharded_process: ShardedProcess, process_execution_environment: ProcessExecutionEnvironment
+) -> ShardedProcessResult:
+    return await native_engine.sharded_process_to_sharded_process_result(
+        sharded_process, process_execution_environment
+    )
+
+
 @rule
 async def digest_subset_to_digest(digest_subset: DigestSubset) -> Digest:
     return await native_engine.digest_subset_to_digest(digest_subset)
 
 
+@rule
+async def relocate_digest_to_digest(relocate_digest: RelocateDigest) -> Digest:
+    return await native_engine.relocate_digest_to_digest(relocate_digest)
+
+
+@rule
+async def transform_digest_to_digest(transform_digest: TransformDigest) -> Digest:
+    return await native_engine.transform_digest_to_digest(transform_digest)
+
+
 @rule
 async def session_values() -> SessionValues:
     return await native_engine.session_values()
@@ -145,6 +188,60 @@ async def parse_javascript_deps(
     return await native_engine.parse_javascript_deps(deps_request)
 
 
+@rule
+async def parse_css_deps(
+    deps_request: NativeDependenciesRequest,
+) -> NativeParsedCssDependencies:
+    return await native_engine.parse_css_deps(deps_request)
+
+
+@rule
+async def parse_go_deps(
+    deps_request: NativeDependenciesRequest,
+) -> NativeParsedGoDependencies:
+    return await native_engine.parse_go_deps(deps_request)
+
+
+@rule
+async def parse_jvm_deps(
+    deps_request: NativeDependenciesRequest,
+) -> NativeParsedJvmDependencies:
+    return await native_engine.parse_jvm_deps(deps_request)
+
+
+@rule
+async def parse_dockerfile_deps(
+    deps_request: NativeDependenciesRequest,
+) -> NativeParsedDockerfileDependencies:
+    return await native_engine.parse_dockerfile_deps(deps_request)
+
+
+@rule
+async def parse_shell_deps(
+    deps_request: NativeDependenciesRequest,
+) -> NativeParsedShellDependencies:
+    return await native_engine.parse_shell_deps(deps_request)
+
+
+@rule
+async def parse_deps_batch(
+    deps_request: NativeDependenciesBatchRequest,
+) -> NativeParsedDependenciesBatch:
+    return await native_engine.parse_deps_batch(deps_request)
+
+
+@rule
+async def aggregate_test_results(
+    request: AggregateTestResultsRequest,
+) -> TestResultsSummary:
+    return await native_engine.aggregate_test_results(request)
+
+
+@rule
+async def address_family_dirs(request: AddressFamilyDirsRequest) -> AddressFamilyDirs:
+    return await native_engine.address_family_dirs(request)
+
+
 @rule
 async def path_metadata_request(request: PathMetadataRequest) -> PathMetadataResult:
     return await native_engine.path_metadata_request(request)
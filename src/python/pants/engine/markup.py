@@ -0,0 +1,137 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+"""Semantic elements for console output, which are rendered appropriately for their destination.
+
+Goals which output tables, links or groups of related output should use these elements (via
+`Console.print_markup`) rather than formatting them by hand, so that the output is readable on a
+terminal, in a plain log file, and in CI.
+"""
+
+from __future__ import annotations
+
+from dataclasses import dataclass
+from enum import Enum
+from typing import Any, Iterable, Mapping, Union
+
+from colors import bold
+
+
+class MarkupStyle(Enum):
+    """How semantic elements are rendered."""
+
+    # A terminal: links are rendered as OSC-8 hyperlinks, and headings are emphasized.
+    TTY = "tty"
+    # A file or pipe: no escape sequences are used.
+    PLAIN = "plain"
+    # GitHub Actions logs: sections are rendered as collapsible groups.
+    GITHUB_ACTIONS = "github_actions"
+
+    @classmethod
+    def detect(cls, env: Mapping[str, str], *, isatty: bool) -> MarkupStyle:
+        """Choose a style for a destination, given the environment of the client."""
+        if env.get("GITHUB_ACTIONS") == "true":
+            return cls.GITHUB_ACTIONS
+        return cls.TTY if isatty else cls.PLAIN
+
+
+@dataclass(frozen=True)
+class Text:
+    text: str
+
+
+@dataclass(frozen=True)
+class Link:
+    text: str
+    url: str
+
+
+@dataclass(frozen=True)
+class Table:
+    headers: tuple[str, ...]
+    rows: tuple[tuple[str, ...], ...]
+
+    def __init__(self, headers: Iterable[str], rows: Iterable[Iterable[str]]) -> None:
+        object.__setattr__(self, "headers", tuple(headers))
+        object.__setattr__(self, "rows", tuple(tuple(row) for row in rows))
+        for row in self.rows:
+            if len(row) != len(self.headers):
+                raise ValueError(
+                    f"Each row of a Table must have {len(self.headers)} cells, but got: {row}"
+                )
+
+
+@dataclass(frozen=True)
+class Section:
+    """A titled group of elements, which may be collapsed where that is supported."""
+
+    title: str
+    body: tuple[Markup, ...]
+
+    def __init__(self, title: str, body: Iterable[Markup]) -> None:
+        object.__setattr__(self, "title", title)
+        object.__setattr__(self, "body", tuple(body))
+
+
+Markup = Union[Text, Link, Table, Section]
+
+
+def _render_link(link: Link, style: MarkupStyle) -> str:
+    if style == MarkupStyle.TTY:
+        return f"\x1b]8;;{link.url}\x1b\\{link.text}\x1b]8;;\x1b\\"
+    return link.url if link.text == link.url else f"{link.text} ({link.url})"
+
+
+def _render_table(table: Table, style: MarkupStyle) -> list[str]:
+    widths = [max(len(cell) for cell in column) for column in zip(table.headers, *table.rows)]
+
+    def line(cells: Iterable[str]) -> str:
+        return "  ".join(cell.ljust(width) for cell, width in zip(cells, widths)).rstrip()
+
+    header = line(table.headers)
+    return [
+        bold(header) if style == MarkupStyle.TTY else header,
+        line("-" * width for width in widths),
+        *(line(row) for row in table.rows),
+    ]
+
+
+def _render_lines(element: Markup, style: MarkupStyle) -> list[str]:
+    if isinstance(element, Text):
+        return element.text.splitlines() or [""]
+    if isinstance(element, Link):
+        return [_render_link(element, style)]
+    if isinstance(element, Table):
+        return _render_table(element, style)
+    if isinstance(element, Section):
+        body = [line for child in element.body for line in _render_lines(child, style)]
+        if style == MarkupStyle.GITHUB_ACTIONS:
+            return [f"::group::{element.title}", *body, "::endgroup::"]
+        title = bold(element.title) if style == MarkupStyle.TTY else element.title
+        return [title, *(f"  {line}" if line else line for line in body)]
+    raise TypeError(f"Expected a Markup element, but got: {element!r}")
+
+
+def render_markup(elements: Iterable[Markup], style: MarkupStyle) -> str:
+    """Render the given elements as text (ending with a newline) in the given style."""
+    return "".join(f"{line}\n" for element in elements for line in _render_lines(element, style))
+
+
+def markup_to_json(element: Markup) -> dict[str, Any]:
+    """Encode the given element as a JSON-compatible dict, for structured consumers."""
+    if isinstance(element, Text):
+        return {"type": "text", "text": element.text}
+    if isinstance(element, Link):
+        return {"type": "link", "text": element.text, "url": element.url}
+    if isinstance(element, Table):
+        return {
+            "type": "table",
+            "headers": list(element.headers),
+            "rows": [list(row) for row in element.rows],
+        }
+    if isinstance(element, Section):
+        return {
+            "type": "section",
+            "title": element.title,
+            "body": [markup_to_json(child) for child in element.body],
+        }
+    raise TypeError(f"Expected a Markup element, but got: {element!r}")
@@ -0,0 +1,76 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+from io import StringIO
+
+import pytest
+
+from pants.engine.console import Console
+from pants.engine.markup import (
+    Link,
+    MarkupStyle,
+    Section,
+    Table,
+    Text,
+    markup_to_json,
+    render_markup,
+)
+
+
+def test_detect() -> None:
+    assert MarkupStyle.detect({}, isatty=True) == MarkupStyle.TTY
+    assert MarkupStyle.detect({}, isatty=False) == MarkupStyle.PLAIN
+    assert MarkupStyle.detect({"GITHUB_ACTIONS": "true"}, isatty=True) == (
+        MarkupStyle.GITHUB_ACTIONS
+    )
+
+
+def test_table() -> None:
+    table = Table(["name", "count"], [["a", "1"], ["longer", "22"]])
+    assert render_markup([table], MarkupStyle.PLAIN) == (
+        "name    count\n------  -----\na       1\nlonger  22\n"
+    )
+
+
+def test_table_rows_must_match_headers() -> None:
+    with pytest.raises(ValueError, match="must have 2 cells"):
+        Table(["name", "count"], [["a"]])
+
+
+def test_link() -> None:
+    link = Link("docs", "https://www.pantsbuild.org")
+    assert render_markup([link], MarkupStyle.PLAIN) == "docs (https://www.pantsbuild.org)\n"
+    assert render_markup([link], MarkupStyle.TTY) == (
+        "\x1b]8;;https://www.pantsbuild.org\x1b\\docs\x1b]8;;\x1b\\\n"
+    )
+    bare = Link("https://www.pantsbuild.org", "https://www.pantsbuild.org")
+    assert render_markup([bare], MarkupStyle.PLAIN) == "https://www.pantsbuild.org\n"
+
+
+def test_section() -> None:
+    section = Section("Results", [Text("line one\n\nline two")])
+    assert render_markup([section], MarkupStyle.PLAIN) == "Results\n  line one\n\n  line two\n"
+    assert render_markup([section], MarkupStyle.GITHUB_ACTIONS) == (
+        "::group::Results\nline one\n\nline two\n::endgroup::\n"
+    )
+
+
+def test_markup_to_json() -> None:
+    section = Section("Results", [Table(["a"], [["1"]]), Link("docs", "https://example.com")])
+    assert markup_to_json(section) == {
+        "type": "section",
+        "title": "Results",
+        "body": [
+            {"type": "table", "headers": ["a"], "rows": [["1"]]},
+            {"type": "link", "text": "docs", "url": "https://example.com"},
+        ],
+    }
+
+
+def test_console_print_markup() -> None:
+    stdout = StringIO()
+    console = Console(stdout=stdout, use_colors=False, markup_style=MarkupStyle.PLAIN)
+    console.print_markup(Text("Summary:"), Table(["a", "b"], [["1", "2"]]))
+    assert stdout.getvalue() == "Summary:\na  b\n-  -\n1  2\n"
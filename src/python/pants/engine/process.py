@@ -35,6 +35,18 @@ class ProcessCacheScope(Enum):
     ALWAYS = "always"
     # Cached in all locations, but only if the process exits successfully.
     SUCCESSFUL = "successful"
+    # Cached in memory and in the local cache, but never read from or written to the remote cache,
+    # regardless of success or failure.
+    LOCAL_ALWAYS = "local_always"
+    # Cached in memory and in the local cache, but never read from or written to the remote cache,
+    # and only if the process exits successfully.
+    LOCAL_SUCCESSFUL = "local_successful"
+    # Cached in all locations, but only written to the local cache (i.e., the remote cache is only
+    # read), regardless of success or failure.
+    REMOTE_READ_ONLY_ALWAYS = "remote_read_only_always"
+    # Cached in all locations, but only written to the local cache (i.e., the remote cache is only
+    # read), and only if the process exits successfully.
+    REMOTE_READ_ONLY_SUCCESSFUL = "remote_read_only_successful"
     # Cached only in memory (i.e. memoized in pantsd), but never persistently, regardless of
     # success vs. failure.
     PER_RESTART_ALWAYS = "per_restart_always"
@@ -46,6 +58,76 @@ class ProcessCacheScope(Enum):
     PER_SESSION = "per_session"
 
 
+class WorkerProtocol(Enum):
+    # Newline-delimited JSON WorkRequests and WorkResponses.
+    JSON = "json"
+    # Length-delimited `blaze.worker.WorkRequest` and `WorkResponse` protobuf messages.
+    PROTO = "proto"
+
+
+@dataclass(frozen=True)
+class PersistentWorker:
+    """Declares that a Process may be run as a request to a long-lived worker process.
+
+    The worker is started with `argv[:request_args_start]` (plus `--persistent_worker`), and each
+    request sends `argv[request_args_start:]`, as with Bazel's persistent workers. Workers are
+    shared between Processes with the same `key`, startup arguments, env and immutable inputs.
+
+    The `argv` of the Process must also be runnable directly: Processes which do not run locally
+    ignore this declaration.
+    """
+
+    key: str
+    request_args_start: int
+    protocol: WorkerProtocol = WorkerProtocol.JSON
+    multiplex: bool = False
+
+
+@dataclass(frozen=True)
+class ProcessRetryPolicy:
+    """Declares that failed attempts to run a Process should be retried, for flaky tools.
+
+    By default, any non-zero exit code is retried. If `exit_codes` is set, only those exit codes
+    are retried, and if `infrastructure_errors_only` is set, only failures to run the process at all
+    (rather than failures of the process itself) are retried. The delay between attempts starts at
+    `backoff_seconds`, and doubles for each subsequent attempt.
+
+    Retries happen beneath the caches, so only the result of the final attempt is cached, and the
+    number of attempts is recorded in `ProcessResultMetadata.attempts`.
+    """
+
+    max_attempts: int
+    exit_codes: tuple[int, ...] | None = None
+    infrastructure_errors_only: bool = False
+    backoff_seconds: float = 0.0
+
+    def __post_init__(self) -> None:
+        if self.max_attempts < 1:
+            raise ValueError(f"max_attempts must be at least 1, but was {self.max_attempts}.")
+
+
+@dataclass(frozen=True)
+class IncrementalOutputs:
+    """Declares output directories of a Process which hold the incremental state of a tool.
+
+    Before the process runs, the `directories` (which must also be `output_directories` of the
+    process) are populated with their content from the previous successful run of a process with
+    the same `key`: for example, the `.tsbuildinfo` of `tsc --incremental`. Processes share state
+    only if they also have the same `working_directory`, `tool_fingerprints` and
+    `immutable_input_digests`, so that state is dropped when the version of a tool changes.
+
+    The previous state is an input of the process, so the process remains sandboxed, but it will
+    usually miss the cache when its state has changed. State is stored only in the local cache.
+    """
+
+    key: str
+    directories: tuple[str, ...]
+
+    def __init__(self, key: str, directories: Iterable[str]) -> None:
+        object.__setattr__(self, "key", key)
+        object.__setattr__(self, "directories", tuple(directories))
+
+
 @dataclass(frozen=True)
 class Process:
     argv: tuple[str, ...]
@@ -56,15 +138,26 @@ class Process:
     use_nailgun: tuple[str, ...]
     working_directory: str | None
     env: FrozenDict[str, str]
+    env_globs: tuple[str, ...]
     append_only_caches: FrozenDict[str, str]
+    append_only_cache_seeds: FrozenDict[str, Digest]
+    incremental_outputs: IncrementalOutputs | None
     output_files: tuple[str, ...]
     output_directories: tuple[str, ...]
+    output_globs: tuple[str, ...]
     timeout_seconds: int | float
     jdk_home: str | None
     execution_slot_variable: str | None
     concurrency_available: int
     cache_scope: ProcessCacheScope
+    tool_fingerprints: FrozenDict[str, str]
     remote_cache_speculation_delay_millis: int
+    remote_execution_priority: int | None
+    remote_results_cache_priority: int | None
+    persistent_worker: PersistentWorker | None
+    cache_validation_argv: tuple[str, ...] | None
+    stdin_digest: FileDigest | None
+    retry_policy: ProcessRetryPolicy | None
     attempt: int
 
     def __init__(
@@ -78,15 +171,26 @@ class Process:
         use_nailgun: Iterable[str] = (),
         working_directory: str | None = None,
         env: Mapping[str, str] | None = None,
+        env_globs: Iterable[str] = (),
         append_only_caches: Mapping[str, str] | None = None,
+        append_only_cache_seeds: Mapping[str, Digest] | None = None,
+        incremental_outputs: IncrementalOutputs | None = None,
         output_files: Iterable[str] | None = None,
         output_directories: Iterable[str] | None = None,
+        output_globs: Iterable[str] | None = None,
         timeout_seconds: int | float | None = None,
         jdk_home: str | None = None,
         execution_slot_variable: str | None = None,
         concurrency_available: int = 0,
         cache_scope: ProcessCacheScope = ProcessCacheScope.SUCCESSFUL,
+        tool_fingerprints: Mapping[str, str] | None = None,
         remote_cache_speculation_delay_millis: int = 0,
+        remote_execution_priority: int | None = None,
+        remote_results_cache_priority: int | None = None,
+        persistent_worker: PersistentWorker | None = None,
+        cache_validation_argv: Iterable[str] | None = None,
+        stdin_digest: FileDigest | None = None,
+        retry_policy: ProcessRetryPolicy | None = None,
         attempt: int = 0,
     ) -> None:
         """Request to run a subprocess, similar to subprocess.Popen.
@@ -105,11 +209,49 @@ class Process:
         you can either set `output_files` or `output_directories`. The specified paths should be
         specified relative to the `working_directory`, if any, and will then be used to populate
         `output_digest` on the `ProcessResult`. If you want to split up this output digest into
-        multiple digests, use `await Get(Digest, DigestSubset)` on the `output_digest`.
+        multiple digests, use `await Get(Digest, DigestSubset)` on the `output_digest`. If the names
+        of the outputs cannot be predicted in advance, set `output_globs` (e.g. `dist/**/*.whl`)
+        rather than capturing an entire directory.
 
         To actually run the process, use `await Get(ProcessResult, Process)` or
         `await Get(FallibleProcessResult, Process)`.
 
+        If `[GLOBAL].process_cache_max_age` is set, cached results which are older than it are
+        revalidated before they are used. If `cache_validation_argv` is set, revalidation runs that
+        (cheap) command against the process's inputs and the cached outputs, and the cached result
+        is only used if the command succeeds.
+
+        By default, the process's stdin is empty. To provide stdin, set `stdin_digest` to the
+        `FileDigest` of a file whose content should be piped to the process (see
+        `Get(DigestEntries, Digest)`).
+
+        When run remotely, `remote_execution_priority` and `remote_results_cache_priority` override
+        `[GLOBAL].remote_execution_priority` and `[GLOBAL].remote_results_cache_priority` for this
+        process.
+
+        To retry a flaky tool, set `retry_policy` (see `ProcessRetryPolicy`).
+
+        To reduce cold-start times on fresh machines, `append_only_cache_seeds` may map the name of
+        any of the `append_only_caches` to a `Digest` (e.g. a prewarmed package manager store) with
+        which the cache is populated if it is empty before its first use. Seeds apply only to local
+        execution, and are not a part of the cache key of the process.
+
+        To allow an incremental tool to reuse its state from a previous run, set
+        `incremental_outputs` (see `IncrementalOutputs`).
+
+        To invalidate cached results when a tool changes in a way which is not visible in the argv,
+        env or inputs of the process (for example, a tool which is installed out of band), set
+        `tool_fingerprints` to a mapping from the name of each tool to its version or content hash.
+        The fingerprints are included in the cache key of the process, so changing one invalidates
+        only the results of processes which declare it.
+
+        To pass through environment variables whose names are not known in advance (such as
+        credentials), set `env_globs` to patterns (e.g. `AWS_*`) matching their names. The patterns
+        are resolved against the environment of Pants when the process runs, and matching variables
+        which are not already set in `env` are added to it. Only the names of the matched variables
+        (not their values) are included in the cache key of the process. `env_globs` are only
+        supported for processes which run in local environments.
+
         Example:
 
             result = await Get(
@@ -130,9 +272,15 @@ class Process:
         object.__setattr__(self, "use_nailgun", tuple(use_nailgun))
         object.__setattr__(self, "working_directory", working_directory)
         object.__setattr__(self, "env", FrozenDict(env or {}))
+        object.__setattr__(self, "env_globs", tuple(env_globs))
         object.__setattr__(self, "append_only_caches", FrozenDict(append_only_caches or {}))
+        object.__setattr__(
+            self, "append_only_cache_seeds", FrozenDict(append_only_cache_seeds or {})
+        )
+        object.__setattr__(self, "incremental_outputs", incremental_outputs)
         object.__setattr__(self, "output_files", tuple(output_files or ()))
         object.__setattr__(self, "output_directories", tuple(output_directories or ()))
+        object.__setattr__(self, "output_globs", tuple(output_globs or ()))
         # NB: A negative or None time value is normalized to -1 to ease the transfer to Rust.
         object.__setattr__(
             self,
@@ -143,9 +291,20 @@ class Process:
         object.__setattr__(self, "execution_slot_variable", execution_slot_variable)
         object.__setattr__(self, "concurrency_available", concurrency_available)
         object.__setattr__(self, "cache_scope", cache_scope)
+        object.__setattr__(self, "tool_fingerprints", FrozenDict(tool_fingerprints or {}))
         object.__setattr__(
             self, "remote_cache_speculation_delay_millis", remote_cache_speculation_delay_millis
         )
+        object.__setattr__(self, "remote_execution_priority", remote_execution_priority)
+        object.__setattr__(self, "remote_results_cache_priority", remote_results_cache_priority)
+        object.__setattr__(self, "persistent_worker", persistent_worker)
+        object.__setattr__(
+            self,
+            "cache_validation_argv",
+            tuple(cache_validation_argv) if cache_validation_argv is not None else None,
+        )
+        object.__setattr__(self, "stdin_digest", stdin_digest)
+        object.__setattr__(self, "retry_policy", retry_policy)
         object.__setattr__(self, "attempt", attempt)
 
 
@@ -204,6 +363,94 @@ class ProcessResultWithRetries:
         return self.results[-1]
 
 
+@dataclass(frozen=True)
+class ProcessShard:
+    """A partition of the inputs of a `ShardedProcess`.
+
+    The `input_digest` is merged into the input digest of the template `Process`, and the `argv`
+    and `env` are added to its argv and environment.
+    """
+
+    input_digest: Digest
+    argv: tuple[str, ...]
+    env: FrozenDict[str, str]
+
+    def __init__(
+        self,
+        input_digest: Digest,
+        argv: Iterable[str] = (),
+        env: Mapping[str, str] | None = None,
+    ) -> None:
+        object.__setattr__(self, "input_digest", input_digest)
+        object.__setattr__(self, "argv", tuple(argv))
+        object.__setattr__(self, "env", FrozenDict(env or {}))
+
+
+@dataclass(frozen=True)
+class ShardedProcess:
+    """Runs a copy of the template `Process` for each of the `shards`, concurrently.
+
+    Each shard is a separate process with its own cache entry, so changing the inputs of one shard
+    does not cause the others to re-run. A shard which fails is re-run (as a new attempt) until it
+    succeeds or has been attempted `attempts` times, in the same way as `ProcessWithRetries`.
+
+    Request a `ShardedProcessResult` to run it.
+    """
+
+    process: Process
+    shards: tuple[ProcessShard, ...]
+    attempts: int
+
+    def __init__(
+        self, process: Process, shards: Iterable[ProcessShard], *, attempts: int = 1
+    ) -> None:
+        if attempts < 1:
+            raise ValueError(f"attempts must be at least 1, but was {attempts}.")
+        object.__setattr__(self, "process", process)
+        object.__setattr__(self, "shards", tuple(shards))
+        object.__setattr__(self, "attempts", attempts)
+
+
+@dataclass(frozen=True)
+class ShardedProcessResult:
+    """The merged result of running a `ShardedProcess`.
+
+    The `output_digest` merges the outputs of the final attempt of each shard, and the `exit_code`
+    is that of the first shard whose final attempt failed, or 0 if every shard succeeded.
+    """
+
+    exit_code: int
+    output_digest: Digest
+    # The results of every attempt of each shard, in the order of `ShardedProcess.shards`.
+    shards: tuple[ProcessResultWithRetries, ...]
+
+    def __init__(
+        self,
+        exit_code: int,
+        output_digest: Digest,
+        shards: Iterable[Iterable[FallibleProcessResult]],
+    ) -> None:
+        object.__setattr__(self, "exit_code", exit_code)
+        object.__setattr__(self, "output_digest", output_digest)
+        object.__setattr__(
+            self, "shards", tuple(ProcessResultWithRetries(tuple(results)) for results in shards)
+        )
+
+    @property
+    def failed_shards(self) -> tuple[int, ...]:
+        """The indexes of the shards whose final attempt failed."""
+        return tuple(i for i, results in enumerate(self.shards) if results.last.exit_code != 0)
+
+    @property
+    def total_elapsed_ms(self) -> int:
+        """The total execution time of every attempt of every shard which reported one."""
+        return sum(
+            result.metadata.total_elapsed_ms or 0
+            for results in self.shards
+            for result in results.results
+        )
+
+
 @dataclass(frozen=True)
 class ProcessResultMetadata:
     """Metadata for a ProcessResult, which is not included in its definition of equality."""
@@ -224,6 +471,14 @@ class ProcessResultMetadata:
     _source: str
     # The run_id in which a ProcessResult was created. See the `self.source` method.
     source_run_id: int
+    # The number of attempts which were made to produce the ProcessResult, including the first. See
+    # `ProcessRetryPolicy`.
+    attempts: int = 1
+    # The path of the sandbox which was preserved after the process ran (see `--keep-sandboxes`),
+    # and a shell command which will re-run the process in it. Only set for the run in which the
+    # process actually executed.
+    sandbox_path: str | None = None
+    repro_command: str | None = None
 
     @property
     def platform(self) -> Platform:
@@ -257,11 +512,15 @@ class ProcessExecutionFailure(Exception):
         process_description: str,
         *,
         keep_sandboxes: KeepSandboxes,
+        sandbox_path: str | None = None,
+        repro_command: str | None = None,
     ) -> None:
         # These are intentionally "public" members.
         self.exit_code = exit_code
         self.stdout = stdout
         self.stderr = stderr
+        self.sandbox_path = sandbox_path
+        self.repro_command = repro_command
 
         def try_decode(content: bytes) -> str:
             try:
@@ -279,7 +538,11 @@ class ProcessExecutionFailure(Exception):
             "stderr:",
             try_decode(stderr),
         ]
-        if keep_sandboxes == KeepSandboxes.never:
+        if sandbox_path is not None:
+            err_strings.append(f"\n\nThe process sandbox was preserved at: {sandbox_path}")
+            if repro_command is not None:
+                err_strings.append(f"To re-run the process, run: {repro_command}")
+        elif keep_sandboxes == KeepSandboxes.never:
             err_strings.append(
                 "\n\nUse `--keep-sandboxes=on_failure` to preserve the process chroot for inspection."
             )
@@ -314,6 +577,8 @@ def fallible_to_exec_result_or_raise(
         fallible_result.stderr,
         description.value,
         keep_sandboxes=keep_sandboxes,
+        sandbox_path=fallible_result.metadata.sandbox_path,
+        repro_command=fallible_result.metadata.repro_command,
     )
 
 
@@ -18,6 +18,8 @@ from pants.engine.fs import (
     DigestEntries,
     Directory,
     FileContent,
+    FileDigest,
+    FileEntry,
     SymlinkEntry,
 )
 from pants.engine.internals.native_engine import Snapshot
@@ -25,11 +27,16 @@ from pants.engine.internals.scheduler import ExecutionError
 from pants.engine.platform import Platform
 from pants.engine.process import (
     FallibleProcessResult,
+    IncrementalOutputs,
     InteractiveProcess,
     InteractiveProcessResult,
     Process,
     ProcessCacheScope,
     ProcessResult,
+    ProcessRetryPolicy,
+    ProcessShard,
+    ShardedProcess,
+    ShardedProcessResult,
 )
 from pants.testutil.rule_runner import QueryRule, RuleRunner, mock_console
 from pants.util.contextutil import environment_as
@@ -40,6 +47,7 @@ def new_rule_runner(**extra_kwargs) -> RuleRunner:
         rules=[
             QueryRule(ProcessResult, [Process]),
             QueryRule(FallibleProcessResult, [Process]),
+            QueryRule(ShardedProcessResult, [ShardedProcess]),
             QueryRule(InteractiveProcessResult, [InteractiveProcess]),
             QueryRule(DigestEntries, [Digest]),
             QueryRule(Platform, []),
@@ -120,6 +128,148 @@ def test_output_digest(rule_runner: RuleRunner, working_directory) -> None:
     assert digest_contents == DigestContents([FileContent("roland", b"European Burmese", False)])
 
 
+def test_output_globs(rule_runner: RuleRunner) -> None:
+    process = Process(
+        argv=(
+            "/bin/bash",
+            "-c",
+            "mkdir -p dist/a && touch dist/a/one.whl dist/two.whl dist/three.tar.gz",
+        ),
+        description="build wheels",
+        output_globs=("dist/**/*.whl",),
+    )
+    result = rule_runner.request(ProcessResult, [process])
+    digest_contents = rule_runner.request(DigestContents, [result.output_digest])
+    assert sorted(fc.path for fc in digest_contents) == ["dist/a/one.whl", "dist/two.whl"]
+
+
+def test_stdin(rule_runner: RuleRunner) -> None:
+    digest = rule_runner.request(
+        Digest, [CreateDigest([FileContent("stdin", b"European Burmese")])]
+    )
+    (entry,) = rule_runner.request(DigestEntries, [digest])
+    assert isinstance(entry, FileEntry)
+
+    def run_process(stdin_digest: FileDigest | None) -> bytes:
+        process = Process(argv=("/bin/cat",), description="cat stdin", stdin_digest=stdin_digest)
+        return rule_runner.request(ProcessResult, [process]).stdout
+
+    assert run_process(entry.file_digest) == b"European Burmese"
+    assert run_process(None) == b""
+
+
+def test_retry_policy(rule_runner: RuleRunner, tmp_path: Path) -> None:
+    # The process fails on its first attempt, and succeeds on its second.
+    attempts_file = tmp_path / "attempts"
+    process = Process(
+        argv=(
+            "/bin/bash",
+            "-c",
+            f"echo >> {attempts_file}; [ $(wc -l < {attempts_file}) -ge 2 ]",
+        ),
+        description="flaky",
+        retry_policy=ProcessRetryPolicy(max_attempts=3),
+    )
+    result = rule_runner.request(ProcessResult, [process])
+    assert result.metadata.attempts == 2
+    assert len(attempts_file.read_text().splitlines()) == 2
+
+
+def test_sharded_process(rule_runner: RuleRunner, tmp_path: Path) -> None:
+    names = ("one", "two", "three")
+    shard_inputs = [
+        rule_runner.request(Digest, [CreateDigest([FileContent(f"in/{name}", name.encode())])])
+        for name in names
+    ]
+    # Each shard copies its inputs to its outputs, and the `three` shard fails the first two times
+    # that it runs.
+    attempts_file = tmp_path / "attempts"
+    process = Process(
+        argv=(
+            "/bin/bash",
+            "-c",
+            'mkdir out && cp in/* out/ && echo -n "$SUFFIX" > "out/$1.suffix" && '
+            f'{{ [ "$1" != three ] || {{ echo >> {attempts_file}; '
+            f"[ $(wc -l < {attempts_file}) -ge 3 ]; }}; }}",
+            "--",
+        ),
+        description="copy",
+        output_directories=("out",),
+    )
+    shards = [
+        ProcessShard(digest, argv=[name], env={"SUFFIX": name[0]})
+        for digest, name in zip(shard_inputs, names)
+    ]
+
+    result = rule_runner.request(ShardedProcessResult, [ShardedProcess(process, shards)])
+    assert result.exit_code == 1
+    assert result.failed_shards == (2,)
+    assert [len(shard.results) for shard in result.shards] == [1, 1, 1]
+
+    # Failed attempts are not cached, so the first attempt of the `three` shard runs (and fails)
+    # again, and then its second attempt succeeds.
+    result = rule_runner.request(
+        ShardedProcessResult, [ShardedProcess(process, shards, attempts=2)]
+    )
+    assert result.exit_code == 0
+    assert result.failed_shards == ()
+    assert [len(shard.results) for shard in result.shards] == [1, 1, 2]
+    digest_contents = rule_runner.request(DigestContents, [result.output_digest])
+    assert {fc.path: fc.content for fc in digest_contents} == {
+        "out/one": b"one",
+        "out/one.suffix": b"o",
+        "out/three": b"three",
+        "out/three.suffix": b"t",
+        "out/two": b"two",
+        "out/two.suffix": b"t",
+    }
+
+    with pytest.raises(ValueError, match="attempts must be at least 1"):
+        ShardedProcess(process, shards, attempts=0)
+
+
+def test_incremental_outputs(rule_runner: RuleRunner) -> None:
+    def run_process(run: str, tool_version: str = "1") -> list[str]:
+        process = Process(
+            argv=("/bin/bash", "-c", "mkdir -p state && echo $RUN >> state/runs"),
+            env={"RUN": run},
+            description="incremental",
+            output_directories=("state",),
+            incremental_outputs=IncrementalOutputs("test-tool", ["state"]),
+            tool_fingerprints={"tool": tool_version},
+        )
+        result = rule_runner.request(ProcessResult, [process])
+        (runs,) = rule_runner.request(DigestContents, [result.output_digest])
+        return runs.content.decode().splitlines()
+
+    assert run_process("a") == ["a"]
+    # The state of the previous run is an input of the next.
+    assert run_process("b") == ["a", "b"]
+    # But it is dropped when the version of the tool changes.
+    assert run_process("c", tool_version="2") == ["c"]
+    assert run_process("d", tool_version="2") == ["c", "d"]
+
+    process = Process(
+        argv=("/bin/true",),
+        description="undeclared",
+        incremental_outputs=IncrementalOutputs("test-tool", ["state"]),
+    )
+    with pytest.raises(ExecutionError, match="must also be one of the `output_directories`"):
+        rule_runner.request(ProcessResult, [process])
+
+
+def test_append_only_cache_seeds_must_be_declared(rule_runner: RuleRunner) -> None:
+    seed = rule_runner.request(Digest, [CreateDigest([FileContent("seeded", b"")])])
+    process = Process(
+        argv=("/bin/true",),
+        append_only_caches={"declared": ".cache/declared"},
+        append_only_cache_seeds={"undeclared": seed},
+        description="seeded",
+    )
+    with pytest.raises(ExecutionError, match="Cannot seed the append-only cache `undeclared`"):
+        rule_runner.request(ProcessResult, [process])
+
+
 def test_timeout(rule_runner: RuleRunner) -> None:
     process = Process(
         argv=("/bin/bash", "-c", "/bin/sleep 0.5; /bin/echo -n 'European Burmese'"),
@@ -142,6 +292,25 @@ def test_failing_process(rule_runner: RuleRunner) -> None:
     assert "Process 'failure' failed with exit code 1." in str(exc.value)
 
 
+def test_failing_process_retained_sandbox() -> None:
+    rule_runner = new_rule_runner(bootstrap_args=["--keep-sandboxes=on_failure"])
+    process = Process(argv=("/bin/bash", "-c", "exit 1"), description="failure")
+    result = rule_runner.request(FallibleProcessResult, [process])
+    assert result.metadata.sandbox_path is not None
+    assert result.metadata.repro_command == f"{result.metadata.sandbox_path}/__run.sh"
+    assert Path(result.metadata.sandbox_path, "__run.sh").is_file()
+
+    with pytest.raises(ExecutionError) as exc:
+        rule_runner.request(ProcessResult, [process])
+    assert f"The process sandbox was preserved at: {result.metadata.sandbox_path}" in str(
+        exc.value
+    )
+
+    # Successful processes do not retain their sandboxes.
+    success = Process(argv=("/bin/bash", "-c", "exit 0"), description="success")
+    assert rule_runner.request(FallibleProcessResult, [success]).metadata.sandbox_path is None
+
+
 def test_cache_scope_always(rule_runner: RuleRunner) -> None:
     # Should not re-run on failure, even in a new Session.
     process = Process(
@@ -186,6 +355,23 @@ def test_cache_scope_successful(rule_runner: RuleRunner) -> None:
     assert result_four != result_five
 
 
+def test_tool_fingerprints(rule_runner: RuleRunner) -> None:
+    def run(**tool_fingerprints: str) -> FallibleProcessResult:
+        process = Process(
+            argv=("/bin/bash", "-c", "echo $RANDOM"),
+            tool_fingerprints=tool_fingerprints,
+            description="fingerprinted",
+        )
+        return rule_runner.request(FallibleProcessResult, [process])
+
+    result_one = run(tool="1.0")
+    rule_runner.new_session("session one")
+    rule_runner.set_options([])
+    # The same fingerprint hits the cache, but a changed fingerprint re-runs the process.
+    assert run(tool="1.0") is result_one
+    assert run(tool="1.1").stdout != result_one.stdout
+
+
 def test_cache_scope_per_restart() -> None:
     success_argv = ("/bin/bash", "-c", "echo $RANDOM")
     failure_argv = ("/bin/bash", "-c", "echo $RANDOM; exit 1")
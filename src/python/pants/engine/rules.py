@@ -29,7 +29,7 @@ from typing import (
 
 from typing_extensions import ParamSpec
 
-from pants.engine.engine_aware import SideEffecting
+from pants.engine.engine_aware import PersistableValue, SideEffecting
 from pants.engine.goal import Goal
 from pants.engine.internals.rule_visitor import collect_awaitables
 from pants.engine.internals.selectors import AwaitableConstraints, Call
@@ -84,6 +84,7 @@ def _make_rule(
     masked_types: Iterable[Type],
     *,
     cacheable: bool,
+    persist: bool,
     canonical_name: str,
     desc: Optional[str],
     level: LogLevel,
@@ -96,6 +97,8 @@ def _make_rule(
                             the decorated function.
     :param cacheable: Whether the results of executing the Rule should be cached as keyed by all of
                       its inputs.
+    :param persist: Whether the results of executing the Rule should additionally be persisted in
+                    the local cache, so that they survive restarts.
     """
 
     is_goal_cls = issubclass(return_type, Goal)
@@ -113,6 +116,8 @@ def _make_rule(
         awaitables = FrozenOrderedSet(collect_awaitables(original_func))
 
         validate_requirements(func_id, parameter_types, awaitables, cacheable)
+        if persist:
+            validate_persistable(func_id, return_type, parameter_types, cacheable)
 
         # Set our own custom `__line_number__` dunder so that the engine may visualize the line number.
         original_func.__line_number__ = original_func.__code__.co_firstlineno
@@ -133,6 +138,7 @@ def _make_rule(
             desc=desc,
             level=level,
             cacheable=cacheable,
+            persist=persist,
         )
 
         return func
@@ -179,7 +185,13 @@ def _ensure_type_annotation(
     return type_annotation
 
 
-PUBLIC_RULE_DECORATOR_ARGUMENTS = {"canonical_name", "canonical_name_suffix", "desc", "level"}
+PUBLIC_RULE_DECORATOR_ARGUMENTS = {
+    "canonical_name",
+    "canonical_name_suffix",
+    "desc",
+    "level",
+    "persist",
+}
 # We aren't sure if these'll stick around or be removed at some point, so they are "private"
 # and should only be used in Pants' codebase.
 PRIVATE_RULE_DECORATOR_ARGUMENTS = {
@@ -303,6 +315,7 @@ def rule_decorator(func: SyncRuleT | AsyncRuleT, **kwargs) -> AsyncRuleT:
         parameter_types,
         masked_types,
         cacheable=cacheable,
+        persist=kwargs.get("persist", False),
         canonical_name=effective_name,
         desc=effective_desc,
         level=effective_level,
@@ -344,6 +357,26 @@ def validate_requirements(
             )
 
 
+def validate_persistable(
+    func_id: str,
+    return_type: Type,
+    parameter_types: dict[str, Type],
+    cacheable: bool,
+) -> None:
+    if not cacheable:
+        raise ValueError(f"Only a `@rule` ({func_id}) may be persisted.")
+    for ty in (return_type, *parameter_types.values()):
+        if not issubclass(ty, PersistableValue):
+            raise ValueError(
+                softwrap(
+                    f"""
+                    A persisted `@rule` ({func_id}) must return and consume only
+                    `PersistableValue`s, but `{ty.__name__}` is not one.
+                    """
+                )
+            )
+
+
 def inner_rule(*args, **kwargs) -> AsyncRuleT | RuleDecorator:
     if len(args) == 1 and inspect.isfunction(args[0]):
         return rule_decorator(*args, **kwargs)
@@ -469,6 +502,7 @@ class TaskRule:
     desc: Optional[str] = None
     level: LogLevel = LogLevel.TRACE
     cacheable: bool = True
+    persist: bool = False
 
     def __str__(self):
         return "(name={}, {}, {!r}, {}, gets={})".format(
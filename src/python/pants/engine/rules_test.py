@@ -11,6 +11,7 @@ from typing import Callable, List, Optional, Tuple, Type, Union, get_type_hints
 import pytest
 
 from pants.engine.console import Console
+from pants.engine.engine_aware import PersistableValue
 from pants.engine.goal import Goal, GoalSubsystem
 from pants.engine.internals.engine_testutil import assert_equal_with_printing
 from pants.engine.internals.native_engine import PyExecutor
@@ -299,6 +300,33 @@ class TestRule:
         )
         assert "pants.engine.console.Console" in error_str
 
+    def test_persisted_rule_types(self) -> None:
+        class Persistable(PersistableValue):
+            def persisted_form(self):
+                return b"", ()
+
+            @classmethod
+            def from_persisted_form(cls, data: bytes) -> Persistable:
+                return cls()
+
+        @rule(persist=True)
+        def valid_rule(p: Persistable) -> Persistable:
+            return p
+
+        assert valid_rule.rule.persist  # type: ignore[attr-defined]
+
+        with pytest.raises(ValueError, match="`A` is not one"):
+
+            @rule(persist=True)
+            def invalid_param(a: A) -> Persistable:
+                return Persistable()
+
+        with pytest.raises(ValueError, match="`bool` is not one"):
+
+            @rule(persist=True)
+            def invalid_return(p: Persistable) -> bool:
+                return False
+
 
 def test_rule_index_creation_fails_with_bad_declaration_type():
     with pytest.raises(TypeError) as exc:
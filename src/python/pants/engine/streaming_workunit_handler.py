@@ -9,13 +9,14 @@ from abc import ABC, abstractmethod
 from dataclasses import dataclass
 from typing import Any, Callable, Iterable, Sequence, Tuple
 
+from pants.base.deprecated import warn_or_error
 from pants.base.specs import Specs
 from pants.core.util_rules.environments import determine_bootstrap_environment
 from pants.engine.addresses import Addresses
 from pants.engine.environment import EnvironmentName
 from pants.engine.fs import Digest, DigestContents, FileDigest, Snapshot
 from pants.engine.internals.native_engine import PyThreadLocals
-from pants.engine.internals.scheduler import SchedulerSession, Workunit
+from pants.engine.internals.scheduler import PolledWorkunits, SchedulerSession, Workunit
 from pants.engine.internals.selectors import Params
 from pants.engine.rules import Get, MultiGet, QueryRule, collect_rules, rule
 from pants.engine.target import Targets
@@ -101,6 +102,26 @@ class StreamingWorkunitContext:
         """
         return self._scheduler.get_observation_histograms()
 
+    def get_critical_path(self) -> list[dict[str, Any]]:
+        """Return the critical path of the workunits which have completed so far in this run.
+
+        The critical path is the longest chain of blocking work: each entry describes one workunit
+        on the chain (outermost first), including its `description` and the `self_duration_secs`
+        and `self_duration_nanos` which are attributable to it rather than to the next entry.
+
+        Empty unless `[GLOBAL].record_critical_path` is enabled.
+        """
+        return self._scheduler.get_critical_path()
+
+    def get_sampled_workunits(self) -> list[dict[str, Any]]:
+        """Return the aggregate counts of workunits which were not reported individually.
+
+        When `--streaming-workunits-sampling-threshold` is set, workunits with a single name beyond
+        the threshold are sampled. Each entry describes the workunits with one `name` which were
+        sampled out: their `count`, and their total `duration_secs` and `duration_nanos`.
+        """
+        return self._scheduler.get_sampled_workunits()
+
     def get_expanded_specs(self) -> ExpandedSpecs:
         """Return a dict containing the canonicalized addresses of the specs for this run, and what
         files they expand to."""
@@ -222,7 +243,7 @@ async def construct_workunits_callback_factories(
 
 
 class StreamingWorkunitHandler:
-    """Periodically calls each registered WorkunitsCallback in a dedicated thread.
+    """Calls each registered WorkunitsCallback in a dedicated thread as workunits are recorded.
 
     This class should be used as a context manager.
     """
@@ -234,10 +255,21 @@ class StreamingWorkunitHandler:
         callbacks: Iterable[WorkunitsCallback],
         options_bootstrapper: OptionsBootstrapper,
         specs: Specs,
-        report_interval_seconds: float,
         allow_async_completion: bool,
         max_workunit_verbosity: LogLevel,
+        report_interval_seconds: float | None = None,
     ) -> None:
+        if report_interval_seconds is not None:
+            warn_or_error(
+                removal_version="2.25.0.dev0",
+                entity="the `report_interval_seconds` argument to `StreamingWorkunitHandler`",
+                hint=softwrap(
+                    """
+                    Workunits are now reported to callbacks as soon as they are recorded, so the
+                    argument has no effect.
+                    """
+                ),
+            )
         scheduler = scheduler.isolated_shallow_clone("streaming_workunit_handler_session")
         self.callbacks = callbacks
         self.context = StreamingWorkunitContext(
@@ -251,7 +283,6 @@ class StreamingWorkunitHandler:
                 scheduler=scheduler,
                 context=self.context,
                 callbacks=self.callbacks,
-                report_interval=report_interval_seconds,
                 # TODO(10092) The max verbosity should be a per-client setting, rather than a global
                 #  setting.
                 max_workunit_verbosity=max_workunit_verbosity,
@@ -280,17 +311,17 @@ class _InnerHandler(threading.Thread):
         scheduler: Any,
         context: StreamingWorkunitContext,
         callbacks: Iterable[WorkunitsCallback],
-        report_interval: float,
         max_workunit_verbosity: LogLevel,
         allow_async_completion: bool,
     ) -> None:
         super().__init__(daemon=True)
         self.scheduler = scheduler
         self.context = context
-        self.stop_request = threading.Event()
-        self.report_interval = report_interval
         self.callbacks = callbacks
         self.max_workunit_verbosity = max_workunit_verbosity
+        self.stream = scheduler.stream_workunits(
+            max_workunit_verbosity, lambda workunits: self.report(workunits, finished=False)
+        )
         # TODO: Have a thread per callback so that some callbacks can always finish async even
         #  if others must be finished synchronously.
         self.block_until_complete = not allow_async_completion or any(
@@ -300,8 +331,7 @@ class _InnerHandler(threading.Thread):
         # as we are only in the constructor.
         self.thread_locals = PyThreadLocals.get_for_current_thread()
 
-    def poll_workunits(self, *, finished: bool) -> None:
-        workunits = self.scheduler.poll_workunits(self.max_workunit_verbosity)
+    def report(self, workunits: PolledWorkunits, *, finished: bool) -> None:
         for callback in self.callbacks:
             callback(
                 started_workunits=workunits["started"],
@@ -314,16 +344,15 @@ class _InnerHandler(threading.Thread):
         # First, set the thread's thread locals to the parent thread's in order to propagate the
         # console, workunit stores, etc.
         self.thread_locals.set_for_current_thread()
-        while not self.stop_request.is_set():
-            self.poll_workunits(finished=False)
-            self.stop_request.wait(timeout=self.report_interval)
-        else:
-            # Make one final call. Note that this may run after the Pants run has already
-            # completed, depending on whether the thread was joined or not.
-            self.poll_workunits(finished=True)
+        # Report batches of workunits as they are recorded, until the stream is closed by `end`.
+        self.stream.run()
+        # Make one final call with any workunits which the stream did not deliver. Note that this
+        # may run after the Pants run has already completed, depending on whether the thread was
+        # joined or not.
+        self.report(self.scheduler.poll_workunits(self.max_workunit_verbosity), finished=True)
 
     def end(self) -> None:
-        self.stop_request.set()
+        self.stream.close()
         if self.block_until_complete:
             logger.debug(
                 "Async completion is disabled: waiting for workunit callbacks to complete..."
@@ -0,0 +1,222 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+import json
+import logging
+import re
+import time
+import uuid
+from dataclasses import dataclass
+
+from pants.base.build_environment import get_buildroot
+from pants.engine.internals.native_engine import PyBuildEventPublisher
+from pants.engine.internals.scheduler import Workunit
+from pants.engine.rules import collect_rules, rule
+from pants.engine.streaming_workunit_handler import (
+    StreamingWorkunitContext,
+    WorkunitsCallback,
+    WorkunitsCallbackFactory,
+    WorkunitsCallbackFactoryRequest,
+)
+from pants.engine.unions import UnionRule
+from pants.option.global_options import GlobalOptions
+from pants.option.option_types import DictOption, StrListOption, StrOption
+from pants.option.subsystem import Subsystem
+from pants.util.strutil import softwrap
+from pants.version import VERSION
+
+logger = logging.getLogger(__name__)
+
+
+class BuildEventProtocolSubsystem(Subsystem):
+    options_scope = "bep"
+    help = softwrap(
+        """
+        Streams the events of each run to a Build Event Service using the Build Event Protocol
+        (BEP), so that runs can be displayed by services which consume it.
+        """
+    )
+
+    address = StrOption(
+        default=None,
+        help=softwrap(
+            """
+            The address of a Build Event Service to publish events to, e.g.
+            `grpcs://remote.example.com`. Publishing is disabled unless this is set.
+
+            The CA certificates in `[GLOBAL].remote_ca_certs_path` are used for TLS connections.
+            """
+        ),
+    )
+    headers = DictOption[str](
+        advanced=True,
+        help="Headers to set on requests to the Build Event Service, e.g. for authentication.",
+    )
+    project_id = StrOption(
+        default="",
+        advanced=True,
+        help="The project id to associate builds with, for services which require one.",
+    )
+    keywords = StrListOption(
+        advanced=True,
+        help="Keywords to attach to the notification of each new build event stream.",
+    )
+
+
+def _start_secs(workunit: Workunit) -> float:
+    return float(workunit["start_secs"]) + workunit["start_nanos"] / 1e9
+
+
+def _duration_secs(workunit: Workunit) -> float:
+    return float(workunit.get("duration_secs", 0)) + workunit.get("duration_nanos", 0) / 1e9
+
+
+class BuildEventProtocolCallback(WorkunitsCallback):
+    def __init__(
+        self,
+        *,
+        address: str,
+        headers: dict[str, str],
+        project_id: str,
+        keywords: list[str],
+        root_ca_certs_path: str | None,
+    ) -> None:
+        super().__init__()
+        self.address = address
+        self.headers = headers
+        self.project_id = project_id
+        self.keywords = keywords
+        self.root_ca_certs_path = root_ca_certs_path
+        self.invocation_id = str(uuid.uuid4())
+        self.publisher: PyBuildEventPublisher | None = None
+        self.failed = False
+        # The labels of targets whose tests failed.
+        self.failed_labels: set[str] = set()
+
+    @property
+    def can_finish_async(self) -> bool:
+        return True
+
+    def _start(self, context: StreamingWorkunitContext) -> PyBuildEventPublisher | None:
+        if self.publisher is None and not self.failed:
+            try:
+                self.publisher = PyBuildEventPublisher(
+                    context._scheduler.scheduler.py_executor,
+                    self.address,
+                    self.headers,
+                    context.run_tracker.run_id,
+                    self.invocation_id,
+                    self.project_id,
+                    self.keywords,
+                    self.root_ca_certs_path,
+                )
+            except Exception as e:
+                logger.warning(f"Failed to connect to the Build Event Service: {e}")
+                self.failed = True
+                return None
+            run_information = context.run_tracker.run_information()
+            self.publisher.started(
+                self.invocation_id,
+                " ".join(context.run_tracker.goals),
+                VERSION,
+                get_buildroot(),
+                float(run_information.get("timestamp", time.time())),
+            )
+        return self.publisher
+
+    def _publish_workunit(self, publisher: PyBuildEventPublisher, workunit: Workunit) -> None:
+        metadata = workunit.get("metadata", {})
+        artifacts = workunit.get("artifacts", {})
+        if "definition" in metadata:
+            definition = json.loads(metadata["definition"])
+            start = _start_secs(workunit)
+            publisher.action_executed(
+                workunit["span_id"],
+                None,
+                definition["description"],
+                definition["argv"],
+                metadata["exit_code"],
+                artifacts.get("stdout_digest"),
+                artifacts.get("stderr_digest"),
+                start,
+                start + _duration_secs(workunit),
+            )
+        elif "exit_code" in metadata and "addresses" in metadata:
+            # A `TestResult`.
+            exit_code = metadata["exit_code"]
+            for label in metadata["addresses"]:
+                if exit_code not in (None, 0):
+                    self.failed_labels.add(label)
+                publisher.test_result(
+                    label,
+                    exit_code,
+                    False,
+                    _start_secs(workunit),
+                    _duration_secs(workunit),
+                    artifacts.get("stdout"),
+                    artifacts.get("stderr"),
+                )
+
+    def __call__(
+        self,
+        *,
+        started_workunits: tuple[Workunit, ...],
+        completed_workunits: tuple[Workunit, ...],
+        finished: bool,
+        context: StreamingWorkunitContext,
+    ) -> None:
+        publisher = self._start(context)
+        if publisher is None:
+            return
+
+        for workunit in completed_workunits:
+            self._publish_workunit(publisher, workunit)
+
+        if not finished:
+            return
+
+        for label in context.get_expanded_specs().targets:
+            publisher.target_configured(label, "", [])
+            publisher.target_completed(label, label not in self.failed_labels, [])
+
+        outcome = context.run_tracker.run_information().get("outcome")
+        try:
+            publisher.finished(0 if outcome == "SUCCESS" else 1, time.time())
+        except Exception as e:
+            logger.warning(f"Failed to publish build events: {e}")
+
+
+@dataclass(frozen=True)
+class BuildEventProtocolCallbackFactoryRequest:
+    """A unique request type that is installed to trigger construction of the WorkunitsCallback."""
+
+
+@rule
+def construct_build_event_protocol_callback(
+    _: BuildEventProtocolCallbackFactoryRequest,
+    subsystem: BuildEventProtocolSubsystem,
+    global_options: GlobalOptions,
+) -> WorkunitsCallbackFactory:
+    if not subsystem.address:
+        return WorkunitsCallbackFactory(lambda: None)
+
+    # NB: Tonic expects the schemes `http` and `https`, even though they are gRPC requests.
+    address = re.sub(r"^grpc", "http", subsystem.address)
+    return WorkunitsCallbackFactory(
+        lambda: BuildEventProtocolCallback(
+            address=address,
+            headers=subsystem.headers,
+            project_id=subsystem.project_id,
+            keywords=list(subsystem.keywords),
+            root_ca_certs_path=global_options.remote_ca_certs_path,
+        )
+    )
+
+
+def rules():
+    return [
+        UnionRule(WorkunitsCallbackFactoryRequest, BuildEventProtocolCallbackFactoryRequest),
+        *collect_rules(),
+    ]
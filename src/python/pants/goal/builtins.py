@@ -7,6 +7,7 @@ from pants.bsp.goal import BSPGoal
 from pants.build_graph.build_configuration import BuildConfiguration
 from pants.goal import help
 from pants.goal.builtin_goal import BuiltinGoal
+from pants.goal.cache_miss_debug import CacheMissDebugBuiltinGoal
 from pants.goal.completion import CompletionBuiltinGoal
 from pants.goal.explorer import ExplorerBuiltinGoal
 from pants.goal.migrate_call_by_name import MigrateCallByNameBuiltinGoal
@@ -19,6 +20,7 @@ def register_builtin_goals(build_configuration: BuildConfiguration.Builder) -> N
 def builtin_goals() -> tuple[type[BuiltinGoal], ...]:
     return (
         BSPGoal,
+        CacheMissDebugBuiltinGoal,
         CompletionBuiltinGoal,
         ExplorerBuiltinGoal,
         MigrateCallByNameBuiltinGoal,
@@ -0,0 +1,68 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+import logging
+
+from pants.base.exiter import PANTS_FAILED_EXIT_CODE, PANTS_SUCCEEDED_EXIT_CODE, ExitCode
+from pants.base.specs import Specs
+from pants.build_graph.build_configuration import BuildConfiguration
+from pants.engine.unions import UnionMembership
+from pants.goal.builtin_goal import BuiltinGoal
+from pants.init.engine_initializer import GraphSession
+from pants.option.option_types import StrOption
+from pants.option.options import Options
+from pants.util.strutil import softwrap
+
+logger = logging.getLogger(__name__)
+
+
+class CacheMissDebugBuiltinGoal(BuiltinGoal):
+    name = "cache-miss-debug"
+    help = softwrap(
+        """
+        Explain why processes missed the cache in a run, relative to an earlier run.
+
+        Runs are only recorded while `[GLOBAL].record_cache_misses` is enabled. By default, the most
+        recently recorded run is compared to the run before it. For each process which missed the
+        cache, the input files, environment variables and argv elements which changed are listed.
+        """
+    )
+
+    before = StrOption(
+        default=None,
+        help="The build id of the run to compare against. Defaults to the run before `--after`.",
+    )
+    after = StrOption(
+        default=None,
+        help="The build id of the run whose cache misses to explain. Defaults to the latest run.",
+    )
+
+    def run(
+        self,
+        *,
+        build_config: BuildConfiguration,
+        graph_session: GraphSession,
+        options: Options,
+        specs: Specs,
+        union_membership: UnionMembership,
+    ) -> ExitCode:
+        try:
+            explanation = graph_session.scheduler_session.explain_cache_misses(
+                self.before, self.after
+            )
+        except Exception as e:
+            logger.error(str(e))
+            return PANTS_FAILED_EXIT_CODE
+
+        misses = explanation["misses"]
+        print(
+            f"{len(misses)} cache miss(es) in run `{explanation['after']}`, relative to run "
+            f"`{explanation['before']}`:"
+        )
+        for miss in misses:
+            print(f"\n{miss['description']} (action {miss['action_digest']}):")
+            for change in miss["changes"]:
+                print(f"  - {change}")
+        return PANTS_SUCCEEDED_EXIT_CODE
@@ -6,20 +6,26 @@
 
 from __future__ import annotations
 
+import inspect
 import logging
 from enum import Enum
+from typing import Any
 
 from pants.base.exiter import PANTS_SUCCEEDED_EXIT_CODE, ExitCode
 from pants.base.specs import Specs
 from pants.build_graph.build_configuration import BuildConfiguration
+from pants.engine.internals import native_engine
 from pants.engine.unions import UnionMembership
 from pants.goal.builtin_goal import BuiltinGoal
+from pants.help.help_info_extracter import HelpInfoExtracter
 from pants.init.engine_initializer import GraphSession
-from pants.option.option_types import EnumOption
+from pants.option.option_types import BoolOption, EnumOption
+from pants.option.option_util import is_dict_option, is_list_option
 from pants.option.options import Options
+from pants.option.parser import Parser
 from pants.option.scope import GLOBAL_SCOPE
 from pants.util.resources import read_resource
-from pants.util.strutil import softwrap
+from pants.util.strutil import softwrap, strval
 
 _COMPLETIONS_PACKAGE = "pants.goal"
 
@@ -29,6 +35,7 @@ logger = logging.getLogger(__name__)
 class Shell(Enum):
     BASH = "bash"
     ZSH = "zsh"
+    FISH = "fish"
 
 
 class CompletionBuiltinGoal(BuiltinGoal):
@@ -48,6 +55,10 @@ class CompletionBuiltinGoal(BuiltinGoal):
         An example of this usage is in the bash completion script, where we use the following command:
         `pants complete -- ${COMP_WORDS[@]}`. This will generate the completion options for the
         current args, and then pass them to the bash completion script.
+
+        With `--static`, the generated script instead embeds the goals and options of this repo, so
+        that completing them does not run Pants at all (the fish script is always static). The
+        script should then be regenerated when backends or plugins change.
         """
     )
 
@@ -55,6 +66,25 @@ class CompletionBuiltinGoal(BuiltinGoal):
         default=Shell.BASH,
         help="Which shell completion type should be printed to stdout.",
     )
+    static = BoolOption(
+        default=False,
+        help=softwrap(
+            """
+            Generate a completion script which embeds the goals and options of this repo, rather
+            than one which calls `pants complete` on each completion.
+            """
+        ),
+    )
+    schema = BoolOption(
+        default=False,
+        help=softwrap(
+            """
+            Print the schema of the registered options (their scopes, names, types, choices and
+            defaults) as JSON, rather than a completion script. This can be used to build custom
+            completion scripts.
+            """
+        ),
+    )
 
     def run(
         self,
@@ -83,19 +113,29 @@ class CompletionBuiltinGoal(BuiltinGoal):
                 print("\n".join(completion_options))
             return PANTS_SUCCEEDED_EXIT_CODE
 
-        script = self._generate_completion_script(self.shell)
+        if self.schema:
+            print(self._option_schema_json(options))
+            return PANTS_SUCCEEDED_EXIT_CODE
+
+        script = self._generate_completion_script(self.shell, options)
         print(script)
         return PANTS_SUCCEEDED_EXIT_CODE
 
-    def _generate_completion_script(self, shell: Shell) -> str:
+    def _generate_completion_script(self, shell: Shell, options: Options) -> str:
         """Generate a completion script for the specified shell.
 
-        Implementation note: In practice, we're just going to read in
-        and return the contents of the appropriate static completion script file.
+        Implementation note: Unless a static script was requested, we're just going to read in
+        and return the contents of the appropriate completion script file, which calls back into
+        this goal for the completion options.
 
         :param shell: The shell to generate a completion script for.
+        :param options: The options object for the current Pants run.
         :return: The completion script for the specified shell.
         """
+        if self.static or shell == Shell.FISH:
+            return native_engine.generate_completion_script(
+                shell.value, self._option_schema_json(options)
+            )
         if shell == Shell.ZSH:
             return read_resource(_COMPLETIONS_PACKAGE, "pants-completion.zsh").decode("utf-8")
         else:
@@ -182,3 +222,54 @@ class CompletionBuiltinGoal(BuiltinGoal):
             # options.for_scope will throw if the goal is unknown, so we'll just return an empty list
             # Since this is used for user-entered tab completion, it's not a warning or error
             return []
+
+    def _option_schema_json(self, options: Options) -> str:
+        """Render the schema of all registered options as JSON.
+
+        :param options: The options object for the current Pants run.
+        :return: The JSON rendering of the scopes and their options.
+        """
+        scopes = []
+        for scope, scope_info in sorted(options.known_scope_to_info.items()):
+            scope_options = []
+            for args, kwargs in options.get_parser(scope).option_registrations_iter():
+                flags = [arg for arg in args if arg.startswith("--")]
+                if not flags:
+                    continue
+                scope_options.append(
+                    (
+                        flags[0][2:],
+                        self._option_type_name(kwargs),
+                        list(HelpInfoExtracter.compute_choices(kwargs) or ()),
+                        self._option_default(kwargs["default"].value),
+                        strval(kwargs.get("help", "")),
+                    )
+                )
+            scopes.append(
+                (scope, scope_info.is_goal, strval(scope_info.description or ""), scope_options)
+            )
+        return native_engine.render_option_schema(scopes)
+
+    @staticmethod
+    def _option_type_name(kwargs: dict[str, Any]) -> str:
+        if Parser.is_bool(kwargs):
+            return "bool"
+        if is_list_option(kwargs):
+            return "list"
+        if is_dict_option(kwargs):
+            return "dict"
+        typ = kwargs.get("type", str)
+        if inspect.isclass(typ) and issubclass(typ, Enum):
+            return "str"
+        return getattr(typ, "__name__", "str")
+
+    @classmethod
+    def _option_default(cls, value: Any) -> Any:
+        """Convert enum members (possibly nested in collections) to their values."""
+        if isinstance(value, Enum):
+            return value.value
+        if isinstance(value, (list, tuple)):
+            return [cls._option_default(v) for v in value]
+        if isinstance(value, dict):
+            return {k: cls._option_default(v) for k, v in value.items()}
+        return value
@@ -1,6 +1,8 @@
 # Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
 # Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+import json
+
 from pants.goal.completion import CompletionBuiltinGoal
 from pants.option.option_value_container import OptionValueContainer
 from pants.testutil.pants_integration_test import PantsResult, run_pants
@@ -41,6 +43,10 @@ def test_completion_script_generation():
     zsh_result.assert_success()
     assert "compdef" in zsh_result.stdout
 
+    fish_result = run_pants(["complete", "--shell=fish"])
+    fish_result.assert_success()
+    assert "complete -c pants -f -a 'fmt'" in fish_result.stdout
+
     other_result = run_pants(["complete", "--shell=gibberish"])
     other_result.assert_failure()
 
@@ -90,3 +96,32 @@ def test_completions_with_options_on_invalid_goal():
     result = run_pants_complete(["unknown-goal", "-"])
     result.assert_success()
     assert result.stdout == ""
+
+
+def test_static_completion_script_generation():
+    bash_result = run_pants(["complete", "--shell=bash", "--static"])
+    bash_result.assert_success()
+    assert "pants complete" not in bash_result.stdout
+    goals_line = next(
+        line for line in bash_result.stdout.splitlines() if line.startswith("_pants_goals=")
+    )
+    assert all(goal in goals_line.split('"')[1].split() for goal in ("check", "fmt", "help"))
+
+    zsh_result = run_pants(["complete", "--shell=zsh", "--static"])
+    zsh_result.assert_success()
+    assert "pants complete" not in zsh_result.stdout
+    assert "compadd -a _pants_goals" in zsh_result.stdout
+
+
+def test_option_schema():
+    result = run_pants(["complete", "--schema"])
+    result.assert_success()
+    scopes = {scope["scope"]: scope for scope in json.loads(result.stdout)["scopes"]}
+
+    global_options = {option["name"]: option for option in scopes[""]["options"]}
+    assert global_options["colors"]["type"] == "bool"
+    assert global_options["level"]["choices"] == ["trace", "debug", "info", "warn", "error"]
+    assert global_options["level"]["default"] == "info"
+
+    assert scopes["fmt"]["is_goal"]
+    assert "only" in {option["name"] for option in scopes["fmt"]["options"]}
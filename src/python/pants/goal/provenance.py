@@ -0,0 +1,197 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+import hashlib
+import json
+import logging
+from dataclasses import dataclass
+from pathlib import Path
+from typing import Any, TypedDict
+
+from pants.engine.internals.scheduler import Workunit
+from pants.engine.rules import collect_rules, rule
+from pants.engine.streaming_workunit_handler import (
+    StreamingWorkunitContext,
+    WorkunitsCallback,
+    WorkunitsCallbackFactory,
+    WorkunitsCallbackFactoryRequest,
+)
+from pants.engine.unions import UnionRule
+from pants.option.option_types import BoolOption, StrOption
+from pants.option.subsystem import Subsystem
+from pants.util.dirutil import safe_open
+from pants.util.strutil import softwrap
+
+logger = logging.getLogger(__name__)
+
+PROVENANCE_MANIFEST_FILENAME = "provenance.json"
+
+
+class DigestObject(TypedDict):
+    fingerprint: str
+    size_bytes: int
+
+
+class ProcessProvenanceObject(TypedDict, total=False):
+    description: str
+    argv: list[str]
+    env_sha256: str
+    input_digest: DigestObject
+    output_digest: DigestObject
+    stdout_digest: DigestObject
+    stderr_digest: DigestObject
+    exit_code: int
+    source: str
+    environment_type: str
+    environment_name: str
+    start_secs: float
+    duration_secs: float
+    total_elapsed_ms: int
+    saved_by_cache_ms: int
+
+
+class ProvenanceManifestObject(TypedDict):
+    run_id: str
+    command: str
+    processes: list[ProcessProvenanceObject]
+
+
+class ProvenanceSubsystem(Subsystem):
+    options_scope = "provenance"
+    help = softwrap(
+        """
+        Records a manifest of every process which was executed (or hit in a cache) during a run,
+        for use in auditing and supply-chain provenance.
+        """
+    )
+
+    enabled = BoolOption(
+        default=False,
+        help=softwrap(
+            f"""
+            At the end of each run, write a JSON manifest of all of the processes which were
+            executed or hit in a cache: their argv, a hash of their environment, their input and
+            output digests, where their result came from, and their timing.
+
+            Unless `[{options_scope}].output_file` is set, the manifest is written to
+            `{PROVENANCE_MANIFEST_FILENAME}` in the run's directory under `[GLOBAL].pants_workdir`.
+            """
+        ),
+    )
+    output_file = StrOption(
+        default=None,
+        metavar="<path>",
+        advanced=True,
+        help="Write the provenance manifest to this file rather than to the run's directory.",
+    )
+
+
+def _env_sha256(env: dict[str, str]) -> str:
+    return hashlib.sha256(json.dumps(env, sort_keys=True).encode()).hexdigest()
+
+
+def _file_digest_object(file_digest: Any) -> DigestObject:
+    return {
+        "fingerprint": file_digest.fingerprint,
+        "size_bytes": file_digest.serialized_bytes_length,
+    }
+
+
+def process_provenance(workunit: Workunit) -> ProcessProvenanceObject | None:
+    """Extract the provenance of a process from its completed workunit, if it represents one."""
+    metadata = workunit.get("metadata", {})
+    if "definition" not in metadata:
+        return None
+
+    definition = json.loads(metadata["definition"])
+    process: ProcessProvenanceObject = {
+        "description": definition["description"],
+        "argv": definition["argv"],
+        "env_sha256": _env_sha256(definition["env"]),
+        "input_digest": definition["input_digests"]["complete"]["digest"],
+        "output_digest": {
+            "fingerprint": metadata["output_digest_fingerprint"],
+            "size_bytes": metadata["output_digest_size_bytes"],
+        },
+        "exit_code": metadata["exit_code"],
+        "source": metadata["source"],
+        "environment_type": metadata["environment_type"],
+    }
+
+    artifacts = workunit.get("artifacts", {})
+    for key in ("stdout_digest", "stderr_digest"):
+        if key in artifacts:
+            process[key] = _file_digest_object(artifacts[key])  # type: ignore[literal-required]
+    for key in ("environment_name", "total_elapsed_ms", "saved_by_cache_ms"):
+        if key in metadata:
+            process[key] = metadata[key]  # type: ignore[literal-required]
+    if "start_secs" in workunit:
+        process["start_secs"] = workunit["start_secs"] + workunit["start_nanos"] / 1e9
+    if "duration_secs" in workunit:
+        process["duration_secs"] = workunit["duration_secs"] + workunit["duration_nanos"] / 1e9
+    return process
+
+
+class ProvenanceCallback(WorkunitsCallback):
+    def __init__(self, output_file: str | None) -> None:
+        super().__init__()
+        self.output_file = output_file
+        self.processes: list[ProcessProvenanceObject] = []
+
+    @property
+    def can_finish_async(self) -> bool:
+        return True
+
+    def __call__(
+        self,
+        *,
+        started_workunits: tuple[Workunit, ...],
+        completed_workunits: tuple[Workunit, ...],
+        finished: bool,
+        context: StreamingWorkunitContext,
+    ) -> None:
+        for workunit in completed_workunits:
+            process = process_provenance(workunit)
+            if process is not None:
+                self.processes.append(process)
+
+        if not finished:
+            return
+
+        run_tracker = context.run_tracker
+        output_file = (
+            Path(self.output_file)
+            if self.output_file
+            else run_tracker.run_logs_file.parent / PROVENANCE_MANIFEST_FILENAME
+        )
+        manifest: ProvenanceManifestObject = {
+            "run_id": run_tracker.run_id,
+            "command": run_tracker.run_information().get("cmd_line", ""),
+            "processes": sorted(self.processes, key=lambda p: p.get("start_secs", 0)),
+        }
+        with safe_open(output_file, "w") as fh:
+            json.dump(manifest, fh, indent=2)
+        logger.debug(f"Wrote provenance manifest to {output_file}")
+
+
+@dataclass(frozen=True)
+class ProvenanceCallbackFactoryRequest:
+    """A unique request type that is installed to trigger construction of the WorkunitsCallback."""
+
+
+@rule
+def construct_provenance_callback(
+    _: ProvenanceCallbackFactoryRequest, subsystem: ProvenanceSubsystem
+) -> WorkunitsCallbackFactory:
+    return WorkunitsCallbackFactory(
+        lambda: ProvenanceCallback(subsystem.output_file) if subsystem.enabled else None
+    )
+
+
+def rules():
+    return [
+        UnionRule(WorkunitsCallbackFactoryRequest, ProvenanceCallbackFactoryRequest),
+        *collect_rules(),
+    ]
@@ -0,0 +1,61 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+import json
+
+from pants.engine.internals.native_engine import FileDigest
+from pants.goal.provenance import process_provenance
+
+
+def test_process_provenance() -> None:
+    definition = {
+        "argv": ["/bin/echo", "hello"],
+        "env": {"B": "2", "A": "1"},
+        "description": "Say hello",
+        "input_digests": {
+            "complete": {"digest": {"fingerprint": "ab" * 32, "size_bytes": 80}},
+        },
+    }
+    workunit = {
+        "name": "process",
+        "start_secs": 10,
+        "start_nanos": 500_000_000,
+        "duration_secs": 1,
+        "duration_nanos": 0,
+        "metadata": {
+            "definition": json.dumps(definition),
+            "source": "HitLocally",
+            "exit_code": 0,
+            "environment_type": "local",
+            "output_digest_fingerprint": "cd" * 32,
+            "output_digest_size_bytes": 77,
+            "saved_by_cache_ms": 900,
+        },
+        "artifacts": {"stdout_digest": FileDigest("ef" * 32, 6)},
+    }
+
+    process = process_provenance(workunit)
+    assert process is not None
+    assert process["argv"] == ["/bin/echo", "hello"]
+    assert process["description"] == "Say hello"
+    assert process["input_digest"] == {"fingerprint": "ab" * 32, "size_bytes": 80}
+    assert process["output_digest"] == {"fingerprint": "cd" * 32, "size_bytes": 77}
+    assert process["stdout_digest"] == {"fingerprint": "ef" * 32, "size_bytes": 6}
+    assert "stderr_digest" not in process
+    assert process["source"] == "HitLocally"
+    assert process["saved_by_cache_ms"] == 900
+    assert process["start_secs"] == 10.5
+    assert process["duration_secs"] == 1.0
+
+    # The environment hash is independent of the order of the variables.
+    definition["env"] = {"A": "1", "B": "2"}
+    workunit["metadata"]["definition"] = json.dumps(definition)  # type: ignore[index]
+    reordered = process_provenance(workunit)
+    assert reordered is not None
+    assert reordered["env_sha256"] == process["env_sha256"]
+
+
+def test_non_process_workunits_are_ignored() -> None:
+    assert process_provenance({"name": "some_rule", "metadata": {}}) is None
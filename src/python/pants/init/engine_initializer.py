@@ -35,6 +35,7 @@ from pants.engine.internals.parser import Parser
 from pants.engine.internals.scheduler import Scheduler, SchedulerSession
 from pants.engine.internals.selectors import Params
 from pants.engine.internals.session import SessionValues
+from pants.engine.markup import MarkupStyle
 from pants.engine.rules import QueryRule, collect_rules, rule
 from pants.engine.streaming_workunit_handler import rules as streaming_workunit_handler_rules
 from pants.engine.target import RegisteredTargetTypes
@@ -76,6 +77,14 @@ class GraphScheduler:
         max_workunit_level: LogLevel = LogLevel.DEBUG,
         session_values: SessionValues | None = None,
         cancellation_latch: PySessionCancellationLatch | None = None,
+        chrome_trace_file: str | None = None,
+        record_critical_path: bool = False,
+        stream_process_output: bool = False,
+        run_budget_seconds: float | None = None,
+        workunit_sampling_threshold: int | None = None,
+        workunit_sampling_interval: int = 0,
+        cache_miss_records_dir: str | None = None,
+        markup_style: MarkupStyle | None = None,
     ) -> GraphSession:
         session = self.scheduler.new_session(
             build_id,
@@ -84,8 +93,19 @@ class GraphScheduler:
             max_workunit_level=max_workunit_level,
             session_values=session_values,
             cancellation_latch=cancellation_latch,
+            chrome_trace_file=chrome_trace_file,
+            record_critical_path=record_critical_path,
+            stream_process_output=stream_process_output,
+            run_budget_seconds=run_budget_seconds,
+            workunit_sampling_threshold=workunit_sampling_threshold,
+            workunit_sampling_interval=workunit_sampling_interval,
+            cache_miss_records_dir=cache_miss_records_dir,
+        )
+        console = Console(
+            use_colors=use_colors,
+            session=session if dynamic_ui else None,
+            markup_style=markup_style,
         )
-        console = Console(use_colors=use_colors, session=session if dynamic_ui else None)
         return GraphSession(session, console, self.goal_map)
 
 
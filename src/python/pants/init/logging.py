@@ -4,6 +4,7 @@
 from __future__ import annotations
 
 import http.client
+import json
 import locale
 import logging
 import sys
@@ -11,7 +12,7 @@ from contextlib import contextmanager
 from io import BufferedReader, TextIOWrapper
 from logging import Formatter, Handler, LogRecord
 from pathlib import PurePath
-from typing import Iterator
+from typing import Any, Iterator
 
 import pants.util.logging as pants_logging
 from pants.engine.internals import native_engine
@@ -66,7 +67,12 @@ class _ExceptionFormatter(Formatter):
 
 
 @contextmanager
-def stdio_destination(stdin_fileno: int, stdout_fileno: int, stderr_fileno: int) -> Iterator[None]:
+def stdio_destination(
+    stdin_fileno: int,
+    stdout_fileno: int,
+    stderr_fileno: int,
+    events_fileno: int | None = None,
+) -> Iterator[None]:
     """Sets a destination for both logging and stdio: must be called after `initialize_stdio`.
 
     After `initialize_stdio` and outside of this contextmanager, the default stdio destination is
@@ -74,11 +80,15 @@ def stdio_destination(stdin_fileno: int, stdout_fileno: int, stderr_fileno: int)
     thread/task-local state that directs their IO to the given destination. When the contextmanager
     exits all tasks will be restored to the default destination (regardless of whether they have
     completed).
+
+    If an `events_fileno` is given, events sent via `stdio_destination_emit_event` are written to
+    it while the destination is active.
     """
     if not logging.getLogger(None).handlers:
         raise AssertionError("stdio_destination should only be called after initialize_stdio.")
 
     native_engine.stdio_thread_console_set(stdin_fileno, stdout_fileno, stderr_fileno)
+    native_engine.stdio_thread_console_events_set(events_fileno)
     try:
         yield
     finally:
@@ -98,6 +108,39 @@ def stdio_destination_use_color(use_color: bool) -> None:
     native_engine.stdio_thread_console_color_mode_set(use_color)
 
 
+def stdio_destination_log_levels_by_target(global_options: OptionValueContainer) -> None:
+    """Sets log levels by target for the current thread's destination.
+
+    These take precedence over the levels that logging was initialized with (which for pantsd are
+    those of the run that started the daemon), but only for logging which occurs while the
+    destination is active. Targets match as prefixes: `process_execution` will also match
+    `process_execution::remote`.
+    """
+    log_levels_by_target = _get_log_levels_by_target(global_options)
+    native_engine.stdio_thread_console_log_filters_set(
+        {k: v.level for k, v in log_levels_by_target.items()}
+    )
+    # Python loggers are shared by all destinations, and so have the levels that logging was
+    # initialized with. Lower them where necessary so that records reach the Rust logger, which
+    # applies the per-destination levels (and the initial levels for other destinations).
+    for target, level in log_levels_by_target.items():
+        logger = logging.getLogger(target)
+        if not logger.isEnabledFor(level.level):
+            level.set_level_for(logger)
+
+
+def stdio_destination_emit_event(event_type: str, **fields: Any) -> None:
+    """Sends a structured event to the client of the current thread's destination.
+
+    Events are encoded as a single line of JSON, with the given `event_type` as the value of the
+    `type` field. They are ignored if the client did not request events (which is always the case
+    for runs without pantsd).
+    """
+    native_engine.stdio_thread_console_event_write(
+        json.dumps({"type": event_type, **fields}, sort_keys=True)
+    )
+
+
 @contextmanager
 def _python_logging_setup(
     level: LogLevel, log_levels_by_target: dict[str, LogLevel], *, print_stacktrace: bool
@@ -170,6 +213,8 @@ def initialize_stdio(global_bootstrap_options: OptionValueContainer) -> Iterator
         global_bootstrap_options.print_stacktrace,
         global_bootstrap_options.ignore_warnings,
         global_bootstrap_options.pants_workdir,
+        json_per_run_logs=global_bootstrap_options.log_json,
+        json_log_fd=global_bootstrap_options.log_json_fd,
     ):
         yield
 
@@ -183,6 +228,8 @@ def initialize_stdio_raw(
     print_stacktrace: bool,
     ignore_warnings: list[str],
     pants_workdir: str,
+    json_per_run_logs: bool = False,
+    json_log_fd: int | None = None,
 ) -> Iterator[None]:
     literal_filters = []
     regex_filters = []
@@ -207,6 +254,8 @@ def initialize_stdio_raw(
             tuple(literal_filters),
             tuple(regex_filters),
             log_path,
+            json_per_run_logs,
+            json_log_fd,
         )
         sys.stdin = TextIOWrapper(
             BufferedReader(raw_stdin),
@@ -1,7 +1,12 @@
 # Copyright 2021 Pants project contributors (see CONTRIBUTORS.md).
 # Licensed under the Apache License, Version 2.0 (see LICENSE).
 
-from pants.testutil.pants_integration_test import run_pants, setup_tmpdir
+from pants.testutil.pants_integration_test import (
+    run_pants,
+    run_pants_with_workdir,
+    setup_tmpdir,
+    temporary_workdir,
+)
 
 PLUGIN = """
 import logging
@@ -83,3 +88,30 @@ def test_log_by_level() -> None:
     # properly.
     assert "[DEBUG] (workunit_store) Starting: `logger` goal" in result.stderr
     assert "[DEBUG] (workunit_store) Completed: `logger` goal" in result.stderr
+
+
+def test_log_by_level_with_pantsd() -> None:
+    """Check that log levels by target apply to a run of a pantsd which was started without them."""
+    with setup_tmpdir(
+        {"plugins/logger.py": PLUGIN, "plugins/register.py": REGISTER}
+    ) as tmpdir, temporary_workdir() as workdir:
+        args = [
+            f"--pythonpath={tmpdir}",
+            "--backend-packages=plugins",
+            "--no-dynamic-ui",
+            "--show-log-target",
+            "--level=warn",
+        ]
+        run_pants_with_workdir([*args, "logger"], workdir=workdir).assert_success()
+        result = run_pants_with_workdir(
+            [
+                *args,
+                "--log-levels-by-target={'plugins.logger.debugOverride': 'debug'}",
+                "logger",
+            ],
+            workdir=workdir,
+        )
+        result.assert_success()
+
+    assert "[DEBUG] (plugins.logger.debugOverride) debug log" in result.stderr
+    assert "[INFO] (plugins.logger.globalLevel) info log" not in result.stderr
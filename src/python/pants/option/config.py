@@ -5,7 +5,6 @@ from __future__ import annotations
 
 import logging
 import os
-import re
 from dataclasses import dataclass
 from types import SimpleNamespace
 from typing import Any, Dict, Iterable, List, Mapping, Protocol, Union, cast
@@ -13,7 +12,8 @@ from typing import Any, Dict, Iterable, List, Mapping, Protocol, Union, cast
 import toml
 
 from pants.base.build_environment import get_buildroot
-from pants.option.errors import ConfigError, ConfigValidationError, InterpolationMissingOptionError
+from pants.engine.internals.native_engine import interpolate_config_value
+from pants.option.errors import ConfigError, ConfigValidationError, InterpolationError
 from pants.option.ranked_value import Value
 from pants.util.osutil import getuser
 from pants.util.strutil import softwrap
@@ -50,7 +50,7 @@ class Config:
     config files.
 
     Supports variable substitution using old-style Python format strings. E.g., %(var_name)s will be
-    replaced with the value of var_name.
+    replaced with the value of var_name, and ${env.VAR_NAME} with the environment variable VAR_NAME.
     """
 
     values: tuple[_ConfigValues, ...]
@@ -120,8 +120,8 @@ class Config:
 
         This sets up those defaults and checks if the user overrode any of the values.
 
-        In addition, we pre-populate any supplied env entries to allow %(env.[env-var-name])s
-        interpolation.
+        In addition, we pre-populate any supplied env entries to allow %(env.[env-var-name])s (or
+        ${env.[env-var-name]}) interpolation.
         """
         safe_seed_values = seed_values or {}
         buildroot = cast(str, safe_seed_values.get("buildroot", get_buildroot()))
@@ -172,6 +172,17 @@ _TomlPrimitive = Union[bool, int, float, str]
 _TomlValue = Union[_TomlPrimitive, List[_TomlPrimitive], Dict[str, _TomlPrimitive]]
 
 
+def _interpolation_map(values: Mapping[str, Any]) -> dict[str, str]:
+    """The values which may be interpolated: strings, integers, and env vars as `env.NAME`."""
+    replacements = {}
+    for key, value in values.items():
+        if isinstance(value, SimpleNamespace):
+            replacements.update((f"{key}.{name}", str(v)) for name, v in vars(value).items())
+        elif isinstance(value, str) or (isinstance(value, int) and not isinstance(value, bool)):
+            replacements[key] = str(value)
+    return replacements
+
+
 @dataclass(frozen=True)
 class _ConfigValues:
     """The parsed contents of a TOML config file."""
@@ -192,37 +203,16 @@ class _ConfigValues:
         section: str,
         section_values: dict,
     ) -> str:
-        """For any values with %(foo)s, substitute it with the corresponding value from DEFAULT or
-        the same section."""
-
-        def format_str(value: str) -> str:
-            # Escape embedded { and } characters, so that .format() does not act on them.
-            escaped_str = value.replace("{", "{{").replace("}", "}}")
-            new_style_format_str = re.sub(
-                pattern=r"%\((?P<interpolated>[a-zA-Z_0-9.]+)\)s",
-                repl=r"{\g<interpolated>}",
-                string=escaped_str,
-            )
-            try:
-                possible_interpolations = {**self.seed_values, **section_values}
-                return new_style_format_str.format(**possible_interpolations)
-            except KeyError as e:
-                bad_reference = e.args[0]
-                raise InterpolationMissingOptionError(
-                    option,
-                    section,
-                    raw_value,
-                    bad_reference,
-                )
-
-        def recursively_format_str(value: str) -> str:
-            # It's possible to interpolate with a value that itself has an interpolation.
-            match = re.search(r"%\(([a-zA-Z_0-9.]+)\)s", value)
-            if not match:
-                return value
-            return recursively_format_str(value=format_str(value))
+        """For any values with %(foo)s or ${env.FOO}, substitute it with the corresponding value
+        from DEFAULT, the same section, or the environment.
 
-        return recursively_format_str(raw_value)
+        The substitution is shared with the Rust options parser, so that the two can't disagree.
+        """
+        replacements = _interpolation_map({**self.seed_values, **section_values})
+        try:
+            return interpolate_config_value(raw_value, replacements)
+        except ValueError as e:
+            raise InterpolationError(option, section, raw_value, e)
 
     def get_value(self, section: str, option: str) -> str | None:
         section_values = self.section_to_values.get(section)
@@ -11,6 +11,7 @@ import pytest
 
 from pants.engine.fs import FileContent
 from pants.option.config import Config, TomlSerializer
+from pants.option.errors import InterpolationError
 
 
 @dataclass(frozen=True)
@@ -286,6 +287,30 @@ def test_merged_config() -> None:
     _compare(config, _expected_combined_values)
 
 
+def test_interpolation() -> None:
+    content = dedent(
+        """
+        [a]
+        env_var = "${env.NAME}/%(env.NAME)s"
+        not_env_var = "${NAME}"
+        cycle = "x %(other)s"
+        other = "%(cycle)s"
+        unknown = "%(unknown)s"
+        """
+    )
+    config = Config.load(
+        file_contents=[FileContent("file.toml", content.encode())],
+        seed_values=_seed_values,
+        env=_env,
+    )
+    assert config.get("a", "env_var") == ["foo/foo"]
+    assert config.get("a", "not_env_var") == ["${NAME}"]
+    with pytest.raises(InterpolationError, match="`cycle` -> `other` -> `cycle`"):
+        config.get("a", "cycle")
+    with pytest.raises(InterpolationError, match="Unknown value for placeholder `unknown`"):
+        config.get("a", "unknown")
+
+
 def test_toml_serializer() -> None:
     original_values: Dict = {
         "GLOBAL": {
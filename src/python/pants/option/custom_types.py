@@ -333,12 +333,14 @@ class DictValueComponent:
 
     One or more instances of this class can be merged to form a dict value.
 
-    Each component may either replace or extend the preceding component.  So that, e.g., a config
-    file can extend the default value of a dict, instead of having to repeat it.
+    Each component may either replace or extend the preceding component, or remove keys from it.
+    So that, e.g., a config file can extend the default value of a dict, instead of having to repeat
+    it.
     """
 
     REPLACE = "REPLACE"
     EXTEND = "EXTEND"
+    REMOVE = "REMOVE"
 
     @classmethod
     def merge(cls, components: Iterable[DictValueComponent]) -> DictValueComponent:
@@ -356,6 +358,10 @@ class DictValueComponent:
                 action = cls.REPLACE
             elif component.action is cls.EXTEND:
                 val.update(component.val)
+            elif component.action is cls.REMOVE:
+                # NB: A removal only applies to the keys of the components preceding it.
+                for key in component.val:
+                    val.pop(key, None)
             else:
                 raise ParseError(f"Unknown action for dict value: {component.action}")
         return cls(action, val)
@@ -369,7 +375,8 @@ class DictValueComponent:
         """Interpret value as either a dict or something to extend another dict with.
 
         :param value: The value to convert.  Can be an instance of DictValueComponent, a dict,
-                      or a string representation (possibly prefixed by +) of a dict.
+                      a string representation (possibly prefixed by +) of a dict, or a string
+                      representation prefixed by - of a list of keys to remove.
         """
         if isinstance(value, bytes):
             value = value.decode()
@@ -385,6 +392,9 @@ class DictValueComponent:
         elif value.startswith("+{"):
             action = cls.EXTEND
             val = _convert(value[1:], dict)
+        elif value.startswith(("-[", "-(")):
+            action = cls.REMOVE
+            val = dict.fromkeys(_convert(value[1:], (list, tuple)))
         else:
             raise ParseError(f"Invalid dict value: {value}")
         return cls(action, dict(val))
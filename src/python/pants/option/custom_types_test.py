@@ -97,6 +97,14 @@ class TestCustomTypes:
         assert_dict_error("1")
         assert_dict_error('"a"')
 
+    def test_dict_merge(self) -> None:
+        merged = DictValueComponent.merge(
+            DictValueComponent.create(s)
+            for s in ("{'a': 1, 'b': 2}", "+{'c': 3}", "-['a', 'd']", "-('c',)")
+        )
+        assert merged.action == DictValueComponent.REPLACE
+        assert merged.val == {"b": 2}
+
     def test_list(self) -> None:
         self.assert_list_parsed("[]", expected=[])
         self.assert_list_parsed("[1, 2, 3]", expected=[1, 2, 3])
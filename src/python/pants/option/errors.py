@@ -133,14 +133,13 @@ class ConfigValidationError(ConfigError):
     """A config file is invalid."""
 
 
-class InterpolationMissingOptionError(ConfigError):
-    def __init__(self, option, section, rawval, reference):
+class InterpolationError(ConfigError):
+    def __init__(self, option, section, rawval, error):
         super().__init__(
             self,
             softwrap(
                 f"""
-                Bad value substitution: option {option} in section {section} contains an
-                interpolation key {reference} which is not a valid option name.
+                Bad value substitution: option {option} in section {section}: {error}.
 
                 Raw value: {rawval}
                 """
@@ -269,14 +269,22 @@ def test_remote_provider_matches_rust_enum(
         store_rpc_retries=0,
         store_rpc_concurrency=0,
         store_rpc_timeout_millis=0,
+        store_streaming_rpc_timeout_millis=0,
         store_batch_api_size_limit=0,
+        asset_fetch=False,
         cache_warnings_behavior="ignore",
         cache_content_behavior="validate",
         cache_rpc_concurrency=0,
         cache_rpc_timeout_millis=0,
+        cache_write_behind=False,
+        cache_write_behind_rate_limit=0,
+        cache_circuit_breaker_threshold=0,
+        cache_circuit_breaker_cooldown_secs=0,
         execution_headers={},
         execution_overall_deadline_secs=0,
         execution_rpc_concurrency=0,
+        execution_priority=0,
+        results_cache_priority=0,
         store_address=None,
         execution_address=None,
         execution_process_cache_namespace=None,
@@ -285,4 +293,9 @@ def test_remote_provider_matches_rust_enum(
         client_certs_path=None,
         client_key_path=None,
         append_only_caches_base_path=None,
+        http_proxy=None,
+        https_proxy=None,
+        no_proxy=None,
+        store_upload_rate_limit=None,
+        store_download_rate_limit=None,
     )
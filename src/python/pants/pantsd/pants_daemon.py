@@ -26,7 +26,8 @@ from pants.option.options import Options
 from pants.option.options_bootstrapper import OptionsBootstrapper
 from pants.pantsd.pants_daemon_core import PantsDaemonCore
 from pants.pantsd.process_manager import PantsDaemonProcessManager
-from pants.pantsd.service.pants_service import PantsServices
+from pants.pantsd.service.metrics_service import MetricsService
+from pants.pantsd.service.pants_service import PantsService, PantsServices
 from pants.pantsd.service.scheduler_service import SchedulerService
 from pants.pantsd.service.store_gc_service import StoreGCService
 from pants.util.contextutil import argv_as, hermetic_environment_as
@@ -121,7 +122,13 @@ class PantsDaemon(PantsDaemonProcessManager):
             graph_scheduler.scheduler,
             local_store_options=LocalStoreOptions.from_options(bootstrap_options),
         )
-        return PantsServices(services=(scheduler_service, store_gc_service))
+        services: tuple[PantsService, ...] = (scheduler_service, store_gc_service)
+        if bootstrap_options.pantsd_metrics_port is not None:
+            metrics_service = MetricsService(
+                graph_scheduler.scheduler, port=bootstrap_options.pantsd_metrics_port
+            )
+            services = (*services, metrics_service)
+        return PantsServices(services=services)
 
     def __init__(
         self,
@@ -3,10 +3,11 @@
 
 from __future__ import annotations
 
+import functools
 import logging
 import threading
 from contextlib import contextmanager
-from typing import Iterator, Protocol
+from typing import Callable, Iterator, Protocol
 
 from pants.build_graph.build_configuration import BuildConfiguration
 from pants.engine.env_vars import CompleteEnvironmentVars
@@ -51,12 +52,15 @@ class PantsDaemonCore:
         self._executor = executor
         self._services_constructor = services_constructor
         self._lifecycle_lock = threading.RLock()
-        # N.B. This Event is used as nothing more than an atomic flag - nothing waits on it.
+        # N.B. These Events are used as nothing more than atomic flags - nothing waits on them.
         self._kill_switch = threading.Event()
+        self._restart_switch = threading.Event()
 
         self._scheduler: GraphScheduler | None = None
         self._services: PantsServices | None = None
         self._fingerprint: str | None = None
+        self._memory_check: Callable[[], bool] | None = None
+        self._shutdown_drain_timeout = 0
 
         self._prior_dynamic_remote_options: DynamicRemoteOptions | None = None
         self._prior_auth_plugin_result: AuthPluginResult | None = None
@@ -70,6 +74,9 @@ class PantsDaemonCore:
         if self._kill_switch.is_set():
             logger.error("Client failed to create a Scheduler: shutting down.")
             return False
+        if self._restart_switch.is_set():
+            logger.info("Restarting to reclaim memory.")
+            return False
         with self._lifecycle_lock:
             if self._services is None:
                 return True
@@ -110,6 +117,17 @@ class PantsDaemonCore:
 
             self._services = self._services_constructor(bootstrap_options, self._scheduler)
             self._fingerprint = options_fingerprint
+            self._shutdown_drain_timeout = bootstrap_options.pantsd_shutdown_drain_timeout
+            # This session is only used for checking memory usage between runs.
+            memory_check_session = self._scheduler.scheduler.new_session(
+                build_id="memory_check_session"
+            )
+            self._memory_check = functools.partial(
+                memory_check_session.check_memory,
+                max_memory_usage=bootstrap_options.pantsd_max_memory_usage,
+                clear_caches_fraction=bootstrap_options.pantsd_memory_clear_caches_fraction,
+                max_graph_size=bootstrap_options.pantsd_max_graph_size,
+            )
             logger.info("Scheduler initialized.")
         except Exception as e:
             self._kill_switch.set()
@@ -185,11 +203,27 @@ class PantsDaemonCore:
             assert self._scheduler is not None
             return self._scheduler, self._options_initializer
 
+    def check_memory_usage(self) -> None:
+        """Check memory usage, and reclaim memory if it is above the configured limits.
+
+        This allows the daemon to restart gracefully between runs. The SchedulerService separately
+        polls `--pantsd-max-memory-usage` during runs, so that long-running sessions (such as
+        `--loop`) are also bounded.
+
+        Must be called between runs (generally in DaemonPantsRunner). If clearing in-memory caches
+        is not sufficient to reclaim memory, the core is marked invalid, which will cause the daemon
+        to restart gracefully once ongoing runs have completed.
+        """
+        with self._lifecycle_lock:
+            if self._memory_check is not None and self._memory_check():
+                self._restart_switch.set()
+
     def shutdown(self) -> None:
         with self._lifecycle_lock:
             if self._services is not None:
                 self._services.shutdown()
                 self._services = None
+            self._memory_check = None
             if self._scheduler is not None:
-                self._scheduler.scheduler.shutdown()
+                self._scheduler.scheduler.shutdown(drain_timeout_secs=self._shutdown_drain_timeout)
                 self._scheduler = None
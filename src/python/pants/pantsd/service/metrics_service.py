@@ -0,0 +1,40 @@
+# Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+# Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+from __future__ import annotations
+
+import logging
+
+from pants.engine.internals import native_engine
+from pants.engine.internals.scheduler import Scheduler
+from pants.pantsd.service.pants_service import PantsService
+
+logger = logging.getLogger(__name__)
+
+
+class MetricsService(PantsService):
+    """Serves metrics about pantsd in the Prometheus text format, for monitoring.
+
+    The metrics include the totals of the counters and observations recorded by all completed runs,
+    and the current sizes of the graph, the local store, and the executor's queues.
+    """
+
+    def __init__(self, scheduler: Scheduler, port: int, period_secs: float = 1) -> None:
+        super().__init__()
+        self._server = native_engine.metrics_server_create(scheduler.py_scheduler, port)
+        self._period_secs = period_secs
+        logger.info(f"Serving metrics at http://127.0.0.1:{self.port}/metrics")
+
+    @property
+    def port(self) -> int:
+        return self._server.port()
+
+    def run(self) -> None:
+        """Main service entrypoint.
+
+        The server runs in the background on the engine's executor: this thread only waits to shut
+        it down when the service is terminated.
+        """
+        while not self._state.is_terminating:
+            self._state.maybe_pause(timeout=self._period_secs)
+        native_engine.metrics_server_shutdown(self._server)
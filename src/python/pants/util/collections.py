@@ -7,12 +7,15 @@ import collections
 import collections.abc
 import gc
 import math
+import types
 from sys import getsizeof
 from typing import Any, Callable, Iterable, Iterator, MutableMapping, TypeVar
 
 from pants.engine.internals import native_engine
 from pants.util.strutil import softwrap
 
+_UNCOUNTED_TYPES = (type, types.ModuleType, types.FunctionType, types.BuiltinFunctionType)
+
 
 def recursively_update(d: MutableMapping, d2: MutableMapping) -> None:
     """dict.update but which merges child dicts (dict2 takes precedence where there's conflict)."""
@@ -28,16 +31,19 @@ def deep_getsizeof(o: Any, ids: set[int]) -> int:
     """Find the memory footprint of the given object.
 
     To avoid double-counting, `ids` should be a set of object ids which have been visited by
-    previous calls to this method.
+    previous calls to this method. Types, modules and functions are shared by many objects, so are
+    not counted (and are not traversed).
     """
-    if id(o) in ids:
-        return 0
-
-    d = deep_getsizeof
-    r = getsizeof(o)
-    ids.add(id(o))
-
-    return r + sum(d(x, ids) for x in gc.get_referents())
+    total = 0
+    stack = [o]
+    while stack:
+        item = stack.pop()
+        if id(item) in ids or isinstance(item, _UNCOUNTED_TYPES):
+            continue
+        ids.add(id(item))
+        total += getsizeof(item)
+        stack.extend(gc.get_referents(item))
+    return total
 
 
 _T = TypeVar("_T")
@@ -5,11 +5,13 @@
 from __future__ import annotations
 
 from functools import partial
+from sys import getsizeof
 
 import pytest
 
 from pants.util.collections import (
     assert_single_element,
+    deep_getsizeof,
     ensure_list,
     ensure_str_list,
     partition_sequentially,
@@ -24,6 +26,18 @@ def test_recursively_update() -> None:
     assert d1 == {"a": 1, "b": {"c": 2, "f": 4, "o": 9}, "e": 3, "g": {"h": 5}, "z": 7}
 
 
+def test_deep_getsizeof() -> None:
+    inner = ("a" * 100,)
+    outer = [inner, inner]
+    ids: set[int] = set()
+    expected = getsizeof(outer) + getsizeof(inner) + getsizeof(inner[0])
+    assert expected == deep_getsizeof(outer, ids)
+    # Objects which have already been visited are not counted again.
+    assert 0 == deep_getsizeof(inner, ids)
+    # Nor are types.
+    assert 0 == deep_getsizeof(str, set())
+
+
 def test_assert_single_element() -> None:
     single_element = [1]
     assert 1 == assert_single_element(single_element)
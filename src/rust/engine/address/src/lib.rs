@@ -1,6 +1,13 @@
 // Copyright 2021 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+use std::fmt;
+
+// `:`, `#`, `@` are used as delimiters already. Others are reserved for possible future needs.
+pub const BANNED_CHARS_IN_TARGET_NAME: [char; 8] = [':', '#', '!', '@', '?', '/', '\\', '='];
+pub const BANNED_CHARS_IN_GENERATED_NAME: [char; 6] = [':', '#', '!', '@', '?', '='];
+pub const BANNED_CHARS_IN_PARAMETERS: [char; 8] = [':', '#', '!', '@', '?', '=', ',', ' '];
+
 pub struct AddressInput<'a> {
     pub path: &'a str,
     pub target: Option<&'a str>,
@@ -65,6 +72,134 @@ peg::parser! {
     }
 }
 
-pub fn parse_address_spec(value: &str) -> Result<SpecInput, String> {
-    parsers::spec(value).map_err(|e| format!("Failed to parse address spec `{value}`: {e}"))
+/// A spec which failed to parse, with the span of the spec that the failure applies to.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SpecError {
+    pub spec: String,
+    /// The offset in chars of the start of the span.
+    pub start: usize,
+    /// The offset in chars of the end of the span, which is never before its start.
+    pub end: usize,
+    pub message: String,
+}
+
+impl SpecError {
+    fn new(spec: &str, start: usize, end: usize, message: String) -> SpecError {
+        SpecError {
+            spec: spec.to_owned(),
+            start,
+            end,
+            message,
+        }
+    }
+
+    /// An error about the given component of the spec, which must be a slice of it.
+    fn for_component(spec: &str, component: &str, message: String) -> SpecError {
+        let start_byte = component.as_ptr() as usize - spec.as_ptr() as usize;
+        let start = spec[..start_byte].chars().count();
+        SpecError::new(spec, start, start + component.chars().count(), message)
+    }
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let carets = "^".repeat(std::cmp::max(1, self.end - self.start));
+        write!(
+            f,
+            "Failed to parse address spec `{spec}`: {message}\n\n  {spec}\n  {padding}{carets}",
+            spec = self.spec,
+            message = self.message,
+            padding = " ".repeat(self.start),
+        )
+    }
+}
+
+pub fn parse_address_spec(value: &str) -> Result<SpecInput, SpecError> {
+    parsers::spec(value).map_err(|e| {
+        let start = e.location.column - 1;
+        SpecError::new(value, start, start + 1, format!("expected {}", e.expected))
+    })
 }
+
+/// A spec given on the command line, which might be negated with a `-` prefix to ignore its
+/// matches.
+pub struct CommandLineSpec<'a> {
+    pub spec: SpecInput<'a>,
+    pub is_ignore: bool,
+}
+
+///
+/// Parses and validates a spec given on the command line, so that invalid specs can be reported
+/// before they are resolved.
+///
+/// In addition to the syntax of `parse_address_spec`, this rejects names and parameters with
+/// banned characters, and wildcards which follow a target name, generated name or parameters.
+///
+pub fn parse_command_line_spec(value: &str) -> Result<CommandLineSpec, SpecError> {
+    let (is_ignore, spec_str) = match value.strip_prefix('-') {
+        Some("") => {
+            return Err(SpecError::new(
+                value,
+                1,
+                1,
+                "expected a spec to follow the `-` of an ignore.".to_owned(),
+            ))
+        }
+        Some(stripped) => (true, stripped),
+        None => (false, value),
+    };
+    let offset = value.len() - spec_str.len();
+    let spec = parse_address_spec(spec_str).map_err(|e| SpecError {
+        spec: value.to_owned(),
+        start: e.start + offset,
+        end: e.end + offset,
+        message: e.message,
+    })?;
+
+    let banned = |component: &str, banned_chars: &[char], description: &str| match component
+        .find(banned_chars)
+    {
+        Some(index) => {
+            let c = component[index..].chars().next().unwrap();
+            Err(SpecError::for_component(
+                value,
+                &component[index..index + c.len_utf8()],
+                format!("the character `{c}` is not allowed in {description}."),
+            ))
+        }
+        None => Ok(()),
+    };
+    let address = &spec.address;
+    if let Some(target) = address.target {
+        banned(target, &BANNED_CHARS_IN_TARGET_NAME, "a target name")?;
+    }
+    if let Some(generated) = address.generated {
+        banned(
+            generated,
+            &BANNED_CHARS_IN_GENERATED_NAME,
+            "a generated target name",
+        )?;
+    }
+    for (key, val) in &address.parameters {
+        banned(key, &BANNED_CHARS_IN_PARAMETERS, "a parameter")?;
+        banned(val, &BANNED_CHARS_IN_PARAMETERS, "a parameter")?;
+    }
+    if let Some(wildcard) = spec.wildcard {
+        if address.target.is_some() || address.generated.is_some() || !address.parameters.is_empty()
+        {
+            return Err(SpecError::for_component(
+                value,
+                wildcard,
+                format!(
+                    "the wildcard `{wildcard}` may not follow a target name, generated target \
+                     name or parameters."
+                ),
+            ));
+        }
+    }
+
+    Ok(CommandLineSpec { spec, is_ignore })
+}
+
+#[cfg(test)]
+mod tests;
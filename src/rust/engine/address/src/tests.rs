@@ -0,0 +1,109 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::{parse_address_spec, parse_command_line_spec, SpecError};
+
+fn error_span(value: &str) -> (usize, usize, String) {
+    let SpecError {
+        start,
+        end,
+        message,
+        ..
+    } = parse_command_line_spec(value)
+        .err()
+        .unwrap_or_else(|| panic!("Expected `{value}` to fail to parse."));
+    (start, end, message)
+}
+
+#[test]
+fn parse_specs() {
+    let spec = parse_address_spec("src/python:tgt#gen@resolve=a,k=v").unwrap();
+    assert_eq!("src/python", spec.address.path);
+    assert_eq!(Some("tgt"), spec.address.target);
+    assert_eq!(Some("gen"), spec.address.generated);
+    assert_eq!(vec![("resolve", "a"), ("k", "v")], spec.address.parameters);
+    assert_eq!(None, spec.wildcard);
+
+    let spec = parse_command_line_spec("-src/python::").unwrap();
+    assert!(spec.is_ignore);
+    assert_eq!("src/python", spec.spec.address.path);
+    assert_eq!(Some("::"), spec.spec.wildcard);
+
+    let spec = parse_command_line_spec("dir/file.py").unwrap();
+    assert!(!spec.is_ignore);
+    assert_eq!("dir/file.py", spec.spec.address.path);
+    assert_eq!(None, spec.spec.wildcard);
+
+    let spec = parse_command_line_spec(":tgt").unwrap();
+    assert_eq!("", spec.spec.address.path);
+    assert_eq!(Some("tgt"), spec.spec.address.target);
+}
+
+#[test]
+fn syntax_errors() {
+    let expected_generated_name =
+        "expected a non-empty generated target name to follow a `#`.".to_owned();
+    assert_eq!(
+        (8, 9, expected_generated_name.clone()),
+        error_span("dir:tgt#")
+    );
+    // The offsets account for the ignore prefix.
+    assert_eq!((9, 10, expected_generated_name), error_span("-dir:tgt#"));
+    assert_eq!(
+        (
+            1,
+            1,
+            "expected a spec to follow the `-` of an ignore.".to_owned()
+        ),
+        error_span("-")
+    );
+}
+
+#[test]
+fn validation_errors() {
+    assert_eq!(
+        (
+            6,
+            7,
+            "the character `!` is not allowed in a target name.".to_owned()
+        ),
+        error_span("dir:tg!t")
+    );
+    assert_eq!(
+        (
+            7,
+            8,
+            "the character `?` is not allowed in a generated target name.".to_owned()
+        ),
+        error_span("dir:t#g?")
+    );
+    assert_eq!(
+        (
+            7,
+            8,
+            "the character ` ` is not allowed in a parameter.".to_owned()
+        ),
+        error_span("dir@k=v w")
+    );
+    assert_eq!(
+        (
+            7,
+            9,
+            "the wildcard `::` may not follow a target name, generated target name or parameters."
+                .to_owned()
+        ),
+        error_span("dir:tgt::")
+    );
+}
+
+#[test]
+fn render_error() {
+    assert_eq!(
+        "Failed to parse address spec `dir:tgt::`: the wildcard `::` may not follow a target name, \
+         generated target name or parameters.\n\n  dir:tgt::\n         ^^",
+        parse_command_line_spec("dir:tgt::")
+            .err()
+            .unwrap()
+            .to_string()
+    );
+}
@@ -0,0 +1,179 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Constructors for the subset of `build_event_stream.BuildEvent`s which Pants emits.
+
+use std::time::{Duration, SystemTime};
+
+use hashing::Digest;
+use protos::gen::build_event_stream as bes;
+use protos::gen::build_event_stream::build_event::Payload;
+use protos::gen::build_event_stream::build_event_id::{self, Id};
+
+fn event(id: Id, children: Vec<Id>, payload: Payload) -> bes::BuildEvent {
+    bes::BuildEvent {
+        id: Some(bes::BuildEventId { id: Some(id) }),
+        children: children
+            .into_iter()
+            .map(|id| bes::BuildEventId { id: Some(id) })
+            .collect(),
+        last_message: false,
+        payload: Some(payload),
+    }
+}
+
+fn timestamp(time: SystemTime) -> prost_types::Timestamp {
+    prost_types::Timestamp::from(time)
+}
+
+fn duration(duration: Duration) -> Option<prost_types::Duration> {
+    prost_types::Duration::try_from(duration).ok()
+}
+
+///
+/// A reference to the content of a file (generally stdout or stderr) by Digest.
+///
+pub fn file(name: &str, digest: Digest) -> bes::File {
+    bes::File {
+        name: name.to_owned(),
+        digest: digest.hash.to_hex(),
+        length: digest.size_bytes as i64,
+        ..bes::File::default()
+    }
+}
+
+pub fn started(
+    uuid: &str,
+    command: &str,
+    build_tool_version: &str,
+    workspace_directory: &str,
+    start_time: SystemTime,
+) -> bes::BuildEvent {
+    event(
+        Id::Started(build_event_id::BuildStartedId {}),
+        vec![Id::BuildFinished(build_event_id::BuildFinishedId {})],
+        Payload::Started(bes::BuildStarted {
+            uuid: uuid.to_owned(),
+            start_time: Some(timestamp(start_time)),
+            build_tool_version: build_tool_version.to_owned(),
+            command: command.to_owned(),
+            working_directory: workspace_directory.to_owned(),
+            workspace_directory: workspace_directory.to_owned(),
+            server_pid: std::process::id() as i64,
+            ..bes::BuildStarted::default()
+        }),
+    )
+}
+
+pub fn target_configured(label: &str, target_kind: &str, tags: Vec<String>) -> bes::BuildEvent {
+    event(
+        Id::TargetConfigured(build_event_id::TargetConfiguredId {
+            label: label.to_owned(),
+            aspect: String::new(),
+        }),
+        vec![Id::TargetCompleted(build_event_id::TargetCompletedId {
+            label: label.to_owned(),
+            ..build_event_id::TargetCompletedId::default()
+        })],
+        Payload::Configured(bes::TargetConfigured {
+            target_kind: target_kind.to_owned(),
+            tag: tags,
+        }),
+    )
+}
+
+pub fn target_completed(label: &str, success: bool, tags: Vec<String>) -> bes::BuildEvent {
+    event(
+        Id::TargetCompleted(build_event_id::TargetCompletedId {
+            label: label.to_owned(),
+            ..build_event_id::TargetCompletedId::default()
+        }),
+        vec![],
+        Payload::Completed(bes::TargetComplete { success, tag: tags }),
+    )
+}
+
+///
+/// An executed (or cache hit) process. Since Pants processes do not have a unique "primary output"
+/// in the Bazel sense, the `id` should be something which uniquely identifies the execution: for
+/// example, the span id of its workunit.
+///
+pub fn action_executed(
+    id: &str,
+    label: Option<&str>,
+    mnemonic: &str,
+    argv: Vec<String>,
+    exit_code: i32,
+    stdout: Option<Digest>,
+    stderr: Option<Digest>,
+    start_time: SystemTime,
+    end_time: SystemTime,
+) -> bes::BuildEvent {
+    event(
+        Id::ActionCompleted(build_event_id::ActionCompletedId {
+            primary_output: id.to_owned(),
+            label: label.unwrap_or_default().to_owned(),
+            configuration: None,
+        }),
+        vec![],
+        Payload::Action(bes::ActionExecuted {
+            success: exit_code == 0,
+            r#type: mnemonic.to_owned(),
+            exit_code,
+            stdout: stdout.map(|d| file("stdout", d)),
+            stderr: stderr.map(|d| file("stderr", d)),
+            primary_output: None,
+            command_line: argv,
+            start_time: Some(timestamp(start_time)),
+            end_time: Some(timestamp(end_time)),
+        }),
+    )
+}
+
+pub fn test_result(
+    label: &str,
+    attempt: i32,
+    status: bes::TestStatus,
+    cached_locally: bool,
+    start_time: SystemTime,
+    test_duration: Duration,
+    outputs: Vec<bes::File>,
+) -> bes::BuildEvent {
+    event(
+        Id::TestResult(build_event_id::TestResultId {
+            label: label.to_owned(),
+            configuration: None,
+            run: 1,
+            shard: 1,
+            attempt,
+        }),
+        vec![],
+        Payload::TestResult(bes::TestResult {
+            status: status.into(),
+            status_details: String::new(),
+            cached_locally,
+            test_attempt_start: Some(timestamp(start_time)),
+            test_attempt_duration: duration(test_duration),
+            test_action_output: outputs,
+        }),
+    )
+}
+
+///
+/// The final event of a build, which announces no children and is marked as the last message.
+///
+pub fn finished(exit_code: i32, finish_time: SystemTime) -> bes::BuildEvent {
+    let mut event = event(
+        Id::BuildFinished(build_event_id::BuildFinishedId {}),
+        vec![],
+        Payload::Finished(bes::BuildFinished {
+            exit_code: Some(bes::build_finished::ExitCode {
+                name: if exit_code == 0 { "SUCCESS" } else { "FAILED" }.to_owned(),
+                code: exit_code,
+            }),
+            finish_time: Some(timestamp(finish_time)),
+        }),
+    );
+    event.last_message = true;
+    event
+}
@@ -0,0 +1,185 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! A publisher for the Build Event Protocol (BEP), which streams `build_event_stream` events to a
+//! Build Event Service (BES) endpoint, so that Pants runs can be displayed by BES consumers.
+
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+use futures::{Stream, StreamExt};
+use grpc_util::{headers_to_http_header_map, layered_service, status_to_str};
+use parking_lot::Mutex;
+use prost::Message;
+use protos::gen::build_event_stream as bes;
+use protos::gen::google::devtools::build::v1::{
+    build_event, build_event::build_component_stream_finished::FinishType,
+    publish_build_event_client::PublishBuildEventClient, stream_id::BuildComponent, BuildEvent,
+    OrderedBuildEvent, PublishBuildToolEventStreamRequest, StreamId,
+};
+use task_executor::Executor;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+pub mod events;
+
+#[cfg(test)]
+mod tests;
+
+const BAZEL_EVENT_TYPE_URL: &str = "type.googleapis.com/build_event_stream.BuildEvent";
+
+#[derive(Clone)]
+pub struct BepOptions {
+    /// The address of the Build Event Service, e.g. `grpcs://remote.example.com`.
+    pub address: String,
+    pub headers: BTreeMap<String, String>,
+    pub tls_config: grpc_util::tls::Config,
+    /// The project which builds are associated with, for services which require one.
+    pub project_id: String,
+    /// Keywords attached to the notification of a new build event stream.
+    pub keywords: Vec<String>,
+}
+
+///
+/// Publishes the build events of a single invocation as one `PublishBuildToolEventStream` stream.
+///
+/// Events are buffered and sent in the background as they are published: a failure to publish is
+/// logged rather than failing the run. `finish` must be called after the final event in order to
+/// close the stream and wait for the service to acknowledge the events.
+///
+pub struct BuildEventPublisher {
+    sender: Mutex<Option<mpsc::UnboundedSender<(SystemTime, bes::BuildEvent)>>>,
+    stream: Mutex<Option<JoinHandle<Result<usize, String>>>>,
+}
+
+impl BuildEventPublisher {
+    pub async fn new(
+        executor: &Executor,
+        options: BepOptions,
+        build_id: String,
+        invocation_id: String,
+    ) -> Result<Self, String> {
+        let needs_tls = options.address.starts_with("https://");
+        let tls_client_config: Option<_> = needs_tls
+            .then(|| options.tls_config.clone().try_into())
+            .transpose()?;
+        let endpoint =
+            grpc_util::create_channel(&options.address, tls_client_config.as_ref()).await?;
+        let channel = layered_service(
+            endpoint,
+            1,
+            headers_to_http_header_map(&options.headers)?,
+            None,
+        );
+        let mut client = PublishBuildEventClient::new(channel);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let stream_id = StreamId {
+            build_id,
+            invocation_id,
+            component: BuildComponent::Tool.into(),
+        };
+        let requests = ordered_requests(
+            stream_id,
+            options.project_id,
+            options.keywords,
+            UnboundedReceiverStream::new(receiver),
+        );
+        let stream = executor.native_spawn_io(async move {
+            let mut acks = client
+                .publish_build_tool_event_stream(requests)
+                .await
+                .map_err(status_to_str)?
+                .into_inner();
+            let mut acknowledged = 0;
+            while let Some(ack) = acks.next().await {
+                ack.map_err(status_to_str)?;
+                acknowledged += 1;
+            }
+            Ok(acknowledged)
+        });
+
+        Ok(Self {
+            sender: Mutex::new(Some(sender)),
+            stream: Mutex::new(Some(stream)),
+        })
+    }
+
+    ///
+    /// Publish the given event. Events published after `finish` has been called are ignored.
+    ///
+    pub fn publish(&self, event: bes::BuildEvent) {
+        if let Some(sender) = self.sender.lock().as_ref() {
+            // If the stream has failed, the failure will be reported by `finish`.
+            let _ = sender.send((SystemTime::now(), event));
+        }
+    }
+
+    ///
+    /// Close the stream, and wait for the service to acknowledge all of the published events.
+    ///
+    pub async fn finish(&self) -> Result<(), String> {
+        self.sender.lock().take();
+        let Some(stream) = self.stream.lock().take() else {
+            return Ok(());
+        };
+        let acknowledged = stream
+            .await
+            .map_err(|e| format!("Build event stream task failed: {e}"))?
+            .map_err(|e| format!("Failed to publish build events: {e}"))?;
+        log::debug!("The Build Event Service acknowledged {acknowledged} events.");
+        Ok(())
+    }
+}
+
+///
+/// Wraps the given events into requests with consecutive sequence numbers (starting from one), and
+/// terminates the stream with a `BuildComponentStreamFinished` event once the events are exhausted.
+///
+pub fn ordered_requests(
+    stream_id: StreamId,
+    project_id: String,
+    keywords: Vec<String>,
+    events: impl Stream<Item = (SystemTime, bes::BuildEvent)> + Send + 'static,
+) -> impl Stream<Item = PublishBuildToolEventStreamRequest> + Send + 'static {
+    let bazel_events = events.map(|(event_time, event)| BuildEvent {
+        event_time: Some(event_time.into()),
+        event: Some(build_event::Event::BazelEvent(prost_types::Any {
+            type_url: BAZEL_EVENT_TYPE_URL.to_owned(),
+            value: event.encode_to_vec(),
+        })),
+    });
+    let finished = futures::stream::once(async {
+        BuildEvent {
+            event_time: Some(SystemTime::now().into()),
+            event: Some(build_event::Event::ComponentStreamFinished(
+                build_event::BuildComponentStreamFinished {
+                    r#type: FinishType::Finished.into(),
+                },
+            )),
+        }
+    });
+
+    bazel_events
+        .chain(finished)
+        .enumerate()
+        .map(move |(index, event)| {
+            let sequence_number = index as i64 + 1;
+            PublishBuildToolEventStreamRequest {
+                ordered_build_event: Some(OrderedBuildEvent {
+                    stream_id: Some(stream_id.clone()),
+                    sequence_number,
+                    event: Some(event),
+                }),
+                // Keywords are only read for the first event in a stream.
+                notification_keywords: if sequence_number == 1 {
+                    keywords.clone()
+                } else {
+                    vec![]
+                },
+                project_id: project_id.clone(),
+                check_preceding_lifecycle_events_present: false,
+            }
+        })
+}
@@ -0,0 +1,123 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::time::{Duration, SystemTime};
+
+use futures::StreamExt;
+use prost::Message;
+use protos::gen::build_event_stream as bes;
+use protos::gen::google::devtools::build::v1::{build_event, stream_id::BuildComponent, StreamId};
+
+use crate::{events, ordered_requests};
+
+fn stream_id() -> StreamId {
+    StreamId {
+        build_id: "build".to_owned(),
+        invocation_id: "invocation".to_owned(),
+        component: BuildComponent::Tool.into(),
+    }
+}
+
+#[tokio::test]
+async fn ordered_requests_are_sequenced_and_finished() {
+    let now = SystemTime::now();
+    let events = vec![
+        (
+            now,
+            events::started("invocation", "test", "2.0.0", "/repo", now),
+        ),
+        (now, events::finished(0, now)),
+    ];
+
+    let requests = ordered_requests(
+        stream_id(),
+        "project".to_owned(),
+        vec!["keyword".to_owned()],
+        futures::stream::iter(events),
+    )
+    .collect::<Vec<_>>()
+    .await;
+
+    // The two published events are followed by the end of the stream.
+    assert_eq!(requests.len(), 3);
+    for (index, request) in requests.iter().enumerate() {
+        let ordered = request.ordered_build_event.as_ref().unwrap();
+        assert_eq!(ordered.sequence_number, index as i64 + 1);
+        assert_eq!(ordered.stream_id.as_ref(), Some(&stream_id()));
+        assert_eq!(request.project_id, "project");
+    }
+    assert_eq!(
+        requests[0].notification_keywords,
+        vec!["keyword".to_owned()]
+    );
+    assert!(requests[1].notification_keywords.is_empty());
+
+    let event = |index: usize| {
+        requests[index]
+            .ordered_build_event
+            .as_ref()
+            .unwrap()
+            .event
+            .as_ref()
+            .unwrap()
+            .event
+            .clone()
+            .unwrap()
+    };
+    match event(1) {
+        build_event::Event::BazelEvent(any) => {
+            let decoded = bes::BuildEvent::decode(any.value.as_slice()).unwrap();
+            assert!(decoded.last_message);
+            assert!(matches!(
+                decoded.payload,
+                Some(bes::build_event::Payload::Finished(_))
+            ));
+        }
+        e => panic!("Unexpected event: {e:?}"),
+    }
+    assert!(matches!(
+        event(2),
+        build_event::Event::ComponentStreamFinished(_)
+    ));
+}
+
+#[test]
+fn build_started_announces_build_finished() {
+    let now = SystemTime::now();
+    let event = events::started("invocation", "test", "2.0.0", "/repo", now);
+    assert_eq!(
+        event.children,
+        vec![bes::BuildEventId {
+            id: Some(bes::build_event_id::Id::BuildFinished(
+                bes::build_event_id::BuildFinishedId {}
+            ))
+        }]
+    );
+}
+
+#[test]
+fn test_result_records_duration() {
+    let now = SystemTime::now();
+    let event = events::test_result(
+        "src/python:tests",
+        1,
+        bes::TestStatus::Failed,
+        true,
+        now,
+        Duration::from_millis(1500),
+        vec![],
+    );
+    match event.payload {
+        Some(bes::build_event::Payload::TestResult(result)) => {
+            assert_eq!(result.status(), bes::TestStatus::Failed);
+            assert!(result.cached_locally);
+            assert_eq!(
+                result.test_attempt_duration,
+                Some(prost_types::Duration {
+                    seconds: 1,
+                    nanos: 500_000_000
+                })
+            );
+        }
+        p => panic!("Unexpected payload: {p:?}"),
+    }
+}
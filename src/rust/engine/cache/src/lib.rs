@@ -61,4 +61,13 @@ impl PersistentCache {
             .load_bytes_with(fingerprint, move |bytes| Ok(Bytes::copy_from_slice(bytes)))
             .await
     }
+
+    ///
+    /// Removes the value stored for the given key (if any), so that a new value may be stored: values
+    /// are otherwise never overwritten.
+    ///
+    pub async fn remove(&self, key: &CacheKey) -> Result<bool, String> {
+        let fingerprint = Digest::of_bytes(&key.to_bytes()).hash;
+        self.store.remove(fingerprint).await
+    }
 }
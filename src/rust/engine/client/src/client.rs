@@ -1,7 +1,9 @@
 // Copyright 2021 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+use std::fmt;
 use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
 use log::debug;
@@ -9,12 +11,47 @@ use log::debug;
 use nailgun::NailgunClientError;
 use pantsd::ConnectionSettings;
 
+use crate::events::{Event, EventRecorder};
+
+#[derive(Debug)]
+pub enum CommandError {
+    ///
+    /// pantsd could not be used, and so the command was never started. It is always safe to run the
+    /// command some other way.
+    ///
+    Unavailable(String),
+    ///
+    /// The connection to pantsd was lost after the command started. `wrote_stdout` indicates whether
+    /// the command had already written to stdout.
+    ///
+    ConnectionLost { message: String, wrote_stdout: bool },
+    /// The command failed in some other way, and should not be retried.
+    Failed(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Unavailable(message)
+            | CommandError::ConnectionLost { message, .. }
+            | CommandError::Failed(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Unavailable(message)
+    }
+}
+
 pub async fn execute_command(
     start: SystemTime,
     connection_settings: ConnectionSettings,
     mut env: Vec<(String, String)>,
     argv: Vec<String>,
-) -> Result<i32, String> {
+    events: Option<&Arc<Mutex<EventRecorder>>>,
+) -> Result<i32, CommandError> {
     env.push((
         "PANTSD_RUNTRACKER_CLIENT_START_TIME".to_owned(),
         start
@@ -61,8 +98,17 @@ pub async fn execute_command(
 
     let args = argv.iter().skip(1).cloned().collect();
 
-    let nailgun_result =
-        nailgun::client_execute(connection_settings.port, command, args, env).await;
+    if let Some(events) = events {
+        events.lock().unwrap().reset();
+    }
+    let nailgun_result = nailgun::client_execute(
+        connection_settings.port,
+        command,
+        args,
+        env,
+        events.map(EventRecorder::handler),
+    )
+    .await;
     for (raw_fd, termios) in tty_settings {
         if let Err(err) =
             nix::sys::termios::tcsetattr(*raw_fd, nix::sys::termios::SetArg::TCSADRAIN, &termios)
@@ -74,21 +120,49 @@ pub async fn execute_command(
             );
         }
     }
+    // If the run reported which phase it was in, include it in errors.
+    let phase = if let Some(events) = events {
+        let events = events.lock().unwrap();
+        if let Some(Event::Exit {
+            run_id,
+            elapsed_secs,
+            ..
+        }) = events.exit()
+        {
+            debug!("Run {run_id} completed in {elapsed_secs:.3}s.");
+        }
+        events
+            .phase()
+            .map(|phase| format!(" while {phase}"))
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
     nailgun_result.map_err(|error| match error {
-        NailgunClientError::PreConnect(err) => format!(
+        NailgunClientError::PreConnect(err) => CommandError::Unavailable(format!(
             "Problem connecting to pantsd at {port}: {err}",
             port = connection_settings.port,
             err = err
-        ),
-        NailgunClientError::PostConnect(err) => format!(
-            "Problem communicating with pantsd at {port}: {err}",
+        )),
+        NailgunClientError::PostConnect(err) => CommandError::Failed(format!(
+            "Problem communicating with pantsd at {port}{phase}: {err}",
             port = connection_settings.port,
             err = err
-        ),
-        NailgunClientError::BrokenPipe => format!(
+        )),
+        NailgunClientError::ConnectionLost {
+            message,
+            wrote_stdout,
+        } => CommandError::ConnectionLost {
+            message: format!(
+                "Lost connection to pantsd at {port}{phase}: {message}",
+                port = connection_settings.port,
+            ),
+            wrote_stdout,
+        },
+        NailgunClientError::BrokenPipe => CommandError::Failed(format!(
             "Broken pipe communicating with pantsd at {port}.",
             port = connection_settings.port
-        ),
-        NailgunClientError::KeyboardInterrupt => "User interrupt.".to_owned(),
+        )),
+        NailgunClientError::KeyboardInterrupt => CommandError::Failed("User interrupt.".to_owned()),
     })
 }
@@ -19,6 +19,7 @@ async fn test_client() {
         connection_settings,
         std::env::vars().collect(),
         ["pants", "-V"].iter().map(ToString::to_string).collect(),
+        None,
     )
     .await
     .unwrap();
@@ -0,0 +1,126 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+
+use options::{option_id, OptionParser};
+
+///
+/// A structured event sent by pantsd during a run. See `stdio_destination_emit_event` in
+/// `pants.init.logging`.
+///
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The run has moved to a new phase.
+    Phase {
+        phase: String,
+        #[serde(default)]
+        run_id: Option<String>,
+        #[serde(default)]
+        goals: Vec<String>,
+    },
+    /// The counters of the workunits which completed during the run.
+    WorkunitSummary { counters: BTreeMap<String, u64> },
+    /// The run has completed.
+    Exit {
+        exit_code: i32,
+        run_id: String,
+        elapsed_secs: f64,
+    },
+    /// An event from a newer version of pantsd, which is ignored.
+    #[serde(other)]
+    Unknown,
+}
+
+///
+/// Records the events of a run, optionally copying them (as JSON lines) to a file for consumption
+/// by other tools.
+///
+#[derive(Debug, Default)]
+pub struct EventRecorder {
+    json_output: Option<File>,
+    phase: Option<String>,
+    exit: Option<Event>,
+}
+
+impl EventRecorder {
+    ///
+    /// Returns a recorder if a file to record events to was configured, and otherwise None: in
+    /// which case pantsd is not asked to send events at all.
+    ///
+    pub fn parse(options_parser: &OptionParser) -> Result<Option<Self>, String> {
+        let Some(path) = options_parser
+            .parse_string_optional(&option_id!("pantsd", "client", "events", "json"), None)?
+            .value
+        else {
+            return Ok(None);
+        };
+        let path = PathBuf::from(path);
+        let json_output = File::create(&path).map_err(|e| {
+            format!(
+                "Failed to create events file at {path}: {e}",
+                path = path.display()
+            )
+        })?;
+        Ok(Some(EventRecorder {
+            json_output: Some(json_output),
+            ..EventRecorder::default()
+        }))
+    }
+
+    ///
+    /// Records an event: a single line of JSON.
+    ///
+    pub fn record(&mut self, line: &str) {
+        if let Some(json_output) = self.json_output.as_mut() {
+            if let Err(e) = writeln!(json_output, "{line}") {
+                log::warn!("Failed to write event, no further events will be written: {e}");
+                self.json_output = None;
+            }
+        }
+
+        match serde_json::from_str::<Event>(line) {
+            Ok(Event::Phase { phase, .. }) => self.phase = Some(phase),
+            Ok(event @ Event::Exit { .. }) => self.exit = Some(event),
+            Ok(_) => {}
+            Err(e) => log::debug!("Failed to decode event {line:?}: {e}"),
+        }
+    }
+
+    ///
+    /// The most recent phase that the run reported, if any.
+    ///
+    pub fn phase(&self) -> Option<&str> {
+        self.phase.as_deref()
+    }
+
+    ///
+    /// The exit event of the run, if it completed.
+    ///
+    pub fn exit(&self) -> Option<&Event> {
+        self.exit.as_ref()
+    }
+
+    ///
+    /// Clears the state of a previous attempt at the run, before it is replayed.
+    ///
+    pub fn reset(&mut self) {
+        self.phase = None;
+        self.exit = None;
+    }
+
+    ///
+    /// Creates a handler which records events into the given recorder.
+    ///
+    pub fn handler(recorder: &Arc<Mutex<EventRecorder>>) -> nailgun::EventHandler {
+        let recorder = recorder.clone();
+        Box::new(move |line: &str| recorder.lock().unwrap().record(line))
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::{BTreeMap, HashMap};
+
+use options::{Args, Env, OptionParser};
+use tempfile::TempDir;
+
+use crate::events::{Event, EventRecorder};
+
+fn options_parser(args: Vec<String>) -> OptionParser {
+    OptionParser::new(
+        Args::new(args),
+        Env::new(HashMap::new()),
+        Some(vec![]),
+        false,
+        false,
+        None,
+    )
+    .unwrap()
+}
+
+#[test]
+fn decode_events() {
+    let decode = |line: &str| serde_json::from_str::<Event>(line).unwrap();
+    assert_eq!(
+        Event::Phase {
+            phase: "running".to_owned(),
+            run_id: Some("pants_run_1".to_owned()),
+            goals: vec!["list".to_owned()],
+        },
+        decode(
+            r#"{"goals": ["list"], "phase": "running", "run_id": "pants_run_1", "type": "phase"}"#
+        )
+    );
+    assert_eq!(
+        Event::WorkunitSummary {
+            counters: BTreeMap::from([("local_cache_requests".to_owned(), 3)]),
+        },
+        decode(r#"{"counters": {"local_cache_requests": 3}, "type": "workunit_summary"}"#)
+    );
+    assert_eq!(
+        Event::Exit {
+            exit_code: 1,
+            run_id: "pants_run_1".to_owned(),
+            elapsed_secs: 0.5,
+        },
+        decode(r#"{"elapsed_secs": 0.5, "exit_code": 1, "run_id": "pants_run_1", "type": "exit"}"#)
+    );
+    // Events from newer versions of pantsd are ignored.
+    assert_eq!(
+        Event::Unknown,
+        decode(r#"{"type": "something_new", "x": 1}"#)
+    );
+}
+
+#[test]
+fn record_events() {
+    let mut recorder = EventRecorder::default();
+    assert_eq!(None, recorder.phase());
+
+    recorder.record(r#"{"phase": "initializing", "type": "phase"}"#);
+    recorder.record("not json");
+    recorder.record(r#"{"phase": "running", "type": "phase"}"#);
+    assert_eq!(Some("running"), recorder.phase());
+    assert_eq!(None, recorder.exit());
+
+    recorder.record(
+        r#"{"elapsed_secs": 1.0, "exit_code": 0, "run_id": "pants_run_1", "type": "exit"}"#,
+    );
+    assert!(matches!(
+        recorder.exit(),
+        Some(Event::Exit { exit_code: 0, .. })
+    ));
+
+    recorder.reset();
+    assert_eq!(None, recorder.phase());
+    assert_eq!(None, recorder.exit());
+}
+
+#[test]
+fn recorder_is_only_created_when_requested() {
+    assert!(EventRecorder::parse(&options_parser(vec![]))
+        .unwrap()
+        .is_none());
+
+    let tmpdir = TempDir::new().unwrap();
+    let path = tmpdir.path().join("events.jsonl");
+    let mut recorder = EventRecorder::parse(&options_parser(vec![format!(
+        "--pantsd-client-events-json={}",
+        path.display()
+    )]))
+    .unwrap()
+    .unwrap();
+    recorder.record(r#"{"phase": "running", "type": "phase"}"#);
+    std::mem::drop(recorder);
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "{\"phase\": \"running\", \"type\": \"phase\"}\n"
+    );
+}
@@ -4,8 +4,20 @@
 mod client;
 #[cfg(test)]
 mod client_tests;
+mod events;
+#[cfg(test)]
+mod events_tests;
+mod reconnect;
+#[cfg(test)]
+mod reconnect_tests;
+mod specs;
+#[cfg(test)]
+mod specs_tests;
 
-pub use crate::client::execute_command;
+pub use crate::client::{execute_command, CommandError};
+pub use crate::events::{Event, EventRecorder};
+pub use crate::reconnect::ReconnectSettings;
+pub use crate::specs::validate_specs;
 
 #[cfg(test)]
 mod lib_tests;
@@ -7,14 +7,16 @@ use std::ffi::{CString, OsString};
 use std::os::unix::ffi::OsStringExt;
 use std::path::PathBuf;
 use std::str::FromStr;
-use std::time::SystemTime;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 use nix::unistd::execv;
 use strum::VariantNames;
 use strum_macros::{AsRefStr, EnumString, EnumVariantNames};
 
+use client::{validate_specs, CommandError, EventRecorder, ReconnectSettings};
 use options::{option_id, render_choice, Args, BuildRoot, Env, OptionParser};
-use pantsd::find_pantsd;
+use pantsd::{find_pantsd, ConnectionSettings};
 
 // TODO(John Sirois): Maybe consolidate with PythonLogLevel in src/rust/engine/logging/src/lib.rs.
 #[derive(AsRefStr, EnumString, EnumVariantNames)]
@@ -27,10 +29,10 @@ enum PythonLogLevel {
     Error,
 }
 
-async fn execute(start: SystemTime) -> Result<i32, String> {
+async fn execute(start: SystemTime, can_relaunch: bool) -> Result<i32, CommandError> {
     let build_root = BuildRoot::find()?;
     let (env, dropped) = Env::capture_lossy();
-    let env_items = (&env).into();
+    let env_items: Vec<(String, String)> = (&env).into();
     let argv = env::args().collect::<Vec<_>>();
     let options_parser = OptionParser::new(Args::argv(), env, None, true, false, None)?;
 
@@ -39,12 +41,15 @@ async fn execute(start: SystemTime) -> Result<i32, String> {
         return Err(format!(
             "Pantsd has been turned off via {option_source:?}.",
             option_source = use_pantsd.source
-        ));
+        )
+        .into());
     }
 
     let concurrent = options_parser.parse_bool(&option_id!("concurrent"), false)?;
     if concurrent.value {
-        return Err("Pantsd is being turned off since --concurrent is true.".to_owned());
+        return Err("Pantsd is being turned off since --concurrent is true."
+            .to_owned()
+            .into());
     }
 
     let level_option = option_id!(-'l', "level");
@@ -75,8 +80,63 @@ async fn execute(start: SystemTime) -> Result<i32, String> {
             name.to_string_lossy()
         );
     }
-    let pantsd_settings = find_pantsd(&build_root, &options_parser)?;
-    client::execute_command(start, pantsd_settings, env_items, argv).await
+    validate_specs(&argv[1..])?;
+    let reconnect = ReconnectSettings::parse(&options_parser, &argv)?;
+    let events = EventRecorder::parse(&options_parser)?.map(|events| Arc::new(Mutex::new(events)));
+    let mut pantsd_settings = find_pantsd(&build_root, &options_parser)?;
+    let mut attempts = 0;
+    loop {
+        match client::execute_command(
+            start,
+            pantsd_settings,
+            env_items.clone(),
+            argv.clone(),
+            events.as_ref(),
+        )
+        .await
+        {
+            Err(CommandError::ConnectionLost {
+                message,
+                wrote_stdout,
+            }) if reconnect.should_replay(attempts, wrote_stdout) => {
+                attempts += 1;
+                log::warn!(
+                    "{message}\n\nReplaying the run (attempt {attempts} of {max_attempts}).",
+                    max_attempts = reconnect.attempts
+                );
+                pantsd_settings = reconnect_to_pantsd(&build_root, &options_parser, can_relaunch)
+                    .await
+                    .map_err(CommandError::Unavailable)?;
+            }
+            res => return res,
+        }
+    }
+}
+
+// How long to wait for pantsd to be restarted by another client when we cannot relaunch it.
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const RECONNECT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+///
+/// Finds pantsd again after the connection to it was lost.
+///
+/// If we are able to relaunch pantsd (by falling back to the legacy client), we only check once
+/// whether it has already been restarted: the resulting `CommandError::Unavailable` causes the
+/// fallback. Otherwise, we wait for a while in case another client restarts it.
+///
+async fn reconnect_to_pantsd(
+    build_root: &BuildRoot,
+    options_parser: &OptionParser,
+    can_relaunch: bool,
+) -> Result<ConnectionSettings, String> {
+    let deadline = Instant::now() + RECONNECT_TIMEOUT;
+    loop {
+        match find_pantsd(build_root, options_parser) {
+            Ok(settings) => return Ok(settings),
+            Err(err) if can_relaunch || Instant::now() >= deadline => return Err(err),
+            Err(_) => tokio::time::sleep(RECONNECT_POLL_INTERVAL).await,
+        }
+    }
 }
 
 fn try_execv_fallback_client(pants_server: OsString) -> Result<Infallible, i32> {
@@ -137,13 +197,13 @@ async fn main() {
         _ => {}
     }
 
-    match (execute(start).await, pants_server) {
-        (Err(_), Some(pants_server)) => {
+    match (execute(start, pants_server.is_some()).await, pants_server) {
+        (Err(CommandError::Unavailable(_)), Some(pants_server)) => {
             // We failed to connect to `pantsd`, but a server variable was provided. Fall back
             // to `execv`'ing the legacy Python client, which will handle spawning `pantsd`.
             execv_fallback_client(pants_server);
         }
-        (Err(err), None) => {
+        (Err(err), _) => {
             eprintln!("{err}");
             // We use this exit code to indicate an error running pants via the nailgun protocol to
             // differentiate from a successful nailgun protocol session.
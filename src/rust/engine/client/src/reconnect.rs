@@ -0,0 +1,71 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashSet;
+
+use options::{option_id, OptionParser};
+use pantsd::DEFAULT_IDEMPOTENT_GOALS;
+
+///
+/// Settings which control whether a run is replayed if the connection to pantsd is lost mid-run.
+///
+#[derive(Debug)]
+pub struct ReconnectSettings {
+    /// The number of times to reconnect to (or relaunch) pantsd and replay the run.
+    pub attempts: usize,
+    /// True if all of the goals of the run are idempotent.
+    pub idempotent: bool,
+}
+
+impl ReconnectSettings {
+    pub fn parse(options_parser: &OptionParser, argv: &[String]) -> Result<Self, String> {
+        let attempts = options_parser
+            .parse_int(&option_id!("pantsd", "client", "reconnect", "attempts"), 0)?
+            .value;
+        let idempotent_goals = options_parser
+            .parse_string_list(
+                &option_id!("pantsd", "client", "idempotent", "goals"),
+                DEFAULT_IDEMPOTENT_GOALS
+                    .iter()
+                    .map(|goal| (*goal).to_owned())
+                    .collect(),
+            )?
+            .value;
+        Ok(ReconnectSettings {
+            attempts: usize::try_from(attempts).unwrap_or(0),
+            idempotent: is_idempotent(argv, &idempotent_goals.into_iter().collect()),
+        })
+    }
+
+    ///
+    /// True if the run should be replayed after the given number of previous attempts, given
+    /// whether it had already written to stdout (which would then be duplicated).
+    ///
+    pub fn should_replay(&self, previous_attempts: usize, wrote_stdout: bool) -> bool {
+        self.idempotent && !wrote_stdout && previous_attempts < self.attempts
+    }
+}
+
+///
+/// Returns true if every goal in the given argv is idempotent.
+///
+/// Goals are not distinguished from specs here, so any positional argument which could be a goal
+/// name must be an idempotent goal: this errs on the side of not replaying runs which might have
+/// side effects.
+///
+pub fn is_idempotent(argv: &[String], idempotent_goals: &HashSet<String>) -> bool {
+    argv.iter()
+        // Skip argv[0], and stop at the passthrough args.
+        .skip(1)
+        .take_while(|arg| *arg != "--")
+        .filter(|arg| !arg.starts_with('-'))
+        .filter(|arg| could_be_goal(arg))
+        .all(|arg| idempotent_goals.contains(arg))
+}
+
+fn could_be_goal(arg: &str) -> bool {
+    !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
@@ -0,0 +1,62 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashSet;
+
+use pantsd::DEFAULT_IDEMPOTENT_GOALS;
+
+use crate::reconnect::{is_idempotent, ReconnectSettings};
+
+fn idempotent(args: &[&str]) -> bool {
+    let argv = std::iter::once("pants")
+        .chain(args.iter().copied())
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    let goals = DEFAULT_IDEMPOTENT_GOALS
+        .iter()
+        .map(|goal| (*goal).to_owned())
+        .collect::<HashSet<_>>();
+    is_idempotent(&argv, &goals)
+}
+
+#[test]
+fn idempotent_goals() {
+    assert!(idempotent(&[]));
+    assert!(idempotent(&["-V"]));
+    assert!(idempotent(&["list", "src/python::"]));
+    assert!(idempotent(&[
+        "--level=debug",
+        "peek",
+        "dependencies",
+        "//:root"
+    ]));
+    // Passthrough args are not goals.
+    assert!(idempotent(&["help", "--", "publish"]));
+}
+
+#[test]
+fn non_idempotent_goals() {
+    assert!(!idempotent(&["test", "src/python::"]));
+    assert!(!idempotent(&["list", "publish", "::"]));
+    // A spec which looks like a goal name is conservatively treated as one.
+    assert!(!idempotent(&["list", "src"]));
+}
+
+#[test]
+fn should_replay() {
+    let settings = ReconnectSettings {
+        attempts: 2,
+        idempotent: true,
+    };
+    assert!(settings.should_replay(0, false));
+    assert!(settings.should_replay(1, false));
+    assert!(!settings.should_replay(2, false));
+    // Output would be duplicated.
+    assert!(!settings.should_replay(0, true));
+
+    let settings = ReconnectSettings {
+        attempts: 2,
+        idempotent: false,
+    };
+    assert!(!settings.should_replay(0, false));
+}
@@ -0,0 +1,23 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::client::CommandError;
+
+///
+/// Validates the address specs among the given command line args (which must *not* include the
+/// argv[0] process name), so that an invalid spec is reported without waiting for pantsd.
+///
+/// Only args which contain one of the delimiters of an address are validated: goals, files and
+/// directories are not parsed as addresses, and so can't be invalid. Flags and passthrough args
+/// are skipped.
+///
+pub fn validate_specs(args: &[String]) -> Result<(), CommandError> {
+    args.iter()
+        .take_while(|arg| *arg != "--")
+        .filter(|arg| !arg.starts_with("--") && arg.contains([':', '#', '@']))
+        .try_for_each(|arg| {
+            address::parse_command_line_spec(arg)
+                .map(|_| ())
+                .map_err(|e| CommandError::Failed(e.to_string()))
+        })
+}
@@ -0,0 +1,45 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::specs::validate_specs;
+use crate::CommandError;
+
+fn validate(args: &[&str]) -> Result<(), String> {
+    let args = args.iter().map(|arg| (*arg).to_owned()).collect::<Vec<_>>();
+    validate_specs(&args).map_err(|e| match e {
+        CommandError::Failed(message) => message,
+        e => panic!("Unexpected error: {e}"),
+    })
+}
+
+#[test]
+fn test_valid_specs() {
+    assert_eq!(
+        Ok(()),
+        validate(&[
+            "--level=debug",
+            "test",
+            "src/python::",
+            "-src/python/tests:",
+            "dir:tgt#gen@resolve=a",
+            "file.py",
+        ])
+    );
+    // Flags and passthrough args are not validated.
+    assert_eq!(
+        Ok(()),
+        validate(&["--tag=a:b:c", "run", "dir:bin", "--", "dir:tgt::"])
+    );
+}
+
+#[test]
+fn test_invalid_specs() {
+    assert_eq!(
+        Err(
+            "Failed to parse address spec `dir:tgt::`: the wildcard `::` may not follow a target \
+             name, generated target name or parameters.\n\n  dir:tgt::\n         ^^"
+                .to_owned()
+        ),
+        validate(&["test", "dir:", "dir:tgt::"])
+    );
+}
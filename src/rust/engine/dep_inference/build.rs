@@ -218,6 +218,39 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         &source_dir,
         out_dir,
     )?;
+    gen_files_for_language(
+        tree_sitter_typescript::language_tsx(),
+        "typescript",
+        &source_dir,
+        out_dir,
+    )?;
+    gen_files_for_language(tree_sitter_go::language(), "go", &source_dir, out_dir)?;
+    gen_files_for_language(tree_sitter_bash::language(), "shell", &source_dir, out_dir)?;
+    // Java and Kotlin share an implementation (and so an impl hash), but each has a grammar.
+    let jvm_out_dir = out_dir.join("jvm");
+    for (language, name) in [
+        (tree_sitter_java::language(), "java"),
+        (tree_sitter_kotlin::language(), "kotlin"),
+    ] {
+        let subdir = jvm_out_dir.join(name);
+        fs::create_dir_all(&subdir)?;
+        gen_constants_file(&language, &subdir);
+        gen_visitor_file(&language, &subdir);
+    }
+    gen_impl_hash_file("jvm", &source_dir.join("jvm"), &jvm_out_dir, out_dir);
+    // Stylesheets and Dockerfiles are scanned without a tree-sitter grammar, so only impl hashes
+    // are generated.
+    let css_out_dir = out_dir.join("css");
+    fs::create_dir_all(&css_out_dir)?;
+    gen_impl_hash_file("css", &source_dir.join("css"), &css_out_dir, out_dir);
+    let dockerfile_out_dir = out_dir.join("dockerfile");
+    fs::create_dir_all(&dockerfile_out_dir)?;
+    gen_impl_hash_file(
+        "dockerfile",
+        &source_dir.join("dockerfile"),
+        &dockerfile_out_dir,
+        out_dir,
+    );
     println!("cargo:rerun-if-env-changed=PANTS_PRINT_IMPL_HASHES");
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=src");
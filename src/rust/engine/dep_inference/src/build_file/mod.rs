@@ -0,0 +1,298 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! A pre-parser of BUILD files, which extracts their targets without evaluating them.
+//!
+//! Only BUILD files which consist entirely of calls with literal keyword arguments (the vast
+//! majority of them) are "static": anything else (assignments, macros with computed arguments,
+//! control flow, ...) must be evaluated by the Python interpreter, as before.
+use tree_sitter::{Node, Parser};
+
+use crate::span::SourceSpan;
+
+/// A literal Python value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    List(Vec<Literal>),
+    Tuple(Vec<Literal>),
+    Dict(Vec<(Literal, Literal)>),
+}
+
+impl Literal {
+    /// Whether the value may be used as a dict key.
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            Literal::List(_) | Literal::Dict(_) => false,
+            Literal::Tuple(values) => values.iter().all(Literal::is_hashable),
+            _ => true,
+        }
+    }
+}
+
+/// A top-level call in a BUILD file, which is usually the declaration of a target.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildFileTarget {
+    /// The called symbol, e.g. `python_sources`.
+    pub type_alias: String,
+    /// The span of the call.
+    pub span: SourceSpan,
+    /// The keyword arguments with literal values, in the order they were given.
+    pub fields: Vec<(String, Literal)>,
+    /// The keyword arguments whose values could not be extracted without evaluating them.
+    pub dynamic_fields: Vec<String>,
+}
+
+impl BuildFileTarget {
+    /// The literal `name` of the target, if it was given.
+    pub fn name(&self) -> Option<&str> {
+        self.fields.iter().find_map(|(field, value)| match value {
+            Literal::Str(name) if field == "name" => Some(name.as_str()),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedBuildFile {
+    pub targets: Vec<BuildFileTarget>,
+    /// The locations of syntax errors, if the file could not be parsed.
+    pub syntax_errors: Vec<SourceSpan>,
+    /// Whether the targets were entirely extracted, i.e. the file does not need to be evaluated.
+    pub is_static: bool,
+}
+
+pub fn parse(contents: &str) -> ParsedBuildFile {
+    let mut parser = Parser::new();
+    parser
+        .set_language(tree_sitter_python::language())
+        .expect("Error loading Python grammar");
+    let tree = parser.parse(contents, None).unwrap();
+    let root = tree.root_node();
+
+    let mut syntax_errors = Vec::new();
+    if root.has_error() {
+        collect_syntax_errors(root, &mut syntax_errors);
+    }
+
+    let mut targets = Vec::new();
+    let mut is_static = syntax_errors.is_empty();
+    let mut cursor = root.walk();
+    for statement in root.named_children(&mut cursor) {
+        match statement.kind() {
+            "comment" => (),
+            "expression_statement" => match target(contents, statement) {
+                Some(target) => {
+                    is_static &= target.dynamic_fields.is_empty();
+                    targets.push(target);
+                }
+                None => is_static = false,
+            },
+            _ => is_static = false,
+        }
+    }
+
+    ParsedBuildFile {
+        targets,
+        syntax_errors,
+        is_static,
+    }
+}
+
+fn collect_syntax_errors(node: Node, syntax_errors: &mut Vec<SourceSpan>) {
+    if node.is_error() || node.is_missing() {
+        syntax_errors.push(SourceSpan::of(node));
+        return;
+    }
+    if !node.has_error() {
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_syntax_errors(child, syntax_errors);
+    }
+}
+
+/// The target declared by a statement, if it is a call of a plain symbol with only keyword
+/// arguments.
+fn target(code: &str, statement: Node) -> Option<BuildFileTarget> {
+    if statement.named_child_count() != 1 {
+        return None;
+    }
+    let call = statement
+        .named_child(0)
+        .filter(|node| node.kind() == "call")?;
+    let function = call
+        .child_by_field_name("function")
+        .filter(|node| node.kind() == "identifier")?;
+    let arguments = call
+        .child_by_field_name("arguments")
+        .filter(|node| node.kind() == "argument_list")?;
+
+    let mut fields = Vec::new();
+    let mut dynamic_fields = Vec::new();
+    let mut cursor = arguments.walk();
+    for argument in arguments.named_children(&mut cursor) {
+        match argument.kind() {
+            "comment" => (),
+            "keyword_argument" => {
+                let name = text(code, argument.child_by_field_name("name")?).to_owned();
+                match argument
+                    .child_by_field_name("value")
+                    .and_then(|value| literal(code, value))
+                {
+                    Some(value) => fields.push((name, value)),
+                    None => dynamic_fields.push(name),
+                }
+            }
+            // Positional arguments and splats can't be mapped to fields.
+            _ => return None,
+        }
+    }
+
+    Some(BuildFileTarget {
+        type_alias: text(code, function).to_owned(),
+        span: SourceSpan::of(call),
+        fields,
+        dynamic_fields,
+    })
+}
+
+fn text<'a>(code: &'a str, node: Node) -> &'a str {
+    &code[node.byte_range()]
+}
+
+fn literal(code: &str, node: Node) -> Option<Literal> {
+    match node.kind() {
+        "none" => Some(Literal::None),
+        "true" => Some(Literal::Bool(true)),
+        "false" => Some(Literal::Bool(false)),
+        "integer" => integer(text(code, node)).map(Literal::Int),
+        "float" => text(code, node)
+            .replace('_', "")
+            .parse()
+            .ok()
+            .map(Literal::Float),
+        "string" => string(text(code, node)).map(Literal::Str),
+        "concatenated_string" => {
+            let mut value = String::new();
+            for part in named_children(node) {
+                value.push_str(&string(text(code, part))?);
+            }
+            Some(Literal::Str(value))
+        }
+        "list" => elements(code, node).map(Literal::List),
+        "tuple" => elements(code, node).map(Literal::Tuple),
+        "dictionary" => named_children(node)
+            .into_iter()
+            .map(|pair| {
+                if pair.kind() != "pair" {
+                    return None;
+                }
+                let key = literal(code, pair.child_by_field_name("key")?)?;
+                if !key.is_hashable() {
+                    return None;
+                }
+                Some((key, literal(code, pair.child_by_field_name("value")?)?))
+            })
+            .collect::<Option<_>>()
+            .map(Literal::Dict),
+        "parenthesized_expression" => match named_children(node).as_slice() {
+            [inner] => literal(code, *inner),
+            _ => None,
+        },
+        "unary_operator" => {
+            let operator = node.child_by_field_name("operator")?;
+            match (
+                text(code, operator),
+                literal(code, node.child_by_field_name("argument")?)?,
+            ) {
+                ("-", Literal::Int(value)) => value.checked_neg().map(Literal::Int),
+                ("-", Literal::Float(value)) => Some(Literal::Float(-value)),
+                ("+", value @ (Literal::Int(_) | Literal::Float(_))) => Some(value),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The named children of a node, other than comments.
+fn named_children(node: Node) -> Vec<Node> {
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor)
+        .filter(|child| child.kind() != "comment")
+        .collect()
+}
+
+fn elements(code: &str, node: Node) -> Option<Vec<Literal>> {
+    named_children(node)
+        .into_iter()
+        .map(|element| literal(code, element))
+        .collect()
+}
+
+fn integer(text: &str) -> Option<i64> {
+    let digits = text.replace('_', "").to_lowercase();
+    let (digits, radix) = match digits.get(..2) {
+        Some("0x") => (&digits[2..], 16),
+        Some("0o") => (&digits[2..], 8),
+        Some("0b") => (&digits[2..], 2),
+        _ => (digits.as_str(), 10),
+    };
+    i64::from_str_radix(digits, radix).ok()
+}
+
+/// The value of a string literal, unless it is an f-string, a bytes literal or uses escape
+/// sequences that we don't interpret.
+fn string(text: &str) -> Option<String> {
+    let quote_start = text.find(['"', '\''])?;
+    let prefix = text[..quote_start].to_lowercase();
+    if prefix.contains('f') || prefix.contains('b') {
+        return None;
+    }
+    let quoted = &text[quote_start..];
+    let quotes = if quoted.starts_with("\"\"\"") || quoted.starts_with("'''") {
+        3
+    } else {
+        1
+    };
+    if quoted.len() < 2 * quotes {
+        return None;
+    }
+    let body = &quoted[quotes..quoted.len() - quotes];
+    if prefix.contains('r') {
+        Some(body.to_owned())
+    } else {
+        unescape(body)
+    }
+}
+
+fn unescape(body: &str) -> Option<String> {
+    let mut value = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\n' => (),
+            '\\' => value.push('\\'),
+            '\'' => value.push('\''),
+            '"' => value.push('"'),
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            'r' => value.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests;
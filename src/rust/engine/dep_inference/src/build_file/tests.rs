@@ -0,0 +1,129 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::build_file::{parse, Literal};
+
+fn string(value: &str) -> Literal {
+    Literal::Str(value.to_owned())
+}
+
+#[test]
+fn static_targets() {
+    let result = parse(
+        r#"
+# A comment.
+python_sources()
+
+python_tests(
+    name="tests",  # Another comment.
+    sources=["*_test.py", '!ignored_test.py'],
+    timeout=-120,
+    skip_mypy=True,
+    overrides={("a_test.py",): {"tags": ("slow",), "batch_compatibility_tag": None}},
+    description="a " 'long'
+    " description",
+)
+"#,
+    );
+
+    assert!(result.is_static);
+    assert!(result.syntax_errors.is_empty());
+    assert_eq!(2, result.targets.len());
+
+    let sources = &result.targets[0];
+    assert_eq!("python_sources", sources.type_alias);
+    assert_eq!(3, sources.span.start_line);
+    assert_eq!(None, sources.name());
+    assert!(sources.fields.is_empty());
+
+    let tests = &result.targets[1];
+    assert_eq!("python_tests", tests.type_alias);
+    assert_eq!(5, tests.span.start_line);
+    assert_eq!(13, tests.span.end_line);
+    assert_eq!(Some("tests"), tests.name());
+    assert_eq!(
+        vec![
+            ("name".to_owned(), string("tests")),
+            (
+                "sources".to_owned(),
+                Literal::List(vec![string("*_test.py"), string("!ignored_test.py")])
+            ),
+            ("timeout".to_owned(), Literal::Int(-120)),
+            ("skip_mypy".to_owned(), Literal::Bool(true)),
+            (
+                "overrides".to_owned(),
+                Literal::Dict(vec![(
+                    Literal::Tuple(vec![string("a_test.py")]),
+                    Literal::Dict(vec![
+                        (string("tags"), Literal::Tuple(vec![string("slow")])),
+                        (string("batch_compatibility_tag"), Literal::None),
+                    ])
+                )])
+            ),
+            ("description".to_owned(), string("a long description")),
+        ],
+        tests.fields
+    );
+}
+
+#[test]
+fn literal_values() {
+    let value = |code: &str| {
+        let result = parse(&format!("target(value={code})"));
+        result.targets[0]
+            .fields
+            .first()
+            .map(|(_, value)| value.clone())
+    };
+    assert_eq!(Some(Literal::Int(1000)), value("1_000"));
+    assert_eq!(Some(Literal::Int(255)), value("0xFF"));
+    assert_eq!(Some(Literal::Float(-1.5)), value("-1.5"));
+    assert_eq!(Some(Literal::Bool(false)), value("False"));
+    assert_eq!(Some(string("a\"b\n")), value(r#""a\"b\n""#));
+    assert_eq!(Some(string(r"a\d")), value(r#"r"a\d""#));
+    assert_eq!(
+        Some(string("multi\nline")),
+        value("\"\"\"multi\nline\"\"\"")
+    );
+    assert_eq!(Some(Literal::Tuple(vec![])), value("()"));
+    assert_eq!(Some(Literal::Int(1)), value("(1)"));
+
+    // Values which must be evaluated.
+    assert_eq!(None, value(r#"f"{x}""#));
+    assert_eq!(None, value(r#"b"bytes""#));
+    assert_eq!(None, value("1 + 1"));
+    assert_eq!(None, value("[*other]"));
+    assert_eq!(None, value("{**other}"));
+    assert_eq!(None, value("{'a', 'b'}"));
+    assert_eq!(None, value("{('a', [1]): 2}"));
+    assert_eq!(None, value("env('VAR')"));
+    assert_eq!(None, value("99999999999999999999"));
+}
+
+#[test]
+fn dynamic_build_files() {
+    let assert_dynamic = |code: &str| {
+        let result = parse(code);
+        assert!(!result.is_static, "Expected `{code}` to be dynamic.");
+        assert!(result.syntax_errors.is_empty());
+    };
+    assert_dynamic("files(sources=SOURCES)");
+    assert_dynamic("SOURCES = ['*.txt']\nfiles(sources=SOURCES)");
+    assert_dynamic("files('positional')");
+    assert_dynamic("files(**kwargs)");
+    assert_dynamic("macros.files()");
+    assert_dynamic("for name in ['a', 'b']:\n    files(name=name)");
+    assert_dynamic("def macro():\n    pass");
+
+    let result = parse("files(name='a')\nfiles(name='b', sources=glob())");
+    assert_eq!(2, result.targets.len());
+    assert_eq!(vec!["sources".to_owned()], result.targets[1].dynamic_fields);
+}
+
+#[test]
+fn syntax_errors() {
+    let result = parse("files(name='a')\nfiles(name='b',, sources=[])\nfiles(name='c'\n");
+    assert!(!result.is_static);
+    assert!(!result.syntax_errors.is_empty());
+    assert_eq!(2, result.syntax_errors[0].start_line);
+}
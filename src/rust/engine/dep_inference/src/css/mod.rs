@@ -0,0 +1,371 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Dependency inference for stylesheets: CSS, SCSS, Sass and LESS.
+//!
+//! The dialects differ enough in their syntax (and the tree-sitter CSS grammar is strict enough)
+//! that a tolerant scanner for the few constructs which reference other files is used instead:
+//! `@import`, `@use`, `@forward` and `url()`.
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use fnv::FnvHashSet as HashSet;
+use serde_derive::{Deserialize, Serialize};
+
+use protos::gen::pants::cache::CssInferenceMetadata;
+
+use crate::javascript::util::normalize_path;
+
+include!(concat!(env!("OUT_DIR"), "/css_impl_hash.rs"));
+
+const PRAGMA: &str = "pants: no-infer-dep";
+
+#[derive(Serialize, Deserialize)]
+pub struct ParsedCssDependencies {
+    /// Candidate files for each reference, relative to the build root. Since preprocessors try
+    /// several file names (e.g. partials and index files), most candidates will not exist.
+    pub file_imports: HashSet<String>,
+    /// References to files in other packages, e.g. `~bootstrap/scss/bootstrap`.
+    pub package_imports: HashSet<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Dialect {
+    Css,
+    /// Both the SCSS and the indented Sass syntax.
+    Sass,
+    Less,
+}
+
+impl Dialect {
+    fn for_path(filepath: &Path) -> Dialect {
+        match filepath.extension().and_then(OsStr::to_str) {
+            Some("scss" | "sass") => Dialect::Sass,
+            Some("less") => Dialect::Less,
+            _ => Dialect::Css,
+        }
+    }
+}
+
+/// A reference to another file, along with how it was referenced.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Reference {
+    /// `@import`, `@use` or `@forward`, which preprocessors resolve to a stylesheet.
+    Stylesheet(String),
+    /// `url()`, which refers to an asset relative to the stylesheet.
+    Asset(String),
+}
+
+pub fn get_dependencies(
+    contents: &str,
+    filepath: PathBuf,
+    metadata: CssInferenceMetadata,
+) -> Result<ParsedCssDependencies, String> {
+    let dialect = Dialect::for_path(&filepath);
+    let directory = filepath.parent().unwrap_or(Path::new(""));
+    let package_root = Path::new(&metadata.package_root);
+
+    let mut file_imports = HashSet::default();
+    let mut package_imports = HashSet::default();
+    for reference in collect_references(contents) {
+        let (reference, is_stylesheet) = match reference {
+            Reference::Stylesheet(reference) => (reference, true),
+            // Query strings and fragments are commonly used for cache busting and SVG sprites.
+            Reference::Asset(reference) => match reference.find(['?', '#']) {
+                Some(end) => (reference[..end].to_owned(), false),
+                None => (reference, false),
+            },
+        };
+        if let Some(package) = reference
+            .strip_prefix('~')
+            .or_else(|| reference.strip_prefix("pkg:"))
+        {
+            package_imports.insert(package.to_owned());
+            continue;
+        }
+        let path = if let Some(from_root) = reference.strip_prefix('/') {
+            package_root.join(from_root)
+        } else {
+            directory.join(&reference)
+        };
+        let Some(path) = normalize_path(&path) else {
+            continue;
+        };
+        if is_stylesheet {
+            file_imports.extend(stylesheet_candidates(dialect, &path));
+            // Preprocessors fall back to their load paths (generally including `node_modules`)
+            // for references which are not explicitly relative.
+            if dialect != Dialect::Css && !reference.starts_with('.') && !reference.starts_with('/')
+            {
+                package_imports.insert(reference);
+            }
+        } else {
+            file_imports.insert(path.to_string_lossy().to_string());
+        }
+    }
+    Ok(ParsedCssDependencies {
+        file_imports,
+        package_imports,
+    })
+}
+
+/// The files which a preprocessor would try for a stylesheet reference.
+fn stylesheet_candidates(dialect: Dialect, path: &Path) -> Vec<String> {
+    let candidates = match (dialect, path.extension().and_then(OsStr::to_str)) {
+        (Dialect::Sass, Some("scss" | "sass" | "css")) => vec![path.to_owned(), partial(path)],
+        (Dialect::Sass, _) => ["scss", "sass", "css"]
+            .into_iter()
+            .flat_map(|extension| {
+                let file = with_extension(path, extension);
+                let index = path.join("index").with_extension(extension);
+                [partial(&file), partial(&index), file, index]
+            })
+            .collect(),
+        (Dialect::Less, None) => vec![with_extension(path, "less")],
+        _ => vec![path.to_owned()],
+    };
+    candidates
+        .into_iter()
+        .map(|candidate| candidate.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Append an extension, without replacing any existing one (e.g. `theme.dark` -> `theme.dark.scss`).
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+/// The Sass partial for a file, which is prefixed with an underscore.
+fn partial(path: &Path) -> PathBuf {
+    let mut file_name = OsStr::new("_").to_owned();
+    file_name.push(path.file_name().unwrap_or_default());
+    path.with_file_name(file_name)
+}
+
+///
+/// Scan a stylesheet for references to other files, skipping comments, references which are not
+/// local files (URLs, data URIs, built-in Sass modules), and those which are interpolated.
+///
+/// References on a line containing a `pants: no-infer-dep` comment are ignored.
+///
+pub(crate) fn collect_references(contents: &str) -> Vec<Reference> {
+    let mut scanner = Scanner {
+        contents,
+        position: 0,
+        references: vec![],
+        ignored_lines: contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.contains(PRAGMA))
+            .map(|(number, _)| number)
+            .collect(),
+    };
+    scanner.scan();
+    scanner.references
+}
+
+struct Scanner<'a> {
+    contents: &'a str,
+    position: usize,
+    references: Vec<Reference>,
+    ignored_lines: HashSet<usize>,
+}
+
+impl Scanner<'_> {
+    fn rest(&self) -> &str {
+        &self.contents[self.position..]
+    }
+
+    fn advance(&mut self, bytes: usize) {
+        self.position = (self.position + bytes).min(self.contents.len());
+    }
+
+    fn advance_past(&mut self, terminator: &str) {
+        match self.rest().find(terminator) {
+            Some(end) => self.advance(end + terminator.len()),
+            None => self.position = self.contents.len(),
+        }
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.advance(rest.len() - trimmed.len());
+            if self.rest().starts_with("/*") {
+                self.advance_past("*/");
+            } else if self.rest().starts_with("//") {
+                self.advance_past("\n");
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn scan(&mut self) {
+        while let Some(c) = self.rest().chars().next() {
+            let rest = self.rest();
+            if rest.starts_with("/*") {
+                self.advance_past("*/");
+            } else if rest.starts_with("//") {
+                self.advance_past("\n");
+            } else if c == '"' || c == '\'' {
+                self.string();
+            } else if is_identifier_char(c) {
+                // Consume whole identifiers, so that e.g. `my-url(` is not mistaken for `url(`.
+                let end = rest
+                    .find(|c: char| !is_identifier_char(c))
+                    .unwrap_or(rest.len());
+                let is_url =
+                    rest[..end].eq_ignore_ascii_case("url") && rest[end..].starts_with('(');
+                self.advance(end);
+                if is_url {
+                    self.advance(1);
+                    if let Some((start, url)) = self.url() {
+                        self.push(start, Reference::Asset(url));
+                    }
+                }
+            } else if starts_with_keyword(rest, "@import") {
+                self.advance("@import".len());
+                self.import();
+            } else if starts_with_keyword(rest, "@use") {
+                self.advance("@use".len());
+                self.use_or_forward();
+            } else if starts_with_keyword(rest, "@forward") {
+                self.advance("@forward".len());
+                self.use_or_forward();
+            } else {
+                self.advance(c.len_utf8());
+            }
+        }
+    }
+
+    /// Consume a quoted string, returning its start position and its unquoted content.
+    fn string(&mut self) -> Option<(usize, String)> {
+        let start = self.position;
+        let quote = self.rest().chars().next()?;
+        self.advance(1);
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some((_, escaped)) = chars.next() {
+                        value.push(escaped);
+                    }
+                }
+                '\n' => {
+                    // An unterminated string.
+                    self.advance(i);
+                    return None;
+                }
+                c if c == quote => {
+                    self.advance(i + 1);
+                    return Some((start, value));
+                }
+                c => value.push(c),
+            }
+        }
+        self.position = self.contents.len();
+        None
+    }
+
+    /// Consume the remainder of a `url(`, which may or may not be quoted.
+    fn url(&mut self) -> Option<(usize, String)> {
+        self.skip_trivia();
+        let start = self.position;
+        let url = if let Some('"' | '\'') = self.rest().chars().next() {
+            self.string()?.1
+        } else {
+            let end = self.rest().find(')').unwrap_or(self.rest().len());
+            let url = self.rest()[..end].trim().to_owned();
+            self.advance(end);
+            url
+        };
+        self.advance_past(")");
+        Some((start, url))
+    }
+
+    /// `@import` takes a comma separated list of strings or `url()`s, optionally followed by
+    /// media queries, and in LESS preceded by options such as `(reference)`.
+    fn import(&mut self) {
+        loop {
+            self.skip_trivia();
+            let rest = self.rest();
+            match rest.chars().next() {
+                Some('"' | '\'') => {
+                    if let Some((start, import)) = self.string() {
+                        self.push(start, Reference::Stylesheet(import));
+                    }
+                }
+                Some(_) if starts_with_ignore_case(rest, "url(") => {
+                    self.advance("url(".len());
+                    if let Some((start, import)) = self.url() {
+                        self.push(start, Reference::Stylesheet(import));
+                    }
+                }
+                Some('(') => self.advance_past(")"),
+                Some(',') => self.advance(1),
+                _ => return,
+            }
+        }
+    }
+
+    /// `@use` and `@forward` take a single string, followed by configuration which might itself
+    /// contain strings, e.g. `@use "theme" with ($font: "Helvetica")`.
+    fn use_or_forward(&mut self) {
+        self.skip_trivia();
+        if let Some('"' | '\'') = self.rest().chars().next() {
+            if let Some((start, module)) = self.string() {
+                // Built-in modules, e.g. `sass:math`.
+                if !module.starts_with("sass:") {
+                    self.push(start, Reference::Stylesheet(module));
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, start: usize, reference: Reference) {
+        let (Reference::Stylesheet(path) | Reference::Asset(path)) = &reference;
+        let line = self.contents[..start].matches('\n').count();
+        if is_local_file(path) && !self.ignored_lines.contains(&line) {
+            self.references.push(reference);
+        }
+    }
+}
+
+fn starts_with_ignore_case(s: &str, prefix: &str) -> bool {
+    s.get(..prefix.len())
+        .map_or(false, |start| start.eq_ignore_ascii_case(prefix))
+}
+
+/// Whether `s` starts with an at-keyword, which must not continue as a longer identifier.
+fn starts_with_keyword(s: &str, keyword: &str) -> bool {
+    starts_with_ignore_case(s, keyword)
+        && !s[keyword.len()..]
+            .chars()
+            .next()
+            .map_or(false, is_identifier_char)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+fn is_local_file(path: &str) -> bool {
+    let is_interpolated = path.contains("#{") || path.contains("@{") || path.contains('$');
+    let has_scheme = path.split_once(':').map_or(false, |(scheme, _)| {
+        scheme != "pkg" && !scheme.contains('/')
+    });
+    !(path.is_empty()
+        || path.starts_with('#')
+        || path.starts_with("//")
+        || has_scheme
+        || is_interpolated)
+}
+
+#[cfg(test)]
+mod tests;
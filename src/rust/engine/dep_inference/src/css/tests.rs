@@ -0,0 +1,276 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::css::{collect_references, get_dependencies, Reference};
+use protos::gen::pants::cache::CssInferenceMetadata;
+
+fn assert_references(code: &str, references: &[Reference]) {
+    assert_eq!(references, collect_references(code).as_slice());
+}
+
+fn stylesheet(path: &str) -> Reference {
+    Reference::Stylesheet(path.to_owned())
+}
+
+fn asset(path: &str) -> Reference {
+    Reference::Asset(path.to_owned())
+}
+
+fn assert_dependencies(
+    filepath: &str,
+    code: &str,
+    file_imports: &[&str],
+    package_imports: &[&str],
+) {
+    let result = get_dependencies(
+        code,
+        PathBuf::from(filepath),
+        CssInferenceMetadata {
+            package_root: "root".to_owned(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        file_imports
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<HashSet<_>>(),
+        result.file_imports.into_iter().collect::<HashSet<_>>()
+    );
+    assert_eq!(
+        package_imports
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<HashSet<_>>(),
+        result.package_imports.into_iter().collect::<HashSet<_>>()
+    );
+}
+
+#[test]
+fn css_imports() {
+    assert_references(
+        r#"
+        @import "a.css";
+        @import 'b.css' screen;
+        @import url("c.css");
+        @import url(d.css) print, screen;
+        @IMPORT "e.css";
+        "#,
+        &[
+            stylesheet("a.css"),
+            stylesheet("b.css"),
+            stylesheet("c.css"),
+            stylesheet("d.css"),
+            stylesheet("e.css"),
+        ],
+    )
+}
+
+#[test]
+fn multiple_imports() {
+    assert_references(
+        r#"@import "a", 'b', url(c);"#,
+        &[stylesheet("a"), stylesheet("b"), stylesheet("c")],
+    )
+}
+
+#[test]
+fn less_import_options() {
+    assert_references(
+        r#"@import (reference, optional) "mixins";"#,
+        &[stylesheet("mixins")],
+    )
+}
+
+#[test]
+fn use_and_forward() {
+    assert_references(
+        r#"
+        @use "sass:math";
+        @use "theme" with ($font: "Helvetica");
+        @use 'colors' as c;
+        @forward "src/list" hide list-reset;
+        "#,
+        &[
+            stylesheet("theme"),
+            stylesheet("colors"),
+            stylesheet("src/list"),
+        ],
+    )
+}
+
+#[test]
+fn urls() {
+    assert_references(
+        r#"
+        .a { background: url("images/a.png"); }
+        .b { background: url( 'images/b.png' ); }
+        .c { background: url(images/c.png) no-repeat; }
+        @font-face { src: url(fonts/d.woff2) format("woff2"), url(fonts/d.woff); }
+        "#,
+        &[
+            asset("images/a.png"),
+            asset("images/b.png"),
+            asset("images/c.png"),
+            asset("fonts/d.woff2"),
+            asset("fonts/d.woff"),
+        ],
+    )
+}
+
+#[test]
+fn skip_non_local_references() {
+    assert_references(
+        r##"
+        @import "https://fonts.googleapis.com/css?family=Roboto";
+        @import url(//cdn.example.com/reset.css);
+        .a { background: url(data:image/png;base64,iVBORw0KGgo=); }
+        .b { fill: url(#gradient); }
+        .c { background: url(""); }
+        .d { background: url("#{$root}/e.png"); }
+        .e { background: url("@{root}/e.png"); }
+        .f { background: url($image); }
+        "##,
+        &[],
+    )
+}
+
+#[test]
+fn skip_comments_and_strings() {
+    assert_references(
+        r#"
+        /* @import "a.css"; url(a.png) */
+        // @use "b";
+        .c::before { content: "@import 'c.css'"; }
+        .d { background: url(d.png); } /* url(e.png) */
+        "#,
+        &[asset("d.png")],
+    )
+}
+
+#[test]
+fn keywords_are_not_prefixes() {
+    assert_references(
+        r#"
+        @imports "a";
+        @user "b";
+        .c { background: my-url(c.png); }
+        .d { background: URL(d.png); }
+        "#,
+        &[asset("d.png")],
+    )
+}
+
+#[test]
+fn ignore_pragma() {
+    assert_references(
+        r#"
+        @import "a"; // pants: no-infer-dep
+        @import "b";
+        .c { background: url(c.png); } /* pants: no-infer-dep */
+        "#,
+        &[stylesheet("b")],
+    )
+}
+
+#[test]
+fn css_file_imports() {
+    assert_dependencies(
+        "root/styles/main.css",
+        r#"
+        @import "base.css";
+        @import "../shared/reset.css";
+        @import "/vendor/grid.css";
+        .a { background: url("../images/a.png?v=1"); }
+        .b { fill: url(icons.svg#check); }
+        "#,
+        &[
+            "root/styles/base.css",
+            "root/shared/reset.css",
+            "root/vendor/grid.css",
+            "root/images/a.png",
+            "root/styles/icons.svg",
+        ],
+        &[],
+    )
+}
+
+#[test]
+fn scss_candidates() {
+    assert_dependencies(
+        "root/styles/main.scss",
+        r#"@use "./theme";"#,
+        &[
+            "root/styles/theme.scss",
+            "root/styles/_theme.scss",
+            "root/styles/theme/index.scss",
+            "root/styles/theme/_index.scss",
+            "root/styles/theme.sass",
+            "root/styles/_theme.sass",
+            "root/styles/theme/index.sass",
+            "root/styles/theme/_index.sass",
+            "root/styles/theme.css",
+            "root/styles/_theme.css",
+            "root/styles/theme/index.css",
+            "root/styles/theme/_index.css",
+        ],
+        &[],
+    )
+}
+
+#[test]
+fn scss_candidates_with_extension() {
+    assert_dependencies(
+        "root/main.scss",
+        r#"@import "variables.scss";"#,
+        &["root/variables.scss", "root/_variables.scss"],
+        &["variables.scss"],
+    )
+}
+
+#[test]
+fn scss_load_path_fallback() {
+    assert_dependencies(
+        "root/main.scss",
+        r#"@use "bootstrap/scss/bootstrap.scss";"#,
+        &[
+            "root/bootstrap/scss/bootstrap.scss",
+            "root/bootstrap/scss/_bootstrap.scss",
+        ],
+        &["bootstrap/scss/bootstrap.scss"],
+    )
+}
+
+#[test]
+fn package_imports() {
+    assert_dependencies(
+        "root/main.scss",
+        r#"
+        @import "~normalize.css/normalize";
+        @use "pkg:@material/button";
+        "#,
+        &[],
+        &["normalize.css/normalize", "@material/button"],
+    )
+}
+
+#[test]
+fn less_candidates() {
+    assert_dependencies(
+        "root/main.less",
+        r#"
+        @import (reference) "mixins";
+        @import "theme.css";
+        "#,
+        &["root/mixins.less", "root/theme.css"],
+        &["mixins", "theme.css"],
+    )
+}
+
+#[test]
+fn references_outside_the_build_root() {
+    assert_dependencies("main.css", r#"@import "../../a.css";"#, &[], &[])
+}
@@ -0,0 +1,229 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Dependency inference for Dockerfiles: the images which stages are built from, and the files
+//! which are copied from the build context.
+//!
+//! Dockerfiles have a line-based syntax, so they are scanned without a tree-sitter grammar.
+use serde_derive::{Deserialize, Serialize};
+
+use crate::dockerfile::syntax::{expand, split_words, whole_arg_reference, Args, Instruction};
+
+mod syntax;
+
+include!(concat!(env!("OUT_DIR"), "/dockerfile_impl_hash.rs"));
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DockerfileStage {
+    /// The `AS` name of the stage.
+    pub alias: Option<String>,
+    /// The image of the `FROM` instruction as written, e.g. `python:${PYTHON_VERSION}`.
+    pub image: String,
+    /// The image with the `ARG`s which it references substituted, if their values are known.
+    pub resolved_image: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ParsedDockerfileDependencies {
+    /// Every `ARG`, as `NAME` or `NAME=DEFAULT`.
+    pub build_args: Vec<String>,
+    pub stages: Vec<DockerfileStage>,
+    /// The resolved images which the stages are built from or copy files from, other than those of
+    /// the stages themselves and `scratch`.
+    pub image_references: Vec<String>,
+    /// The paths which `COPY` and `ADD` instructions copy from the build context.
+    pub copy_source_paths: Vec<String>,
+    /// The `ARG`s which are `COPY` or `ADD` source paths, as `NAME=VALUE`.
+    pub copy_build_args: Vec<String>,
+    /// The `ARG`s which are the image of a stage, as `NAME=DEFAULT`.
+    pub from_image_build_args: Vec<String>,
+    /// The tag of the image of each stage, as `STAGE TAG`, where the stage is its alias or index
+    /// (e.g. `stage0`), and the tag is `build-arg:NAME` for an image which is an `ARG`.
+    pub version_tags: Vec<String>,
+}
+
+pub fn get_dependencies(contents: &str) -> ParsedDockerfileDependencies {
+    let dockerfile = syntax::parse(contents);
+    let mut analyzer = Analyzer {
+        escape: dockerfile.escape,
+        args: Args::new(),
+        stage_names: vec![],
+        result: ParsedDockerfileDependencies::default(),
+    };
+    for instruction in dockerfile.instructions {
+        analyzer.visit(instruction);
+    }
+    analyzer.result
+}
+
+struct Analyzer {
+    escape: char,
+    /// The `ARG`s declared so far. Unlike `docker build`, which scopes `ARG`s to a stage (or to
+    /// the `FROM`s for those declared before the first one), these are used for any instruction
+    /// which follows them, like the Python-based parser does.
+    args: Args,
+    /// The lower-cased alias of each stage so far, since stage names are case-insensitive.
+    stage_names: Vec<Option<String>>,
+    result: ParsedDockerfileDependencies,
+}
+
+impl Analyzer {
+    fn visit(&mut self, instruction: Instruction) {
+        let words = split_words(&instruction.arguments, self.escape);
+        match instruction.keyword.as_str() {
+            "ARG" => self.visit_arg(words),
+            "FROM" => self.visit_from(words),
+            "COPY" => self.visit_copy(words, false),
+            "ADD" => self.visit_copy(words, true),
+            _ => (),
+        }
+    }
+
+    fn visit_arg(&mut self, words: Vec<String>) {
+        for word in words {
+            let (name, value) = match word.split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value)),
+                None => (word.clone(), None),
+            };
+            let resolved = match value {
+                Some(value) => expand(value, self.escape, &self.args),
+                // A redeclared `ARG` keeps its default.
+                None => self.args.get(&name).cloned().flatten(),
+            };
+            self.result.build_args.push(match (value, &resolved) {
+                (Some(_), Some(resolved)) => format!("{name}={resolved}"),
+                (Some(value), None) => format!("{name}={}", value.trim_matches(['"', '\''])),
+                (None, _) => name.clone(),
+            });
+            self.args.insert(name, resolved);
+        }
+    }
+
+    fn visit_from(&mut self, words: Vec<String>) {
+        let mut words = words.into_iter().skip_while(|word| word.starts_with("--"));
+        let Some(image) = words.next() else {
+            return;
+        };
+        let alias = match (words.next(), words.next()) {
+            (Some(keyword), Some(alias)) if keyword.eq_ignore_ascii_case("as") => Some(alias),
+            _ => None,
+        };
+
+        let resolved_image = expand(&image, self.escape, &self.args);
+        if let Some(resolved) = &resolved_image {
+            if !self.is_stage(resolved) && resolved != "scratch" {
+                self.insert_image_reference(resolved.clone());
+            }
+        }
+        if let Some(name) = whole_arg_reference(&image) {
+            if let Some(Some(value)) = self.args.get(name) {
+                self.result
+                    .from_image_build_args
+                    .push(format!("{name}={value}"));
+            }
+        }
+        let stage = alias
+            .clone()
+            .unwrap_or_else(|| format!("stage{}", self.stage_names.len()));
+        if let Some(tag) = image_tag(&image) {
+            self.result.version_tags.push(format!("{stage} {tag}"));
+        }
+
+        self.stage_names
+            .push(alias.as_ref().map(|alias| alias.to_lowercase()));
+        self.result.stages.push(DockerfileStage {
+            alias,
+            image,
+            resolved_image,
+        });
+    }
+
+    fn visit_copy(&mut self, words: Vec<String>, is_add: bool) {
+        let mut from = None;
+        let mut words = words.into_iter().peekable();
+        while let Some(flag) = words.next_if(|word| word.starts_with("--")) {
+            if let Some(value) = flag.strip_prefix("--from=") {
+                from = Some(value.to_owned());
+            }
+        }
+        let words: Vec<String> = words.collect();
+
+        if let Some(from) = from {
+            // Files copied from another stage or image are not in the build context.
+            if let Some(from) = expand(&from, self.escape, &self.args) {
+                if from.parse::<usize>().is_err() && !self.is_stage(&from) {
+                    self.insert_image_reference(from);
+                }
+            }
+            return;
+        }
+
+        // The exec form, e.g. `COPY ["a file", "dest/"]`.
+        let exec_form = words
+            .first()
+            .filter(|word| word.starts_with('['))
+            .and_then(|_| serde_json::from_str::<Vec<String>>(&words.join(" ")).ok());
+        let mut paths = exec_form.unwrap_or(words);
+        // The last path is the destination.
+        paths.pop();
+
+        for path in paths {
+            // Heredocs are inline files, and `ADD` may also fetch URLs and git repositories.
+            if path.starts_with("<<")
+                || (is_add && (path.contains("://") || path.starts_with("git@")))
+            {
+                continue;
+            }
+            if let Some(name) = whole_arg_reference(&path) {
+                // An `ARG` without a value may be given one by the build, but cannot be inferred.
+                if let Some(Some(value)) = self.args.get(name) {
+                    self.result.copy_build_args.push(format!("{name}={value}"));
+                }
+            } else if let Some(path) = expand(&path, self.escape, &self.args) {
+                self.result.copy_source_paths.push(path);
+            }
+        }
+    }
+
+    /// Whether a `FROM` image or `COPY --from` refers to a previous stage.
+    fn is_stage(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.stage_names
+            .iter()
+            .any(|stage_name| stage_name.as_deref() == Some(name.as_str()))
+    }
+
+    fn insert_image_reference(&mut self, image: String) {
+        if !self.result.image_references.contains(&image) {
+            self.result.image_references.push(image);
+        }
+    }
+}
+
+/// The tag of an image reference (`[registry/]repository[:tag][@digest]`), which defaults to
+/// `latest` unless there is a digest.
+fn image_tag(image: &str) -> Option<String> {
+    let name = image.rsplit('/').next().unwrap_or(image);
+    if let Some(arg) = whole_arg_reference(name) {
+        return Some(format!("build-arg:{arg}"));
+    }
+    let (name, digest) = match name.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (name, None),
+    };
+    let (repository, tag) = match name.split_once(':') {
+        Some((repository, tag)) => (repository, Some(tag)),
+        None => (name, None),
+    };
+    if repository.is_empty() || tag == Some("") || digest == Some("") {
+        return None;
+    }
+    match (tag, digest) {
+        (Some(tag), _) => Some(tag.to_owned()),
+        (None, Some(_)) => None,
+        (None, None) => Some("latest".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests;
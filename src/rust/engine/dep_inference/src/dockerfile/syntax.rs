@@ -0,0 +1,230 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! The lexical structure of a Dockerfile: parser directives, comments, line continuations,
+//! heredocs, and the quoting and variable substitution rules of instruction arguments.
+use std::collections::{HashMap, VecDeque};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The values of the `ARG`s in scope, which are `None` when they are declared without a default
+/// (or with one which cannot be statically resolved).
+pub(crate) type Args = HashMap<String, Option<String>>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Instruction {
+    /// The upper-cased keyword, e.g. `FROM`.
+    pub keyword: String,
+    /// The rest of the instruction, with line continuations removed.
+    pub arguments: String,
+}
+
+pub(crate) struct Dockerfile {
+    /// The escape character, which is `\` unless overridden by an `# escape=` parser directive.
+    pub escape: char,
+    pub instructions: Vec<Instruction>,
+}
+
+pub(crate) fn parse(contents: &str) -> Dockerfile {
+    let mut escape = '\\';
+    let mut lines = contents.lines().peekable();
+    // Parser directives are only recognized before any comment, blank line or instruction.
+    while let Some((key, value)) = lines.peek().and_then(|line| parser_directive(line)) {
+        if key.eq_ignore_ascii_case("escape") && matches!(value, "\\" | "`") {
+            escape = value.chars().next().unwrap();
+        }
+        lines.next();
+    }
+
+    let mut instructions = vec![];
+    let mut heredocs = VecDeque::new();
+    let mut current = String::new();
+    for line in lines {
+        if let Some((terminator, strip_tabs)) = heredocs.front() {
+            let line = if *strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                line
+            };
+            if line == terminator {
+                heredocs.pop_front();
+            }
+            continue;
+        }
+        let trimmed = line.trim();
+        // Comments and blank lines are also skipped within continued instructions.
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let line = line.trim_end();
+        if let Some(continued) = line.strip_suffix(escape) {
+            current.push_str(continued);
+            continue;
+        }
+        current.push_str(line);
+        if let Some(instruction) = Instruction::parse(&std::mem::take(&mut current)) {
+            if matches!(instruction.keyword.as_str(), "RUN" | "COPY" | "ADD") {
+                heredocs.extend(heredoc_terminators(&instruction.arguments));
+            }
+            instructions.push(instruction);
+        }
+    }
+    instructions.extend(Instruction::parse(&current));
+
+    Dockerfile {
+        escape,
+        instructions,
+    }
+}
+
+impl Instruction {
+    fn parse(line: &str) -> Option<Instruction> {
+        let line = line.trim();
+        let (keyword, arguments) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        (!keyword.is_empty()).then(|| Instruction {
+            keyword: keyword.to_ascii_uppercase(),
+            arguments: arguments.trim().to_owned(),
+        })
+    }
+}
+
+/// A `# key=value` parser directive.
+fn parser_directive(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.trim().strip_prefix('#')?.split_once('=')?;
+    let key = key.trim();
+    (!key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric()))
+        .then_some((key, value.trim()))
+}
+
+/// The terminators of the heredocs (e.g. `<<EOF` or `<<-"EOF"`) which an instruction starts, and
+/// whether leading tabs are stripped from their lines.
+fn heredoc_terminators(arguments: &str) -> Vec<(String, bool)> {
+    arguments
+        .split("<<")
+        .skip(1)
+        .filter_map(|rest| {
+            let (rest, strip_tabs) = match rest.strip_prefix('-') {
+                Some(rest) => (rest, true),
+                None => (rest, false),
+            };
+            let rest = rest.trim_start_matches(['"', '\'']);
+            let terminator: String = rest
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect();
+            (!terminator.is_empty()).then_some((terminator, strip_tabs))
+        })
+        .collect()
+}
+
+/// Split the arguments of an instruction into whitespace-separated words, keeping their quotes and
+/// escapes.
+pub(crate) fn split_words(arguments: &str, escape: char) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quote = None;
+    let mut chars = arguments.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() && quote.is_none() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c if c == escape && quote != Some('\'') => {
+                word.push(c);
+                word.extend(chars.next());
+            }
+            '"' | '\'' if quote.is_none() => {
+                quote = Some(c);
+                word.push(c);
+            }
+            c if Some(c) == quote => {
+                quote = None;
+                word.push(c);
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Remove the quotes and escapes of a word and substitute the `ARG`s which it references, unless
+/// any of them has an unknown value.
+pub(crate) fn expand(word: &str, escape: char, args: &Args) -> Option<String> {
+    let mut result = String::new();
+    let mut quote = None;
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c == escape && quote != Some('\'') => result.extend(chars.next()),
+            '"' | '\'' if quote.is_none() => quote = Some(c),
+            c if Some(c) == quote => quote = None,
+            '$' if quote != Some('\'') => {
+                result.push_str(&expand_variable(&mut chars, escape, args)?)
+            }
+            c => result.push(c),
+        }
+    }
+    Some(result)
+}
+
+/// Expand `$NAME`, `${NAME}`, `${NAME:-default}` or `${NAME:+alternative}`, just after the `$`.
+fn expand_variable(chars: &mut Peekable<Chars>, escape: char, args: &Args) -> Option<String> {
+    let braced = chars.next_if_eq(&'{').is_some();
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+        name.push(c);
+    }
+    if !braced {
+        if name.is_empty() {
+            return Some("$".to_owned());
+        }
+        return args.get(&name).cloned().flatten();
+    }
+
+    let mut modifier = String::new();
+    let mut depth = 0;
+    for c in chars.by_ref() {
+        match c {
+            '}' if depth == 0 => break,
+            '}' => depth -= 1,
+            '{' => depth += 1,
+            _ => (),
+        }
+        modifier.push(c);
+    }
+    let value = args.get(&name).cloned().flatten();
+    let is_set = value.as_ref().map_or(false, |value| !value.is_empty());
+    if modifier.is_empty() {
+        value
+    } else if let Some(default) = modifier.strip_prefix(":-") {
+        if is_set {
+            value
+        } else {
+            expand(default, escape, args)
+        }
+    } else if let Some(alternative) = modifier.strip_prefix(":+") {
+        if is_set {
+            expand(alternative, escape, args)
+        } else {
+            Some(String::new())
+        }
+    } else {
+        None
+    }
+}
+
+/// The name of the `ARG` which a word consists of entirely, e.g. `NAME` for `$NAME` or `${NAME}`.
+pub(crate) fn whole_arg_reference(word: &str) -> Option<&str> {
+    let reference = word.strip_prefix('$')?;
+    let name = match reference.strip_prefix('{') {
+        Some(braced) => braced.strip_suffix('}')?,
+        None => reference,
+    };
+    (!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .then_some(name)
+}
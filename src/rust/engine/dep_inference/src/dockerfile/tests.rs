@@ -0,0 +1,218 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::dockerfile::syntax::{expand, parse, split_words, Args, Instruction};
+use crate::dockerfile::{get_dependencies, DockerfileStage};
+
+fn stage(alias: Option<&str>, image: &str, resolved_image: Option<&str>) -> DockerfileStage {
+    DockerfileStage {
+        alias: alias.map(str::to_owned),
+        image: image.to_owned(),
+        resolved_image: resolved_image.map(str::to_owned),
+    }
+}
+
+fn instruction(keyword: &str, arguments: &str) -> Instruction {
+    Instruction {
+        keyword: keyword.to_owned(),
+        arguments: arguments.to_owned(),
+    }
+}
+
+#[test]
+fn instructions() {
+    let dockerfile = parse(
+        r#"# syntax=docker/dockerfile:1
+# escape=`
+
+# A comment.
+from python:3.11 `
+  AS base
+RUN apt-get update && `
+    # A comment within a continued instruction.
+
+    apt-get install -y git
+COPY <<EOF /app/config.ini
+FROM not-an-instruction
+EOF
+RUN <<-"ONE" cat <<TWO
+	FROM not-an-instruction
+	ONE
+FROM not-an-instruction-either
+TWO
+CMD ["python"]
+"#,
+    );
+    assert_eq!('`', dockerfile.escape);
+    assert_eq!(
+        vec![
+            instruction("FROM", "python:3.11   AS base"),
+            instruction("RUN", "apt-get update &&     apt-get install -y git"),
+            instruction("COPY", "<<EOF /app/config.ini"),
+            instruction("RUN", r#"<<-"ONE" cat <<TWO"#),
+            instruction("CMD", r#"["python"]"#),
+        ],
+        dockerfile.instructions
+    );
+}
+
+#[test]
+fn words_and_expansion() {
+    let args: Args = [
+        ("VERSION".to_owned(), Some("3.11".to_owned())),
+        ("EMPTY".to_owned(), Some(String::new())),
+        ("UNKNOWN".to_owned(), None),
+    ]
+    .into_iter()
+    .collect();
+    let expand = |word: &str| expand(word, '\\', &args);
+
+    assert_eq!(
+        vec!["a", r#""b c""#, r"d\ e", "'$f'"],
+        split_words(r#"a  "b c" d\ e '$f'"#, '\\')
+    );
+    assert_eq!(Some("python:3.11".to_owned()), expand("python:$VERSION"));
+    assert_eq!(Some("python:3.11".to_owned()), expand("python:${VERSION}"));
+    assert_eq!(Some("b c".to_owned()), expand(r#""b c""#));
+    assert_eq!(Some("$VERSION".to_owned()), expand("'$VERSION'"));
+    assert_eq!(Some("$VERSION".to_owned()), expand(r"\$VERSION"));
+    assert_eq!(Some("3.10".to_owned()), expand("${EMPTY:-3.10}"));
+    assert_eq!(Some("3.10".to_owned()), expand("${UNKNOWN:-3.10}"));
+    assert_eq!(Some("3.11".to_owned()), expand("${VERSION:-3.10}"));
+    assert_eq!(Some("-slim".to_owned()), expand("${VERSION:+-slim}"));
+    assert_eq!(Some(String::new()), expand("${EMPTY:+-slim}"));
+    assert_eq!(None, expand("$UNKNOWN"));
+    assert_eq!(None, expand("${UNDECLARED}"));
+    assert_eq!(None, expand("${VERSION%.*}"));
+}
+
+#[test]
+fn stages_and_image_references() {
+    let result = get_dependencies(
+        r#"
+ARG REGISTRY=registry.example.com
+ARG PYTHON_VERSION=3.11
+ARG BASE_IMAGE
+FROM --platform=$BUILDPLATFORM $REGISTRY/python:${PYTHON_VERSION}-slim AS builder
+FROM builder as tests
+FROM ${BASE_IMAGE}
+FROM scratch
+COPY --from=builder /app /app
+COPY --from=1 /tests /tests
+COPY --from=busybox:1.36 /bin/busybox /bin/busybox
+"#,
+    );
+    assert_eq!(
+        vec![
+            stage(
+                Some("builder"),
+                "$REGISTRY/python:${PYTHON_VERSION}-slim",
+                Some("registry.example.com/python:3.11-slim"),
+            ),
+            stage(Some("tests"), "builder", Some("builder")),
+            stage(None, "${BASE_IMAGE}", None),
+            stage(None, "scratch", Some("scratch")),
+        ],
+        result.stages
+    );
+    assert_eq!(
+        vec!["registry.example.com/python:3.11-slim", "busybox:1.36"],
+        result.image_references
+    );
+    assert_eq!(
+        vec![
+            "REGISTRY=registry.example.com",
+            "PYTHON_VERSION=3.11",
+            "BASE_IMAGE"
+        ],
+        result.build_args
+    );
+}
+
+#[test]
+fn from_image_build_args() {
+    let result = get_dependencies(
+        r#"
+ARG BASE_IMAGE=src/docker/base:image
+ARG OTHER_IMAGE
+FROM $BASE_IMAGE
+ARG STAGE_IMAGE="src/docker/stage:image"
+FROM ${OTHER_IMAGE} AS other
+FROM $STAGE_IMAGE
+FROM ${BASE_IMAGE}-slim
+"#,
+    );
+    assert_eq!(
+        vec![
+            "BASE_IMAGE=src/docker/base:image",
+            "STAGE_IMAGE=src/docker/stage:image"
+        ],
+        result.from_image_build_args
+    );
+    assert_eq!(
+        vec![
+            "src/docker/base:image",
+            "src/docker/stage:image",
+            "src/docker/base:image-slim"
+        ],
+        result.image_references
+    );
+}
+
+#[test]
+fn version_tags() {
+    let result = get_dependencies(
+        r#"
+FROM base:1.0 AS build
+FROM interim
+FROM $argname as dynamic
+FROM registry:5000/repo/image@sha256:abcdef
+FROM registry:5000/repo/image:2.0@sha256:abcdef AS pinned
+FROM final as out
+"#,
+    );
+    assert_eq!(
+        vec![
+            "build 1.0",
+            "stage1 latest",
+            "dynamic build-arg:argname",
+            "pinned 2.0",
+            "out latest",
+        ],
+        result.version_tags
+    );
+}
+
+#[test]
+fn copy_source_paths() {
+    let result = get_dependencies(
+        r#"
+ARG GLOBAL_DIR=global
+FROM python:3.11
+ARG CONFIG=src/config.ini
+ARG UNSET_FILE
+ARG GLOBAL_DIR
+ARG CONFIG
+COPY --chown=app:app requirements.txt setup.py /app/
+COPY ["a file.txt", "/app/"]
+COPY $CONFIG /app/config.ini
+COPY ${UNSET_FILE} /app/
+COPY $GLOBAL_DIR/file.txt ${UNSET_FILE}/other.txt /app/
+ADD archive.tar.gz https://example.com/file.txt git@github.com:pantsbuild/pants.git /app/
+COPY <<EOF /app/inline.txt
+contents
+EOF
+"#,
+    );
+    assert_eq!(
+        vec![
+            "requirements.txt",
+            "setup.py",
+            "a file.txt",
+            "global/file.txt",
+            "archive.tar.gz",
+        ],
+        result.copy_source_paths
+    );
+    assert_eq!(vec!["CONFIG=src/config.ini"], result.copy_build_args);
+}
@@ -0,0 +1,295 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Build constraints, as described in https://pkg.go.dev/cmd/go#hdr-Build_constraints.
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::Path;
+
+use fnv::FnvHashSet as HashSet;
+
+/// The operating systems and architectures which may be used as file name suffixes, per Go's
+/// `internal/syslist`.
+const KNOWN_OS: &[&str] = &[
+    "aix",
+    "android",
+    "darwin",
+    "dragonfly",
+    "freebsd",
+    "hurd",
+    "illumos",
+    "ios",
+    "js",
+    "linux",
+    "nacl",
+    "netbsd",
+    "openbsd",
+    "plan9",
+    "solaris",
+    "wasip1",
+    "windows",
+    "zos",
+];
+const KNOWN_ARCH: &[&str] = &[
+    "386",
+    "amd64",
+    "amd64p32",
+    "arm",
+    "armbe",
+    "arm64",
+    "arm64be",
+    "loong64",
+    "mips",
+    "mipsle",
+    "mips64",
+    "mips64le",
+    "mips64p32",
+    "mips64p32le",
+    "ppc",
+    "ppc64",
+    "ppc64le",
+    "riscv",
+    "riscv64",
+    "s390",
+    "s390x",
+    "sparc",
+    "sparc64",
+    "wasm",
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Expr {
+    Tag(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub(crate) fn eval(&self, tags: &HashSet<&str>) -> bool {
+        match self {
+            Expr::Tag(tag) => tags.contains(tag.as_str()),
+            Expr::Not(expr) => !expr.eval(tags),
+            Expr::And(left, right) => left.eval(tags) && right.eval(tags),
+            Expr::Or(left, right) => left.eval(tags) || right.eval(tags),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(..) => 1,
+            Expr::And(..) => 2,
+            Expr::Not(..) | Expr::Tag(..) => 3,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+/// Renders the expression in `//go:build` syntax, with only the parentheses which are needed.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Tag(tag) => write!(f, "{tag}"),
+            Expr::Not(expr) => {
+                write!(f, "!")?;
+                expr.fmt_operand(f, 3)
+            }
+            Expr::And(left, right) => {
+                left.fmt_operand(f, 2)?;
+                write!(f, " && ")?;
+                right.fmt_operand(f, 2)
+            }
+            Expr::Or(left, right) => {
+                left.fmt_operand(f, 1)?;
+                write!(f, " || ")?;
+                right.fmt_operand(f, 1)
+            }
+        }
+    }
+}
+
+///
+/// The build constraint of a file, from the comments which precede its package clause.
+///
+/// A `//go:build` line takes precedence over `// +build` lines, which only count when their
+/// comment block is followed by a blank line (so that they are not part of the package docs).
+///
+pub(crate) fn header_constraint(contents: &str) -> Result<Option<Expr>, String> {
+    let mut go_build = None;
+    let mut plus_build = vec![];
+    let mut pending_plus_build = vec![];
+    let mut in_block_comment = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if in_block_comment {
+            if let Some(end) = line.find("*/") {
+                in_block_comment = false;
+                if !line[end + 2..].trim().is_empty() {
+                    break;
+                }
+            }
+            continue;
+        }
+        if line.is_empty() {
+            plus_build.append(&mut pending_plus_build);
+        } else if let Some(comment) = line.strip_prefix("/*") {
+            pending_plus_build.clear();
+            in_block_comment = !comment.contains("*/");
+        } else if let Some(comment) = line.strip_prefix("//") {
+            if let Some(expr) = strip_directive(comment, "go:build") {
+                if go_build.is_some() {
+                    return Err("multiple //go:build comments".to_owned());
+                }
+                go_build = Some(parse_expr(expr)?);
+            } else if let Some(options) = strip_directive(comment.trim_start(), "+build") {
+                pending_plus_build.push(parse_plus_build(options)?);
+            }
+        } else {
+            break;
+        }
+    }
+    Ok(go_build.or_else(|| {
+        plus_build
+            .into_iter()
+            .reduce(|left, right| Expr::And(Box::new(left), Box::new(right)))
+    }))
+}
+
+/// Whether a file is built for the given tags, according to its `_GOOS`, `_GOARCH` or
+/// `_GOOS_GOARCH` file name suffix (before any `_test` suffix).
+pub(crate) fn file_name_matches(filepath: &Path, tags: &HashSet<&str>) -> bool {
+    let Some(name) = filepath.file_name().and_then(OsStr::to_str) else {
+        return true;
+    };
+    let stem = name.split_once('.').map_or(name, |(stem, _)| stem);
+    // Everything before the first underscore is the name proper, and never a constraint.
+    let Some(start) = stem.find('_') else {
+        return true;
+    };
+    let mut parts: Vec<&str> = stem[start..].split('_').collect();
+    if parts.last() == Some(&"test") {
+        parts.pop();
+    }
+    match parts.as_slice() {
+        [.., os, arch] if KNOWN_OS.contains(os) && KNOWN_ARCH.contains(arch) => {
+            tags.contains(os) && tags.contains(arch)
+        }
+        [.., last] if KNOWN_OS.contains(last) || KNOWN_ARCH.contains(last) => tags.contains(last),
+        _ => true,
+    }
+}
+
+/// The arguments of a directive, which must be followed by whitespace or the end of the line.
+fn strip_directive<'a>(comment: &'a str, directive: &str) -> Option<&'a str> {
+    let rest = comment.strip_prefix(directive)?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then_some(rest)
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+fn tag(name: &str) -> Result<Expr, String> {
+    if name.is_empty() || !name.chars().all(is_tag_char) {
+        return Err(format!("invalid build tag {name:?}"));
+    }
+    Ok(Expr::Tag(name.to_owned()))
+}
+
+/// A `// +build` line ORs its space separated options, each of which ANDs its comma separated
+/// (and possibly negated) tags.
+fn parse_plus_build(options: &str) -> Result<Expr, String> {
+    options
+        .split_whitespace()
+        .map(|option| {
+            option
+                .split(',')
+                .map(|term| match term.strip_prefix('!') {
+                    Some(negated) => Ok(Expr::Not(Box::new(tag(negated)?))),
+                    None => tag(term),
+                })
+                .reduce(|left, right| Ok(Expr::And(Box::new(left?), Box::new(right?))))
+                .unwrap()
+        })
+        .reduce(|left, right| Ok(Expr::Or(Box::new(left?), Box::new(right?))))
+        .unwrap_or_else(|| Err("empty +build line".to_owned()))
+}
+
+pub(crate) fn parse_expr(expr: &str) -> Result<Expr, String> {
+    let mut parser = ExprParser { rest: expr };
+    let parsed = parser.or()?;
+    if !parser.rest.trim().is_empty() {
+        return Err(format!("unexpected {:?} in {:?}", parser.rest.trim(), expr));
+    }
+    Ok(parsed)
+}
+
+/// A recursive descent parser for `//go:build` expressions, in which `!` binds tighter than `&&`,
+/// which binds tighter than `||`.
+struct ExprParser<'a> {
+    rest: &'a str,
+}
+
+impl ExprParser<'_> {
+    fn eat(&mut self, token: &str) -> bool {
+        self.rest = self.rest.trim_start();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            Ok(Expr::Not(Box::new(self.not()?)))
+        } else {
+            self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        if self.eat("(") {
+            let expr = self.or()?;
+            if !self.eat(")") {
+                return Err("missing )".to_owned());
+            }
+            return Ok(expr);
+        }
+        let end = self
+            .rest
+            .find(|c: char| !is_tag_char(c))
+            .unwrap_or(self.rest.len());
+        let name = &self.rest[..end];
+        self.rest = &self.rest[end..];
+        if name.is_empty() {
+            return Err("expected a build tag".to_owned());
+        }
+        tag(name)
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Dependency inference for Go: the package clause, imports and build constraints of a file, which
+//! are everything needed to decide which files make up a package and what that package imports.
+use std::path::PathBuf;
+
+use fnv::FnvHashSet as HashSet;
+use serde_derive::{Deserialize, Serialize};
+use tree_sitter::{Node, Parser};
+
+use protos::gen::pants::cache::GoInferenceMetadata;
+
+use crate::go::build_constraint::{file_name_matches, header_constraint};
+
+mod build_constraint;
+
+include!(concat!(env!("OUT_DIR"), "/go/constants.rs"));
+include!(concat!(env!("OUT_DIR"), "/go/visitor.rs"));
+include!(concat!(env!("OUT_DIR"), "/go_impl_hash.rs"));
+
+const PRAGMA: &str = "pants: no-infer-dep";
+
+#[derive(Serialize, Deserialize)]
+pub struct ParsedGoDependencies {
+    pub package_name: String,
+    /// Imported paths, along with the name they are imported as: an alias, `_` for a blank import
+    /// or `.` for a dot import.
+    pub imports: Vec<(String, Option<String>)>,
+    /// The `//go:build` constraint of the file, or the equivalent of its `// +build` lines.
+    pub build_constraint: Option<String>,
+    /// Whether the file is built with the requested build tags, according to both its build
+    /// constraint and any `_GOOS`/`_GOARCH` suffixes of its file name.
+    pub matches_build_tags: bool,
+}
+
+pub fn get_dependencies(
+    contents: &str,
+    filepath: PathBuf,
+    metadata: GoInferenceMetadata,
+) -> Result<ParsedGoDependencies, String> {
+    let tags: HashSet<&str> = metadata.build_tags.iter().map(String::as_str).collect();
+    let constraint = header_constraint(contents)
+        .map_err(|e| format!("Invalid build constraint in {}: {e}", filepath.display()))?;
+    let matches_build_tags = constraint
+        .as_ref()
+        .map_or(true, |constraint| constraint.eval(&tags))
+        && file_name_matches(&filepath, &tags);
+
+    let mut collector = ImportCollector::new(contents);
+    collector.collect();
+    Ok(ParsedGoDependencies {
+        package_name: collector.package_name,
+        imports: collector.imports,
+        build_constraint: constraint.map(|constraint| constraint.to_string()),
+        matches_build_tags,
+    })
+}
+
+struct ImportCollector<'a> {
+    package_name: String,
+    imports: Vec<(String, Option<String>)>,
+    code: &'a str,
+}
+
+impl<'a> ImportCollector<'a> {
+    fn new(code: &'a str) -> ImportCollector<'a> {
+        ImportCollector {
+            package_name: String::new(),
+            imports: vec![],
+            code,
+        }
+    }
+
+    fn collect(&mut self) {
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_go::language())
+            .expect("Error loading Go grammar");
+        let parsed = parser.parse(self.code, None);
+        let tree = parsed.unwrap();
+        let mut cursor = tree.walk();
+
+        self.walk(&mut cursor);
+    }
+
+    fn code_at(&self, range: tree_sitter::Range) -> &'a str {
+        &self.code[range.start_byte..range.end_byte]
+    }
+
+    /// Both interpreted (`"fmt"`) and raw (`` `fmt` ``) string literals are valid import paths.
+    fn string_at(&self, node: Node) -> String {
+        let literal = self.code_at(node.range());
+        literal
+            .get(1..literal.len().saturating_sub(1))
+            .unwrap_or_default()
+            .to_owned()
+    }
+
+    /// An import is ignored if a `pants: no-infer-dep` comment follows it on the same line, for
+    /// both grouped imports and single `import "fmt" // pants: no-infer-dep` declarations.
+    fn is_pragma_ignored(&self, node: Node) -> bool {
+        let row = node.end_position().row;
+        let is_ignored_by = |node: Node| {
+            node.next_sibling().map_or(false, |sibling| {
+                sibling.kind_id() == KindID::COMMENT
+                    && sibling.start_position().row == row
+                    && self.code_at(sibling.range()).contains(PRAGMA)
+            })
+        };
+        is_ignored_by(node)
+            || node.parent().map_or(false, |parent| {
+                parent.kind_id() == KindID::IMPORT_DECLARATION && is_ignored_by(parent)
+            })
+    }
+}
+
+impl Visitor for ImportCollector<'_> {
+    fn visit_package_clause(&mut self, node: Node) -> ChildBehavior {
+        if let Some(name) = node
+            .named_children(&mut node.walk())
+            .find(|child| child.kind_id() == KindID::PACKAGE_IDENTIFIER)
+        {
+            self.code_at(name.range())
+                .clone_into(&mut self.package_name);
+        }
+        ChildBehavior::Ignore
+    }
+
+    fn visit_import_spec(&mut self, node: Node) -> ChildBehavior {
+        if let Some(path) = node.child_by_field_name("path") {
+            if !self.is_pragma_ignored(node) {
+                let name = node
+                    .child_by_field_name("name")
+                    .map(|name| self.code_at(name.range()).to_owned());
+                self.imports.push((self.string_at(path), name));
+            }
+        }
+        ChildBehavior::Ignore
+    }
+
+    // Imports must precede all other declarations, so there is no need to descend into them.
+
+    fn visit_function_declaration(&mut self, _node: Node) -> ChildBehavior {
+        ChildBehavior::Ignore
+    }
+
+    fn visit_method_declaration(&mut self, _node: Node) -> ChildBehavior {
+        ChildBehavior::Ignore
+    }
+
+    fn visit_type_declaration(&mut self, _node: Node) -> ChildBehavior {
+        ChildBehavior::Ignore
+    }
+
+    fn visit_var_declaration(&mut self, _node: Node) -> ChildBehavior {
+        ChildBehavior::Ignore
+    }
+
+    fn visit_const_declaration(&mut self, _node: Node) -> ChildBehavior {
+        ChildBehavior::Ignore
+    }
+}
+
+#[cfg(test)]
+mod tests;
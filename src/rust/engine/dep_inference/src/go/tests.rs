@@ -0,0 +1,222 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::path::PathBuf;
+
+use crate::go::build_constraint::parse_expr;
+use crate::go::{get_dependencies, ParsedGoDependencies};
+use protos::gen::pants::cache::GoInferenceMetadata;
+
+fn parse(filepath: &str, code: &str, build_tags: &[&str]) -> ParsedGoDependencies {
+    get_dependencies(
+        code,
+        PathBuf::from(filepath),
+        GoInferenceMetadata {
+            build_tags: build_tags.iter().map(|tag| tag.to_string()).collect(),
+        },
+    )
+    .unwrap()
+}
+
+fn assert_imports(code: &str, imports: &[(&str, Option<&str>)]) {
+    let result = parse("pkg/a.go", code, &[]);
+    assert_eq!(
+        imports
+            .iter()
+            .map(|(path, name)| (path.to_string(), name.map(str::to_owned)))
+            .collect::<Vec<_>>(),
+        result.imports
+    );
+}
+
+fn assert_constraint(code: &str, constraint: Option<&str>) {
+    let result = parse("pkg/a.go", code, &[]);
+    assert_eq!(constraint.map(str::to_owned), result.build_constraint);
+}
+
+fn assert_matches(filepath: &str, code: &str, build_tags: &[&str], matches: bool) {
+    assert_eq!(
+        matches,
+        parse(filepath, code, build_tags).matches_build_tags
+    );
+}
+
+#[test]
+fn package_name() {
+    let result = parse(
+        "pkg/a.go",
+        "// Package foo does things.\npackage foo\n",
+        &[],
+    );
+    assert_eq!("foo", result.package_name);
+    assert!(result.imports.is_empty());
+}
+
+#[test]
+fn single_imports() {
+    assert_imports(
+        r#"
+package main
+
+import "fmt"
+import `os`
+"#,
+        &[("fmt", None), ("os", None)],
+    )
+}
+
+#[test]
+fn grouped_imports() {
+    assert_imports(
+        r#"
+package main
+
+import (
+    "fmt"
+    str "strings"
+    _ "embed"
+    . "math"
+
+    "github.com/pantsbuild/example/lib"
+)
+"#,
+        &[
+            ("fmt", None),
+            ("strings", Some("str")),
+            ("embed", Some("_")),
+            ("math", Some(".")),
+            ("github.com/pantsbuild/example/lib", None),
+        ],
+    )
+}
+
+#[test]
+fn cgo_import() {
+    assert_imports(
+        r#"
+package main
+
+// #include <stdio.h>
+import "C"
+"#,
+        &[("C", None)],
+    )
+}
+
+#[test]
+fn ignores_imports_with_pragma() {
+    assert_imports(
+        r#"
+package main
+
+import "fmt" // pants: no-infer-dep
+import (
+    "os" // pants: no-infer-dep
+    "strings"
+)
+"#,
+        &[("strings", None)],
+    )
+}
+
+#[test]
+fn ignores_strings_outside_imports() {
+    assert_imports(
+        r#"
+package main
+
+import "fmt"
+
+var name = "os"
+
+func main() {
+    fmt.Println("strings")
+}
+"#,
+        &[("fmt", None)],
+    )
+}
+
+#[test]
+fn go_build_constraint() {
+    assert_constraint(
+        "//go:build linux && (amd64 || arm64)\n\npackage main\n",
+        Some("linux && (amd64 || arm64)"),
+    );
+    assert_constraint(
+        "// Copyright 2024 Someone.\n\n//go:build !windows\n\npackage main\n",
+        Some("!windows"),
+    );
+    assert_constraint("package main\n", None);
+}
+
+#[test]
+fn plus_build_constraint() {
+    assert_constraint(
+        "// +build linux,386 darwin,!cgo\n// +build go1.18\n\npackage main\n",
+        Some("(linux && 386 || darwin && !cgo) && go1.18"),
+    );
+    // `+build` lines which are part of the package docs are not constraints.
+    assert_constraint("// +build linux\npackage main\n", None);
+    // Nor are those after the package clause.
+    assert_constraint("package main\n\n// +build linux\n", None);
+}
+
+#[test]
+fn go_build_takes_precedence() {
+    assert_constraint(
+        "//go:build linux\n// +build darwin\n\npackage main\n",
+        Some("linux"),
+    );
+}
+
+#[test]
+fn invalid_constraints() {
+    for code in [
+        "//go:build linux &&\n\npackage main\n",
+        "//go:build (linux\n\npackage main\n",
+        "//go:build linux\n//go:build darwin\n\npackage main\n",
+    ] {
+        assert!(
+            get_dependencies(code, PathBuf::from("a.go"), GoInferenceMetadata::default()).is_err()
+        );
+    }
+}
+
+#[test]
+fn expression_precedence() {
+    assert_eq!(
+        "a || b && !c",
+        parse_expr("a || (b && !c)").unwrap().to_string()
+    );
+    assert_eq!(
+        "!(a || b) && c",
+        parse_expr("!(a || b) && c").unwrap().to_string()
+    );
+}
+
+#[test]
+fn matches_constraint() {
+    let code = "//go:build linux && !cgo\n\npackage main\n";
+    assert_matches("a.go", code, &["linux", "amd64"], true);
+    assert_matches("a.go", code, &["linux", "amd64", "cgo"], false);
+    assert_matches("a.go", code, &["darwin", "amd64"], false);
+}
+
+#[test]
+fn matches_file_name() {
+    let code = "package main\n";
+    let tags = &["linux", "amd64"];
+    assert_matches("pkg/a.go", code, tags, true);
+    assert_matches("pkg/a_linux.go", code, tags, true);
+    assert_matches("pkg/a_linux_test.go", code, tags, true);
+    assert_matches("pkg/a_linux_amd64.go", code, tags, true);
+    assert_matches("pkg/a_amd64.go", code, tags, true);
+    assert_matches("pkg/a_windows.go", code, tags, false);
+    assert_matches("pkg/a_linux_arm64.go", code, tags, false);
+    assert_matches("pkg/a_windows_test.go", code, tags, false);
+    // Only known operating systems and architectures are constraints.
+    assert_matches("pkg/a_other.go", code, tags, true);
+    // A bare `GOOS.go` file name is not a suffix.
+    assert_matches("pkg/windows.go", code, tags, true);
+}
@@ -74,17 +74,79 @@ impl<'a> Pattern<'a> {
     }
 }
 
+/// The replacements of a single pattern: either unconditional, or only applying under
+/// [conditions](https://nodejs.org/api/packages.html#conditional-exports) such as `import`,
+/// `require` or `types`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Replacements {
+    pub unconditional: Vec<String>,
+    /// Conditional replacements in declaration order, each with its (possibly nested) conditions,
+    /// outermost first.
+    pub conditional: Vec<(Vec<String>, String)>,
+}
+
+impl Replacements {
+    ///
+    /// The replacements which apply under any of the given conditions, or under all conditions if
+    /// none are given.
+    ///
+    /// Each condition is resolved separately, as NodeJS would when only that condition (and
+    /// `default`) is active: the first conditional replacement in declaration order whose
+    /// conditions are all active wins.
+    ///
+    pub fn resolve(&self, conditions: &[String]) -> Vec<&str> {
+        let mut resolved: Vec<&str> = self.unconditional.iter().map(String::as_str).collect();
+        if conditions.is_empty() {
+            resolved.extend(
+                self.conditional
+                    .iter()
+                    .map(|(_, replacement)| replacement.as_str()),
+            );
+            return resolved;
+        }
+        for condition in conditions {
+            let is_active = |c: &String| c == condition || c == "default";
+            let winner = self
+                .conditional
+                .iter()
+                .find(|(conditions, _)| conditions.iter().all(is_active));
+            if let Some((winning_conditions, _)) = winner {
+                // An array of fallbacks results in multiple replacements for the same conditions.
+                resolved.extend(
+                    self.conditional
+                        .iter()
+                        .filter(|(conditions, _)| conditions == winning_conditions)
+                        .map(|(_, replacement)| replacement.as_str()),
+                );
+            }
+        }
+        resolved
+    }
+}
+
+impl From<Vec<String>> for Replacements {
+    fn from(unconditional: Vec<String>) -> Self {
+        Replacements {
+            unconditional,
+            conditional: vec![],
+        }
+    }
+}
+
 /// Replaces patterns provided on the form outlined in
-/// [NodeJS subpath patterns](https://nodejs.org/api/packages.html#subpath-patterns).
+/// [NodeJS subpath patterns](https://nodejs.org/api/packages.html#subpath-patterns),
+/// using the replacements which apply under the given conditions.
 /// If no pattern matches, the import string is returned unchanged.
 pub fn imports_from_patterns(
     root: &str,
-    patterns: &HashMap<String, Vec<String>>,
+    patterns: &HashMap<String, Replacements>,
+    conditions: &[String],
     import: String,
 ) -> HashSet<String> {
     if let Some((star_match, pattern)) = find_best_match(patterns, &import) {
         let mut matches = patterns[pattern]
-            .iter()
+            .resolve(conditions)
+            .into_iter()
             .filter_map(move |replacement| apply_replacements_to_match(&star_match, replacement))
             .map(|new_import| add_root_dir_to_dot_slash(root, new_import))
             .peekable();
@@ -99,7 +161,7 @@ pub fn imports_from_patterns(
     .collect()
 }
 
-fn apply_replacements_to_match(
+pub(crate) fn apply_replacements_to_match(
     star_match: &Option<StarMatch>,
     replacement: &str,
 ) -> Option<String> {
@@ -121,8 +183,8 @@ fn add_root_dir_to_dot_slash(root: &str, new_import: String) -> String {
     }
 }
 
-fn find_best_match<'a, 'b>(
-    patterns: &'a FnvHashMap<String, Vec<String>>,
+pub(crate) fn find_best_match<'a, 'b, V>(
+    patterns: &'a FnvHashMap<String, V>,
     import: &'b str,
 ) -> Option<(Option<StarMatch<'b>>, &'a String)> {
     patterns
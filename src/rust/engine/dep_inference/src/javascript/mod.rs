@@ -1,18 +1,26 @@
 // Copyright 2023 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 
-use fnv::FnvHashSet as HashSet;
+use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
 use serde_derive::{Deserialize, Serialize};
 use tree_sitter::{Node, Parser};
 
+use protos::gen::pants::cache::javascript_inference_metadata::ImportPattern;
 use protos::gen::pants::cache::JavascriptInferenceMetadata;
 
-use crate::javascript::import_pattern::imports_from_patterns;
-use crate::javascript::util::normalize_path;
+use crate::javascript::import_pattern::{imports_from_patterns, Replacements};
+use crate::javascript::sfc::script_blocks;
+use crate::javascript::tsconfig::{TsConfigPaths, TsConfigResolution};
+use crate::javascript::util::{normalize_path, resolution_candidates};
+use crate::span::SourceSpan;
 
 mod import_pattern;
-mod util;
+mod sfc;
+mod tsconfig;
+mod typescript;
+pub(crate) mod util;
 
 include!(concat!(env!("OUT_DIR"), "/javascript/constants.rs"));
 include!(concat!(env!("OUT_DIR"), "/javascript/visitor.rs"));
@@ -22,6 +30,11 @@ include!(concat!(env!("OUT_DIR"), "/javascript_impl_hash.rs"));
 pub struct ParsedJavascriptDependencies {
     pub file_imports: HashSet<String>,
     pub package_imports: HashSet<String>,
+    /// Globs of the files which dynamic imports of template literals might refer to, e.g.
+    /// `src/pages/*.js` for ``import(`./pages/${name}.js`)``, to be expanded by the caller.
+    pub pattern_imports: HashSet<String>,
+    /// The location of the first import which resolved to each file, package or pattern import.
+    pub import_spans: HashMap<String, SourceSpan>,
 }
 
 pub fn get_dependencies(
@@ -29,75 +42,244 @@ pub fn get_dependencies(
     filepath: PathBuf,
     metadata: JavascriptInferenceMetadata,
 ) -> Result<ParsedJavascriptDependencies, String> {
-    let patterns = metadata
+    let config_files: HashMap<PathBuf, &str> = metadata
+        .config_files
+        .iter()
+        .map(|config_file| {
+            (
+                PathBuf::from(&config_file.path),
+                config_file.content.as_str(),
+            )
+        })
+        .collect();
+    let tsconfig_paths = TsConfigPaths::for_file(&config_files, &filepath)?.unwrap_or_default();
+    let mut patterns: HashMap<String, Replacements> = metadata
         .import_patterns
         .into_iter()
-        .map(|pattern| (pattern.pattern, pattern.replacements))
+        .map(|pattern| (pattern.pattern.clone(), replacements(pattern)))
         .collect();
-    let mut collector = ImportCollector::new(contents);
-    collector.collect();
-    let (relative_files, packages): (HashSet<String>, HashSet<String>) = collector
-        .imports
-        .into_iter()
-        .flat_map(|import| imports_from_patterns(&metadata.package_root, &patterns, import))
-        .partition(|import| {
-            import.starts_with('.')
+    if !metadata.package_name.is_empty() {
+        patterns.extend(metadata.export_patterns.into_iter().filter_map(|pattern| {
+            let self_reference = self_reference(&metadata.package_name, &pattern.pattern)?;
+            Some((self_reference, replacements(pattern)))
+        }));
+    }
+    let (imports, pattern_imports, spans) = collect_imports(contents, &filepath);
+    let directory = filepath.parent().unwrap_or(Path::new("")).to_owned();
+    let mut file_imports = HashSet::default();
+    let mut package_imports = HashSet::default();
+    let mut import_spans = HashMap::default();
+    for import in imports {
+        let span = spans.get(&import).copied();
+        // Aliases from a `tsconfig.json` are already relative to the build root.
+        match tsconfig_paths.resolve(&import) {
+            Some(TsConfigResolution::Alias(files)) => {
+                for file in files {
+                    insert_span(&mut import_spans, &file, span);
+                    file_imports.insert(file);
+                }
+                continue;
+            }
+            Some(TsConfigResolution::BaseUrl(file)) => {
+                insert_span(&mut import_spans, &file, span);
+                file_imports.insert(file);
+            }
+            None => (),
+        }
+        for import in imports_from_patterns(
+            &metadata.package_root,
+            &patterns,
+            &metadata.conditions,
+            import,
+        ) {
+            let is_file = import.starts_with('.')
                 || import.starts_with('/')
-                || (!metadata.package_root.is_empty() && import.starts_with(&metadata.package_root))
-        });
+                || (!metadata.package_root.is_empty()
+                    && import.starts_with(&metadata.package_root));
+            if is_file {
+                let file = normalize_from_path(&metadata.package_root, &directory, import);
+                insert_span(&mut import_spans, &file, span);
+                file_imports.insert(file);
+            } else {
+                insert_span(&mut import_spans, &import, span);
+                package_imports.insert(import);
+            }
+        }
+    }
+    // A file import without an extension might refer to any of several files, which are all
+    // candidates for the owners of the import.
+    let file_imports = file_imports
+        .into_iter()
+        .flat_map(|file| {
+            let span = import_spans.get(&file).copied();
+            let candidates = resolution_candidates(&file, &metadata.resolve_extensions);
+            for candidate in &candidates {
+                insert_span(&mut import_spans, candidate, span);
+            }
+            candidates
+        })
+        .collect();
+    let pattern_imports = pattern_imports
+        .into_iter()
+        .filter_map(|pattern| {
+            let span = spans.get(&pattern).copied();
+            let pattern = normalize_path(&directory.join(pattern))?
+                .to_string_lossy()
+                .to_string();
+            insert_span(&mut import_spans, &pattern, span);
+            Some(pattern)
+        })
+        .collect();
     Ok(ParsedJavascriptDependencies {
-        file_imports: normalize_from_path(&metadata.package_root, filepath, relative_files),
-        package_imports: packages,
+        file_imports,
+        package_imports,
+        pattern_imports,
+        import_spans,
     })
 }
 
-fn normalize_from_path(
-    root: &str,
-    filepath: PathBuf,
-    file_imports: HashSet<String>,
-) -> HashSet<String> {
-    let directory = filepath.parent().unwrap_or(Path::new(""));
-    file_imports
-        .into_iter()
-        .map(|string| {
-            let path = Path::new(&string);
-            if path.has_root() {
-                string
-            } else if path.starts_with(root) && !root.is_empty() {
-                normalize_path(path).map_or(string, |path| path.to_string_lossy().to_string())
-            } else {
-                normalize_path(&directory.join(path))
-                    .map_or(string, |path| path.to_string_lossy().to_string())
+/// The imports and import patterns of a file, and the location of the first occurrence of each.
+type CollectedImports = (Vec<String>, Vec<String>, HashMap<String, SourceSpan>);
+
+/// Collect the imports and import patterns of a file, using the grammar which its extension
+/// implies.
+fn collect_imports(contents: &str, filepath: &Path) -> CollectedImports {
+    match filepath.extension().and_then(OsStr::to_str) {
+        Some("ts" | "tsx" | "mts" | "cts") => {
+            let mut collector = ImportCollector::new(contents);
+            collector.collect_typescript();
+            (
+                collector.imports,
+                collector.pattern_imports,
+                collector.spans,
+            )
+        }
+        Some("vue" | "svelte") => {
+            let mut imports = vec![];
+            let mut pattern_imports = vec![];
+            let mut spans = HashMap::default();
+            for block in script_blocks(contents) {
+                let mut collector = ImportCollector::new(block.content);
+                // The content of a block is a slice of the component, so its spans are offset by
+                // everything before it.
+                let offset = block.content.as_ptr() as usize - contents.as_ptr() as usize;
+                collector.prefix = &contents[..offset];
+                if block.is_typescript() {
+                    collector.collect_typescript();
+                } else {
+                    collector.collect();
+                }
+                imports.extend(collector.imports);
+                imports.extend(block.src.map(str::to_owned));
+                pattern_imports.extend(collector.pattern_imports);
+                for (import, span) in collector.spans {
+                    insert_span(&mut spans, &import, Some(span));
+                }
+            }
+            (imports, pattern_imports, spans)
+        }
+        _ => {
+            let mut collector = ImportCollector::new(contents);
+            collector.collect();
+            (
+                collector.imports,
+                collector.pattern_imports,
+                collector.spans,
+            )
+        }
+    }
+}
+
+/// Record the span of an import, unless an earlier one was already recorded.
+fn insert_span(spans: &mut HashMap<String, SourceSpan>, import: &str, span: Option<SourceSpan>) {
+    let Some(span) = span else {
+        return;
+    };
+    spans
+        .entry(import.to_owned())
+        .and_modify(|existing| {
+            if span.start_byte < existing.start_byte {
+                *existing = span;
             }
         })
-        .collect()
+        .or_insert(span);
 }
 
+fn replacements(pattern: ImportPattern) -> Replacements {
+    Replacements {
+        unconditional: pattern.replacements,
+        conditional: pattern
+            .conditional_replacements
+            .into_iter()
+            .map(|conditional| (conditional.conditions, conditional.replacement))
+            .collect(),
+    }
+}
+
+/// The import which refers to the given `exports` subpath of the package by its own name.
+fn self_reference(package_name: &str, subpath: &str) -> Option<String> {
+    match subpath {
+        "." => Some(package_name.to_string()),
+        _ => subpath
+            .strip_prefix("./")
+            .map(|subpath| format!("{package_name}/{subpath}")),
+    }
+}
+
+fn normalize_from_path(root: &str, directory: &Path, string: String) -> String {
+    let path = Path::new(&string);
+    if path.has_root() {
+        string
+    } else if path.starts_with(root) && !root.is_empty() {
+        normalize_path(path).map_or(string, |path| path.to_string_lossy().to_string())
+    } else {
+        normalize_path(&directory.join(path))
+            .map_or(string, |path| path.to_string_lossy().to_string())
+    }
+}
+
+/// Collects the imports of a Javascript or TypeScript source.
+///
+/// The node kinds of the Javascript and TypeScript grammars have different ids, so the collector
+/// matches on the kind names, which they share. Each grammar's `Visitor` delegates to the
+/// grammar-independent handlers below, which return whether to visit the children of the node.
 struct ImportCollector<'a> {
     pub imports: Vec<String>,
+    /// Relative globs for dynamic imports of template literals, e.g. `./pages/*.js`.
+    pub pattern_imports: Vec<String>,
+    /// The location of the first occurrence of each import and import pattern.
+    pub spans: HashMap<String, SourceSpan>,
     code: &'a str,
+    /// The contents of the file before the code, when the code is a part of it.
+    prefix: &'a str,
 }
 
 impl ImportCollector<'_> {
     pub fn new(code: &'_ str) -> ImportCollector<'_> {
         ImportCollector {
             imports: Vec::new(),
+            pattern_imports: Vec::new(),
+            spans: HashMap::default(),
             code,
+            prefix: "",
         }
     }
 
     pub fn collect(&mut self) {
-        let mut parser = Parser::new();
-        parser
-            .set_language(tree_sitter_javascript::language())
-            .expect("Error loading Javascript grammar");
-        let parsed = parser.parse(self.code, None);
-        let tree = parsed.unwrap();
+        let tree = self.parse(tree_sitter_javascript::language(), "Javascript");
         let mut cursor = tree.walk();
 
         self.walk(&mut cursor);
     }
 
+    fn parse(&self, language: tree_sitter::Language, name: &str) -> tree_sitter::Tree {
+        let mut parser = Parser::new();
+        parser
+            .set_language(language)
+            .unwrap_or_else(|_| panic!("Error loading {name} grammar"));
+        parser.parse(self.code, None).unwrap()
+    }
+
     fn code_at(&self, range: tree_sitter::Range) -> &str {
         &self.code[range.start_byte..range.end_byte]
     }
@@ -105,11 +287,11 @@ impl ImportCollector<'_> {
     fn is_pragma_ignored(&self, node: Node) -> bool {
         fn comment_after_semicolon(node: Node) -> Option<Node> {
             node.next_named_sibling()
-                .filter(|comment| comment.kind_id() == KindID::COMMENT)
+                .filter(|comment| comment.kind() == "comment")
         }
         fn comment_after_no_semicolon(node: Node) -> Option<Node> {
             node.children(&mut node.walk())
-                .find(|node| node.kind_id() == KindID::COMMENT)
+                .find(|node| node.kind() == "comment")
         }
         let contains_pragma = |node: Node, comment: Node| -> bool {
             let comment_range = comment.range();
@@ -124,65 +306,157 @@ impl ImportCollector<'_> {
     }
 
     fn insert_import(&mut self, import_string: Option<Node>) {
-        if let Some(import_string) = import_string {
-            let import_string = self.code_at(import_string.range());
-            self.imports
-                .push(import_string.strip_first_last().to_string())
+        if let Some(node) = import_string {
+            let import_string = self.code_at(node.range()).strip_first_last().to_string();
+            self.insert_span(&import_string, node);
+            self.imports.push(import_string)
         }
     }
 
-    fn propagate_pragma(&self, node: Node) -> ChildBehavior {
-        if !self.is_pragma_ignored(node) {
-            return ChildBehavior::Visit;
+    fn insert_span(&mut self, import: &str, node: Node) {
+        if !self.spans.contains_key(import) {
+            let span = SourceSpan::of(node).offset_by(self.prefix);
+            self.spans.insert(import.to_owned(), span);
         }
-        ChildBehavior::Ignore
     }
-}
 
-impl Visitor for ImportCollector<'_> {
-    fn visit_import_statement(&mut self, node: Node) -> ChildBehavior {
-        if !self.is_pragma_ignored(node) {
-            self.insert_import(node.child_by_field_name("source"));
+    ///
+    /// A template literal without substitutions is an ordinary import. Otherwise, if it is relative
+    /// and has a static prefix or suffix, each substitution is replaced by a `*` to give a glob of
+    /// the files it might import: e.g. `./pages/${name}.js` becomes `./pages/*.js`.
+    ///
+    fn insert_template_import(&mut self, template: Node) {
+        let template_code = self.code_at(template.range());
+        if template_code.len() < 2 || !template_code.ends_with('`') {
+            // An unterminated template, from a syntax error.
+            return;
         }
-        ChildBehavior::Ignore
-    }
-
-    fn visit_export_statement(&mut self, node: Node) -> ChildBehavior {
-        if !self.is_pragma_ignored(node) {
-            self.insert_import(node.child_by_field_name("source"));
+        let mut pattern = String::new();
+        let mut start = template.start_byte() + 1;
+        for substitution in template
+            .children(&mut template.walk())
+            .filter(|child| child.kind() == "template_substitution")
+        {
+            pattern.push_str(&self.code[start..substitution.start_byte()]);
+            if !pattern.ends_with('*') {
+                pattern.push('*');
+            }
+            start = substitution.end_byte();
         }
+        pattern.push_str(&self.code[start..template.end_byte() - 1]);
 
-        ChildBehavior::Ignore
+        if !pattern.contains('*') {
+            self.insert_span(&pattern, template);
+            self.imports.push(pattern);
+            return;
+        }
+        let mut static_part = pattern.as_str();
+        while let Some(rest) = static_part
+            .strip_prefix("./")
+            .or_else(|| static_part.strip_prefix("../"))
+        {
+            static_part = rest;
+        }
+        let is_relative = static_part.len() < pattern.len();
+        if is_relative && static_part.chars().any(|c| c != '*' && c != '/') {
+            self.insert_span(&pattern, template);
+            self.pattern_imports.push(pattern);
+        }
     }
 
-    fn visit_expression_statement(&mut self, node: Node) -> ChildBehavior {
-        if node.children(&mut node.walk()).any(|child| {
-            let id = child.kind_id();
-            KindID::CALL_EXPRESSION.contains(&id) || id == KindID::AWAIT_EXPRESSION
-        }) {
-            return self.propagate_pragma(node);
+    fn import_or_export_statement(&mut self, node: Node) -> bool {
+        if self.is_pragma_ignored(node) {
+            return false;
         }
-        ChildBehavior::Ignore
+        // TypeScript's `import x = require('x')` has no `source`, but a require clause.
+        let source = node.child_by_field_name("source").or_else(|| {
+            node.children(&mut node.walk())
+                .find(|child| child.kind() == "import_require_clause")
+                .and_then(|clause| {
+                    clause
+                        .children(&mut clause.walk())
+                        .find(|child| child.kind() == "string")
+                })
+        });
+        if source.is_none() {
+            // Exported declarations (e.g. `export const x = require('x')`) might contain imports.
+            return node.kind() == "export_statement";
+        }
+        self.insert_import(source);
+        false
     }
 
-    fn visit_lexical_declaration(&mut self, node: Node) -> ChildBehavior {
-        self.propagate_pragma(node)
+    fn expression_statement(&mut self, node: Node) -> bool {
+        node.children(&mut node.walk()).any(|child| {
+            matches!(
+                child.kind(),
+                "call_expression"
+                    | "await_expression"
+                    // e.g. `module.exports = require('x')`
+                    | "assignment_expression"
+                    // e.g. `debug ? require('x') : require('y')`
+                    | "ternary_expression"
+            )
+        }) && !self.is_pragma_ignored(node)
     }
 
-    fn visit_call_expression(&mut self, node: Node) -> ChildBehavior {
+    fn call_expression(&mut self, node: Node) -> bool {
         if let (Some(function), Some(args)) = (node.named_child(0), node.named_child(1)) {
-            if let "require" | "import" = self.code_at(function.range()) {
+            if let "require" | "require.resolve" | "import" = self.code_at(function.range()) {
                 for arg in args.children(&mut args.walk()) {
-                    if arg.kind_id() == KindID::STRING {
-                        self.insert_import(Some(arg))
+                    match arg.kind() {
+                        "string" => self.insert_import(Some(arg)),
+                        "template_string" => self.insert_template_import(arg),
+                        _ => {}
                     }
                 }
+                return false;
             }
         }
+        // Other calls might contain requires in their arguments or callee, e.g.
+        // `path.dirname(require.resolve('x'))` or `(function() { require('x') })()`.
+        true
+    }
+}
+
+fn child_behavior(visit_children: bool) -> ChildBehavior {
+    if visit_children {
+        ChildBehavior::Visit
+    } else {
         ChildBehavior::Ignore
     }
 }
 
+impl Visitor for ImportCollector<'_> {
+    fn visit_import_statement(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(self.import_or_export_statement(node))
+    }
+
+    fn visit_export_statement(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(self.import_or_export_statement(node))
+    }
+
+    fn visit_expression_statement(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(self.expression_statement(node))
+    }
+
+    fn visit_lexical_declaration(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(!self.is_pragma_ignored(node))
+    }
+
+    fn visit_variable_declaration(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(!self.is_pragma_ignored(node))
+    }
+
+    fn visit_return_statement(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(!self.is_pragma_ignored(node))
+    }
+
+    fn visit_call_expression(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(self.call_expression(node))
+    }
+}
+
 trait StripFirstLast {
     fn strip_first_last(&self) -> &Self;
 }
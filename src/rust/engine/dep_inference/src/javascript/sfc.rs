@@ -0,0 +1,129 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Extraction of the `<script>` blocks of single-file components, i.e. `.vue` and `.svelte` files.
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScriptBlock<'a> {
+    /// The value of the `lang` attribute, e.g. `ts`.
+    pub lang: Option<&'a str>,
+    /// The value of the `src` attribute, which refers to an external script.
+    pub src: Option<&'a str>,
+    pub content: &'a str,
+}
+
+impl ScriptBlock<'_> {
+    pub fn is_typescript(&self) -> bool {
+        matches!(self.lang, Some("ts" | "tsx" | "typescript"))
+    }
+}
+
+///
+/// Find the `<script>` blocks of a component, skipping any which are commented out. Component
+/// formats allow multiple blocks: for example `<script>` and `<script setup>` in Vue, or
+/// `<script context="module">` and `<script>` in Svelte.
+///
+pub fn script_blocks(contents: &str) -> Vec<ScriptBlock> {
+    let mut blocks = vec![];
+    let mut rest = contents;
+    loop {
+        let script = find_tag(rest, "<script");
+        let comment = rest.find("<!--");
+        match (script, comment) {
+            (Some(script), Some(comment)) if comment < script => {
+                let Some(end) = rest[comment..].find("-->") else {
+                    break;
+                };
+                rest = &rest[comment + end + "-->".len()..];
+            }
+            (Some(script), _) => {
+                let tag = &rest[script + "<script".len()..];
+                let Some(tag_end) = find_tag_end(tag) else {
+                    break;
+                };
+                let attributes = &tag[..tag_end];
+                let body = &tag[tag_end + 1..];
+                let (content, next) = if attributes.ends_with('/') {
+                    // NB: The (empty) content is still a slice of the component, so that the spans of
+                    // blocks can be computed from their offsets.
+                    (&body[..0], body)
+                } else {
+                    match body.find("</script") {
+                        Some(end) => (&body[..end], &body[end..]),
+                        None => (body, ""),
+                    }
+                };
+                blocks.push(ScriptBlock {
+                    lang: attribute(attributes, "lang"),
+                    src: attribute(attributes, "src"),
+                    content,
+                });
+                rest = next;
+            }
+            (None, _) => break,
+        }
+    }
+    blocks
+}
+
+/// Find an opening tag, which must be followed by whitespace, `/` or `>` (unlike e.g. `<scripts>`).
+fn find_tag(contents: &str, tag: &str) -> Option<usize> {
+    contents.match_indices(tag).map(|(i, _)| i).find(|i| {
+        contents[i + tag.len()..]
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_whitespace() || c == '/' || c == '>')
+    })
+}
+
+/// Find the `>` which ends a tag, skipping quoted attribute values such as Vue's
+/// `generic="T extends Record<string, unknown>"`.
+fn find_tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    tag.char_indices().find_map(|(i, c)| {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            (Some(q), c) if q == c => quote = None,
+            _ => {}
+        }
+        None
+    })
+}
+
+/// The value of an attribute of a tag, which may be single, double or un-quoted.
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return None;
+        }
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let attribute_name = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+        let value = if let Some(value) = rest.strip_prefix('=') {
+            let value = value.trim_start();
+            let (value, remainder) = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let value = &value[1..];
+                    let end = value.find(quote).unwrap_or(value.len());
+                    (&value[..end], value.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                    (&value[..end], &value[end..])
+                }
+            };
+            rest = remainder;
+            Some(value)
+        } else {
+            None
+        };
+        if attribute_name == name {
+            return value;
+        }
+    }
+}
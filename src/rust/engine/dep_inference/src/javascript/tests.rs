@@ -4,11 +4,24 @@
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
-use crate::javascript::import_pattern::{imports_from_patterns, Pattern, StarMatch};
+use crate::javascript::import_pattern::{imports_from_patterns, Pattern, Replacements, StarMatch};
+use crate::javascript::tsconfig::strip_jsonc;
+use crate::javascript::util::resolution_candidates;
 use crate::javascript::{get_dependencies, ImportCollector};
-use javascript_inference_metadata::ImportPattern;
+use crate::span::SourceSpan;
+use javascript_inference_metadata::import_pattern::ConditionalReplacement;
+use javascript_inference_metadata::{ConfigFile, ImportPattern};
 use protos::gen::pants::cache::{javascript_inference_metadata, JavascriptInferenceMetadata};
 
+fn assert_typescript_imports(code: &str, imports: &[&str]) {
+    let mut collector = ImportCollector::new(code);
+    collector.collect_typescript();
+    assert_eq!(
+        HashSet::from_iter(imports.iter().map(|s| s.to_string())),
+        collector.imports.into_iter().collect::<HashSet<_>>()
+    );
+}
+
 fn assert_imports(code: &str, imports: &[&str]) {
     let mut collector = ImportCollector::new(code);
     collector.collect();
@@ -18,6 +31,13 @@ fn assert_imports(code: &str, imports: &[&str]) {
     );
 }
 
+fn assert_pattern_imports(code: &str, imports: &[&str], pattern_imports: &[&str]) {
+    let mut collector = ImportCollector::new(code);
+    collector.collect();
+    assert_eq!(imports, collector.imports);
+    assert_eq!(pattern_imports, collector.pattern_imports);
+}
+
 fn given_metadata(
     root: &str,
     pattern_replacements: HashMap<String, Vec<String>>,
@@ -27,11 +47,29 @@ fn given_metadata(
         .map(|(key, value)| ImportPattern {
             pattern: key.clone(),
             replacements: value.clone(),
+            ..Default::default()
         })
         .collect();
     JavascriptInferenceMetadata {
         package_root: root.to_string(),
         import_patterns,
+        ..Default::default()
+    }
+}
+
+fn given_config_files<'a>(
+    metadata: JavascriptInferenceMetadata,
+    config_files: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> JavascriptInferenceMetadata {
+    JavascriptInferenceMetadata {
+        config_files: config_files
+            .into_iter()
+            .map(|(path, content)| ConfigFile {
+                path: path.to_string(),
+                content: content.to_string(),
+            })
+            .collect(),
+        ..metadata
     }
 }
 
@@ -132,6 +170,83 @@ fn ignore_imports() {
     );
 }
 
+#[test]
+fn commonjs_re_exports() {
+    assert_imports("module.exports = require('a');", &["a"]);
+    assert_imports("module.exports.b = require('b')", &["b"]);
+    assert_imports("exports.c = require('c');", &["c"]);
+    assert_imports("module.exports = { ...require('d') };", &["d"]);
+}
+
+#[test]
+fn require_resolve() {
+    assert_imports("require.resolve('a');", &["a"]);
+    assert_imports("const b = require.resolve('b');", &["b"]);
+    assert_imports(
+        "const c = path.dirname(require.resolve('c/package.json'));",
+        &["c/package.json"],
+    );
+}
+
+#[test]
+fn destructured_require() {
+    assert_imports("const { a } = require('a');", &["a"]);
+    assert_imports("const { b: { c } } = require('b');", &["b"]);
+    assert_imports("let [d] = require('d');", &["d"]);
+    assert_imports("var { e } = require('e');", &["e"]);
+}
+
+#[test]
+fn nested_requires() {
+    assert_imports(
+        "const a = process.env.DEBUG ? require('a-debug') : require('a');",
+        &["a-debug", "a"],
+    );
+    assert_imports(
+        "process.env.DEBUG ? require('b-debug') : require('b');",
+        &["b-debug", "b"],
+    );
+    assert_imports(
+        "module.exports = process.env.DEBUG ? require('c-debug') : require('c');",
+        &["c-debug", "c"],
+    );
+    assert_imports(
+        r"
+    function load() {
+        const { d } = require('d');
+        module.exports = require('e');
+        return require.resolve('f');
+    }
+    const g = () => require('g');
+    (function () {
+        module.exports = require('h');
+    })();
+    describe('i', () => { require('i'); });
+    ",
+        &["d", "e", "f", "g", "h", "i"],
+    );
+}
+
+#[test]
+fn ignore_commonjs_requires() {
+    assert_imports("module.exports = require('a'); // pants: no-infer-dep", &[]);
+    assert_imports("require.resolve('b') // pants: no-infer-dep", &[]);
+    assert_imports("const { c } = require('c'); // pants: no-infer-dep", &[]);
+    assert_imports("var d = require('d'); // pants: no-infer-dep", &[]);
+    assert_imports(
+        "const e = debug ? require('e-debug') : require('e'); // pants: no-infer-dep",
+        &[],
+    );
+    assert_imports(
+        r"
+    function load() {
+        return require('f'); // pants: no-infer-dep
+    }
+    ",
+        &[],
+    );
+}
+
 #[test]
 fn simple_exports() {
     // https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Statements/export
@@ -252,6 +367,65 @@ fn dynamic_scope() {
     );
 }
 
+#[test]
+fn template_literal_imports() {
+    assert_pattern_imports(
+        r"
+  import(`./pages/${name}.js`);
+  const locale = require(`../locales/${lang}/messages.json`);
+  await import(`./icons/${size}-${name}.svg`);
+  ",
+        &[],
+        &[
+            "./pages/*.js",
+            "../locales/*/messages.json",
+            "./icons/*-*.svg",
+        ],
+    );
+}
+
+#[test]
+fn template_literal_without_substitutions() {
+    assert_pattern_imports("import(`./static.js`)", &["./static.js"], &[]);
+}
+
+#[test]
+fn template_literal_imports_without_static_parts() {
+    assert_pattern_imports(
+        r"
+  import(`${base}/page.js`);
+  import(`./${name}`);
+  import(`../${dir}/${name}`);
+  import(`pkg-${name}`);
+  ",
+        &[],
+        &[],
+    );
+}
+
+#[test]
+fn ignore_template_literal_imports() {
+    assert_pattern_imports(
+        "import(`./pages/${name}.js`); // pants: no-infer-dep",
+        &[],
+        &[],
+    );
+}
+
+#[test]
+fn template_literal_imports_relative_to_file() {
+    let result = get_dependencies(
+        "const page = await import(`../pages/${name}.js`);",
+        PathBuf::from("src/app/index.js"),
+        JavascriptInferenceMetadata::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        HashSet::from_iter(["src/pages/*.js".to_string()]),
+        result.pattern_imports
+    );
+}
+
 #[test]
 fn adds_dir_to_file_imports() -> Result<(), Box<dyn std::error::Error>> {
     let result = get_dependencies(
@@ -518,9 +692,9 @@ fn replaces_groups() {
     let mut patterns = HashMap::default();
     patterns.insert(
         "#internal/*.js".to_string(),
-        vec!["./src/internal/*.js".to_string()],
+        vec!["./src/internal/*.js".to_string()].into(),
     );
-    let imports = imports_from_patterns("dir", &patterns, "#internal/z.js".to_string());
+    let imports = imports_from_patterns("dir", &patterns, &[], "#internal/z.js".to_string());
 
     assert_eq!(
         imports,
@@ -534,17 +708,491 @@ fn longest_prefix_wins() {
 
     patterns.insert(
         "#internal/stuff/*.js".to_string(),
-        vec!["./src/stuff/*.js".to_string()],
+        vec!["./src/stuff/*.js".to_string()].into(),
     );
     patterns.insert(
         "#internal/*.js".to_string(),
-        vec!["./src/things/*.js".to_string()],
+        vec!["./src/things/*.js".to_string()].into(),
     );
 
-    let imports = imports_from_patterns("dir", &patterns, "#internal/stuff/index.js".to_string());
+    let imports = imports_from_patterns(
+        "dir",
+        &patterns,
+        &[],
+        "#internal/stuff/index.js".to_string(),
+    );
 
     assert_eq!(
         imports,
         HashSet::from_iter(["dir/src/stuff/index.js".to_string()])
     )
 }
+
+#[test]
+fn tsconfig_strips_comments_and_trailing_commas() {
+    let stripped = strip_jsonc(
+        r#"{
+          // A comment.
+          "a": "http://not/a/comment", /* Another
+          comment. */
+          "b": ["c", "d",],
+        }"#,
+    );
+    let value: serde_json::Value = serde_json::from_str(&stripped).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({"a": "http://not/a/comment", "b": ["c", "d"]})
+    );
+}
+
+#[test]
+fn tsconfig_paths_relative_to_declaring_config() {
+    assert_dependency_imports(
+        "app/src/index.ts",
+        r#"
+    import { a } from '@lib/a';
+    import React from 'react';
+    "#,
+        ["app/lib/a"],
+        ["react"],
+        given_config_files(
+            given_metadata("app", HashMap::default()),
+            [(
+                "app/tsconfig.json",
+                r#"{"compilerOptions": {"paths": {"@lib/*": ["./lib/*"]}}}"#,
+            )],
+        ),
+    );
+}
+
+#[test]
+fn tsconfig_paths_inherited_through_extends_chain() {
+    assert_dependency_imports(
+        "packages/app/src/index.ts",
+        r#"
+    import { a } from '@shared/a';
+    "#,
+        ["packages/shared/src/a"],
+        [],
+        given_config_files(
+            given_metadata("packages/app", HashMap::default()),
+            [
+                (
+                    "packages/app/tsconfig.json",
+                    r#"{
+                      // The app only overrides the output directory.
+                      "extends": "../tsconfig.base",
+                      "compilerOptions": {"outDir": "dist",},
+                    }"#,
+                ),
+                (
+                    "packages/tsconfig.base.json",
+                    r#"{"extends": "../tsconfig.root.json", "compilerOptions": {"baseUrl": "."}}"#,
+                ),
+                (
+                    "tsconfig.root.json",
+                    r#"{"compilerOptions": {"paths": {"@shared/*": ["shared/src/*"]}}}"#,
+                ),
+            ],
+        ),
+    );
+}
+
+#[test]
+fn tsconfig_extends_node_modules_package() {
+    assert_dependency_imports(
+        "app/src/index.ts",
+        r#"
+    import { a } from '~/a';
+    "#,
+        ["app/src/a"],
+        [],
+        given_config_files(
+            given_metadata("app", HashMap::default()),
+            [
+                (
+                    "app/tsconfig.json",
+                    r#"{"extends": ["@company/tsconfig", "@company/tsconfig/strict"]}"#,
+                ),
+                (
+                    "node_modules/@company/tsconfig/tsconfig.json",
+                    r#"{"compilerOptions": {"paths": {"~/*": ["./wrong/*"]}}}"#,
+                ),
+                (
+                    "node_modules/@company/tsconfig/strict.json",
+                    r#"{"compilerOptions": {"baseUrl": "../../../app", "paths": {"~/*": ["src/*"]}}}"#,
+                ),
+            ],
+        ),
+    );
+}
+
+#[test]
+fn tsconfig_base_url_falls_back_to_packages() {
+    assert_dependency_imports(
+        "app/src/index.ts",
+        r#"
+    import { a } from 'components/a';
+    "#,
+        ["app/src/components/a"],
+        ["components/a"],
+        given_config_files(
+            given_metadata("app", HashMap::default()),
+            [(
+                "app/tsconfig.json",
+                r#"{"compilerOptions": {"baseUrl": "src"}}"#,
+            )],
+        ),
+    );
+}
+
+#[test]
+fn tsconfig_missing_base_config_is_skipped() {
+    assert_dependency_imports(
+        "app/src/index.ts",
+        r#"
+    import { a } from '@lib/a';
+    "#,
+        [],
+        ["@lib/a"],
+        given_config_files(
+            given_metadata("app", HashMap::default()),
+            [("app/tsconfig.json", r#"{"extends": "@tsconfig/node18"}"#)],
+        ),
+    );
+}
+
+#[test]
+fn tsconfig_circular_extends_is_an_error() {
+    let metadata = given_config_files(
+        given_metadata("app", HashMap::default()),
+        [
+            (
+                "app/tsconfig.json",
+                r#"{"extends": "./tsconfig.base.json"}"#,
+            ),
+            (
+                "app/tsconfig.base.json",
+                r#"{"extends": "./tsconfig.json"}"#,
+            ),
+        ],
+    );
+    assert!(get_dependencies("", PathBuf::from("app/index.ts"), metadata).is_err());
+}
+
+fn given_conditional_replacements(replacements: &[(&[&str], &str)]) -> Vec<ConditionalReplacement> {
+    replacements
+        .iter()
+        .map(|(conditions, replacement)| ConditionalReplacement {
+            conditions: conditions.iter().map(|c| c.to_string()).collect(),
+            replacement: replacement.to_string(),
+        })
+        .collect()
+}
+
+fn given_conditions(
+    metadata: JavascriptInferenceMetadata,
+    conditions: &[&str],
+) -> JavascriptInferenceMetadata {
+    JavascriptInferenceMetadata {
+        conditions: conditions.iter().map(|c| c.to_string()).collect(),
+        ..metadata
+    }
+}
+
+fn given_conditional_imports_metadata() -> JavascriptInferenceMetadata {
+    JavascriptInferenceMetadata {
+        package_root: "js".to_string(),
+        import_patterns: vec![ImportPattern {
+            pattern: "#lib/*".to_string(),
+            replacements: vec![],
+            conditional_replacements: given_conditional_replacements(&[
+                (&["types"], "./types/*.d.ts"),
+                (&["node", "import"], "./node/*.mjs"),
+                (&["import"], "./esm/*.mjs"),
+                (&["require"], "./cjs/*.cjs"),
+                (&["default"], "./cjs/*.cjs"),
+            ]),
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn conditional_imports_without_conditions_use_all_replacements() {
+    assert_dependency_imports(
+        "js/src/index.mjs",
+        "import { a } from '#lib/a';",
+        [
+            "js/types/a.d.ts",
+            "js/node/a.mjs",
+            "js/esm/a.mjs",
+            "js/cjs/a.cjs",
+        ],
+        [],
+        given_conditional_imports_metadata(),
+    );
+}
+
+#[test]
+fn conditional_imports_resolved_per_condition() {
+    assert_dependency_imports(
+        "js/src/index.mjs",
+        "import { a } from '#lib/a';",
+        ["js/types/a.d.ts", "js/esm/a.mjs"],
+        [],
+        given_conditions(given_conditional_imports_metadata(), &["import", "types"]),
+    );
+}
+
+#[test]
+fn conditional_imports_fall_back_to_default() {
+    assert_dependency_imports(
+        "js/src/index.mjs",
+        "import { a } from '#lib/a';",
+        ["js/cjs/a.cjs"],
+        [],
+        given_conditions(given_conditional_imports_metadata(), &["browser"]),
+    );
+}
+
+#[test]
+fn conditional_replacements_with_fallbacks() {
+    let replacements = Replacements {
+        unconditional: vec!["./always.js".to_string()],
+        conditional: vec![
+            (vec!["import".to_string()], "./a.mjs".to_string()),
+            (vec!["import".to_string()], "./b.mjs".to_string()),
+            (vec!["default".to_string()], "./c.js".to_string()),
+        ],
+    };
+    assert_eq!(
+        replacements.resolve(&["import".to_string()]),
+        vec!["./always.js", "./a.mjs", "./b.mjs"]
+    );
+    assert_eq!(
+        replacements.resolve(&["require".to_string()]),
+        vec!["./always.js", "./c.js"]
+    );
+}
+
+#[test]
+fn conditional_exports_resolve_self_references() {
+    let metadata = JavascriptInferenceMetadata {
+        package_root: "js".to_string(),
+        package_name: "ham".to_string(),
+        export_patterns: vec![
+            ImportPattern {
+                pattern: ".".to_string(),
+                replacements: vec![],
+                conditional_replacements: given_conditional_replacements(&[
+                    (&["types"], "./index.d.ts"),
+                    (&["import"], "./index.mjs"),
+                    (&["require"], "./index.cjs"),
+                ]),
+            },
+            ImportPattern {
+                pattern: "./utils/*".to_string(),
+                replacements: vec!["./src/utils/*.js".to_string()],
+                conditional_replacements: vec![],
+            },
+        ],
+        ..Default::default()
+    };
+    assert_dependency_imports(
+        "js/src/index.mjs",
+        r#"
+    import ham from 'ham';
+    import { x } from 'ham/utils/x';
+    import spam from 'spam';
+    "#,
+        ["js/index.cjs", "js/src/utils/x.js"],
+        ["spam"],
+        given_conditions(metadata, &["require"]),
+    );
+}
+
+#[test]
+fn jsx_imports() {
+    assert_imports(
+        r#"
+    import React from 'react';
+    import { Button } from './button';
+    export const App = () => <Button onClick={() => require('./handler')}>Hi</Button>;
+    "#,
+        &["react", "./button", "./handler"],
+    );
+}
+
+#[test]
+fn typescript_imports() {
+    assert_typescript_imports(
+        r#"
+    import type { A } from './a';
+    import { type B, c } from './b';
+    export type { D } from './d';
+    import e = require('e');
+    const f: Promise<typeof import('f')> = import('f');
+    function g<T>(x: T): T { return require('g'); }
+    "#,
+        &["./a", "./b", "./d", "e", "f", "g"],
+    );
+}
+
+#[test]
+fn tsx_imports() {
+    assert_typescript_imports(
+        r#"
+    import * as React from 'react';
+    import { Button } from './button'; // pants: no-infer-dep
+    const App = <T,>(props: { items: T[] }) => <ul>{props.items.map((i) => <li>{String(i)}</li>)}</ul>;
+    export default App as React.FC<unknown>;
+    export { Other } from './other';
+    "#,
+        &["react", "./other"],
+    );
+}
+
+#[test]
+fn typescript_files_use_typescript_grammar() {
+    assert_dependency_imports(
+        "src/index.ts",
+        "import type { A } from './a'; import fs = require('fs');",
+        ["src/a"],
+        ["fs"],
+        JavascriptInferenceMetadata::default(),
+    );
+}
+
+#[test]
+fn vue_script_blocks() {
+    assert_dependency_imports(
+        "src/App.vue",
+        r#"
+<template>
+  <div><Child /></div>
+</template>
+
+<!-- <script>import commentedOut from './commented-out';</script> -->
+<script>
+import Vue from 'vue';
+</script>
+
+<script setup lang="ts" generic="T extends Record<string, unknown>">
+import type { Props } from './props';
+import Child from './Child.vue';
+</script>
+
+<style>
+@import './style.css';
+</style>
+"#,
+        ["src/props", "src/Child.vue"],
+        ["vue"],
+        JavascriptInferenceMetadata::default(),
+    );
+}
+
+#[test]
+fn vue_external_script() {
+    assert_dependency_imports(
+        "src/App.vue",
+        r#"<template><div /></template><script src="./app.js" />"#,
+        ["src/app.js"],
+        [],
+        JavascriptInferenceMetadata::default(),
+    );
+}
+
+#[test]
+fn svelte_script_blocks() {
+    assert_dependency_imports(
+        "src/Counter.svelte",
+        r#"
+<script context="module" lang='ts'>
+  export const preload = async (): Promise<void> => { await import('./preload'); };
+</script>
+<script>
+  import { onMount } from 'svelte';
+  import Button from './Button.svelte';
+  let count = 0;
+</script>
+
+<Button on:click={() => count++}>{count}</Button>
+"#,
+        ["src/preload", "src/Button.svelte"],
+        ["svelte"],
+        JavascriptInferenceMetadata::default(),
+    );
+}
+
+#[test]
+fn import_spans() {
+    let result = get_dependencies(
+        r#"<template></template>
+<script>
+import a from './a';
+  const b = require('b/c');
+  const c = require('./a');
+</script>
+"#,
+        PathBuf::from("src/App.vue"),
+        given_metadata("", HashMap::default()),
+    )
+    .unwrap();
+    let span = |start_byte, end_byte, line, start_column, end_column| SourceSpan {
+        start_byte,
+        end_byte,
+        start_line: line,
+        start_column,
+        end_line: line,
+        end_column,
+    };
+    // Spans are relative to the whole component, rather than to its `<script>` block.
+    assert_eq!(
+        HashMap::from_iter([
+            ("src/a".to_string(), span(45, 50, 3, 14, 19)),
+            ("b/c".to_string(), span(72, 77, 4, 20, 25)),
+        ]),
+        result.import_spans.into_iter().collect::<HashMap<_, _>>()
+    );
+}
+
+#[test]
+fn resolution_candidates_of_files() {
+    let extensions = [".js", ".ts", "/index.*"].map(str::to_owned);
+    assert_eq!(
+        vec![
+            "src/util",
+            "src/util.js",
+            "src/util.ts",
+            "src/util/index.js",
+            "src/util/index.ts"
+        ],
+        resolution_candidates("src/util", &extensions)
+    );
+    assert_eq!(
+        vec!["src/util.js"],
+        resolution_candidates("src/util.js", &extensions)
+    );
+    assert_eq!(vec!["src/util"], resolution_candidates("src/util", &[]));
+}
+
+#[test]
+fn extensionless_file_imports_are_probed() {
+    let metadata = JavascriptInferenceMetadata {
+        resolve_extensions: vec![".js".to_string(), "/index.*".to_string()],
+        ..given_metadata("", HashMap::default())
+    };
+    assert_dependency_imports(
+        "src/index.js",
+        r#"
+import { a } from './util';
+import b from '../lib/b.js';
+import c from 'c';
+"#,
+        ["src/util", "src/util.js", "src/util/index.js", "lib/b.js"],
+        ["c"],
+        metadata,
+    );
+}
@@ -0,0 +1,261 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::path::{Path, PathBuf};
+
+use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
+use serde_json::{Map, Value};
+
+use crate::javascript::import_pattern::{apply_replacements_to_match, find_best_match};
+use crate::javascript::util::normalize_path;
+
+const TSCONFIG: &str = "tsconfig.json";
+
+/// The outcome of resolving a (non-relative) import against the `paths` and `baseUrl` of a
+/// `tsconfig.json`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TsConfigResolution {
+    /// The import matched an entry in `paths`: it is one of these build root relative files.
+    Alias(HashSet<String>),
+    /// The import might be this file below the `baseUrl`, but might also be a package, since the
+    /// compiler falls back to `node_modules` resolution.
+    BaseUrl(String),
+}
+
+/// The `compilerOptions` which influence module resolution, after the `extends` chain of a
+/// `tsconfig.json` has been applied.
+#[derive(Debug, Default, Clone)]
+struct CompilerOptions {
+    /// The (build root relative) `baseUrl`.
+    base_url: Option<PathBuf>,
+    /// The `paths`, along with the directory of the config which declared them.
+    paths: Option<(PathBuf, HashMap<String, Vec<String>>)>,
+}
+
+impl CompilerOptions {
+    /// Options are inherited individually: any option set by `other` wins.
+    fn overridden_by(self, other: CompilerOptions) -> CompilerOptions {
+        CompilerOptions {
+            base_url: other.base_url.or(self.base_url),
+            paths: other.paths.or(self.paths),
+        }
+    }
+}
+
+/// The path aliases which apply to a source file, as declared by its nearest `tsconfig.json`
+/// and the configs which that one `extends`.
+#[derive(Debug, Default)]
+pub struct TsConfigPaths {
+    /// The `paths` patterns, with replacements relative to the build root.
+    patterns: HashMap<String, Vec<String>>,
+    base_url: Option<PathBuf>,
+}
+
+impl TsConfigPaths {
+    ///
+    /// Find the nearest `tsconfig.json` above `filepath` in `config_files`, and resolve its
+    /// `extends` chain. Configs which are extended but not present in `config_files` (for example,
+    /// because `node_modules` has not been installed) are skipped.
+    ///
+    pub fn for_file(
+        config_files: &HashMap<PathBuf, &str>,
+        filepath: &Path,
+    ) -> Result<Option<TsConfigPaths>, String> {
+        let Some(config) = filepath
+            .ancestors()
+            .skip(1)
+            .map(|dir| dir.join(TSCONFIG))
+            .find(|candidate| config_files.contains_key(candidate))
+        else {
+            return Ok(None);
+        };
+        let options = load(config_files, &config, &mut vec![])?;
+
+        let patterns = if let Some((declared_in, paths)) = options.paths {
+            // Without a `baseUrl`, `paths` are relative to the config which declared them.
+            let base = options.base_url.as_deref().unwrap_or(&declared_in);
+            paths
+                .into_iter()
+                .map(|(pattern, replacements)| {
+                    let replacements = replacements
+                        .iter()
+                        .filter_map(|replacement| join_normalized(base, replacement))
+                        .collect();
+                    (pattern, replacements)
+                })
+                .collect()
+        } else {
+            HashMap::default()
+        };
+        Ok(Some(TsConfigPaths {
+            patterns,
+            base_url: options.base_url,
+        }))
+    }
+
+    pub fn resolve(&self, import: &str) -> Option<TsConfigResolution> {
+        if import.starts_with('.') || import.starts_with('/') {
+            return None;
+        }
+        if let Some((star_match, pattern)) = find_best_match(&self.patterns, import) {
+            let files: HashSet<String> = self.patterns[pattern]
+                .iter()
+                .filter_map(|replacement| apply_replacements_to_match(&star_match, replacement))
+                .collect();
+            if !files.is_empty() {
+                return Some(TsConfigResolution::Alias(files));
+            }
+        }
+        self.base_url
+            .as_deref()
+            .and_then(|base_url| join_normalized(base_url, import))
+            .map(TsConfigResolution::BaseUrl)
+    }
+}
+
+fn join_normalized(base: &Path, path: &str) -> Option<String> {
+    normalize_path(&base.join(path)).map(|path| path.to_string_lossy().to_string())
+}
+
+fn load(
+    config_files: &HashMap<PathBuf, &str>,
+    path: &Path,
+    seen: &mut Vec<PathBuf>,
+) -> Result<CompilerOptions, String> {
+    if seen.iter().any(|seen| seen == path) {
+        return Err(format!(
+            "Circular `extends` in {}: {}",
+            path.display(),
+            seen.iter()
+                .map(|seen| seen.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        ));
+    }
+    let config: Map<String, Value> = serde_json::from_str(&strip_jsonc(config_files[path]))
+        .map_err(|e| format!("Failed to parse {}: {e}", path.display()))?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    seen.push(path.to_owned());
+    let mut options = CompilerOptions::default();
+    // Since TypeScript 5.0, `extends` may be a list, in which later entries take precedence.
+    let extends = match config.get("extends") {
+        Some(Value::String(extends)) => vec![extends.as_str()],
+        Some(Value::Array(extends)) => extends.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    };
+    for extends in extends {
+        if let Some(parent) = resolve_extends(config_files, directory, extends) {
+            options = options.overridden_by(load(config_files, &parent, seen)?);
+        }
+    }
+    seen.pop();
+
+    let compiler_options = config.get("compilerOptions").and_then(Value::as_object);
+    let base_url = compiler_options
+        .and_then(|compiler_options| compiler_options.get("baseUrl"))
+        .and_then(Value::as_str)
+        .and_then(|base_url| normalize_path(&directory.join(base_url)));
+    let paths = compiler_options
+        .and_then(|compiler_options| compiler_options.get("paths"))
+        .and_then(Value::as_object)
+        .map(|paths| {
+            let paths = paths
+                .iter()
+                .map(|(pattern, replacements)| {
+                    let replacements = replacements
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(Value::as_str)
+                        .map(str::to_owned)
+                        .collect();
+                    (pattern.clone(), replacements)
+                })
+                .collect();
+            (directory.to_owned(), paths)
+        });
+    Ok(options.overridden_by(CompilerOptions { base_url, paths }))
+}
+
+///
+/// Resolve the value of an `extends` field, either relative to the extending config, or as a
+/// config published in a package below a `node_modules` directory of any parent directory.
+///
+fn resolve_extends(
+    config_files: &HashMap<PathBuf, &str>,
+    directory: &Path,
+    extends: &str,
+) -> Option<PathBuf> {
+    let with_json_extension = |path: PathBuf| {
+        let mut with_extension = path.clone().into_os_string();
+        with_extension.push(".json");
+        [path, with_extension.into()]
+    };
+    if extends.starts_with("./") || extends.starts_with("../") {
+        let path = normalize_path(&directory.join(extends))?;
+        return with_json_extension(path)
+            .into_iter()
+            .find(|candidate| config_files.contains_key(candidate));
+    }
+    directory
+        .ancestors()
+        .map(|dir| dir.join("node_modules").join(extends))
+        .flat_map(|path| {
+            let package_default = path.join(TSCONFIG);
+            with_json_extension(path)
+                .into_iter()
+                .chain([package_default])
+        })
+        .find(|candidate| config_files.contains_key(candidate))
+}
+
+///
+/// `tsconfig.json` files are "JSON with comments": strip comments and trailing commas so that they
+/// can be parsed as JSON.
+///
+pub(crate) fn strip_jsonc(contents: &str) -> String {
+    let mut stripped = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                stripped.push(c);
+                while let Some(c) = chars.next() {
+                    stripped.push(c);
+                    match c {
+                        '\\' => stripped.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        stripped.push(c);
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = None;
+                for c in chars.by_ref() {
+                    if previous == Some('*') && c == '/' {
+                        break;
+                    }
+                    previous = Some(c);
+                }
+            }
+            '}' | ']' => {
+                let content_len = stripped.trim_end().len();
+                if stripped[..content_len].ends_with(',') {
+                    stripped.remove(content_len - 1);
+                }
+                stripped.push(c);
+            }
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
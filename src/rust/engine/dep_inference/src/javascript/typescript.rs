@@ -0,0 +1,58 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Import collection for TypeScript sources, using the TSX grammar: it is a superset of both
+//! TypeScript and JSX, other than the angle-bracket type assertions (`<T>x`) which JSX replaces.
+use tree_sitter::Node;
+
+use crate::javascript::ImportCollector;
+
+include!(concat!(env!("OUT_DIR"), "/typescript/visitor.rs"));
+
+impl ImportCollector<'_> {
+    pub fn collect_typescript(&mut self) {
+        let tree = self.parse(tree_sitter_typescript::language_tsx(), "TypeScript");
+        let mut cursor = tree.walk();
+
+        Visitor::walk(self, &mut cursor);
+    }
+}
+
+// NB: The generated `ChildBehavior` of each grammar is a distinct type.
+fn child_behavior(visit_children: bool) -> ChildBehavior {
+    if visit_children {
+        ChildBehavior::Visit
+    } else {
+        ChildBehavior::Ignore
+    }
+}
+
+impl Visitor for ImportCollector<'_> {
+    fn visit_import_statement(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(self.import_or_export_statement(node))
+    }
+
+    fn visit_export_statement(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(self.import_or_export_statement(node))
+    }
+
+    fn visit_expression_statement(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(self.expression_statement(node))
+    }
+
+    fn visit_lexical_declaration(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(!self.is_pragma_ignored(node))
+    }
+
+    fn visit_variable_declaration(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(!self.is_pragma_ignored(node))
+    }
+
+    fn visit_return_statement(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(!self.is_pragma_ignored(node))
+    }
+
+    fn visit_call_expression(&mut self, node: Node) -> ChildBehavior {
+        child_behavior(self.call_expression(node))
+    }
+}
@@ -36,3 +36,31 @@ pub fn normalize_path(path: &Path) -> Option<PathBuf> {
     }
     Some(ret)
 }
+
+/// The files which an import of `file` might resolve to, like Node and TypeScript resolve them: the
+/// file itself and, unless it has one of the `extensions`, the file with each of them appended.
+///
+/// An extension with a `*`, such as `/index.*`, is tried with each of the other extensions (without
+/// their leading `.`), e.g. `/index.js`.
+pub fn resolution_candidates(file: &str, extensions: &[String]) -> Vec<String> {
+    let (patterns, extensions): (Vec<&str>, Vec<&str>) = extensions
+        .iter()
+        .map(String::as_str)
+        .partition(|extension| extension.contains('*'));
+    let mut candidates = vec![file.to_owned()];
+    if extensions.iter().any(|extension| file.ends_with(extension)) {
+        return candidates;
+    }
+    candidates.extend(
+        extensions
+            .iter()
+            .map(|extension| format!("{file}{extension}")),
+    );
+    for pattern in patterns {
+        candidates.extend(extensions.iter().map(|extension| {
+            let replacement = extension.strip_prefix('.').unwrap_or(extension);
+            format!("{file}{}", pattern.replacen('*', replacement, 1))
+        }));
+    }
+    candidates
+}
@@ -0,0 +1,203 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use tree_sitter::{Node, Parser};
+
+use crate::jvm::{name_text, qualify, JvmImport, ParsedJvmDependencies};
+
+include!(concat!(env!("OUT_DIR"), "/jvm/java/visitor.rs"));
+
+pub(super) fn get_dependencies(contents: &str) -> ParsedJvmDependencies {
+    let mut collector = DependencyCollector {
+        code: contents,
+        result: ParsedJvmDependencies::default(),
+    };
+    let mut parser = Parser::new();
+    parser
+        .set_language(tree_sitter_java::language())
+        .expect("Error loading Java grammar");
+    let tree = parser.parse(contents, None).unwrap();
+    collector.walk(&mut tree.walk());
+    collector.result
+}
+
+struct DependencyCollector<'a> {
+    code: &'a str,
+    result: ParsedJvmDependencies,
+}
+
+impl DependencyCollector<'_> {
+    fn name_child(&self, node: Node) -> Option<String> {
+        node.named_children(&mut node.walk())
+            .find(|child| matches!(child.kind(), "identifier" | "scoped_identifier"))
+            .map(|name| name_text(self.code, name))
+    }
+
+    /// Records the name of a top-level type declaration.
+    fn insert_declaration(&mut self, node: Node) {
+        let is_top_level = node
+            .parent()
+            .map_or(true, |parent| parent.kind() == "program");
+        if let Some(name) = node.child_by_field_name("name").filter(|_| is_top_level) {
+            let name = name_text(self.code, name);
+            let qualified = qualify(self.result.package.as_deref(), &name);
+            self.result.declared_symbols.push(qualified);
+        }
+    }
+
+    /// The names of the types mentioned within a node, e.g. `Map`, `String` and `a.b.C` for
+    /// `Map<String, a.b.C>`.
+    fn type_names(&self, node: Node) -> Vec<String> {
+        match node.kind() {
+            "type_identifier" | "scoped_type_identifier" => vec![name_text(self.code, node)],
+            _ => node
+                .named_children(&mut node.walk())
+                .flat_map(|child| self.type_names(child))
+                .collect(),
+        }
+    }
+
+    fn insert_exports(&mut self, node: Option<Node>) {
+        if let Some(node) = node {
+            let names = self.type_names(node);
+            self.result.export_types.extend(names);
+        }
+    }
+
+    /// The types of the class literals (`Foo.class`) within a node.
+    fn class_literals(&self, node: Node) -> Vec<String> {
+        if node.kind() == "class_literal" {
+            return node
+                .named_child(0)
+                .map(|type_node| self.type_names(type_node))
+                .unwrap_or_default();
+        }
+        node.named_children(&mut node.walk())
+            .flat_map(|child| self.class_literals(child))
+            .collect()
+    }
+
+    fn visit_annotation_node(&mut self, node: Node) {
+        if let Some(name) = node.child_by_field_name("name") {
+            let name = name_text(self.code, name);
+            self.result.consumed_symbols.insert(name.clone());
+            self.result.annotation_references.insert(name);
+        }
+        if let Some(arguments) = node.child_by_field_name("arguments") {
+            let literals = self.class_literals(arguments);
+            self.result.annotation_references.extend(literals);
+        }
+    }
+
+    /// `Foo` in `Foo.bar()` or `Foo.BAR`, which might be a type.
+    fn insert_object_name(&mut self, node: Node) {
+        if let Some(object) = node.child_by_field_name("object") {
+            if object.kind() == "identifier" {
+                self.result
+                    .consumed_symbols
+                    .insert(name_text(self.code, object));
+            }
+        }
+    }
+}
+
+impl Visitor for DependencyCollector<'_> {
+    fn visit_package_declaration(&mut self, node: Node) -> ChildBehavior {
+        self.result.package = self.name_child(node);
+        ChildBehavior::Ignore
+    }
+
+    fn visit_import_declaration(&mut self, node: Node) -> ChildBehavior {
+        if let Some(name) = self.name_child(node) {
+            let (mut is_static, mut is_wildcard) = (false, false);
+            for child in node.children(&mut node.walk()) {
+                match child.kind() {
+                    "static" => is_static = true,
+                    "asterisk" => is_wildcard = true,
+                    _ => (),
+                }
+            }
+            self.result.imports.push(JvmImport {
+                name,
+                is_static,
+                is_wildcard,
+                alias: None,
+            });
+        }
+        ChildBehavior::Ignore
+    }
+
+    fn visit_type_identifier(&mut self, node: Node) -> ChildBehavior {
+        let name = name_text(self.code, node);
+        // `var` is parsed as a type, but is not one.
+        if name != "var" {
+            self.result.consumed_symbols.insert(name);
+        }
+        ChildBehavior::Ignore
+    }
+
+    fn visit_scoped_type_identifier(&mut self, node: Node) -> ChildBehavior {
+        self.result
+            .consumed_symbols
+            .insert(name_text(self.code, node));
+        ChildBehavior::Ignore
+    }
+
+    fn visit_class_declaration(&mut self, node: Node) -> ChildBehavior {
+        self.insert_declaration(node);
+        self.insert_exports(node.child_by_field_name("superclass"));
+        self.insert_exports(node.child_by_field_name("interfaces"));
+        ChildBehavior::Visit
+    }
+
+    fn visit_interface_declaration(&mut self, node: Node) -> ChildBehavior {
+        self.insert_declaration(node);
+        let extends = node
+            .named_children(&mut node.walk())
+            .find(|child| child.kind() == "extends_interfaces");
+        self.insert_exports(extends);
+        ChildBehavior::Visit
+    }
+
+    fn visit_enum_declaration(&mut self, node: Node) -> ChildBehavior {
+        self.insert_declaration(node);
+        self.insert_exports(node.child_by_field_name("interfaces"));
+        ChildBehavior::Visit
+    }
+
+    fn visit_record_declaration(&mut self, node: Node) -> ChildBehavior {
+        self.insert_declaration(node);
+        self.insert_exports(node.child_by_field_name("interfaces"));
+        ChildBehavior::Visit
+    }
+
+    fn visit_annotation_type_declaration(&mut self, node: Node) -> ChildBehavior {
+        self.insert_declaration(node);
+        ChildBehavior::Visit
+    }
+
+    fn visit_method_declaration(&mut self, node: Node) -> ChildBehavior {
+        self.insert_exports(node.child_by_field_name("type"));
+        self.insert_exports(node.child_by_field_name("parameters"));
+        ChildBehavior::Visit
+    }
+
+    fn visit_annotation(&mut self, node: Node) -> ChildBehavior {
+        self.visit_annotation_node(node);
+        ChildBehavior::Visit
+    }
+
+    fn visit_marker_annotation(&mut self, node: Node) -> ChildBehavior {
+        self.visit_annotation_node(node);
+        ChildBehavior::Visit
+    }
+
+    fn visit_method_invocation(&mut self, node: Node) -> ChildBehavior {
+        self.insert_object_name(node);
+        ChildBehavior::Visit
+    }
+
+    fn visit_field_access(&mut self, node: Node) -> ChildBehavior {
+        self.insert_object_name(node);
+        ChildBehavior::Visit
+    }
+}
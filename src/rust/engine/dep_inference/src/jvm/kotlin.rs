@@ -0,0 +1,202 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use tree_sitter::{Node, Parser};
+
+use crate::jvm::{name_text, qualify, JvmImport, ParsedJvmDependencies};
+
+include!(concat!(env!("OUT_DIR"), "/jvm/kotlin/visitor.rs"));
+
+pub(super) fn get_dependencies(contents: &str) -> ParsedJvmDependencies {
+    let mut collector = DependencyCollector {
+        code: contents,
+        result: ParsedJvmDependencies::default(),
+    };
+    let mut parser = Parser::new();
+    parser
+        .set_language(tree_sitter_kotlin::language())
+        .expect("Error loading Kotlin grammar");
+    let tree = parser.parse(contents, None).unwrap();
+    collector.walk(&mut tree.walk());
+    collector.result
+}
+
+struct DependencyCollector<'a> {
+    code: &'a str,
+    result: ParsedJvmDependencies,
+}
+
+impl DependencyCollector<'_> {
+    fn child_text(&self, node: Node, kind: &str) -> Option<String> {
+        node.named_children(&mut node.walk())
+            .find(|child| child.kind() == kind)
+            .map(|child| name_text(self.code, child))
+    }
+
+    /// The qualified name of a class or object, which may be nested in others, unless it is local
+    /// to a function (or otherwise anonymous).
+    fn insert_class_declaration(&mut self, node: Node) {
+        let Some(name) = self.child_text(node, "type_identifier") else {
+            return;
+        };
+        let mut names = vec![name];
+        let mut parent = node.parent();
+        while let Some(ancestor) = parent {
+            match ancestor.kind() {
+                "source_file" => break,
+                "class_body" | "enum_class_body" => (),
+                "class_declaration" | "object_declaration" => {
+                    match self.child_text(ancestor, "type_identifier") {
+                        Some(name) => names.push(name),
+                        None => return,
+                    }
+                }
+                _ => return,
+            }
+            parent = ancestor.parent();
+        }
+        names.reverse();
+        let qualified = qualify(self.result.package.as_deref(), &names.join("."));
+        self.result.declared_symbols.push(qualified);
+    }
+
+    fn insert_top_level_declaration(&mut self, node: Node, name: Option<String>) {
+        let is_top_level = node
+            .parent()
+            .map_or(false, |parent| parent.kind() == "source_file");
+        if let Some(name) = name.filter(|_| is_top_level) {
+            let qualified = qualify(self.result.package.as_deref(), &name);
+            self.result.declared_symbols.push(qualified);
+        }
+    }
+
+    /// The name of a `user_type`, e.g. `a.b.C` for `a.b.C<D>`.
+    fn user_type_name(&self, node: Node) -> String {
+        node.named_children(&mut node.walk())
+            .filter(|child| child.kind() == "type_identifier")
+            .map(|child| name_text(self.code, child))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// The types of the class literals (`Foo::class`) within a node.
+    fn class_literals(&self, node: Node) -> Vec<String> {
+        if node.kind() == "callable_reference" {
+            let is_class_literal = node
+                .child(node.child_count().saturating_sub(1))
+                .map_or(false, |last| last.kind() == "class");
+            return self
+                .child_text(node, "type_identifier")
+                .filter(|_| is_class_literal)
+                .into_iter()
+                .collect();
+        }
+        node.named_children(&mut node.walk())
+            .flat_map(|child| self.class_literals(child))
+            .collect()
+    }
+
+    /// `Foo` in `Foo()` or `Foo.bar`, which might be a type.
+    fn insert_receiver_name(&mut self, node: Node) {
+        if let Some(receiver) = node
+            .named_child(0)
+            .filter(|child| child.kind() == "simple_identifier")
+        {
+            self.result
+                .consumed_symbols
+                .insert(name_text(self.code, receiver));
+        }
+    }
+}
+
+impl Visitor for DependencyCollector<'_> {
+    fn visit_package_header(&mut self, node: Node) -> ChildBehavior {
+        self.result.package = self.child_text(node, "identifier");
+        ChildBehavior::Ignore
+    }
+
+    fn visit_import_header(&mut self, node: Node) -> ChildBehavior {
+        if let Some(name) = self.child_text(node, "identifier") {
+            // NB: Older releases of the grammar represent a wildcard as an anonymous `.*` token.
+            let is_wildcard = node
+                .children(&mut node.walk())
+                .any(|child| matches!(child.kind(), "wildcard_import" | ".*"));
+            let alias = node
+                .named_children(&mut node.walk())
+                .find(|child| child.kind() == "import_alias")
+                .and_then(|alias| self.child_text(alias, "type_identifier"));
+            self.result.imports.push(JvmImport {
+                name,
+                is_static: false,
+                is_wildcard,
+                alias,
+            });
+        }
+        ChildBehavior::Ignore
+    }
+
+    fn visit_class_declaration(&mut self, node: Node) -> ChildBehavior {
+        self.insert_class_declaration(node);
+        ChildBehavior::Visit
+    }
+
+    fn visit_object_declaration(&mut self, node: Node) -> ChildBehavior {
+        self.insert_class_declaration(node);
+        ChildBehavior::Visit
+    }
+
+    fn visit_function_declaration(&mut self, node: Node) -> ChildBehavior {
+        let name = self.child_text(node, "simple_identifier");
+        self.insert_top_level_declaration(node, name);
+        ChildBehavior::Visit
+    }
+
+    fn visit_property_declaration(&mut self, node: Node) -> ChildBehavior {
+        let name = node
+            .named_children(&mut node.walk())
+            .find(|child| child.kind() == "variable_declaration")
+            .and_then(|declaration| self.child_text(declaration, "simple_identifier"));
+        self.insert_top_level_declaration(node, name);
+        ChildBehavior::Visit
+    }
+
+    fn visit_type_alias(&mut self, node: Node) -> ChildBehavior {
+        let name = self.child_text(node, "type_identifier");
+        self.insert_top_level_declaration(node, name);
+        ChildBehavior::Visit
+    }
+
+    fn visit_user_type(&mut self, node: Node) -> ChildBehavior {
+        let name = self.user_type_name(node);
+        self.result.consumed_symbols.insert(name);
+        ChildBehavior::Visit
+    }
+
+    fn visit_annotation(&mut self, node: Node) -> ChildBehavior {
+        let user_type =
+            node.named_children(&mut node.walk())
+                .find_map(|child| match child.kind() {
+                    "user_type" => Some(child),
+                    "constructor_invocation" => child
+                        .named_children(&mut child.walk())
+                        .find(|child| child.kind() == "user_type"),
+                    _ => None,
+                });
+        if let Some(user_type) = user_type {
+            let name = self.user_type_name(user_type);
+            self.result.annotation_references.insert(name);
+        }
+        let literals = self.class_literals(node);
+        self.result.annotation_references.extend(literals);
+        ChildBehavior::Visit
+    }
+
+    fn visit_call_expression(&mut self, node: Node) -> ChildBehavior {
+        self.insert_receiver_name(node);
+        ChildBehavior::Visit
+    }
+
+    fn visit_navigation_expression(&mut self, node: Node) -> ChildBehavior {
+        self.insert_receiver_name(node);
+        ChildBehavior::Visit
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Dependency inference for JVM languages: the package, imports and referenced symbols of Java and
+//! Kotlin sources, which share a result so that the JVM backends can treat them alike.
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+use fnv::FnvHashSet as HashSet;
+use serde_derive::{Deserialize, Serialize};
+
+mod java;
+mod kotlin;
+
+include!(concat!(env!("OUT_DIR"), "/jvm_impl_hash.rs"));
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JvmImport {
+    pub name: String,
+    /// A Java `import static`.
+    pub is_static: bool,
+    /// An `import a.b.*`, for which `name` is `a.b`.
+    pub is_wildcard: bool,
+    /// A Kotlin `import a.b.C as D`.
+    pub alias: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ParsedJvmDependencies {
+    pub package: Option<String>,
+    pub imports: Vec<JvmImport>,
+    /// The fully qualified names of the symbols which the file declares: its top-level types, and
+    /// for Kotlin also nested classes and objects, and top-level functions, properties and type
+    /// aliases.
+    pub declared_symbols: Vec<String>,
+    /// Possibly qualified names of the types (or values, e.g. for `Foo.bar()`) used in the file.
+    pub consumed_symbols: HashSet<String>,
+    /// The types used in the signatures of methods and the supertypes of classes, which consumers
+    /// of the file will likely need too.
+    pub export_types: HashSet<String>,
+    /// Annotations, and the class literals in their arguments (e.g. `Foo` for
+    /// `@AutoService(Foo.class)`), which annotation processors will need on their classpath.
+    pub annotation_references: HashSet<String>,
+}
+
+pub fn get_dependencies(
+    contents: &str,
+    filepath: PathBuf,
+) -> Result<ParsedJvmDependencies, String> {
+    match filepath.extension().and_then(OsStr::to_str) {
+        Some("java") => Ok(java::get_dependencies(contents)),
+        Some("kt" | "kts") => Ok(kotlin::get_dependencies(contents)),
+        _ => Err(format!(
+            "{} is not a Java or Kotlin source file",
+            filepath.display()
+        )),
+    }
+}
+
+/// A name qualified by a package, if there is one.
+fn qualify(package: Option<&str>, name: &str) -> String {
+    match package {
+        Some(package) => format!("{package}.{name}"),
+        None => name.to_owned(),
+    }
+}
+
+/// The text of a (possibly qualified) name, without any whitespace between its parts.
+fn name_text(code: &str, node: tree_sitter::Node) -> String {
+    code[node.start_byte()..node.end_byte()]
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
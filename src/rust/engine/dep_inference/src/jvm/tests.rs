@@ -0,0 +1,273 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::jvm::{get_dependencies, JvmImport, ParsedJvmDependencies};
+
+fn parse(filepath: &str, code: &str) -> ParsedJvmDependencies {
+    get_dependencies(code, PathBuf::from(filepath)).unwrap()
+}
+
+fn import(name: &str, is_static: bool, is_wildcard: bool, alias: Option<&str>) -> JvmImport {
+    JvmImport {
+        name: name.to_owned(),
+        is_static,
+        is_wildcard,
+        alias: alias.map(str::to_owned),
+    }
+}
+
+fn set(names: &[&str]) -> HashSet<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn assert_set(expected: &[&str], actual: &fnv::FnvHashSet<String>) {
+    assert_eq!(
+        set(expected),
+        actual.iter().cloned().collect::<HashSet<_>>()
+    );
+}
+
+#[test]
+fn java_package_and_imports() {
+    let result = parse(
+        "Foo.java",
+        r#"
+package org.pantsbuild.example;
+
+import java.util.List;
+import static org.junit.Assert.assertEquals;
+import java.util.concurrent.*;
+import static org.mockito.Mockito.*;
+"#,
+    );
+    assert_eq!(Some("org.pantsbuild.example"), result.package.as_deref());
+    assert_eq!(
+        vec![
+            import("java.util.List", false, false, None),
+            import("org.junit.Assert.assertEquals", true, false, None),
+            import("java.util.concurrent", false, true, None),
+            import("org.mockito.Mockito", true, true, None),
+        ],
+        result.imports
+    );
+}
+
+#[test]
+fn java_declared_symbols() {
+    let result = parse(
+        "Foo.java",
+        r#"
+package org.pantsbuild.example;
+
+public class Foo {
+    class Inner {}
+}
+interface Bar {}
+enum Baz {}
+record Qux(int a) {}
+@interface Quux {}
+"#,
+    );
+    assert_eq!(
+        vec![
+            "org.pantsbuild.example.Foo",
+            "org.pantsbuild.example.Bar",
+            "org.pantsbuild.example.Baz",
+            "org.pantsbuild.example.Qux",
+            "org.pantsbuild.example.Quux",
+        ],
+        result.declared_symbols
+    );
+}
+
+#[test]
+fn java_default_package() {
+    let result = parse("Foo.java", "public class Foo {}");
+    assert_eq!(None, result.package);
+    assert_eq!(vec!["Foo"], result.declared_symbols);
+}
+
+#[test]
+fn java_consumed_and_export_types() {
+    let result = parse(
+        "Foo.java",
+        r#"
+package org.pantsbuild.example;
+
+public class Foo extends Base implements a.b.Iface {
+    private Map<String, Widget> widgets;
+
+    public Result run(Input input) throws Failure {
+        var local = Helper.create();
+        return Constants.DEFAULT;
+    }
+}
+"#,
+    );
+    assert_set(
+        &[
+            "Base",
+            "a.b.Iface",
+            "Map",
+            "String",
+            "Widget",
+            "Result",
+            "Input",
+            "Failure",
+            "Helper",
+            "Constants",
+        ],
+        &result.consumed_symbols,
+    );
+    assert_set(
+        &["Base", "a.b.Iface", "Result", "Input"],
+        &result.export_types,
+    );
+}
+
+#[test]
+fn java_annotation_references() {
+    let result = parse(
+        "Foo.java",
+        r#"
+@AutoService(Processor.class)
+@JsonDeserialize(using = a.b.Deserializer.class, as = Impl.class)
+@Deprecated
+public class Foo {
+    @Override
+    public String toString() { return "" + String.class; }
+}
+"#,
+    );
+    assert_set(
+        &[
+            "AutoService",
+            "Processor",
+            "JsonDeserialize",
+            "a.b.Deserializer",
+            "Impl",
+            "Deprecated",
+            "Override",
+        ],
+        &result.annotation_references,
+    );
+}
+
+#[test]
+fn kotlin_package_and_imports() {
+    let result = parse(
+        "Foo.kt",
+        r#"
+package org.pantsbuild.example
+
+import java.util.List
+import org.pantsbuild.lib.*
+import org.pantsbuild.other.Thing as OtherThing
+"#,
+    );
+    assert_eq!(Some("org.pantsbuild.example"), result.package.as_deref());
+    assert_eq!(
+        vec![
+            import("java.util.List", false, false, None),
+            import("org.pantsbuild.lib", false, true, None),
+            import(
+                "org.pantsbuild.other.Thing",
+                false,
+                false,
+                Some("OtherThing")
+            ),
+        ],
+        result.imports
+    );
+}
+
+#[test]
+fn kotlin_declared_symbols() {
+    let result = parse(
+        "Foo.kts",
+        r#"
+package org.pantsbuild.example
+
+class Foo {
+    class Nested
+    object Singleton
+    fun method() {
+        class Local
+    }
+}
+object Bar
+interface Baz
+fun topLevel() {}
+val property = 1
+typealias Alias = Foo
+"#,
+    );
+    assert_eq!(
+        set(&[
+            "org.pantsbuild.example.Foo",
+            "org.pantsbuild.example.Foo.Nested",
+            "org.pantsbuild.example.Foo.Singleton",
+            "org.pantsbuild.example.Bar",
+            "org.pantsbuild.example.Baz",
+            "org.pantsbuild.example.topLevel",
+            "org.pantsbuild.example.property",
+            "org.pantsbuild.example.Alias",
+        ]),
+        result.declared_symbols.into_iter().collect::<HashSet<_>>()
+    );
+}
+
+#[test]
+fn kotlin_consumed_symbols() {
+    let result = parse(
+        "Foo.kt",
+        r#"
+class Foo(val widgets: Map<String, a.b.Widget>) : Base() {
+    fun run(): Result {
+        val helper = Helper()
+        return Constants.DEFAULT
+    }
+}
+"#,
+    );
+    for symbol in [
+        "Map",
+        "String",
+        "a.b.Widget",
+        "Base",
+        "Result",
+        "Helper",
+        "Constants",
+    ] {
+        assert!(
+            result.consumed_symbols.contains(symbol),
+            "{symbol} was not consumed"
+        );
+    }
+}
+
+#[test]
+fn kotlin_annotation_references() {
+    let result = parse(
+        "Foo.kt",
+        r#"
+@AutoService(Processor::class)
+@Deprecated("old")
+class Foo {
+    @JvmField val bar = Bar::class
+}
+"#,
+    );
+    assert_set(
+        &["AutoService", "Processor", "Deprecated", "JvmField"],
+        &result.annotation_references,
+    );
+}
+
+#[test]
+fn unsupported_extension() {
+    assert!(get_dependencies("", PathBuf::from("Foo.scala")).is_err());
+}
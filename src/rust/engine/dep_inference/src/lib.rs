@@ -1,5 +1,12 @@
 // Copyright 2023 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+pub mod build_file;
+pub mod css;
+pub mod dockerfile;
+pub mod go;
 pub mod javascript;
+pub mod jvm;
 pub mod python;
+pub mod shell;
+pub mod span;
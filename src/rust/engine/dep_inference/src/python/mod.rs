@@ -10,10 +10,34 @@ use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
 use serde_derive::{Deserialize, Serialize};
 use tree_sitter::Parser;
 
+use crate::span::SourceSpan;
+
+/// How certain it is that an inferred import refers to a module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportConfidence {
+    /// An import statement, or a call which imports a module named by a string.
+    Strong,
+    /// A string which looks like a module path, but which might not be used as one.
+    Weak,
+}
+
+impl ImportConfidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportConfidence::Strong => "strong",
+            ImportConfidence::Weak => "weak",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ParsedPythonDependencies {
-    pub imports: HashMap<String, (u64, bool)>,
+    /// The line, whether the import is weak (i.e. it might legitimately fail, such as inside a
+    /// `try` which handles `ImportError`), and the confidence of each import.
+    pub imports: HashMap<String, (u64, bool, ImportConfidence)>,
     pub string_candidates: HashMap<String, u64>,
+    /// The location of each import, on the same line as the line of the import.
+    pub import_spans: HashMap<String, SourceSpan>,
 }
 
 pub fn get_dependencies(
@@ -24,6 +48,7 @@ pub fn get_dependencies(
     collector.collect();
 
     let mut import_map = collector.import_map;
+    let mut import_spans = collector.import_spans;
 
     // NB: the import collector doesn't do anything special for relative imports, we need to fix
     // those up.
@@ -57,19 +82,52 @@ pub fn get_dependencies(
             new_key_parts.push(nonrelative);
         }
 
+        let new_key = new_key_parts.join(".");
         let old_value = import_map.remove(&key).unwrap();
-        import_map.insert(new_key_parts.join("."), old_value);
+        if let Some(span) = import_spans.remove(&key) {
+            import_spans.insert(new_key.clone(), span);
+        }
+        import_map.insert(new_key, old_value);
+    }
+
+    let mut imports: HashMap<_, _> = import_map
+        .into_iter()
+        .map(|(name, (line, weak))| (name, (line, weak, ImportConfidence::Strong)))
+        .collect();
+    // Strings which look like module paths might be imported dynamically, e.g. by a plugin
+    // registry. They are always weak, and never replace an actual import of the same module.
+    for (string, line) in &collector.string_candidates {
+        if is_module_path(string) && !imports.contains_key(string) {
+            imports.insert(string.clone(), (*line, true, ImportConfidence::Weak));
+            if let Some(span) = collector.string_spans.get(string) {
+                import_spans.insert(string.clone(), *span);
+            }
+        }
     }
 
     Ok(ParsedPythonDependencies {
-        imports: import_map,
+        imports,
         string_candidates: collector.string_candidates,
+        import_spans,
+    })
+}
+
+/// Whether `string` is a dotted path of identifiers, e.g. `a.b.C`.
+fn is_module_path(string: &str) -> bool {
+    string.split('.').all(|part| {
+        let mut chars = part.chars();
+        chars
+            .next()
+            .map_or(false, |first| first.is_alphabetic() || first == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_')
     })
 }
 
 struct ImportCollector<'a> {
     pub import_map: HashMap<String, (u64, bool)>,
     pub string_candidates: HashMap<String, u64>,
+    pub import_spans: HashMap<String, SourceSpan>,
+    pub string_spans: HashMap<String, SourceSpan>,
     code: &'a str,
     weaken_imports: bool,
 }
@@ -79,6 +137,8 @@ impl ImportCollector<'_> {
         ImportCollector {
             import_map: HashMap::default(),
             string_candidates: HashMap::default(),
+            import_spans: HashMap::default(),
+            string_spans: HashMap::default(),
             code,
             weaken_imports: false,
         }
@@ -206,13 +266,136 @@ impl ImportCollector<'_> {
             None => base_ref.to_string(),
         };
 
-        let line0 = most_specific.range().start_point.row;
+        self.insert_import_name(full_name, most_specific);
+    }
+
+    fn insert_import_name(&mut self, full_name: String, node: tree_sitter::Node) {
+        let line0 = node.range().start_point.row;
 
+        self.import_spans
+            .entry(full_name.clone())
+            .or_insert_with(|| SourceSpan::of(node));
         self.import_map
             .entry(full_name)
             .and_modify(|v| *v = (v.0, v.1 && self.weaken_imports))
             .or_insert(((line0 as u64) + 1, self.weaken_imports));
     }
+
+    /// The value of a constant string argument, i.e. one without interpolations.
+    fn constant_string(&self, node: tree_sitter::Node) -> Option<&str> {
+        let is_constant = node.kind_id() == KindID::STRING
+            && !node
+                .named_children(&mut node.walk())
+                .any(|child| child.kind_id() == KindID::INTERPOLATION);
+        is_constant.then(|| self.string_at(node.range()))
+    }
+
+    /// The positional argument at `position`, or else the keyword argument called `keyword`.
+    fn argument<'t>(
+        &self,
+        args: tree_sitter::Node<'t>,
+        position: usize,
+        keyword: &str,
+    ) -> Option<tree_sitter::Node<'t>> {
+        let mut cursor = args.walk();
+        let (keyword_args, positional_args): (Vec<_>, Vec<_>) = args
+            .named_children(&mut cursor)
+            .filter(|arg| arg.kind_id() != KindID::COMMENT)
+            .partition(|arg| arg.kind_id() == KindID::KEYWORD_ARGUMENT);
+        positional_args.get(position).copied().or_else(|| {
+            keyword_args
+                .into_iter()
+                .find(|arg| {
+                    arg.child_by_field_name("name")
+                        .map_or(false, |name| self.code_at(name.range()) == keyword)
+                })
+                .and_then(|arg| arg.child_by_field_name("value"))
+        })
+    }
+
+    ///
+    /// Handle calls which import a module named by a string:
+    ///
+    /// ```python
+    /// __import__("a.b")
+    /// importlib.import_module("a.b")
+    /// importlib.import_module(".b", package="a")
+    /// pkgutil.resolve_name("a.b:c")
+    /// pkgutil.get_data("a.b", "data.json")
+    /// ```
+    ///
+    /// Returns whether the call was one of these.
+    ///
+    fn string_import_call(&mut self, node: tree_sitter::Node) -> bool {
+        let (Some(function), Some(args)) = (
+            node.child_by_field_name("function"),
+            node.child_by_field_name("arguments"),
+        ) else {
+            return false;
+        };
+        let function = self.code_at(function.range());
+        let is_import_module = match function {
+            "importlib.import_module" | "import_module" => true,
+            "__import__"
+            | "pkgutil.resolve_name"
+            | "pkgutil.get_data"
+            | "pkgutil.get_loader"
+            | "pkgutil.find_loader" => false,
+            _ => return false,
+        };
+        let keyword = if function == "pkgutil.get_data" {
+            "package"
+        } else {
+            "name"
+        };
+        let Some(name_node) = self.argument(args, 0, keyword) else {
+            return true;
+        };
+        let Some(name) = self.constant_string(name_node) else {
+            return true;
+        };
+        // The pragma may follow the call, the opening parenthesis, or the name itself.
+        let call_row = node.range().start_point.row;
+        let ignored_after_parenthesis = args
+            .named_children(&mut args.walk())
+            .any(|comment| self.is_pragma_ignored_at_row(comment, call_row));
+        if ignored_after_parenthesis
+            || self.is_pragma_ignored_recursive(node)
+            || self.is_pragma_ignored(name_node)
+        {
+            return true;
+        }
+
+        let name = if is_import_module && name.starts_with('.') {
+            // A relative import is resolved against `package`: when it is a constant, resolve it
+            // here. Otherwise it is usually `__package__`, which is equivalent to a relative import
+            // from this file.
+            let package = self
+                .argument(args, 1, "package")
+                .and_then(|package| self.constant_string(package));
+            match package {
+                Some(package) => {
+                    let relative = name.trim_start_matches('.');
+                    let level = name.len() - relative.len();
+                    let mut parts: Vec<&str> = package.split('.').collect();
+                    if level > parts.len() {
+                        return true;
+                    }
+                    parts.truncate(parts.len() + 1 - level);
+                    if !relative.is_empty() {
+                        parts.push(relative);
+                    }
+                    parts.join(".")
+                }
+                None => name.to_string(),
+            }
+        } else {
+            // `pkgutil.resolve_name` accepts `module:attribute` as well as dotted names.
+            name.replacen(':', ".", 1)
+        };
+        self.insert_import_name(name, name_node);
+        true
+    }
 }
 
 // NB: https://tree-sitter.github.io/tree-sitter/playground is very helpful
@@ -331,31 +514,22 @@ impl Visitor for ImportCollector<'_> {
     }
 
     fn visit_call(&mut self, node: tree_sitter::Node) -> ChildBehavior {
-        let funcname = node.named_child(0).unwrap();
-        if self.code_at(funcname.range()) != "__import__" {
-            return ChildBehavior::Visit;
-        }
-
-        let args = node.named_child(1).unwrap();
-        if let Some(arg) = args.named_child(0) {
-            if arg.kind_id() == KindID::STRING {
-                // NB: Call nodes are children of expression nodes. The comment is a sibling of the expression.
-                if !self.is_pragma_ignored(node.parent().unwrap()) {
-                    self.insert_import(arg, None, true);
-                }
-            }
+        if self.string_import_call(node) {
+            ChildBehavior::Ignore
+        } else {
+            ChildBehavior::Visit
         }
-        ChildBehavior::Ignore
     }
 
     fn visit_string(&mut self, node: tree_sitter::Node) -> ChildBehavior {
         let range = node.range();
-        let text: &str = self.string_at(range);
+        let text = self.string_at(range).to_string();
         if !text.contains(|c: char| c.is_ascii_whitespace() || c == '\\')
             && !self.is_pragma_ignored_recursive(node)
         {
             self.string_candidates
-                .insert(text.to_string(), (range.start_point.row + 1) as u64);
+                .insert(text.clone(), (range.start_point.row + 1) as u64);
+            self.string_spans.insert(text, SourceSpan::of(node));
         }
         ChildBehavior::Ignore
     }
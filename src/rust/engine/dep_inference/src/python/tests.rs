@@ -1,6 +1,7 @@
 // Copyright 2023 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
-use crate::python::{get_dependencies, ImportCollector};
+use crate::python::{get_dependencies, ImportCollector, ImportConfidence};
+use crate::span::SourceSpan;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
@@ -281,6 +282,58 @@ fn dunder_import() {
     );
 }
 
+#[test]
+fn importlib_import_module() {
+    assert_imports("importlib.import_module('a.b')", &["a.b"]);
+    assert_imports("import_module('a.b')", &["a.b"]);
+    assert_imports("m = importlib.import_module(name='a.b')", &["a.b"]);
+    assert_imports("importlib.import_module('.b', 'a')", &["a.b"]);
+    assert_imports("importlib.import_module('..c', package='a.b')", &["a.c"]);
+    assert_imports("importlib.import_module('.b', __package__)", &[".b"]);
+    assert_imports("importlib.import_module('...c', 'a')", &[]);
+    assert_imports("importlib.import_module(f'plugins.{name}')", &[]);
+    assert_imports("importlib.import_module(name)", &[]);
+    assert_imports("other.import_module('a')", &[]);
+    assert_imports(
+        "m = importlib.import_module('ignored')  # pants: no-infer-dep",
+        &[],
+    );
+    assert_imports(
+        r"
+    plugin = importlib.import_module(
+        'ignored'  # pants: no-infer-dep
+    )",
+        &[],
+    );
+}
+
+#[test]
+fn pkgutil_imports() {
+    assert_imports("pkgutil.resolve_name('a.b')", &["a.b"]);
+    assert_imports("pkgutil.resolve_name('a.b:c')", &["a.b.c"]);
+    assert_imports("pkgutil.get_data('a.b', 'data.json')", &["a.b"]);
+    assert_imports(
+        "pkgutil.get_data(package='a.b', resource='data.json')",
+        &["a.b"],
+    );
+    assert_imports("pkgutil.get_loader('a.b')", &["a.b"]);
+    assert_imports("pkgutil.find_loader('a.b')", &["a.b"]);
+    assert_imports("resolve_name('a.b')", &[]);
+    assert_imports(
+        "pkgutil.get_data('ignored', 'x')  # pants: no-infer-dep",
+        &[],
+    );
+}
+
+#[test]
+fn relative_import_module() {
+    assert_relative_imports(
+        "foo/bar/baz.py",
+        "importlib.import_module('.d', __package__)",
+        &["foo.bar.d"],
+    );
+}
+
 fn assert_imports_strong_weak(code: &str, strong: &[&str], weak: &[&str]) {
     let mut collector = ImportCollector::new(code);
     collector.collect();
@@ -737,16 +790,15 @@ fn python2() {
             ("project.demo.Demo", (5, false)),
             ("pkg_resources", (7, false)),
             ("treat.as.a.regular.import.not.a.string.import", (8, false)),
+            ("dep.from.bytes", (11, false)),
+            ("dep.from.str", (12, false)),
+            ("dep.from.str_狗", (13, false)),
             ("weak1", (17, true)),
             ("strong1", (18, false)),
             ("strong2", (19, false)),
             ("strong3", (20, false)),
         ]),
-        HashMap::from([
-            ("dep.from.bytes", 11),
-            ("dep.from.str", 12),
-            ("dep.from.str_狗", 13),
-        ]),
+        HashMap::new(),
     );
 }
 
@@ -763,6 +815,67 @@ fn assert_relative_imports(filename: &str, code: &str, resolved_imports: &[&str]
     );
 }
 
+#[test]
+fn import_confidence() {
+    let result = get_dependencies(
+        r"
+import a.b
+'a.b'
+'c.d.E'
+'_e'
+'not a module'
+'f/g.json'
+'h..i'
+'2j.k'
+'l.m'  # pants: no-infer-dep
+",
+        PathBuf::from("foo/bar.py"),
+    )
+    .unwrap();
+    assert_eq!(
+        HashMap::from_iter([
+            ("a.b".to_string(), (2, false, ImportConfidence::Strong)),
+            ("c.d.E".to_string(), (4, true, ImportConfidence::Weak)),
+            ("_e".to_string(), (5, true, ImportConfidence::Weak)),
+        ]),
+        result.imports
+    );
+}
+
+#[test]
+fn import_spans() {
+    let result = get_dependencies(
+        r"
+import a.b
+from .c import (
+    d,
+)
+import a.b
+'e.f'
+",
+        PathBuf::from("foo/bar.py"),
+    )
+    .unwrap();
+    let span = |start_byte, end_byte, line, start_column, end_column| SourceSpan {
+        start_byte,
+        end_byte,
+        start_line: line,
+        start_column,
+        end_line: line,
+        end_column,
+    };
+    assert_eq!(
+        HashMap::from_iter([
+            // The first import of a module.
+            ("a.b".to_string(), span(8, 11, 2, 7, 10)),
+            // The most specific name of a `from` import.
+            ("foo.c.d".to_string(), span(33, 34, 4, 4, 5)),
+            ("e.f".to_string(), span(49, 54, 7, 0, 5)),
+        ]),
+        result.import_spans.into_iter().collect::<HashMap<_, _>>()
+    );
+}
+
 #[test]
 fn relative_imports_resolution() {
     let filename = "foo/bar/baz.py";
@@ -0,0 +1,154 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Dependency inference for shell scripts: the files which are sourced (with `source` or `.`), and
+//! the commands which are invoked.
+use std::collections::HashMap;
+
+use fnv::FnvHashSet as HashSet;
+use serde_derive::{Deserialize, Serialize};
+use tree_sitter::{Node, Parser};
+
+include!(concat!(env!("OUT_DIR"), "/shell/visitor.rs"));
+include!(concat!(env!("OUT_DIR"), "/shell_impl_hash.rs"));
+
+const PRAGMA: &str = "pants: no-infer-dep";
+
+#[derive(Serialize, Deserialize)]
+pub struct ParsedShellDependencies {
+    /// The paths of sourced files, as written.
+    pub file_imports: HashSet<String>,
+    /// The names (or paths) of invoked commands, including builtins.
+    pub commands: HashSet<String>,
+}
+
+pub fn get_dependencies(contents: &str) -> ParsedShellDependencies {
+    let mut collector = DependencyCollector {
+        code: contents,
+        ignored_rows: HashSet::default(),
+        source_directives: HashMap::new(),
+        file_imports: HashSet::default(),
+        commands: HashSet::default(),
+    };
+    for (row, line) in contents.lines().enumerate() {
+        if line.contains(PRAGMA) {
+            collector.ignored_rows.insert(row);
+        }
+        if let Some(path) = source_directive(line) {
+            // A directive applies to the command which follows it.
+            collector.source_directives.insert(row + 1, path.to_owned());
+        }
+    }
+
+    let mut parser = Parser::new();
+    parser
+        .set_language(tree_sitter_bash::language())
+        .expect("Error loading Bash grammar");
+    let tree = parser.parse(contents, None).unwrap();
+    collector.walk(&mut tree.walk());
+
+    ParsedShellDependencies {
+        file_imports: collector.file_imports,
+        commands: collector.commands,
+    }
+}
+
+/// The path of a `# shellcheck source=path` directive, which tells ShellCheck (and so us) which
+/// file a dynamic `source` refers to.
+fn source_directive(line: &str) -> Option<&str> {
+    let directive = line
+        .trim()
+        .strip_prefix('#')?
+        .trim()
+        .strip_prefix("shellcheck ")?;
+    directive
+        .split_whitespace()
+        .find_map(|option| option.strip_prefix("source="))
+}
+
+struct DependencyCollector<'a> {
+    code: &'a str,
+    /// The rows on which a `pants: no-infer-dep` comment appears.
+    ignored_rows: HashSet<usize>,
+    /// The `# shellcheck source=path` directives, by the row of the command they apply to.
+    source_directives: HashMap<usize, String>,
+    file_imports: HashSet<String>,
+    commands: HashSet<String>,
+}
+
+impl DependencyCollector<'_> {
+    fn code_at(&self, node: Node) -> &str {
+        &self.code[node.start_byte()..node.end_byte()]
+    }
+
+    /// The value of a word, unless it depends on expansions or substitutions.
+    fn static_word(&self, node: Node) -> Option<String> {
+        match node.kind() {
+            "command_name" => self.static_word(node.named_child(0)?),
+            "word" | "number" => Some(unescape(self.code_at(node))),
+            "raw_string" => Some(strip_quotes(self.code_at(node)).to_owned()),
+            "string" => node
+                .named_children(&mut node.walk())
+                .all(|child| child.kind() == "string_content")
+                .then(|| unescape(strip_quotes(self.code_at(node)))),
+            "concatenation" => node
+                .named_children(&mut node.walk())
+                .map(|child| self.static_word(child))
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+impl Visitor for DependencyCollector<'_> {
+    fn visit_command(&mut self, node: Node) -> ChildBehavior {
+        let Some(name) = node
+            .child_by_field_name("name")
+            .and_then(|name| self.static_word(name))
+        else {
+            return ChildBehavior::Visit;
+        };
+        if self.ignored_rows.contains(&node.end_position().row) {
+            return ChildBehavior::Visit;
+        }
+        if name == "source" || name == "." {
+            let directive = self.source_directives.get(&node.start_position().row);
+            let path = match directive {
+                Some(path) => Some(path.clone()),
+                None => node
+                    .children_by_field_name("argument", &mut node.walk())
+                    .next()
+                    .and_then(|argument| self.static_word(argument)),
+            };
+            // ShellCheck's convention for a file which should not be followed.
+            if let Some(path) = path.filter(|path| path != "/dev/null") {
+                self.file_imports.insert(path);
+            }
+        } else {
+            self.commands.insert(name);
+        }
+        ChildBehavior::Visit
+    }
+}
+
+fn strip_quotes(literal: &str) -> &str {
+    literal
+        .get(1..literal.len().saturating_sub(1))
+        .unwrap_or_default()
+}
+
+/// Remove the backslashes which escape characters, e.g. in `my\ file.sh`.
+fn unescape(word: &str) -> String {
+    let mut result = String::with_capacity(word.len());
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.extend(chars.next()),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests;
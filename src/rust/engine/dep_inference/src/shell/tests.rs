@@ -0,0 +1,132 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashSet;
+
+use crate::shell::get_dependencies;
+
+fn assert_file_imports(code: &str, file_imports: &[&str]) {
+    let result = get_dependencies(code);
+    assert_eq!(
+        file_imports
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<HashSet<_>>(),
+        result.file_imports.into_iter().collect::<HashSet<_>>()
+    );
+}
+
+fn assert_commands(code: &str, commands: &[&str]) {
+    let result = get_dependencies(code);
+    assert_eq!(
+        commands
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<HashSet<_>>(),
+        result.commands.into_iter().collect::<HashSet<_>>()
+    );
+}
+
+#[test]
+fn source_and_dot() {
+    assert_file_imports(
+        r#"
+source lib/a.sh
+. lib/b.sh
+source "lib/c d.sh"
+source 'lib/e.sh' arg1 arg2
+. lib/f\ g.sh
+source lib/"h".sh
+"#,
+        &[
+            "lib/a.sh",
+            "lib/b.sh",
+            "lib/c d.sh",
+            "lib/e.sh",
+            "lib/f g.sh",
+            "lib/h.sh",
+        ],
+    );
+}
+
+#[test]
+fn nested_sources() {
+    assert_file_imports(
+        r#"
+setup() {
+    source lib/a.sh
+}
+if [ -n "$DEBUG" ]; then
+    . lib/b.sh
+fi
+for f in x y; do source lib/c.sh; done
+output="$(source lib/d.sh && run)"
+"#,
+        &["lib/a.sh", "lib/b.sh", "lib/c.sh", "lib/d.sh"],
+    );
+}
+
+#[test]
+fn dynamic_sources_are_ignored() {
+    assert_file_imports(
+        r#"
+source "$DIR/lib.sh"
+source ${LIB}
+. "$(dirname "$0")/lib.sh"
+source lib/$NAME.sh
+"#,
+        &[],
+    );
+}
+
+#[test]
+fn shellcheck_source_directives() {
+    assert_file_imports(
+        r#"
+# shellcheck source=lib/a.sh
+source "$DIR/a.sh"
+# shellcheck disable=SC1091 source=lib/b.sh
+. "$(dirname "$0")/b.sh"
+# shellcheck source=/dev/null
+source "$HOME/.profile"
+"#,
+        &["lib/a.sh", "lib/b.sh"],
+    );
+}
+
+#[test]
+fn pragma_ignores() {
+    assert_file_imports(
+        r#"
+source lib/a.sh  # pants: no-infer-dep
+source lib/b.sh
+"#,
+        &["lib/b.sh"],
+    );
+    assert_commands("./build.sh # pants: no-infer-dep", &[]);
+}
+
+#[test]
+fn commands() {
+    assert_commands(
+        r#"
+#!/usr/bin/env bash
+set -euo pipefail
+source lib.sh
+./scripts/build.sh --release | tee build.log
+if command -v docker > /dev/null; then
+    "bin/run tests" "$@"
+fi
+VERSION="$(git describe --tags)"
+$RUNNER --verbose
+"#,
+        &[
+            "set",
+            "./scripts/build.sh",
+            "tee",
+            "command",
+            "bin/run tests",
+            "git",
+        ],
+    );
+}
@@ -0,0 +1,69 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! The locations of discovered imports, so that they can be reported at their exact position.
+use serde_derive::{Deserialize, Serialize};
+use tree_sitter::{Node, Point};
+
+/// The location of an import in a source file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    /// The 1-based line, as editors show it.
+    pub start_line: usize,
+    /// The 0-based column, in bytes.
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl SourceSpan {
+    pub fn of(node: Node) -> Self {
+        let Point { row, column } = node.start_position();
+        let end = node.end_position();
+        Self {
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+            start_line: row + 1,
+            start_column: column,
+            end_line: end.row + 1,
+            end_column: end.column,
+        }
+    }
+
+    /// The span within a whole file of a span within a part of it (e.g. a `<script>` block),
+    /// given the contents of the file before that part.
+    pub fn offset_by(self, prefix: &str) -> Self {
+        let lines = prefix.matches('\n').count();
+        let last_line = prefix.rsplit('\n').next().unwrap_or_default().len();
+        let column = |line: usize, column: usize| {
+            if line == 1 {
+                column + last_line
+            } else {
+                column
+            }
+        };
+        Self {
+            start_byte: self.start_byte + prefix.len(),
+            end_byte: self.end_byte + prefix.len(),
+            start_line: self.start_line + lines,
+            start_column: column(self.start_line, self.start_column),
+            end_line: self.end_line + lines,
+            end_column: column(self.end_line, self.end_column),
+        }
+    }
+
+    /// The span as a tuple of `(start_byte, end_byte, start_line, start_column, end_line,
+    /// end_column)`, to be converted to a Python `SourceSpan`.
+    pub fn as_tuple(&self) -> (usize, usize, usize, usize, usize, usize) {
+        (
+            self.start_byte,
+            self.end_byte,
+            self.start_line,
+            self.start_column,
+            self.end_line,
+            self.end_column,
+        )
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::fmt;
+
+///
+/// Who is (most likely) responsible for an error, which determines whether it is worth retrying,
+/// and the exit code of a run which fails due to it.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// The inputs provided by the user (or by a rule on their behalf) were invalid. Retrying will
+    /// not help.
+    User,
+    /// A dependency of the engine (such as the filesystem, a local tool, or a remote service)
+    /// failed. Retrying might help.
+    Infra,
+    /// A bug in Pants.
+    Internal,
+}
+
+impl ErrorCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::User => "user",
+            ErrorCategory::Infra => "infra",
+            ErrorCategory::Internal => "internal",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+///
+/// The kinds of errors which the engine distinguishes.
+///
+/// The string form of each code is stable, since it is exposed to Python (and to tools which
+/// consume the output of Pants): codes may be added, but should never be renamed or reused.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// An error which has not (yet) been classified.
+    Unclassified,
+
+    // User errors.
+    InvalidPathGlobs,
+    InvalidDigestPrefix,
+    InvalidDigestEntries,
+    InvalidWorkingDirectory,
+    InvalidPersistentWorker,
+    InvalidProcessEnvironment,
+    InvalidUrl,
+    InvalidUnionMember,
+    DependencyCycle,
+
+    // Infrastructure errors.
+    MissingDigest,
+    RemoteStoreUnavailable,
+    ProcessExecutionFailed,
+    SandboxSetupFailed,
+    FilesystemAccessFailed,
+
+    // Internal errors.
+    InvalidExecutionStrategy,
+    UndetectedRuleCall,
+    InvariantViolated,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Unclassified => "unclassified",
+            ErrorCode::InvalidPathGlobs => "invalid_path_globs",
+            ErrorCode::InvalidDigestPrefix => "invalid_digest_prefix",
+            ErrorCode::InvalidDigestEntries => "invalid_digest_entries",
+            ErrorCode::InvalidWorkingDirectory => "invalid_working_directory",
+            ErrorCode::InvalidPersistentWorker => "invalid_persistent_worker",
+            ErrorCode::InvalidProcessEnvironment => "invalid_process_environment",
+            ErrorCode::InvalidUrl => "invalid_url",
+            ErrorCode::InvalidUnionMember => "invalid_union_member",
+            ErrorCode::DependencyCycle => "dependency_cycle",
+            ErrorCode::MissingDigest => "missing_digest",
+            ErrorCode::RemoteStoreUnavailable => "remote_store_unavailable",
+            ErrorCode::ProcessExecutionFailed => "process_execution_failed",
+            ErrorCode::SandboxSetupFailed => "sandbox_setup_failed",
+            ErrorCode::FilesystemAccessFailed => "filesystem_access_failed",
+            ErrorCode::InvalidExecutionStrategy => "invalid_execution_strategy",
+            ErrorCode::UndetectedRuleCall => "undetected_rule_call",
+            ErrorCode::InvariantViolated => "invariant_violated",
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ErrorCode::InvalidPathGlobs
+            | ErrorCode::InvalidDigestPrefix
+            | ErrorCode::InvalidDigestEntries
+            | ErrorCode::InvalidWorkingDirectory
+            | ErrorCode::InvalidPersistentWorker
+            | ErrorCode::InvalidProcessEnvironment
+            | ErrorCode::InvalidUrl
+            | ErrorCode::InvalidUnionMember
+            | ErrorCode::DependencyCycle => ErrorCategory::User,
+            ErrorCode::MissingDigest
+            | ErrorCode::RemoteStoreUnavailable
+            | ErrorCode::ProcessExecutionFailed
+            | ErrorCode::SandboxSetupFailed
+            | ErrorCode::FilesystemAccessFailed => ErrorCategory::Infra,
+            ErrorCode::Unclassified
+            | ErrorCode::InvalidExecutionStrategy
+            | ErrorCode::UndetectedRuleCall
+            | ErrorCode::InvariantViolated => ErrorCategory::Internal,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+///
+/// A classified error, with a chain of context which was added as it propagated.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EngineError {
+    code: ErrorCode,
+    message: String,
+    // Context added as the error propagated, innermost first.
+    context: Vec<String>,
+}
+
+impl EngineError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> EngineError {
+        EngineError {
+            code,
+            message: message.into(),
+            context: Vec::new(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        self.code.category()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    ///
+    /// The context which was added to this error as it propagated, outermost first.
+    ///
+    pub fn context(&self) -> impl Iterator<Item = &str> {
+        self.context.iter().rev().map(|c| c.as_str())
+    }
+
+    ///
+    /// Adds a description of the operation which failed due to this error.
+    ///
+    pub fn with_context(mut self, context: impl Into<String>) -> EngineError {
+        self.context.push(context.into());
+        self
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for context in self.context() {
+            write!(f, "{context}: ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,41 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::{EngineError, ErrorCategory, ErrorCode};
+
+#[test]
+fn context_is_rendered_outermost_first() {
+    let err = EngineError::new(ErrorCode::RemoteStoreUnavailable, "connection reset")
+        .with_context("Failed to load digest")
+        .with_context("Failed to materialize directory");
+
+    assert_eq!(err.code(), ErrorCode::RemoteStoreUnavailable);
+    assert_eq!(err.category(), ErrorCategory::Infra);
+    assert_eq!(err.message(), "connection reset");
+    assert_eq!(
+        err.context().collect::<Vec<_>>(),
+        vec!["Failed to materialize directory", "Failed to load digest"]
+    );
+    assert_eq!(
+        err.to_string(),
+        "Failed to materialize directory: Failed to load digest: connection reset"
+    );
+}
+
+#[test]
+fn codes_are_stable() {
+    assert_eq!(ErrorCode::InvalidPathGlobs.as_str(), "invalid_path_globs");
+    assert_eq!(ErrorCode::InvalidPathGlobs.category(), ErrorCategory::User);
+    assert_eq!(ErrorCode::Unclassified.category(), ErrorCategory::Internal);
+    assert_eq!(ErrorCode::DependencyCycle.as_str(), "dependency_cycle");
+    assert_eq!(ErrorCode::DependencyCycle.category(), ErrorCategory::User);
+    assert_eq!(
+        ErrorCode::FilesystemAccessFailed.category(),
+        ErrorCategory::Infra
+    );
+    assert_eq!(
+        ErrorCode::UndetectedRuleCall.category(),
+        ErrorCategory::Internal
+    );
+    assert_eq!(ErrorCategory::Infra.to_string(), "infra");
+}
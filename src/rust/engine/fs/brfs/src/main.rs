@@ -777,6 +777,7 @@ async fn main() {
                 headers,
                 chunk_size_bytes: 4 * 1024 * 1024,
                 timeout: std::time::Duration::from_secs(5 * 60),
+                stream_timeout: std::time::Duration::from_secs(5 * 60),
                 retries: 1,
                 concurrency_limit: args
                     .value_of_t::<usize>("rpc-concurrency-limit")
@@ -784,6 +785,7 @@ async fn main() {
                 batch_api_size_limit: args
                     .value_of_t::<usize>("batch-api-size-limit")
                     .expect("Bad batch-api-size-limit flag"),
+                circuit_breaker: None,
             })
             .await
             .expect("Error making remote store"),
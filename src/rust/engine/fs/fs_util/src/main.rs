@@ -43,6 +43,7 @@ impl From<StoreError> for ExitError {
     fn from(s: StoreError) -> Self {
         match s {
             md @ StoreError::MissingDigest { .. } => ExitError(md.to_string(), ExitCode::NotFound),
+            StoreError::Classified(e) => ExitError(e.to_string(), ExitCode::UnknownError),
             StoreError::Unclassified(s) => ExitError(s, ExitCode::UnknownError),
         }
     }
@@ -208,6 +209,22 @@ Destination must not exist before this command is run.",
                     .required(true),
               )
         )
+        .subcommand(
+          Command::new("reshard")
+              .about("Move the on-disk store from one shard count to another, after changing `--local-store-shard-count`. No other process (e.g. a pantsd) may have the store open while this command runs.")
+              .arg(
+                Arg::new("from-shard-count")
+                    .takes_value(true)
+                    .long("from-shard-count")
+                    .required(true),
+              )
+              .arg(
+                Arg::new("to-shard-count")
+                    .takes_value(true)
+                    .long("to-shard-count")
+                    .required(true),
+              )
+        )
       .arg(
         Arg::new("local-store-path")
           .takes_value(true)
@@ -314,6 +331,17 @@ async fn execute(top_match: &clap::ArgMatches) -> Result<(), ExitError> {
         .value_of("local-store-path")
         .map(PathBuf::from)
         .unwrap_or_else(Store::default_path);
+    // NB: Resharding requires exclusive access to the store, and so must happen before it is opened.
+    if let Some(("reshard", args)) = top_match.subcommand() {
+        let from_shard_count = args
+            .value_of_t::<u8>("from-shard-count")
+            .expect("--from-shard-count must be passed as a power of two");
+        let to_shard_count = args
+            .value_of_t::<u8>("to-shard-count")
+            .expect("--to-shard-count must be passed as a power of two");
+        Store::reshard_local(&store_dir, from_shard_count, to_shard_count)?;
+        return Ok(());
+    }
     let runtime = task_executor::Executor::new();
     let (store, store_has_remote) = {
         let local_only = Store::local_only(runtime.clone(), &store_dir)
@@ -405,6 +433,7 @@ async fn execute(top_match: &clap::ArgMatches) -> Result<(), ExitError> {
                             // Make fs_util have a very long deadline (because it's not configurable,
                             // like it is inside pants).
                             timeout: Duration::from_secs(30 * 60),
+                            stream_timeout: Duration::from_secs(30 * 60),
                             retries: top_match
                                 .value_of_t::<usize>("rpc-attempts")
                                 .expect("Bad rpc-attempts flag"),
@@ -414,6 +443,7 @@ async fn execute(top_match: &clap::ArgMatches) -> Result<(), ExitError> {
                             batch_api_size_limit: top_match
                                 .value_of_t::<usize>("batch-api-size-limit")
                                 .expect("Bad batch-api-size-limit flag"),
+                            circuit_breaker: None,
                         })
                         .await,
                     true,
@@ -7,13 +7,14 @@ use std::fmt::{self, Debug, Display};
 use std::hash::{self, Hash};
 use std::ops::Deref;
 use std::path::{Component, Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 
 use deepsize::{known_deep_size, DeepSizeOf};
 use internment::Intern;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use log::warn;
+use parking_lot::Mutex;
 use serde::Serialize;
 
 // TODO: Extract protobuf-specific pieces to a new crate.
@@ -30,6 +31,52 @@ lazy_static! {
         digest: EMPTY_DIGEST,
         tree: Some(EMPTY_DIGEST_TREE.clone()),
     };
+    static ref INTERNED_TRIES: Mutex<TrieInterner> = Mutex::new(TrieInterner::default());
+}
+
+// The minimum number of entries in the interning table before dead entries are swept.
+const MIN_SWEEP_SIZE: usize = 1024;
+
+///
+/// A table of the DigestTries which are in memory, so that identical subtrees which are loaded or
+/// computed independently (in different Sessions, or by different Nodes) share their entries.
+///
+/// The table holds weak references, and so does not keep tries alive: references to tries which
+/// have been dropped are swept from the table whenever it has doubled in size since the last sweep.
+///
+struct TrieInterner {
+    tries: HashMap<Digest, Weak<[Entry]>>,
+    sweep_at: usize,
+}
+
+impl Default for TrieInterner {
+    fn default() -> Self {
+        Self {
+            tries: HashMap::new(),
+            sweep_at: MIN_SWEEP_SIZE,
+        }
+    }
+}
+
+impl TrieInterner {
+    fn get(&self, digest: &Digest) -> Option<DigestTrie> {
+        self.tries
+            .get(digest)
+            .and_then(Weak::upgrade)
+            .map(DigestTrie)
+    }
+
+    fn intern(&mut self, digest: Digest, tree: DigestTrie) -> DigestTrie {
+        if let Some(interned) = self.get(&digest) {
+            return interned;
+        }
+        self.tries.insert(digest, Arc::downgrade(&tree.0));
+        if self.tries.len() >= self.sweep_at {
+            self.tries.retain(|_, trie| trie.strong_count() > 0);
+            self.sweep_at = std::cmp::max(MIN_SWEEP_SIZE, self.tries.len() * 2);
+        }
+        tree
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -226,18 +273,26 @@ impl Directory {
                 name = dir_node.name
             )
         })?;
+        let tree = match DigestTrie::interned(digest) {
+            Some(tree) => tree,
+            None => DigestTrie::intern(
+                digest,
+                DigestTrie::from_remexec_directories(directory, directories_by_digest)?,
+            ),
+        };
         Ok(Self {
             name: Name(Intern::from(&dir_node.name)),
             digest,
-            tree: DigestTrie::from_remexec_directories(directory, directories_by_digest)?,
+            tree,
         })
     }
 
     fn from_digest_tree(name: Name, tree: DigestTrie) -> Self {
+        let digest = tree.compute_root_digest();
         Self {
             name,
-            digest: tree.compute_root_digest(),
-            tree,
+            digest,
+            tree: DigestTrie::intern(digest, tree),
         }
     }
 
@@ -412,6 +467,21 @@ impl From<DigestTrie> for DirectoryDigest {
 }
 
 impl DigestTrie {
+    ///
+    /// Returns a DigestTrie with the given Digest, if one is already in memory.
+    ///
+    pub fn interned(digest: Digest) -> Option<DigestTrie> {
+        INTERNED_TRIES.lock().get(&digest)
+    }
+
+    ///
+    /// Returns the in-memory DigestTrie for the given Digest if there is one, and otherwise records
+    /// the given DigestTrie (which must have the given Digest) as the in-memory instance.
+    ///
+    pub fn intern(digest: Digest, tree: DigestTrie) -> DigestTrie {
+        INTERNED_TRIES.lock().intern(digest, tree)
+    }
+
     /// Create a DigestTrie from unique TypedPath. Fails for duplicate items.
     pub fn from_unique_paths(
         mut path_stats: Vec<TypedPath>,
@@ -634,6 +704,46 @@ impl DigestTrie {
         self.walk_helper(self, PathBuf::new(), symlink_behavior, 0, f)
     }
 
+    /// Return a trie containing only the entries for which `keep` returns true, or None if every
+    /// entry was kept. Directories which are not kept are removed along with their contents, and
+    /// subtrees which are unchanged are reused as-is (without re-computing their digests).
+    /// NOTE: symlinks are not followed, and are passed to `keep` as `SymlinkEntry`s.
+    pub fn filter(&self, keep: &mut impl FnMut(&Path, &Entry) -> bool) -> Option<DigestTrie> {
+        self.filter_helper(&PathBuf::new(), keep)
+    }
+
+    fn filter_helper(
+        &self,
+        path_so_far: &Path,
+        keep: &mut impl FnMut(&Path, &Entry) -> bool,
+    ) -> Option<DigestTrie> {
+        let mut changed = false;
+        let mut entries = Vec::with_capacity(self.0.len());
+        for entry in &*self.0 {
+            let path = path_so_far.join(entry.name().as_ref());
+            if !keep(&path, entry) {
+                changed = true;
+                continue;
+            }
+            match entry {
+                Entry::Directory(d) => match d.tree.filter_helper(&path, keep) {
+                    Some(tree) => {
+                        changed = true;
+                        entries.push(Entry::Directory(Directory::from_digest_tree(d.name, tree)));
+                    }
+                    None => entries.push(entry.clone()),
+                },
+                _ => entries.push(entry.clone()),
+            }
+        }
+
+        if changed {
+            Some(Self(entries.into()))
+        } else {
+            None
+        }
+    }
+
     fn walk_helper(
         &self,
         root: &DigestTrie,
@@ -984,12 +1094,22 @@ impl DigestTrie {
     /// If a file is present with the same name and contents multiple times, it will appear once.
     /// If a file is present with the same name, but different contents, an error will be returned.
     pub fn merge(trees: Vec<DigestTrie>) -> Result<DigestTrie, MergeError> {
-        Self::merge_helper(PathBuf::new(), trees)
+        Self::merge_with_policy(trees, MergeConflictPolicy::ErrorUnlessIdentical)
+    }
+
+    /// Given DigestTries, merge them recursively into a single DigestTrie, using the given policy
+    /// to resolve any paths which are present in more than one of them.
+    pub fn merge_with_policy(
+        trees: Vec<DigestTrie>,
+        policy: MergeConflictPolicy,
+    ) -> Result<DigestTrie, MergeError> {
+        Self::merge_helper(PathBuf::new(), trees, policy)
     }
 
     fn merge_helper(
         parent_path: PathBuf,
         trees: Vec<DigestTrie>,
+        policy: MergeConflictPolicy,
     ) -> Result<DigestTrie, MergeError> {
         if trees.is_empty() {
             return Ok(EMPTY_DIGEST_TREE.clone());
@@ -998,28 +1118,67 @@ impl DigestTrie {
             return Ok(trees.pop().unwrap());
         }
 
-        // Merge sorted Entries.
+        // Merge sorted Entries, preserving the order of the input trees for identically named
+        // Entries.
         let input_entries = trees
             .iter()
-            .map(|tree| tree.entries().iter())
-            .kmerge_by(|a, b| a.name() < b.name());
+            .enumerate()
+            .map(|(idx, tree)| tree.entries().iter().map(move |entry| (idx, entry)))
+            .kmerge_by(|(a_idx, a), (b_idx, b)| (a.name(), a_idx) < (b.name(), b_idx));
 
         // Then group by name, and merge into an output list.
         let mut entries: Vec<Entry> = Vec::new();
-        for (name, group) in &input_entries.group_by(|e| e.name()) {
-            let mut group = group.peekable();
-            let first = group.next().unwrap();
-            if group.peek().is_none() {
+        for (name, group) in &input_entries.group_by(|(_, e)| e.name()) {
+            let group = group.map(|(_, e)| e).collect::<Vec<_>>();
+            let first = group[0];
+            if group.len() == 1 {
                 // There was only one Entry: emit it.
                 entries.push(first.clone());
                 continue;
             }
+            let others = || group[1..].iter().copied();
+
+            let preferred = match policy {
+                MergeConflictPolicy::PreferLeft => Some(first),
+                MergeConflictPolicy::PreferRight => Some(group[group.len() - 1]),
+                MergeConflictPolicy::Error | MergeConflictPolicy::ErrorUnlessIdentical => None,
+            };
+            if let Some(preferred) = preferred {
+                match preferred {
+                    Entry::Directory(_) => {
+                        // Merge all of the Directories, and discard any other types of Entry.
+                        let directories = group
+                            .iter()
+                            .filter_map(|e| match e {
+                                Entry::Directory(d) => Some(d.tree.clone()),
+                                _ => None,
+                            })
+                            .collect::<Vec<_>>();
+                        let merged_tree = Self::merge_helper(
+                            parent_path.join(name.as_ref()),
+                            directories,
+                            policy,
+                        )?;
+                        entries.push(Entry::Directory(Directory::from_digest_tree(
+                            name,
+                            merged_tree,
+                        )));
+                    }
+                    _ => entries.push(preferred.clone()),
+                }
+                continue;
+            }
 
             match first {
                 Entry::File(f) => {
-                    // If any Entry is a File, then they must all be identical.
+                    // If any Entry is a File, then they must all be identical (unless any duplicate
+                    // is an error).
                     let (mut mismatched_files, mismatched_dirs, mismatched_symlinks) =
-                        collisions(f.digest, group);
+                        if policy == MergeConflictPolicy::Error {
+                            partition(others())
+                        } else {
+                            collisions(f.digest, others())
+                        };
                     if !mismatched_files.is_empty()
                         || !mismatched_dirs.is_empty()
                         || !mismatched_symlinks.is_empty()
@@ -1040,10 +1199,13 @@ impl DigestTrie {
                     let mut mismatched_files = Vec::new();
                     let mut mismatched_dirs = Vec::new();
                     let mut mismatched_symlinks = Vec::new();
-                    for entry in group {
+                    for entry in others() {
                         match entry {
                             Entry::File(other) => mismatched_files.push(other),
-                            Entry::Symlink(other) if other.target != s.target => {
+                            Entry::Symlink(other)
+                                if other.target != s.target
+                                    || policy == MergeConflictPolicy::Error =>
+                            {
                                 mismatched_symlinks.push(other)
                             }
                             Entry::Directory(other) => mismatched_dirs.push(other),
@@ -1070,7 +1232,7 @@ impl DigestTrie {
                 Entry::Directory(d) => {
                     // If any Entry is a Directory, then they must all be Directories which will be merged.
                     let (mismatched_files, mut mismatched_dirs, mismatched_symlinks) =
-                        collisions(d.digest, group);
+                        collisions(d.digest, others());
 
                     // If there were any Files, error.
                     if !mismatched_files.is_empty() || !mismatched_symlinks.is_empty() {
@@ -1095,6 +1257,7 @@ impl DigestTrie {
                                 .into_iter()
                                 .map(|d| d.tree.clone())
                                 .collect(),
+                            policy,
                         )?;
                         entries.push(Entry::Directory(Directory::from_digest_tree(
                             name,
@@ -1160,6 +1323,35 @@ pub struct DigestTrieDiff {
     pub changed_symlinks: Vec<PathBuf>,
 }
 
+/// How to resolve a path which is present in more than one of the inputs to a merge.
+///
+/// Directories are always merged recursively, so the policy applies to the files and symlinks
+/// within them (and to Entries of differing types).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum MergeConflictPolicy {
+    /// Fail if a file or symlink is present more than once, even with identical content.
+    Error,
+    /// Fail if a file or symlink is present more than once with differing content.
+    #[default]
+    ErrorUnlessIdentical,
+    /// Use the Entry from the earliest input which contains the path.
+    PreferLeft,
+    /// Use the Entry from the latest input which contains the path.
+    PreferRight,
+}
+
+impl MergeConflictPolicy {
+    pub fn create(spec: &str) -> Result<Self, String> {
+        match spec {
+            "error" => Ok(MergeConflictPolicy::Error),
+            "error_unless_identical" => Ok(MergeConflictPolicy::ErrorUnlessIdentical),
+            "prefer_left" => Ok(MergeConflictPolicy::PreferLeft),
+            "prefer_right" => Ok(MergeConflictPolicy::PreferRight),
+            _ => Err(format!("Unrecognized merge conflict policy: {spec}.")),
+        }
+    }
+}
+
 pub enum MergeError {
     Duplicates {
         parent_path: PathBuf,
@@ -1242,6 +1434,23 @@ fn collisions<'a>(
     (mismatched_files, mismatched_dirs, mismatched_symlinks)
 }
 
+/// Partition the given entries by type, treating all of them as collisions.
+fn partition<'a>(
+    entries: impl Iterator<Item = &'a Entry>,
+) -> (Vec<&'a File>, Vec<&'a Directory>, Vec<&'a Symlink>) {
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    let mut symlinks = Vec::new();
+    for entry in entries {
+        match entry {
+            Entry::File(other) => files.push(other),
+            Entry::Symlink(other) => symlinks.push(other),
+            Entry::Directory(other) => dirs.push(other),
+        }
+    }
+    (files, dirs, symlinks)
+}
+
 /// Format entries as a human readable string.
 fn format_entries(directories: &[String], files: &[String], symlinks: &[String]) -> String {
     format!(
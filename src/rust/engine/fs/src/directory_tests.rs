@@ -392,3 +392,95 @@ fn leaf_paths() {
 
     assert_eq!(leaf_paths, vec![empty_dir, file, link])
 }
+
+#[test]
+fn filter() {
+    let kept = PathBuf::from("kept/file.txt");
+    let removed_file = PathBuf::from("parent/removed.txt");
+    let remaining_file = PathBuf::from("parent/remaining.txt");
+    let removed_dir = PathBuf::from("parent/removed/file.txt");
+    let tree = make_tree(vec![
+        TypedPath::File {
+            path: &kept,
+            is_executable: false,
+        },
+        TypedPath::File {
+            path: &removed_file,
+            is_executable: false,
+        },
+        TypedPath::File {
+            path: &remaining_file,
+            is_executable: false,
+        },
+        TypedPath::File {
+            path: &removed_dir,
+            is_executable: false,
+        },
+    ]);
+
+    // Keeping everything results in no new trie.
+    assert!(tree.filter(&mut |_, _| true).is_none());
+
+    let mut visited = Vec::new();
+    let filtered = tree
+        .filter(&mut |path, _| {
+            visited.push(path.to_owned());
+            path != Path::new("parent/removed.txt") && path != Path::new("parent/removed")
+        })
+        .unwrap();
+    assert_eq!(
+        filtered.files(crate::SymlinkBehavior::Aware),
+        vec![kept, remaining_file]
+    );
+    // The contents of removed directories are not visited.
+    assert!(!visited.contains(&removed_dir));
+
+    // Unchanged subtrees are reused.
+    let subtree_digest = |tree: &DigestTrie, name: &str| match tree.entry(Path::new(name)) {
+        Ok(Some(Entry::Directory(d))) => d.digest(),
+        _ => panic!("Expected a directory at {name}"),
+    };
+    assert_eq!(
+        subtree_digest(&filtered, "kept"),
+        subtree_digest(&tree, "kept")
+    );
+    assert_ne!(
+        subtree_digest(&filtered, "parent"),
+        subtree_digest(&tree, "parent")
+    );
+}
+
+#[test]
+fn identical_subtrees_are_interned() {
+    let subtree = |tree: &DigestTrie| match tree.entry(Path::new("shared")).unwrap() {
+        Some(Entry::Directory(d)) => (d.digest(), d.tree().entries().as_ptr()),
+        other => panic!("Expected a directory, got {other:?}"),
+    };
+    let tree1 = make_tree(vec![
+        TypedPath::File {
+            path: Path::new("shared/interned_subtree_file.txt"),
+            is_executable: false,
+        },
+        TypedPath::File {
+            path: Path::new("one.txt"),
+            is_executable: false,
+        },
+    ]);
+    let tree2 = make_tree(vec![
+        TypedPath::File {
+            path: Path::new("shared/interned_subtree_file.txt"),
+            is_executable: false,
+        },
+        TypedPath::File {
+            path: Path::new("two.txt"),
+            is_executable: false,
+        },
+    ]);
+    let (digest, entries) = subtree(&tree1);
+    assert_eq!(subtree(&tree2), (digest, entries));
+    assert!(DigestTrie::interned(digest).is_some());
+
+    // The table does not keep tries alive.
+    std::mem::drop((tree1, tree2));
+    assert!(DigestTrie::interned(digest).is_none());
+}
@@ -90,6 +90,25 @@ impl PathGlob {
         }
     }
 
+    ///
+    /// The directory that this glob is relative to, and the patterns that it will match beneath it.
+    ///
+    fn patterns(&self) -> (&Dir, impl Iterator<Item = &Pattern>) {
+        match self {
+            PathGlob::Wildcard {
+                canonical_dir,
+                wildcard,
+                ..
+            } => (canonical_dir, std::iter::once(wildcard).chain(&[])),
+            PathGlob::DirWildcard {
+                canonical_dir,
+                wildcard,
+                remainder,
+                ..
+            } => (canonical_dir, std::iter::once(wildcard).chain(remainder)),
+        }
+    }
+
     pub fn create(filespecs: Vec<String>) -> Result<Vec<PathGlob>, String> {
         // Getting a Vec<PathGlob> per filespec is needed to create a `PreparedPathGlobs`, but we don't
         // need that here.
@@ -268,6 +287,7 @@ pub struct PreparedPathGlobs {
     pub(crate) exclude: Arc<GitignoreStyleExcludes>,
     strict_match_behavior: StrictGlobMatching,
     conjunction: GlobExpansionConjunction,
+    pub(crate) preserve_empty_dirs: bool,
 }
 
 impl PreparedPathGlobs {
@@ -294,9 +314,73 @@ impl PreparedPathGlobs {
             exclude,
             strict_match_behavior,
             conjunction,
+            preserve_empty_dirs: false,
         })
     }
 
+    ///
+    /// True if these globs match every path which is not excluded (i.e. the includes contain `**`),
+    /// and unmatched globs will not be reported. Matching such globs against a tree only needs to
+    /// apply the excludes.
+    ///
+    pub fn is_exclude_only(&self) -> bool {
+        if self.strict_match_behavior.should_check_glob_matches() {
+            return false;
+        }
+        // `**` and `**/*` are each parsed into a recursive glob and a glob for the root directory.
+        self.include.iter().any(|entry| {
+            let patterns = entry
+                .globs
+                .iter()
+                .map(|glob| {
+                    let (canonical_dir, patterns) = glob.patterns();
+                    let patterns = patterns.map(Pattern::as_str).collect::<Vec<_>>();
+                    (canonical_dir.0.components().next().is_none(), patterns)
+                })
+                .collect::<Vec<_>>();
+            patterns.iter().any(|(_, patterns)| patterns.len() > 1)
+                && patterns.iter().all(|(at_root, patterns)| {
+                    *at_root
+                        && matches!(patterns.as_slice(), ["*"] | ["*", "**"] | ["*", "**", "*"])
+                })
+        })
+    }
+
+    pub fn excludes(&self) -> &GitignoreStyleExcludes {
+        &self.exclude
+    }
+
+    ///
+    /// Returns the literal (i.e. wildcard-free) leading portions of the includes. Every path which
+    /// these globs match is either a parent of, or beneath, one of the returned paths, so subtrees
+    /// which are neither can be skipped. An include with no literal prefix results in an empty path.
+    ///
+    pub fn literal_prefixes(&self) -> Vec<PathBuf> {
+        self.include
+            .iter()
+            .flat_map(|entry| entry.globs.iter())
+            .map(|glob| {
+                let (canonical_dir, patterns) = glob.patterns();
+                let patterns = patterns.collect::<Vec<_>>();
+                if patterns
+                    .iter()
+                    .any(|p| p.as_str() == Component::ParentDir.as_os_str())
+                {
+                    // A parent reference may escape a literal prefix.
+                    return PathBuf::new();
+                }
+                let mut prefix = canonical_dir.0.clone();
+                prefix.extend(
+                    patterns
+                        .into_iter()
+                        .map(Pattern::as_str)
+                        .take_while(|p| Pattern::escape(p) == *p),
+                );
+                prefix
+            })
+            .collect()
+    }
+
     fn from_globs(include: Vec<PathGlob>) -> Result<PreparedPathGlobs, String> {
         let include: Vec<PathGlobIncludeEntry> = include
             .into_iter()
@@ -312,6 +396,7 @@ impl PreparedPathGlobs {
             exclude: GitignoreStyleExcludes::create(vec![])?,
             strict_match_behavior: StrictGlobMatching::Ignore,
             conjunction: GlobExpansionConjunction::AllMatch,
+            preserve_empty_dirs: false,
         })
     }
 }
@@ -498,6 +583,7 @@ trait GlobMatchingImplementation<E: Display + Send + Sync + 'static>: Vfs<E> {
             exclude,
             strict_match_behavior,
             conjunction,
+            preserve_empty_dirs,
             ..
         } = path_globs;
 
@@ -518,6 +604,7 @@ trait GlobMatchingImplementation<E: Display + Send + Sync + 'static>: Vfs<E> {
                     exclude.clone(),
                     path_glob,
                     symlink_behavior,
+                    preserve_empty_dirs,
                 ));
             }
         }
@@ -616,6 +703,7 @@ trait GlobMatchingImplementation<E: Display + Send + Sync + 'static>: Vfs<E> {
         exclude: Arc<GitignoreStyleExcludes>,
         path_glob: PathGlob,
         symlink_behavior: SymlinkBehavior,
+        preserve_empty_dirs: bool,
     ) -> Result<bool, E> {
         match path_glob {
             PathGlob::Wildcard {
@@ -651,6 +739,7 @@ trait GlobMatchingImplementation<E: Display + Send + Sync + 'static>: Vfs<E> {
                     remainder,
                     symlink_behavior,
                     link_depth,
+                    preserve_empty_dirs,
                 )
                 .await
             }
@@ -695,6 +784,7 @@ trait GlobMatchingImplementation<E: Display + Send + Sync + 'static>: Vfs<E> {
         remainder: Vec<Pattern>,
         symlink_behavior: SymlinkBehavior,
         link_depth: LinkDepth,
+        preserve_empty_dirs: bool,
     ) -> Result<bool, E> {
         // Filter directory listing and recurse for matched Dirs.
         let context = self.clone();
@@ -709,6 +799,30 @@ trait GlobMatchingImplementation<E: Display + Send + Sync + 'static>: Vfs<E> {
             )
             .await?;
 
+        if preserve_empty_dirs {
+            // Directories which are empty (other than for excluded paths) would otherwise only be
+            // matched by the final component of a glob.
+            let empty_dirs = future::try_join_all(path_stats.iter().filter_map(|(ps, _)| {
+                let PathStat::Dir { stat, .. } = ps else {
+                    return None;
+                };
+                let context = self.clone();
+                let exclude = exclude.clone();
+                Some(async move {
+                    let listing = context.scandir(stat.clone()).await?;
+                    Ok::<_, E>(
+                        listing
+                            .0
+                            .iter()
+                            .all(|child| exclude.is_ignored(&child.within(&stat.0)))
+                            .then(|| ps.clone()),
+                    )
+                })
+            }))
+            .await?;
+            result.lock().extend(empty_dirs.into_iter().flatten());
+        }
+
         let path_globs = path_stats
             .into_iter()
             .filter_map(|(ps, link_depth)| match ps {
@@ -724,7 +838,15 @@ trait GlobMatchingImplementation<E: Display + Send + Sync + 'static>: Vfs<E> {
         let child_globs = path_globs
             .into_iter()
             .flat_map(Vec::into_iter)
-            .map(|pg| context.expand_single(result.clone(), exclude.clone(), pg, symlink_behavior))
+            .map(|pg| {
+                context.expand_single(
+                    result.clone(),
+                    exclude.clone(),
+                    pg,
+                    symlink_behavior,
+                    preserve_empty_dirs,
+                )
+            })
             .collect::<Vec<_>>();
 
         let child_matches = future::try_join_all(child_globs).await?;
@@ -2,7 +2,10 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
 use crate::glob_matching::PathGlob;
-use crate::{GitignoreStyleExcludes, GlobExpansionConjunction, PathGlobs, StrictGlobMatching};
+use crate::{
+    GitignoreStyleExcludes, GlobExpansionConjunction, PathGlobs, PreparedPathGlobs,
+    StrictGlobMatching,
+};
 
 #[test]
 fn path_globs_create_distinguishes_between_includes_and_excludes() {
@@ -32,3 +35,55 @@ fn path_globs_create_distinguishes_between_includes_and_excludes() {
             .exclude_patterns()
     );
 }
+
+fn prepared(globs: &[&str], strict_match_behavior: StrictGlobMatching) -> PreparedPathGlobs {
+    PathGlobs::new(
+        globs.iter().map(|g| g.to_string()).collect(),
+        strict_match_behavior,
+        GlobExpansionConjunction::AllMatch,
+    )
+    .parse()
+    .unwrap()
+}
+
+#[test]
+fn path_globs_exclude_only() {
+    for globs in [
+        vec!["**"],
+        vec!["**/*"],
+        vec!["./**", "!node_modules/**"],
+        vec!["src/*.rs", "**", "!*.pyc"],
+    ] {
+        assert!(
+            prepared(&globs, StrictGlobMatching::Ignore).is_exclude_only(),
+            "{globs:?}"
+        );
+    }
+    for globs in [vec!["*"], vec!["src/**"], vec!["**/*.rs"], vec!["!*.pyc"]] {
+        assert!(
+            !prepared(&globs, StrictGlobMatching::Ignore).is_exclude_only(),
+            "{globs:?}"
+        );
+    }
+
+    // Unmatched globs must still be checked, which requires expanding them.
+    assert!(!prepared(&["**"], StrictGlobMatching::Error("test".to_owned())).is_exclude_only());
+}
+
+#[test]
+fn path_globs_literal_prefixes() {
+    let prefixes = |globs: &[&str]| {
+        prepared(globs, StrictGlobMatching::Ignore)
+            .literal_prefixes()
+            .into_iter()
+            .map(|p| p.to_str().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(prefixes(&["a/b/c.txt"]), vec!["a/b/c.txt"]);
+    assert_eq!(prefixes(&["a/b/*.txt", "!a/b/d.txt"]), vec!["a/b"]);
+    assert_eq!(prefixes(&["a/**/c.txt"]), vec!["a"]);
+    assert_eq!(prefixes(&["a/[bc]/d.txt"]), vec!["a"]);
+    assert_eq!(prefixes(&["*.txt"]), vec![""]);
+    assert_eq!(prefixes(&["a/../b.txt"]), vec![""]);
+}
@@ -8,14 +8,17 @@ pub mod gitignore;
 mod glob_matching;
 #[cfg(test)]
 mod glob_matching_tests;
+pub mod platform;
+#[cfg(test)]
+mod platform_tests;
 #[cfg(test)]
 mod posixfs_tests;
 #[cfg(test)]
 mod testutil;
 
 pub use crate::directory::{
-    DigestTrie, DirectoryDigest, Entry, SymlinkBehavior, TypedPath, EMPTY_DIGEST_TREE,
-    EMPTY_DIRECTORY_DIGEST,
+    DigestTrie, DirectoryDigest, Entry, MergeConflictPolicy, SymlinkBehavior, TypedPath,
+    EMPTY_DIGEST_TREE, EMPTY_DIRECTORY_DIGEST,
 };
 pub use crate::gitignore::GitignoreStyleExcludes;
 pub use crate::glob_matching::{
@@ -25,7 +28,6 @@ pub use crate::glob_matching::{
 use std::cmp::min;
 use std::io::{self, ErrorKind};
 use std::ops::Deref;
-use std::os::unix::fs::PermissionsExt;
 use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 use std::time::SystemTime;
@@ -337,6 +339,7 @@ pub struct PathGlobs {
     globs: Vec<String>,
     strict_match_behavior: StrictGlobMatching,
     conjunction: GlobExpansionConjunction,
+    preserve_empty_dirs: bool,
 }
 
 impl PathGlobs {
@@ -349,15 +352,28 @@ impl PathGlobs {
             globs,
             strict_match_behavior,
             conjunction,
+            preserve_empty_dirs: false,
         }
     }
 
+    ///
+    /// If set, empty directories which match a directory component of a glob are matched, even
+    /// though they contain nothing which matches the rest of the glob. For example, with
+    /// `src/**/*.java`, an empty `src/main/resources` directory will be matched.
+    ///
+    pub fn preserving_empty_dirs(mut self, preserve_empty_dirs: bool) -> PathGlobs {
+        self.preserve_empty_dirs = preserve_empty_dirs;
+        self
+    }
+
     pub fn parse(self) -> Result<glob_matching::PreparedPathGlobs, String> {
-        glob_matching::PreparedPathGlobs::create(
+        let mut prepared = glob_matching::PreparedPathGlobs::create(
             self.globs,
             self.strict_match_behavior,
             self.conjunction,
-        )
+        )?;
+        prepared.preserve_empty_dirs = self.preserve_empty_dirs;
+        Ok(prepared)
     }
 }
 
@@ -426,7 +442,7 @@ impl PosixFS {
     pub async fn scandir(&self, dir_relative_to_root: Dir) -> Result<DirectoryListing, io::Error> {
         let vfs = self.clone();
         self.executor
-            .spawn_blocking(
+            .spawn_blocking_fs(
                 move || vfs.scandir_sync(&dir_relative_to_root),
                 |e| {
                     Err(io::Error::new(
@@ -557,7 +573,7 @@ impl PosixFS {
                 target: std::fs::read_link(path_to_stat)?,
             })))
         } else if file_type.is_file() {
-            let is_executable = compute_metadata()?.permissions().mode() & 0o100 == 0o100;
+            let is_executable = platform::is_executable(&compute_metadata()?);
             Ok(Some(Stat::File(File {
                 path,
                 is_executable: is_executable,
@@ -614,11 +630,8 @@ impl PosixFS {
                     _ => unreachable!("std::fs::FileType was not a symlink, directory, or file"),
                 };
 
-                #[cfg(target_family = "unix")]
-                let (unix_mode, is_executable) = {
-                    let mode = metadata.permissions().mode();
-                    (Some(mode), (mode & 0o111) != 0)
-                };
+                let unix_mode = platform::unix_mode(&metadata);
+                let is_executable = unix_mode.is_some_and(|mode| (mode & 0o111) != 0);
 
                 Ok(Some(PathMetadata {
                     path,
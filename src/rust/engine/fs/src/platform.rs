@@ -0,0 +1,129 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Helpers which wrap the platform-specific filesystem APIs that the engine uses, so that they are
+//! defined in one place.
+
+use std::fs::{Metadata, OpenOptions};
+use std::io;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+
+///
+/// Returns the unix mode of the given metadata, if the platform has one.
+///
+pub fn unix_mode(metadata: &Metadata) -> Option<u32> {
+    Some(metadata.permissions().mode())
+}
+
+///
+/// Returns true if the file described by the given metadata is executable by its owner.
+///
+pub fn is_executable(metadata: &Metadata) -> bool {
+    metadata.permissions().mode() & 0o100 == 0o100
+}
+
+///
+/// Sets the permissions of the given path to the given unix mode.
+///
+pub async fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).await
+}
+
+///
+/// Synchronous equivalent of `set_mode`.
+///
+pub fn set_mode_sync(path: &Path, mode: u32) -> io::Result<()> {
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+///
+/// Configures the given OpenOptions to create files with the given unix mode.
+///
+pub fn open_options_with_mode(options: &mut OpenOptions, mode: u32) -> &mut OpenOptions {
+    options.mode(mode)
+}
+
+///
+/// Creates a symlink at `link` pointing to `target`, which may be relative to the parent of
+/// `link`.
+///
+pub async fn symlink(target: impl AsRef<Path>, link: impl AsRef<Path>) -> io::Result<()> {
+    tokio::fs::symlink(target, link).await
+}
+
+///
+/// Copies the file at `src` to `dst`, replacing any existing file there.
+///
+/// If `clone` is set, then on Linux the copy is first attempted as a copy-on-write clone (a
+/// "reflink", supported by filesystems like XFS and btrfs), which shares the underlying extents
+/// rather than copying them. If cloning is not supported, this falls back to `std::fs::copy`, which
+/// itself uses `copy_file_range` on Linux, and `fclonefileat` (APFS clones) on macOS.
+///
+pub fn copy_file(src: &Path, dst: &Path, clone: bool) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    if clone && clone_file(src, dst).is_ok() {
+        return Ok(());
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = clone;
+    std::fs::copy(src, dst).map(|_| ())
+}
+
+#[cfg(target_os = "linux")]
+fn clone_file(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    // From `linux/fs.h`: `_IOW(0x94, 9, int)`.
+    const FICLONE: libc::c_ulong = 0x40049409;
+
+    let src_file = std::fs::File::open(src)?;
+    let dst_file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(dst)?;
+    if unsafe { libc::ioctl(dst_file.as_raw_fd(), FICLONE as _, src_file.as_raw_fd()) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+///
+/// Returns true if the given error indicates that an executable could not be spawned because it
+/// was still open for writing (which may be retried).
+///
+pub fn is_text_file_busy(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(libc::ETXTBSY)
+}
+
+///
+/// Blocks until an exclusive advisory lock has been acquired on the given open file. The lock is
+/// released when the file is closed.
+///
+pub fn lock_exclusive(file: &std::fs::File) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+///
+/// Attempts to acquire an exclusive advisory lock on the given open file without blocking, and
+/// returns false if another open file already holds one. The lock is released when the file is
+/// closed.
+///
+pub fn try_lock_exclusive(file: &std::fs::File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use crate::platform;
+
+#[tokio::test]
+async fn symlink_to_file_and_directory() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("subdir")).unwrap();
+    std::fs::write(dir.path().join("subdir/file"), b"content").unwrap();
+
+    platform::symlink("subdir", dir.path().join("dir_link"))
+        .await
+        .unwrap();
+    platform::symlink("subdir/file", dir.path().join("file_link"))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read(dir.path().join("dir_link/file")).unwrap(),
+        b"content"
+    );
+    assert_eq!(
+        std::fs::read(dir.path().join("file_link")).unwrap(),
+        b"content"
+    );
+}
+
+#[tokio::test]
+async fn set_mode_readonly() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("file");
+    platform::open_options_with_mode(OpenOptions::new().create(true).write(true), 0o644)
+        .open(&path)
+        .unwrap()
+        .write_all(b"content")
+        .unwrap();
+
+    platform::set_mode(&path, 0o444).await.unwrap();
+    assert!(std::fs::metadata(&path).unwrap().permissions().readonly());
+    platform::set_mode(&path, 0o755).await.unwrap();
+    let metadata = std::fs::metadata(&path).unwrap();
+    assert!(!metadata.permissions().readonly());
+    assert!(platform::is_executable(&metadata));
+}
+
+#[test]
+fn copy_file_with_and_without_clone() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let src = dir.path().join("src");
+    std::fs::write(&src, b"content").unwrap();
+
+    for (clone, name) in [(true, "cloned"), (false, "copied")] {
+        let dst = dir.path().join(name);
+        // An existing destination is replaced.
+        std::fs::write(&dst, b"existing content which is longer").unwrap();
+        platform::copy_file(&src, &dst, clone).unwrap();
+        assert_eq!(std::fs::read(&dst).unwrap(), b"content");
+    }
+}
+
+#[test]
+fn lock_exclusive() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = std::fs::File::create(dir.path().join("lock")).unwrap();
+    platform::lock_exclusive(&file).unwrap();
+}
+
+#[test]
+fn try_lock_exclusive() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("lock");
+    let first = std::fs::File::create(&path).unwrap();
+    let second = std::fs::File::open(&path).unwrap();
+    assert!(platform::try_lock_exclusive(&first).unwrap());
+    // Locks are held by open files, and so conflict even within a single process.
+    assert!(!platform::try_lock_exclusive(&second).unwrap());
+    drop(first);
+    assert!(platform::try_lock_exclusive(&second).unwrap());
+}
@@ -359,6 +359,47 @@ async fn memfs_expand_basic() {
     );
 }
 
+#[tokio::test]
+async fn memfs_expand_preserving_empty_dirs() {
+    let java = PathBuf::from("src/main/java/A.java");
+    let resources = PathBuf::from("src/main/resources");
+    let fs = DigestTrie::from_unique_paths(
+        vec![
+            TypedPath::File {
+                path: &java,
+                is_executable: false,
+            },
+            TypedPath::Dir(&resources),
+        ],
+        &vec![(java.clone(), EMPTY_DIGEST)].into_iter().collect(),
+    )
+    .unwrap();
+    let expand = |preserve_empty_dirs: bool| {
+        let globs = PathGlobs::new(
+            vec!["src/**/*.java".into()],
+            StrictGlobMatching::Ignore,
+            GlobExpansionConjunction::AnyMatch,
+        )
+        .preserving_empty_dirs(preserve_empty_dirs)
+        .parse()
+        .unwrap();
+        fs.expand_globs(globs, SymlinkBehavior::Oblivious, None)
+    };
+
+    let java_stat = PathStat::file(
+        java.clone(),
+        File {
+            path: java.clone(),
+            is_executable: false,
+        },
+    );
+    assert_eq!(expand(false).await.unwrap(), vec![java_stat.clone()]);
+    assert_eq!(
+        expand(true).await.unwrap(),
+        vec![java_stat, PathStat::dir(resources.clone(), Dir(resources))],
+    );
+}
+
 async fn assert_only_file_is_executable(path: &Path, want_is_executable: bool) {
     let fs = new_posixfs(path);
     let stats = fs.scandir(Dir(PathBuf::from("."))).await.unwrap();
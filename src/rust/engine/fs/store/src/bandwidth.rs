@@ -0,0 +1,161 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+///
+/// The priority of transfers to and from the remote store. When bandwidth is limited, interactive
+/// transfers (such as fetching the outputs of cached action results) are served before background
+/// transfers (such as opportunistic remote cache writes).
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum TransferPriority {
+    Background,
+    Interactive,
+}
+
+tokio::task_local! {
+    static TRANSFER_PRIORITY: TransferPriority;
+}
+
+///
+/// Runs the given future with all of its remote store transfers at the given priority.
+///
+/// NB: The priority is task-local, and so does not apply to tasks which the future spawns.
+///
+pub async fn with_transfer_priority<F: Future>(priority: TransferPriority, f: F) -> F::Output {
+    TRANSFER_PRIORITY.scope(priority, f).await
+}
+
+///
+/// The priority of remote store transfers in the current task: `Interactive` unless set by
+/// `with_transfer_priority`.
+///
+pub fn current_transfer_priority() -> TransferPriority {
+    TRANSFER_PRIORITY
+        .try_with(|priority| *priority)
+        .unwrap_or(TransferPriority::Interactive)
+}
+
+///
+/// The (optional) limits on the rates of uploads to and downloads from the remote store, which are
+/// shared by all clones of a Store.
+///
+#[derive(Clone, Debug, Default)]
+pub struct BandwidthLimits {
+    pub(crate) upload: Option<Arc<BandwidthLimiter>>,
+    pub(crate) download: Option<Arc<BandwidthLimiter>>,
+}
+
+impl BandwidthLimits {
+    pub fn new(
+        upload_bytes_per_second: Option<usize>,
+        download_bytes_per_second: Option<usize>,
+    ) -> Self {
+        let limiter = |bytes_per_second: Option<usize>| {
+            bytes_per_second
+                .filter(|bytes_per_second| *bytes_per_second > 0)
+                .map(|bytes_per_second| Arc::new(BandwidthLimiter::new(bytes_per_second)))
+        };
+        Self {
+            upload: limiter(upload_bytes_per_second),
+            download: limiter(download_bytes_per_second),
+        }
+    }
+}
+
+///
+/// A token bucket which limits the rate of transfers, and which serves waiting interactive
+/// transfers before waiting background transfers.
+///
+/// The bucket holds up to one second of transfer, and a transfer may proceed whenever the bucket is
+/// not in debt: a transfer larger than the bucket puts it into debt, which delays later transfers
+/// until it has been repaid.
+///
+#[derive(Debug)]
+pub(crate) struct BandwidthLimiter {
+    bytes_per_second: f64,
+    state: Mutex<LimiterState>,
+    /// Notified when the last waiting interactive transfer proceeds.
+    interactive_done: Notify,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    /// The bytes which may be transferred immediately, which is negative while in debt.
+    available: f64,
+    last_refill: Instant,
+    waiting_interactive: usize,
+}
+
+impl BandwidthLimiter {
+    pub(crate) fn new(bytes_per_second: usize) -> Self {
+        Self {
+            bytes_per_second: bytes_per_second as f64,
+            state: Mutex::new(LimiterState {
+                available: bytes_per_second as f64,
+                last_refill: Instant::now(),
+                waiting_interactive: 0,
+            }),
+            interactive_done: Notify::new(),
+        }
+    }
+
+    ///
+    /// Waits until a transfer of the given size may proceed at the given priority.
+    ///
+    pub(crate) async fn acquire(&self, bytes: usize, priority: TransferPriority) {
+        let _interactive = (priority == TransferPriority::Interactive).then(|| {
+            self.state.lock().waiting_interactive += 1;
+            InteractiveWaiter(self)
+        });
+        loop {
+            // NB: Created before checking the state, so that a notification which is sent after the
+            // check is not missed.
+            let interactive_done = self.interactive_done.notified();
+            let debt = {
+                let mut state = self.state.lock();
+                let now = Instant::now();
+                let refill =
+                    now.duration_since(state.last_refill).as_secs_f64() * self.bytes_per_second;
+                state.available = (state.available + refill).min(self.bytes_per_second);
+                state.last_refill = now;
+
+                let yield_to_interactive =
+                    priority == TransferPriority::Background && state.waiting_interactive > 0;
+                if !yield_to_interactive && state.available >= 0.0 {
+                    state.available -= bytes as f64;
+                    return;
+                }
+                (!yield_to_interactive).then_some(-state.available)
+            };
+            match debt {
+                Some(debt) => {
+                    tokio::time::sleep(Duration::from_secs_f64(debt / self.bytes_per_second)).await
+                }
+                None => interactive_done.await,
+            }
+        }
+    }
+}
+
+///
+/// Counts a waiting interactive transfer for as long as it is held.
+///
+struct InteractiveWaiter<'a>(&'a BandwidthLimiter);
+
+impl Drop for InteractiveWaiter<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock();
+        state.waiting_interactive -= 1;
+        if state.waiting_interactive == 0 {
+            self.0.interactive_done.notify_waiters();
+        }
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+use crate::bandwidth::{
+    current_transfer_priority, with_transfer_priority, BandwidthLimiter, BandwidthLimits,
+    TransferPriority,
+};
+
+#[tokio::test]
+async fn transfer_priority_is_scoped() {
+    assert_eq!(current_transfer_priority(), TransferPriority::Interactive);
+    let priority = with_transfer_priority(TransferPriority::Background, async {
+        current_transfer_priority()
+    })
+    .await;
+    assert_eq!(priority, TransferPriority::Background);
+    assert_eq!(current_transfer_priority(), TransferPriority::Interactive);
+}
+
+#[test]
+fn zero_is_unlimited() {
+    let limits = BandwidthLimits::new(Some(0), Some(1024));
+    assert!(limits.upload.is_none());
+    assert!(limits.download.is_some());
+}
+
+#[tokio::test(start_paused = true)]
+async fn limits_rate() {
+    let limiter = BandwidthLimiter::new(100);
+    let start = tokio::time::Instant::now();
+    // The first second of transfer is available immediately, and then the rate is limited.
+    for _ in 0..4 {
+        limiter.acquire(50, TransferPriority::Interactive).await;
+    }
+    limiter.acquire(1, TransferPriority::Interactive).await;
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test(start_paused = true)]
+async fn interactive_before_background() {
+    let limiter = Arc::new(BandwidthLimiter::new(100));
+    // Put the bucket into debt.
+    limiter.acquire(300, TransferPriority::Interactive).await;
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let transfer = |priority: TransferPriority| {
+        let limiter = limiter.clone();
+        let order = order.clone();
+        tokio::spawn(async move {
+            limiter.acquire(100, priority).await;
+            order.lock().push(priority);
+        })
+    };
+    let background = transfer(TransferPriority::Background);
+    tokio::task::yield_now().await;
+    let interactive = transfer(TransferPriority::Interactive);
+    background.await.unwrap();
+    interactive.await.unwrap();
+
+    assert_eq!(
+        *order.lock(),
+        vec![TransferPriority::Interactive, TransferPriority::Background]
+    );
+}
@@ -0,0 +1,199 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hashing::{Digest, Fingerprint, FINGERPRINT_SIZE};
+
+use crate::{EntryType, Store, StoreError};
+
+///
+/// Identifies (and versions) the format of a bundle.
+///
+/// A bundle is a gzip compressed stream containing this header, followed by:
+///   1. the number of root digests, and then each root as an entry without content.
+///   2. the number of entries, and then each entry with its content.
+///
+/// An entry is encoded as its `EntryType`, fingerprint, and size, followed by `size` bytes of
+/// content. All integers are little endian `u64`s, except for the `EntryType`, which is a `u8`.
+///
+const BUNDLE_HEADER: &[u8] = b"pants-store-bundle-v1\n";
+
+/// The number of bytes of entries to buffer before writing them to the local store on import.
+const IMPORT_BATCH_BYTES: usize = 64 * 1024 * 1024;
+
+impl Store {
+    ///
+    /// Writes a self-contained, compressed bundle containing the given digests (which may be either
+    /// Files or Directories), and everything reachable from them, to the given path.
+    ///
+    /// Each Directory proto and file blob is written exactly once, regardless of how many times it
+    /// is reachable. All of the digests must be present in the local store.
+    ///
+    pub async fn export_bundle(&self, digests: Vec<Digest>, path: &Path) -> Result<(), StoreError> {
+        let reachable = self.expand_local_digests(digests.iter()).await?;
+        let entry = |digest: &Digest| match reachable.get(digest) {
+            Some(Some(entry_type)) => Ok((*entry_type, *digest)),
+            _ => Err(StoreError::MissingDigest(
+                "Cannot export a digest which is not present in the local store".to_owned(),
+                *digest,
+            )),
+        };
+        let roots = digests.iter().map(entry).collect::<Result<Vec<_>, _>>()?;
+        let mut entries = reachable.keys().map(entry).collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|(entry_type, digest)| (*entry_type, digest.hash));
+
+        let write_err = |e: io::Error| format!("Failed to write bundle to {path:?}: {e}");
+        let file = File::create(path).map_err(write_err)?;
+        let mut writer = GzEncoder::new(BufWriter::new(file), Compression::default());
+        writer.write_all(BUNDLE_HEADER).map_err(write_err)?;
+        writer
+            .write_all(&(roots.len() as u64).to_le_bytes())
+            .map_err(write_err)?;
+        for (entry_type, digest) in roots {
+            write_entry_header(&mut writer, entry_type, digest).map_err(write_err)?;
+        }
+
+        writer
+            .write_all(&(entries.len() as u64).to_le_bytes())
+            .map_err(write_err)?;
+        for (entry_type, digest) in entries {
+            let bytes = self
+                .local
+                .load_bytes_with(entry_type, digest, Bytes::copy_from_slice)
+                .await?
+                .ok_or_else(|| {
+                    StoreError::MissingDigest(
+                        "Digest was removed from the local store during export".to_owned(),
+                        digest,
+                    )
+                })?;
+            write_entry_header(&mut writer, entry_type, digest).map_err(write_err)?;
+            writer.write_all(&bytes).map_err(write_err)?;
+        }
+
+        writer
+            .finish()
+            .and_then(|mut w| w.flush())
+            .map_err(write_err)?;
+        Ok(())
+    }
+
+    ///
+    /// Reads a bundle written by `export_bundle` into the local store, and returns the digests (and
+    /// types) of its roots.
+    ///
+    /// The content of every entry is verified against its digest before it is stored.
+    ///
+    pub async fn import_bundle(&self, path: &Path) -> Result<Vec<(EntryType, Digest)>, StoreError> {
+        let read_err = |e: io::Error| format!("Failed to read bundle from {path:?}: {e}");
+        let file = File::open(path).map_err(read_err)?;
+        let mut reader = GzDecoder::new(BufReader::new(file));
+
+        let mut header = vec![0; BUNDLE_HEADER.len()];
+        reader.read_exact(&mut header).map_err(read_err)?;
+        if header != BUNDLE_HEADER {
+            return Err(format!("{path:?} is not a bundle of the store.").into());
+        }
+
+        let root_count = read_u64(&mut reader).map_err(read_err)?;
+        let roots = (0..root_count)
+            .map(|_| read_entry_header(&mut reader))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(read_err)?;
+
+        let entry_count = read_u64(&mut reader).map_err(read_err)?;
+        let mut batches: Vec<(EntryType, Vec<(Fingerprint, Bytes)>)> = vec![
+            (EntryType::Directory, Vec::new()),
+            (EntryType::File, Vec::new()),
+        ];
+        let mut batched_bytes = 0;
+        for _ in 0..entry_count {
+            let (entry_type, digest) = read_entry_header(&mut reader).map_err(read_err)?;
+            let mut bytes = vec![0; digest.size_bytes];
+            reader.read_exact(&mut bytes).map_err(read_err)?;
+            if Digest::of_bytes(&bytes) != digest {
+                return Err(format!(
+                    "The content of {digest:?} in the bundle {path:?} did not match its digest."
+                )
+                .into());
+            }
+
+            batched_bytes += bytes.len();
+            batches
+                .iter_mut()
+                .find(|(t, _)| *t == entry_type)
+                .expect("All entry types are batched.")
+                .1
+                .push((digest.hash, Bytes::from(bytes)));
+            if batched_bytes >= IMPORT_BATCH_BYTES {
+                self.store_batches(&mut batches).await?;
+                batched_bytes = 0;
+            }
+        }
+        self.store_batches(&mut batches).await?;
+
+        Ok(roots)
+    }
+
+    async fn store_batches(
+        &self,
+        batches: &mut [(EntryType, Vec<(Fingerprint, Bytes)>)],
+    ) -> Result<(), StoreError> {
+        for (entry_type, items) in batches {
+            if !items.is_empty() {
+                self.local
+                    .store_bytes_batch(*entry_type, std::mem::take(items), true)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_entry_header(
+    writer: &mut impl Write,
+    entry_type: EntryType,
+    digest: Digest,
+) -> io::Result<()> {
+    let entry_type: u8 = match entry_type {
+        EntryType::Directory => 0,
+        EntryType::File => 1,
+    };
+    writer.write_all(&[entry_type])?;
+    writer.write_all(digest.hash.as_bytes())?;
+    writer.write_all(&(digest.size_bytes as u64).to_le_bytes())
+}
+
+fn read_entry_header(reader: &mut impl Read) -> io::Result<(EntryType, Digest)> {
+    let mut entry_type = [0; 1];
+    reader.read_exact(&mut entry_type)?;
+    let entry_type = match entry_type[0] {
+        0 => EntryType::Directory,
+        1 => EntryType::File,
+        t => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unrecognized entry type: {t}"),
+            ))
+        }
+    };
+    let mut fingerprint = [0; FINGERPRINT_SIZE];
+    reader.read_exact(&mut fingerprint)?;
+    let size_bytes = read_u64(reader)?;
+    Ok((
+        entry_type,
+        Digest::new(Fingerprint(fingerprint), size_bytes as usize),
+    ))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
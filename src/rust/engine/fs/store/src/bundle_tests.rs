@@ -0,0 +1,92 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use tempfile::TempDir;
+use testutil::data::{TestData, TestDirectory};
+
+use crate::tests::new_local_store;
+use crate::{EntryType, StoreError};
+
+#[tokio::test]
+async fn roundtrip_bundle() {
+    let source_dir = TempDir::new().unwrap();
+    let source = new_local_store(source_dir.path());
+    let roland = TestData::roland();
+    let catnip = TestData::catnip();
+    let recursive = TestDirectory::recursive();
+    source
+        .store_file_bytes(roland.bytes(), false)
+        .await
+        .unwrap();
+    source
+        .store_file_bytes(catnip.bytes(), false)
+        .await
+        .unwrap();
+    source
+        .record_directory(&TestDirectory::containing_roland().directory(), false)
+        .await
+        .unwrap();
+    source
+        .record_directory(&recursive.directory(), false)
+        .await
+        .unwrap();
+
+    let bundle_dir = TempDir::new().unwrap();
+    let bundle = bundle_dir.path().join("bundle.gz");
+    source
+        .export_bundle(vec![recursive.digest(), catnip.digest()], &bundle)
+        .await
+        .unwrap();
+
+    let destination_dir = TempDir::new().unwrap();
+    let destination = new_local_store(destination_dir.path());
+    let roots = destination.import_bundle(&bundle).await.unwrap();
+    assert_eq!(
+        roots,
+        vec![
+            (EntryType::Directory, recursive.digest()),
+            (EntryType::File, catnip.digest())
+        ]
+    );
+
+    // Everything reachable from the roots was imported.
+    let trie = destination
+        .load_digest_trie(recursive.directory_digest())
+        .await
+        .unwrap();
+    assert_eq!(trie.compute_root_digest(), recursive.digest());
+    for file in [roland, catnip] {
+        assert_eq!(
+            destination
+                .load_file_bytes_with(file.digest(), |bytes| bytes.to_vec())
+                .await
+                .unwrap(),
+            file.bytes().to_vec()
+        );
+    }
+}
+
+#[tokio::test]
+async fn export_missing_digest_errors() {
+    let store_dir = TempDir::new().unwrap();
+    let store = new_local_store(store_dir.path());
+    let bundle_dir = TempDir::new().unwrap();
+
+    let result = store
+        .export_bundle(
+            vec![TestData::roland().digest()],
+            &bundle_dir.path().join("bundle.gz"),
+        )
+        .await;
+    assert!(matches!(result, Err(StoreError::MissingDigest(..))));
+}
+
+#[tokio::test]
+async fn import_non_bundle_errors() {
+    let store_dir = TempDir::new().unwrap();
+    let store = new_local_store(store_dir.path());
+    let bundle_dir = TempDir::new().unwrap();
+    let not_a_bundle = bundle_dir.path().join("not_a_bundle");
+    std::fs::write(&not_a_bundle, b"European Burmese").unwrap();
+
+    assert!(store.import_bundle(&not_a_bundle).await.is_err());
+}
@@ -0,0 +1,266 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fs::{directory, DirectoryDigest, Permissions, SymlinkBehavior};
+use futures::future;
+use hashing::Digest;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{Store, StoreError};
+
+/// The name of the manifest of exported entries which is written to the root of an export.
+pub const EXPORT_MANIFEST_NAME: &str = ".pants-export-manifest.json";
+
+///
+/// How the files of a digest are materialized by `Store::export_directory`.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, strum_macros::EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ExportMode {
+    /// All files are copied, and are writable.
+    Copy,
+    /// Files which are stored as files in the local store are symlinked to it: all other files are
+    /// copied.
+    Symlink,
+    /// Files which are stored as files in the local store are hardlinked to it (or cloned, on macOS)
+    /// if the destination is on the same filesystem: all other files are copied.
+    Hardlink,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ManifestEntry {
+    Directory,
+    File { digest: Digest, is_executable: bool },
+    Symlink { target: PathBuf },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    mode: Option<ExportMode>,
+    entries: BTreeMap<PathBuf, ManifestEntry>,
+}
+
+///
+/// The number of files and symlinks which were written or left unchanged by an export, and the
+/// number of stale entries which were removed.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+impl Store {
+    ///
+    /// Exports the given digest into a user-visible destination directory.
+    ///
+    /// A manifest of the exported entries is recorded in the destination, so that re-exporting
+    /// only writes the entries which have changed since the previous export, and removes the
+    /// entries which are no longer present. Other files in the destination are left alone unless
+    /// they collide with an exported entry.
+    ///
+    /// In the link modes, files which are linked into the local store are read-only, and (for
+    /// `ExportMode::Symlink`) will dangle if they are garbage collected from the store before the
+    /// next export.
+    ///
+    pub async fn export_directory(
+        &self,
+        destination: &Path,
+        digest: DirectoryDigest,
+        mode: ExportMode,
+    ) -> Result<ExportSummary, StoreError> {
+        let mut entries = BTreeMap::new();
+        self.load_digest_trie(digest)
+            .await?
+            .walk(SymlinkBehavior::Aware, &mut |path, entry| {
+                if path.as_os_str().is_empty() {
+                    return;
+                }
+                let entry = match entry {
+                    directory::Entry::Directory(_) => ManifestEntry::Directory,
+                    directory::Entry::File(f) => ManifestEntry::File {
+                        digest: f.digest(),
+                        is_executable: f.is_executable(),
+                    },
+                    directory::Entry::Symlink(s) => ManifestEntry::Symlink {
+                        target: s.target().to_owned(),
+                    },
+                };
+                entries.insert(path.to_owned(), entry);
+            });
+
+        tokio::fs::create_dir_all(destination)
+            .await
+            .map_err(|e| format!("Failed to create {}: {e}", destination.display()))?;
+        let manifest_path = destination.join(EXPORT_MANIFEST_NAME);
+        let previous = read_manifest(&manifest_path);
+        let is_unchanged = |path: &Path, entry: &ManifestEntry| {
+            previous.mode == Some(mode)
+                && previous.entries.get(path) == Some(entry)
+                && entry_exists(&destination.join(path), entry)
+        };
+
+        // Remove the previously exported entries which are stale.
+        let mut summary = ExportSummary::default();
+        for (path, previous_entry) in &previous.entries {
+            let still_exported = entries.get(path) == Some(previous_entry)
+                && (*previous_entry == ManifestEntry::Directory
+                    || is_unchanged(path, previous_entry));
+            if still_exported {
+                continue;
+            }
+            if remove_path(&destination.join(path))? {
+                summary.removed += 1;
+            }
+        }
+
+        // Create directories (in order, so that parents precede children), and then write the other
+        // entries concurrently.
+        let mut writes = Vec::new();
+        for (path, entry) in &entries {
+            let dst = destination.join(path);
+            match entry {
+                ManifestEntry::Directory => {
+                    if !dst.is_dir() {
+                        remove_path(&dst)?;
+                    }
+                    std::fs::create_dir_all(&dst).map_err(|e| {
+                        format!("Failed to create directory {}: {e}", dst.display())
+                    })?;
+                }
+                _ if is_unchanged(path, entry) => summary.unchanged += 1,
+                _ => {
+                    // The path may be occupied by an entry which was not exported.
+                    remove_path(&dst)?;
+                    writes.push((dst, entry));
+                }
+            }
+        }
+        summary.written = writes.len();
+
+        let can_hardlink = mode == ExportMode::Hardlink
+            && self.local.is_hardlinkable_destination(destination).await?;
+        future::try_join_all(writes.into_iter().map(|(dst, entry)| async move {
+            let _permit = self.materialize.acquire().await;
+            match entry {
+                ManifestEntry::File {
+                    digest,
+                    is_executable,
+                } => {
+                    self.export_file(dst, *digest, *is_executable, mode, can_hardlink)
+                        .await
+                }
+                ManifestEntry::Symlink { target } => {
+                    self.materialize_symlink(dst, target.to_str().unwrap().to_owned())
+                        .await
+                }
+                ManifestEntry::Directory => unreachable!("Directories were created above."),
+            }
+        }))
+        .await?;
+
+        write_manifest(
+            &manifest_path,
+            &Manifest {
+                mode: Some(mode),
+                entries,
+            },
+        )?;
+        Ok(summary)
+    }
+
+    async fn export_file(
+        &self,
+        destination: PathBuf,
+        digest: Digest,
+        is_executable: bool,
+        mode: ExportMode,
+        can_hardlink: bool,
+    ) -> Result<(), StoreError> {
+        let link_target = match mode {
+            ExportMode::Copy => None,
+            ExportMode::Symlink => self.local.load_from_fs(digest).await?,
+            ExportMode::Hardlink if can_hardlink => self.local.load_from_fs(digest).await?,
+            ExportMode::Hardlink => None,
+        };
+        let link_target = link_target
+            .map(|target| {
+                target
+                    .into_os_string()
+                    .into_string()
+                    .map_err(|target| format!("Non-UTF8 path in the local store: {target:?}"))
+            })
+            .transpose()?;
+        match link_target {
+            Some(target) if mode == ExportMode::Symlink => {
+                self.materialize_symlink(destination, target).await
+            }
+            Some(target) => self.materialize_hardlink(destination, target).await,
+            None => {
+                self.materialize_file(destination, digest, Permissions::Writable, is_executable)
+                    .await
+            }
+        }
+    }
+}
+
+///
+/// Reads the manifest of a previous export, if any. A manifest which cannot be read is treated as
+/// empty, meaning that all entries will be re-exported.
+///
+fn read_manifest(path: &Path) -> Manifest {
+    std::fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn write_manifest(path: &Path, manifest: &Manifest) -> Result<(), String> {
+    let bytes = serde_json::to_vec(manifest)
+        .map_err(|e| format!("Failed to serialize export manifest: {e}"))?;
+    // Write to a temporary file and then rename, so that an interrupted export never leaves a
+    // partial manifest.
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, bytes)
+        .and_then(|()| std::fs::rename(&tmp_path, path))
+        .map_err(|e| format!("Failed to write export manifest {}: {e}", path.display()))
+}
+
+///
+/// Returns true if the given previously exported entry is still present on disk. Files are
+/// compared by size, which detects most (but not all) modifications of exported copies.
+///
+fn entry_exists(path: &Path, entry: &ManifestEntry) -> bool {
+    match entry {
+        ManifestEntry::Directory => path.is_dir(),
+        ManifestEntry::File { digest, .. } => std::fs::metadata(path)
+            .map(|metadata| metadata.is_file() && metadata.len() as usize == digest.size_bytes)
+            .unwrap_or(false),
+        ManifestEntry::Symlink { target } => {
+            std::fs::read_link(path).map_or(false, |existing| existing == *target)
+        }
+    }
+}
+
+///
+/// Removes whatever exists at the given path (without following symlinks), and returns true if
+/// anything was removed.
+///
+fn remove_path(path: &Path) -> Result<bool, String> {
+    let res = match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) => Err(e),
+    };
+    match res {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to remove {}: {e}", path.display())),
+    }
+}
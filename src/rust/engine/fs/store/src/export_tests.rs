@@ -0,0 +1,112 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use tempfile::TempDir;
+
+use crate::tests::{new_local_store, store_files};
+use crate::{ExportMode, ExportSummary, EXPORT_MANIFEST_NAME};
+
+#[tokio::test]
+async fn reexport_writes_only_changed_entries() {
+    let store_dir = TempDir::new().unwrap();
+    let store = new_local_store(store_dir.path());
+    let export_dir = TempDir::new().unwrap();
+    let destination = export_dir.path().join("export");
+
+    let first = store_files(
+        &store,
+        &[
+            ("a.txt", "a", false),
+            ("stale/b.txt", "b", false),
+            ("c/c.txt", "c", false),
+        ],
+    )
+    .await;
+    let summary = store
+        .export_directory(&destination, first, ExportMode::Copy)
+        .await
+        .unwrap();
+    assert_eq!(
+        summary,
+        ExportSummary {
+            written: 3,
+            unchanged: 0,
+            removed: 0,
+        }
+    );
+    assert!(destination.join(EXPORT_MANIFEST_NAME).is_file());
+
+    // Files which were not exported are preserved.
+    std::fs::write(destination.join("c/untracked.txt"), "untracked").unwrap();
+
+    let second = store_files(
+        &store,
+        &[("a.txt", "a", false), ("c/c.txt", "changed", false)],
+    )
+    .await;
+    let summary = store
+        .export_directory(&destination, second.clone(), ExportMode::Copy)
+        .await
+        .unwrap();
+    assert_eq!(
+        summary,
+        ExportSummary {
+            written: 1,
+            unchanged: 1,
+            // The `stale` directory and its file, and the previous `c/c.txt`.
+            removed: 2,
+        }
+    );
+    assert_eq!(std::fs::read(destination.join("a.txt")).unwrap(), b"a");
+    assert_eq!(
+        std::fs::read(destination.join("c/c.txt")).unwrap(),
+        b"changed"
+    );
+    assert!(!destination.join("stale").exists());
+    assert!(destination.join("c/untracked.txt").exists());
+
+    // An exported file which was deleted is restored.
+    std::fs::remove_file(destination.join("a.txt")).unwrap();
+    let summary = store
+        .export_directory(&destination, second, ExportMode::Copy)
+        .await
+        .unwrap();
+    assert_eq!((summary.written, summary.unchanged), (1, 1));
+    assert_eq!(std::fs::read(destination.join("a.txt")).unwrap(), b"a");
+}
+
+#[tokio::test]
+async fn symlink_export_links_large_files() {
+    let store_dir = TempDir::new().unwrap();
+    let store = new_local_store(store_dir.path());
+    let export_dir = TempDir::new().unwrap();
+    let destination = export_dir.path().join("export");
+
+    let large = "x".repeat(1024 * 1024);
+    let digest = store_files(
+        &store,
+        &[("large.bin", &large, false), ("small.txt", "small", false)],
+    )
+    .await;
+    store
+        .export_directory(&destination, digest.clone(), ExportMode::Symlink)
+        .await
+        .unwrap();
+
+    let large_path = destination.join("large.bin");
+    assert!(large_path.symlink_metadata().unwrap().is_symlink());
+    assert!(std::fs::read_link(&large_path)
+        .unwrap()
+        .starts_with(store_dir.path()));
+    assert_eq!(std::fs::read_to_string(&large_path).unwrap(), large);
+    let small_path = destination.join("small.txt");
+    assert!(small_path.symlink_metadata().unwrap().is_file());
+
+    // Changing the mode re-exports everything.
+    let summary = store
+        .export_directory(&destination, digest, ExportMode::Copy)
+        .await
+        .unwrap();
+    assert_eq!((summary.written, summary.unchanged), (2, 0));
+    assert!(large_path.symlink_metadata().unwrap().is_file());
+    assert_eq!(std::fs::read_to_string(&large_path).unwrap(), large);
+}
@@ -6,6 +6,7 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_oncecell::OnceCell;
+use async_trait::async_trait;
 use fs::{DirectoryDigest, Permissions, RelativePath};
 use hashing::Digest;
 use parking_lot::Mutex;
@@ -20,10 +21,30 @@ pub struct WorkdirSymlink {
     pub dst: PathBuf,
 }
 
+///
+/// Materializes immutable inputs on behalf of `ImmutableInputs`: for example, in a separate process
+/// which owns the directory that they are materialized in, so that the processes which consume
+/// them cannot modify them.
+///
+#[async_trait]
+pub trait DelegatedMaterializer: Send + Sync {
+    /// The directory below which all Digests are materialized.
+    fn workdir(&self) -> &Path;
+
+    /// Materializes the given (persisted) Directory, and returns the absolute path it was
+    /// materialized at, which must be below `Self::workdir`.
+    async fn materialize_directory(&self, digest: Digest) -> Result<PathBuf, String>;
+}
+
+enum Materializer {
+    // The TempDir that digests are materialized in.
+    Local(TempDir),
+    Delegated(Arc<dyn DelegatedMaterializer>),
+}
+
 struct Inner {
     store: Store,
-    // The TempDir that digests are materialized in.
-    workdir: TempDir,
+    materializer: Materializer,
     // A map from Digest to the location it has been materialized at. The OnceCell allows
     // for cooperation between threads attempting to create Digests.
     contents: Mutex<HashMap<Digest, Arc<OnceCell<PathBuf>>>>,
@@ -47,17 +68,32 @@ impl ImmutableInputs {
             })?;
         Ok(Self(Arc::new(Inner {
             store,
-            workdir,
+            materializer: Materializer::Local(workdir),
             contents: Mutex::default(),
         })))
     }
 
+    ///
+    /// Creates an instance which delegates materialization to the given DelegatedMaterializer,
+    /// after ensuring that each Digest has been persisted to the given Store.
+    ///
+    pub fn new_delegated(store: Store, materializer: Arc<dyn DelegatedMaterializer>) -> Self {
+        Self(Arc::new(Inner {
+            store,
+            materializer: Materializer::Delegated(materializer),
+            contents: Mutex::default(),
+        }))
+    }
+
     pub fn workdir(&self) -> &Path {
-        self.0.workdir.path()
+        match &self.0.materializer {
+            Materializer::Local(workdir) => workdir.path(),
+            Materializer::Delegated(materializer) => materializer.workdir(),
+        }
     }
 
     /// Returns an absolute Path to immutably consume the given Digest from.
-    pub(crate) async fn path_for_dir(
+    pub async fn path_for_dir(
         &self,
         directory_digest: DirectoryDigest,
     ) -> Result<PathBuf, StoreError> {
@@ -94,7 +130,18 @@ impl ImmutableInputs {
         // We take the final approach here currently (for simplicity's sake), but the advanced variant
         // of approach 2 might eventually be worthwhile.
         cell.get_or_try_init(async {
-            let chroot = TempDir::new_in(self.0.workdir.path()).map_err(|e| {
+            let workdir = match &self.0.materializer {
+                Materializer::Local(workdir) => workdir.path(),
+                Materializer::Delegated(materializer) => {
+                    // The delegate loads the Directory from the Store, and so it must be persisted.
+                    self.0
+                        .store
+                        .ensure_directory_digest_persisted(directory_digest)
+                        .await?;
+                    return Ok(materializer.materialize_directory(digest).await?);
+                }
+            };
+            let chroot = TempDir::new_in(workdir).map_err(|e| {
                 format!(
             "Failed to create a temporary directory for materialization of immutable input \
             digest {digest:?}: {e}"
@@ -106,7 +153,7 @@ impl ImmutableInputs {
                 .store
                 .materialize_directory(
                     dest.clone(),
-                    self.0.workdir.path(),
+                    workdir,
                     directory_digest,
                     false,
                     &BTreeSet::new(),
@@ -114,6 +161,13 @@ impl ImmutableInputs {
                 )
                 .await?;
 
+            // Access to the workdir is controlled by its own permissions: make the chroot
+            // traversable, so that a workdir which is shared with other users (as by the sandboxer)
+            // is usable by them.
+            fs::platform::set_mode_sync(chroot.path(), 0o755).map_err(|e| {
+                format!("Failed to set permissions for immutable input {digest:?}: {e}")
+            })?;
+
             // Now that we've successfully initialized the destination, forget the TempDir so that it
             // is not cleaned up.
             let _ = chroot.into_path();
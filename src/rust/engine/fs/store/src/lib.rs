@@ -3,8 +3,18 @@
 
 #![recursion_limit = "256"]
 
+pub mod bandwidth;
+#[cfg(test)]
+mod bandwidth_tests;
+mod bundle;
+#[cfg(test)]
+mod bundle_tests;
+mod export;
+pub use crate::export::{ExportMode, ExportSummary, EXPORT_MANIFEST_NAME};
+#[cfg(test)]
+mod export_tests;
 mod immutable_inputs;
-pub use crate::immutable_inputs::{ImmutableInputs, WorkdirSymlink};
+pub use crate::immutable_inputs::{DelegatedMaterializer, ImmutableInputs, WorkdirSymlink};
 mod snapshot;
 pub use crate::snapshot::{OneOffStoreFileByDigest, Snapshot, StoreFileByDigest};
 mod snapshot_ops;
@@ -12,15 +22,17 @@ mod snapshot_ops;
 mod snapshot_ops_tests;
 #[cfg(test)]
 mod snapshot_tests;
-pub use crate::snapshot_ops::{SnapshotOps, SubsetParams};
+pub use crate::snapshot_ops::{Relocation, SnapshotOps, SubsetParams};
+mod transform;
+pub use crate::transform::{FileTransform, LineEnding};
+#[cfg(test)]
+mod transform_tests;
 
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::{self, Debug, Display};
 use std::fs::OpenOptions;
-use std::fs::Permissions as FSPermissions;
 use std::future::Future;
 use std::io::Write;
-use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 use std::path::{Component, Path, PathBuf};
 use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
@@ -28,6 +40,7 @@ use std::time::{Duration, Instant};
 use async_oncecell::OnceCell;
 use async_trait::async_trait;
 use bytes::Bytes;
+use engine_error::{EngineError, ErrorCode};
 use fs::{
     default_cache_path, directory, DigestEntry, DigestTrie, Dir, DirectoryDigest, File,
     FileContent, FileEntry, Link, PathStat, Permissions, RelativePath, SymlinkBehavior,
@@ -42,20 +55,27 @@ use prost::Message;
 use protos::gen::build::bazel::remote::execution::v2 as remexec;
 use protos::require_digest;
 use remexec::Tree;
+use remote_provider::ProviderError;
 use serde_derive::Serialize;
 use sharded_lmdb::DEFAULT_LEASE_TIME;
 #[cfg(target_os = "macos")]
 use tokio::fs::copy;
 #[cfg(not(target_os = "macos"))]
 use tokio::fs::hard_link;
-use tokio::fs::symlink;
+use tokio::sync::Semaphore;
 use tryfuture::try_future;
 use workunit_store::{in_workunit, Level, Metric};
 
+use crate::bandwidth::BandwidthLimits;
+
 const KILOBYTES: usize = 1024;
 const MEGABYTES: usize = 1024 * KILOBYTES;
 const GIGABYTES: usize = 1024 * MEGABYTES;
 
+/// The maximum number of small files which are written from a single snapshot of the local store
+/// while materializing a directory: see `Store::materialize_local_files_batch`.
+const MATERIALIZE_BATCH_SIZE: usize = 256;
+
 mod local;
 #[cfg(test)]
 pub mod local_tests;
@@ -63,6 +83,9 @@ pub mod local_tests;
 pub mod remote;
 #[cfg(test)]
 mod remote_tests;
+pub mod server;
+#[cfg(test)]
+mod server_tests;
 
 // Consumers of this crate shouldn't need to worry about the exact crate structure that comes
 // together to make a store.
@@ -73,6 +96,11 @@ pub struct LocalOptions {
     pub directories_max_size_bytes: usize,
     pub lease_time: Duration,
     pub shard_count: u8,
+    /// The maximum number of concurrent file writes while materializing directories.
+    pub materialize_concurrency: usize,
+    /// Whether to attempt to clone (rather than copy) files from the local store when they are
+    /// materialized on a filesystem which supports it: see `fs::platform::copy_file`.
+    pub clone_files: bool,
 }
 
 ///
@@ -86,6 +114,8 @@ impl Default for LocalOptions {
             directories_max_size_bytes: 2 * 4 * GIGABYTES,
             lease_time: DEFAULT_LEASE_TIME,
             shard_count: 16,
+            materialize_concurrency: 128,
+            clone_files: true,
         }
     }
 }
@@ -94,6 +124,8 @@ impl Default for LocalOptions {
 pub enum StoreError {
     /// A Digest was not present in either of the local or remote Stores.
     MissingDigest(String, Digest),
+    /// An error with a stable code and category: see `engine_error::ErrorCode`.
+    Classified(EngineError),
     /// All other error types.
     Unclassified(String),
 }
@@ -102,9 +134,18 @@ impl StoreError {
     pub fn enrich(self, prefix: &str) -> Self {
         match self {
             Self::MissingDigest(s, d) => Self::MissingDigest(format!("{prefix}: {s}"), d),
+            Self::Classified(e) => Self::Classified(e.with_context(prefix)),
             Self::Unclassified(s) => Self::Unclassified(format!("{prefix}: {s}")),
         }
     }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::MissingDigest(..) => ErrorCode::MissingDigest,
+            Self::Classified(e) => e.code(),
+            Self::Unclassified(_) => ErrorCode::Unclassified,
+        }
+    }
 }
 
 impl Display for StoreError {
@@ -113,17 +154,37 @@ impl Display for StoreError {
             Self::MissingDigest(s, d) => {
                 write!(f, "{s}: {d:?}")
             }
+            Self::Classified(e) => write!(f, "{e}"),
             Self::Unclassified(s) => write!(f, "{s}"),
         }
     }
 }
 
+impl From<EngineError> for StoreError {
+    fn from(err: EngineError) -> Self {
+        Self::Classified(err)
+    }
+}
+
 impl From<String> for StoreError {
     fn from(err: String) -> Self {
         Self::Unclassified(err)
     }
 }
 
+impl From<ProviderError> for StoreError {
+    fn from(err: ProviderError) -> Self {
+        match err {
+            // Only failures to reach the remote store are retryable: other errors (such as invalid
+            // requests or responses) will fail in the same way if retried.
+            ProviderError::Unavailable(s) => {
+                EngineError::new(ErrorCode::RemoteStoreUnavailable, s).into()
+            }
+            ProviderError::Other(s) => Self::Unclassified(s),
+        }
+    }
+}
+
 // Summary of the files and directories uploaded with an operation
 // ingested_file_{count, bytes}: Number and combined size of processed files
 // uploaded_file_{count, bytes}: Number and combined size of files uploaded to the remote
@@ -137,6 +198,31 @@ pub struct UploadSummary {
     pub upload_wall_time: Duration,
 }
 
+///
+/// The outcome of scrubbing the local store: see `Store::scrub`.
+///
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScrubSummary {
+    /// The number of entries whose content was re-hashed.
+    pub checked: usize,
+    /// The entries whose content did not match their digest, and which were removed.
+    pub corrupted: Vec<(EntryType, Digest)>,
+    /// The corrupted entries which were re-fetched from the remote store.
+    pub repaired: Vec<(EntryType, Digest)>,
+}
+
+impl Display for ScrubSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Checked {} entries: {} were corrupted, of which {} were repaired from the remote store.",
+            self.checked,
+            self.corrupted.len(),
+            self.repaired.len()
+        )
+    }
+}
+
 ///
 /// Wraps a `remote::ByteStore` with state to help avoid uploading common blobs multiple times.
 ///
@@ -286,6 +372,36 @@ pub struct Store {
     local: local::ByteStore,
     remote: Option<RemoteStore>,
     immutable_inputs_base: Option<PathBuf>,
+    materialize: MaterializeOptions,
+    /// The limits on the rates of remote transfers, which apply to any remote which is added by
+    /// `into_with_remote`.
+    bandwidth: BandwidthLimits,
+}
+
+///
+/// Limits on the filesystem operations which are used to materialize directories.
+///
+#[derive(Debug, Clone)]
+struct MaterializeOptions {
+    /// Bounds the number of concurrent file writes (or batches of writes).
+    permits: Arc<Semaphore>,
+    clone_files: bool,
+}
+
+impl MaterializeOptions {
+    fn new(options: &LocalOptions) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(options.materialize_concurrency.max(1))),
+            clone_files: options.clone_files,
+        }
+    }
+
+    async fn acquire(&self) -> tokio::sync::SemaphorePermit<'_> {
+        self.permits
+            .acquire()
+            .await
+            .expect("The materialize semaphore is never closed.")
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -306,6 +422,16 @@ pub enum ShrinkBehavior {
     Compact,
 }
 
+///
+/// A prefix of the REv2 `Tree` proto, which decodes only the root Directory of the Tree (skipping
+/// over the bytes of its children).
+///
+#[derive(Clone, PartialEq, prost::Message)]
+struct TreeRoot {
+    #[prost(message, optional, tag = "1")]
+    root: Option<remexec::Directory>,
+}
+
 // Note that Store doesn't implement ByteStore because it operates at a higher level of abstraction,
 // considering Directories as a standalone concept, rather than a buffer of bytes.
 // This has the nice property that Directories can be trusted to be valid and canonical.
@@ -322,6 +448,8 @@ impl Store {
             local: local::ByteStore::new(executor, path)?,
             remote: None,
             immutable_inputs_base: None,
+            materialize: MaterializeOptions::new(&LocalOptions::default()),
+            bandwidth: BandwidthLimits::default(),
         })
     }
 
@@ -331,10 +459,13 @@ impl Store {
         immutable_inputs_base: &Path,
         options: LocalOptions,
     ) -> Result<Store, String> {
+        let materialize = MaterializeOptions::new(&options);
         Ok(Store {
             local: local::ByteStore::new_with_options(executor, path, options)?,
             remote: None,
             immutable_inputs_base: Some(immutable_inputs_base.to_path_buf()),
+            materialize,
+            bandwidth: BandwidthLimits::default(),
         })
     }
 
@@ -349,9 +480,33 @@ impl Store {
             local: self.local,
             remote: None,
             immutable_inputs_base: self.immutable_inputs_base,
+            materialize: self.materialize,
+            bandwidth: self.bandwidth,
         }
     }
 
+    ///
+    /// Limits the rates of the transfers to and from the remote half of this (copy of) a Store,
+    /// including a remote which is added later by `into_with_remote`.
+    ///
+    pub fn with_bandwidth_limits(self, bandwidth: BandwidthLimits) -> Store {
+        Store {
+            remote: self.remote.map(|remote| RemoteStore {
+                store: remote.store.with_bandwidth_limits(bandwidth.clone()),
+                ..remote
+            }),
+            bandwidth,
+            ..self
+        }
+    }
+
+    ///
+    /// The number of bytes used by the local store's databases, by type of entry.
+    ///
+    pub fn local_used_bytes(&self) -> Vec<(EntryType, Result<usize, String>)> {
+        self.local.used_bytes()
+    }
+
     ///
     /// Add remote storage to a Store. If it is missing a value which it tries to load, it will
     /// attempt to back-fill its local storage from the remote storage.
@@ -363,9 +518,13 @@ impl Store {
         Ok(Store {
             local: self.local,
             remote: Some(RemoteStore::new(
-                remote::ByteStore::from_options(remote_options).await?,
+                remote::ByteStore::from_options(remote_options)
+                    .await?
+                    .with_bandwidth_limits(self.bandwidth.clone()),
             )),
             immutable_inputs_base: self.immutable_inputs_base,
+            materialize: self.materialize,
+            bandwidth: self.bandwidth,
         })
     }
 
@@ -374,6 +533,19 @@ impl Store {
         default_cache_path().join("lmdb_store")
     }
 
+    ///
+    /// Moves the local store at `path` from `from_shard_count` shards into `to_shard_count` shards,
+    /// for use after changing `LocalOptions::shard_count`. No other process may have the store
+    /// open while this runs.
+    ///
+    pub fn reshard_local(
+        path: &Path,
+        from_shard_count: u8,
+        to_shard_count: u8,
+    ) -> Result<(), String> {
+        local::ByteStore::reshard(path, from_shard_count, to_shard_count)
+    }
+
     ///
     /// Remove a file locally, returning true if it existed, or false otherwise.
     ///
@@ -557,6 +729,10 @@ impl Store {
             // The DigestTrie is already loaded.
             return Ok(tree);
         }
+        if let Some(tree) = DigestTrie::interned(digest.as_digest()) {
+            // The DigestTrie is already in memory elsewhere.
+            return Ok(tree);
+        }
 
         // The DigestTrie needs to be loaded from the Store.
         let path_stats_per_directory = self
@@ -614,7 +790,7 @@ impl Store {
             .into());
         }
 
-        Ok(tree)
+        Ok(DigestTrie::intern(computed_digest, tree))
     }
 
     ///
@@ -1018,6 +1194,60 @@ impl Store {
         }
     }
 
+    ///
+    /// Like `load_tree_from_remote`, but places the result beneath the given prefix, and avoids
+    /// expanding the Tree if its root Directory has already been persisted locally (as it will have
+    /// been if the same output was previously consumed): only the root Directory of the Tree is
+    /// decoded in order to check.
+    ///
+    /// Otherwise, the Tree is expanded in memory, and (as with `load_tree_from_remote`) is only
+    /// persisted if the resulting DirectoryDigest is.
+    ///
+    pub async fn load_tree_from_remote_lazily(
+        &self,
+        tree_digest: Digest,
+        prefix: &RelativePath,
+    ) -> Result<Option<DirectoryDigest>, String> {
+        let remote = if let Some(ref remote) = self.remote {
+            remote
+        } else {
+            return Err("Cannot load Trees from a remote without a remote".to_owned());
+        };
+
+        let Some(bytes) = remote.store.load_bytes(tree_digest).await? else {
+            return Ok(None);
+        };
+        let root = TreeRoot::decode(bytes.clone())
+            .map_err(|e| format!("protobuf decode error: {e:?}"))?
+            .root
+            .unwrap_or_default();
+        let mut digest = Digest::of_bytes(&root.to_bytes());
+        protos::verify_directory_canonical(digest, &root)?;
+        if self.local.entry_type(digest.hash).await? != Some(EntryType::Directory) {
+            let tree = Tree::decode(bytes).map_err(|e| format!("protobuf decode error: {e:?}"))?;
+            let trie = DigestTrie::try_from(tree)?.add_prefix(prefix)?;
+            return Ok(Some(trie.into()));
+        }
+
+        // The Tree is already persisted: record only the (small) Directories of the prefix.
+        for component in prefix.components().rev() {
+            let name = component
+                .as_os_str()
+                .to_str()
+                .ok_or_else(|| format!("{prefix:?} is not valid UTF-8"))?
+                .to_owned();
+            let directory = remexec::Directory {
+                directories: vec![remexec::DirectoryNode {
+                    name,
+                    digest: Some(digest.into()),
+                }],
+                ..remexec::Directory::default()
+            };
+            digest = self.record_directory(&directory, true).await?;
+        }
+        Ok(Some(DirectoryDigest::from_persisted_digest(digest)))
+    }
+
     pub async fn lease_all_recursively<'a, Ds: Iterator<Item = &'a Digest>>(
         &self,
         digests: Ds,
@@ -1055,6 +1285,37 @@ impl Store {
         }
     }
 
+    ///
+    /// Verifies the integrity of the local store by re-hashing its entries (or a random sample of
+    /// the given fraction of them). Entries which are corrupted are removed, and then re-fetched
+    /// from the remote store if one is configured. Entries which cannot be re-fetched will be
+    /// reported as missing (rather than as mismatched) when they are next used.
+    ///
+    pub async fn scrub(&self, sample_fraction: f64) -> Result<ScrubSummary, StoreError> {
+        let (checked, corrupted) = self.local.scrub(sample_fraction).await?;
+
+        let mut repaired = vec![];
+        for (entry_type, digest) in &corrupted {
+            log::warn!("Removed corrupted {entry_type:?} {digest:?} from the local store.");
+            let Some(remote) = &self.remote else {
+                continue;
+            };
+            match remote
+                .download_digest_to_local(self.local.clone(), *digest, *entry_type, None)
+                .await
+            {
+                Ok(()) => repaired.push((*entry_type, *digest)),
+                Err(e) => log::warn!("Failed to re-fetch {digest:?} from the remote store: {e}"),
+            }
+        }
+
+        Ok(ScrubSummary {
+            checked,
+            corrupted,
+            repaired,
+        })
+    }
+
     ///
     /// To check if it might be faster to upload the digests recursively
     /// vs checking if the files are present first.
@@ -1222,6 +1483,10 @@ impl Store {
             destination_is_hardlinkable
         };
 
+        let written_files = self
+            .materialize_local_files_batch(&parent_to_child, perms)
+            .await?;
+
         self.materialize_directory_children(
             destination,
             true,
@@ -1229,11 +1494,86 @@ impl Store {
             destination_is_hardlinkable,
             &parent_to_child,
             &mutable_path_ancestors,
+            &written_files,
             perms,
         )
         .await
     }
 
+    ///
+    /// Writes all of the files in the given tree which are small enough to be stored in the local
+    /// databases (and so will never be hardlinked) in batches, each of which is loaded from a single
+    /// consistent snapshot of the local store rather than loading each file individually. Files are
+    /// grouped into batches by their parent directories, and batches are written concurrently.
+    ///
+    /// Returns the paths which were written: files which are not present locally are left to be
+    /// materialized individually.
+    ///
+    async fn materialize_local_files_batch(
+        &self,
+        parent_to_child: &HashMap<PathBuf, Vec<directory::Entry>>,
+        perms: Permissions,
+    ) -> Result<HashSet<PathBuf>, StoreError> {
+        let mut files = Vec::new();
+        for (parent, children) in parent_to_child {
+            for child in children {
+                if let directory::Entry::File(f) = child {
+                    if !ByteStore::should_use_fsdb(EntryType::File, f.digest().size_bytes) {
+                        files.push((
+                            parent.join(child.name().as_ref()),
+                            file_mode(perms, f.is_executable()),
+                            f.digest(),
+                        ));
+                    }
+                }
+            }
+        }
+        // Sort by path so that the files in each directory are written by the same batch.
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let batches = files
+            .chunks(MATERIALIZE_BATCH_SIZE)
+            .map(|batch| self.materialize_local_files(batch.to_vec()));
+        let written = future::try_join_all(batches).await?;
+        Ok(written.into_iter().flatten().collect())
+    }
+
+    async fn materialize_local_files(
+        &self,
+        files: Vec<(PathBuf, u32, Digest)>,
+    ) -> Result<Vec<PathBuf>, StoreError> {
+        let _permit = self.materialize.acquire().await;
+        let (destinations, digests): (Vec<_>, Vec<_>) = files
+            .into_iter()
+            .map(|(path, mode, digest)| ((path, mode), digest))
+            .unzip();
+        let paths = destinations
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        let mut created_parents = HashSet::new();
+        let written = self
+            .local
+            .load_bytes_batch_with(EntryType::File, digests, move |index, bytes| {
+                let (destination, mode) = &destinations[index];
+                if let Some(parent) = destination.parent() {
+                    if created_parents.insert(parent.to_owned()) {
+                        std::fs::create_dir_all(parent).map_err(|e| {
+                            format!("Failed to create directory {}: {e}", parent.display())
+                        })?;
+                    }
+                }
+                write_file(destination, *mode, bytes)
+            })
+            .await?;
+
+        Ok(paths
+            .into_iter()
+            .zip(written)
+            .filter_map(|(path, written)| written.map(|()| path))
+            .collect())
+    }
+
     fn materialize_directory_children<'a>(
         &self,
         destination: PathBuf,
@@ -1242,6 +1582,7 @@ impl Store {
         can_hardlink: bool,
         parent_to_child: &'a HashMap<PathBuf, Vec<directory::Entry>>,
         mutable_paths: &'a BTreeSet<PathBuf>,
+        written_files: &'a HashSet<PathBuf>,
         perms: Permissions,
     ) -> BoxFuture<'a, Result<(), StoreError>> {
         let store = self.clone();
@@ -1265,7 +1606,10 @@ impl Store {
                             !force_mutable && can_hardlink && !mutable_paths.contains(&path);
 
                         match child {
+                            // Already written by `materialize_local_files_batch`.
+                            directory::Entry::File(_) if written_files.contains(&path) => Ok(()),
                             directory::Entry::File(f) => {
+                                let _permit = store.materialize.acquire().await;
                                 store
                                     .materialize_file_maybe_hardlink(
                                         path,
@@ -1277,6 +1621,7 @@ impl Store {
                                     .await
                             }
                             directory::Entry::Symlink(s) => {
+                                let _permit = store.materialize.acquire().await;
                                 store
                                     .materialize_symlink(
                                         path,
@@ -1293,6 +1638,7 @@ impl Store {
                                         can_hardlink,
                                         parent_to_child,
                                         mutable_paths,
+                                        written_files,
                                         perms,
                                     )
                                     .await
@@ -1304,7 +1650,7 @@ impl Store {
             }
 
             if perms == Permissions::ReadOnly {
-                tokio::fs::set_permissions(&destination, std::fs::Permissions::from_mode(0o555))
+                fs::platform::set_mode(&destination, 0o555)
                     .await
                     .map_err(|e| {
                         format!(
@@ -1351,47 +1697,36 @@ impl Store {
         perms: Permissions,
         is_executable: bool,
     ) -> Result<(), StoreError> {
-        let mode = match perms {
-            Permissions::ReadOnly if is_executable => 0o555,
-            Permissions::ReadOnly => 0o444,
-            Permissions::Writable if is_executable => 0o755,
-            Permissions::Writable => 0o644,
-        };
+        let mode = file_mode(perms, is_executable);
         match self.local.load_from_fs(digest).await? {
             Some(path) => {
-                tokio::fs::copy(&path, &destination).await.map_err(|e| {
-                    format!(
-                        "Error copying bytes from {} to {}: {e}",
-                        path.display(),
-                        destination.display()
+                let clone_files = self.materialize.clone_files;
+                let (src, dst) = (path.clone(), destination.clone());
+                self.local
+                    .executor()
+                    .spawn_blocking_fs(
+                        move || fs::platform::copy_file(&src, &dst, clone_files),
+                        |e| Err(std::io::Error::other(format!("Copying task failed: {e}"))),
                     )
-                })?;
-                tokio::fs::set_permissions(destination, FSPermissions::from_mode(mode))
+                    .await
+                    .map_err(|e| {
+                        format!(
+                            "Error copying bytes from {} to {}: {e}",
+                            path.display(),
+                            destination.display()
+                        )
+                    })?;
+                fs::platform::set_mode(&destination, mode)
                     .await
                     .map_err(|e| format!("Error setting permissions on {}: {e}", path.display()))?;
                 Ok(())
             }
             None => {
                 self.load_file_bytes_with(digest, move |bytes| {
-                    let mut f = OpenOptions::new()
-                        .create(true)
-                        .write(true)
-                        .truncate(true)
-                        .mode(mode)
-                        .open(&destination)
-                        .map_err(|e| {
-                            format!(
-                                "Error opening file {} for writing: {:?}",
-                                destination.display(),
-                                e
-                            )
-                        })?;
-                    f.write_all(bytes).map_err(|e| {
-                        format!("Error writing file {}: {:?}", destination.display(), e)
-                    })?;
-                    Ok(())
+                    write_file(&destination, mode, bytes)
                 })
-                .await?
+                .await??;
+                Ok(())
             }
         }
     }
@@ -1407,7 +1742,7 @@ impl Store {
         //
         // NB. #17758, #18849: this is a work-around for inaccurate management of the contents of dist/.
         for first in [true, false] {
-            match symlink(&target, &destination).await {
+            match fs::platform::symlink(&target, &destination).await {
                 Ok(()) => break,
                 Err(e) if first && e.kind() == std::io::ErrorKind::AlreadyExists => {
                     tokio::fs::remove_dir_all(&destination).await.map_err(|e| {
@@ -1638,6 +1973,32 @@ impl SnapshotOps for Store {
     }
 }
 
+fn file_mode(perms: Permissions, is_executable: bool) -> u32 {
+    match perms {
+        Permissions::ReadOnly if is_executable => 0o555,
+        Permissions::ReadOnly => 0o444,
+        Permissions::Writable if is_executable => 0o755,
+        Permissions::Writable => 0o644,
+    }
+}
+
+fn write_file(destination: &Path, mode: u32, bytes: &[u8]) -> Result<(), String> {
+    let mut f = fs::platform::open_options_with_mode(
+        OpenOptions::new().create(true).write(true).truncate(true),
+        mode,
+    )
+    .open(destination)
+    .map_err(|e| {
+        format!(
+            "Error opening file {} for writing: {:?}",
+            destination.display(),
+            e
+        )
+    })?;
+    f.write_all(bytes)
+        .map_err(|e| format!("Error writing file {}: {:?}", destination.display(), e))
+}
+
 // Only public for testing.
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
 pub enum EntryType {
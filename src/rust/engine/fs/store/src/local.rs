@@ -12,12 +12,12 @@ use async_oncecell::OnceCell;
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::future::{self, join_all, try_join, try_join_all};
+use futures::stream::{self, StreamExt};
 use hashing::{
     async_copy_and_hash, async_verified_copy, AgedFingerprint, Digest, Fingerprint, EMPTY_DIGEST,
 };
 use parking_lot::Mutex;
 use sharded_lmdb::ShardedLmdb;
-use std::os::unix::fs::PermissionsExt;
 use task_executor::Executor;
 use tempfile::Builder;
 use tokio::fs::hard_link;
@@ -31,6 +31,9 @@ use workunit_store::ObservationMetric;
 // for somewhere between 2 and 3 uses of the corresponding entry to "break even".
 const LARGE_FILE_SIZE_LIMIT: usize = 512 * 1024;
 
+/// The number of entries which are concurrently re-hashed while scrubbing a database.
+const SCRUB_CONCURRENCY: usize = 16;
+
 /// Trait for the underlying storage, which is either a ShardedLMDB or a ShardedFS.
 #[async_trait]
 trait UnderlyingByteStore {
@@ -203,7 +206,7 @@ impl ShardedFSDB {
                 .map_err(|e| format!("Failed to create directory: {e}"))?;
             let (src_file, dst_dir) = fsdb
                 .executor
-                .spawn_blocking(
+                .spawn_blocking_fs(
                     move || {
                         let src_file = Builder::new()
                             .suffix(".hardlink_canary")
@@ -289,7 +292,7 @@ impl ShardedFSDB {
             // have to worry about parent dirs.
             let named_temp_file = self
                 .executor
-                .spawn_blocking(
+                .spawn_blocking_fs(
                     move || {
                         Builder::new()
                             .suffix(".tmp")
@@ -309,7 +312,7 @@ impl ShardedFSDB {
                         .shutdown()
                         .await
                         .map_err(|e| format!("Failed to shutdown {tmp_path:?}: {e}"))?;
-                    tokio::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o555))
+                    fs::platform::set_mode(&tmp_path, 0o555)
                         .await
                         .map_err(|e| format!("Failed to set permissions on {:?}: {e}", tmp_path))?;
                     // NB: Syncing metadata to disk ensures the `hard_link` we do later has the opportunity
@@ -366,7 +369,7 @@ impl UnderlyingByteStore for ShardedFSDB {
     async fn lease(&self, fingerprint: Fingerprint) -> Result<(), String> {
         let path = self.get_path(fingerprint);
         self.executor
-            .spawn_blocking(
+            .spawn_blocking_fs(
                 move || {
                     fs_set_times::set_mtime(&path, fs_set_times::SystemTimeSpec::SymbolicNow)
                         .map_err(|e| format!("Failed to extend mtime of {path:?}: {e}"))
@@ -470,7 +473,7 @@ impl UnderlyingByteStore for ShardedFSDB {
         let root = self.root.clone();
         let expiration_time = SystemTime::now() - self.lease_time;
         self.executor
-            .spawn_blocking(
+            .spawn_blocking_fs(
                 move || {
                     let maybe_shards = std::fs::read_dir(&root);
                     let mut fingerprints = vec![];
@@ -575,6 +578,20 @@ impl ByteStore {
         Self::new_with_options(executor, path, super::LocalOptions::default())
     }
 
+    ///
+    /// Moves the LMDB databases of the store at `path` from `from_shard_count` shards into
+    /// `to_shard_count` shards: see `ShardedLmdb::reshard`. No other process may have the store
+    /// open while this runs.
+    ///
+    pub fn reshard(path: &Path, from_shard_count: u8, to_shard_count: u8) -> Result<(), String> {
+        for lmdb_root in [path.join("files"), path.join("directories")] {
+            if lmdb_root.exists() {
+                ShardedLmdb::reshard(&lmdb_root, from_shard_count, to_shard_count)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn new_with_options<P: AsRef<Path>>(
         executor: task_executor::Executor,
         path: P,
@@ -626,6 +643,27 @@ impl ByteStore {
         })
     }
 
+    ///
+    /// The number of bytes used by the databases of each type of entry. Files which are large
+    /// enough to be stored outside of the databases are not included.
+    ///
+    pub fn used_bytes(&self) -> Vec<(EntryType, Result<usize, String>)> {
+        [
+            (EntryType::File, &self.inner.file_lmdb),
+            (EntryType::Directory, &self.inner.directory_lmdb),
+        ]
+        .into_iter()
+        .map(|(entry_type, lmdb)| {
+            let used_bytes = lmdb.clone().and_then(|lmdb| lmdb.used_bytes());
+            (entry_type, used_bytes)
+        })
+        .collect()
+    }
+
+    pub(crate) fn executor(&self) -> &Executor {
+        &self.inner.file_fsdb.executor
+    }
+
     pub async fn is_hardlinkable_destination(&self, destination: &Path) -> Result<bool, String> {
         self.inner
             .file_fsdb
@@ -756,6 +794,54 @@ impl ByteStore {
         Ok(used_bytes)
     }
 
+    ///
+    /// Re-hashes the content of every entry (or of a random sample of the given fraction of the
+    /// entries), and removes any entry whose content does not match its digest, or which cannot be
+    /// read: for example, because of a torn write or a disk error.
+    ///
+    /// Returns the number of entries which were checked, and the entries which were removed.
+    ///
+    pub async fn scrub(
+        &self,
+        sample_fraction: f64,
+    ) -> Result<(usize, Vec<(EntryType, Digest)>), String> {
+        // Sample by fingerprint, with a random seed so that repeated scrubs cover different entries.
+        let seed = uuid::Uuid::new_v4().as_u128() as u64;
+        let sampled = move |fingerprint: Fingerprint| {
+            if sample_fraction >= 1.0 {
+                return true;
+            }
+            let mut prefix = [0; 8];
+            prefix.copy_from_slice(&fingerprint.0[..8]);
+            ((u64::from_le_bytes(prefix) ^ seed) as f64 / u64::MAX as f64) < sample_fraction
+        };
+
+        let file_lmdb = self.inner.file_lmdb.clone()?;
+        let directory_lmdb = self.inner.directory_lmdb.clone()?;
+        let results = [
+            (
+                EntryType::File,
+                scrub_underlying(file_lmdb.as_ref(), &sampled).await?,
+            ),
+            (
+                EntryType::Directory,
+                scrub_underlying(directory_lmdb.as_ref(), &sampled).await?,
+            ),
+            (
+                EntryType::File,
+                scrub_underlying(&self.inner.file_fsdb, &sampled).await?,
+            ),
+        ];
+
+        let mut checked = 0;
+        let mut removed = vec![];
+        for (entry_type, (db_checked, db_removed)) in results {
+            checked += db_checked;
+            removed.extend(db_removed.into_iter().map(|digest| (entry_type, digest)));
+        }
+        Ok((checked, removed))
+    }
+
     pub async fn remove(&self, entry_type: EntryType, digest: Digest) -> Result<bool, String> {
         match entry_type {
             EntryType::Directory => self.inner.directory_lmdb.clone()?.remove(digest.hash).await,
@@ -985,6 +1071,61 @@ impl ByteStore {
         Ok(result)
     }
 
+    ///
+    /// Loads a batch of digests from a consistent snapshot of the databases, without opening a
+    /// transaction per digest: see `ShardedLmdb::load_bytes_batch_with`.
+    ///
+    /// `f` is called with the index of each digest which is present, and the results are returned
+    /// in the order of the given digests. Digests which are empty or which are large enough to be
+    /// stored outside of the databases are never loaded (see `Self::load_from_fs`).
+    ///
+    pub async fn load_bytes_batch_with<
+        T: Send + 'static,
+        F: FnMut(usize, &[u8]) -> Result<T, String> + Send + 'static,
+    >(
+        &self,
+        entry_type: EntryType,
+        digests: Vec<Digest>,
+        mut f: F,
+    ) -> Result<Vec<Option<T>>, String> {
+        let (indices, lmdb_digests): (Vec<_>, Vec<_>) = digests
+            .iter()
+            .enumerate()
+            .filter(|(_, digest)| {
+                **digest != EMPTY_DIGEST
+                    && !ByteStore::should_use_fsdb(entry_type, digest.size_bytes)
+            })
+            .map(|(index, digest)| (index, *digest))
+            .unzip();
+        let lmdb = match entry_type {
+            EntryType::Directory => self.inner.directory_lmdb.clone(),
+            EntryType::File => self.inner.file_lmdb.clone(),
+        }?;
+
+        let fingerprints = lmdb_digests.iter().map(|digest| digest.hash).collect();
+        let lmdb_indices = indices.clone();
+        let lmdb_results = lmdb
+            .load_bytes_batch_with(fingerprints, move |lmdb_index, bytes| {
+                let digest = lmdb_digests[lmdb_index];
+                if bytes.len() == digest.size_bytes {
+                    f(lmdb_indices[lmdb_index], bytes)
+                } else {
+                    Err(format!(
+                        "Got hash collision reading from store - digest {digest:?} was requested, \
+                        but retrieved bytes with that fingerprint had length {}.",
+                        bytes.len()
+                    ))
+                }
+            })
+            .await?;
+
+        let mut results = digests.iter().map(|_| None).collect::<Vec<_>>();
+        for (index, result) in indices.into_iter().zip(lmdb_results) {
+            results[index] = result;
+        }
+        Ok(results)
+    }
+
     pub async fn all_digests(&self, entry_type: EntryType) -> Result<Vec<Digest>, String> {
         let lmdb = match entry_type {
             EntryType::File => self.inner.file_lmdb.clone(),
@@ -1004,3 +1145,47 @@ impl ByteStore {
         self.inner.file_fsdb.clone()
     }
 }
+
+///
+/// Re-hashes the sampled entries of the given database, and removes those whose content does not
+/// match their digest (or cannot be read). Returns the number of entries which were checked, and
+/// the digests which were removed.
+///
+async fn scrub_underlying(
+    db: &(impl UnderlyingByteStore + Sync),
+    sampled: impl Fn(Fingerprint) -> bool,
+) -> Result<(usize, Vec<Digest>), String> {
+    let digests = db
+        .all_digests()
+        .await?
+        .into_iter()
+        .filter(|digest| sampled(digest.hash))
+        .collect::<Vec<_>>();
+    let checked = digests.len();
+
+    let corrupted = stream::iter(digests)
+        .map(|digest| async move {
+            let is_corrupt = match db
+                .load_bytes_with(digest.hash, |bytes| Ok(Digest::of_bytes(bytes)))
+                .await
+            {
+                Ok(Some(actual)) => actual != digest,
+                // The entry was removed concurrently.
+                Ok(None) => false,
+                Err(e) => {
+                    log::debug!("Failed to read {digest:?} while scrubbing: {e}");
+                    true
+                }
+            };
+            is_corrupt.then_some(digest)
+        })
+        .buffer_unordered(SCRUB_CONCURRENCY)
+        .filter_map(future::ready)
+        .collect::<Vec<_>>()
+        .await;
+
+    for digest in &corrupted {
+        db.remove(digest.hash).await?;
+    }
+    Ok((checked, corrupted))
+}
@@ -81,6 +81,30 @@ async fn roundtrip_file() {
     );
 }
 
+#[tokio::test]
+async fn reshard_file() {
+    let testdata = TestData::roland();
+    let dir = TempDir::new().unwrap();
+    let new_store_with_shard_count = |shard_count| {
+        ByteStore::new_with_options(
+            task_executor::Executor::new(),
+            dir.path(),
+            LocalOptions {
+                shard_count,
+                ..LocalOptions::default()
+            },
+        )
+        .unwrap()
+    };
+
+    let hash = prime_store_with_file_bytes(&new_store_with_shard_count(1), testdata.bytes()).await;
+    ByteStore::reshard(dir.path(), 1, 4).unwrap();
+    assert_eq!(
+        load_file_bytes(&new_store_with_shard_count(4), hash).await,
+        Ok(Some(testdata.bytes()))
+    );
+}
+
 #[tokio::test]
 async fn missing_file() {
     let dir = TempDir::new().unwrap();
@@ -716,6 +740,34 @@ async fn all_digests() {
     );
 }
 
+#[tokio::test]
+async fn load_bytes_batch_with() {
+    let dir = TempDir::new().unwrap();
+    let store = new_store(dir.path());
+    let roland = prime_store_with_file_bytes(&store, TestData::roland().bytes()).await;
+    let large_testdata = TestData::new("123456789".repeat(1000 * 512).as_str());
+    let large = prime_store_with_file_bytes(&store, large_testdata.bytes()).await;
+
+    // Missing, empty, and large digests are not loaded.
+    let results = store
+        .load_bytes_batch_with(
+            EntryType::File,
+            vec![
+                TestData::catnip().digest(),
+                roland,
+                TestData::empty().digest(),
+                large,
+            ],
+            |index, bytes| Ok((index, Bytes::copy_from_slice(bytes))),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        results,
+        vec![None, Some((1, TestData::roland().bytes())), None, None]
+    );
+}
+
 #[tokio::test]
 async fn get_missing_digests() {
     let dir = TempDir::new().unwrap();
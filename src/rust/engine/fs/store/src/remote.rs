@@ -2,23 +2,37 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 use std::collections::HashSet;
 use std::fmt;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Instant;
 
+use async_trait::async_trait;
 use bytes::Bytes;
 use futures::Future;
-use hashing::Digest;
+use hashing::{Digest, Hasher};
 use log::Level;
+use parking_lot::Mutex;
 use remote_provider::{
-    choose_byte_store_provider, ByteStoreProvider, LoadDestination, RemoteStoreOptions,
+    choose_byte_store_provider, ByteStoreProvider, LoadDestination, ProviderError,
+    RemoteStoreOptions,
 };
 use tokio::fs::File;
-use workunit_store::{in_workunit, Metric, ObservationMetric};
+use tokio::io::AsyncWrite;
+use workunit_store::{
+    in_workunit, Metric, ObservationMetric, TransferDirection, TransferProgress, WaitingOn,
+};
+
+use crate::bandwidth::{current_transfer_priority, BandwidthLimits};
 
 #[derive(Clone)]
 pub struct ByteStore {
     instance_name: Option<String>,
     provider: Arc<dyn ByteStoreProvider>,
+    limits: BandwidthLimits,
+    /// Digests which the remote store served with the wrong content (even when retried). They are
+    /// treated as missing for the lifetime of the ByteStore, until they are next stored.
+    quarantined: Arc<Mutex<HashSet<Digest>>>,
 }
 
 impl fmt::Debug for ByteStore {
@@ -35,9 +49,18 @@ impl ByteStore {
         ByteStore {
             instance_name,
             provider,
+            limits: BandwidthLimits::default(),
+            quarantined: Arc::default(),
         }
     }
 
+    ///
+    /// Limits the rates of the transfers of this ByteStore (and its clones) to the given limits.
+    ///
+    pub fn with_bandwidth_limits(self, limits: BandwidthLimits) -> ByteStore {
+        ByteStore { limits, ..self }
+    }
+
     pub async fn from_options(options: RemoteStoreOptions) -> Result<ByteStore, String> {
         let instance_name = options.instance_name.clone();
         let provider = choose_byte_store_provider(options).await?;
@@ -45,14 +68,14 @@ impl ByteStore {
     }
 
     /// Store the bytes readable from `file` into the remote store
-    pub async fn store_file(&self, digest: Digest, file: File) -> Result<(), String> {
+    pub async fn store_file(&self, digest: Digest, file: File) -> Result<(), ProviderError> {
         self.store_tracking("store", digest, || self.provider.store_file(digest, file))
             .await
     }
 
     /// Store the bytes in `bytes` into the remote store, as an optimisation of `store_file` when the
     /// bytes are already in memory
-    pub async fn store_bytes(&self, bytes: Bytes) -> Result<(), String> {
+    pub async fn store_bytes(&self, bytes: Bytes) -> Result<(), ProviderError> {
         let digest = Digest::of_bytes(&bytes);
         self.store_tracking("store_bytes", digest, || {
             self.provider.store_bytes(digest, bytes)
@@ -65,10 +88,10 @@ impl ByteStore {
         workunit: &'static str,
         digest: Digest,
         do_store: DoStore,
-    ) -> Result<(), String>
+    ) -> Result<(), ProviderError>
     where
         DoStore: FnOnce() -> Fut + Send,
-        Fut: Future<Output = Result<(), String>> + Send,
+        Fut: Future<Output = Result<(), ProviderError>> + Send,
     {
         in_workunit!(
             workunit,
@@ -76,7 +99,29 @@ impl ByteStore {
             desc = Some(format!("Storing {digest:?}")),
             |workunit| async move {
                 workunit.increment_counter(Metric::RemoteStoreWriteAttempts, 1);
-                let result = do_store().await;
+                // NB: Providers may report incremental progress for streaming writes (via
+                // `transfer_progress_if_in_workunit`), but otherwise the transfer completes all at
+                // once.
+                let progress = TransferProgress::new(
+                    TransferDirection::Upload,
+                    Some(digest.size_bytes as u64),
+                );
+                workunit.track_transfer(&progress);
+                if let Some(limiter) = &self.limits.upload {
+                    let _waiting_token = workunit.waiting_on(WaitingOn::Semaphore);
+                    limiter
+                        .acquire(digest.size_bytes, current_transfer_priority())
+                        .await;
+                }
+                let result = {
+                    let _waiting_token = workunit.waiting_on(WaitingOn::RemoteRpc);
+                    do_store().await
+                };
+                if result.is_ok() {
+                    progress.complete();
+                    // The remote store now has the correct content.
+                    self.quarantined.lock().remove(&digest);
+                }
 
                 let result_metric = match result {
                     Ok(()) => {
@@ -84,6 +129,10 @@ impl ByteStore {
                             ObservationMetric::RemoteStoreBlobBytesUploaded,
                             digest.size_bytes as u64,
                         );
+                        workunit.increment_counter(
+                            Metric::RemoteStoreBytesUploaded,
+                            digest.size_bytes as u64,
+                        );
                         Metric::RemoteStoreWriteSuccesses
                     }
                     Err(_) => Metric::RemoteStoreWriteErrors,
@@ -100,7 +149,8 @@ impl ByteStore {
         &self,
         digest: Digest,
         destination: &mut dyn LoadDestination,
-    ) -> Result<bool, String> {
+        progress: TransferProgress,
+    ) -> Result<bool, ProviderError> {
         let start = Instant::now();
         let workunit_desc = format!(
             "Loading bytes at: {} {} ({} bytes)",
@@ -115,7 +165,17 @@ impl ByteStore {
             desc = Some(workunit_desc),
             |workunit| async move {
                 workunit.increment_counter(Metric::RemoteStoreReadAttempts, 1);
-                let result = self.provider.load(digest, destination).await;
+                workunit.track_transfer(&progress);
+                if let Some(limiter) = &self.limits.download {
+                    let _waiting_token = workunit.waiting_on(WaitingOn::Semaphore);
+                    limiter
+                        .acquire(digest.size_bytes, current_transfer_priority())
+                        .await;
+                }
+                let result = {
+                    let _waiting_token = workunit.waiting_on(WaitingOn::RemoteRpc);
+                    self.provider.load(digest, destination).await
+                };
                 workunit.record_observation(
                     ObservationMetric::RemoteStoreReadBlobTimeMicros,
                     start.elapsed().as_micros() as u64,
@@ -126,6 +186,10 @@ impl ByteStore {
                             ObservationMetric::RemoteStoreBlobBytesDownloaded,
                             digest.size_bytes as u64,
                         );
+                        workunit.increment_counter(
+                            Metric::RemoteStoreBytesDownloaded,
+                            digest.size_bytes as u64,
+                        );
                         Metric::RemoteStoreReadCached
                     }
                     Ok(false) => Metric::RemoteStoreReadUncached,
@@ -139,20 +203,63 @@ impl ByteStore {
         .await
     }
 
+    ///
+    /// Loads the content of `digest` into `destination`, verifying it as it is streamed.
+    ///
+    /// If the remote store serves content which does not match the digest, the load is retried
+    /// once. If the content still does not match, the load fails and the digest is quarantined:
+    /// later loads report it as missing (so that it will be recomputed rather than trusted), and
+    /// `list_missing_digests` reports it as missing (so that the next store of it replaces the bad
+    /// copy on the server).
+    ///
     async fn load<W: LoadDestination>(
         &self,
         digest: Digest,
-        mut destination: W,
-    ) -> Result<Option<W>, String> {
-        if self.load_monomorphic(digest, &mut destination).await? {
-            Ok(Some(destination))
-        } else {
-            Ok(None)
+        destination: W,
+    ) -> Result<Option<W>, ProviderError> {
+        if self.quarantined.lock().contains(&digest) {
+            return Ok(None);
+        }
+
+        let progress =
+            TransferProgress::new(TransferDirection::Download, Some(digest.size_bytes as u64));
+        let mut destination = VerifyingDestination::new(
+            digest,
+            ProgressDestination {
+                inner: destination,
+                progress: progress.clone(),
+            },
+        );
+        let mut retried = false;
+        loop {
+            let loaded = self
+                .load_monomorphic(digest, &mut destination, progress.clone())
+                .await;
+            if loaded == Ok(false) {
+                return Ok(None);
+            }
+            // NB: A failed load may have been caused by mismatched content (whether detected by the
+            // destination or by the provider), so it is verified too.
+            let Err(mismatch) = destination.verify(loaded.is_ok()) else {
+                return loaded.map(|_| Some(destination.inner.inner));
+            };
+            workunit_store::increment_counter_if_in_workunit(Metric::RemoteStoreReadMismatches, 1);
+
+            if retried {
+                self.quarantined.lock().insert(digest);
+                return Err(mismatch.into());
+            }
+            log::warn!("{mismatch}: retrying.");
+            retried = true;
+            destination
+                .reset()
+                .await
+                .map_err(|e| format!("Failed to reset destination to retry {digest:?}: {e}"))?;
         }
     }
 
     /// Load the data for `digest` (if it exists in the remote store) into memory.
-    pub async fn load_bytes(&self, digest: Digest) -> Result<Option<Bytes>, String> {
+    pub async fn load_bytes(&self, digest: Digest) -> Result<Option<Bytes>, ProviderError> {
         let result = self
             .load(digest, Vec::with_capacity(digest.size_bytes))
             .await?;
@@ -164,7 +271,7 @@ impl ByteStore {
         &self,
         digest: Digest,
         file: tokio::fs::File,
-    ) -> Result<Option<tokio::fs::File>, String> {
+    ) -> Result<Option<tokio::fs::File>, ProviderError> {
         self.load(digest, file).await
     }
 
@@ -172,17 +279,169 @@ impl ByteStore {
     /// Given a collection of Digests (digests),
     /// returns the set of digests from that collection not present in the CAS.
     ///
-    pub async fn list_missing_digests<I>(&self, digests: I) -> Result<HashSet<Digest>, String>
+    pub async fn list_missing_digests<I>(
+        &self,
+        digests: I,
+    ) -> Result<HashSet<Digest>, ProviderError>
     where
         I: IntoIterator<Item = Digest>,
         I::IntoIter: Send,
     {
         let mut iter = digests.into_iter();
+        let quarantined = self.quarantined.lock().clone();
         in_workunit!(
             "list_missing_digests",
             Level::Trace,
-            |_workunit| async move { self.provider.list_missing_digests(&mut iter).await }
+            |_workunit| async move {
+                if quarantined.is_empty() {
+                    return self.provider.list_missing_digests(&mut iter).await;
+                }
+                // Quarantined digests are reported as missing even if the remote store has (bad
+                // content for) them, so that they are stored again.
+                let digests = iter.collect::<Vec<_>>();
+                let mut missing = self
+                    .provider
+                    .list_missing_digests(&mut digests.iter().copied())
+                    .await?;
+                missing.extend(
+                    digests
+                        .into_iter()
+                        .filter(|digest| quarantined.contains(digest)),
+                );
+                Ok(missing)
+            }
         )
         .await
     }
 }
+
+///
+/// A LoadDestination which hashes the bytes written to it in order to verify them against an
+/// expected digest, and which rejects writes beyond the expected size.
+///
+struct VerifyingDestination<W> {
+    inner: W,
+    digest: Digest,
+    hasher: Hasher,
+    written: usize,
+    overflowed: bool,
+}
+
+impl<W> VerifyingDestination<W> {
+    fn new(digest: Digest, inner: W) -> Self {
+        Self {
+            inner,
+            digest,
+            hasher: Hasher::new(),
+            written: 0,
+            overflowed: false,
+        }
+    }
+
+    fn overflow_error(&self) -> String {
+        format!(
+            "Remote CAS gave more than the expected {} bytes for {:?}",
+            self.digest.size_bytes, self.digest
+        )
+    }
+
+    ///
+    /// Verifies that the bytes which were written match the expected digest. Unless the load
+    /// `completed`, it might have been interrupted, and so the bytes are only verified if there
+    /// were too many of them, or if all of them were written.
+    ///
+    fn verify(&mut self, completed: bool) -> Result<(), String> {
+        if self.overflowed {
+            return Err(self.overflow_error());
+        }
+        if !completed && self.written < self.digest.size_bytes {
+            return Ok(());
+        }
+        let actual = std::mem::replace(&mut self.hasher, Hasher::new()).finish();
+        if actual != self.digest {
+            return Err(format!(
+                "Remote CAS gave wrong digest: expected {:?}, got {actual:?}",
+                self.digest
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<W: LoadDestination> AsyncWrite for VerifyingDestination<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        if self.written + buf.len() > self.digest.size_bytes {
+            self.overflowed = true;
+            return Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                self.overflow_error(),
+            )));
+        }
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = result {
+            self.hasher.update(&buf[..written]);
+            self.written += written;
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[async_trait]
+impl<W: LoadDestination> LoadDestination for VerifyingDestination<W> {
+    async fn reset(&mut self) -> std::io::Result<()> {
+        self.hasher = Hasher::new();
+        self.written = 0;
+        self.overflowed = false;
+        self.inner.reset().await
+    }
+}
+
+///
+/// A LoadDestination which reports the bytes written to it to a TransferProgress.
+///
+struct ProgressDestination<W> {
+    inner: W,
+    progress: TransferProgress,
+}
+
+impl<W: LoadDestination> AsyncWrite for ProgressDestination<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = result {
+            self.progress.add_bytes(written as u64);
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[async_trait]
+impl<W: LoadDestination> LoadDestination for ProgressDestination<W> {
+    async fn reset(&mut self) -> std::io::Result<()> {
+        self.progress.reset();
+        self.inner.reset().await
+    }
+}
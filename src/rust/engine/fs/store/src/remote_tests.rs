@@ -8,7 +8,9 @@ use bytes::Bytes;
 use grpc_util::tls;
 use hashing::{Digest, Fingerprint};
 use parking_lot::Mutex;
-use remote_provider::{ByteStoreProvider, LoadDestination, RemoteProvider, RemoteStoreOptions};
+use remote_provider::{
+    ByteStoreProvider, LoadDestination, ProviderError, RemoteProvider, RemoteStoreOptions,
+};
 use tempfile::TempDir;
 use testutil::data::TestData;
 use testutil::file::mk_tempfile;
@@ -37,9 +39,11 @@ async fn smoke_test_from_options_reapi_provider() {
         headers: BTreeMap::new(),
         chunk_size_bytes: 10 * MEGABYTES,
         timeout: Duration::from_secs(5),
+        stream_timeout: Duration::from_secs(5),
         retries: 1,
         concurrency_limit: 256,
         batch_api_size_limit: crate::tests::STORE_BATCH_API_SIZE_LIMIT,
+        circuit_breaker: None,
     })
     .await
     .unwrap();
@@ -86,9 +90,11 @@ async fn smoke_test_from_options_file_provider() {
         headers: BTreeMap::new(),
         chunk_size_bytes: 10 * MEGABYTES,
         timeout: Duration::from_secs(5),
+        stream_timeout: Duration::from_secs(5),
         retries: 1,
         concurrency_limit: 256,
         batch_api_size_limit: crate::tests::STORE_BATCH_API_SIZE_LIMIT,
+        circuit_breaker: None,
     })
     .await
     .unwrap();
@@ -150,6 +156,73 @@ async fn load_bytes_provider_error() {
     assert_error(store.load_bytes(TestData::roland().digest()).await);
 }
 
+#[tokio::test]
+async fn load_bytes_retries_wrong_content() {
+    let _ = WorkunitStore::setup_for_tests();
+    let testdata = TestData::roland();
+    let (store, provider) = empty_byte_store();
+    provider.add(testdata.bytes());
+    provider.corrupt_next_loads(TestData::catnip().bytes(), 1);
+
+    assert_eq!(
+        store.load_bytes(testdata.digest()).await,
+        Ok(Some(testdata.bytes()))
+    );
+}
+
+#[tokio::test]
+async fn load_bytes_quarantines_wrong_content() {
+    let _ = WorkunitStore::setup_for_tests();
+    let testdata = TestData::roland();
+    let (store, provider) = empty_byte_store();
+    provider.add(testdata.bytes());
+    // The same size as the content, but different.
+    provider.corrupt_next_loads(Bytes::from_static(b"European Burmesf"), 2);
+
+    let error = store
+        .load_bytes(testdata.digest())
+        .await
+        .expect_err("Want error");
+    assert!(
+        error.to_string().contains("Remote CAS gave wrong digest"),
+        "Bad error message, got: {error}"
+    );
+
+    // Once quarantined, the digest is missing until it is stored again.
+    assert_eq!(store.load_bytes(testdata.digest()).await, Ok(None));
+    assert_eq!(
+        store.list_missing_digests(vec![testdata.digest()]).await,
+        Ok(HashSet::from([testdata.digest()]))
+    );
+    assert_eq!(store.store_bytes(testdata.bytes()).await, Ok(()));
+    assert_eq!(
+        store.load_bytes(testdata.digest()).await,
+        Ok(Some(testdata.bytes()))
+    );
+}
+
+#[tokio::test]
+async fn load_file_rejects_oversized_content() {
+    let _ = WorkunitStore::setup_for_tests();
+    let testdata = TestData::roland();
+    let (store, provider) = empty_byte_store();
+    provider.add(testdata.bytes());
+    provider.corrupt_next_loads(Bytes::from_static(b"European Burmese cats"), 2);
+
+    let file = mk_tempfile(None).await;
+
+    let error = store
+        .load_file(testdata.digest(), file)
+        .await
+        .expect_err("Want error");
+    assert!(
+        error
+            .to_string()
+            .contains("Remote CAS gave more than the expected"),
+        "Bad error message, got: {error}"
+    );
+}
+
 #[tokio::test]
 async fn load_file_existing() {
     // 5MB of data
@@ -324,16 +397,20 @@ async fn assert_file_contents(mut file: tokio::fs::File, expected: &str) {
     assert!(buf == expected);
 }
 
-fn assert_error<T: std::fmt::Debug>(result: Result<T, String>) {
+fn assert_error<T: std::fmt::Debug>(result: Result<T, ProviderError>) {
     let error = result.expect_err("Want error");
     assert!(
-        error.contains("AlwaysErrorProvider always fails"),
+        error
+            .to_string()
+            .contains("AlwaysErrorProvider always fails"),
         "Bad error message, got: {error}"
     );
 }
 
 struct TestProvider {
     blobs: Mutex<HashMap<Fingerprint, Bytes>>,
+    /// Content which is served (instead of the stored content) by the next loads.
+    corrupt_loads: Mutex<Vec<Bytes>>,
 }
 
 impl TestProvider {
@@ -341,9 +418,14 @@ impl TestProvider {
     fn new() -> Arc<TestProvider> {
         Arc::new(TestProvider {
             blobs: Mutex::new(HashMap::new()),
+            corrupt_loads: Mutex::new(Vec::new()),
         })
     }
 
+    fn corrupt_next_loads(&self, bytes: Bytes, count: usize) {
+        *self.corrupt_loads.lock() = vec![bytes; count];
+    }
+
     #[allow(dead_code)]
     fn add(&self, bytes: Bytes) {
         self.blobs
@@ -354,12 +436,12 @@ impl TestProvider {
 
 #[async_trait::async_trait]
 impl ByteStoreProvider for TestProvider {
-    async fn store_bytes(&self, digest: Digest, bytes: Bytes) -> Result<(), String> {
+    async fn store_bytes(&self, digest: Digest, bytes: Bytes) -> Result<(), ProviderError> {
         self.blobs.lock().insert(digest.hash, bytes);
         Ok(())
     }
 
-    async fn store_file(&self, digest: Digest, mut file: File) -> Result<(), String> {
+    async fn store_file(&self, digest: Digest, mut file: File) -> Result<(), ProviderError> {
         // just pull it all into memory
         let mut bytes = Vec::new();
         file.read_to_end(&mut bytes).await.unwrap();
@@ -371,12 +453,16 @@ impl ByteStoreProvider for TestProvider {
         &self,
         digest: Digest,
         destination: &mut dyn LoadDestination,
-    ) -> Result<bool, String> {
+    ) -> Result<bool, ProviderError> {
         let bytes = self.blobs.lock().get(&digest.hash).cloned();
         match bytes {
             None => Ok(false),
             Some(bytes) => {
-                destination.write_all(&bytes).await.unwrap();
+                let bytes = self.corrupt_loads.lock().pop().unwrap_or(bytes);
+                destination
+                    .write_all(&bytes)
+                    .await
+                    .map_err(|e| e.to_string())?;
                 Ok(true)
             }
         }
@@ -385,7 +471,7 @@ impl ByteStoreProvider for TestProvider {
     async fn list_missing_digests(
         &self,
         digests: &mut (dyn Iterator<Item = Digest> + Send),
-    ) -> Result<HashSet<Digest>, String> {
+    ) -> Result<HashSet<Digest>, ProviderError> {
         let blobs = self.blobs.lock();
         Ok(digests.filter(|d| !blobs.contains_key(&d.hash)).collect())
     }
@@ -399,22 +485,22 @@ impl AlwaysErrorProvider {
 }
 #[async_trait::async_trait]
 impl ByteStoreProvider for AlwaysErrorProvider {
-    async fn store_bytes(&self, _: Digest, _: Bytes) -> Result<(), String> {
-        Err("AlwaysErrorProvider always fails".to_owned())
+    async fn store_bytes(&self, _: Digest, _: Bytes) -> Result<(), ProviderError> {
+        Err("AlwaysErrorProvider always fails".to_owned().into())
     }
 
-    async fn store_file(&self, _: Digest, _: File) -> Result<(), String> {
-        Err("AlwaysErrorProvider always fails".to_owned())
+    async fn store_file(&self, _: Digest, _: File) -> Result<(), ProviderError> {
+        Err("AlwaysErrorProvider always fails".to_owned().into())
     }
 
-    async fn load(&self, _: Digest, _: &mut dyn LoadDestination) -> Result<bool, String> {
-        Err("AlwaysErrorProvider always fails".to_owned())
+    async fn load(&self, _: Digest, _: &mut dyn LoadDestination) -> Result<bool, ProviderError> {
+        Err("AlwaysErrorProvider always fails".to_owned().into())
     }
 
     async fn list_missing_digests(
         &self,
         _: &mut (dyn Iterator<Item = Digest> + Send),
-    ) -> Result<HashSet<Digest>, String> {
-        Err("AlwaysErrorProvider always fails".to_owned())
+    ) -> Result<HashSet<Digest>, ProviderError> {
+        Err("AlwaysErrorProvider always fails".to_owned().into())
     }
 }
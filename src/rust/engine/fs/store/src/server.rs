@@ -0,0 +1,521 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::collections::HashSet;
+use std::io::SeekFrom;
+use std::net::SocketAddr;
+use std::pin::Pin;
+
+use bytes::{Bytes, BytesMut};
+use engine_error::ErrorCategory;
+use futures::{FutureExt, Stream, StreamExt};
+use grpc_util::hyper_util::AddrIncomingWithStream;
+use grpc_util::resource_name::{parse_read_resource_name, parse_write_resource_name};
+use hashing::{Digest, Fingerprint};
+use protos::gen::build::bazel::remote::execution::v2 as remexec;
+use protos::gen::build::bazel::semver::SemVer;
+use protos::gen::google::bytestream::byte_stream_server::{ByteStream, ByteStreamServer};
+use protos::gen::google::bytestream::{
+    QueryWriteStatusRequest, QueryWriteStatusResponse, ReadRequest, ReadResponse, WriteRequest,
+    WriteResponse,
+};
+use remexec::capabilities_server::{Capabilities, CapabilitiesServer};
+use remexec::content_addressable_storage_server::{
+    ContentAddressableStorage, ContentAddressableStorageServer,
+};
+use remexec::{
+    BatchReadBlobsRequest, BatchReadBlobsResponse, BatchUpdateBlobsRequest,
+    BatchUpdateBlobsResponse, CacheCapabilities, FindMissingBlobsRequest, FindMissingBlobsResponse,
+    GetCapabilitiesRequest, GetTreeRequest, GetTreeResponse, ServerCapabilities,
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::oneshot;
+use tokio_util::io::ReaderStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::{local, EntryType, Store, StoreError};
+
+/// The size of the chunks in which blobs are streamed to ByteStream readers.
+const READ_CHUNK_SIZE_BYTES: usize = 1024 * 1024;
+
+/// The largest total size of the blobs in a batch request that clients are told to send.
+const MAX_BATCH_TOTAL_SIZE_BYTES: i64 = 4 * 1024 * 1024;
+
+///
+/// A server which exposes a Store over the REAPI ByteStream and ContentAddressableStorage gRPC
+/// services on a localhost socket, so that other tools on the machine (such as a local Bazel, or
+/// a sandboxer process) can read and write blobs without downloading them a second time.
+///
+/// Reads are served from the local store, backfilling from the Store's remote (if any) on a miss.
+/// Writes are stored locally as files: the server cannot tell whether an uploaded blob is a
+/// Directory, and so uploaded Directories are only visible to the Store as file content.
+///
+/// The server stops when it is dropped.
+///
+pub struct StoreServer {
+    local_addr: SocketAddr,
+    shutdown_sender: Option<oneshot::Sender<()>>,
+}
+
+impl StoreServer {
+    ///
+    /// Starts serving the given Store on `127.0.0.1:{port}`, or on an arbitrary free port if `port`
+    /// is 0. Requests must use the given instance name (or no instance name, if it is None).
+    ///
+    pub fn start(
+        executor: &task_executor::Executor,
+        store: Store,
+        instance_name: Option<String>,
+        port: u16,
+    ) -> Result<StoreServer, String> {
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let incoming = executor
+            .enter_io(|| hyper::server::conn::AddrIncoming::bind(&addr))
+            .map_err(|e| format!("Failed to bind local store server to {addr}: {e}"))?;
+        let local_addr = incoming.local_addr();
+        let incoming = AddrIncomingWithStream(incoming);
+
+        let responder = StoreResponder {
+            store,
+            instance_name: instance_name.unwrap_or_default(),
+        };
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel();
+        executor.native_spawn_io(async move {
+            let result = Server::builder()
+                .add_service(ByteStreamServer::new(responder.clone()))
+                .add_service(ContentAddressableStorageServer::new(responder.clone()))
+                .add_service(CapabilitiesServer::new(responder))
+                .serve_with_incoming_shutdown(incoming, shutdown_receiver.map(drop))
+                .await;
+            if let Err(e) = result {
+                log::warn!("Local store server on {local_addr} failed: {e}");
+            }
+        });
+        log::debug!("Serving the local store on {local_addr}.");
+
+        Ok(StoreServer {
+            local_addr,
+            shutdown_sender: Some(shutdown_sender),
+        })
+    }
+
+    ///
+    /// The address on which this server is listening over insecure HTTP transport.
+    ///
+    pub fn address(&self) -> String {
+        format!("http://{}", self.local_addr)
+    }
+}
+
+impl Drop for StoreServer {
+    fn drop(&mut self) {
+        if let Some(shutdown_sender) = self.shutdown_sender.take() {
+            let _ = shutdown_sender.send(());
+        }
+    }
+}
+
+#[derive(Clone)]
+struct StoreResponder {
+    store: Store,
+    instance_name: String,
+}
+
+fn store_error_to_status(err: StoreError) -> Status {
+    match err {
+        StoreError::MissingDigest(..) => Status::not_found(err.to_string()),
+        StoreError::Classified(e) => match e.category() {
+            ErrorCategory::User => Status::invalid_argument(e.to_string()),
+            ErrorCategory::Infra => Status::unavailable(e.to_string()),
+            ErrorCategory::Internal => Status::internal(e.to_string()),
+        },
+        StoreError::Unclassified(msg) => Status::internal(msg),
+    }
+}
+
+fn to_rpc_status(status: Status) -> protos::gen::google::rpc::Status {
+    protos::gen::google::rpc::Status {
+        code: status.code() as i32,
+        message: status.message().to_string(),
+        ..protos::gen::google::rpc::Status::default()
+    }
+}
+
+fn require_digest(digest: Option<&remexec::Digest>) -> Result<Digest, Status> {
+    protos::require_digest(digest).map_err(Status::invalid_argument)
+}
+
+fn check_digest(expected: Digest, actual: Digest) -> Result<(), Status> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(Status::invalid_argument(format!(
+            "Content did not match digest: expected {expected:?}, but got {actual:?}"
+        )))
+    }
+}
+
+impl StoreResponder {
+    fn check_instance_name(&self, instance_name: &str) -> Result<(), Status> {
+        if instance_name == self.instance_name {
+            Ok(())
+        } else {
+            Err(Status::not_found(format!(
+                "Instance {instance_name} does not exist"
+            )))
+        }
+    }
+
+    ///
+    /// Loads the given blob, which may be either a file or a Directory, backfilling it from the
+    /// remote if it is not present locally.
+    ///
+    async fn load(&self, digest: Digest) -> Result<Bytes, Status> {
+        let entry_type = self
+            .store
+            .local
+            .entry_type(digest.hash)
+            .await
+            .map_err(Status::internal)?
+            .unwrap_or(EntryType::File);
+        self.store
+            .load_bytes_with(
+                entry_type,
+                digest,
+                |bytes| Ok(Bytes::copy_from_slice(bytes)),
+                None,
+            )
+            .await
+            .map_err(store_error_to_status)
+    }
+
+    ///
+    /// Stores the given bytes locally as a file, after validating that they match the given
+    /// Digest.
+    ///
+    async fn store_bytes(&self, digest: Digest, bytes: Bytes) -> Result<(), Status> {
+        check_digest(digest, Digest::of_bytes(&bytes))?;
+        self.store
+            .store_file_bytes(bytes, true)
+            .await
+            .map_err(Status::internal)?;
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl ByteStream for StoreResponder {
+    type ReadStream = Pin<Box<dyn Stream<Item = Result<ReadResponse, Status>> + Send + 'static>>;
+
+    async fn read(
+        &self,
+        request: Request<ReadRequest>,
+    ) -> Result<Response<Self::ReadStream>, Status> {
+        let request = request.into_inner();
+        let resource_name = parse_read_resource_name(&request.resource_name).map_err(|err| {
+            Status::invalid_argument(format!("Failed to parse resource name: {err}"))
+        })?;
+        self.check_instance_name(resource_name.instance_name)?;
+        let fingerprint = Fingerprint::from_hex_string(resource_name.hash).map_err(|e| {
+            Status::invalid_argument(format!("Bad digest {}: {e}", resource_name.hash))
+        })?;
+        let digest = Digest::new(fingerprint, resource_name.size);
+
+        if request.read_offset < 0 || request.read_offset as usize > digest.size_bytes {
+            return Err(Status::out_of_range(format!(
+                "Read offset {} is out of range for {digest:?}",
+                request.read_offset
+            )));
+        }
+        if request.read_limit < 0 {
+            return Err(Status::invalid_argument("Read limit must not be negative"));
+        }
+        let offset = request.read_offset as usize;
+        let limit = if request.read_limit == 0 {
+            digest.size_bytes - offset
+        } else {
+            (request.read_limit as usize).min(digest.size_bytes - offset)
+        };
+
+        // Large files are stored outside of the databases: stream them from disk rather than
+        // loading them into memory.
+        if local::ByteStore::should_use_fsdb(EntryType::File, digest.size_bytes) {
+            if let Some(path) = self
+                .store
+                .local
+                .load_from_fs(digest)
+                .await
+                .map_err(Status::internal)?
+            {
+                let mut file = tokio::fs::File::open(&path).await.map_err(|e| {
+                    Status::internal(format!("Failed to open {}: {e}", path.display()))
+                })?;
+                file.seek(SeekFrom::Start(offset as u64))
+                    .await
+                    .map_err(|e| Status::internal(format!("Failed to seek: {e}")))?;
+                let stream =
+                    ReaderStream::with_capacity(file.take(limit as u64), READ_CHUNK_SIZE_BYTES)
+                        .map(|chunk| match chunk {
+                            Ok(data) => Ok(ReadResponse { data }),
+                            Err(e) => Err(Status::internal(format!("Failed to read: {e}"))),
+                        });
+                return Ok(Response::new(Box::pin(stream)));
+            }
+        }
+
+        let bytes = self.load(digest).await?.slice(offset..offset + limit);
+        let chunks = (0..bytes.len())
+            .step_by(READ_CHUNK_SIZE_BYTES)
+            .map(|start| {
+                let end = (start + READ_CHUNK_SIZE_BYTES).min(bytes.len());
+                Ok(ReadResponse {
+                    data: bytes.slice(start..end),
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(Response::new(Box::pin(futures::stream::iter(chunks))))
+    }
+
+    async fn write(
+        &self,
+        request: Request<tonic::Streaming<WriteRequest>>,
+    ) -> Result<Response<WriteResponse>, Status> {
+        let mut stream = request.into_inner();
+
+        let first = match stream.next().await {
+            Some(req) => req?,
+            None => return Err(Status::invalid_argument("Stream saw no messages")),
+        };
+        let resource_name = first.resource_name.clone();
+        let parsed = parse_write_resource_name(&resource_name).map_err(|err| {
+            Status::invalid_argument(format!("Failed to parse resource name: {err}"))
+        })?;
+        self.check_instance_name(parsed.instance_name)?;
+        let fingerprint = Fingerprint::from_hex_string(parsed.hash).map_err(|e| {
+            Status::invalid_argument(format!(
+                "Bad fingerprint in resource name: {}: {e}",
+                parsed.hash
+            ))
+        })?;
+        let digest = Digest::new(fingerprint, parsed.size);
+
+        // Large blobs are spooled to a temporary file, which the Store will then ingest.
+        let mut spool = if local::ByteStore::should_use_fsdb(EntryType::File, digest.size_bytes) {
+            let tmp = tempfile::NamedTempFile::new()
+                .map_err(|e| Status::internal(format!("Failed to create temporary file: {e}")))?;
+            let file = tmp
+                .reopen()
+                .map_err(|e| Status::internal(format!("Failed to open temporary file: {e}")))?;
+            Some((tmp, tokio::fs::File::from_std(file)))
+        } else {
+            None
+        };
+        let mut bytes = BytesMut::new();
+
+        let mut next_offset = 0;
+        let mut finished = false;
+        let mut req = first;
+        loop {
+            if req.resource_name != resource_name && !req.resource_name.is_empty() {
+                return Err(Status::invalid_argument(format!(
+                    "All resource names in stream must be the same. Got {} but earlier saw {}",
+                    req.resource_name, resource_name
+                )));
+            }
+            if finished {
+                return Err(Status::invalid_argument(
+                    "Received data after the write was finished",
+                ));
+            }
+            if req.write_offset != next_offset {
+                return Err(Status::invalid_argument(format!(
+                    "Missing chunk. Expected next offset {next_offset}, got next offset: {}",
+                    req.write_offset
+                )));
+            }
+            next_offset += req.data.len() as i64;
+            if next_offset as usize > digest.size_bytes {
+                return Err(Status::invalid_argument(format!(
+                    "Received more than the {} bytes declared in the resource name",
+                    digest.size_bytes
+                )));
+            }
+            match spool {
+                Some((_, ref mut file)) => file.write_all(&req.data).await.map_err(|e| {
+                    Status::internal(format!("Failed to write temporary file: {e}"))
+                })?,
+                None => bytes.extend_from_slice(&req.data),
+            }
+            finished = req.finish_write;
+
+            req = match stream.next().await {
+                Some(req) => req?,
+                None => break,
+            };
+        }
+
+        if next_offset as usize != digest.size_bytes {
+            return Err(Status::invalid_argument(format!(
+                "Size was incorrect: resource name said size={} but got {next_offset}",
+                digest.size_bytes
+            )));
+        }
+
+        match spool {
+            Some((tmp, mut file)) => {
+                file.flush().await.map_err(|e| {
+                    Status::internal(format!("Failed to write temporary file: {e}"))
+                })?;
+                let actual_digest = self
+                    .store
+                    .store_file(true, true, tmp.path().to_owned())
+                    .await
+                    .map_err(Status::internal)?;
+                check_digest(digest, actual_digest)?;
+            }
+            None => self.store_bytes(digest, bytes.freeze()).await?,
+        }
+
+        Ok(Response::new(WriteResponse {
+            committed_size: digest.size_bytes as i64,
+        }))
+    }
+
+    async fn query_write_status(
+        &self,
+        _: Request<QueryWriteStatusRequest>,
+    ) -> Result<Response<QueryWriteStatusResponse>, Status> {
+        Err(Status::unimplemented(
+            "Resumable writes are not supported by the local store server.",
+        ))
+    }
+}
+
+#[tonic::async_trait]
+impl ContentAddressableStorage for StoreResponder {
+    async fn find_missing_blobs(
+        &self,
+        request: Request<FindMissingBlobsRequest>,
+    ) -> Result<Response<FindMissingBlobsResponse>, Status> {
+        let request = request.into_inner();
+        self.check_instance_name(&request.instance_name)?;
+
+        let digests = request
+            .blob_digests
+            .iter()
+            .map(|d| require_digest(Some(d)))
+            .collect::<Result<HashSet<_>, _>>()?;
+        // A blob is missing only if it is present as neither a file nor a Directory.
+        let missing_files = self
+            .store
+            .local
+            .get_missing_digests(EntryType::File, digests)
+            .await
+            .map_err(Status::internal)?;
+        let missing = self
+            .store
+            .local
+            .get_missing_digests(EntryType::Directory, missing_files)
+            .await
+            .map_err(Status::internal)?;
+
+        Ok(Response::new(FindMissingBlobsResponse {
+            missing_blob_digests: missing.into_iter().map(|d| d.into()).collect(),
+        }))
+    }
+
+    async fn batch_update_blobs(
+        &self,
+        request: Request<BatchUpdateBlobsRequest>,
+    ) -> Result<Response<BatchUpdateBlobsResponse>, Status> {
+        let request = request.into_inner();
+        self.check_instance_name(&request.instance_name)?;
+
+        let responses = futures::future::join_all(request.requests.into_iter().map(
+            |blob_request| async move {
+                let result = match require_digest(blob_request.digest.as_ref()) {
+                    Ok(digest) => self.store_bytes(digest, blob_request.data).await,
+                    Err(e) => Err(e),
+                };
+                remexec::batch_update_blobs_response::Response {
+                    digest: blob_request.digest,
+                    status: Some(to_rpc_status(
+                        result.err().unwrap_or_else(|| Status::ok("")),
+                    )),
+                }
+            },
+        ))
+        .await;
+
+        Ok(Response::new(BatchUpdateBlobsResponse { responses }))
+    }
+
+    async fn batch_read_blobs(
+        &self,
+        request: Request<BatchReadBlobsRequest>,
+    ) -> Result<Response<BatchReadBlobsResponse>, Status> {
+        let request = request.into_inner();
+        self.check_instance_name(&request.instance_name)?;
+
+        let responses =
+            futures::future::join_all(request.digests.into_iter().map(|digest| async move {
+                let result = match require_digest(Some(&digest)) {
+                    Ok(d) => self.load(d).await,
+                    Err(e) => Err(e),
+                };
+                let (data, status) = match result {
+                    Ok(data) => (data, Status::ok("")),
+                    Err(status) => (Bytes::new(), status),
+                };
+                remexec::batch_read_blobs_response::Response {
+                    digest: Some(digest),
+                    data,
+                    status: Some(to_rpc_status(status)),
+                    compressor: remexec::compressor::Value::Identity as i32,
+                }
+            }))
+            .await;
+
+        Ok(Response::new(BatchReadBlobsResponse { responses }))
+    }
+
+    type GetTreeStream = tonic::codec::Streaming<GetTreeResponse>;
+
+    async fn get_tree(
+        &self,
+        _: Request<GetTreeRequest>,
+    ) -> Result<Response<Self::GetTreeStream>, Status> {
+        Err(Status::unimplemented(
+            "GetTree is not supported by the local store server.",
+        ))
+    }
+}
+
+#[tonic::async_trait]
+impl Capabilities for StoreResponder {
+    async fn get_capabilities(
+        &self,
+        request: Request<GetCapabilitiesRequest>,
+    ) -> Result<Response<ServerCapabilities>, Status> {
+        let request = request.into_inner();
+        self.check_instance_name(&request.instance_name)?;
+
+        Ok(Response::new(ServerCapabilities {
+            cache_capabilities: Some(CacheCapabilities {
+                digest_functions: vec![remexec::digest_function::Value::Sha256 as i32],
+                max_batch_total_size_bytes: MAX_BATCH_TOTAL_SIZE_BYTES,
+                ..CacheCapabilities::default()
+            }),
+            low_api_version: Some(SemVer {
+                major: 2,
+                ..SemVer::default()
+            }),
+            high_api_version: Some(SemVer {
+                major: 2,
+                minor: 3,
+                ..SemVer::default()
+            }),
+            ..ServerCapabilities::default()
+        }))
+    }
+}
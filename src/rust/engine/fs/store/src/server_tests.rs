@@ -0,0 +1,147 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+
+use grpc_util::tls;
+use remote_provider::{RemoteProvider, RemoteStoreOptions};
+use tempfile::TempDir;
+use testutil::data::{TestData, TestDirectory};
+use workunit_store::WorkunitStore;
+
+use crate::remote::ByteStore;
+use crate::server::StoreServer;
+use crate::tests::new_local_store;
+
+async fn new_client(
+    server: &StoreServer,
+    instance_name: Option<String>,
+    chunk_size_bytes: usize,
+    batch_api_size_limit: usize,
+) -> ByteStore {
+    let _ = WorkunitStore::setup_for_tests();
+    ByteStore::from_options(RemoteStoreOptions {
+        provider: RemoteProvider::Reapi,
+        store_address: server.address(),
+        instance_name,
+        tls_config: tls::Config::default(),
+        headers: BTreeMap::new(),
+        chunk_size_bytes,
+        timeout: Duration::from_secs(5),
+        stream_timeout: Duration::from_secs(5),
+        retries: 0,
+        concurrency_limit: 16,
+        batch_api_size_limit,
+        circuit_breaker: None,
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn serve_files_and_directories() {
+    let dir = TempDir::new().unwrap();
+    let store = new_local_store(dir.path());
+    let roland = TestData::roland();
+    let catnip = TestData::catnip();
+    let directory = TestDirectory::containing_roland();
+    store.store_file_bytes(roland.bytes(), false).await.unwrap();
+    store
+        .record_directory(&directory.directory(), false)
+        .await
+        .unwrap();
+
+    let server = StoreServer::start(
+        &task_executor::Executor::new(),
+        store.clone(),
+        Some("main".to_owned()),
+        0,
+    )
+    .unwrap();
+
+    // Both the batch and streaming APIs are used to read and write.
+    for batch_api_size_limit in [0, 1024] {
+        let client = new_client(&server, Some("main".to_owned()), 4, batch_api_size_limit).await;
+
+        assert_eq!(
+            client.load_bytes(roland.digest()).await,
+            Ok(Some(roland.bytes()))
+        );
+        assert_eq!(
+            client.load_bytes(directory.digest()).await,
+            Ok(Some(directory.bytes()))
+        );
+        assert_eq!(client.load_bytes(catnip.digest()).await, Ok(None));
+        assert_eq!(
+            client
+                .list_missing_digests(vec![roland.digest(), directory.digest(), catnip.digest()])
+                .await,
+            Ok(HashSet::from([catnip.digest()]))
+        );
+    }
+
+    for (batch_api_size_limit, data) in [(0, TestData::catnip()), (1024, TestData::robin())] {
+        let client = new_client(&server, Some("main".to_owned()), 4, batch_api_size_limit).await;
+        client.store_bytes(data.bytes()).await.unwrap();
+        assert_eq!(
+            store
+                .load_file_bytes_with(data.digest(), |bytes| bytes.to_vec())
+                .await
+                .unwrap(),
+            data.bytes().to_vec()
+        );
+    }
+}
+
+#[tokio::test]
+async fn serve_large_files() {
+    let dir = TempDir::new().unwrap();
+    let store = new_local_store(dir.path());
+    let henries = TestData::all_the_henries();
+    let double_henries = TestData::double_all_the_henries();
+    store
+        .store_file_bytes(henries.bytes(), false)
+        .await
+        .unwrap();
+
+    let server =
+        StoreServer::start(&task_executor::Executor::new(), store.clone(), None, 0).unwrap();
+    let client = new_client(&server, None, 64 * 1024, 0).await;
+
+    assert_eq!(
+        client.load_bytes(henries.digest()).await,
+        Ok(Some(henries.bytes()))
+    );
+
+    client.store_bytes(double_henries.bytes()).await.unwrap();
+    assert_eq!(
+        store
+            .load_file_bytes_with(double_henries.digest(), |bytes| bytes.len())
+            .await
+            .unwrap(),
+        double_henries.len()
+    );
+}
+
+#[tokio::test]
+async fn reject_unknown_instance() {
+    let dir = TempDir::new().unwrap();
+    let store = new_local_store(dir.path());
+    let roland = TestData::roland();
+    store.store_file_bytes(roland.bytes(), false).await.unwrap();
+
+    let server = StoreServer::start(
+        &task_executor::Executor::new(),
+        store,
+        Some("main".to_owned()),
+        0,
+    )
+    .unwrap();
+    let client = new_client(&server, Some("other".to_owned()), 1024, 1024).await;
+
+    let err = client
+        .list_missing_digests(vec![roland.digest()])
+        .await
+        .unwrap_err();
+    assert!(err.contains("does not exist"), "{err}");
+}
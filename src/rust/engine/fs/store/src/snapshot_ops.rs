@@ -5,14 +5,16 @@ use std::collections::HashMap;
 use std::convert::From;
 use std::fmt::{Debug, Display};
 use std::iter::Iterator;
+use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
 use bytes::BytesMut;
 use fs::{
-    directory, DigestTrie, DirectoryDigest, GlobMatching, PreparedPathGlobs, RelativePath,
-    SymlinkBehavior, EMPTY_DIRECTORY_DIGEST,
+    directory, DigestTrie, DirectoryDigest, GlobMatching, MergeConflictPolicy, PreparedPathGlobs,
+    RelativePath, SymlinkBehavior, TypedPath, EMPTY_DIRECTORY_DIGEST,
 };
 use futures::future::{self, FutureExt};
+use glob::{MatchOptions, Pattern};
 use hashing::Digest;
 use itertools::Itertools;
 use log::log_enabled;
@@ -25,16 +27,215 @@ pub struct SubsetParams {
     pub globs: PreparedPathGlobs,
 }
 
+///
+/// A rename to apply within a digest: each path which matches the `src` glob is moved to the path
+/// rendered from the `dest` template.
+///
+/// The `dest` template may refer to the following placeholders, which are computed for each
+/// matched path relative to the literal (wildcard-free) parent directories of the `src` glob:
+///   * `{path}`: the relative path.
+///   * `{dir}`: the directory containing the relative path.
+///   * `{name}`: the file name of the path.
+/// A template which contains no placeholders is treated as a directory, and is equivalent to
+/// `{dest}/{path}`. A leading `/` in the template refers to the root of the digest.
+///
+/// For example, `src/py/** -> /` moves the content of `src/py` to the root, while
+/// `src/py/**/*.py -> /{name}` flattens all of the Python files beneath it into the root.
+///
+#[derive(Debug, Clone)]
+pub struct Relocation {
+    pub src: String,
+    pub dest: String,
+}
+
+const PLACEHOLDERS: [&str; 3] = ["{path}", "{dir}", "{name}"];
+
+struct PreparedRelocation<'a> {
+    relocation: &'a Relocation,
+    pattern: Pattern,
+    prefix: PathBuf,
+}
+
+impl<'a> PreparedRelocation<'a> {
+    fn new(relocation: &'a Relocation) -> Result<Self, String> {
+        let src = RelativePath::new(&relocation.src)
+            .map_err(|e| format!("Invalid relocation source {:?}: {e}", relocation.src))?;
+        let pattern = Pattern::new(&src.to_string_lossy())
+            .map_err(|e| format!("Could not parse {:?} as a glob: {e}", relocation.src))?;
+        let components = src
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>();
+        let prefix = components[..components.len().saturating_sub(1)]
+            .iter()
+            .take_while(|c| Pattern::escape(c) == **c)
+            .map(|c| &**c)
+            .collect::<PathBuf>();
+
+        // Validate the template eagerly, so that it fails even if nothing matches.
+        let unrecognized = PLACEHOLDERS
+            .iter()
+            .fold(relocation.dest.clone(), |dest, p| dest.replace(p, ""));
+        if unrecognized.contains('{') || unrecognized.contains('}') {
+            return Err(format!(
+                "Unrecognized placeholder in relocation destination {:?}: expected only {}.",
+                relocation.dest,
+                PLACEHOLDERS.iter().map(|p| format!("`{p}`")).join(", ")
+            ));
+        }
+
+        Ok(PreparedRelocation {
+            relocation,
+            pattern,
+            prefix,
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        self.pattern.matches_path_with(
+            path,
+            MatchOptions {
+                case_sensitive: true,
+                require_literal_separator: true,
+                require_literal_leading_dot: false,
+            },
+        )
+    }
+
+    fn render(&self, path: &Path) -> Result<PathBuf, String> {
+        let relative = path.strip_prefix(&self.prefix).unwrap_or(path);
+        let template = self.relocation.dest.trim_start_matches('/');
+        let rendered = if PLACEHOLDERS.iter().any(|p| template.contains(p)) {
+            let rendered = template
+                .replace("{path}", &relative.to_string_lossy())
+                .replace(
+                    "{dir}",
+                    &relative
+                        .parent()
+                        .map(|p| p.to_string_lossy())
+                        .unwrap_or_default(),
+                )
+                .replace(
+                    "{name}",
+                    &relative
+                        .file_name()
+                        .map(|n| n.to_string_lossy())
+                        .unwrap_or_default(),
+                );
+            PathBuf::from(rendered)
+        } else {
+            Path::new(template).join(relative)
+        };
+        let rendered = RelativePath::new(rendered).map_err(|e| {
+            format!(
+                "Cannot relocate `{}` using {:?}: {e}",
+                path.display(),
+                self.relocation.dest
+            )
+        })?;
+        if rendered.components().next().is_none() {
+            return Err(format!(
+                "Cannot relocate `{}` using {:?}: the destination was empty.",
+                path.display(),
+                self.relocation.dest
+            ));
+        }
+        Ok(rendered.into())
+    }
+}
+
+///
+/// Apply the given relocations to the leaves (files, symlinks, and empty directories) of the tree
+/// in a single pass. Each leaf is moved by the first relocation which matches it, and leaves which
+/// match no relocation are left in place.
+///
+fn relocate_tree(tree: &DigestTrie, relocations: &[Relocation]) -> Result<DigestTrie, String> {
+    let relocations = relocations
+        .iter()
+        .map(PreparedRelocation::new)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut leaves = Vec::new();
+    tree.walk(SymlinkBehavior::Aware, &mut |path, entry| {
+        let is_leaf = match entry {
+            directory::Entry::Directory(d) => {
+                d.tree().entries().is_empty() && path.components().next().is_some()
+            }
+            directory::Entry::File(_) | directory::Entry::Symlink(_) => true,
+        };
+        if is_leaf {
+            leaves.push((path.to_owned(), entry.clone()));
+        }
+    });
+
+    let mut sources_by_destination: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut relocated = Vec::with_capacity(leaves.len());
+    for (path, entry) in leaves {
+        let destination = match relocations.iter().find(|r| r.matches(&path)) {
+            Some(relocation) => relocation.render(&path)?,
+            None => path.clone(),
+        };
+        if let Some(other) = sources_by_destination.insert(destination.clone(), path.clone()) {
+            return Err(format!(
+                "Cannot relocate both `{}` and `{}` to `{}`.",
+                other.display(),
+                path.display(),
+                destination.display()
+            ));
+        }
+        relocated.push((destination, entry));
+    }
+    for (destination, source) in &sources_by_destination {
+        if let Some(parent) = destination
+            .ancestors()
+            .skip(1)
+            .find(|a| sources_by_destination.contains_key(*a))
+        {
+            return Err(format!(
+                "Cannot relocate `{}` to `{}`, because its parent `{}` is not a directory (it is \
+                 the destination of `{}`).",
+                source.display(),
+                destination.display(),
+                parent.display(),
+                sources_by_destination[parent].display()
+            ));
+        }
+    }
+
+    let file_digests = relocated
+        .iter()
+        .filter_map(|(destination, entry)| match entry {
+            directory::Entry::File(f) => Some((destination.clone(), f.digest())),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+    let typed_paths = relocated
+        .iter()
+        .map(|(destination, entry)| match entry {
+            directory::Entry::File(f) => TypedPath::File {
+                path: destination,
+                is_executable: f.is_executable(),
+            },
+            directory::Entry::Symlink(s) => TypedPath::Link {
+                path: destination,
+                target: s.target(),
+            },
+            directory::Entry::Directory(_) => TypedPath::Dir(destination),
+        })
+        .collect();
+    DigestTrie::from_unique_paths(typed_paths, &file_digests)
+}
+
 ///
 /// Given Digest(s) representing Directory instances, merge them recursively into a single
 /// output Directory Digest.
 ///
-/// If a file is present with the same name and contents multiple times, it will appear once.
-/// If a file is present with the same name, but different contents, an error will be returned.
+/// Paths which are present in more than one of the inputs are resolved using the given policy.
 ///
 async fn merge_directories<T: SnapshotOps + 'static>(
     store: T,
     dir_digests: Vec<DirectoryDigest>,
+    policy: MergeConflictPolicy,
 ) -> Result<DirectoryDigest, T::Error> {
     let trees = future::try_join_all(
         dir_digests
@@ -44,7 +245,7 @@ async fn merge_directories<T: SnapshotOps + 'static>(
     )
     .await?;
 
-    let tree = match DigestTrie::merge(trees) {
+    let tree = match DigestTrie::merge_with_policy(trees, policy) {
         Ok(tree) => tree,
         Err(merge_error) => {
             // TODO: Use https://doc.rust-lang.org/nightly/std/result/enum.Result.html#method.into_ok_or_err
@@ -163,6 +364,66 @@ async fn render_merge_error<T: SnapshotOps + 'static>(
     ))
 }
 
+///
+/// Collect the digests of the files in the given tree which are beneath any of the given prefixes,
+/// skipping subtrees which are not.
+///
+fn file_digests_beneath(tree: &DigestTrie, mut prefixes: Vec<PathBuf>) -> HashMap<PathBuf, Digest> {
+    // Sorting places each prefix immediately before any prefixes which are beneath it, which are
+    // redundant.
+    prefixes.sort();
+    prefixes.dedup_by(|child, parent| child.starts_with(parent));
+
+    let mut files = HashMap::new();
+    let mut collect = |path: &Path, entry: &directory::Entry| {
+        if let directory::Entry::File(f) = entry {
+            files.insert(path.to_owned(), f.digest());
+        }
+    };
+    let mut subtrees = Vec::new();
+    for prefix in prefixes {
+        if prefix.components().next().is_none() {
+            subtrees.push((prefix, tree));
+            continue;
+        }
+        match subtree_entry(tree, &prefix) {
+            Some(directory::Entry::Directory(d)) => subtrees.push((prefix, d.tree())),
+            Some(entry) => collect(&prefix, entry),
+            None => (),
+        }
+    }
+
+    for (prefix, subtree) in subtrees {
+        subtree.walk(SymlinkBehavior::Aware, &mut |path, entry| {
+            collect(&prefix.join(path), entry)
+        });
+    }
+    files
+}
+
+///
+/// Find the entry at the given path without following symlinks: because globs are expanded
+/// without traversing symlinks, nothing beneath a symlink can match.
+///
+fn subtree_entry<'a>(tree: &'a DigestTrie, path: &Path) -> Option<&'a directory::Entry> {
+    let mut tree = tree;
+    let mut components = path.components().peekable();
+    while let Some(component) = components.next() {
+        let entry = tree
+            .entries()
+            .iter()
+            .find(|e| Path::new(e.name().as_ref()).as_os_str() == component.as_os_str())?;
+        if components.peek().is_none() {
+            return Some(entry);
+        }
+        match entry {
+            directory::Entry::Directory(d) => tree = d.tree(),
+            directory::Entry::File(_) | directory::Entry::Symlink(_) => return None,
+        }
+    }
+    None
+}
+
 ///
 /// High-level operations to manipulate and merge `Digest`s.
 ///
@@ -187,8 +448,24 @@ pub trait SnapshotOps: Clone + Send + Sync + 'static {
     ///
     /// Given N Snapshots, returns a new Snapshot that merges them.
     ///
+    /// If a file is present with the same name and contents multiple times, it will appear once.
+    /// If a file is present with the same name, but different contents, an error will be returned.
+    ///
     async fn merge(&self, digests: Vec<DirectoryDigest>) -> Result<DirectoryDigest, Self::Error> {
-        merge_directories(self.clone(), digests).await
+        self.merge_with_policy(digests, MergeConflictPolicy::ErrorUnlessIdentical)
+            .await
+    }
+
+    ///
+    /// Given N Snapshots, returns a new Snapshot that merges them, resolving paths which are
+    /// present in more than one of them using the given policy.
+    ///
+    async fn merge_with_policy(
+        &self,
+        digests: Vec<DirectoryDigest>,
+        policy: MergeConflictPolicy,
+    ) -> Result<DirectoryDigest, Self::Error> {
+        merge_directories(self.clone(), digests, policy).await
     }
 
     async fn add_prefix(
@@ -221,26 +498,47 @@ pub trait SnapshotOps: Clone + Send + Sync + 'static {
         params: SubsetParams,
     ) -> Result<DirectoryDigest, Self::Error> {
         let input_tree = self.load_digest_trie(directory_digest.clone()).await?;
+
+        if params.globs.is_exclude_only() {
+            // Only the excludes need to be applied, which can be done without expanding the globs
+            // or re-computing the digests of subtrees that they do not touch.
+            let excludes = params.globs.excludes();
+            return Ok(
+                match input_tree.filter(&mut |path, entry| {
+                    !excludes.is_ignored_path(path, matches!(entry, directory::Entry::Directory(_)))
+                }) {
+                    Some(tree) => tree.into(),
+                    None => directory_digest,
+                },
+            );
+        }
+
+        let prefixes = params.globs.literal_prefixes();
         let path_stats = input_tree
             .expand_globs(params.globs, SymlinkBehavior::Aware, None)
             .await
             .map_err(|err| format!("Error matching globs against {directory_digest:?}: {err}"))?;
 
-        let mut files = HashMap::new();
-        input_tree.walk(SymlinkBehavior::Oblivious, &mut |path, entry| match entry {
-            directory::Entry::File(f) => {
-                files.insert(path.to_owned(), f.digest());
-            }
-            directory::Entry::Symlink(_) => panic!("Unexpected symlink"),
-            directory::Entry::Directory(_) => (),
-        });
-
+        let files = file_digests_beneath(&input_tree, prefixes);
         Ok(
             DigestTrie::from_unique_paths(path_stats.iter().map(|p| p.into()).collect(), &files)?
                 .into(),
         )
     }
 
+    ///
+    /// Applies the given relocations to the content of the digest in a single pass, rather than
+    /// building an intermediate tree per subset and prefix operation.
+    ///
+    async fn relocate(
+        &self,
+        digest: DirectoryDigest,
+        relocations: Vec<Relocation>,
+    ) -> Result<DirectoryDigest, Self::Error> {
+        let tree = self.load_digest_trie(digest).await?;
+        Ok(relocate_tree(&tree, &relocations)?.into())
+    }
+
     async fn create_empty_dir(&self, path: &RelativePath) -> Result<DirectoryDigest, Self::Error> {
         self.add_prefix(EMPTY_DIRECTORY_DIGEST.clone(), path).await
     }
@@ -7,12 +7,13 @@ use std::sync::Arc;
 
 use fs::{
     DirectoryDigest, GlobExpansionConjunction, PosixFS, PreparedPathGlobs, StrictGlobMatching,
+    SymlinkBehavior,
 };
 use testutil::make_file;
 
 use crate::{
     snapshot_tests::{expand_all_sorted, setup, STR, STR2},
-    OneOffStoreFileByDigest, Snapshot, SnapshotOps, SubsetParams,
+    OneOffStoreFileByDigest, Relocation, Snapshot, SnapshotOps, SubsetParams,
 };
 
 async fn get_duplicate_rolands<T: SnapshotOps>(
@@ -50,15 +51,60 @@ async fn get_duplicate_rolands<T: SnapshotOps>(
 }
 
 fn make_subset_params(globs: &[&str]) -> SubsetParams {
+    make_subset_params_with(globs, StrictGlobMatching::Ignore)
+}
+
+fn make_subset_params_with(
+    globs: &[&str],
+    strict_match_behavior: StrictGlobMatching,
+) -> SubsetParams {
     let globs = PreparedPathGlobs::create(
         globs.iter().map(|s| s.to_string()).collect(),
-        StrictGlobMatching::Ignore,
+        strict_match_behavior,
         GlobExpansionConjunction::AllMatch,
     )
     .unwrap();
     SubsetParams { globs }
 }
 
+async fn make_node_modules_snapshot(
+    tempdir: &Path,
+    posix_fs: Arc<PosixFS>,
+    digester: OneOffStoreFileByDigest,
+) -> DirectoryDigest {
+    for path in [
+        "node_modules/a/index.js",
+        "node_modules/b/index.js",
+        "node_modules/b/lib/util.js",
+        "src/main.js",
+        "src/nested/util.js",
+    ] {
+        let path = tempdir.join(path);
+        create_dir_all(path.parent().unwrap()).unwrap();
+        make_file(&path, STR.as_bytes(), 0o600);
+    }
+    Snapshot::from_path_stats(digester, expand_all_sorted(posix_fs).await)
+        .await
+        .unwrap()
+        .into()
+}
+
+async fn subset_files<T: SnapshotOps>(
+    store: &T,
+    digest: DirectoryDigest,
+    params: SubsetParams,
+) -> Vec<String> {
+    let subset = store.subset(digest, params).await.unwrap();
+    store
+        .load_digest_trie(subset)
+        .await
+        .unwrap()
+        .files(SymlinkBehavior::Aware)
+        .into_iter()
+        .map(|p| p.to_str().unwrap().to_owned())
+        .collect()
+}
+
 #[tokio::test]
 async fn subset_single_files() {
     let (store, tempdir, posix_fs, digester) = setup();
@@ -167,3 +213,168 @@ async fn subset_recursive_wildcard() {
         .unwrap();
     assert_eq!(subset_roland4, snapshot1.into());
 }
+
+#[tokio::test]
+async fn subset_exclude_only() {
+    let (store, tempdir, posix_fs, digester) = setup();
+    let digest = make_node_modules_snapshot(tempdir.path(), posix_fs, digester).await;
+
+    // Nothing is excluded, so the input is returned unchanged.
+    let subset = store
+        .subset(digest.clone(), make_subset_params(&["**"]))
+        .await
+        .unwrap();
+    assert_eq!(subset, digest);
+
+    let globs = ["**", "!node_modules/b", "!src/**/util.js"];
+    assert_eq!(
+        subset_files(&store, digest.clone(), make_subset_params(&globs)).await,
+        vec!["node_modules/a/index.js", "src/main.js"]
+    );
+
+    // The result is identical to that of expanding the globs (which is required in order to warn
+    // about unmatched globs).
+    let fast = store
+        .subset(digest.clone(), make_subset_params(&globs))
+        .await
+        .unwrap();
+    let expanded = store
+        .subset(
+            digest,
+            make_subset_params_with(&globs, StrictGlobMatching::Warn("test".to_owned())),
+        )
+        .await
+        .unwrap();
+    assert_eq!(fast, expanded);
+}
+
+#[tokio::test]
+async fn subset_beneath_literal_prefixes() {
+    let (store, tempdir, posix_fs, digester) = setup();
+    let digest = make_node_modules_snapshot(tempdir.path(), posix_fs, digester).await;
+
+    assert_eq!(
+        subset_files(&store, digest.clone(), make_subset_params(&["src/**/*.js"])).await,
+        vec!["src/main.js", "src/nested/util.js"]
+    );
+    assert_eq!(
+        subset_files(
+            &store,
+            digest.clone(),
+            make_subset_params(&["node_modules/*/index.js", "!node_modules/a"])
+        )
+        .await,
+        vec!["node_modules/b/index.js"]
+    );
+    assert_eq!(
+        subset_files(
+            &store,
+            digest,
+            make_subset_params(&["src/main.js", "src/nested/*", "**/lib/*.js"])
+        )
+        .await,
+        vec![
+            "node_modules/b/lib/util.js",
+            "src/main.js",
+            "src/nested/util.js"
+        ]
+    );
+}
+
+async fn relocate_files<T: SnapshotOps>(
+    store: &T,
+    digest: DirectoryDigest,
+    relocations: &[(&str, &str)],
+) -> Result<Vec<String>, String> {
+    let relocations = relocations
+        .iter()
+        .map(|(src, dest)| Relocation {
+            src: src.to_string(),
+            dest: dest.to_string(),
+        })
+        .collect();
+    let relocated = store
+        .relocate(digest, relocations)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(store
+        .load_digest_trie(relocated)
+        .await
+        .unwrap()
+        .files(SymlinkBehavior::Aware)
+        .into_iter()
+        .map(|p| p.to_str().unwrap().to_owned())
+        .collect())
+}
+
+#[tokio::test]
+async fn relocate() {
+    let (store, tempdir, posix_fs, digester) = setup();
+    let digest = make_node_modules_snapshot(tempdir.path(), posix_fs, digester).await;
+
+    // Unmatched paths are left in place.
+    assert_eq!(
+        relocate_files(&store, digest.clone(), &[("src/**", "/")]).await,
+        Ok(vec![
+            "main.js".to_owned(),
+            "nested/util.js".to_owned(),
+            "node_modules/a/index.js".to_owned(),
+            "node_modules/b/index.js".to_owned(),
+            "node_modules/b/lib/util.js".to_owned(),
+        ])
+    );
+
+    // The first matching relocation is used.
+    assert_eq!(
+        relocate_files(
+            &store,
+            digest.clone(),
+            &[
+                ("node_modules/*/index.js", "lib/{dir}.js"),
+                ("src/**/*.js", "/{name}"),
+                ("**", "rest"),
+            ]
+        )
+        .await,
+        Ok(vec![
+            "lib/a.js".to_owned(),
+            "lib/b.js".to_owned(),
+            "main.js".to_owned(),
+            "rest/node_modules/b/lib/util.js".to_owned(),
+            "util.js".to_owned(),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn relocate_errors() {
+    let (store, tempdir, posix_fs, digester) = setup();
+    let digest = make_node_modules_snapshot(tempdir.path(), posix_fs, digester).await;
+
+    let err = relocate_files(&store, digest.clone(), &[("**/util.js", "{name}")])
+        .await
+        .unwrap_err();
+    assert!(
+        err.contains("Cannot relocate both `node_modules/b/lib/util.js` and `src/nested/util.js`"),
+        "{err}"
+    );
+
+    let err = relocate_files(
+        &store,
+        digest.clone(),
+        &[("src/main.js", "node_modules/a/index.js/x")],
+    )
+    .await
+    .unwrap_err();
+    assert!(err.contains("is not a directory"), "{err}");
+
+    let err = relocate_files(&store, digest.clone(), &[("src/*", "{stem}")])
+        .await
+        .unwrap_err();
+    assert!(err.contains("Unrecognized placeholder"), "{err}");
+
+    let err = relocate_files(&store, digest, &[("src/*", "../{name}")])
+        .await
+        .unwrap_err();
+    assert!(err.contains("escape the root"), "{err}");
+}
@@ -12,7 +12,7 @@ use testutil::make_file;
 use crate::{OneOffStoreFileByDigest, RelativePath, Snapshot, SnapshotOps, Store, StoreError};
 use fs::{
     Dir, DirectoryDigest, File, GitignoreStyleExcludes, GlobExpansionConjunction, GlobMatching,
-    PathGlobs, PathStat, PosixFS, StrictGlobMatching, SymlinkBehavior,
+    MergeConflictPolicy, PathGlobs, PathStat, PosixFS, StrictGlobMatching, SymlinkBehavior,
 };
 
 pub const STR: &str = "European Burmese";
@@ -262,6 +262,67 @@ async fn merge_directories_same_files() {
     );
 }
 
+#[tokio::test]
+async fn merge_directories_conflict_policies() {
+    let (store, _, _, _) = setup();
+
+    let containing_roland = TestDirectory::containing_roland();
+    let containing_wrong_roland = TestDirectory::containing_wrong_roland();
+    for directory in [&containing_roland, &containing_wrong_roland] {
+        store
+            .record_directory(&directory.directory(), false)
+            .await
+            .expect("Storing directory");
+    }
+    let clashing = || {
+        vec![
+            containing_roland.directory_digest(),
+            containing_wrong_roland.directory_digest(),
+        ]
+    };
+
+    for (policy, expected) in [
+        (MergeConflictPolicy::Error, None),
+        (MergeConflictPolicy::ErrorUnlessIdentical, None),
+        (
+            MergeConflictPolicy::PreferLeft,
+            Some(containing_roland.directory_digest()),
+        ),
+        (
+            MergeConflictPolicy::PreferRight,
+            Some(containing_wrong_roland.directory_digest()),
+        ),
+    ] {
+        let result = store.merge_with_policy(clashing(), policy).await;
+        match expected {
+            Some(expected) => assert_eq!(result, Ok(expected), "{policy:?}"),
+            None => assert!(result.is_err(), "{policy:?}"),
+        }
+    }
+
+    // Identical files are only an error when any duplicate is.
+    let identical = || {
+        vec![
+            containing_roland.directory_digest(),
+            containing_roland.directory_digest(),
+        ]
+    };
+    assert_eq!(
+        store
+            .merge_with_policy(identical(), MergeConflictPolicy::ErrorUnlessIdentical)
+            .await,
+        Ok(containing_roland.directory_digest())
+    );
+    let err = store
+        .merge_with_policy(identical(), MergeConflictPolicy::Error)
+        .await
+        .expect_err("Want error merging");
+    assert!(
+        format!("{err:?}").contains("roland"),
+        "Want error message to contain roland but was: {err:?}"
+    );
+}
+
 #[tokio::test]
 async fn snapshot_merge_two_files() {
     let (store, tempdir, _, digester) = setup();
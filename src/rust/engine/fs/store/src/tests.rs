@@ -6,12 +6,13 @@ use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tempfile::TempDir;
-use testutil::data::{TestData, TestDirectory};
+use testutil::data::{TestData, TestDirectory, TestTree};
 
 use bytes::Bytes;
+use engine_error::{ErrorCategory, ErrorCode};
 use fs::{
-    DigestEntry, DirectoryDigest, FileEntry, Link, PathStat, Permissions, RelativePath,
-    EMPTY_DIRECTORY_DIGEST,
+    DigestEntry, DigestTrie, DirectoryDigest, FileEntry, Link, PathStat, Permissions, RelativePath,
+    TypedPath, EMPTY_DIRECTORY_DIGEST,
 };
 use grpc_util::prost::MessageExt;
 use grpc_util::tls;
@@ -22,8 +23,8 @@ use workunit_store::WorkunitStore;
 
 use crate::local::ByteStore;
 use crate::{
-    EntryType, FileContent, RemoteProvider, RemoteStoreOptions, Snapshot, Store, StoreError,
-    StoreFileByDigest, UploadSummary, MEGABYTES,
+    EntryType, FileContent, LocalOptions, RemoteProvider, RemoteStoreOptions, Snapshot, Store,
+    StoreError, StoreFileByDigest, UploadSummary, MATERIALIZE_BATCH_SIZE, MEGABYTES,
 };
 
 pub(crate) const STORE_BATCH_API_SIZE_LIMIT: usize = 4 * 1024 * 1024;
@@ -54,10 +55,34 @@ pub fn new_empty_cas() -> StubCAS {
 ///
 /// Create a new local store with whatever was already serialized in dir.
 ///
-fn new_local_store<P: AsRef<Path>>(dir: P) -> Store {
+pub fn new_local_store<P: AsRef<Path>>(dir: P) -> Store {
     Store::local_only(task_executor::Executor::new(), dir).expect("Error creating local store")
 }
 
+///
+/// Store the given files (as path, content and whether they are executable) in the given store,
+/// and return the digest of a directory containing them.
+///
+pub async fn store_files(store: &Store, files: &[(&str, &str, bool)]) -> DirectoryDigest {
+    let mut file_digests = HashMap::new();
+    for (path, content, _) in files {
+        let digest = store
+            .store_file_bytes(Bytes::copy_from_slice(content.as_bytes()), false)
+            .await
+            .unwrap();
+        file_digests.insert(PathBuf::from(path), digest);
+    }
+    let typed_paths = files
+        .iter()
+        .map(|(path, _, is_executable)| TypedPath::File {
+            path: Path::new(path),
+            is_executable: *is_executable,
+        })
+        .collect();
+    let tree = DigestTrie::from_unique_paths(typed_paths, &file_digests).unwrap();
+    store.record_digest_trie(tree, false).await.unwrap()
+}
+
 fn remote_options(
     store_address: String,
     instance_name: Option<String>,
@@ -71,9 +96,11 @@ fn remote_options(
         headers,
         chunk_size_bytes: 10 * MEGABYTES,
         timeout: Duration::from_secs(1),
+        stream_timeout: Duration::from_secs(1),
         retries: 1,
         concurrency_limit: 256,
         batch_api_size_limit: STORE_BATCH_API_SIZE_LIMIT,
+        circuit_breaker: None,
     }
 }
 ///
@@ -328,6 +355,132 @@ async fn load_recursive_directory() {
     );
 }
 
+#[tokio::test]
+async fn load_tree_from_remote_lazily() {
+    let dir = TempDir::new().unwrap();
+
+    let tree = TestTree::nested();
+    let root_digest = TestDirectory::nested().digest();
+    let prefix = RelativePath::new("a/b").unwrap();
+    let expected_digest = tree
+        .digest_trie()
+        .add_prefix(&prefix)
+        .unwrap()
+        .compute_root_digest();
+
+    let _ = WorkunitStore::setup_for_tests();
+    let cas = StubCAS::builder()
+        .file(&TestData::roland())
+        .tree(&tree)
+        .build();
+    let store = new_store(dir.path(), &cas.address()).await;
+
+    // The Tree is expanded in memory, but not persisted.
+    let digest = store
+        .load_tree_from_remote_lazily(tree.digest(), &prefix)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(digest.as_digest(), expected_digest);
+    assert!(digest.tree.is_some());
+    assert_eq!(store.local.entry_type(root_digest.hash).await, Ok(None));
+
+    // Once it has been persisted, it is not expanded again.
+    store
+        .ensure_directory_digest_persisted(digest)
+        .await
+        .unwrap();
+    let digest = store
+        .load_tree_from_remote_lazily(tree.digest(), &prefix)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(digest.as_digest(), expected_digest);
+    assert!(digest.tree.is_none());
+    assert_eq!(
+        new_local_store(dir.path())
+            .load_directory(root_digest)
+            .await
+            .unwrap(),
+        TestDirectory::nested().directory()
+    );
+}
+
+/// Stores content under the fingerprint of `TestData::roland()` which has the right length, but
+/// the wrong content.
+async fn store_corrupted_roland(store: &Store) {
+    let roland = TestData::roland();
+    let mut corrupted = roland.bytes().to_vec();
+    corrupted[0] ^= 0xff;
+    store
+        .local
+        .store_bytes(
+            EntryType::File,
+            roland.fingerprint(),
+            Bytes::from(corrupted),
+            false,
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn scrub_removes_corrupted_entries() {
+    let dir = TempDir::new().unwrap();
+    let store = new_local_store(dir.path());
+    let catnip = TestData::catnip();
+    store.store_file_bytes(catnip.bytes(), false).await.unwrap();
+    store_corrupted_roland(&store).await;
+
+    let summary = store.scrub(1.0).await.unwrap();
+    assert_eq!(summary.checked, 2);
+    assert_eq!(
+        summary.corrupted,
+        vec![(EntryType::File, TestData::roland().digest())]
+    );
+    assert_eq!(summary.repaired, vec![]);
+
+    // The corrupted entry is now missing, rather than mismatched.
+    assert!(matches!(
+        load_file_bytes(&store, TestData::roland().digest()).await,
+        Err(StoreError::MissingDigest(..))
+    ));
+    assert_eq!(
+        load_file_bytes(&store, catnip.digest()).await.unwrap(),
+        catnip.bytes()
+    );
+}
+
+#[tokio::test]
+async fn scrub_repairs_corrupted_entries_from_remote() {
+    let dir = TempDir::new().unwrap();
+    let cas = new_cas(1024);
+    let store = new_store(dir.path(), &cas.address()).await;
+    store_corrupted_roland(&store).await;
+
+    let summary = store.scrub(1.0).await.unwrap();
+    let roland = (EntryType::File, TestData::roland().digest());
+    assert_eq!(summary.corrupted, vec![roland]);
+    assert_eq!(summary.repaired, vec![roland]);
+    assert_eq!(1, cas.request_count(RequestType::BSRead));
+
+    assert_eq!(
+        crate::local_tests::load_file_bytes(&store.local, TestData::roland().digest()).await,
+        Ok(Some(TestData::roland().bytes()))
+    );
+}
+
+#[tokio::test]
+async fn scrub_samples_entries() {
+    let dir = TempDir::new().unwrap();
+    let store = new_local_store(dir.path());
+    store_corrupted_roland(&store).await;
+
+    let summary = store.scrub(0.0).await.unwrap();
+    assert_eq!(summary.checked, 0);
+    assert_eq!(summary.corrupted, vec![]);
+}
+
 #[tokio::test]
 async fn load_file_missing_is_none() {
     let dir = TempDir::new().unwrap();
@@ -378,6 +531,23 @@ async fn load_file_remote_error_is_error() {
             .contains("StubCAS is configured to always fail"),
         "Bad error message"
     );
+    // The remote store was reachable, but failed the request.
+    assert_eq!(error.code(), ErrorCode::Unclassified);
+}
+
+#[tokio::test]
+async fn load_file_remote_unavailable_is_classified() {
+    let dir = TempDir::new().unwrap();
+
+    let _ = WorkunitStore::setup_for_tests();
+    let error = load_file_bytes(
+        &new_store(dir.path(), "http://doesnotexist.example").await,
+        TestData::roland().digest(),
+    )
+    .await
+    .expect_err("Want error");
+    assert_eq!(error.code(), ErrorCode::RemoteStoreUnavailable);
+    assert_eq!(error.code().category(), ErrorCategory::Infra);
 }
 
 #[tokio::test]
@@ -525,12 +695,14 @@ async fn wrong_remote_file_bytes_is_error() {
             TestDirectory::containing_roland().bytes(),
         )
         .build();
-    load_file_bytes(
+    let error = load_file_bytes(
         &new_store(dir.path(), &cas.address()).await,
         testdata.digest(),
     )
     .await
     .expect_err("Want error");
+    // Content which does not match its digest will not match if retried either.
+    assert_eq!(error.code(), ErrorCode::Unclassified);
 
     assert_eq!(
         crate::local_tests::load_file_bytes(
@@ -1249,6 +1421,68 @@ async fn materialize_directory_readonly_executable() {
     materialize_directory(Permissions::Writable, true).await
 }
 
+#[tokio::test]
+async fn materialize_directory_batches_with_limited_concurrency() {
+    let materialize_dir = TempDir::new().unwrap();
+    let store_dir = TempDir::new().unwrap();
+    // A concurrency of one ensures that batches and individual files do not wait on one another.
+    let store = Store::local_only_with_options(
+        task_executor::Executor::new(),
+        store_dir.path(),
+        store_dir.path(),
+        LocalOptions {
+            materialize_concurrency: 1,
+            ..LocalOptions::default()
+        },
+    )
+    .unwrap();
+
+    // More files than fit in a single batch, spread across directories.
+    let mut paths = Vec::new();
+    let mut file_digests = HashMap::new();
+    for i in 0..(MATERIALIZE_BATCH_SIZE * 2 + 1) {
+        let path = PathBuf::from(format!("dir{}/file{i}", i % 3));
+        let digest = store
+            .store_file_bytes(Bytes::from(format!("content {i}")), false)
+            .await
+            .unwrap();
+        file_digests.insert(path.clone(), digest);
+        paths.push(path);
+    }
+    let typed_paths = paths
+        .iter()
+        .map(|path| fs::TypedPath::File {
+            path,
+            is_executable: false,
+        })
+        .collect::<Vec<_>>();
+    let tree = fs::DigestTrie::from_unique_paths(typed_paths, &file_digests).unwrap();
+    let digest = store.record_digest_trie(tree, false).await.unwrap();
+
+    store
+        .materialize_directory(
+            materialize_dir.path().to_owned(),
+            materialize_dir.path(),
+            digest,
+            false,
+            &BTreeSet::new(),
+            Permissions::Writable,
+        )
+        .await
+        .expect("Error materializing");
+
+    assert_eq!(
+        list_dir(materialize_dir.path()),
+        vec!["dir0", "dir1", "dir2"]
+    );
+    for (i, path) in paths.iter().enumerate() {
+        assert_eq!(
+            file_contents(&materialize_dir.path().join(path)),
+            Bytes::from(format!("content {i}"))
+        );
+    }
+}
+
 #[tokio::test]
 async fn contents_for_directory_empty() {
     let store_dir = TempDir::new().unwrap();
@@ -0,0 +1,216 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use fs::{directory, DigestTrie, DirectoryDigest, FilespecMatcher, SymlinkBehavior, TypedPath};
+use futures::future;
+use hashing::Digest;
+use itertools::Itertools;
+
+use crate::{Store, StoreError};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn create(line_ending: &str) -> Result<LineEnding, String> {
+        match line_ending {
+            "lf" => Ok(LineEnding::Lf),
+            "crlf" => Ok(LineEnding::Crlf),
+            _ => Err(format!("Unrecognized line ending: {line_ending:?}")),
+        }
+    }
+
+    fn as_bytes(&self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::Crlf => b"\r\n",
+        }
+    }
+}
+
+///
+/// A deterministic transformation to apply to the files selected by `Store::transform_files`.
+///
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FileTransform {
+    /// Rewrites each line ending (either `\r\n` or `\n`) to the given line ending.
+    NormalizeLineEndings(LineEnding),
+    /// Replaces each occurrence of a literal placeholder with its value. The placeholders are
+    /// replaced in a single pass, so values are never themselves searched for placeholders, and
+    /// the longest placeholder wins when more than one begins at the same position.
+    Substitute(BTreeMap<String, String>),
+    /// Sets whether the file is executable.
+    SetExecutable(bool),
+}
+
+impl Store {
+    ///
+    /// Applies the given transforms (in order) to each file in the digest which matches the given
+    /// matcher, and returns the resulting digest.
+    ///
+    /// The content of each matched file is transformed in parallel, and only files whose content
+    /// actually changes are written to the Store. Symlinks are not followed, and so neither they nor
+    /// the files that they point to are transformed via a symlinked path.
+    ///
+    pub async fn transform_files(
+        &self,
+        digest: DirectoryDigest,
+        matcher: &FilespecMatcher,
+        transforms: Vec<FileTransform>,
+    ) -> Result<DirectoryDigest, StoreError> {
+        for transform in &transforms {
+            if let FileTransform::Substitute(placeholders) = transform {
+                if placeholders.contains_key("") {
+                    return Err("Cannot substitute an empty placeholder.".to_owned().into());
+                }
+            }
+        }
+        let transforms_content = transforms
+            .iter()
+            .any(|t| !matches!(t, FileTransform::SetExecutable(_)));
+        let set_executable = transforms.iter().rev().find_map(|t| match t {
+            FileTransform::SetExecutable(is_executable) => Some(*is_executable),
+            _ => None,
+        });
+
+        let tree = self.load_digest_trie(digest.clone()).await?;
+        let mut leaves = Vec::new();
+        let mut matched = Vec::new();
+        tree.walk(SymlinkBehavior::Aware, &mut |path, entry| {
+            let is_leaf = match entry {
+                directory::Entry::Directory(d) => {
+                    d.tree().entries().is_empty() && path.components().next().is_some()
+                }
+                directory::Entry::File(f) => {
+                    if matcher.matches(path) {
+                        matched.push((path.to_owned(), f.digest()));
+                    }
+                    true
+                }
+                directory::Entry::Symlink(_) => true,
+            };
+            if is_leaf {
+                leaves.push((path.to_owned(), entry.clone()));
+            }
+        });
+        if matched.is_empty() {
+            return Ok(digest);
+        }
+
+        let transforms = Arc::new(transforms);
+        let transformed = future::try_join_all(matched.into_iter().map(|(path, digest)| {
+            let transforms = transforms.clone();
+            async move {
+                if !transforms_content {
+                    return Ok::<_, StoreError>((path, digest));
+                }
+                let content = self
+                    .load_file_bytes_with(digest, move |bytes| {
+                        transform_content(&transforms, bytes)
+                    })
+                    .await?;
+                let digest = match content {
+                    Some(content) => self.store_file_bytes(content, true).await?,
+                    None => digest,
+                };
+                Ok((path, digest))
+            }
+        }))
+        .await?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        let mut file_digests: HashMap<PathBuf, Digest> = HashMap::new();
+        let typed_paths = leaves
+            .iter()
+            .map(|(path, entry)| match entry {
+                directory::Entry::File(f) => {
+                    let (digest, is_executable) = match transformed.get(path) {
+                        Some(digest) => (*digest, set_executable.unwrap_or(f.is_executable())),
+                        None => (f.digest(), f.is_executable()),
+                    };
+                    file_digests.insert(path.clone(), digest);
+                    TypedPath::File {
+                        path,
+                        is_executable,
+                    }
+                }
+                directory::Entry::Symlink(s) => TypedPath::Link {
+                    path,
+                    target: s.target(),
+                },
+                directory::Entry::Directory(_) => TypedPath::Dir(path),
+            })
+            .collect();
+        Ok(DigestTrie::from_unique_paths(typed_paths, &file_digests)?.into())
+    }
+}
+
+///
+/// Applies the content transforms to the given bytes, returning None if the content is unchanged.
+///
+fn transform_content(transforms: &[FileTransform], bytes: &[u8]) -> Option<Bytes> {
+    let mut content = Cow::Borrowed(bytes);
+    for transform in transforms {
+        match transform {
+            FileTransform::NormalizeLineEndings(line_ending) => {
+                content = Cow::Owned(normalize_line_endings(&content, *line_ending));
+            }
+            FileTransform::Substitute(placeholders) => {
+                content = Cow::Owned(substitute(&content, placeholders));
+            }
+            FileTransform::SetExecutable(_) => (),
+        }
+    }
+    if *content == *bytes {
+        None
+    } else {
+        Some(Bytes::from(content.into_owned()))
+    }
+}
+
+fn normalize_line_endings(bytes: &[u8], line_ending: LineEnding) -> Vec<u8> {
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut rest = bytes;
+    while let Some(idx) = rest.iter().position(|b| *b == b'\n') {
+        let line = &rest[..idx];
+        result.extend_from_slice(line.strip_suffix(b"\r").unwrap_or(line));
+        result.extend_from_slice(line_ending.as_bytes());
+        rest = &rest[idx + 1..];
+    }
+    result.extend_from_slice(rest);
+    result
+}
+
+fn substitute(bytes: &[u8], placeholders: &BTreeMap<String, String>) -> Vec<u8> {
+    // Sort by descending length so that the longest placeholder at a position wins.
+    let placeholders = placeholders
+        .iter()
+        .map(|(placeholder, value)| (placeholder.as_bytes(), value.as_bytes()))
+        .sorted_by_key(|(placeholder, _)| Reverse(placeholder.len()))
+        .collect::<Vec<_>>();
+
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    'outer: while idx < bytes.len() {
+        for (placeholder, value) in &placeholders {
+            if bytes[idx..].starts_with(placeholder) {
+                result.extend_from_slice(value);
+                idx += placeholder.len();
+                continue 'outer;
+            }
+        }
+        result.push(bytes[idx]);
+        idx += 1;
+    }
+    result
+}
@@ -0,0 +1,170 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::collections::BTreeMap;
+
+use fs::{directory, DirectoryDigest, FilespecMatcher, SymlinkBehavior};
+use tempfile::TempDir;
+
+use crate::tests::{new_local_store, store_files};
+use crate::{FileTransform, LineEnding, Store};
+
+async fn load_files(store: &Store, digest: DirectoryDigest) -> Vec<(String, String, bool)> {
+    let tree = store.load_digest_trie(digest).await.unwrap();
+    let mut files = Vec::new();
+    tree.walk(SymlinkBehavior::Aware, &mut |path, entry| {
+        if let directory::Entry::File(f) = entry {
+            files.push((path.to_owned(), f.digest(), f.is_executable()));
+        }
+    });
+    let mut result = Vec::new();
+    for (path, digest, is_executable) in files {
+        let content = store
+            .load_file_bytes_with(digest, |bytes| String::from_utf8(bytes.to_vec()).unwrap())
+            .await
+            .unwrap();
+        result.push((path.display().to_string(), content, is_executable));
+    }
+    result
+}
+
+fn matcher(includes: &[&str], excludes: &[&str]) -> FilespecMatcher {
+    FilespecMatcher::new(
+        includes.iter().map(|s| s.to_string()).collect(),
+        excludes.iter().map(|s| s.to_string()).collect(),
+    )
+    .unwrap()
+}
+
+fn owned(files: &[(&str, &str, bool)]) -> Vec<(String, String, bool)> {
+    files
+        .iter()
+        .map(|(path, content, is_executable)| {
+            (path.to_string(), content.to_string(), *is_executable)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn transform_selected_files() {
+    let store_dir = TempDir::new().unwrap();
+    let store = new_local_store(store_dir.path());
+    let digest = store_files(
+        &store,
+        &[
+            ("bin/run.sh", "echo @VERSION@\r\n", false),
+            (
+                "src/version.txt",
+                "@VERSION@-@VERSION_SUFFIX@\r\nend",
+                false,
+            ),
+            ("src/untouched.txt", "@VERSION@\r\n", false),
+        ],
+    )
+    .await;
+
+    let placeholders = BTreeMap::from([
+        ("@VERSION@".to_owned(), "1.2.3".to_owned()),
+        ("@VERSION_SUFFIX@".to_owned(), "@VERSION@".to_owned()),
+    ]);
+    let result = store
+        .transform_files(
+            digest,
+            &matcher(&["**/*.sh", "src/*.txt"], &["src/untouched.txt"]),
+            vec![
+                FileTransform::NormalizeLineEndings(LineEnding::Lf),
+                FileTransform::Substitute(placeholders),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        load_files(&store, result).await,
+        owned(&[
+            ("bin/run.sh", "echo 1.2.3\n", false),
+            ("src/untouched.txt", "@VERSION@\r\n", false),
+            // Substituted values are not themselves searched for placeholders.
+            ("src/version.txt", "1.2.3-@VERSION@\nend", false),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn transform_line_endings_and_modes() {
+    let store_dir = TempDir::new().unwrap();
+    let store = new_local_store(store_dir.path());
+    let digest = store_files(
+        &store,
+        &[
+            ("a.bat", "one\ntwo\r\nthree", false),
+            ("b.sh", "#!/bin/sh\n", true),
+        ],
+    )
+    .await;
+
+    let result = store
+        .transform_files(
+            digest.clone(),
+            &matcher(&["*.bat"], &[]),
+            vec![
+                FileTransform::NormalizeLineEndings(LineEnding::Crlf),
+                FileTransform::SetExecutable(true),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        load_files(&store, result).await,
+        owned(&[
+            ("a.bat", "one\r\ntwo\r\nthree", true),
+            ("b.sh", "#!/bin/sh\n", true),
+        ])
+    );
+
+    // Mode changes alone do not need to load content.
+    let result = store
+        .transform_files(
+            digest.clone(),
+            &matcher(&["*"], &[]),
+            vec![FileTransform::SetExecutable(false)],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        load_files(&store, result).await,
+        owned(&[
+            ("a.bat", "one\ntwo\r\nthree", false),
+            ("b.sh", "#!/bin/sh\n", false),
+        ])
+    );
+
+    // If nothing matches, the input is returned unchanged.
+    let result = store
+        .transform_files(
+            digest.clone(),
+            &matcher(&["*.txt"], &[]),
+            vec![FileTransform::SetExecutable(true)],
+        )
+        .await
+        .unwrap();
+    assert_eq!(result, digest);
+}
+
+#[tokio::test]
+async fn transform_empty_placeholder() {
+    let store_dir = TempDir::new().unwrap();
+    let store = new_local_store(store_dir.path());
+    let digest = store_files(&store, &[("a.txt", "a", false)]).await;
+
+    let err = store
+        .transform_files(
+            digest,
+            &matcher(&["*"], &[]),
+            vec![FileTransform::Substitute(BTreeMap::from([(
+                "".to_owned(),
+                "b".to_owned(),
+            )]))],
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("empty placeholder"), "{err}");
+}
@@ -392,7 +392,9 @@ impl<N: Node> Entry<N> {
             }
         };
 
-        let _join = context2.graph().executor.clone().native_spawn(async move {
+        let named_entry = entry2.clone();
+        let name = move || format!("node {}", named_entry.node());
+        let _join = context2.graph().executor.clone().native_spawn_named(name, async move {
       let mut run_or_clean = pin!(run_or_clean);
       let (maybe_res, dep_state) = loop {
         tokio::select! {
@@ -433,6 +435,7 @@ impl<N: Node> Entry<N> {
       // The node completed or was cleaned.
       entry2.complete(
         &context2,
+        entry_id,
         run_token,
         sender,
         dep_state.generations,
@@ -502,6 +505,20 @@ impl<N: Node> Entry<N> {
             _ => (),
         };
 
+        // While the Graph is draining, Nodes which are not already running may not be started.
+        if context.graph().is_draining() {
+            let generation = match *state {
+                EntryState::NotStarted { generation, .. }
+                | EntryState::Running { generation, .. }
+                | EntryState::Completed { generation, .. } => generation,
+            };
+            let err = N::Error::generic(format!(
+                "Could not start `{}`: the engine is shutting down.",
+                self.node
+            ));
+            return future::ready((Err(err), generation, true)).boxed();
+        }
+
         // Otherwise, we'll need to swap the state of the Node, so take it by value.
         let (next_state, receiver, generation) =
             match mem::replace(&mut *state, EntryState::initial()) {
@@ -625,6 +642,7 @@ impl<N: Node> Entry<N> {
     fn complete(
         &self,
         context: &Context<N>,
+        entry_id: EntryId,
         result_run_token: RunToken,
         sender: AsyncValueSender<NodeResult<N>, NodeInterrupt<N>>,
         dep_generations: Vec<(EntryId, Generation)>,
@@ -648,6 +666,7 @@ impl<N: Node> Entry<N> {
             }
         }
 
+        let mut wasted_recompute = false;
         *state = match mem::replace(&mut *state, EntryState::initial()) {
             EntryState::Running {
                 run_token,
@@ -678,7 +697,12 @@ impl<N: Node> Entry<N> {
                         {
                             // Node was re-executed (ie not cleaned) and had a different result value.
                             generation = generation.next()
-                        };
+                        } else {
+                            // Node was re-executed, but had the same result value. Uncacheable Nodes
+                            // (and their dependents) re-execute in each Run regardless of
+                            // invalidation, so are not counted.
+                            wasted_recompute = !next_result.has_uncacheable_deps();
+                        }
                         sender.send((
                             Ok(next_result.as_ref().clone()),
                             generation,
@@ -719,6 +743,12 @@ impl<N: Node> Entry<N> {
             }
             s => s,
         };
+
+        if wasted_recompute {
+            // NB: The Graph lock must not be acquired while an Entry lock is held.
+            drop(state);
+            context.graph().record_wasted_recompute(entry_id);
+        }
     }
 
     ///
@@ -4,15 +4,18 @@
 mod context;
 mod entry;
 mod node;
+mod stability;
 
 use crate::entry::{Entry, Generation, RunToken};
+use crate::stability::OutputStability;
 
 use std::collections::VecDeque;
 use std::fs::File;
 use std::io::{self, BufWriter, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use fixedbitset::FixedBitSet;
 use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
@@ -29,9 +32,13 @@ use workunit_store::RunId;
 
 pub use crate::context::Context;
 pub use crate::node::{CompoundNode, EntryId, Node, NodeError};
+pub use crate::stability::WastedRecomputes;
 
 type PGraph<N> = DiGraph<Entry<N>, (), u32>;
 
+/// How often to check whether running Nodes have completed while draining.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct InvalidationResult {
     pub cleared: usize,
@@ -44,6 +51,8 @@ struct InnerGraph<N: Node> {
     nodes: Nodes<N>,
     pg: PGraph<N>,
     run_id_generator: u32,
+    // If enabled, the wasted recomputes of Nodes: see `Graph::set_output_stability_tracking`.
+    output_stability: Option<OutputStability>,
 }
 
 impl<N: Node> InnerGraph<N> {
@@ -264,6 +273,9 @@ impl<N: Node> InnerGraph<N> {
             if let Some(entry) = self.pg.node_weight_mut(*id) {
                 entry.clear(false);
             }
+            if let Some(output_stability) = self.output_stability.as_mut() {
+                output_stability.cleared(*id);
+            }
         }
         self.pg.retain_edges(|pg, edge| {
             if let Some((src, _)) = pg.edge_endpoints(edge) {
@@ -366,6 +378,7 @@ pub struct Graph<N: Node> {
     inner: Arc<Mutex<InnerGraph<N>>>,
     invalidation_delay: Duration,
     executor: Executor,
+    draining: Arc<AtomicBool>,
 }
 
 impl<N: Node> Graph<N> {
@@ -381,6 +394,7 @@ impl<N: Node> Graph<N> {
             nodes: HashMap::default(),
             pg: DiGraph::new(),
             run_id_generator: 0,
+            output_stability: None,
         }));
         let _join = executor.native_spawn(Self::cycle_check_task(Arc::downgrade(&inner)));
 
@@ -388,6 +402,7 @@ impl<N: Node> Graph<N> {
             inner,
             invalidation_delay,
             executor,
+            draining: Arc::default(),
         }
     }
 
@@ -434,6 +449,54 @@ impl<N: Node> Graph<N> {
         inner.nodes.len()
     }
 
+    ///
+    /// The number of Nodes which are currently running (or cleaning).
+    ///
+    pub fn running_len(&self) -> usize {
+        let inner = self.inner.lock();
+        inner.pg.node_weights().filter(|e| e.is_running()).count()
+    }
+
+    ///
+    /// The Nodes which are currently running (or cleaning).
+    ///
+    pub fn running_nodes(&self) -> Vec<N> {
+        let inner = self.inner.lock();
+        inner
+            .pg
+            .node_weights()
+            .filter(|e| e.is_running())
+            .map(|e| e.node().clone())
+            .collect()
+    }
+
+    ///
+    /// True if `drain` has been called: Nodes which are not already running will not be started.
+    ///
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    ///
+    /// Stops admitting new Nodes, and then waits at most `timeout` for the Nodes which are already
+    /// running to complete. Returns the number of Nodes which were still running at the timeout.
+    ///
+    /// Requests for Nodes which are not already running (or complete) will fail while the Graph is
+    /// draining, so the Graph is generally only drained before it is shut down.
+    ///
+    pub async fn drain(&self, timeout: Duration) -> usize {
+        self.draining.store(true, Ordering::SeqCst);
+        let deadline = Instant::now() + timeout;
+        loop {
+            let running = self.running_len();
+            let now = Instant::now();
+            if running == 0 || now >= deadline {
+                return running;
+            }
+            sleep(DRAIN_POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
     async fn get_inner(
         &self,
         src_id: Option<EntryId>,
@@ -576,7 +639,7 @@ impl<N: Node> Graph<N> {
                                 dep_entry.node(),
                                 entry.map(|e| e.node().to_string())
                             );
-                            Err(())
+                            Err(dep_id)
                         }
                     }
                 })
@@ -594,11 +657,14 @@ impl<N: Node> Graph<N> {
                 // Return true if any dep was uncacheable.
                 Ok(uncacheable_deps.into_iter().any(|u| u))
             }
-            Err(()) => {
+            Err(changed_dep) => {
                 // Cleaning failed.
                 //
                 // If the RunToken still matches, clear all edges of the Node before returning.
                 let mut inner = self.inner.lock();
+                if let Some(output_stability) = inner.output_stability.as_mut() {
+                    output_stability.cleaning_failed(entry_id, changed_dep);
+                }
                 if let Some(entry) = inner.entry_for_id_mut(entry_id) {
                     if entry.cleaning_failed(run_token).is_ok() {
                         // Clear the deps. We remove edges in reverse index order, because `remove_edge` is
@@ -637,6 +703,57 @@ impl<N: Node> Graph<N> {
         inner.invalidate_from_roots(log_dirtied, predicate)
     }
 
+    ///
+    /// Enables or disables tracking of "wasted recomputes": Nodes which re-run after having been
+    /// invalidated, but which then produce values identical to their previous values. Frequent
+    /// wasted recomputes of a kind of Node indicate that it is invalidated more often than its
+    /// output changes, and so that it might benefit from being split or made more incremental.
+    ///
+    /// Disabling tracking discards the wasted recomputes which have been recorded so far.
+    ///
+    pub fn set_output_stability_tracking(&self, enabled: bool) {
+        let mut inner = self.inner.lock();
+        match (enabled, inner.output_stability.is_some()) {
+            (true, false) => inner.output_stability = Some(OutputStability::default()),
+            (false, true) => inner.output_stability = None,
+            _ => {}
+        }
+    }
+
+    ///
+    /// Returns the wasted recomputes which have been recorded for each kind of Node since tracking
+    /// was enabled, in descending order by count. See `set_output_stability_tracking`.
+    ///
+    pub fn output_stability_report(&self) -> Vec<WastedRecomputes> {
+        let inner = self.inner.lock();
+        inner
+            .output_stability
+            .as_ref()
+            .map(OutputStability::report)
+            .unwrap_or_default()
+    }
+
+    ///
+    /// Records that the given Node re-ran and produced a value identical to its previous value.
+    ///
+    pub(crate) fn record_wasted_recompute(&self, entry_id: EntryId) {
+        let mut inner = self.inner.lock();
+        let InnerGraph {
+            pg,
+            output_stability,
+            ..
+        } = &mut *inner;
+        let Some(output_stability) = output_stability.as_mut() else {
+            return;
+        };
+        let trigger = output_stability
+            .trigger(entry_id)
+            .into_iter()
+            .filter_map(|id| pg.node_weight(id).map(|entry| entry.node().to_string()))
+            .collect();
+        output_stability.wasted_recompute(pg[entry_id].node().kind(), trigger);
+    }
+
     pub fn visualize(&self, roots: &[N], path: &Path, context: &Context<N>) -> io::Result<()> {
         let inner = self.inner.lock();
         inner.visualize(roots, path, context)
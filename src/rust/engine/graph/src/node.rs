@@ -57,6 +57,14 @@ pub trait Node: Clone + Debug + Display + Eq + Hash + Send + Sync + 'static {
         self.cacheable()
     }
 
+    ///
+    /// A name for the kind of this Node (for example, the rule that it runs), which is used to
+    /// aggregate Nodes in reports about the Graph.
+    ///
+    fn kind(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
     ///
     /// Creates an error instance that represents that a Node dependency was cyclic along the given
     /// path.
@@ -0,0 +1,75 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
+
+use crate::node::EntryId;
+
+///
+/// The Nodes of a particular kind which re-ran after being invalidated, but which produced values
+/// identical to their previous values: see `Graph::set_output_stability_tracking`.
+///
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WastedRecomputes {
+    pub kind: &'static str,
+    pub count: usize,
+    /// The most recent path of invalidation which led to a wasted recompute of this kind of Node,
+    /// from the Node which was invalidated to the Node which re-ran.
+    pub trigger: Vec<String>,
+}
+
+#[derive(Default)]
+pub(crate) struct OutputStability {
+    // For Nodes which failed to clean, the first dependency which was found to have changed.
+    changed_deps: HashMap<EntryId, EntryId>,
+    wasted: HashMap<&'static str, WastedRecomputes>,
+}
+
+impl OutputStability {
+    pub(crate) fn cleaning_failed(&mut self, entry_id: EntryId, changed_dep: EntryId) {
+        self.changed_deps.insert(entry_id, changed_dep);
+    }
+
+    pub(crate) fn cleared(&mut self, entry_id: EntryId) {
+        self.changed_deps.remove(&entry_id);
+    }
+
+    ///
+    /// Returns the path of invalidation which caused the given Node to re-run, from the Node which
+    /// was invalidated to the given Node.
+    ///
+    pub(crate) fn trigger(&self, entry_id: EntryId) -> Vec<EntryId> {
+        let mut path = vec![entry_id];
+        let mut visited = HashSet::default();
+        visited.insert(entry_id);
+        let mut current = entry_id;
+        while let Some(&dep) = self.changed_deps.get(&current) {
+            if !visited.insert(dep) {
+                break;
+            }
+            path.push(dep);
+            current = dep;
+        }
+        path.reverse();
+        path
+    }
+
+    pub(crate) fn wasted_recompute(&mut self, kind: &'static str, trigger: Vec<String>) {
+        let wasted = self.wasted.entry(kind).or_insert_with(|| WastedRecomputes {
+            kind,
+            count: 0,
+            trigger: vec![],
+        });
+        wasted.count += 1;
+        wasted.trigger = trigger;
+    }
+
+    ///
+    /// The wasted recomputes of each kind of Node, in descending order by count.
+    ///
+    pub(crate) fn report(&self) -> Vec<WastedRecomputes> {
+        let mut report = self.wasted.values().cloned().collect::<Vec<_>>();
+        report.sort_by(|a, b| b.count.cmp(&a.count).then(a.kind.cmp(b.kind)));
+        report
+    }
+}
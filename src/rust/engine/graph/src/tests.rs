@@ -107,6 +107,45 @@ async fn invalidate_and_rerun() {
     assert_eq!(context.runs(), vec![TNode::new(1), TNode::new(2)]);
 }
 
+#[tokio::test]
+async fn invalidate_and_rerun_identically() {
+    let graph = empty_graph();
+    graph.set_output_stability_tracking(true);
+    let context = graph.context(TContext::new());
+
+    // Create three nodes.
+    assert_eq!(
+        graph.create(TNode::new(2), &context).await,
+        Ok(vec![T(0, 0), T(1, 0), T(2, 0)])
+    );
+    assert_eq!(graph.output_stability_report(), vec![]);
+
+    // Clear the bottom Node, which re-runs and produces an identical value: a wasted recompute.
+    graph.invalidate_from_roots(true, |n| n.id == 0);
+    assert_eq!(
+        graph.create(TNode::new(2), &context).await,
+        Ok(vec![T(0, 0), T(1, 0), T(2, 0)])
+    );
+    let report = graph.output_stability_report();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].count, 1);
+    assert_eq!(report[0].trigger, vec![TNode::new(0).to_string()]);
+
+    // Clear it again and request with a different salt: all of the nodes re-run with different
+    // values, so no recomputes are wasted.
+    graph.invalidate_from_roots(true, |n| n.id == 0);
+    let context = graph.context(TContext::new().with_salt(1));
+    assert_eq!(
+        graph.create(TNode::new(2), &context).await,
+        Ok(vec![T(0, 1), T(1, 1), T(2, 1)])
+    );
+    assert_eq!(graph.output_stability_report()[0].count, 1);
+
+    // Disabling tracking discards the report.
+    graph.set_output_stability_tracking(false);
+    assert_eq!(graph.output_stability_report(), vec![]);
+}
+
 #[tokio::test]
 async fn invalidate_uncacheable() {
     let graph = empty_graph();
@@ -656,6 +695,101 @@ async fn canceled_on_loss_of_interest() {
     assert_eq!(vec![TNode::new(2), TNode::new(1),], context.aborts(),);
 }
 
+#[tokio::test]
+async fn drain_completes_running_nodes() {
+    let _logger = env_logger::try_init();
+    let graph = empty_graph();
+
+    // Delay the middle node after it has already requested its dependency.
+    let context = {
+        let mut delays = HashMap::new();
+        delays.insert(TNode::new(1), Duration::from_millis(500));
+        graph.context(TContext::new().with_delays_post(delays))
+    };
+    let run = {
+        let graph = graph.clone();
+        let context = context.clone();
+        tokio::spawn(async move { graph.create(TNode::new(2), &context).await })
+    };
+    sleep(Duration::from_millis(100)).await;
+
+    // Draining waits for the running nodes to complete successfully.
+    assert_eq!(graph.drain(Duration::from_secs(10)).await, 0);
+    assert_eq!(run.await.unwrap(), Ok(vec![T(0, 0), T(1, 0), T(2, 0)]));
+
+    // But new nodes may not be started.
+    assert_eq!(
+        graph.create(TNode::new(3), &context).await,
+        Err(TError::Error)
+    );
+}
+
+#[tokio::test]
+async fn drain_fails_nodes_which_request_new_nodes() {
+    let _logger = env_logger::try_init();
+    let graph = empty_graph();
+
+    // Delay the middle node before it has requested its dependency.
+    let context = {
+        let mut delays = HashMap::new();
+        delays.insert(TNode::new(1), Duration::from_millis(500));
+        graph.context(TContext::new().with_delays_pre(delays))
+    };
+    let run = {
+        let graph = graph.clone();
+        let context = context.clone();
+        tokio::spawn(async move { graph.create(TNode::new(2), &context).await })
+    };
+    sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(graph.drain(Duration::from_secs(10)).await, 0);
+    assert_eq!(run.await.unwrap(), Err(TError::Error));
+}
+
+#[tokio::test]
+async fn drain_times_out() {
+    let _logger = env_logger::try_init();
+    let graph = empty_graph();
+
+    let context = {
+        let mut delays = HashMap::new();
+        delays.insert(TNode::new(1), Duration::from_millis(2000));
+        graph.context(TContext::new().with_delays_post(delays))
+    };
+    let graph2 = graph.clone();
+    let _run = tokio::spawn(async move { graph2.create(TNode::new(2), &context).await });
+    sleep(Duration::from_millis(100)).await;
+
+    // Both the middle and top nodes are still running at the timeout.
+    assert_eq!(graph.drain(Duration::from_millis(100)).await, 2);
+}
+
+#[tokio::test]
+async fn running_nodes() {
+    let _logger = env_logger::try_init();
+    let graph = empty_graph();
+
+    let context = {
+        let mut delays = HashMap::new();
+        delays.insert(TNode::new(1), Duration::from_millis(500));
+        graph.context(TContext::new().with_delays_post(delays))
+    };
+    let run = {
+        let graph = graph.clone();
+        let context = context.clone();
+        tokio::spawn(async move { graph.create(TNode::new(2), &context).await })
+    };
+    sleep(Duration::from_millis(100)).await;
+
+    // The middle and top nodes are running, but the completed bottom node is not.
+    let mut running = graph.running_nodes();
+    running.sort_by_key(|n| n.id);
+    assert_eq!(running, vec![TNode::new(1), TNode::new(2)]);
+
+    run.await.unwrap().unwrap();
+    assert_eq!(graph.running_nodes(), vec![]);
+}
+
 #[tokio::test]
 async fn clean_speculatively() {
     let _logger = env_logger::try_init();
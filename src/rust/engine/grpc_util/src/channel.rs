@@ -1,23 +1,26 @@
 // Copyright 2023 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use http::{Request, Response, Uri};
-use hyper::client::{HttpConnector, ResponseFuture};
+use hyper::client::ResponseFuture;
 use hyper_rustls::HttpsConnector;
 use rustls::ClientConfig;
 use tonic::body::BoxBody;
 use tower_service::Service;
 
+use crate::proxy::{ProxyConfig, ProxyConnector};
+
 // Inspired by https://github.com/LucioFranco/tonic-openssl/blob/master/example/src/client2.rs.
 
 /// Enumeration wrapping the HTTP and HTTPS clients so they can be treated equivalently by
 /// `Channel`.
 #[derive(Clone, Debug)]
 pub enum Client {
-    Plain(hyper::Client<HttpConnector, BoxBody>),
-    Tls(hyper::Client<HttpsConnector<HttpConnector>, BoxBody>),
+    Plain(hyper::Client<ProxyConnector, BoxBody>),
+    Tls(hyper::Client<HttpsConnector<ProxyConnector>, BoxBody>),
 }
 
 /// A communication channel which may either communicate using HTTP or HTTP over TLS. This
@@ -27,6 +30,8 @@ pub enum Client {
 /// This strategy is necessary because Tonic removed the ability to pass in a raw `rustls`
 /// configuration, and so Pants must implement its own connection setup logic to be able to
 /// continue to use `rustls` directly.
+///
+/// Connections are tunneled through the proxy which `proxy` configures for the address, if any.
 #[derive(Clone, Debug)]
 pub struct Channel {
     client: Client,
@@ -37,14 +42,11 @@ impl Channel {
     pub async fn new(
         tls_config: Option<&ClientConfig>,
         uri: Uri,
+        proxy: Arc<ProxyConfig>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
+        let http = ProxyConnector::new(proxy);
         let client = match tls_config {
-            None => {
-                let mut http = HttpConnector::new();
-                http.enforce_http(false);
-
-                Client::Plain(hyper::Client::builder().http2_only(true).build(http))
-            }
+            None => Client::Plain(hyper::Client::builder().http2_only(true).build(http)),
             Some(tls_config) => {
                 let tls_config = tls_config.to_owned();
 
@@ -52,7 +54,7 @@ impl Channel {
                     .with_tls_config(tls_config)
                     .https_or_http()
                     .enable_http2()
-                    .build();
+                    .wrap_connector(http);
 
                 Client::Tls(hyper::Client::builder().http2_only(true).build(https))
             }
@@ -131,7 +133,7 @@ mod tests {
 
         let uri = Uri::try_from(format!("http://{}", addr)).unwrap();
 
-        let mut channel = Channel::new(None, uri).await.unwrap();
+        let mut channel = Channel::new(None, uri, Arc::default()).await.unwrap();
 
         let request = Request::builder()
             .uri(format!("http://{}", addr))
@@ -175,7 +177,9 @@ mod tests {
             .with_custom_certificate_verifier(Arc::new(NoVerifier))
             .with_no_client_auth();
 
-        let mut channel = Channel::new(Some(&tls_config), uri).await.unwrap();
+        let mut channel = Channel::new(Some(&tls_config), uri, Arc::default())
+            .await
+            .unwrap();
 
         let request = Request::builder()
             .uri(format!("https://{}", addr))
@@ -278,7 +282,9 @@ mod tests {
 
         let tls_config: rustls::ClientConfig = tls_config.try_into().unwrap();
 
-        let mut channel = Channel::new(Some(&tls_config), uri).await.unwrap();
+        let mut channel = Channel::new(Some(&tls_config), uri, Arc::default())
+            .await
+            .unwrap();
 
         match &channel.client {
             super::Client::Plain(_) => panic!("Expected a TLS client"),
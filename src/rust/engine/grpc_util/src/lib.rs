@@ -32,6 +32,9 @@ pub mod headers;
 pub mod hyper_util;
 pub mod metrics;
 pub mod prost;
+pub mod proxy;
+pub mod resilience;
+pub mod resource_name;
 pub mod retry;
 pub mod tls;
 
@@ -76,7 +79,7 @@ pub async fn create_channel(
     tls_config: Option<&ClientConfig>,
 ) -> Result<Channel, String> {
     let uri = Uri::try_from(addr).map_err(|err| format!("invalid address: {err}"))?;
-    Channel::new(tls_config, uri)
+    Channel::new(tls_config, uri, proxy::global())
         .await
         .map_err(|err| format!("gRPC connection error: {err}"))
 }
@@ -0,0 +1,442 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::Future;
+use parking_lot::Mutex;
+use rand::{thread_rng, Rng};
+use tonic::{Code, Status};
+use workunit_store::{increment_counter_if_in_workunit, Metric};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(20);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// The kind of an RPC, which determines its deadline.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CallType {
+    /// A unary read, such as `GetActionResult` or `FindMissingBlobs`.
+    Read,
+    /// A unary write, such as `UpdateActionResult` or `BatchUpdateBlobs`.
+    Write,
+    /// A streaming transfer of a blob, which is given the (longer) `stream_deadline` regardless of
+    /// the size of the blob.
+    Stream,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CircuitBreakerOptions {
+    /// The number of consecutive failed calls after which the breaker opens.
+    pub failure_threshold: usize,
+    /// How long the breaker stays open before a single probe call is allowed through.
+    pub cooldown: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct ResilienceOptions {
+    pub read_deadline: Duration,
+    pub write_deadline: Duration,
+    pub stream_deadline: Duration,
+    /// The maximum number of attempts for each call, including the first.
+    pub max_attempts: usize,
+    pub circuit_breaker: Option<CircuitBreakerOptions>,
+}
+
+impl ResilienceOptions {
+    fn deadline(&self, call_type: CallType) -> Duration {
+        match call_type {
+            CallType::Read => self.read_deadline,
+            CallType::Write => self.write_deadline,
+            CallType::Stream => self.stream_deadline,
+        }
+    }
+}
+
+/// An error type which may wrap a gRPC `Status`.
+pub trait RpcError {
+    fn from_status(status: Status) -> Self;
+
+    fn status(&self) -> Option<&Status>;
+}
+
+impl RpcError for Status {
+    fn from_status(status: Status) -> Self {
+        status
+    }
+
+    fn status(&self) -> Option<&Status> {
+        Some(self)
+    }
+}
+
+/// Whether the given status indicates that the service is unavailable (rather than that the
+/// particular request failed), and so should count towards opening a circuit breaker.
+pub fn status_is_unavailable(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unavailable | Code::ResourceExhausted | Code::DeadlineExceeded
+    )
+}
+
+///
+/// Applies a consistent policy of deadlines, retries and circuit breaking to the RPCs made by a
+/// client of a remote service.
+///
+/// Each attempt of a call is bounded by the deadline for its `CallType`. Failed attempts which
+/// the caller considers retryable are retried with exponential backoff and full jitter, up to
+/// `max_attempts` in total.
+///
+/// If a circuit breaker is configured, then after `failure_threshold` consecutive calls fail
+/// because the service is unavailable, the breaker opens and calls fail immediately (without
+/// contacting the service) until the cooldown has elapsed. A single probe call is then allowed
+/// through: if it succeeds the breaker closes, and otherwise it opens again.
+///
+#[derive(Clone)]
+pub struct Resilience {
+    name: &'static str,
+    options: ResilienceOptions,
+    timeout_metric: Option<Metric>,
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl Resilience {
+    pub fn new(
+        name: &'static str,
+        options: ResilienceOptions,
+        timeout_metric: Option<Metric>,
+    ) -> Self {
+        let breaker = options
+            .circuit_breaker
+            .map(|options| Arc::new(CircuitBreaker::new(options)));
+        Resilience {
+            name,
+            options,
+            timeout_metric,
+            breaker,
+        }
+    }
+
+    ///
+    /// Calls `f` (with a clone of the client and the index of the attempt) until it succeeds, fails
+    /// with an error that `is_retryable` rejects, or runs out of attempts.
+    ///
+    pub async fn call<T, E, C, F, G, Fut>(
+        &self,
+        call_type: CallType,
+        client: C,
+        mut f: F,
+        is_retryable: G,
+    ) -> Result<T, E>
+    where
+        E: RpcError,
+        C: Clone,
+        F: FnMut(C, u32) -> Fut,
+        G: Fn(&E) -> bool,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        if let Some(breaker) = &self.breaker {
+            if !breaker.try_acquire() {
+                return Err(E::from_status(Status::unavailable(format!(
+                    "Calls to the {} are temporarily disabled after repeated failures.",
+                    self.name
+                ))));
+            }
+        }
+
+        let deadline = self.options.deadline(call_type);
+        let max_attempts = self.options.max_attempts.max(1) as u32;
+        let mut attempt = 0;
+        let result = loop {
+            if attempt > 0 {
+                tokio::time::sleep(backoff(attempt)).await;
+            }
+
+            let result = match tokio::time::timeout(deadline, f(client.clone(), attempt)).await {
+                Ok(result) => result,
+                Err(_) => {
+                    if let Some(metric) = self.timeout_metric {
+                        increment_counter_if_in_workunit(metric, 1);
+                    }
+                    Err(E::from_status(Status::deadline_exceeded(format!(
+                        "{call_type:?} call to the {} exceeded its deadline of {deadline:?}",
+                        self.name
+                    ))))
+                }
+            };
+
+            attempt += 1;
+            match result {
+                Err(err) if attempt < max_attempts && is_retryable(&err) => continue,
+                result => break result,
+            }
+        };
+
+        if let Some(breaker) = &self.breaker {
+            let unavailable = match &result {
+                Ok(_) => false,
+                Err(err) => err.status().map(status_is_unavailable).unwrap_or(false),
+            };
+            breaker.record(self.name, unavailable);
+        }
+        result
+    }
+}
+
+/// Computes a delay with full jitter before the given (non-zero) attempt.
+fn backoff(attempt: u32) -> Duration {
+    let max = INITIAL_BACKOFF
+        .saturating_mul(2_u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF);
+    thread_rng().gen_range(Duration::ZERO..=max)
+}
+
+enum BreakerState {
+    Closed {
+        consecutive_failures: usize,
+    },
+    Open {
+        until: Instant,
+    },
+    /// A probe call is in flight. If the probe is cancelled before it records its result, another
+    /// probe is allowed once a further cooldown has elapsed.
+    Probing {
+        since: Instant,
+    },
+}
+
+struct CircuitBreaker {
+    options: CircuitBreakerOptions,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    fn new(options: CircuitBreakerOptions) -> Self {
+        CircuitBreaker {
+            options,
+            state: Mutex::new(BreakerState::Closed {
+                consecutive_failures: 0,
+            }),
+        }
+    }
+
+    /// Returns true if a call may proceed.
+    fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock();
+        let now = Instant::now();
+        match *state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { until } if now < until => false,
+            BreakerState::Probing { since } if now < since + self.options.cooldown => false,
+            BreakerState::Open { .. } | BreakerState::Probing { .. } => {
+                *state = BreakerState::Probing { since: now };
+                true
+            }
+        }
+    }
+
+    fn record(&self, name: &str, unavailable: bool) {
+        let mut state = self.state.lock();
+        match (&*state, unavailable) {
+            (BreakerState::Probing { .. }, false) => {
+                log::info!("The {name} is available again: re-enabling calls to it.");
+                *state = BreakerState::Closed {
+                    consecutive_failures: 0,
+                };
+            }
+            (_, false) => {
+                *state = BreakerState::Closed {
+                    consecutive_failures: 0,
+                };
+            }
+            (BreakerState::Probing { .. }, true) | (BreakerState::Open { .. }, true) => {
+                *state = BreakerState::Open {
+                    until: Instant::now() + self.options.cooldown,
+                };
+            }
+            (
+                BreakerState::Closed {
+                    consecutive_failures,
+                },
+                true,
+            ) => {
+                let consecutive_failures = consecutive_failures + 1;
+                if consecutive_failures >= self.options.failure_threshold {
+                    log::warn!(
+                        "Disabling calls to the {name} for {:?} after {consecutive_failures} \
+                         consecutive failures.",
+                        self.options.cooldown
+                    );
+                    *state = BreakerState::Open {
+                        until: Instant::now() + self.options.cooldown,
+                    };
+                } else {
+                    *state = BreakerState::Closed {
+                        consecutive_failures,
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use parking_lot::Mutex;
+    use tonic::{Code, Status};
+
+    use super::{CallType, CircuitBreakerOptions, Resilience, ResilienceOptions};
+
+    #[derive(Clone, Debug)]
+    struct MockClient {
+        values: Arc<Mutex<VecDeque<Result<isize, Status>>>>,
+    }
+
+    impl MockClient {
+        fn new(values: Vec<Result<isize, Status>>) -> Self {
+            MockClient {
+                values: Arc::new(Mutex::new(values.into())),
+            }
+        }
+
+        async fn next(&self) -> Result<isize, Status> {
+            self.values.lock().pop_front().unwrap()
+        }
+
+        fn remaining(&self) -> usize {
+            self.values.lock().len()
+        }
+    }
+
+    fn resilience(
+        max_attempts: usize,
+        circuit_breaker: Option<CircuitBreakerOptions>,
+    ) -> Resilience {
+        Resilience::new(
+            "mock service",
+            ResilienceOptions {
+                read_deadline: Duration::from_millis(100),
+                write_deadline: Duration::from_secs(5),
+                stream_deadline: Duration::from_secs(5),
+                max_attempts,
+                circuit_breaker,
+            },
+            None,
+        )
+    }
+
+    async fn call(resilience: &Resilience, client: &MockClient) -> Result<isize, Status> {
+        resilience
+            .call(
+                CallType::Write,
+                client.clone(),
+                |client, _| async move { client.next().await },
+                |status: &Status| status.code() == Code::Unavailable,
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn retries_up_to_max_attempts() {
+        let resilience = resilience(3, None);
+
+        let client = MockClient::new(vec![
+            Err(Status::unavailable("first")),
+            Err(Status::unavailable("second")),
+            Ok(3),
+            Ok(4),
+        ]);
+        assert_eq!(call(&resilience, &client).await.unwrap(), 3);
+        assert_eq!(client.remaining(), 1);
+
+        let client = MockClient::new(vec![
+            Err(Status::unavailable("first")),
+            Err(Status::invalid_argument("second")),
+            Ok(3),
+        ]);
+        assert_eq!(
+            call(&resilience, &client).await.unwrap_err().message(),
+            "second"
+        );
+        assert_eq!(client.remaining(), 1);
+
+        let client = MockClient::new(vec![
+            Err(Status::unavailable("first")),
+            Err(Status::unavailable("second")),
+            Err(Status::unavailable("third")),
+            Ok(1),
+        ]);
+        assert_eq!(
+            call(&resilience, &client).await.unwrap_err().message(),
+            "third"
+        );
+        assert_eq!(client.remaining(), 1);
+    }
+
+    #[tokio::test]
+    async fn deadline_per_call_type() {
+        let resilience = resilience(1, None);
+        let result: Result<(), Status> = resilience
+            .call(
+                CallType::Read,
+                (),
+                |_, _| async move {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    Ok(())
+                },
+                |_| true,
+            )
+            .await;
+        assert_eq!(result.unwrap_err().code(), Code::DeadlineExceeded);
+    }
+
+    #[tokio::test]
+    async fn circuit_breaker_opens_and_probes() {
+        let resilience = resilience(
+            1,
+            Some(CircuitBreakerOptions {
+                failure_threshold: 2,
+                cooldown: Duration::from_millis(200),
+            }),
+        );
+        let client = MockClient::new(vec![
+            // A failure which does not indicate unavailability does not count.
+            Err(Status::not_found("missing")),
+            Err(Status::unavailable("first")),
+            Err(Status::unavailable("second")),
+            // The first probe fails, and so the breaker opens again.
+            Err(Status::unavailable("probe")),
+            Ok(1),
+            Ok(2),
+        ]);
+
+        assert_eq!(
+            call(&resilience, &client).await.unwrap_err().message(),
+            "missing"
+        );
+        call(&resilience, &client).await.unwrap_err();
+        call(&resilience, &client).await.unwrap_err();
+
+        // The breaker is open: calls fail without reaching the client.
+        let err = call(&resilience, &client).await.unwrap_err();
+        assert_eq!(err.code(), Code::Unavailable);
+        assert!(err.message().contains("temporarily disabled"), "{err:?}");
+        assert_eq!(client.remaining(), 3);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(
+            call(&resilience, &client).await.unwrap_err().message(),
+            "probe"
+        );
+        call(&resilience, &client).await.unwrap_err();
+        assert_eq!(client.remaining(), 2);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(call(&resilience, &client).await.unwrap(), 1);
+        assert_eq!(call(&resilience, &client).await.unwrap(), 2);
+    }
+}
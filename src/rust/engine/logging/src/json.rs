@@ -0,0 +1,122 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use log::Record;
+use parking_lot::Mutex;
+use serde_json::json;
+use stdio::RawFd;
+use workunit_store::get_workunit_store_handle;
+
+///
+/// Configuration for the machine-readable (JSON lines) log sink, which runs in parallel to the
+/// human-readable logger.
+///
+#[derive(Clone, Debug, Default)]
+pub struct JsonLogConfig {
+    /// If true, each per-run log file is accompanied by a JSON lines file with a `.json` suffix.
+    pub per_run: bool,
+    /// An already-open file descriptor to write JSON lines to for the lifetime of the process.
+    pub fd: Option<RawFd>,
+}
+
+pub(crate) struct JsonLogSink {
+    per_run: bool,
+    per_run_file: Mutex<Option<File>>,
+    fd_file: Mutex<Option<File>>,
+}
+
+impl JsonLogSink {
+    pub(crate) fn new(config: JsonLogConfig) -> Result<JsonLogSink, String> {
+        let fd_file = config
+            .fd
+            .map(|fd| {
+                // NB: We duplicate the file descriptor rather than taking ownership of it, so that the
+                // caller's descriptor remains open if the logger is re-initialized.
+                stdio::try_clone_fd(fd)
+                    .map_err(|e| format!("Error opening file descriptor {fd} for JSON logs: {e}"))
+            })
+            .transpose()?;
+        Ok(JsonLogSink {
+            per_run: config.per_run,
+            per_run_file: Mutex::default(),
+            fd_file: Mutex::new(fd_file),
+        })
+    }
+
+    pub(crate) fn disabled() -> JsonLogSink {
+        JsonLogSink {
+            per_run: false,
+            per_run_file: Mutex::default(),
+            fd_file: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.per_run_file.lock().is_some() || self.fd_file.lock().is_some()
+    }
+
+    ///
+    /// Opens (or closes, if None) the per-run JSON log file which accompanies the given per-run
+    /// log file.
+    ///
+    pub(crate) fn set_per_run_log(&self, per_run_log_path: Option<&Path>) -> Result<(), String> {
+        let file = match per_run_log_path {
+            Some(path) if self.per_run => {
+                let mut json_path = path.as_os_str().to_owned();
+                json_path.push(".json");
+                Some(
+                    OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&json_path)
+                        .map_err(|err| format!("Error opening per-run JSON logfile: {err}"))?,
+                )
+            }
+            _ => None,
+        };
+        *self.per_run_file.lock() = file;
+        Ok(())
+    }
+
+    pub(crate) fn log(&self, record: &Record, message: &str) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut line = render(record, message);
+        line.push('\n');
+        // Errors are deliberately ignored: the human-readable logger is the source of truth.
+        if let Some(ref mut file) = *self.per_run_file.lock() {
+            let _ = file.write_all(line.as_bytes());
+        }
+        if let Some(ref mut file) = *self.fd_file.lock() {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+}
+
+///
+/// Renders a log record as a single line JSON object.
+///
+pub(crate) fn render(record: &Record, message: &str) -> String {
+    let (workunit_id, session_id) = get_workunit_store_handle()
+        .map(|handle| {
+            (
+                handle.parent_id.map(|span_id| span_id.to_string()),
+                handle.store.session_id().map(str::to_owned),
+            )
+        })
+        .unwrap_or_default();
+    json!({
+        "timestamp": chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": message,
+        "workunit_id": workunit_id,
+        "session_id": session_id,
+    })
+    .to_string()
+}
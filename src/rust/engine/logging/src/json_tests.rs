@@ -0,0 +1,104 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use log::{Level, Record};
+use serde_json::Value;
+use tempfile::TempDir;
+use workunit_store::{SpanId, WorkunitStore, WorkunitStoreHandle};
+
+use crate::json::{render, JsonLogConfig, JsonLogSink};
+
+fn render_json(level: Level, target: &str, message: &str) -> Value {
+    let line = render(
+        &Record::builder()
+            .level(level)
+            .target(target)
+            .args(format_args!("{message}"))
+            .build(),
+        message,
+    );
+    // Each record must be rendered as exactly one line.
+    assert!(!line.contains('\n'), "{line}");
+    serde_json::from_str(&line).unwrap()
+}
+
+#[test]
+fn messages_are_escaped() {
+    workunit_store::set_thread_workunit_store_handle(None);
+    let message = "a \"quoted\" C:\\path\nsecond line\ttab \u{1b}[31mred\u{1b}[0m ☃";
+
+    let json = render_json(Level::Warn, "pants.engine", message);
+    assert_eq!(json["message"], message);
+}
+
+#[test]
+fn records_have_structured_fields() {
+    workunit_store::set_thread_workunit_store_handle(None);
+    let json = render_json(Level::Info, "process_execution::local", "hello");
+    assert_eq!(json["level"], "INFO");
+    assert_eq!(json["target"], "process_execution::local");
+    assert_eq!(json["message"], "hello");
+    assert_eq!(json["workunit_id"], Value::Null);
+    assert_eq!(json["session_id"], Value::Null);
+    assert!(chrono::DateTime::parse_from_rfc3339(json["timestamp"].as_str().unwrap()).is_ok());
+}
+
+#[test]
+fn records_include_workunit_and_session() {
+    let parent_id = SpanId::new();
+    workunit_store::set_thread_workunit_store_handle(Some(WorkunitStoreHandle {
+        store: WorkunitStore::new(false, log::Level::Debug).with_session_id("session-1".to_owned()),
+        parent_id: Some(parent_id),
+    }));
+
+    let json = render_json(Level::Debug, "store", "hello");
+    assert_eq!(json["workunit_id"], parent_id.to_string());
+    assert_eq!(json["session_id"], "session-1");
+    workunit_store::set_thread_workunit_store_handle(None);
+}
+
+#[test]
+fn per_run_logs_are_written_alongside_log_file() {
+    workunit_store::set_thread_workunit_store_handle(None);
+    let dir = TempDir::new().unwrap();
+    let log_path = dir.path().join("run.log");
+    let sink = JsonLogSink::new(JsonLogConfig {
+        per_run: true,
+        fd: None,
+    })
+    .unwrap();
+    assert!(!sink.is_enabled());
+
+    sink.set_per_run_log(Some(&log_path)).unwrap();
+    assert!(sink.is_enabled());
+    for message in ["first", "second"] {
+        sink.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("pants")
+                .args(format_args!("{message}"))
+                .build(),
+            message,
+        );
+    }
+    sink.set_per_run_log(None).unwrap();
+    assert!(!sink.is_enabled());
+
+    let content = std::fs::read_to_string(dir.path().join("run.log.json")).unwrap();
+    let messages = content
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap()["message"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, vec!["first", "second"]);
+}
+
+#[test]
+fn per_run_logs_are_not_written_unless_configured() {
+    let dir = TempDir::new().unwrap();
+    let log_path = dir.path().join("run.log");
+    let sink = JsonLogSink::new(JsonLogConfig::default()).unwrap();
+
+    sink.set_per_run_log(Some(&log_path)).unwrap();
+    assert!(!sink.is_enabled());
+    assert!(!dir.path().join("run.log.json").exists());
+}
@@ -17,8 +17,13 @@ macro_rules! fatal_log {
     };
 }
 
+mod json;
+#[cfg(test)]
+mod json_tests;
 pub mod logger;
 
+pub use json::JsonLogConfig;
+
 pub type Logger = logger::PantsLogger;
 
 use num_enum::TryFromPrimitive;
@@ -1,5 +1,6 @@
 // Copyright 2018 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
+use crate::json::{JsonLogConfig, JsonLogSink};
 use crate::PythonLogLevel;
 
 use std::collections::HashMap;
@@ -27,6 +28,7 @@ lazy_static! {
 struct Inner {
     per_run_logs: Mutex<Option<File>>,
     log_file: Mutex<Option<File>>,
+    json_sink: JsonLogSink,
     global_level: LevelFilter,
     show_rust_3rdparty_logs: bool,
     show_target: bool,
@@ -42,6 +44,7 @@ impl PantsLogger {
         PantsLogger(ArcSwap::from(Arc::new(Inner {
             per_run_logs: Mutex::new(None),
             log_file: Mutex::new(None),
+            json_sink: JsonLogSink::disabled(),
             global_level: LevelFilter::Off,
             show_rust_3rdparty_logs: true,
             show_target: false,
@@ -59,6 +62,7 @@ impl PantsLogger {
         literal_filters: Vec<String>,
         regex_filters: Vec<Regex>,
         log_file_path: PathBuf,
+        json_log_config: JsonLogConfig,
     ) -> Result<(), String> {
         let log_level_filters = log_levels_by_target
             .iter()
@@ -81,10 +85,12 @@ impl PantsLogger {
             .append(true)
             .open(log_file_path)
             .map_err(|err| format!("Error opening pantsd logfile: {err}"))?;
+        let json_sink = JsonLogSink::new(json_log_config)?;
 
         PANTS_LOGGER.0.store(Arc::new(Inner {
             per_run_logs: Mutex::default(),
             log_file: Mutex::new(Some(log_file)),
+            json_sink,
             global_level,
             show_rust_3rdparty_logs,
             show_target,
@@ -106,6 +112,11 @@ impl PantsLogger {
     }
 
     pub fn set_per_run_logs(&self, per_run_log_path: Option<PathBuf>) {
+        self.0
+            .load()
+            .json_sink
+            .set_per_run_log(per_run_log_path.as_deref())
+            .unwrap();
         match per_run_log_path {
             None => {
                 *self.0.load().per_run_logs.lock() = None;
@@ -135,6 +146,14 @@ impl PantsLogger {
 
 impl Log for PantsLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
+        // Filters set for the current Destination (generally: for the current Session) take
+        // precedence over the global filters.
+        if let Some(log_filters) = stdio::get_destination().log_filters() {
+            if let Some(level) = log_filters.level_for(metadata.target()) {
+                return metadata.level() <= level;
+            }
+        }
+
         let inner = self.0.load();
         let enabled_globally = metadata.level() <= inner.global_level;
         let enabled_for_target = inner
@@ -182,6 +201,8 @@ impl Log for PantsLogger {
             return;
         }
 
+        inner.json_sink.log(record, &log_msg);
+
         let destination = stdio::get_destination();
 
         // Build the message string.
@@ -12,13 +12,30 @@ use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
 use tokio::signal::unix::{signal, Signal, SignalKind};
 
+use crate::events::{EventHandler, EventsListener};
+
 pub enum NailgunClientError {
     PreConnect(String),
     PostConnect(String),
+    ///
+    /// The connection to the server was lost before it returned a result (for example, because the
+    /// server was killed mid-run). `wrote_stdout` indicates whether any stdout had already been
+    /// written for the run, which determines whether it would be safe to replay.
+    ///
+    ConnectionLost {
+        message: String,
+        wrote_stdout: bool,
+    },
     BrokenPipe,
     KeyboardInterrupt,
 }
 
+/// A summary of the output that was handled for a run.
+struct ClientOutput {
+    wrote_stdout: bool,
+    interrupted: bool,
+}
+
 fn handle_postconnect_stdio(err: io::Error, msg: &str) -> NailgunClientError {
     if err.kind() == io::ErrorKind::BrokenPipe {
         // A BrokenPipe error is a semi-expected error caused when stdout/stderr closes, and which
@@ -33,15 +50,17 @@ async fn handle_client_output(
     mut stdio_read: impl Stream<Item = ChildOutput> + Unpin,
     mut signal_stream: Signal,
     child: &mut nails::client::Child,
-) -> Result<(), NailgunClientError> {
+) -> Result<ClientOutput, NailgunClientError> {
     let mut stdout = tokio::io::stdout();
     let mut stderr = tokio::io::stderr();
     let mut is_exiting = false;
+    let mut wrote_stdout = false;
     loop {
         tokio::select! {
           output = stdio_read.next() => {
             match output {
               Some(ChildOutput::Stdout(bytes)) => {
+                wrote_stdout |= !bytes.is_empty();
                 stdout.write_all(&bytes).await.map_err(|err| handle_postconnect_stdio(err, "Failed to write to stdout"))?
               },
               Some(ChildOutput::Stderr(bytes)) => {
@@ -66,7 +85,10 @@ async fn handle_client_output(
     }
     try_join!(stdout.flush(), stderr.flush())
         .map_err(|err| handle_postconnect_stdio(err, "Failed to flush stdio"))?;
-    Ok(())
+    Ok(ClientOutput {
+        wrote_stdout,
+        interrupted: is_exiting,
+    })
 }
 
 async fn handle_client_input(mut stdin_write: mpsc::Sender<ChildInput>) -> Result<(), io::Error> {
@@ -88,6 +110,9 @@ async fn handle_client_input(mut stdin_write: mpsc::Sender<ChildInput>) -> Resul
 ///   1. the first SIGINT will cause the client to attempt to exit gracefully.
 ///   2. the second SIGINT will eagerly exit the client without waiting for the server.
 ///
+/// If an `event_handler` is given, the server is asked to send structured events for the run,
+/// which are passed to the handler as they arrive (see `crate::events`).
+///
 /// NB: This method installs a signal handler that will affect signal handling throughout the
 /// entire process. Because of this, it should only be used in a process that is relatively
 /// dedicated to the task of connecting to a nailgun server.
@@ -96,13 +121,24 @@ pub async fn client_execute(
     port: u16,
     command: String,
     args: Vec<String>,
-    env: Vec<(String, String)>,
+    mut env: Vec<(String, String)>,
+    event_handler: Option<EventHandler>,
 ) -> Result<i32, NailgunClientError> {
     use nails::execution::{child_channel, Command};
 
     let working_dir =
         std::env::current_dir().map_err(|e| NailgunClientError::PreConnect(e.to_string()))?;
 
+    let events_listener = event_handler
+        .map(|handler| {
+            let listener = EventsListener::bind().map_err(|err| {
+                NailgunClientError::PreConnect(format!("Failed to listen for events: {err}"))
+            })?;
+            env.push(listener.env_entry());
+            Ok((listener, handler))
+        })
+        .transpose()?;
+
     let config = Config::default();
     let command = Command {
         command,
@@ -127,15 +163,29 @@ pub async fn client_execute(
     })
     .await
     .map_err(|err| NailgunClientError::PreConnect(format!("Failed to start: {err}")))?;
+    let events_reader = events_listener.map(|(listener, handler)| listener.spawn(handler));
 
-    handle_client_output(
+    let output = match handle_client_output(
         child.output_stream.take().unwrap(),
         signal_stream,
         &mut child,
     )
-    .await?;
+    .await
+    {
+        Ok(output) => output,
+        Err(err) => {
+            if let Some(events_reader) = events_reader {
+                events_reader.abort();
+            }
+            return Err(err);
+        }
+    };
 
-    let exit_code: ExitCode = child.wait().await.map_err(|err| {
+    let exit_code = child.wait().await;
+    if let Some(events_reader) = events_reader {
+        events_reader.drain().await;
+    }
+    let exit_code: ExitCode = exit_code.map_err(|err| {
     let err_str = match err.to_string().as_str() {
       "Client exited before the server's result could be returned." => {
         "The pantsd process was killed during the run.\n\nIf this was not intentionally done by you, \
@@ -152,7 +202,14 @@ pub async fn client_execute(
       }
       _ => format!("Failed during execution: {err}"),
     };
-    NailgunClientError::PostConnect(err_str)
+    if output.interrupted {
+      NailgunClientError::PostConnect(err_str)
+    } else {
+      NailgunClientError::ConnectionLost {
+        message: err_str,
+        wrote_stdout: output.wrote_stdout,
+      }
+    }
   })?;
 
     Ok(exit_code.0)
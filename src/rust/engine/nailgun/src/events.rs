@@ -0,0 +1,143 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//!
+//! An auxiliary channel which carries structured events (encoded as newline-delimited JSON) from
+//! the server to the client, alongside the stdio of a run.
+//!
+//! The nailgun protocol only supports stdio, so the client listens on a Unix domain socket, and
+//! passes its path to the server in the environment of the command (in the same way as the paths
+//! of TTYs: see `RawFdNail`). The server connects to the socket before the run starts, and closes
+//! the connection when the run completes.
+//!
+
+use std::collections::HashMap;
+use std::io;
+use std::os::unix::net::UnixStream as StdUnixStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::UnixListener;
+use tokio::task::JoinHandle;
+
+/// The environment variable which holds the path of the client's events socket.
+pub(crate) const EVENTS_PATH_ENV: &str = "NAILGUN_EVENTS_PATH";
+
+/// How long to wait for the server to finish sending events once the run has exited.
+const EVENTS_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+///
+/// Called by the client with each event (a line of JSON, without its trailing newline) that the
+/// server sends.
+///
+pub type EventHandler = Box<dyn FnMut(&str) + Send>;
+
+///
+/// The client side of the channel: a listening socket, which is removed when it is dropped.
+///
+pub(crate) struct EventsListener {
+    path: PathBuf,
+    listener: UnixListener,
+}
+
+impl EventsListener {
+    pub(crate) fn bind() -> io::Result<EventsListener> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "pants-nailgun-events-{}-{}.sock",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        // Remove any socket left behind by a previous process with the same pid.
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+        Ok(EventsListener { path, listener })
+    }
+
+    ///
+    /// The environment entry which tells the server where to connect.
+    ///
+    pub(crate) fn env_entry(&self) -> (String, String) {
+        (EVENTS_PATH_ENV.to_owned(), self.path.display().to_string())
+    }
+
+    ///
+    /// Spawns a task which accepts a single connection from the server, and calls the handler with
+    /// each event until the server closes the connection.
+    ///
+    pub(crate) fn spawn(self, mut handler: EventHandler) -> EventsReader {
+        let task = tokio::spawn(async move {
+            let (stream, _) = match self.listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::debug!("Failed to accept events connection: {e}");
+                    return;
+                }
+            };
+            let mut lines = BufReader::new(stream).lines();
+            loop {
+                match lines.next_line().await {
+                    Ok(Some(line)) if line.trim().is_empty() => {}
+                    Ok(Some(line)) => handler(&line),
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::debug!("Failed to read events: {e}");
+                        break;
+                    }
+                }
+            }
+            // NB: `self` (and so the socket) is held until the connection completes.
+            std::mem::drop(self);
+        });
+        EventsReader { task }
+    }
+}
+
+impl Drop for EventsListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+pub(crate) struct EventsReader {
+    task: JoinHandle<()>,
+}
+
+impl EventsReader {
+    ///
+    /// Waits (briefly) for the server to finish sending events. The server closes its connection
+    /// before the exit code of a run is sent, so this should not usually block.
+    ///
+    pub(crate) async fn drain(mut self) {
+        if tokio::time::timeout(EVENTS_DRAIN_TIMEOUT, &mut self.task)
+            .await
+            .is_err()
+        {
+            log::debug!("Timed out waiting for events from the server.");
+            self.task.abort();
+        }
+    }
+
+    ///
+    /// Stops reading events without waiting for the server.
+    ///
+    pub(crate) fn abort(self) {
+        self.task.abort();
+    }
+}
+
+///
+/// The server side of the channel: connects to the client's socket if it requested events.
+///
+pub(crate) fn connect_from_env(env: &HashMap<String, String>) -> Option<StdUnixStream> {
+    let path = env.get(EVENTS_PATH_ENV)?;
+    StdUnixStream::connect(path)
+        .map_err(|e| {
+            log::debug!(
+                "Failed to connect to events socket at {path}: {e}, events will not be sent."
+            );
+        })
+        .ok()
+}
@@ -5,8 +5,10 @@
 mod tests;
 
 mod client;
+mod events;
 mod server;
 
 pub use client::{client_execute, NailgunClientError};
+pub use events::EventHandler;
 pub use nails::execution::ExitCode;
 pub use server::{RawFdExecution, Server};
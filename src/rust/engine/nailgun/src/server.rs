@@ -23,6 +23,8 @@ use tokio::net::TcpListener;
 use tokio::sync::{mpsc, Notify, RwLock};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
+use crate::events;
+
 pub struct Server {
     exit_sender: oneshot::Sender<()>,
     exited_receiver: oneshot::Receiver<Result<(), String>>,
@@ -177,6 +179,8 @@ pub struct RawFdExecution {
     pub stdin_fd: RawFd,
     pub stdout_fd: RawFd,
     pub stderr_fd: RawFd,
+    /// If the client requested structured events, a writable handle for them: see `crate::events`.
+    pub events_fd: Option<RawFd>,
 }
 
 ///
@@ -196,6 +200,9 @@ impl<T: Fn(RawFdExecution) -> ExitCode + Send + Sync> RawFdRunner for T {}
 /// be addressable as a file in OSX and Linux. In that case, there is no middle-man, and we
 /// ignore the fds opened by the nailgun server for data sent via the protocol.
 ///
+/// Similarly, if the client requested structured events, we connect to the socket that it is
+/// listening on, and pass the connection to the callback as an additional file handle.
+///
 #[derive(Clone)]
 struct RawFdNail {
     executor: Executor,
@@ -229,6 +236,9 @@ impl Nail for RawFdNail {
         // See: https://pubs.opengroup.org/onlinepubs/9699919799/functions/stdin.html
         let (stderr_stream, stderr_handle) = Self::output(Self::ttypath_from_env(&env, 2), true)?;
 
+        // And the events channel, if requested.
+        let events_handle = events::connect_from_env(&env);
+
         // Set up a cancellation token that is triggered on client shutdown.
         let cancelled = AsyncLatch::new();
         let shutdown = {
@@ -252,6 +262,7 @@ impl Nail for RawFdNail {
                         stdin_fd: stdin_handle.as_raw_fd(),
                         stdout_fd: stdout_handle.as_raw_fd(),
                         stderr_fd: stderr_handle.as_raw_fd(),
+                        events_fd: events_handle.as_ref().map(|handle| handle.as_raw_fd()),
                     })
                 },
                 |e| {
@@ -1,9 +1,13 @@
 // Copyright 2022 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
-use crate::Server;
+use crate::{client_execute, Server};
 
+use std::fs::File;
+use std::io::Write;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::FromRawFd;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use futures::{future, FutureExt};
@@ -79,6 +83,39 @@ async fn shutdown_awaits_ongoing() {
     server_shutdown.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn events() {
+    let server = Server::new(Executor::new(), 0, |exe: crate::RawFdExecution| {
+        // NB: The handle is owned by the server, so must not be closed here.
+        let mut events = ManuallyDrop::new(unsafe { File::from_raw_fd(exe.events_fd.unwrap()) });
+        events.write_all(b"{\"type\": \"phase\"}\n\n").unwrap();
+        events.write_all(b"{\"type\": \"exit\"}\n").unwrap();
+        ExitCode(0)
+    })
+    .await
+    .unwrap();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let exit_code = client_execute(
+        server.port(),
+        "nothing".to_owned(),
+        vec![],
+        vec![],
+        Some(Box::new({
+            let received = received.clone();
+            move |event: &str| received.lock().unwrap().push(event.to_owned())
+        })),
+    )
+    .await
+    .unwrap_or_else(|_| panic!("Client failed."));
+    assert_eq!(0, exit_code);
+    assert_eq!(
+        vec!["{\"type\": \"phase\"}", "{\"type\": \"exit\"}"],
+        *received.lock().unwrap()
+    );
+    server.shutdown().await.unwrap();
+}
+
 async fn run_client(port: u16) -> Result<ExitCode, String> {
     let cmd = Command {
         command: "nothing".to_owned(),
@@ -0,0 +1,225 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Generates shell completion scripts which embed the goals and options of a `Schema`, so that
+//! completing them does not require running Pants.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::schema::{OptionSchema, Schema, ScopeSchema};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Shell, String> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!(
+                "Unsupported shell `{s}`: expected one of `bash`, `zsh` or `fish`."
+            )),
+        }
+    }
+}
+
+pub fn completion_script(schema: &Schema, shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash_script(schema),
+        Shell::Zsh => zsh_script(schema),
+        Shell::Fish => fish_script(schema),
+    }
+}
+
+fn goal_names(schema: &Schema) -> String {
+    schema
+        .goals()
+        .iter()
+        .map(|goal| goal.scope.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn flags(options: &[OptionSchema]) -> String {
+    let mut flags = options
+        .iter()
+        .map(|option| format!("--{}", option.name))
+        .collect::<Vec<_>>();
+    flags.sort();
+    flags.join(" ")
+}
+
+/// The scopes whose options are completed: the global scope, followed by the goals.
+fn option_scopes(schema: &Schema) -> Vec<(&str, &[OptionSchema])> {
+    let mut scopes = vec![("", schema.global_options())];
+    scopes.extend(
+        schema
+            .goals()
+            .into_iter()
+            .map(|goal| (goal.scope.as_str(), goal.options.as_slice())),
+    );
+    scopes
+}
+
+fn bash_script(schema: &Schema) -> String {
+    let mut cases = String::new();
+    for (scope, options) in option_scopes(schema) {
+        writeln!(cases, "        \"{scope}\") echo \"{}\" ;;", flags(options)).unwrap();
+    }
+    format!(
+        r#"# bash completion support for Pants, generated by `pants complete --static`.
+
+_pants_goals="{goals}"
+
+function _pants_options() {{
+    case "$1" in
+{cases}    esac
+}}
+
+function _pants_completions() {{
+    local current_word previous_goal word
+    current_word=${{COMP_WORDS[COMP_CWORD]}}
+
+    for word in "${{COMP_WORDS[@]:1:COMP_CWORD-1}}"; do
+        if [[ $word =~ ^[[:alnum:]] ]]; then
+            previous_goal=$word
+        fi
+    done
+
+    if [[ $current_word =~ ^(\.|/|~/) ]]; then
+        COMPREPLY=()
+    elif [[ $current_word == -* ]]; then
+        COMPREPLY=( $(compgen -W "$(_pants_options "$previous_goal")" -- "$current_word") )
+    else
+        COMPREPLY=( $(compgen -W "$_pants_goals" -- "$current_word") )
+    fi
+
+    return 0
+}}
+
+complete -o default -F _pants_completions pants
+"#,
+        goals = goal_names(schema),
+    )
+}
+
+fn zsh_script(schema: &Schema) -> String {
+    let mut cases = String::new();
+    for (scope, options) in option_scopes(schema) {
+        writeln!(cases, "        \"{scope}\") reply=({}) ;;", flags(options)).unwrap();
+    }
+    format!(
+        r#"#compdef _pants_completions pants
+
+# zsh completion support for Pants, generated by `pants complete --shell=zsh --static`.
+
+_pants_goals=({goals})
+
+function _pants_options() {{
+    case "$1" in
+{cases}        *) reply=() ;;
+    esac
+}}
+
+function _pants_completions() {{
+    local current_word previous_goal word
+    local -a reply
+    current_word=${{words[CURRENT]}}
+
+    for word in "${{(@)words[2,CURRENT-1]}}"; do
+        if [[ $word =~ "^[[:alnum:]]" ]]; then
+            previous_goal=$word
+        fi
+    done
+
+    if [[ $current_word =~ "^(\.|/|~\/)" ]]; then
+        _files
+    elif [[ $current_word == -* ]]; then
+        _pants_options "$previous_goal"
+        compadd -a reply
+    else
+        compadd -a _pants_goals
+    fi
+
+    return 0
+}}
+
+compdef _pants_completions pants
+"#,
+        goals = goal_names(schema),
+    )
+}
+
+fn fish_script(schema: &Schema) -> String {
+    let mut script = String::from(
+        r#"# fish completion support for Pants, generated by `pants complete --shell=fish`.
+
+function __pants_previous_goal
+    set -l goal
+    for word in (commandline -opc)[2..-1]
+        if string match -qr '^[[:alnum:]]' -- $word
+            set goal $word
+        end
+    end
+    echo $goal
+end
+
+function __pants_using_goal
+    set -l goal (__pants_previous_goal)
+    test "$goal" = "$argv[1]"
+end
+"#,
+    );
+
+    script.push('\n');
+    for goal in schema.goals() {
+        writeln!(script, "{}", fish_goal(goal)).unwrap();
+    }
+    for (scope, options) in option_scopes(schema) {
+        script.push('\n');
+        for option in options {
+            writeln!(script, "{}", fish_option(scope, option)).unwrap();
+        }
+    }
+    script
+}
+
+fn fish_goal(goal: &ScopeSchema) -> String {
+    let mut line = format!("complete -c pants -f -a {}", fish_quote(&goal.scope));
+    let summary = goal.summary();
+    if !summary.is_empty() {
+        write!(line, " -d {}", fish_quote(&summary)).unwrap();
+    }
+    line
+}
+
+fn fish_option(scope: &str, option: &OptionSchema) -> String {
+    let mut line = format!(
+        "complete -c pants -n {} -l {}",
+        fish_quote(&format!("__pants_using_goal '{scope}'")),
+        fish_quote(&option.name),
+    );
+    if !option.choices.is_empty() {
+        write!(line, " -x -a {}", fish_quote(&option.choices.join(" "))).unwrap();
+    } else if !option.is_bool() {
+        line.push_str(" -r");
+    }
+    let summary = option.summary();
+    if !summary.is_empty() {
+        write!(line, " -d {}", fish_quote(&summary)).unwrap();
+    }
+    line
+}
+
+/// Quotes a value as a single-quoted fish string, in which only `\` and `'` are escaped.
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
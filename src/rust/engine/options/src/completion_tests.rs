@@ -0,0 +1,80 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::completion::{completion_script, Shell};
+use crate::schema::{OptionSchema, Schema};
+use crate::schema_tests::{option, scope};
+
+fn schema() -> Schema {
+    Schema {
+        scopes: vec![
+            scope(
+                "",
+                false,
+                vec![option("pants-distdir", "str"), option("colors", "bool")],
+            ),
+            scope(
+                "fmt",
+                true,
+                vec![OptionSchema {
+                    choices: vec!["black".to_owned(), "isort".to_owned()],
+                    help: "Only run these formatters.\nSee the docs.".to_owned(),
+                    ..option("only", "list")
+                }],
+            ),
+            scope("lint", true, vec![option("batch-size", "int")]),
+            // Only the options of the global scope and goals are completed.
+            scope(
+                "python",
+                false,
+                vec![option("interpreter-constraints", "list")],
+            ),
+        ],
+    }
+}
+
+#[test]
+fn test_shell_from_str() {
+    assert_eq!(Ok(Shell::Bash), "bash".parse());
+    assert_eq!(Ok(Shell::Zsh), "zsh".parse());
+    assert_eq!(Ok(Shell::Fish), "fish".parse());
+    assert_eq!(
+        Err("Unsupported shell `csh`: expected one of `bash`, `zsh` or `fish`.".to_owned()),
+        "csh".parse::<Shell>()
+    );
+}
+
+#[test]
+fn test_bash_script() {
+    let script = completion_script(&schema(), Shell::Bash);
+    assert!(script.contains("\n_pants_goals=\"fmt lint\"\n"));
+    assert!(script.contains("        \"\") echo \"--colors --pants-distdir\" ;;\n"));
+    assert!(script.contains("        \"fmt\") echo \"--only\" ;;\n"));
+    assert!(script.contains("        \"lint\") echo \"--batch-size\" ;;\n"));
+    assert!(!script.contains("interpreter-constraints"));
+    assert!(script.ends_with("complete -o default -F _pants_completions pants\n"));
+}
+
+#[test]
+fn test_zsh_script() {
+    let script = completion_script(&schema(), Shell::Zsh);
+    assert!(script.starts_with("#compdef _pants_completions pants\n"));
+    assert!(script.contains("\n_pants_goals=(fmt lint)\n"));
+    assert!(script.contains("        \"\") reply=(--colors --pants-distdir) ;;\n"));
+    assert!(script.contains("        \"fmt\") reply=(--only) ;;\n"));
+    assert!(!script.contains("interpreter-constraints"));
+}
+
+#[test]
+fn test_fish_script() {
+    let script = completion_script(&schema(), Shell::Fish);
+    assert!(script.contains("\ncomplete -c pants -f -a 'fmt'\ncomplete -c pants -f -a 'lint'\n"));
+    assert!(script
+        .contains("\ncomplete -c pants -n '__pants_using_goal \\'\\'' -l 'pants-distdir' -r\n"));
+    assert!(script.contains("\ncomplete -c pants -n '__pants_using_goal \\'\\'' -l 'colors'\n"));
+    assert!(script.contains(concat!(
+        "\ncomplete -c pants -n '__pants_using_goal \\'fmt\\'' -l 'only' -x -a 'black isort' ",
+        "-d 'Only run these formatters.'\n"
+    )));
+    assert!(!script.contains("interpreter-constraints"));
+}
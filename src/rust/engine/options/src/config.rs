@@ -20,34 +20,67 @@ type InterpolationMap = HashMap<String, String>;
 static DEFAULT_SECTION: &str = "DEFAULT";
 
 lazy_static! {
-    static ref PLACEHOLDER_RE: Regex = Regex::new(r"%\(([a-zA-Z0-9_.]+)\)s").unwrap();
+    // Either the `%(name)s` form, or the `${env.NAME}` form for environment variables.
+    static ref PLACEHOLDER_RE: Regex =
+        Regex::new(r"%\((?P<name>[a-zA-Z0-9_.]+)\)s|\$\{(?P<env>env\.[a-zA-Z0-9_]+)\}").unwrap();
 }
 
-pub(crate) fn interpolate_string(
+pub fn interpolate_string(
     value: String,
     replacements: &InterpolationMap,
 ) -> Result<String, String> {
-    let caps_vec: Vec<_> = PLACEHOLDER_RE.captures_iter(&value).collect();
-    if caps_vec.is_empty() {
-        return Ok(value);
-    }
+    let mut placeholders = Vec::new();
+    interpolate_placeholders(&value, replacements, &mut placeholders)
+}
 
+/// Interpolates the placeholders in the given value, where `placeholders` are those whose values
+/// are already being interpolated, which must not be referred to again.
+fn interpolate_placeholders(
+    value: &str,
+    replacements: &InterpolationMap,
+    placeholders: &mut Vec<String>,
+) -> Result<String, String> {
     let mut new_value = String::with_capacity(value.len());
     let mut last_match = 0;
-    for caps in caps_vec {
+    for caps in PLACEHOLDER_RE.captures_iter(value) {
         let m = caps.get(0).unwrap();
         new_value.push_str(&value[last_match..m.start()]);
-        let placeholder_name = &caps[1];
-        let replacement = replacements.get(placeholder_name).ok_or(format!(
-            "Unknown value for placeholder `{}`",
-            placeholder_name
-        ))?;
-        new_value.push_str(replacement);
+        let placeholder_name = caps
+            .name("name")
+            .or_else(|| caps.name("env"))
+            .unwrap()
+            .as_str();
+        if let Some(start) = placeholders.iter().position(|p| p == placeholder_name) {
+            let cycle = placeholders[start..]
+                .iter()
+                .chain(std::iter::once(&placeholder_name.to_owned()))
+                .map(|p| format!("`{p}`"))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(format!(
+                "Placeholder `{placeholder_name}` refers to itself: {cycle}"
+            ));
+        }
+        let replacement = replacements.get(placeholder_name).ok_or_else(|| {
+            format!(
+                "Unknown value for placeholder `{}` at column {} of `{}`",
+                placeholder_name,
+                value[..m.start()].chars().count() + 1,
+                value
+            )
+        })?;
+        // A replacement string may itself contain a placeholder, so we recurse.
+        placeholders.push(placeholder_name.to_owned());
+        new_value.push_str(&interpolate_placeholders(
+            replacement,
+            replacements,
+            placeholders,
+        )?);
+        placeholders.pop();
         last_match = m.end();
     }
     new_value.push_str(&value[last_match..]);
-    // A replacement string may itself contain a placeholder, so we recurse.
-    interpolate_string(new_value, replacements)
+    Ok(new_value)
 }
 
 struct InterpolationError {
@@ -276,8 +309,14 @@ impl Config {
             if let Some(section) = section {
                 if let Some(table) = section.as_table() {
                     for (key, value) in table.iter() {
-                        if let Value::String(s) = value {
-                            imap.insert(key.clone(), s.clone());
+                        match value {
+                            Value::String(s) => {
+                                imap.insert(key.clone(), s.clone());
+                            }
+                            Value::Integer(i) => {
+                                imap.insert(key.clone(), i.to_string());
+                            }
+                            _ => (),
                         }
                     }
                 }
@@ -453,13 +492,25 @@ impl ConfigReader {
             if let Some(value) = table.get(&option_name) {
                 match value {
                     Value::Table(sub_table) => {
-                        if let Some(add) = sub_table.get("add") {
-                            if sub_table.len() == 1 && add.is_table() {
-                                return Ok(Some(vec![DictEdit {
+                        // A table of only an `add` table and/or a `remove` array of keys is an
+                        // edit: anything else (e.g. `add = 0`) is a literal dict value.
+                        let add = sub_table.get("add").filter(|add| add.is_table());
+                        let remove = sub_table
+                            .get("remove")
+                            .and_then(|remove| String::extract_list("", remove).ok());
+                        let edit_keys = usize::from(add.is_some()) + usize::from(remove.is_some());
+                        if edit_keys > 0 && edit_keys == sub_table.len() {
+                            let mut dict_edits = vec![];
+                            if let Some(add) = add {
+                                dict_edits.push(DictEdit {
                                     action: DictEditAction::Add,
                                     items: toml_table_to_dict(add),
-                                }]));
+                                });
+                            }
+                            if let Some(remove) = remove {
+                                dict_edits.push(DictEdit::remove(remove));
                             }
+                            return Ok(Some(dict_edits));
                         }
                         return Ok(Some(vec![DictEdit {
                             action: DictEditAction::Replace,
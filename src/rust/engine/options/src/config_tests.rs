@@ -85,7 +85,7 @@ fn test_interpolate_string() {
     let result = interp(template, replacements);
     assert!(result.is_err());
     assert_eq!(
-        "Unknown value for placeholder `unknown`",
+        "Unknown value for placeholder `unknown` at column 11 of `%(known)s %(unknown)s`",
         result.unwrap_err()
     );
 
@@ -99,6 +99,38 @@ fn test_interpolate_string() {
         "Hello world, what's your real name?",
         interp(template, replacements).unwrap()
     );
+
+    let template = "${env.HOME}/.cache and %(env.HOME)s, but not ${HOME} or ${env.}";
+    let replacements = vec![("env.HOME", "/home/user")];
+    assert_eq!(
+        "/home/user/.cache and /home/user, but not ${HOME} or ${env.}",
+        interp(template, replacements).unwrap()
+    );
+
+    let template = "${env.UNSET}";
+    assert_eq!(
+        "Unknown value for placeholder `env.UNSET` at column 1 of `${env.UNSET}`",
+        interp(template, vec![]).unwrap_err()
+    );
+
+    // The same placeholder may be used repeatedly, as long as it doesn't refer to itself.
+    let template = "%(a)s %(b)s";
+    let replacements = vec![("a", "%(c)s"), ("b", "%(c)s %(c)s"), ("c", "c")];
+    assert_eq!("c c c", interp(template, replacements).unwrap());
+
+    let template = "%(a)s";
+    let replacements = vec![("a", "x %(b)s"), ("b", "%(c)s"), ("c", "y %(a)s")];
+    assert_eq!(
+        "Placeholder `a` refers to itself: `a` -> `b` -> `c` -> `a`",
+        interp(template, replacements).unwrap_err()
+    );
+
+    let template = "%(a)s";
+    let replacements = vec![("a", "%(a)s")];
+    assert_eq!(
+        "Placeholder `a` refers to itself: `a` -> `a`",
+        interp(template, replacements).unwrap_err()
+    );
 }
 
 #[test]
@@ -111,6 +143,8 @@ fn test_interpolate_config() {
      field2 = '%(field1)s else'\n\
      field3 = 'entirely'\n\
      field4 = '%(field2)s %(field3)s %(seed2)s'\n\
+     answer = 42\n\
+     field5 = 'the answer is %(answer)s'\n\
      [groceries]\n\
      berryprefix = 'straw'\n\
      stringlist.add = ['apple', '%(berryprefix)sberry', 'banana']\n\
@@ -125,6 +159,13 @@ fn test_interpolate_config() {
             .unwrap()
     );
 
+    assert_eq!(
+        "the answer is 42",
+        conf.get_string(&option_id!(["foo"], "field5"))
+            .unwrap()
+            .unwrap()
+    );
+
     assert_eq!(
         vec![
             ListEdit {
@@ -165,14 +206,28 @@ fn test_interpolate_config() {
      bad_field = '%(unknown)s'\n",
     );
     let err_msg = bad_conf.err().unwrap();
-    let pat =
-        r"^Unknown value for placeholder `unknown` in config file .*, section foo, key bad_field$";
+    let pat = concat!(
+        r"^Unknown value for placeholder `unknown` at column 1 of `%\(unknown\)s` ",
+        r"in config file .*, section foo, key bad_field$"
+    );
     assert!(
         Regex::new(pat).unwrap().is_match(&err_msg),
         "Error message:  {}\nDid not match: {}",
         &err_msg,
         pat
     );
+
+    let cyclic_conf = maybe_config(
+        "[foo]\n\
+     field1 = '%(field2)s'\n\
+     field2 = 'x %(field1)s'\n",
+    );
+    let err_msg = cyclic_conf.err().unwrap();
+    assert!(
+        err_msg.starts_with("Placeholder `field2` refers to itself"),
+        "Error message: {}",
+        err_msg
+    );
 }
 
 #[test]
@@ -299,6 +354,46 @@ fn test_default_section_dict() {
     );
 }
 
+#[test]
+fn test_dict_add_and_remove() {
+    let conf = config(
+        "[foo.bar]\n\
+     add = { x = 2 }\n\
+     remove = ['y', 'z']\n\
+     [foo.baz]\n\
+     remove = ['y']\n\
+     [foo.qux]\n\
+     add = 0\n\
+     remove = ['y']",
+    );
+
+    assert_eq!(
+        vec![
+            DictEdit {
+                action: DictEditAction::Add,
+                items: hashmap! { "x".to_string() => Val::Int(2) },
+            },
+            DictEdit::remove(["y".to_string(), "z".to_string()]),
+        ],
+        conf.get_dict(&option_id!(["foo"], "bar")).unwrap().unwrap()
+    );
+    assert_eq!(
+        vec![DictEdit::remove(["y".to_string()])],
+        conf.get_dict(&option_id!(["foo"], "baz")).unwrap().unwrap()
+    );
+    // Not an edit, since `add` is not a table.
+    assert_eq!(
+        vec![DictEdit {
+            action: DictEditAction::Replace,
+            items: hashmap! {
+                "add".to_string() => Val::Int(0),
+                "remove".to_string() => Val::List(vec![Val::String("y".to_string())]),
+            },
+        }],
+        conf.get_dict(&option_id!(["foo"], "qux")).unwrap().unwrap()
+    );
+}
+
 #[test]
 fn test_scalar_fromfile() {
     fn do_test<T: PartialEq + Debug>(
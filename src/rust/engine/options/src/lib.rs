@@ -9,6 +9,10 @@ mod build_root;
 #[cfg(test)]
 mod build_root_tests;
 
+mod completion;
+#[cfg(test)]
+mod completion_tests;
+
 mod config;
 #[cfg(test)]
 mod config_tests;
@@ -29,6 +33,10 @@ mod parse;
 #[cfg(test)]
 mod parse_tests;
 
+mod schema;
+#[cfg(test)]
+mod schema_tests;
+
 #[cfg(test)]
 mod tests;
 
@@ -40,18 +48,20 @@ use std::hash::Hash;
 use std::path::Path;
 use std::sync::Arc;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 pub use self::args::Args;
 use self::args::ArgsReader;
-pub use self::config::ConfigSource;
+pub use self::config::{interpolate_string, ConfigSource};
 use self::config::{Config, ConfigReader};
 pub use self::env::Env;
 use self::env::EnvReader;
 use crate::fromfile::FromfileExpander;
 use crate::parse::Parseable;
 pub use build_root::BuildRoot;
+pub use completion::{completion_script, Shell};
 pub use id::{OptionId, Scope};
+pub use schema::{OptionSchema, Schema, ScopeSchema};
 pub use types::OptionType;
 
 // NB: The legacy Python options parser supported dicts with member_type "Any", which means
@@ -63,7 +73,7 @@ pub use types::OptionType;
 // We only use this for parsing values in dicts, as in other cases we know that the type must
 // be some scalar or string, or a uniform list of one type of scalar or string, so we can
 // parse as such.
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Val {
     Bool(bool),
@@ -91,6 +101,8 @@ pub struct ListEdit<T> {
 pub enum DictEditAction {
     Replace,
     Add,
+    /// Removes the keys of the edit's items: their values are ignored.
+    Remove,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -99,6 +111,103 @@ pub struct DictEdit {
     pub items: HashMap<String, Val>,
 }
 
+impl DictEdit {
+    pub fn remove<I: IntoIterator<Item = String>>(keys: I) -> Self {
+        Self {
+            action: DictEditAction::Remove,
+            items: keys.into_iter().map(|key| (key, Val::Bool(true))).collect(),
+        }
+    }
+}
+
+///
+/// A conversion between the untyped values of dict options and the type that their consumer
+/// expects.
+///
+pub trait TypedVal: Sized {
+    const TYPE_NAME: &'static str;
+
+    fn from_val(val: Val) -> Option<Self>;
+
+    fn into_val(self) -> Val;
+}
+
+impl TypedVal for bool {
+    const TYPE_NAME: &'static str = "bool";
+
+    fn from_val(val: Val) -> Option<Self> {
+        match val {
+            Val::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    fn into_val(self) -> Val {
+        Val::Bool(self)
+    }
+}
+
+impl TypedVal for i64 {
+    const TYPE_NAME: &'static str = "int";
+
+    fn from_val(val: Val) -> Option<Self> {
+        match val {
+            Val::Int(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    fn into_val(self) -> Val {
+        Val::Int(self)
+    }
+}
+
+impl TypedVal for f64 {
+    const TYPE_NAME: &'static str = "float";
+
+    fn from_val(val: Val) -> Option<Self> {
+        match val {
+            Val::Float(f) => Some(f),
+            Val::Int(i) => Some(i as f64),
+            _ => None,
+        }
+    }
+
+    fn into_val(self) -> Val {
+        Val::Float(self)
+    }
+}
+
+impl TypedVal for String {
+    const TYPE_NAME: &'static str = "string";
+
+    fn from_val(val: Val) -> Option<Self> {
+        match val {
+            Val::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn into_val(self) -> Val {
+        Val::String(self)
+    }
+}
+
+impl<T: TypedVal> TypedVal for Vec<T> {
+    const TYPE_NAME: &'static str = "list";
+
+    fn from_val(val: Val) -> Option<Self> {
+        match val {
+            Val::List(items) => items.into_iter().map(T::from_val).collect(),
+            _ => None,
+        }
+    }
+
+    fn into_val(self) -> Val {
+        Val::List(self.into_iter().map(T::into_val).collect())
+    }
+}
+
 pub(crate) trait OptionsSource: Send + Sync {
     ///
     /// Get a display version of the option `id` that most closely matches the syntax used to supply
@@ -652,6 +761,11 @@ impl OptionParser {
                     match dict_edit.action {
                         DictEditAction::Replace => dict = dict_edit.items,
                         DictEditAction::Add => dict.extend(dict_edit.items),
+                        DictEditAction::Remove => {
+                            for key in dict_edit.items.keys() {
+                                dict.remove(key);
+                            }
+                        }
                     }
                 }
             }
@@ -663,6 +777,44 @@ impl OptionParser {
         })
     }
 
+    ///
+    /// Parses a dict option whose values must all be of type `T`, e.g. a `dict[str, str]`.
+    ///
+    /// The derivation of typed dicts is not recorded: use `parse_dict` for that.
+    ///
+    pub fn parse_typed_dict<T: TypedVal>(
+        &self,
+        id: &OptionId,
+        default: HashMap<String, T>,
+    ) -> Result<OptionValue<HashMap<String, T>>, String> {
+        let default = default
+            .into_iter()
+            .map(|(key, value)| (key, value.into_val()))
+            .collect();
+        let dict = self.parse_dict(id, default)?;
+        let value = dict
+            .value
+            .into_iter()
+            .map(|(key, val)| {
+                let description = format!("{val:?}");
+                T::from_val(val)
+                    .map(|value| (key.clone(), value))
+                    .ok_or_else(|| {
+                        format!(
+                        "Expected the value of key `{key}` of dict option {id} to be a {}, but \
+                        given {description}.",
+                        T::TYPE_NAME
+                    )
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(OptionValue {
+            derivation: None,
+            source: dict.source,
+            value,
+        })
+    }
+
     pub fn get_passthrough_args(&self) -> Option<&Vec<String>> {
         self.passthrough_args.as_ref()
     }
@@ -227,7 +227,9 @@ peg::parser! {
 
         rule dict_start() -> ()
             = quiet!{ "{" }
-            / expected!("the start of a dict indicated by '{' or '+{'")
+            / expected!(
+                "the start of a dict indicated by '{' or '+{', or of keys to remove indicated by '-['"
+            )
 
         rule dict_end() -> ()
             = quiet!{ "}" }
@@ -238,8 +240,13 @@ peg::parser! {
                 (key, value)
             }
 
+        // Removes the given keys, e.g. `-["key1", "key2"]`.
+        rule dict_remove() -> DictEdit
+            = whitespace()* "-" keys:items(<quoted_string()>) { DictEdit::remove(keys) }
+
         pub(crate) rule dict_edit() -> DictEdit
-            = whitespace()* plus:"+"? d:dict() {
+            = dict_remove()
+            / whitespace()* plus:"+"? d:dict() {
                 DictEdit {
                     action: if plus.is_some() { DictEditAction::Add } else { DictEditAction::Replace },
                     items: d,
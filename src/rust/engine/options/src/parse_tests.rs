@@ -607,6 +607,15 @@ fn test_parse_dict_add() {
     );
 }
 
+#[test]
+fn test_parse_dict_remove() {
+    check!(
+        DictEdit::remove(["foo".to_string(), "baz".to_string()]),
+        parse_dict(r#"-["foo", 'baz']"#)
+    );
+    check!(DictEdit::remove([]), parse_dict(" -( ) "));
+}
+
 #[test]
 fn test_parse_dict_whitespace() {
     check!(
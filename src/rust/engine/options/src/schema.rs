@@ -0,0 +1,103 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use serde::{Deserialize, Serialize};
+
+use crate::Val;
+
+/// The machine-readable description of a registered option.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OptionSchema {
+    /// The flag name, without the leading dashes: e.g. `pants-config-files`.
+    pub name: String,
+    /// The name of the value type: e.g. `bool`, `int`, `str`, `list` or `dict`.
+    #[serde(rename = "type")]
+    pub option_type: String,
+    /// The valid values of the option, if it is restricted to a fixed set of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+    /// The default value, if it can be represented as a `Val`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Val>,
+    #[serde(default)]
+    pub help: String,
+}
+
+impl OptionSchema {
+    pub fn is_bool(&self) -> bool {
+        self.option_type == "bool"
+    }
+
+    /// The first sentence of the help, on a single line.
+    pub fn summary(&self) -> String {
+        summarize(&self.help)
+    }
+}
+
+/// The machine-readable description of the options of a scope.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScopeSchema {
+    /// The name of the scope, or the empty string for the global scope.
+    pub scope: String,
+    #[serde(default)]
+    pub is_goal: bool,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub options: Vec<OptionSchema>,
+}
+
+impl ScopeSchema {
+    pub fn is_global(&self) -> bool {
+        self.scope.is_empty()
+    }
+
+    /// The first sentence of the description, on a single line.
+    pub fn summary(&self) -> String {
+        summarize(&self.description)
+    }
+}
+
+/// The schema of all registered options, e.g. for generating shell completions.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    pub scopes: Vec<ScopeSchema>,
+}
+
+impl Schema {
+    pub fn from_json(json: &str) -> Result<Schema, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid option schema: {e}"))
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to render schema: {e}"))
+    }
+
+    /// The options of the global scope, if they were registered.
+    pub fn global_options(&self) -> &[OptionSchema] {
+        self.scopes
+            .iter()
+            .find(|scope| scope.is_global())
+            .map(|scope| scope.options.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The goal scopes, sorted by name.
+    pub fn goals(&self) -> Vec<&ScopeSchema> {
+        let mut goals = self
+            .scopes
+            .iter()
+            .filter(|scope| scope.is_goal)
+            .collect::<Vec<_>>();
+        goals.sort_by(|a, b| a.scope.cmp(&b.scope));
+        goals
+    }
+}
+
+fn summarize(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.find(". ") {
+        Some(end) => text[..=end].to_owned(),
+        None => text,
+    }
+}
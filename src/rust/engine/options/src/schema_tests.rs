@@ -0,0 +1,106 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::schema::{OptionSchema, Schema, ScopeSchema};
+use crate::Val;
+
+pub(crate) fn option(name: &str, option_type: &str) -> OptionSchema {
+    OptionSchema {
+        name: name.to_owned(),
+        option_type: option_type.to_owned(),
+        choices: vec![],
+        default: None,
+        help: String::new(),
+    }
+}
+
+pub(crate) fn scope(name: &str, is_goal: bool, options: Vec<OptionSchema>) -> ScopeSchema {
+    ScopeSchema {
+        scope: name.to_owned(),
+        is_goal,
+        description: String::new(),
+        options,
+    }
+}
+
+#[test]
+fn test_json_roundtrip() {
+    let schema = Schema {
+        scopes: vec![
+            scope(
+                "",
+                false,
+                vec![OptionSchema {
+                    default: Some(Val::List(vec![Val::String("pants.toml".to_owned())])),
+                    help: "Paths to config files.".to_owned(),
+                    ..option("pants-config-files", "list")
+                }],
+            ),
+            scope(
+                "fmt",
+                true,
+                vec![OptionSchema {
+                    choices: vec!["black".to_owned(), "isort".to_owned()],
+                    ..option("only", "list")
+                }],
+            ),
+        ],
+    };
+    let json = schema.to_json().unwrap();
+    assert!(json.contains(r#""type": "list""#));
+    assert!(json.contains(r#""default": ["#));
+    assert_eq!(schema, Schema::from_json(&json).unwrap());
+
+    // Optional fields may be omitted.
+    assert_eq!(
+        Schema {
+            scopes: vec![scope("test", false, vec![option("debug", "bool")])]
+        },
+        Schema::from_json(
+            r#"{"scopes": [{"scope": "test", "options": [{"name": "debug", "type": "bool"}]}]}"#
+        )
+        .unwrap()
+    );
+
+    let err = Schema::from_json(r#"{"scopes": [{"is_goal": true}]}"#).unwrap_err();
+    assert!(err.starts_with("Invalid option schema: missing field `scope`"));
+}
+
+#[test]
+fn test_goals_and_global_options() {
+    let schema = Schema {
+        scopes: vec![
+            scope("test", true, vec![]),
+            scope("", false, vec![option("level", "str")]),
+            scope("python", false, vec![]),
+            scope("fmt", true, vec![]),
+        ],
+    };
+    assert_eq!(
+        vec!["fmt", "test"],
+        schema
+            .goals()
+            .iter()
+            .map(|goal| goal.scope.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(vec![option("level", "str")], schema.global_options());
+    assert!(Schema::default().global_options().is_empty());
+}
+
+#[test]
+fn test_summary() {
+    let option = OptionSchema {
+        help: "Run the tests.  Uses the\nconfigured runner. More details.".to_owned(),
+        ..option("run", "bool")
+    };
+    assert_eq!("Run the tests.", option.summary());
+    assert_eq!(
+        "Uses the configured runner",
+        OptionSchema {
+            help: "Uses the\n  configured runner".to_owned(),
+            ..option
+        }
+        .summary()
+    );
+}
@@ -543,6 +543,36 @@ fn test_parse_dict_options() {
         "[scope.foo]\nkey6 = 6",
     );
 
+    check(
+        hashmap! {
+            "key5" => Val::Bool(true),
+            "key6" => Val::Int(6),
+        },
+        vec![
+            default_derivation.clone(),
+            (
+                config_source(),
+                vec![
+                    DictEdit {
+                        action: DictEditAction::Add,
+                        items: with_owned_keys(hashmap! {"key5" => Val::Bool(true)}),
+                    },
+                    DictEdit::remove(["key1".to_string()]),
+                ],
+            ),
+            (extra_config_source(), add(hashmap! {"key6" => Val::Int(6)})),
+            (Source::Env, vec![DictEdit::remove(["key4".to_string()])]),
+            (
+                Source::Flag,
+                vec![DictEdit::remove(["key2".to_string(), "key3".to_string()])],
+            ),
+        ],
+        vec!["--scope-foo=-['key2', 'key3']"],
+        vec![("PANTS_SCOPE_FOO", "-['key4']")],
+        "[scope.foo]\nadd = { key5 = true }\nremove = ['key1']",
+        "[scope]\nfoo = \"+{ 'key6': 6 }\"",
+    );
+
     check(
         hashmap! {
             "key1" => Val::Int(1),
@@ -555,3 +585,33 @@ fn test_parse_dict_options() {
         "",
     );
 }
+
+#[test]
+fn test_parse_typed_dict_options() {
+    with_setup(
+        vec!["--scope-foo=+{'key2': 'val2'}", "--scope-bar=+{'key': 1}"],
+        vec![],
+        "[scope.foo]\nremove = ['key1']",
+        "",
+        |option_parser| {
+            let default = HashMap::from([("key1".to_string(), "val1".to_string())]);
+            let option_value = option_parser
+                .parse_typed_dict(&option_id!(["scope"], "foo"), default.clone())
+                .unwrap();
+            assert_eq!(
+                HashMap::from([("key2".to_string(), "val2".to_string())]),
+                option_value.value
+            );
+            assert_eq!(Source::Flag, option_value.source);
+
+            let err = option_parser
+                .parse_typed_dict(&option_id!(["scope"], "bar"), default)
+                .unwrap_err();
+            assert_eq!(
+                "Expected the value of key `key` of dict option [scope] bar to be a string, but \
+                given Int(1).",
+                err
+            );
+        },
+    );
+}
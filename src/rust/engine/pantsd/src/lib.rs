@@ -8,12 +8,11 @@ mod pantsd_tests;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use libc::pid_t;
 use log::debug;
 use options::{option_id, BuildRoot, OptionId, OptionParser, OptionType};
 use sha2::digest::Update;
 use sha2::{Digest, Sha256};
-use sysinfo::{ProcessExt, ProcessStatus, System, SystemExt};
+use sysinfo::{Pid, ProcessExt, ProcessStatus, System, SystemExt};
 
 pub struct ConnectionSettings {
     pub port: u16,
@@ -31,6 +30,24 @@ impl ConnectionSettings {
     }
 }
 
+/// Goals which only read state, and so may be safely replayed by the client if its connection to
+/// pantsd is lost. This is the default of `[GLOBAL].pantsd_client_idempotent_goals`, which is
+/// exposed to Python as `native_engine.PANTSD_CLIENT_IDEMPOTENT_GOALS`.
+pub const DEFAULT_IDEMPOTENT_GOALS: &[&str] = &[
+    "count-loc",
+    "dependencies",
+    "dependents",
+    "filedeps",
+    "filter",
+    "help",
+    "help-advanced",
+    "help-all",
+    "list",
+    "paths",
+    "peek",
+    "roots",
+];
+
 pub(crate) struct Metadata {
     metadata_dir: PathBuf,
 }
@@ -60,7 +77,7 @@ impl Metadata {
         }
     }
 
-    fn pid(&self) -> Result<pid_t, String> {
+    fn pid(&self) -> Result<Pid, String> {
         self.read_metadata("pid")
             .and_then(|(pid_metadata_path, value)| {
                 value
@@ -16,7 +16,8 @@ use bollard::image::CreateImageOptions;
 use bollard::service::CreateImageInfo;
 use bollard::volume::CreateVolumeOptions;
 use bollard::{errors::Error as DockerError, Docker};
-use bytes::{Bytes, BytesMut};
+use bytes::Bytes;
+use engine_error::{EngineError, ErrorCode};
 use futures::stream::BoxStream;
 use futures::{FutureExt, StreamExt, TryFutureExt};
 use hashing::Digest;
@@ -24,16 +25,18 @@ use log::Level;
 use nails::execution::ExitCode;
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
+use stdio::OutputCapture;
 use store::{ImmutableInputs, Store};
 use task_executor::Executor;
 use workunit_store::{in_workunit, Metric, RunningWorkunit};
 
 use process_execution::local::{
-    apply_chroot, collect_child_outputs, create_sandbox, prepare_workdir, setup_run_sh_script,
+    apply_chroot, collect_child_outputs, create_sandbox, prepare_workdir, retain_sandbox,
     CapturedWorkdir, ChildOutput, KeepSandboxes,
 };
 use process_execution::{
-    Context, FallibleProcessResultWithPlatform, NamedCaches, Platform, Process, ProcessError,
+    redirect_stdin_from_input_root, validate_working_directory, Context,
+    FallibleProcessResultWithPlatform, NamedCaches, Platform, Process, ProcessError,
     ProcessExecutionStrategy,
 };
 
@@ -409,6 +412,7 @@ impl<'a> process_execution::CommandRunner for CommandRunner<'a> {
             // renders at the Process's level.
             desc = Some(req.description.clone()),
             |workunit| async move {
+                validate_working_directory(&self.store, &req).await?;
                 let mut workdir = create_sandbox(
                     self.executor.clone(),
                     &self.work_dir_base,
@@ -421,11 +425,12 @@ impl<'a> process_execution::CommandRunner for CommandRunner<'a> {
                     let ProcessExecutionStrategy::Docker(image) =
                         &req.execution_environment.strategy
                     else {
-                        return Err(ProcessError::Unclassified(
+                        return Err(EngineError::new(
+                            ErrorCode::InvalidExecutionStrategy,
                             "The Docker execution strategy was not set on the Process, but \
-                 the Docker CommandRunner was used."
-                                .to_owned(),
-                        ));
+                 the Docker CommandRunner was used.",
+                        )
+                        .into());
                     };
 
                     self.container_cache
@@ -437,8 +442,9 @@ impl<'a> process_execution::CommandRunner for CommandRunner<'a> {
                         .await?
                 };
 
-                // Start working on a mutable version of the process.
-                let mut req = req;
+                // Start working on a mutable version of the process. Docker execs do not attach to
+                // stdin, so any stdin is provided as an input file instead.
+                let mut req = redirect_stdin_from_input_root(&self.store, req).await?;
 
                 // Compute the absolute working directory within the container, and update the env to
                 // replace `{chroot}` placeholders with the path to the sandbox within the Docker container.
@@ -493,10 +499,11 @@ impl<'a> process_execution::CommandRunner for CommandRunner<'a> {
 
                 workunit.increment_counter(Metric::DockerExecutionRequests, 1);
 
-                let res = self
+                let mut res = self
                     .run_and_capture_workdir(
                         req.clone(),
                         context,
+                        workunit,
                         self.store.clone(),
                         self.executor.clone(),
                         workdir.path().to_owned(),
@@ -512,8 +519,9 @@ impl<'a> process_execution::CommandRunner for CommandRunner<'a> {
                         //
                         // Given that this is expected to be rare, we dump the entire process definition in the
                         // error.
-                        ProcessError::Unclassified(format!(
-                            "Failed to execute: {req_debug_repr}\n\n{msg}"
+                        ProcessError::from(EngineError::new(
+                            ErrorCode::ProcessExecutionFailed,
+                            format!("Failed to execute: {req_debug_repr}\n\n{msg}"),
                         ))
                     })
                     .await;
@@ -527,14 +535,10 @@ impl<'a> process_execution::CommandRunner for CommandRunner<'a> {
                     || self.keep_sandboxes == KeepSandboxes::OnFailure
                         && res.as_ref().map(|r| r.exit_code).unwrap_or(1) != 0
                 {
-                    workdir.keep(&req.description);
-                    setup_run_sh_script(
-                        workdir.path(),
-                        &req.env,
-                        &req.working_directory,
-                        &req.argv,
-                        workdir.path(),
-                    )?;
+                    let retained_sandbox = retain_sandbox(&mut workdir, &req)?;
+                    if let Ok(res) = &mut res {
+                        res.metadata.retained_sandbox = Some(retained_sandbox);
+                    }
                 }
 
                 res
@@ -737,10 +741,17 @@ impl Command {
         container_id: String,
     ) -> Result<(i32, Bytes, Bytes), String> {
         let child_outputs = self.spawn(docker, container_id).await?;
-        let mut stdout = BytesMut::with_capacity(8192);
-        let mut stderr = BytesMut::with_capacity(8192);
-        let exit_code = collect_child_outputs(&mut stdout, &mut stderr, child_outputs).await?;
-        Ok((exit_code, stdout.freeze(), stderr.freeze()))
+        let mut stdout = OutputCapture::unbounded();
+        let mut stderr = OutputCapture::unbounded();
+        let exit_code =
+            collect_child_outputs(&mut stdout, &mut stderr, child_outputs, None).await?;
+        let finish = |capture: OutputCapture| {
+            capture
+                .finish()
+                .map(|captured| Bytes::from(captured.tail))
+                .map_err(|e| format!("Failed to capture output: {e}"))
+        };
+        Ok((exit_code, finish(stdout)?, finish(stderr)?))
     }
 }
 
@@ -6,6 +6,7 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 use bollard::Docker;
+use engine_error::ErrorCode;
 use fs::{RelativePath, EMPTY_DIRECTORY_DIGEST};
 use maplit::hashset;
 use store::{ImmutableInputs, Store};
@@ -97,9 +98,10 @@ async fn runner_errors_if_docker_image_not_set() {
     let err = run_command_via_docker(Process::new(owned_string_vec(&["/bin/echo", "-n", "foo"])))
         .await
         .unwrap_err();
-    if let ProcessError::Unclassified(msg) = &err {
+    if let ProcessError::Classified(e) = &err {
+        assert_eq!(e.code(), ErrorCode::InvalidExecutionStrategy);
         assert!(
-      msg.contains("The Docker execution strategy was not set on the Process, but the Docker CommandRunner was used")
+      e.message().contains("The Docker execution strategy was not set on the Process, but the Docker CommandRunner was used")
     );
     } else {
         panic!("unexpected value: {err:?}")
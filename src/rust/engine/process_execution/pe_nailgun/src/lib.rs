@@ -31,6 +31,7 @@ mod parsed_jvm_command_lines;
 mod parsed_jvm_command_lines_tests;
 
 use nailgun_pool::NailgunPool;
+pub use nailgun_pool::NailgunPoolConfig;
 use parsed_jvm_command_lines::ParsedJVMCommandLines;
 
 // Hardcoded constants for connecting to nailgun
@@ -102,12 +103,12 @@ impl CommandRunner {
         executor: Executor,
         named_caches: NamedCaches,
         immutable_inputs: ImmutableInputs,
-        nailgun_pool_size: usize,
+        nailgun_pool_config: NailgunPoolConfig,
     ) -> Self {
         CommandRunner {
             nailgun_pool: NailgunPool::new(
                 workdir_base,
-                nailgun_pool_size,
+                nailgun_pool_config,
                 store.clone(),
                 executor.clone(),
             ),
@@ -202,6 +203,7 @@ impl process_execution::CommandRunner for CommandRunner {
                     .run_and_capture_workdir(
                         client_req,
                         context,
+                        workunit,
                         self.store.clone(),
                         self.executor.clone(),
                         nailgun_process.workdir_path().to_owned(),
@@ -253,6 +255,7 @@ impl CapturedWorkdir for CommandRunner {
                 env: req
                     .env
                     .iter()
+                    .chain(req.globbed_env.iter())
                     .map(|(k, v)| (k.clone(), v.clone()))
                     .collect(),
                 working_dir: client_workdir,
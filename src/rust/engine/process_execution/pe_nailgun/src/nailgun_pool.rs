@@ -5,24 +5,31 @@ use std::collections::HashSet;
 use std::ffi::OsString;
 use std::io::{self, BufRead, Read};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::os::unix::process::ExitStatusExt;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 use async_lock::{Mutex, MutexGuardArc};
-use futures::future;
+use futures::future::{self, TryFutureExt};
+use futures::StreamExt;
 use lazy_static::lazy_static;
 use log::{debug, info};
+use nails::execution::{child_channel, ChildInput, Command};
 use regex::Regex;
 use tempfile::TempDir;
+use tokio::net::TcpStream;
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
 
 use hashing::Fingerprint;
 use store::{ImmutableInputs, Store};
 use task_executor::Executor;
-use workunit_store::{in_workunit, Level};
+use workunit_store::{
+    in_workunit, increment_counter_if_in_workunit, record_observation_if_in_workunit, Level,
+    Metric, ObservationMetric, WaitingOn,
+};
 
 use process_execution::local::prepare_workdir;
 use process_execution::{NamedCaches, Process, ProcessError};
@@ -48,6 +55,47 @@ struct PoolEntry {
 
 pub type Port = u16;
 
+///
+/// Configuration for the sizing and health checking of a NailgunPool.
+///
+#[derive(Clone, Debug)]
+pub struct NailgunPoolConfig {
+    /// The number of servers which may be in use concurrently before the pool begins to grow.
+    pub min_size: usize,
+    /// The maximum number of servers which may be in use concurrently, or idle in the pool.
+    pub max_size: usize,
+    /// How long a request must wait for a server before the pool grows by one server.
+    pub grow_after: Duration,
+    /// Idle servers which have not been used for longer than this are shut down, and the pool
+    /// shrinks back towards `min_size`.
+    pub idle_ttl: Option<Duration>,
+    /// If set, idle servers are periodically probed by running a trivial command in them, and are
+    /// replaced if they do not complete it within this timeout.
+    pub liveness_probe_timeout: Option<Duration>,
+    /// How often to check for idle or unhealthy servers.
+    pub maintenance_interval: Duration,
+}
+
+impl NailgunPoolConfig {
+    ///
+    /// A pool with a fixed size, which never shuts down idle servers or probes their health.
+    ///
+    pub fn fixed(size: usize) -> NailgunPoolConfig {
+        NailgunPoolConfig {
+            min_size: size,
+            max_size: size,
+            grow_after: Duration::ZERO,
+            idle_ttl: None,
+            liveness_probe_timeout: None,
+            maintenance_interval: Duration::from_secs(30),
+        }
+    }
+
+    fn needs_maintenance(&self) -> bool {
+        self.idle_ttl.is_some() || self.liveness_probe_timeout.is_some()
+    }
+}
+
 ///
 /// A NailgunPool contains a small Vec of running NailgunProcess instances, fingerprinted with the
 /// request used to start them.
@@ -55,27 +103,53 @@ pub type Port = u16;
 /// Mutations of the Vec are protected by a Mutex, but each NailgunProcess is also protected by its
 /// own Mutex, which is used to track when the process is in use.
 ///
+/// The number of servers which may be in use concurrently starts at `min_size`, and grows (up to
+/// `max_size`) when requests are forced to wait for a server. If configured, a background task
+/// shuts down servers which have been idle for longer than the idle TTL (shrinking the pool again),
+/// and replaces servers which fail a liveness probe.
+///
 #[derive(Clone)]
 pub struct NailgunPool {
     workdir_base: PathBuf,
-    size: usize,
+    config: NailgunPoolConfig,
     sema: Arc<Semaphore>,
+    // The number of permits which the semaphore has been sized to.
+    capacity: Arc<AtomicUsize>,
     store: Store,
     executor: Executor,
     processes: Arc<Mutex<Vec<PoolEntry>>>,
 }
 
 impl NailgunPool {
-    pub fn new(workdir_base: PathBuf, size: usize, store: Store, executor: Executor) -> Self {
-        info!("Initializing Nailgun pool for {} processes...", size);
-        NailgunPool {
+    pub fn new(
+        workdir_base: PathBuf,
+        config: NailgunPoolConfig,
+        store: Store,
+        executor: Executor,
+    ) -> Self {
+        info!(
+            "Initializing Nailgun pool for {}-{} processes...",
+            config.min_size, config.max_size
+        );
+        let min_size = std::cmp::min(config.min_size, config.max_size);
+        let pool = NailgunPool {
             workdir_base,
-            size,
-            sema: Arc::new(Semaphore::new(size)),
+            sema: Arc::new(Semaphore::new(min_size)),
+            capacity: Arc::new(AtomicUsize::new(min_size)),
+            config,
             store,
             executor,
             processes: Arc::default(),
+        };
+        if pool.config.needs_maintenance() {
+            let _join = pool.executor.native_spawn(Self::maintain(
+                Arc::downgrade(&pool.processes),
+                pool.sema.clone(),
+                pool.capacity.clone(),
+                pool.config.clone(),
+            ));
         }
+        pool
     }
 
     pub fn workdir_base(&self) -> &Path {
@@ -98,20 +172,18 @@ impl NailgunPool {
         let name = server_process.description.clone();
         let requested_fingerprint =
             NailgunProcessFingerprint::new(name.clone(), &server_process, &self.store).await?;
-        let semaphore_acquisition = self.sema.clone().acquire_owned();
         let permit = in_workunit!(
             "acquire_nailgun_process",
             // TODO: See also `acquire_command_runner_slot` in `bounded::CommandRunner`.
             // https://github.com/pantsbuild/pants/issues/14680
             Level::Debug,
             |workunit| async move {
-                let _blocking_token = workunit.blocking();
-                semaphore_acquisition
-                    .await
-                    .expect("Semaphore should not have been closed.")
+                let _blocking_token = workunit.waiting_on(WaitingOn::Semaphore);
+                self.acquire_permit().await
             }
         )
         .await;
+        increment_counter_if_in_workunit(Metric::NailgunRequests, 1);
 
         let mut process_ref = {
             let mut processes = self.processes.lock().await;
@@ -124,7 +196,7 @@ impl NailgunPool {
             }
 
             // There wasn't a matching, valid, available process. We need to start one.
-            if processes.len() >= self.size {
+            if processes.len() >= self.config.max_size {
                 // Find the oldest idle non-matching process and remove it.
                 let idx = Self::find_lru_idle(&mut processes)?.ok_or_else(|| {
                     // NB: We've acquired a semaphore permit, so this should be impossible.
@@ -146,6 +218,7 @@ impl NailgunPool {
         };
 
         // Now that we're outside the pool's mutex, spawn and return the process.
+        increment_counter_if_in_workunit(Metric::NailgunServersStarted, 1);
         *process_ref = Some(
             NailgunProcess::start_new(
                 name.clone(),
@@ -163,6 +236,130 @@ impl NailgunPool {
         Ok(BorrowedNailgunProcess::new(process_ref, permit))
     }
 
+    ///
+    /// Acquires a permit to use a server, growing the pool (up to its maximum size) each time the
+    /// caller has waited for `grow_after` without a permit becoming available.
+    ///
+    async fn acquire_permit(&self) -> OwnedSemaphorePermit {
+        let acquisition = self.sema.clone().acquire_owned();
+        tokio::pin!(acquisition);
+        loop {
+            // If the pool cannot grow (any further), wait for a permit without polling.
+            if self.config.grow_after.is_zero()
+                || self.capacity.load(Ordering::SeqCst) >= self.config.max_size
+            {
+                return acquisition
+                    .await
+                    .expect("Semaphore should not have been closed.");
+            }
+            match tokio::time::timeout(self.config.grow_after, &mut acquisition).await {
+                Ok(permit) => return permit.expect("Semaphore should not have been closed."),
+                Err(_) => {
+                    let grown = self.capacity.fetch_update(
+                        Ordering::SeqCst,
+                        Ordering::SeqCst,
+                        |capacity| (capacity < self.config.max_size).then_some(capacity + 1),
+                    );
+                    if let Ok(previous) = grown {
+                        debug!("Growing nailgun pool to {} processes.", previous + 1);
+                        self.sema.add_permits(1);
+                    }
+                }
+            }
+        }
+    }
+
+    ///
+    /// Periodically shuts down idle servers, and replaces unhealthy ones, until the pool is dropped.
+    ///
+    async fn maintain(
+        processes: Weak<Mutex<Vec<PoolEntry>>>,
+        sema: Arc<Semaphore>,
+        capacity: Arc<AtomicUsize>,
+        config: NailgunPoolConfig,
+    ) {
+        loop {
+            tokio::time::sleep(config.maintenance_interval).await;
+            let Some(processes) = processes.upgrade() else {
+                return;
+            };
+
+            // Claim the expired idle processes for removal, and note the other idle processes to be
+            // probed.
+            let mut to_remove = Vec::new();
+            let mut to_probe = Vec::new();
+            let mut expired = 0;
+            for pool_entry in processes.lock().await.iter() {
+                let Some(mut process_guard) = pool_entry.process.try_lock_arc() else {
+                    continue;
+                };
+                let Some(process) = process_guard.as_mut() else {
+                    continue;
+                };
+                if config
+                    .idle_ttl
+                    .map(|ttl| process.last_used.elapsed() > ttl)
+                    .unwrap_or(false)
+                {
+                    debug!("Shutting down idle nailgun server {}.", process.name);
+                    expired += 1;
+                    to_remove.push(process_guard);
+                } else if config.liveness_probe_timeout.is_some() {
+                    to_probe.push((pool_entry.process.clone(), process.probe_target()));
+                }
+            }
+
+            // Probe concurrently, without the pool's lock held, and without claiming the processes:
+            // nailgun servers accept concurrent connections, so a probe does not interfere with a
+            // request which claims the process in the meantime.
+            if let Some(timeout) = config.liveness_probe_timeout {
+                let probes = to_probe.into_iter().map(|(process, target)| async move {
+                    target
+                        .probe(timeout)
+                        .await
+                        .err()
+                        .map(|e| (process, target.name, e))
+                });
+                for (process, name, e) in future::join_all(probes).await.into_iter().flatten() {
+                    // Only an unhealthy process which is (still) idle is replaced: one which is in
+                    // use will be probed again later, if it is returned to the pool.
+                    if let Some(process_guard) = process.try_lock_arc() {
+                        log::warn!("Replacing unhealthy nailgun server {name}: {e}");
+                        to_remove.push(process_guard);
+                    }
+                }
+            }
+            if to_remove.is_empty() {
+                continue;
+            }
+
+            processes.lock().await.retain(|pool_entry| {
+                !to_remove
+                    .iter()
+                    .any(|guard| Arc::ptr_eq(MutexGuardArc::source(guard), &pool_entry.process))
+            });
+            // Dropping the guards for the removed entries will drop the processes.
+            std::mem::drop(to_remove);
+
+            // Shrink the pool by forgetting idle permits, down to the minimum size.
+            for _ in 0..expired {
+                let shrunk = capacity.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |c| {
+                    (c > config.min_size).then(|| c - 1)
+                });
+                if shrunk.is_err() {
+                    break;
+                }
+                if let Ok(permit) = sema.clone().try_acquire_owned() {
+                    permit.forget();
+                } else {
+                    // All permits are in use: undo, and try again later.
+                    capacity.fetch_add(1, Ordering::SeqCst);
+                    break;
+                }
+            }
+        }
+    }
+
     ///
     /// Find a usable process in the pool that matches the given fingerprint.
     ///
@@ -240,7 +437,7 @@ impl NailgunPool {
             }
             Some(status) => {
                 // The process has exited with some exit code: restart it.
-                if status.signal() != Some(9) {
+                if process_execution::children::exit_code(status) != -9 {
                     // TODO: BorrowedNailgunProcess cancellation uses `kill` currently, so we avoid warning
                     // for that. In future it would be nice to find a better cancellation strategy.
                     log::warn!(
@@ -276,6 +473,10 @@ pub struct NailgunProcess {
     port: Port,
     executor: task_executor::Executor,
     handle: std::process::Child,
+    // The number of requests which this server has handled.
+    requests: u64,
+    // When this server was last returned to the pool.
+    last_used: Instant,
 }
 
 /// Spawn a nailgun process, and read its port from stdout.
@@ -408,13 +609,90 @@ impl NailgunProcess {
             name,
             executor,
             handle: child,
+            requests: 0,
+            last_used: Instant::now(),
         })
     }
+
+    ///
+    /// The information needed to probe the health of this server without holding it.
+    ///
+    fn probe_target(&mut self) -> ProbeTarget {
+        let exited = match self.handle.try_wait() {
+            Ok(status) => status.map(|status| format!("exited with {status}")),
+            Err(e) => Some(format!(
+                "Error getting the process status from nailgun: {e}"
+            )),
+        };
+        ProbeTarget {
+            name: self.name.clone(),
+            port: self.port,
+            workdir: self.workdir.path().to_owned(),
+            exited,
+        }
+    }
+}
+
+///
+/// A nailgun server to be probed by `NailgunPool::maintain`.
+///
+struct ProbeTarget {
+    name: String,
+    port: Port,
+    workdir: PathBuf,
+    // Set if the server had already exited when it was last checked.
+    exited: Option<String>,
+}
+
+impl ProbeTarget {
+    ///
+    /// Checks that the server is responsive by running the builtin `ng-version` command in it.
+    ///
+    async fn probe(&self, timeout: Duration) -> Result<(), String> {
+        if let Some(exited) = &self.exited {
+            return Err(exited.clone());
+        }
+        let cmd = Command {
+            command: "ng-version".to_owned(),
+            args: vec![],
+            env: vec![],
+            working_dir: self.workdir.clone(),
+        };
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), self.port);
+        let run = TcpStream::connect(addr)
+            .and_then(move |stream| {
+                nails::client::handle_connection(nails::Config::default(), stream, cmd, async {
+                    let (_stdin_write, stdin_read) = child_channel::<ChildInput>();
+                    stdin_read
+                })
+            })
+            .and_then(|mut child| async move {
+                // Drain the output of the command while waiting for it to exit.
+                let output = child
+                    .output_stream
+                    .take()
+                    .unwrap()
+                    .for_each(|_| future::ready(()));
+                let ((), exit_code) = future::join(output, child.wait()).await;
+                exit_code
+            });
+        match tokio::time::timeout(timeout, run).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(format!("liveness probe failed: {e}")),
+            Err(_) => Err(format!(
+                "liveness probe did not complete within {timeout:?}"
+            )),
+        }
+    }
 }
 
 impl Drop for NailgunProcess {
     fn drop(&mut self) {
-        debug!("Exiting nailgun server process {:?}", self.name);
+        debug!(
+            "Exiting nailgun server process {:?} after {} requests",
+            self.name, self.requests
+        );
+        record_observation_if_in_workunit(ObservationMetric::NailgunServerRequests, self.requests);
         if self.handle.kill().is_ok() {
             // NB: This is blocking, but should be a short wait in general.
             let _ = self.handle.wait();
@@ -454,8 +732,11 @@ pub struct BorrowedNailgunProcess(
 );
 
 impl BorrowedNailgunProcess {
-    fn new(process: NailgunProcessRef, permit: OwnedSemaphorePermit) -> Self {
-        assert!(process.is_some());
+    fn new(mut process: NailgunProcessRef, permit: OwnedSemaphorePermit) -> Self {
+        process
+            .as_mut()
+            .expect("A borrowed nailgun process must be present.")
+            .requests += 1;
         Self(Some(process), permit)
     }
 
@@ -483,10 +764,11 @@ impl BorrowedNailgunProcess {
     pub async fn release(&mut self) -> Result<(), String> {
         let process = self
             .0
-            .as_ref()
+            .as_mut()
             .expect("release may only be called once.")
-            .as_ref()
+            .as_mut()
             .unwrap();
+        process.last_used = Instant::now();
 
         clear_workdir(
             &process.executor,
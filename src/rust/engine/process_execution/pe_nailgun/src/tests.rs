@@ -1,6 +1,7 @@
 // Copyright 2022 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 use std::path::PathBuf;
+use std::time::Duration;
 
 use store::{ImmutableInputs, Store};
 use task_executor::Executor;
@@ -8,10 +9,17 @@ use tempfile::TempDir;
 use testutil::owned_string_vec;
 use workunit_store::WorkunitStore;
 
-use crate::NailgunPool;
+use crate::nailgun_pool::BorrowedNailgunProcess;
+use crate::{NailgunPool, NailgunPoolConfig};
 use crate::{NamedCaches, Process};
 
 fn pool(size: usize) -> (NailgunPool, NamedCaches, ImmutableInputs, TempDir) {
+    pool_with_config(NailgunPoolConfig::fixed(size))
+}
+
+fn pool_with_config(
+    config: NailgunPoolConfig,
+) -> (NailgunPool, NamedCaches, ImmutableInputs, TempDir) {
     let _ = WorkunitStore::setup_for_tests();
     let base_dir = TempDir::new().unwrap();
     let named_caches_dir = base_dir.path().join("named");
@@ -19,7 +27,7 @@ fn pool(size: usize) -> (NailgunPool, NamedCaches, ImmutableInputs, TempDir) {
     let executor = Executor::new();
     let store = Store::local_only(executor.clone(), store_dir).unwrap();
 
-    let pool = NailgunPool::new(base_dir.path().to_owned(), size, store.clone(), executor);
+    let pool = NailgunPool::new(base_dir.path().to_owned(), config, store.clone(), executor);
     (
         pool,
         NamedCaches::new_local(named_caches_dir),
@@ -28,9 +36,11 @@ fn pool(size: usize) -> (NailgunPool, NamedCaches, ImmutableInputs, TempDir) {
     )
 }
 
-async fn run(pool: &(NailgunPool, NamedCaches, ImmutableInputs, TempDir), port: u16) -> PathBuf {
-    let mut p = pool
-        .0
+async fn acquire_mock(
+    pool: &(NailgunPool, NamedCaches, ImmutableInputs, TempDir),
+    port: u16,
+) -> BorrowedNailgunProcess {
+    pool.0
         .acquire(
             Process::new(owned_string_vec(&[
                 "/bin/bash",
@@ -41,7 +51,11 @@ async fn run(pool: &(NailgunPool, NamedCaches, ImmutableInputs, TempDir), port:
             &pool.2,
         )
         .await
-        .unwrap();
+        .unwrap()
+}
+
+async fn run(pool: &(NailgunPool, NamedCaches, ImmutableInputs, TempDir), port: u16) -> PathBuf {
+    let mut p = acquire_mock(pool, port).await;
     assert_eq!(port, p.port());
     let workdir = p.workdir_path().to_owned();
     p.release().await.unwrap();
@@ -61,3 +75,79 @@ async fn acquire() {
     let workdir_three = run(&pool, 200).await;
     assert_ne!(workdir_two, workdir_three);
 }
+
+#[tokio::test]
+async fn waits_when_saturated() {
+    tokio::time::pause();
+    let pool = pool(1);
+
+    // While the only process is held, another acquisition waits (without spinning) until it is
+    // released.
+    let mut first = acquire_mock(&pool, 100).await;
+    assert!(
+        tokio::time::timeout(Duration::from_secs(60), acquire_mock(&pool, 200))
+            .await
+            .is_err()
+    );
+    first.release().await.unwrap();
+    drop(first);
+    let mut second = acquire_mock(&pool, 200).await;
+    second.release().await.unwrap();
+}
+
+#[tokio::test]
+async fn grows_under_pressure() {
+    tokio::time::pause();
+    let pool = pool_with_config(NailgunPoolConfig {
+        min_size: 1,
+        grow_after: Duration::from_millis(10),
+        ..NailgunPoolConfig::fixed(2)
+    });
+
+    // While the first process is held, a second acquisition must grow the pool to succeed.
+    let mut first = acquire_mock(&pool, 100).await;
+    let mut second = tokio::time::timeout(Duration::from_secs(5), acquire_mock(&pool, 200))
+        .await
+        .expect("The pool should have grown.");
+    assert_eq!(200, second.port());
+    second.release().await.unwrap();
+    first.release().await.unwrap();
+}
+
+#[tokio::test]
+async fn shuts_down_idle_processes() {
+    tokio::time::pause();
+    let pool = pool_with_config(NailgunPoolConfig {
+        idle_ttl: Some(Duration::from_secs(60)),
+        maintenance_interval: Duration::from_secs(10),
+        ..NailgunPoolConfig::fixed(1)
+    });
+
+    // While the process has been idle for less than the TTL, it is reused.
+    let workdir_one = run(&pool, 100).await;
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    let workdir_two = run(&pool, 100).await;
+    assert_eq!(workdir_one, workdir_two);
+
+    // Once it has been idle for longer than the TTL, it is replaced with a new one.
+    tokio::time::sleep(Duration::from_secs(120)).await;
+    let workdir_three = run(&pool, 100).await;
+    assert_ne!(workdir_two, workdir_three);
+}
+
+#[tokio::test]
+async fn replaces_unhealthy_processes() {
+    tokio::time::pause();
+    let pool = pool_with_config(NailgunPoolConfig {
+        liveness_probe_timeout: Some(Duration::from_secs(1)),
+        maintenance_interval: Duration::from_secs(10),
+        ..NailgunPoolConfig::fixed(1)
+    });
+
+    // The mock process does not serve the port that it reports, so it fails its liveness probe,
+    // and is replaced with a new one.
+    let workdir_one = run(&pool, 100).await;
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    let workdir_two = run(&pool, 100).await;
+    assert_ne!(workdir_one, workdir_two);
+}
@@ -0,0 +1,298 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use engine_error::{EngineError, ErrorCode};
+use fs::{DirectoryDigest, Entry, SymlinkBehavior, EMPTY_DIRECTORY_DIGEST};
+use futures::future::TryFutureExt;
+use futures::stream::{BoxStream, StreamExt};
+use log::{debug, trace};
+use nails::execution::ExitCode;
+use store::{ImmutableInputs, Store};
+use task_executor::Executor;
+use workunit_store::{in_workunit, Metric, RunningWorkunit};
+
+use process_execution::local::{prepare_workdir, CapturedWorkdir, ChildOutput};
+use process_execution::{
+    Context, FallibleProcessResultWithPlatform, InputDigests, NamedCaches, PersistentWorker,
+    Process, ProcessError,
+};
+
+pub mod protocol;
+#[cfg(test)]
+mod tests;
+mod worker_pool;
+
+use protocol::Input;
+pub use worker_pool::WorkerPoolConfig;
+use worker_pool::{Worker, WorkerPool};
+
+/// The flag which tells a tool to start as a persistent worker, rather than running once.
+static PERSISTENT_WORKER_FLAG: &str = "--persistent_worker";
+
+///
+/// Constructs the Process which starts a worker: the startup arguments of the client request, with
+/// its immutable inputs, caches and JDK.
+///
+fn construct_worker_request(
+    client_request: &Process,
+    worker: &PersistentWorker,
+    input_digests: InputDigests,
+) -> Process {
+    let mut argv = client_request.argv[..worker.request_args_start].to_vec();
+    argv.push(PERSISTENT_WORKER_FLAG.to_owned());
+
+    Process {
+        argv,
+        input_digests,
+        working_directory: None,
+        output_files: BTreeSet::new(),
+        output_directories: BTreeSet::new(),
+        timeout: None,
+        description: format!("persistent worker for {}", worker.key),
+        level: log::Level::Info,
+        execution_slot_variable: None,
+        concurrency_available: 0,
+        ..client_request.clone()
+    }
+}
+
+fn construct_worker_client_request(original_req: Process) -> Process {
+    Process {
+        input_digests: InputDigests::with_input_files(original_req.input_digests.inputs.clone()),
+        // The append_only_caches and JDK are set up in the worker's directory.
+        append_only_caches: BTreeMap::new(),
+        jdk_home: None,
+        ..original_req
+    }
+}
+
+///
+/// A command runner that runs local requests as requests to persistent workers.
+///
+/// It should only be invoked with local requests which have set `Process::persistent_worker`.
+/// Workers are started with the startup arguments of the request (followed by
+/// `--persistent_worker`), and are then sent the remaining arguments of each request via the
+/// (Bazel-compatible) worker protocol.
+///
+pub struct CommandRunner {
+    worker_pool: WorkerPool,
+    store: Store,
+    executor: Executor,
+    named_caches: NamedCaches,
+    immutable_inputs: ImmutableInputs,
+}
+
+impl CommandRunner {
+    pub fn new(
+        workdir_base: PathBuf,
+        store: Store,
+        executor: Executor,
+        named_caches: NamedCaches,
+        immutable_inputs: ImmutableInputs,
+        worker_pool_config: WorkerPoolConfig,
+    ) -> Self {
+        CommandRunner {
+            worker_pool: WorkerPool::new(
+                workdir_base,
+                worker_pool_config,
+                store.clone(),
+                executor.clone(),
+            ),
+            store,
+            executor,
+            named_caches,
+            immutable_inputs,
+        }
+    }
+
+    ///
+    /// Lists the files of the given input digest, with their digests, to be sent in a WorkRequest.
+    ///
+    async fn request_inputs(&self, inputs: DirectoryDigest) -> Result<Vec<Input>, ProcessError> {
+        let trie = self.store.load_digest_trie(inputs).await?;
+        let mut files = Vec::new();
+        trie.walk(SymlinkBehavior::Oblivious, &mut |path, entry| {
+            if let Entry::File(f) = entry {
+                files.push(Input {
+                    path: path.to_string_lossy().into_owned(),
+                    digest: f.digest().hash.to_hex(),
+                });
+            }
+        });
+        Ok(files)
+    }
+}
+
+impl Debug for CommandRunner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("pe_worker::CommandRunner")
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl process_execution::CommandRunner for CommandRunner {
+    async fn run(
+        &self,
+        context: Context,
+        _workunit: &mut RunningWorkunit,
+        req: Process,
+    ) -> Result<FallibleProcessResultWithPlatform, ProcessError> {
+        debug!("Running request in a persistent worker:\n {:?}", req);
+
+        in_workunit!(
+            "run_persistent_worker_process",
+            // NB: See engine::nodes::NodeKey::workunit_level for more information on why this workunit
+            // renders at the Process's level.
+            req.level,
+            desc = Some(req.description.clone()),
+            |workunit| async move {
+                workunit.increment_counter(Metric::LocalExecutionRequests, 1);
+
+                let worker = req.persistent_worker.clone().ok_or_else(|| {
+                    ProcessError::from(EngineError::new(
+                        ErrorCode::InvalidExecutionStrategy,
+                        "The persistent worker runner was used for a Process which did not declare \
+                         a persistent worker.",
+                    ))
+                })?;
+                if worker.request_args_start == 0 || worker.request_args_start > req.argv.len() {
+                    return Err(EngineError::new(
+                        ErrorCode::InvalidPersistentWorker,
+                        format!(
+                            "The persistent worker `request_args_start` ({}) must be between 1 and \
+                             the length of the argv ({}).",
+                            worker.request_args_start,
+                            req.argv.len()
+                        ),
+                    )
+                    .into());
+                }
+                if req.working_directory.is_some() {
+                    return Err(EngineError::new(
+                        ErrorCode::InvalidPersistentWorker,
+                        "A Process which runs in a persistent worker may not set a \
+                         working_directory.",
+                    )
+                    .into());
+                }
+
+                // Separate the inputs, to form distinct Processes for
+                //  1. starting the worker
+                //  2. the request sent to it
+                let worker_input_digests = InputDigests::new(
+                    &self.store,
+                    EMPTY_DIRECTORY_DIGEST.clone(),
+                    req.input_digests.immutable_inputs.clone(),
+                    BTreeSet::new(),
+                )
+                .await?;
+                let worker_req = construct_worker_request(&req, &worker, worker_input_digests);
+                let client_req = construct_worker_client_request(req);
+                trace!(
+                    "Running request in a persistent worker:\n {:#?}",
+                    &client_req
+                );
+
+                // Get a worker for this fingerprint, and then run in its sandbox.
+                let mut borrowed_worker = self
+                    .worker_pool
+                    .acquire(
+                        worker_req,
+                        &worker,
+                        &self.named_caches,
+                        &self.immutable_inputs,
+                    )
+                    .await
+                    .map_err(|e| e.enrich("Failed to start persistent worker"))?;
+
+                prepare_workdir(
+                    borrowed_worker.sandbox_path().to_owned(),
+                    self.worker_pool.workdir_base(),
+                    &client_req,
+                    client_req.input_digests.inputs.clone(),
+                    &self.store,
+                    &self.named_caches,
+                    &self.immutable_inputs,
+                    None,
+                    None,
+                )
+                .await?;
+                let inputs = self
+                    .request_inputs(client_req.input_digests.inputs.clone())
+                    .await?;
+
+                let res = self
+                    .run_and_capture_workdir(
+                        client_req,
+                        context,
+                        workunit,
+                        self.store.clone(),
+                        self.executor.clone(),
+                        borrowed_worker.sandbox_path().to_owned(),
+                        (
+                            borrowed_worker.worker().clone(),
+                            borrowed_worker.sandbox_dir(),
+                            inputs,
+                        ),
+                        false,
+                    )
+                    .await;
+
+                // NB: We explicitly release the BorrowedWorker, because when it is Dropped without
+                // release, it assumes that it has been canceled.
+                borrowed_worker.release().await?;
+
+                Ok(res?)
+            }
+        )
+        .await
+    }
+
+    async fn shutdown(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CapturedWorkdir for CommandRunner {
+    /// The worker, the sandbox directory of the request (if multiplex), and the request's inputs.
+    type WorkdirToken = (Arc<Worker>, Option<String>, Vec<Input>);
+
+    async fn run_in_workdir<'s, 'c, 'w, 'r>(
+        &'s self,
+        _context: &'c Context,
+        _workdir_path: &'w Path,
+        workdir_token: Self::WorkdirToken,
+        req: Process,
+        _exclusive_spawn: bool,
+    ) -> Result<BoxStream<'r, Result<ChildOutput, String>>, String> {
+        let (worker, sandbox_dir, inputs) = workdir_token;
+        let request_args_start = req
+            .persistent_worker
+            .as_ref()
+            .map(|w| w.request_args_start)
+            .unwrap_or_default();
+        let arguments = req.argv[request_args_start..].to_vec();
+        debug!("Sending request to persistent worker {}...", worker.name());
+
+        let response = async move { worker.request(arguments, inputs, sandbox_dir).await };
+        // NB: Like Bazel, we render the output of the response as the stderr of the process, since
+        // it generally contains diagnostics.
+        Ok(response
+            .map_ok(|response| {
+                futures::stream::iter(vec![
+                    Ok(ChildOutput::Stderr(response.output.into())),
+                    Ok(ChildOutput::Exit(ExitCode(response.exit_code))),
+                ])
+            })
+            .try_flatten_stream()
+            .boxed())
+    }
+}
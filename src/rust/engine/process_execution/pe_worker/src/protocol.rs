@@ -0,0 +1,176 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use bytes::BytesMut;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use process_execution::WorkerProtocol;
+use protos::gen::blaze::worker as pb;
+
+///
+/// An input to a WorkRequest. The digest is an opaque token which changes when the content of the
+/// file changes.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Input {
+    pub path: String,
+    pub digest: String,
+}
+
+///
+/// A request sent to a worker. Field names (when encoded as JSON) match the JSON encoding of
+/// `blaze.worker.WorkRequest`.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkRequest {
+    pub arguments: Vec<String>,
+    pub inputs: Vec<Input>,
+    pub request_id: i32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cancel: bool,
+    pub verbosity: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox_dir: Option<String>,
+}
+
+///
+/// A response received from a worker. Field names (when encoded as JSON) match the JSON encoding
+/// of `blaze.worker.WorkResponse`.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkResponse {
+    pub exit_code: i32,
+    pub output: String,
+    pub request_id: i32,
+    pub was_cancelled: bool,
+}
+
+impl From<WorkRequest> for pb::WorkRequest {
+    fn from(request: WorkRequest) -> Self {
+        pb::WorkRequest {
+            arguments: request.arguments,
+            inputs: request
+                .inputs
+                .into_iter()
+                .map(|input| pb::Input {
+                    path: input.path,
+                    digest: input.digest.into_bytes().into(),
+                })
+                .collect(),
+            request_id: request.request_id,
+            cancel: request.cancel,
+            verbosity: request.verbosity,
+            sandbox_dir: request.sandbox_dir.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<pb::WorkResponse> for WorkResponse {
+    fn from(response: pb::WorkResponse) -> Self {
+        WorkResponse {
+            exit_code: response.exit_code,
+            output: response.output,
+            request_id: response.request_id,
+            was_cancelled: response.was_cancelled,
+        }
+    }
+}
+
+///
+/// Encodes a request in the given protocol, including any framing.
+///
+pub fn encode_request(protocol: WorkerProtocol, request: WorkRequest) -> Result<Vec<u8>, String> {
+    match protocol {
+        WorkerProtocol::Json => {
+            let mut line = serde_json::to_vec(&request)
+                .map_err(|e| format!("Failed to encode WorkRequest as JSON: {e}"))?;
+            line.push(b'\n');
+            Ok(line)
+        }
+        WorkerProtocol::Proto => {
+            Ok(pb::WorkRequest::from(request).encode_length_delimited_to_vec())
+        }
+    }
+}
+
+pub async fn write_request<W: AsyncWrite + Unpin>(
+    protocol: WorkerProtocol,
+    writer: &mut W,
+    request: WorkRequest,
+) -> Result<(), String> {
+    let bytes = encode_request(protocol, request)?;
+    writer
+        .write_all(&bytes)
+        .await
+        .map_err(|e| format!("Failed to write WorkRequest: {e}"))?;
+    writer
+        .flush()
+        .await
+        .map_err(|e| format!("Failed to flush WorkRequest: {e}"))
+}
+
+///
+/// Reads the next response from the worker, or returns None if the worker closed its output.
+///
+pub async fn read_response<R: AsyncBufRead + Unpin>(
+    protocol: WorkerProtocol,
+    reader: &mut R,
+) -> Result<Option<WorkResponse>, String> {
+    match protocol {
+        WorkerProtocol::Json => loop {
+            let mut line = String::new();
+            let read = reader
+                .read_line(&mut line)
+                .await
+                .map_err(|e| format!("Failed to read WorkResponse: {e}"))?;
+            if read == 0 {
+                return Ok(None);
+            }
+            // Tolerate blank lines between responses.
+            if line.trim().is_empty() {
+                continue;
+            }
+            return serde_json::from_str(&line)
+                .map(Some)
+                .map_err(|e| format!("Failed to decode WorkResponse from {line:?}: {e}"));
+        },
+        WorkerProtocol::Proto => {
+            let len = match read_varint(reader).await? {
+                Some(len) => len,
+                None => return Ok(None),
+            };
+            let mut buf = BytesMut::zeroed(len as usize);
+            reader
+                .read_exact(&mut buf)
+                .await
+                .map_err(|e| format!("Failed to read WorkResponse: {e}"))?;
+            pb::WorkResponse::decode(buf.freeze())
+                .map(|response| Some(response.into()))
+                .map_err(|e| format!("Failed to decode WorkResponse: {e}"))
+        }
+    }
+}
+
+///
+/// Reads a protobuf varint, or returns None if the reader was already at EOF.
+///
+async fn read_varint<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<u64>, String> {
+    let mut value: u64 = 0;
+    for (i, shift) in (0..64).step_by(7).enumerate() {
+        let byte = match reader.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof && i == 0 => return Ok(None),
+            Err(e) => return Err(format!("Failed to read WorkResponse length: {e}")),
+        };
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err("WorkResponse length was not a valid varint.".to_owned())
+}
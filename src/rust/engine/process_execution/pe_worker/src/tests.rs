@@ -0,0 +1,243 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::io::Cursor;
+use std::time::Duration;
+
+use prost::Message;
+use store::{ImmutableInputs, Store};
+use task_executor::Executor;
+use tempfile::TempDir;
+use testutil::owned_string_vec;
+use workunit_store::{RunningWorkunit, WorkunitStore};
+
+use process_execution::{
+    CommandRunner as _, Context, NamedCaches, PersistentWorker, Process, ProcessError,
+    WorkerProtocol,
+};
+use protos::gen::blaze::worker as pb;
+
+use crate::protocol::{self, WorkRequest, WorkResponse};
+use crate::{CommandRunner, WorkerPoolConfig};
+
+#[tokio::test]
+async fn proto_roundtrip() {
+    let request = WorkRequest {
+        arguments: owned_string_vec(&["--output", "out.jar"]),
+        request_id: 7,
+        sandbox_dir: Some("sandbox".to_owned()),
+        ..WorkRequest::default()
+    };
+    let encoded = protocol::encode_request(WorkerProtocol::Proto, request).unwrap();
+    let decoded = pb::WorkRequest::decode_length_delimited(&encoded[..]).unwrap();
+    assert_eq!(decoded.arguments, vec!["--output", "out.jar"]);
+    assert_eq!(decoded.request_id, 7);
+    assert_eq!(decoded.sandbox_dir, "sandbox");
+
+    let mut responses = Vec::new();
+    for request_id in [1, 2] {
+        pb::WorkResponse {
+            exit_code: 1,
+            output: format!("response {request_id}"),
+            request_id,
+            was_cancelled: false,
+        }
+        .encode_length_delimited(&mut responses)
+        .unwrap();
+    }
+    let mut reader = Cursor::new(responses);
+    for request_id in [1, 2] {
+        let response = protocol::read_response(WorkerProtocol::Proto, &mut reader)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.request_id, request_id);
+        assert_eq!(response.output, format!("response {request_id}"));
+    }
+    assert_eq!(
+        None,
+        protocol::read_response(WorkerProtocol::Proto, &mut reader)
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn json_roundtrip() {
+    let request = WorkRequest {
+        arguments: owned_string_vec(&["a"]),
+        ..WorkRequest::default()
+    };
+    let encoded = protocol::encode_request(WorkerProtocol::Json, request).unwrap();
+    assert_eq!(
+        String::from_utf8(encoded).unwrap(),
+        "{\"arguments\":[\"a\"],\"inputs\":[],\"requestId\":0,\"verbosity\":0}\n"
+    );
+
+    // Missing fields are defaulted.
+    let mut reader = Cursor::new(b"{\"exitCode\": 2}\n\n{\"output\": \"ok\"}\n".to_vec());
+    let first = protocol::read_response(WorkerProtocol::Json, &mut reader)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.exit_code, 2);
+    let second = protocol::read_response(WorkerProtocol::Json, &mut reader)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        second,
+        WorkResponse {
+            output: "ok".to_owned(),
+            ..WorkResponse::default()
+        }
+    );
+}
+
+fn runner(base_dir: &TempDir, config: WorkerPoolConfig) -> (CommandRunner, Store) {
+    let executor = Executor::new();
+    let store = Store::local_only(executor.clone(), base_dir.path().join("store")).unwrap();
+    let runner = CommandRunner::new(
+        base_dir.path().to_owned(),
+        store.clone(),
+        executor,
+        NamedCaches::new_local(base_dir.path().join("named")),
+        ImmutableInputs::new(store.clone(), base_dir.path()).unwrap(),
+        config,
+    );
+    (runner, store)
+}
+
+fn config() -> WorkerPoolConfig {
+    WorkerPoolConfig {
+        max_workers: 1,
+        max_workers_per_key: 1,
+        max_multiplex_requests: 1,
+        idle_ttl: None,
+        maintenance_interval: Duration::from_secs(60),
+    }
+}
+
+///
+/// A worker which counts the requests that it has handled, after running the given (shell)
+/// prelude for each request.
+///
+fn counter_process(key: &str, prelude: &str) -> Process {
+    let script = format!(
+        "n=0; while IFS= read -r line; do {prelude} n=$((n+1)); \
+         echo \"{{\\\"exitCode\\\": 3, \\\"output\\\": \\\"request $n\\\"}}\"; done"
+    );
+    Process::new(owned_string_vec(&[
+        "/bin/bash",
+        "-c",
+        &script,
+        "request-arg",
+    ]))
+    .persistent_worker(PersistentWorker {
+        key: key.to_owned(),
+        protocol: WorkerProtocol::Json,
+        request_args_start: 3,
+        multiplex: false,
+    })
+}
+
+///
+/// Runs the process, and returns the output of the worker.
+///
+async fn run_counter(
+    runner: &CommandRunner,
+    store: &Store,
+    workunit: &mut RunningWorkunit,
+    process: Process,
+) -> Result<String, ProcessError> {
+    let result = runner.run(Context::default(), workunit, process).await?;
+    assert_eq!(result.exit_code, 3);
+    let stderr = store
+        .load_file_bytes_with(result.stderr_digest, |bytes| bytes.to_vec())
+        .await
+        .unwrap();
+    Ok(String::from_utf8(stderr).unwrap())
+}
+
+#[tokio::test]
+async fn reuses_worker() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    let base_dir = TempDir::new().unwrap();
+    let (runner, store) = runner(&base_dir, config());
+
+    let process = counter_process("counter", "");
+    for n in 1..=2 {
+        let output = run_counter(&runner, &store, &mut workunit, process.clone())
+            .await
+            .unwrap();
+        assert_eq!(output, format!("request {n}"));
+    }
+}
+
+#[tokio::test]
+async fn replaces_exited_worker() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    let base_dir = TempDir::new().unwrap();
+    let (runner, store) = runner(&base_dir, config());
+
+    // The first worker exits when it receives its first request: its replacement does not.
+    let marker = base_dir.path().join("crashed");
+    let process = counter_process(
+        "crashing",
+        &format!(
+            "if [ ! -e {0} ]; then : > {0}; exit 1; fi;",
+            marker.display()
+        ),
+    );
+    let err = run_counter(&runner, &store, &mut workunit, process.clone())
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("exited while handling a request"),
+        "{err}"
+    );
+    let output = run_counter(&runner, &store, &mut workunit, process)
+        .await
+        .unwrap();
+    assert_eq!(output, "request 1");
+}
+
+#[tokio::test]
+async fn evicts_idle_worker_when_full() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    let base_dir = TempDir::new().unwrap();
+    let (runner, store) = runner(&base_dir, config());
+
+    // With room for only one worker, each distinct worker replaces the other.
+    let a = counter_process("a", "");
+    let b = counter_process("b", "");
+    for process in [a.clone(), b, a] {
+        let output = run_counter(&runner, &store, &mut workunit, process)
+            .await
+            .unwrap();
+        assert_eq!(output, "request 1");
+    }
+}
+
+#[tokio::test]
+async fn evicts_expired_worker() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    let base_dir = TempDir::new().unwrap();
+    let (runner, store) = runner(
+        &base_dir,
+        WorkerPoolConfig {
+            max_workers: 2,
+            idle_ttl: Some(Duration::ZERO),
+            maintenance_interval: Duration::from_millis(10),
+            ..config()
+        },
+    );
+
+    let process = counter_process("counter", "");
+    for _ in 1..=2 {
+        let output = run_counter(&runner, &store, &mut workunit, process.clone())
+            .await
+            .unwrap();
+        assert_eq!(output, "request 1");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
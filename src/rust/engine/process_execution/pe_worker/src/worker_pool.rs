@@ -0,0 +1,717 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use futures::future;
+use log::debug;
+use parking_lot::Mutex;
+use tempfile::TempDir;
+use tokio::io::BufReader;
+use tokio::process::{Child, ChildStdin};
+use tokio::sync::{oneshot, Notify};
+
+use hashing::Fingerprint;
+use store::{ImmutableInputs, Store};
+use task_executor::Executor;
+use workunit_store::{increment_counter_if_in_workunit, Metric};
+
+use process_execution::local::{create_sandbox, prepare_workdir, AsyncDropSandbox, KeepSandboxes};
+use process_execution::{NamedCaches, PersistentWorker, Process, ProcessError, WorkerProtocol};
+
+use crate::protocol::{self, Input, WorkRequest, WorkResponse};
+
+/// The name of the directory within a worker's directory which it is started in.
+const EXECROOT: &str = "execroot";
+/// The name of the file within a worker's directory which its stderr is written to.
+const STDERR_LOG: &str = "stderr.log";
+
+///
+/// Configuration for the sizing of a WorkerPool.
+///
+#[derive(Clone, Debug)]
+pub struct WorkerPoolConfig {
+    /// The maximum number of workers which may be running, across all distinct workers. When the
+    /// pool is full, the least recently used idle worker is shut down to make room for a new one.
+    pub max_workers: usize,
+    /// The maximum number of workers which may be running for each distinct worker.
+    pub max_workers_per_key: usize,
+    /// The maximum number of concurrent requests which will be sent to one multiplex worker.
+    pub max_multiplex_requests: usize,
+    /// Idle workers which have not been used for longer than this are shut down.
+    pub idle_ttl: Option<Duration>,
+    /// How often to check for idle workers.
+    pub maintenance_interval: Duration,
+}
+
+///
+/// A WorkerPool holds running persistent workers, fingerprinted with the request used to start
+/// them.
+///
+/// Singleplex workers handle one request at a time, in their own working directory. Multiplex
+/// workers handle up to `max_multiplex_requests` concurrent requests, each in a sandbox directory
+/// below the worker's working directory.
+///
+/// Workers which exit are replaced on demand. If configured, a background task shuts down workers
+/// which have been idle for longer than the idle TTL.
+///
+#[derive(Clone)]
+pub struct WorkerPool {
+    workdir_base: PathBuf,
+    config: WorkerPoolConfig,
+    store: Store,
+    executor: Executor,
+    state: Arc<Mutex<PoolState>>,
+    // Notified whenever a worker is released (or fails to start), so that waiters can try again.
+    released: Arc<Notify>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    workers: Vec<Arc<Worker>>,
+    // The number of workers which are being started (without the lock held) for each key and
+    // fingerprint: they count towards the limits of the pool.
+    starting: HashMap<(String, Fingerprint), usize>,
+}
+
+impl PoolState {
+    fn running(&self) -> usize {
+        self.workers.len() + self.starting.values().sum::<usize>()
+    }
+
+    ///
+    /// Shuts down the least recently used idle worker, if there is one.
+    ///
+    fn evict_lru_idle(&mut self) -> bool {
+        let lru = self
+            .workers
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| w.in_flight.load(Ordering::SeqCst) == 0)
+            .min_by_key(|(_, w)| *w.last_used.lock())
+            .map(|(idx, _)| idx);
+        let Some(idx) = lru else {
+            return false;
+        };
+        let w = self.workers.swap_remove(idx);
+        debug!(
+            "Shutting down idle persistent worker {} to make room.",
+            w.name
+        );
+        w.kill();
+        true
+    }
+}
+
+impl WorkerPool {
+    pub fn new(
+        workdir_base: PathBuf,
+        config: WorkerPoolConfig,
+        store: Store,
+        executor: Executor,
+    ) -> Self {
+        let state: Arc<Mutex<PoolState>> = Arc::default();
+        if let Some(idle_ttl) = config.idle_ttl {
+            let _join = executor.native_spawn(Self::maintain(
+                Arc::downgrade(&state),
+                idle_ttl,
+                config.maintenance_interval,
+            ));
+        }
+        WorkerPool {
+            workdir_base,
+            config,
+            store,
+            executor,
+            state,
+            released: Arc::default(),
+        }
+    }
+
+    pub fn workdir_base(&self) -> &Path {
+        &self.workdir_base
+    }
+
+    ///
+    /// Given a Process to start a worker, find a compatible worker with capacity in the pool
+    /// (starting one if there is room), or wait for one to be released.
+    ///
+    pub async fn acquire(
+        &self,
+        worker_req: Process,
+        worker: &PersistentWorker,
+        named_caches: &NamedCaches,
+        immutable_inputs: &ImmutableInputs,
+    ) -> Result<BorrowedWorker, ProcessError> {
+        let fingerprint = process_execution::get_digest(&worker_req, None, None, &self.store, None)
+            .await
+            .hash;
+        let capacity = if worker.multiplex {
+            self.config.max_multiplex_requests
+        } else {
+            1
+        };
+
+        let starting = loop {
+            // NB: Created before checking the pool, so that a release which happens between checking
+            // and waiting is not missed.
+            let released = self.released.notified();
+            {
+                let mut state = self.state.lock();
+
+                // Drop workers which have exited, and idle workers for this key which were started with
+                // a different fingerprint (because their tool or configuration has changed).
+                state.workers.retain(|w| {
+                    let stale = w.key == worker.key
+                        && !w.matches(worker, fingerprint)
+                        && w.in_flight.load(Ordering::SeqCst) == 0;
+                    let keep = w.is_alive() && !stale;
+                    if !keep {
+                        w.kill();
+                    }
+                    keep
+                });
+
+                if let Some(w) = state
+                    .workers
+                    .iter()
+                    .filter(|w| w.matches(worker, fingerprint))
+                    .find(|w| w.try_reserve())
+                {
+                    return self.borrow(w.clone());
+                }
+
+                let starting_key = (worker.key.clone(), fingerprint);
+                let running_for_key = state
+                    .workers
+                    .iter()
+                    .filter(|w| w.matches(worker, fingerprint))
+                    .count()
+                    + state.starting.get(&starting_key).copied().unwrap_or(0);
+                if running_for_key < self.config.max_workers_per_key
+                    && (state.running() < self.config.max_workers || state.evict_lru_idle())
+                {
+                    *state.starting.entry(starting_key.clone()).or_default() += 1;
+                    break Starting {
+                        pool: self,
+                        key: starting_key,
+                    };
+                }
+            }
+            released.await;
+        };
+
+        // NB: Workers are started without the lock held, since preparing their directories may be
+        // slow: the `Starting` guard counts them towards the limits of the pool in the meantime.
+        let w = Arc::new(
+            Worker::start(
+                worker_req,
+                worker,
+                fingerprint,
+                capacity,
+                &self.workdir_base,
+                &self.store,
+                &self.executor,
+                named_caches,
+                immutable_inputs,
+            )
+            .await?,
+        );
+        increment_counter_if_in_workunit(Metric::PersistentWorkersStarted, 1);
+        // NB: Reserved before it is added to the pool, so that no other caller can claim it first.
+        // The worker may nonetheless have already exited.
+        if !w.try_reserve() {
+            return Err(format!(
+                "Persistent worker {} exited after starting. Its stderr is in {}",
+                w.name,
+                w.workdir.path().join(STDERR_LOG).display()
+            )
+            .into());
+        }
+        self.state.lock().workers.push(w.clone());
+        std::mem::drop(starting);
+        self.borrow(w)
+    }
+
+    fn borrow(&self, worker: Arc<Worker>) -> Result<BorrowedWorker, ProcessError> {
+        // NB: The guard owns the reservation before the sandbox is created, so that the reservation
+        // is released if creating the sandbox fails.
+        let mut borrowed = BorrowedWorker {
+            worker: Some(worker),
+            sandbox: None,
+            executor: self.executor.clone(),
+            released: self.released.clone(),
+        };
+        if borrowed.worker().capacity > 1 {
+            let worker = borrowed.worker().clone();
+            borrowed.sandbox = Some(create_sandbox(
+                self.executor.clone(),
+                &worker.execroot,
+                &worker.name,
+                KeepSandboxes::Never,
+            )?);
+        }
+        Ok(borrowed)
+    }
+
+    ///
+    /// Periodically shuts down workers which have been idle for longer than `idle_ttl`, until the
+    /// pool is dropped.
+    ///
+    async fn maintain(state: Weak<Mutex<PoolState>>, idle_ttl: Duration, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(state) = state.upgrade() else {
+                return;
+            };
+            // NB: Workers are only reserved while the lock is held, so an idle worker cannot be
+            // reserved while it is being removed.
+            state.lock().workers.retain(|w| {
+                let expired = w.in_flight.load(Ordering::SeqCst) == 0
+                    && w.last_used.lock().elapsed() > idle_ttl;
+                if expired {
+                    debug!("Shutting down idle persistent worker {}.", w.name);
+                    w.kill();
+                }
+                !expired
+            });
+        }
+    }
+}
+
+///
+/// Counts a worker which is being started towards the limits of the pool, until it is dropped.
+///
+struct Starting<'a> {
+    pool: &'a WorkerPool,
+    key: (String, Fingerprint),
+}
+
+impl Drop for Starting<'_> {
+    fn drop(&mut self) {
+        {
+            let mut state = self.pool.state.lock();
+            if let Some(count) = state.starting.get_mut(&self.key) {
+                *count -= 1;
+                if *count == 0 {
+                    state.starting.remove(&self.key);
+                }
+            }
+        }
+        // Waiters may now have room to start a worker (or may find the one which was started).
+        self.pool.released.notify_waiters();
+    }
+}
+
+///
+/// A running persistent worker process.
+///
+pub struct Worker {
+    name: String,
+    key: String,
+    fingerprint: Fingerprint,
+    protocol: WorkerProtocol,
+    // The number of concurrent requests which may be sent to this worker.
+    capacity: usize,
+    workdir: TempDir,
+    execroot: PathBuf,
+    execroot_include_names: HashSet<OsString>,
+    child: Mutex<Child>,
+    stdin: async_lock::Mutex<ChildStdin>,
+    pending: Arc<Mutex<HashMap<i32, oneshot::Sender<WorkResponse>>>>,
+    alive: Arc<AtomicBool>,
+    in_flight: AtomicUsize,
+    // When a request to this worker was last completed (or when it was started).
+    last_used: Mutex<Instant>,
+    next_request_id: AtomicI32,
+    requests: AtomicU64,
+}
+
+impl Worker {
+    async fn start(
+        worker_req: Process,
+        worker: &PersistentWorker,
+        fingerprint: Fingerprint,
+        capacity: usize,
+        workdir_base: &Path,
+        store: &Store,
+        executor: &Executor,
+        named_caches: &NamedCaches,
+        immutable_inputs: &ImmutableInputs,
+    ) -> Result<Worker, ProcessError> {
+        let name = format!("{}_{}", worker.key, &fingerprint.to_hex()[..8]);
+        let workdir = tempfile::Builder::new()
+            .prefix("pants-worker-")
+            .tempdir_in(workdir_base)
+            .map_err(|err| format!("Error making tempdir for persistent worker: {err:?}"))?;
+        let execroot = workdir.path().join(EXECROOT);
+        tokio::fs::create_dir(&execroot)
+            .await
+            .map_err(|e| format!("Error making execroot for persistent worker: {e}"))?;
+
+        // Prepare the execroot, and then list it to identify the base set of names which should be
+        // preserved across requests.
+        prepare_workdir(
+            execroot.clone(),
+            workdir_base,
+            &worker_req,
+            worker_req.input_digests.inputs.clone(),
+            store,
+            named_caches,
+            immutable_inputs,
+            None,
+            None,
+        )
+        .await?;
+        let execroot_include_names = list_workdir(&execroot).await?;
+
+        let stderr_path = workdir.path().join(STDERR_LOG);
+        let stderr = std::fs::File::create(&stderr_path)
+            .map_err(|e| format!("Error creating {}: {e}", stderr_path.display()))?;
+        debug!(
+            "Starting persistent worker {name} with argv {:?} in {}",
+            worker_req.argv,
+            execroot.display()
+        );
+        let mut child = tokio::process::Command::new(&worker_req.argv[0])
+            .args(&worker_req.argv[1..])
+            .env_clear()
+            .envs(&worker_req.env)
+            .envs(&worker_req.globbed_env)
+            .current_dir(&execroot)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(stderr)
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                format!(
+                    "Failed to start persistent worker {name} with argv {:?}: {e}",
+                    worker_req.argv
+                )
+            })?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // Dispatch responses to the requests which are waiting for them.
+        let pending: Arc<Mutex<HashMap<i32, oneshot::Sender<WorkResponse>>>> = Arc::default();
+        let alive = Arc::new(AtomicBool::new(true));
+        let _reader = executor.native_spawn({
+            let name = name.clone();
+            let protocol = worker.protocol;
+            let pending = pending.clone();
+            let alive = alive.clone();
+            async move {
+                let mut stdout = BufReader::new(stdout);
+                let error = loop {
+                    match protocol::read_response(protocol, &mut stdout).await {
+                        Ok(Some(response)) => {
+                            if let Some(sender) = pending.lock().remove(&response.request_id) {
+                                let _ = sender.send(response);
+                            } else {
+                                debug!(
+                                    "Persistent worker {name} responded to request {}, which is no longer \
+                                     waiting.",
+                                    response.request_id
+                                );
+                            }
+                        }
+                        Ok(None) => break "closed its stdout".to_owned(),
+                        Err(e) => break e,
+                    }
+                };
+                debug!("Persistent worker {name} is no longer usable: {error}");
+                alive.store(false, Ordering::SeqCst);
+                // Dropping the senders fails any requests which are still waiting.
+                pending.lock().clear();
+            }
+        });
+
+        Ok(Worker {
+            name,
+            key: worker.key.clone(),
+            fingerprint,
+            protocol: worker.protocol,
+            capacity,
+            workdir,
+            execroot,
+            execroot_include_names,
+            child: Mutex::new(child),
+            stdin: async_lock::Mutex::new(stdin),
+            pending,
+            alive,
+            in_flight: AtomicUsize::new(0),
+            last_used: Mutex::new(Instant::now()),
+            next_request_id: AtomicI32::new(0),
+            requests: AtomicU64::new(0),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn matches(&self, worker: &PersistentWorker, fingerprint: Fingerprint) -> bool {
+        self.key == worker.key
+            && self.protocol == worker.protocol
+            && (self.capacity > 1) == worker.multiplex
+            && self.fingerprint == fingerprint
+    }
+
+    fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    fn try_reserve(&self) -> bool {
+        self.is_alive()
+            && self
+                .in_flight
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |in_flight| {
+                    (in_flight < self.capacity).then_some(in_flight + 1)
+                })
+                .is_ok()
+    }
+
+    fn kill(&self) {
+        self.alive.store(false, Ordering::SeqCst);
+        let _ = self.child.lock().start_kill();
+    }
+
+    ///
+    /// Sends a request to the worker, and waits for its response.
+    ///
+    pub async fn request(
+        &self,
+        arguments: Vec<String>,
+        inputs: Vec<Input>,
+        sandbox_dir: Option<String>,
+    ) -> Result<WorkResponse, String> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        increment_counter_if_in_workunit(Metric::PersistentWorkerRequests, 1);
+
+        // Singleplex requests must use request id 0.
+        let request_id = if self.capacity > 1 {
+            self.next_request_id.fetch_add(1, Ordering::SeqCst) + 1
+        } else {
+            0
+        };
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().insert(request_id, sender);
+        let mut in_flight = InFlightRequest {
+            worker: self,
+            request_id,
+            completed: false,
+        };
+
+        let request = WorkRequest {
+            arguments,
+            inputs,
+            request_id,
+            sandbox_dir,
+            ..WorkRequest::default()
+        };
+        protocol::write_request(self.protocol, &mut *self.stdin.lock().await, request)
+            .await
+            .map_err(|e| {
+                format!(
+                    "Failed to send request to persistent worker {}: {e}",
+                    self.name
+                )
+            })?;
+
+        let response = receiver.await.map_err(|_| {
+            format!(
+                "Persistent worker {} exited while handling a request. Its stderr is in {}",
+                self.name,
+                self.workdir.path().join(STDERR_LOG).display()
+            )
+        })?;
+        in_flight.completed = true;
+        Ok(response)
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        debug!(
+            "Exiting persistent worker {:?} after {} requests",
+            self.name,
+            self.requests.load(Ordering::SeqCst)
+        );
+        // NB: The child is also killed on drop, but this avoids waiting for the reader to notice.
+        self.kill();
+    }
+}
+
+///
+/// A guard for a request which has been sent to a worker. If the request is abandoned before the
+/// worker responds, a singleplex worker is killed: it would otherwise eventually respond to the
+/// abandoned request as though it were the next one.
+///
+struct InFlightRequest<'a> {
+    worker: &'a Worker,
+    request_id: i32,
+    completed: bool,
+}
+
+impl Drop for InFlightRequest<'_> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        self.worker.pending.lock().remove(&self.request_id);
+        if self.worker.capacity == 1 {
+            debug!(
+                "Killing persistent worker {:?} due to cancellation.",
+                self.worker.name
+            );
+            self.worker.kill();
+        }
+    }
+}
+
+///
+/// A reservation of capacity on a Worker. If `release` is not called, the guard assumes
+/// cancellation, and kills the worker if it is singleplex (since its working directory has not been
+/// cleared).
+///
+pub struct BorrowedWorker {
+    worker: Option<Arc<Worker>>,
+    sandbox: Option<AsyncDropSandbox>,
+    executor: Executor,
+    released: Arc<Notify>,
+}
+
+impl BorrowedWorker {
+    pub fn worker(&self) -> &Arc<Worker> {
+        self.worker.as_ref().unwrap()
+    }
+
+    ///
+    /// The directory in which the inputs and outputs for this request should be placed.
+    ///
+    pub fn sandbox_path(&self) -> &Path {
+        match &self.sandbox {
+            Some(sandbox) => sandbox.path(),
+            None => &self.worker().execroot,
+        }
+    }
+
+    ///
+    /// The sandbox directory for this request relative to the worker's working directory, if the
+    /// worker is multiplex.
+    ///
+    pub fn sandbox_dir(&self) -> Option<String> {
+        self.sandbox.as_ref().map(|sandbox| {
+            sandbox
+                .path()
+                .strip_prefix(&self.worker().execroot)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
+    ///
+    /// Return the reservation to the pool.
+    ///
+    /// Clears the working directory of a singleplex worker before returning it: the sandbox of a
+    /// multiplex request is deleted in the background.
+    ///
+    pub async fn release(&mut self) -> Result<(), String> {
+        let worker = self
+            .worker
+            .take()
+            .expect("release may only be called once.");
+        let _ = self.sandbox.take();
+        let res = if worker.capacity == 1 {
+            clear_workdir(
+                &self.executor,
+                &worker.execroot,
+                &worker.execroot_include_names,
+            )
+            .await
+        } else {
+            Ok(())
+        };
+        if res.is_err() {
+            worker.kill();
+        }
+        *worker.last_used.lock() = Instant::now();
+        worker.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.released.notify_waiters();
+        res
+    }
+}
+
+impl Drop for BorrowedWorker {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            if worker.capacity == 1 {
+                debug!(
+                    "Killing persistent worker {:?} due to cancellation.",
+                    worker.name
+                );
+                worker.kill();
+            }
+            worker.in_flight.fetch_sub(1, Ordering::SeqCst);
+            self.released.notify_waiters();
+        }
+    }
+}
+
+async fn clear_workdir(
+    executor: &Executor,
+    workdir: &Path,
+    exclude_names: &HashSet<OsString>,
+) -> Result<(), String> {
+    // Move all content into a temporary directory.
+    let garbage_dir = tempfile::Builder::new()
+        .prefix("pants-sandbox-")
+        .tempdir_in(workdir.parent().unwrap())
+        .map_err(|err| format!("Error making garbage directory for worker cleanup: {err:?}"))?;
+    let moves = list_workdir(workdir)
+        .await?
+        .into_iter()
+        .filter(|n| !exclude_names.contains(n))
+        .map(|name| async {
+            tokio::fs::rename(workdir.join(&name), garbage_dir.path().join(&name))
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Failed to move {} to garbage: {}",
+                        workdir.join(name).display(),
+                        e
+                    )
+                })
+        })
+        .collect::<Vec<_>>();
+    future::try_join_all(moves).await?;
+
+    // And drop it in the background.
+    let fut = executor.native_spawn_blocking(move || std::mem::drop(garbage_dir));
+    drop(fut);
+
+    Ok(())
+}
+
+async fn list_workdir(workdir: &Path) -> Result<HashSet<OsString>, String> {
+    let mut dir_entries = tokio::fs::read_dir(workdir)
+        .await
+        .map_err(|e| format!("Failed to read persistent worker directory: {e}"))?;
+    let mut names = HashSet::new();
+    while let Some(dir_entry) = dir_entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read entry in persistent worker directory: {e}"))?
+    {
+        names.insert(dir_entry.file_name());
+    }
+    Ok(names)
+}
@@ -8,3 +8,5 @@ pub mod remote_tests;
 pub mod remote_cache;
 #[cfg(test)]
 mod remote_cache_tests;
+
+pub mod write_behind;
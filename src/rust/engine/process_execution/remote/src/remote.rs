@@ -18,7 +18,7 @@ use protos::gen::build::bazel::remote::execution::v2 as remexec;
 use protos::gen::google::longrunning::{
     operations_client::OperationsClient, CancelOperationRequest, Operation,
 };
-use protos::gen::google::rpc::{PreconditionFailure, Status as StatusProto};
+use protos::gen::google::rpc::{PreconditionFailure, RetryInfo, Status as StatusProto};
 use rand::{thread_rng, Rng};
 use remexec::{
     capabilities_client::CapabilitiesClient, execution_client::ExecutionClient,
@@ -38,13 +38,14 @@ use store::{Store, StoreError};
 use task_executor::Executor;
 use workunit_store::{
     in_workunit, Metric, ObservationMetric, RunId, RunningWorkunit, SpanId, UserMetadataItem,
-    WorkunitMetadata, WorkunitStore,
+    WaitingOn, WorkunitMetadata, WorkunitStore,
 };
 
+use process_execution::children::TIMEOUT_EXIT_CODE;
 use process_execution::{
-    make_execute_request, populate_fallible_execution_result, Context, EntireExecuteRequest,
-    FallibleProcessResultWithPlatform, Process, ProcessError, ProcessExecutionEnvironment,
-    ProcessResultMetadata, ProcessResultSource,
+    make_execute_request, populate_fallible_execution_result, subset_output_globs,
+    validate_working_directory, Context, EntireExecuteRequest, FallibleProcessResultWithPlatform,
+    Process, ProcessError, ProcessExecutionEnvironment, ProcessResultMetadata, ProcessResultSource,
 };
 
 #[derive(Debug)]
@@ -93,6 +94,8 @@ pub struct CommandRunner {
     operations_client: Arc<OperationsClient<LayeredService>>,
     overall_deadline: Duration,
     retry_interval_duration: Duration,
+    execution_priority: i32,
+    results_cache_priority: i32,
     capabilities_cell: Arc<OnceCell<ServerCapabilities>>,
     capabilities_client: Arc<CapabilitiesClient<LayeredService>>,
 }
@@ -100,6 +103,9 @@ pub struct CommandRunner {
 enum StreamOutcome {
     Complete(OperationOrStatus),
     StreamClosed,
+    /// The stream failed with a transient error after the server had reported the name of the
+    /// operation: the operation may still be running, and can be resumed with `WaitExecution`.
+    StreamFailed(Status),
 }
 
 enum OperationStreamItem {
@@ -145,7 +151,7 @@ impl Drop for RunningOperation {
         if let Some(operation_name) = self.name.take() {
             debug!("Canceling remote operation {operation_name}");
             let mut operations_client = self.operations_client.as_ref().clone();
-            let fut = self.executor.native_spawn(async move {
+            let fut = self.executor.native_spawn_io(async move {
                 operations_client
                     .cancel_operation(CancelOperationRequest {
                         name: operation_name,
@@ -171,6 +177,8 @@ impl CommandRunner {
         overall_deadline: Duration,
         retry_interval_duration: Duration,
         execution_concurrency_limit: usize,
+        execution_priority: i32,
+        results_cache_priority: i32,
     ) -> Result<Self, String> {
         let needs_tls = execution_address.starts_with("https://");
 
@@ -200,6 +208,8 @@ impl CommandRunner {
             executor,
             overall_deadline,
             retry_interval_duration,
+            execution_priority,
+            results_cache_priority,
             capabilities_cell: Arc::new(OnceCell::new()),
             capabilities_client,
         };
@@ -283,13 +293,11 @@ impl CommandRunner {
 
             Some(Err(err)) => {
                 debug!("wait_on_operation_stream: got error: {:?}", err);
-                let status_proto = StatusProto {
-                    code: err.code() as i32,
-                    message: err.message().to_string(),
-                    ..StatusProto::default()
-                };
+                if running_operation.name.is_some() && is_transient_stream_error(err.code()) {
+                    return OperationStreamItem::Outcome(StreamOutcome::StreamFailed(err));
+                }
                 OperationStreamItem::Outcome(StreamOutcome::Complete(OperationOrStatus::Status(
-                    status_proto,
+                    status_to_proto(&err),
                 )))
             }
 
@@ -357,7 +365,8 @@ impl CommandRunner {
             // renders at the Process's level.
             running_operation.process_level,
             desc = Some(running_operation.process_description.clone()),
-            |_workunit| async move {
+            |workunit| async move {
+                let _waiting_token = workunit.waiting_on(WaitingOn::RemoteRpc);
                 loop {
                     match Self::wait_on_operation_stream_item(
                         &mut stream,
@@ -701,7 +710,9 @@ impl CommandRunner {
     // Main loop: This function connects to the RE server and submits the given remote execution
     // request via the REv2 Execute method. It then monitors the operation stream until the
     // request completes. It will reconnect using the REv2 WaitExecution method if the connection
-    // is dropped.
+    // is dropped or fails with a transient error, and will only resubmit the request if the
+    // server no longer knows about the operation. When the server attaches `RetryInfo` to an
+    // error, its requested delay is used instead of the exponential backoff.
     //
     // The `run` method on CommandRunner uses this function to implement the bulk of the
     // processing for remote execution requests. The `run` method wraps the call with the method
@@ -725,15 +736,19 @@ impl CommandRunner {
             process.description.clone(),
         );
         let mut num_retries = 0;
+        // A delay which the server requested (via `RetryInfo`) before the next retry.
+        let mut server_retry_delay = None;
 
         loop {
-            // If we are currently retrying a request, then delay using an exponential backoff.
+            // If we are currently retrying a request, then delay using either the delay requested by
+            // the server, or an exponential backoff.
             if num_retries > 0 {
                 workunit.increment_counter(Metric::RemoteExecutionRPCRetries, 1);
 
-                let multiplier = thread_rng().gen_range(0..2_u32.pow(num_retries) + 1);
-                let sleep_time = self.retry_interval_duration * multiplier;
-                let sleep_time = sleep_time.min(MAX_BACKOFF_DURATION);
+                let sleep_time = server_retry_delay.take().unwrap_or_else(|| {
+                    let multiplier = thread_rng().gen_range(0..2_u32.pow(num_retries) + 1);
+                    (self.retry_interval_duration * multiplier).min(MAX_BACKOFF_DURATION)
+                });
                 debug!("delaying {:?} before retry", sleep_time);
                 tokio::time::sleep(sleep_time).await;
             }
@@ -820,21 +835,67 @@ impl CommandRunner {
                             // Iterate the loop to reconnect to the operation.
                             continue;
                         }
+                        StreamOutcome::StreamFailed(status) => {
+                            // The operation may still be running: rather than re-executing it,
+                            // reconnect to it with WaitExecution.
+                            trace!(
+                                "wait_on_operation_stream (build_id={}) failed with {:?}, \
+                     will resume operation_name={:?}",
+                                context.build_id,
+                                status,
+                                running_operation.name
+                            );
+                            if num_retries >= MAX_RETRIES {
+                                workunit.increment_counter(Metric::RemoteExecutionRPCErrors, 1);
+                                return Err(format!(
+                                    "Too many failures from server. The last error was: {}",
+                                    status.message()
+                                )
+                                .into());
+                            }
+                            num_retries += 1;
+                            server_retry_delay = retry_delay(&status_to_proto(&status));
+                            continue;
+                        }
+                    }
+                }
+                Err(status)
+                    if running_operation.name.is_some()
+                        && (is_transient_stream_error(status.code())
+                            || status.code() == Code::NotFound) =>
+                {
+                    // A WaitExecution request failed.
+                    if status.code() == Code::NotFound {
+                        // The server no longer knows about the operation (for example, because it
+                        // was restarted): submit it again with Execute.
+                        debug!(
+                            "remote operation {:?} was not found: resubmitting it",
+                            running_operation.name
+                        );
+                        running_operation.completed();
+                    }
+                    if num_retries >= MAX_RETRIES {
+                        workunit.increment_counter(Metric::RemoteExecutionRPCErrors, 1);
+                        return Err(format!(
+                            "Too many failures from server. The last error was: {}",
+                            status.message()
+                        )
+                        .into());
                     }
+                    num_retries += 1;
+                    server_retry_delay = retry_delay(&status_to_proto(&status));
+                    continue;
                 }
                 Err(status) => {
-                    let status_proto = StatusProto {
-                        code: status.code() as i32,
-                        message: status.message().to_owned(),
-                        ..StatusProto::default()
-                    };
                     // `OperationOrStatus` always represents a completed operation, so this operation
                     // is completed.
                     running_operation.completed();
-                    OperationOrStatus::Status(status_proto)
+                    OperationOrStatus::Status(status_to_proto(&status))
                 }
             };
 
+            let requested_retry_delay = operation_retry_delay(&actionable_result);
+
             match self
                 .extract_execute_response(
                     context.run_id,
@@ -863,6 +924,7 @@ impl CommandRunner {
                         } else {
                             // Increment the retry counter and allow loop to retry.
                             num_retries += 1;
+                            server_retry_delay = requested_retry_delay;
                         }
                     }
                     ExecutionError::MissingRemoteDigests(missing_digests) => {
@@ -915,11 +977,13 @@ impl process_execution::CommandRunner for CommandRunner {
         let capabilities = self.get_capabilities().await?;
         trace!("RE capabilities: {:?}", &capabilities);
 
+        validate_working_directory(&self.store, &request).await?;
+
         // Construct the REv2 ExecuteRequest and related data for this execution request.
         let EntireExecuteRequest {
             action,
             command,
-            execute_request,
+            mut execute_request,
             input_root_digest,
         } = make_execute_request(
             &request,
@@ -931,6 +995,15 @@ impl process_execution::CommandRunner for CommandRunner {
                 .map(|s| s.as_ref()),
         )
         .await?;
+        apply_priorities(
+            &mut execute_request,
+            request
+                .remote_execution_priority
+                .unwrap_or(self.execution_priority),
+            request
+                .remote_results_cache_priority
+                .unwrap_or(self.results_cache_priority),
+        );
         let build_id = context.build_id.clone();
 
         debug!("Remote execution: {}", request.description);
@@ -970,13 +1043,13 @@ impl process_execution::CommandRunner for CommandRunner {
             |workunit| async move {
                 workunit.increment_counter(Metric::RemoteExecutionRequests, 1);
                 let result_fut =
-                    self.run_execute_request(execute_request, request, &context2, workunit);
+                    self.run_execute_request(execute_request, request.clone(), &context2, workunit);
 
                 // Detect whether the operation ran or hit the deadline timeout.
                 match tokio::time::timeout(deadline_duration, result_fut).await {
                     Ok(Ok(result)) => {
                         workunit.increment_counter(Metric::RemoteExecutionSuccess, 1);
-                        Ok(result)
+                        Ok(subset_output_globs(&self.store, &request, result).await?)
                     }
                     Ok(Err(err)) => {
                         workunit.increment_counter(Metric::RemoteExecutionErrors, 1);
@@ -1017,6 +1090,87 @@ impl process_execution::CommandRunner for CommandRunner {
     }
 }
 
+///
+/// True if a gRPC error on an operation stream is likely to have been caused by the connection
+/// to the server (rather than by the operation), such that the operation may still be running.
+///
+fn is_transient_stream_error(code: Code) -> bool {
+    matches!(code, Code::Unavailable | Code::Internal | Code::Unknown)
+}
+
+///
+/// Converts a gRPC `Status` to its protobuf representation, including any error details (such as
+/// `RetryInfo` or `PreconditionFailure`) which the server attached to it.
+///
+fn status_to_proto(status: &Status) -> StatusProto {
+    let details = StatusProto::decode(status.details())
+        .map(|status| status.details)
+        .unwrap_or_default();
+    StatusProto {
+        code: status.code() as i32,
+        message: status.message().to_owned(),
+        details,
+    }
+}
+
+///
+/// Returns the delay before retrying which a server requested by attaching `RetryInfo` to an
+/// error status, if any.
+///
+pub(crate) fn retry_delay(status: &StatusProto) -> Option<Duration> {
+    let full_name = format!("type.googleapis.com/{}", "google.rpc.RetryInfo");
+    status
+        .details
+        .iter()
+        .filter(|details| details.type_url == full_name)
+        .find_map(|details| RetryInfo::decode(&details.value[..]).ok()?.retry_delay)
+        .and_then(|delay| Duration::try_from(delay).ok())
+}
+
+///
+/// Returns the delay before retrying which a server requested for a failed operation, if any.
+///
+fn operation_retry_delay(operation_or_status: &OperationOrStatus) -> Option<Duration> {
+    use protos::gen::google::longrunning::operation::Result as OperationResult;
+    match operation_or_status {
+        OperationOrStatus::Status(status) => retry_delay(status),
+        OperationOrStatus::Operation(operation) => match &operation.result {
+            Some(OperationResult::Error(status)) => retry_delay(status),
+            Some(OperationResult::Response(response)) => {
+                ExecuteResponse::decode(&response.value[..])
+                    .ok()?
+                    .status
+                    .as_ref()
+                    .and_then(retry_delay)
+            }
+            None => None,
+        },
+    }
+}
+
+///
+/// Applies scheduling priorities to an ExecuteRequest. Lower values are more urgent, and 0 is the
+/// server's default priority (and so is not sent).
+///
+/// NB: Priorities are not a part of the Action, and so do not affect its cache key.
+///
+pub(crate) fn apply_priorities(
+    execute_request: &mut ExecuteRequest,
+    execution_priority: i32,
+    results_cache_priority: i32,
+) {
+    if execution_priority != 0 {
+        execute_request.execution_policy = Some(remexec::ExecutionPolicy {
+            priority: execution_priority,
+        });
+    }
+    if results_cache_priority != 0 {
+        execute_request.results_cache_policy = Some(remexec::ResultsCachePolicy {
+            priority: results_cache_priority,
+        });
+    }
+}
+
 fn maybe_add_workunit(
     result_cached: bool,
     name: &'static str,
@@ -1053,7 +1207,7 @@ async fn populate_fallible_execution_result_for_timeout(
     Ok(FallibleProcessResultWithPlatform {
         stdout_digest,
         stderr_digest: hashing::EMPTY_DIGEST,
-        exit_code: -libc::SIGTERM,
+        exit_code: TIMEOUT_EXIT_CODE,
         output_directory: EMPTY_DIRECTORY_DIGEST.clone(),
         metadata: ProcessResultMetadata::new(
             Some(elapsed.into()),
@@ -3,7 +3,7 @@
 use std::collections::{BTreeMap, HashSet};
 use std::fmt::{self, Debug};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use fs::{directory, DigestTrie, RelativePath, SymlinkBehavior};
@@ -15,18 +15,22 @@ use protos::gen::build::bazel::remote::execution::v2 as remexec;
 use protos::require_digest;
 use remexec::{ActionResult, Command, Tree};
 use remote_provider::{choose_action_cache_provider, ActionCacheProvider};
+use store::bandwidth::{with_transfer_priority, TransferPriority};
 use store::{Store, StoreError};
 use workunit_store::{
     in_workunit, Level, Metric, ObservationMetric, RunningWorkunit, WorkunitMetadata,
 };
 
 use process_execution::{
-    check_cache_content, populate_fallible_execution_result, CacheContentBehavior, Context,
-    FallibleProcessResultWithPlatform, Process, ProcessCacheScope, ProcessError,
-    ProcessExecutionEnvironment, ProcessResultSource,
+    check_cache_content, metadata_for_cache, populate_fallible_execution_result,
+    revalidate_cached_result, subset_output_globs, CacheContentBehavior, CacheLocation, Context,
+    FallibleProcessResultWithPlatform, Process, ProcessError, ProcessExecutionEnvironment,
+    ProcessResultSource,
 };
 use process_execution::{make_execute_request, EntireExecuteRequest};
 
+use crate::write_behind::WriteBehindQueue;
+
 // Consumers of this crate shouldn't need to worry about the exact crate structure that comes
 // together to make a remote cache command runner.
 pub use remote_provider::RemoteStoreOptions;
@@ -51,6 +55,10 @@ pub struct RemoteCacheRunnerOptions {
     pub warnings_behavior: RemoteCacheWarningsBehavior,
     pub cache_content_behavior: CacheContentBehavior,
     pub append_only_caches_base_path: Option<String>,
+    pub max_age: Option<Duration>,
+    /// If set, cache writes are persisted to this queue and uploaded in the background, rather
+    /// than being uploaded by a tail task of the session.
+    pub write_behind: Option<WriteBehindQueue>,
 }
 
 /// This `CommandRunner` implementation caches results remotely using the Action Cache service
@@ -74,6 +82,8 @@ pub struct CommandRunner {
     cache_write: bool,
     cache_content_behavior: CacheContentBehavior,
     warnings_behavior: RemoteCacheWarningsBehavior,
+    max_age: Option<Duration>,
+    write_behind: Option<WriteBehindQueue>,
     read_errors_counter: Arc<Mutex<BTreeMap<String, usize>>>,
     write_errors_counter: Arc<Mutex<BTreeMap<String, usize>>>,
 }
@@ -91,6 +101,8 @@ impl CommandRunner {
             warnings_behavior,
             cache_content_behavior,
             append_only_caches_base_path,
+            max_age,
+            write_behind,
         }: RemoteCacheRunnerOptions,
         provider: Arc<dyn ActionCacheProvider + 'static>,
     ) -> Self {
@@ -106,6 +118,8 @@ impl CommandRunner {
             cache_write,
             cache_content_behavior,
             warnings_behavior,
+            max_age,
+            write_behind,
             read_errors_counter: Arc::new(Mutex::new(BTreeMap::new())),
             write_errors_counter: Arc::new(Mutex::new(BTreeMap::new())),
         }
@@ -214,7 +228,7 @@ impl CommandRunner {
             exit_code: result.exit_code,
             stdout_digest: Some(result.stdout_digest.into()),
             stderr_digest: Some(result.stderr_digest.into()),
-            execution_metadata: Some(result.metadata.clone().into()),
+            execution_metadata: Some(metadata_for_cache(&result.metadata)),
             ..ActionResult::default()
         };
 
@@ -267,7 +281,6 @@ impl CommandRunner {
         context: Context,
         cache_lookup_start: Instant,
         action_digest: Digest,
-        failures_cached: bool,
         request: &Process,
         mut local_execution_future: BoxFuture<
             '_,
@@ -286,26 +299,61 @@ impl CommandRunner {
                 self.cache_content_behavior,
             )
             .await;
+            // The cached outputs include the literal parent directories of any output globs.
+            let response = match response {
+                Ok(Some(cached_response)) => {
+                    subset_output_globs(&self.store, request, cached_response)
+                        .await
+                        .map(Some)
+                        .map_err(ProcessError::from)
+                }
+                response => response,
+            };
             match response {
-                Ok(cached_response_opt) => match &cached_response_opt {
-                    Some(cached_response) if cached_response.exit_code == 0 || failures_cached => {
-                        log::debug!(
-                            "remote cache hit for: {:?} digest={:?} response={:?}",
-                            request.description,
-                            action_digest,
-                            cached_response
-                        );
-                        cached_response_opt
-                    }
-                    _ => {
-                        log::debug!(
-                            "remote cache miss for: {:?} digest={:?}",
-                            request.description,
-                            action_digest
-                        );
-                        None
+                Ok(Some(cached_response))
+                    if request.cache_scope.is_cacheable(cached_response.exit_code) =>
+                {
+                    if cached_response.metadata.is_stale(self.max_age) {
+                        let revalidated = revalidate_cached_result(
+                            self.inner.as_ref(),
+                            context.clone(),
+                            &self.store,
+                            request,
+                            &cached_response,
+                        )
+                        .await;
+                        match revalidated {
+                            Ok(true) => {}
+                            Ok(false) => {
+                                log::debug!(
+                                    "stale remote cache hit failed revalidation for: {:?} digest={:?}",
+                                    request.description,
+                                    action_digest
+                                );
+                                return None;
+                            }
+                            Err(err) => {
+                                self.log_cache_error(err.to_string(), CacheErrorType::ReadError);
+                                return None;
+                            }
+                        }
                     }
-                },
+                    log::debug!(
+                        "remote cache hit for: {:?} digest={:?} response={:?}",
+                        request.description,
+                        action_digest,
+                        cached_response
+                    );
+                    Some(cached_response)
+                }
+                Ok(_) => {
+                    log::debug!(
+                        "remote cache miss for: {:?} digest={:?}",
+                        request.description,
+                        action_digest
+                    );
+                    None
+                }
                 Err(err) => {
                     self.log_cache_error(err.to_string(), CacheErrorType::ReadError);
                     None
@@ -411,6 +459,68 @@ impl CommandRunner {
         Ok(())
     }
 
+    /// Persists an execution result to the write-behind queue, to be uploaded to the remote Action
+    /// Cache in the background.
+    async fn enqueue_action_cache_write(
+        &self,
+        queue: &WriteBehindQueue,
+        result: &FallibleProcessResultWithPlatform,
+        command: &Command,
+        action_digest: Digest,
+        command_digest: Digest,
+    ) -> Result<(), StoreError> {
+        // NB: This stores any Tree protos for the ActionResult locally, so that they are available
+        // when the write is uploaded.
+        let (action_result, digests_for_action_result) = self
+            .make_action_result(command, result, &self.store)
+            .await?;
+        queue
+            .enqueue(
+                action_digest,
+                command_digest,
+                action_result,
+                digests_for_action_result,
+            )
+            .await?;
+        Ok(())
+    }
+
+    ///
+    /// Stores a result which was previously produced for the given Process into the remote Action
+    /// Cache, regardless of whether this runner was created with `cache_write` enabled.
+    ///
+    /// Returns false without writing if the Process's cache scope does not allow the result to be
+    /// cached remotely.
+    ///
+    pub async fn publish(
+        &self,
+        request: &Process,
+        result: &FallibleProcessResultWithPlatform,
+    ) -> Result<bool, StoreError> {
+        if !request
+            .cache_scope
+            .writes_to(CacheLocation::Remote, result.exit_code)
+        {
+            return Ok(false);
+        }
+
+        let EntireExecuteRequest {
+            action, command, ..
+        } = make_execute_request(
+            request,
+            self.instance_name.clone(),
+            self.process_cache_namespace.clone(),
+            &self.store,
+            self.append_only_caches_base_path.as_deref(),
+        )
+        .await?;
+        let (command_digest, action_digest) =
+            crate::remote::ensure_action_stored_locally(&self.store, &command, &action).await?;
+        self.update_action_cache(result, &command, action_digest, command_digest)
+            .await?;
+        Ok(true)
+    }
+
     fn log_cache_error(&self, err: String, err_type: CacheErrorType) {
         let err_count = {
             let mut errors_counter = match err_type {
@@ -481,45 +591,66 @@ impl process_execution::CommandRunner for CommandRunner {
                 .map(|s| s.as_ref()),
         )
         .await?;
-        let failures_cached = request.cache_scope == ProcessCacheScope::Always;
+        let cache_scope = request.cache_scope;
 
         // Ensure the action and command are stored locally.
         let (command_digest, action_digest) =
             crate::remote::ensure_action_stored_locally(&self.store, &command, &action).await?;
 
-        let use_remote_cache = request.cache_scope == ProcessCacheScope::Always
-            || request.cache_scope == ProcessCacheScope::Successful;
+        let (mut result, hit_cache) =
+            if self.cache_read && cache_scope.reads_from(CacheLocation::Remote) {
+                self.speculate_read_action_cache(
+                    context.clone(),
+                    cache_lookup_start,
+                    action_digest,
+                    &request.clone(),
+                    self.inner.run(context.clone(), workunit, request),
+                )
+                .await?
+            } else {
+                (
+                    self.inner.run(context.clone(), workunit, request).await?,
+                    false,
+                )
+            };
 
-        let (result, hit_cache) = if self.cache_read && use_remote_cache {
-            self.speculate_read_action_cache(
-                context.clone(),
-                cache_lookup_start,
-                action_digest,
-                failures_cached,
-                &request.clone(),
-                self.inner.run(context.clone(), workunit, request),
-            )
-            .await?
-        } else {
-            (
-                self.inner.run(context.clone(), workunit, request).await?,
-                false,
-            )
-        };
+        result.metadata.cache_scope = Some(cache_scope);
 
-        if !hit_cache
-            && (result.exit_code == 0 || failures_cached)
+        // A stale hit is only used once it has been revalidated: it is rewritten with a fresh
+        // timestamp, so that it is not revalidated again until it next becomes stale.
+        let revalidated_hit = hit_cache && result.metadata.is_stale(self.max_age);
+        if (!hit_cache || revalidated_hit)
             && self.cache_write
-            && use_remote_cache
+            && cache_scope.writes_to(CacheLocation::Remote, result.exit_code)
         {
             let command_runner = self.clone();
             let result = result.clone();
             let write_fut =
                 in_workunit!("remote_cache_write", Level::Trace, |workunit| async move {
                     workunit.increment_counter(Metric::RemoteCacheWriteAttempts, 1);
-                    let write_result = command_runner
-                        .update_action_cache(&result, &command, action_digest, command_digest)
-                        .await;
+                    let write_result = match &command_runner.write_behind {
+                        Some(queue) => {
+                            command_runner
+                                .enqueue_action_cache_write(
+                                    queue,
+                                    &result,
+                                    &command,
+                                    action_digest,
+                                    command_digest,
+                                )
+                                .await
+                        }
+                        None => {
+                            command_runner
+                                .update_action_cache(
+                                    &result,
+                                    &command,
+                                    action_digest,
+                                    command_digest,
+                                )
+                                .await
+                        }
+                    };
                     match write_result {
                         Ok(_) => workunit.increment_counter(Metric::RemoteCacheWriteSuccesses, 1),
                         Err(err) => {
@@ -531,6 +662,8 @@ impl process_execution::CommandRunner for CommandRunner {
                 }
                 // NB: We must box the future to avoid a stack overflow.
                 .boxed());
+            // Opportunistic cache writes yield any limited bandwidth to interactive transfers.
+            let write_fut = with_transfer_priority(TransferPriority::Background, write_fut);
             let task_name = format!("remote cache write {action_digest:?}");
             context
                 .tail_tasks
@@ -2,6 +2,7 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 use std::collections::{BTreeMap, HashSet};
 use std::convert::TryInto;
+use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
@@ -22,6 +23,7 @@ use workunit_store::{RunId, RunningWorkunit, WorkunitStore};
 
 use crate::remote::ensure_action_stored_locally;
 use crate::remote_cache::{RemoteCacheRunnerOptions, RemoteCacheWarningsBehavior};
+use crate::write_behind::WriteBehindQueue;
 use process_execution::{
     make_execute_request, CacheContentBehavior, CommandRunner as CommandRunnerTrait, Context,
     EntireExecuteRequest, FallibleProcessResultWithPlatform, Platform, Process, ProcessCacheScope,
@@ -113,9 +115,11 @@ impl StoreSetup {
                 headers: BTreeMap::new(),
                 chunk_size_bytes: 10 * 1024 * 1024,
                 timeout: Duration::from_secs(1),
+                stream_timeout: Duration::from_secs(1),
                 retries: 1,
                 concurrency_limit: 256,
                 batch_api_size_limit: 4 * 1024 * 1024,
+                circuit_breaker: None,
             })
             .await
             .unwrap();
@@ -159,6 +163,8 @@ async fn create_cached_runner(
                 warnings_behavior: RemoteCacheWarningsBehavior::FirstOnly,
                 cache_content_behavior,
                 append_only_caches_base_path: None,
+                max_age: None,
+                write_behind: None,
             },
             RemoteStoreOptions {
                 provider: RemoteProvider::Reapi,
@@ -168,9 +174,11 @@ async fn create_cached_runner(
                 headers: BTreeMap::default(),
                 concurrency_limit: 256,
                 timeout: CACHE_READ_TIMEOUT,
+                stream_timeout: CACHE_READ_TIMEOUT,
                 retries: 0,
                 batch_api_size_limit: 0,
                 chunk_size_bytes: 0,
+                circuit_breaker: None,
             },
         )
         .await
@@ -553,6 +561,95 @@ async fn cache_write_not_for_failures() {
     assert!(store_setup.cas.action_cache.action_map.lock().is_empty());
 }
 
+#[tokio::test]
+async fn cache_write_not_for_remote_read_only_scope() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    let store_setup = StoreSetup::new().await;
+    let (local_runner, local_runner_call_counter) = create_local_runner(0, 100);
+    let cache_runner =
+        create_cached_runner(local_runner, &store_setup, CacheContentBehavior::Defer).await;
+    let (process, _action_digest) = create_process(&store_setup).await;
+    let process = process.cache_scope(ProcessCacheScope::RemoteReadOnlySuccessful);
+
+    let context = Context::default();
+    let local_result = cache_runner
+        .run(context.clone(), &mut workunit, process)
+        .await
+        .unwrap();
+    context.tail_tasks.wait(Duration::from_secs(2)).await;
+    assert_eq!(local_result.exit_code, 0);
+    assert_eq!(
+        local_result.metadata.cache_scope,
+        Some(ProcessCacheScope::RemoteReadOnlySuccessful)
+    );
+    assert_eq!(local_runner_call_counter.load(Ordering::SeqCst), 1);
+    assert!(store_setup.cas.action_cache.action_map.lock().is_empty());
+}
+
+#[tokio::test]
+async fn publish_writes_when_cache_write_disabled() {
+    let _ = WorkunitStore::setup_for_tests();
+    let store_setup = StoreSetup::new().await;
+    let (local_runner, _) = create_local_runner(0, 0);
+    let cache_runner = crate::remote_cache::CommandRunner::from_provider_options(
+        RemoteCacheRunnerOptions {
+            inner: Arc::new(*local_runner),
+            instance_name: None,
+            process_cache_namespace: None,
+            executor: store_setup.executor.clone(),
+            store: store_setup.store.clone(),
+            cache_read: false,
+            cache_write: false,
+            warnings_behavior: RemoteCacheWarningsBehavior::FirstOnly,
+            cache_content_behavior: CacheContentBehavior::Defer,
+            append_only_caches_base_path: None,
+            max_age: None,
+            write_behind: None,
+        },
+        RemoteStoreOptions {
+            provider: RemoteProvider::Reapi,
+            instance_name: None,
+            store_address: store_setup.cas.address(),
+            tls_config: tls::Config::default(),
+            headers: BTreeMap::default(),
+            concurrency_limit: 256,
+            timeout: CACHE_READ_TIMEOUT,
+            stream_timeout: CACHE_READ_TIMEOUT,
+            retries: 0,
+            batch_api_size_limit: 0,
+            chunk_size_bytes: 0,
+            circuit_breaker: None,
+        },
+    )
+    .await
+    .expect("caching command runner");
+    let (process, action_digest) = create_process(&store_setup).await;
+    let result = create_local_runner(0, 0).0.result.unwrap();
+
+    // A failed result is not eligible for the default cache scope.
+    let failed_result = FallibleProcessResultWithPlatform {
+        exit_code: 1,
+        ..result.clone()
+    };
+    assert!(!cache_runner
+        .publish(&process, &failed_result)
+        .await
+        .unwrap());
+    assert!(store_setup.cas.action_cache.action_map.lock().is_empty());
+
+    assert!(cache_runner.publish(&process, &result).await.unwrap());
+    assert_eq!(store_setup.cas.action_cache.len(), 1);
+    assert_eq!(
+        store_setup
+            .cas
+            .action_cache
+            .get(action_digest)
+            .unwrap()
+            .exit_code,
+        0
+    );
+}
+
 /// Cache writes should be async and not block the CommandRunner from returning.
 #[tokio::test]
 async fn cache_write_does_not_block() {
@@ -596,6 +693,110 @@ async fn cache_write_does_not_block() {
     );
 }
 
+fn remote_store_options(store_setup: &StoreSetup) -> RemoteStoreOptions {
+    RemoteStoreOptions {
+        provider: RemoteProvider::Reapi,
+        instance_name: None,
+        store_address: store_setup.cas.address(),
+        tls_config: tls::Config::default(),
+        headers: BTreeMap::default(),
+        concurrency_limit: 256,
+        timeout: CACHE_READ_TIMEOUT,
+        stream_timeout: CACHE_READ_TIMEOUT,
+        retries: 0,
+        batch_api_size_limit: 0,
+        chunk_size_bytes: 0,
+        circuit_breaker: None,
+    }
+}
+
+async fn create_write_behind_queue(store_setup: &StoreSetup, dir: &Path) -> WriteBehindQueue {
+    WriteBehindQueue::from_provider_options(
+        dir,
+        &store_setup.executor,
+        store_setup.store.clone(),
+        remote_store_options(store_setup),
+        0,
+    )
+    .await
+    .unwrap()
+    .expect("write-behind queue should not be locked")
+}
+
+#[tokio::test]
+async fn cache_write_behind_survives_restart() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    let store_setup = StoreSetup::new().await;
+    let queue_dir = TempDir::new().unwrap();
+    let (process, action_digest) = create_process(&store_setup).await;
+    store_setup
+        .cas
+        .action_cache
+        .always_errors
+        .store(true, Ordering::SeqCst);
+
+    {
+        let queue = create_write_behind_queue(&store_setup, queue_dir.path()).await;
+        let (local_runner, _) = create_local_runner(0, 0);
+        let cache_runner = crate::remote_cache::CommandRunner::from_provider_options(
+            RemoteCacheRunnerOptions {
+                inner: Arc::new(*local_runner),
+                instance_name: None,
+                process_cache_namespace: None,
+                executor: store_setup.executor.clone(),
+                store: store_setup.store.clone(),
+                cache_read: false,
+                cache_write: true,
+                warnings_behavior: RemoteCacheWarningsBehavior::FirstOnly,
+                cache_content_behavior: CacheContentBehavior::Defer,
+                append_only_caches_base_path: None,
+                max_age: None,
+                write_behind: Some(queue.clone()),
+            },
+            remote_store_options(&store_setup),
+        )
+        .await
+        .unwrap();
+
+        let context = Context::default();
+        let result = cache_runner
+            .run(context.clone(), &mut workunit, process)
+            .await
+            .unwrap();
+        assert_eq!(result.exit_code, 0);
+        context.tail_tasks.wait(Duration::from_secs(2)).await;
+
+        // The upload fails, and so the write remains queued.
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(queue.pending_writes().await.unwrap(), 1);
+        assert!(store_setup.cas.action_cache.action_map.lock().is_empty());
+    }
+
+    // Once the server recovers, a new queue (as if after a restart) uploads the write.
+    store_setup
+        .cas
+        .action_cache
+        .always_errors
+        .store(false, Ordering::SeqCst);
+    let queue = create_write_behind_queue(&store_setup, queue_dir.path()).await;
+    for _ in 0..50 {
+        if queue.pending_writes().await.unwrap() == 0 {
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(queue.pending_writes().await.unwrap(), 0);
+    assert_eq!(
+        store_setup
+            .cas
+            .action_cache
+            .get(action_digest)
+            .unwrap()
+            .exit_code,
+        0
+    );
+}
+
 #[tokio::test]
 async fn make_tree_from_directory() {
     let store_dir = TempDir::new().unwrap();
@@ -763,6 +964,8 @@ async fn make_action_result_basic() {
             warnings_behavior: RemoteCacheWarningsBehavior::FirstOnly,
             cache_content_behavior: CacheContentBehavior::Defer,
             append_only_caches_base_path: None,
+            max_age: None,
+            write_behind: None,
         },
         RemoteStoreOptions {
             provider: RemoteProvider::Reapi,
@@ -772,9 +975,11 @@ async fn make_action_result_basic() {
             headers: BTreeMap::default(),
             concurrency_limit: 256,
             timeout: CACHE_READ_TIMEOUT,
+            stream_timeout: CACHE_READ_TIMEOUT,
             retries: 0,
             batch_api_size_limit: 0,
             chunk_size_bytes: 0,
+            circuit_breaker: None,
         },
     )
     .await
@@ -21,7 +21,10 @@ use testutil::{owned_string_vec, relative_paths};
 use tokio::time::{sleep, timeout};
 use workunit_store::{Level, RunId, RunningWorkunit, WorkunitStore};
 
-use crate::remote::{CommandRunner, ExecutionError, OperationOrStatus};
+use crate::remote::{
+    apply_priorities, ensure_action_stored_locally, retry_delay, CommandRunner, ExecutionError,
+    OperationOrStatus,
+};
 use fs::{DirectoryDigest, RelativePath, SymlinkBehavior, EMPTY_DIRECTORY_DIGEST};
 use process_execution::{
     CacheName, CommandRunner as CommandRunnerTrait, Context, EntireExecuteRequest,
@@ -88,21 +91,33 @@ async fn make_execute_request() {
         env: vec![("SOME".to_owned(), "value".to_owned())]
             .into_iter()
             .collect(),
+        env_globs: BTreeSet::new(),
+        globbed_env: BTreeMap::new(),
         working_directory: None,
         input_digests: InputDigests::with_input_files(input_directory.directory_digest()),
         // Intentionally poorly sorted:
         output_files: relative_paths(&["path/to/file.ext", "other/file.ext"]).collect(),
         output_directories: relative_paths(&["directory/name"]).collect(),
+        output_globs: BTreeSet::new(),
         timeout: None,
         description: "some description".to_owned(),
         level: log::Level::Info,
         append_only_caches: BTreeMap::new(),
+        append_only_cache_seeds: BTreeMap::new(),
+        incremental_outputs: None,
         jdk_home: None,
         execution_slot_variable: None,
         concurrency_available: 0,
         cache_scope: ProcessCacheScope::Always,
+        tool_fingerprints: BTreeMap::new(),
         execution_environment: make_environment(Platform::Linux_x86_64),
         remote_cache_speculation_delay: std::time::Duration::from_millis(0),
+        remote_execution_priority: None,
+        remote_results_cache_priority: None,
+        persistent_worker: None,
+        cache_validation_argv: None,
+        stdin_digest: None,
+        retry_policy: None,
         attempt: 0,
     };
 
@@ -169,6 +184,269 @@ async fn make_execute_request() {
     );
 }
 
+#[tokio::test]
+async fn make_execute_request_with_stdin() {
+    let executor = task_executor::Executor::new();
+    let store_dir = TempDir::new().unwrap();
+    let store = Store::local_only(executor, store_dir).unwrap();
+
+    let roland = TestData::roland();
+    let mut req = Process::new(owned_string_vec(&["/bin/cat"])).stdin_digest(roland.digest());
+    req.working_directory = Some(RelativePath::new("sub/dir").unwrap());
+
+    let EntireExecuteRequest {
+        command,
+        input_root_digest,
+        ..
+    } = process_execution::make_execute_request(&req, None, None, &store, None)
+        .await
+        .unwrap();
+
+    // The stdin file is added to the input root, and redirected to the process by a wrapper.
+    assert_eq!(
+        command.arguments,
+        owned_string_vec(&[
+            "/bin/sh",
+            "-c",
+            "exec \"$@\" < \"$0\"",
+            "../../__pants_stdin__",
+            "/bin/cat",
+        ])
+    );
+    let input_root = store.load_digest_trie(input_root_digest).await.unwrap();
+    assert_eq!(
+        input_root.files(SymlinkBehavior::Aware),
+        vec![PathBuf::from(process_execution::STDIN_FILE)]
+    );
+
+    // And the stdin is a part of the cache key of the process.
+    let without_stdin = Process {
+        stdin_digest: None,
+        ..req.clone()
+    };
+    assert_ne!(
+        process_execution::get_digest(&req, None, None, &store, None).await,
+        process_execution::get_digest(&without_stdin, None, None, &store, None).await,
+    );
+}
+
+#[tokio::test]
+async fn make_execute_request_with_local_cache_scope() {
+    let executor = task_executor::Executor::new();
+    let store_dir = TempDir::new().unwrap();
+    let store = Store::local_only(executor, store_dir).unwrap();
+
+    let mut req = Process::new(owned_string_vec(&["/bin/echo", "yo"]));
+    for (cache_scope, do_not_cache) in [
+        (ProcessCacheScope::Always, false),
+        (ProcessCacheScope::RemoteReadOnlySuccessful, false),
+        (ProcessCacheScope::LocalAlways, true),
+        (ProcessCacheScope::LocalSuccessful, true),
+    ] {
+        req.cache_scope = cache_scope;
+        let EntireExecuteRequest { action, .. } =
+            process_execution::make_execute_request(&req, None, None, &store, None)
+                .await
+                .unwrap();
+        assert_eq!(action.do_not_cache, do_not_cache, "{cache_scope:?}");
+    }
+}
+
+#[tokio::test]
+async fn make_execute_request_with_output_globs() {
+    let executor = task_executor::Executor::new();
+    let store_dir = TempDir::new().unwrap();
+    let store = Store::local_only(executor, store_dir).unwrap();
+
+    let req = Process::new(owned_string_vec(&["/bin/echo", "yo"]))
+        .output_directories(relative_paths(&["directory/name"]).collect())
+        .output_globs(["dist/**/*.whl".to_owned()].into());
+
+    let EntireExecuteRequest { command, .. } =
+        process_execution::make_execute_request(&req, None, None, &store, None)
+            .await
+            .unwrap();
+
+    // The literal parent directories of the globs are captured.
+    assert_eq!(
+        command.output_directories,
+        owned_string_vec(&["directory/name", "dist"])
+    );
+    // And the globs are a part of the cache key of the process.
+    assert!(command
+        .environment_variables
+        .contains(&remexec::command::EnvironmentVariable {
+            name: process_execution::CACHE_KEY_OUTPUT_GLOBS_ENV_VAR_NAME.to_owned(),
+            value: "dist/**/*.whl".to_owned(),
+        }));
+    // And can be recovered from it.
+    assert_eq!(process_execution::output_globs(&command), req.output_globs);
+}
+
+#[tokio::test]
+async fn make_execute_request_with_env_globs() {
+    let executor = task_executor::Executor::new();
+    let store_dir = TempDir::new().unwrap();
+    let store = Store::local_only(executor, store_dir).unwrap();
+
+    let environment = btreemap! {
+        "AWS_ACCESS_KEY_ID".to_owned() => "key".to_owned(),
+        "AWS_REGION".to_owned() => "explicit".to_owned(),
+        "AWS_SECRET_ACCESS_KEY".to_owned() => "secret".to_owned(),
+        "HOME".to_owned() => "/home/user".to_owned(),
+    };
+    let mut req = Process::new(owned_string_vec(&["/bin/echo", "yo"]))
+        .env(btreemap! {
+            "AWS_REGION".to_owned() => "us-east-1".to_owned(),
+        })
+        .env_globs(BTreeSet::from(["AWS_*".to_owned()]));
+    req.resolve_env_globs(&environment).unwrap();
+
+    // Explicitly set variables take precedence over globbed variables.
+    assert_eq!(
+        req.globbed_env,
+        btreemap! {
+            "AWS_ACCESS_KEY_ID".to_owned() => "key".to_owned(),
+            "AWS_SECRET_ACCESS_KEY".to_owned() => "secret".to_owned(),
+        }
+    );
+
+    // Only the names of the globbed variables are a part of the cache key.
+    let EntireExecuteRequest { command, .. } =
+        process_execution::make_execute_request(&req, None, None, &store, None)
+            .await
+            .unwrap();
+    assert!(command
+        .environment_variables
+        .contains(&remexec::command::EnvironmentVariable {
+            name: process_execution::CACHE_KEY_ENV_GLOBS_ENV_VAR_NAME.to_owned(),
+            value: "AWS_ACCESS_KEY_ID\nAWS_SECRET_ACCESS_KEY".to_owned(),
+        }));
+    assert!(!command
+        .environment_variables
+        .iter()
+        .any(|env| env.value == "key" || env.value == "secret"));
+
+    // The names can be recovered from the cache key, and re-resolved to produce the same key.
+    let mut recovered = Process::new(owned_string_vec(&["/bin/echo", "yo"]))
+        .env(req.env.clone())
+        .env_globs(process_execution::env_globs(&command));
+    recovered.resolve_env_globs(&environment).unwrap();
+    assert_eq!(recovered.globbed_env, req.globbed_env);
+    assert_eq!(
+        process_execution::get_digest(&req, None, None, &store, None).await,
+        process_execution::get_digest(&recovered, None, None, &store, None).await,
+    );
+
+    let mut rotated = req.clone();
+    rotated
+        .resolve_env_globs(&btreemap! {
+            "AWS_ACCESS_KEY_ID".to_owned() => "rotated".to_owned(),
+            "AWS_SECRET_ACCESS_KEY".to_owned() => "rotated".to_owned(),
+        })
+        .unwrap();
+    assert_eq!(
+        process_execution::get_digest(&req, None, None, &store, None).await,
+        process_execution::get_digest(&rotated, None, None, &store, None).await,
+    );
+
+    let mut removed = req.clone();
+    removed.resolve_env_globs(&BTreeMap::new()).unwrap();
+    assert_ne!(
+        process_execution::get_digest(&req, None, None, &store, None).await,
+        process_execution::get_digest(&removed, None, None, &store, None).await,
+    );
+
+    // Invalid patterns are rejected.
+    let mut invalid = req.env_globs(BTreeSet::from(["AWS_[".to_owned()]));
+    assert!(invalid.resolve_env_globs(&environment).is_err());
+}
+
+#[tokio::test]
+async fn make_execute_request_with_tool_fingerprints() {
+    let executor = task_executor::Executor::new();
+    let store_dir = TempDir::new().unwrap();
+    let store = Store::local_only(executor, store_dir).unwrap();
+
+    let tool_fingerprints = btreemap! {
+        "black".to_owned() => "23.1.0".to_owned(),
+        "protoc".to_owned() => "sha256:abc=def".to_owned(),
+    };
+    let req = Process::new(owned_string_vec(&["/bin/echo", "yo"]))
+        .tool_fingerprints(tool_fingerprints.clone());
+
+    let EntireExecuteRequest {
+        action,
+        command,
+        execute_request,
+        ..
+    } = process_execution::make_execute_request(&req, None, None, &store, None)
+        .await
+        .unwrap();
+
+    // The fingerprints are a part of the cache key of the process, and can be recovered from it.
+    assert!(command
+        .environment_variables
+        .contains(&remexec::command::EnvironmentVariable {
+            name: process_execution::CACHE_KEY_TOOL_FINGERPRINTS_ENV_VAR_NAME.to_owned(),
+            value: "black=23.1.0\nprotoc=sha256:abc=def".to_owned(),
+        }));
+    assert_eq!(
+        process_execution::tool_fingerprints(&command),
+        tool_fingerprints
+    );
+    let changed = req.clone().tool_fingerprints(btreemap! {
+        "black".to_owned() => "23.1.1".to_owned(),
+    });
+    assert_ne!(
+        process_execution::get_digest(&req, None, None, &store, None).await,
+        process_execution::get_digest(&changed, None, None, &store, None).await,
+    );
+
+    // Querying by action digest requires that the Action and Command have been stored.
+    let action_digest: Digest = execute_request.action_digest.unwrap().try_into().unwrap();
+    assert_eq!(
+        process_execution::tool_fingerprints_for_action(&store, action_digest).await,
+        Ok(None)
+    );
+    ensure_action_stored_locally(&store, &command, &action)
+        .await
+        .unwrap();
+    assert_eq!(
+        process_execution::tool_fingerprints_for_action(&store, action_digest).await,
+        Ok(Some(tool_fingerprints))
+    );
+
+    // Tool names may not contain the separator.
+    let invalid = req.tool_fingerprints(btreemap! {
+        "a=b".to_owned() => "1".to_owned(),
+    });
+    assert!(
+        process_execution::make_execute_request(&invalid, None, None, &store, None)
+            .await
+            .unwrap_err()
+            .contains("Invalid tool fingerprint")
+    );
+}
+
+#[test]
+fn execute_request_priorities() {
+    // The default priority of 0 is not sent.
+    let mut execute_request = remexec::ExecuteRequest::default();
+    apply_priorities(&mut execute_request, 0, 0);
+    assert_eq!(execute_request, remexec::ExecuteRequest::default());
+
+    apply_priorities(&mut execute_request, -10, 5);
+    assert_eq!(
+        execute_request.execution_policy,
+        Some(remexec::ExecutionPolicy { priority: -10 })
+    );
+    assert_eq!(
+        execute_request.results_cache_policy,
+        Some(remexec::ResultsCachePolicy { priority: 5 })
+    );
+}
+
 #[tokio::test]
 async fn make_execute_request_with_instance_name() {
     let executor = task_executor::Executor::new();
@@ -181,19 +459,25 @@ async fn make_execute_request_with_instance_name() {
         env: vec![("SOME".to_owned(), "value".to_owned())]
             .into_iter()
             .collect(),
+        env_globs: BTreeSet::new(),
+        globbed_env: BTreeMap::new(),
         working_directory: None,
         input_digests: InputDigests::with_input_files(input_directory.directory_digest()),
         // Intentionally poorly sorted:
         output_files: relative_paths(&["path/to/file.ext", "other/file.ext"]).collect(),
         output_directories: relative_paths(&["directory/name"]).collect(),
+        output_globs: BTreeSet::new(),
         timeout: None,
         description: "some description".to_owned(),
         level: log::Level::Info,
         append_only_caches: BTreeMap::new(),
+        append_only_cache_seeds: BTreeMap::new(),
+        incremental_outputs: None,
         jdk_home: None,
         execution_slot_variable: None,
         concurrency_available: 0,
         cache_scope: ProcessCacheScope::Always,
+        tool_fingerprints: BTreeMap::new(),
         execution_environment: ProcessExecutionEnvironment {
             name: None,
             platform: Platform::Linux_x86_64,
@@ -203,6 +487,12 @@ async fn make_execute_request_with_instance_name() {
             )]),
         },
         remote_cache_speculation_delay: std::time::Duration::from_millis(0),
+        remote_execution_priority: None,
+        remote_results_cache_priority: None,
+        persistent_worker: None,
+        cache_validation_argv: None,
+        stdin_digest: None,
+        retry_policy: None,
         attempt: 0,
     };
 
@@ -294,21 +584,33 @@ async fn make_execute_request_with_cache_key_gen_version() {
         env: vec![("SOME".to_owned(), "value".to_owned())]
             .into_iter()
             .collect(),
+        env_globs: BTreeSet::new(),
+        globbed_env: BTreeMap::new(),
         working_directory: None,
         input_digests: InputDigests::with_input_files(input_directory.directory_digest()),
         // Intentionally poorly sorted:
         output_files: relative_paths(&["path/to/file.ext", "other/file.ext"]).collect(),
         output_directories: relative_paths(&["directory/name"]).collect(),
+        output_globs: BTreeSet::new(),
         timeout: None,
         description: "some description".to_owned(),
         level: log::Level::Info,
         append_only_caches: BTreeMap::new(),
+        append_only_cache_seeds: BTreeMap::new(),
+        incremental_outputs: None,
         jdk_home: None,
         execution_slot_variable: None,
         concurrency_available: 0,
         cache_scope: ProcessCacheScope::Always,
+        tool_fingerprints: BTreeMap::new(),
         execution_environment: make_environment(Platform::Linux_x86_64),
         remote_cache_speculation_delay: std::time::Duration::from_millis(0),
+        remote_execution_priority: None,
+        remote_results_cache_priority: None,
+        persistent_worker: None,
+        cache_validation_argv: None,
+        stdin_digest: None,
+        retry_policy: None,
         attempt: 0,
     };
 
@@ -568,21 +870,33 @@ async fn make_execute_request_with_timeout() {
         env: vec![("SOME".to_owned(), "value".to_owned())]
             .into_iter()
             .collect(),
+        env_globs: BTreeSet::new(),
+        globbed_env: BTreeMap::new(),
         working_directory: None,
         input_digests: InputDigests::with_input_files(input_directory.directory_digest()),
         // Intentionally poorly sorted:
         output_files: relative_paths(&["path/to/file.ext", "other/file.ext"]).collect(),
         output_directories: relative_paths(&["directory/name"]).collect(),
+        output_globs: BTreeSet::new(),
         timeout: one_second(),
         description: "some description".to_owned(),
         level: log::Level::Info,
         append_only_caches: BTreeMap::new(),
+        append_only_cache_seeds: BTreeMap::new(),
+        incremental_outputs: None,
         jdk_home: None,
         execution_slot_variable: None,
         concurrency_available: 0,
         cache_scope: ProcessCacheScope::Always,
+        tool_fingerprints: BTreeMap::new(),
         execution_environment: make_environment(Platform::Linux_x86_64),
         remote_cache_speculation_delay: std::time::Duration::from_millis(0),
+        remote_execution_priority: None,
+        remote_results_cache_priority: None,
+        persistent_worker: None,
+        cache_validation_argv: None,
+        stdin_digest: None,
+        retry_policy: None,
         attempt: 0,
     };
 
@@ -667,22 +981,34 @@ async fn make_execute_request_with_append_only_caches() {
         env: vec![("SOME".to_owned(), "value".to_owned())]
             .into_iter()
             .collect(),
+        env_globs: BTreeSet::new(),
+        globbed_env: BTreeMap::new(),
         working_directory: Some(RelativePath::new(Path::new("animals")).unwrap()),
         input_digests: InputDigests::with_input_files(input_directory.directory_digest()),
         output_files: BTreeSet::new(),
         output_directories: BTreeSet::new(),
+        output_globs: BTreeSet::new(),
         timeout: one_second(),
         description: "some description".to_owned(),
         level: log::Level::Info,
         append_only_caches: btreemap! {
           CacheName::new(String::from("xyzzy")).unwrap() => RelativePath::new(Path::new(".cache/xyzzy")).unwrap(),
         },
+        append_only_cache_seeds: BTreeMap::new(),
+        incremental_outputs: None,
         jdk_home: None,
         execution_slot_variable: None,
         concurrency_available: 0,
         cache_scope: ProcessCacheScope::Always,
+        tool_fingerprints: BTreeMap::new(),
         execution_environment: make_environment(Platform::Linux_x86_64),
         remote_cache_speculation_delay: std::time::Duration::from_millis(0),
+        remote_execution_priority: None,
+        remote_results_cache_priority: None,
+        persistent_worker: None,
+        cache_validation_argv: None,
+        stdin_digest: None,
+        retry_policy: None,
         attempt: 0,
     };
 
@@ -829,20 +1155,32 @@ async fn make_execute_request_using_immutable_inputs() {
         env: vec![("SOME".to_owned(), "value".to_owned())]
             .into_iter()
             .collect(),
+        env_globs: BTreeSet::new(),
+        globbed_env: BTreeMap::new(),
         working_directory: None,
         input_digests,
         output_files: relative_paths(&["path/to/file.ext", "other/file.ext"]).collect(),
         output_directories: relative_paths(&["directory/name"]).collect(),
+        output_globs: BTreeSet::new(),
         timeout: None,
         description: "some description".to_owned(),
         level: log::Level::Info,
         append_only_caches: BTreeMap::new(),
+        append_only_cache_seeds: BTreeMap::new(),
+        incremental_outputs: None,
         jdk_home: None,
         execution_slot_variable: None,
         concurrency_available: 0,
         cache_scope: ProcessCacheScope::Always,
+        tool_fingerprints: BTreeMap::new(),
         execution_environment: make_environment(Platform::Linux_x86_64),
         remote_cache_speculation_delay: std::time::Duration::from_millis(0),
+        remote_execution_priority: None,
+        remote_results_cache_priority: None,
+        persistent_worker: None,
+        cache_validation_argv: None,
+        stdin_digest: None,
+        retry_policy: None,
         attempt: 0,
     };
 
@@ -1059,6 +1397,138 @@ async fn successful_after_reconnect_from_retryable_error() {
     assert_cancellation_requests(&mock_server, vec![]);
 }
 
+#[tokio::test]
+async fn successful_after_resuming_from_transient_stream_error() {
+    WorkunitStore::setup_for_tests();
+    let executor = task_executor::Executor::new();
+    let store_dir = TempDir::new().unwrap();
+    let store = Store::local_only(executor, store_dir).unwrap();
+
+    let execute_request = echo_foo_request();
+    let op_name = "gimme-foo".to_string();
+
+    let mock_server = {
+        let EntireExecuteRequest {
+            execute_request, ..
+        } = process_execution::make_execute_request(&execute_request, None, None, &store, None)
+            .await
+            .unwrap();
+
+        // The stream fails after the operation has been named, so the operation is resumed rather
+        // than re-executed.
+        mock::execution_server::TestServer::new(
+            mock::execution_server::MockExecution::new(vec![
+                ExpectedAPICall::Execute {
+                    execute_request,
+                    stream_responses: Ok(vec![
+                        make_incomplete_operation(&op_name),
+                        // NB: Delayed so that the incomplete operation is flushed to the client before
+                        // the stream fails.
+                        MockOperation {
+                            op: Err(Status::unavailable("connection reset")),
+                            duration: Some(Duration::from_millis(100)),
+                        },
+                    ]),
+                },
+                ExpectedAPICall::WaitExecution {
+                    operation_name: op_name.clone(),
+                    stream_responses: Err(Status::unavailable("connection refused")),
+                },
+                ExpectedAPICall::WaitExecution {
+                    operation_name: op_name.clone(),
+                    stream_responses: Ok(vec![make_successful_operation(
+                        &op_name,
+                        StdoutType::Raw("foo".to_owned()),
+                        StderrType::Raw("".to_owned()),
+                        0,
+                    )]),
+                },
+            ]),
+            None,
+        )
+    };
+
+    let result = run_command_remote(mock_server.address(), execute_request)
+        .await
+        .unwrap();
+
+    assert_eq!(result.stdout_bytes, "foo".as_bytes());
+    assert_eq!(result.original.exit_code, 0);
+    assert_cancellation_requests(&mock_server, vec![]);
+}
+
+#[tokio::test]
+async fn resubmits_when_operation_is_not_found() {
+    WorkunitStore::setup_for_tests();
+    let executor = task_executor::Executor::new();
+    let store_dir = TempDir::new().unwrap();
+    let store = Store::local_only(executor, store_dir).unwrap();
+
+    let execute_request = echo_foo_request();
+    let op_name_1 = "gimme-foo".to_string();
+    let op_name_2 = "gimme-bar".to_string();
+
+    let mock_server = {
+        let EntireExecuteRequest {
+            execute_request, ..
+        } = process_execution::make_execute_request(&execute_request, None, None, &store, None)
+            .await
+            .unwrap();
+
+        mock::execution_server::TestServer::new(
+            mock::execution_server::MockExecution::new(vec![
+                ExpectedAPICall::Execute {
+                    execute_request: execute_request.clone(),
+                    stream_responses: Ok(vec![make_incomplete_operation(&op_name_1)]),
+                },
+                ExpectedAPICall::WaitExecution {
+                    operation_name: op_name_1.clone(),
+                    stream_responses: Err(Status::not_found("no such operation")),
+                },
+                ExpectedAPICall::Execute {
+                    execute_request,
+                    stream_responses: Ok(vec![make_successful_operation(
+                        &op_name_2,
+                        StdoutType::Raw("foo".to_owned()),
+                        StderrType::Raw("".to_owned()),
+                        0,
+                    )]),
+                },
+            ]),
+            None,
+        )
+    };
+
+    let result = run_command_remote(mock_server.address(), execute_request)
+        .await
+        .unwrap();
+
+    assert_eq!(result.stdout_bytes, "foo".as_bytes());
+    assert_eq!(result.original.exit_code, 0);
+    assert_cancellation_requests(&mock_server, vec![]);
+}
+
+#[test]
+fn retry_delay_from_retry_info() {
+    let status = |details| protos::gen::google::rpc::Status {
+        code: Code::Unavailable as i32,
+        message: "busy".to_owned(),
+        details,
+    };
+    assert_eq!(retry_delay(&status(vec![])), None);
+
+    let retry_info = protos::gen::google::rpc::RetryInfo {
+        retry_delay: Some(prost_types::Duration {
+            seconds: 2,
+            nanos: 500_000_000,
+        }),
+    };
+    assert_eq!(
+        retry_delay(&status(vec![make_any_proto(&retry_info, "protos::gen::")])),
+        Some(Duration::from_millis(2500))
+    );
+}
+
 #[tokio::test]
 async fn creates_executing_workunit() {
     let (workunit_store, mut workunit) = WorkunitStore::setup_for_tests();
@@ -1277,9 +1747,11 @@ fn remote_options_for_cas(cas: &mock::StubCAS) -> RemoteStoreOptions {
         headers: BTreeMap::new(),
         chunk_size_bytes: 10 * 1024 * 1024,
         timeout: Duration::from_secs(1),
+        stream_timeout: Duration::from_secs(1),
         retries: 1,
         concurrency_limit: STORE_CONCURRENCY_LIMIT,
         batch_api_size_limit: STORE_BATCH_API_SIZE_LIMIT,
+        circuit_breaker: None,
     }
 }
 
@@ -1338,6 +1810,8 @@ async fn sends_headers() {
         OVERALL_DEADLINE_SECS,
         RETRY_INTERVAL,
         EXEC_CONCURRENCY_LIMIT,
+        0,
+        0,
     )
     .await
     .unwrap();
@@ -1497,6 +1971,8 @@ async fn ensure_inline_stdio_is_stored() {
         OVERALL_DEADLINE_SECS,
         RETRY_INTERVAL,
         EXEC_CONCURRENCY_LIMIT,
+        0,
+        0,
     )
     .await
     .unwrap();
@@ -1873,6 +2349,8 @@ async fn execute_missing_file_uploads_if_known() {
         OVERALL_DEADLINE_SECS,
         RETRY_INTERVAL,
         EXEC_CONCURRENCY_LIMIT,
+        0,
+        0,
     )
     .await
     .unwrap();
@@ -1924,6 +2402,8 @@ async fn execute_missing_file_errors_if_unknown() {
         OVERALL_DEADLINE_SECS,
         RETRY_INTERVAL,
         EXEC_CONCURRENCY_LIMIT,
+        0,
+        0,
     )
     .await
     .unwrap();
@@ -2635,6 +3115,8 @@ async fn create_command_runner(
         OVERALL_DEADLINE_SECS,
         RETRY_INTERVAL,
         EXEC_CONCURRENCY_LIMIT,
+        0,
+        0,
     )
     .await
     .expect("Failed to make command runner");
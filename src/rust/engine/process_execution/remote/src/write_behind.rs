@@ -0,0 +1,607 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use hashing::{Digest, Fingerprint};
+use parking_lot::Mutex;
+use prost::Message;
+use protos::gen::build::bazel::remote::execution::v2 as remexec;
+use protos::require_digest;
+use remexec::ActionResult;
+use remote_provider::{choose_action_cache_provider, ActionCacheProvider, RemoteStoreOptions};
+use sharded_lmdb::ShardedLmdb;
+use store::bandwidth::{with_transfer_priority, TransferPriority};
+use store::{Store, StoreError};
+use tokio::sync::Notify;
+use workunit_store::{scope_task_workunit_store_handle, WorkunitStore, WorkunitStoreHandle};
+
+/// The maximum size of the queue's database. Queued writes reference their outputs by digest, and
+/// so are small: this bounds only the number of pending writes.
+const MAX_QUEUE_SIZE_BYTES: usize = 64 * 1024 * 1024;
+
+/// How long an idle worker waits for new writes before checking whether its queue was dropped.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+const MAX_ATTEMPTS: u32 = 10;
+
+/// A pending write to the remote Action Cache, as persisted in the queue.
+#[derive(Clone, PartialEq, Message)]
+struct QueuedWrite {
+    #[prost(message, optional, tag = "1")]
+    action_digest: Option<remexec::Digest>,
+    #[prost(message, optional, tag = "2")]
+    command_digest: Option<remexec::Digest>,
+    #[prost(message, optional, tag = "3")]
+    action_result: Option<ActionResult>,
+    /// All digests referenced directly or indirectly by the ActionResult.
+    #[prost(message, repeated, tag = "4")]
+    digests: Vec<remexec::Digest>,
+}
+
+struct Retry {
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+///
+/// A persistent queue of writes to the remote Action Cache, which are uploaded in the background
+/// by a worker task.
+///
+/// Writes are persisted in a small database below the local store directory, and so survive both
+/// the end of the session which enqueued them and restarts of the process: when a queue is
+/// created, it begins by draining any writes which were left behind by a previous process.
+///
+/// Uploads are rate limited, and failed uploads are retried with exponential backoff. The outputs
+/// of a write are leased when it is enqueued, and the lease (see `DEFAULT_LEASE_TIME`) outlasts
+/// all of its retries. A write is nonetheless dropped if its outputs are no longer available
+/// locally (because they were garbage collected before they could be uploaded), or after
+/// `MAX_ATTEMPTS` failed uploads.
+///
+/// Only one process may drain the queue of a particular store directory at a time: the queue is
+/// locked while it is open.
+///
+#[derive(Clone)]
+pub struct WriteBehindQueue {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    db: ShardedLmdb,
+    store: Store,
+    provider: Arc<dyn ActionCacheProvider>,
+    min_interval: Option<Duration>,
+    notify: Arc<Notify>,
+    retries: Mutex<HashMap<Fingerprint, Retry>>,
+    // Held (and so locked) until the database has been closed.
+    _lock: std::fs::File,
+}
+
+impl WriteBehindQueue {
+    ///
+    /// Opens (or creates) the queue below the given store directory, and spawns a worker which
+    /// uploads writes using the given Store and provider at no more than `rate_limit` writes per
+    /// second (or without a limit if `rate_limit` is 0).
+    ///
+    /// The worker exits once all clones of the queue have been dropped. Returns `None` if the queue
+    /// for the store directory is already open in another process (or elsewhere in this one).
+    ///
+    pub fn new(
+        store_dir: &Path,
+        executor: &task_executor::Executor,
+        store: Store,
+        provider: Arc<dyn ActionCacheProvider>,
+        rate_limit: usize,
+    ) -> Result<Option<Self>, String> {
+        let Some(inner) = Inner::open(store_dir, executor, store, provider, rate_limit)? else {
+            log::info!(
+                "The remote cache write queue in {} is in use by another process: remote cache \
+                 writes will be made directly instead.",
+                store_dir.display()
+            );
+            return Ok(None);
+        };
+        let notify = inner.notify.clone();
+        let queue = WriteBehindQueue {
+            inner: Arc::new(inner),
+        };
+        executor.native_spawn(Self::work(Arc::downgrade(&queue.inner), notify));
+        Ok(Some(queue))
+    }
+
+    pub async fn from_provider_options(
+        store_dir: &Path,
+        executor: &task_executor::Executor,
+        store: Store,
+        provider_options: RemoteStoreOptions,
+        rate_limit: usize,
+    ) -> Result<Option<Self>, String> {
+        let provider = choose_action_cache_provider(provider_options).await?;
+        Self::new(store_dir, executor, store, provider, rate_limit)
+    }
+
+    ///
+    /// Persists a write of the given ActionResult, which will be uploaded along with the Action,
+    /// the Command, and the given digests (which must all already be stored locally).
+    ///
+    /// The digests are leased, so that they are not garbage collected before they are uploaded.
+    ///
+    pub(crate) async fn enqueue(
+        &self,
+        action_digest: Digest,
+        command_digest: Digest,
+        action_result: ActionResult,
+        digests: Vec<Digest>,
+    ) -> Result<(), String> {
+        self.inner
+            .store
+            .lease_all_recursively([action_digest, command_digest].iter().chain(&digests))
+            .await
+            .map_err(|err| {
+                format!("Failed to lease outputs of queued remote cache write: {err}")
+            })?;
+        let write = QueuedWrite {
+            action_digest: Some(action_digest.into()),
+            command_digest: Some(command_digest.into()),
+            action_result: Some(action_result),
+            digests: digests.into_iter().map(|d| d.into()).collect(),
+        };
+        self.inner
+            .db
+            .store_bytes(action_digest.hash, write.encode_to_vec().into(), false)
+            .await?;
+        self.inner.notify.notify_one();
+        Ok(())
+    }
+
+    ///
+    /// Returns the number of writes which have not yet been uploaded.
+    ///
+    pub async fn pending_writes(&self) -> Result<usize, String> {
+        Ok(self.inner.db.all_fingerprints().await?.len())
+    }
+
+    async fn work(inner: Weak<Inner>, notify: Arc<Notify>) {
+        // The worker outlives the sessions which enqueue writes, and so records its workunits in a
+        // store of its own.
+        let workunit_store_handle = WorkunitStoreHandle {
+            store: WorkunitStore::new(false, log::Level::Debug),
+            parent_id: None,
+        };
+        scope_task_workunit_store_handle(Some(workunit_store_handle), async move {
+            loop {
+                let wait = match inner.upgrade() {
+                    Some(inner) => inner.upload_ready().await,
+                    None => break,
+                };
+                let _ = tokio::time::timeout(wait, notify.notified()).await;
+            }
+        })
+        .await
+    }
+}
+
+impl Inner {
+    fn open(
+        store_dir: &Path,
+        executor: &task_executor::Executor,
+        store: Store,
+        provider: Arc<dyn ActionCacheProvider>,
+        rate_limit: usize,
+    ) -> Result<Option<Self>, String> {
+        // Two queues for the same directory would race to upload (and remove) the same writes.
+        let lock = std::fs::create_dir_all(store_dir)
+            .and_then(|()| {
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .truncate(false)
+                    .write(true)
+                    .open(store_dir.join("remote_cache_queue.lock"))
+            })
+            .and_then(|lock| Ok(fs::platform::try_lock_exclusive(&lock)?.then_some(lock)))
+            .map_err(|err| format!("Could not lock remote cache write queue: {err}"))?;
+        let Some(lock) = lock else {
+            return Ok(None);
+        };
+
+        let db = ShardedLmdb::new(
+            store_dir.join("remote_cache_queue"),
+            MAX_QUEUE_SIZE_BYTES,
+            executor.clone(),
+            // NB: Entries are removed once they have been uploaded, rather than being leased.
+            Duration::ZERO,
+            1,
+        )
+        .map_err(|err| format!("Could not initialize remote cache write queue: {err}"))?;
+
+        let min_interval = if rate_limit == 0 {
+            None
+        } else {
+            // NB: Saturates rather than truncating, since truncation could produce a zero divisor.
+            Some(Duration::from_secs(1) / u32::try_from(rate_limit).unwrap_or(u32::MAX))
+        };
+        Ok(Some(Inner {
+            db,
+            store,
+            provider,
+            min_interval,
+            notify: Arc::new(Notify::new()),
+            retries: Mutex::default(),
+            _lock: lock,
+        }))
+    }
+
+    ///
+    /// Attempts to upload each queued write which is not waiting to be retried, and returns how
+    /// long to wait before the next attempt.
+    ///
+    async fn upload_ready(&self) -> Duration {
+        let fingerprints = match self.db.all_fingerprints().await {
+            Ok(fingerprints) => fingerprints,
+            Err(err) => {
+                log::warn!("Failed to list queued remote cache writes: {err}");
+                return IDLE_POLL_INTERVAL;
+            }
+        };
+
+        let mut wait = IDLE_POLL_INTERVAL;
+        for fingerprint in fingerprints.into_iter().map(|aged| aged.fingerprint) {
+            let next_attempt = self
+                .retries
+                .lock()
+                .get(&fingerprint)
+                .map(|retry| retry.next_attempt);
+            if let Some(next_attempt) = next_attempt {
+                let now = Instant::now();
+                if next_attempt > now {
+                    wait = wait.min(next_attempt - now);
+                    continue;
+                }
+            }
+
+            match self.upload(fingerprint).await {
+                Ok(()) => self.remove(fingerprint).await,
+                Err(err) => {
+                    let (attempts, delay) = {
+                        let mut retries = self.retries.lock();
+                        let retry = retries.entry(fingerprint).or_insert(Retry {
+                            attempts: 0,
+                            next_attempt: Instant::now(),
+                        });
+                        retry.attempts += 1;
+                        let delay = retry_delay(retry.attempts);
+                        retry.next_attempt = Instant::now() + delay;
+                        (retry.attempts, delay)
+                    };
+                    if matches!(err, StoreError::MissingDigest(..)) || attempts >= MAX_ATTEMPTS {
+                        log::warn!(
+                            "Dropping queued write to remote cache after {attempts} attempt(s): {err}"
+                        );
+                        self.remove(fingerprint).await;
+                    } else {
+                        log::debug!(
+                            "Failed to write to remote cache ({attempts} attempt(s) so far), will \
+                             retry in {delay:?}: {err}"
+                        );
+                        wait = wait.min(delay);
+                    }
+                }
+            }
+
+            if let Some(min_interval) = self.min_interval {
+                tokio::time::sleep(min_interval).await;
+            }
+        }
+        wait
+    }
+
+    async fn upload(&self, fingerprint: Fingerprint) -> Result<(), StoreError> {
+        let write = self
+            .db
+            .load_bytes_with(fingerprint, |bytes| {
+                QueuedWrite::decode(bytes)
+                    .map_err(|err| format!("Failed to decode queued remote cache write: {err}"))
+            })
+            .await?;
+        let Some(write) = write else {
+            // Already removed.
+            return Ok(());
+        };
+
+        let action_digest = require_digest(write.action_digest.as_ref())?;
+        let command_digest = require_digest(write.command_digest.as_ref())?;
+        let digests = write
+            .digests
+            .iter()
+            .map(require_digest)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Upload the Action and Command, but not the input files. See #12432.
+        with_transfer_priority(TransferPriority::Background, async {
+            crate::remote::ensure_action_uploaded(&self.store, command_digest, action_digest, None)
+                .await?;
+            self.store.ensure_remote_has_recursive(digests).await
+        })
+        .await?;
+        self.provider
+            .update_action_result(action_digest, write.action_result.unwrap_or_default())
+            .await?;
+        Ok(())
+    }
+
+    async fn remove(&self, fingerprint: Fingerprint) {
+        self.retries.lock().remove(&fingerprint);
+        if let Err(err) = self.db.remove(fingerprint).await {
+            log::warn!("Failed to remove queued remote cache write: {err}");
+        }
+    }
+}
+
+fn retry_delay(attempts: u32) -> Duration {
+    INITIAL_RETRY_DELAY
+        .saturating_mul(2_u32.saturating_pow(attempts.saturating_sub(1)))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use async_trait::async_trait;
+    use grpc_util::tls;
+    use hashing::Digest;
+    use mock::StubCAS;
+    use parking_lot::Mutex;
+    use protos::gen::build::bazel::remote::execution::v2 as remexec;
+    use remote_provider::ActionCacheProvider;
+    use store::{RemoteProvider, RemoteStoreOptions, ShrinkBehavior, Store};
+    use tempfile::TempDir;
+    use testutil::data::TestData;
+    use workunit_store::WorkunitStore;
+
+    use super::{Inner, WriteBehindQueue, MAX_ATTEMPTS};
+
+    /// An ActionCacheProvider which fails a given number of writes before succeeding.
+    #[derive(Default)]
+    struct FlakyActionCache {
+        failures: AtomicUsize,
+        attempts: AtomicUsize,
+        written: Mutex<HashMap<Digest, remexec::ActionResult>>,
+    }
+
+    #[async_trait]
+    impl ActionCacheProvider for FlakyActionCache {
+        async fn update_action_result(
+            &self,
+            action_digest: Digest,
+            action_result: remexec::ActionResult,
+        ) -> Result<(), String> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |f| f.checked_sub(1))
+                .is_ok()
+            {
+                return Err("Unavailable".to_owned());
+            }
+            self.written.lock().insert(action_digest, action_result);
+            Ok(())
+        }
+
+        async fn get_action_result(
+            &self,
+            action_digest: Digest,
+            _build_id: &str,
+        ) -> Result<Option<remexec::ActionResult>, String> {
+            Ok(self.written.lock().get(&action_digest).cloned())
+        }
+    }
+
+    struct Setup {
+        store: Store,
+        executor: task_executor::Executor,
+        action_cache: Arc<FlakyActionCache>,
+        queue_dir: TempDir,
+        _store_dir: TempDir,
+        _cas: StubCAS,
+    }
+
+    impl Setup {
+        async fn new(failures: usize) -> Self {
+            let _ = WorkunitStore::setup_for_tests();
+            let executor = task_executor::Executor::new();
+            let cas = StubCAS::empty();
+            let store_dir = TempDir::new().unwrap();
+            let store = Store::local_only(executor.clone(), store_dir.path())
+                .unwrap()
+                .into_with_remote(RemoteStoreOptions {
+                    provider: RemoteProvider::Reapi,
+                    store_address: cas.address(),
+                    instance_name: None,
+                    tls_config: tls::Config::default(),
+                    headers: BTreeMap::new(),
+                    chunk_size_bytes: 10 * 1024 * 1024,
+                    timeout: Duration::from_secs(1),
+                    stream_timeout: Duration::from_secs(1),
+                    retries: 1,
+                    concurrency_limit: 256,
+                    batch_api_size_limit: 4 * 1024 * 1024,
+                    circuit_breaker: None,
+                })
+                .await
+                .unwrap();
+            // The Action and Command are uploaded as opaque blobs, so any content will do.
+            for data in [TestData::roland(), TestData::catnip(), TestData::robin()] {
+                store.store_file_bytes(data.bytes(), false).await.unwrap();
+            }
+            Setup {
+                store,
+                executor,
+                action_cache: Arc::new(FlakyActionCache {
+                    failures: AtomicUsize::new(failures),
+                    ..FlakyActionCache::default()
+                }),
+                queue_dir: TempDir::new().unwrap(),
+                _store_dir: store_dir,
+                _cas: cas,
+            }
+        }
+
+        fn open(&self) -> Option<Inner> {
+            Inner::open(
+                self.queue_dir.path(),
+                &self.executor,
+                self.store.clone(),
+                self.action_cache.clone(),
+                0,
+            )
+            .unwrap()
+        }
+
+        /// Opens the queue without a worker, so that tests can drive uploads themselves.
+        fn queue(&self) -> WriteBehindQueue {
+            WriteBehindQueue {
+                inner: Arc::new(self.open().expect("queue should not be locked")),
+            }
+        }
+    }
+
+    async fn enqueue(queue: &WriteBehindQueue, output: Digest) {
+        queue
+            .enqueue(
+                TestData::roland().digest(),
+                TestData::catnip().digest(),
+                remexec::ActionResult {
+                    exit_code: 0,
+                    ..remexec::ActionResult::default()
+                },
+                vec![output],
+            )
+            .await
+            .unwrap();
+    }
+
+    /// Makes all writes which are waiting to be retried ready immediately.
+    fn skip_backoff(queue: &WriteBehindQueue) {
+        for retry in queue.inner.retries.lock().values_mut() {
+            retry.next_attempt = Instant::now();
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let setup = Setup::new(2).await;
+        let queue = setup.queue();
+        enqueue(&queue, TestData::robin().digest()).await;
+
+        queue.inner.upload_ready().await;
+        assert_eq!(setup.action_cache.attempts.load(Ordering::SeqCst), 1);
+        assert_eq!(queue.pending_writes().await.unwrap(), 1);
+
+        // The write is not retried until its backoff has elapsed.
+        let wait = queue.inner.upload_ready().await;
+        assert!(wait <= Duration::from_secs(1));
+        assert_eq!(setup.action_cache.attempts.load(Ordering::SeqCst), 1);
+
+        skip_backoff(&queue);
+        queue.inner.upload_ready().await;
+        assert_eq!(queue.pending_writes().await.unwrap(), 1);
+
+        skip_backoff(&queue);
+        queue.inner.upload_ready().await;
+        assert_eq!(setup.action_cache.attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(queue.pending_writes().await.unwrap(), 0);
+        assert!(setup
+            .action_cache
+            .written
+            .lock()
+            .contains_key(&TestData::roland().digest()));
+    }
+
+    #[tokio::test]
+    async fn drops_after_max_attempts() {
+        let setup = Setup::new(usize::MAX).await;
+        let queue = setup.queue();
+        enqueue(&queue, TestData::robin().digest()).await;
+
+        for _ in 1..MAX_ATTEMPTS {
+            queue.inner.upload_ready().await;
+            skip_backoff(&queue);
+        }
+        assert_eq!(queue.pending_writes().await.unwrap(), 1);
+
+        queue.inner.upload_ready().await;
+        assert_eq!(
+            setup.action_cache.attempts.load(Ordering::SeqCst),
+            MAX_ATTEMPTS as usize
+        );
+        assert_eq!(queue.pending_writes().await.unwrap(), 0);
+        assert!(queue.inner.retries.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn drops_writes_with_missing_digests() {
+        let setup = Setup::new(0).await;
+        let queue = setup.queue();
+        // Never stored locally, and so cannot be uploaded.
+        enqueue(&queue, TestData::forty_chars().digest()).await;
+
+        queue.inner.upload_ready().await;
+        assert_eq!(queue.pending_writes().await.unwrap(), 0);
+        assert_eq!(setup.action_cache.attempts.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn enqueue_leases_digests() {
+        let setup = Setup::new(0).await;
+        let queue = setup.queue();
+        enqueue(&queue, TestData::robin().digest()).await;
+
+        setup
+            .store
+            .garbage_collect(0, ShrinkBehavior::Fast)
+            .await
+            .unwrap();
+        // NB: The remote is empty, so this would fail if the file had been collected locally.
+        setup
+            .store
+            .load_file_bytes_with(TestData::robin().digest(), |_| ())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn drains_writes_after_restart() {
+        let setup = Setup::new(0).await;
+        {
+            let queue = setup.queue();
+            enqueue(&queue, TestData::robin().digest()).await;
+        }
+
+        let queue = setup.queue();
+        assert_eq!(queue.pending_writes().await.unwrap(), 1);
+        queue.inner.upload_ready().await;
+        assert_eq!(queue.pending_writes().await.unwrap(), 0);
+        assert!(setup
+            .action_cache
+            .written
+            .lock()
+            .contains_key(&TestData::roland().digest()));
+    }
+
+    #[tokio::test]
+    async fn only_one_queue_per_directory() {
+        let setup = Setup::new(0).await;
+        let queue = setup.queue();
+        assert!(setup.open().is_none());
+        drop(queue);
+        assert!(setup.open().is_some());
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use fs::DirectoryDigest;
+use hashing::Digest;
+use protos::gen::pants::sandboxer::sandboxer_client::SandboxerClient;
+use protos::gen::pants::sandboxer::sandboxer_server::{Sandboxer, SandboxerServer};
+use protos::gen::pants::sandboxer::{
+    GetCacheDirectoryRequest, GetCacheDirectoryResponse, MaterializeDirectoryRequest,
+    MaterializeDirectoryResponse,
+};
+use protos::require_digest;
+use store::{DelegatedMaterializer, ImmutableInputs};
+use tokio::net::{UnixListener, UnixStream};
+use tokio_stream::wrappers::UnixListenerStream;
+use tonic::transport::{Channel, Endpoint, Server, Uri};
+use tonic::{Request, Response, Status};
+
+#[cfg(test)]
+mod tests;
+
+///
+/// Serves the Sandboxer service on the given Unix socket until `shutdown` completes, materializing
+/// Directories using the given ImmutableInputs.
+///
+/// Access to the service is controlled by the permissions of the socket (and its parent
+/// directory), which are those of the process' umask.
+///
+pub async fn serve(
+    socket: &Path,
+    immutable_inputs: ImmutableInputs,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), String> {
+    let listener = UnixListener::bind(socket)
+        .map_err(|e| format!("Failed to bind to {}: {e}", socket.display()))?;
+    log::info!(
+        "Materializing into {} for clients of {}.",
+        immutable_inputs.workdir().display(),
+        socket.display()
+    );
+    Server::builder()
+        .add_service(SandboxerServer::new(SandboxerResponder {
+            immutable_inputs,
+        }))
+        .serve_with_incoming_shutdown(UnixListenerStream::new(listener), shutdown)
+        .await
+        .map_err(|e| format!("Sandboxer server failed: {e}"))
+}
+
+struct SandboxerResponder {
+    immutable_inputs: ImmutableInputs,
+}
+
+#[tonic::async_trait]
+impl Sandboxer for SandboxerResponder {
+    async fn get_cache_directory(
+        &self,
+        _: Request<GetCacheDirectoryRequest>,
+    ) -> Result<Response<GetCacheDirectoryResponse>, Status> {
+        let path = self
+            .immutable_inputs
+            .workdir()
+            .to_str()
+            .ok_or_else(|| Status::internal("The cache directory is not valid UTF-8."))?
+            .to_owned();
+        Ok(Response::new(GetCacheDirectoryResponse { path }))
+    }
+
+    async fn materialize_directory(
+        &self,
+        request: Request<MaterializeDirectoryRequest>,
+    ) -> Result<Response<MaterializeDirectoryResponse>, Status> {
+        let digest = require_digest(request.into_inner().digest.as_ref())
+            .map_err(Status::invalid_argument)?;
+        let path = self
+            .immutable_inputs
+            .path_for_dir(DirectoryDigest::from_persisted_digest(digest))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let path = path
+            .into_os_string()
+            .into_string()
+            .map_err(|p| Status::internal(format!("Path {p:?} is not valid UTF-8.")))?;
+        Ok(Response::new(MaterializeDirectoryResponse { path }))
+    }
+}
+
+///
+/// A client of a sandboxer process, which delegates the materialization of immutable inputs to it.
+///
+#[derive(Clone)]
+pub struct SandboxerMaterializer {
+    client: SandboxerClient<Channel>,
+    cache_dir: PathBuf,
+}
+
+impl SandboxerMaterializer {
+    ///
+    /// Connects to the sandboxer listening on the given Unix socket.
+    ///
+    pub async fn connect(socket: PathBuf) -> Result<Self, String> {
+        // NB: The URI is required by the Endpoint, but is ignored by the connector.
+        let channel = Endpoint::from_static("http://[::]:0")
+            .connect_with_connector(tower::service_fn(move |_: Uri| {
+                UnixStream::connect(socket.clone())
+            }))
+            .await
+            .map_err(|e| format!("Failed to connect to the sandboxer: {e}"))?;
+        let mut client = SandboxerClient::new(channel);
+        let cache_dir = client
+            .get_cache_directory(GetCacheDirectoryRequest {})
+            .await
+            .map_err(|e| format!("Failed to get the sandboxer's cache directory: {e}"))?
+            .into_inner()
+            .path
+            .into();
+        Ok(Self { client, cache_dir })
+    }
+}
+
+#[async_trait]
+impl DelegatedMaterializer for SandboxerMaterializer {
+    fn workdir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    async fn materialize_directory(&self, digest: Digest) -> Result<PathBuf, String> {
+        let path: PathBuf = self
+            .client
+            .clone()
+            .materialize_directory(MaterializeDirectoryRequest {
+                digest: Some(digest.into()),
+            })
+            .await
+            .map_err(|e| format!("Sandboxer failed to materialize {digest:?}: {e}"))?
+            .into_inner()
+            .path
+            .into();
+        if !path.starts_with(&self.cache_dir) {
+            return Err(format!(
+                "Sandboxer materialized {digest:?} outside of its cache directory, at {}",
+                path.display()
+            ));
+        }
+        Ok(path)
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::BTreeMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
+
+use clap::StructOpt;
+use store::{ImmutableInputs, RemoteProvider, RemoteStoreOptions, Store};
+
+///
+/// Materializes the immutable inputs of processes run by Pants into a directory which this
+/// process owns, so that (when this process runs as a different user to Pants) the processes which
+/// consume the inputs cannot modify them.
+///
+/// Inputs are fetched from the store that Pants serves with `--local-store-server-port`.
+///
+#[derive(StructOpt)]
+#[structopt(name = "sandboxer")]
+struct Opt {
+    /// The Unix socket to listen on, which Pants connects to via `--sandboxer-socket`.
+    #[structopt(long)]
+    socket: PathBuf,
+
+    /// The directory below which inputs are materialized.
+    #[structopt(long)]
+    cache_dir: PathBuf,
+
+    /// The address of the store served by Pants, e.g. `http://127.0.0.1:9000`.
+    #[structopt(long)]
+    store_address: String,
+
+    #[structopt(long)]
+    remote_instance_name: Option<String>,
+
+    /// Path to the lmdb directory used to cache inputs fetched from Pants.
+    #[structopt(long)]
+    local_store_path: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    let args = Opt::from_args();
+    if let Err(e) = run(args).await {
+        eprintln!("{e}");
+        exit(1);
+    }
+}
+
+async fn run(args: Opt) -> Result<(), String> {
+    let executor = task_executor::Executor::new();
+    let local_store_path = args
+        .local_store_path
+        .unwrap_or_else(|| args.cache_dir.join("lmdb_store"));
+    let store = Store::local_only(executor, local_store_path)?
+        .into_with_remote(RemoteStoreOptions {
+            provider: RemoteProvider::Reapi,
+            store_address: args.store_address,
+            instance_name: args.remote_instance_name,
+            tls_config: grpc_util::tls::Config::default(),
+            headers: BTreeMap::new(),
+            chunk_size_bytes: 1024 * 1024,
+            timeout: Duration::from_secs(30),
+            stream_timeout: Duration::from_secs(10 * 60),
+            retries: 3,
+            concurrency_limit: 128,
+            batch_api_size_limit: 4 * 1024 * 1024,
+            circuit_breaker: None,
+        })
+        .await?;
+
+    let immutable_inputs = ImmutableInputs::new(store, &args.cache_dir)?;
+    // Consumers run as other users, and so must be able to traverse (but not modify) the workdir.
+    std::fs::set_permissions(
+        immutable_inputs.workdir(),
+        std::fs::Permissions::from_mode(0o755),
+    )
+    .map_err(|e| format!("Failed to set permissions of the cache directory: {e}"))?;
+
+    // Remove any socket left behind by a previous run.
+    let _ = std::fs::remove_file(&args.socket);
+    let result = sandboxer::serve(&args.socket, immutable_inputs, async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await;
+    let _ = std::fs::remove_file(&args.socket);
+    result
+}
@@ -0,0 +1,62 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use fs::DirectoryDigest;
+use store::{ImmutableInputs, Store};
+use tempfile::TempDir;
+use testutil::data::{TestData, TestDirectory};
+use tokio::sync::oneshot;
+
+use crate::SandboxerMaterializer;
+
+#[tokio::test]
+async fn materialize_via_sandboxer() {
+    let dir = TempDir::new().unwrap();
+    let store =
+        Store::local_only(task_executor::Executor::new(), dir.path().join("store")).unwrap();
+    let roland = TestData::roland();
+    let directory = TestDirectory::containing_roland();
+    store.store_file_bytes(roland.bytes(), false).await.unwrap();
+    store
+        .record_directory(&directory.directory(), false)
+        .await
+        .unwrap();
+
+    let socket = dir.path().join("sandboxer.sock");
+    let sandboxer_inputs = ImmutableInputs::new(store.clone(), &dir.path().join("cache")).unwrap();
+    let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+    let server = tokio::spawn({
+        let socket = socket.clone();
+        async move {
+            crate::serve(&socket, sandboxer_inputs, async {
+                let _ = shutdown_receiver.await;
+            })
+            .await
+        }
+    });
+    while !socket.exists() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let materializer = SandboxerMaterializer::connect(socket).await.unwrap();
+    let immutable_inputs = ImmutableInputs::new_delegated(store, Arc::new(materializer));
+    let path = immutable_inputs
+        .path_for_dir(DirectoryDigest::from_persisted_digest(directory.digest()))
+        .await
+        .unwrap();
+
+    assert!(path.starts_with(immutable_inputs.workdir()));
+    let file = path.join("roland.ext");
+    assert_eq!(std::fs::read(&file).unwrap(), roland.bytes());
+    assert_eq!(
+        std::fs::metadata(&file).unwrap().permissions().mode() & 0o222,
+        0,
+        "Materialized inputs should not be writable."
+    );
+
+    shutdown_sender.send(()).unwrap();
+    server.await.unwrap().unwrap();
+}
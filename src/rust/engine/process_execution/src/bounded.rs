@@ -16,7 +16,7 @@ use regex::Regex;
 use task_executor::Executor;
 use tokio::sync::{Notify, Semaphore, SemaphorePermit};
 use tokio::time::sleep;
-use workunit_store::{in_workunit, RunningWorkunit};
+use workunit_store::{in_workunit, RunningWorkunit, WaitingOn};
 
 use crate::{Context, FallibleProcessResultWithPlatform, Process, ProcessError};
 
@@ -84,7 +84,7 @@ impl crate::CommandRunner for CommandRunner {
             // filtering blocked workunits at creation time, regardless of level.
             Level::Debug,
             |workunit| async move {
-                let _blocking_token = workunit.blocking();
+                let _blocking_token = workunit.waiting_on(WaitingOn::Semaphore);
                 semaphore_acquisition.await
             }
         )
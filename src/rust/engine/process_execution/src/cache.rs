@@ -2,7 +2,7 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 use std::fmt::{self, Debug};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -18,9 +18,8 @@ use workunit_store::{
 };
 
 use crate::{
-    check_cache_content, CacheContentBehavior, Context, FallibleProcessResultWithPlatform,
-    Platform, Process, ProcessCacheScope, ProcessError, ProcessExecutionEnvironment,
-    ProcessResultSource,
+    check_cache_content, CacheContentBehavior, CacheLocation, Context,
+    FallibleProcessResultWithPlatform, Platform, Process, ProcessError, ProcessResultSource,
 };
 
 // TODO: Consider moving into protobuf as a CacheValue type.
@@ -38,6 +37,7 @@ pub struct CommandRunner {
     cache_read: bool,
     cache_content_behavior: CacheContentBehavior,
     process_cache_namespace: Option<String>,
+    max_age: Option<Duration>,
 }
 
 impl CommandRunner {
@@ -48,6 +48,7 @@ impl CommandRunner {
         cache_read: bool,
         cache_content_behavior: CacheContentBehavior,
         process_cache_namespace: Option<String>,
+        max_age: Option<Duration>,
     ) -> CommandRunner {
         CommandRunner {
             inner,
@@ -56,6 +57,7 @@ impl CommandRunner {
             cache_read,
             cache_content_behavior,
             process_cache_namespace,
+            max_age,
         }
     }
 }
@@ -76,7 +78,7 @@ impl crate::CommandRunner for CommandRunner {
         workunit: &mut RunningWorkunit,
         req: Process,
     ) -> Result<FallibleProcessResultWithPlatform, ProcessError> {
-        let write_failures_to_cache = req.cache_scope == ProcessCacheScope::Always;
+        let cache_scope = req.cache_scope;
         let key = CacheKey {
             digest: Some(
                 crate::get_digest(
@@ -92,10 +94,10 @@ impl crate::CommandRunner for CommandRunner {
             key_type: CacheKeyType::Process.into(),
         };
 
-        if self.cache_read {
+        if self.cache_read && cache_scope.reads_from(CacheLocation::Local) {
             let context2 = context.clone();
             let key2 = key.clone();
-            let environment = req.execution_environment.clone();
+            let req2 = req.clone();
             let cache_read_result = in_workunit!(
                 "local_cache_read",
                 Level::Trace,
@@ -103,8 +105,9 @@ impl crate::CommandRunner for CommandRunner {
                 |workunit| async move {
                     workunit.increment_counter(Metric::LocalCacheRequests, 1);
 
-                    match self.lookup(&context2, &key2, environment).await {
-                        Ok(Some(result)) if result.exit_code == 0 || write_failures_to_cache => {
+                    match self.lookup(&context2, &key2, &req2).await {
+                        Ok(Some(mut result)) if cache_scope.is_cacheable(result.exit_code) => {
+                            result.metadata.cache_scope = Some(cache_scope);
                             workunit.increment_counter(Metric::LocalCacheRequestsCached, 1);
                             if let Some(time_saved) = result.metadata.saved_by_cache {
                                 let time_saved =
@@ -162,8 +165,9 @@ impl crate::CommandRunner for CommandRunner {
             }
         }
 
-        let result = self.inner.run(context.clone(), workunit, req).await?;
-        if result.exit_code == 0 || write_failures_to_cache {
+        let mut result = self.inner.run(context.clone(), workunit, req).await?;
+        result.metadata.cache_scope = Some(cache_scope);
+        if cache_scope.writes_to(CacheLocation::Local, result.exit_code) {
             let result = result.clone();
             in_workunit!("local_cache_write", Level::Trace, |workunit| async move {
                 if let Err(err) = self.store(&key, &result).await {
@@ -189,7 +193,7 @@ impl CommandRunner {
         &self,
         context: &Context,
         action_key: &CacheKey,
-        environment: ProcessExecutionEnvironment,
+        req: &Process,
     ) -> Result<Option<FallibleProcessResultWithPlatform>, StoreError> {
         let cache_lookup_start = Instant::now();
         use remexec::ExecuteResponse;
@@ -217,7 +221,7 @@ impl CommandRunner {
                     action_result,
                     true,
                     ProcessResultSource::HitLocally,
-                    environment,
+                    req.execution_environment.clone(),
                 )
                 .await?
             } else {
@@ -229,15 +233,40 @@ impl CommandRunner {
             return Ok(None);
         };
 
-        if check_cache_content(&result, &self.file_store, self.cache_content_behavior).await? {
-            // NB: We set the cache hit elapsed time as late as possible (after having validated the cache content).
-            result
-                .metadata
-                .update_cache_hit_elapsed(cache_lookup_start.elapsed());
-            Ok(Some(result))
-        } else {
-            Ok(None)
+        if !check_cache_content(&result, &self.file_store, self.cache_content_behavior).await? {
+            return Ok(None);
         }
+        if result.metadata.is_stale(self.max_age) {
+            let revalidated = crate::revalidate_cached_result(
+                self.inner.as_ref(),
+                context.clone(),
+                &self.file_store,
+                req,
+                &result,
+            )
+            .await
+            .map_err(|e| format!("Failed to revalidate cached result: {e}"))?;
+            if !revalidated {
+                return Ok(None);
+            }
+            // Rewrite the entry with a fresh timestamp, so that it is not revalidated again until it
+            // next becomes stale.
+            let refreshed = match self.cache.remove(action_key).await {
+                Ok(_) => self.store(action_key, &result).await,
+                Err(err) => Err(err.into()),
+            };
+            if let Err(err) = refreshed {
+                warn!(
+                    "Error refreshing revalidated process execution result in local cache: {err}"
+                );
+            }
+        }
+
+        // NB: We set the cache hit elapsed time as late as possible (after having validated the cache content).
+        result
+            .metadata
+            .update_cache_hit_elapsed(cache_lookup_start.elapsed());
+        Ok(Some(result))
     }
 
     async fn store(
@@ -262,7 +291,7 @@ impl CommandRunner {
             }],
             stdout_digest: Some((&stdout_digest).into()),
             stderr_digest: Some((&stderr_digest).into()),
-            execution_metadata: Some(result.metadata.clone().into()),
+            execution_metadata: Some(crate::metadata_for_cache(&result.metadata)),
             ..remexec::ActionResult::default()
         };
         let execute_response = remexec::ExecuteResponse {
@@ -4,6 +4,7 @@ use std::convert::TryInto;
 use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use cache::PersistentCache;
 use sharded_lmdb::DEFAULT_LEASE_TIME;
@@ -37,6 +38,7 @@ fn create_local_runner() -> (Box<dyn CommandRunnerTrait>, Store, TempDir) {
         NamedCaches::new_local(named_cache_dir),
         ImmutableInputs::new(store.clone(), base_dir.path()).unwrap(),
         KeepSandboxes::Never,
+        None,
         Arc::new(RwLock::new(())),
     ));
     (runner, store, base_dir)
@@ -45,6 +47,7 @@ fn create_local_runner() -> (Box<dyn CommandRunnerTrait>, Store, TempDir) {
 fn create_cached_runner(
     local: Box<dyn CommandRunnerTrait>,
     store: Store,
+    max_age: Option<Duration>,
 ) -> (Box<dyn CommandRunnerTrait>, TempDir) {
     let runtime = task_executor::Executor::new();
     let cache_dir = TempDir::new().unwrap();
@@ -66,6 +69,7 @@ fn create_cached_runner(
         true,
         CacheContentBehavior::Fetch,
         None,
+        max_age,
     ));
 
     (runner, cache_dir)
@@ -102,7 +106,7 @@ async fn run_roundtrip(script_exit_code: i8, workunit: &mut RunningWorkunit) ->
         .run(Context::default(), workunit, process.clone())
         .await;
 
-    let (caching, _cache_dir) = create_cached_runner(local, store.clone());
+    let (caching, _cache_dir) = create_cached_runner(local, store.clone(), None);
 
     let uncached_result = caching
         .run(Context::default(), workunit, process.clone())
@@ -143,7 +147,7 @@ async fn recover_from_missing_store_contents() {
     let (_, mut workunit) = WorkunitStore::setup_for_tests();
 
     let (local, store, _local_runner_dir) = create_local_runner();
-    let (caching, _cache_dir) = create_cached_runner(local, store.clone());
+    let (caching, _cache_dir) = create_cached_runner(local, store.clone(), None);
     let (process, _script_path, _script_dir) = create_script(0);
 
     // Run once to cache the process.
@@ -195,3 +199,89 @@ async fn recover_from_missing_store_contents() {
         .ok()
         .is_some())
 }
+
+#[tokio::test]
+async fn stale_hits_revalidated() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+
+    let (local, store, _local_runner_dir) = create_local_runner();
+    // With a maximum age of zero, every hit is stale.
+    let (caching, _cache_dir) = create_cached_runner(local, store.clone(), Some(Duration::ZERO));
+    let (process, script_path, _script_dir) = create_script(0);
+    let with_validation = |script: &str| Process {
+        cache_validation_argv: Some(vec![
+            testutil::path::find_bash(),
+            "-c".to_owned(),
+            script.to_owned(),
+        ]),
+        ..process.clone()
+    };
+
+    let uncached_result = caching
+        .run(
+            Context::default(),
+            &mut workunit,
+            with_validation("test -f roland"),
+        )
+        .await
+        .unwrap();
+    std::fs::remove_file(&script_path).unwrap();
+
+    // A stale hit is used if its validation command succeeds against its outputs...
+    let revalidated_result = caching
+        .run(
+            Context::default(),
+            &mut workunit,
+            with_validation("test -f roland"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(uncached_result, revalidated_result);
+
+    // ...but otherwise the process is re-run (and fails, since the script was removed).
+    let rerun_result = caching
+        .run(Context::default(), &mut workunit, with_validation("exit 1"))
+        .await
+        .unwrap();
+    assert_eq!(rerun_result.exit_code, 127);
+}
+
+#[tokio::test]
+async fn revalidated_hits_refreshed() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+
+    let (local, store, _local_runner_dir) = create_local_runner();
+    let max_age = Duration::from_millis(500);
+    let (caching, _cache_dir) = create_cached_runner(local, store.clone(), Some(max_age));
+    let (process, script_path, _script_dir) = create_script(0);
+    let with_validation = |script: &str| Process {
+        cache_validation_argv: Some(vec![
+            testutil::path::find_bash(),
+            "-c".to_owned(),
+            script.to_owned(),
+        ]),
+        ..process.clone()
+    };
+
+    let uncached_result = caching
+        .run(Context::default(), &mut workunit, with_validation("exit 0"))
+        .await
+        .unwrap();
+    std::fs::remove_file(&script_path).unwrap();
+
+    // Once the entry is stale, a hit is revalidated...
+    tokio::time::sleep(max_age).await;
+    let revalidated_result = caching
+        .run(Context::default(), &mut workunit, with_validation("exit 0"))
+        .await
+        .unwrap();
+    assert_eq!(uncached_result, revalidated_result);
+
+    // ...and the entry is rewritten, so that a failing validation command is not run again until it
+    // is next stale.
+    let fresh_result = caching
+        .run(Context::default(), &mut workunit, with_validation("exit 1"))
+        .await
+        .unwrap();
+    assert_eq!(uncached_result, fresh_result);
+}
@@ -1,19 +1,92 @@
 // Copyright 2022 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::collections::BTreeMap;
 use std::ops::{Deref, DerefMut};
+use std::process::ExitStatus;
+use std::sync::Arc;
 use std::{thread, time};
 
-use nix::sys::signal;
-use nix::unistd::getpgid;
+use nix::sys::signal::Signal;
 use nix::unistd::Pid;
+use parking_lot::{const_mutex, Mutex};
 use tokio::process::{Child, Command};
 
 const GRACEFUL_SHUTDOWN_POLL_TIME: time::Duration = time::Duration::from_millis(50);
 
-/// A child process running in its own PGID, with a drop implementation that will kill that
-/// PGID.
+/// The process groups of all ManagedChild instances which have not yet been reaped, by id: see
+/// `kill_session`.
+static LIVE_PROCESS_GROUPS: Mutex<BTreeMap<i32, LiveProcessGroup>> = const_mutex(BTreeMap::new());
+
+struct LiveProcessGroup {
+    process_group: Arc<ProcessGroup>,
+    // The id of the Session which spawned the child, if it was spawned under a WorkunitStore.
+    session_id: Option<Arc<str>>,
+}
+
+///
+/// Returns the process group ids of the live ManagedChild instances which were spawned by the given
+/// Session.
+///
+pub fn live_process_groups(session_id: &str) -> Vec<i32> {
+    LIVE_PROCESS_GROUPS
+        .lock()
+        .iter()
+        .filter(|(_, live)| live.session_id.as_deref() == Some(session_id))
+        .map(|(pgid, _)| *pgid)
+        .collect()
+}
+
+///
+/// Kills the process groups of the live ManagedChild instances which were spawned by the given
+/// Session, without waiting for them to exit, and returns the number of process groups which were
+/// killed.
+///
+/// Used to forcibly stop processes when a graceful shutdown of the Session is not completing: the
+/// owning ManagedChild instances will reap them.
+///
+pub fn kill_session(session_id: &str) -> usize {
+    let process_groups = LIVE_PROCESS_GROUPS
+        .lock()
+        .values()
+        .filter(|live| live.session_id.as_deref() == Some(session_id))
+        .map(|live| live.process_group.clone())
+        .collect::<Vec<_>>();
+    process_groups
+        .into_iter()
+        .filter(|process_group| process_group.kill().is_ok())
+        .count()
+}
+
+fn register(process_group: Arc<ProcessGroup>) {
+    let session_id = workunit_store::get_workunit_store_handle()
+        .and_then(|handle| handle.store.session_id().map(Arc::from));
+    LIVE_PROCESS_GROUPS.lock().insert(
+        process_group.id(),
+        LiveProcessGroup {
+            process_group,
+            session_id,
+        },
+    );
+}
+
+///
+/// Removes the given process group from `LIVE_PROCESS_GROUPS`, unless its id has already been
+/// reused by another (registered) process group.
 ///
-/// Will optionally attempt a graceful shutdown first using `SIGINT`.
+fn unregister(process_group: &Arc<ProcessGroup>) {
+    let mut live_process_groups = LIVE_PROCESS_GROUPS.lock();
+    if live_process_groups
+        .get(&process_group.id())
+        .is_some_and(|live| Arc::ptr_eq(&live.process_group, process_group))
+    {
+        live_process_groups.remove(&process_group.id());
+    }
+}
+
+/// A child process running in its own process group, with a drop implementation that will kill
+/// that process group.
+///
+/// Will optionally attempt a graceful shutdown first by interrupting the process group.
 ///
 /// TODO: If this API is useful, we should consider extending it to parented Nailgun processes
 /// and to all local execution in general. It could also be adjusted for sending other posix
@@ -22,6 +95,9 @@ pub struct ManagedChild {
     child: Child,
     graceful_shutdown_timeout: Option<time::Duration>,
     killed: bool,
+    // The process group of the child, which is registered in `LIVE_PROCESS_GROUPS` until the child
+    // has been reaped (after which its id may be reused by an unrelated process).
+    process_group: Option<Arc<ProcessGroup>>,
 }
 
 impl ManagedChild {
@@ -34,42 +110,60 @@ impl ManagedChild {
         //   see https://docs.rs/tokio/1.14.0/tokio/process/struct.Command.html#method.kill_on_drop
         command.kill_on_drop(true);
 
-        // Adjust the Command to create its own PGID as it starts, to make it safe to kill the PGID
-        // later.
-        unsafe {
-            command.pre_exec(|| {
-                nix::unistd::setsid().map(|_pgid| ()).map_err(|e| {
-                    std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Could not create new pgid: {e}"),
-                    )
-                })
-            });
-        };
+        // Adjust the Command to create its own process group as it starts, to make it safe to kill
+        // the group later.
+        ProcessGroup::prepare(command);
 
-        // Then spawn.
         let child = command.spawn()?;
+        let process_group = match child.id() {
+            Some(pid) => {
+                let process_group = Arc::new(ProcessGroup::attach(pid, &child)?);
+                register(process_group.clone());
+                Some(process_group)
+            }
+            None => None,
+        };
         Ok(Self {
             child,
             graceful_shutdown_timeout,
             killed: false,
+            process_group,
         })
     }
 
-    fn get_pgid(&self) -> Result<Pid, String> {
-        let pid = self.id().ok_or_else(|| "Process had no PID.".to_owned())?;
-        let pgid = getpgid(Some(Pid::from_raw(pid as i32)))
-            .map_err(|e| format!("Could not get process group id of child process: {e}"))?;
-        Ok(pgid)
+    fn process_group(&self) -> Result<&ProcessGroup, String> {
+        self.process_group
+            .as_deref()
+            .ok_or_else(|| "Process had no PID.".to_owned())
     }
 
-    /// Send a signal to the child process group.
-    fn signal_pg<T: Into<Option<signal::Signal>>>(&mut self, signal: T) -> Result<(), String> {
-        let pgid = self.get_pgid()?;
-        // the negative PGID will signal the entire process group.
-        signal::kill(Pid::from_raw(-pgid.as_raw()), signal)
-            .map_err(|e| format!("Failed to interrupt child process group: {e}"))?;
-        Ok(())
+    fn reaped(&self) {
+        if let Some(process_group) = &self.process_group {
+            unregister(process_group);
+        }
+    }
+
+    ///
+    /// Waits for the child to exit: see `Child::wait`.
+    ///
+    /// NB: This shadows `Child::wait` so that the process group of the child is unregistered as
+    /// soon as the child has been reaped.
+    ///
+    pub async fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        let exit_status = self.child.wait().await?;
+        self.reaped();
+        Ok(exit_status)
+    }
+
+    ///
+    /// Checks whether the child has exited: see `Child::try_wait`.
+    ///
+    pub fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        let exit_status = self.child.try_wait()?;
+        if exit_status.is_some() {
+            self.reaped();
+        }
+        Ok(exit_status)
     }
 
     /// Check if the child has exited.
@@ -79,8 +173,7 @@ impl ManagedChild {
     /// the result of the child process, and does not necessarily indicate that
     /// has exited or not.
     fn check_child_has_exited(&mut self) -> Result<bool, String> {
-        self.child
-            .try_wait()
+        self.try_wait()
             .map(|o| o.is_some())
             .map_err(|e| e.to_string())
     }
@@ -113,8 +206,9 @@ impl ManagedChild {
 
     /// Attempt to shutdown the process (gracefully, if was configured that way at creation).
     ///
-    /// Graceful shutdown will send a SIGINT to the process and give it a chance to exit. If the
-    /// process does not respond to the SIGINT within a fixed interval, a SIGKILL will be sent.
+    /// Graceful shutdown will send a SIGINT to the process group and give it a chance to exit. If
+    /// the process does not respond to the SIGINT within a fixed interval, the process group will be
+    /// killed with a SIGKILL.
     ///
     /// NB: This method *will* block the current thread but it will do so for a bounded amount of time,
     /// as long as the operating system responds to `SIGKILL` in a bounded amount of time.
@@ -125,7 +219,7 @@ impl ManagedChild {
         if let Some(graceful_shutdown_timeout) = self.graceful_shutdown_timeout {
             // If we fail to send SIGINT, then we will also fail to send SIGKILL, so we return eagerly
             // on error here.
-            self.signal_pg(signal::Signal::SIGINT)?;
+            self.process_group()?.interrupt()?;
             match self.wait_for_child_exit_sync(graceful_shutdown_timeout) {
                 Ok(true) => {
                     // Process was gracefully shutdown: return.
@@ -149,8 +243,8 @@ impl ManagedChild {
 
     /// Kill the process's unique PGID or return an error if we don't have a PID or cannot kill.
     fn kill_pgid(&mut self) -> Result<(), String> {
-        self.signal_pg(signal::Signal::SIGKILL)?;
-        // NB: Since the SIGKILL was successfully delivered above, the only things that could cause the
+        self.process_group()?.kill()?;
+        // NB: Since the kill was successfully delivered above, the only things that could cause the
         // child not to eventually exit would be if it had become a zombie (which shouldn't be possible,
         // because we are its parent process, and we are still alive).
         let _ = self.wait_for_child_exit_sync(time::Duration::from_secs(1800))?;
@@ -179,5 +273,71 @@ impl Drop for ManagedChild {
         if !self.killed {
             let _ = self.attempt_shutdown_sync();
         }
+        self.reaped();
+    }
+}
+
+///
+/// The exit code reported for a process which was terminated because it exceeded its timeout: the
+/// negated signal number of `SIGTERM`, as if the process had been killed by it.
+///
+pub const TIMEOUT_EXIT_CODE: i32 = -libc::SIGTERM;
+
+///
+/// Returns the exit code of the given ExitStatus, or the negated signal number if the process was
+/// killed by a signal.
+///
+pub fn exit_code(exit_status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    exit_status
+        .code()
+        .or_else(|| exit_status.signal().map(|signal| -signal))
+        .expect("Child process should exit via returned code or signal.")
+}
+
+///
+/// The process group of a ManagedChild.
+///
+/// This is the session created by the child calling `setsid` before it execs: because
+/// the child is the session leader, its PID is also its PGID.
+///
+struct ProcessGroup {
+    pgid: i32,
+}
+
+impl ProcessGroup {
+    fn prepare(command: &mut Command) {
+        unsafe {
+            command.pre_exec(|| {
+                nix::unistd::setsid().map(|_pgid| ()).map_err(|e| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!("Could not create new pgid: {e}"),
+                    )
+                })
+            });
+        };
+    }
+
+    fn attach(pid: u32, _child: &Child) -> std::io::Result<Self> {
+        Ok(Self { pgid: pid as i32 })
+    }
+
+    fn id(&self) -> i32 {
+        self.pgid
+    }
+
+    fn interrupt(&self) -> Result<(), String> {
+        self.signal(Signal::SIGINT)
+    }
+
+    fn kill(&self) -> Result<(), String> {
+        self.signal(Signal::SIGKILL)
+    }
+
+    fn signal(&self, signal: Signal) -> Result<(), String> {
+        // The negative PGID will signal the entire process group.
+        nix::sys::signal::kill(Pid::from_raw(-self.pgid), signal)
+            .map_err(|e| format!("Failed to signal child process group: {e}"))
     }
 }
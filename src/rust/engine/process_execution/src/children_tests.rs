@@ -0,0 +1,41 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use tokio::process::Command;
+use workunit_store::{Level, WorkunitStore};
+
+use crate::children::{self, ManagedChild};
+
+fn spawn_in_session(session_id: &str, argv: &[&str]) -> ManagedChild {
+    WorkunitStore::new(false, Level::Debug)
+        .with_session_id(session_id.to_owned())
+        .init_thread_state(None);
+    let mut command = Command::new(argv[0]);
+    command.args(&argv[1..]);
+    ManagedChild::spawn(&mut command, None).unwrap()
+}
+
+#[tokio::test]
+async fn kill_session_only_kills_the_children_of_the_session() {
+    let mut killed = spawn_in_session("kill_session_1", &["sleep", "60"]);
+    let mut survivor = spawn_in_session("kill_session_2", &["sleep", "60"]);
+
+    assert_eq!(children::kill_session("kill_session_1"), 1);
+    let exit_status = killed.wait().await.unwrap();
+    assert!(!exit_status.success());
+    assert!(survivor.try_wait().unwrap().is_none());
+    assert_eq!(
+        children::live_process_groups("kill_session_2"),
+        vec![survivor.id().unwrap() as i32]
+    );
+}
+
+#[tokio::test]
+async fn reaped_children_are_unregistered() {
+    let mut child = spawn_in_session("reaped", &["true"]);
+    assert_eq!(children::live_process_groups("reaped").len(), 1);
+
+    assert!(child.wait().await.unwrap().success());
+    // Once reaped, the id of the process group may be reused, so it must not be killed.
+    assert!(children::live_process_groups("reaped").is_empty());
+    assert_eq!(children::kill_session("reaped"), 0);
+}
@@ -47,7 +47,7 @@ pub async fn spawn_process(
         loop {
             match fork_exec() {
                 Err(e) => {
-                    if e.raw_os_error() == Some(libc::ETXTBSY)
+                    if fs::platform::is_text_file_busy(&e)
                         && start_time.elapsed() < MAX_ETXTBSY_WAIT
                     {
                         tokio::time::sleep(std::time::Duration::from_millis(sleep_millis)).await;
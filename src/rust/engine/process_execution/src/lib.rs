@@ -14,8 +14,9 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use concrete_time::{Duration, TimeSpan};
 use deepsize::DeepSizeOf;
+use engine_error::{EngineError, ErrorCategory, ErrorCode};
 use fs::{DirectoryDigest, RelativePath, EMPTY_DIRECTORY_DIGEST};
-use fs::{File, PathStat};
+use fs::{File, GlobExpansionConjunction, PathStat, PreparedPathGlobs, StrictGlobMatching};
 use futures::future::try_join_all;
 use futures::future::{self, BoxFuture, TryFutureExt};
 use futures::try_join;
@@ -23,6 +24,7 @@ use futures::FutureExt;
 use grpc_util::prost::MessageExt;
 use hashing::Digest;
 use itertools::Itertools;
+use prost::Message;
 use protos::gen::build::bazel::remote::execution::v2 as remexec;
 use protos::require_digest;
 use remexec::ExecutedActionMetadata;
@@ -33,7 +35,7 @@ use std::collections::HashMap;
 use std::fmt::Write;
 use std::path::Path;
 use store::{Snapshot, StoreFileByDigest};
-use store::{SnapshotOps, Store, StoreError};
+use store::{SnapshotOps, Store, StoreError, SubsetParams};
 use task_executor::TailTasks;
 use tryfuture::try_future;
 use uuid::Uuid;
@@ -47,9 +49,27 @@ pub mod cache;
 #[cfg(test)]
 mod cache_tests;
 
+pub mod nondeterminism;
+#[cfg(test)]
+mod nondeterminism_tests;
+
+pub mod retry;
+#[cfg(test)]
+mod retry_tests;
+
+pub mod sandbox_exec;
+#[cfg(test)]
+mod sandbox_exec_tests;
+
 pub mod switched;
 
+pub mod watchdog;
+#[cfg(test)]
+mod watchdog_tests;
+
 pub mod children;
+#[cfg(all(test, unix))]
+mod children_tests;
 
 pub mod local;
 #[cfg(test)]
@@ -88,10 +108,24 @@ pub const CACHE_KEY_SALT_ENV_VAR_NAME: &str = "PANTS_CACHE_KEY_SALT";
 // CommandRunner.
 pub const CACHE_KEY_TARGET_PLATFORM_ENV_VAR_NAME: &str = "PANTS_CACHE_KEY_TARGET_PLATFORM";
 
+// Environment variable which is exclusively used for cache key invalidation, because the REv2
+// Command has no equivalent to the output globs of a Process.
+pub const CACHE_KEY_OUTPUT_GLOBS_ENV_VAR_NAME: &str = "PANTS_CACHE_KEY_OUTPUT_GLOBS";
+
+// Environment variable which is exclusively used for cache key invalidation, and which records the
+// `tool_fingerprints` of a Process: see `tool_fingerprints`.
+pub const CACHE_KEY_TOOL_FINGERPRINTS_ENV_VAR_NAME: &str = "PANTS_CACHE_KEY_TOOL_FINGERPRINTS";
+
+// Environment variable which is exclusively used for cache key invalidation, and which records the
+// names (but not the values) of the environment variables matched by the `env_globs` of a Process.
+pub const CACHE_KEY_ENV_GLOBS_ENV_VAR_NAME: &str = "PANTS_CACHE_KEY_ENV_GLOBS";
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ProcessError {
     /// A Digest was not present in either of the local or remote Stores.
     MissingDigest(String, Digest),
+    /// An error with a stable code and category: see `engine_error::ErrorCode`.
+    Classified(EngineError),
     /// All other error types.
     Unclassified(String),
 }
@@ -100,9 +134,18 @@ impl ProcessError {
     pub fn enrich(self, prefix: &str) -> Self {
         match self {
             Self::MissingDigest(s, d) => Self::MissingDigest(format!("{prefix}: {s}"), d),
+            Self::Classified(e) => Self::Classified(e.with_context(prefix)),
             Self::Unclassified(s) => Self::Unclassified(format!("{prefix}: {s}")),
         }
     }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::MissingDigest(..) => ErrorCode::MissingDigest,
+            Self::Classified(e) => e.code(),
+            Self::Unclassified(_) => ErrorCode::Unclassified,
+        }
+    }
 }
 
 impl Display for ProcessError {
@@ -111,6 +154,7 @@ impl Display for ProcessError {
             Self::MissingDigest(s, d) => {
                 write!(f, "{s}: {d:?}")
             }
+            Self::Classified(e) => write!(f, "{e}"),
             Self::Unclassified(s) => write!(f, "{s}"),
         }
     }
@@ -120,11 +164,18 @@ impl From<StoreError> for ProcessError {
     fn from(err: StoreError) -> Self {
         match err {
             StoreError::MissingDigest(s, d) => Self::MissingDigest(s, d),
+            StoreError::Classified(e) => Self::Classified(e),
             StoreError::Unclassified(s) => Self::Unclassified(s),
         }
     }
 }
 
+impl From<EngineError> for ProcessError {
+    fn from(err: EngineError) -> Self {
+        Self::Classified(err)
+    }
+}
+
 impl From<String> for ProcessError {
     fn from(err: String) -> Self {
         Self::Unclassified(err)
@@ -218,6 +269,18 @@ pub enum ProcessCacheScope {
     Always,
     // Cached in all locations, but only if the process exits successfully.
     Successful,
+    // Cached in memory and in the local cache, but never read from or written to the remote cache,
+    // regardless of success or failure.
+    LocalAlways,
+    // Cached in memory and in the local cache, but never read from or written to the remote cache,
+    // and only if successful.
+    LocalSuccessful,
+    // Cached in all locations, but only written to the local cache (i.e., the remote cache is only
+    // read), regardless of success or failure.
+    RemoteReadOnlyAlways,
+    // Cached in all locations, but only written to the local cache (i.e., the remote cache is only
+    // read), and only if successful.
+    RemoteReadOnlySuccessful,
     // Cached only in memory (i.e. memoized in pantsd), but never persistently, regardless of
     // success vs. failure.
     PerRestartAlways,
@@ -229,12 +292,79 @@ pub enum ProcessCacheScope {
     PerSession,
 }
 
+/// A persistent cache of process results.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CacheLocation {
+    Local,
+    Remote,
+}
+
+impl ProcessCacheScope {
+    ///
+    /// True if a result with the given exit code may be reused (from memory or from a persistent
+    /// cache) under this scope.
+    ///
+    pub fn is_cacheable(self, exit_code: i32) -> bool {
+        match self {
+            ProcessCacheScope::Always
+            | ProcessCacheScope::LocalAlways
+            | ProcessCacheScope::RemoteReadOnlyAlways
+            | ProcessCacheScope::PerRestartAlways => true,
+            ProcessCacheScope::Successful
+            | ProcessCacheScope::LocalSuccessful
+            | ProcessCacheScope::RemoteReadOnlySuccessful
+            | ProcessCacheScope::PerRestartSuccessful => exit_code == 0,
+            ProcessCacheScope::PerSession => false,
+        }
+    }
+
+    ///
+    /// True if results may be read from the given persistent cache under this scope.
+    ///
+    pub fn reads_from(self, location: CacheLocation) -> bool {
+        match self {
+            ProcessCacheScope::Always
+            | ProcessCacheScope::Successful
+            | ProcessCacheScope::RemoteReadOnlyAlways
+            | ProcessCacheScope::RemoteReadOnlySuccessful => true,
+            ProcessCacheScope::LocalAlways | ProcessCacheScope::LocalSuccessful => {
+                location == CacheLocation::Local
+            }
+            ProcessCacheScope::PerRestartAlways
+            | ProcessCacheScope::PerRestartSuccessful
+            | ProcessCacheScope::PerSession => false,
+        }
+    }
+
+    ///
+    /// True if a result with the given exit code may be written to the given persistent cache
+    /// under this scope.
+    ///
+    pub fn writes_to(self, location: CacheLocation, exit_code: i32) -> bool {
+        let writable = match self {
+            ProcessCacheScope::Always | ProcessCacheScope::Successful => true,
+            ProcessCacheScope::LocalAlways
+            | ProcessCacheScope::LocalSuccessful
+            | ProcessCacheScope::RemoteReadOnlyAlways
+            | ProcessCacheScope::RemoteReadOnlySuccessful => location == CacheLocation::Local,
+            ProcessCacheScope::PerRestartAlways
+            | ProcessCacheScope::PerRestartSuccessful
+            | ProcessCacheScope::PerSession => false,
+        };
+        writable && self.is_cacheable(exit_code)
+    }
+}
+
 impl TryFrom<String> for ProcessCacheScope {
     type Error = String;
     fn try_from(variant_candidate: String) -> Result<Self, Self::Error> {
         match variant_candidate.to_lowercase().as_ref() {
             "always" => Ok(ProcessCacheScope::Always),
             "successful" => Ok(ProcessCacheScope::Successful),
+            "local_always" => Ok(ProcessCacheScope::LocalAlways),
+            "local_successful" => Ok(ProcessCacheScope::LocalSuccessful),
+            "remote_read_only_always" => Ok(ProcessCacheScope::RemoteReadOnlyAlways),
+            "remote_read_only_successful" => Ok(ProcessCacheScope::RemoteReadOnlySuccessful),
             "per_restart_always" => Ok(ProcessCacheScope::PerRestartAlways),
             "per_restart_successful" => Ok(ProcessCacheScope::PerRestartSuccessful),
             "per_session" => Ok(ProcessCacheScope::PerSession),
@@ -479,6 +609,119 @@ pub struct ProcessExecutionEnvironment {
     pub strategy: ProcessExecutionStrategy,
 }
 
+/// The encoding of the requests and responses exchanged with a persistent worker.
+#[derive(DeepSizeOf, Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize)]
+pub enum WorkerProtocol {
+    /// Newline-delimited JSON `WorkRequest` and `WorkResponse` objects.
+    Json,
+    /// Length-delimited `blaze.worker.WorkRequest` and `WorkResponse` protobuf messages.
+    Proto,
+}
+
+impl TryFrom<String> for WorkerProtocol {
+    type Error = String;
+    fn try_from(variant_candidate: String) -> Result<Self, Self::Error> {
+        match variant_candidate.to_lowercase().as_ref() {
+            "json" => Ok(WorkerProtocol::Json),
+            "proto" => Ok(WorkerProtocol::Proto),
+            other => Err(format!("Unknown persistent worker protocol: {other:?}")),
+        }
+    }
+}
+
+///
+/// Declares that a Process may be run as a request to a long-lived worker process which implements
+/// the (Bazel-compatible) persistent worker protocol, rather than by spawning its `argv` directly.
+///
+/// The `argv` of the Process must still be runnable directly, since Processes which are not run
+/// locally (or whose runner does not support workers) will ignore this declaration.
+///
+#[derive(DeepSizeOf, Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct PersistentWorker {
+    /// Workers are shared between Processes which have the same key and the same startup
+    /// arguments, environment and immutable inputs.
+    pub key: String,
+    pub protocol: WorkerProtocol,
+    /// The index in `argv` at which the per-request arguments begin: arguments before this index
+    /// are used to start the worker, and arguments from it onward are sent in each request.
+    pub request_args_start: usize,
+    /// True if the worker may be sent multiple concurrent requests.
+    pub multiplex: bool,
+}
+
+///
+/// Declares output directories of a Process which hold the incremental state of a tool (e.g. the
+/// `.tsbuildinfo` file of `tsc --incremental`). Before the Process runs, the directories are
+/// pre-populated (as inputs) with their content from the previous successful run of a Process with
+/// the same `key`, tool fingerprints and immutable inputs: see `ExecuteProcess::run_node`.
+///
+/// Since the previous state is an input, the Process remains sandboxed, but it will usually miss
+/// the cache when its state has changed.
+///
+#[derive(DeepSizeOf, Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct IncrementalOutputs {
+    /// Processes which share state have the same key: typically the name of the tool and of the
+    /// project which it is run for.
+    pub key: String,
+    /// The directories (which must also be `output_directories` of the Process) to pre-populate.
+    pub directories: BTreeSet<RelativePath>,
+}
+
+/// The failures of a Process which cause it to be retried: see `ProcessRetryPolicy`.
+#[derive(DeepSizeOf, Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub enum RetryOn {
+    /// Any non-zero exit code, or a failure to run the process at all.
+    AnyFailure,
+    /// One of the given exit codes, or a failure to run the process at all.
+    ExitCodes(BTreeSet<i32>),
+    /// Only a failure to run the process at all (i.e., an error in the runner or its
+    /// infrastructure, rather than in the process itself).
+    InfrastructureErrors,
+}
+
+///
+/// Declares that a Process which fails in some way should be attempted again, up to a maximum
+/// number of attempts. Retries happen beneath the caches (see `retry::CommandRunner`), so only the
+/// result of the final attempt is cached.
+///
+#[derive(DeepSizeOf, Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct ProcessRetryPolicy {
+    /// The maximum number of attempts, including the first.
+    pub max_attempts: usize,
+    pub retry_on: RetryOn,
+    /// The delay before the second attempt, which doubles before each subsequent attempt.
+    pub backoff: std::time::Duration,
+}
+
+impl ProcessRetryPolicy {
+    ///
+    /// True if the given result of an attempt should be retried (assuming that attempts remain).
+    ///
+    pub fn should_retry(
+        &self,
+        result: &Result<FallibleProcessResultWithPlatform, ProcessError>,
+    ) -> bool {
+        match (result, &self.retry_on) {
+            // A missing digest is recovered from by backtracking, rather than by retrying.
+            (Err(ProcessError::MissingDigest(..)), _) => false,
+            // Errors caused by invalid inputs will fail in the same way if retried.
+            (Err(ProcessError::Classified(e)), _) => e.category() != ErrorCategory::User,
+            (Err(ProcessError::Unclassified(_)), _) => true,
+            (Ok(result), RetryOn::AnyFailure) => result.exit_code != 0,
+            (Ok(result), RetryOn::ExitCodes(exit_codes)) => exit_codes.contains(&result.exit_code),
+            (Ok(_), RetryOn::InfrastructureErrors) => false,
+        }
+    }
+
+    ///
+    /// The delay before the given (1-indexed) attempt.
+    ///
+    pub fn backoff_before(&self, attempt: usize) -> std::time::Duration {
+        let exponent = attempt.saturating_sub(2).min(16) as u32;
+        self.backoff.saturating_mul(2_u32.pow(exponent))
+    }
+}
+
 ///
 /// A process to be executed.
 ///
@@ -505,6 +748,22 @@ pub struct Process {
     ///
     pub env: BTreeMap<String, String>,
 
+    ///
+    /// Glob patterns matching the names of environment variables to set for the execution, which
+    /// are resolved against the environment of the execution environment when the process runs:
+    /// see `resolve_env_globs`.
+    ///
+    pub env_globs: BTreeSet<String>,
+
+    ///
+    /// The environment variables matched by `env_globs`, which are set in addition to `env`.
+    ///
+    /// Only the names of these variables are a part of the cache key of the process, and so their
+    /// values are never serialized.
+    ///
+    #[serde(skip)]
+    pub globbed_env: BTreeMap<String, String>,
+
     ///
     /// A relative path to a directory existing in the `input_files` digest to execute the process
     /// from. Defaults to the `input_files` root.
@@ -520,6 +779,15 @@ pub struct Process {
 
     pub output_directories: BTreeSet<RelativePath>,
 
+    ///
+    /// Globs (relative to the working directory) of outputs to capture, for tools whose output
+    /// filenames cannot be predicted in advance.
+    ///
+    /// Runners which cannot expand globs capture the literal parent directory of each glob (see
+    /// `output_glob_roots`), and then subset it (see `subset_output_globs`).
+    ///
+    pub output_globs: BTreeSet<String>,
+
     pub timeout: Option<std::time::Duration>,
 
     /// If not None, then a bounded::CommandRunner executing this Process will set an environment
@@ -559,6 +827,21 @@ pub struct Process {
     ///
     pub append_only_caches: BTreeMap<CacheName, RelativePath>,
 
+    ///
+    /// Digests with which to seed the given (declared) `append_only_caches` if they are empty
+    /// before first use: for example, with a prewarmed package manager store. Seeding is handled by
+    /// `NamedCaches`, and so only applies to caches in the local filesystem.
+    ///
+    /// NB: Like the caches themselves, seeds are not a part of the cache key of the process.
+    ///
+    pub append_only_cache_seeds: BTreeMap<CacheName, DirectoryDigest>,
+
+    ///
+    /// If set, some of the `output_directories` of this process are pre-populated with their
+    /// content from a previous run: see `IncrementalOutputs`.
+    ///
+    pub incremental_outputs: Option<IncrementalOutputs>,
+
     ///
     /// If present, a symlink will be created at .jdk which points to this directory for local
     /// execution, or a system-installed JDK (ignoring the value of the present Some) for remote
@@ -571,10 +854,57 @@ pub struct Process {
 
     pub cache_scope: ProcessCacheScope,
 
+    ///
+    /// Named fingerprints (usually versions or content hashes) of the tools that this process runs,
+    /// which are included in its cache key without affecting its argv or environment. Changing a
+    /// fingerprint invalidates the cached results of only the processes which declare it.
+    ///
+    /// See `tool_fingerprints` to recover the fingerprints which contributed to a cache key.
+    ///
+    pub tool_fingerprints: BTreeMap<String, String>,
+
     pub execution_environment: ProcessExecutionEnvironment,
 
     pub remote_cache_speculation_delay: std::time::Duration,
 
+    ///
+    /// If set, overrides the configured REAPI `ExecutionPolicy.priority` for this process when it
+    /// is run remotely. Lower values are more urgent, and 0 is the server's default priority.
+    ///
+    pub remote_execution_priority: Option<i32>,
+
+    ///
+    /// If set, overrides the configured REAPI `ResultsCachePolicy.priority` for this process when it
+    /// is run remotely.
+    ///
+    pub remote_results_cache_priority: Option<i32>,
+
+    ///
+    /// If set, and the Process is run locally, it will be run as a request to a persistent worker.
+    ///
+    pub persistent_worker: Option<PersistentWorker>,
+
+    ///
+    /// If set, a (cheap) command which is run against the inputs of this Process and the outputs of
+    /// a cached result in order to revalidate cached results which are older than the configured
+    /// maximum age. See `revalidate_cached_result`.
+    ///
+    pub cache_validation_argv: Option<Vec<String>>,
+
+    ///
+    /// If set, the digest of a file whose content is provided to the process on stdin. Otherwise,
+    /// stdin is empty.
+    ///
+    /// Runners which cannot pipe stdin to a process directly expose the file in the input root and
+    /// redirect it via a wrapper: see `redirect_stdin_from_input_root`.
+    ///
+    pub stdin_digest: Option<Digest>,
+
+    ///
+    /// If set, failed attempts to run this process are retried according to the policy.
+    ///
+    pub retry_policy: Option<ProcessRetryPolicy>,
+
     ///
     /// The attempt number, in the case this Process is being retried.
     ///
@@ -599,24 +929,36 @@ impl Process {
         Process {
             argv,
             env: BTreeMap::new(),
+            env_globs: BTreeSet::new(),
+            globbed_env: BTreeMap::new(),
             working_directory: None,
             input_digests: InputDigests::default(),
             output_files: BTreeSet::new(),
             output_directories: BTreeSet::new(),
+            output_globs: BTreeSet::new(),
             timeout: None,
             description: "".to_string(),
             level: log::Level::Info,
             append_only_caches: BTreeMap::new(),
+            append_only_cache_seeds: BTreeMap::new(),
+            incremental_outputs: None,
             jdk_home: None,
             execution_slot_variable: None,
             concurrency_available: 0,
             cache_scope: ProcessCacheScope::Successful,
+            tool_fingerprints: BTreeMap::new(),
             execution_environment: ProcessExecutionEnvironment {
                 name: None,
                 platform: Platform::current().unwrap(),
                 strategy: ProcessExecutionStrategy::Local,
             },
             remote_cache_speculation_delay: std::time::Duration::from_millis(0),
+            remote_execution_priority: None,
+            remote_results_cache_priority: None,
+            persistent_worker: None,
+            cache_validation_argv: None,
+            stdin_digest: None,
+            retry_policy: None,
             attempt: 0,
         }
     }
@@ -629,6 +971,14 @@ impl Process {
         self
     }
 
+    ///
+    /// Replaces the environment variable globs for this process.
+    ///
+    pub fn env_globs(mut self, env_globs: BTreeSet<String>) -> Process {
+        self.env_globs = env_globs;
+        self
+    }
+
     ///
     /// Replaces the working_directory for this process.
     ///
@@ -653,6 +1003,14 @@ impl Process {
         self
     }
 
+    ///
+    /// Replaces the output globs for this process.
+    ///
+    pub fn output_globs(mut self, output_globs: BTreeSet<String>) -> Process {
+        self.output_globs = output_globs;
+        self
+    }
+
     ///
     /// Replaces the append only caches for this process.
     ///
@@ -664,6 +1022,17 @@ impl Process {
         self
     }
 
+    ///
+    /// Replaces the append only cache seeds for this process.
+    ///
+    pub fn append_only_cache_seeds(
+        mut self,
+        append_only_cache_seeds: BTreeMap<CacheName, DirectoryDigest>,
+    ) -> Process {
+        self.append_only_cache_seeds = append_only_cache_seeds;
+        self
+    }
+
     ///
     /// Set the execution environment to Docker, with the specified image.
     ///
@@ -697,6 +1066,52 @@ impl Process {
         self.cache_scope = cache_scope;
         self
     }
+
+    pub fn tool_fingerprints(mut self, tool_fingerprints: BTreeMap<String, String>) -> Process {
+        self.tool_fingerprints = tool_fingerprints;
+        self
+    }
+
+    pub fn persistent_worker(mut self, persistent_worker: PersistentWorker) -> Process {
+        self.persistent_worker = Some(persistent_worker);
+        self
+    }
+
+    pub fn stdin_digest(mut self, stdin_digest: Digest) -> Process {
+        self.stdin_digest = Some(stdin_digest);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: ProcessRetryPolicy) -> Process {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    ///
+    /// Resolves the `env_globs` of this process against the given environment, setting
+    /// `globbed_env` to the matching variables which are not already set explicitly in `env`.
+    ///
+    pub fn resolve_env_globs(
+        &mut self,
+        environment: &BTreeMap<String, String>,
+    ) -> Result<(), String> {
+        let patterns = self
+            .env_globs
+            .iter()
+            .map(|env_glob| {
+                glob::Pattern::new(env_glob)
+                    .map_err(|e| format!("Invalid `env_globs` pattern `{env_glob}`: {e}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.globbed_env = environment
+            .iter()
+            .filter(|(name, _)| {
+                !self.env.contains_key(*name) && patterns.iter().any(|p| p.matches(name))
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        Ok(())
+    }
 }
 
 ///
@@ -724,6 +1139,18 @@ pub struct ProcessResultMetadata {
     ///
     /// NB: This is optional because the REAPI does not guarantee that it is returned.
     pub total_elapsed: Option<Duration>,
+    /// The time that this process spent queued before a worker began to run it, if it was
+    /// reported by a remote execution server.
+    ///
+    /// Corresponds to `queued_timestamp` and `worker_start_timestamp` from
+    /// `ExecutedActionMetadata`.
+    pub queue_elapsed: Option<Duration>,
+    /// The time that this process spent executing, excluding the overhead of fetching its inputs
+    /// and uploading its outputs, if it was reported by a remote execution server.
+    ///
+    /// Corresponds to `execution_start_timestamp` and `execution_completed_timestamp` from
+    /// `ExecutedActionMetadata`.
+    pub execution_elapsed: Option<Duration>,
     /// How much faster a cache hit was than running the process again.
     ///
     /// This includes the overhead of setting up and cleaning up the process for execution, and it
@@ -743,6 +1170,30 @@ pub struct ProcessResultMetadata {
     /// The RunId of the Session in which the `ProcessResultSource` was accurate. In further runs
     /// within the same process, the source of the process implicitly becomes memoization.
     pub source_run_id: RunId,
+    /// The cache scope which the caching CommandRunners applied to this result, or None if the
+    /// result did not pass through a persistent cache.
+    pub cache_scope: Option<ProcessCacheScope>,
+    /// The time since the outputs of this result were stored, if known. For cache hits, this is the
+    /// age of the cache entry: see `metadata_for_cache`.
+    pub cache_entry_age: Option<Duration>,
+    /// The number of attempts which were made to produce this result, including the first: see
+    /// `ProcessRetryPolicy`.
+    pub attempts: usize,
+    /// The sandbox which was preserved after the process ran, if any: see `KeepSandboxes`.
+    ///
+    /// NB: This is only set for the run which actually executed the process: a cache hit for the
+    /// same process will not have a sandbox.
+    pub retained_sandbox: Option<RetainedSandbox>,
+}
+
+///
+/// A sandbox which was preserved for inspection after its process ran.
+///
+#[derive(Clone, Debug, DeepSizeOf, Eq, PartialEq)]
+pub struct RetainedSandbox {
+    pub path: PathBuf,
+    /// A shell command which will re-run the process in the sandbox.
+    pub repro_command: String,
 }
 
 impl ProcessResultMetadata {
@@ -754,10 +1205,16 @@ impl ProcessResultMetadata {
     ) -> Self {
         Self {
             total_elapsed,
+            queue_elapsed: None,
+            execution_elapsed: None,
             saved_by_cache: None,
             source,
             environment,
             source_run_id,
+            cache_scope: None,
+            cache_entry_age: None,
+            attempts: 1,
+            retained_sandbox: None,
         }
     }
 
@@ -767,19 +1224,48 @@ impl ProcessResultMetadata {
         environment: ProcessExecutionEnvironment,
         source_run_id: RunId,
     ) -> Self {
-        let total_elapsed = match (
-            metadata.worker_start_timestamp,
-            metadata.worker_completed_timestamp,
-        ) {
-            (Some(started), Some(completed)) => {
-                TimeSpan::from_start_and_end(&started, &completed, "")
-                    .map(|span| span.duration)
-                    .ok()
-            }
-            _ => None,
-        };
+        fn elapsed(
+            start: &Option<prost_types::Timestamp>,
+            end: &Option<prost_types::Timestamp>,
+        ) -> Option<Duration> {
+            let (start, end) = (start.as_ref()?, end.as_ref()?);
+            TimeSpan::from_start_and_end(start, end, "")
+                .map(|span| span.duration)
+                .ok()
+        }
+        let total_elapsed = elapsed(
+            &metadata.worker_start_timestamp,
+            &metadata.worker_completed_timestamp,
+        );
+        let queue_elapsed = elapsed(&metadata.queued_timestamp, &metadata.worker_start_timestamp);
+        let execution_elapsed = elapsed(
+            &metadata.execution_start_timestamp,
+            &metadata.execution_completed_timestamp,
+        );
+        let cache_entry_age = metadata
+            .output_upload_completed_timestamp
+            .and_then(|stored| std::time::SystemTime::try_from(stored).ok())
+            .and_then(|stored| stored.elapsed().ok())
+            .map(Duration::from);
+
+        Self {
+            queue_elapsed,
+            execution_elapsed,
+            cache_entry_age,
+            ..Self::new(total_elapsed, source, environment, source_run_id)
+        }
+    }
 
-        Self::new(total_elapsed, source, environment, source_run_id)
+    ///
+    /// True if this result was loaded from a cache entry which is at least as old as the given
+    /// maximum age (or whose age is unknown), and so must be revalidated before it is used.
+    ///
+    pub fn is_stale(&self, max_age: Option<std::time::Duration>) -> bool {
+        match (max_age, self.cache_entry_age) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(max_age), Some(age)) => std::time::Duration::from(age) >= max_age,
+        }
     }
 
     pub fn update_cache_hit_elapsed(&mut self, cache_hit_elapsed: std::time::Duration) {
@@ -844,6 +1330,191 @@ pub enum CacheContentBehavior {
     Defer,
 }
 
+///
+/// Converts the given metadata for storage in a cache, recording the current time as the time at
+/// which the outputs were stored, so that the age of the cache entry can be computed when it is hit.
+///
+pub fn metadata_for_cache(metadata: &ProcessResultMetadata) -> ExecutedActionMetadata {
+    ExecutedActionMetadata {
+        output_upload_completed_timestamp: Some(std::time::SystemTime::now().into()),
+        ..metadata.clone().into()
+    }
+}
+
+///
+/// Revalidates a cached result which is stale (see `ProcessResultMetadata::is_stale`), returning
+/// true if it may be used.
+///
+/// All of the outputs of the result must exist in the Store. If the Process declares a
+/// `cache_validation_argv`, that command is additionally run (using the given CommandRunner) with
+/// the inputs of the Process and the outputs of the result, and must exit successfully.
+///
+pub async fn revalidate_cached_result(
+    runner: &dyn CommandRunner,
+    context: Context,
+    store: &Store,
+    request: &Process,
+    result: &FallibleProcessResultWithPlatform,
+) -> Result<bool, ProcessError> {
+    if !check_cache_content(result, store, CacheContentBehavior::Validate).await? {
+        return Ok(false);
+    }
+    let Some(argv) = request.cache_validation_argv.clone() else {
+        return Ok(true);
+    };
+
+    let inputs = store
+        .merge(vec![
+            request.input_digests.inputs.clone(),
+            result.output_directory.clone(),
+        ])
+        .await?;
+    let input_digests = InputDigests::new(
+        store,
+        inputs,
+        request.input_digests.immutable_inputs.clone(),
+        request.input_digests.use_nailgun.clone(),
+    )
+    .await?;
+    let validation_request = Process {
+        argv,
+        input_digests,
+        output_files: BTreeSet::new(),
+        output_directories: BTreeSet::new(),
+        output_globs: BTreeSet::new(),
+        description: format!("Revalidate cached result for: {}", request.description),
+        level: log::Level::Debug,
+        cache_scope: ProcessCacheScope::PerSession,
+        persistent_worker: None,
+        cache_validation_argv: None,
+        stdin_digest: None,
+        retry_policy: None,
+        ..request.clone()
+    };
+    in_workunit!(
+        "revalidate_cached_result",
+        Level::Debug,
+        desc = Some(validation_request.description.clone()),
+        |workunit| async move {
+            let validation_result = runner.run(context, workunit, validation_request).await?;
+            Ok(validation_result.exit_code == 0)
+        }
+    )
+    .await
+}
+
+///
+/// Returns the directories which must be captured in order to expand the given output globs: the
+/// longest prefix of each glob which does not contain a wildcard.
+///
+pub fn output_glob_roots(
+    output_globs: &BTreeSet<String>,
+) -> Result<BTreeSet<RelativePath>, String> {
+    output_globs
+        .iter()
+        .filter(|glob| !glob.starts_with('!'))
+        .map(|glob| {
+            let components = glob.split('/').collect::<Vec<_>>();
+            // NB: The final component of a glob without wildcards might be a file, so it is never
+            // included.
+            let literal = components
+                .iter()
+                .take(components.len() - 1)
+                .take_while(|c| !c.contains(['*', '?', '[']))
+                .count();
+            RelativePath::new(components[..literal].join("/"))
+                .map_err(|e| format!("Invalid output glob `{glob}`: {e}"))
+        })
+        .collect()
+}
+
+///
+/// Returns globs which match all of the declared outputs of the given process: its output files,
+/// output directories (recursively), and output globs.
+///
+pub fn output_capture_globs(
+    output_files: &BTreeSet<RelativePath>,
+    output_directories: &BTreeSet<RelativePath>,
+    output_globs: &BTreeSet<String>,
+) -> Result<Vec<String>, String> {
+    output_directories
+        .iter()
+        .flat_map(|p| {
+            let mut dir_glob = {
+                let mut dir = p.to_path_buf().into_os_string();
+                if dir.is_empty() {
+                    dir.push(".")
+                }
+                dir
+            };
+            let dir = dir_glob.clone();
+            dir_glob.push("/**");
+            vec![dir, dir_glob]
+        })
+        .chain(
+            output_files
+                .iter()
+                .map(|p| p.to_path_buf().into_os_string()),
+        )
+        .map(|s| {
+            s.into_string()
+                .map_err(|e| format!("Error stringifying output paths: {e:?}"))
+        })
+        .chain(output_globs.iter().cloned().map(Ok))
+        .collect()
+}
+
+///
+/// For a runner which captured the `output_glob_roots` of a process rather than expanding its
+/// output globs, subsets the output directory of the result to only the declared outputs.
+///
+pub async fn subset_output_globs(
+    store: &Store,
+    req: &Process,
+    mut result: FallibleProcessResultWithPlatform,
+) -> Result<FallibleProcessResultWithPlatform, StoreError> {
+    if req.output_globs.is_empty() {
+        return Ok(result);
+    }
+    let globs = PreparedPathGlobs::create(
+        output_capture_globs(
+            &req.output_files,
+            &req.output_directories,
+            &req.output_globs,
+        )?,
+        StrictGlobMatching::Ignore,
+        GlobExpansionConjunction::AllMatch,
+    )?;
+    result.output_directory = store
+        .subset(result.output_directory, SubsetParams { globs })
+        .await?;
+    Ok(result)
+}
+
+///
+/// Validates that the `working_directory` of the given Process (if any) is a directory in its
+/// input root, so that a missing directory is reported clearly rather than as a failure to spawn.
+///
+pub async fn validate_working_directory(store: &Store, req: &Process) -> Result<(), ProcessError> {
+    let Some(working_directory) = &req.working_directory else {
+        return Ok(());
+    };
+    let input_root = store
+        .load_digest_trie(req.input_digests.complete.clone())
+        .await?;
+    match input_root.entry(working_directory) {
+        // NB: A symlink may only be followed at runtime.
+        Ok(Some(fs::Entry::Directory(_) | fs::Entry::Symlink(_))) => Ok(()),
+        _ => Err(format!(
+            "The working directory `{}` of process `{}` is not a directory in the input digest of \
+             the process.",
+            working_directory.display(),
+            req.description
+        )
+        .into()),
+    }
+}
+
 ///
 /// Optionally validate that all digests in the result are loadable, returning false if any are not.
 ///
@@ -1063,6 +1734,60 @@ fn make_wrapper_for_append_only_caches(
     Ok(script)
 }
 
+/// The path in the input root at which `redirect_stdin_from_input_root` exposes the stdin of a
+/// Process.
+pub const STDIN_FILE: &str = "__pants_stdin__";
+
+///
+/// If the given Process has a `stdin_digest`, returns an equivalent Process which instead
+/// exposes the stdin file in its input root and redirects it to the original command via a shell
+/// wrapper. For use by runners which cannot pipe stdin to a process directly.
+///
+pub async fn redirect_stdin_from_input_root(
+    store: &Store,
+    mut req: Process,
+) -> Result<Process, String> {
+    let Some(stdin_digest) = req.stdin_digest.take() else {
+        return Ok(req);
+    };
+
+    let stdin_snapshot = store
+        .snapshot_of_one_file(RelativePath::new(STDIN_FILE)?, stdin_digest, false)
+        .await?;
+    let inputs = store
+        .merge(vec![
+            req.input_digests.inputs.clone(),
+            DirectoryDigest::new(stdin_snapshot.digest, stdin_snapshot.tree),
+        ])
+        .await
+        .map_err(|e| format!("Failed to add stdin to the inputs of the process: {e}"))?;
+    req.input_digests = InputDigests::new(
+        store,
+        inputs,
+        req.input_digests.immutable_inputs.clone(),
+        req.input_digests.use_nailgun.clone(),
+    )
+    .await
+    .map_err(|e| format!("Failed to add stdin to the inputs of the process: {e}"))?;
+
+    // The stdin file is relative to the input root, rather than to the working directory.
+    let stdin_path = req
+        .working_directory
+        .iter()
+        .flat_map(|working_directory| working_directory.components())
+        .fold(PathBuf::new(), |path, _| path.join(".."))
+        .join(STDIN_FILE);
+    let mut argv = vec![
+        "/bin/sh".to_owned(),
+        "-c".to_owned(),
+        "exec \"$@\" < \"$0\"".to_owned(),
+        stdin_path.to_string_lossy().into_owned(),
+    ];
+    argv.append(&mut req.argv);
+    req.argv = argv;
+    Ok(req)
+}
+
 pub async fn make_execute_request(
     req: &Process,
     instance_name: Option<String>,
@@ -1072,6 +1797,10 @@ pub async fn make_execute_request(
 ) -> Result<EntireExecuteRequest, String> {
     const WRAPPER_SCRIPT: &str = "./__pants_wrapper__";
 
+    // Remote execution has no equivalent to stdin, so it is provided as an input file instead. This
+    // also ensures that the stdin of the process is a part of its cache key.
+    let req = &redirect_stdin_from_input_root(store, req.clone()).await?;
+
     // Implement append-only caches by running a wrapper script before the actual program
     // to be invoked in the remote environment.
     let wrapper_script_digest_opt = match (append_only_caches_base_path, &req.append_only_caches) {
@@ -1114,6 +1843,9 @@ pub async fn make_execute_request(
         if name == CACHE_KEY_GEN_VERSION_ENV_VAR_NAME
             || name == CACHE_KEY_TARGET_PLATFORM_ENV_VAR_NAME
             || name == CACHE_KEY_SALT_ENV_VAR_NAME
+            || name == CACHE_KEY_OUTPUT_GLOBS_ENV_VAR_NAME
+            || name == CACHE_KEY_TOOL_FINGERPRINTS_ENV_VAR_NAME
+            || name == CACHE_KEY_ENV_GLOBS_ENV_VAR_NAME
         {
             return Err(format!(
                 "Cannot set env var with name {name} as that is reserved for internal use by pants"
@@ -1172,6 +1904,35 @@ pub async fn make_execute_request(
             });
     }
 
+    if !req.output_globs.is_empty() {
+        command
+            .environment_variables
+            .push(remexec::command::EnvironmentVariable {
+                name: CACHE_KEY_OUTPUT_GLOBS_ENV_VAR_NAME.to_string(),
+                value: req.output_globs.iter().join("\n"),
+            });
+    }
+
+    if !req.tool_fingerprints.is_empty() {
+        command
+            .environment_variables
+            .push(remexec::command::EnvironmentVariable {
+                name: CACHE_KEY_TOOL_FINGERPRINTS_ENV_VAR_NAME.to_string(),
+                value: encode_tool_fingerprints(&req.tool_fingerprints)?,
+            });
+    }
+
+    if !req.env_globs.is_empty() {
+        // NB: Only the names of the matched variables are included, so that secrets do not leak
+        // into the cache key (or to a remote cache).
+        command
+            .environment_variables
+            .push(remexec::command::EnvironmentVariable {
+                name: CACHE_KEY_ENV_GLOBS_ENV_VAR_NAME.to_string(),
+                value: req.globbed_env.keys().join("\n"),
+            });
+    }
+
     let mut output_files = req
         .output_files
         .iter()
@@ -1184,9 +1945,13 @@ pub async fn make_execute_request(
     output_files.sort();
     command.output_files = output_files;
 
+    // Output globs are emulated by capturing their literal parent directories, which are subset by
+    // the runner once the outputs have been fetched.
     let mut output_directories = req
         .output_directories
         .iter()
+        .chain(output_glob_roots(&req.output_globs)?.iter())
+        .unique()
         .map(|p| {
             p.to_str()
                 .map(str::to_owned)
@@ -1274,6 +2039,13 @@ pub async fn make_execute_request(
     let mut action = remexec::Action {
         command_digest: Some((&digest(&command)?).into()),
         input_root_digest: Some(input_root_digest.as_digest().into()),
+        // Results of processes which may only be cached locally must not be cached by the server
+        // either. This only applies to the `Local*` scopes: the `RemoteReadOnly*` scopes read from
+        // the remote cache by action digest, and so must not change it.
+        do_not_cache: matches!(
+            req.cache_scope,
+            ProcessCacheScope::LocalAlways | ProcessCacheScope::LocalSuccessful
+        ),
         ..remexec::Action::default()
     };
 
@@ -1300,6 +2072,98 @@ pub async fn make_execute_request(
     })
 }
 
+///
+/// Encodes the `tool_fingerprints` of a Process as one `name=fingerprint` line per tool.
+///
+fn encode_tool_fingerprints(
+    tool_fingerprints: &BTreeMap<String, String>,
+) -> Result<String, String> {
+    for (name, fingerprint) in tool_fingerprints {
+        if name.is_empty() || name.contains(['=', '\n']) || fingerprint.contains('\n') {
+            return Err(format!(
+                "Invalid tool fingerprint {name:?}: {fingerprint:?}. Tool names must be non-empty \
+                 and may not contain `=` or newlines, and fingerprints may not contain newlines."
+            ));
+        }
+    }
+    Ok(tool_fingerprints
+        .iter()
+        .map(|(name, fingerprint)| format!("{name}={fingerprint}"))
+        .join("\n"))
+}
+
+///
+/// Returns the `output_globs` which contributed to the cache key of the given Command.
+///
+pub fn output_globs(command: &Command) -> BTreeSet<String> {
+    command
+        .environment_variables
+        .iter()
+        .filter(|env| env.name == CACHE_KEY_OUTPUT_GLOBS_ENV_VAR_NAME)
+        .flat_map(|env| env.value.lines())
+        .map(str::to_owned)
+        .collect()
+}
+
+///
+/// Returns `env_globs` which match exactly the (globbed) environment variable names that
+/// contributed to the cache key of the given Command: the original globs are not recorded.
+///
+pub fn env_globs(command: &Command) -> BTreeSet<String> {
+    command
+        .environment_variables
+        .iter()
+        .filter(|env| env.name == CACHE_KEY_ENV_GLOBS_ENV_VAR_NAME)
+        .flat_map(|env| env.value.lines())
+        .map(glob::Pattern::escape)
+        .collect()
+}
+
+///
+/// Returns the `tool_fingerprints` which contributed to the cache key of the given Command.
+///
+pub fn tool_fingerprints(command: &Command) -> BTreeMap<String, String> {
+    command
+        .environment_variables
+        .iter()
+        .filter(|env| env.name == CACHE_KEY_TOOL_FINGERPRINTS_ENV_VAR_NAME)
+        .flat_map(|env| env.value.lines())
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, fingerprint)| (name.to_owned(), fingerprint.to_owned()))
+        .collect()
+}
+
+///
+/// Returns the `tool_fingerprints` which contributed to the given cache key (i.e. the digest of an
+/// Action), or None if the Action or its Command are not present in the Store. Actions are stored
+/// when they are run remotely, or when their results are written to a remote cache.
+///
+pub async fn tool_fingerprints_for_action(
+    store: &Store,
+    action_digest: Digest,
+) -> Result<Option<BTreeMap<String, String>>, String> {
+    let action = match store
+        .load_file_bytes_with(action_digest, |bytes| Action::decode(bytes))
+        .await
+    {
+        Ok(action) => action
+            .map_err(|e| format!("Error deserializing Action proto {action_digest:?}: {e}"))?,
+        Err(StoreError::MissingDigest(..)) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    let command_digest = require_digest(&action.command_digest)?;
+    let command = match store
+        .load_file_bytes_with(command_digest, |bytes| Command::decode(bytes))
+        .await
+    {
+        Ok(command) => command
+            .map_err(|e| format!("Error deserializing Command proto {command_digest:?}: {e}"))?,
+        Err(StoreError::MissingDigest(..)) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    Ok(Some(tool_fingerprints(&command)))
+}
+
 /// Convert an ActionResult into a FallibleProcessResultWithPlatform.
 ///
 /// HACK: The caching CommandRunner stores the digest of the Directory that merges all output
@@ -1437,17 +2301,13 @@ pub fn extract_output_files(
                 // of the `Directory` protos for child directories of that root.
 
                 // Retrieve the Tree proto and hash its root `Directory` proto to obtain the digest
-                // of the output directory needed to construct the series of `Directory` protos needed
-                // for the final merge of the output directories.
+                // of the output directory. The remainder of the Tree is only expanded if that
+                // root has not already been persisted locally.
                 let tree_digest: Digest = require_digest(dir.tree_digest.as_ref())?;
-                let directory_digest = store
-                    .load_tree_from_remote(tree_digest)
-                    .await?
-                    .ok_or_else(|| format!("Tree with digest {tree_digest:?} was not in remote"))?;
-
                 store
-                    .add_prefix(directory_digest, &RelativePath::new(dir.path)?)
-                    .await
+                    .load_tree_from_remote_lazily(tree_digest, &RelativePath::new(dir.path)?)
+                    .await?
+                    .ok_or_else(|| format!("Tree with digest {tree_digest:?} was not in remote"))
             })
             .map_err(|err| format!("Error saving remote output directory to local cache: {err}")),
         );
@@ -1518,6 +2378,14 @@ pub fn extract_output_files(
 
         directory_digests.push(files_snapshot.into());
 
+        // Avoid merging (and thus loading) a lone output directory.
+        directory_digests.retain(|digest| *digest != *EMPTY_DIRECTORY_DIGEST);
+        if directory_digests.len() <= 1 {
+            return Ok(directory_digests
+                .pop()
+                .unwrap_or_else(|| EMPTY_DIRECTORY_DIGEST.clone()));
+        }
+
         store
             .merge(directory_digests)
             .map_err(|err| err.enrich("Error when merging output files and directories"))
@@ -3,8 +3,6 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::{self, Debug};
 use std::io::Write;
-use std::ops::Neg;
-use std::os::unix::{fs::OpenOptionsExt, process::ExitStatusExt};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::str;
@@ -12,7 +10,8 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use async_trait::async_trait;
-use bytes::{Bytes, BytesMut};
+use bytes::Bytes;
+use engine_error::{EngineError, ErrorCode};
 use fs::{
     self, DigestTrie, DirectoryDigest, GlobExpansionConjunction, GlobMatching, PathGlobs,
     Permissions, RelativePath, StrictGlobMatching, SymlinkBehavior, TypedPath,
@@ -20,29 +19,42 @@ use fs::{
 };
 use futures::stream::{BoxStream, StreamExt, TryStreamExt};
 use futures::{try_join, FutureExt, TryFutureExt};
+use hashing::Digest;
 use log::{debug, info};
 use nails::execution::ExitCode;
 use shell_quote::bash;
+use stdio::{CapturedOutput, OutputCapture};
 use store::{
     ImmutableInputs, OneOffStoreFileByDigest, Snapshot, SnapshotOps, Store, StoreError,
     WorkdirSymlink,
 };
 use task_executor::Executor;
 use tempfile::TempDir;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
 use tokio::sync::RwLock;
 use tokio::time::timeout;
 use tokio_util::codec::{BytesCodec, FramedRead};
-use workunit_store::{in_workunit, Level, Metric, RunningWorkunit};
+use uuid::Uuid;
+use workunit_store::{
+    get_workunit_store_handle, in_workunit, ArtifactOutput, Level, Metric, OutputStream,
+    ProcessOutputSink, RunningWorkunit, SpanId, UserMetadataItem, WaitingOn,
+};
 
 use crate::fork_exec::spawn_process;
+use crate::sandbox_exec::SandboxExec;
 use crate::{
-    Context, FallibleProcessResultWithPlatform, ManagedChild, NamedCaches, Process, ProcessError,
-    ProcessResultMetadata, ProcessResultSource,
+    children, output_capture_globs, validate_working_directory, Context,
+    FallibleProcessResultWithPlatform, ManagedChild, NamedCaches, Process, ProcessError,
+    ProcessResultMetadata, ProcessResultSource, RetainedSandbox,
 };
 
 pub const USER_EXECUTABLE_MODE: u32 = 0o100755;
 
+/// The maximum number of bytes of each of stdout and stderr of a process to hold in memory while it
+/// runs. Output beyond this is spilled to disk, and then to the Store.
+pub const MAX_BUFFERED_OUTPUT_BYTES: usize = 1024 * 1024;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, strum_macros::EnumString)]
 #[strum(serialize_all = "snake_case")]
 pub enum KeepSandboxes {
@@ -58,6 +70,7 @@ pub struct CommandRunner {
     named_caches: NamedCaches,
     immutable_inputs: ImmutableInputs,
     keep_sandboxes: KeepSandboxes,
+    sandbox_exec: Option<SandboxExec>,
     spawn_lock: Arc<RwLock<()>>,
 }
 
@@ -69,6 +82,7 @@ impl CommandRunner {
         named_caches: NamedCaches,
         immutable_inputs: ImmutableInputs,
         keep_sandboxes: KeepSandboxes,
+        sandbox_exec: Option<SandboxExec>,
         spawn_lock: Arc<RwLock<()>>,
     ) -> CommandRunner {
         CommandRunner {
@@ -78,6 +92,7 @@ impl CommandRunner {
             named_caches,
             immutable_inputs,
             keep_sandboxes,
+            sandbox_exec,
             spawn_lock,
         }
     }
@@ -87,31 +102,10 @@ impl CommandRunner {
         posix_fs: Arc<fs::PosixFS>,
         output_file_paths: BTreeSet<RelativePath>,
         output_dir_paths: BTreeSet<RelativePath>,
+        output_globs: BTreeSet<String>,
     ) -> Result<Snapshot, String> {
-        let output_paths = output_dir_paths
-            .into_iter()
-            .flat_map(|p| {
-                let mut dir_glob = {
-                    let mut dir = PathBuf::from(p).into_os_string();
-                    if dir.is_empty() {
-                        dir.push(".")
-                    }
-                    dir
-                };
-                let dir = dir_glob.clone();
-                dir_glob.push("/**");
-                vec![dir, dir_glob]
-            })
-            .chain(
-                output_file_paths
-                    .into_iter()
-                    .map(|p| PathBuf::from(p).into_os_string()),
-            )
-            .map(|s| {
-                s.into_string()
-                    .map_err(|e| format!("Error stringifying output paths: {e:?}"))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+        let output_paths =
+            output_capture_globs(&output_file_paths, &output_dir_paths, &output_globs)?;
 
         // TODO: should we error when globs fail?
         let output_globs = PathGlobs::new(
@@ -159,19 +153,71 @@ pub enum ChildOutput {
 }
 
 ///
-/// Collect the outputs of a child process.
+/// Forwards the output of a child process to a `ProcessOutputSink` as it is produced.
+///
+pub struct OutputTap {
+    sink: Arc<dyn ProcessOutputSink>,
+    span_id: SpanId,
+    description: String,
+}
+
+impl OutputTap {
+    ///
+    /// If the given Context has a ProcessOutputSink installed, creates an OutputTap which will
+    /// attribute output to the current workunit.
+    ///
+    pub fn for_current_workunit(context: &Context, description: &str) -> Option<OutputTap> {
+        let sink = context.workunit_store.process_output_sink()?;
+        let span_id = get_workunit_store_handle()?.parent_id?;
+        Some(OutputTap {
+            sink,
+            span_id,
+            description: description.to_owned(),
+        })
+    }
+
+    fn output(&self, stream: OutputStream, bytes: &[u8]) {
+        self.sink
+            .output(self.span_id, &self.description, stream, bytes);
+    }
+}
+
+impl Drop for OutputTap {
+    fn drop(&mut self) {
+        self.sink.completed(self.span_id);
+    }
+}
+
+///
+/// Collect the outputs of a child process, forwarding them to the given OutputTap (if any) as they
+/// are produced.
 ///
 pub async fn collect_child_outputs<'a, 'b>(
-    stdout: &'a mut BytesMut,
-    stderr: &'a mut BytesMut,
+    stdout: &'a mut OutputCapture,
+    stderr: &'a mut OutputCapture,
     mut stream: BoxStream<'b, Result<ChildOutput, String>>,
+    tap: Option<OutputTap>,
 ) -> Result<i32, String> {
     let mut exit_code = 1;
 
     while let Some(child_output_res) = stream.next().await {
         match child_output_res? {
-            ChildOutput::Stdout(bytes) => stdout.extend_from_slice(&bytes),
-            ChildOutput::Stderr(bytes) => stderr.extend_from_slice(&bytes),
+            ChildOutput::Stdout(bytes) => {
+                if let Some(tap) = &tap {
+                    tap.output(OutputStream::Stdout, &bytes);
+                }
+                stdout
+                    .extend(&bytes)
+                    .map_err(|e| format!("Failed to capture stdout: {e}"))?
+            }
+            ChildOutput::Stderr(bytes) => {
+                if let Some(tap) = &tap {
+                    tap.output(OutputStream::Stderr, &bytes);
+                }
+                stderr
+                    .extend(&bytes)
+                    .map_err(|e| format!("Failed to capture stderr: {e}"))?
+            }
             ChildOutput::Exit(code) => exit_code = code.0,
         };
     }
@@ -179,6 +225,73 @@ pub async fn collect_child_outputs<'a, 'b>(
     Ok(exit_code)
 }
 
+///
+/// The result of storing the captured output of one stream of a process.
+///
+struct StoredOutput {
+    /// The digest of the complete output.
+    digest: Digest,
+    /// If the output overflowed the in-memory capture: the digest of its tail, and its total size.
+    overflow: Option<(Digest, u64)>,
+}
+
+impl StoredOutput {
+    async fn store(
+        store: &Store,
+        capture: OutputCapture,
+        name: &str,
+    ) -> Result<StoredOutput, String> {
+        let CapturedOutput {
+            tail,
+            spill_path,
+            total_bytes,
+        } = capture
+            .finish()
+            .map_err(|e| format!("Failed to capture {name}: {e}"))?;
+        let Some(spill_path) = spill_path else {
+            return Ok(StoredOutput {
+                digest: store.store_file_bytes(tail.into(), true).await?,
+                overflow: None,
+            });
+        };
+
+        let stored = store.store_file(true, false, spill_path.clone()).await;
+        if let Err(e) = std::fs::remove_file(&spill_path) {
+            debug!("Failed to remove {}: {e}", spill_path.display());
+        }
+        Ok(StoredOutput {
+            digest: stored?,
+            overflow: Some((
+                store.store_file_bytes(tail.into(), true).await?,
+                total_bytes,
+            )),
+        })
+    }
+
+    ///
+    /// If the output overflowed, tags the workunit with the digest of its tail and its total size,
+    /// so that consumers can render the tail without loading the complete output.
+    ///
+    fn record_overflow(&self, workunit: &mut RunningWorkunit, name: &str) {
+        let Some((tail_digest, total_bytes)) = self.overflow else {
+            return;
+        };
+        workunit.update_metadata(|initial| {
+            initial.map(|(mut metadata, level)| {
+                metadata.artifacts.push((
+                    format!("{name}_tail"),
+                    ArtifactOutput::FileDigest(tail_digest),
+                ));
+                metadata.user_metadata.push((
+                    format!("{name}_total_bytes"),
+                    UserMetadataItem::Int(total_bytes as i64),
+                ));
+                (metadata, level)
+            })
+        });
+    }
+}
+
 #[async_trait]
 impl super::CommandRunner for CommandRunner {
     ///
@@ -198,6 +311,7 @@ impl super::CommandRunner for CommandRunner {
             // renders at the Process's level.
             desc = Some(req.description.clone()),
             |workunit| async move {
+                validate_working_directory(&self.store, &req).await?;
                 let mut workdir = create_sandbox(
                     self.executor.clone(),
                     &self.work_dir_base,
@@ -229,10 +343,11 @@ impl super::CommandRunner for CommandRunner {
                 // exited (or been killed in their `Drop` handlers), so this function can rely on the usual
                 // Drop order of local variables to assume that the sandbox is cleaned up after the process
                 // is.
-                let res = self
+                let mut res = self
                     .run_and_capture_workdir(
                         req.clone(),
                         context,
+                        workunit,
                         self.store.clone(),
                         self.executor.clone(),
                         workdir.path().to_owned(),
@@ -248,8 +363,9 @@ impl super::CommandRunner for CommandRunner {
                         //
                         // Given that this is expected to be rare, we dump the entire process definition in the
                         // error.
-                        ProcessError::Unclassified(format!(
-                            "Failed to execute: {req_debug_repr}\n\n{msg}"
+                        ProcessError::from(EngineError::new(
+                            ErrorCode::ProcessExecutionFailed,
+                            format!("Failed to execute: {req_debug_repr}\n\n{msg}"),
                         ))
                     })
                     .await;
@@ -258,14 +374,10 @@ impl super::CommandRunner for CommandRunner {
                     || self.keep_sandboxes == KeepSandboxes::OnFailure
                         && res.as_ref().map(|r| r.exit_code).unwrap_or(1) != 0
                 {
-                    workdir.keep(&req.description);
-                    setup_run_sh_script(
-                        workdir.path(),
-                        &req.env,
-                        &req.working_directory,
-                        &req.argv,
-                        workdir.path(),
-                    )?;
+                    let retained_sandbox = retain_sandbox(&mut workdir, &req)?;
+                    if let Ok(res) = &mut res {
+                        res.metadata.retained_sandbox = Some(retained_sandbox);
+                    }
                 }
 
                 res
@@ -296,16 +408,40 @@ impl CapturedWorkdir for CommandRunner {
         } else {
             workdir_path.to_owned()
         };
-        let mut command = Command::new(&req.argv[0]);
+        let stdin_bytes = if let Some(stdin_digest) = req.stdin_digest {
+            Some(
+                self.store
+                    .load_file_bytes_with(stdin_digest, Bytes::copy_from_slice)
+                    .await
+                    .map_err(|e| format!("Failed to load stdin for the process: {e}"))?,
+            )
+        } else {
+            None
+        };
+        let argv = match self.sandbox_exec {
+            Some(ref sandbox_exec) => sandbox_exec.wrap(
+                workdir_path,
+                &[self.named_caches.base_path()],
+                &[self.immutable_inputs.workdir()],
+                &req.argv,
+            ),
+            None => req.argv.clone(),
+        };
+        let mut command = Command::new(&argv[0]);
         command
             .env_clear()
             // It would be really nice not to have to manually set PATH but this is sadly the only way
             // to stop automatic PATH searching.
             .env("PATH", "")
-            .args(&req.argv[1..])
+            .args(&argv[1..])
             .current_dir(cwd)
             .envs(&req.env)
-            .stdin(Stdio::null())
+            .envs(&req.globbed_env)
+            .stdin(if stdin_bytes.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
@@ -314,6 +450,13 @@ impl CapturedWorkdir for CommandRunner {
         })
         .await?;
 
+        if let (Some(stdin_bytes), Some(mut stdin)) = (stdin_bytes, child.stdin.take()) {
+            // NB: Errors are ignored, because a process may exit without consuming all of its stdin.
+            let _join = self.executor.native_spawn(async move {
+                let _ = stdin.write_all(&stdin_bytes).await;
+            });
+        }
+
         debug!("spawned local process as {:?} for {:?}", child.id(), req);
         let stdout_stream = FramedRead::new(child.stdout.take().unwrap(), BytesCodec::new())
             .map_ok(|bytes| ChildOutput::Stdout(bytes.into()))
@@ -326,14 +469,7 @@ impl CapturedWorkdir for CommandRunner {
         let exit_stream = async move {
             child
                 .wait()
-                .map_ok(|exit_status| {
-                    ChildOutput::Exit(ExitCode(
-                        exit_status
-                            .code()
-                            .or_else(|| exit_status.signal().map(Neg::neg))
-                            .expect("Child process should exit via returned code or signal."),
-                    ))
-                })
+                .map_ok(|exit_status| ChildOutput::Exit(ExitCode(children::exit_code(exit_status))))
                 .await
         }
         .into_stream()
@@ -359,6 +495,7 @@ pub trait CapturedWorkdir {
         &self,
         req: Process,
         context: Context,
+        workunit: &mut RunningWorkunit,
         store: Store,
         executor: Executor,
         workdir_path: PathBuf,
@@ -366,15 +503,21 @@ pub trait CapturedWorkdir {
         exclusive_spawn: bool,
     ) -> Result<FallibleProcessResultWithPlatform, String> {
         let start_time = Instant::now();
-        let mut stdout = BytesMut::with_capacity(8192);
-        let mut stderr = BytesMut::with_capacity(8192);
+        // Spill files are created next to (rather than inside) the workdir, so that they cannot be
+        // captured as outputs of the process.
+        let spill_path = |name: &str| {
+            workdir_path.with_file_name(format!(".{}-{name}", Uuid::new_v4().as_simple()))
+        };
+        let mut stdout = OutputCapture::new(MAX_BUFFERED_OUTPUT_BYTES, spill_path("stdout"));
+        let mut stderr = OutputCapture::new(MAX_BUFFERED_OUTPUT_BYTES, spill_path("stderr"));
 
         // Spawn the process.
-        // NB: We fully buffer the `Stream` into the stdout/stderr buffers, but the idea going forward
-        // is that we eventually want to pass incremental results on down the line for streaming
-        // process results to console logs, etc.
+        // NB: We buffer the tail of the `Stream` into the stdout/stderr captures (which spill to disk
+        // once they exit their size limit), and optionally tap it for streaming to the console.
         let exit_code_result = {
+            let _waiting_token = workunit.waiting_on(WaitingOn::ProcessExecution);
             let workdir_token = workdir_token.clone();
+            let tap = OutputTap::for_current_workunit(&context, &req.description);
             let exit_code_future = collect_child_outputs(
                 &mut stdout,
                 &mut stderr,
@@ -386,6 +529,7 @@ pub trait CapturedWorkdir {
                     exclusive_spawn,
                 )
                 .await?,
+                tap,
             );
             if let Some(req_timeout) = req.timeout {
                 timeout(req_timeout, exit_code_future)
@@ -400,7 +544,10 @@ pub trait CapturedWorkdir {
         // Capture the process outputs.
         self.prepare_workdir_for_capture(&context, &workdir_path, workdir_token, &req)
             .await?;
-        let output_snapshot = if req.output_files.is_empty() && req.output_directories.is_empty() {
+        let output_snapshot = if req.output_files.is_empty()
+            && req.output_directories.is_empty()
+            && req.output_globs.is_empty()
+        {
             store::Snapshot::empty()
         } else {
             let root = match (
@@ -423,6 +570,7 @@ pub trait CapturedWorkdir {
                 posix_fs,
                 req.output_files,
                 req.output_directories,
+                req.output_globs,
             )
             .await?
         };
@@ -437,37 +585,43 @@ pub trait CapturedWorkdir {
 
         match exit_code_result {
             Ok(exit_code) => {
-                let (stdout_digest, stderr_digest) = try_join!(
-                    store.store_file_bytes(stdout.into(), true),
-                    store.store_file_bytes(stderr.into(), true),
+                let (stdout, stderr) = try_join!(
+                    StoredOutput::store(&store, stdout, "stdout"),
+                    StoredOutput::store(&store, stderr, "stderr"),
                 )?;
+                stdout.record_overflow(workunit, "stdout");
+                stderr.record_overflow(workunit, "stderr");
                 Ok(FallibleProcessResultWithPlatform {
-                    stdout_digest,
-                    stderr_digest,
+                    stdout_digest: stdout.digest,
+                    stderr_digest: stderr.digest,
                     exit_code,
                     output_directory: output_snapshot.into(),
                     metadata: result_metadata,
                 })
             }
             Err(msg) if msg == "deadline has elapsed" => {
-                stderr.extend_from_slice(
-                    format!(
-                        "\n\nExceeded timeout of {:.1} seconds when executing local process: {}",
-                        req.timeout.map(|dur| dur.as_secs_f32()).unwrap_or(-1.0),
-                        req.description
+                stderr
+                    .extend(
+                        format!(
+                            "\n\nExceeded timeout of {:.1} seconds when executing local process: {}",
+                            req.timeout.map(|dur| dur.as_secs_f32()).unwrap_or(-1.0),
+                            req.description
+                        )
+                        .as_bytes(),
                     )
-                    .as_bytes(),
-                );
+                    .map_err(|e| format!("Failed to capture stderr: {e}"))?;
 
-                let (stdout_digest, stderr_digest) = try_join!(
-                    store.store_file_bytes(stdout.into(), true),
-                    store.store_file_bytes(stderr.into(), true),
+                let (stdout, stderr) = try_join!(
+                    StoredOutput::store(&store, stdout, "stdout"),
+                    StoredOutput::store(&store, stderr, "stderr"),
                 )?;
+                stdout.record_overflow(workunit, "stdout");
+                stderr.record_overflow(workunit, "stderr");
 
                 Ok(FallibleProcessResultWithPlatform {
-                    stdout_digest,
-                    stderr_digest,
-                    exit_code: -libc::SIGTERM,
+                    stdout_digest: stdout.digest,
+                    stderr_digest: stderr.digest,
+                    exit_code: children::TIMEOUT_EXIT_CODE,
                     output_directory: EMPTY_DIRECTORY_DIGEST.clone(),
                     metadata: result_metadata,
                 })
@@ -577,11 +731,12 @@ pub async fn prepare_workdir_digest(
         }
 
         let symlinks = named_caches
-            .paths(&req.append_only_caches)
+            .paths(&req.append_only_caches, &req.append_only_cache_seeds, store)
             .await
             .map_err(|err| {
-                StoreError::Unclassified(format!(
-                    "Failed to make named cache(s) for local execution: {err:?}"
+                StoreError::from(EngineError::new(
+                    ErrorCode::SandboxSetupFailed,
+                    format!("Failed to make named cache(s) for local execution: {err:?}"),
                 ))
             })?;
         match named_caches_prefix {
@@ -800,12 +955,40 @@ env -i {stringified_env_vars} {stringified_command_line}
 
     let full_file_path = sandbox_path.join("__run.sh");
 
-    std::fs::OpenOptions::new()
-        .create_new(true)
-        .write(true)
-        .mode(USER_EXECUTABLE_MODE) // Executable for user, read-only for others.
-        .open(full_file_path)
+    // Executable for user, read-only for others.
+    fs::platform::open_options_with_mode(
+        std::fs::OpenOptions::new().create_new(true).write(true),
+        USER_EXECUTABLE_MODE,
+    )
+    .open(full_file_path)
+    .map_err(|e| format!("{e:?}"))?
+    .write_all(full_script.as_bytes())
+    .map_err(|e| format!("{e:?}"))
+}
+
+///
+/// Preserves the given sandbox after its process has run, and creates a `__run.sh` script in it
+/// which will re-run the process.
+///
+pub fn retain_sandbox(
+    workdir: &mut AsyncDropSandbox,
+    req: &Process,
+) -> Result<RetainedSandbox, String> {
+    workdir.keep(&req.description);
+    setup_run_sh_script(
+        workdir.path(),
+        &req.env,
+        &req.working_directory,
+        &req.argv,
+        workdir.path(),
+    )?;
+
+    let quoted_script_path = bash::escape(workdir.path().join("__run.sh"));
+    let repro_command = str::from_utf8(&quoted_script_path)
         .map_err(|e| format!("{e:?}"))?
-        .write_all(full_script.as_bytes())
-        .map_err(|e| format!("{e:?}"))
+        .to_string();
+    Ok(RetainedSandbox {
+        path: workdir.path().to_owned(),
+        repro_command,
+    })
 }
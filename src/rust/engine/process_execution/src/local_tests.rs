@@ -44,6 +44,62 @@ async fn stdout() {
     assert_eq!(result.original.output_directory, *EMPTY_DIRECTORY_DIGEST);
 }
 
+#[tokio::test]
+#[cfg(unix)]
+async fn stdin() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    let executor = task_executor::Executor::new();
+    let store_dir = TempDir::new().unwrap();
+    let store = Store::local_only(executor.clone(), store_dir.path()).unwrap();
+    let roland = TestData::roland();
+    store
+        .store_file_bytes(roland.bytes(), false)
+        .await
+        .expect("Error saving file bytes");
+
+    let work_dir = TempDir::new().unwrap();
+    let result = run_command_locally_in_dir(
+        Process::new(owned_string_vec(&["/bin/cat"])).stdin_digest(roland.digest()),
+        work_dir.path().to_owned(),
+        KeepSandboxes::Never,
+        &mut workunit,
+        Some(store),
+        Some(executor),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(result.stdout_bytes, roland.bytes());
+    assert_eq!(result.original.exit_code, 0);
+}
+
+#[tokio::test]
+#[cfg(unix)]
+async fn stdout_larger_than_buffer_limit() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    let work_dir = TempDir::new().unwrap();
+    let output_len = crate::local::MAX_BUFFERED_OUTPUT_BYTES * 3 + 17;
+    let result = run_command_locally_in_dir(
+        Process::new(owned_string_vec(&[
+            "/bin/bash",
+            "-c",
+            &format!("/usr/bin/head -c {output_len} /dev/zero"),
+        ])),
+        work_dir.path().to_owned(),
+        KeepSandboxes::Never,
+        &mut workunit,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(result.stdout_bytes, vec![0; output_len]);
+    assert_eq!(result.original.exit_code, 0);
+    // The spill files should have been cleaned up.
+    assert_eq!(std::fs::read_dir(work_dir.path()).unwrap().count(), 0);
+}
+
 #[tokio::test]
 #[cfg(unix)]
 async fn stdout_and_stderr_and_exit_code() {
@@ -198,6 +254,30 @@ async fn output_dirs() {
     );
 }
 
+#[tokio::test]
+async fn output_globs() {
+    let result = run_command_locally(
+        Process::new(vec![
+            find_bash(),
+            "-c".to_owned(),
+            format!(
+                "/bin/mkdir cats && echo -n {} > cats/roland.ext ; echo -n {} > treats.ext ; echo -n > cats/other.txt",
+                TestData::roland().string(),
+                TestData::catnip().string()
+            ),
+        ])
+        .output_globs(["cats/*.ext".to_owned(), "*.ext".to_owned()].into()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(result.original.exit_code, 0);
+    assert_eq!(
+        result.original.output_directory,
+        TestDirectory::recursive().directory_digest()
+    );
+}
+
 #[tokio::test]
 async fn output_files_many() {
     let result = run_command_locally(
@@ -497,6 +577,56 @@ async fn test_directory_preservation_error() {
     assert_eq!(testutil::file::list_dir(&preserved_work_root).len(), 1);
 }
 
+#[tokio::test]
+#[cfg(unix)]
+async fn test_directory_preservation_on_failure() {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+
+    let preserved_work_tmpdir = TempDir::new().unwrap();
+    let preserved_work_root = preserved_work_tmpdir.path().to_owned();
+
+    let result = run_command_locally_in_dir(
+        Process::new(vec![find_bash(), "-c".to_owned(), "exit 1".to_owned()]),
+        preserved_work_root.clone(),
+        KeepSandboxes::OnFailure,
+        &mut workunit,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(result.original.exit_code, 1);
+    let retained_sandbox = result
+        .original
+        .metadata
+        .retained_sandbox
+        .expect("The sandbox of a failed process should be retained.");
+    let subdirs = testutil::file::list_dir(&preserved_work_root);
+    assert_eq!(subdirs.len(), 1);
+    assert_eq!(retained_sandbox.path, preserved_work_root.join(&subdirs[0]));
+    let run_script_path = retained_sandbox.path.join("__run.sh");
+    assert!(run_script_path.exists());
+    assert_eq!(
+        retained_sandbox.repro_command,
+        run_script_path.to_str().unwrap()
+    );
+
+    // A successful process does not retain its sandbox.
+    let result = run_command_locally_in_dir(
+        Process::new(vec![find_bash(), "-c".to_owned(), "exit 0".to_owned()]),
+        preserved_work_root.clone(),
+        KeepSandboxes::OnFailure,
+        &mut workunit,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(result.original.metadata.retained_sandbox, None);
+    assert_eq!(testutil::file::list_dir(&preserved_work_root).len(), 1);
+}
+
 #[tokio::test]
 async fn all_containing_directories_for_outputs_are_created() {
     let result = run_command_locally(
@@ -624,6 +754,22 @@ async fn working_directory() {
     );
 }
 
+#[tokio::test]
+async fn working_directory_missing() {
+    let mut process = Process::new(vec![find_bash(), "-c".to_owned(), "/bin/ls".to_string()]);
+    process.working_directory = Some(RelativePath::new("cats").unwrap());
+    process.description = "lost-cat".to_string();
+
+    let err = run_command_locally(process).await.unwrap_err();
+    assert!(
+        err.to_string().contains(
+            "The working directory `cats` of process `lost-cat` is not a directory in the input \
+             digest of the process."
+        ),
+        "{err}"
+    );
+}
+
 #[tokio::test]
 async fn immutable_inputs() {
     let (_, mut workunit) = WorkunitStore::setup_for_tests();
@@ -796,6 +942,7 @@ async fn run_command_locally_in_dir(
         named_caches,
         immutable_inputs,
         cleanup,
+        None,
         Arc::new(RwLock::new(())),
     );
     let original = runner.run(Context::default(), workunit, req).await?;
@@ -1,17 +1,20 @@
 // Copyright 2022 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_oncecell::OnceCell;
 use deepsize::DeepSizeOf;
 use futures::{FutureExt, TryFutureExt};
+use log::debug;
 use parking_lot::Mutex;
 use serde::Serialize;
 
-use fs::{default_cache_path, RelativePath};
-use store::WorkdirSymlink;
+use fs::{default_cache_path, DirectoryDigest, Permissions, RelativePath};
+use store::{Store, WorkdirSymlink};
 
 #[derive(Clone, Debug, DeepSizeOf, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize)]
 pub struct CacheName(String);
@@ -50,6 +53,9 @@ struct Inner {
     initializer: Box<dyn Fn(&Path) -> futures::future::BoxFuture<Result<(), String>> + Send + Sync>,
     /// Caches which have been initialized.
     initialized: Mutex<HashMap<PathBuf, Arc<OnceCell<()>>>>,
+    /// True if the named caches are in the local filesystem, and so may be seeded by materializing
+    /// digests into them.
+    local: bool,
 }
 
 #[derive(Clone)]
@@ -69,16 +75,22 @@ impl NamedCaches {
             base_path,
             initializer: Box::new(initializer),
             initialized: Mutex::default(),
+            local: false,
         }))
     }
 
     /// Create a NamedCache in the local filesystem.
     pub fn new_local(base_path: PathBuf) -> Self {
-        Self::new(base_path, |dst| {
-            tokio::fs::create_dir_all(dst)
-                .map_err(|e| format!("Failed to create path {}: {e}", dst.display()))
-                .boxed()
-        })
+        Self(Arc::new(Inner {
+            base_path,
+            initializer: Box::new(|dst| {
+                tokio::fs::create_dir_all(dst)
+                    .map_err(|e| format!("Failed to create path {}: {e}", dst.display()))
+                    .boxed()
+            }),
+            initialized: Mutex::default(),
+            local: true,
+        }))
     }
 
     pub fn base_path(&self) -> &Path {
@@ -104,9 +116,15 @@ impl NamedCaches {
     ///
     /// Returns symlinks to create for the given set of NamedCaches, initializing them if necessary.
     ///
+    /// Caches which have a seed and which are empty when they are initialized are populated with
+    /// the content of the seed digest before first use. Seeding is only supported for caches in the
+    /// local filesystem: for other caches, seeds are ignored.
+    ///
     pub async fn paths<'a>(
         &'a self,
         caches: &'a BTreeMap<CacheName, RelativePath>,
+        seeds: &'a BTreeMap<CacheName, DirectoryDigest>,
+        store: &'a Store,
     ) -> Result<Vec<WorkdirSymlink>, String> {
         // Collect the symlinks to create, and their destination cache cells.
         let (symlinks, initialization_futures): (Vec<_>, Vec<_>) = {
@@ -120,13 +138,18 @@ impl NamedCaches {
 
                     // Create the initialization future under the lock, but await it outside.
                     let dst: PathBuf = symlink.dst.clone();
+                    let seed = seeds.get(cache_name).cloned();
                     let named_caches: NamedCaches = self.clone();
                     let initialization_future = async move {
                         named_caches
                             .cache_cell(dst.clone())
-                            .get_or_try_init(
-                                async move { (named_caches.0.initializer)(&dst).await },
-                            )
+                            .get_or_try_init(async move {
+                                (named_caches.0.initializer)(&dst).await?;
+                                if let Some(seed) = seed {
+                                    named_caches.seed(store, &dst, seed).await?;
+                                }
+                                Ok::<_, String>(())
+                            })
                             .await?;
                         Ok::<_, String>(())
                     };
@@ -141,4 +164,91 @@ impl NamedCaches {
 
         Ok(symlinks)
     }
+
+    ///
+    /// Populates the given (initialized) cache directory with the content of the given digest, if
+    /// it is empty.
+    ///
+    /// A lock file is held while seeding so that only one seeder runs for a cache, even across
+    /// processes which share the named caches directory. The seed is materialized into a temporary
+    /// directory beside the cache, which is then renamed into place, so a cache is never observed
+    /// partially seeded.
+    ///
+    async fn seed(&self, store: &Store, dst: &Path, digest: DirectoryDigest) -> Result<(), String> {
+        if !self.0.local {
+            debug!(
+                "Not seeding named cache {}, because it is not in the local filesystem.",
+                dst.display()
+            );
+            return Ok(());
+        }
+
+        // NB: Cache names may not contain `.`, so the lock file cannot collide with another cache.
+        let lock_path = dst.with_extension("seed_lock");
+        let _lock = tokio::task::spawn_blocking(move || lock_exclusive(&lock_path))
+            .await
+            .map_err(|e| format!("Failed to lock named cache for seeding: {e}"))??;
+        if !is_empty_dir(dst)
+            .await
+            .map_err(|e| format!("Failed to read named cache {}: {e}", dst.display()))?
+        {
+            return Ok(());
+        }
+
+        let seed_dir = tempfile::Builder::new()
+            .prefix(".seed-")
+            .tempdir_in(&self.0.base_path)
+            .map_err(|e| format!("Failed to create a directory to seed named cache: {e}"))?;
+        store
+            .materialize_directory(
+                seed_dir.path().to_owned(),
+                &self.0.base_path,
+                digest,
+                // The content of a cache will be mutated, and so must not be hardlinked to the store.
+                true,
+                &BTreeSet::new(),
+                Permissions::Writable,
+            )
+            .await
+            .map_err(|e| format!("Failed to seed named cache {}: {e}", dst.display()))?;
+
+        // Replace the empty cache directory with the seed. If the cache is no longer empty, then it
+        // was populated by a process which does not respect the lock, and the seed is discarded.
+        match tokio::fs::remove_dir(dst).await {
+            Ok(()) => (),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => {
+                debug!("Not seeding named cache {}: {e}", dst.display());
+                return Ok(());
+            }
+        }
+        tokio::fs::rename(seed_dir.path(), dst)
+            .await
+            .map_err(|e| format!("Failed to seed named cache {}: {e}", dst.display()))?;
+        Ok(())
+    }
+}
+
+///
+/// Opens and exclusively locks the given file, which remains locked until the returned File is
+/// dropped.
+///
+fn lock_exclusive(path: &Path) -> Result<File, String> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open lock file {}: {e}", path.display()))?;
+    fs::platform::lock_exclusive(&file)
+        .map_err(|e| format!("Failed to lock {}: {e}", path.display()))?;
+    Ok(file)
+}
+
+async fn is_empty_dir(path: &Path) -> Result<bool, io::Error> {
+    match tokio::fs::read_dir(path).await {
+        Ok(mut entries) => Ok(entries.next_entry().await?.is_none()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e),
+    }
 }
@@ -1,6 +1,14 @@
 // Copyright 2023 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
-use crate::named_caches::CacheName;
+use std::collections::BTreeMap;
+
+use fs::RelativePath;
+use maplit::btreemap;
+use store::Store;
+use tempfile::TempDir;
+use testutil::data::{TestData, TestDirectory};
+
+use crate::named_caches::{CacheName, NamedCaches};
 
 #[test]
 fn alphanumeric_lowercase_are_valid() {
@@ -16,3 +24,63 @@ fn uppercase_characters_are_invalid() {
     let cache_name = CacheName::new(name.to_string());
     assert!(cache_name.is_err());
 }
+
+#[tokio::test]
+async fn seeded_when_empty() {
+    let store_dir = TempDir::new().unwrap();
+    let store = Store::local_only(task_executor::Executor::new(), store_dir.path()).unwrap();
+    let roland = TestData::roland();
+    let seed = TestDirectory::containing_roland();
+    store.store_file_bytes(roland.bytes(), false).await.unwrap();
+    store
+        .record_directory(&seed.directory(), false)
+        .await
+        .unwrap();
+
+    let base_dir = TempDir::new().unwrap();
+    let seeded = CacheName::new("seeded".to_owned()).unwrap();
+    let populated = CacheName::new("populated".to_owned()).unwrap();
+    let caches = btreemap! {
+        seeded.clone() => RelativePath::new(".cache/seeded").unwrap(),
+        populated.clone() => RelativePath::new(".cache/populated").unwrap(),
+    };
+    let seeds = btreemap! {
+        seeded.clone() => seed.directory_digest(),
+        populated.clone() => seed.directory_digest(),
+    };
+
+    // A cache which already has content is not seeded.
+    let populated_dir = base_dir.path().join("populated");
+    std::fs::create_dir_all(&populated_dir).unwrap();
+    std::fs::write(populated_dir.join("existing"), b"content").unwrap();
+
+    let named_caches = NamedCaches::new_local(base_dir.path().to_owned());
+    named_caches.paths(&caches, &seeds, &store).await.unwrap();
+
+    let seeded_file = base_dir.path().join("seeded").join("roland.ext");
+    assert_eq!(std::fs::read(&seeded_file).unwrap(), roland.bytes());
+    // The seeded content is mutable, since tools will write to the cache.
+    assert!(!std::fs::metadata(&seeded_file)
+        .unwrap()
+        .permissions()
+        .readonly());
+    assert!(!populated_dir.join("roland.ext").exists());
+
+    // A cache is only seeded if it is empty when it is first used.
+    std::fs::remove_file(&seeded_file).unwrap();
+    std::fs::write(base_dir.path().join("seeded").join("other"), b"").unwrap();
+    let named_caches = NamedCaches::new_local(base_dir.path().to_owned());
+    named_caches.paths(&caches, &seeds, &store).await.unwrap();
+    assert!(!seeded_file.exists());
+
+    // Caches without seeds are only created.
+    let named_caches = NamedCaches::new_local(base_dir.path().to_owned());
+    let unseeded = btreemap! {
+        CacheName::new("unseeded".to_owned()).unwrap() => RelativePath::new(".cache/unseeded").unwrap(),
+    };
+    named_caches
+        .paths(&unseeded, &BTreeMap::new(), &store)
+        .await
+        .unwrap();
+    assert!(base_dir.path().join("unseeded").is_dir());
+}
@@ -0,0 +1,288 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use fs::{DirectoryDigest, Entry, SymlinkBehavior};
+use hashing::Digest;
+use log::warn;
+use parking_lot::Mutex;
+use regex::RegexSet;
+use serde::Serialize;
+use store::{Store, StoreError};
+use workunit_store::{Metric, RunningWorkunit};
+
+use crate::{Context, FallibleProcessResultWithPlatform, Process, ProcessError};
+
+///
+/// A CommandRunner which runs selected processes a second time (possibly with a different inner
+/// CommandRunner, e.g. remotely rather than locally) and compares the results of the two runs.
+///
+/// Processes are selected by matching their descriptions against a set of regular expressions.
+/// Differences are reported as warnings, and are appended as JSON lines to an optional report
+/// file. The result of the first run is always the one which is returned.
+///
+pub struct CommandRunner {
+    inner: Arc<dyn crate::CommandRunner>,
+    verifier: Arc<dyn crate::CommandRunner>,
+    store: Store,
+    selection: RegexSet,
+    report_path: Option<PathBuf>,
+    report_lock: Mutex<()>,
+}
+
+impl CommandRunner {
+    pub fn new(
+        inner: Arc<dyn crate::CommandRunner>,
+        verifier: Arc<dyn crate::CommandRunner>,
+        store: Store,
+        selection: &[String],
+        report_path: Option<PathBuf>,
+    ) -> Result<CommandRunner, String> {
+        let selection = RegexSet::new(selection).map_err(|e| {
+            format!("Invalid pattern for selecting processes to verify determinism of: {e}")
+        })?;
+        Ok(CommandRunner {
+            inner,
+            verifier,
+            store,
+            selection,
+            report_path,
+            report_lock: Mutex::new(()),
+        })
+    }
+
+    fn is_selected(&self, req: &Process) -> bool {
+        self.selection.is_match(&req.description)
+    }
+
+    ///
+    /// Compares the results of two runs of the same process, returning a Finding if they differ.
+    ///
+    async fn compare(
+        &self,
+        req: &Process,
+        first: &FallibleProcessResultWithPlatform,
+        second: &FallibleProcessResultWithPlatform,
+    ) -> Result<Option<Finding>, StoreError> {
+        let mut differences = Vec::new();
+        if first.exit_code != second.exit_code {
+            differences.push("exit_code");
+        }
+        if first.stdout_digest != second.stdout_digest {
+            differences.push("stdout");
+        }
+        if first.stderr_digest != second.stderr_digest {
+            differences.push("stderr");
+        }
+        let differing_outputs = if first.output_directory == second.output_directory {
+            vec![]
+        } else {
+            differences.push("output_directory");
+            self.differing_outputs(&first.output_directory, &second.output_directory)
+                .await?
+        };
+
+        if differences.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Finding {
+            description: req.description.clone(),
+            argv: req.argv.clone(),
+            differences,
+            differing_outputs,
+            first: RunSummary::from(first),
+            second: RunSummary::from(second),
+        }))
+    }
+
+    ///
+    /// Returns the output paths which are present in only one of the given digests, or which have
+    /// different content in each.
+    ///
+    async fn differing_outputs(
+        &self,
+        first: &DirectoryDigest,
+        second: &DirectoryDigest,
+    ) -> Result<Vec<String>, StoreError> {
+        let (first, second) = futures::try_join!(
+            self.store.load_digest_trie(first.clone()),
+            self.store.load_digest_trie(second.clone()),
+        )?;
+
+        let leaves = |trie: &fs::DigestTrie| {
+            let mut leaves = BTreeMap::new();
+            trie.walk(SymlinkBehavior::Aware, &mut |path, entry| match entry {
+                Entry::File(f) => {
+                    leaves.insert(path.to_owned(), Leaf::File(f.digest(), f.is_executable()));
+                }
+                Entry::Symlink(s) => {
+                    leaves.insert(path.to_owned(), Leaf::Symlink(s.target().to_owned()));
+                }
+                Entry::Directory(_) => {}
+            });
+            leaves
+        };
+        let first = leaves(&first);
+        let second = leaves(&second);
+
+        let mut differing = first
+            .iter()
+            .filter(|(path, content)| second.get(*path) != Some(content))
+            .map(|(path, _)| path.display().to_string())
+            .collect::<Vec<_>>();
+        differing.extend(
+            second
+                .keys()
+                .filter(|path| !first.contains_key(*path))
+                .map(|path| path.display().to_string()),
+        );
+        differing.sort();
+        Ok(differing)
+    }
+
+    fn record(&self, finding: &Finding) {
+        warn!(
+            "Process `{}` is nondeterministic: two runs differed in {}{}",
+            finding.description,
+            finding.differences.join(", "),
+            if finding.differing_outputs.is_empty() {
+                "".to_owned()
+            } else {
+                format!(" (outputs: {})", finding.differing_outputs.join(", "))
+            }
+        );
+
+        let Some(report_path) = &self.report_path else {
+            return;
+        };
+        let res = serde_json::to_string(finding)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                let _guard = self.report_lock.lock();
+                if let Some(parent) = report_path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(report_path)
+                    .map_err(|e| e.to_string())?;
+                writeln!(file, "{line}").map_err(|e| e.to_string())
+            });
+        if let Err(e) = res {
+            warn!(
+                "Failed to record nondeterminism finding in {}: {e}",
+                report_path.display()
+            );
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Leaf {
+    File(Digest, bool),
+    Symlink(PathBuf),
+}
+
+impl Debug for CommandRunner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("nondeterminism::CommandRunner")
+            .field("inner", &self.inner)
+            .field("verifier", &self.verifier)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl crate::CommandRunner for CommandRunner {
+    async fn run(
+        &self,
+        context: Context,
+        workunit: &mut RunningWorkunit,
+        req: Process,
+    ) -> Result<FallibleProcessResultWithPlatform, ProcessError> {
+        if !self.is_selected(&req) {
+            return self.inner.run(context, workunit, req).await;
+        }
+
+        let first = self
+            .inner
+            .run(context.clone(), workunit, req.clone())
+            .await?;
+        workunit.increment_counter(Metric::DeterminismVerificationRuns, 1);
+        let second = match self.verifier.run(context, workunit, req.clone()).await {
+            Ok(second) => second,
+            Err(e) => {
+                // A failure to run the process a second time is not a finding: the first result
+                // is still usable.
+                warn!(
+                    "Failed to re-run `{}` to verify its determinism: {e}",
+                    req.description
+                );
+                return Ok(first);
+            }
+        };
+
+        match self.compare(&req, &first, &second).await {
+            Ok(Some(finding)) => {
+                workunit.increment_counter(Metric::NondeterministicProcesses, 1);
+                self.record(&finding);
+            }
+            Ok(None) => {}
+            Err(e) => warn!(
+                "Failed to compare the outputs of two runs of `{}`: {e}",
+                req.description
+            ),
+        }
+
+        Ok(first)
+    }
+
+    async fn shutdown(&self) -> Result<(), String> {
+        futures::try_join!(self.inner.shutdown(), self.verifier.shutdown())?;
+        Ok(())
+    }
+}
+
+///
+/// A record of a process whose two runs produced different results.
+///
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub description: String,
+    pub argv: Vec<String>,
+    /// Which parts of the results differed: any of `exit_code`, `stdout`, `stderr` and
+    /// `output_directory`.
+    pub differences: Vec<&'static str>,
+    /// The output paths whose content differed between the runs.
+    pub differing_outputs: Vec<String>,
+    pub first: RunSummary,
+    pub second: RunSummary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub exit_code: i32,
+    pub stdout_digest: Digest,
+    pub stderr_digest: Digest,
+    pub output_digest: Digest,
+    pub source: &'static str,
+}
+
+impl From<&FallibleProcessResultWithPlatform> for RunSummary {
+    fn from(result: &FallibleProcessResultWithPlatform) -> Self {
+        RunSummary {
+            exit_code: result.exit_code,
+            stdout_digest: result.stdout_digest,
+            stderr_digest: result.stderr_digest,
+            output_digest: result.output_directory.as_digest(),
+            source: result.metadata.source.into(),
+        }
+    }
+}
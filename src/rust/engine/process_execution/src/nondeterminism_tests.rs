@@ -0,0 +1,137 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use fs::EMPTY_DIRECTORY_DIGEST;
+use store::Store;
+use tempfile::TempDir;
+use testutil::data::{TestData, TestDirectory};
+use workunit_store::WorkunitStore;
+
+use crate::nondeterminism::CommandRunner;
+use crate::tests::{mock_result, MockCommandRunner, MockResponse};
+use crate::{
+    CommandRunner as CommandRunnerTrait, Context, FallibleProcessResultWithPlatform, Process,
+    ProcessError,
+};
+
+fn result(stdout: &TestData, output_directory: fs::DirectoryDigest) -> MockResponse {
+    MockResponse::Result(Ok(FallibleProcessResultWithPlatform {
+        stdout_digest: stdout.digest(),
+        output_directory,
+        ..mock_result(0)
+    }))
+}
+
+fn process(description: &str) -> Process {
+    let mut process = Process::new(vec!["/bin/true".to_owned()]);
+    description.clone_into(&mut process.description);
+    process
+}
+
+async fn run(
+    runner: &CommandRunner,
+    req: Process,
+) -> Result<FallibleProcessResultWithPlatform, ProcessError> {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    runner.run(Context::default(), &mut workunit, req).await
+}
+
+fn report_lines(report_dir: &TempDir) -> Vec<serde_json::Value> {
+    std::fs::read_to_string(report_dir.path().join("report.jsonl"))
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn create_runner(
+    inner: Arc<MockCommandRunner>,
+    verifier: Arc<MockCommandRunner>,
+) -> (CommandRunner, Store, TempDir, TempDir) {
+    let store_dir = TempDir::new().unwrap();
+    let report_dir = TempDir::new().unwrap();
+    let store = Store::local_only(task_executor::Executor::new(), store_dir.path()).unwrap();
+    let runner = CommandRunner::new(
+        inner,
+        verifier,
+        store.clone(),
+        &["^Compile ".to_owned()],
+        Some(report_dir.path().join("report.jsonl")),
+    )
+    .unwrap();
+    (runner, store, store_dir, report_dir)
+}
+
+#[tokio::test]
+async fn unselected_processes_run_once() {
+    let inner = MockCommandRunner::new(vec![result(
+        &TestData::roland(),
+        EMPTY_DIRECTORY_DIGEST.clone(),
+    )]);
+    let verifier = MockCommandRunner::new(vec![result(
+        &TestData::catnip(),
+        EMPTY_DIRECTORY_DIGEST.clone(),
+    )]);
+    let (runner, _store, _store_dir, report_dir) = create_runner(inner.clone(), verifier.clone());
+
+    let res = run(&runner, process("Link app")).await.unwrap();
+
+    assert_eq!(res.stdout_digest, TestData::roland().digest());
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+    assert_eq!(verifier.calls.load(Ordering::SeqCst), 0);
+    assert!(report_lines(&report_dir).is_empty());
+}
+
+#[tokio::test]
+async fn deterministic_processes_are_not_reported() {
+    let inner = MockCommandRunner::new(vec![result(
+        &TestData::roland(),
+        EMPTY_DIRECTORY_DIGEST.clone(),
+    )]);
+    let (runner, _store, _store_dir, report_dir) = create_runner(inner.clone(), inner.clone());
+
+    let res = run(&runner, process("Compile lib")).await.unwrap();
+
+    assert_eq!(res.stdout_digest, TestData::roland().digest());
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    assert!(report_lines(&report_dir).is_empty());
+}
+
+#[tokio::test]
+async fn nondeterministic_processes_are_reported() {
+    let roland_dir = TestDirectory::containing_roland();
+    let wrong_roland_dir = TestDirectory::containing_wrong_roland();
+    let inner = MockCommandRunner::new(vec![
+        result(&TestData::roland(), roland_dir.directory_digest()),
+        result(&TestData::catnip(), wrong_roland_dir.directory_digest()),
+    ]);
+    let (runner, store, _store_dir, report_dir) = create_runner(inner.clone(), inner.clone());
+    store
+        .record_directory(&roland_dir.directory(), false)
+        .await
+        .unwrap();
+    store
+        .record_directory(&wrong_roland_dir.directory(), false)
+        .await
+        .unwrap();
+
+    // The result of the first run is returned.
+    let res = run(&runner, process("Compile lib")).await.unwrap();
+    assert_eq!(res.stdout_digest, TestData::roland().digest());
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+
+    let findings = report_lines(&report_dir);
+    assert_eq!(findings.len(), 1);
+    let finding = &findings[0];
+    assert_eq!(finding["description"], "Compile lib");
+    assert_eq!(
+        finding["differences"],
+        serde_json::json!(["stdout", "output_directory"])
+    );
+    assert_eq!(
+        finding["differing_outputs"],
+        serde_json::json!(["roland.ext"])
+    );
+}
@@ -0,0 +1,88 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use log::debug;
+use workunit_store::{in_workunit, Level, RunningWorkunit};
+
+use crate::{Context, FallibleProcessResultWithPlatform, Process, ProcessError};
+
+///
+/// A CommandRunner which retries failed attempts to run Processes which declare a
+/// `ProcessRetryPolicy`, and records the number of attempts in the metadata of the result.
+///
+/// Each attempt is run in its own workunit. Processes without a policy are passed through to the
+/// inner CommandRunner unchanged.
+///
+pub struct CommandRunner {
+    inner: Arc<dyn crate::CommandRunner>,
+}
+
+impl CommandRunner {
+    pub fn new(inner: Arc<dyn crate::CommandRunner>) -> CommandRunner {
+        CommandRunner { inner }
+    }
+}
+
+impl Debug for CommandRunner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("retry::CommandRunner")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl crate::CommandRunner for CommandRunner {
+    async fn run(
+        &self,
+        context: Context,
+        workunit: &mut RunningWorkunit,
+        req: Process,
+    ) -> Result<FallibleProcessResultWithPlatform, ProcessError> {
+        let Some(policy) = req.retry_policy.clone() else {
+            return self.inner.run(context, workunit, req).await;
+        };
+
+        let mut attempt = 1;
+        loop {
+            let (attempt_context, attempt_req) = (context.clone(), req.clone());
+            let result = in_workunit!(
+                "run_process_attempt",
+                Level::Debug,
+                desc = Some(format!(
+                    "Attempt {attempt} of {}: {}",
+                    policy.max_attempts, req.description
+                )),
+                |workunit| self.inner.run(attempt_context, workunit, attempt_req)
+            )
+            .await;
+
+            if attempt >= policy.max_attempts || !policy.should_retry(&result) {
+                return result.map(|mut result| {
+                    result.metadata.attempts = attempt;
+                    result
+                });
+            }
+
+            attempt += 1;
+            let backoff = policy.backoff_before(attempt);
+            debug!(
+                "Retrying `{}` (attempt {attempt} of {}) in {backoff:?} after: {}",
+                req.description,
+                policy.max_attempts,
+                match &result {
+                    Ok(result) => format!("exit code {}", result.exit_code),
+                    Err(e) => e.to_string(),
+                }
+            );
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
+    async fn shutdown(&self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::collections::BTreeSet;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use engine_error::{EngineError, ErrorCode};
+use hashing::EMPTY_DIGEST;
+use workunit_store::WorkunitStore;
+
+use crate::retry::CommandRunner;
+use crate::tests::MockCommandRunner;
+use crate::{
+    CommandRunner as CommandRunnerTrait, Context, FallibleProcessResultWithPlatform, Process,
+    ProcessError, ProcessRetryPolicy, RetryOn,
+};
+
+fn process(retry_on: RetryOn) -> Process {
+    Process::new(vec!["/bin/flaky".to_owned()]).retry_policy(ProcessRetryPolicy {
+        max_attempts: 3,
+        retry_on,
+        backoff: Duration::from_millis(1),
+    })
+}
+
+async fn run(
+    inner: Arc<MockCommandRunner>,
+    req: Process,
+) -> Result<FallibleProcessResultWithPlatform, ProcessError> {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    CommandRunner::new(inner)
+        .run(Context::default(), &mut workunit, req)
+        .await
+}
+
+#[tokio::test]
+async fn processes_without_a_policy_run_once() {
+    let inner = MockCommandRunner::exiting(vec![Ok(1), Ok(0)]);
+    let res = run(inner.clone(), Process::new(vec!["/bin/flaky".to_owned()]))
+        .await
+        .unwrap();
+
+    assert_eq!(res.exit_code, 1);
+    assert_eq!(res.metadata.attempts, 1);
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn any_failure_is_retried_until_success() {
+    let inner = MockCommandRunner::exiting(vec![Ok(1), Ok(2), Ok(0)]);
+    let res = run(inner.clone(), process(RetryOn::AnyFailure))
+        .await
+        .unwrap();
+
+    assert_eq!(res.exit_code, 0);
+    assert_eq!(res.metadata.attempts, 3);
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn attempts_are_bounded() {
+    let inner = MockCommandRunner::exiting(vec![Ok(1)]);
+    let res = run(inner.clone(), process(RetryOn::AnyFailure))
+        .await
+        .unwrap();
+
+    assert_eq!(res.exit_code, 1);
+    assert_eq!(res.metadata.attempts, 3);
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn only_matching_exit_codes_are_retried() {
+    let inner = MockCommandRunner::exiting(vec![Ok(75), Ok(1), Ok(0)]);
+    let res = run(
+        inner.clone(),
+        process(RetryOn::ExitCodes(BTreeSet::from([75]))),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(res.exit_code, 1);
+    assert_eq!(res.metadata.attempts, 2);
+}
+
+#[tokio::test]
+async fn infrastructure_errors_are_retried() {
+    let inner = MockCommandRunner::exiting(vec![
+        Err(ProcessError::Unclassified("connection reset".to_owned())),
+        Ok(1),
+        Ok(0),
+    ]);
+    let res = run(inner.clone(), process(RetryOn::InfrastructureErrors))
+        .await
+        .unwrap();
+
+    // The failing exit code is not retried.
+    assert_eq!(res.exit_code, 1);
+    assert_eq!(res.metadata.attempts, 2);
+}
+
+#[tokio::test]
+async fn missing_digests_are_not_retried() {
+    let inner = MockCommandRunner::exiting(vec![
+        Err(ProcessError::MissingDigest(
+            "missing".to_owned(),
+            EMPTY_DIGEST,
+        )),
+        Ok(0),
+    ]);
+    let res = run(inner.clone(), process(RetryOn::AnyFailure)).await;
+
+    assert!(matches!(res, Err(ProcessError::MissingDigest(..))));
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn user_errors_are_not_retried() {
+    let inner = MockCommandRunner::exiting(vec![
+        Err(EngineError::new(ErrorCode::InvalidPersistentWorker, "invalid").into()),
+        Ok(0),
+    ]);
+    let res = run(inner.clone(), process(RetryOn::InfrastructureErrors)).await;
+
+    assert_eq!(res.unwrap_err().code(), ErrorCode::InvalidPersistentWorker);
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn classified_infrastructure_errors_are_retried() {
+    let inner = MockCommandRunner::exiting(vec![
+        Err(EngineError::new(ErrorCode::RemoteStoreUnavailable, "unavailable").into()),
+        Ok(0),
+    ]);
+    let res = run(inner.clone(), process(RetryOn::InfrastructureErrors))
+        .await
+        .unwrap();
+
+    assert_eq!(res.exit_code, 0);
+    assert_eq!(res.metadata.attempts, 2);
+}
+
+#[test]
+fn backoff_doubles() {
+    let policy = ProcessRetryPolicy {
+        max_attempts: 4,
+        retry_on: RetryOn::AnyFailure,
+        backoff: Duration::from_millis(100),
+    };
+    assert_eq!(policy.backoff_before(2), Duration::from_millis(100));
+    assert_eq!(policy.backoff_before(3), Duration::from_millis(200));
+    assert_eq!(policy.backoff_before(4), Duration::from_millis(400));
+}
@@ -0,0 +1,164 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+const SANDBOX_EXEC_PATH: &str = "/usr/bin/sandbox-exec";
+
+/// Paths containing the system's own tools and libraries, which are readable by all sandboxed
+/// processes.
+const SYSTEM_READABLE_PATHS: &[&str] = &[
+    "/bin",
+    "/sbin",
+    "/usr",
+    "/System",
+    "/Library",
+    "/Applications/Xcode.app",
+    "/private/etc",
+    "/private/var/db/dyld",
+    "/private/var/db/timezone",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum_macros::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum SandboxExecMode {
+    /// Processes are not wrapped in `sandbox-exec`.
+    Off,
+    /// Filesystem accesses outside of the allowed paths are denied.
+    Enforce,
+    /// Filesystem accesses outside of the allowed paths are allowed, but are reported to the
+    /// system log: see `SandboxExec`.
+    Report,
+}
+
+///
+/// Wraps local processes in macOS's `sandbox-exec`, with a profile which restricts filesystem
+/// access to the sandbox of the process, the named caches and immutable inputs that it uses, the
+/// system's own tools and libraries, and any additionally allowed (tool) paths.
+///
+/// In `SandboxExecMode::Report`, accesses outside of those paths are not denied, but are reported
+/// as sandbox violations in the system log, where they can be viewed with:
+///
+///   log stream --style compact --predicate 'sender == "Sandbox"'
+///
+#[derive(Clone, Debug)]
+pub struct SandboxExec {
+    pub mode: SandboxExecMode,
+    /// Paths which are additionally readable by sandboxed processes, such as the locations of tools.
+    pub allowed_paths: Vec<PathBuf>,
+}
+
+impl SandboxExec {
+    ///
+    /// Returns a SandboxExec for the given mode (or None if it is `Off`), which will additionally
+    /// allow processes to read the given paths.
+    ///
+    pub fn new(
+        mode: SandboxExecMode,
+        allowed_paths: Vec<PathBuf>,
+    ) -> Result<Option<SandboxExec>, String> {
+        if mode == SandboxExecMode::Off {
+            return Ok(None);
+        }
+        if !cfg!(target_os = "macos") {
+            return Err(
+                "`--process-execution-local-sandbox-exec` is only supported on macOS.".to_owned(),
+            );
+        }
+        Ok(Some(SandboxExec {
+            mode,
+            allowed_paths,
+        }))
+    }
+
+    ///
+    /// Wraps the given argv to run in a sandbox which may write to the given workdir and writable
+    /// paths, and may read the given readable paths.
+    ///
+    pub(crate) fn wrap(
+        &self,
+        workdir: &Path,
+        writable_paths: &[&Path],
+        readable_paths: &[&Path],
+        argv: &[String],
+    ) -> Vec<String> {
+        let profile = self.profile(workdir, writable_paths, readable_paths, argv);
+        let mut wrapped = vec![
+            SANDBOX_EXEC_PATH.to_owned(),
+            "-p".to_owned(),
+            profile,
+            "--".to_owned(),
+        ];
+        wrapped.extend(argv.iter().cloned());
+        wrapped
+    }
+
+    pub(crate) fn profile(
+        &self,
+        workdir: &Path,
+        writable_paths: &[&Path],
+        readable_paths: &[&Path],
+        argv: &[String],
+    ) -> String {
+        let mut profile = "(version 1)\n(allow default)\n".to_owned();
+        match self.mode {
+            SandboxExecMode::Enforce => profile.push_str("(deny file-read* file-write*)\n"),
+            SandboxExecMode::Report => {
+                profile.push_str("(allow file-read* file-write* (with report))\n")
+            }
+            SandboxExecMode::Off => unreachable!("SandboxExec is not created when it is off."),
+        }
+        // Resolving paths requires reading the metadata of their parent directories.
+        profile.push_str("(allow file-read-metadata)\n");
+        profile.push_str("(allow file-read* (literal \"/\"))\n");
+        profile.push_str("(allow file-read* file-write* (subpath \"/dev\"))\n");
+
+        let mut readable = SYSTEM_READABLE_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .chain(readable_paths.iter().map(|p| p.to_path_buf()))
+            .chain(self.allowed_paths.iter().cloned())
+            .collect::<Vec<_>>();
+        // An absolute binary is a declared tool, and so is readable even if it is not below an
+        // allowed path.
+        if let Some(binary) = argv.first().map(Path::new).filter(|b| b.is_absolute()) {
+            readable.push(binary.to_owned());
+        }
+        let writable = std::iter::once(workdir)
+            .chain(writable_paths.iter().copied())
+            .map(Path::to_path_buf)
+            .collect::<Vec<_>>();
+
+        for (operations, paths) in [
+            ("file-read*", readable),
+            ("file-read* file-write*", writable),
+        ] {
+            let _ = write!(profile, "(allow {operations}");
+            for path in paths {
+                let _ = write!(profile, " (subpath {})", quote(&resolve(&path)));
+            }
+            profile.push_str(")\n");
+        }
+        profile
+    }
+}
+
+///
+/// The sandbox matches the resolved paths of files, so paths containing symlinks (such as `/tmp`,
+/// which on macOS is a symlink to `/private/tmp`) are resolved if they exist.
+///
+fn resolve(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+fn quote(path: &Path) -> String {
+    let mut quoted = String::from("\"");
+    for c in path.to_string_lossy().chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,82 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::path::{Path, PathBuf};
+
+use crate::sandbox_exec::{SandboxExec, SandboxExecMode};
+
+fn sandbox_exec(mode: SandboxExecMode) -> SandboxExec {
+    SandboxExec {
+        mode,
+        allowed_paths: vec![PathBuf::from("/opt/tools")],
+    }
+}
+
+#[test]
+fn off() {
+    assert!(SandboxExec::new(SandboxExecMode::Off, vec![])
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn only_supported_on_macos() {
+    let result = SandboxExec::new(SandboxExecMode::Enforce, vec![]);
+    if cfg!(target_os = "macos") {
+        assert!(result.unwrap().is_some());
+    } else {
+        assert!(result.unwrap_err().contains("only supported on macOS"));
+    }
+}
+
+#[test]
+fn wrap() {
+    let argv = vec!["/nonexistent/bin/tool".to_owned(), "--flag".to_owned()];
+    let wrapped = sandbox_exec(SandboxExecMode::Enforce).wrap(
+        Path::new("/nonexistent/sandbox"),
+        &[Path::new("/nonexistent/caches")],
+        &[Path::new("/nonexistent/immutable \"inputs\"")],
+        &argv,
+    );
+
+    assert_eq!(wrapped[0], "/usr/bin/sandbox-exec");
+    assert_eq!(wrapped[1], "-p");
+    assert_eq!(wrapped[3], "--");
+    assert_eq!(&wrapped[4..], &argv[..]);
+
+    let profile = &wrapped[2];
+    assert!(
+        profile.contains("(deny file-read* file-write*)\n"),
+        "{profile}"
+    );
+    assert!(
+        profile.contains(
+            "(allow file-read* file-write* (subpath \"/nonexistent/sandbox\") \
+             (subpath \"/nonexistent/caches\"))\n"
+        ),
+        "{profile}"
+    );
+    for readable in [
+        "(subpath \"/nonexistent/immutable \\\"inputs\\\"\")",
+        "(subpath \"/opt/tools\")",
+        "(subpath \"/nonexistent/bin/tool\")",
+    ] {
+        assert!(profile.contains(readable), "{profile}");
+    }
+}
+
+#[test]
+fn report() {
+    let profile = sandbox_exec(SandboxExecMode::Report).profile(
+        Path::new("/nonexistent/sandbox"),
+        &[],
+        &[],
+        &["tool".to_owned()],
+    );
+    assert!(!profile.contains("(deny"), "{profile}");
+    assert!(
+        profile.contains("(allow file-read* file-write* (with report))\n"),
+        "{profile}"
+    );
+    // Relative binaries are not made readable.
+    assert!(!profile.contains("\"tool\""), "{profile}");
+}
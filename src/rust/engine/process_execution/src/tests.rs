@@ -2,17 +2,25 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
 use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeSet;
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use crate::{
-    Platform, Process, ProcessExecutionEnvironment, ProcessExecutionStrategy,
-    ProcessResultMetadata, ProcessResultSource,
+    metadata_for_cache, output_glob_roots, CacheLocation, CommandRunner, Context,
+    FallibleProcessResultWithPlatform, Platform, Process, ProcessCacheScope, ProcessError,
+    ProcessExecutionEnvironment, ProcessExecutionStrategy, ProcessResultMetadata,
+    ProcessResultSource,
 };
+use async_trait::async_trait;
+use fs::{RelativePath, EMPTY_DIRECTORY_DIGEST};
+use hashing::EMPTY_DIGEST;
 use prost_types::Timestamp;
 use protos::gen::build::bazel::remote::execution::v2 as remexec;
 use remexec::ExecutedActionMetadata;
-use workunit_store::RunId;
+use workunit_store::{RunId, RunningWorkunit};
 
 #[test]
 fn process_equality() {
@@ -49,6 +57,34 @@ fn process_equality() {
     assert_ne!(hash(&a), hash(&d));
 }
 
+#[test]
+fn process_cache_scope_locations() {
+    use CacheLocation::{Local, Remote};
+
+    let scope = ProcessCacheScope::Successful;
+    assert!(scope.reads_from(Local) && scope.reads_from(Remote));
+    assert!(scope.writes_to(Local, 0) && scope.writes_to(Remote, 0));
+    assert!(!scope.writes_to(Local, 1) && !scope.writes_to(Remote, 1));
+
+    let scope = ProcessCacheScope::LocalAlways;
+    assert!(scope.reads_from(Local) && !scope.reads_from(Remote));
+    assert!(scope.writes_to(Local, 1) && !scope.writes_to(Remote, 1));
+
+    let scope = ProcessCacheScope::RemoteReadOnlySuccessful;
+    assert!(scope.reads_from(Local) && scope.reads_from(Remote));
+    assert!(scope.writes_to(Local, 0) && !scope.writes_to(Remote, 0));
+    assert!(!scope.writes_to(Local, 1));
+
+    let scope = ProcessCacheScope::PerRestartAlways;
+    assert!(scope.is_cacheable(1));
+    assert!(!scope.reads_from(Local) && !scope.reads_from(Remote));
+    assert!(!scope.writes_to(Local, 0) && !scope.writes_to(Remote, 0));
+
+    let scope = ProcessCacheScope::PerSession;
+    assert!(!scope.is_cacheable(0));
+    assert!(!scope.reads_from(Local) && !scope.writes_to(Local, 0));
+}
+
 #[test]
 fn process_result_metadata_to_and_from_executed_action_metadata() {
     let env = ProcessExecutionEnvironment {
@@ -114,6 +150,66 @@ fn process_result_metadata_to_and_from_executed_action_metadata() {
     assert_eq!(process_result_missing, ExecutedActionMetadata::default());
 }
 
+#[test]
+fn process_result_metadata_queue_and_execution_elapsed() {
+    let env = ProcessExecutionEnvironment {
+        name: None,
+        platform: Platform::Linux_x86_64,
+        strategy: ProcessExecutionStrategy::Local,
+    };
+    let timestamp = |seconds| Some(Timestamp { seconds, nanos: 0 });
+    let metadata = ProcessResultMetadata::new_from_metadata(
+        ExecutedActionMetadata {
+            queued_timestamp: timestamp(100),
+            worker_start_timestamp: timestamp(103),
+            execution_start_timestamp: timestamp(104),
+            execution_completed_timestamp: timestamp(109),
+            worker_completed_timestamp: timestamp(110),
+            ..ExecutedActionMetadata::default()
+        },
+        ProcessResultSource::Ran,
+        env,
+        RunId(0),
+    );
+
+    assert_eq!(
+        metadata.total_elapsed,
+        Some(concrete_time::Duration::new(7, 0))
+    );
+    assert_eq!(
+        metadata.queue_elapsed,
+        Some(concrete_time::Duration::new(3, 0))
+    );
+    assert_eq!(
+        metadata.execution_elapsed,
+        Some(concrete_time::Duration::new(5, 0))
+    );
+}
+
+#[test]
+fn process_result_metadata_cache_entry_age() {
+    let env = ProcessExecutionEnvironment {
+        name: None,
+        platform: Platform::Linux_x86_64,
+        strategy: ProcessExecutionStrategy::Local,
+    };
+    let ran = ProcessResultMetadata::new(None, ProcessResultSource::Ran, env.clone(), RunId(0));
+    let hit = ProcessResultMetadata::new_from_metadata(
+        metadata_for_cache(&ran),
+        ProcessResultSource::HitLocally,
+        env,
+        RunId(1),
+    );
+    assert!(hit.cache_entry_age.is_some());
+    assert!(!hit.is_stale(None));
+    assert!(!hit.is_stale(Some(Duration::from_secs(3600))));
+    assert!(hit.is_stale(Some(Duration::ZERO)));
+
+    // An entry of unknown age is always stale when a maximum age is set.
+    assert!(!ran.is_stale(None));
+    assert!(ran.is_stale(Some(Duration::from_secs(3600))));
+}
+
 #[test]
 fn process_result_metadata_time_saved_from_cache() {
     let env = ProcessExecutionEnvironment {
@@ -151,3 +247,103 @@ fn process_result_metadata_time_saved_from_cache() {
     metadata.update_cache_hit_elapsed(Duration::new(1, 100));
     assert_eq!(metadata.saved_by_cache, None);
 }
+
+#[test]
+fn output_glob_roots_are_literal_prefixes() {
+    let roots = |globs: &[&str]| {
+        output_glob_roots(&globs.iter().map(|g| g.to_string()).collect())
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>()
+    };
+    let relative = |p: &str| RelativePath::new(p).unwrap();
+
+    assert_eq!(vec![relative("dist")], roots(&["dist/**/*.whl"]));
+    assert_eq!(
+        vec![relative("dist/a")],
+        roots(&["dist/a/*.whl", "dist/a/b?/c"])
+    );
+    assert_eq!(vec![relative("dist")], roots(&["dist/a.whl"]));
+    assert_eq!(vec![relative("")], roots(&["*.whl", "a.whl"]));
+    assert_eq!(Vec::<RelativePath>::new(), roots(&["!dist/*.txt"]));
+    assert!(output_glob_roots(&BTreeSet::from(["../*.whl".to_owned()])).is_err());
+}
+
+///
+/// A response of a `MockCommandRunner` to one of its calls.
+///
+#[derive(Clone, Debug)]
+pub enum MockResponse {
+    Result(Result<FallibleProcessResultWithPlatform, ProcessError>),
+    /// The call never completes.
+    Hang,
+}
+
+///
+/// A CommandRunner which makes the given responses in turn, repeating the last one once they are
+/// exhausted, and which counts its calls.
+///
+#[derive(Debug)]
+pub struct MockCommandRunner {
+    responses: Vec<MockResponse>,
+    pub calls: AtomicUsize,
+}
+
+impl MockCommandRunner {
+    pub fn new(responses: Vec<MockResponse>) -> Arc<Self> {
+        Arc::new(Self {
+            responses,
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    ///
+    /// Returns results with the given exit codes (or errors) in turn.
+    ///
+    pub fn exiting(exit_codes: Vec<Result<i32, ProcessError>>) -> Arc<Self> {
+        Self::new(
+            exit_codes
+                .into_iter()
+                .map(|exit_code| MockResponse::Result(exit_code.map(mock_result)))
+                .collect(),
+        )
+    }
+}
+
+#[async_trait]
+impl CommandRunner for MockCommandRunner {
+    async fn run(
+        &self,
+        _context: Context,
+        _workunit: &mut RunningWorkunit,
+        _req: Process,
+    ) -> Result<FallibleProcessResultWithPlatform, ProcessError> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        match &self.responses[call.min(self.responses.len() - 1)] {
+            MockResponse::Result(result) => result.clone(),
+            MockResponse::Hang => futures::future::pending().await,
+        }
+    }
+
+    async fn shutdown(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+///
+/// A result with the given exit code, and empty outputs.
+///
+pub fn mock_result(exit_code: i32) -> FallibleProcessResultWithPlatform {
+    FallibleProcessResultWithPlatform {
+        stdout_digest: EMPTY_DIGEST,
+        stderr_digest: EMPTY_DIGEST,
+        exit_code,
+        output_directory: EMPTY_DIRECTORY_DIGEST.clone(),
+        metadata: ProcessResultMetadata::new(
+            None,
+            ProcessResultSource::Ran,
+            Process::new(vec![]).execution_environment,
+            RunId(0),
+        ),
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use log::warn;
+use workunit_store::{expect_workunit_store_handle, in_workunit, Level, RunningWorkunit};
+
+use crate::{Context, FallibleProcessResultWithPlatform, Process, ProcessError};
+
+///
+/// A CommandRunner which cancels and retries (once) any Process which makes no observable progress
+/// for longer than a threshold: see `WorkunitStore::stalled_for`.
+///
+/// Stalls are usually silent hangs in external tools, which a retry will often avoid. A Process
+/// which stalls on its retry is left to run.
+///
+pub struct CommandRunner {
+    inner: Arc<dyn crate::CommandRunner>,
+    threshold: Duration,
+}
+
+impl CommandRunner {
+    pub fn new(inner: Arc<dyn crate::CommandRunner>, threshold: Duration) -> CommandRunner {
+        CommandRunner { inner, threshold }
+    }
+
+    fn poll_interval(&self) -> Duration {
+        (self.threshold / 4).max(Duration::from_millis(1))
+    }
+}
+
+impl Debug for CommandRunner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("watchdog::CommandRunner")
+            .field("inner", &self.inner)
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl crate::CommandRunner for CommandRunner {
+    async fn run(
+        &self,
+        context: Context,
+        _workunit: &mut RunningWorkunit,
+        req: Process,
+    ) -> Result<FallibleProcessResultWithPlatform, ProcessError> {
+        let mut is_retry = false;
+        loop {
+            let (attempt_context, attempt_req) = (context.clone(), req.clone());
+            let result = in_workunit!(
+                "run_watched_process",
+                Level::Debug,
+                desc = Some(req.description.clone()),
+                |workunit| async move {
+                    let attempt = self.inner.run(attempt_context, workunit, attempt_req);
+                    if is_retry {
+                        return Some(attempt.await);
+                    }
+
+                    let store_handle = expect_workunit_store_handle();
+                    let span_id = store_handle
+                        .parent_id
+                        .expect("A workunit was started for the attempt.");
+                    let mut interval = tokio::time::interval(self.poll_interval());
+                    tokio::pin!(attempt);
+                    loop {
+                        tokio::select! {
+                            result = &mut attempt => break Some(result),
+                            _ = interval.tick() => {
+                                let stalled_for = store_handle.store.stalled_for(span_id);
+                                if stalled_for.map_or(false, |d| d >= self.threshold) {
+                                    // Dropping the attempt cancels it.
+                                    break None;
+                                }
+                            }
+                        }
+                    }
+                }
+            )
+            .await;
+
+            match result {
+                Some(result) => return result,
+                None => {
+                    warn!(
+                        "Cancelling and retrying `{}`, which made no progress for {:.1}s.",
+                        req.description,
+                        self.threshold.as_secs_f64()
+                    );
+                    is_retry = true;
+                }
+            }
+        }
+    }
+
+    async fn shutdown(&self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
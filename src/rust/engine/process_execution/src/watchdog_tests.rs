@@ -0,0 +1,61 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use workunit_store::WorkunitStore;
+
+use crate::tests::{mock_result, MockCommandRunner, MockResponse};
+use crate::watchdog::CommandRunner;
+use crate::{
+    CommandRunner as CommandRunnerTrait, Context, FallibleProcessResultWithPlatform, Process,
+    ProcessError,
+};
+
+/// Hangs for the given number of calls, and then succeeds.
+fn hanging(hanging_calls: usize) -> Arc<MockCommandRunner> {
+    let mut responses = vec![MockResponse::Hang; hanging_calls];
+    responses.push(MockResponse::Result(Ok(mock_result(0))));
+    MockCommandRunner::new(responses)
+}
+
+async fn run(
+    inner: Arc<MockCommandRunner>,
+) -> Result<FallibleProcessResultWithPlatform, ProcessError> {
+    let (_, mut workunit) = WorkunitStore::setup_for_tests();
+    CommandRunner::new(inner, Duration::from_millis(50))
+        .run(
+            Context::default(),
+            &mut workunit,
+            Process::new(vec!["/bin/hang".to_owned()]),
+        )
+        .await
+}
+
+#[tokio::test]
+async fn processes_which_complete_run_once() {
+    let inner = hanging(0);
+    let res = run(inner.clone()).await.unwrap();
+
+    assert_eq!(res.exit_code, 0);
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn stalled_processes_are_retried_once() {
+    let inner = hanging(1);
+    let res = run(inner.clone()).await.unwrap();
+
+    assert_eq!(res.exit_code, 0);
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn stalled_retries_are_left_to_run() {
+    let inner = hanging(2);
+    let res = tokio::time::timeout(Duration::from_millis(500), run(inner.clone())).await;
+
+    assert!(res.is_err());
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+}
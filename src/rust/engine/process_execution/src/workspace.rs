@@ -1,8 +1,6 @@
 // Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
-use std::ops::Neg;
-use std::os::unix::process::ExitStatusExt;
 use std::sync::Arc;
 use std::{
     fmt,
@@ -11,6 +9,7 @@ use std::{
 };
 
 use async_trait::async_trait;
+use engine_error::{EngineError, ErrorCode};
 use futures::stream::BoxStream;
 use futures::{FutureExt, StreamExt, TryFutureExt, TryStreamExt};
 use log::debug;
@@ -24,6 +23,7 @@ use workunit_store::{in_workunit, RunningWorkunit};
 
 use crate::fork_exec::spawn_process;
 use crate::{
+    children,
     local::{
         apply_chroot, create_sandbox, prepare_workdir, CapturedWorkdir, ChildOutput, KeepSandboxes,
     },
@@ -78,7 +78,7 @@ impl super::CommandRunner for CommandRunner {
             // NB: See engine::nodes::NodeKey::workunit_level for more information on why this workunit
             // renders at the Process's level.
             desc = Some(req.description.clone()),
-            |_workunit| async move {
+            |workunit| async move {
                 let tempdir = create_sandbox(
                     self.executor.clone(),
                     &self.work_dir_base,
@@ -106,6 +106,7 @@ impl super::CommandRunner for CommandRunner {
                 self.run_and_capture_workdir(
                     req.clone(),
                     context,
+                    workunit,
                     self.store.clone(),
                     self.executor.clone(),
                     tempdir.path().to_owned(),
@@ -121,8 +122,9 @@ impl super::CommandRunner for CommandRunner {
                     //
                     // Given that this is expected to be rare, we dump the entire process definition in the
                     // error.
-                    ProcessError::Unclassified(format!(
-                        "Failed to execute: {req_debug_repr}\n\n{msg}"
+                    ProcessError::from(EngineError::new(
+                        ErrorCode::ProcessExecutionFailed,
+                        format!("Failed to execute: {req_debug_repr}\n\n{msg}"),
                     ))
                 })
                 .await
@@ -170,6 +172,7 @@ impl CapturedWorkdir for CommandRunner {
             .args(&req.argv[1..])
             .current_dir(cwd)
             .envs(&req.env)
+            .envs(&req.globbed_env)
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
@@ -195,14 +198,7 @@ impl CapturedWorkdir for CommandRunner {
         let exit_stream = async move {
             child
                 .wait()
-                .map_ok(|exit_status| {
-                    ChildOutput::Exit(ExitCode(
-                        exit_status
-                            .code()
-                            .or_else(|| exit_status.signal().map(Neg::neg))
-                            .expect("Child process should exit via returned code or signal."),
-                    ))
-                })
+                .map_ok(|exit_status| ChildOutput::Exit(ExitCode(children::exit_code(exit_status))))
                 .await
         }
         .into_stream()
@@ -270,10 +270,12 @@ async fn main() {
           headers,
           chunk_size_bytes: args.upload_chunk_bytes,
           timeout: Duration::from_secs(30),
+          stream_timeout: Duration::from_secs(30),
           retries: args.store_rpc_retries,
           concurrency_limit: args.store_rpc_concurrency,
 
           batch_api_size_limit: args.store_batch_api_size_limit,
+          circuit_breaker: None,
         })
         .await
     }
@@ -342,6 +344,8 @@ async fn main() {
                 Duration::from_secs(args.overall_deadline_secs),
                 Duration::from_millis(100),
                 args.execution_rpc_concurrency,
+                0,
+                0,
             )
             .await
             .expect("Failed to make remote command runner");
@@ -363,6 +367,8 @@ async fn main() {
                             append_only_caches_base_path: args
                                 .named_cache_path
                                 .map(|p| p.to_string_lossy().to_string()),
+                            max_age: None,
+                            write_behind: None,
                         },
                         RemoteStoreOptions {
                             provider: RemoteProvider::Reapi,
@@ -372,9 +378,11 @@ async fn main() {
                             headers,
                             concurrency_limit: args.cache_rpc_concurrency,
                             timeout: Duration::from_secs(2),
+                            stream_timeout: Duration::from_secs(2),
                             retries: 0,
                             batch_api_size_limit: 0,
                             chunk_size_bytes: 0,
+                            circuit_breaker: None,
                         },
                     )
                     .await
@@ -394,6 +402,7 @@ async fn main() {
             ),
             ImmutableInputs::new(store.clone(), &workdir).unwrap(),
             KeepSandboxes::Never,
+            None,
             Arc::new(RwLock::new(())),
         )) as Box<dyn process_execution::CommandRunner>,
     };
@@ -536,20 +545,32 @@ async fn make_request_from_flat_args(
     let process = process_execution::Process {
         argv: args.command.argv.clone(),
         env: collection_from_keyvalues(args.command.env.iter()),
+        env_globs: BTreeSet::new(),
+        globbed_env: BTreeMap::new(),
         working_directory,
         input_digests,
         output_files,
         output_directories,
+        output_globs: BTreeSet::new(),
         timeout: Some(Duration::new(15 * 60, 0)),
         description: "process_executor".to_string(),
         level: Level::Info,
         append_only_caches: BTreeMap::new(),
+        append_only_cache_seeds: BTreeMap::new(),
+        incremental_outputs: None,
         jdk_home: args.command.jdk.clone(),
         execution_slot_variable: None,
         concurrency_available: args.command.concurrency_available.unwrap_or(0),
         cache_scope: ProcessCacheScope::Always,
+        tool_fingerprints: BTreeMap::new(),
         execution_environment,
         remote_cache_speculation_delay: Duration::from_millis(0),
+        remote_execution_priority: None,
+        remote_results_cache_priority: None,
+        persistent_worker: None,
+        cache_validation_argv: None,
+        stdin_digest: None,
+        retry_policy: None,
         attempt: 0,
     };
     let metadata = ProcessMetadata {
@@ -583,6 +604,9 @@ async fn extract_request_from_action_digest(
                 .to_string()
         })?
         .map_err(|err| format!("Error deserializing command proto {command_digest:?}: {err:?}"))?;
+    let env_globs = process_execution::env_globs(&command);
+    let output_globs = process_execution::output_globs(&command);
+    let tool_fingerprints = process_execution::tool_fingerprints(&command);
     let working_directory = if command.working_directory.is_empty() {
         None
     } else {
@@ -604,7 +628,7 @@ async fn extract_request_from_action_digest(
         .await
         .map_err(|e| e.to_string())?;
 
-    let process = process_execution::Process {
+    let mut process = process_execution::Process {
         argv: command.arguments,
         env: command
             .environment_variables
@@ -613,9 +637,14 @@ async fn extract_request_from_action_digest(
                 // Filter out environment variables which will be (re-)set by ExecutionRequest
                 // construction.
                 env.name != process_execution::CACHE_KEY_TARGET_PLATFORM_ENV_VAR_NAME
+                    && env.name != process_execution::CACHE_KEY_OUTPUT_GLOBS_ENV_VAR_NAME
+                    && env.name != process_execution::CACHE_KEY_TOOL_FINGERPRINTS_ENV_VAR_NAME
+                    && env.name != process_execution::CACHE_KEY_ENV_GLOBS_ENV_VAR_NAME
             })
             .map(|env| (env.name.clone(), env.value.clone()))
             .collect(),
+        env_globs,
+        globbed_env: BTreeMap::new(),
         working_directory,
         input_digests,
         output_files: command
@@ -628,6 +657,7 @@ async fn extract_request_from_action_digest(
             .iter()
             .map(RelativePath::new)
             .collect::<Result<_, _>>()?,
+        output_globs,
         timeout: action.timeout.map(|timeout| {
             Duration::from_nanos(timeout.nanos as u64 + timeout.seconds as u64 * 1000000000)
         }),
@@ -636,12 +666,24 @@ async fn extract_request_from_action_digest(
         description: "".to_string(),
         level: Level::Error,
         append_only_caches: BTreeMap::new(),
+        append_only_cache_seeds: BTreeMap::new(),
+        incremental_outputs: None,
         jdk_home: None,
         cache_scope: ProcessCacheScope::Always,
+        tool_fingerprints,
         execution_environment,
         remote_cache_speculation_delay: Duration::from_millis(0),
+        remote_execution_priority: None,
+        remote_results_cache_priority: None,
+        persistent_worker: None,
+        cache_validation_argv: None,
+        stdin_digest: None,
+        retry_policy: None,
         attempt: 0,
     };
+    // The values of globbed environment variables are not recorded in the Action, and so are
+    // resolved from the environment of this process.
+    process.resolve_env_globs(&std::env::vars().collect())?;
 
     let metadata = ProcessMetadata {
         instance_name,
@@ -19,18 +19,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     .compile_with_config(
       config,
       &[
+        "protos/bazelbuild_bazel/blaze/worker/worker_protocol.proto",
+        "protos/bazelbuild_bazel/build_event_stream/build_event_stream.proto",
+        "protos/bazelbuild_remote-apis/build/bazel/remote/asset/v1/remote_asset.proto",
         "protos/bazelbuild_remote-apis/build/bazel/remote/execution/v2/remote_execution.proto",
         "protos/bazelbuild_remote-apis/build/bazel/semver/semver.proto",
         "protos/buildbarn/cas.proto",
         "protos/googleapis/google/bytestream/bytestream.proto",
+        "protos/googleapis/google/devtools/build/v1/publish_build_event.proto",
         "protos/googleapis/google/rpc/code.proto",
         "protos/googleapis/google/rpc/error_details.proto",
         "protos/googleapis/google/rpc/status.proto",
         "protos/googleapis/google/longrunning/operations.proto",
         "protos/pants/cache.proto",
+        "protos/pants/sandboxer.proto",
         "protos/standard/google/protobuf/empty.proto",
       ],
       &[
+        "protos/bazelbuild_bazel",
         "protos/bazelbuild_remote-apis",
         "protos/buildbarn",
         "protos/googleapis",
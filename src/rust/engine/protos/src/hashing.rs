@@ -3,15 +3,46 @@
 use std::hash::{Hash, Hasher};
 
 use crate::gen::pants::cache::dependency_inference_request::Metadata;
-use crate::gen::pants::cache::JavascriptInferenceMetadata;
+use crate::gen::pants::cache::javascript_inference_metadata::ImportPattern;
+use crate::gen::pants::cache::{
+    CssInferenceMetadata, GoInferenceMetadata, JavascriptInferenceMetadata,
+};
+
+impl Hash for ImportPattern {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pattern.hash(state);
+        self.replacements.hash(state);
+        for conditional in &self.conditional_replacements {
+            conditional.conditions.hash(state);
+            conditional.replacement.hash(state);
+        }
+    }
+}
 
 impl Hash for JavascriptInferenceMetadata {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.package_root.hash(state);
-        for pattern in &self.import_patterns {
-            pattern.pattern.hash(state);
-            pattern.replacements.hash(state);
+        self.import_patterns.hash(state);
+        for config_file in &self.config_files {
+            config_file.path.hash(state);
+            config_file.content.hash(state);
         }
+        self.conditions.hash(state);
+        self.package_name.hash(state);
+        self.export_patterns.hash(state);
+        self.resolve_extensions.hash(state);
+    }
+}
+
+impl Hash for CssInferenceMetadata {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.package_root.hash(state);
+    }
+}
+
+impl Hash for GoInferenceMetadata {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.build_tags.hash(state);
     }
 }
 
@@ -19,6 +50,8 @@ impl Hash for Metadata {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
             Metadata::Js(m) => m.hash(state),
+            Metadata::Css(m) => m.hash(state),
+            Metadata::Go(m) => m.hash(state),
         }
     }
 }
@@ -19,6 +19,13 @@ pub mod gen {
         pub mod bytestream {
             tonic::include_proto!("google.bytestream");
         }
+        pub mod devtools {
+            pub mod build {
+                pub mod v1 {
+                    tonic::include_proto!("google.devtools.build.v1");
+                }
+            }
+        }
         pub mod longrunning {
             tonic::include_proto!("google.longrunning");
         }
@@ -26,9 +33,22 @@ pub mod gen {
             tonic::include_proto!("google.rpc");
         }
     }
+    pub mod blaze {
+        pub mod worker {
+            tonic::include_proto!("blaze.worker");
+        }
+    }
+    pub mod build_event_stream {
+        tonic::include_proto!("build_event_stream");
+    }
     pub mod build {
         pub mod bazel {
             pub mod remote {
+                pub mod asset {
+                    pub mod v1 {
+                        tonic::include_proto!("build.bazel.remote.asset.v1");
+                    }
+                }
                 pub mod execution {
                     pub mod v2 {
                         tonic::include_proto!("build.bazel.remote.execution.v2");
@@ -58,6 +78,9 @@ pub mod gen {
         pub mod cache {
             tonic::include_proto!("pants.cache");
         }
+        pub mod sandboxer {
+            tonic::include_proto!("pants.sandboxer");
+        }
     }
 }
 
@@ -37,9 +37,11 @@ fn remote_options() -> RemoteStoreOptions {
         headers: BTreeMap::new(),
         chunk_size_bytes: 10000,
         timeout: Duration::from_secs(5),
+        stream_timeout: Duration::from_secs(5),
         retries: 1,
         concurrency_limit: 256,
         batch_api_size_limit: 10000,
+        circuit_breaker: None,
     }
 }
 
@@ -33,9 +33,11 @@ fn remote_options() -> RemoteStoreOptions {
         headers: BTreeMap::new(),
         chunk_size_bytes: 10000,
         timeout: Duration::from_secs(5),
+        stream_timeout: Duration::from_secs(5),
         retries: 1,
         concurrency_limit: 256,
         batch_api_size_limit: 10000,
+        circuit_breaker: None,
     }
 }
 
@@ -112,7 +114,7 @@ async fn load_existing_wrong_digest_eror() {
         .expect_err("Want error");
 
     assert!(
-        error.contains("Remote CAS gave wrong digest"),
+        error.to_string().contains("Remote CAS gave wrong digest"),
         "Bad error message, got: {error}"
     )
 }
@@ -19,7 +19,7 @@ use tokio::fs::File;
 use workunit_store::{Metric, ObservationMetric};
 
 use remote_provider_traits::{
-    ActionCacheProvider, ByteStoreProvider, LoadDestination, RemoteStoreOptions,
+    ActionCacheProvider, ByteStoreProvider, LoadDestination, ProviderError, RemoteStoreOptions,
 };
 
 #[cfg(test)]
@@ -35,6 +35,16 @@ pub enum LoadMode {
     NoValidate,
 }
 
+/// Classifies an error from the operator: only errors which OpenDAL considers temporary (such as
+/// failures to connect, or server errors) or rate limiting are considered unavailability.
+fn provider_error(e: &opendal::Error, msg: String) -> ProviderError {
+    if e.is_temporary() || e.kind() == opendal::ErrorKind::RateLimited {
+        ProviderError::Unavailable(msg)
+    } else {
+        ProviderError::Other(msg)
+    }
+}
+
 pub struct Provider {
     operator: Operator,
     base_path: String,
@@ -133,7 +143,7 @@ impl Provider {
         digest: Digest,
         destination: &mut dyn LoadDestination,
         mode: LoadMode,
-    ) -> Result<bool, String> {
+    ) -> Result<bool, ProviderError> {
         // Some providers (e.g. GitHub Actions Cache) don't like storing an empty file, so we just magic
         // it up here, and ignore it when storing.
         if digest == EMPTY_DIGEST {
@@ -146,7 +156,12 @@ impl Provider {
         let mut reader = match self.operator.reader(&path).await {
             Ok(reader) => reader,
             Err(e) if e.kind() == opendal::ErrorKind::NotFound => return Ok(false),
-            Err(e) => return Err(format!("failed to read {}: {}", path, e)),
+            Err(e) => {
+                return Err(provider_error(
+                    &e,
+                    format!("failed to read {}: {}", path, e),
+                ))
+            }
         };
 
         // TODO: this pretends that the time-to-first-byte can be approximated by "time to create
@@ -167,7 +182,7 @@ impl Provider {
 
                 if !correct_digest {
                     // TODO: include the actual digest here
-                    return Err(format!("Remote CAS gave wrong digest: expected {digest:?}"));
+                    return Err(format!("Remote CAS gave wrong digest: expected {digest:?}").into());
                 }
             }
             LoadMode::NoValidate => {
@@ -188,7 +203,7 @@ impl Provider {
         &self,
         digest: Digest,
         destination: &mut dyn LoadDestination,
-    ) -> Result<bool, String> {
+    ) -> Result<bool, ProviderError> {
         self.load_raw(digest, destination, LoadMode::NoValidate)
             .await
     }
@@ -196,7 +211,7 @@ impl Provider {
 
 #[async_trait]
 impl ByteStoreProvider for Provider {
-    async fn store_bytes(&self, digest: Digest, bytes: Bytes) -> Result<(), String> {
+    async fn store_bytes(&self, digest: Digest, bytes: Bytes) -> Result<(), ProviderError> {
         // Some providers (e.g. GitHub Actions Cache) don't like storing an empty file, so we don't
         // store it here, and magic it up when loading.
         if digest == EMPTY_DIGEST {
@@ -212,11 +227,14 @@ impl ByteStoreProvider for Provider {
             // which ever execution won the race to create the item successfully finishes the write, and
             // so no wait + retry (or similar) here.
             Err(e) if e.kind() == opendal::ErrorKind::AlreadyExists => Ok(()),
-            Err(e) => Err(format!("failed to write bytes to {path}: {e}")),
+            Err(e) => Err(provider_error(
+                &e,
+                format!("failed to write bytes to {path}: {e}"),
+            )),
         }
     }
 
-    async fn store_file(&self, digest: Digest, mut file: File) -> Result<(), String> {
+    async fn store_file(&self, digest: Digest, mut file: File) -> Result<(), ProviderError> {
         // Some providers (e.g. GitHub Actions Cache) don't like storing an empty file, so we don't
         // store it here, and magic it up when loading.
         if digest == EMPTY_DIGEST {
@@ -232,21 +250,31 @@ impl ByteStoreProvider for Provider {
             // which ever execution won the race to create the item successfully finishes the write, and
             // so no wait + retry (or similar) here.
             Err(e) if e.kind() == opendal::ErrorKind::AlreadyExists => return Ok(()),
-            Err(e) => return Err(format!("failed to start write to {path}: {e} {}", e.kind())),
+            Err(e) => {
+                return Err(provider_error(
+                    &e,
+                    format!("failed to start write to {path}: {e} {}", e.kind()),
+                ))
+            }
         };
 
         // TODO: it would be good to pass through options.chunk_size_bytes here
         match tokio::io::copy(&mut file, &mut writer).await {
             Ok(_) => writer.close().await.map_err(|e| {
-                format!("Uploading file with digest {digest:?} to {path}: failed to commit: {e}")
+                provider_error(
+                    &e,
+                    format!(
+                        "Uploading file with digest {digest:?} to {path}: failed to commit: {e}"
+                    ),
+                )
             }),
             Err(e) => {
                 let abort_err = writer.abort().await.err().map_or("".to_owned(), |e| {
                     format!(" (additional error while aborting = {e})")
                 });
-                Err(format!(
+                Err(ProviderError::Other(format!(
           "Uploading file with digest {digest:?} to {path}: failed to copy: {e}{abort_err}"
-        ))
+        )))
             }
         }
     }
@@ -255,14 +283,14 @@ impl ByteStoreProvider for Provider {
         &self,
         digest: Digest,
         destination: &mut dyn LoadDestination,
-    ) -> Result<bool, String> {
+    ) -> Result<bool, ProviderError> {
         self.load_raw(digest, destination, LoadMode::Validate).await
     }
 
     async fn list_missing_digests(
         &self,
         digests: &mut (dyn Iterator<Item = Digest> + Send),
-    ) -> Result<HashSet<Digest>, String> {
+    ) -> Result<HashSet<Digest>, ProviderError> {
         // NB. this is doing individual requests and thus may be expensive.
         let existences = future::try_join_all(digests.map(|digest| async move {
             // Some providers (e.g. GitHub Actions Cache) don't like storing an empty file, so we don't
@@ -286,7 +314,10 @@ impl ByteStoreProvider for Provider {
             match result {
                 Ok(true) => Ok(None),
                 Ok(false) => Ok(Some(digest)),
-                Err(e) => Err(format!("failed to query {}: {}", path, e)),
+                Err(e) => Err(provider_error(
+                    &e,
+                    format!("failed to query {}: {}", path, e),
+                )),
             }
         }))
         .await?;
@@ -303,7 +334,9 @@ impl ActionCacheProvider for Provider {
         action_result: ActionResult,
     ) -> Result<(), String> {
         let bytes = action_result.to_bytes();
-        self.store_bytes(action_digest, bytes).await
+        self.store_bytes(action_digest, bytes)
+            .await
+            .map_err(String::from)
     }
     async fn get_action_result(
         &self,
@@ -4,7 +4,8 @@ use std::convert::TryInto;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use grpc_util::retry::{retry_call, status_is_retryable};
+use grpc_util::resilience::{CallType, Resilience};
+use grpc_util::retry::status_is_retryable;
 use grpc_util::{headers_to_http_header_map, layered_service, status_to_str, LayeredService};
 use hashing::Digest;
 use protos::gen::build::bazel::remote::execution::v2 as remexec;
@@ -20,37 +21,38 @@ use crate::apply_headers;
 pub struct Provider {
     instance_name: Option<String>,
     action_cache_client: Arc<ActionCacheClient<LayeredService>>,
+    resilience: Resilience,
 }
 
 impl Provider {
-    pub async fn new(
-        RemoteStoreOptions {
+    pub async fn new(options: RemoteStoreOptions) -> Result<Self, String> {
+        let resilience = Resilience::new(
+            "remote cache",
+            options.resilience_options(),
+            Some(Metric::RemoteCacheRequestTimeouts),
+        );
+        let RemoteStoreOptions {
             instance_name,
             store_address,
             tls_config,
             headers,
             concurrency_limit,
-            timeout,
             ..
-        }: RemoteStoreOptions,
-    ) -> Result<Self, String> {
+        } = options;
         let needs_tls = store_address.starts_with("https://");
 
         let tls_client_config = needs_tls.then(|| tls_config.try_into()).transpose()?;
 
         let channel = grpc_util::create_channel(&store_address, tls_client_config.as_ref()).await?;
         let http_headers = headers_to_http_header_map(&headers)?;
-        let channel = layered_service(
-            channel,
-            concurrency_limit,
-            http_headers,
-            Some((timeout, Metric::RemoteCacheRequestTimeouts)),
-        );
+        // NB: Deadlines are applied per-call by `Resilience`.
+        let channel = layered_service(channel, concurrency_limit, http_headers, None);
         let action_cache_client = Arc::new(ActionCacheClient::new(channel));
 
         Ok(Provider {
             instance_name,
             action_cache_client,
+            resilience,
         })
     }
 }
@@ -63,26 +65,28 @@ impl ActionCacheProvider for Provider {
         action_result: ActionResult,
     ) -> Result<(), String> {
         let client = self.action_cache_client.as_ref().clone();
-        retry_call(
-            client,
-            move |mut client, _| {
-                let update_action_cache_request = remexec::UpdateActionResultRequest {
-                    instance_name: self.instance_name.clone().unwrap_or_else(|| "".to_owned()),
-                    action_digest: Some(action_digest.into()),
-                    action_result: Some(action_result.clone()),
-                    ..remexec::UpdateActionResultRequest::default()
-                };
+        self.resilience
+            .call(
+                CallType::Write,
+                client,
+                move |mut client, _| {
+                    let update_action_cache_request = remexec::UpdateActionResultRequest {
+                        instance_name: self.instance_name.clone().unwrap_or_else(|| "".to_owned()),
+                        action_digest: Some(action_digest.into()),
+                        action_result: Some(action_result.clone()),
+                        ..remexec::UpdateActionResultRequest::default()
+                    };
 
-                async move {
-                    client
-                        .update_action_result(update_action_cache_request)
-                        .await
-                }
-            },
-            status_is_retryable,
-        )
-        .await
-        .map_err(status_to_str)?;
+                    async move {
+                        client
+                            .update_action_result(update_action_cache_request)
+                            .await
+                    }
+                },
+                status_is_retryable,
+            )
+            .await
+            .map_err(status_to_str)?;
 
         Ok(())
     }
@@ -93,20 +97,23 @@ impl ActionCacheProvider for Provider {
         build_id: &str,
     ) -> Result<Option<ActionResult>, String> {
         let client = self.action_cache_client.as_ref().clone();
-        let response = retry_call(
-            client,
-            move |mut client, _| {
-                let request = remexec::GetActionResultRequest {
-                    action_digest: Some(action_digest.into()),
-                    instance_name: self.instance_name.clone().unwrap_or_default(),
-                    ..remexec::GetActionResultRequest::default()
-                };
-                let request = apply_headers(Request::new(request), build_id);
-                async move { client.get_action_result(request).await }
-            },
-            status_is_retryable,
-        )
-        .await;
+        let response = self
+            .resilience
+            .call(
+                CallType::Read,
+                client,
+                move |mut client, _| {
+                    let request = remexec::GetActionResultRequest {
+                        action_digest: Some(action_digest.into()),
+                        instance_name: self.instance_name.clone().unwrap_or_default(),
+                        ..remexec::GetActionResultRequest::default()
+                    };
+                    let request = apply_headers(Request::new(request), build_id);
+                    async move { client.get_action_result(request).await }
+                },
+                status_is_retryable,
+            )
+            .await;
 
         match response {
             Ok(response) => Ok(Some(response.into_inner())),
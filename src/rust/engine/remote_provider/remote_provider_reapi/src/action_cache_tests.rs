@@ -18,9 +18,11 @@ async fn new_provider(cas: &StubCAS) -> Provider {
         headers: BTreeMap::new(),
         concurrency_limit: 256,
         timeout: Duration::from_secs(2),
+        stream_timeout: Duration::from_secs(2),
         retries: 0,
         batch_api_size_limit: 0,
         chunk_size_bytes: 0,
+        circuit_breaker: None,
     })
     .await
     .unwrap()
@@ -11,10 +11,9 @@ use async_oncecell::OnceCell;
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::{FutureExt, StreamExt};
-use grpc_util::retry::{retry_call, status_is_retryable};
-use grpc_util::{
-    headers_to_http_header_map, layered_service, status_ref_to_str, status_to_str, LayeredService,
-};
+use grpc_util::resilience::{status_is_unavailable, CallType, Resilience, RpcError};
+use grpc_util::retry::status_is_retryable;
+use grpc_util::{headers_to_http_header_map, layered_service, status_ref_to_str, LayeredService};
 use hashing::{Digest, Hasher};
 use protos::gen::build::bazel::remote::execution::v2 as remexec;
 use protos::gen::google::bytestream::byte_stream_client::ByteStreamClient;
@@ -29,12 +28,14 @@ use tokio::sync::Mutex;
 use tonic::{Code, Request, Status};
 use workunit_store::{Metric, ObservationMetric};
 
-use remote_provider_traits::{ByteStoreProvider, LoadDestination, RemoteStoreOptions};
+use remote_provider_traits::{
+    ByteStoreProvider, LoadDestination, ProviderError, RemoteStoreOptions,
+};
 
 pub struct Provider {
     instance_name: Option<String>,
     chunk_size_bytes: usize,
-    _rpc_attempts: usize,
+    resilience: Resilience,
     byte_stream_client: Arc<ByteStreamClient<LayeredService>>,
     cas_client: Arc<ContentAddressableStorageClient<LayeredService>>,
     capabilities_cell: Arc<OnceCell<ServerCapabilities>>,
@@ -61,6 +62,19 @@ impl ByteStoreError {
     }
 }
 
+impl RpcError for ByteStoreError {
+    fn from_status(status: Status) -> Self {
+        ByteStoreError::Grpc(status)
+    }
+
+    fn status(&self) -> Option<&Status> {
+        match self {
+            ByteStoreError::Grpc(status) => Some(status),
+            ByteStoreError::Other(_) => None,
+        }
+    }
+}
+
 impl fmt::Display for ByteStoreError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -72,10 +86,35 @@ impl fmt::Display for ByteStoreError {
 
 impl std::error::Error for ByteStoreError {}
 
+impl From<ByteStoreError> for ProviderError {
+    fn from(err: ByteStoreError) -> Self {
+        match err {
+            ByteStoreError::Grpc(status) => status_to_provider_error(status),
+            ByteStoreError::Other(msg) => ProviderError::Other(msg),
+        }
+    }
+}
+
+/// Classifies a failed RPC: only failures to reach the service (which tonic reports as
+/// `Unavailable`) and statuses which indicate that it is overloaded are considered unavailability.
+fn status_to_provider_error(status: Status) -> ProviderError {
+    if status_is_unavailable(&status) {
+        ProviderError::Unavailable(status_ref_to_str(&status))
+    } else {
+        ProviderError::Other(status_ref_to_str(&status))
+    }
+}
+
 impl Provider {
     // TODO: Consider extracting these options to a struct with `impl Default`, similar to
     // `super::LocalOptions`.
     pub async fn new(options: RemoteStoreOptions) -> Result<Provider, String> {
+        let resilience = Resilience::new(
+            "remote store",
+            options.resilience_options(),
+            Some(Metric::RemoteStoreRequestTimeouts),
+        );
+
         let tls_client_config = options
             .store_address
             .starts_with("https://")
@@ -85,12 +124,8 @@ impl Provider {
         let channel =
             grpc_util::create_channel(&options.store_address, tls_client_config.as_ref()).await?;
         let http_headers = headers_to_http_header_map(&options.headers)?;
-        let channel = layered_service(
-            channel,
-            options.concurrency_limit,
-            http_headers,
-            Some((options.timeout, Metric::RemoteStoreRequestTimeouts)),
-        );
+        // NB: Deadlines are applied per-call by `Resilience`.
+        let channel = layered_service(channel, options.concurrency_limit, http_headers, None);
 
         let byte_stream_client = Arc::new(ByteStreamClient::new(channel.clone()));
 
@@ -101,7 +136,7 @@ impl Provider {
         Ok(Provider {
             instance_name: options.instance_name,
             chunk_size_bytes: options.chunk_size_bytes,
-            _rpc_attempts: options.retries + 1,
+            resilience,
             byte_stream_client,
             cas_client,
             capabilities_cell: Arc::new(OnceCell::new()),
@@ -150,6 +185,12 @@ impl Provider {
         let error_occurred = Arc::new(parking_lot::Mutex::new(None));
         let error_occurred_stream = error_occurred.clone();
 
+        // Report the bytes sent by this attempt if the caller is tracking the transfer.
+        let progress = workunit_store::transfer_progress_if_in_workunit();
+        if let Some(progress) = &progress {
+            progress.reset();
+        }
+
         let chunk_size_bytes = self.chunk_size_bytes;
         let stream = async_stream::stream! {
           if len == 0 {
@@ -176,6 +217,9 @@ impl Provider {
               Ok(data) => {
                 let write_offset = num_seen_bytes as i64;
                 num_seen_bytes += data.len();
+                if let Some(progress) = &progress {
+                  progress.add_bytes(data.len() as u64);
+                }
                 yield protos::gen::google::bytestream::WriteRequest {
                   resource_name: resource_name.clone(),
                   write_offset,
@@ -249,11 +293,11 @@ impl Provider {
 
 #[async_trait]
 impl ByteStoreProvider for Provider {
-    async fn store_bytes(&self, digest: Digest, bytes: Bytes) -> Result<(), String> {
+    async fn store_bytes(&self, digest: Digest, bytes: Bytes) -> Result<(), ProviderError> {
         let len = digest.size_bytes;
 
         let max_batch_total_size_bytes = {
-            let capabilities = self.get_capabilities().await.map_err(|e| e.to_string())?;
+            let capabilities = self.get_capabilities().await?;
 
             capabilities
                 .cache_capabilities
@@ -266,52 +310,64 @@ impl ByteStoreProvider for Provider {
         let batch_api_allowed_by_server_config =
             max_batch_total_size_bytes == 0 || len < max_batch_total_size_bytes;
 
-        retry_call(
-            bytes,
-            move |bytes, _| async move {
-                if batch_api_allowed_by_local_config && batch_api_allowed_by_server_config {
-                    self.store_bytes_batch(digest, bytes).await
-                } else {
-                    self.store_source_stream(digest, Arc::new(Mutex::new(Cursor::new(bytes))))
-                        .await
-                }
-            },
-            ByteStoreError::is_retryable,
-        )
-        .await
-        .map_err(|e| e.to_string())
+        let use_batch_api = batch_api_allowed_by_local_config && batch_api_allowed_by_server_config;
+        let call_type = if use_batch_api {
+            CallType::Write
+        } else {
+            CallType::Stream
+        };
+        self.resilience
+            .call(
+                call_type,
+                bytes,
+                move |bytes, _| async move {
+                    if use_batch_api {
+                        self.store_bytes_batch(digest, bytes).await
+                    } else {
+                        self.store_source_stream(digest, Arc::new(Mutex::new(Cursor::new(bytes))))
+                            .await
+                    }
+                },
+                ByteStoreError::is_retryable,
+            )
+            .await
+            .map_err(ProviderError::from)
     }
 
-    async fn store_file(&self, digest: Digest, file: File) -> Result<(), String> {
+    async fn store_file(&self, digest: Digest, file: File) -> Result<(), ProviderError> {
         let source = Arc::new(Mutex::new(file));
-        retry_call(
-      source,
-      move |source, retry_attempt| async move {
-        if retry_attempt > 0 {
-          // if we're retrying, we need to go back to the start of the source to start the whole
-          // read fresh
-          source.lock().await.rewind().await.map_err(|err| {
-            ByteStoreError::Other(format!(
-              "Uploading file with digest {digest:?}: failed to rewind before retry {retry_attempt}: {err}"
-            ))
-          })?;
-        }
+        self.resilience
+            .call(
+                CallType::Stream,
+                source,
+                move |source, retry_attempt| async move {
+                    if retry_attempt > 0 {
+                        // if we're retrying, we need to go back to the start of the source to
+                        // start the whole read fresh
+                        source.lock().await.rewind().await.map_err(|err| {
+                            ByteStoreError::Other(format!(
+                                "Uploading file with digest {digest:?}: failed to rewind before \
+                                 retry {retry_attempt}: {err}"
+                            ))
+                        })?;
+                    }
 
-        // A file might be small enough to write via the batch API, but we ignore that possibility
-        // for now, because these are expected to stored in the FSDB, and thus large
-        self.store_source_stream(digest, source).await
-      },
-      ByteStoreError::is_retryable,
-    )
-    .await
-    .map_err(|e| e.to_string())
+                    // A file might be small enough to write via the batch API, but we ignore that
+                    // possibility for now, because these are expected to stored in the FSDB, and
+                    // thus large
+                    self.store_source_stream(digest, source).await
+                },
+                ByteStoreError::is_retryable,
+            )
+            .await
+            .map_err(ProviderError::from)
     }
 
     async fn load(
         &self,
         digest: Digest,
         destination: &mut dyn LoadDestination,
-    ) -> Result<bool, String> {
+    ) -> Result<bool, ProviderError> {
         let instance_name = self.instance_name.clone().unwrap_or_default();
         let resource_name = format!(
             "{}{}blobs/{}/{}",
@@ -331,68 +387,70 @@ impl ByteStoreProvider for Provider {
 
         let destination = Arc::new(Mutex::new(destination));
 
-        retry_call(
-            (client, request, destination),
-            move |(mut client, request, destination), retry_attempt| {
-                async move {
-                    let mut start_opt = Some(Instant::now());
-                    let response = client.read(request).await?;
-
-                    let mut stream = response.into_inner().inspect(|_| {
-                        // Record the observed time to receive the first response for this read.
-                        if let Some(start) = start_opt.take() {
-                            let timing: Result<u64, _> =
-                                Instant::now().duration_since(start).as_micros().try_into();
-
-                            if let Ok(obs) = timing {
-                                workunit_store::record_observation_if_in_workunit(
-                                    ObservationMetric::RemoteStoreTimeToFirstByteMicros,
-                                    obs,
-                                );
+        self.resilience
+            .call(
+                CallType::Stream,
+                (client, request, destination),
+                move |(mut client, request, destination), retry_attempt| {
+                    async move {
+                        let mut start_opt = Some(Instant::now());
+                        let response = client.read(request).await?;
+
+                        let mut stream = response.into_inner().inspect(|_| {
+                            // Record the observed time to receive the first response for this read.
+                            if let Some(start) = start_opt.take() {
+                                let timing: Result<u64, _> =
+                                    Instant::now().duration_since(start).as_micros().try_into();
+
+                                if let Ok(obs) = timing {
+                                    workunit_store::record_observation_if_in_workunit(
+                                        ObservationMetric::RemoteStoreTimeToFirstByteMicros,
+                                        obs,
+                                    );
+                                }
                             }
+                        });
+
+                        let mut writer = destination.lock().await;
+                        let mut hasher = Hasher::new();
+                        if retry_attempt > 0 {
+                            // if we're retrying, we need to clear out the destination to start the whole write
+                            // fresh
+                            writer.reset().await?;
                         }
-                    });
-
-                    let mut writer = destination.lock().await;
-                    let mut hasher = Hasher::new();
-                    if retry_attempt > 0 {
-                        // if we're retrying, we need to clear out the destination to start the whole write
-                        // fresh
-                        writer.reset().await?;
-                    }
-                    while let Some(response) = stream.next().await {
-                        let response = response?;
-                        writer.write_all(&response.data).await?;
-                        hasher.update(&response.data);
-                    }
-                    writer.shutdown().await?;
+                        while let Some(response) = stream.next().await {
+                            let response = response?;
+                            writer.write_all(&response.data).await?;
+                            hasher.update(&response.data);
+                        }
+                        writer.shutdown().await?;
 
-                    let actual_digest = hasher.finish();
-                    if actual_digest != digest {
-                        // Return an `internal` status to attempt retry.
-                        return Err(Status::internal(format!(
+                        let actual_digest = hasher.finish();
+                        if actual_digest != digest {
+                            // Return an `internal` status to attempt retry.
+                            return Err(Status::internal(format!(
               "Remote CAS gave wrong digest: expected {digest:?}, got {actual_digest:?}"
             )));
-                    }
+                        }
 
-                    Ok(())
-                }
-                .map(|read_result| match read_result {
-                    Ok(()) => Ok(true),
-                    Err(status) if status.code() == Code::NotFound => Ok(false),
-                    Err(err) => Err(err),
-                })
-            },
-            status_is_retryable,
-        )
-        .await
-        .map_err(|e| e.to_string())
+                        Ok(())
+                    }
+                    .map(|read_result| match read_result {
+                        Ok(()) => Ok(true),
+                        Err(status) if status.code() == Code::NotFound => Ok(false),
+                        Err(err) => Err(err),
+                    })
+                },
+                status_is_retryable,
+            )
+            .await
+            .map_err(status_to_provider_error)
     }
 
     async fn list_missing_digests(
         &self,
         digests: &mut (dyn Iterator<Item = Digest> + Send),
-    ) -> Result<HashSet<Digest>, String> {
+    ) -> Result<HashSet<Digest>, ProviderError> {
         let request = remexec::FindMissingBlobsRequest {
             instance_name: self.instance_name.as_ref().cloned().unwrap_or_default(),
             blob_digests: digests.into_iter().map(|d| d.into()).collect::<Vec<_>>(),
@@ -401,16 +459,19 @@ impl ByteStoreProvider for Provider {
         let client = self.cas_client.as_ref().clone();
 
         workunit_store::increment_counter_if_in_workunit(Metric::RemoteStoreExistsAttempts, 1);
-        let result = retry_call(
-            client,
-            move |mut client, _| {
-                let request = request.clone();
-                async move { client.find_missing_blobs(request).await }
-            },
-            status_is_retryable,
-        )
-        .await
-        .map_err(status_to_str);
+        let result = self
+            .resilience
+            .call(
+                CallType::Read,
+                client,
+                move |mut client, _| {
+                    let request = request.clone();
+                    async move { client.find_missing_blobs(request).await }
+                },
+                status_is_retryable,
+            )
+            .await
+            .map_err(status_to_provider_error);
 
         let metric = match result {
             Ok(_) => Metric::RemoteStoreExistsSuccesses,
@@ -425,6 +486,7 @@ impl ByteStoreProvider for Provider {
             .missing_blob_digests
             .iter()
             .map(|digest| digest.try_into())
-            .collect::<Result<HashSet<_>, _>>()
+            .collect::<Result<HashSet<_>, String>>()
+            .map_err(ProviderError::from)
     }
 }
@@ -10,9 +10,11 @@ use tempfile::TempDir;
 use testutil::data::TestData;
 use testutil::file::mk_tempfile;
 use tokio::fs::File;
-use workunit_store::WorkunitStore;
+use workunit_store::{in_workunit, Level, TransferDirection, TransferProgress, WorkunitStore};
 
-use remote_provider_traits::{ByteStoreProvider, RemoteProvider, RemoteStoreOptions};
+use remote_provider_traits::{
+    ByteStoreProvider, ProviderError, RemoteProvider, RemoteStoreOptions,
+};
 
 use crate::byte_store::Provider;
 
@@ -32,9 +34,11 @@ fn remote_options(
         headers: BTreeMap::new(),
         chunk_size_bytes,
         timeout: Duration::from_secs(5),
-        retries: 1,
+        stream_timeout: Duration::from_secs(5),
+        retries: 2,
         concurrency_limit: 256,
         batch_api_size_limit,
+        circuit_breaker: None,
     }
 }
 
@@ -118,7 +122,9 @@ async fn load_grpc_error() {
         .expect_err("Want error");
 
     assert!(
-        error.contains("StubCAS is configured to always fail"),
+        error
+            .to_string()
+            .contains("StubCAS is configured to always fail"),
         "Bad error message, got: {error}"
     );
     // retries:
@@ -147,9 +153,13 @@ async fn load_existing_wrong_digest_error() {
         .expect_err("Want error");
 
     assert!(
-        error.contains("Remote CAS gave wrong digest"),
+        error.to_string().contains("Remote CAS gave wrong digest"),
         "Bad error message, got: {error}"
-    )
+    );
+    assert!(
+        matches!(error, ProviderError::Other(_)),
+        "Want other, got: {error:?}"
+    );
 }
 
 fn assert_cas_store(cas: &StubCAS, testdata: &TestData, chunks: usize, chunk_size: usize) {
@@ -209,6 +219,45 @@ async fn store_file_multiple_chunks() {
     assert_cas_store(&cas, &testdata, 98, chunk_size)
 }
 
+#[tokio::test]
+async fn store_file_reports_progress() {
+    let _ = WorkunitStore::setup_for_tests();
+    let testdata = TestData::all_the_henries();
+
+    let cas = StubCAS::empty();
+    let chunk_size = 10 * 1024;
+    let provider = Provider::new(remote_options(
+        cas.address(),
+        chunk_size,
+        0, // disable batch API, force streaming API
+    ))
+    .await
+    .unwrap();
+
+    let progress = TransferProgress::new(
+        TransferDirection::Upload,
+        Some(testdata.bytes().len() as u64),
+    );
+    let (digest, file) = (
+        testdata.digest(),
+        mk_tempfile(Some(&testdata.bytes())).await,
+    );
+    let tracked = progress.clone();
+    in_workunit!("store", Level::Trace, |workunit| async move {
+        workunit.track_transfer(&tracked);
+        provider.store_file(digest, file).await
+    })
+    .await
+    .unwrap();
+
+    // The provider reported each chunk as it was sent.
+    assert_eq!(
+        progress.snapshot().bytes_done,
+        testdata.bytes().len() as u64
+    );
+    assert_cas_store(&cas, &testdata, 98, chunk_size)
+}
+
 #[tokio::test]
 async fn store_file_empty_file() {
     let testdata = TestData::empty();
@@ -240,7 +289,9 @@ async fn store_file_grpc_error() {
         .await
         .expect_err("Want err");
     assert!(
-        error.contains("StubCAS is configured to always fail"),
+        error
+            .to_string()
+            .contains("StubCAS is configured to always fail"),
         "Bad error message, got: {error}"
     );
 
@@ -270,9 +321,15 @@ async fn store_file_connection_error() {
         .await
         .expect_err("Want err");
     assert!(
-        error.contains("Unavailable: \"error trying to connect: dns error"),
+        error
+            .to_string()
+            .contains("Unavailable: \"error trying to connect: dns error"),
         "Bad error message, got: {error}"
     );
+    assert!(
+        matches!(error, ProviderError::Unavailable(_)),
+        "Want unavailable, got: {error:?}"
+    );
 }
 
 #[tokio::test]
@@ -289,7 +346,7 @@ async fn store_file_source_read_error_immediately() {
         .await
         .expect_err("Want err");
     assert!(
-        error.contains("Is a directory"),
+        error.to_string().contains("Is a directory"),
         "Bad error message, got: {error}",
     )
 }
@@ -357,7 +414,9 @@ async fn store_bytes_batch_grpc_error() {
         .await
         .expect_err("Want err");
     assert!(
-        error.contains("StubCAS is configured to always fail"),
+        error
+            .to_string()
+            .contains("StubCAS is configured to always fail"),
         "Bad error message, got: {error}"
     );
 
@@ -388,7 +447,9 @@ async fn store_bytes_write_stream_grpc_error() {
         .await
         .expect_err("Want err");
     assert!(
-        error.contains("StubCAS is configured to always fail"),
+        error
+            .to_string()
+            .contains("StubCAS is configured to always fail"),
         "Bad error message, got: {error}"
     );
 
@@ -415,9 +476,15 @@ async fn store_bytes_connection_error() {
         .await
         .expect_err("Want err");
     assert!(
-        error.contains("Unavailable: \"error trying to connect: dns error"),
+        error
+            .to_string()
+            .contains("Unavailable: \"error trying to connect: dns error"),
         "Bad error message, got: {error}"
     );
+    assert!(
+        matches!(error, ProviderError::Unavailable(_)),
+        "Want unavailable, got: {error:?}"
+    );
 }
 
 #[tokio::test]
@@ -464,7 +531,9 @@ async fn list_missing_digests_grpc_error() {
         .await
         .expect_err("Want error");
     assert!(
-        error.contains("StubCAS is configured to always fail"),
+        error
+            .to_string()
+            .contains("StubCAS is configured to always fail"),
         "Bad error message, got: {error}"
     );
     // retries:
@@ -13,6 +13,9 @@ pub mod action_cache_tests;
 pub mod byte_store;
 #[cfg(test)]
 pub mod byte_store_tests;
+pub mod remote_asset;
+#[cfg(test)]
+pub mod remote_asset_tests;
 
 /// Apply REAPI request metadata header to a `tonic::Request`.
 pub fn apply_headers<T>(mut request: Request<T>, build_id: &str) -> Request<T> {
@@ -0,0 +1,131 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use base64::Engine;
+use grpc_util::resilience::{CallType, Resilience};
+use grpc_util::retry::status_is_retryable;
+use grpc_util::{headers_to_http_header_map, layered_service, status_to_str, LayeredService};
+use hashing::Digest;
+use protos::gen::build::bazel::remote::asset::v1 as remote_asset;
+use protos::gen::build::bazel::remote::execution::v2 as remexec;
+use protos::require_digest;
+use remote_asset::fetch_client::FetchClient;
+use remote_provider_traits::{RemoteAssetProvider, RemoteStoreOptions};
+use tonic::{Code, Request};
+
+use crate::apply_headers;
+
+pub struct Provider {
+    instance_name: Option<String>,
+    fetch_client: Arc<FetchClient<LayeredService>>,
+    resilience: Resilience,
+}
+
+impl Provider {
+    pub async fn new(options: RemoteStoreOptions) -> Result<Self, String> {
+        let resilience = Resilience::new("remote asset", options.resilience_options(), None);
+        let RemoteStoreOptions {
+            instance_name,
+            store_address,
+            tls_config,
+            headers,
+            concurrency_limit,
+            ..
+        } = options;
+        let needs_tls = store_address.starts_with("https://");
+
+        let tls_client_config = needs_tls.then(|| tls_config.try_into()).transpose()?;
+
+        let channel = grpc_util::create_channel(&store_address, tls_client_config.as_ref()).await?;
+        let http_headers = headers_to_http_header_map(&headers)?;
+        // NB: Deadlines are applied per-call by `Resilience`.
+        let channel = layered_service(channel, concurrency_limit, http_headers, None);
+        let fetch_client = Arc::new(FetchClient::new(channel));
+
+        Ok(Provider {
+            instance_name,
+            fetch_client,
+            resilience,
+        })
+    }
+}
+
+///
+/// The Subresource Integrity (https://www.w3.org/TR/SRI/) checksum of content with the given
+/// digest, which is the standard qualifier for the expected content of a fetch.
+///
+pub(crate) fn checksum_sri(digest: Digest) -> String {
+    format!(
+        "sha256-{}",
+        base64::engine::general_purpose::STANDARD.encode(digest.hash.as_bytes())
+    )
+}
+
+#[async_trait]
+impl RemoteAssetProvider for Provider {
+    async fn fetch_blob(
+        &self,
+        urls: Vec<String>,
+        digest: Digest,
+        build_id: &str,
+    ) -> Result<bool, String> {
+        let client = self.fetch_client.as_ref().clone();
+        let response = self
+            .resilience
+            .call(
+                // NB: The server might fetch the content from its origin before responding, and so
+                // the duration of the call scales with the size of the content.
+                CallType::Stream,
+                client,
+                move |mut client, _| {
+                    let request = remote_asset::FetchBlobRequest {
+                        instance_name: self.instance_name.clone().unwrap_or_default(),
+                        uris: urls.clone(),
+                        qualifiers: vec![remote_asset::Qualifier {
+                            name: "checksum.sri".to_owned(),
+                            value: checksum_sri(digest),
+                        }],
+                        digest_function: remexec::digest_function::Value::Sha256 as i32,
+                        ..remote_asset::FetchBlobRequest::default()
+                    };
+                    let request = apply_headers(Request::new(request), build_id);
+                    async move { client.fetch_blob(request).await }
+                },
+                status_is_retryable,
+            )
+            .await;
+
+        let response = match response {
+            Ok(response) => response.into_inner(),
+            Err(status) if status.code() == Code::NotFound => return Ok(false),
+            Err(status) => return Err(status_to_str(status)),
+        };
+
+        // Errors which were outside of the server's control (such as the content not existing at
+        // any of the URLs) are reported inline.
+        if let Some(status) = response
+            .status
+            .filter(|status| status.code != Code::Ok as i32)
+        {
+            return match Code::from_i32(status.code) {
+                Code::NotFound | Code::PermissionDenied | Code::Aborted => Ok(false),
+                code => Err(format!(
+                    "Remote asset fetch of {} failed: {code:?}: {}",
+                    response.uri, status.message
+                )),
+            };
+        }
+
+        let blob_digest = require_digest(response.blob_digest.as_ref())?;
+        if blob_digest != digest {
+            return Err(format!(
+                "Remote asset fetch of {} returned digest {blob_digest:?}, but {digest:?} was expected.",
+                response.uri
+            ));
+        }
+        Ok(true)
+    }
+}
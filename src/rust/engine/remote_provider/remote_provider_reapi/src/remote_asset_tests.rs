@@ -0,0 +1,17 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use hashing::{Digest, EMPTY_DIGEST};
+
+use super::remote_asset::checksum_sri;
+
+#[test]
+fn checksum_sri_of_digest() {
+    assert_eq!(
+        checksum_sri(EMPTY_DIGEST),
+        "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+    );
+    assert_eq!(
+        checksum_sri(Digest::of_bytes(b"European Burmese")),
+        "sha256-aT2Nt7Bemca3p8BhZFYDnYnFVQKQJpNiSAhRk1WaC10="
+    );
+}
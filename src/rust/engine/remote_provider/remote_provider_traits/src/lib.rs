@@ -2,10 +2,12 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
 use std::collections::{BTreeMap, HashSet};
+use std::fmt;
 use std::time::Duration;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use grpc_util::resilience::{CircuitBreakerOptions, ResilienceOptions};
 use hashing::Digest;
 use protos::gen::build::bazel::remote::execution::v2 as remexec;
 use remexec::ActionResult;
@@ -33,10 +35,56 @@ pub struct RemoteStoreOptions {
     pub headers: BTreeMap<String, String>,
     pub tls_config: grpc_util::tls::Config,
     pub chunk_size_bytes: usize,
+    /// The deadline for each attempt of a unary RPC.
     pub timeout: Duration,
+    /// The deadline for each attempt of a streaming RPC.
+    pub stream_timeout: Duration,
     pub retries: usize,
     pub concurrency_limit: usize,
     pub batch_api_size_limit: usize,
+    pub circuit_breaker: Option<CircuitBreakerOptions>,
+}
+
+impl RemoteStoreOptions {
+    pub fn resilience_options(&self) -> ResilienceOptions {
+        ResilienceOptions {
+            read_deadline: self.timeout,
+            write_deadline: self.timeout,
+            stream_deadline: self.stream_timeout,
+            max_attempts: self.retries + 1,
+            circuit_breaker: self.circuit_breaker,
+        }
+    }
+}
+
+/// An error from a `ByteStoreProvider`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProviderError {
+    /// The remote store could not be reached, or was temporarily unable to serve the request (for
+    /// example, because it was overloaded or the request timed out). Retrying later might succeed.
+    Unavailable(String),
+    /// All other errors, which include invalid requests and invalid responses.
+    Other(String),
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unavailable(s) | Self::Other(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl From<String> for ProviderError {
+    fn from(err: String) -> Self {
+        Self::Other(err)
+    }
+}
+
+impl From<ProviderError> for String {
+    fn from(err: ProviderError) -> Self {
+        err.to_string()
+    }
 }
 
 #[async_trait]
@@ -44,13 +92,13 @@ pub trait ByteStoreProvider: Sync + Send + 'static {
     /// Store the bytes readable from `file` into the remote store
     ///
     /// NB. this does not need to update any observations or counters.
-    async fn store_file(&self, digest: Digest, file: File) -> Result<(), String>;
+    async fn store_file(&self, digest: Digest, file: File) -> Result<(), ProviderError>;
 
     /// Store the bytes in `bytes` into the remote store, as an optimisation of `store_file` when the
     /// bytes are already in memory
     ///
     /// NB. this does not need to update any observations or counters.
-    async fn store_bytes(&self, digest: Digest, bytes: Bytes) -> Result<(), String>;
+    async fn store_bytes(&self, digest: Digest, bytes: Bytes) -> Result<(), ProviderError>;
 
     /// Load the data stored (if any) in the remote store for `digest` into `destination`. Returns
     /// true when found, false when not.
@@ -61,7 +109,7 @@ pub trait ByteStoreProvider: Sync + Send + 'static {
         &self,
         digest: Digest,
         destination: &mut dyn LoadDestination,
-    ) -> Result<bool, String>;
+    ) -> Result<bool, ProviderError>;
 
     /// Return any digests from `digests` that are not (currently) available in the remote store.
     ///
@@ -70,7 +118,7 @@ pub trait ByteStoreProvider: Sync + Send + 'static {
     async fn list_missing_digests(
         &self,
         digests: &mut (dyn Iterator<Item = Digest> + Send),
-    ) -> Result<HashSet<Digest>, String>;
+    ) -> Result<HashSet<Digest>, ProviderError>;
 }
 
 /// Places that write the result of a remote `load`
@@ -112,3 +160,18 @@ pub trait ActionCacheProvider: Sync + Send + 'static {
         build_id: &str,
     ) -> Result<Option<ActionResult>, String>;
 }
+
+/// This `RemoteAssetProvider` trait captures the operations required to have a remote service (e.g.
+/// a REAPI Remote Asset server) fetch the content of URLs into the remote store on our behalf.
+#[async_trait]
+pub trait RemoteAssetProvider: Sync + Send + 'static {
+    /// Ask the remote service to fetch the content of any one of `urls` into the remote store,
+    /// verifying that it matches `digest`. Returns true when the content is available in the remote
+    /// store, and false when the service could not fetch it.
+    async fn fetch_blob(
+        &self,
+        urls: Vec<String>,
+        digest: Digest,
+        build_id: &str,
+    ) -> Result<bool, String>;
+}
@@ -6,7 +6,8 @@ use std::sync::Arc;
 // Re-export these so that consumers don't have to know about the exact arrangement of underlying
 // crates.
 pub use remote_provider_traits::{
-    ActionCacheProvider, ByteStoreProvider, LoadDestination, RemoteProvider, RemoteStoreOptions,
+    ActionCacheProvider, ByteStoreProvider, LoadDestination, ProviderError, RemoteAssetProvider,
+    RemoteProvider, RemoteStoreOptions,
 };
 
 // TODO(#19902): a unified view of choosing a provider would be nice
@@ -78,3 +79,16 @@ pub async fn choose_action_cache_provider(
         )),
     }
 }
+
+pub async fn choose_remote_asset_provider(
+    options: RemoteStoreOptions,
+) -> Result<Arc<dyn RemoteAssetProvider>, String> {
+    match options.provider {
+        RemoteProvider::Reapi => Ok(Arc::new(
+            remote_provider_reapi::remote_asset::Provider::new(options).await?,
+        )),
+        provider => Err(format!(
+            "The remote asset API is not supported by the {provider:?} remote provider."
+        )),
+    }
+}
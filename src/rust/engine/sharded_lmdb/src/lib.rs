@@ -16,6 +16,7 @@ use lmdb::{
     RwTransaction, Transaction, WriteFlags,
 };
 use log::trace;
+use parking_lot::RwLock;
 use tempfile::TempDir;
 
 ///
@@ -95,6 +96,10 @@ pub struct ShardedLmdb {
     lease_time: Duration,
     shard_count: u8,
     shard_fingerprint_mask: u8,
+    // Held for reading by all transactions, and for writing while resizing the map of an
+    // Environment, because LMDB requires that no transactions are active in the process when the
+    // map is resized.
+    resize_lock: Arc<RwLock<()>>,
 }
 
 impl ShardedLmdb {
@@ -106,11 +111,12 @@ impl ShardedLmdb {
     // with a different version of pants on a different schema version.
     pub const SCHEMA_VERSION: u8 = 2;
 
-    // max_size is the maximum size the databases together will be allowed to grow to.
-    // When calling this function, we will attempt to allocate that much virtual (not resident) memory
-    // for the mmap; in theory it should be possible not to bound this, but in practice we see travis
-    // occasionally fail tests because it's unable to allocate virtual memory if we set this too high,
-    // and we have too many tests running concurrently or close together.
+    // max_size is the initial size of the maps of the databases together. When calling this function,
+    // we will attempt to allocate that much virtual (not resident) memory for the mmap; in theory it
+    // should be possible not to bound this, but in practice we see travis occasionally fail tests
+    // because it's unable to allocate virtual memory if we set this too high, and we have too many
+    // tests running concurrently or close together. When a write would exceed the map of a shard,
+    // the map is grown and the write is retried: see `Self::with_map_growth`.
     pub fn new(
         root_path: PathBuf,
         max_size: usize,
@@ -125,19 +131,7 @@ impl ShardedLmdb {
         }
 
         let max_size_per_shard = max_size / (shard_count as usize);
-        // We select which shard to use by masking to select only the relevant number of high order bits
-        // from the high order byte of each stored key.
-        let shard_fingerprint_mask = {
-            // Create a mask of the appropriate width.
-            let mask_width = shard_count.trailing_zeros();
-            let mut mask = 0_u8;
-            for _ in 0..mask_width {
-                mask <<= 1;
-                mask |= 1;
-            }
-            // Then move it into the high order bits.
-            mask.rotate_left(Self::shard_shift(shard_count) as u32)
-        };
+        let shard_fingerprint_mask = Self::shard_fingerprint_mask(shard_count);
 
         trace!("Initializing ShardedLmdb at root {:?}", root_path);
         let mut lmdbs = HashMap::new();
@@ -145,14 +139,7 @@ impl ShardedLmdb {
         for (env, dir, environment_id) in
             ShardedLmdb::envs(&root_path, max_size_per_shard, shard_count)?
         {
-            let content_database = env
-                .create_db(Some("content-versioned"), DatabaseFlags::empty())
-                .map_err(|e| format!("Error creating/opening content database at {dir:?}: {e}"))?;
-
-            let lease_database = env
-                .create_db(Some("leases-versioned"), DatabaseFlags::empty())
-                .map_err(|e| format!("Error creating/opening content database at {dir:?}: {e}"))?;
-
+            let (content_database, lease_database) = ShardedLmdb::databases(&env, &dir)?;
             lmdbs.insert(
                 environment_id,
                 (
@@ -173,9 +160,26 @@ impl ShardedLmdb {
             lease_time,
             shard_count,
             shard_fingerprint_mask,
+            resize_lock: Arc::default(),
         })
     }
 
+    ///
+    /// We select which shard to use by masking to select only the relevant number of high order bits
+    /// from the high order byte of each stored key.
+    ///
+    fn shard_fingerprint_mask(shard_count: u8) -> u8 {
+        // Create a mask of the appropriate width.
+        let mask_width = shard_count.trailing_zeros();
+        let mut mask = 0_u8;
+        for _ in 0..mask_width {
+            mask <<= 1;
+            mask |= 1;
+        }
+        // Then move it into the high order bits.
+        mask.rotate_left(Self::shard_shift(shard_count) as u32)
+    }
+
     ///
     /// Return the left shift value that will place the relevant portion of a byte (for the given
     /// shard count, which is asserted in the constructor to be a power of two) into the high order
@@ -248,6 +252,19 @@ impl ShardedLmdb {
             .map_err(|e| format!("Error making env for store at {dir:?}: {e}"))
     }
 
+    // First Database is content, second is leases.
+    fn databases(env: &Environment, dir: &Path) -> Result<(Database, Database), String> {
+        let content_database = env
+            .create_db(Some("content-versioned"), DatabaseFlags::empty())
+            .map_err(|e| format!("Error creating/opening content database at {dir:?}: {e}"))?;
+
+        let lease_database = env
+            .create_db(Some("leases-versioned"), DatabaseFlags::empty())
+            .map_err(|e| format!("Error creating/opening content database at {dir:?}: {e}"))?;
+
+        Ok((content_database, lease_database))
+    }
+
     // First Database is content, second is leases.
     pub fn get(&self, fingerprint: &Fingerprint) -> (Arc<Environment>, Database, Database) {
         let (_, _, env, db1, db2) = self.get_raw(&fingerprint.0);
@@ -268,22 +285,71 @@ impl ShardedLmdb {
             .collect()
     }
 
+    ///
+    /// Runs the given transaction against the given Environment while holding the resize lock.
+    ///
+    /// If the map of the Environment is full, it is grown (to at least double its size, and by at
+    /// least twice `min_growth` bytes) and the transaction is retried once. If another process has
+    /// grown the map, the new size is adopted and the transaction is retried once.
+    ///
+    fn with_map_growth<T, E: MapError>(
+        &self,
+        env: &Environment,
+        min_growth: usize,
+        mut f: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let map_size = env.info().map(|info| info.map_size()).unwrap_or(0);
+        let res = {
+            let _guard = self.resize_lock.read();
+            f()
+        };
+        let err = match res {
+            Ok(t) => return Ok(t),
+            Err(err) => err,
+        };
+
+        let new_map_size = match err.lmdb_error() {
+            Some(lmdb::Error::MapFull) => std::cmp::max(map_size * 2, map_size + min_growth * 2),
+            // A size of zero adopts the size which was set by another process.
+            Some(lmdb::Error::MapResized) => 0,
+            _ => return Err(err),
+        };
+        {
+            let _guard = self.resize_lock.write();
+            // Another thread may have already grown the map while we waited for the lock.
+            let current_map_size = env.info().map(|info| info.map_size()).unwrap_or(0);
+            if new_map_size == 0 || current_map_size <= map_size {
+                if let Err(e) = env.set_map_size(new_map_size) {
+                    log::warn!("Failed to grow the map of {env:?} to {new_map_size} bytes: {e}");
+                    return Err(err);
+                }
+                log::debug!("Grew the map of {env:?} from {map_size} to {new_map_size} bytes.");
+            }
+        }
+
+        let _guard = self.resize_lock.read();
+        f()
+    }
+
     pub async fn remove(&self, fingerprint: Fingerprint) -> Result<bool, String> {
         let store = self.clone();
         self.executor
-            .spawn_blocking(
+            .spawn_blocking_fs(
                 move || {
                     let effective_key =
                         VersionedFingerprint::new(fingerprint, ShardedLmdb::SCHEMA_VERSION);
                     let (env, db, lease_database) = store.get(&fingerprint);
-                    let del_res = env.begin_rw_txn().and_then(|mut txn| {
-                        txn.del(db, &effective_key, None)?;
-                        txn.del(lease_database, &effective_key, None)
-                            .or_else(|err| match err {
-                                lmdb::Error::NotFound => Ok(()),
-                                err => Err(err),
-                            })?;
-                        txn.commit()
+                    let del_res = store.with_map_growth(&env, 0, || {
+                        env.begin_rw_txn().and_then(|mut txn| {
+                            txn.del(db, &effective_key, None)?;
+                            txn.del(lease_database, &effective_key, None).or_else(
+                                |err| match err {
+                                    lmdb::Error::NotFound => Ok(()),
+                                    err => Err(err),
+                                },
+                            )?;
+                            txn.commit()
+                        })
                     });
 
                     match del_res {
@@ -320,7 +386,7 @@ impl ShardedLmdb {
     ) -> Result<HashSet<Fingerprint>, String> {
         let store = self.clone();
         self.executor
-            .spawn_blocking(
+            .spawn_blocking_fs(
                 move || {
                     // Group the items by the Environment that they will be applied to.
                     let mut items_by_env = HashMap::new();
@@ -340,8 +406,9 @@ impl ShardedLmdb {
                     // Open and commit a Transaction per Environment. Since we never have more than one
                     // Transaction open at a time, we don't have to worry about ordering.
                     for (_, (env, db, batch)) in items_by_env {
-                        env.begin_ro_txn()
-                            .and_then(|txn| {
+                        store
+                            .with_map_growth(&env, 0, || {
+                                let txn = env.begin_ro_txn()?;
                                 for effective_key in &batch {
                                     let get_res = txn.get(db, &effective_key);
                                     match get_res {
@@ -378,8 +445,9 @@ impl ShardedLmdb {
     pub async fn all_fingerprints(&self) -> Result<Vec<AgedFingerprint>, String> {
         let store = self.clone();
         self.executor
-            .spawn_blocking(
+            .spawn_blocking_fs(
                 move || {
+                    let _guard = store.resize_lock.read();
                     let mut fingerprints = Vec::new();
                     for (env, database, lease_database) in &store.all_lmdbs() {
                         let txn = env.begin_ro_txn().map_err(|err| {
@@ -464,7 +532,7 @@ impl ShardedLmdb {
     ) -> Result<(), String> {
         let store = self.clone();
         self.executor
-            .spawn_blocking(
+            .spawn_blocking_fs(
                 move || {
                     // Group the items by the Environment that they will be applied to.
                     let mut items_by_env = HashMap::new();
@@ -484,8 +552,10 @@ impl ShardedLmdb {
                     // Open and commit a Transaction per Environment. Since we never have more than one
                     // Transaction open at a time, we don't have to worry about ordering.
                     for (_, (env, db, lease_database, batch)) in items_by_env {
-                        env.begin_rw_txn()
-                            .and_then(|mut txn| {
+                        let batch_bytes = batch.iter().map(|(_, bytes)| bytes.len()).sum();
+                        store
+                            .with_map_growth(&env, batch_bytes, || {
+                                let mut txn = env.begin_rw_txn()?;
                                 for (effective_key, bytes) in &batch {
                                     let put_res = txn.put(
                                         db,
@@ -548,7 +618,7 @@ impl ShardedLmdb {
     {
         let store = self.clone();
         self.executor
-            .spawn_blocking(
+            .spawn_blocking_fs(
                 move || {
                     let mut attempts = 0;
                     loop {
@@ -558,43 +628,46 @@ impl ShardedLmdb {
                         );
                         let (env, db, lease_database) = store.get(&expected_digest.hash);
                         let put_res: Result<(), StoreError> =
-                            env.begin_rw_txn()
-                                .map_err(StoreError::Lmdb)
-                                .and_then(|mut txn| {
-                                    // Second pass: copy into the reserved memory.
-                                    let mut writer = txn
-                                        .reserve(
-                                            db,
-                                            &effective_key,
-                                            expected_digest.size_bytes,
-                                            WriteFlags::NO_OVERWRITE,
-                                        )?
-                                        .writer();
-                                    let mut read = data_provider()
-                                        .map_err(|e| format!("Failed to read: {e}"))?;
-                                    let should_retry =
+                            store.with_map_growth(&env, expected_digest.size_bytes, || {
+                                env.begin_rw_txn()
+                                    .map_err(StoreError::Lmdb)
+                                    .and_then(|mut txn| {
+                                        // Second pass: copy into the reserved memory.
+                                        let mut writer = txn
+                                            .reserve(
+                                                db,
+                                                &effective_key,
+                                                expected_digest.size_bytes,
+                                                WriteFlags::NO_OVERWRITE,
+                                            )?
+                                            .writer();
+                                        let mut read = data_provider()
+                                            .map_err(|e| format!("Failed to read: {e}"))?;
+                                        let should_retry =
                   !sync_verified_copy(expected_digest, data_is_immutable, &mut read, &mut writer)
                     .map_err(|e| {
                     format!("Failed to copy from {read:?} or store in {env:?}: {e:?}")
                   })?;
 
-                                    if should_retry {
-                                        let msg = format!("Input {read:?} changed while reading.");
-                                        log::debug!("{}", msg);
-                                        return Err(StoreError::Retry(msg));
-                                    }
+                                        if should_retry {
+                                            let msg =
+                                                format!("Input {read:?} changed while reading.");
+                                            log::debug!("{}", msg);
+                                            return Err(StoreError::Retry(msg));
+                                        }
 
-                                    if initial_lease {
-                                        store.lease_inner(
-                                            lease_database,
-                                            &effective_key,
-                                            store.lease_until_secs_since_epoch(),
-                                            &mut txn,
-                                        )?;
-                                    }
-                                    txn.commit()?;
-                                    Ok(())
-                                });
+                                        if initial_lease {
+                                            store.lease_inner(
+                                                lease_database,
+                                                &effective_key,
+                                                store.lease_until_secs_since_epoch(),
+                                                &mut txn,
+                                            )?;
+                                        }
+                                        txn.commit()?;
+                                        Ok(())
+                                    })
+                            });
 
                         match put_res {
                             Ok(()) => return Ok(()),
@@ -625,12 +698,13 @@ impl ShardedLmdb {
     pub async fn lease(&self, fingerprint: Fingerprint) -> Result<(), String> {
         let store = self.clone();
         self.executor
-            .spawn_blocking(
+            .spawn_blocking_fs(
                 move || {
                     let until_secs_since_epoch: u64 = store.lease_until_secs_since_epoch();
                     let (env, _, lease_database) = store.get(&fingerprint);
-                    env.begin_rw_txn()
-                        .and_then(|mut txn| {
+                    store
+                        .with_map_growth(&env, 0, || {
+                            let mut txn = env.begin_rw_txn()?;
                             store.lease_inner(
                                 lease_database,
                                 &VersionedFingerprint::new(
@@ -682,27 +756,113 @@ impl ShardedLmdb {
         let store = self.clone();
         let effective_key = VersionedFingerprint::new(fingerprint, ShardedLmdb::SCHEMA_VERSION);
         self.executor
-            .spawn_blocking(
+            .spawn_blocking_fs(
                 move || {
                     let (env, db, _) = store.get(&fingerprint);
-                    let ro_txn = env
-                        .begin_ro_txn()
-                        .map_err(|err| format!("Failed to begin read transaction: {err}"))?;
-                    match ro_txn.get(db, &effective_key) {
-                        Ok(bytes) => f(bytes).map(Some),
-                        Err(lmdb::Error::NotFound) => Ok(None),
-                        Err(err) => Err(format!(
-                            "Error loading versioned key {:?}: {}",
-                            effective_key.to_hex(),
-                            err,
-                        )),
-                    }
+                    store
+                        .with_map_growth(&env, 0, || {
+                            let ro_txn = env.begin_ro_txn()?;
+                            match ro_txn.get(db, &effective_key) {
+                                Ok(bytes) => Ok(Some(f(bytes))),
+                                Err(lmdb::Error::NotFound) => Ok(None),
+                                Err(err) => Err(err),
+                            }
+                        })
+                        .map_err(|err| {
+                            format!(
+                                "Error loading versioned key {:?}: {}",
+                                effective_key.to_hex(),
+                                err,
+                            )
+                        })?
+                        .transpose()
                 },
                 |e| Err(format!("`load_bytes_with` task failed: {e}")),
             )
             .await
     }
 
+    ///
+    /// Loads a batch of fingerprints while pinning one read transaction per shard, so that all of the
+    /// loads observe a consistent snapshot of the store (even under concurrent writes), and so that
+    /// large batches (such as all of the files in a large tree) do not repeatedly open transactions.
+    ///
+    /// The given function is called with the index of each fingerprint which is present, and the
+    /// results are returned in the order of the given fingerprints.
+    ///
+    pub async fn load_bytes_batch_with<
+        T: Send + 'static,
+        F: FnMut(usize, &[u8]) -> Result<T, String> + Send + 'static,
+    >(
+        &self,
+        fingerprints: Vec<Fingerprint>,
+        mut f: F,
+    ) -> Result<Vec<Option<T>>, String> {
+        let store = self.clone();
+        self.executor
+            .spawn_blocking_fs(
+                move || {
+                    // Group the fingerprints by the Environment that they will be loaded from.
+                    let mut indices_by_env = HashMap::new();
+                    for (index, fingerprint) in fingerprints.iter().enumerate() {
+                        let (env_id, _, env, db, _) = store.get_raw(&fingerprint.0);
+                        let (_, _, indices) = indices_by_env
+                            .entry(*env_id)
+                            .or_insert_with(|| (env.clone(), *db, vec![]));
+                        indices.push(index);
+                    }
+
+                    let mut results = fingerprints.iter().map(|_| None).collect::<Vec<_>>();
+                    for (_, (env, db, indices)) in indices_by_env {
+                        let loaded = store
+                            .with_map_growth(&env, 0, || {
+                                let ro_txn = env.begin_ro_txn()?;
+                                let mut loaded = Vec::with_capacity(indices.len());
+                                for &index in &indices {
+                                    let effective_key = VersionedFingerprint::new(
+                                        fingerprints[index],
+                                        ShardedLmdb::SCHEMA_VERSION,
+                                    );
+                                    match ro_txn.get(db, &effective_key) {
+                                        Ok(bytes) => loaded.push((index, f(index, bytes))),
+                                        Err(lmdb::Error::NotFound) => (),
+                                        Err(err) => return Err(err),
+                                    }
+                                }
+                                Ok(loaded)
+                            })
+                            .map_err(|err| format!("Error loading versioned keys: {err}"))?;
+                        for (index, res) in loaded {
+                            results[index] = Some(res?);
+                        }
+                    }
+                    Ok(results)
+                },
+                |e| Err(format!("`load_bytes_batch_with` task failed: {e}")),
+            )
+            .await
+    }
+
+    ///
+    /// The number of bytes used by the data files of all shards: this includes free pages which have
+    /// not been reclaimed by `compact`, but excludes space which is reserved but unused.
+    ///
+    pub fn used_bytes(&self) -> Result<usize, String> {
+        let mut total = 0;
+        for (_, dir, env, _, _) in self.lmdbs.values() {
+            let page_size = env
+                .stat()
+                .map_err(|e| format!("Failed to stat store at {dir:?}: {e}"))?
+                .page_size() as usize;
+            let last_page = env
+                .info()
+                .map_err(|e| format!("Failed to get info for store at {dir:?}: {e}"))?
+                .last_pgno();
+            total += (last_page + 1) * page_size;
+        }
+        Ok(total)
+    }
+
     #[allow(clippy::useless_conversion)] // False positive: https://github.com/rust-lang/rust-clippy/issues/3913
     pub fn compact(&self) -> Result<(), String> {
         for (env, old_dir, _) in
@@ -734,6 +894,106 @@ impl ShardedLmdb {
         }
         Ok(())
     }
+
+    ///
+    /// Moves the content and leases of the store at `root_path` from `from_shard_count` shards into
+    /// `to_shard_count` shards. Increasing the shard count splits each shard by the next bit of the
+    /// fingerprints that it contains, which rebalances a store with disproportionately large shards.
+    ///
+    /// This must be run offline: no other instance may have the store open while it runs.
+    ///
+    pub fn reshard(
+        root_path: &Path,
+        from_shard_count: u8,
+        to_shard_count: u8,
+    ) -> Result<(), String> {
+        for shard_count in [from_shard_count, to_shard_count] {
+            if shard_count.count_ones() != 1 {
+                return Err(format!(
+                    "The shard_count must be a power of two: got {shard_count}."
+                ));
+            }
+        }
+        if from_shard_count == to_shard_count {
+            return Ok(());
+        }
+
+        let shard_dir = |root: &Path, b: u8| root.join(format!("{b:x}"));
+        // Every new shard is opened with a map large enough to hold the entire existing store.
+        let total_bytes: usize = (0..from_shard_count)
+            .map(|b| {
+                std::fs::metadata(shard_dir(root_path, b).join("data.mdb"))
+                    .map(|metadata| metadata.len() as usize)
+                    .unwrap_or(0)
+            })
+            .sum();
+        let map_size = std::cmp::max(2 * total_bytes, 1024 * 1024);
+
+        let staging_path = root_path.join("reshard");
+        if staging_path.exists() {
+            std::fs::remove_dir_all(&staging_path)
+                .map_err(|e| format!("Error removing {staging_path:?}: {e}"))?;
+        }
+        let open = |root: &Path, shard_count: u8| {
+            ShardedLmdb::envs(root, map_size, shard_count)?
+                .into_iter()
+                .map(|(env, dir, environment_id)| {
+                    let (content_database, lease_database) = ShardedLmdb::databases(&env, &dir)?;
+                    Ok((env, content_database, lease_database, environment_id))
+                })
+                .collect::<Result<Vec<_>, String>>()
+        };
+        let from_lmdbs = open(root_path, from_shard_count)?;
+        let to_lmdbs = open(&staging_path, to_shard_count)?;
+
+        let to_shard_fingerprint_mask = Self::shard_fingerprint_mask(to_shard_count);
+        for (to_env, to_database, to_lease_database, to_environment_id) in &to_lmdbs {
+            to_env
+                .begin_rw_txn()
+                .and_then(|mut to_txn| {
+                    for (from_env, from_database, from_lease_database, _) in &from_lmdbs {
+                        let from_txn = from_env.begin_ro_txn()?;
+                        let mut cursor = from_txn.open_ro_cursor(*from_database)?;
+                        for key_res in cursor.iter() {
+                            let (key, bytes) = key_res?;
+                            if EnvironmentId(key[0] & to_shard_fingerprint_mask)
+                                != *to_environment_id
+                            {
+                                continue;
+                            }
+                            to_txn.put(*to_database, &key, &bytes, WriteFlags::empty())?;
+                            match from_txn.get(*from_lease_database, &key) {
+                                Ok(lease) => to_txn.put(
+                                    *to_lease_database,
+                                    &key,
+                                    &lease,
+                                    WriteFlags::empty(),
+                                )?,
+                                Err(lmdb::Error::NotFound) => (),
+                                Err(err) => return Err(err),
+                            }
+                        }
+                    }
+                    to_txn.commit()
+                })
+                .map_err(|e| format!("Error resharding store at {root_path:?}: {e}"))?;
+        }
+        std::mem::drop(from_lmdbs);
+        std::mem::drop(to_lmdbs);
+
+        for b in 0..from_shard_count {
+            let dir = shard_dir(root_path, b);
+            std::fs::remove_dir_all(&dir)
+                .map_err(|e| format!("Error removing old store at {dir:?}: {e}"))?;
+        }
+        for b in 0..to_shard_count {
+            let (src, dst) = (shard_dir(&staging_path, b), shard_dir(root_path, b));
+            std::fs::rename(&src, &dst)
+                .map_err(|e| format!("Error replacing {dst:?} with {src:?}: {e}"))?;
+        }
+        std::fs::remove_dir_all(&staging_path)
+            .map_err(|e| format!("Error removing {staging_path:?}: {e}"))
+    }
 }
 
 enum StoreError {
@@ -742,6 +1002,26 @@ enum StoreError {
     Retry(String),
 }
 
+/// An error which may indicate that the map of an Environment needs to be resized.
+trait MapError {
+    fn lmdb_error(&self) -> Option<&lmdb::Error>;
+}
+
+impl MapError for lmdb::Error {
+    fn lmdb_error(&self) -> Option<&lmdb::Error> {
+        Some(self)
+    }
+}
+
+impl MapError for StoreError {
+    fn lmdb_error(&self) -> Option<&lmdb::Error> {
+        match self {
+            Self::Lmdb(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
 impl From<lmdb::Error> for StoreError {
     fn from(err: lmdb::Error) -> Self {
         Self::Lmdb(err)
@@ -3,7 +3,7 @@
 use std::collections::HashMap;
 
 use bytes::{Buf, Bytes};
-use hashing::Digest;
+use hashing::{Digest, Fingerprint};
 use parking_lot::Mutex;
 use task_executor::Executor;
 use tempfile::TempDir;
@@ -92,6 +92,100 @@ async fn store_failure() {
     assert!(result.is_err());
 }
 
+fn items(count: u8, len: usize) -> Vec<(Fingerprint, Bytes)> {
+    (0..count)
+        .map(|b| {
+            let bytes = Bytes::from(vec![b; len]);
+            (Digest::of_bytes(&bytes).hash, bytes)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn map_grows_on_demand() {
+    let tempdir = TempDir::new().unwrap();
+    let s = ShardedLmdb::new(
+        tempdir.path().to_owned(),
+        1024 * 1024,
+        Executor::new(),
+        DEFAULT_LEASE_TIME,
+        1,
+    )
+    .unwrap();
+
+    // Store more than the initial size of the map: first in a single batch, and then in many
+    // smaller writes.
+    let items = items(32, 256 * 1024);
+    s.store_bytes_batch(items[..8].to_vec(), true)
+        .await
+        .unwrap();
+    for (fingerprint, bytes) in &items[8..] {
+        s.store_bytes(*fingerprint, bytes.clone(), true)
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(s.all_fingerprints().await.unwrap().len(), items.len());
+}
+
+#[tokio::test]
+async fn reshard() {
+    let (s, tempdir) = new_store(1);
+    let items = items(255, 100);
+    s.store_bytes_batch(items.clone(), true).await.unwrap();
+    std::mem::drop(s);
+
+    ShardedLmdb::reshard(tempdir.path(), 1, 4).unwrap();
+
+    let s = ShardedLmdb::new(
+        tempdir.path().to_owned(),
+        15_000_000,
+        Executor::new(),
+        DEFAULT_LEASE_TIME,
+        4,
+    )
+    .unwrap();
+    for (fingerprint, bytes) in items.clone() {
+        assert_eq!(
+            s.load_bytes_with(fingerprint, |b| Ok(Bytes::copy_from_slice(b)))
+                .await
+                .unwrap(),
+            Some(bytes)
+        );
+    }
+    // Leases are preserved.
+    let fingerprints = s.all_fingerprints().await.unwrap();
+    assert_eq!(fingerprints.len(), items.len());
+    assert!(fingerprints
+        .iter()
+        .all(|fingerprint| fingerprint.expired_seconds_ago == 0));
+}
+
+#[tokio::test]
+async fn load_bytes_batch_with() {
+    let (s, _tempdir) = new_store(4);
+    let items = items(16, 100);
+    s.store_bytes_batch(items[..8].to_vec(), true)
+        .await
+        .unwrap();
+
+    // The results are in the order of the request, and are None for missing entries.
+    let fingerprints = items.iter().rev().map(|(f, _)| *f).collect::<Vec<_>>();
+    let results = s
+        .load_bytes_batch_with(fingerprints, |index, bytes| {
+            Ok((index, Bytes::copy_from_slice(bytes)))
+        })
+        .await
+        .unwrap();
+    let expected = items
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, (_, bytes))| (index >= 8).then(|| (index, bytes.clone())))
+        .collect::<Vec<_>>();
+    assert_eq!(results, expected);
+}
+
 fn bytes(content: u8) -> Bytes {
     Bytes::from(vec![content; 100])
 }
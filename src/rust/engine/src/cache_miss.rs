@@ -0,0 +1,429 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fs::{DigestTrie, Entry, SymlinkBehavior};
+use hashing::Digest;
+use parking_lot::Mutex;
+use process_execution::Process;
+use serde::{Deserialize, Serialize};
+
+// The number of runs to retain records for.
+const MAX_RECORDED_RUNS: usize = 10;
+
+///
+/// The fingerprint of a process which was requested during a run: the digest of the Action that it
+/// is cached under, along with the inputs to that Action which most commonly change between runs.
+///
+/// The values of environment variables are recorded as digests, so that secrets are not written to
+/// disk.
+///
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessFingerprint {
+    pub description: String,
+    pub action_digest: String,
+    pub cache_hit: bool,
+    pub argv: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub inputs: BTreeMap<String, String>,
+}
+
+impl ProcessFingerprint {
+    pub fn new(
+        process: &Process,
+        action_digest: Digest,
+        cache_hit: bool,
+        input_tree: &DigestTrie,
+    ) -> ProcessFingerprint {
+        let env = process
+            .env
+            .iter()
+            .chain(process.globbed_env.iter())
+            .map(|(name, value)| {
+                (
+                    name.clone(),
+                    Digest::of_bytes(value.as_bytes()).hash.to_hex(),
+                )
+            })
+            .collect();
+
+        let mut inputs = BTreeMap::new();
+        input_tree.walk(SymlinkBehavior::Aware, &mut |path, entry| match entry {
+            Entry::File(f) => {
+                let fingerprint = f.digest().hash.to_hex();
+                inputs.insert(
+                    path.to_string_lossy().into_owned(),
+                    if f.is_executable() {
+                        format!("{fingerprint} (executable)")
+                    } else {
+                        fingerprint
+                    },
+                );
+            }
+            Entry::Symlink(s) => {
+                inputs.insert(
+                    path.to_string_lossy().into_owned(),
+                    format!("-> {}", s.target().display()),
+                );
+            }
+            Entry::Directory(_) => {}
+        });
+
+        ProcessFingerprint {
+            description: process.description.clone(),
+            action_digest: action_digest.hash.to_hex(),
+            cache_hit,
+            argv: process.argv.clone(),
+            env,
+            inputs,
+        }
+    }
+}
+
+///
+/// The fingerprints of all of the processes which were requested during one run.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub build_id: String,
+    pub started_millis: u64,
+    pub processes: Vec<ProcessFingerprint>,
+}
+
+///
+/// Records the fingerprints of the processes of a Session, which are written to a directory of
+/// run records when the Session ends (see `explain_cache_misses`).
+///
+pub struct CacheMissRecorder {
+    dir: PathBuf,
+    record: Mutex<RunRecord>,
+}
+
+impl CacheMissRecorder {
+    pub fn new(dir: PathBuf, build_id: &str) -> CacheMissRecorder {
+        let started_millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        CacheMissRecorder {
+            dir,
+            record: Mutex::new(RunRecord {
+                build_id: build_id.to_owned(),
+                started_millis,
+                processes: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn record(&self, fingerprint: ProcessFingerprint) {
+        self.record.lock().processes.push(fingerprint);
+    }
+
+    ///
+    /// Writes the record of this run (unless no processes were requested), and then removes all but
+    /// the most recent records.
+    ///
+    pub fn write(&self) -> Result<(), String> {
+        let record = self.record.lock();
+        if record.processes.is_empty() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.dir).map_err(|e| {
+            format!(
+                "Failed to create the cache miss record directory {}: {e}",
+                self.dir.display()
+            )
+        })?;
+        let path = self.dir.join(record_file_name(&record.build_id));
+        let content = serde_json::to_vec(&*record)
+            .map_err(|e| format!("Failed to serialize the cache miss record: {e}"))?;
+        std::fs::write(&path, content).map_err(|e| {
+            format!(
+                "Failed to write the cache miss record {}: {e}",
+                path.display()
+            )
+        })?;
+
+        let runs = load_runs(&self.dir)?;
+        for run in &runs[..runs.len().saturating_sub(MAX_RECORDED_RUNS)] {
+            let _ = std::fs::remove_file(self.dir.join(record_file_name(&run.build_id)));
+        }
+        Ok(())
+    }
+}
+
+fn record_file_name(build_id: &str) -> String {
+    let build_id: String = build_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{build_id}.json")
+}
+
+///
+/// Loads all of the run records in the given directory, in the order that the runs started.
+///
+pub fn load_runs(dir: &Path) -> Result<Vec<RunRecord>, String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => {
+            return Err(format!(
+                "Failed to read the cache miss record directory {}: {e}",
+                dir.display()
+            ))
+        }
+    };
+    let mut runs = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.extension().map_or(true, |ext| ext != "json") {
+            continue;
+        }
+        let content =
+            std::fs::read(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        match serde_json::from_slice::<RunRecord>(&content) {
+            Ok(run) => runs.push(run),
+            // Records are best-effort: skip any which were partially written or which were written by
+            // an incompatible version.
+            Err(e) => log::debug!("Skipping cache miss record {}: {e}", path.display()),
+        }
+    }
+    runs.sort_by(|a, b| {
+        a.started_millis
+            .cmp(&b.started_millis)
+            .then_with(|| a.build_id.cmp(&b.build_id))
+    });
+    Ok(runs)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        })
+    }
+}
+
+///
+/// A reason that a process missed the cache, relative to the most similar process in an earlier
+/// run.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Argv {
+        index: usize,
+        before: Option<String>,
+        after: Option<String>,
+    },
+    Env {
+        name: String,
+        kind: ChangeKind,
+    },
+    Input {
+        path: String,
+        kind: ChangeKind,
+    },
+    /// The Action changed, but not in its argv, environment or inputs.
+    Other,
+    /// An identical Action was requested in the earlier run, but its result was not cached.
+    Unchanged,
+    /// No process with the same description was requested in the earlier run.
+    New,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Argv {
+                index,
+                before: Some(before),
+                after: Some(after),
+            } => write!(f, "argv[{index}] changed from `{before}` to `{after}`"),
+            Change::Argv {
+                index,
+                after: Some(after),
+                ..
+            } => write!(f, "argv[{index}] `{after}` was added"),
+            Change::Argv {
+                index,
+                before: Some(before),
+                ..
+            } => write!(f, "argv[{index}] `{before}` was removed"),
+            Change::Argv { index, .. } => write!(f, "argv[{index}] changed"),
+            Change::Env { name, kind } => write!(f, "environment variable `{name}` was {kind}"),
+            Change::Input { path, kind } => write!(f, "input file `{path}` was {kind}"),
+            Change::Other => write!(
+                f,
+                "the process changed in a way which is not recorded (such as its working directory, \
+                 output paths, platform or timeout)"
+            ),
+            Change::Unchanged => write!(
+                f,
+                "an identical process was requested in the earlier run, but its result was not \
+                 cached (it may have failed, been uncacheable, or been evicted)"
+            ),
+            Change::New => write!(
+                f,
+                "no process with this description was requested in the earlier run"
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheMiss {
+    pub description: String,
+    pub action_digest: String,
+    pub changes: Vec<Change>,
+}
+
+///
+/// Explains each of the cache misses in the `after` run, relative to the `before` run.
+///
+/// Processes are matched by description: when a description was used by multiple processes in the
+/// earlier run, the miss is explained relative to the process with the fewest changes.
+///
+pub fn diff(before: &RunRecord, after: &RunRecord) -> Vec<CacheMiss> {
+    after
+        .processes
+        .iter()
+        .filter(|process| !process.cache_hit)
+        .map(|process| {
+            let changes = if before
+                .processes
+                .iter()
+                .any(|p| p.action_digest == process.action_digest)
+            {
+                vec![Change::Unchanged]
+            } else {
+                before
+                    .processes
+                    .iter()
+                    .filter(|p| p.description == process.description)
+                    .map(|p| changes(p, process))
+                    .min_by_key(|changes| changes.len())
+                    .map(|changes| {
+                        if changes.is_empty() {
+                            vec![Change::Other]
+                        } else {
+                            changes
+                        }
+                    })
+                    .unwrap_or_else(|| vec![Change::New])
+            };
+            CacheMiss {
+                description: process.description.clone(),
+                action_digest: process.action_digest.clone(),
+                changes,
+            }
+        })
+        .collect()
+}
+
+fn changes(before: &ProcessFingerprint, after: &ProcessFingerprint) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for index in 0..std::cmp::max(before.argv.len(), after.argv.len()) {
+        let (b, a) = (before.argv.get(index), after.argv.get(index));
+        if b != a {
+            changes.push(Change::Argv {
+                index,
+                before: b.cloned(),
+                after: a.cloned(),
+            });
+        }
+    }
+    for (name, kind) in map_changes(&before.env, &after.env) {
+        changes.push(Change::Env { name, kind });
+    }
+    for (path, kind) in map_changes(&before.inputs, &after.inputs) {
+        changes.push(Change::Input { path, kind });
+    }
+    changes
+}
+
+fn map_changes(
+    before: &BTreeMap<String, String>,
+    after: &BTreeMap<String, String>,
+) -> Vec<(String, ChangeKind)> {
+    let mut changes = Vec::new();
+    for (key, value) in before {
+        match after.get(key) {
+            None => changes.push((key.clone(), ChangeKind::Removed)),
+            Some(v) if v != value => changes.push((key.clone(), ChangeKind::Changed)),
+            Some(_) => {}
+        }
+    }
+    for key in after.keys() {
+        if !before.contains_key(key) {
+            changes.push((key.clone(), ChangeKind::Added));
+        }
+    }
+    changes.sort_by(|(a, _), (b, _)| a.cmp(b));
+    changes
+}
+
+///
+/// Explains the cache misses of a recorded run relative to an earlier one, identified by their
+/// build ids. If `after` is not given, the most recent run is used, and if `before` is not given,
+/// the run preceding `after` is used.
+///
+/// Returns the build ids of the compared runs along with the explanations.
+///
+pub fn explain_cache_misses(
+    dir: &Path,
+    before: Option<&str>,
+    after: Option<&str>,
+) -> Result<(String, String, Vec<CacheMiss>), String> {
+    let runs = load_runs(dir)?;
+    let find = |build_id: &str| {
+        runs.iter()
+            .position(|run| run.build_id == build_id)
+            .ok_or_else(|| format!("No cache miss record was found for run `{build_id}`."))
+    };
+    let after_index = match after {
+        Some(after) => find(after)?,
+        None => runs
+            .len()
+            .checked_sub(1)
+            .ok_or_else(|| "No runs have been recorded.".to_owned())?,
+    };
+    let before_index = match before {
+        Some(before) => find(before)?,
+        None => after_index.checked_sub(1).ok_or_else(|| {
+            format!(
+                "No run was recorded before run `{}`.",
+                runs[after_index].build_id
+            )
+        })?,
+    };
+    let (before, after) = (&runs[before_index], &runs[after_index]);
+    Ok((
+        before.build_id.clone(),
+        after.build_id.clone(),
+        diff(before, after),
+    ))
+}
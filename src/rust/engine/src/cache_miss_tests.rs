@@ -0,0 +1,217 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::BTreeMap;
+
+use tempfile::TempDir;
+
+use crate::cache_miss::{
+    diff, explain_cache_misses, CacheMiss, CacheMissRecorder, Change, ChangeKind,
+    ProcessFingerprint, RunRecord,
+};
+
+fn fingerprint(
+    description: &str,
+    action_digest: &str,
+    cache_hit: bool,
+    argv: &[&str],
+    env: &[(&str, &str)],
+    inputs: &[(&str, &str)],
+) -> ProcessFingerprint {
+    let to_map = |items: &[(&str, &str)]| {
+        items
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    ProcessFingerprint {
+        description: description.to_owned(),
+        action_digest: action_digest.to_owned(),
+        cache_hit,
+        argv: argv.iter().map(|a| a.to_string()).collect(),
+        env: to_map(env),
+        inputs: to_map(inputs),
+    }
+}
+
+fn run(build_id: &str, started_millis: u64, processes: Vec<ProcessFingerprint>) -> RunRecord {
+    RunRecord {
+        build_id: build_id.to_owned(),
+        started_millis,
+        processes,
+    }
+}
+
+#[test]
+fn explains_changed_argv_env_and_inputs() {
+    let before = run(
+        "before",
+        1,
+        vec![fingerprint(
+            "Run pytest",
+            "a",
+            false,
+            &["pytest", "-v"],
+            &[("HOME", "1"), ("TZ", "2")],
+            &[("src/a.py", "1"), ("src/b.py", "2")],
+        )],
+    );
+    let after = run(
+        "after",
+        2,
+        vec![fingerprint(
+            "Run pytest",
+            "b",
+            false,
+            &["pytest", "-vv", "--lf"],
+            &[("HOME", "3"), ("LANG", "4")],
+            &[("src/a.py", "1"), ("src/b.py", "5"), ("src/c.py", "6")],
+        )],
+    );
+
+    assert_eq!(
+        diff(&before, &after),
+        vec![CacheMiss {
+            description: "Run pytest".to_owned(),
+            action_digest: "b".to_owned(),
+            changes: vec![
+                Change::Argv {
+                    index: 1,
+                    before: Some("-v".to_owned()),
+                    after: Some("-vv".to_owned()),
+                },
+                Change::Argv {
+                    index: 2,
+                    before: None,
+                    after: Some("--lf".to_owned()),
+                },
+                Change::Env {
+                    name: "HOME".to_owned(),
+                    kind: ChangeKind::Changed,
+                },
+                Change::Env {
+                    name: "LANG".to_owned(),
+                    kind: ChangeKind::Added,
+                },
+                Change::Env {
+                    name: "TZ".to_owned(),
+                    kind: ChangeKind::Removed,
+                },
+                Change::Input {
+                    path: "src/b.py".to_owned(),
+                    kind: ChangeKind::Changed,
+                },
+                Change::Input {
+                    path: "src/c.py".to_owned(),
+                    kind: ChangeKind::Added,
+                },
+            ],
+        }]
+    );
+}
+
+#[test]
+fn explains_relative_to_most_similar_process() {
+    let before = run(
+        "before",
+        1,
+        vec![
+            fingerprint("Compile", "a", true, &["cc", "a.c"], &[], &[("a.c", "1")]),
+            fingerprint("Compile", "b", true, &["cc", "b.c"], &[], &[("b.c", "1")]),
+        ],
+    );
+    let after = run(
+        "after",
+        2,
+        vec![
+            fingerprint("Compile", "a", true, &["cc", "a.c"], &[], &[("a.c", "1")]),
+            fingerprint("Compile", "c", false, &["cc", "b.c"], &[], &[("b.c", "2")]),
+        ],
+    );
+
+    let misses = diff(&before, &after);
+    assert_eq!(misses.len(), 1);
+    assert_eq!(
+        misses[0].changes,
+        vec![Change::Input {
+            path: "b.c".to_owned(),
+            kind: ChangeKind::Changed,
+        }]
+    );
+}
+
+#[test]
+fn explains_new_unchanged_and_unrecorded_changes() {
+    let before = run(
+        "before",
+        1,
+        vec![
+            fingerprint("Lint", "a", false, &["lint"], &[], &[]),
+            fingerprint("Format", "b", true, &["fmt"], &[], &[]),
+        ],
+    );
+    let after = run(
+        "after",
+        2,
+        vec![
+            fingerprint("Lint", "a", false, &["lint"], &[], &[]),
+            fingerprint("Format", "c", false, &["fmt"], &[], &[]),
+            fingerprint("Typecheck", "d", false, &["check"], &[], &[]),
+        ],
+    );
+
+    let changes = diff(&before, &after)
+        .into_iter()
+        .map(|miss| miss.changes)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        vec![
+            vec![Change::Unchanged],
+            vec![Change::Other],
+            vec![Change::New]
+        ]
+    );
+}
+
+#[test]
+fn records_are_written_and_compared() {
+    let dir = TempDir::new().unwrap();
+
+    // Runs which requested no processes are not recorded.
+    CacheMissRecorder::new(dir.path().to_owned(), "empty")
+        .write()
+        .unwrap();
+    assert!(explain_cache_misses(dir.path(), None, None).is_err());
+
+    for (build_id, input) in [("one", "1"), ("two", "2")] {
+        let recorder = CacheMissRecorder::new(dir.path().to_owned(), build_id);
+        recorder.record(fingerprint(
+            "Run",
+            input,
+            false,
+            &["run"],
+            &[],
+            &[("input", input)],
+        ));
+        recorder.write().unwrap();
+        // Ensure that the runs are ordered by their start times.
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+
+    let (before, after, misses) = explain_cache_misses(dir.path(), None, None).unwrap();
+    assert_eq!((before.as_str(), after.as_str()), ("one", "two"));
+    assert_eq!(
+        misses[0].changes,
+        vec![Change::Input {
+            path: "input".to_owned(),
+            kind: ChangeKind::Changed,
+        }]
+    );
+
+    let (before, after, _) = explain_cache_misses(dir.path(), Some("two"), Some("one")).unwrap();
+    assert_eq!((before.as_str(), after.as_str()), ("two", "one"));
+
+    assert!(explain_cache_misses(dir.path(), None, Some("one")).is_err());
+    assert!(explain_cache_misses(dir.path(), Some("three"), None).is_err());
+}
@@ -10,35 +10,46 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::downloads::RemoteAssetFetcher;
 use crate::nodes::{ExecuteProcess, NodeKey, NodeOutput, NodeResult};
-use crate::python::{throw, Failure};
+use crate::python::Failure;
 use crate::session::{Session, Sessions};
 use crate::tasks::{Rule, Tasks};
+use crate::timings;
 use crate::types::Types;
 
 use cache::PersistentCache;
+use engine_error::{EngineError, ErrorCode};
 use fs::{GitignoreStyleExcludes, PosixFS};
 use futures::FutureExt;
 use graph::{Graph, InvalidationResult};
+use grpc_util::proxy::ProxyConfig;
+use grpc_util::resilience::CircuitBreakerOptions;
 use hashing::Digest;
+use internment::Intern;
 use log::{log, Level};
 use parking_lot::Mutex;
 // use docker::docker::{self, DOCKER, IMAGE_PULL_CACHE};
 use docker::docker;
+use process_execution::sandbox_exec::{SandboxExec, SandboxExecMode};
 use process_execution::switched::SwitchedCommandRunner;
 use process_execution::{
-    self, bounded, local, CacheContentBehavior, CommandRunner, NamedCaches,
+    self, bounded, local, CacheContentBehavior, CommandRunner, NamedCaches, Process,
     ProcessExecutionStrategy,
 };
 use regex::Regex;
 use remote::remote_cache::{RemoteCacheRunnerOptions, RemoteCacheWarningsBehavior};
+use remote::write_behind::WriteBehindQueue;
 use remote::{self, remote_cache};
-use rule_graph::RuleGraph;
+use rule_graph::{Entry, RuleGraph};
+use sandboxer::SandboxerMaterializer;
+use store::bandwidth::BandwidthLimits;
+use store::server::StoreServer;
 use store::{self, ImmutableInputs, RemoteProvider, RemoteStoreOptions, Store};
 use task_executor::Executor;
 use tokio::sync::RwLock;
 use watch::{Invalidatable, InvalidateCaller, InvalidationWatcher};
-use workunit_store::{Metric, RunningWorkunit};
+use workunit_store::{Metric, MetricsAccumulator, RunningWorkunit, TimingHistory};
 
 // The reqwest crate has no support for ingesting multiple certificates in a single file,
 // and requires single PEM blocks. There is a crate (https://crates.io/crates/pem) that can decode
@@ -68,16 +79,37 @@ pub struct Core {
     /// their outputs, and so should be listed before uncached `CommandRunners`.
     pub command_runners: Vec<Arc<dyn CommandRunner>>,
     pub http_client: reqwest::Client,
+    /// If set, the remote asset service which URL downloads are delegated to: see `downloads`.
+    pub remote_asset_fetcher: Option<RemoteAssetFetcher>,
     pub local_cache: PersistentCache,
     pub vfs: PosixFS,
     pub watcher: Option<Arc<InvalidationWatcher>>,
     pub build_root: PathBuf,
     pub local_parallelism: usize,
     pub graceful_shutdown_timeout: Duration,
+    /// If set, the duration after which running work which has made no observable progress is
+    /// reported as stalled: see `Session::maybe_report_stalls`.
+    pub stall_threshold: Option<Duration>,
     pub sessions: Sessions,
     pub named_caches: NamedCaches,
     pub immutable_inputs: ImmutableInputs,
     pub local_execution_root_dir: PathBuf,
+    /// The directory beneath which each Session creates its temporary directories: see
+    /// `SessionTmpDir`.
+    pub session_tmpdir_root: PathBuf,
+    pub local_store_dir: PathBuf,
+    /// The server exposing the store to other tools, if enabled. It stops when the Core is dropped.
+    pub store_server: Option<StoreServer>,
+    /// The metrics of all Sessions which have completed on this Core.
+    pub completed_session_metrics: MetricsAccumulator,
+    /// The historical durations of workunits, which are shared by all Sessions in order to estimate
+    /// their progress, and persisted in the `local_cache` as each Session ends.
+    pub timing_history: Arc<Mutex<TimingHistory>>,
+    /// The salts of the persisted results of Tasks, memoized by rule graph entry: see
+    /// `nodes::Task::persistent_cache_salt`.
+    pub persistent_cache_salts: Mutex<HashMap<Intern<Entry<Rule>>, Arc<str>>>,
+    remoting_opts: RemotingOptions,
+    remoting_tls_config: grpc_util::tls::Config,
 }
 
 #[derive(Clone, Debug)]
@@ -96,15 +128,30 @@ pub struct RemotingOptions {
     pub store_rpc_retries: usize,
     pub store_rpc_concurrency: usize,
     pub store_rpc_timeout: Duration,
+    pub store_streaming_rpc_timeout: Duration,
     pub store_batch_api_size_limit: usize,
+    /// The limits on the rates of transfers to and from the remote store, in bytes per second.
+    pub store_upload_rate_limit: Option<usize>,
+    pub store_download_rate_limit: Option<usize>,
+    pub asset_fetch: bool,
     pub cache_warnings_behavior: RemoteCacheWarningsBehavior,
     pub cache_content_behavior: CacheContentBehavior,
     pub cache_rpc_concurrency: usize,
     pub cache_rpc_timeout: Duration,
+    pub cache_write_behind: bool,
+    pub cache_write_behind_rate_limit: usize,
+    pub cache_circuit_breaker_threshold: usize,
+    pub cache_circuit_breaker_cooldown: Duration,
     pub execution_headers: BTreeMap<String, String>,
     pub execution_overall_deadline: Duration,
     pub execution_rpc_concurrency: usize,
+    pub execution_priority: i32,
+    pub results_cache_priority: i32,
     pub append_only_caches_base_path: Option<String>,
+    /// The proxies for remoting and downloads: if unset, they are taken from the environment.
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub no_proxy: Option<String>,
 }
 
 impl RemotingOptions {
@@ -126,9 +173,32 @@ impl RemotingOptions {
             headers: self.store_headers.clone(),
             chunk_size_bytes: self.store_chunk_bytes,
             timeout: self.store_rpc_timeout,
+            stream_timeout: self.store_streaming_rpc_timeout,
             retries: self.store_rpc_retries,
             concurrency_limit: self.store_rpc_concurrency,
             batch_api_size_limit: self.store_batch_api_size_limit,
+            circuit_breaker: None,
+        })
+    }
+
+    ///
+    /// As `to_remote_store_options`, but with the timeouts, concurrency and circuit breaker which
+    /// apply to the remote Action Cache.
+    ///
+    fn to_remote_cache_options(
+        &self,
+        tls_config: grpc_util::tls::Config,
+    ) -> Result<RemoteStoreOptions, String> {
+        let circuit_breaker =
+            (self.cache_circuit_breaker_threshold > 0).then_some(CircuitBreakerOptions {
+                failure_threshold: self.cache_circuit_breaker_threshold,
+                cooldown: self.cache_circuit_breaker_cooldown,
+            });
+        Ok(RemoteStoreOptions {
+            timeout: self.cache_rpc_timeout,
+            concurrency_limit: self.cache_rpc_concurrency,
+            circuit_breaker,
+            ..self.to_remote_store_options(tls_config)?
         })
     }
 }
@@ -145,6 +215,26 @@ pub struct ExecutionStrategyOptions {
     pub child_max_memory: usize,
     pub child_default_memory: usize,
     pub graceful_shutdown_timeout: Duration,
+    /// If set, cache hits older than this age are revalidated before they are used.
+    pub cache_max_age: Option<Duration>,
+    /// Patterns matching the descriptions of processes which should be run twice in order to
+    /// detect nondeterminism.
+    pub verify_determinism: Vec<String>,
+    /// Where to append nondeterminism findings, if anywhere.
+    pub determinism_report_path: Option<PathBuf>,
+    /// If set, the directory beneath which Sessions create their temporary directories, rather than
+    /// the local execution root directory.
+    pub session_tmpdir: Option<PathBuf>,
+    /// If set, the Unix socket of a sandboxer process which materializes immutable inputs.
+    pub sandboxer_socket: Option<PathBuf>,
+    /// Whether to wrap local processes in `sandbox-exec` on macOS: see `SandboxExec`.
+    pub local_sandbox_exec: SandboxExecMode,
+    pub local_sandbox_exec_allowed_paths: Vec<PathBuf>,
+    /// If set, the duration after which running work which has made no observable progress is
+    /// reported as stalled.
+    pub stall_threshold: Option<Duration>,
+    /// Whether to cancel and retry (once) processes which are stalled: see `watchdog::CommandRunner`.
+    pub retry_stalled_processes: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -155,6 +245,10 @@ pub struct LocalStoreOptions {
     pub directories_max_size_bytes: usize,
     pub lease_time: Duration,
     pub shard_count: u8,
+    /// If set, the localhost port on which to serve the store to other tools: see `StoreServer`.
+    pub server_port: Option<u16>,
+    pub materialize_concurrency: usize,
+    pub clone_files: bool,
 }
 
 impl From<&LocalStoreOptions> for store::LocalOptions {
@@ -164,6 +258,8 @@ impl From<&LocalStoreOptions> for store::LocalOptions {
             directories_max_size_bytes: lso.directories_max_size_bytes,
             lease_time: lso.lease_time,
             shard_count: lso.shard_count,
+            materialize_concurrency: lso.materialize_concurrency,
+            clone_files: lso.clone_files,
         }
     }
 }
@@ -185,6 +281,10 @@ impl Core {
         )?;
         if enable_remote {
             local_only
+                .with_bandwidth_limits(BandwidthLimits::new(
+                    remoting_opts.store_upload_rate_limit,
+                    remoting_opts.store_download_rate_limit,
+                ))
                 .into_with_remote(remoting_opts.to_remote_store_options(tls_config)?)
                 .await
         } else {
@@ -221,6 +321,10 @@ impl Core {
             named_caches.clone(),
             immutable_inputs.clone(),
             exec_strategy_opts.local_keep_sandboxes,
+            SandboxExec::new(
+                exec_strategy_opts.local_sandbox_exec,
+                exec_strategy_opts.local_sandbox_exec_allowed_paths.clone(),
+            )?,
             spawn_lock.clone(),
         );
 
@@ -243,11 +347,36 @@ impl Core {
             |req| req.execution_environment.strategy == ProcessExecutionStrategy::LocalInWorkspace,
         ));
 
+        // Note that the persistent worker runner is only used if the Process declares a worker. So,
+        // it's safe to always create this command runner.
+        let worker_runner = pe_worker::CommandRunner::new(
+            local_execution_root_dir.to_path_buf(),
+            local_runner_store.clone(),
+            executor.clone(),
+            named_caches.clone(),
+            immutable_inputs.clone(),
+            pe_worker::WorkerPoolConfig {
+                max_workers: max(1, exec_strategy_opts.local_parallelism * 2),
+                max_workers_per_key: max(1, exec_strategy_opts.local_parallelism),
+                max_multiplex_requests: max(1, exec_strategy_opts.local_parallelism),
+                idle_ttl: Some(Duration::from_secs(15 * 60)),
+                maintenance_interval: Duration::from_secs(60),
+            },
+        );
+        let local_command_runner: Box<dyn CommandRunner> = Box::new(SwitchedCommandRunner::new(
+            worker_runner,
+            local_command_runner,
+            |req| {
+                req.persistent_worker.is_some()
+                    && req.execution_environment.strategy == ProcessExecutionStrategy::Local
+            },
+        ));
+
         let runner: Box<dyn CommandRunner> = if exec_strategy_opts.local_enable_nailgun {
-            // We set the nailgun pool size to the number of instances that fit within the memory
-            // parameters configured when a max child process memory has been given.
-            // Otherwise, pool size will be double of the local parallelism so we can always keep
-            // a jvm warmed up.
+            // We set the maximum nailgun pool size to the number of instances that fit within the
+            // memory parameters configured when a max child process memory has been given.
+            // Otherwise, the maximum pool size will be double of the local parallelism so we can
+            // always keep a jvm warmed up.
             let pool_size: usize = if exec_strategy_opts.child_max_memory > 0 {
                 max(
                     1,
@@ -256,6 +385,14 @@ impl Core {
             } else {
                 exec_strategy_opts.local_parallelism * 2
             };
+            // Servers are started on demand up to the pool size, and are shut down again once they
+            // have been idle for a while.
+            let nailgun_pool_config = pe_nailgun::NailgunPoolConfig {
+                idle_ttl: Some(Duration::from_secs(15 * 60)),
+                liveness_probe_timeout: Some(Duration::from_secs(10)),
+                maintenance_interval: Duration::from_secs(60),
+                ..pe_nailgun::NailgunPoolConfig::fixed(pool_size)
+            };
 
             let nailgun_runner = pe_nailgun::CommandRunner::new(
                 local_execution_root_dir.to_path_buf(),
@@ -263,7 +400,7 @@ impl Core {
                 executor.clone(),
                 named_caches.clone(),
                 immutable_inputs.clone(),
-                pool_size,
+                nailgun_pool_config,
             );
 
             Box::new(SwitchedCommandRunner::new(
@@ -317,6 +454,8 @@ impl Core {
                     remoting_opts.execution_overall_deadline,
                     Duration::from_millis(100),
                     remoting_opts.execution_rpc_concurrency,
+                    remoting_opts.execution_priority,
+                    remoting_opts.results_cache_priority,
                 )
                 .await?,
             );
@@ -351,6 +490,7 @@ impl Core {
         full_store: &Store,
         executor: &Executor,
         local_cache: &PersistentCache,
+        write_behind: Option<&WriteBehindQueue>,
         instance_name: Option<String>,
         process_cache_namespace: Option<String>,
         tls_config: grpc_util::tls::Config,
@@ -359,6 +499,7 @@ impl Core {
         remote_cache_write: bool,
         local_cache_read: bool,
         local_cache_write: bool,
+        cache_max_age: Option<Duration>,
     ) -> Result<Arc<dyn CommandRunner>, String> {
         if remote_cache_read || remote_cache_write {
             runner = Arc::new(
@@ -376,8 +517,10 @@ impl Core {
                         append_only_caches_base_path: remoting_opts
                             .append_only_caches_base_path
                             .clone(),
+                        max_age: cache_max_age,
+                        write_behind: write_behind.cloned(),
                     },
-                    remoting_opts.to_remote_store_options(tls_config)?,
+                    remoting_opts.to_remote_cache_options(tls_config)?,
                 )
                 .await?,
             );
@@ -391,6 +534,7 @@ impl Core {
                 local_cache_read,
                 remoting_opts.cache_content_behavior,
                 process_cache_namespace,
+                cache_max_age,
             ));
         }
 
@@ -405,6 +549,7 @@ impl Core {
         local_runner_store: &Store,
         executor: &Executor,
         local_cache: &PersistentCache,
+        write_behind: Option<&WriteBehindQueue>,
         build_root: &Path,
         local_execution_root_dir: &Path,
         immutable_inputs: &ImmutableInputs,
@@ -431,6 +576,34 @@ impl Core {
         )
         .await?;
 
+        // Processes which are selected for determinism verification are run a second time below the
+        // caches, so that only actual executions are verified.
+        let leaf_runner: Arc<dyn CommandRunner> =
+            if exec_strategy_opts.verify_determinism.is_empty() {
+                leaf_runner
+            } else {
+                Arc::new(process_execution::nondeterminism::CommandRunner::new(
+                    leaf_runner.clone(),
+                    leaf_runner,
+                    full_store.clone(),
+                    &exec_strategy_opts.verify_determinism,
+                    exec_strategy_opts.determinism_report_path.clone(),
+                )?)
+            };
+
+        // Processes which stall are cancelled and retried below the caches.
+        let leaf_runner: Arc<dyn CommandRunner> = match exec_strategy_opts.stall_threshold {
+            Some(threshold) if exec_strategy_opts.retry_stalled_processes => Arc::new(
+                process_execution::watchdog::CommandRunner::new(leaf_runner, threshold),
+            ),
+            _ => leaf_runner,
+        };
+
+        // Processes which declare a retry policy are retried below the caches, so that only the
+        // result of their final attempt is cached.
+        let leaf_runner: Arc<dyn CommandRunner> =
+            Arc::new(process_execution::retry::CommandRunner::new(leaf_runner));
+
         let remote_cache_read = exec_strategy_opts.remote_cache_read;
         let remote_cache_write = exec_strategy_opts.remote_cache_write;
         let local_cache_read_write = exec_strategy_opts.local_cache;
@@ -442,6 +615,7 @@ impl Core {
                 full_store,
                 executor,
                 local_cache,
+                write_behind,
                 instance_name.clone(),
                 process_cache_namespace.clone(),
                 tls_config.clone(),
@@ -450,6 +624,7 @@ impl Core {
                 remote_cache_write,
                 local_cache_read_write,
                 local_cache_read_write,
+                exec_strategy_opts.cache_max_age,
             )
             .await?;
 
@@ -463,6 +638,7 @@ impl Core {
                 full_store,
                 executor,
                 local_cache,
+                write_behind,
                 instance_name.clone(),
                 process_cache_namespace.clone(),
                 tls_config,
@@ -471,6 +647,7 @@ impl Core {
                 remote_cache_write,
                 false,
                 local_cache_read_write,
+                exec_strategy_opts.cache_max_age,
             )
             .await?;
 
@@ -571,6 +748,14 @@ impl Core {
 
         let tls_config = grpc_util::tls::Config::new(root_ca_certs.as_deref(), mtls_data)?;
 
+        // NB: The proxy config must be installed before any gRPC channels are created.
+        let proxy_config = Arc::new(ProxyConfig::from_options(
+            remoting_opts.http_proxy.as_deref(),
+            remoting_opts.https_proxy.as_deref(),
+            remoting_opts.no_proxy.as_deref(),
+        )?);
+        grpc_util::proxy::set_global(proxy_config.clone());
+
         let need_remote_store = remoting_opts.execution_enable
             || exec_strategy_opts.remote_cache_read
             || exec_strategy_opts.remote_cache_write;
@@ -601,6 +786,37 @@ impl Core {
             local_store_options.lease_time,
             local_store_options.shard_count,
         )?;
+        let timing_history = timings::load(&local_cache).await.unwrap_or_else(|e| {
+            log::warn!("Failed to load historical node timings: {e}");
+            TimingHistory::default()
+        });
+
+        let remote_asset_fetcher = if remoting_opts.asset_fetch {
+            Some(
+                RemoteAssetFetcher::new(
+                    full_store.clone(),
+                    remoting_opts.to_remote_store_options(tls_config.clone())?,
+                )
+                .await?,
+            )
+        } else {
+            None
+        };
+
+        let write_behind =
+            if exec_strategy_opts.remote_cache_write && remoting_opts.cache_write_behind {
+                // NB: If another process already has the queue open, writes are made directly.
+                WriteBehindQueue::from_provider_options(
+                    &local_store_options.store_dir,
+                    &executor,
+                    full_store.clone(),
+                    remoting_opts.to_remote_cache_options(tls_config.clone())?,
+                    remoting_opts.cache_write_behind_rate_limit,
+                )
+                .await?
+            } else {
+                None
+            };
 
         let store = if (exec_strategy_opts.remote_cache_read
             || exec_strategy_opts.remote_cache_write)
@@ -619,13 +835,42 @@ impl Core {
             full_store.clone()
         };
 
-        let immutable_inputs = ImmutableInputs::new(store.clone(), &local_execution_root_dir)?;
+        // The server sees the remote (if any) so that other tools reading through it share a single
+        // download of each blob with this process.
+        let store_server = local_store_options
+            .server_port
+            .map(|port| {
+                StoreServer::start(
+                    &executor,
+                    full_store.clone(),
+                    remoting_opts.instance_name.clone(),
+                    port,
+                )
+            })
+            .transpose()?;
+
+        let immutable_inputs = match exec_strategy_opts.sandboxer_socket {
+            Some(ref socket) => {
+                if store_server.is_none() {
+                    return Err(
+                        "The sandboxer fetches inputs from the local store server, and so \
+                         `--local-store-server-port` must be set in order to use \
+                         `--sandboxer-socket`."
+                            .to_owned(),
+                    );
+                }
+                let materializer = SandboxerMaterializer::connect(socket.clone()).await?;
+                ImmutableInputs::new_delegated(store.clone(), Arc::new(materializer))
+            }
+            None => ImmutableInputs::new(store.clone(), &local_execution_root_dir)?,
+        };
         let named_caches = NamedCaches::new_local(named_caches_dir);
         let command_runners = Self::make_command_runners(
             &full_store,
             &store,
             &executor,
             &local_cache,
+            write_behind.as_ref(),
             &build_root,
             &local_execution_root_dir,
             &immutable_inputs,
@@ -648,7 +893,14 @@ impl Core {
             .iter()
             .fold(reqwest::Client::builder(), |builder, cert| {
                 builder.add_root_certificate(cert.clone())
-            });
+            })
+            // NB: This replaces the client's own handling of the proxy environment variables, so
+            // that downloads and remoting are proxied consistently.
+            .proxy(reqwest::Proxy::custom(move |url| {
+                proxy_config
+                    .proxy_for(url.scheme(), url.host_str()?)
+                    .map(|proxy| proxy.url().to_owned())
+            }));
         let http_client = http_client_builder
             .build()
             .map_err(|err| format!("Error building HTTP client: {err}"))?;
@@ -673,7 +925,7 @@ impl Core {
             None
         };
 
-        let sessions = Sessions::new(&executor)?;
+        let sessions = Sessions::new(&executor, Graph::clone(&graph))?;
 
         Ok(Core {
             graph,
@@ -684,6 +936,7 @@ impl Core {
             store,
             command_runners,
             http_client,
+            remote_asset_fetcher,
             local_cache,
             vfs: PosixFS::new(&build_root, ignorer, executor)
                 .map_err(|e| format!("Could not initialize Vfs: {e:?}"))?,
@@ -691,10 +944,22 @@ impl Core {
             watcher,
             local_parallelism: exec_strategy_opts.local_parallelism,
             graceful_shutdown_timeout: exec_strategy_opts.graceful_shutdown_timeout,
+            stall_threshold: exec_strategy_opts.stall_threshold,
             sessions,
             named_caches,
             immutable_inputs,
+            session_tmpdir_root: exec_strategy_opts
+                .session_tmpdir
+                .clone()
+                .unwrap_or_else(|| local_execution_root_dir.clone()),
             local_execution_root_dir,
+            local_store_dir: local_store_options.store_dir.clone(),
+            store_server,
+            completed_session_metrics: MetricsAccumulator::default(),
+            timing_history: Arc::new(Mutex::new(timing_history)),
+            persistent_cache_salts: Mutex::default(),
+            remoting_opts,
+            remoting_tls_config: tls_config,
         })
     }
 
@@ -702,10 +967,87 @@ impl Core {
         self.store.clone()
     }
 
+    ///
+    /// Computes the digest of the Action that the given Process is cached under remotely.
+    ///
+    pub async fn process_action_digest(&self, process: &Process) -> Digest {
+        process_execution::get_digest(
+            process,
+            self.remoting_opts.instance_name.clone(),
+            self.remoting_opts.execution_process_cache_namespace.clone(),
+            &self.store,
+            self.remoting_opts.append_only_caches_base_path.as_deref(),
+        )
+        .await
+    }
+
+    ///
+    /// Creates a remote cache CommandRunner which is used only to publish existing results to the
+    /// remote Action Cache (see `remote_cache::CommandRunner::publish`), even if remote cache writes
+    /// were not enabled for process execution.
+    ///
+    pub async fn make_remote_cache_publisher(&self) -> Result<remote_cache::CommandRunner, String> {
+        let remote_store_options = self
+            .remoting_opts
+            .to_remote_store_options(self.remoting_tls_config.clone())?;
+        let store = self
+            .store
+            .clone()
+            .into_local_only()
+            .into_with_remote(remote_store_options)
+            .await?;
+        remote_cache::CommandRunner::from_provider_options(
+            RemoteCacheRunnerOptions {
+                // NB: The publisher never runs processes, so the inner runner is unused.
+                inner: self.command_runners[0].clone(),
+                instance_name: self.remoting_opts.instance_name.clone(),
+                process_cache_namespace: self
+                    .remoting_opts
+                    .execution_process_cache_namespace
+                    .clone(),
+                executor: self.executor.clone(),
+                store,
+                cache_read: false,
+                cache_write: true,
+                warnings_behavior: self.remoting_opts.cache_warnings_behavior,
+                cache_content_behavior: self.remoting_opts.cache_content_behavior,
+                append_only_caches_base_path: self
+                    .remoting_opts
+                    .append_only_caches_base_path
+                    .clone(),
+                max_age: None,
+                // NB: Publishing is explicitly requested, and so is not deferred.
+                write_behind: None,
+            },
+            self.remoting_opts
+                .to_remote_cache_options(self.remoting_tls_config.clone())?,
+        )
+        .await
+    }
+
     ///
     /// Shuts down this Core.
     ///
-    pub async fn shutdown(&self, timeout: Duration) {
+    /// If `drain_timeout` is non-zero, the Graph first stops admitting new Nodes, and Nodes which
+    /// are already running (in particular, local and remote processes) are given up to
+    /// `drain_timeout` to complete, so that their results are cached rather than discarded.
+    ///
+    pub async fn shutdown(&self, timeout: Duration, drain_timeout: Duration) {
+        if !drain_timeout.is_zero() {
+            let running = self.graph.running_len();
+            if running > 0 {
+                log::info!(
+                    "Waiting up to {drain_timeout:?} for {running} running nodes to complete..."
+                );
+            }
+            let still_running = self.graph.drain(drain_timeout).await;
+            if still_running > 0 {
+                log::warn!(
+                    "{still_running} nodes did not complete within {drain_timeout:?}: they will be canceled."
+                );
+            }
+        }
+
         // Shutdown the Sessions, which will prevent new work from starting and then await any ongoing
         // work.
         if let Err(msg) = self.sessions.shutdown(timeout).await {
@@ -860,9 +1202,11 @@ impl SessionCore {
             } else {
                 // There are no live or invalidated sources of this Digest. Directly fail.
                 return result.map_err(|e| {
-                    throw(format!(
-                        "Could not identify a process to backtrack to for: {e}"
-                    ))
+                    EngineError::new(
+                        ErrorCode::MissingDigest,
+                        format!("Could not identify a process to backtrack to for: {e}"),
+                    )
+                    .into()
                 });
             }
         } else {
@@ -0,0 +1,290 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+use fs::{DirectoryDigest, Entry};
+use hashing::{Digest, Fingerprint};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use parking_lot::Mutex;
+use store::Store;
+use task_executor::Executor;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// The file which is served for a request for a directory, if it is present.
+const INDEX_FILE: &str = "index.html";
+
+///
+/// An HTTP server (bound to localhost) which serves the contents of DirectoryDigests from the
+/// Store, without materializing them to disk.
+///
+/// Digests must be registered with `serve` before they are visible, and are addressed by their
+/// content: a digest is served beneath `/{fingerprint}-{size_bytes}/`. Single byte ranges are
+/// supported for files, so that media and large reports can be partially fetched.
+///
+pub struct DigestServer {
+    port: u16,
+    digests: Arc<Mutex<HashMap<Digest, DirectoryDigest>>>,
+    shutdown_sender: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl DigestServer {
+    ///
+    /// Binds to the given port (or to a random port if it is 0), and serves registered digests from
+    /// the given Store.
+    ///
+    pub fn new(executor: &Executor, port: u16, store: Store) -> Result<DigestServer, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .map_err(|e| format!("Failed to bind the digest server to port {port}: {e}"))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to get the address of the digest server: {e}"))?
+            .port();
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to configure the digest server: {e}"))?;
+
+        let digests: Arc<Mutex<HashMap<Digest, DirectoryDigest>>> = Arc::default();
+        let make_service = {
+            let digests = digests.clone();
+            make_service_fn(move |_| {
+                let digests = digests.clone();
+                let store = store.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request| {
+                        let digests = digests.clone();
+                        let store = store.clone();
+                        async move { Ok::<_, Infallible>(respond(request, &digests, &store).await) }
+                    }))
+                }
+            })
+        };
+
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel();
+        let server = executor.enter_io(|| {
+            hyper::Server::from_tcp(listener)
+                .map(|builder| {
+                    builder
+                        .serve(make_service)
+                        .with_graceful_shutdown(async move {
+                            let _ = shutdown_receiver.await;
+                        })
+                })
+                .map_err(|e| format!("Failed to start the digest server: {e}"))
+        })?;
+        let task = executor.native_spawn_io(async move {
+            if let Err(e) = server.await {
+                log::warn!("The digest server exited with an error: {e}");
+            }
+        });
+
+        Ok(DigestServer {
+            port,
+            digests,
+            shutdown_sender,
+            task,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    ///
+    /// Registers the given digest to be served, and returns the URL of its root directory.
+    ///
+    pub fn serve(&self, digest: DirectoryDigest) -> String {
+        let root = digest.as_digest();
+        self.digests.lock().insert(root, digest);
+        format!(
+            "http://127.0.0.1:{}/{}-{}/",
+            self.port, root.hash, root.size_bytes
+        )
+    }
+
+    ///
+    /// Stops accepting connections, and waits for in-flight requests to complete.
+    ///
+    pub async fn shutdown(self) -> Result<(), String> {
+        let _ = self.shutdown_sender.send(());
+        self.task
+            .await
+            .map_err(|e| format!("Failed to shut down the digest server: {e}"))
+    }
+}
+
+async fn respond(
+    request: Request<Body>,
+    digests: &Mutex<HashMap<Digest, DirectoryDigest>>,
+    store: &Store,
+) -> Response<Body> {
+    let response = match *request.method() {
+        Method::GET | Method::HEAD => respond_to_get(&request, digests, store).await,
+        _ => Err((
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Only GET and HEAD requests are supported.".to_owned(),
+        )),
+    };
+    response.unwrap_or_else(|(status, msg)| {
+        Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(Body::from(format!("{msg}\n")))
+            .expect("Response headers are statically valid.")
+    })
+}
+
+async fn respond_to_get(
+    request: &Request<Body>,
+    digests: &Mutex<HashMap<Digest, DirectoryDigest>>,
+    store: &Store,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let not_found = || (StatusCode::NOT_FOUND, "Not found.".to_owned());
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+
+    let (root, path) = parse_path(request.uri().path()).ok_or_else(not_found)?;
+    let digest = digests.lock().get(&root).cloned().ok_or_else(not_found)?;
+    let tree = store
+        .load_digest_trie(digest)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let (file_path, file_digest) = match tree.entry(Path::new(path)).map_err(|_| not_found())? {
+        Some(Entry::File(f)) => (path.to_owned(), f.digest()),
+        Some(Entry::Directory(d)) => {
+            let index_path = Path::new(path).join(INDEX_FILE);
+            match d.tree().entry(Path::new(INDEX_FILE)) {
+                Ok(Some(Entry::File(f))) => (index_path.to_string_lossy().into_owned(), f.digest()),
+                _ => return Err(not_found()),
+            }
+        }
+        None if path.is_empty() => match tree.entry(Path::new(INDEX_FILE)) {
+            Ok(Some(Entry::File(f))) => (INDEX_FILE.to_owned(), f.digest()),
+            _ => return Err(not_found()),
+        },
+        _ => return Err(not_found()),
+    };
+
+    let len = file_digest.size_bytes;
+    let range = match request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|range| range.to_str().ok())
+    {
+        Some(range) => Some(parse_range(range, len).ok_or_else(|| {
+            (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                format!("The range {range:?} cannot be satisfied for a file of {len} bytes."),
+            )
+        })?),
+        None => None,
+    };
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, content_type(&file_path))
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, format!("\"{}\"", file_digest.hash));
+    let (start, end) = if let Some((start, end)) = range {
+        builder = builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}"));
+        (start, end + 1)
+    } else {
+        (0, len)
+    };
+
+    let body = if request.method() == Method::HEAD || start == end {
+        builder = builder.header(header::CONTENT_LENGTH, end - start);
+        Body::empty()
+    } else {
+        let bytes = store
+            .load_file_bytes_with(file_digest, move |bytes| {
+                bytes::Bytes::copy_from_slice(&bytes[start..end])
+            })
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+        Body::from(bytes)
+    };
+    builder
+        .body(body)
+        .map_err(|e| internal_error(e.to_string()))
+}
+
+///
+/// Splits a request path into the root digest that it is beneath, and the relative path of an
+/// entry within that digest. Returns None for paths which do not match `/{fingerprint}-{size}/..`
+/// or which attempt to escape the root.
+///
+pub(crate) fn parse_path(path: &str) -> Option<(Digest, &str)> {
+    let path = path.strip_prefix('/')?;
+    let (root, relative) = path.split_once('/').unwrap_or((path, ""));
+    let (fingerprint, size_bytes) = root.split_once('-')?;
+    let digest = Digest::new(
+        Fingerprint::from_hex_string(fingerprint).ok()?,
+        size_bytes.parse().ok()?,
+    );
+    let relative = relative.trim_end_matches('/');
+    if Path::new(relative)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some((digest, relative))
+}
+
+///
+/// Parses a `Range` header containing a single byte range into inclusive start and end offsets,
+/// or returns None if the range is malformed or cannot be satisfied by a file of the given length.
+///
+pub(crate) fn parse_range(range: &str, len: usize) -> Option<(usize, usize)> {
+    let (start, end) = range.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            // The final `suffix` bytes of the file.
+            let suffix: usize = suffix.parse().ok()?;
+            if suffix == 0 {
+                return None;
+            }
+            (len.saturating_sub(suffix), len.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => {
+            let end: usize = end.parse().ok()?;
+            (start.parse().ok()?, std::cmp::min(end, len.checked_sub(1)?))
+        }
+    };
+    if start > end {
+        return None;
+    }
+    Some((start, end))
+}
+
+fn content_type(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json" | "map") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("xml") => "application/xml",
+        Some("txt" | "md" | "log") => "text/plain; charset=utf-8",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
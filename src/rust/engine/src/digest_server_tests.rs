@@ -0,0 +1,115 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use store::Store;
+use task_executor::Executor;
+use testutil::data::{TestData, TestDirectory};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::digest_server::{parse_path, parse_range, DigestServer};
+
+async fn get(port: u16, path: &str, headers: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    stream
+        .write_all(format!("GET {path} HTTP/1.0\r\n{headers}\r\n").as_bytes())
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn serves_digest_contents() {
+    let executor = Executor::new();
+    let store_dir = tempfile::TempDir::new().unwrap();
+    let store = Store::local_only(executor.clone(), store_dir.path()).unwrap();
+    let roland = TestData::roland();
+    let directory = TestDirectory::containing_roland();
+    store.store_file_bytes(roland.bytes(), false).await.unwrap();
+    store
+        .record_directory(&directory.directory(), false)
+        .await
+        .unwrap();
+
+    let server = DigestServer::new(&executor, 0, store).unwrap();
+    let url = server.serve(directory.directory_digest());
+    let root = url
+        .strip_prefix(&format!("http://127.0.0.1:{}", server.port()))
+        .unwrap()
+        .to_owned();
+
+    let response = get(server.port(), &format!("{root}roland.ext"), "").await;
+    assert!(response.contains(" 200 OK\r\n"), "{response}");
+    assert!(response.ends_with("\r\n\r\nEuropean Burmese"), "{response}");
+
+    let response = get(
+        server.port(),
+        &format!("{root}roland.ext"),
+        "Range: bytes=9-\r\n",
+    )
+    .await;
+    assert!(response.contains(" 206 Partial Content\r\n"), "{response}");
+    assert!(response.contains("bytes 9-15/16"), "{response}");
+    assert!(response.ends_with("\r\n\r\nBurmese"), "{response}");
+
+    let response = get(
+        server.port(),
+        &format!("{root}roland.ext"),
+        "Range: bytes=20-\r\n",
+    )
+    .await;
+    assert!(
+        response.contains(" 416 Range Not Satisfiable\r\n"),
+        "{response}"
+    );
+
+    // Missing files, and digests which were never registered, are not found.
+    let response = get(server.port(), &format!("{root}missing.ext"), "").await;
+    assert!(response.contains(" 404 Not Found\r\n"), "{response}");
+    let unregistered = TestDirectory::nested().digest();
+    let response = get(
+        server.port(),
+        &format!(
+            "/{}-{}/roland.ext",
+            unregistered.hash, unregistered.size_bytes
+        ),
+        "",
+    )
+    .await;
+    assert!(response.contains(" 404 Not Found\r\n"), "{response}");
+
+    server.shutdown().await.unwrap();
+}
+
+#[test]
+fn parse_paths() {
+    let digest = TestDirectory::containing_roland().digest();
+    let root = format!("/{}-{}", digest.hash, digest.size_bytes);
+    assert_eq!(Some((digest, "")), parse_path(&format!("{root}/")));
+    assert_eq!(Some((digest, "")), parse_path(&root));
+    assert_eq!(
+        Some((digest, "a/b.html")),
+        parse_path(&format!("{root}/a/b.html"))
+    );
+    assert_eq!(Some((digest, "a")), parse_path(&format!("{root}/a/")));
+    assert_eq!(None, parse_path(&format!("{root}/../a")));
+    assert_eq!(None, parse_path("/not-a-digest/a"));
+    assert_eq!(None, parse_path("/"));
+}
+
+#[test]
+fn parse_ranges() {
+    assert_eq!(Some((0, 9)), parse_range("bytes=0-9", 16));
+    assert_eq!(Some((4, 15)), parse_range("bytes=4-", 16));
+    assert_eq!(Some((12, 15)), parse_range("bytes=-4", 16));
+    assert_eq!(Some((0, 15)), parse_range("bytes=-100", 16));
+    assert_eq!(Some((10, 15)), parse_range("bytes=10-100", 16));
+    assert_eq!(None, parse_range("bytes=16-", 16));
+    assert_eq!(None, parse_range("bytes=5-4", 16));
+    assert_eq!(None, parse_range("bytes=-0", 16));
+    assert_eq!(None, parse_range("bytes=0-", 0));
+    assert_eq!(None, parse_range("items=0-4", 16));
+    assert_eq!(None, parse_range("bytes=0-1,4-5", 16));
+}
@@ -1,7 +1,7 @@
 // Copyright 2021 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::io::{self, Write};
 use std::pin::Pin;
 use std::sync::Arc;
@@ -11,14 +11,16 @@ use bytes::{BufMut, Bytes};
 use futures::stream::StreamExt;
 use hashing::Digest;
 use humansize::{file_size_opts, FileSize};
+use remote_provider::{RemoteAssetProvider, RemoteStoreOptions};
 use reqwest::header::{HeaderMap, HeaderName};
 use reqwest::Error;
+use store::Store;
 use tokio_retry::strategy::{jitter, ExponentialBackoff};
 use tokio_retry::RetryIf;
 use url::Url;
 
 use crate::context::Core;
-use workunit_store::{in_workunit, Level};
+use workunit_store::{in_workunit, Level, TransferDirection, TransferProgress};
 
 enum StreamingError {
     Retryable(String),
@@ -137,7 +139,9 @@ async fn attempt_download(
     auth_headers: &BTreeMap<String, String>,
     file_name: String,
     expected_digest: Digest,
+    progress: &TransferProgress,
 ) -> Result<(Digest, Bytes), StreamingError> {
+    progress.reset();
     let mut response_stream: Box<dyn StreamingDownload> = {
         if url.scheme() == "file" {
             if let Some(host) = url.host_str() {
@@ -195,18 +199,85 @@ async fn attempt_download(
         hasher.write_all(&chunk).map_err(|err| {
             StreamingError::Retryable(format!("Error hashing/capturing URL fetch response: {err}"))
         })?;
+        progress.add_bytes(chunk.len() as u64);
     }
     let (digest, bytewriter) = hasher.finish();
     Ok((digest, bytewriter.writer.into_inner().freeze()))
 }
 
+///
+/// Delegates downloads to a remote asset service (i.e. the `Fetch` service of the REAPI Remote
+/// Asset API), which fetches the content of URLs into the remote store, and caches it there for all
+/// of its clients.
+///
+pub struct RemoteAssetFetcher {
+    provider: Arc<dyn RemoteAssetProvider>,
+    /// A Store which loads the fetched content from the remote store into the local store.
+    store: Store,
+}
+
+impl RemoteAssetFetcher {
+    pub async fn new(store: Store, options: RemoteStoreOptions) -> Result<Self, String> {
+        let provider = remote_provider::choose_remote_asset_provider(options.clone()).await?;
+        let store = store.into_local_only().into_with_remote(options).await?;
+        Ok(Self { provider, store })
+    }
+
+    ///
+    /// Fetches the content of the given URL into the local store via the remote asset service.
+    /// Returns false if the service could not fetch the content, in which case it should be
+    /// downloaded directly.
+    ///
+    async fn fetch(&self, url: &Url, digest: Digest, build_id: &str) -> Result<bool, String> {
+        if !self
+            .provider
+            .fetch_blob(vec![url.to_string()], digest, build_id)
+            .await?
+        {
+            return Ok(false);
+        }
+        self.store
+            .ensure_downloaded(HashSet::from([digest]), HashSet::new())
+            .await
+            .map_err(|e| format!("Failed to load fetched content from the remote store: {e}"))?;
+        Ok(true)
+    }
+}
+
 pub async fn download(
     core: Arc<Core>,
     url: Url,
     auth_headers: BTreeMap<String, String>,
     file_name: String,
     expected_digest: hashing::Digest,
+    build_id: &str,
 ) -> Result<(), String> {
+    // NB: The remote asset service is not given the auth headers (which might be specific to this
+    // client), and so only unauthenticated downloads are delegated to it.
+    if let Some(fetcher) = core
+        .remote_asset_fetcher
+        .as_ref()
+        .filter(|_| auth_headers.is_empty() && matches!(url.scheme(), "http" | "https"))
+    {
+        let fetch_url = &url;
+        let fetched = in_workunit!(
+            "remote_asset_fetch",
+            Level::Debug,
+            desc = Some(format!("Fetching {url} via the remote asset service")),
+            |_workunit| fetcher.fetch(fetch_url, expected_digest, build_id)
+        )
+        .await;
+        match fetched {
+            Ok(true) => return Ok(()),
+            Ok(false) => {
+                log::debug!("The remote asset service could not fetch {url}: downloading directly.")
+            }
+            Err(e) => log::warn!(
+                "Failed to fetch {url} via the remote asset service, so downloading directly: {e}"
+            ),
+        }
+    }
+
     let core2 = core.clone();
     let (actual_digest, bytes) = in_workunit!(
         "download_file",
@@ -218,7 +289,12 @@ pub async fn download(
                 .file_size(file_size_opts::CONVENTIONAL)
                 .unwrap()
         )),
-        |_workunit| async move {
+        |workunit| async move {
+            let progress = TransferProgress::new(
+                TransferDirection::Download,
+                Some(expected_digest.size_bytes as u64),
+            );
+            workunit.track_transfer(&progress);
             // TODO: Allow the retry strategy to be configurable?
             // For now we retry after 10ms, 100ms, 1s, and 10s.
             let retry_strategy = ExponentialBackoff::from_millis(10).map(jitter).take(4);
@@ -231,6 +307,7 @@ pub async fn download(
                         &auth_headers,
                         file_name.clone(),
                         expected_digest,
+                        &progress,
                     )
                 },
                 |err: &StreamingError| matches!(err, StreamingError::Retryable(_)),
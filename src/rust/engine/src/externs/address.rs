@@ -25,6 +25,7 @@ create_exception!(native_engine, UnsupportedWildcardError, InvalidAddressError);
 
 pub fn register(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(address_spec_parse, m)?)?;
+    m.add_function(wrap_pyfunction!(command_line_spec_parse, m)?)?;
 
     m.add(
         "AddressParseException",
@@ -68,13 +69,12 @@ pub fn register(py: Python, m: &PyModule) -> PyResult<()> {
 }
 
 lazy_static! {
-  // `:`, `#`, `@` are used as delimiters already. Others are reserved for possible future needs.
-  pub static ref BANNED_CHARS_IN_TARGET_NAME: HashSet<char> =
-    [':', '#', '!', '@', '?', '/', '\\', '='].into();
-  pub static ref BANNED_CHARS_IN_GENERATED_NAME: HashSet<char> =
-    [':', '#', '!', '@', '?', '='].into();
-  pub static ref BANNED_CHARS_IN_PARAMETERS: HashSet<char> =
-    [':', '#', '!', '@', '?', '=', ',', ' '].into();
+    pub static ref BANNED_CHARS_IN_TARGET_NAME: HashSet<char> =
+        address::BANNED_CHARS_IN_TARGET_NAME.into();
+    pub static ref BANNED_CHARS_IN_GENERATED_NAME: HashSet<char> =
+        address::BANNED_CHARS_IN_GENERATED_NAME.into();
+    pub static ref BANNED_CHARS_IN_PARAMETERS: HashSet<char> =
+        address::BANNED_CHARS_IN_PARAMETERS.into();
 }
 
 #[pyclass(name = "AddressInput")]
@@ -160,8 +160,8 @@ impl AddressInput {
             .zip(relative_to)
             .and_then(|(roots, relative_to)| split_on_longest_dir_prefix(relative_to, &roots));
 
-        let parsed_spec =
-            address::parse_address_spec(spec).map_err(AddressParseException::new_err)?;
+        let parsed_spec = address::parse_address_spec(spec)
+            .map_err(|e| AddressParseException::new_err(e.to_string()))?;
         if let Some(wildcard) = parsed_spec.wildcard {
             return Err(UnsupportedWildcardError::new_err(format!(
                 "The address `{spec}` from {description_of_origin} ended in a wildcard \
@@ -805,11 +805,8 @@ type ParsedAddress<'a> = (
 /// 2. an optional wildcard component (`:` or `::`)
 type ParsedSpec<'a> = (ParsedAddress<'a>, Option<&'a str>);
 
-/// Parses an "address spec" from the CLI.
-#[pyfunction]
-fn address_spec_parse(spec_str: &str) -> PyResult<ParsedSpec> {
-    let spec = address::parse_address_spec(spec_str).map_err(AddressParseException::new_err)?;
-    Ok((
+fn parsed_spec(spec: address::SpecInput) -> ParsedSpec {
+    (
         (
             spec.address.path,
             spec.address.target,
@@ -817,5 +814,21 @@ fn address_spec_parse(spec_str: &str) -> PyResult<ParsedSpec> {
             spec.address.parameters,
         ),
         spec.wildcard,
-    ))
+    )
+}
+
+/// Parses an "address spec" from the CLI.
+#[pyfunction]
+fn address_spec_parse(spec_str: &str) -> PyResult<ParsedSpec> {
+    let spec = address::parse_address_spec(spec_str)
+        .map_err(|e| AddressParseException::new_err(e.to_string()))?;
+    Ok(parsed_spec(spec))
+}
+
+/// Parses and validates a spec from the CLI, which is an ignore if it is prefixed with a `-`.
+#[pyfunction]
+fn command_line_spec_parse(spec_str: &str) -> PyResult<(bool, ParsedSpec)> {
+    let spec = address::parse_command_line_spec(spec_str)
+        .map_err(|e| AddressParseException::new_err(e.to_string()))?;
+    Ok((spec.is_ignore, parsed_spec(spec.spec)))
 }
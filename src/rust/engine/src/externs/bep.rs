@@ -0,0 +1,189 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bep::{events, BepOptions, BuildEventPublisher};
+use protos::gen::build_event_stream::TestStatus;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+use crate::externs::fs::PyFileDigest;
+use crate::externs::scheduler::PyExecutor;
+use task_executor::Executor;
+
+pub fn register(m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyBuildEventPublisher>()?;
+    Ok(())
+}
+
+fn system_time(secs_since_epoch: f64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs_f64(secs_since_epoch.max(0.0))
+}
+
+/// Publishes the events of a single run to a Build Event Service.
+#[pyclass]
+struct PyBuildEventPublisher {
+    publisher: BuildEventPublisher,
+    executor: Executor,
+}
+
+#[pymethods]
+impl PyBuildEventPublisher {
+    #[new]
+    fn __new__(
+        py: Python,
+        py_executor: &PyExecutor,
+        address: String,
+        headers: BTreeMap<String, String>,
+        build_id: String,
+        invocation_id: String,
+        project_id: String,
+        keywords: Vec<String>,
+        root_ca_certs_path: Option<PathBuf>,
+    ) -> PyResult<Self> {
+        let root_ca_certs = root_ca_certs_path
+            .map(|path| {
+                std::fs::read(&path)
+                    .map_err(|err| format!("Error reading root CA certs file {path:?}: {err}"))
+            })
+            .transpose()
+            .map_err(PyException::new_err)?;
+        let tls_config = grpc_util::tls::Config::new(root_ca_certs.as_deref(), None)
+            .map_err(PyException::new_err)?;
+
+        let executor = py_executor.0.clone();
+        let publisher = py
+            .allow_threads(|| {
+                executor.block_on(BuildEventPublisher::new(
+                    &executor,
+                    BepOptions {
+                        address,
+                        headers,
+                        tls_config,
+                        project_id,
+                        keywords,
+                    },
+                    build_id,
+                    invocation_id,
+                ))
+            })
+            .map_err(PyException::new_err)?;
+        Ok(Self {
+            publisher,
+            executor,
+        })
+    }
+
+    fn started(
+        &self,
+        uuid: &str,
+        command: &str,
+        build_tool_version: &str,
+        workspace_directory: &str,
+        start_time_secs: f64,
+    ) {
+        self.publisher.publish(events::started(
+            uuid,
+            command,
+            build_tool_version,
+            workspace_directory,
+            system_time(start_time_secs),
+        ));
+    }
+
+    fn target_configured(&self, label: &str, target_kind: &str, tags: Vec<String>) {
+        self.publisher
+            .publish(events::target_configured(label, target_kind, tags));
+    }
+
+    fn target_completed(&self, label: &str, success: bool, tags: Vec<String>) {
+        self.publisher
+            .publish(events::target_completed(label, success, tags));
+    }
+
+    #[pyo3(signature = (
+        id,
+        label,
+        mnemonic,
+        argv,
+        exit_code,
+        stdout,
+        stderr,
+        start_time_secs,
+        end_time_secs
+    ))]
+    fn action_executed(
+        &self,
+        id: &str,
+        label: Option<&str>,
+        mnemonic: &str,
+        argv: Vec<String>,
+        exit_code: i32,
+        stdout: Option<PyFileDigest>,
+        stderr: Option<PyFileDigest>,
+        start_time_secs: f64,
+        end_time_secs: f64,
+    ) {
+        self.publisher.publish(events::action_executed(
+            id,
+            label,
+            mnemonic,
+            argv,
+            exit_code,
+            stdout.map(|d| d.0),
+            stderr.map(|d| d.0),
+            system_time(start_time_secs),
+            system_time(end_time_secs),
+        ));
+    }
+
+    #[pyo3(signature = (
+        label,
+        exit_code,
+        cached_locally,
+        start_time_secs,
+        duration_secs,
+        stdout,
+        stderr
+    ))]
+    fn test_result(
+        &self,
+        label: &str,
+        exit_code: Option<i32>,
+        cached_locally: bool,
+        start_time_secs: f64,
+        duration_secs: f64,
+        stdout: Option<PyFileDigest>,
+        stderr: Option<PyFileDigest>,
+    ) {
+        let status = match exit_code {
+            Some(0) => TestStatus::Passed,
+            Some(_) => TestStatus::Failed,
+            None => TestStatus::NoStatus,
+        };
+        let outputs = [("test.log", stdout), ("test.err", stderr)]
+            .into_iter()
+            .filter_map(|(name, digest)| digest.map(|d| events::file(name, d.0)))
+            .collect();
+        self.publisher.publish(events::test_result(
+            label,
+            1,
+            status,
+            cached_locally,
+            system_time(start_time_secs),
+            Duration::from_secs_f64(duration_secs.max(0.0)),
+            outputs,
+        ));
+    }
+
+    /// Publishes the final event of the run, and waits for the stream to be acknowledged.
+    fn finished(&self, py: Python, exit_code: i32, finish_time_secs: f64) -> PyResult<()> {
+        self.publisher
+            .publish(events::finished(exit_code, system_time(finish_time_secs)));
+        py.allow_threads(|| self.executor.block_on(self.publisher.finish()))
+            .map_err(PyException::new_err)
+    }
+}
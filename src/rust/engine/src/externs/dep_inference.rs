@@ -1,51 +1,131 @@
 // Copyright 2023 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
 
 use pyo3::basic::CompareOp;
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
+use pyo3::types::{PyDict, PyList, PyTuple};
 use pyo3::{IntoPy, PyObject, Python};
 
+use dep_inference::build_file::{self, Literal};
+
 use fs::DirectoryDigest;
 use protos::gen::pants::cache::{
-    dependency_inference_request, javascript_inference_metadata, JavascriptInferenceMetadata,
+    dependency_inference_request, javascript_inference_metadata, CssInferenceMetadata,
+    GoInferenceMetadata, JavascriptInferenceMetadata,
 };
 
 use crate::externs::fs::PyDigest;
 
 pub(crate) fn register(m: &PyModule) -> PyResult<()> {
     m.add_class::<PyNativeDependenciesRequest>()?;
-    m.add_class::<PyInferenceMetadata>()
+    m.add_class::<PyNativeDependenciesBatchRequest>()?;
+    m.add_class::<PyInferenceMetadata>()?;
+    m.add_function(wrap_pyfunction!(parse_build_file, m)?)
 }
 
 #[pyclass(name = "InferenceMetadata")]
 #[derive(Clone, Debug, PartialEq)]
 pub struct PyInferenceMetadata(pub dependency_inference_request::Metadata);
 
+/// A replacement of an import pattern, which might only apply under (nested) conditions.
+#[derive(FromPyObject)]
+enum Replacement {
+    Unconditional(String),
+    Conditional(Vec<String>, String),
+}
+
+fn import_patterns(
+    patterns: &PyDict,
+) -> PyResult<Vec<javascript_inference_metadata::ImportPattern>> {
+    use javascript_inference_metadata::import_pattern::ConditionalReplacement;
+    use javascript_inference_metadata::ImportPattern;
+    patterns
+        .iter()
+        .map(|(key, value)| {
+            let mut pattern = ImportPattern {
+                pattern: key.extract()?,
+                ..ImportPattern::default()
+            };
+            for replacement in value.extract::<Vec<Replacement>>()? {
+                match replacement {
+                    Replacement::Conditional(conditions, replacement) if !conditions.is_empty() => {
+                        pattern
+                            .conditional_replacements
+                            .push(ConditionalReplacement {
+                                conditions,
+                                replacement,
+                            })
+                    }
+                    Replacement::Conditional(_, replacement)
+                    | Replacement::Unconditional(replacement) => {
+                        pattern.replacements.push(replacement)
+                    }
+                }
+            }
+            Ok(pattern)
+        })
+        .collect()
+}
+
 #[pymethods]
 impl PyInferenceMetadata {
     #[staticmethod]
-    fn javascript(package_root: String, import_patterns: &PyDict) -> PyResult<Self> {
-        use javascript_inference_metadata::ImportPattern;
-        let import_patterns: PyResult<Vec<ImportPattern>> = import_patterns
-            .iter()
-            .map(|(key, value)| {
-                Ok(ImportPattern {
-                    pattern: key.extract()?,
-                    replacements: value.extract()?,
-                })
-            })
-            .collect();
+    #[pyo3(signature = (
+        package_root,
+        import_patterns,
+        config_files = None,
+        conditions = None,
+        package_name = None,
+        export_patterns = None,
+        resolve_extensions = None
+    ))]
+    fn javascript(
+        package_root: String,
+        import_patterns: &PyDict,
+        config_files: Option<BTreeMap<String, String>>,
+        conditions: Option<Vec<String>>,
+        package_name: Option<String>,
+        export_patterns: Option<&PyDict>,
+        resolve_extensions: Option<Vec<String>>,
+    ) -> PyResult<Self> {
+        use javascript_inference_metadata::ConfigFile;
         Ok(Self(dependency_inference_request::Metadata::Js(
             JavascriptInferenceMetadata {
                 package_root,
-                import_patterns: import_patterns?,
+                import_patterns: self::import_patterns(import_patterns)?,
+                config_files: config_files
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(path, content)| ConfigFile { path, content })
+                    .collect(),
+                conditions: conditions.unwrap_or_default(),
+                package_name: package_name.unwrap_or_default(),
+                export_patterns: export_patterns
+                    .map(self::import_patterns)
+                    .transpose()?
+                    .unwrap_or_default(),
+                resolve_extensions: resolve_extensions.unwrap_or_default(),
             },
         )))
     }
 
+    #[staticmethod]
+    fn css(package_root: String) -> Self {
+        Self(dependency_inference_request::Metadata::Css(
+            CssInferenceMetadata { package_root },
+        ))
+    }
+
+    #[staticmethod]
+    fn go(build_tags: Vec<String>) -> Self {
+        Self(dependency_inference_request::Metadata::Go(
+            GoInferenceMetadata { build_tags },
+        ))
+    }
+
     fn __richcmp__(&self, other: &Self, op: CompareOp, py: Python) -> PyObject {
         match op {
             CompareOp::Eq => (self == other).into_py(py),
@@ -105,3 +185,124 @@ impl PyNativeDependenciesRequest {
         }
     }
 }
+
+/// A request to infer the dependencies of every file in a digest, which are all in the same
+/// language and share the same metadata.
+#[pyclass(name = "NativeDependenciesBatchRequest")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PyNativeDependenciesBatchRequest {
+    pub directory_digest: DirectoryDigest,
+    pub language: String,
+    pub metadata: Option<dependency_inference_request::Metadata>,
+}
+
+#[pymethods]
+impl PyNativeDependenciesBatchRequest {
+    #[new]
+    fn __new__(digest: PyDigest, language: String, metadata: Option<PyInferenceMetadata>) -> Self {
+        Self {
+            directory_digest: digest.0,
+            language,
+            metadata: metadata.map(|inner| inner.0),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut s = DefaultHasher::new();
+        self.directory_digest.hash(&mut s);
+        self.language.hash(&mut s);
+        self.metadata.hash(&mut s);
+        s.finish()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "NativeDependenciesBatchRequest('{}', {:?}, {:?})",
+            PyDigest(self.directory_digest.clone()),
+            self.language,
+            self.metadata
+        )
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp, py: Python) -> PyObject {
+        match op {
+            CompareOp::Eq => (self == other).into_py(py),
+            CompareOp::Ne => (self != other).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+}
+
+type BuildFileTargetTuple = (
+    String,
+    (usize, usize, usize, usize, usize, usize),
+    PyObject,
+    Vec<String>,
+);
+
+/// Pre-parses the given BUILD file, returning a tuple of its targets, the spans of its syntax
+/// errors, and whether it is static (i.e. does not need to be evaluated).
+#[pyfunction]
+#[allow(clippy::type_complexity)]
+fn parse_build_file(
+    py: Python,
+    content: &str,
+) -> (
+    Vec<BuildFileTargetTuple>,
+    Vec<(usize, usize, usize, usize, usize, usize)>,
+    bool,
+) {
+    let parsed = py.allow_threads(|| build_file::parse(content));
+    let targets = parsed
+        .targets
+        .into_iter()
+        .map(|target| {
+            let fields = PyDict::new(py);
+            for (name, value) in target.fields {
+                fields
+                    .set_item(name, literal_to_object(py, value))
+                    .expect("Setting a str key in a dict cannot fail.");
+            }
+            (
+                target.type_alias,
+                target.span.as_tuple(),
+                fields.into_py(py),
+                target.dynamic_fields,
+            )
+        })
+        .collect();
+    let syntax_errors = parsed
+        .syntax_errors
+        .iter()
+        .map(|span| span.as_tuple())
+        .collect();
+    (targets, syntax_errors, parsed.is_static)
+}
+
+fn literal_to_object(py: Python, literal: Literal) -> PyObject {
+    match literal {
+        Literal::None => py.None(),
+        Literal::Bool(value) => value.into_py(py),
+        Literal::Int(value) => value.into_py(py),
+        Literal::Float(value) => value.into_py(py),
+        Literal::Str(value) => value.into_py(py),
+        Literal::List(values) => PyList::new(
+            py,
+            values.into_iter().map(|value| literal_to_object(py, value)),
+        )
+        .into_py(py),
+        Literal::Tuple(values) => PyTuple::new(
+            py,
+            values.into_iter().map(|value| literal_to_object(py, value)),
+        )
+        .into_py(py),
+        Literal::Dict(items) => {
+            let dict = PyDict::new(py);
+            for (key, value) in items {
+                dict.set_item(literal_to_object(py, key), literal_to_object(py, value))
+                    .expect("Dict keys of BUILD file literals are hashable.");
+            }
+            dict.into_py(py)
+        }
+    }
+}
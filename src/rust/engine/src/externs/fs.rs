@@ -14,8 +14,8 @@ use pyo3::prelude::*;
 use pyo3::types::{PyIterator, PyString, PyTuple, PyType};
 
 use fs::{
-    DirectoryDigest, FilespecMatcher, GlobExpansionConjunction, PathGlobs, StrictGlobMatching,
-    EMPTY_DIRECTORY_DIGEST,
+    DirectoryDigest, FilespecMatcher, GlobExpansionConjunction, MergeConflictPolicy, PathGlobs,
+    StrictGlobMatching, EMPTY_DIRECTORY_DIGEST,
 };
 use hashing::{Digest, Fingerprint, EMPTY_DIGEST};
 use store::Snapshot;
@@ -255,34 +255,56 @@ impl PySnapshot {
 
 #[pyclass(name = "MergeDigests")]
 #[derive(Debug, PartialEq, Eq)]
-pub struct PyMergeDigests(pub Vec<DirectoryDigest>);
+pub struct PyMergeDigests {
+    pub digests: Vec<DirectoryDigest>,
+    pub conflict_policy: MergeConflictPolicy,
+}
 
 #[pymethods]
 impl PyMergeDigests {
     #[new]
-    fn __new__(digests: &PyAny, _py: Python) -> PyResult<Self> {
+    #[pyo3(signature = (digests, conflict_policy = None))]
+    fn __new__(digests: &PyAny, conflict_policy: Option<&PyAny>, _py: Python) -> PyResult<Self> {
         let digests: PyResult<Vec<DirectoryDigest>> = PyIterator::from_object(digests)?
             .map(|v| {
                 let py_digest = v?.extract::<PyDigest>()?;
                 Ok(py_digest.0)
             })
             .collect();
-        Ok(Self(digests?))
+        let conflict_policy = match conflict_policy {
+            Some(conflict_policy) => {
+                MergeConflictPolicy::create(conflict_policy.getattr("value")?.extract()?)
+                    .map_err(PyValueError::new_err)?
+            }
+            None => MergeConflictPolicy::default(),
+        };
+        Ok(Self {
+            digests: digests?,
+            conflict_policy,
+        })
     }
 
     fn __hash__(&self) -> u64 {
         let mut s = DefaultHasher::new();
-        self.0.hash(&mut s);
+        self.digests.hash(&mut s);
+        self.conflict_policy.hash(&mut s);
         s.finish()
     }
 
     fn __repr__(&self) -> String {
         let digests = self
-            .0
+            .digests
             .iter()
             .map(|d| format!("{}", PyDigest(d.clone())))
             .join(", ");
-        format!("MergeDigests([{digests}])")
+        if self.conflict_policy == MergeConflictPolicy::default() {
+            format!("MergeDigests([{digests}])")
+        } else {
+            format!(
+                "MergeDigests([{digests}], conflict_policy={:?})",
+                self.conflict_policy
+            )
+        }
     }
 
     fn __richcmp__(&self, other: &PyMergeDigests, op: CompareOp, py: Python) -> PyObject {
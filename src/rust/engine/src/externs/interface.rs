@@ -27,7 +27,7 @@ use futures::Future;
 use hashing::Digest;
 use log::{self, debug, error, warn, Log};
 use logging::logger::PANTS_LOGGER;
-use logging::{Logger, PythonLogLevel};
+use logging::{JsonLogConfig, Logger, PythonLogLevel};
 use petgraph::graph::{DiGraph, Graph};
 use process_execution::CacheContentBehavior;
 use pyo3::exceptions::{PyException, PyIOError, PyKeyboardInterrupt, PyValueError};
@@ -40,20 +40,22 @@ use pyo3::{create_exception, IntoPy, PyAny, PyRef};
 use regex::Regex;
 use remote::remote_cache::RemoteCacheWarningsBehavior;
 use rule_graph::{self, RuleGraph};
-use store::RemoteProvider;
+use store::{EntryType, RemoteProvider};
 use task_executor::Executor;
 use workunit_store::{
-    ArtifactOutput, ObservationMetric, UserMetadataItem, Workunit, WorkunitState, WorkunitStore,
-    WorkunitStoreHandle,
+    ArtifactOutput, BuildStatValue, ObservationMetric, UserMetadataItem, WaitingOn, Workunit,
+    WorkunitSampling, WorkunitState, WorkunitStore, WorkunitStoreHandle,
 };
 
-use crate::externs::fs::{possible_store_missing_digest, PyFileDigest};
+use crate::cache_miss;
+use crate::externs::fs::{possible_store_missing_digest, PyDigest, PyFileDigest};
 use crate::externs::process::PyProcessExecutionEnvironment;
 use crate::intrinsics;
+use crate::metrics_server::{self, MetricsServer};
 use crate::{
     externs, nodes, Core, ExecutionRequest, ExecutionStrategyOptions, ExecutionTermination,
-    Failure, Function, Key, LocalStoreOptions, Params, RemotingOptions, Rule, Scheduler, Session,
-    SessionCore, Tasks, TypeId, Types, Value,
+    Failure, Function, Key, LocalStoreOptions, MemoryAction, MemoryLimits, Params, RemotingOptions,
+    Rule, Scheduler, Session, SessionCore, Subscription, Tasks, TypeId, Types, Value,
 };
 
 #[pymodule]
@@ -61,6 +63,7 @@ fn native_engine(py: Python, m: &PyModule) -> PyO3Result<()> {
     intrinsics::register(py, m)?;
     externs::register(py, m)?;
     externs::address::register(py, m)?;
+    externs::bep::register(m)?;
     externs::fs::register(m)?;
     externs::nailgun::register(py, m)?;
     externs::options::register(m)?;
@@ -71,12 +74,14 @@ fn native_engine(py: Python, m: &PyModule) -> PyO3Result<()> {
     externs::testutil::register(m)?;
     externs::workunits::register(m)?;
     externs::dep_inference::register(m)?;
+    externs::test_results::register(m)?;
 
     m.add("PollTimeout", py.get_type::<PollTimeout>())?;
 
     m.add_class::<PyExecutionRequest>()?;
     m.add_class::<PyExecutionStrategyOptions>()?;
     m.add_class::<PyLocalStoreOptions>()?;
+    m.add_class::<PyMetricsServer>()?;
     m.add_class::<PyNailgunServer>()?;
     m.add_class::<PyRemotingOptions>()?;
     m.add_class::<PyResult>()?;
@@ -84,14 +89,19 @@ fn native_engine(py: Python, m: &PyModule) -> PyO3Result<()> {
     m.add_class::<PySession>()?;
     m.add_class::<PySessionCancellationLatch>()?;
     m.add_class::<PyStdioDestination>()?;
+    m.add_class::<PySubscription>()?;
     m.add_class::<PyTasks>()?;
     m.add_class::<PyThreadLocals>()?;
     m.add_class::<PyTypes>()?;
+    m.add_class::<PyWorkunitStream>()?;
 
     m.add_function(wrap_pyfunction!(stdio_initialize, m)?)?;
     m.add_function(wrap_pyfunction!(stdio_thread_console_set, m)?)?;
     m.add_function(wrap_pyfunction!(stdio_thread_console_color_mode_set, m)?)?;
     m.add_function(wrap_pyfunction!(stdio_thread_console_clear, m)?)?;
+    m.add_function(wrap_pyfunction!(stdio_thread_console_log_filters_set, m)?)?;
+    m.add_function(wrap_pyfunction!(stdio_thread_console_events_set, m)?)?;
+    m.add_function(wrap_pyfunction!(stdio_thread_console_event_write, m)?)?;
     m.add_function(wrap_pyfunction!(stdio_thread_get_destination, m)?)?;
     m.add_function(wrap_pyfunction!(stdio_thread_set_destination, m)?)?;
 
@@ -111,18 +121,24 @@ fn native_engine(py: Python, m: &PyModule) -> PyO3Result<()> {
     m.add_function(wrap_pyfunction!(tasks_add_query, m)?)?;
 
     m.add_function(wrap_pyfunction!(write_digest, m)?)?;
+    m.add_function(wrap_pyfunction!(export_digest, m)?)?;
     m.add_function(wrap_pyfunction!(capture_snapshots, m)?)?;
 
     m.add_function(wrap_pyfunction!(graph_invalidate_paths, m)?)?;
     m.add_function(wrap_pyfunction!(graph_invalidate_all_paths, m)?)?;
     m.add_function(wrap_pyfunction!(graph_invalidate_all, m)?)?;
     m.add_function(wrap_pyfunction!(graph_len, m)?)?;
+    m.add_function(wrap_pyfunction!(graph_set_output_stability_tracking, m)?)?;
+    m.add_function(wrap_pyfunction!(graph_output_stability_report, m)?)?;
     m.add_function(wrap_pyfunction!(graph_visualize, m)?)?;
 
     m.add_function(wrap_pyfunction!(nailgun_server_create, m)?)?;
     m.add_function(wrap_pyfunction!(nailgun_server_await_shutdown, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics_server_create, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics_server_shutdown, m)?)?;
 
     m.add_function(wrap_pyfunction!(garbage_collect_store, m)?)?;
+    m.add_function(wrap_pyfunction!(scrub_store, m)?)?;
     m.add_function(wrap_pyfunction!(lease_files_in_graph, m)?)?;
     m.add_function(wrap_pyfunction!(check_invalidation_watcher_liveness, m)?)?;
 
@@ -139,17 +155,34 @@ fn native_engine(py: Python, m: &PyModule) -> PyO3Result<()> {
     m.add_function(wrap_pyfunction!(session_run_interactive_process, m)?)?;
     m.add_function(wrap_pyfunction!(session_get_metrics, m)?)?;
     m.add_function(wrap_pyfunction!(session_get_observation_histograms, m)?)?;
+    m.add_function(wrap_pyfunction!(session_get_build_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(session_render_build_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(session_get_critical_path, m)?)?;
+    m.add_function(wrap_pyfunction!(session_get_running_workunits, m)?)?;
+    m.add_function(wrap_pyfunction!(session_render_running_workunits, m)?)?;
+    m.add_function(wrap_pyfunction!(session_get_sampled_workunits, m)?)?;
+    m.add_function(wrap_pyfunction!(session_explain_cache_misses, m)?)?;
     m.add_function(wrap_pyfunction!(session_record_test_observation, m)?)?;
     m.add_function(wrap_pyfunction!(session_isolated_shallow_clone, m)?)?;
+    m.add_function(wrap_pyfunction!(session_cancel_roots, m)?)?;
+    m.add_function(wrap_pyfunction!(session_serve_digest, m)?)?;
     m.add_function(wrap_pyfunction!(session_wait_for_tail_tasks, m)?)?;
+    m.add_function(wrap_pyfunction!(session_write_chrome_trace, m)?)?;
 
     m.add_function(wrap_pyfunction!(single_file_digests_to_bytes, m)?)?;
     m.add_function(wrap_pyfunction!(ensure_remote_has_recursive, m)?)?;
     m.add_function(wrap_pyfunction!(ensure_directory_digest_persisted, m)?)?;
+    m.add_function(wrap_pyfunction!(export_store_bundle, m)?)?;
+    m.add_function(wrap_pyfunction!(import_store_bundle, m)?)?;
 
     m.add_function(wrap_pyfunction!(scheduler_execute, m)?)?;
+    m.add_function(wrap_pyfunction!(scheduler_subscribe, m)?)?;
+    m.add_function(wrap_pyfunction!(scheduler_subscription_next, m)?)?;
     m.add_function(wrap_pyfunction!(scheduler_metrics, m)?)?;
     m.add_function(wrap_pyfunction!(scheduler_live_items, m)?)?;
+    m.add_function(wrap_pyfunction!(scheduler_check_memory, m)?)?;
+    m.add_function(wrap_pyfunction!(scheduler_memory_usage, m)?)?;
+    m.add_function(wrap_pyfunction!(scheduler_publish_to_remote_cache, m)?)?;
     m.add_function(wrap_pyfunction!(scheduler_create, m)?)?;
     m.add_function(wrap_pyfunction!(scheduler_shutdown, m)?)?;
 
@@ -180,6 +213,7 @@ impl PyTypes {
     #[new]
     fn __new__(
         paths: &PyType,
+        path_digests: &PyType,
         path_metadata_request: &PyType,
         path_metadata_result: &PyType,
         file_content: &PyType,
@@ -196,8 +230,10 @@ impl PyTypes {
         process: &PyType,
         process_result: &PyType,
         process_result_metadata: &PyType,
+        sharded_process_result: &PyType,
         coroutine: &PyType,
         session_values: &PyType,
+        complete_environment_vars: &PyType,
         run_id: &PyType,
         interactive_process: &PyType,
         interactive_process_result: &PyType,
@@ -206,6 +242,14 @@ impl PyTypes {
         docker_resolve_image_result: &PyType,
         parsed_python_deps_result: &PyType,
         parsed_javascript_deps_result: &PyType,
+        parsed_css_deps_result: &PyType,
+        parsed_go_deps_result: &PyType,
+        parsed_jvm_deps_result: &PyType,
+        parsed_dockerfile_deps_result: &PyType,
+        parsed_shell_deps_result: &PyType,
+        parsed_deps_batch_result: &PyType,
+        address_family_dirs: &PyType,
+        test_results_summary: &PyType,
         py: Python,
     ) -> Self {
         Self(RefCell::new(Some(Types {
@@ -213,6 +257,7 @@ impl PyTypes {
             file_digest: TypeId::new(py.get_type::<externs::fs::PyFileDigest>()),
             snapshot: TypeId::new(py.get_type::<externs::fs::PySnapshot>()),
             paths: TypeId::new(paths),
+            path_digests: TypeId::new(path_digests),
             path_metadata_request: TypeId::new(path_metadata_request),
             path_metadata_result: TypeId::new(path_metadata_result),
             file_content: TypeId::new(file_content),
@@ -235,8 +280,10 @@ impl PyTypes {
                 py.get_type::<externs::process::PyProcessExecutionEnvironment>(),
             ),
             process_result_metadata: TypeId::new(process_result_metadata),
+            sharded_process_result: TypeId::new(sharded_process_result),
             coroutine: TypeId::new(coroutine),
             session_values: TypeId::new(session_values),
+            complete_environment_vars: TypeId::new(complete_environment_vars),
             run_id: TypeId::new(run_id),
             interactive_process: TypeId::new(interactive_process),
             interactive_process_result: TypeId::new(interactive_process_result),
@@ -245,9 +292,17 @@ impl PyTypes {
             docker_resolve_image_result: TypeId::new(docker_resolve_image_result),
             parsed_python_deps_result: TypeId::new(parsed_python_deps_result),
             parsed_javascript_deps_result: TypeId::new(parsed_javascript_deps_result),
+            parsed_css_deps_result: TypeId::new(parsed_css_deps_result),
+            parsed_go_deps_result: TypeId::new(parsed_go_deps_result),
+            parsed_jvm_deps_result: TypeId::new(parsed_jvm_deps_result),
+            parsed_dockerfile_deps_result: TypeId::new(parsed_dockerfile_deps_result),
+            parsed_shell_deps_result: TypeId::new(parsed_shell_deps_result),
+            parsed_deps_batch_result: TypeId::new(parsed_deps_batch_result),
             deps_request: TypeId::new(
                 py.get_type::<externs::dep_inference::PyNativeDependenciesRequest>(),
             ),
+            address_family_dirs: TypeId::new(address_family_dirs),
+            test_results_summary: TypeId::new(test_results_summary),
         })))
     }
 }
@@ -269,6 +324,27 @@ struct PyExecutionStrategyOptions(ExecutionStrategyOptions);
 #[pymethods]
 impl PyExecutionStrategyOptions {
     #[new]
+    #[pyo3(signature = (
+        local_parallelism,
+        remote_parallelism,
+        local_keep_sandboxes,
+        local_cache,
+        local_enable_nailgun,
+        remote_cache_read,
+        remote_cache_write,
+        child_default_memory,
+        child_max_memory,
+        graceful_shutdown_timeout,
+        cache_max_age_secs,
+        verify_determinism,
+        determinism_report_path,
+        session_tmpdir,
+        sandboxer_socket,
+        local_sandbox_exec,
+        local_sandbox_exec_allowed_paths,
+        stall_threshold_secs,
+        retry_stalled_processes
+    ))]
     fn __new__(
         local_parallelism: usize,
         remote_parallelism: usize,
@@ -280,6 +356,15 @@ impl PyExecutionStrategyOptions {
         child_default_memory: usize,
         child_max_memory: usize,
         graceful_shutdown_timeout: usize,
+        cache_max_age_secs: Option<u64>,
+        verify_determinism: Option<Vec<String>>,
+        determinism_report_path: Option<PathBuf>,
+        session_tmpdir: Option<PathBuf>,
+        sandboxer_socket: Option<PathBuf>,
+        local_sandbox_exec: String,
+        local_sandbox_exec_allowed_paths: Vec<PathBuf>,
+        stall_threshold_secs: Option<u64>,
+        retry_stalled_processes: bool,
     ) -> Self {
         Self(ExecutionStrategyOptions {
             local_parallelism,
@@ -297,6 +382,18 @@ impl PyExecutionStrategyOptions {
             graceful_shutdown_timeout: Duration::from_secs(
                 graceful_shutdown_timeout.try_into().unwrap(),
             ),
+            cache_max_age: cache_max_age_secs.map(Duration::from_secs),
+            verify_determinism: verify_determinism.unwrap_or_default(),
+            determinism_report_path,
+            session_tmpdir,
+            sandboxer_socket,
+            local_sandbox_exec: process_execution::sandbox_exec::SandboxExecMode::from_str(
+                &local_sandbox_exec,
+            )
+            .unwrap(),
+            local_sandbox_exec_allowed_paths,
+            stall_threshold: stall_threshold_secs.map(Duration::from_secs),
+            retry_stalled_processes,
         })
     }
 }
@@ -316,14 +413,22 @@ impl PyRemotingOptions {
         store_rpc_retries: usize,
         store_rpc_concurrency: usize,
         store_rpc_timeout_millis: u64,
+        store_streaming_rpc_timeout_millis: u64,
         store_batch_api_size_limit: usize,
+        asset_fetch: bool,
         cache_warnings_behavior: String,
         cache_content_behavior: String,
         cache_rpc_concurrency: usize,
         cache_rpc_timeout_millis: u64,
+        cache_write_behind: bool,
+        cache_write_behind_rate_limit: usize,
+        cache_circuit_breaker_threshold: usize,
+        cache_circuit_breaker_cooldown_secs: u64,
         execution_headers: BTreeMap<String, String>,
         execution_overall_deadline_secs: u64,
         execution_rpc_concurrency: usize,
+        execution_priority: i32,
+        results_cache_priority: i32,
         store_address: Option<String>,
         execution_address: Option<String>,
         execution_process_cache_namespace: Option<String>,
@@ -332,6 +437,11 @@ impl PyRemotingOptions {
         client_certs_path: Option<PathBuf>,
         client_key_path: Option<PathBuf>,
         append_only_caches_base_path: Option<String>,
+        http_proxy: Option<String>,
+        https_proxy: Option<String>,
+        no_proxy: Option<String>,
+        store_upload_rate_limit: Option<usize>,
+        store_download_rate_limit: Option<usize>,
     ) -> Self {
         Self(RemotingOptions {
             provider: RemoteProvider::from_str(&provider).unwrap(),
@@ -348,7 +458,11 @@ impl PyRemotingOptions {
             store_rpc_retries,
             store_rpc_concurrency,
             store_rpc_timeout: Duration::from_millis(store_rpc_timeout_millis),
+            store_streaming_rpc_timeout: Duration::from_millis(store_streaming_rpc_timeout_millis),
             store_batch_api_size_limit,
+            store_upload_rate_limit,
+            store_download_rate_limit,
+            asset_fetch,
             cache_warnings_behavior: RemoteCacheWarningsBehavior::from_str(
                 &cache_warnings_behavior,
             )
@@ -357,10 +471,21 @@ impl PyRemotingOptions {
                 .unwrap(),
             cache_rpc_concurrency,
             cache_rpc_timeout: Duration::from_millis(cache_rpc_timeout_millis),
+            cache_write_behind,
+            cache_write_behind_rate_limit,
+            cache_circuit_breaker_threshold,
+            cache_circuit_breaker_cooldown: Duration::from_secs(
+                cache_circuit_breaker_cooldown_secs,
+            ),
             execution_headers,
             execution_overall_deadline: Duration::from_secs(execution_overall_deadline_secs),
             execution_rpc_concurrency,
+            execution_priority,
+            results_cache_priority,
             append_only_caches_base_path,
+            http_proxy,
+            https_proxy,
+            no_proxy,
         })
     }
 }
@@ -371,6 +496,17 @@ struct PyLocalStoreOptions(LocalStoreOptions);
 #[pymethods]
 impl PyLocalStoreOptions {
     #[new]
+    #[pyo3(signature = (
+        store_dir,
+        process_cache_max_size_bytes,
+        files_max_size_bytes,
+        directories_max_size_bytes,
+        lease_time_millis,
+        shard_count,
+        server_port,
+        materialize_concurrency,
+        clone_files
+    ))]
     fn __new__(
         store_dir: PathBuf,
         process_cache_max_size_bytes: usize,
@@ -378,6 +514,9 @@ impl PyLocalStoreOptions {
         directories_max_size_bytes: usize,
         lease_time_millis: u64,
         shard_count: u8,
+        server_port: Option<u16>,
+        materialize_concurrency: usize,
+        clone_files: bool,
     ) -> PyO3Result<Self> {
         if shard_count.count_ones() != 1 {
             return Err(PyValueError::new_err(format!(
@@ -391,6 +530,9 @@ impl PyLocalStoreOptions {
             directories_max_size_bytes,
             lease_time: Duration::from_millis(lease_time_millis),
             shard_count,
+            server_port,
+            materialize_concurrency,
+            clone_files,
         }))
     }
 }
@@ -401,6 +543,22 @@ struct PySession(Session);
 #[pymethods]
 impl PySession {
     #[new]
+    #[pyo3(signature = (
+        scheduler,
+        dynamic_ui,
+        ui_use_prodash,
+        max_workunit_level,
+        build_id,
+        session_values,
+        cancellation_latch,
+        chrome_trace_file,
+        record_critical_path,
+        stream_process_output,
+        run_budget_seconds,
+        workunit_sampling_threshold,
+        workunit_sampling_interval,
+        cache_miss_records_dir
+    ))]
     fn __new__(
         scheduler: &PyScheduler,
         dynamic_ui: bool,
@@ -409,6 +567,13 @@ impl PySession {
         build_id: String,
         session_values: PyObject,
         cancellation_latch: &PySessionCancellationLatch,
+        chrome_trace_file: Option<PathBuf>,
+        record_critical_path: bool,
+        stream_process_output: bool,
+        run_budget_seconds: Option<f64>,
+        workunit_sampling_threshold: Option<usize>,
+        workunit_sampling_interval: usize,
+        cache_miss_records_dir: Option<PathBuf>,
         py: Python,
     ) -> PyO3Result<Self> {
         let core = scheduler.0.core.clone();
@@ -428,6 +593,15 @@ impl PySession {
                     build_id,
                     session_values,
                     cancellation_latch,
+                    chrome_trace_file,
+                    record_critical_path,
+                    stream_process_output,
+                    run_budget_seconds.map(Duration::from_secs_f64),
+                    workunit_sampling_threshold.map(|threshold| WorkunitSampling {
+                        threshold,
+                        sample_interval: workunit_sampling_interval,
+                    }),
+                    cache_miss_records_dir,
                 )
             })
             .map_err(PyException::new_err)?;
@@ -442,6 +616,10 @@ impl PySession {
         self.0.is_cancelled()
     }
 
+    fn run_budget_exceeded(&self) -> bool {
+        self.0.run_budget_exceeded()
+    }
+
     #[getter]
     fn session_values(&self) -> PyObject {
         self.0.session_values()
@@ -480,6 +658,23 @@ impl PyNailgunServer {
     }
 }
 
+#[pyclass]
+struct PyMetricsServer {
+    server: RefCell<Option<MetricsServer>>,
+    executor: Executor,
+}
+
+#[pymethods]
+impl PyMetricsServer {
+    fn port(&self) -> PyO3Result<u16> {
+        let borrowed_server = self.server.borrow();
+        let server = borrowed_server.as_ref().ok_or_else(|| {
+            PyException::new_err("Cannot get the port of a server that has already shut down.")
+        })?;
+        Ok(server.port())
+    }
+}
+
 #[pyclass]
 struct PyExecutionRequest(RefCell<ExecutionRequest>);
 
@@ -497,6 +692,9 @@ impl PyExecutionRequest {
     }
 }
 
+#[pyclass]
+struct PySubscription(RefCell<Subscription>);
+
 #[pyclass]
 struct PyResult {
     #[pyo3(get)]
@@ -590,6 +788,7 @@ fn nailgun_server_create(
                     exe.stdin_fd as i64,
                     exe.stdout_fd as i64,
                     exe.stderr_fd as i64,
+                    exe.events_fd.map(|fd| fd as i64),
                 ));
                 match result {
                     Ok(exit_code) => {
@@ -632,6 +831,31 @@ fn nailgun_server_await_shutdown(
     }
 }
 
+#[pyfunction]
+fn metrics_server_create(py_scheduler: &PyScheduler, port: u16) -> PyO3Result<PyMetricsServer> {
+    let core = py_scheduler.0.core.clone();
+    let executor = core.executor.clone();
+    let server = MetricsServer::new(&executor, port, move || {
+        metrics_server::render_metrics(&core)
+    })
+    .map_err(PyException::new_err)?;
+    Ok(PyMetricsServer {
+        server: RefCell::new(Some(server)),
+        executor,
+    })
+}
+
+#[pyfunction]
+fn metrics_server_shutdown(py: Python, metrics_server: &PyMetricsServer) -> PyO3Result<()> {
+    if let Some(server) = metrics_server.server.borrow_mut().take() {
+        let executor = metrics_server.executor.clone();
+        py.allow_threads(|| executor.block_on(server.shutdown()))
+            .map_err(PyException::new_err)
+    } else {
+        Ok(())
+    }
+}
+
 #[pyfunction]
 fn strongly_connected_components(
     py: Python,
@@ -1009,6 +1233,88 @@ fn session_poll_workunits(
     })
 }
 
+///
+/// Streams batches of workunits to a Python callback as they become available, rather than
+/// requiring them to be polled for on an interval. See `WorkunitStore::next_workunits`.
+///
+#[pyclass]
+struct PyWorkunitStream {
+    core: Arc<Core>,
+    workunit_store: WorkunitStore,
+    max_verbosity: log::Level,
+    closed: AsyncLatch,
+}
+
+#[pymethods]
+impl PyWorkunitStream {
+    #[new]
+    fn __new__(
+        py_scheduler: &PyScheduler,
+        py_session: &PySession,
+        max_log_verbosity_level: u64,
+    ) -> PyO3Result<Self> {
+        let py_level: PythonLogLevel = max_log_verbosity_level
+            .try_into()
+            .map_err(|e| PyException::new_err(format!("{e}")))?;
+        Ok(Self {
+            core: py_scheduler.0.core.clone(),
+            workunit_store: py_session.0.workunit_store(),
+            max_verbosity: py_level.into(),
+            closed: AsyncLatch::new(),
+        })
+    }
+
+    ///
+    /// Calls the callback with each batch of `(started, completed)` workunits until `close` is
+    /// called, releasing the GIL while waiting.
+    ///
+    /// The callback runs on the calling thread, and workunits which arrive while it is running are
+    /// coalesced into the next batch. If the callback falls far behind, the work which produces
+    /// workunits is slowed down until it catches up: see `WorkunitStore::next_workunits`.
+    ///
+    fn run(&self, py: Python, callback: &PyAny) -> PyO3Result<()> {
+        self.core.executor.enter(|| loop {
+            let batch = py.allow_threads(|| {
+                self.core.executor.block_on(async {
+                    tokio::select! {
+                        biased;
+                        _ = self.closed.triggered() => None,
+                        batch = self.workunit_store.next_workunits(self.max_verbosity) => Some(batch),
+                    }
+                })
+            });
+            let Some((started, completed)) = batch else {
+                return Ok(());
+            };
+            let started = self.core.executor.block_on(workunits_to_py_tuple_value(
+                py,
+                &self.workunit_store,
+                started,
+                &self.core,
+            ))?;
+            let completed = self.core.executor.block_on(workunits_to_py_tuple_value(
+                py,
+                &self.workunit_store,
+                completed,
+                &self.core,
+            ))?;
+            callback.call1((
+                started.consume_into_py_object(py),
+                completed.consume_into_py_object(py),
+            ))?;
+        })
+    }
+
+    ///
+    /// Causes `run` to return once any in-progress call to the callback has completed. Workunits
+    /// which have not yet been delivered remain available to `session_poll_workunits`.
+    ///
+    fn close(&self) {
+        self.workunit_store.close_workunit_stream();
+        self.closed.trigger();
+    }
+}
+
 #[pyfunction]
 fn session_run_interactive_process(
     py: Python,
@@ -1064,13 +1370,115 @@ fn scheduler_live_items<'py>(
     (py_items, sizes)
 }
 
+///
+/// Checks memory usage against the given limits, clearing the graph if necessary. Returns true if
+/// the process should be restarted in order to reclaim memory.
+///
+#[pyfunction]
+fn scheduler_check_memory(
+    py: Python,
+    py_scheduler: &PyScheduler,
+    py_session: &PySession,
+    max_memory_usage: usize,
+    clear_caches_fraction: f64,
+    max_graph_size: Option<usize>,
+) -> bool {
+    let limits = MemoryLimits {
+        max_memory_usage_bytes: max_memory_usage,
+        clear_caches_fraction,
+        max_graph_size,
+    };
+    py_scheduler.0.core.executor.enter(|| {
+        py.allow_threads(|| py_scheduler.0.check_memory(&py_session.0, &limits))
+            == MemoryAction::Restart
+    })
+}
+
+///
+/// Returns the memory used by the live nodes in the graph, grouped by node type and rule, as
+/// tuples of `(node_type, rule, count, rust_bytes, python_bytes)`.
+///
 #[pyfunction]
-fn scheduler_shutdown(py: Python, py_scheduler: &PyScheduler, timeout_secs: u64) {
+#[allow(clippy::type_complexity)]
+fn scheduler_memory_usage(
+    py: Python,
+    py_scheduler: &PyScheduler,
+    py_session: &PySession,
+    include_python_values: bool,
+) -> PyO3Result<
+    Vec<(
+        &'static str,
+        Option<&'static str>,
+        usize,
+        usize,
+        Option<usize>,
+    )>,
+> {
+    let usages = py_scheduler
+        .0
+        .core
+        .executor
+        .enter(|| {
+            py.allow_threads(|| {
+                py_scheduler
+                    .0
+                    .memory_usage(&py_session.0, include_python_values)
+            })
+        })
+        .map_err(PyException::new_err)?;
+    Ok(usages
+        .into_iter()
+        .map(|usage| {
+            (
+                usage.node_type,
+                usage.rule,
+                usage.count,
+                usage.rust_bytes,
+                usage.python_bytes,
+            )
+        })
+        .collect())
+}
+
+///
+/// Uploads the locally produced process results reachable from the roots of the given request
+/// (which must have completed in the given Session) to the remote cache, returning the number of
+/// results which were published.
+///
+#[pyfunction]
+fn scheduler_publish_to_remote_cache(
+    py: Python,
+    py_scheduler: &PyScheduler,
+    py_session: &PySession,
+    py_execution_request: &PyExecutionRequest,
+) -> PyO3Result<usize> {
+    let core = &py_scheduler.0.core;
+    let roots = py_execution_request.0.borrow().roots.clone();
+    core.executor
+        .enter(|| {
+            py.allow_threads(|| {
+                py_scheduler
+                    .0
+                    .publish_to_remote_cache(&py_session.0, &roots)
+            })
+        })
+        .map_err(PyException::new_err)
+}
+
+#[pyfunction]
+fn scheduler_shutdown(
+    py: Python,
+    py_scheduler: &PyScheduler,
+    timeout_secs: u64,
+    drain_timeout_secs: u64,
+) {
     let core = &py_scheduler.0.core;
     core.executor.enter(|| {
         py.allow_threads(|| {
-            core.executor
-                .block_on(core.shutdown(Duration::from_secs(timeout_secs)));
+            core.executor.block_on(core.shutdown(
+                Duration::from_secs(timeout_secs),
+                Duration::from_secs(drain_timeout_secs),
+            ));
         })
     })
 }
@@ -1104,6 +1512,53 @@ fn scheduler_execute(
     })
 }
 
+#[pyfunction]
+fn scheduler_subscribe(
+    py_scheduler: &PyScheduler,
+    py_execution_request: &PyExecutionRequest,
+    debounce_in_ms: u64,
+) -> PyO3Result<PySubscription> {
+    let execution_request = py_execution_request.0.borrow();
+    let subscription = py_scheduler
+        .0
+        .subscribe(&execution_request, Duration::from_millis(debounce_in_ms))
+        .map_err(PyException::new_err)?;
+    Ok(PySubscription(RefCell::new(subscription)))
+}
+
+#[pyfunction]
+fn scheduler_subscription_next(
+    py: Python,
+    py_scheduler: &PyScheduler,
+    py_session: &PySession,
+    py_subscription: &PySubscription,
+    timeout_in_ms: Option<u64>,
+) -> PyO3Result<Vec<(usize, PyResult)>> {
+    py_scheduler.0.core.executor.enter(|| {
+        py_session.0.workunit_store().init_thread_state(None);
+
+        let subscription: &mut Subscription = &mut py_subscription.0.borrow_mut();
+        Ok(py
+            .allow_threads(|| {
+                py_scheduler
+                    .0
+                    .subscription_next(
+                        subscription,
+                        &py_session.0,
+                        timeout_in_ms.map(Duration::from_millis),
+                    )
+                    .map_err(|e| match e {
+                        ExecutionTermination::KeyboardInterrupt => PyKeyboardInterrupt::new_err(()),
+                        ExecutionTermination::PollTimeout => PollTimeout::new_err(()),
+                        ExecutionTermination::Fatal(msg) => PyException::new_err(msg),
+                    })
+            })?
+            .into_iter()
+            .map(|(index, root_result)| (index, py_result_from_root(py, root_result)))
+            .collect())
+    })
+}
+
 #[pyfunction]
 fn execution_add_root_select(
     py_scheduler: &PyScheduler,
@@ -1129,6 +1584,20 @@ fn execution_add_root_select(
 }
 
 #[pyfunction]
+#[pyo3(signature = (
+    py_tasks,
+    func,
+    output_type,
+    arg_types,
+    masked_types,
+    side_effecting,
+    engine_aware_return_type,
+    cacheable,
+    persistent_cache_salt,
+    name,
+    desc,
+    level
+))]
 fn tasks_task_begin(
     py_tasks: &PyTasks,
     func: PyObject,
@@ -1138,6 +1607,7 @@ fn tasks_task_begin(
     side_effecting: bool,
     engine_aware_return_type: bool,
     cacheable: bool,
+    persistent_cache_salt: Option<String>,
     name: String,
     desc: String,
     level: u64,
@@ -1161,6 +1631,7 @@ fn tasks_task_begin(
         arg_types,
         masked_types,
         cacheable,
+        persistent_cache_salt,
         name,
         if desc.is_empty() { None } else { Some(desc) },
         py_level.into(),
@@ -1261,6 +1732,34 @@ fn graph_len(py: Python, py_scheduler: &PyScheduler) -> u64 {
         .enter(|| py.allow_threads(|| core.graph.len() as u64))
 }
 
+#[pyfunction]
+fn graph_set_output_stability_tracking(py: Python, py_scheduler: &PyScheduler, enabled: bool) {
+    let core = &py_scheduler.0.core;
+    core.executor
+        .enter(|| py.allow_threads(|| core.graph.set_output_stability_tracking(enabled)))
+}
+
+#[pyfunction]
+fn graph_output_stability_report<'py>(
+    py: Python<'py>,
+    py_scheduler: &PyScheduler,
+) -> PyO3Result<Vec<&'py PyDict>> {
+    let core = &py_scheduler.0.core;
+    let report = core
+        .executor
+        .enter(|| py.allow_threads(|| core.graph.output_stability_report()));
+    report
+        .into_iter()
+        .map(|wasted| {
+            let result = PyDict::new(py);
+            result.set_item("kind", wasted.kind)?;
+            result.set_item("count", wasted.count)?;
+            result.set_item("trigger", wasted.trigger)?;
+            Ok(result)
+        })
+        .collect()
+}
+
 #[pyfunction]
 fn graph_visualize(
     py: Python,
@@ -1321,6 +1820,157 @@ fn session_get_observation_histograms<'py>(
     })
 }
 
+#[pyfunction]
+fn session_get_build_stats<'py>(
+    py: Python<'py>,
+    py_session: &PySession,
+) -> PyO3Result<&'py PyDict> {
+    let build_stats = py.allow_threads(|| py_session.0.workunit_store().build_stats());
+    let result = PyDict::new(py);
+    for (name, value) in build_stats.entries() {
+        match value {
+            BuildStatValue::Count(v) | BuildStatValue::Bytes(v) | BuildStatValue::Millis(v) => {
+                result.set_item(name, v)?
+            }
+            BuildStatValue::Ratio(r) => result.set_item(name, r)?,
+        }
+    }
+    Ok(result)
+}
+
+#[pyfunction]
+fn session_render_build_stats(py: Python, py_session: &PySession) -> String {
+    py.allow_threads(|| py_session.0.workunit_store().build_stats().render_table())
+}
+
+#[pyfunction]
+fn session_get_critical_path<'py>(
+    py: Python<'py>,
+    py_session: &PySession,
+) -> PyO3Result<Vec<&'py PyDict>> {
+    let critical_path = py.allow_threads(|| py_session.0.workunit_store().critical_path());
+    critical_path
+        .segments
+        .into_iter()
+        .map(|segment| {
+            let result = PyDict::new(py);
+            result.set_item("span_id", segment.span_id.to_string())?;
+            result.set_item("name", segment.name)?;
+            result.set_item("description", segment.description)?;
+            result.set_item("start_secs", segment.time_span.start.secs)?;
+            result.set_item("start_nanos", segment.time_span.start.nanos)?;
+            result.set_item("duration_secs", segment.time_span.duration.secs)?;
+            result.set_item("duration_nanos", segment.time_span.duration.nanos)?;
+            result.set_item("self_duration_secs", segment.self_duration.as_secs())?;
+            result.set_item("self_duration_nanos", segment.self_duration.subsec_nanos())?;
+            Ok(result)
+        })
+        .collect()
+}
+
+#[pyfunction]
+fn session_get_running_workunits<'py>(
+    py: Python<'py>,
+    py_session: &PySession,
+) -> PyO3Result<Vec<&'py PyDict>> {
+    let running = py.allow_threads(|| py_session.0.workunit_store().running_workunits());
+    running
+        .into_iter()
+        .map(|workunit| {
+            let result = PyDict::new(py);
+            result.set_item("span_id", workunit.span_id.to_string())?;
+            result.set_item("name", workunit.name)?;
+            result.set_item("description", workunit.description)?;
+            result.set_item("duration_secs", workunit.duration.as_secs())?;
+            result.set_item("duration_nanos", workunit.duration.subsec_nanos())?;
+            result.set_item("waiting_on", workunit.waiting_on.map(WaitingOn::as_str))?;
+            result.set_item(
+                "parent_ids",
+                workunit
+                    .parent_ids
+                    .into_iter()
+                    .map(|span_id| span_id.to_string())
+                    .collect::<Vec<_>>(),
+            )?;
+            Ok(result)
+        })
+        .collect()
+}
+
+#[pyfunction]
+fn session_render_running_workunits(py: Python, py_session: &PySession) -> String {
+    py.allow_threads(|| {
+        workunit_store::render_running_workunits(&py_session.0.workunit_store().running_workunits())
+    })
+}
+
+#[pyfunction]
+fn session_get_sampled_workunits<'py>(
+    py: Python<'py>,
+    py_session: &PySession,
+) -> PyO3Result<Vec<&'py PyDict>> {
+    py_session
+        .0
+        .workunit_store()
+        .sampled_workunits()
+        .into_iter()
+        .map(|(name, aggregate)| {
+            let result = PyDict::new(py);
+            result.set_item("name", name)?;
+            result.set_item("count", aggregate.count)?;
+            result.set_item("duration_secs", aggregate.duration.as_secs())?;
+            result.set_item("duration_nanos", aggregate.duration.subsec_nanos())?;
+            Ok(result)
+        })
+        .collect()
+}
+
+#[pyfunction]
+fn session_explain_cache_misses<'py>(
+    py: Python<'py>,
+    py_session: &PySession,
+    before: Option<String>,
+    after: Option<String>,
+) -> PyO3Result<&'py PyDict> {
+    let dir = py_session
+        .0
+        .cache_miss_recorder()
+        .ok_or_else(|| {
+            PyException::new_err(
+                "Cache misses are not being recorded: set `--record-cache-misses` to record them.",
+            )
+        })?
+        .dir()
+        .to_owned();
+    let (before, after, misses) = py
+        .allow_threads(|| {
+            cache_miss::explain_cache_misses(&dir, before.as_deref(), after.as_deref())
+        })
+        .map_err(PyException::new_err)?;
+
+    let result = PyDict::new(py);
+    result.set_item("before", before)?;
+    result.set_item("after", after)?;
+    let misses = misses
+        .into_iter()
+        .map(|miss| {
+            let item = PyDict::new(py);
+            item.set_item("description", miss.description)?;
+            item.set_item("action_digest", miss.action_digest)?;
+            item.set_item(
+                "changes",
+                miss.changes
+                    .iter()
+                    .map(|change| change.to_string())
+                    .collect::<Vec<_>>(),
+            )?;
+            Ok(item)
+        })
+        .collect::<PyO3Result<Vec<_>>>()?;
+    result.set_item("misses", misses)?;
+    Ok(result)
+}
+
 #[pyfunction]
 fn session_record_test_observation(py_scheduler: &PyScheduler, py_session: &PySession, value: u64) {
     py_scheduler.0.core.executor.enter(|| {
@@ -1343,6 +1993,36 @@ fn session_isolated_shallow_clone(
     Ok(PySession(session_clone))
 }
 
+#[pyfunction]
+fn session_cancel_roots(
+    py_scheduler: &PyScheduler,
+    py_session: &PySession,
+    py_execution_request: &PyExecutionRequest,
+) -> usize {
+    py_scheduler.0.core.executor.enter(|| {
+        let execution_request = py_execution_request.0.borrow();
+        py_scheduler
+            .0
+            .cancel_roots(&execution_request, &py_session.0)
+    })
+}
+
+#[pyfunction]
+fn session_serve_digest(
+    py_scheduler: &PyScheduler,
+    py_session: &PySession,
+    py_digest: &PyAny,
+) -> PyO3Result<String> {
+    py_scheduler.0.core.executor.enter(|| {
+        let digest =
+            crate::nodes::lift_directory_digest(py_digest).map_err(PyException::new_err)?;
+        py_session
+            .0
+            .serve_digest(digest)
+            .map_err(PyException::new_err)
+    })
+}
+
 #[pyfunction]
 fn session_wait_for_tail_tasks(
     py: Python,
@@ -1361,6 +2041,11 @@ fn session_wait_for_tail_tasks(
     Ok(())
 }
 
+#[pyfunction]
+fn session_write_chrome_trace(py: Python, py_session: &PySession) {
+    py.allow_threads(|| py_session.0.write_chrome_trace())
+}
+
 #[pyfunction]
 fn validate_reachability(py_scheduler: &PyScheduler) -> PyO3Result<()> {
     let core = &py_scheduler.0.core;
@@ -1530,6 +2215,26 @@ fn garbage_collect_store(
     })
 }
 
+#[pyfunction]
+fn scrub_store(
+    py: Python,
+    py_scheduler: &PyScheduler,
+    sample_fraction: f64,
+) -> PyO3Result<(usize, usize, usize)> {
+    let core = &py_scheduler.0.core;
+    core.executor.enter(|| {
+        let summary = py
+            .allow_threads(|| core.executor.block_on(core.store().scrub(sample_fraction)))
+            .map_err(|e| PyException::new_err(e.to_string()))?;
+        log::info!("{summary}");
+        Ok((
+            summary.checked,
+            summary.corrupted.len(),
+            summary.repaired.len(),
+        ))
+    })
+}
+
 #[pyfunction]
 fn lease_files_in_graph(
     py: Python,
@@ -1654,6 +2359,60 @@ fn ensure_directory_digest_persisted(
     })
 }
 
+#[pyfunction]
+fn export_store_bundle(
+    py: Python,
+    py_scheduler: &PyScheduler,
+    py_digests: &PyList,
+    path: PathBuf,
+) -> PyO3Result<()> {
+    let core = &py_scheduler.0.core;
+    core.executor.enter(|| {
+        // NB: Supports either a PyFileDigest or PyDigest as input.
+        let digests: Vec<Digest> = py_digests
+            .iter()
+            .map(|value| {
+                crate::nodes::lift_directory_digest(value)
+                    .map(|dd| dd.as_digest())
+                    .or_else(|_| crate::nodes::lift_file_digest(value))
+            })
+            .collect::<Result<Vec<Digest>, _>>()
+            .map_err(PyException::new_err)?;
+
+        py.allow_threads(|| {
+            core.executor
+                .block_on(core.store().export_bundle(digests, &path))
+        })
+        .map_err(possible_store_missing_digest)?;
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn import_store_bundle<'py>(
+    py: Python<'py>,
+    py_scheduler: &PyScheduler,
+    path: PathBuf,
+) -> PyO3Result<&'py PyList> {
+    let core = &py_scheduler.0.core;
+    core.executor.enter(|| {
+        let roots = py
+            .allow_threads(|| core.executor.block_on(core.store().import_bundle(&path)))
+            .map_err(possible_store_missing_digest)?;
+
+        let py_roots = roots
+            .into_iter()
+            .map(|(entry_type, digest)| match entry_type {
+                EntryType::Directory => {
+                    PyDigest(DirectoryDigest::from_persisted_digest(digest)).into_py(py)
+                }
+                EntryType::File => PyFileDigest(digest).into_py(py),
+            })
+            .collect::<Vec<_>>();
+        Ok(PyList::new(py, &py_roots))
+    })
+}
+
 #[pyfunction]
 fn single_file_digests_to_bytes<'py>(
     py: Python<'py>,
@@ -1762,6 +2521,55 @@ fn write_digest(
     })
 }
 
+///
+/// Exports the given digest to a directory relative to the build root, only writing the entries
+/// which have changed since the previous export: see `Store::export_directory`.
+///
+#[pyfunction]
+fn export_digest(
+    py: Python,
+    py_scheduler: &PyScheduler,
+    py_session: &PySession,
+    digest: &PyAny,
+    path_prefix: String,
+    mode: String,
+) -> PyO3Result<(usize, usize, usize)> {
+    let core = &py_scheduler.0.core;
+    core.executor.enter(|| {
+        py_session.0.workunit_store().init_thread_state(None);
+
+        let lifted_digest = nodes::lift_directory_digest(digest).map_err(PyValueError::new_err)?;
+        let mode = store::ExportMode::from_str(&mode)
+            .map_err(|e| PyValueError::new_err(format!("Unknown export mode `{mode}`: {e}")))?;
+
+        // Python will have already validated that path_prefix is a relative path.
+        let path_prefix = Path::new(&path_prefix);
+        let destination = core.build_root.join(path_prefix);
+
+        block_in_place_and_wait(py, || async move {
+            let store = core.store();
+            let summary = store
+                .export_directory(&destination, lifted_digest.clone(), mode)
+                .await?;
+
+            // Invalidate the exported paths within `path_prefix`, and the prefix itself (which
+            // contains any stale entries which were removed).
+            let snapshot = store::Snapshot::from_digest(store, lifted_digest).await?;
+            let changed_paths = snapshot
+                .tree
+                .leaf_paths()
+                .iter()
+                .map(|p| path_prefix.join(p))
+                .chain(std::iter::once(path_prefix.to_owned()))
+                .collect();
+            py_scheduler.0.invalidate_paths(&changed_paths);
+
+            Ok((summary.written, summary.unchanged, summary.removed))
+        })
+        .map_err(possible_store_missing_digest)
+    })
+}
+
 #[pyfunction]
 fn stdio_initialize(
     level: u64,
@@ -1771,6 +2579,8 @@ fn stdio_initialize(
     literal_filters: Vec<String>,
     regex_filters: Vec<String>,
     log_file_path: PathBuf,
+    json_per_run_logs: bool,
+    json_log_fd: Option<i32>,
 ) -> PyO3Result<(
     externs::stdio::PyStdioRead,
     externs::stdio::PyStdioWrite,
@@ -1797,6 +2607,10 @@ fn stdio_initialize(
         literal_filters,
         regex_filters,
         log_file_path,
+        JsonLogConfig {
+            per_run: json_per_run_logs,
+            fd: json_log_fd,
+        },
     )
     .map_err(|s| PyException::new_err(format!("Could not initialize logging: {s}")))?;
 
@@ -1823,6 +2637,38 @@ fn stdio_thread_console_clear() {
     stdio::get_destination().console_clear();
 }
 
+#[pyfunction]
+fn stdio_thread_console_log_filters_set(
+    log_levels_by_target: HashMap<String, u64>,
+) -> PyO3Result<()> {
+    let levels_by_target = log_levels_by_target
+        .into_iter()
+        .map(|(target, level)| {
+            let python_level: PythonLogLevel = level.try_into().map_err(|e| {
+                PyException::new_err(format!("Unrecognized log level from Python: {level}: {e}"))
+            })?;
+            Ok((target, python_level.into()))
+        })
+        .collect::<PyO3Result<Vec<_>>>()?;
+    let log_filters = if levels_by_target.is_empty() {
+        None
+    } else {
+        Some(stdio::LogFilters::new(None, levels_by_target))
+    };
+    stdio::get_destination().set_log_filters(log_filters);
+    Ok(())
+}
+
+#[pyfunction]
+fn stdio_thread_console_events_set(events_fileno: Option<i32>) {
+    stdio::get_destination().set_events_fd(events_fileno);
+}
+
+#[pyfunction]
+fn stdio_thread_console_event_write(event: &str) {
+    stdio::get_destination().write_event(event.as_bytes());
+}
+
 // TODO: Deprecated, but without easy access to the decorator. Use
 // `PyThreadLocals::get_for_current_thread` instead. Remove in Pants 2.17.0.dev0.
 #[pyfunction]
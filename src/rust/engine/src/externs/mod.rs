@@ -9,12 +9,13 @@ use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::fmt;
 
+use engine_error::ErrorCategory;
 use futures::future::{BoxFuture, Future};
 use futures::FutureExt;
 use lazy_static::lazy_static;
 use pyo3::exceptions::{PyAssertionError, PyException, PyStopIteration, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::{PyBytes, PyDict, PySequence, PyTuple, PyType};
+use pyo3::types::{PyBytes, PyDict, PySequence, PySet, PyTuple, PyType};
 use pyo3::{create_exception, import_exception, intern};
 use pyo3::{FromPyObject, ToPyObject};
 use smallvec::{smallvec, SmallVec};
@@ -26,6 +27,7 @@ use crate::interning::Interns;
 use crate::python::{Failure, Key, TypeId, Value};
 
 mod address;
+mod bep;
 pub mod dep_inference;
 pub mod engine_aware;
 pub mod fs;
@@ -35,10 +37,12 @@ mod interface_tests;
 pub mod nailgun;
 mod options;
 mod pantsd;
+pub mod persistable;
 pub mod process;
 pub mod scheduler;
 mod stdio;
 mod target;
+pub mod test_results;
 pub mod testutil;
 pub mod workunits;
 
@@ -54,6 +58,11 @@ pub fn register(py: Python, m: &PyModule) -> PyResult<()> {
         "IncorrectProductError",
         py.get_type::<IncorrectProductError>(),
     )?;
+    m.add("RootCancelled", py.get_type::<RootCancelled>())?;
+    m.add("RunBudgetExceeded", py.get_type::<RunBudgetExceeded>())?;
+    m.add("UserError", py.get_type::<UserError>())?;
+    m.add("InfrastructureError", py.get_type::<InfrastructureError>())?;
+    m.add("InternalError", py.get_type::<InternalError>())?;
 
     Ok(())
 }
@@ -61,6 +70,11 @@ pub fn register(py: Python, m: &PyModule) -> PyResult<()> {
 create_exception!(native_engine, EngineError, PyException);
 create_exception!(native_engine, IntrinsicError, EngineError);
 create_exception!(native_engine, IncorrectProductError, EngineError);
+create_exception!(native_engine, RootCancelled, EngineError);
+create_exception!(native_engine, RunBudgetExceeded, RootCancelled);
+create_exception!(native_engine, UserError, IntrinsicError);
+create_exception!(native_engine, InfrastructureError, IntrinsicError);
+create_exception!(native_engine, InternalError, IntrinsicError);
 
 #[derive(Clone)]
 #[pyclass]
@@ -208,6 +222,38 @@ pub fn collect_iterable(value: &PyAny) -> Result<Vec<&PyAny>, String> {
     }
 }
 
+///
+/// Extracts each item of a Python iterable into a Rust value, in a single pass while the GIL is
+/// held.
+///
+/// Large collections should be converted with one call to this function (rather than acquiring the
+/// GIL per item or per attribute), after which the GIL can be released while the Rust values are
+/// processed: see `create_digest_to_digest`.
+///
+pub fn extract_batch<'py, T>(
+    value: &'py PyAny,
+    mut extract: impl FnMut(&'py PyAny) -> PyResult<T>,
+) -> Result<Vec<T>, String> {
+    let type_name = || {
+        value
+            .get_type()
+            .name()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|_| "<unknown>".to_owned())
+    };
+    let py_iter = value
+        .iter()
+        .map_err(|e| format!("Could not iterate {}: {e}", type_name()))?;
+    let mut items = Vec::with_capacity(value.len().unwrap_or(0));
+    for (i, py_item) in py_iter.enumerate() {
+        let item = py_item
+            .and_then(&mut extract)
+            .map_err(|e| format!("Failed to convert item {i} of {}: {e}", type_name()))?;
+        items.push(item);
+    }
+    Ok(items)
+}
+
 /// Read a `FrozenDict[str, T]`.
 pub fn getattr_from_str_frozendict<'p, T: FromPyObject<'p>>(
     value: &'p PyAny,
@@ -255,10 +301,57 @@ pub fn doc_url(py: Python, slug: &str) -> String {
     doc_url_func.call1((slug,)).unwrap().extract().unwrap()
 }
 
+///
+/// Estimates the memory used by Python values, via `pants.util.collections.deep_getsizeof`.
+///
+/// Objects which are reachable from more than one value are only counted for the first value
+/// which reaches them, so that the sum of the sizes of many values is not inflated by sharing.
+///
+pub struct SizeEstimator<'py> {
+    deep_getsizeof: &'py PyAny,
+    ids: &'py PySet,
+}
+
+impl<'py> SizeEstimator<'py> {
+    pub fn new(py: Python<'py>) -> PyResult<Self> {
+        let deep_getsizeof = py
+            .import("pants.util.collections")?
+            .getattr("deep_getsizeof")?;
+        Ok(SizeEstimator {
+            deep_getsizeof,
+            ids: PySet::empty(py)?,
+        })
+    }
+
+    pub fn size_of(&self, value: &PyAny) -> PyResult<usize> {
+        self.deep_getsizeof.call1((value, self.ids))?.extract()
+    }
+}
+
 pub fn create_exception(py: Python, msg: String) -> Value {
     Value::new(IntrinsicError::new_err(msg).into_py(py))
 }
 
+///
+/// Creates an exception of the type corresponding to the category of the given error, with its
+/// stable `code`, its `category`, and its `context` (outermost first) set as attributes.
+///
+pub fn create_classified_exception(py: Python, err: &engine_error::EngineError) -> Value {
+    let msg = err.to_string();
+    let exception = match err.category() {
+        ErrorCategory::User => UserError::new_err(msg),
+        ErrorCategory::Infra => InfrastructureError::new_err(msg),
+        ErrorCategory::Internal => InternalError::new_err(msg),
+    };
+    let value = exception.value(py);
+    value
+        .setattr("code", err.code().as_str())
+        .and_then(|()| value.setattr("category", err.category().as_str()))
+        .and_then(|()| value.setattr("context", err.context().collect::<Vec<_>>()))
+        .expect("Failed to set attributes of a new exception.");
+    Value::new(exception.into_py(py))
+}
+
 pub(crate) enum GeneratorInput {
     Initial,
     Arg(Value),
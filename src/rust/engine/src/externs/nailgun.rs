@@ -61,14 +61,17 @@ impl PyNailgunClient {
 
         py.allow_threads(|| {
             self.executor
-                .block_on(nailgun::client_execute(self.port, command, args, env_list))
+                .block_on(nailgun::client_execute(
+                    self.port, command, args, env_list, None,
+                ))
                 .map_err(|e| match e {
                     NailgunClientError::PreConnect(err_str) => {
                         PantsdConnectionException::new_err(err_str)
                     }
-                    NailgunClientError::PostConnect(err_str) => {
-                        PantsdClientException::new_err(err_str)
-                    }
+                    NailgunClientError::PostConnect(err_str)
+                    | NailgunClientError::ConnectionLost {
+                        message: err_str, ..
+                    } => PantsdClientException::new_err(err_str),
                     NailgunClientError::BrokenPipe => PyBrokenPipeError::new_err(""),
                     NailgunClientError::KeyboardInterrupt => PyKeyboardInterrupt::new_err(""),
                 })
@@ -6,8 +6,8 @@ use pyo3::prelude::*;
 use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
 
 use options::{
-    Args, ConfigSource, Env, ListOptionValue, OptionId, OptionParser, OptionalOptionValue, Scope,
-    Val,
+    Args, ConfigSource, Env, ListOptionValue, OptionId, OptionParser, OptionSchema,
+    OptionalOptionValue, Schema, Scope, ScopeSchema, Shell, Val,
 };
 
 use std::collections::HashMap;
@@ -16,9 +16,62 @@ pub(crate) fn register(m: &PyModule) -> PyResult<()> {
     m.add_class::<PyOptionId>()?;
     m.add_class::<PyConfigSource>()?;
     m.add_class::<PyOptionParser>()?;
+    m.add_function(wrap_pyfunction!(interpolate_config_value, m)?)?;
+    m.add_function(wrap_pyfunction!(render_option_schema, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_completion_script, m)?)?;
     Ok(())
 }
 
+/// Interpolates the `%(name)s` and `${env.NAME}` placeholders of a config value, as the Rust
+/// options parser does.
+#[pyfunction]
+fn interpolate_config_value(
+    value: String,
+    replacements: HashMap<String, String>,
+) -> PyResult<String> {
+    options::interpolate_string(value, &replacements).map_err(PyValueError::new_err)
+}
+
+/// The (name, type, choices, default, help) of a registered option.
+type OptionSchemaTuple<'a> = (String, String, Vec<String>, &'a PyAny, String);
+
+/// Renders the (scope, is_goal, description, options) of the registered scopes as JSON.
+///
+/// Defaults which can't be represented as option values (e.g. `None`) are omitted.
+#[pyfunction]
+fn render_option_schema(
+    scopes: Vec<(String, bool, String, Vec<OptionSchemaTuple>)>,
+) -> PyResult<String> {
+    let scopes = scopes
+        .into_iter()
+        .map(|(scope, is_goal, description, options)| ScopeSchema {
+            scope,
+            is_goal,
+            description,
+            options: options
+                .into_iter()
+                .map(|(name, option_type, choices, default, help)| OptionSchema {
+                    name,
+                    option_type,
+                    choices,
+                    default: py_object_to_val(default).ok(),
+                    help,
+                })
+                .collect(),
+        })
+        .collect();
+    Schema { scopes }.to_json().map_err(PyException::new_err)
+}
+
+/// Generates a completion script for the given shell which embeds the goals and options of a
+/// schema rendered by `render_option_schema`.
+#[pyfunction]
+fn generate_completion_script(shell: &str, schema_json: &str) -> PyResult<String> {
+    let shell = shell.parse::<Shell>().map_err(PyValueError::new_err)?;
+    let schema = Schema::from_json(schema_json).map_err(PyValueError::new_err)?;
+    Ok(options::completion_script(&schema, shell))
+}
+
 fn val_to_py_object(py: Python, val: &Val) -> PyResult<PyObject> {
     let res = match val {
         Val::Bool(b) => b.into_py(py),
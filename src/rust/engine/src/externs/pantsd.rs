@@ -5,11 +5,16 @@ use std::collections::HashSet;
 
 use pyo3::exceptions::PyException;
 use pyo3::prelude::*;
+use pyo3::types::PyTuple;
 
 use options::{Args, BuildRoot, Env, OptionParser};
 
-pub fn register(_py: Python, m: &PyModule) -> PyResult<()> {
+pub fn register(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(pantsd_fingerprint_compute, m)?)?;
+    m.add(
+        "PANTSD_CLIENT_IDEMPOTENT_GOALS",
+        PyTuple::new(py, pantsd::DEFAULT_IDEMPOTENT_GOALS),
+    )?;
     Ok(())
 }
 
@@ -0,0 +1,45 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use fs::DirectoryDigest;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::nodes::lift_directory_digest;
+use crate::python::{TypeId, Value};
+
+///
+/// The persisted form of a `pants.engine.engine_aware.PersistableValue`: its serialized bytes, and
+/// the Digests which it references.
+///
+pub(crate) struct PersistedForm {
+    pub(crate) value: Vec<u8>,
+    pub(crate) digests: Vec<DirectoryDigest>,
+}
+
+impl PersistedForm {
+    pub(crate) fn of(value: &PyAny) -> Result<PersistedForm, String> {
+        let (bytes, digests): (&PyBytes, Vec<&PyAny>) = value
+            .call_method0("persisted_form")
+            .and_then(|form| form.extract())
+            .map_err(|e| format!("Failed to compute the persisted form of {value}: {e}"))?;
+        Ok(PersistedForm {
+            value: bytes.as_bytes().to_vec(),
+            digests: digests
+                .into_iter()
+                .map(lift_directory_digest)
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+
+    ///
+    /// Restores a value of the given type from bytes which were produced by `PersistedForm::of`.
+    ///
+    pub(crate) fn restore(py: Python, product: TypeId, value: &[u8]) -> Result<Value, String> {
+        product
+            .as_py_type(py)
+            .call_method1("from_persisted_form", (PyBytes::new(py, value),))
+            .map(|value| Value::new(value.into_py(py)))
+            .map_err(|e| format!("Failed to restore a persisted {product}: {e}"))
+    }
+}
@@ -12,11 +12,7 @@ pub struct PyStdioRead;
 #[pymethods]
 impl PyStdioRead {
     fn isatty(&self) -> bool {
-        if let Ok(fd) = self.fileno() {
-            unsafe { libc::isatty(fd) != 0 }
-        } else {
-            false
-        }
+        self.fileno().map(stdio::is_terminal).unwrap_or(false)
     }
 
     fn fileno(&self) -> PyResult<i32> {
@@ -71,11 +67,7 @@ impl PyStdioWrite {
     }
 
     fn isatty(&self) -> bool {
-        if let Ok(fd) = self.fileno() {
-            unsafe { libc::isatty(fd) != 0 }
-        } else {
-            false
-        }
+        self.fileno().map(stdio::is_terminal).unwrap_or(false)
     }
 
     fn fileno(&self) -> PyResult<i32> {
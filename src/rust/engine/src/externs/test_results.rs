@@ -0,0 +1,58 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use fs::DirectoryDigest;
+use pyo3::basic::CompareOp;
+use pyo3::prelude::*;
+
+use crate::externs::fs::PyDigest;
+
+pub(crate) fn register(m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyAggregateTestResultsRequest>()
+}
+
+/// A request to parse and merge the test result files (JUnit XML or jest JSON) in digests, which
+/// are ordered from the oldest to the most recent attempt.
+#[pyclass(name = "AggregateTestResultsRequest")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PyAggregateTestResultsRequest {
+    pub digests: Vec<DirectoryDigest>,
+}
+
+#[pymethods]
+impl PyAggregateTestResultsRequest {
+    #[new]
+    fn __new__(digests: Vec<PyDigest>) -> Self {
+        Self {
+            digests: digests.into_iter().map(|digest| digest.0).collect(),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut s = DefaultHasher::new();
+        self.digests.hash(&mut s);
+        s.finish()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "AggregateTestResultsRequest([{}])",
+            self.digests
+                .iter()
+                .map(|digest| format!("'{}'", PyDigest(digest.clone())))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp, py: Python) -> PyObject {
+        match op {
+            CompareOp::Eq => (self == other).into_py(py),
+            CompareOp::Ne => (self != other).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+}
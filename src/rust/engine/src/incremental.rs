@@ -0,0 +1,135 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use cache::PersistentCache;
+use fs::{DirectoryDigest, Entry};
+use grpc_util::prost::MessageExt;
+use hashing::Digest;
+use process_execution::{InputDigests, Process};
+use prost::Message;
+use protos::gen::build::bazel::remote::execution::v2 as remexec;
+use protos::gen::pants::cache::{CacheKey, CacheKeyType};
+use store::{SnapshotOps, Store, StoreError};
+
+///
+/// Returns the key under which the state of a Process with `IncrementalOutputs` is stored in the
+/// local cache.
+///
+/// Processes share state if they have the same `IncrementalOutputs`, working directory and
+/// platform. Because the state written by one version of a tool cannot necessarily be consumed by
+/// another, the tool fingerprints and the immutable inputs (in which tools are usually provided)
+/// of the Process are also a part of the key, so that state is dropped when a tool changes.
+///
+pub(crate) fn key(process: &Process) -> Option<CacheKey> {
+    let incremental_outputs = process.incremental_outputs.as_ref()?;
+    let mut key = Vec::new();
+    let mut append = |bytes: &[u8]| {
+        key.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        key.extend_from_slice(bytes);
+    };
+    append(incremental_outputs.key.as_bytes());
+    for directory in &incremental_outputs.directories {
+        append(directory.to_string_lossy().as_bytes());
+    }
+    if let Some(working_directory) = &process.working_directory {
+        append(working_directory.to_string_lossy().as_bytes());
+    }
+    append(format!("{:?}", process.execution_environment.platform).as_bytes());
+    for (name, fingerprint) in &process.tool_fingerprints {
+        append(name.as_bytes());
+        append(fingerprint.as_bytes());
+    }
+    for (path, digest) in &process.input_digests.immutable_inputs {
+        append(path.to_string_lossy().as_bytes());
+        append(digest.as_digest().hash.to_hex().as_bytes());
+    }
+    Some(CacheKey {
+        key_type: CacheKeyType::IncrementalOutputs.into(),
+        digest: Some(Digest::of_bytes(&key).into()),
+    })
+}
+
+///
+/// Loads the state stored under the given key, if it exists and all of its content is still
+/// present in the Store.
+///
+pub(crate) async fn load(
+    local_cache: &PersistentCache,
+    store: &Store,
+    key: &CacheKey,
+) -> Result<Option<DirectoryDigest>, String> {
+    let Some(bytes) = local_cache.load(key).await? else {
+        return Ok(None);
+    };
+    let digest = remexec::Digest::decode(bytes)
+        .map_err(|e| format!("Invalid persisted incremental state: {e}"))?;
+    let state = DirectoryDigest::from_persisted_digest(Digest::try_from(&digest)?);
+    if !store
+        .exists_recursive(vec![state.clone()], vec![])
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        return Ok(None);
+    }
+    Ok(Some(state))
+}
+
+///
+/// Adds the given state to the inputs of the Process. Like its outputs, the state is relative to
+/// the working directory of the Process.
+///
+pub(crate) async fn apply(
+    store: &Store,
+    process: &mut Process,
+    state: DirectoryDigest,
+) -> Result<(), StoreError> {
+    let state = match &process.working_directory {
+        Some(working_directory) => store.add_prefix(state, working_directory).await?,
+        None => state,
+    };
+    let inputs = store
+        .merge(vec![process.input_digests.inputs.clone(), state])
+        .await?;
+    process.input_digests = InputDigests::new(
+        store,
+        inputs,
+        process.input_digests.immutable_inputs.clone(),
+        process.input_digests.use_nailgun.clone(),
+    )
+    .await?;
+    Ok(())
+}
+
+///
+/// Stores the `IncrementalOutputs` directories of the outputs of a successful run of the Process
+/// as the state for the given key.
+///
+pub(crate) async fn store(
+    local_cache: &PersistentCache,
+    store: &Store,
+    process: &Process,
+    key: &CacheKey,
+    output_directory: DirectoryDigest,
+) -> Result<(), String> {
+    let Some(incremental_outputs) = &process.incremental_outputs else {
+        return Ok(());
+    };
+    let outputs = store
+        .load_digest_trie(output_directory)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut directories = Vec::new();
+    for directory in &incremental_outputs.directories {
+        if let Some(Entry::Directory(d)) = outputs.entry(directory)? {
+            directories.push(d.tree().clone().add_prefix(directory)?.into());
+        }
+    }
+    let state = store.merge(directories).await.map_err(|e| e.to_string())?;
+    store
+        .ensure_directory_digest_persisted(state.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    local_cache
+        .store(key, remexec::Digest::from(state.as_digest()).to_bytes())
+        .await
+}
@@ -1,24 +1,35 @@
 // Copyright 2021 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use bytes::Bytes;
+use dep_inference::css::ParsedCssDependencies;
+use dep_inference::dockerfile::ParsedDockerfileDependencies;
+use dep_inference::go::ParsedGoDependencies;
 use dep_inference::javascript::ParsedJavascriptDependencies;
+use dep_inference::jvm::ParsedJvmDependencies;
 use dep_inference::python::ParsedPythonDependencies;
-use dep_inference::{javascript, python};
+use dep_inference::shell::ParsedShellDependencies;
+use dep_inference::span::SourceSpan;
+use dep_inference::{css, dockerfile, go, javascript, jvm, python, shell};
 use fs::{DirectoryDigest, Entry, SymlinkBehavior};
+use futures::future;
 use grpc_util::prost::MessageExt;
 use hashing::Digest;
 use protos::gen::pants::cache::{
     dependency_inference_request, CacheKey, CacheKeyType, DependencyInferenceRequest,
 };
 use pyo3::prelude::{pyfunction, wrap_pyfunction, PyModule, PyResult, Python, ToPyObject};
+use pyo3::types::PyDict;
 use store::Store;
 use workunit_store::{in_workunit, Level};
 
-use crate::externs::dep_inference::PyNativeDependenciesRequest;
+use crate::externs::dep_inference::{
+    PyNativeDependenciesBatchRequest, PyNativeDependenciesRequest,
+};
 use crate::externs::PyGeneratorResponseNativeCall;
 use crate::nodes::{task_get_context, NodeResult};
 use crate::python::{Failure, Value};
@@ -27,6 +38,12 @@ use crate::{externs, Core};
 pub fn register(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(parse_python_deps, m)?)?;
     m.add_function(wrap_pyfunction!(parse_javascript_deps, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_css_deps, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_go_deps, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_jvm_deps, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_dockerfile_deps, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_shell_deps, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_deps_batch, m)?)?;
 
     Ok(())
 }
@@ -53,17 +70,49 @@ impl PreparedInferenceRequest {
         } = Python::with_gil(|py| deps_request.extract(py))?;
 
         let (path, digest) = Self::find_one_file(directory_digest, store, backend).await?;
-        let str_path = path.display().to_string();
+        Ok(Self::new(path, digest, metadata, impl_hash))
+    }
 
-        Ok(Self {
+    /// Prepare a request for each file in the digest, which all share the same metadata.
+    ///
+    /// The requests are identical to those which `::prepare()` would create for the files one at a
+    /// time, so that they share cache entries.
+    pub async fn prepare_all(
+        directory_digest: DirectoryDigest,
+        metadata: Option<dependency_inference_request::Metadata>,
+        store: &Store,
+        impl_hash: &str,
+    ) -> NodeResult<Vec<Self>> {
+        let mut files = vec![];
+        store.load_digest_trie(directory_digest).await?.walk(
+            SymlinkBehavior::Oblivious,
+            &mut |node_path, entry| {
+                if let Entry::File(file) = entry {
+                    files.push((node_path.to_owned(), file.digest()));
+                }
+            },
+        );
+        Ok(files
+            .into_iter()
+            .map(|(path, digest)| Self::new(path, digest, metadata.clone(), impl_hash))
+            .collect())
+    }
+
+    fn new(
+        path: PathBuf,
+        digest: Digest,
+        metadata: Option<dependency_inference_request::Metadata>,
+        impl_hash: &str,
+    ) -> Self {
+        Self {
             digest,
             inner: DependencyInferenceRequest {
-                input_file_path: str_path,
+                input_file_path: path.display().to_string(),
                 input_file_digest: Some(digest.into()),
                 metadata,
                 impl_hash: impl_hash.to_string(),
             },
-        })
+        }
     }
 
     pub async fn read_digest(&self, store: &Store) -> NodeResult<String> {
@@ -109,108 +158,219 @@ impl PreparedInferenceRequest {
     }
 }
 
+/// A language with a native dependency parser.
+#[derive(Clone, Copy, Debug)]
+enum Language {
+    Python,
+    Javascript,
+    Css,
+    Go,
+    Jvm,
+    Dockerfile,
+    Shell,
+}
+
+impl Language {
+    /// The language of a `NativeDependenciesBatchRequest`.
+    fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "python" => Ok(Self::Python),
+            "javascript" => Ok(Self::Javascript),
+            "css" => Ok(Self::Css),
+            "go" => Ok(Self::Go),
+            "jvm" => Ok(Self::Jvm),
+            "dockerfile" => Ok(Self::Dockerfile),
+            "shell" => Ok(Self::Shell),
+            _ => Err(format!(
+                "Unknown language for dependency inference: {name:?}. Expected one of: python, \
+                 javascript, css, go, jvm, dockerfile, shell"
+            )),
+        }
+    }
+
+    fn backend(self) -> &'static str {
+        match self {
+            Self::Python => "Python",
+            Self::Javascript => "Javascript",
+            Self::Css => "CSS",
+            Self::Go => "Go",
+            Self::Jvm => "JVM",
+            Self::Dockerfile => "Dockerfile",
+            Self::Shell => "Shell",
+        }
+    }
+
+    fn workunit_name(self) -> &'static str {
+        match self {
+            Self::Python => "parse_python_dependencies",
+            Self::Javascript => "parse_javascript_dependencies",
+            Self::Css => "parse_css_dependencies",
+            Self::Go => "parse_go_dependencies",
+            Self::Jvm => "parse_jvm_dependencies",
+            Self::Dockerfile => "parse_dockerfile_dependencies",
+            Self::Shell => "parse_shell_dependencies",
+        }
+    }
+
+    fn impl_hash(self) -> &'static str {
+        match self {
+            Self::Python => python::IMPL_HASH,
+            Self::Javascript => javascript::IMPL_HASH,
+            Self::Css => css::IMPL_HASH,
+            Self::Go => go::IMPL_HASH,
+            Self::Jvm => jvm::IMPL_HASH,
+            Self::Dockerfile => dockerfile::IMPL_HASH,
+            Self::Shell => shell::IMPL_HASH,
+        }
+    }
+
+    /// Infer the dependencies of a file, as the language's `NativeParsed*Dependencies`.
+    async fn infer(
+        self,
+        core: &Arc<Core>,
+        store: &Store,
+        request: PreparedInferenceRequest,
+    ) -> NodeResult<Value> {
+        match self {
+            Self::Python => infer_python_deps(core, store, request).await,
+            Self::Javascript => infer_javascript_deps(core, store, request).await,
+            Self::Css => infer_css_deps(core, store, request).await,
+            Self::Go => infer_go_deps(core, store, request).await,
+            Self::Jvm => infer_jvm_deps(core, store, request).await,
+            Self::Dockerfile => infer_dockerfile_deps(core, store, request).await,
+            Self::Shell => infer_shell_deps(core, store, request).await,
+        }
+    }
+}
+
 #[pyfunction]
 fn parse_python_deps(deps_request: Value) -> PyGeneratorResponseNativeCall {
+    parse_deps(deps_request, Language::Python)
+}
+
+#[pyfunction]
+fn parse_javascript_deps(deps_request: Value) -> PyGeneratorResponseNativeCall {
+    parse_deps(deps_request, Language::Javascript)
+}
+
+#[pyfunction]
+fn parse_css_deps(deps_request: Value) -> PyGeneratorResponseNativeCall {
+    parse_deps(deps_request, Language::Css)
+}
+
+#[pyfunction]
+fn parse_go_deps(deps_request: Value) -> PyGeneratorResponseNativeCall {
+    parse_deps(deps_request, Language::Go)
+}
+
+#[pyfunction]
+fn parse_jvm_deps(deps_request: Value) -> PyGeneratorResponseNativeCall {
+    parse_deps(deps_request, Language::Jvm)
+}
+
+#[pyfunction]
+fn parse_dockerfile_deps(deps_request: Value) -> PyGeneratorResponseNativeCall {
+    parse_deps(deps_request, Language::Dockerfile)
+}
+
+#[pyfunction]
+fn parse_shell_deps(deps_request: Value) -> PyGeneratorResponseNativeCall {
+    parse_deps(deps_request, Language::Shell)
+}
+
+fn parse_deps(deps_request: Value, language: Language) -> PyGeneratorResponseNativeCall {
     PyGeneratorResponseNativeCall::new(async move {
         let context = task_get_context();
 
         let core = &context.core;
         let store = core.store();
-        let prepared_inference_request =
-            PreparedInferenceRequest::prepare(deps_request, &store, "Python", python::IMPL_HASH)
-                .await?;
+        let prepared_inference_request = PreparedInferenceRequest::prepare(
+            deps_request,
+            &store,
+            language.backend(),
+            language.impl_hash(),
+        )
+        .await?;
+
         in_workunit!(
-            "parse_python_dependencies",
+            language.workunit_name(),
             Level::Debug,
             desc = Some(format!(
-                "Determine Python dependencies for {:?}",
-                &prepared_inference_request.inner.input_file_path
+                "Determine {} dependencies for {:?}",
+                language.backend(),
+                prepared_inference_request.inner.input_file_path
             )),
             |_workunit| async move {
-                let result: ParsedPythonDependencies = get_or_create_inferred_dependencies(
-                    core,
-                    &store,
-                    prepared_inference_request,
-                    |content, request| {
-                        python::get_dependencies(content, request.inner.input_file_path.into())
-                    },
-                )
-                .await?;
-
-                let result = Python::with_gil(|py| {
-                    externs::unsafe_call(
-                        py,
-                        core.types.parsed_python_deps_result,
-                        &[
-                            result.imports.to_object(py).into(),
-                            result.string_candidates.to_object(py).into(),
-                        ],
-                    )
-                });
-
-                Ok::<_, Failure>(result)
+                language
+                    .infer(core, &store, prepared_inference_request)
+                    .await
             }
         )
         .await
     })
 }
 
+/// Infer the dependencies of every file in a digest, parsing them in parallel.
+///
+/// This avoids the overhead of a `NativeDependenciesRequest` (and a rule invocation) per file, and
+/// results in a `NativeParsedDependenciesBatch` keyed by the path of each file.
 #[pyfunction]
-fn parse_javascript_deps(deps_request: Value) -> PyGeneratorResponseNativeCall {
+fn parse_deps_batch(deps_request: Value) -> PyGeneratorResponseNativeCall {
     PyGeneratorResponseNativeCall::new(async move {
         let context = task_get_context();
 
         let core = &context.core;
         let store = core.store();
-        let prepared_inference_request = PreparedInferenceRequest::prepare(
-            deps_request,
+        let PyNativeDependenciesBatchRequest {
+            directory_digest,
+            language,
+            metadata,
+        } = Python::with_gil(|py| deps_request.extract(py))?;
+        let language = Language::from_name(&language)?;
+        let prepared_inference_requests = PreparedInferenceRequest::prepare_all(
+            directory_digest,
+            metadata,
             &store,
-            "Javascript",
-            javascript::IMPL_HASH,
+            language.impl_hash(),
         )
         .await?;
 
         in_workunit!(
-            "parse_javascript_dependencies",
+            "parse_dependencies_batch",
             Level::Debug,
             desc = Some(format!(
-                "Determine Javascript dependencies for {:?}",
-                prepared_inference_request.inner.input_file_path
+                "Determine {} dependencies for {} files",
+                language.backend(),
+                prepared_inference_requests.len()
             )),
             |_workunit| async move {
-                let result: ParsedJavascriptDependencies = get_or_create_inferred_dependencies(
-                    core,
-                    &store,
-                    prepared_inference_request,
-                    |content, request| {
-                        if let Some(dependency_inference_request::Metadata::Js(metadata)) =
-                            request.inner.metadata
-                        {
-                            javascript::get_dependencies(
-                                content,
-                                request.inner.input_file_path.into(),
-                                metadata,
-                            )
-                        } else {
-                            Err(format!(
-                                "{:?} is not valid metadata for Javascript dependency inference",
-                                request.inner.metadata
-                            ))
-                        }
-                    },
-                )
-                .await?;
+                let results =
+                    future::try_join_all(prepared_inference_requests.into_iter().map(|request| {
+                        let path = request.inner.input_file_path.clone();
+                        let task_core = core.clone();
+                        let task_store = store.clone();
+                        core.executor.spawn(
+                            async move {
+                                let result =
+                                    language.infer(&task_core, &task_store, request).await?;
+                                Ok::<_, Failure>((path, result))
+                            },
+                            |e| Err(format!("Dependency inference task failed: {e}").into()),
+                        )
+                    }))
+                    .await?;
 
                 let result = Python::with_gil(|py| {
-                    externs::unsafe_call(
+                    let results_by_path = PyDict::new(py);
+                    for (path, result) in results {
+                        results_by_path.set_item(path, result.consume_into_py_object(py))?;
+                    }
+                    Ok::<_, Failure>(externs::unsafe_call(
                         py,
-                        core.types.parsed_javascript_deps_result,
-                        &[
-                            result.file_imports.to_object(py).into(),
-                            result.package_imports.to_object(py).into(),
-                        ],
-                    )
-                });
+                        core.types.parsed_deps_batch_result,
+                        &[results_by_path.to_object(py).into()],
+                    ))
+                })?;
 
                 Ok::<_, Failure>(result)
             }
@@ -219,6 +379,245 @@ fn parse_javascript_deps(deps_request: Value) -> PyGeneratorResponseNativeCall {
     })
 }
 
+async fn infer_python_deps(
+    core: &Arc<Core>,
+    store: &Store,
+    request: PreparedInferenceRequest,
+) -> NodeResult<Value> {
+    let result: ParsedPythonDependencies =
+        get_or_create_inferred_dependencies(core, store, request, |content, request| {
+            python::get_dependencies(content, request.inner.input_file_path.into())
+        })
+        .await?;
+
+    let imports: HashMap<_, _> = result
+        .imports
+        .into_iter()
+        .map(|(name, (line, weak, confidence))| (name, (line, weak, confidence.as_str())))
+        .collect();
+    let import_spans = import_span_tuples(result.import_spans);
+    Ok(Python::with_gil(|py| {
+        externs::unsafe_call(
+            py,
+            core.types.parsed_python_deps_result,
+            &[
+                imports.to_object(py).into(),
+                result.string_candidates.to_object(py).into(),
+                import_spans.to_object(py).into(),
+            ],
+        )
+    }))
+}
+
+async fn infer_javascript_deps(
+    core: &Arc<Core>,
+    store: &Store,
+    request: PreparedInferenceRequest,
+) -> NodeResult<Value> {
+    let result: ParsedJavascriptDependencies =
+        get_or_create_inferred_dependencies(core, store, request, |content, request| {
+            if let Some(dependency_inference_request::Metadata::Js(metadata)) =
+                request.inner.metadata
+            {
+                javascript::get_dependencies(
+                    content,
+                    request.inner.input_file_path.into(),
+                    metadata,
+                )
+            } else {
+                Err(format!(
+                    "{:?} is not valid metadata for Javascript dependency inference",
+                    request.inner.metadata
+                ))
+            }
+        })
+        .await?;
+
+    Ok(Python::with_gil(|py| {
+        externs::unsafe_call(
+            py,
+            core.types.parsed_javascript_deps_result,
+            &[
+                result.file_imports.to_object(py).into(),
+                result.package_imports.to_object(py).into(),
+                result.pattern_imports.to_object(py).into(),
+                import_span_tuples(result.import_spans).to_object(py).into(),
+            ],
+        )
+    }))
+}
+
+async fn infer_css_deps(
+    core: &Arc<Core>,
+    store: &Store,
+    request: PreparedInferenceRequest,
+) -> NodeResult<Value> {
+    let result: ParsedCssDependencies =
+        get_or_create_inferred_dependencies(core, store, request, |content, request| {
+            if let Some(dependency_inference_request::Metadata::Css(metadata)) =
+                request.inner.metadata
+            {
+                css::get_dependencies(content, request.inner.input_file_path.into(), metadata)
+            } else {
+                Err(format!(
+                    "{:?} is not valid metadata for CSS dependency inference",
+                    request.inner.metadata
+                ))
+            }
+        })
+        .await?;
+
+    Ok(Python::with_gil(|py| {
+        externs::unsafe_call(
+            py,
+            core.types.parsed_css_deps_result,
+            &[
+                result.file_imports.to_object(py).into(),
+                result.package_imports.to_object(py).into(),
+            ],
+        )
+    }))
+}
+
+async fn infer_go_deps(
+    core: &Arc<Core>,
+    store: &Store,
+    request: PreparedInferenceRequest,
+) -> NodeResult<Value> {
+    let result: ParsedGoDependencies =
+        get_or_create_inferred_dependencies(core, store, request, |content, request| {
+            if let Some(dependency_inference_request::Metadata::Go(metadata)) =
+                request.inner.metadata
+            {
+                go::get_dependencies(content, request.inner.input_file_path.into(), metadata)
+            } else {
+                Err(format!(
+                    "{:?} is not valid metadata for Go dependency inference",
+                    request.inner.metadata
+                ))
+            }
+        })
+        .await?;
+
+    Ok(Python::with_gil(|py| {
+        externs::unsafe_call(
+            py,
+            core.types.parsed_go_deps_result,
+            &[
+                result.package_name.to_object(py).into(),
+                result.imports.to_object(py).into(),
+                result.build_constraint.to_object(py).into(),
+                result.matches_build_tags.to_object(py).into(),
+            ],
+        )
+    }))
+}
+
+async fn infer_jvm_deps(
+    core: &Arc<Core>,
+    store: &Store,
+    request: PreparedInferenceRequest,
+) -> NodeResult<Value> {
+    let result: ParsedJvmDependencies =
+        get_or_create_inferred_dependencies(core, store, request, |content, request| {
+            jvm::get_dependencies(content, request.inner.input_file_path.into())
+        })
+        .await?;
+
+    Ok(Python::with_gil(|py| {
+        let imports: Vec<_> = result
+            .imports
+            .into_iter()
+            .map(|import| {
+                (
+                    import.name,
+                    import.is_static,
+                    import.is_wildcard,
+                    import.alias,
+                )
+            })
+            .collect();
+        externs::unsafe_call(
+            py,
+            core.types.parsed_jvm_deps_result,
+            &[
+                result.package.to_object(py).into(),
+                imports.to_object(py).into(),
+                result.declared_symbols.to_object(py).into(),
+                result.consumed_symbols.to_object(py).into(),
+                result.export_types.to_object(py).into(),
+                result.annotation_references.to_object(py).into(),
+            ],
+        )
+    }))
+}
+
+async fn infer_dockerfile_deps(
+    core: &Arc<Core>,
+    store: &Store,
+    request: PreparedInferenceRequest,
+) -> NodeResult<Value> {
+    let result: ParsedDockerfileDependencies =
+        get_or_create_inferred_dependencies(core, store, request, |content, _request| {
+            Ok(dockerfile::get_dependencies(content))
+        })
+        .await?;
+
+    Ok(Python::with_gil(|py| {
+        let stages: Vec<_> = result
+            .stages
+            .into_iter()
+            .map(|stage| (stage.alias, stage.image, stage.resolved_image))
+            .collect();
+        externs::unsafe_call(
+            py,
+            core.types.parsed_dockerfile_deps_result,
+            &[
+                result.build_args.to_object(py).into(),
+                stages.to_object(py).into(),
+                result.image_references.to_object(py).into(),
+                result.copy_source_paths.to_object(py).into(),
+                result.copy_build_args.to_object(py).into(),
+                result.from_image_build_args.to_object(py).into(),
+                result.version_tags.to_object(py).into(),
+            ],
+        )
+    }))
+}
+
+async fn infer_shell_deps(
+    core: &Arc<Core>,
+    store: &Store,
+    request: PreparedInferenceRequest,
+) -> NodeResult<Value> {
+    let result: ParsedShellDependencies =
+        get_or_create_inferred_dependencies(core, store, request, |content, _request| {
+            Ok(shell::get_dependencies(content))
+        })
+        .await?;
+
+    Ok(Python::with_gil(|py| {
+        externs::unsafe_call(
+            py,
+            core.types.parsed_shell_deps_result,
+            &[
+                result.file_imports.to_object(py).into(),
+                result.commands.to_object(py).into(),
+            ],
+        )
+    }))
+}
+
+/// The spans of imports as tuples, which the Python result types convert to `SourceSpan`s.
+fn import_span_tuples<S>(
+    import_spans: HashMap<String, SourceSpan, S>,
+) -> HashMap<String, (usize, usize, usize, usize, usize, usize)> {
+    import_spans
+        .into_iter()
+        .map(|(import, span)| (import, span.as_tuple()))
+        .collect()
+}
+
 pub(crate) async fn get_or_create_inferred_dependencies<T, F>(
     core: &Arc<Core>,
     store: &Store,
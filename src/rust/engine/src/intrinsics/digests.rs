@@ -2,17 +2,18 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use fs::{
-    DigestTrie, DirectoryDigest, GlobMatching, PathStat, RelativePath, SymlinkBehavior, TypedPath,
-};
+use engine_error::{EngineError, ErrorCode};
+use fs::{DigestTrie, DirectoryDigest, FilespecMatcher, PathStat, RelativePath, TypedPath};
+use futures::future;
 use hashing::{Digest, EMPTY_DIGEST};
-use pyo3::prelude::{pyfunction, wrap_pyfunction, PyModule, PyRef, PyResult, Python};
-use pyo3::types::PyTuple;
-use pyo3::IntoPy;
-use store::{SnapshotOps, SubsetParams};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::{pyfunction, wrap_pyfunction, PyAny, PyModule, PyRef, PyResult, Python};
+use pyo3::types::{PyBytes, PyTuple};
+use pyo3::{intern, IntoPy, ToPyObject};
+use store::{FileTransform, LineEnding, Relocation, SnapshotOps, SubsetParams};
 
 use crate::externs;
 use crate::externs::fs::{
@@ -20,10 +21,10 @@ use crate::externs::fs::{
 };
 use crate::externs::PyGeneratorResponseNativeCall;
 use crate::nodes::{
-    lift_directory_digest, task_get_context, unmatched_globs_additional_context, DownloadedFile,
-    NodeResult, PathMetadataNode, Snapshot,
+    lift_directory_digest, task_get_context, DigestFile, DownloadedFile, NodeResult,
+    PathMetadataNode, Paths, Snapshot,
 };
-use crate::python::{throw, Key, Value};
+use crate::python::{Key, Value};
 use crate::Failure;
 
 pub fn register(_py: Python, m: &PyModule) -> PyResult<()> {
@@ -37,7 +38,10 @@ pub fn register(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(merge_digests_request_to_digest, m)?)?;
     m.add_function(wrap_pyfunction!(path_globs_to_digest, m)?)?;
     m.add_function(wrap_pyfunction!(path_globs_to_paths, m)?)?;
+    m.add_function(wrap_pyfunction!(path_globs_to_path_digests, m)?)?;
+    m.add_function(wrap_pyfunction!(relocate_digest_to_digest, m)?)?;
     m.add_function(wrap_pyfunction!(remove_prefix_request_to_digest, m)?)?;
+    m.add_function(wrap_pyfunction!(transform_digest_to_digest, m)?)?;
     m.add_function(wrap_pyfunction!(path_metadata_request, m)?)?;
 
     Ok(())
@@ -87,9 +91,13 @@ fn remove_prefix_request_to_digest(remove_prefix: Value) -> PyGeneratorResponseN
                 .as_ref()
                 .as_ref(py)
                 .extract::<PyRef<PyRemovePrefix>>()
-                .map_err(|e| throw(format!("{e}")))?;
-            let prefix = RelativePath::new(&py_remove_prefix.prefix)
-                .map_err(|e| throw(format!("The `prefix` must be relative: {e}")))?;
+                .map_err(|e| EngineError::new(ErrorCode::InvalidDigestPrefix, e.to_string()))?;
+            let prefix = RelativePath::new(&py_remove_prefix.prefix).map_err(|e| {
+                EngineError::new(
+                    ErrorCode::InvalidDigestPrefix,
+                    format!("The `prefix` must be relative: {e}"),
+                )
+            })?;
             let res: NodeResult<_> = Ok((py_remove_prefix.digest.clone(), prefix));
             res
         })?;
@@ -110,9 +118,13 @@ fn add_prefix_request_to_digest(add_prefix: Value) -> PyGeneratorResponseNativeC
                 .as_ref()
                 .as_ref(py)
                 .extract::<PyRef<PyAddPrefix>>()
-                .map_err(|e| throw(format!("{e}")))?;
-            let prefix = RelativePath::new(&py_add_prefix.prefix)
-                .map_err(|e| throw(format!("The `prefix` must be relative: {e}")))?;
+                .map_err(|e| EngineError::new(ErrorCode::InvalidDigestPrefix, e.to_string()))?;
+            let prefix = RelativePath::new(&py_add_prefix.prefix).map_err(|e| {
+                EngineError::new(
+                    ErrorCode::InvalidDigestPrefix,
+                    format!("The `prefix` must be relative: {e}"),
+                )
+            })?;
             let res: NodeResult<(DirectoryDigest, RelativePath)> =
                 Ok((py_add_prefix.digest.clone(), prefix));
             res
@@ -149,15 +161,20 @@ fn merge_digests_request_to_digest(digests: Value) -> PyGeneratorResponseNativeC
         let core = &context.core;
         let store = core.store();
 
-        let digests = Python::with_gil(|py| {
+        let (digests, conflict_policy) = Python::with_gil(|py| {
             digests
                 .as_ref()
                 .as_ref(py)
                 .extract::<PyRef<PyMergeDigests>>()
-                .map(|py_merge_digests| py_merge_digests.0.clone())
-                .map_err(|e| throw(format!("{e}")))
+                .map(|py_merge_digests| {
+                    (
+                        py_merge_digests.digests.clone(),
+                        py_merge_digests.conflict_policy,
+                    )
+                })
+                .map_err(|e| EngineError::new(ErrorCode::InvalidDigestEntries, e.to_string()))
         })?;
-        let digest = store.merge(digests).await?;
+        let digest = store.merge_with_policy(digests, conflict_policy).await?;
         Ok::<_, Failure>(Python::with_gil(|py| {
             Snapshot::store_directory_digest(py, digest)
         })?)
@@ -186,7 +203,12 @@ fn path_globs_to_digest(path_globs: Value) -> PyGeneratorResponseNativeCall {
             let py_path_globs = path_globs.as_ref().as_ref(py);
             Snapshot::lift_path_globs(py_path_globs)
         })
-        .map_err(|e| throw(format!("Failed to parse PathGlobs: {e}")))?;
+        .map_err(|e| {
+            EngineError::new(
+                ErrorCode::InvalidPathGlobs,
+                format!("Failed to parse PathGlobs: {e}"),
+            )
+        })?;
         let snapshot = context.get(Snapshot::from_path_globs(path_globs)).await?;
         Ok::<_, Failure>(Python::with_gil(|py| {
             Snapshot::store_directory_digest(py, snapshot.into())
@@ -204,45 +226,115 @@ fn path_globs_to_paths(path_globs: Value) -> PyGeneratorResponseNativeCall {
             let py_path_globs = path_globs.as_ref().as_ref(py);
             Snapshot::lift_path_globs(py_path_globs)
         })
-        .map_err(|e| throw(format!("Failed to parse PathGlobs: {e}")))?;
-
-        let path_globs = path_globs.parse().map_err(throw)?;
-        let path_stats = context
-            .expand_globs(
-                path_globs,
-                SymlinkBehavior::Oblivious,
-                unmatched_globs_additional_context(),
+        .map_err(|e| {
+            EngineError::new(
+                ErrorCode::InvalidPathGlobs,
+                format!("Failed to parse PathGlobs: {e}"),
             )
-            .await?;
+        })?;
 
-        Python::with_gil(|py| {
-            let mut files = Vec::new();
-            let mut dirs = Vec::new();
-            for ps in path_stats.iter() {
-                match ps {
-                    PathStat::File { path, .. } => {
-                        files.push(Snapshot::store_path(py, path)?);
-                    }
-                    PathStat::Link { path, .. } => {
-                        panic!("Paths shouldn't be symlink-aware {path:?}");
-                    }
-                    PathStat::Dir { path, .. } => {
-                        dirs.push(Snapshot::store_path(py, path)?);
-                    }
+        let path_stats = context.get(Paths::from_path_globs(path_globs)).await?;
+
+        // NB: The paths are decoded before acquiring the GIL, and then converted in a single batch.
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        for ps in path_stats.iter() {
+            match ps {
+                PathStat::File { path, .. } => {
+                    files.push(path_to_str(path)?);
+                }
+                PathStat::Link { path, .. } => {
+                    panic!("Paths shouldn't be symlink-aware {path:?}");
+                }
+                PathStat::Dir { path, .. } => {
+                    dirs.push(path_to_str(path)?);
                 }
             }
+        }
+        Python::with_gil(|py| {
             Ok::<_, Failure>(externs::unsafe_call(
                 py,
                 core.types.paths,
                 &[
-                    externs::store_tuple(py, files),
-                    externs::store_tuple(py, dirs),
+                    Value::new(PyTuple::new(py, files).into_py(py)),
+                    Value::new(PyTuple::new(py, dirs).into_py(py)),
+                ],
+            ))
+        })
+    })
+}
+
+#[pyfunction]
+fn path_globs_to_path_digests(path_globs: Value) -> PyGeneratorResponseNativeCall {
+    PyGeneratorResponseNativeCall::new(async move {
+        let context = task_get_context();
+        let core = &context.core;
+
+        let path_globs = Python::with_gil(|py| {
+            let py_path_globs = path_globs.as_ref().as_ref(py);
+            Snapshot::lift_path_globs(py_path_globs)
+        })
+        .map_err(|e| {
+            EngineError::new(
+                ErrorCode::InvalidPathGlobs,
+                format!("Failed to parse PathGlobs: {e}"),
+            )
+        })?;
+
+        let path_stats = context.get(Paths::from_path_globs(path_globs)).await?;
+
+        let mut files = Vec::new();
+        let mut file_stats = Vec::new();
+        let mut dirs = Vec::new();
+        for ps in path_stats.iter() {
+            match ps {
+                PathStat::File { path, stat } => {
+                    files.push(path_to_str(path)?);
+                    file_stats.push(stat.clone());
+                }
+                PathStat::Link { path, .. } => {
+                    panic!("Paths shouldn't be symlink-aware {path:?}");
+                }
+                PathStat::Dir { path, .. } => {
+                    dirs.push(path_to_str(path)?);
+                }
+            }
+        }
+
+        // NB: `DigestFile` Nodes are shared with `Snapshot`, so files which have already been
+        // fingerprinted (in this run or a previous one) are not read again.
+        let file_digests = future::try_join_all(
+            file_stats
+                .into_iter()
+                .map(|stat| context.get(DigestFile(stat))),
+        )
+        .await?;
+
+        Python::with_gil(|py| {
+            let file_digests = file_digests
+                .into_iter()
+                .map(|digest| Snapshot::store_file_digest(py, digest))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok::<_, Failure>(externs::unsafe_call(
+                py,
+                core.types.path_digests,
+                &[
+                    Value::new(PyTuple::new(py, files).into_py(py)),
+                    Value::new(
+                        PyTuple::new(py, file_digests.iter().map(|v| v.to_object(py))).into_py(py),
+                    ),
+                    Value::new(PyTuple::new(py, dirs).into_py(py)),
                 ],
             ))
         })
     })
 }
 
+fn path_to_str(path: &Path) -> Result<&str, String> {
+    path.to_str()
+        .ok_or_else(|| format!("Could not decode path `{path:?}` as UTF8."))
+}
+
 enum CreateDigestItem {
     FileContent(RelativePath, bytes::Bytes, bool),
     FileEntry(RelativePath, Digest, bool),
@@ -250,84 +342,101 @@ enum CreateDigestItem {
     Dir(RelativePath),
 }
 
-#[pyfunction]
-fn create_digest_to_digest(py: Python, create_digest: Value) -> PyGeneratorResponseNativeCall {
-    let (items_to_store, trie) = py.allow_threads(|| {
-        let mut new_file_count = 0;
-
-        let items: Vec<CreateDigestItem> = {
-            Python::with_gil(|py| {
-                let py_create_digest = create_digest.as_ref().as_ref(py);
-                externs::collect_iterable(py_create_digest)
-                    .unwrap()
-                    .into_iter()
-                    .map(|obj| {
-                        let raw_path: String = externs::getattr(obj, "path").unwrap();
-                        let path = RelativePath::new(PathBuf::from(raw_path)).unwrap();
-                        if obj.hasattr("content").unwrap() {
-                            let bytes = bytes::Bytes::from(
-                                externs::getattr::<Vec<u8>>(obj, "content").unwrap(),
-                            );
-                            let is_executable: bool =
-                                externs::getattr(obj, "is_executable").unwrap();
-                            new_file_count += 1;
-                            CreateDigestItem::FileContent(path, bytes, is_executable)
-                        } else if obj.hasattr("file_digest").unwrap() {
-                            let py_file_digest: PyFileDigest =
-                                externs::getattr(obj, "file_digest").unwrap();
-                            let is_executable: bool =
-                                externs::getattr(obj, "is_executable").unwrap();
-                            CreateDigestItem::FileEntry(path, py_file_digest.0, is_executable)
-                        } else if obj.hasattr("target").unwrap() {
-                            let target: String = externs::getattr(obj, "target").unwrap();
-                            CreateDigestItem::SymlinkEntry(path, PathBuf::from(target))
-                        } else {
-                            CreateDigestItem::Dir(path)
-                        }
-                    })
-                    .collect()
-            })
-        };
-
-        let mut typed_paths: Vec<TypedPath> = Vec::with_capacity(items.len());
-        let mut file_digests: HashMap<PathBuf, Digest> = HashMap::with_capacity(items.len());
-        let mut items_to_store = Vec::with_capacity(new_file_count);
-
-        for item in &items {
-            match item {
-                CreateDigestItem::FileContent(path, bytes, is_executable) => {
-                    let digest = Digest::of_bytes(bytes);
-                    items_to_store.push((digest.hash, bytes.clone()));
-                    typed_paths.push(TypedPath::File {
-                        path,
-                        is_executable: *is_executable,
-                    });
-                    file_digests.insert(path.to_path_buf(), digest);
-                }
-                CreateDigestItem::FileEntry(path, digest, is_executable) => {
-                    typed_paths.push(TypedPath::File {
-                        path,
-                        is_executable: *is_executable,
-                    });
-                    file_digests.insert(path.to_path_buf(), *digest);
-                }
-                CreateDigestItem::SymlinkEntry(path, target) => {
-                    typed_paths.push(TypedPath::Link { path, target });
-                    file_digests.insert(path.to_path_buf(), EMPTY_DIGEST);
-                }
-                CreateDigestItem::Dir(path) => {
-                    typed_paths.push(TypedPath::Dir(path));
-                    file_digests.insert(path.to_path_buf(), EMPTY_DIGEST);
-                }
+impl CreateDigestItem {
+    ///
+    /// Converts one of the `FileContent`, `FileEntry`, `SymlinkEntry` or `Directory` types which
+    /// make up a `CreateDigest`.
+    ///
+    fn extract(obj: &PyAny) -> PyResult<Self> {
+        let py = obj.py();
+        let raw_path: String = obj.getattr(intern!(py, "path"))?.extract()?;
+        let path = RelativePath::new(PathBuf::from(raw_path)).map_err(PyValueError::new_err)?;
+        if obj.hasattr(intern!(py, "content"))? {
+            let content: &PyBytes = obj.getattr(intern!(py, "content"))?.downcast()?;
+            let is_executable: bool = obj.getattr(intern!(py, "is_executable"))?.extract()?;
+            Ok(CreateDigestItem::FileContent(
+                path,
+                bytes::Bytes::copy_from_slice(content.as_bytes()),
+                is_executable,
+            ))
+        } else if obj.hasattr(intern!(py, "file_digest"))? {
+            let py_file_digest: PyFileDigest =
+                obj.getattr(intern!(py, "file_digest"))?.extract()?;
+            let is_executable: bool = obj.getattr(intern!(py, "is_executable"))?.extract()?;
+            Ok(CreateDigestItem::FileEntry(
+                path,
+                py_file_digest.0,
+                is_executable,
+            ))
+        } else if obj.hasattr(intern!(py, "target"))? {
+            let target: String = obj.getattr(intern!(py, "target"))?.extract()?;
+            Ok(CreateDigestItem::SymlinkEntry(path, PathBuf::from(target)))
+        } else {
+            Ok(CreateDigestItem::Dir(path))
+        }
+    }
+}
+
+///
+/// Hashes the content of the given items, and builds the trie which represents them.
+///
+/// Returns the file content which must be stored before the trie's digest is valid.
+///
+fn create_digest_trie(
+    items: Vec<CreateDigestItem>,
+) -> Result<(Vec<(hashing::Fingerprint, bytes::Bytes)>, DigestTrie), String> {
+    let mut typed_paths: Vec<TypedPath> = Vec::with_capacity(items.len());
+    let mut file_digests: HashMap<PathBuf, Digest> = HashMap::with_capacity(items.len());
+    let mut items_to_store = Vec::new();
+
+    for item in &items {
+        match item {
+            CreateDigestItem::FileContent(path, bytes, is_executable) => {
+                let digest = Digest::of_bytes(bytes);
+                items_to_store.push((digest.hash, bytes.clone()));
+                typed_paths.push(TypedPath::File {
+                    path,
+                    is_executable: *is_executable,
+                });
+                file_digests.insert(path.to_path_buf(), digest);
+            }
+            CreateDigestItem::FileEntry(path, digest, is_executable) => {
+                typed_paths.push(TypedPath::File {
+                    path,
+                    is_executable: *is_executable,
+                });
+                file_digests.insert(path.to_path_buf(), *digest);
+            }
+            CreateDigestItem::SymlinkEntry(path, target) => {
+                typed_paths.push(TypedPath::Link { path, target });
+                file_digests.insert(path.to_path_buf(), EMPTY_DIGEST);
+            }
+            CreateDigestItem::Dir(path) => {
+                typed_paths.push(TypedPath::Dir(path));
+                file_digests.insert(path.to_path_buf(), EMPTY_DIGEST);
             }
         }
+    }
 
-        let trie = DigestTrie::from_unique_paths(typed_paths, &file_digests).unwrap();
+    let trie = DigestTrie::from_unique_paths(typed_paths, &file_digests)?;
+    Ok((items_to_store, trie))
+}
 
-        (items_to_store, trie)
-    });
+#[pyfunction]
+fn create_digest_to_digest(py: Python, create_digest: Value) -> PyGeneratorResponseNativeCall {
+    // NB: All of the items are converted while we hold the GIL, and it is then released while
+    // their content is hashed.
+    let prepared =
+        externs::extract_batch(create_digest.as_ref().as_ref(py), CreateDigestItem::extract)
+            .and_then(|items| py.allow_threads(|| create_digest_trie(items)));
 
     PyGeneratorResponseNativeCall::new(async move {
+        let (items_to_store, trie) = prepared.map_err(|e| {
+            EngineError::new(
+                ErrorCode::InvalidDigestEntries,
+                format!("Failed to create digest: {e}"),
+            )
+        })?;
         let context = task_get_context();
         let store = context.core.store();
         store.store_file_bytes_batch(items_to_store, true).await?;
@@ -361,6 +470,85 @@ fn digest_subset_to_digest(digest_subset: Value) -> PyGeneratorResponseNativeCal
     })
 }
 
+#[pyfunction]
+fn relocate_digest_to_digest(relocate_digest: Value) -> PyGeneratorResponseNativeCall {
+    PyGeneratorResponseNativeCall::new(async move {
+        let context = task_get_context();
+
+        let store = context.core.store();
+        let (original_digest, relocations) = Python::with_gil(|py| {
+            let py_relocate_digest = relocate_digest.as_ref().as_ref(py);
+            let py_digest = externs::getattr(py_relocate_digest, "digest").unwrap();
+            let py_relocations = externs::getattr(py_relocate_digest, "relocations").unwrap();
+            let relocations = externs::collect_iterable(py_relocations)?
+                .into_iter()
+                .map(|py_relocation| {
+                    Ok(Relocation {
+                        src: externs::getattr(py_relocation, "src")?,
+                        dest: externs::getattr(py_relocation, "dest")?,
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let res: NodeResult<_> = Ok((lift_directory_digest(py_digest)?, relocations));
+            res
+        })?;
+        let digest = store.relocate(original_digest, relocations).await?;
+        Ok::<_, Failure>(Python::with_gil(|py| {
+            Snapshot::store_directory_digest(py, digest)
+        })?)
+    })
+}
+
+#[pyfunction]
+fn transform_digest_to_digest(transform_digest: Value) -> PyGeneratorResponseNativeCall {
+    PyGeneratorResponseNativeCall::new(async move {
+        let context = task_get_context();
+
+        let store = context.core.store();
+        let (original_digest, matcher, transforms) = Python::with_gil(|py| {
+            let py_transform_digest = transform_digest.as_ref().as_ref(py);
+            let py_digest = externs::getattr(py_transform_digest, "digest").unwrap();
+            let globs: Vec<String> = externs::getattr(py_transform_digest, "globs")?;
+            let (excludes, includes): (Vec<_>, Vec<_>) =
+                globs.into_iter().partition(|glob| glob.starts_with('!'));
+            let excludes = excludes
+                .into_iter()
+                .map(|glob| glob[1..].to_owned())
+                .collect();
+            let matcher = FilespecMatcher::new(includes, excludes)?;
+            let py_transforms = externs::getattr(py_transform_digest, "transforms").unwrap();
+            let transforms = externs::collect_iterable(py_transforms)?
+                .into_iter()
+                .map(|py_transform| {
+                    if py_transform.hasattr(intern!(py, "line_ending"))? {
+                        let line_ending = externs::getattr(py_transform, "line_ending")?;
+                        Ok(FileTransform::NormalizeLineEndings(LineEnding::create(
+                            externs::getattr(line_ending, "value")?,
+                        )?))
+                    } else if py_transform.hasattr(intern!(py, "placeholders"))? {
+                        Ok(FileTransform::Substitute(
+                            externs::getattr_from_str_frozendict(py_transform, "placeholders"),
+                        ))
+                    } else {
+                        Ok(FileTransform::SetExecutable(externs::getattr(
+                            py_transform,
+                            "is_executable",
+                        )?))
+                    }
+                })
+                .collect::<NodeResult<Vec<_>>>()?;
+            let res: NodeResult<_> = Ok((lift_directory_digest(py_digest)?, matcher, transforms));
+            res
+        })?;
+        let digest = store
+            .transform_files(original_digest, &matcher, transforms)
+            .await?;
+        Ok::<_, Failure>(Python::with_gil(|py| {
+            Snapshot::store_directory_digest(py, digest)
+        })?)
+    })
+}
+
 #[pyfunction]
 fn path_metadata_request(single_path: Value) -> PyGeneratorResponseNativeCall {
     PyGeneratorResponseNativeCall::new(async move {
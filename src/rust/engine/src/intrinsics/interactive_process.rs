@@ -95,9 +95,16 @@ pub async fn interactive_process_inner(
 
     let session = context.session.clone();
 
+    // Sandboxes which might be preserved must outlive the Session, but otherwise the sandbox is
+    // created in the Session's namespace to guarantee that it is cleaned up.
+    let sandbox_root = if keep_sandboxes == KeepSandboxes::Never {
+        session.tmpdir().path()?
+    } else {
+        context.core.local_execution_root_dir.clone()
+    };
     let mut tempdir = create_sandbox(
         context.core.executor.clone(),
-        &context.core.local_execution_root_dir,
+        &sandbox_root,
         "interactive process",
         keep_sandboxes,
     )?;
@@ -9,6 +9,8 @@ mod digests;
 mod docker;
 mod interactive_process;
 mod process;
+mod specs;
+mod test_results;
 mod values;
 
 pub use interactive_process::interactive_process_inner;
@@ -19,6 +21,8 @@ pub fn register(py: Python, m: &PyModule) -> PyResult<()> {
     docker::register(py, m)?;
     interactive_process::register(py, m)?;
     process::register(py, m)?;
+    specs::register(py, m)?;
+    test_results::register(py, m)?;
     values::register(py, m)?;
 
     Ok(())
@@ -1,18 +1,30 @@
 // Copyright 2021 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+use std::collections::BTreeMap;
 use std::time::Duration;
 
-use futures::future::TryFutureExt;
+use fs::DirectoryDigest;
+use futures::future::{self, TryFutureExt};
 use futures::try_join;
-use pyo3::prelude::{pyfunction, wrap_pyfunction, IntoPy, PyModule, PyResult, Python};
+use process_execution::{FallibleProcessResultWithPlatform, InputDigests, Process};
+use pyo3::prelude::{
+    pyfunction, wrap_pyfunction, IntoPy, PyAny, PyModule, PyObject, PyResult, Python,
+};
+use store::{SnapshotOps, Store, StoreError};
+use workunit_store::{in_workunit, Level};
 
+use crate::context::Context;
 use crate::externs::{self, PyGeneratorResponseNativeCall};
-use crate::nodes::{task_get_context, ExecuteProcess, NodeResult, Snapshot};
+use crate::nodes::{lift_directory_digest, task_get_context, ExecuteProcess, NodeResult, Snapshot};
 use crate::python::Value;
 
 pub fn register(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(process_request_to_process_result, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        sharded_process_to_sharded_process_result,
+        m
+    )?)?;
 
     Ok(())
 }
@@ -33,50 +45,248 @@ fn process_request_to_process_result(
 
         let result = context.get(process_request).await?.result;
 
+        store_process_result(&context, result).await
+    })
+}
+
+/// A partition of the inputs of a `ShardedProcess`.
+struct ProcessShard {
+    input_digest: DirectoryDigest,
+    argv: Vec<String>,
+    env: BTreeMap<String, String>,
+}
+
+impl ProcessShard {
+    ///
+    /// Creates the Process for this shard from the template Process of a `ShardedProcess`. Since
+    /// each shard differs in its inputs, each shard has its own cache entry.
+    ///
+    async fn process(
+        self,
+        store: &Store,
+        template: &Process,
+        index: usize,
+        count: usize,
+    ) -> Result<Process, StoreError> {
+        let inputs = store
+            .merge(vec![
+                template.input_digests.inputs.clone(),
+                self.input_digest,
+            ])
+            .await?;
+        let input_digests = InputDigests::new(
+            store,
+            inputs,
+            template.input_digests.immutable_inputs.clone(),
+            template.input_digests.use_nailgun.clone(),
+        )
+        .await?;
+
+        let mut process = template.clone();
+        process.argv.extend(self.argv);
+        process.env.extend(self.env);
+        process.input_digests = input_digests;
+        process.description = format!("{} (shard {}/{})", template.description, index + 1, count);
+        Ok(process)
+    }
+}
+
+///
+/// Runs a shard, retrying it (as a new attempt, and so with a new cache entry) until it succeeds
+/// or has been attempted `attempts` times. Returns the results of all attempts.
+///
+async fn run_shard(
+    context: &Context,
+    process: Process,
+    attempts: usize,
+) -> NodeResult<Vec<FallibleProcessResultWithPlatform>> {
+    let mut results = Vec::new();
+    for attempt in 0..attempts.max(1) {
+        let result = context
+            .get(ExecuteProcess {
+                process: Process {
+                    attempt,
+                    ..process.clone()
+                },
+            })
+            .await?
+            .result;
+        let succeeded = result.exit_code == 0;
+        results.push(result);
+        if succeeded {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+#[pyfunction]
+fn sharded_process_to_sharded_process_result(
+    sharded_process: Value,
+    process_config: Value,
+) -> PyGeneratorResponseNativeCall {
+    PyGeneratorResponseNativeCall::new(async move {
+        let context = task_get_context();
         let store = context.core.store();
-        let (stdout_bytes, stderr_bytes) = try_join!(
-            store
-                .load_file_bytes_with(result.stdout_digest, |bytes: &[u8]| bytes.to_owned())
-                .map_err(|e| e.enrich("Bytes from stdout")),
-            store
-                .load_file_bytes_with(result.stderr_digest, |bytes: &[u8]| bytes.to_owned())
-                .map_err(|e| e.enrich("Bytes from stderr"))
-        )?;
-
-        Python::with_gil(|py| -> NodeResult<Value> {
-            Ok(externs::unsafe_call(
-                py,
-                context.core.types.process_result,
-                &[
-                    externs::store_bytes(py, &stdout_bytes),
-                    Snapshot::store_file_digest(py, result.stdout_digest)?,
-                    externs::store_bytes(py, &stderr_bytes),
-                    Snapshot::store_file_digest(py, result.stderr_digest)?,
-                    externs::store_i64(py, result.exit_code.into()),
-                    Snapshot::store_directory_digest(py, result.output_directory)?,
-                    externs::unsafe_call(
+
+        let process_config: externs::process::PyProcessExecutionEnvironment =
+            Python::with_gil(|py| process_config.extract(py)).map_err(|e| format!("{e}"))?;
+        let (template, shards, attempts) = Python::with_gil(|py| -> Result<_, String> {
+            let sharded_process = (*sharded_process).as_ref(py);
+            let template = Value::new(externs::getattr::<PyObject>(sharded_process, "process")?);
+            let shards = externs::getattr::<Vec<&PyAny>>(sharded_process, "shards")?
+                .into_iter()
+                .map(|shard| {
+                    Ok(ProcessShard {
+                        input_digest: lift_directory_digest(externs::getattr(
+                            shard,
+                            "input_digest",
+                        )?)?,
+                        argv: externs::getattr(shard, "argv")?,
+                        env: externs::getattr_from_str_frozendict(shard, "env"),
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let attempts: usize = externs::getattr(sharded_process, "attempts")?;
+            Ok((template, shards, attempts))
+        })?;
+        let template = ExecuteProcess::lift(&store, template, process_config)
+            .map_err(|e| e.enrich("Error lifting Process"))
+            .await?
+            .process;
+
+        let count = shards.len();
+        in_workunit!(
+            "run_sharded_process",
+            Level::Debug,
+            desc = Some(format!("{} ({count} shards)", template.description)),
+            |_workunit| async move {
+                let processes = future::try_join_all(
+                    shards
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, shard)| shard.process(&store, &template, index, count)),
+                )
+                .await
+                .map_err(|e| e.enrich("Failed to create the inputs of process shards"))?;
+
+                // The shards are independent nodes, and so run concurrently.
+                let shard_results = future::try_join_all(
+                    processes
+                        .into_iter()
+                        .map(|process| run_shard(&context, process, attempts)),
+                )
+                .await?;
+
+                // The outputs of the final attempt of each shard are merged: shards which produce
+                // the same output path must produce identical content for it.
+                let finals = shard_results
+                    .iter()
+                    .filter_map(|results| results.last())
+                    .collect::<Vec<_>>();
+                let output_directory = store
+                    .merge(
+                        finals
+                            .iter()
+                            .map(|result| result.output_directory.clone())
+                            .collect(),
+                    )
+                    .await
+                    .map_err(|e| e.enrich("Failed to merge the outputs of process shards"))?;
+                let exit_code = finals
+                    .iter()
+                    .map(|result| result.exit_code)
+                    .find(|exit_code| *exit_code != 0)
+                    .unwrap_or(0);
+
+                let mut shard_values = Vec::with_capacity(shard_results.len());
+                for results in shard_results {
+                    let attempt_values = future::try_join_all(
+                        results
+                            .into_iter()
+                            .map(|result| store_process_result(&context, result)),
+                    )
+                    .await?;
+                    shard_values.push(attempt_values);
+                }
+
+                Python::with_gil(|py| -> NodeResult<Value> {
+                    let shard_values = shard_values
+                        .into_iter()
+                        .map(|attempt_values| externs::store_tuple(py, attempt_values))
+                        .collect();
+                    Ok(externs::unsafe_call(
                         py,
-                        context.core.types.process_result_metadata,
+                        context.core.types.sharded_process_result,
                         &[
-                            result
-                                .metadata
-                                .total_elapsed
-                                .map(|d| {
-                                    externs::store_u64(py, Duration::from(d).as_millis() as u64)
-                                })
-                                .unwrap_or_else(|| Value::from(py.None())),
-                            Value::from(
-                                externs::process::PyProcessExecutionEnvironment {
-                                    environment: result.metadata.environment,
-                                }
-                                .into_py(py),
-                            ),
-                            externs::store_utf8(py, result.metadata.source.into()),
-                            externs::store_u64(py, result.metadata.source_run_id.0.into()),
+                            externs::store_i64(py, exit_code.into()),
+                            Snapshot::store_directory_digest(py, output_directory)?,
+                            externs::store_tuple(py, shard_values),
                         ],
-                    ),
-                ],
-            ))
-        })
+                    ))
+                })
+            }
+        )
+        .await
+    })
+}
+
+///
+/// Converts the result of running a Process into a `FallibleProcessResult`.
+///
+async fn store_process_result(
+    context: &Context,
+    result: FallibleProcessResultWithPlatform,
+) -> NodeResult<Value> {
+    let store = context.core.store();
+    let (stdout_bytes, stderr_bytes) = try_join!(
+        store
+            .load_file_bytes_with(result.stdout_digest, |bytes: &[u8]| bytes.to_owned())
+            .map_err(|e| e.enrich("Bytes from stdout")),
+        store
+            .load_file_bytes_with(result.stderr_digest, |bytes: &[u8]| bytes.to_owned())
+            .map_err(|e| e.enrich("Bytes from stderr"))
+    )?;
+
+    let retained_sandbox = result.metadata.retained_sandbox.as_ref();
+    Python::with_gil(|py| -> NodeResult<Value> {
+        Ok(externs::unsafe_call(
+            py,
+            context.core.types.process_result,
+            &[
+                externs::store_bytes(py, &stdout_bytes),
+                Snapshot::store_file_digest(py, result.stdout_digest)?,
+                externs::store_bytes(py, &stderr_bytes),
+                Snapshot::store_file_digest(py, result.stderr_digest)?,
+                externs::store_i64(py, result.exit_code.into()),
+                Snapshot::store_directory_digest(py, result.output_directory)?,
+                externs::unsafe_call(
+                    py,
+                    context.core.types.process_result_metadata,
+                    &[
+                        result
+                            .metadata
+                            .total_elapsed
+                            .map(|d| externs::store_u64(py, Duration::from(d).as_millis() as u64))
+                            .unwrap_or_else(|| Value::from(py.None())),
+                        Value::from(
+                            externs::process::PyProcessExecutionEnvironment {
+                                environment: result.metadata.environment,
+                            }
+                            .into_py(py),
+                        ),
+                        externs::store_utf8(py, result.metadata.source.into()),
+                        externs::store_u64(py, result.metadata.source_run_id.0.into()),
+                        externs::store_u64(py, result.metadata.attempts as u64),
+                        retained_sandbox
+                            .map(|s| externs::store_utf8(py, &s.path.to_string_lossy()))
+                            .unwrap_or_else(|| Value::from(py.None())),
+                        retained_sandbox
+                            .map(|s| externs::store_utf8(py, &s.repro_command))
+                            .unwrap_or_else(|| Value::from(py.None())),
+                    ],
+                ),
+            ],
+        ))
     })
 }
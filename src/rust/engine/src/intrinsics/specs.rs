@@ -0,0 +1,123 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use fs::{
+    GlobExpansionConjunction, GlobMatching, PathGlobs, PathStat, StrictGlobMatching,
+    SymlinkBehavior,
+};
+use pyo3::prelude::{pyfunction, wrap_pyfunction, PyAny, PyModule, PyResult, Python};
+use pyo3::types::PyTuple;
+use pyo3::IntoPy;
+
+use crate::externs::{self, PyGeneratorResponseNativeCall};
+use crate::nodes::{task_get_context, unmatched_globs_additional_context};
+use crate::python::{throw, Value};
+use crate::Failure;
+
+pub fn register(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(address_family_dirs, m)?)?;
+
+    Ok(())
+}
+
+///
+/// Computes the directories whose BUILD files may define targets matched by the directory specs
+/// (e.g. `dir:`) and recursive specs (e.g. `dir::`) of a request.
+///
+/// The BUILD files are matched via the graph, so the result is memoized and invalidated when
+/// BUILD files are added or removed.
+///
+#[pyfunction]
+fn address_family_dirs(request: Value) -> PyGeneratorResponseNativeCall {
+    PyGeneratorResponseNativeCall::new(async move {
+        let context = task_get_context();
+        let core = &context.core;
+
+        let (dirs, recursive_dirs, build_patterns, build_ignore_patterns) =
+            Python::with_gil(|py| {
+                let py_request: &PyAny = request.as_ref().as_ref(py);
+                Ok::<_, String>((
+                    externs::getattr::<Vec<String>>(py_request, "dirs")?,
+                    externs::getattr::<Vec<String>>(py_request, "recursive_dirs")?,
+                    externs::getattr::<Vec<String>>(py_request, "build_patterns")?,
+                    externs::getattr::<Vec<String>>(py_request, "build_ignore_patterns")?,
+                ))
+            })?;
+
+        let path_globs = PathGlobs::new(
+            build_file_globs(
+                &dirs,
+                &recursive_dirs,
+                &build_patterns,
+                &build_ignore_patterns,
+            ),
+            StrictGlobMatching::Ignore,
+            GlobExpansionConjunction::AnyMatch,
+        )
+        .parse()
+        .map_err(throw)?;
+        let path_stats = context
+            .expand_globs(
+                path_globs,
+                SymlinkBehavior::Oblivious,
+                unmatched_globs_additional_context(),
+            )
+            .await?;
+
+        let family_dirs = path_stats
+            .iter()
+            .filter(|path_stat| matches!(path_stat, PathStat::File { .. }))
+            .filter_map(|path_stat| path_stat.path().parent())
+            .map(|dir| {
+                dir.to_str()
+                    .map(str::to_owned)
+                    .ok_or_else(|| format!("Could not decode path `{dir:?}` as UTF8."))
+            })
+            .collect::<Result<BTreeSet<_>, _>>()?;
+
+        Python::with_gil(|py| {
+            Ok::<_, Failure>(externs::unsafe_call(
+                py,
+                core.types.address_family_dirs,
+                &[Value::new(PyTuple::new(py, family_dirs).into_py(py))],
+            ))
+        })
+    })
+}
+
+///
+/// The globs for the BUILD files which may define targets residing in the given directories (or
+/// below the recursive directories): BUILD files in ancestor directories may generate targets
+/// into their descendants.
+///
+fn build_file_globs(
+    dirs: &[String],
+    recursive_dirs: &[String],
+    build_patterns: &[String],
+    build_ignore_patterns: &[String],
+) -> Vec<String> {
+    let join = |dir: &Path, pattern: &str| dir.join(pattern).to_string_lossy().into_owned();
+
+    let mut includes = BTreeSet::new();
+    for dir in dirs.iter().chain(recursive_dirs) {
+        for ancestor in Path::new(dir).ancestors() {
+            includes.extend(build_patterns.iter().map(|pattern| join(ancestor, pattern)));
+        }
+    }
+    for dir in recursive_dirs {
+        let dir = Path::new(dir).join("**");
+        includes.extend(build_patterns.iter().map(|pattern| join(&dir, pattern)));
+    }
+
+    includes
+        .into_iter()
+        .chain(
+            build_ignore_patterns
+                .iter()
+                .map(|pattern| format!("!{pattern}")),
+        )
+        .collect()
+}
@@ -0,0 +1,121 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use fs::{Entry, SymlinkBehavior};
+use futures::future;
+use pyo3::prelude::{pyfunction, wrap_pyfunction, PyModule, PyResult, Python, ToPyObject};
+use test_results::{ResultFormat, TestResultsAggregator};
+use workunit_store::{in_workunit, Level};
+
+use crate::externs;
+use crate::externs::test_results::PyAggregateTestResultsRequest;
+use crate::externs::PyGeneratorResponseNativeCall;
+use crate::nodes::{task_get_context, NodeResult};
+use crate::python::{Failure, Value};
+
+pub fn register(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(aggregate_test_results, m)?)?;
+
+    Ok(())
+}
+
+/// Parse the test result files in the digests of an `AggregateTestResultsRequest` in parallel, and
+/// merge them into a `TestResultsSummary`.
+#[pyfunction]
+fn aggregate_test_results(request: Value) -> PyGeneratorResponseNativeCall {
+    PyGeneratorResponseNativeCall::new(async move {
+        let context = task_get_context();
+
+        let core = &context.core;
+        let store = core.store();
+        let PyAggregateTestResultsRequest { digests } = Python::with_gil(|py| request.extract(py))?;
+
+        in_workunit!(
+            "aggregate_test_results",
+            Level::Debug,
+            desc = Some(format!(
+                "Aggregate test results from {} digests",
+                digests.len()
+            )),
+            |_workunit| async move {
+                // Collect the result files of all of the digests, preserving their order.
+                let mut files = vec![];
+                for digest in digests {
+                    store.load_digest_trie(digest).await?.walk(
+                        SymlinkBehavior::Oblivious,
+                        &mut |path, entry| {
+                            if let Entry::File(file) = entry {
+                                if let Some(format) = ResultFormat::for_path(path) {
+                                    files.push((path.to_owned(), file.digest(), format));
+                                }
+                            }
+                        },
+                    );
+                }
+
+                let parsed =
+                    future::try_join_all(files.into_iter().map(|(path, digest, format)| {
+                        let task_store = store.clone();
+                        core.executor.spawn(
+                            async move {
+                                let content = task_store
+                                    .load_file_bytes_with(digest, |bytes| {
+                                        String::from_utf8_lossy(bytes).into_owned()
+                                    })
+                                    .await?;
+                                let cases = format.parse(&content).map_err(|e| {
+                                    format!(
+                                        "Failed to parse test results from {}: {e}",
+                                        path.display()
+                                    )
+                                })?;
+                                NodeResult::Ok(cases)
+                            },
+                            |e| Err(format!("Test results parsing task failed: {e}").into()),
+                        )
+                    }))
+                    .await?;
+
+                let mut aggregator = TestResultsAggregator::default();
+                for cases in parsed {
+                    aggregator.add(cases);
+                }
+                let summary = aggregator.summary();
+
+                let result = Python::with_gil(|py| {
+                    let cases = summary
+                        .cases
+                        .into_iter()
+                        .map(|case| {
+                            (
+                                case.suite,
+                                case.name,
+                                case.outcome.as_str(),
+                                case.duration.as_secs_f64(),
+                                case.message,
+                                case.attempts,
+                                case.flaky,
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    externs::unsafe_call(
+                        py,
+                        core.types.test_results_summary,
+                        &[
+                            cases.to_object(py).into(),
+                            summary.passed.to_object(py).into(),
+                            summary.failed.to_object(py).into(),
+                            summary.errored.to_object(py).into(),
+                            summary.skipped.to_object(py).into(),
+                            summary.flaky.to_object(py).into(),
+                            summary.duration.as_secs_f64().to_object(py).into(),
+                        ],
+                    )
+                });
+
+                Ok::<_, Failure>(result)
+            }
+        )
+        .await
+    })
+}
@@ -8,23 +8,45 @@
 #[macro_use]
 extern crate derivative;
 
+mod cache_miss;
+#[cfg(test)]
+mod cache_miss_tests;
 mod context;
+mod digest_server;
+#[cfg(test)]
+mod digest_server_tests;
 mod downloads;
 mod externs;
+mod incremental;
 mod interning;
 mod intrinsics;
+mod memory;
+#[cfg(test)]
+mod memory_tests;
+mod metrics_server;
+#[cfg(test)]
+mod metrics_server_tests;
 mod nodes;
 mod python;
 mod scheduler;
 mod session;
+#[cfg(test)]
+mod session_tests;
+mod subscription;
 mod tasks;
+mod timings;
+mod tmpdir;
+#[cfg(test)]
+mod tmpdir_tests;
 mod types;
 
 pub use crate::context::{
     Context, Core, ExecutionStrategyOptions, LocalStoreOptions, RemotingOptions, SessionCore,
 };
+pub use crate::memory::{MemoryAction, MemoryLimits, MemoryReport};
 pub use crate::python::{Failure, Function, Key, Params, TypeId, Value};
 pub use crate::scheduler::{ExecutionRequest, ExecutionTermination, Scheduler};
 pub use crate::session::Session;
+pub use crate::subscription::Subscription;
 pub use crate::tasks::{Rule, Tasks};
 pub use crate::types::Types;
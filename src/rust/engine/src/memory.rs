@@ -0,0 +1,230 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::fmt;
+use std::path::Path;
+
+use sysinfo::{get_current_pid, ProcessExt, System, SystemExt};
+
+/// The number of node types to include in a MemoryReport.
+const REPORTED_NODE_TYPES: usize = 10;
+
+const BYTES_PER_MIB: f64 = 1_048_576.0;
+
+///
+/// Thresholds which are checked (between runs) by `Scheduler::check_memory`.
+///
+#[derive(Clone, Debug)]
+pub struct MemoryLimits {
+    /// Above this resident set size, the daemon should restart.
+    pub max_memory_usage_bytes: usize,
+    /// Above this fraction of `max_memory_usage_bytes`, in-memory caches are cleared.
+    pub clear_caches_fraction: f64,
+    /// Above this number of nodes in the graph, in-memory caches are cleared.
+    pub max_graph_size: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MemoryAction {
+    /// Memory usage is within limits.
+    Continue,
+    /// The graph should be cleared to reclaim memory.
+    ClearCaches,
+    /// Memory usage cannot be reclaimed without restarting the process.
+    Restart,
+}
+
+impl MemoryAction {
+    ///
+    /// Decides what to do given the current memory usage, and the action that was taken by the
+    /// previous check.
+    ///
+    /// If clearing caches did not reduce the resident set size below the threshold for clearing
+    /// them, then the memory is not reclaimable (for example, because the allocator has not
+    /// returned it to the OS), and so rather than clearing caches after every run, we restart.
+    ///
+    pub fn decide(
+        limits: &MemoryLimits,
+        rss_bytes: Option<usize>,
+        graph_size: usize,
+        previous: MemoryAction,
+    ) -> Self {
+        if let Some(rss_bytes) = rss_bytes {
+            if rss_bytes > limits.max_memory_usage_bytes {
+                return MemoryAction::Restart;
+            }
+            if rss_bytes as f64
+                > limits.max_memory_usage_bytes as f64 * limits.clear_caches_fraction
+            {
+                return if previous == MemoryAction::ClearCaches {
+                    MemoryAction::Restart
+                } else {
+                    MemoryAction::ClearCaches
+                };
+            }
+        }
+        match limits.max_graph_size {
+            Some(max_graph_size) if graph_size > max_graph_size => MemoryAction::ClearCaches,
+            _ => MemoryAction::Continue,
+        }
+    }
+}
+
+///
+/// A summary of what is consuming memory, which is logged when a MemoryAction is taken in order to
+/// help users to tune their limits.
+///
+#[derive(Debug)]
+pub struct MemoryReport {
+    pub rss_bytes: Option<usize>,
+    /// The resident size of the local store's memory mapped files, which are included in the RSS.
+    pub store_resident_bytes: Option<usize>,
+    pub graph_size: usize,
+    /// The largest types of node in the graph, as (name, count, size in bytes).
+    pub nodes_by_type: Vec<(&'static str, usize, usize)>,
+}
+
+impl MemoryReport {
+    pub fn new(
+        rss_bytes: Option<usize>,
+        store_resident_bytes: Option<usize>,
+        graph_size: usize,
+        nodes_by_type: impl IntoIterator<Item = (&'static str, (usize, usize))>,
+    ) -> Self {
+        let mut nodes_by_type = nodes_by_type
+            .into_iter()
+            .map(|(name, (count, size))| (name, count, size))
+            .collect::<Vec<_>>();
+        nodes_by_type.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+        nodes_by_type.truncate(REPORTED_NODE_TYPES);
+        MemoryReport {
+            rss_bytes,
+            store_resident_bytes,
+            graph_size,
+            nodes_by_type,
+        }
+    }
+}
+
+///
+/// The memory used by a group of nodes in the graph which share a type and (for `Task` nodes) a
+/// rule. See `Scheduler::memory_usage`.
+///
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NodeMemoryUsage {
+    pub node_type: &'static str,
+    pub rule: Option<&'static str>,
+    pub count: usize,
+    /// The size of the Rust structs of the nodes, including their keys and outputs.
+    pub rust_bytes: usize,
+    /// The size of the Python values held by the nodes, if it was computed.
+    pub python_bytes: Option<usize>,
+}
+
+impl NodeMemoryUsage {
+    pub fn new(node_type: &'static str, rule: Option<&'static str>) -> Self {
+        NodeMemoryUsage {
+            node_type,
+            rule,
+            count: 0,
+            rust_bytes: 0,
+            python_bytes: None,
+        }
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.rust_bytes + self.python_bytes.unwrap_or(0)
+    }
+
+    ///
+    /// Sorts usages by their total size (largest first), and then by name for stability.
+    ///
+    pub fn sort(usages: &mut [NodeMemoryUsage]) {
+        usages.sort_by(|a, b| {
+            b.total_bytes()
+                .cmp(&a.total_bytes())
+                .then(a.node_type.cmp(b.node_type))
+                .then(a.rule.cmp(&b.rule))
+        });
+    }
+}
+
+fn mib(bytes: Option<usize>) -> String {
+    bytes
+        .map(|bytes| format!("{:.2} MiB", bytes as f64 / BYTES_PER_MIB))
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  resident set size: {}", mib(self.rss_bytes))?;
+        writeln!(
+            f,
+            "  local store (memory mapped): {}",
+            mib(self.store_resident_bytes)
+        )?;
+        write!(f, "  graph nodes: {}", self.graph_size)?;
+        for (name, count, size) in &self.nodes_by_type {
+            write!(f, "\n    {name}: {count} nodes, {}", mib(Some(*size)))?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// The resident set size of the current process, if it can be determined.
+///
+pub fn rss_bytes() -> Option<usize> {
+    let pid = get_current_pid().ok()?;
+    let mut system = System::new();
+    system.refresh_process(pid);
+    // NB: sysinfo reports memory in KiB.
+    system
+        .process(pid)
+        .map(|process| process.memory() as usize * 1024)
+}
+
+///
+/// The resident size of files under the given directory which are memory mapped by the current
+/// process. Only supported on Linux.
+///
+pub fn mapped_resident_bytes(dir: &Path) -> Option<usize> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let smaps = std::fs::read_to_string("/proc/self/smaps")
+        .map_err(|e| log::debug!("Failed to read memory mappings: {e}"))
+        .ok()?;
+    Some(parse_smaps_resident_bytes(&smaps, dir))
+}
+
+///
+/// Sums the `Rss` of the mappings of files under the given directory in the content of a
+/// `/proc/<pid>/smaps` file.
+///
+pub(crate) fn parse_smaps_resident_bytes(smaps: &str, dir: &Path) -> usize {
+    let mut total_kib = 0;
+    let mut in_dir = false;
+    for line in smaps.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(first) = fields.next() else {
+            continue;
+        };
+        if first.ends_with(':') {
+            // A field of the current mapping.
+            if in_dir && first == "Rss:" {
+                total_kib += fields
+                    .next()
+                    .and_then(|kib| kib.parse::<usize>().ok())
+                    .unwrap_or(0);
+            }
+        } else {
+            // The header of a new mapping: `address perms offset dev inode [path]`.
+            in_dir = fields
+                .nth(4)
+                .map(|path| Path::new(path).starts_with(dir))
+                .unwrap_or(false);
+        }
+    }
+    total_kib * 1024
+}
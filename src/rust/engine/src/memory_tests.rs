@@ -0,0 +1,167 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::path::Path;
+
+use crate::memory::{
+    parse_smaps_resident_bytes, MemoryAction, MemoryLimits, MemoryReport, NodeMemoryUsage,
+};
+
+const MIB: usize = 1_048_576;
+
+fn limits(max_graph_size: Option<usize>) -> MemoryLimits {
+    MemoryLimits {
+        max_memory_usage_bytes: 100 * MIB,
+        clear_caches_fraction: 0.75,
+        max_graph_size,
+    }
+}
+
+#[test]
+fn decide() {
+    let with_max_graph_size = limits(Some(1000));
+    assert_eq!(
+        MemoryAction::Continue,
+        MemoryAction::decide(
+            &with_max_graph_size,
+            Some(50 * MIB),
+            10,
+            MemoryAction::Continue
+        )
+    );
+    assert_eq!(
+        MemoryAction::ClearCaches,
+        MemoryAction::decide(
+            &with_max_graph_size,
+            Some(80 * MIB),
+            10,
+            MemoryAction::Continue
+        )
+    );
+    assert_eq!(
+        MemoryAction::ClearCaches,
+        MemoryAction::decide(
+            &with_max_graph_size,
+            Some(50 * MIB),
+            1001,
+            MemoryAction::Continue
+        )
+    );
+    assert_eq!(
+        MemoryAction::Restart,
+        MemoryAction::decide(
+            &with_max_graph_size,
+            Some(101 * MIB),
+            1001,
+            MemoryAction::Continue
+        )
+    );
+    // If clearing caches did not reclaim enough memory, restart.
+    assert_eq!(
+        MemoryAction::Restart,
+        MemoryAction::decide(
+            &with_max_graph_size,
+            Some(80 * MIB),
+            10,
+            MemoryAction::ClearCaches
+        )
+    );
+    // But the graph may be cleared repeatedly.
+    assert_eq!(
+        MemoryAction::ClearCaches,
+        MemoryAction::decide(
+            &with_max_graph_size,
+            Some(50 * MIB),
+            1001,
+            MemoryAction::ClearCaches
+        )
+    );
+    // If RSS is unknown, only the graph size is used.
+    assert_eq!(
+        MemoryAction::Continue,
+        MemoryAction::decide(&with_max_graph_size, None, 10, MemoryAction::Continue)
+    );
+    assert_eq!(
+        MemoryAction::Continue,
+        MemoryAction::decide(
+            &limits(None),
+            Some(50 * MIB),
+            1_000_000,
+            MemoryAction::Continue
+        )
+    );
+}
+
+#[test]
+fn smaps_resident_bytes() {
+    let smaps = "\
+7f0000000000-7f0000100000 r--s 00000000 fd:01 1234 /cache/lmdb_store/files/0/data.mdb
+Size:               1024 kB
+Rss:                 512 kB
+Pss:                 512 kB
+VmFlags: rd sh mr mw me ms sd
+7f0000100000-7f0000200000 rw-p 00000000 00:00 0
+Rss:                2048 kB
+7f0000200000-7f0000300000 r--s 00000000 fd:01 1235 /cache/lmdb_store/directories/0/data.mdb
+Rss:                   8 kB
+7f0000300000-7f0000400000 r-xp 00000000 fd:01 1236 /usr/lib/libc.so.6
+Rss:                 100 kB
+";
+    assert_eq!(
+        520 * 1024,
+        parse_smaps_resident_bytes(smaps, Path::new("/cache/lmdb_store"))
+    );
+    assert_eq!(
+        0,
+        parse_smaps_resident_bytes(smaps, Path::new("/elsewhere"))
+    );
+}
+
+#[test]
+fn report() {
+    let report = MemoryReport::new(
+        Some(3 * MIB),
+        None,
+        3,
+        vec![("snapshot", (2, 2 * MIB)), ("process", (1, 3 * MIB))],
+    );
+    assert_eq!(
+        report.to_string(),
+        "  resident set size: 3.00 MiB\n  \
+         local store (memory mapped): unknown\n  \
+         graph nodes: 3\n    \
+         process: 1 nodes, 3.00 MiB\n    \
+         snapshot: 2 nodes, 2.00 MiB"
+    );
+}
+
+#[test]
+fn node_memory_usage_sort() {
+    let usage = |node_type, rule, rust_bytes, python_bytes| NodeMemoryUsage {
+        node_type,
+        rule,
+        count: 1,
+        rust_bytes,
+        python_bytes,
+    };
+    let mut usages = vec![
+        usage("snapshot", None, 100, None),
+        usage("task", Some("b"), 50, Some(100)),
+        usage("task", Some("a"), 50, Some(100)),
+        usage("process", None, 200, None),
+    ];
+    NodeMemoryUsage::sort(&mut usages);
+    assert_eq!(
+        vec![
+            ("process", None),
+            ("task", Some("a")),
+            ("task", Some("b")),
+            ("snapshot", None),
+        ],
+        usages
+            .iter()
+            .map(|usage| (usage.node_type, usage.rule))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(150, usages[1].total_bytes());
+}
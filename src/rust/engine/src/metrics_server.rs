@@ -0,0 +1,167 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use store::EntryType;
+use task_executor::{Executor, Pool};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use workunit_store::PrometheusText;
+
+use crate::context::Core;
+
+/// The path at which metrics are served.
+const METRICS_PATH: &str = "/metrics";
+
+/// The content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+///
+/// An HTTP server (bound to localhost) which serves metrics in the Prometheus text format, for
+/// monitoring of long-lived processes.
+///
+pub struct MetricsServer {
+    port: u16,
+    shutdown_sender: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    ///
+    /// Binds to the given port (or to a random port if it is 0), and serves the output of `render`
+    /// for each request.
+    ///
+    pub fn new<R>(executor: &Executor, port: u16, render: R) -> Result<MetricsServer, String>
+    where
+        R: Fn() -> String + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .map_err(|e| format!("Failed to bind the metrics server to port {port}: {e}"))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to get the address of the metrics server: {e}"))?
+            .port();
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to configure the metrics server: {e}"))?;
+
+        let render = Arc::new(render);
+        let make_service = make_service_fn(move |_| {
+            let render = render.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let render = render.clone();
+                    async move { Ok::<_, Infallible>(respond(&request, render.as_ref())) }
+                }))
+            }
+        });
+
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel();
+        let server = executor.enter_io(|| {
+            hyper::Server::from_tcp(listener)
+                .map(|builder| {
+                    builder
+                        .serve(make_service)
+                        .with_graceful_shutdown(async move {
+                            let _ = shutdown_receiver.await;
+                        })
+                })
+                .map_err(|e| format!("Failed to start the metrics server: {e}"))
+        })?;
+        let task = executor.native_spawn_io(async move {
+            if let Err(e) = server.await {
+                log::warn!("The metrics server exited with an error: {e}");
+            }
+        });
+
+        Ok(MetricsServer {
+            port,
+            shutdown_sender,
+            task,
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    ///
+    /// Stops accepting connections, and waits for in-flight requests to complete.
+    ///
+    pub async fn shutdown(self) -> Result<(), String> {
+        let _ = self.shutdown_sender.send(());
+        self.task
+            .await
+            .map_err(|e| format!("Failed to shut down the metrics server: {e}"))
+    }
+}
+
+fn respond(request: &Request<Body>, render: &(dyn Fn() -> String + Send + Sync)) -> Response<Body> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, METRICS_PATH) => Response::builder()
+            .header(header::CONTENT_TYPE, CONTENT_TYPE)
+            .body(Body::from(render())),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from(format!(
+                "Metrics are served at {METRICS_PATH}.\n"
+            ))),
+    };
+    response.expect("Response headers are statically valid.")
+}
+
+///
+/// Renders the metrics of the given Core: the totals of the metrics recorded by completed
+/// Sessions, and gauges of the current size of the graph, store, and executor queues.
+///
+pub fn render_metrics(core: &Core) -> String {
+    let mut text = PrometheusText::default();
+    core.completed_session_metrics.render(&mut text);
+
+    text.gauge(
+        "graph_nodes",
+        "The number of nodes in the graph.",
+        [(vec![], core.graph.len() as u64)],
+    );
+
+    let store_used_bytes =
+        core.store()
+            .local_used_bytes()
+            .into_iter()
+            .filter_map(|(entry_type, used_bytes)| {
+                let entry_type = match entry_type {
+                    EntryType::File => "file",
+                    EntryType::Directory => "directory",
+                };
+                match used_bytes {
+                    Ok(used_bytes) => Some((vec![("entry_type", entry_type)], used_bytes as u64)),
+                    Err(e) => {
+                        log::debug!("Failed to compute the size of the {entry_type} store: {e}");
+                        None
+                    }
+                }
+            });
+    text.gauge(
+        "local_store_used_bytes",
+        "The number of bytes used by the local store's databases.",
+        store_used_bytes,
+    );
+
+    text.gauge(
+        "executor_in_flight_tasks",
+        "The number of tasks which have been spawned but not completed, including queued tasks.",
+        Pool::all().into_iter().map(|pool| {
+            (
+                vec![("pool", pool.name())],
+                core.executor.in_flight(pool) as u64,
+            )
+        }),
+    );
+
+    text.into_string()
+}
@@ -0,0 +1,38 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use task_executor::Executor;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::metrics_server::MetricsServer;
+
+async fn get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    stream
+        .write_all(format!("GET {path} HTTP/1.0\r\n\r\n").as_bytes())
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn serves_metrics() {
+    let server =
+        MetricsServer::new(&Executor::new(), 0, || "pants_graph_nodes 3\n".to_owned()).unwrap();
+
+    let response = get(server.port(), "/metrics").await;
+    assert!(response.contains(" 200 OK\r\n"), "{response}");
+    assert!(response.contains("text/plain; version=0.0.4"), "{response}");
+    assert!(
+        response.ends_with("\r\n\r\npants_graph_nodes 3\n"),
+        "{response}"
+    );
+
+    let response = get(server.port(), "/other").await;
+    assert!(response.contains(" 404 Not Found\r\n"), "{response}");
+
+    server.shutdown().await.unwrap();
+}
@@ -6,6 +6,7 @@ use std::sync::Arc;
 
 use bytes::Bytes;
 use deepsize::DeepSizeOf;
+use engine_error::{EngineError, ErrorCode};
 use fs::RelativePath;
 use graph::CompoundNode;
 use grpc_util::prost::MessageExt;
@@ -42,6 +43,7 @@ impl DownloadedFile {
         url: Url,
         auth_headers: BTreeMap<String, String>,
         digest: hashing::Digest,
+        build_id: &str,
     ) -> Result<store::Snapshot, String> {
         let file_name = url
             .path_segments()
@@ -72,7 +74,8 @@ impl DownloadedFile {
                 .is_ok());
 
         if !usable_in_store {
-            downloads::download(core.clone(), url, auth_headers, file_name, digest).await?;
+            downloads::download(core.clone(), url, auth_headers, file_name, digest, build_id)
+                .await?;
             // The value was successfully fetched and matched the digest: record in the ObservedUrls
             // cache.
             core.local_cache.store(&url_key, Bytes::from("")).await?;
@@ -94,11 +97,21 @@ impl DownloadedFile {
                 Ok((url_str, py_file_digest.0, auth_headers));
             res
         })?;
-        let url = Url::parse(&url_str)
-            .map_err(|err| throw(format!("Error parsing URL {url_str}: {err}")))?;
-        self.load_or_download(context.core.clone(), url, auth_headers, expected_digest)
-            .await
-            .map_err(throw)
+        let url = Url::parse(&url_str).map_err(|err| {
+            EngineError::new(
+                ErrorCode::InvalidUrl,
+                format!("Error parsing URL {url_str}: {err}"),
+            )
+        })?;
+        self.load_or_download(
+            context.core.clone(),
+            url,
+            auth_headers,
+            expected_digest,
+            context.session.build_id(),
+        )
+        .await
+        .map_err(throw)
     }
 }
 
@@ -6,22 +6,30 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 use deepsize::DeepSizeOf;
+use engine_error::{EngineError, ErrorCode};
 use fs::RelativePath;
 use graph::CompoundNode;
 use process_execution::{
-    self, CacheName, InputDigests, Process, ProcessCacheScope, ProcessExecutionStrategy,
-    ProcessResultSource,
+    self, CacheName, FallibleProcessResultWithPlatform, IncrementalOutputs, InputDigests,
+    PersistentWorker, Process, ProcessCacheScope, ProcessExecutionStrategy, ProcessResultSource,
+    ProcessRetryPolicy, RetryOn,
 };
+use protos::gen::pants::cache::CacheKey;
 use pyo3::prelude::{PyAny, Python};
+use pyo3::types::PyDict;
 use store::{self, Store, StoreError};
 use workunit_store::{
     Metric, ObservationMetric, RunningWorkunit, UserMetadataItem, WorkunitMetadata,
 };
 
-use super::{lift_directory_digest, NodeKey, NodeOutput, NodeResult};
+use super::{
+    lift_directory_digest, lift_file_digest, NodeKey, NodeOutput, NodeResult, SessionValues,
+};
+use crate::cache_miss::{CacheMissRecorder, ProcessFingerprint};
 use crate::context::Context;
 use crate::externs;
-use crate::python::{throw, Value};
+use crate::incremental;
+use crate::python::Value;
 
 /// A Node that represents a process to execute.
 ///
@@ -71,6 +79,10 @@ impl ExecuteProcess {
     ) -> Result<Process, StoreError> {
         let env = externs::getattr_from_str_frozendict(value, "env");
 
+        let env_globs = externs::getattr::<Vec<String>>(value, "env_globs")?
+            .into_iter()
+            .collect();
+
         let working_directory = externs::getattr_as_optional_string(value, "working_directory")
             .map_err(|e| format!("Failed to get `working_directory` from field: {e}"))?
             .map(RelativePath::new)
@@ -81,10 +93,15 @@ impl ExecuteProcess {
             .map(RelativePath::new)
             .collect::<Result<_, _>>()?;
 
-        let output_directories = externs::getattr::<Vec<String>>(value, "output_directories")?
+        let output_directories: BTreeSet<RelativePath> =
+            externs::getattr::<Vec<String>>(value, "output_directories")?
+                .into_iter()
+                .map(RelativePath::new)
+                .collect::<Result<_, _>>()?;
+
+        let output_globs = externs::getattr::<Vec<String>>(value, "output_globs")?
             .into_iter()
-            .map(RelativePath::new)
-            .collect::<Result<_, _>>()?;
+            .collect();
 
         let timeout_in_seconds: f64 = externs::getattr(value, "timeout_seconds")?;
 
@@ -100,12 +117,52 @@ impl ExecuteProcess {
 
         let level = externs::val_to_log_level(py_level)?;
 
-        let append_only_caches =
+        let append_only_caches: BTreeMap<CacheName, RelativePath> =
             externs::getattr_from_str_frozendict::<&str>(value, "append_only_caches")
                 .into_iter()
                 .map(|(name, dest)| Ok((CacheName::new(name)?, RelativePath::new(dest)?)))
                 .collect::<Result<_, String>>()?;
 
+        let append_only_cache_seeds =
+            externs::getattr_from_str_frozendict::<&PyAny>(value, "append_only_cache_seeds")
+                .into_iter()
+                .map(|(name, digest)| {
+                    let name = CacheName::new(name)?;
+                    if !append_only_caches.contains_key(&name) {
+                        return Err(format!(
+                            "Cannot seed the append-only cache `{}`, because it is not one of \
+                             the `append_only_caches` of the process.",
+                            name.name()
+                        ));
+                    }
+                    Ok((name, lift_directory_digest(digest)?))
+                })
+                .collect::<Result<_, String>>()?;
+
+        let incremental_outputs = externs::getattr::<Option<&PyAny>>(value, "incremental_outputs")?
+            .map(|incremental_outputs| -> Result<_, String> {
+                let directories =
+                    externs::getattr::<Vec<String>>(incremental_outputs, "directories")?
+                        .into_iter()
+                        .map(RelativePath::new)
+                        .collect::<Result<BTreeSet<_>, _>>()?;
+                if let Some(undeclared) = directories
+                    .iter()
+                    .find(|directory| !output_directories.contains(*directory))
+                {
+                    return Err(format!(
+                        "The incremental output directory `{}` must also be one of the \
+                             `output_directories` of the process.",
+                        undeclared.display()
+                    ));
+                }
+                Ok(IncrementalOutputs {
+                    key: externs::getattr(incremental_outputs, "key")?,
+                    directories,
+                })
+            })
+            .transpose()?;
+
         let jdk_home = externs::getattr_as_optional_string(value, "jdk_home")
             .map_err(|e| format!("Failed to get `jdk_home` from field: {e}"))?
             .map(PathBuf::from);
@@ -121,30 +178,87 @@ impl ExecuteProcess {
             externs::getattr::<String>(cache_scope_enum, "name")?.try_into()?
         };
 
+        let tool_fingerprints =
+            externs::getattr_from_str_frozendict::<String>(value, "tool_fingerprints");
+
         let remote_cache_speculation_delay = std::time::Duration::from_millis(
             externs::getattr::<i32>(value, "remote_cache_speculation_delay_millis")
                 .map_err(|e| format!("Failed to get `name` for field: {e}"))? as u64,
         );
 
+        let remote_execution_priority = externs::getattr(value, "remote_execution_priority")?;
+
+        let remote_results_cache_priority =
+            externs::getattr(value, "remote_results_cache_priority")?;
+
+        let persistent_worker = externs::getattr::<Option<&PyAny>>(value, "persistent_worker")?
+            .map(|worker| -> Result<_, String> {
+                let protocol_enum = externs::getattr(worker, "protocol")?;
+                Ok(PersistentWorker {
+                    key: externs::getattr(worker, "key")?,
+                    protocol: externs::getattr::<String>(protocol_enum, "name")?.try_into()?,
+                    request_args_start: externs::getattr(worker, "request_args_start")?,
+                    multiplex: externs::getattr(worker, "multiplex")?,
+                })
+            })
+            .transpose()?;
+
+        let cache_validation_argv = externs::getattr(value, "cache_validation_argv")?;
+
+        let stdin_digest = externs::getattr::<Option<&PyAny>>(value, "stdin_digest")?
+            .map(lift_file_digest)
+            .transpose()?;
+
+        let retry_policy = externs::getattr::<Option<&PyAny>>(value, "retry_policy")?
+            .map(|policy| -> Result<_, String> {
+                let retry_on = if externs::getattr::<bool>(policy, "infrastructure_errors_only")? {
+                    RetryOn::InfrastructureErrors
+                } else if let Some(exit_codes) =
+                    externs::getattr::<Option<Vec<i32>>>(policy, "exit_codes")?
+                {
+                    RetryOn::ExitCodes(exit_codes.into_iter().collect())
+                } else {
+                    RetryOn::AnyFailure
+                };
+                Ok(ProcessRetryPolicy {
+                    max_attempts: externs::getattr(policy, "max_attempts")?,
+                    retry_on,
+                    backoff: Duration::from_secs_f64(externs::getattr(policy, "backoff_seconds")?),
+                })
+            })
+            .transpose()?;
+
         let attempt = externs::getattr(value, "attempt").unwrap_or(0);
 
         Ok(Process {
             argv: externs::getattr(value, "argv").unwrap(),
             env,
+            env_globs,
+            globbed_env: BTreeMap::new(),
             working_directory,
             input_digests,
             output_files,
             output_directories,
+            output_globs,
             timeout,
             description,
             level,
             append_only_caches,
+            append_only_cache_seeds,
+            incremental_outputs,
             jdk_home,
             execution_slot_variable,
             concurrency_available,
             cache_scope,
+            tool_fingerprints,
             execution_environment: process_config.environment,
             remote_cache_speculation_delay,
+            remote_execution_priority,
+            remote_results_cache_priority,
+            persistent_worker,
+            cache_validation_argv,
+            stdin_digest,
+            retry_policy,
             attempt,
         })
     }
@@ -161,13 +275,117 @@ impl ExecuteProcess {
         Ok(Self { process })
     }
 
+    ///
+    /// Resolves the `env_globs` of the given Process against the environment of the Session.
+    ///
+    /// NB: The environment is requested via the `SessionValues` node, so that this node is
+    /// invalidated when the environment changes between Sessions.
+    ///
+    async fn resolve_env_globs(context: &Context, request: &mut Process) -> NodeResult<()> {
+        if !matches!(
+            request.execution_environment.strategy,
+            ProcessExecutionStrategy::Local | ProcessExecutionStrategy::LocalInWorkspace
+        ) {
+            return Err(EngineError::new(
+                ErrorCode::InvalidProcessEnvironment,
+                format!(
+                    "Process `{}` uses `env_globs`, which are only supported in local environments.",
+                    request.description
+                ),
+            )
+            .into());
+        }
+        let session_values = context.get(SessionValues).await?;
+        let environment = Python::with_gil(|py| -> Result<BTreeMap<String, String>, String> {
+            let session_values: &PyDict = externs::getattr((*session_values).as_ref(py), "_data")?;
+            let complete_environment_vars =
+                context.core.types.complete_environment_vars.as_py_type(py);
+            match session_values
+                .get_item(complete_environment_vars)
+                .map_err(|e| e.to_string())?
+            {
+                Some(environment) => {
+                    let environment: &PyDict = externs::getattr(environment, "_data")?;
+                    environment.extract().map_err(|e| e.to_string())
+                }
+                None => Ok(BTreeMap::new()),
+            }
+        })?;
+        request.resolve_env_globs(&environment)?;
+        Ok(())
+    }
+
+    ///
+    /// Records the fingerprint of the given Process, so that a later run can explain why it missed
+    /// the cache (see `cache_miss::explain_cache_misses`).
+    ///
+    async fn record_fingerprint(
+        context: &Context,
+        recorder: &CacheMissRecorder,
+        request: &Process,
+        res: &FallibleProcessResultWithPlatform,
+    ) {
+        let input_tree = match context
+            .core
+            .store()
+            .load_digest_trie(request.input_digests.complete.clone())
+            .await
+        {
+            Ok(input_tree) => input_tree,
+            Err(e) => {
+                log::debug!(
+                    "Failed to record the inputs of `{}`: {e}",
+                    request.description
+                );
+                return;
+            }
+        };
+        let action_digest = context.core.process_action_digest(request).await;
+        recorder.record(ProcessFingerprint::new(
+            request,
+            action_digest,
+            res.metadata.source != ProcessResultSource::Ran,
+            &input_tree,
+        ));
+    }
+
+    ///
+    /// Pre-populates the `IncrementalOutputs` of the given Process from the state stored under the
+    /// given key, if any. The state is only an optimization, so failures to restore it are not
+    /// fatal.
+    ///
+    async fn restore_incremental_state(context: &Context, request: &mut Process, key: &CacheKey) {
+        let store = context.core.store();
+        let restored = match incremental::load(&context.core.local_cache, &store, key).await {
+            Ok(Some(state)) => incremental::apply(&store, request, state)
+                .await
+                .map_err(|e| e.to_string()),
+            Ok(None) => return,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = restored {
+            log::debug!(
+                "Failed to restore the incremental state of `{}`: {e}",
+                request.description
+            );
+        }
+    }
+
     pub(super) async fn run_node(
         self,
         context: Context,
         workunit: &mut RunningWorkunit,
         backtrack_level: usize,
     ) -> NodeResult<ProcessResult> {
-        let request = self.process;
+        let mut request = self.process;
+        if !request.env_globs.is_empty() {
+            Self::resolve_env_globs(&context, &mut request).await?;
+        }
+        // NB: The key is computed before the state is applied, since the state changes the inputs.
+        let incremental_key = incremental::key(&request);
+        if let Some(key) = &incremental_key {
+            Self::restore_incremental_state(&context, &mut request, key).await;
+        }
 
         let command_runner = context
             .core
@@ -177,9 +395,12 @@ impl ExecuteProcess {
                 // NB: We only backtrack for a Process if it produces a Digest which cannot be consumed
                 // from disk: if we've fallen all the way back to local execution, and even that
                 // produces an unreadable Digest, then there is a fundamental implementation issue.
-                throw(format!(
+                EngineError::new(
+                    ErrorCode::InvariantViolated,
+                    format!(
           "Process {request:?} produced an invalid result on all configured command runners."
-        ))
+        ),
+                )
             })?;
 
         let execution_context = process_execution::Context::new(
@@ -193,11 +414,36 @@ impl ExecuteProcess {
             .run(execution_context, workunit, request.clone())
             .await?;
 
-        let definition = serde_json::to_string(&request)
-            .map_err(|e| throw(format!("Failed to serialize process: {e}")))?;
+        if let Some(recorder) = context.session.cache_miss_recorder() {
+            Self::record_fingerprint(&context, recorder, &request, &res).await;
+        }
+
+        if let Some(key) = incremental_key.filter(|_| res.exit_code == 0) {
+            if let Err(e) = incremental::store(
+                &context.core.local_cache,
+                &context.core.store(),
+                &request,
+                &key,
+                res.output_directory.clone(),
+            )
+            .await
+            {
+                log::debug!(
+                    "Failed to store the incremental state of `{}`: {e}",
+                    request.description
+                );
+            }
+        }
+
+        let definition = serde_json::to_string(&request).map_err(|e| {
+            EngineError::new(
+                ErrorCode::InvariantViolated,
+                format!("Failed to serialize process: {e}"),
+            )
+        })?;
         workunit.update_metadata(|initial| {
             initial.map(|(initial, level)| {
-                let mut user_metadata = Vec::with_capacity(7);
+                let mut user_metadata = Vec::with_capacity(10);
                 user_metadata.push((
                     "definition".to_string(),
                     UserMetadataItem::String(definition),
@@ -210,6 +456,15 @@ impl ExecuteProcess {
                     "exit_code".to_string(),
                     UserMetadataItem::Int(res.exit_code as i64),
                 ));
+                let output_digest = res.output_directory.as_digest();
+                user_metadata.push((
+                    "output_digest_fingerprint".to_string(),
+                    UserMetadataItem::String(output_digest.hash.to_hex()),
+                ));
+                user_metadata.push((
+                    "output_digest_size_bytes".to_string(),
+                    UserMetadataItem::Int(output_digest.size_bytes as i64),
+                ));
                 user_metadata.push((
                     "environment_type".to_string(),
                     UserMetadataItem::String(
@@ -228,6 +483,24 @@ impl ExecuteProcess {
                         UserMetadataItem::Int(Duration::from(total_elapsed).as_millis() as i64),
                     ));
                 }
+                if let Some(queue_elapsed) = res.metadata.queue_elapsed {
+                    user_metadata.push((
+                        "queue_elapsed_ms".to_string(),
+                        UserMetadataItem::Int(Duration::from(queue_elapsed).as_millis() as i64),
+                    ));
+                }
+                if let Some(execution_elapsed) = res.metadata.execution_elapsed {
+                    user_metadata.push((
+                        "execution_elapsed_ms".to_string(),
+                        UserMetadataItem::Int(Duration::from(execution_elapsed).as_millis() as i64),
+                    ));
+                }
+                if res.metadata.attempts > 1 {
+                    user_metadata.push((
+                        "attempts".to_string(),
+                        UserMetadataItem::Int(res.metadata.attempts as i64),
+                    ));
+                }
                 if let Some(saved_by_cache) = res.metadata.saved_by_cache {
                     user_metadata.push((
                         "saved_by_cache_ms".to_string(),
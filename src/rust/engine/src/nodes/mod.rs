@@ -9,11 +9,11 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use deepsize::DeepSizeOf;
+use engine_error::{EngineError, ErrorCode};
 use fs::{self, Dir, DirectoryDigest, DirectoryListing, File, Link, PathMetadata, Vfs};
 use futures::future::{self, BoxFuture, FutureExt, TryFutureExt};
 use graph::{Node, NodeError};
 use internment::Intern;
-use process_execution::{self, ProcessCacheScope};
 use pyo3::prelude::{PyAny, Python};
 use rule_graph::{DependencyKey, Query};
 use store::{self, StoreFileByDigest};
@@ -30,6 +30,7 @@ mod digest_file;
 mod downloaded_file;
 mod execute_process;
 mod path_metadata;
+mod paths;
 mod read_link;
 mod root;
 mod run_id;
@@ -43,6 +44,7 @@ pub use self::digest_file::DigestFile;
 pub use self::downloaded_file::DownloadedFile;
 pub use self::execute_process::{ExecuteProcess, ProcessResult};
 pub use self::path_metadata::PathMetadata as PathMetadataNode;
+pub use self::paths::Paths;
 pub use self::read_link::{LinkDest, ReadLink};
 pub use self::root::Root;
 pub use self::run_id::RunId;
@@ -159,10 +161,14 @@ async fn select(
             if let Some(key) = params.find(type_id) {
                 Ok(key.to_value())
             } else {
-                Err(throw(format!(
-                    "Expected a Param of type {} to be present, but had only: {}",
-                    type_id, params,
-                )))
+                Err(EngineError::new(
+                    ErrorCode::InvariantViolated,
+                    format!(
+                        "Expected a Param of type {} to be present, but had only: {}",
+                        type_id, params,
+                    ),
+                )
+                .into())
             }
         }
     }
@@ -233,6 +239,7 @@ pub enum NodeKey {
     ReadLink(ReadLink),
     Scandir(Scandir),
     PathMetadata(PathMetadataNode),
+    Paths(Paths),
     Root(Box<Root>),
     Snapshot(Snapshot),
     SessionValues(SessionValues),
@@ -261,6 +268,7 @@ impl NodeKey {
             | &NodeKey::SessionValues { .. }
             | &NodeKey::RunId { .. }
             | &NodeKey::Snapshot { .. }
+            | &NodeKey::Paths { .. }
             | &NodeKey::Task { .. }
             | &NodeKey::DownloadedFile { .. } => None,
         }
@@ -296,6 +304,27 @@ impl NodeKey {
         }
     }
 
+    ///
+    /// The type of this node, for the purposes of grouping nodes when reporting on the graph. All
+    /// `@rule`s share the type "task": see `rule_name` to distinguish them.
+    ///
+    pub fn node_type(&self) -> &'static str {
+        match self {
+            NodeKey::Task(..) => "task",
+            _ => self.workunit_name(),
+        }
+    }
+
+    ///
+    /// The name of the `@rule` which this node runs, if it is a `Task`.
+    ///
+    pub fn rule_name(&self) -> Option<&'static str> {
+        match self {
+            NodeKey::Task(ref task) => Some(&task.task.as_ref().display_info.name),
+            _ => None,
+        }
+    }
+
     ///
     /// Provides the `name` field in workunits associated with this node. These names
     /// should be friendly to machine-parsing (i.e. "my_node" rather than "My awesome node!").
@@ -305,6 +334,7 @@ impl NodeKey {
             NodeKey::Task(ref task) => &task.task.as_ref().display_info.name,
             NodeKey::ExecuteProcess(..) => "process",
             NodeKey::Snapshot(..) => "snapshot",
+            NodeKey::Paths(..) => "paths",
             NodeKey::DigestFile(..) => "digest_file",
             NodeKey::DownloadedFile(..) => "downloaded_file",
             NodeKey::ReadLink(..) => "read_link",
@@ -347,6 +377,7 @@ impl NodeKey {
                 Some(desc)
             }
             NodeKey::Snapshot(ref s) => Some(format!("Snapshotting: {}", s.path_globs)),
+            NodeKey::Paths(ref p) => Some(format!("Finding files: {}", p.path_globs)),
             NodeKey::ExecuteProcess(epr) => {
                 // NB: See Self::workunit_level for more information on why this is prefixed.
                 Some(format!("Scheduling: {}", epr.process.description))
@@ -454,6 +485,7 @@ impl Node for NodeKey {
                     }
                     NodeKey::Root(n) => n.run_node(context).await.map(NodeOutput::Value),
                     NodeKey::Snapshot(n) => n.run_node(context).await.map(NodeOutput::Snapshot),
+                    NodeKey::Paths(n) => n.run_node(context).await.map(NodeOutput::Paths),
                     NodeKey::SessionValues(n) => n.run_node(context).await.map(NodeOutput::Value),
                     NodeKey::RunId(n) => n.run_node(context).await.map(NodeOutput::Value),
                     NodeKey::Task(n) => n.run_node(context, workunit).await.map(NodeOutput::Value),
@@ -482,6 +514,10 @@ impl Node for NodeKey {
         .await
     }
 
+    fn kind(&self) -> &'static str {
+        self.workunit_name()
+    }
+
     fn restartable(&self) -> bool {
         // A Task / @rule is only restartable if it has not had a side effect (as determined by the
         // calls to the `task_side_effected` function).
@@ -501,15 +537,10 @@ impl Node for NodeKey {
 
     fn cacheable_item(&self, output: &NodeOutput) -> bool {
         match (self, output) {
-            (NodeKey::ExecuteProcess(ref ep), NodeOutput::ProcessResult(ref process_result)) => {
-                match ep.process.cache_scope {
-                    ProcessCacheScope::Always | ProcessCacheScope::PerRestartAlways => true,
-                    ProcessCacheScope::Successful | ProcessCacheScope::PerRestartSuccessful => {
-                        process_result.result.exit_code == 0
-                    }
-                    ProcessCacheScope::PerSession => false,
-                }
-            }
+            (NodeKey::ExecuteProcess(ref ep), NodeOutput::ProcessResult(ref process_result)) => ep
+                .process
+                .cache_scope
+                .is_cacheable(process_result.result.exit_code),
             (NodeKey::Task(ref t), NodeOutput::Value(ref v)) if t.task.engine_aware_return_type => {
                 Python::with_gil(|py| {
                     EngineAwareReturnType::is_cacheable((**v).as_ref(py)).unwrap_or(true)
@@ -528,8 +559,10 @@ impl Node for NodeKey {
         let url = Python::with_gil(|py| {
             externs::doc_url(py, "docs/using-pants/key-concepts/targets-and-build-files#dependencies-and-dependency-inference")
         });
-        throw(format!(
-            "The dependency graph contained a cycle:\
+        EngineError::new(
+            ErrorCode::DependencyCycle,
+            format!(
+                "The dependency graph contained a cycle:\
       \n\n  \
       {}\
       \n\n\
@@ -538,9 +571,11 @@ impl Node for NodeKey {
       not for your BUILD targets, then please file a Github issue!\
       \n\n\
       See {} for more information.",
-            path.join("\n  "),
-            url
-        ))
+                path.join("\n  "),
+                url
+            ),
+        )
+        .into()
     }
 }
 
@@ -577,6 +612,7 @@ impl Display for NodeKey {
                 )
             }
             NodeKey::Snapshot(s) => write!(f, "Snapshot({})", s.path_globs),
+            NodeKey::Paths(p) => write!(f, "Paths({})", p.path_globs),
             &NodeKey::SessionValues(_) => write!(f, "SessionValues"),
             &NodeKey::RunId(_) => write!(f, "RunId"),
         }
@@ -598,6 +634,7 @@ pub enum NodeOutput {
     FileDigest(hashing::Digest),
     Snapshot(store::Snapshot),
     DirectoryListing(Arc<DirectoryListing>),
+    Paths(Arc<Vec<fs::PathStat>>),
     LinkDest(LinkDest),
     PathMetadata(Option<fs::PathMetadata>),
     ProcessResult(Box<ProcessResult>),
@@ -622,6 +659,7 @@ impl NodeOutput {
                 digests
             }
             NodeOutput::DirectoryListing(_)
+            | NodeOutput::Paths(_)
             | NodeOutput::LinkDest(_)
             | NodeOutput::Value(_)
             | NodeOutput::PathMetadata(_) => {
@@ -4,11 +4,11 @@
 use std::path::{Path, PathBuf};
 
 use deepsize::DeepSizeOf;
+use engine_error::{EngineError, ErrorCode};
 use graph::CompoundNode;
 
 use super::{NodeKey, NodeOutput, NodeResult};
 use crate::context::Context;
-use crate::python::throw;
 
 ///
 /// A `Node` that represents reading the filesystem metadata of a path.
@@ -34,7 +34,7 @@ impl PathMetadata {
             .vfs
             .path_metadata(node.path.clone())
             .await
-            .map_err(|e| throw(format!("{e}")))
+            .map_err(|e| EngineError::new(ErrorCode::FilesystemAccessFailed, e.to_string()).into())
     }
 }
 
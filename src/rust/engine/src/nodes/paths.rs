@@ -0,0 +1,74 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::sync::Arc;
+
+use deepsize::DeepSizeOf;
+use fs::{GlobMatching, PathGlobs, PathStat, SymlinkBehavior};
+use graph::CompoundNode;
+
+use super::{unmatched_globs_additional_context, NodeKey, NodeOutput, NodeResult};
+use crate::context::Context;
+use crate::python::throw;
+
+///
+/// A Node that expands a PathGlobs subject into the (symlink oblivious) PathStats that it matches.
+///
+/// This Node depends on the `Scandir` and `PathMetadata` Nodes which were used to expand the
+/// globs, and so it is memoized across runs until the watcher observes a change beneath one of
+/// those directories. Repeated runs against an idle repository will then reuse the expansion
+/// rather than re-globbing, and `Snapshot` (which consumes it) will not re-digest files.
+///
+/// The output does not need to record the watcher generation that it was computed in: graph
+/// invalidation is sufficient to decide whether it may be reused. The watcher invalidates the
+/// filesystem Nodes for each changed path (and for the parent directory of any path which was
+/// created, removed or renamed), which dirties this Node as a dependent. When the watcher may
+/// have missed events (because its queue overflowed), it invalidates all filesystem Nodes. A dirty
+/// Node is only reused if all of its dependencies are unchanged after they have been re-run, and
+/// the expansion is computed only from those dependencies.
+///
+#[derive(Clone, Debug, DeepSizeOf, Eq, Hash, PartialEq)]
+pub struct Paths {
+    pub(super) path_globs: PathGlobs,
+}
+
+impl Paths {
+    pub fn from_path_globs(path_globs: PathGlobs) -> Paths {
+        Paths { path_globs }
+    }
+
+    pub(super) async fn run_node(self, context: Context) -> NodeResult<Arc<Vec<PathStat>>> {
+        let path_globs = self.path_globs.parse().map_err(throw)?;
+
+        // We rely on Context::expand_globs to track dependencies for scandirs.
+        let path_stats = context
+            .expand_globs(
+                path_globs,
+                SymlinkBehavior::Oblivious,
+                unmatched_globs_additional_context(),
+            )
+            .await?;
+        Ok(Arc::new(path_stats))
+    }
+}
+
+impl CompoundNode<NodeKey> for Paths {
+    type Item = Arc<Vec<PathStat>>;
+}
+
+impl From<Paths> for NodeKey {
+    fn from(n: Paths) -> Self {
+        NodeKey::Paths(n)
+    }
+}
+
+impl TryFrom<NodeOutput> for Arc<Vec<PathStat>> {
+    type Error = ();
+
+    fn try_from(nr: NodeOutput) -> Result<Self, ()> {
+        match nr {
+            NodeOutput::Paths(v) => Ok(v),
+            _ => Err(()),
+        }
+    }
+}
@@ -4,12 +4,12 @@
 use std::path::PathBuf;
 
 use deepsize::DeepSizeOf;
+use engine_error::{EngineError, ErrorCode};
 use fs::Link;
 use graph::CompoundNode;
 
 use super::{NodeKey, NodeOutput, NodeResult};
 use crate::context::Context;
-use crate::python::throw;
 
 ///
 /// A Node that represents reading the destination of a symlink (non-recursively).
@@ -25,7 +25,7 @@ impl ReadLink {
             .vfs
             .read_link(&node.0)
             .await
-            .map_err(|e| throw(format!("{e}")))?;
+            .map_err(|e| EngineError::new(ErrorCode::FilesystemAccessFailed, e.to_string()))?;
         Ok(LinkDest(link_dest))
     }
 }
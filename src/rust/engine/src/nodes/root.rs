@@ -40,6 +40,10 @@ impl Root {
         }
     }
 
+    pub fn product(&self) -> TypeId {
+        self.product
+    }
+
     pub(super) async fn run_node(self, context: Context) -> NodeResult<Value> {
         super::select(context, None, 0, self.params, self.entry).await
     }
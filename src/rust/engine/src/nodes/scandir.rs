@@ -4,12 +4,12 @@
 use std::sync::Arc;
 
 use deepsize::DeepSizeOf;
+use engine_error::{EngineError, ErrorCode};
 use fs::{Dir, DirectoryListing};
 use graph::CompoundNode;
 
 use super::{NodeKey, NodeOutput, NodeResult};
 use crate::context::Context;
-use crate::python::throw;
 
 ///
 /// A Node that represents executing a directory listing that returns a Stat per directory
@@ -25,7 +25,7 @@ impl Scandir {
             .vfs
             .scandir(self.0)
             .await
-            .map_err(|e| throw(format!("{e}")))?;
+            .map_err(|e| EngineError::new(ErrorCode::FilesystemAccessFailed, e.to_string()))?;
         Ok(Arc::new(directory_listing))
     }
 }
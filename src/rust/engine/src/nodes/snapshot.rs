@@ -2,21 +2,23 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
 use std::path::Path;
+use std::sync::Arc;
 
 use deepsize::DeepSizeOf;
+use engine_error::{EngineError, ErrorCode};
 use fs::{
     self, DigestEntry, DirectoryDigest, FileContent, FileEntry, GlobExpansionConjunction,
-    GlobMatching, PathGlobs, PreparedPathGlobs, StrictGlobMatching, SymlinkBehavior, SymlinkEntry,
+    PathGlobs, PreparedPathGlobs, StrictGlobMatching, SymlinkEntry,
 };
 use futures::TryFutureExt;
 use graph::CompoundNode;
 use pyo3::prelude::{Py, PyAny, Python};
 use pyo3::IntoPy;
 
-use super::{unmatched_globs_additional_context, NodeKey, NodeOutput, NodeResult};
+use super::{NodeKey, NodeOutput, NodeResult, Paths};
 use crate::context::Context;
 use crate::externs;
-use crate::python::{throw, Value};
+use crate::python::Value;
 
 ///
 /// A Node that captures an store::Snapshot for a PathGlobs subject.
@@ -55,7 +57,12 @@ impl Snapshot {
             .map_err(|e| format!("Failed to get `value` for field: {e}"))?;
 
         let conjunction = GlobExpansionConjunction::create(&conjunction_string)?;
-        Ok(PathGlobs::new(globs, strict_glob_matching, conjunction))
+
+        let preserve_empty_dirs: bool = externs::getattr(item, "preserve_empty_dirs")
+            .map_err(|e| format!("Failed to get `preserve_empty_dirs` for field: {e}"))?;
+
+        Ok(PathGlobs::new(globs, strict_glob_matching, conjunction)
+            .preserving_empty_dirs(preserve_empty_dirs))
     }
 
     pub fn lift_prepared_path_globs(item: &PyAny) -> Result<PreparedPathGlobs, String> {
@@ -191,20 +198,20 @@ impl Snapshot {
     }
 
     pub(super) async fn run_node(self, context: Context) -> NodeResult<store::Snapshot> {
-        let path_globs = self.path_globs.parse().map_err(throw)?;
-
-        // We rely on Context::expand_globs to track dependencies for scandirs,
-        // and `context.get(DigestFile)` to track dependencies for file digests.
-        let path_stats = context
-            .expand_globs(
-                path_globs,
-                SymlinkBehavior::Oblivious,
-                unmatched_globs_additional_context(),
-            )
-            .await?;
-
-        store::Snapshot::from_path_stats(context.clone(), path_stats)
-            .map_err(|e| throw(format!("Snapshot failed: {e}")))
+        // We rely on the `Paths` Node to track dependencies for scandirs, and
+        // `context.get(DigestFile)` to track dependencies for file digests. When the watcher
+        // dirties a directory without changing the expansion, the `Paths` Node keeps its
+        // generation, and this Node is cleaned without being re-run.
+        let path_stats = context.get(Paths::from_path_globs(self.path_globs)).await?;
+
+        store::Snapshot::from_path_stats(context.clone(), Arc::unwrap_or_clone(path_stats))
+            .map_err(|e| {
+                EngineError::new(
+                    ErrorCode::FilesystemAccessFailed,
+                    format!("Snapshot failed: {e}"),
+                )
+                .into()
+            })
             .await
     }
 }
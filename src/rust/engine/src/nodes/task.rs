@@ -1,25 +1,33 @@
 // Copyright 2018 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+use std::collections::{BTreeSet, HashSet};
 use std::fmt;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use deepsize::DeepSizeOf;
+use engine_error::{EngineError, ErrorCode};
+use fs::DirectoryDigest;
 use futures::future::{self, BoxFuture, FutureExt};
 use graph::CompoundNode;
+use grpc_util::prost::MessageExt;
+use hashing::Digest;
 use internment::Intern;
-use pyo3::prelude::{PyErr, Python};
+use prost::Message;
+use protos::gen::pants::cache::{CacheKey, CacheKeyType, PersistedRuleValue};
+use pyo3::prelude::{PyAny, PyErr, PyResult, Python};
 use pyo3::types::{PyDict, PyTuple};
 use pyo3::{IntoPy, ToPyObject};
 use rule_graph::DependencyKey;
-use workunit_store::{in_workunit, Level, RunningWorkunit};
+use workunit_store::{in_workunit, Level, RunningWorkunit, WaitingOn};
 
 use super::{select, task_context, NodeKey, NodeResult, Params};
 use crate::context::Context;
 use crate::externs::engine_aware::EngineAwareReturnType;
+use crate::externs::persistable::PersistedForm;
 use crate::externs::{self, GeneratorInput, GeneratorResponse};
-use crate::python::{throw, Failure, Key, TypeId, Value};
+use crate::python::{Failure, Key, TypeId, Value};
 use crate::tasks::{self, Rule};
 
 #[derive(DeepSizeOf, Derivative, Clone)]
@@ -57,15 +65,21 @@ impl Task {
             .core
             .rule_graph
             .edges_for_inner(&entry)
-            .ok_or_else(|| throw(format!("No edges for task {entry:?} exist!")))?;
+            .ok_or_else(|| {
+                EngineError::new(
+                    ErrorCode::InvariantViolated,
+                    format!("No edges for task {entry:?} exist!"),
+                )
+            })?;
 
         // Find the entry for the Call.
         let entry = edges.entry_for(&dependency_key).ok_or_else(|| {
             // NB: The Python constructor for `Call()` will have already errored if
             // `type(input) != input_type`.
-            throw(format!(
-                "{call} was not detected in your @rule body at rule compile time."
-            ))
+            EngineError::new(
+                ErrorCode::UndetectedRuleCall,
+                format!("{call} was not detected in your @rule body at rule compile time."),
+            )
         })?;
         select(context, call.args, call.args_arity, params, entry).await
     }
@@ -85,7 +99,12 @@ impl Task {
             .core
             .rule_graph
             .edges_for_inner(&entry)
-            .ok_or_else(|| throw(format!("No edges for task {entry:?} exist!")))?;
+            .ok_or_else(|| {
+                EngineError::new(
+                    ErrorCode::InvariantViolated,
+                    format!("No edges for task {entry:?} exist!"),
+                )
+            })?;
 
         // Find the entry for the Get.
         let entry = edges
@@ -105,21 +124,27 @@ impl Task {
             })
             .ok_or_else(|| {
                 if get.input_types.iter().any(|t| t.is_union()) {
-                    throw(format!(
+                    EngineError::new(
+                        ErrorCode::InvalidUnionMember,
+                        format!(
             "Invalid Get. Because an input type for `{get}` was annotated with `@union`, \
              the value for that type should be a member of that union. Did you \
              intend to register a `UnionRule`? If not, you may be using the incorrect \
              explicitly declared type.",
-          ))
+          ),
+                    )
                 } else {
                     // NB: The Python constructor for `Get()` will have already errored if
                     // `type(input) != input_type`.
-                    throw(format!(
-                        "{get} was not detected in your @rule body at rule compile time. \
+                    EngineError::new(
+                        ErrorCode::UndetectedRuleCall,
+                        format!(
+                            "{get} was not detected in your @rule body at rule compile time. \
              Was the `Get` constructor called in a non async-function, or \
              was it inside an async function defined after the @rule? \
              Make sure the `Get` is defined before or inside the @rule body.",
-                    ))
+                        ),
+                    )
                 }
             })?;
         select(context.clone(), None, 0, params, entry).await
@@ -167,7 +192,10 @@ impl Task {
     ) -> NodeResult<(Value, TypeId)> {
         let mut input = GeneratorInput::Initial;
         loop {
+            // Record the time spent waiting to acquire the GIL, which is released once it is held.
+            let gil_token = workunit.waiting_on(WaitingOn::Gil);
             let response = Python::with_gil(|py| {
+                drop(gil_token);
                 externs::generator_send(py, &context.core.types.coroutine, &generator, input)
             })?;
             match response {
@@ -235,6 +263,177 @@ impl Task {
         }
     }
 
+    ///
+    /// The salt for the persisted results of the Task at the given rule graph entry: the Task's own
+    /// salt, combined with the source of the module of every rule which it transitively depends on
+    /// in the rule graph, so that a change to any of them invalidates its persisted results.
+    ///
+    fn persistent_cache_salt(
+        context: &Context,
+        entry: Intern<rule_graph::Entry<Rule>>,
+        own_salt: &str,
+    ) -> Result<Arc<str>, String> {
+        if let Some(salt) = context.core.persistent_cache_salts.lock().get(&entry) {
+            return Ok(salt.clone());
+        }
+
+        let mut functions = Vec::new();
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![entry];
+        while let Some(entry) = to_visit.pop() {
+            if !visited.insert(entry) {
+                continue;
+            }
+            let rule_graph::Entry::WithDeps(inner) = &*entry else {
+                continue;
+            };
+            if let Some(Rule(task)) = inner.rule() {
+                functions.push(task.func.clone());
+            }
+            if let Some(edges) = context.core.rule_graph.edges_for_inner(&entry) {
+                to_visit.extend(edges.all_dependencies().copied());
+            }
+        }
+
+        let mut salt = Vec::new();
+        let mut append = |bytes: &[u8]| {
+            salt.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            salt.extend_from_slice(bytes);
+        };
+        append(own_salt.as_bytes());
+        Python::with_gil(|py| -> PyResult<()> {
+            let modules = py.import("sys")?.getattr("modules")?;
+            let getsource = py.import("inspect")?.getattr("getsource")?;
+            let module_names = functions
+                .iter()
+                .map(|func| (*func.0.value).as_ref(py).getattr("__module__")?.extract())
+                .collect::<PyResult<BTreeSet<String>>>()?;
+            for module_name in module_names {
+                let source: String = getsource
+                    .call1((modules.get_item(module_name.as_str())?,))?
+                    .extract()?;
+                append(module_name.as_bytes());
+                append(source.as_bytes());
+            }
+            Ok(())
+        })
+        .map_err(|e| format!("Failed to compute the salt of persisted results: {e}"))?;
+
+        let salt: Arc<str> = Digest::of_bytes(&salt).hash.to_hex().into();
+        context
+            .core
+            .persistent_cache_salts
+            .lock()
+            .insert(entry, salt.clone());
+        Ok(salt)
+    }
+
+    ///
+    /// The key under which the result of a persisted Task is stored in the local cache: computed
+    /// from the persisted forms of the arguments to its function.
+    ///
+    fn persistent_cache_key(
+        task: &tasks::Task,
+        salt: &str,
+        args: Option<&Key>,
+        deps: &[Value],
+    ) -> Result<CacheKey, String> {
+        let mut key = Vec::new();
+        let mut append = |bytes: &[u8]| {
+            key.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            key.extend_from_slice(bytes);
+        };
+        append(task.display_info.name.as_bytes());
+        append(salt.as_bytes());
+        Python::with_gil(|py| {
+            let explicit_args: Vec<&PyAny> = match args {
+                Some(args) => args
+                    .value
+                    .extract::<&PyTuple>(py)
+                    .map_err(|e| e.to_string())?
+                    .iter()
+                    .collect(),
+                None => vec![],
+            };
+            for value in explicit_args
+                .into_iter()
+                .chain(deps.iter().map(|dep| (**dep).as_ref(py)))
+            {
+                let form = PersistedForm::of(value)?;
+                append(TypeId::new(value.get_type()).to_string().as_bytes());
+                append(&form.value);
+                for digest in form.digests {
+                    append(digest.as_digest().hash.to_hex().as_bytes());
+                }
+            }
+            Ok::<_, String>(())
+        })?;
+        Ok(CacheKey {
+            key_type: CacheKeyType::RuleValue.into(),
+            digest: Some(Digest::of_bytes(&key).into()),
+        })
+    }
+
+    ///
+    /// Restores a persisted result, if one exists and all of the Digests which it references are
+    /// still present in the Store.
+    ///
+    async fn load_persisted(
+        context: &Context,
+        key: &CacheKey,
+        product: TypeId,
+    ) -> Result<Option<Value>, String> {
+        let Some(bytes) = context.core.local_cache.load(key).await? else {
+            return Ok(None);
+        };
+        let persisted = PersistedRuleValue::decode(bytes)
+            .map_err(|e| format!("Invalid persisted rule value: {e}"))?;
+        let digests = persisted
+            .digests
+            .iter()
+            .map(|digest| Digest::try_from(digest).map(DirectoryDigest::from_persisted_digest))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !context
+            .core
+            .store()
+            .exists_recursive(digests, vec![])
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            return Ok(None);
+        }
+        Python::with_gil(|py| PersistedForm::restore(py, product, &persisted.value)).map(Some)
+    }
+
+    async fn store_persisted(
+        context: &Context,
+        key: &CacheKey,
+        value: &Value,
+    ) -> Result<(), String> {
+        let form = Python::with_gil(|py| PersistedForm::of((**value).as_ref(py)))?;
+        let store = context.core.store();
+        future::try_join_all(
+            form.digests
+                .iter()
+                .map(|digest| store.ensure_directory_digest_persisted(digest.clone())),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+        let persisted = PersistedRuleValue {
+            value: form.value.into(),
+            digests: form
+                .digests
+                .iter()
+                .map(|digest| digest.as_digest().into())
+                .collect(),
+        };
+        context
+            .core
+            .local_cache
+            .store(key, persisted.to_bytes())
+            .await
+    }
+
     pub(super) async fn run_node(
         self,
         context: Context,
@@ -269,6 +468,36 @@ impl Task {
         };
 
         let args = self.args;
+        let task = self.task;
+
+        // If the Task is persisted, attempt to restore its result from the local cache. Failing to
+        // restore (or later to store) a result is not fatal: the Task just runs as usual.
+        let persistent_cache_key = task.persistent_cache_salt.as_deref().and_then(|salt| {
+            Self::persistent_cache_salt(&context, self.entry, salt)
+                .and_then(|salt| Self::persistent_cache_key(&task, &salt, args.as_ref(), &deps))
+                .map_err(|e| log::debug!("Not persisting the result of {:?}: {e}", task.func))
+                .ok()
+        });
+        if let Some(key) = &persistent_cache_key {
+            match Self::load_persisted(&context, key, task.product).await {
+                Ok(Some(result_val)) => {
+                    if task.engine_aware_return_type {
+                        Python::with_gil(|py| {
+                            EngineAwareReturnType::update_workunit(
+                                workunit,
+                                (*result_val).as_ref(py),
+                            )
+                        })
+                    };
+                    return Ok(result_val);
+                }
+                Ok(None) => (),
+                Err(e) => log::debug!(
+                    "Failed to restore a persisted result of {:?}: {e}",
+                    task.func
+                ),
+            }
+        }
 
         let (mut result_val, mut result_type) = task_context(
             context.clone(),
@@ -335,6 +564,12 @@ impl Task {
             })
         };
 
+        if let Some(key) = &persistent_cache_key {
+            if let Err(e) = Self::store_persisted(&context, key, &result_val).await {
+                log::debug!("Failed to persist the result of {:?}: {e}", task.func);
+            }
+        }
+
         Ok(result_val)
     }
 }
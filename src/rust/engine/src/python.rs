@@ -7,6 +7,7 @@ use std::sync::Arc;
 use std::{fmt, hash};
 
 use deepsize::{known_deep_size, DeepSizeOf};
+use engine_error::EngineError;
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyType};
 use pyo3::{FromPyObject, IntoPy, ToPyObject};
@@ -545,6 +546,7 @@ impl From<ProcessError> for Failure {
     fn from(err: ProcessError) -> Self {
         match err {
             ProcessError::MissingDigest(s, d) => Self::MissingDigest(s, d),
+            ProcessError::Classified(e) => e.into(),
             ProcessError::Unclassified(s) => throw(s),
         }
     }
@@ -554,11 +556,23 @@ impl From<StoreError> for Failure {
     fn from(err: StoreError) -> Self {
         match err {
             StoreError::MissingDigest(s, d) => Self::MissingDigest(s, d),
+            StoreError::Classified(e) => e.into(),
             StoreError::Unclassified(s) => throw(s),
         }
     }
 }
 
+impl From<EngineError> for Failure {
+    fn from(err: EngineError) -> Self {
+        let python_traceback = Failure::native_traceback(&err.to_string());
+        Python::with_gil(|py| Failure::Throw {
+            val: externs::create_classified_exception(py, &err),
+            python_traceback,
+            engine_traceback: Vec::new(),
+        })
+    }
+}
+
 impl From<Failure> for PyErr {
     fn from(err: Failure) -> Self {
         externs::NativeEngineFailure::new_err((err.to_string(), externs::PyFailure(err)))
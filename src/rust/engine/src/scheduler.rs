@@ -5,20 +5,28 @@ use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use async_latch::AsyncLatch;
 use deepsize::DeepSizeOf;
-use futures::{future, FutureExt};
+use futures::{future, Future, FutureExt};
 use log::debug;
+use parking_lot::Mutex;
+use pyo3::prelude::*;
 use tokio::time;
 
 use crate::context::{Context, Core};
-use crate::nodes::{NodeKey, Root};
+use crate::externs;
+use crate::memory::{self, MemoryAction, MemoryLimits, MemoryReport, NodeMemoryUsage};
+use crate::nodes::{NodeKey, NodeOutput, Root};
 use crate::python::{Failure, Params, TypeId, Value};
 use crate::session::{ObservedValueResult, Session};
+use crate::subscription::Subscription;
 
 use graph::LastObserved;
+use process_execution::{ProcessExecutionStrategy, ProcessResultSource};
 use ui::ConsoleUI;
 use watch::{Invalidatable, InvalidateCaller};
 
@@ -60,12 +68,15 @@ pub struct ExecutionRequest {
 ///
 pub struct Scheduler {
     pub core: Arc<Core>,
+    /// The action taken by the previous call to `check_memory`.
+    previous_memory_action: Mutex<MemoryAction>,
 }
 
 impl Scheduler {
     pub fn new(core: Core) -> Scheduler {
         Scheduler {
             core: Arc::new(core),
+            previous_memory_action: Mutex::new(MemoryAction::Continue),
         }
     }
 
@@ -162,16 +173,140 @@ impl Scheduler {
             }
             let entry = sizes.entry(k.workunit_name()).or_insert_with(|| (0, 0));
             entry.0 += 1;
-            entry.1 += {
-                std::mem::size_of_val(k)
-                    + k.deep_size_of_children(&mut deep_context)
-                    + std::mem::size_of_val(&v)
-                    + v.deep_size_of_children(&mut deep_context)
-            };
+            entry.1 += Self::deep_size_of(k, &v, &mut deep_context);
         });
         (items, sizes)
     }
 
+    ///
+    /// Returns a summary of sizes of Rust structs in the graph as a count and total size, as in
+    /// `live_items`.
+    ///
+    pub fn live_sizes(&self, session: &Session) -> HashMap<&'static str, (usize, usize)> {
+        let context = session.graph_context();
+        let mut sizes: HashMap<&'static str, (usize, usize)> = HashMap::new();
+        let mut deep_context = deepsize::Context::new();
+        self.core.graph.visit_live(&context, |k, v| {
+            let entry = sizes.entry(k.workunit_name()).or_insert_with(|| (0, 0));
+            entry.0 += 1;
+            entry.1 += Self::deep_size_of(k, &v, &mut deep_context);
+        });
+        sizes
+    }
+
+    fn deep_size_of(k: &NodeKey, v: &NodeOutput, deep_context: &mut deepsize::Context) -> usize {
+        std::mem::size_of_val(k)
+            + k.deep_size_of_children(deep_context)
+            + std::mem::size_of_val(v)
+            + v.deep_size_of_children(deep_context)
+    }
+
+    ///
+    /// Checks the memory usage of the process against the given limits, and clears the graph if
+    /// that is sufficient to reclaim memory. Should only be called between runs.
+    ///
+    /// If any action is necessary, logs a report of what was consuming memory. Returns the action
+    /// which was decided upon: if it is `MemoryAction::Restart`, the caller should restart the
+    /// process.
+    ///
+    pub fn check_memory(&self, session: &Session, limits: &MemoryLimits) -> MemoryAction {
+        let rss_bytes = memory::rss_bytes();
+        let graph_size = self.core.graph.len();
+        let action = {
+            let mut previous_memory_action = self.previous_memory_action.lock();
+            let action =
+                MemoryAction::decide(limits, rss_bytes, graph_size, *previous_memory_action);
+            *previous_memory_action = action;
+            action
+        };
+        if action == MemoryAction::Continue {
+            return action;
+        }
+
+        // Computing the sizes of nodes is relatively expensive, so we only do it when we have
+        // decided to act.
+        let sizes = self.live_sizes(session);
+        let report = MemoryReport::new(
+            rss_bytes,
+            memory::mapped_resident_bytes(&self.core.local_store_dir),
+            graph_size,
+            sizes,
+        );
+        match action {
+            MemoryAction::ClearCaches => {
+                log::info!("Clearing in-memory caches to reclaim memory. Memory usage:\n{report}");
+                self.invalidate_all();
+            }
+            MemoryAction::Restart => {
+                log::warn!(
+                    "Memory usage exceeded the limit of {limit} bytes, and a restart is required. \
+                     Memory usage:\n{report}",
+                    limit = limits.max_memory_usage_bytes,
+                );
+            }
+            MemoryAction::Continue => unreachable!(),
+        }
+        action
+    }
+
+    ///
+    /// Returns the memory used by the live nodes in the graph, grouped by node type and rule, and
+    /// sorted by total size (largest first).
+    ///
+    /// If `include_python_values` is set, the sizes of the Python values held by nodes are also
+    /// estimated: this acquires the GIL, and is much slower than computing only the Rust sizes.
+    ///
+    pub fn memory_usage(
+        &self,
+        session: &Session,
+        include_python_values: bool,
+    ) -> Result<Vec<NodeMemoryUsage>, String> {
+        type Group = (&'static str, Option<&'static str>);
+        let context = session.graph_context();
+        let mut usages: HashMap<Group, NodeMemoryUsage> = HashMap::new();
+        let mut values: Vec<(Group, Value)> = Vec::new();
+        let mut deep_context = deepsize::Context::new();
+        self.core.graph.visit_live(&context, |k, v| {
+            let group = (k.node_type(), k.rule_name());
+            let usage = usages
+                .entry(group)
+                .or_insert_with(|| NodeMemoryUsage::new(group.0, group.1));
+            usage.count += 1;
+            usage.rust_bytes += Self::deep_size_of(k, &v, &mut deep_context);
+            if include_python_values {
+                if let NodeOutput::Value(value) = v {
+                    values.push((group, value));
+                }
+            }
+        });
+
+        // NB: The GIL is acquired only after visiting the graph, since `visit_live` holds the graph
+        // lock, and nodes which are running may acquire the GIL before requesting the lock.
+        if include_python_values {
+            for usage in usages.values_mut() {
+                usage.python_bytes = Some(0);
+            }
+            Python::with_gil(|py| -> PyResult<()> {
+                let estimator = externs::SizeEstimator::new(py)?;
+                for (group, value) in values {
+                    let size = estimator.size_of(value.as_ref().as_ref(py))?;
+                    if let Some(python_bytes) = usages
+                        .get_mut(&group)
+                        .and_then(|usage| usage.python_bytes.as_mut())
+                    {
+                        *python_bytes += size;
+                    }
+                }
+                Ok(())
+            })
+            .map_err(|e| format!("Failed to estimate the size of Python values: {e}"))?;
+        }
+
+        let mut usages = usages.into_values().collect::<Vec<_>>();
+        NodeMemoryUsage::sort(&mut usages);
+        Ok(usages)
+    }
+
     ///
     /// Return unit if the Scheduler is still valid, or an error string if something has invalidated
     /// the Scheduler, indicating that it should re-initialize. See InvalidationWatcher.
@@ -200,6 +335,52 @@ impl Scheduler {
         digests
     }
 
+    ///
+    /// Uploads the results of all processes which ran locally and which are reachable from the
+    /// given roots (which must have completed in the given Session) to the remote cache, along with
+    /// their outputs. Returns the number of results which were published.
+    ///
+    /// This operates independently of whether remote cache writes were enabled while the roots were
+    /// computed.
+    ///
+    pub fn publish_to_remote_cache(
+        &self,
+        session: &Session,
+        roots: &[Root],
+    ) -> Result<usize, String> {
+        let context = session.graph_context();
+        let roots = roots
+            .iter()
+            .map(|root| root.clone().into())
+            .collect::<Vec<NodeKey>>();
+        let mut processes = Vec::new();
+        self.core
+            .graph
+            .visit_live_reachable(&roots, &context, |k, v| {
+                if let (NodeKey::ExecuteProcess(ep), NodeOutput::ProcessResult(pr)) = (k, v) {
+                    let metadata = &pr.result.metadata;
+                    if metadata.source == ProcessResultSource::Ran
+                        && metadata.environment.strategy == ProcessExecutionStrategy::Local
+                    {
+                        processes.push((ep.process.clone(), pr.result));
+                    }
+                }
+            });
+
+        let core = self.core.clone();
+        self.core.executor.block_on(async move {
+            let publisher = core.make_remote_cache_publisher().await?;
+            let published = future::try_join_all(
+                processes
+                    .iter()
+                    .map(|(process, result)| publisher.publish(process, result)),
+            )
+            .await
+            .map_err(|e| format!("Failed to publish to the remote cache: {e}"))?;
+            Ok(published.into_iter().filter(|published| *published).count())
+        })
+    }
+
     async fn poll_or_create(
         context: &Context,
         root: Root,
@@ -229,25 +410,106 @@ impl Scheduler {
     }
 
     ///
-    /// Attempts to complete all of the given roots.
+    /// Attempts to complete all of the given roots. A root whose latch is triggered completes
+    /// immediately with a cancellation Failure, while the remaining roots continue to run.
+    ///
+    /// If the run deadline of the Session elapses first, all of the roots which have not yet
+    /// completed are cancelled, and the results of those which had completed are returned.
     ///
     async fn execute_helper(
         request: &ExecutionRequest,
         session: &Session,
+        cancellations: Vec<AsyncLatch>,
     ) -> Vec<ObservedValueResult> {
         let context = session.graph_context();
         let roots = session.roots_zip_last_observed(&request.roots);
         let poll = request.poll;
         let poll_delay = request.poll_delay;
-        future::join_all(
+        let run_budget_cancellations = AtomicUsize::new(0);
+        let execution = future::join_all(
             roots
                 .into_iter()
-                .map(|(root, last_observed)| {
-                    Self::poll_or_create(&context, root, last_observed, poll, poll_delay)
+                .zip(cancellations)
+                .map(|((root, last_observed), cancelled)| {
+                    let context = &context;
+                    let run_budget_cancellations = &run_budget_cancellations;
+                    async move {
+                        let product = root.product();
+                        tokio::select! {
+                          res = Self::poll_or_create(context, root, last_observed, poll, poll_delay) => res,
+                          _ = cancelled.triggered() => {
+                            // NB: Dropping the request for the root leaves the cleanup of its Nodes
+                            // to the Graph, which will cancel them unless other roots are waiting.
+                            let failure = if session.run_budget_exceeded() {
+                                run_budget_cancellations.fetch_add(1, Ordering::SeqCst);
+                                Self::run_budget_exceeded(product)
+                            } else {
+                                Self::root_cancelled(product)
+                            };
+                            (Err(failure), last_observed)
+                          }
+                        }
+                    }
                 })
                 .collect::<Vec<_>>(),
-        )
-        .await
+        );
+
+        let Some(run_deadline) = session.run_deadline() else {
+            return execution.await;
+        };
+        let mut execution = std::pin::pin!(execution);
+        tokio::select! {
+          res = &mut execution => res,
+          _ = time::sleep_until(run_deadline.into()) => {
+            // Cancel all of the roots, and then wait for the roots which had already completed
+            // (and the cancellation failures of the rest).
+            session.exceed_run_budget();
+            session.cancel_roots(&request.roots);
+            let results = execution.await;
+            let cancelled = run_budget_cancellations.load(Ordering::SeqCst);
+            log::warn!(
+                "Completed {} of {} requested roots before the run budget was exceeded.",
+                results.len() - cancelled,
+                results.len(),
+            );
+            results
+          }
+        }
+    }
+
+    fn root_cancelled(product: TypeId) -> Failure {
+        let msg = format!("The request for {product} was cancelled.");
+        let python_traceback = Failure::native_traceback(&msg);
+        Python::with_gil(|py| Failure::Throw {
+            val: Value::new(externs::RootCancelled::new_err(msg).into_py(py)),
+            python_traceback,
+            engine_traceback: Vec::new(),
+        })
+    }
+
+    fn run_budget_exceeded(product: TypeId) -> Failure {
+        let msg =
+            format!("The request for {product} was cancelled because the run budget was exceeded.");
+        let python_traceback = Failure::native_traceback(&msg);
+        Python::with_gil(|py| Failure::Throw {
+            val: Value::new(externs::RunBudgetExceeded::new_err(msg).into_py(py)),
+            python_traceback,
+            engine_traceback: Vec::new(),
+        })
+    }
+
+    ///
+    /// Cancels any in-flight executions of the roots in the given request, without cancelling
+    /// other roots which are executing in the Session. Returns the number of roots cancelled.
+    ///
+    pub fn cancel_roots(&self, request: &ExecutionRequest, session: &Session) -> usize {
+        let cancelled = session.cancel_roots(&request.roots);
+        debug!(
+            "Cancelled {} of {} requested roots.",
+            cancelled,
+            request.roots.len()
+        );
+        cancelled
     }
 
     fn execute_record_results(
@@ -284,45 +546,107 @@ impl Scheduler {
             request.poll
         );
 
-        let interval = ConsoleUI::render_interval();
         let deadline = request.timeout.map(|timeout| Instant::now() + timeout);
         let executor = self.core.executor.clone();
 
         // Spawn and wait for all roots to complete.
         self.core.executor.block_on(async move {
             session.maybe_display_initialize(&executor).await;
-            let mut execution_task = Self::execute_helper(request, session).boxed();
-
-            let mut refresh_delay = time::sleep(Self::refresh_delay(interval, deadline)).boxed();
-            let result = loop {
-                tokio::select! {
-                  _ = session.cancelled() => {
-                    // The Session was cancelled.
-                    break Err(ExecutionTermination::KeyboardInterrupt)
-                  }
-                  _ = &mut refresh_delay => {
-                    // It's time to render a new frame (or maybe to time out entirely if the deadline has
-                    // elapsed).
-                    if deadline.map(|d| d < Instant::now()).unwrap_or(false) {
-                      // The timeout on the request has been exceeded.
-                      break Err(ExecutionTermination::PollTimeout);
-                    } else {
-                      // Just a receive timeout. render and continue.
-                      session.maybe_display_render();
-                    }
-                    refresh_delay = time::sleep(Self::refresh_delay(interval, deadline)).boxed();
-                  }
-                  res = &mut execution_task => {
-                    // Completed successfully.
-                    break Ok(Self::execute_record_results(&request.roots, session, res));
-                  }
-                }
-            };
+            let cancellations = session.roots_begin(&request.roots);
+            let result = Self::await_rendering(
+                session,
+                deadline,
+                Self::execute_helper(request, session, cancellations),
+            )
+            .await
+            .map(|res| Self::execute_record_results(&request.roots, session, res));
+            session.roots_end(&request.roots);
+            session.maybe_display_teardown().await;
+            result
+        })
+    }
+
+    ///
+    /// Creates a Subscription to the roots of the given request, which can be used to wait for
+    /// their values to change. See `Subscription`.
+    ///
+    pub fn subscribe(
+        &self,
+        request: &ExecutionRequest,
+        debounce: Duration,
+    ) -> Result<Subscription, String> {
+        if request.roots.is_empty() {
+            return Err("A subscription must contain at least one root.".to_owned());
+        }
+        Ok(Subscription::new(request.roots.clone(), debounce))
+    }
+
+    ///
+    /// Waits for the roots of the given Subscription to change, and returns the indexes and values
+    /// of the roots which changed. The first call for a Subscription returns all of its roots.
+    ///
+    #[allow(clippy::type_complexity)]
+    pub fn subscription_next(
+        &self,
+        subscription: &mut Subscription,
+        session: &Session,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(usize, Result<Value, Failure>)>, ExecutionTermination> {
+        debug!(
+            "Waiting for changes to {} subscribed roots.",
+            subscription.roots().len()
+        );
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let executor = self.core.executor.clone();
+
+        self.core.executor.block_on(async move {
+            session.maybe_display_initialize(&executor).await;
+            let result = Self::await_rendering(session, deadline, subscription.next(session)).await;
             session.maybe_display_teardown().await;
             result
         })
     }
 
+    ///
+    /// Waits for the given future to complete, while rendering the Session's display. Fails if the
+    /// Session is cancelled or the deadline elapses first.
+    ///
+    async fn await_rendering<T>(
+        session: &Session,
+        deadline: Option<Instant>,
+        task: impl Future<Output = T>,
+    ) -> Result<T, ExecutionTermination> {
+        let interval = ConsoleUI::render_interval();
+        let mut task = std::pin::pin!(task);
+        let mut refresh_delay = time::sleep(Self::refresh_delay(interval, deadline)).boxed();
+        loop {
+            tokio::select! {
+              _ = session.cancelled() => {
+                // The Session was cancelled.
+                break Err(ExecutionTermination::KeyboardInterrupt)
+              }
+              _ = &mut refresh_delay => {
+                // It's time to render a new frame (or maybe to time out entirely if the deadline has
+                // elapsed).
+                if deadline.map(|d| d < Instant::now()).unwrap_or(false) {
+                  // The timeout on the request has been exceeded.
+                  break Err(ExecutionTermination::PollTimeout);
+                } else {
+                  // Just a receive timeout. render and continue.
+                  session.maybe_display_render();
+                  session.maybe_report_stalls();
+                }
+                refresh_delay = time::sleep(Self::refresh_delay(interval, deadline)).boxed();
+              }
+              res = &mut task => {
+                // Completed successfully.
+                break Ok(res)
+              }
+            }
+        }
+    }
+
     fn refresh_delay(refresh_interval: Duration, deadline: Option<Instant>) -> Duration {
         deadline
             .and_then(|deadline| deadline.checked_duration_since(Instant::now()))
@@ -1,33 +1,51 @@
 // Copyright 2020 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
-use std::sync::atomic::{self, AtomicU32};
+use std::path::PathBuf;
+use std::sync::atomic::{self, AtomicBool, AtomicU32};
 use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
 
+use crate::cache_miss::CacheMissRecorder;
 use crate::context::{Core, SessionCore};
+use crate::digest_server::DigestServer;
 use crate::nodes::{NodeKey, Root};
 use crate::python::{Failure, Value};
+use crate::timings;
+use crate::tmpdir::SessionTmpDir;
 
 use async_latch::AsyncLatch;
+use fs::DirectoryDigest;
 use futures::future::{self, FutureExt};
-use graph::{Context, LastObserved};
+use graph::{Context, Graph, LastObserved};
 use log::warn;
 use parking_lot::Mutex;
+use process_execution::children;
 use pyo3::prelude::*;
 use task_executor::{Executor, TailTasks};
 use tokio::signal::unix::{signal, SignalKind};
 use tokio::task::JoinHandle;
-use ui::ConsoleUI;
-use workunit_store::{format_workunit_duration_ms, RunId, WorkunitStore};
+use ui::{ConsoleUI, PlainOutputRenderer};
+use workunit_store::{
+    format_workunit_duration_ms, RunId, SpanId, StalledWorkunit, WorkunitSampling, WorkunitStore,
+};
 
 // When enabled, the interval at which all stragglers that have been running for longer than a
 // threshold should be logged. The threshold might become configurable, but this might not need
 // to be.
 const STRAGGLER_LOGGING_INTERVAL: Duration = Duration::from_secs(30);
 
+// The maximum number of running Nodes to include when dumping the state of the engine.
+const STATE_DUMP_MAX_NODES: usize = 100;
+
+// The maximum number of running workunits to log when the run budget of a Session is exceeded.
+const RUN_BUDGET_MAX_WORKUNITS: usize = 10;
+
+// When a stall threshold is configured, the interval at which to check for stalled workunits.
+const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
 pub type ObservedValueResult = (Result<Value, Failure>, Option<LastObserved>);
 
 ///
@@ -67,6 +85,57 @@ impl SessionDisplay {
     }
 }
 
+///
+/// Periodically reports the workunits of a Session which have made no observable progress for
+/// longer than a threshold: see `WorkunitStore::stalled_workunits`.
+///
+struct StallWatchdog {
+    threshold: Duration,
+    next_check: Instant,
+    // The workunits which have already been reported, so that each is reported only once.
+    reported: HashSet<SpanId>,
+}
+
+impl StallWatchdog {
+    fn new(threshold: Duration) -> StallWatchdog {
+        StallWatchdog {
+            threshold,
+            next_check: Instant::now() + STALL_CHECK_INTERVAL,
+            reported: HashSet::new(),
+        }
+    }
+
+    fn check(&mut self, workunit_store: &WorkunitStore) {
+        if self.next_check > Instant::now() {
+            return;
+        }
+        self.next_check = Instant::now() + STALL_CHECK_INTERVAL;
+        for stalled in workunit_store.stalled_workunits(self.threshold) {
+            if self.reported.insert(stalled.span_id) {
+                warn!("{}", Self::render(&stalled));
+            }
+        }
+    }
+
+    fn render(stalled: &StalledWorkunit) -> String {
+        let mut lines = vec![
+            format!(
+                "`{}` has made no observable progress for {}: it may be hung.",
+                stalled.description,
+                format_workunit_duration_ms!(stalled.stalled_for.as_millis()),
+            ),
+            format!("  workunit: {} ({})", stalled.name, stalled.span_id),
+        ];
+        lines.extend(
+            stalled
+                .ancestors
+                .iter()
+                .map(|(name, description)| format!("  within: {name} (`{description}`)")),
+        );
+        lines.join("\n")
+    }
+}
+
 ///
 /// The portion of a Session that uniquely identifies it and holds metrics and the history of
 /// requests made on it.
@@ -89,6 +158,50 @@ struct SessionState {
     run_id: AtomicU32,
     /// Tasks to await at the "tail" of the session.
     tail_tasks: TailTasks,
+    // If set, the path to write a Chrome trace of the workunits of this Session to: see
+    // `Session::write_chrome_trace`.
+    chrome_trace_file: Option<PathBuf>,
+    // A server for the contents of digests, which is started on first use and stopped when the
+    // Session ends.
+    digest_server: Mutex<Option<DigestServer>>,
+    // The namespace for temporary directories of this Session, which is removed when it ends.
+    tmpdir: SessionTmpDir,
+    // If set, the wall-clock deadline for all work in this Session: see `Session::run_deadline`.
+    run_deadline: Option<Instant>,
+    // True if the `run_deadline` elapsed while work was in flight.
+    run_budget_exceeded: AtomicBool,
+    // If set, records the fingerprints of the processes of this Session, which are written when it
+    // ends.
+    cache_miss_recorder: Option<CacheMissRecorder>,
+    // If a stall threshold is configured, reports the work of this Session which is stalled.
+    stall_watchdog: Option<Mutex<StallWatchdog>>,
+}
+
+impl Drop for SessionState {
+    fn drop(&mut self) {
+        self.core
+            .completed_session_metrics
+            .add(&self.workunit_store);
+        if let Some(recorder) = self.cache_miss_recorder.as_ref() {
+            if let Err(e) = recorder.write() {
+                warn!("{}", e);
+            }
+        }
+        let timing_entries = self.core.timing_history.lock().entries();
+        let local_cache = self.core.local_cache.clone();
+        let _join = self.core.executor.native_spawn(async move {
+            if let Err(e) = timings::store(&local_cache, timing_entries).await {
+                warn!("Failed to store historical node timings: {e}");
+            }
+        });
+        if let Some(digest_server) = self.digest_server.get_mut().take() {
+            let _join = self.core.executor.native_spawn(async move {
+                if let Err(e) = digest_server.shutdown().await {
+                    warn!("{}", e);
+                }
+            });
+        }
+    }
 }
 
 ///
@@ -105,6 +218,11 @@ struct SessionHandle {
     isolated: bool,
     // The display mechanism to use in this Session.
     display: tokio::sync::Mutex<SessionDisplay>,
+    // Latches for the roots of in-flight executions which may be cancelled individually (without
+    // cancelling the Session), along with the count of executions which are waiting for each root.
+    root_cancellations: Mutex<HashMap<Root, (AsyncLatch, usize)>>,
+    // The workunits of this Session: used to dump the state of the Session on repeated interrupts.
+    workunit_store: WorkunitStore,
 }
 
 impl SessionHandle {
@@ -148,6 +266,12 @@ impl Session {
         build_id: String,
         session_values: PyObject,
         cancelled: AsyncLatch,
+        chrome_trace_file: Option<PathBuf>,
+        record_critical_path: bool,
+        stream_process_output: bool,
+        run_budget: Option<Duration>,
+        workunit_sampling: Option<WorkunitSampling>,
+        cache_miss_records_dir: Option<PathBuf>,
     ) -> Result<Session, String> {
         // We record workunits with the maximum level of:
         // 1. the given `max_workunit_verbosity`, which should be computed from:
@@ -157,7 +281,23 @@ impl Session {
         if dynamic_ui {
             max_workunit_level = std::cmp::max(max_workunit_level, log::Level::Debug);
         }
-        let workunit_store = WorkunitStore::new(!dynamic_ui, max_workunit_level);
+        let mut workunit_store = WorkunitStore::new(!dynamic_ui, max_workunit_level)
+            .with_session_id(build_id.clone())
+            .with_timing_history(core.timing_history.clone());
+        if chrome_trace_file.is_some() {
+            workunit_store = workunit_store.with_chrome_trace();
+        }
+        if record_critical_path {
+            workunit_store = workunit_store.with_critical_path();
+        }
+        if let Some(workunit_sampling) = workunit_sampling {
+            workunit_store = workunit_store.with_sampling(workunit_sampling);
+        }
+        if stream_process_output {
+            workunit_store = workunit_store.with_process_output_sink(Arc::new(
+                PlainOutputRenderer::new(stdio::get_destination()),
+            ));
+        }
         let display = tokio::sync::Mutex::new(SessionDisplay::new(
             &workunit_store,
             core.local_parallelism,
@@ -165,11 +305,19 @@ impl Session {
             ui_use_prodash,
         ));
 
+        let tmpdir = SessionTmpDir::new(core.session_tmpdir_root.clone(), &build_id);
+        let cache_miss_recorder =
+            cache_miss_records_dir.map(|dir| CacheMissRecorder::new(dir, &build_id));
+        let stall_watchdog = core
+            .stall_threshold
+            .map(|threshold| Mutex::new(StallWatchdog::new(threshold)));
         let handle = Arc::new(SessionHandle {
             build_id,
             cancelled,
             isolated: false,
             display,
+            root_cancellations: Mutex::new(HashMap::new()),
+            workunit_store: workunit_store.clone(),
         });
         core.sessions.add(&handle)?;
         let run_id = core.graph.generate_run_id();
@@ -184,6 +332,13 @@ impl Session {
                 session_values: Mutex::new(session_values),
                 run_id: AtomicU32::new(run_id.0),
                 tail_tasks: TailTasks::new(),
+                chrome_trace_file,
+                digest_server: Mutex::new(None),
+                tmpdir,
+                run_deadline: run_budget.map(|budget| Instant::now() + budget),
+                run_budget_exceeded: AtomicBool::new(false),
+                cache_miss_recorder,
+                stall_watchdog,
             }),
         })
     }
@@ -216,6 +371,8 @@ impl Session {
             isolated: true,
             cancelled: AsyncLatch::new(),
             display,
+            root_cancellations: Mutex::new(HashMap::new()),
+            workunit_store: self.state.workunit_store.clone(),
         });
         self.state.core.sessions.add(&handle)?;
         Ok(Session {
@@ -249,6 +406,127 @@ impl Session {
         self.handle.cancelled.triggered().await;
     }
 
+    ///
+    /// Marks the given roots as in-flight, and returns a latch for each of them which will be
+    /// triggered if the root is cancelled via `cancel_roots`. Must be paired with a call to
+    /// `roots_end` once the roots are no longer being waited for.
+    ///
+    pub fn roots_begin(&self, roots: &[Root]) -> Vec<AsyncLatch> {
+        let mut root_cancellations = self.handle.root_cancellations.lock();
+        roots
+            .iter()
+            .map(|root| {
+                let (latch, waiters) = root_cancellations
+                    .entry(root.clone())
+                    .or_insert_with(|| (AsyncLatch::new(), 0));
+                if latch.poll_triggered() {
+                    // The root was cancelled for a previous waiter: start fresh for this one.
+                    *latch = AsyncLatch::new();
+                }
+                *waiters += 1;
+                latch.clone()
+            })
+            .collect()
+    }
+
+    ///
+    /// Releases roots which were marked in-flight by `roots_begin`.
+    ///
+    pub fn roots_end(&self, roots: &[Root]) {
+        let mut root_cancellations = self.handle.root_cancellations.lock();
+        for root in roots {
+            if let Some((_, waiters)) = root_cancellations.get_mut(root) {
+                *waiters -= 1;
+                if *waiters == 0 {
+                    root_cancellations.remove(root);
+                }
+            }
+        }
+    }
+
+    ///
+    /// Cancels any in-flight executions of the given roots in this Session, without affecting
+    /// other roots. Work that is shared with roots which are still being waited for continues to
+    /// run, while work that was only reachable from the cancelled roots is abandoned.
+    ///
+    /// Returns the number of in-flight roots that were cancelled.
+    ///
+    pub fn cancel_roots(&self, roots: &[Root]) -> usize {
+        let root_cancellations = self.handle.root_cancellations.lock();
+        roots
+            .iter()
+            .filter_map(|root| root_cancellations.get(root))
+            .filter(|(latch, _)| !latch.poll_triggered())
+            .map(|(latch, _)| latch.trigger())
+            .count()
+    }
+
+    ///
+    /// The wall-clock deadline for all work in this Session, if a run budget was set when it was
+    /// created. Executions which are in flight when the deadline elapses are cancelled: see
+    /// `Scheduler::execute`.
+    ///
+    pub fn run_deadline(&self) -> Option<Instant> {
+        self.state.run_deadline
+    }
+
+    ///
+    /// Returns true if the run budget of this Session was exceeded while work was in flight.
+    ///
+    pub fn run_budget_exceeded(&self) -> bool {
+        self.state
+            .run_budget_exceeded
+            .load(atomic::Ordering::SeqCst)
+    }
+
+    ///
+    /// Returns the recorder for the process fingerprints of this Session, if cache misses are being
+    /// recorded.
+    ///
+    pub fn cache_miss_recorder(&self) -> Option<&CacheMissRecorder> {
+        self.state.cache_miss_recorder.as_ref()
+    }
+
+    ///
+    /// Records that the run budget of this Session was exceeded, and logs the longest-running
+    /// workunits (which are about to be cancelled).
+    ///
+    pub fn exceed_run_budget(&self) {
+        if self
+            .state
+            .run_budget_exceeded
+            .swap(true, atomic::Ordering::SeqCst)
+        {
+            // Already reported by a concurrent execution.
+            return;
+        }
+        let running_workunits = self
+            .state
+            .workunit_store
+            .straggling_workunits(Duration::ZERO);
+        let longest_running = running_workunits
+            .into_iter()
+            .rev()
+            .take(RUN_BUDGET_MAX_WORKUNITS)
+            .map(|(duration, desc)| {
+                format!(
+                    "{}\t{}",
+                    format_workunit_duration_ms!(duration.as_millis()),
+                    desc
+                )
+            })
+            .collect::<Vec<_>>();
+        if longest_running.is_empty() {
+            warn!("The run budget was exceeded: cancelling in-flight work.");
+        } else {
+            warn!(
+                "The run budget was exceeded: cancelling in-flight work. The longest running tasks \
+                 were:\n  {}",
+                longest_running.join("\n  ")
+            );
+        }
+    }
+
     pub fn roots_extend(&self, new_roots: Vec<(Root, Option<LastObserved>)>) {
         let mut roots = self.state.roots.lock();
         roots.extend(new_roots);
@@ -270,6 +548,19 @@ impl Session {
         roots.keys().map(|r| r.clone().into()).collect()
     }
 
+    ///
+    /// Serves the contents of the given digest over HTTP on localhost until this Session ends, and
+    /// returns the URL of its root directory.
+    ///
+    pub fn serve_digest(&self, digest: DirectoryDigest) -> Result<String, String> {
+        let mut digest_server = self.state.digest_server.lock();
+        if digest_server.is_none() {
+            let core = &self.state.core;
+            *digest_server = Some(DigestServer::new(&core.executor, 0, core.store())?);
+        }
+        Ok(digest_server.as_ref().unwrap().serve(digest))
+    }
+
     pub fn session_values(&self) -> PyObject {
         self.state.session_values.lock().clone()
     }
@@ -282,10 +573,30 @@ impl Session {
         self.state.workunit_store.clone()
     }
 
+    ///
+    /// If a Chrome trace file was configured for this Session, writes the workunits which have
+    /// completed so far to it. Called explicitly by the end of a run, rather than when the Session
+    /// is dropped, since writing the trace may take a while for large runs.
+    ///
+    pub fn write_chrome_trace(&self) {
+        if let Some(path) = self.state.chrome_trace_file.as_ref() {
+            if let Err(e) = self.state.workunit_store.write_chrome_trace(path) {
+                warn!("{}", e);
+            }
+        }
+    }
+
     pub fn build_id(&self) -> &String {
         &self.handle.build_id
     }
 
+    ///
+    /// The namespace for temporary directories of this Session, which is removed when it ends.
+    ///
+    pub fn tmpdir(&self) -> &SessionTmpDir {
+        &self.state.tmpdir
+    }
+
     pub fn run_id(&self) -> RunId {
         RunId(self.state.run_id.load(atomic::Ordering::SeqCst))
     }
@@ -377,6 +688,16 @@ impl Session {
         }
     }
 
+    ///
+    /// If a stall threshold is configured, logs a warning for each workunit which has newly
+    /// exceeded it without making observable progress.
+    ///
+    pub fn maybe_report_stalls(&self) {
+        if let Some(stall_watchdog) = self.state.stall_watchdog.as_ref() {
+            stall_watchdog.lock().check(&self.state.workunit_store);
+        }
+    }
+
     /// Return a reference to `TailTasks` for this session which monitors tasks representing
     /// asynchronous "tail" tasks that should not block individual nodes in the build graph but
     /// should block the ending of this `Session` (when the `.wait` method is called).
@@ -388,8 +709,16 @@ impl Session {
 ///
 /// A collection of all live Sessions.
 ///
-/// The `Sessions` struct maintains a task monitoring SIGINT, and cancels all current Sessions each time
-/// it arrives.
+/// The `Sessions` struct maintains a task monitoring SIGINT, which escalates on repeated interrupts
+/// (see `InterruptEscalation`):
+///   1. the first interrupt cancels all current (non-isolated) Sessions.
+///   2. if any cancelled Session is still live when another interrupt arrives, the child processes
+///      of the interrupted Sessions are killed.
+///   3. if a cancelled Session is still live after that, the state of the engine is dumped to the
+///      log.
+///
+/// NB: The process never exits due to interrupts, because it may be pantsd, which outlives the
+/// Sessions that it runs.
 ///
 pub struct Sessions {
     /// Live sessions. Completed Sessions (i.e., those for which the Weak reference is dead) are
@@ -403,16 +732,17 @@ pub struct Sessions {
 }
 
 impl Sessions {
-    pub fn new(executor: &Executor) -> Result<Sessions, String> {
+    pub fn new(executor: &Executor, graph: Graph<NodeKey>) -> Result<Sessions, String> {
         let sessions: Arc<Mutex<Option<Vec<Weak<SessionHandle>>>>> =
             Arc::new(Mutex::new(Some(Vec::new())));
         // A task that watches for keyboard interrupts arriving at this process, and cancels all
-        // non-isolated Sessions.
+        // non-isolated Sessions (escalating if cancellation does not complete).
         let signal_task_handle = {
             let mut signal_stream = signal(SignalKind::interrupt())
                 .map_err(|err| format!("Failed to install interrupt handler: {err}"))?;
             let sessions = sessions.clone();
             executor.native_spawn(async move {
+                let mut escalation = InterruptEscalation::default();
                 loop {
                     let _ = signal_stream.recv().await;
                     let cancellable_sessions = {
@@ -427,9 +757,35 @@ impl Sessions {
                             vec![]
                         }
                     };
-                    for session in cancellable_sessions {
+
+                    let still_cancelling = cancellable_sessions
+                        .iter()
+                        .any(|session| session.cancelled.poll_triggered());
+                    let action = escalation.interrupted(still_cancelling);
+
+                    for session in &cancellable_sessions {
                         session.cancel();
                     }
+                    match action {
+                        InterruptAction::Cancel => {}
+                        InterruptAction::KillChildren => {
+                            let killed = cancellable_sessions
+                                .iter()
+                                .filter_map(|session| session.workunit_store.session_id())
+                                .map(children::kill_session)
+                                .sum::<usize>();
+                            warn!(
+                                "Interrupted again while cancelling: killed {killed} running process \
+                                 groups. Interrupt again to log the state of the engine."
+                            );
+                        }
+                        InterruptAction::DumpState => {
+                            warn!(
+                                "Interrupted again while cancelling. {}",
+                                dump_state(&graph, &cancellable_sessions)
+                            );
+                        }
+                    }
                 }
             })
         };
@@ -485,6 +841,90 @@ impl Sessions {
     }
 }
 
+///
+/// The action to take in response to a keyboard interrupt: see `Sessions`.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum InterruptAction {
+    Cancel,
+    KillChildren,
+    DumpState,
+}
+
+///
+/// Tracks consecutive keyboard interrupts in order to escalate when cancellation is not completing.
+///
+#[derive(Default)]
+pub(crate) struct InterruptEscalation {
+    interrupts: usize,
+}
+
+impl InterruptEscalation {
+    ///
+    /// Records an interrupt, and returns the action to take for it. An interrupt escalates if an
+    /// earlier interrupt cancelled a Session which has not yet exited (`still_cancelling`), and
+    /// otherwise starts over.
+    ///
+    pub(crate) fn interrupted(&mut self, still_cancelling: bool) -> InterruptAction {
+        self.interrupts = if still_cancelling {
+            self.interrupts + 1
+        } else {
+            1
+        };
+        match self.interrupts {
+            1 => InterruptAction::Cancel,
+            2 => InterruptAction::KillChildren,
+            _ => InterruptAction::DumpState,
+        }
+    }
+}
+
+///
+/// Renders the running Nodes, live child processes, and running workunits of the given Sessions, in
+/// order to diagnose cancellation which is not completing.
+///
+fn dump_state(graph: &Graph<NodeKey>, sessions: &[Arc<SessionHandle>]) -> String {
+    let mut lines = vec!["State of the engine:".to_owned()];
+
+    let running_nodes = graph.running_nodes();
+    lines.push(format!("  Running nodes ({}):", running_nodes.len()));
+    for node in running_nodes.iter().take(STATE_DUMP_MAX_NODES) {
+        lines.push(format!("    {node}"));
+    }
+    if running_nodes.len() > STATE_DUMP_MAX_NODES {
+        lines.push(format!(
+            "    ... and {} more.",
+            running_nodes.len() - STATE_DUMP_MAX_NODES
+        ));
+    }
+
+    for session in sessions {
+        if let Some(session_id) = session.workunit_store.session_id() {
+            lines.push(format!(
+                "  Live process groups of session {}: {:?}",
+                session.build_id,
+                children::live_process_groups(session_id)
+            ));
+        }
+        lines.push(format!(
+            "  Running workunits of session {}:",
+            session.build_id
+        ));
+        for (duration, description) in session
+            .workunit_store
+            .straggling_workunits(Duration::ZERO)
+            .into_iter()
+            .rev()
+        {
+            lines.push(format!(
+                "    {} {description}",
+                format_workunit_duration_ms!(duration.as_millis())
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
 impl Drop for Sessions {
     fn drop(&mut self) {
         self.signal_task_handle.abort();
@@ -0,0 +1,24 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::session::{InterruptAction, InterruptEscalation};
+
+#[test]
+fn interrupts_escalate_while_cancelling() {
+    let mut escalation = InterruptEscalation::default();
+    assert_eq!(escalation.interrupted(false), InterruptAction::Cancel);
+    assert_eq!(escalation.interrupted(true), InterruptAction::KillChildren);
+    assert_eq!(escalation.interrupted(true), InterruptAction::DumpState);
+    // Further interrupts continue to dump state, rather than exiting.
+    assert_eq!(escalation.interrupted(true), InterruptAction::DumpState);
+}
+
+#[test]
+fn interrupts_start_over_once_cancellation_completes() {
+    let mut escalation = InterruptEscalation::default();
+    assert_eq!(escalation.interrupted(false), InterruptAction::Cancel);
+    assert_eq!(escalation.interrupted(true), InterruptAction::KillChildren);
+    // The cancelled Sessions exited, so a new interrupt is for a new Session.
+    assert_eq!(escalation.interrupted(false), InterruptAction::Cancel);
+    assert_eq!(escalation.interrupted(true), InterruptAction::KillChildren);
+}
@@ -0,0 +1,100 @@
+// Copyright 2023 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::convert::TryInto;
+use std::time::Duration;
+
+use futures::future::{self, FutureExt};
+use graph::LastObserved;
+use tokio::time;
+
+use crate::nodes::Root;
+use crate::python::{Failure, Value};
+use crate::session::Session;
+
+///
+/// A long-lived subscription to the values of a set of roots within a Session.
+///
+/// The first call to `next` computes all of the roots. Each subsequent call waits until the
+/// inputs of at least one root have been invalidated, waits for a debounce period (so that a burst
+/// of invalidation is coalesced into a single re-computation), and then re-computes all of the
+/// roots, returning only those whose values have changed since they were last returned.
+///
+pub struct Subscription {
+    roots: Vec<Root>,
+    last_observed: Vec<Option<LastObserved>>,
+    debounce: Duration,
+}
+
+impl Subscription {
+    pub fn new(roots: Vec<Root>, debounce: Duration) -> Subscription {
+        let last_observed = vec![None; roots.len()];
+        Subscription {
+            roots,
+            last_observed,
+            debounce,
+        }
+    }
+
+    pub fn roots(&self) -> &[Root] {
+        &self.roots
+    }
+
+    ///
+    /// Returns the indexes and new values of the roots which have changed since the previous call.
+    ///
+    /// Never returns an empty list: if the roots are invalidated but re-compute to identical
+    /// values, waits for the next invalidation.
+    ///
+    pub async fn next(&mut self, session: &Session) -> Vec<(usize, Result<Value, Failure>)> {
+        let context = session.graph_context();
+        let graph = &context.core.graph;
+        loop {
+            if self.last_observed.iter().any(|lo| lo.is_some()) {
+                // Wait for any of the roots to be invalidated, and then for the debounce period.
+                let _ = future::select_all(self.roots.iter().zip(self.last_observed.iter()).map(
+                    |(root, last_observed)| {
+                        graph
+                            .poll(root.clone().into(), *last_observed, None, &context)
+                            .boxed()
+                    },
+                ))
+                .await;
+                time::sleep(self.debounce).await;
+            }
+
+            // Re-request all of the roots, and retain only those which have changed.
+            let results = future::join_all(
+                self.roots
+                    .iter()
+                    .map(|root| graph.poll(root.clone().into(), None, None, &context)),
+            )
+            .await;
+            let mut changed = Vec::new();
+            for (index, (result, last_observed)) in results.into_iter().enumerate() {
+                if self.last_observed[index] == Some(last_observed) {
+                    continue;
+                }
+                self.last_observed[index] = Some(last_observed);
+                changed.push((
+                    index,
+                    result.map(|v| {
+                        v.try_into().unwrap_or_else(|e| {
+                            panic!("A Node implementation was ambiguous: {e:?}")
+                        })
+                    }),
+                ));
+            }
+            session.roots_extend(
+                self.roots
+                    .iter()
+                    .cloned()
+                    .zip(self.last_observed.iter().cloned())
+                    .collect(),
+            );
+            if !changed.is_empty() {
+                return changed;
+            }
+        }
+    }
+}
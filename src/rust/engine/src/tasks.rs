@@ -134,6 +134,8 @@ pub struct Task {
     pub masked_types: Vec<TypeId>,
     pub func: Function,
     pub cacheable: bool,
+    // If set, the results of the Task are persisted in the local cache, salted with this value.
+    pub persistent_cache_salt: Option<String>,
     pub display_info: DisplayInfo,
 }
 
@@ -194,6 +196,7 @@ impl Tasks {
         arg_types: Vec<(String, TypeId)>,
         masked_types: Vec<TypeId>,
         cacheable: bool,
+        persistent_cache_salt: Option<String>,
         name: String,
         desc: Option<String>,
         level: Level,
@@ -210,6 +213,7 @@ impl Tasks {
         self.preparing = Some(Task {
             id: RuleId::new(&name),
             cacheable,
+            persistent_cache_salt,
             product: return_type,
             side_effecting,
             engine_aware_return_type,
@@ -0,0 +1,59 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::time::Duration;
+
+use cache::PersistentCache;
+use grpc_util::prost::MessageExt;
+use hashing::Digest;
+use prost::Message;
+use protos::gen::pants::cache::{CacheKey, CacheKeyType, NodeTiming, NodeTimings};
+use workunit_store::TimingHistory;
+
+///
+/// The TimingHistory of a Core is persisted in its local cache (under a fixed key), so that the
+/// progress of runs can be estimated across restarts: see `WorkunitStore::progress_estimate`.
+///
+fn key() -> CacheKey {
+    CacheKey {
+        key_type: CacheKeyType::NodeTimings.into(),
+        digest: Some(Digest::of_bytes(b"node_timings").into()),
+    }
+}
+
+pub(crate) async fn load(local_cache: &PersistentCache) -> Result<TimingHistory, String> {
+    let Some(bytes) = local_cache.load(&key()).await? else {
+        return Ok(TimingHistory::default());
+    };
+    let timings =
+        NodeTimings::decode(bytes).map_err(|e| format!("Invalid persisted node timings: {e}"))?;
+    Ok(TimingHistory::from_entries(
+        timings.timings.into_iter().map(|timing| {
+            (
+                timing.key,
+                Duration::from_micros(timing.mean_micros),
+                timing.samples,
+            )
+        }),
+    ))
+}
+
+///
+/// Stores entries (as returned by `TimingHistory::entries`).
+///
+pub(crate) async fn store(
+    local_cache: &PersistentCache,
+    entries: Vec<(String, Duration, u64)>,
+) -> Result<(), String> {
+    let timings = NodeTimings {
+        timings: entries
+            .into_iter()
+            .map(|(key, mean, samples)| NodeTiming {
+                key,
+                mean_micros: mean.as_micros() as u64,
+                samples,
+            })
+            .collect(),
+    };
+    local_cache.store(&key(), timings.to_bytes()).await
+}
@@ -0,0 +1,112 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::path::PathBuf;
+
+use log::warn;
+use parking_lot::Mutex;
+use tempfile::TempDir;
+
+///
+/// A namespace for the temporary directories of a Session, which is created on first use, and
+/// which is removed (along with everything in it) when the Session ends.
+///
+/// Intrinsics which need temporary directories (for interactive processes, archive handling, or
+/// scratch space for materialization) create them beneath this namespace, so that they are removed
+/// even if the task which created them is cancelled or panics before it can clean up after itself.
+///
+pub struct SessionTmpDir {
+    root: PathBuf,
+    prefix: String,
+    dir: Mutex<Option<TempDir>>,
+}
+
+impl SessionTmpDir {
+    ///
+    /// Creates a namespace (lazily) beneath the given root directory, which may be relocated (for
+    /// example, onto a ramdisk) using `[GLOBAL].session_tmpdir`.
+    ///
+    pub fn new(root: PathBuf, build_id: &str) -> SessionTmpDir {
+        let build_id: String = build_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        SessionTmpDir {
+            root,
+            prefix: format!("pants-session-{build_id}-"),
+            dir: Mutex::new(None),
+        }
+    }
+
+    ///
+    /// Returns the path of the namespace, creating it if this is the first use.
+    ///
+    pub fn path(&self) -> Result<PathBuf, String> {
+        let mut dir = self.dir.lock();
+        if let Some(dir) = &*dir {
+            return Ok(dir.path().to_owned());
+        }
+        std::fs::create_dir_all(&self.root).map_err(|e| {
+            format!(
+                "Failed to create the session temporary directory root {}: {e}",
+                self.root.display()
+            )
+        })?;
+        let created = tempfile::Builder::new()
+            .prefix(&self.prefix)
+            .tempdir_in(&self.root)
+            .map_err(|e| {
+                format!(
+                    "Failed to create a session temporary directory in {}: {e}",
+                    self.root.display()
+                )
+            })?;
+        let path = created.path().to_owned();
+        *dir = Some(created);
+        Ok(path)
+    }
+
+    ///
+    /// Creates a new, uniquely named directory beneath the namespace. The directory is removed when
+    /// the returned TempDir is dropped, or when the Session ends, whichever comes first.
+    ///
+    pub fn create_dir(&self, prefix: &str) -> Result<TempDir, String> {
+        let path = self.path()?;
+        tempfile::Builder::new()
+            .prefix(prefix)
+            .tempdir_in(&path)
+            .map_err(|e| {
+                format!(
+                    "Failed to create a temporary directory in {}: {e}",
+                    path.display()
+                )
+            })
+    }
+
+    ///
+    /// Removes the namespace and everything in it, if it was created.
+    ///
+    pub fn cleanup(&self) {
+        if let Some(dir) = self.dir.lock().take() {
+            let path = dir.path().to_owned();
+            if let Err(e) = dir.close() {
+                warn!(
+                    "Failed to remove the session temporary directory {}: {e}",
+                    path.display()
+                );
+            }
+        }
+    }
+}
+
+impl Drop for SessionTmpDir {
+    fn drop(&mut self) {
+        self.cleanup();
+    }
+}
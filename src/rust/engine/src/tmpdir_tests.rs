@@ -0,0 +1,44 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use tempfile::TempDir;
+
+use crate::tmpdir::SessionTmpDir;
+
+#[test]
+fn created_lazily_beneath_root() {
+    let root = TempDir::new().unwrap();
+    let session_root = root.path().join("does/not/exist/yet");
+    let tmpdir = SessionTmpDir::new(session_root.clone(), "pants_run/1 2");
+    assert!(!session_root.exists());
+
+    let path = tmpdir.path().unwrap();
+    assert!(path.is_dir());
+    assert_eq!(path.parent().unwrap(), session_root);
+    let name = path.file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("pants-session-pants_run_1_2-"), "{name}");
+
+    // The same namespace is used for the rest of the Session.
+    assert_eq!(tmpdir.path().unwrap(), path);
+}
+
+#[test]
+fn removed_on_cleanup() {
+    let root = TempDir::new().unwrap();
+    let tmpdir = SessionTmpDir::new(root.path().to_owned(), "cleanup");
+
+    // Directories which are "leaked" by their creators are removed along with the namespace.
+    let path = tmpdir.path().unwrap();
+    let leaked = tmpdir.create_dir("leaked-").unwrap().into_path();
+    std::fs::write(leaked.join("file"), b"European Burmese").unwrap();
+    assert!(leaked.starts_with(&path));
+
+    tmpdir.cleanup();
+    assert!(!path.exists());
+
+    // A new namespace is created if the directory is used again.
+    let recreated = tmpdir.path().unwrap();
+    assert!(recreated.is_dir());
+    std::mem::drop(tmpdir);
+    assert!(!recreated.exists());
+}
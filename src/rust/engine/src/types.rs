@@ -8,6 +8,7 @@ pub struct Types {
     pub file_digest: TypeId,
     pub snapshot: TypeId,
     pub paths: TypeId,
+    pub path_digests: TypeId,
     pub path_metadata_request: TypeId,
     pub path_metadata_result: TypeId,
     pub file_content: TypeId,
@@ -28,8 +29,10 @@ pub struct Types {
     pub process_config_from_environment: TypeId,
     pub process_result: TypeId,
     pub process_result_metadata: TypeId,
+    pub sharded_process_result: TypeId,
     pub coroutine: TypeId,
     pub session_values: TypeId,
+    pub complete_environment_vars: TypeId,
     pub run_id: TypeId,
     pub interactive_process: TypeId,
     pub interactive_process_result: TypeId,
@@ -38,5 +41,13 @@ pub struct Types {
     pub docker_resolve_image_result: TypeId,
     pub parsed_python_deps_result: TypeId,
     pub parsed_javascript_deps_result: TypeId,
+    pub parsed_css_deps_result: TypeId,
+    pub parsed_go_deps_result: TypeId,
+    pub parsed_jvm_deps_result: TypeId,
+    pub parsed_dockerfile_deps_result: TypeId,
+    pub parsed_shell_deps_result: TypeId,
+    pub parsed_deps_batch_result: TypeId,
     pub deps_request: TypeId,
+    pub address_family_dirs: TypeId,
+    pub test_results_summary: TypeId,
 }
@@ -0,0 +1,154 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+///
+/// Captures a stream of output (generally the stdout or stderr of a process) while holding at most
+/// a fixed number of bytes in memory.
+///
+/// The most recent `limit` bytes are kept in a ring buffer. Once the limit is exceeded, the oldest
+/// bytes are evicted to a spill file, which is only created if it is needed. When the capture is
+/// finished the spill file contains the complete output, so the caller can store it without ever
+/// having held all of it in memory.
+///
+#[derive(Debug)]
+pub struct OutputCapture {
+    limit: Option<usize>,
+    tail: VecDeque<u8>,
+    spill_path: Option<PathBuf>,
+    spill: Option<File>,
+    spilled_bytes: u64,
+}
+
+impl OutputCapture {
+    ///
+    /// Creates a capture which keeps at most `limit` bytes in memory, and spills older output to
+    /// a file at `spill_path`.
+    ///
+    pub fn new(limit: usize, spill_path: PathBuf) -> OutputCapture {
+        OutputCapture {
+            limit: Some(limit),
+            tail: VecDeque::with_capacity(std::cmp::min(limit, 8192)),
+            spill_path: Some(spill_path),
+            spill: None,
+            spilled_bytes: 0,
+        }
+    }
+
+    ///
+    /// Creates a capture which keeps all output in memory. Only appropriate for output which is
+    /// known to be small.
+    ///
+    pub fn unbounded() -> OutputCapture {
+        OutputCapture {
+            limit: None,
+            tail: VecDeque::with_capacity(8192),
+            spill_path: None,
+            spill: None,
+            spilled_bytes: 0,
+        }
+    }
+
+    pub fn extend(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let limit = if let Some(limit) = self.limit {
+            limit
+        } else {
+            self.tail.extend(bytes);
+            return Ok(());
+        };
+
+        let overflow = (self.tail.len() + bytes.len()).saturating_sub(limit);
+        if overflow > 0 {
+            self.open_spill()?;
+            let spill = self.spill.as_mut().unwrap();
+            // Evict from the buffered tail first, and then (if the new bytes alone exceed the
+            // limit) from the front of the new bytes.
+            let from_tail = std::cmp::min(overflow, self.tail.len());
+            let (first, second) = self.tail.as_slices();
+            let first_len = std::cmp::min(from_tail, first.len());
+            spill.write_all(&first[..first_len])?;
+            spill.write_all(&second[..from_tail - first_len])?;
+            let from_bytes = overflow - from_tail;
+            spill.write_all(&bytes[..from_bytes])?;
+            self.tail.drain(..from_tail);
+            self.tail.extend(&bytes[from_bytes..]);
+            self.spilled_bytes += overflow as u64;
+        } else {
+            self.tail.extend(bytes);
+        }
+        Ok(())
+    }
+
+    fn open_spill(&mut self) -> io::Result<()> {
+        if self.spill.is_none() {
+            let path = self
+                .spill_path
+                .as_ref()
+                .expect("A bounded OutputCapture always has a spill path.");
+            self.spill = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(path)?,
+            );
+        }
+        Ok(())
+    }
+
+    /// True if any output has been evicted from memory to the spill file.
+    pub fn overflowed(&self) -> bool {
+        self.spilled_bytes > 0
+    }
+
+    /// The total number of bytes which have been captured.
+    pub fn total_bytes(&self) -> u64 {
+        self.spilled_bytes + self.tail.len() as u64
+    }
+
+    ///
+    /// Completes the capture.
+    ///
+    /// If the capture overflowed, the tail is appended to the spill file, so that the file contains
+    /// the complete output.
+    ///
+    pub fn finish(mut self) -> io::Result<CapturedOutput> {
+        let tail: Vec<u8> = self.tail.drain(..).collect();
+        let spill_path = if let Some(mut spill) = self.spill.take() {
+            spill.write_all(&tail)?;
+            spill.flush()?;
+            self.spill_path.take()
+        } else {
+            None
+        };
+        Ok(CapturedOutput {
+            total_bytes: self.spilled_bytes + tail.len() as u64,
+            tail,
+            spill_path,
+        })
+    }
+}
+
+impl Drop for OutputCapture {
+    fn drop(&mut self) {
+        // If the capture was not finished, nothing will consume the spill file.
+        if self.spill.take().is_some() {
+            if let Some(spill_path) = &self.spill_path {
+                let _ = std::fs::remove_file(spill_path);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CapturedOutput {
+    /// The most recent output, which is at most the limit of the capture in length.
+    pub tail: Vec<u8>,
+    /// If the capture overflowed, the path of a file containing the complete output.
+    pub spill_path: Option<PathBuf>,
+    pub total_bytes: u64,
+}
@@ -0,0 +1,80 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use tempfile::TempDir;
+
+use crate::OutputCapture;
+
+#[test]
+fn unbounded_keeps_all_output() {
+    let mut capture = OutputCapture::unbounded();
+    capture.extend(b"hello ").unwrap();
+    capture.extend(b"world").unwrap();
+    assert!(!capture.overflowed());
+    assert_eq!(capture.total_bytes(), 11);
+
+    let output = capture.finish().unwrap();
+    assert_eq!(output.tail, b"hello world");
+    assert_eq!(output.spill_path, None);
+    assert_eq!(output.total_bytes, 11);
+}
+
+#[test]
+fn output_within_limit_is_not_spilled() {
+    let dir = TempDir::new().unwrap();
+    let spill_path = dir.path().join("stdout");
+    let mut capture = OutputCapture::new(8, spill_path.clone());
+    capture.extend(b"1234").unwrap();
+    capture.extend(b"5678").unwrap();
+    assert!(!capture.overflowed());
+
+    let output = capture.finish().unwrap();
+    assert_eq!(output.tail, b"12345678");
+    assert_eq!(output.spill_path, None);
+    assert!(!spill_path.exists());
+}
+
+#[test]
+fn overflow_spills_oldest_output() {
+    let dir = TempDir::new().unwrap();
+    let spill_path = dir.path().join("stdout");
+    let mut capture = OutputCapture::new(4, spill_path.clone());
+    // Each write evicts part of the buffered tail, so that the ring buffer wraps around.
+    for chunk in [b"abc".as_slice(), b"def", b"gh", b"i"] {
+        capture.extend(chunk).unwrap();
+    }
+    assert!(capture.overflowed());
+    assert_eq!(capture.total_bytes(), 9);
+
+    let output = capture.finish().unwrap();
+    assert_eq!(output.tail, b"fghi");
+    assert_eq!(output.total_bytes, 9);
+    assert_eq!(output.spill_path.as_ref(), Some(&spill_path));
+    assert_eq!(std::fs::read(&spill_path).unwrap(), b"abcdefghi");
+}
+
+#[test]
+fn writes_larger_than_limit_are_spilled() {
+    let dir = TempDir::new().unwrap();
+    let spill_path = dir.path().join("stderr");
+    let mut capture = OutputCapture::new(3, spill_path.clone());
+    capture.extend(b"ab").unwrap();
+    capture.extend(b"cdefgh").unwrap();
+    assert_eq!(capture.total_bytes(), 8);
+
+    let output = capture.finish().unwrap();
+    assert_eq!(output.tail, b"fgh");
+    assert_eq!(std::fs::read(&spill_path).unwrap(), b"abcdefgh");
+}
+
+#[test]
+fn unfinished_capture_removes_spill_file() {
+    let dir = TempDir::new().unwrap();
+    let spill_path = dir.path().join("stdout");
+    let mut capture = OutputCapture::new(2, spill_path.clone());
+    capture.extend(b"abcd").unwrap();
+    assert!(spill_path.exists());
+
+    std::mem::drop(capture);
+    assert!(!spill_path.exists());
+}
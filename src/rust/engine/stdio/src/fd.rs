@@ -0,0 +1,46 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Conversions between the file descriptors which are handed to the engine by Python and Files.
+
+use std::fs::File;
+use std::io::IsTerminal;
+
+pub use std::os::unix::io::RawFd;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+///
+/// Materializes a File for the given file descriptor, which must be passed to `forget` rather than
+/// being dropped (which would close the descriptor).
+///
+pub(crate) unsafe fn borrow(fd: RawFd) -> File {
+    File::from_raw_fd(fd)
+}
+
+///
+/// Forgets a File created by `borrow` without closing it.
+///
+pub(crate) fn forget(file: File) {
+    let _ = file.into_raw_fd();
+}
+
+///
+/// Returns true if the given file descriptor refers to a terminal.
+///
+pub fn is_terminal(fd: RawFd) -> bool {
+    let file = unsafe { borrow(fd) };
+    let is_terminal = file.is_terminal();
+    forget(file);
+    is_terminal
+}
+
+///
+/// Clones the given file descriptor into an owned File which can be closed independently.
+///
+pub fn try_clone(fd: RawFd) -> std::io::Result<File> {
+    let underlying_file = unsafe { borrow(fd) };
+    let cloned = underlying_file.try_clone();
+    // Drop the temporarily materialized file now that we've duped it.
+    forget(underlying_file);
+    cloned
+}
@@ -1,8 +1,18 @@
 // Copyright 2018 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
+mod capture;
+#[cfg(test)]
+mod capture_tests;
+mod fd;
+mod log_filters;
+#[cfg(test)]
+mod log_filters_tests;
 mod term;
 
+pub use capture::{CapturedOutput, OutputCapture};
+pub use fd::{is_terminal, try_clone as try_clone_fd, RawFd};
+pub use log_filters::LogFilters;
 pub use term::{TermReadDestination, TermWriteDestination, TryCloneAsFile};
 
 use std::cell::RefCell;
@@ -10,9 +20,9 @@ use std::fmt;
 use std::fs::File;
 use std::future::Future;
 use std::io::{Read, Write};
-use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::sync::Arc;
 
+use arc_swap::ArcSwapOption;
 use parking_lot::Mutex;
 use tokio::task_local;
 
@@ -23,6 +33,9 @@ use tokio::task_local;
 ///
 #[derive(Debug)]
 struct Console {
+    stdin_fd: RawFd,
+    stdout_fd: RawFd,
+    stderr_fd: RawFd,
     stdin_handle: Option<File>,
     stdout_handle: Option<File>,
     stderr_handle: Option<File>,
@@ -33,12 +46,15 @@ impl Console {
     fn new(stdin_fd: RawFd, stdout_fd: RawFd, stderr_fd: RawFd) -> Console {
         let (stdin, stdout, stderr) = unsafe {
             (
-                File::from_raw_fd(stdin_fd),
-                File::from_raw_fd(stdout_fd),
-                File::from_raw_fd(stderr_fd),
+                fd::borrow(stdin_fd),
+                fd::borrow(stdout_fd),
+                fd::borrow(stderr_fd),
             )
         };
         Console {
+            stdin_fd,
+            stdout_fd,
+            stderr_fd,
             stdin_handle: Some(stdin),
             stdout_handle: Some(stdout),
             stderr_handle: Some(stderr),
@@ -63,7 +79,7 @@ impl Console {
     }
 
     fn stdin_as_raw_fd(&self) -> RawFd {
-        self.stdin_handle.as_ref().unwrap().as_raw_fd()
+        self.stdin_fd
     }
 
     fn stderr_set_use_color(&mut self, use_color: bool) {
@@ -71,20 +87,53 @@ impl Console {
     }
 
     fn stdout_as_raw_fd(&self) -> RawFd {
-        self.stdout_handle.as_ref().unwrap().as_raw_fd()
+        self.stdout_fd
     }
 
     fn stderr_as_raw_fd(&self) -> RawFd {
-        self.stderr_handle.as_ref().unwrap().as_raw_fd()
+        self.stderr_fd
     }
 }
 
 impl Drop for Console {
     fn drop(&mut self) {
         // "Forget" about our file handles without closing them.
-        self.stdin_handle.take().unwrap().into_raw_fd();
-        self.stdout_handle.take().unwrap().into_raw_fd();
-        self.stderr_handle.take().unwrap().into_raw_fd();
+        fd::forget(self.stdin_handle.take().unwrap());
+        fd::forget(self.stdout_handle.take().unwrap());
+        fd::forget(self.stderr_handle.take().unwrap());
+    }
+}
+
+///
+/// A "borrowed" file handle to which structured events for a run are written as lines of JSON. Like
+/// a Console, it forgets about the file handle rather than closing it when it is dropped.
+///
+#[derive(Debug)]
+struct Events {
+    handle: Option<File>,
+}
+
+impl Events {
+    fn new(events_fd: RawFd) -> Events {
+        Events {
+            handle: Some(unsafe { fd::borrow(events_fd) }),
+        }
+    }
+
+    fn write_event(&mut self, event: &[u8]) -> Result<(), std::io::Error> {
+        let mut handle = self.handle.as_ref().unwrap();
+        let mut line = Vec::with_capacity(event.len() + 1);
+        line.extend_from_slice(event);
+        line.push(b'\n');
+        handle.write_all(&line)?;
+        handle.flush()
+    }
+}
+
+impl Drop for Events {
+    fn drop(&mut self) {
+        // "Forget" about our file handle without closing it.
+        fd::forget(self.handle.take().unwrap());
     }
 }
 
@@ -123,14 +172,69 @@ impl fmt::Debug for InnerDestination {
 }
 
 #[derive(Debug)]
-pub struct Destination(Mutex<InnerDestination>);
+pub struct Destination {
+    inner: Mutex<InnerDestination>,
+    // NB: Consulted for every log call, so swapped atomically rather than locked.
+    log_filters: ArcSwapOption<LogFilters>,
+    events: Mutex<Option<Events>>,
+}
 
 impl Destination {
+    fn new(inner: InnerDestination) -> Destination {
+        Destination {
+            inner: Mutex::new(inner),
+            log_filters: ArcSwapOption::empty(),
+            events: Mutex::default(),
+        }
+    }
+
     ///
-    /// Clears the Destination, setting it back to Logging.
+    /// Clears the Destination, setting it back to Logging (and clearing any log filters and events
+    /// handle).
     ///
     pub fn console_clear(&self) {
-        *self.0.lock() = InnerDestination::Logging;
+        *self.inner.lock() = InnerDestination::Logging;
+        self.log_filters.store(None);
+        *self.events.lock() = None;
+    }
+
+    ///
+    /// Sets log filters which override the global log filters for logging which occurs while this
+    /// Destination is active.
+    ///
+    pub fn set_log_filters(&self, log_filters: Option<LogFilters>) {
+        self.log_filters.store(log_filters.map(Arc::new));
+    }
+
+    pub fn log_filters(&self) -> Option<Arc<LogFilters>> {
+        self.log_filters.load_full()
+    }
+
+    ///
+    /// Sets a (borrowed) file handle to which structured events will be written while this
+    /// Destination is active. Unlike stdio, events are unaffected by Exclusive access.
+    ///
+    pub fn set_events_fd(&self, events_fd: Option<RawFd>) {
+        *self.events.lock() = events_fd.map(Events::new);
+    }
+
+    ///
+    /// Writes the given event (which should be a single line of JSON) if an events handle is set,
+    /// and otherwise ignores it.
+    ///
+    /// Events are a best effort side channel: if writing fails, the handle is dropped and later
+    /// events are ignored.
+    ///
+    pub fn write_event(&self, event: &[u8]) {
+        let mut events = self.events.lock();
+        if let Some(ref mut handle) = *events {
+            if let Err(e) = handle.write_event(event) {
+                *events = None;
+                // Release the lock before logging.
+                std::mem::drop(events);
+                log::debug!("Failed to write event, no further events will be sent: {e}");
+            }
+        }
     }
 
     ///
@@ -150,7 +254,7 @@ impl Destination {
         ),
         String,
     > {
-        let mut destination = self.0.lock();
+        let mut destination = self.inner.lock();
         let stderr_use_color = match *destination {
             InnerDestination::Console(Console {
                 stderr_use_color, ..
@@ -180,7 +284,7 @@ impl Destination {
     /// Clears Exclusive access and restores the Console.
     ///
     fn exclusive_clear(&self, console: Console) {
-        let mut destination = self.0.lock();
+        let mut destination = self.inner.lock();
         if matches!(*destination, InnerDestination::Exclusive { .. }) {
             *destination = InnerDestination::Console(console);
         } else {
@@ -193,7 +297,7 @@ impl Destination {
     /// Set whether to use color for stderr.
     ///
     pub fn stderr_set_use_color(&self, use_color: bool) {
-        let mut destination = self.0.lock();
+        let mut destination = self.inner.lock();
         if let InnerDestination::Console(ref mut console) = *destination {
             console.stderr_set_use_color(use_color);
         }
@@ -203,7 +307,7 @@ impl Destination {
     /// True if color should be used with stderr.
     ///
     pub fn stderr_use_color(&self) -> bool {
-        let destination = self.0.lock();
+        let destination = self.inner.lock();
         match *destination {
             InnerDestination::Console(ref console) => console.stderr_use_color,
             InnerDestination::Exclusive {
@@ -217,7 +321,7 @@ impl Destination {
     /// Read from stdin if it is available on the current Destination.
     ///
     pub fn read_stdin(&self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let mut destination = self.0.lock();
+        let mut destination = self.inner.lock();
         match *destination {
             InnerDestination::Console(ref mut console) => console.read_stdin(buf),
             InnerDestination::Exclusive { .. } => Err(std::io::Error::new(
@@ -236,7 +340,7 @@ impl Destination {
     /// available.
     ///
     pub fn write_stdout(&self, content: &[u8]) {
-        let mut destination = self.0.lock();
+        let mut destination = self.inner.lock();
         let error_res = match *destination {
             InnerDestination::Console(ref mut console) => {
                 // Write to the underlying Console.
@@ -273,7 +377,7 @@ impl Destination {
     /// written stdio might result in infinite recursion.
     ///
     pub fn write_stderr_raw(&self, content: &[u8]) -> Result<(), String> {
-        let mut destination = self.0.lock();
+        let mut destination = self.inner.lock();
         match *destination {
             InnerDestination::Console(ref mut console) => {
                 console.write_stderr(content).map_err(|e| e.to_string())
@@ -293,7 +397,7 @@ impl Destination {
     /// available.
     ///
     pub fn write_stderr(&self, content: &[u8]) {
-        let mut destination = self.0.lock();
+        let mut destination = self.inner.lock();
         let error_res = match *destination {
             InnerDestination::Console(ref mut console) => {
                 // Write to the underlying Console.
@@ -339,7 +443,7 @@ impl Destination {
     /// time the caller interacts with it.
     ///
     pub fn stdin_as_raw_fd(&self) -> Result<RawFd, String> {
-        match &*self.0.lock() {
+        match &*self.inner.lock() {
       InnerDestination::Console(console) => Ok(console.stdin_as_raw_fd()),
       InnerDestination::Logging => {
         Err("No associated file descriptor for the Logging destination".to_owned())
@@ -356,7 +460,7 @@ impl Destination {
     /// time the caller interacts with it.
     ///
     pub fn stdout_as_raw_fd(&self) -> Result<RawFd, String> {
-        match &*self.0.lock() {
+        match &*self.inner.lock() {
       InnerDestination::Console(console) => Ok(console.stdout_as_raw_fd()),
       InnerDestination::Logging => {
         Err("No associated file descriptor for the Logging destination".to_owned())
@@ -373,7 +477,7 @@ impl Destination {
     /// time the caller interacts with it.
     ///
     pub fn stderr_as_raw_fd(&self) -> Result<RawFd, String> {
-        match &*self.0.lock() {
+        match &*self.inner.lock() {
       InnerDestination::Console(console) => Ok(console.stderr_as_raw_fd()),
       InnerDestination::Logging => {
         Err("No associated file descriptor for the Logging destination".to_owned())
@@ -389,7 +493,7 @@ thread_local! {
   ///
   /// See set_thread_destination.
   ///
-  static THREAD_DESTINATION: RefCell<Arc<Destination>> = RefCell::new(Arc::new(Destination(Mutex::new(InnerDestination::Logging))))
+  static THREAD_DESTINATION: RefCell<Arc<Destination>> = RefCell::new(Arc::new(Destination::new(InnerDestination::Logging)))
 }
 
 // Note: The behavior of this task_local! invocation is affected by the `tokio_no_const_thread_local`
@@ -408,8 +512,8 @@ pub fn new_console_destination(
     stdout_fd: RawFd,
     stderr_fd: RawFd,
 ) -> Arc<Destination> {
-    Arc::new(Destination(Mutex::new(InnerDestination::Console(
-        Console::new(stdin_fd, stdout_fd, stderr_fd),
+    Arc::new(Destination::new(InnerDestination::Console(Console::new(
+        stdin_fd, stdout_fd, stderr_fd,
     ))))
 }
 
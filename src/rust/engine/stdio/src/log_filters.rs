@@ -0,0 +1,49 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use log::LevelFilter;
+
+///
+/// Log level filters which apply to the logging of a particular Destination (and thus, generally,
+/// to a particular Session), and which override the global filters of the logger.
+///
+/// Targets match either exactly, or as a prefix of a more specific target: `process_execution`
+/// matches both `process_execution` and `process_execution::remote` (and similarly for `.`
+/// separated Python logger names). The most specific matching target wins.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogFilters {
+    /// The level for targets which do not match any of `levels_by_target`.
+    pub level: Option<LevelFilter>,
+    pub levels_by_target: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilters {
+    pub fn new(level: Option<LevelFilter>, levels_by_target: Vec<(String, LevelFilter)>) -> Self {
+        LogFilters {
+            level,
+            levels_by_target,
+        }
+    }
+
+    ///
+    /// Returns the level filter for the given target, or None if these filters do not apply to
+    /// the target (in which case the global filters should be used).
+    ///
+    pub fn level_for(&self, target: &str) -> Option<LevelFilter> {
+        self.levels_by_target
+            .iter()
+            .filter(|(prefix, _)| Self::matches(prefix, target))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+            .or(self.level)
+    }
+
+    fn matches(prefix: &str, target: &str) -> bool {
+        match target.strip_prefix(prefix) {
+            Some("") => true,
+            Some(rest) => rest.starts_with("::") || rest.starts_with('.'),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use log::LevelFilter;
+
+use crate::{get_destination, LogFilters};
+
+fn filters(level: Option<LevelFilter>, levels_by_target: &[(&str, LevelFilter)]) -> LogFilters {
+    LogFilters::new(
+        level,
+        levels_by_target
+            .iter()
+            .map(|(target, level)| (target.to_string(), *level))
+            .collect(),
+    )
+}
+
+#[test]
+fn targets_match_exactly_or_as_prefixes() {
+    let filters = filters(
+        None,
+        &[
+            ("process_execution", LevelFilter::Debug),
+            ("pants.engine", LevelFilter::Trace),
+        ],
+    );
+
+    assert_eq!(
+        filters.level_for("process_execution"),
+        Some(LevelFilter::Debug)
+    );
+    assert_eq!(
+        filters.level_for("process_execution::remote"),
+        Some(LevelFilter::Debug)
+    );
+    assert_eq!(
+        filters.level_for("pants.engine.rules"),
+        Some(LevelFilter::Trace)
+    );
+    // Prefixes only match at a separator.
+    assert_eq!(filters.level_for("process_executor"), None);
+    assert_eq!(filters.level_for("pants.engineering"), None);
+    assert_eq!(filters.level_for("pants"), None);
+}
+
+#[test]
+fn most_specific_target_wins() {
+    let filters = filters(
+        None,
+        &[
+            ("process_execution::remote", LevelFilter::Trace),
+            ("process_execution", LevelFilter::Warn),
+        ],
+    );
+
+    assert_eq!(
+        filters.level_for("process_execution::remote::cache"),
+        Some(LevelFilter::Trace)
+    );
+    assert_eq!(
+        filters.level_for("process_execution::local"),
+        Some(LevelFilter::Warn)
+    );
+}
+
+#[test]
+fn unmatched_targets_use_level() {
+    let filters = filters(
+        Some(LevelFilter::Error),
+        &[("workunit_store", LevelFilter::Debug)],
+    );
+
+    assert_eq!(
+        filters.level_for("workunit_store"),
+        Some(LevelFilter::Debug)
+    );
+    assert_eq!(filters.level_for("store"), Some(LevelFilter::Error));
+}
+
+#[test]
+fn destination_filters_are_set_and_cleared() {
+    let destination = get_destination();
+    assert_eq!(destination.log_filters(), None);
+
+    let log_filters = filters(None, &[("store", LevelFilter::Debug)]);
+    destination.set_log_filters(Some(log_filters.clone()));
+    assert_eq!(destination.log_filters().as_deref(), Some(&log_filters));
+
+    destination.console_clear();
+    assert_eq!(destination.log_filters(), None);
+}
@@ -2,11 +2,12 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 use std::fs::File;
 use std::io::{Read, Write};
-use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::io::AsRawFd;
 use std::sync::Arc;
 
 use parking_lot::Mutex;
 
+use crate::fd::{self, RawFd};
 use crate::{Console, Destination};
 
 ///
@@ -68,9 +69,15 @@ impl Read for TermReadDestination {
     }
 }
 
+impl TermReadDestination {
+    fn raw_fd(&self) -> RawFd {
+        self.0.console.lock().as_ref().unwrap().stdin_as_raw_fd()
+    }
+}
+
 impl AsRawFd for TermReadDestination {
     fn as_raw_fd(&self) -> RawFd {
-        self.0.console.lock().as_ref().unwrap().stdin_as_raw_fd()
+        self.raw_fd()
     }
 }
 
@@ -99,8 +106,8 @@ impl Write for TermWriteDestination {
     }
 }
 
-impl AsRawFd for TermWriteDestination {
-    fn as_raw_fd(&self) -> RawFd {
+impl TermWriteDestination {
+    fn raw_fd(&self) -> RawFd {
         if self.is_stderr {
             self.destination
                 .console
@@ -119,6 +126,12 @@ impl AsRawFd for TermWriteDestination {
     }
 }
 
+impl AsRawFd for TermWriteDestination {
+    fn as_raw_fd(&self) -> RawFd {
+        self.raw_fd()
+    }
+}
+
 impl Drop for TermDestination {
     fn drop(&mut self) {
         self.destination
@@ -136,15 +149,14 @@ pub trait TryCloneAsFile {
     fn try_clone_as_file(&self) -> std::io::Result<File>;
 }
 
-impl<T: AsRawFd> TryCloneAsFile for T {
+impl TryCloneAsFile for TermReadDestination {
     fn try_clone_as_file(&self) -> std::io::Result<File> {
-        let raw_fd = self.as_raw_fd();
-        unsafe {
-            let underlying_file = File::from_raw_fd(raw_fd);
-            let cloned = underlying_file.try_clone()?;
-            // Drop the temporarily materialized file now that we've duped it.
-            let _ = underlying_file.into_raw_fd();
-            Ok(cloned)
-        }
+        fd::try_clone(self.raw_fd())
+    }
+}
+
+impl TryCloneAsFile for TermWriteDestination {
+    fn try_clone_as_file(&self) -> std::io::Result<File> {
+        fd::try_clone(self.raw_fd())
     }
 }
@@ -4,7 +4,8 @@
 use std::collections::HashMap;
 use std::env;
 use std::future::Future;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 
 use futures::future::FutureExt;
@@ -13,6 +14,19 @@ use parking_lot::Mutex;
 use tokio::runtime::{Builder, Handle, Runtime};
 use tokio::task::{Id, JoinError, JoinHandle, JoinSet};
 
+#[cfg(test)]
+mod tests;
+
+/// If set, the address on which to serve the tokio-console protocol: see `enable_console`.
+const CONSOLE_ADDRESS_ENV_VAR: &str = "PANTS_TOKIO_CONSOLE_ADDRESS";
+
+/// Whether spawned tasks should be named for instrumentation: set by `enable_console`.
+static INSTRUMENT_TASKS: AtomicBool = AtomicBool::new(false);
+
+/// The outcome of enabling the console, which (like the global tracing subscriber which it
+/// installs) happens at most once per process, regardless of how many Executors are created.
+static CONSOLE: OnceLock<Result<(), String>> = OnceLock::new();
+
 /// Copy our (thread-local or task-local) stdio destination and current workunit parent into
 /// the task. The former ensures that when a pantsd thread kicks off a future, any stdio done
 /// by it ends up in the pantsd log as we expect. The latter ensures that when a new workunit
@@ -30,6 +44,129 @@ fn future_with_correct_context<F: Future>(future: F) -> impl Future<Output = F::
     })
 }
 
+///
+/// Enables the console on the given address the first time that it is called: later calls return
+/// the outcome of the first.
+///
+fn init_console(address: &str) -> Result<(), String> {
+    CONSOLE.get_or_init(|| enable_console(address)).clone()
+}
+
+///
+/// Serves the tokio-console protocol on the given address, and enables naming of spawned tasks
+/// after the Pool, Node and workunit which spawned them. A console attached to a wedged process
+/// can then show which tasks are stalled, and for how long, without sampling thread dumps.
+///
+/// NB: Instrumentation has a cost for every spawned task, so it is only compiled in when the
+/// `console` feature is enabled, and is then only enabled on request.
+///
+#[cfg(feature = "console")]
+fn enable_console(address: &str) -> Result<(), String> {
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    let address: std::net::SocketAddr = address
+        .parse()
+        .map_err(|e| format!("Invalid {CONSOLE_ADDRESS_ENV_VAR} `{address}`: {e}"))?;
+    let layer = console_subscriber::ConsoleLayer::builder()
+        .server_addr(address)
+        .spawn();
+    if let Err(e) = tracing_subscriber::registry().with(layer).try_init() {
+        log::warn!("Failed to install the tokio-console subscriber: {e}");
+        return Ok(());
+    }
+    INSTRUMENT_TASKS.store(true, Ordering::Relaxed);
+    log::info!("Serving tokio-console on {address}.");
+    Ok(())
+}
+
+#[cfg(not(feature = "console"))]
+fn enable_console(_address: &str) -> Result<(), String> {
+    log::warn!(
+        "{CONSOLE_ADDRESS_ENV_VAR} is set, but tokio-console support was not compiled in: build the \
+         engine with the `tokio-console` feature to use it."
+    );
+    Ok(())
+}
+
+///
+/// Spawns the given Future on the given Handle, with the given name if it is set.
+///
+#[cfg(feature = "console")]
+#[track_caller]
+fn spawn_on<F>(handle: &Handle, name: Option<String>, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match name {
+        Some(name) => tokio::task::Builder::new()
+            .name(&name)
+            .spawn_on(future, handle)
+            .expect("Spawning a named task cannot fail."),
+        None => handle.spawn(future),
+    }
+}
+
+#[cfg(not(feature = "console"))]
+#[track_caller]
+fn spawn_on<F>(handle: &Handle, _name: Option<String>, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    handle.spawn(future)
+}
+
+///
+/// Spawns the given blocking function on the given Handle, with the given name if it is set.
+///
+#[cfg(feature = "console")]
+#[track_caller]
+fn spawn_blocking_on<F, R>(handle: &Handle, name: Option<String>, f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    match name {
+        Some(name) => tokio::task::Builder::new()
+            .name(&name)
+            .spawn_blocking_on(f, handle)
+            .expect("Spawning a named task cannot fail."),
+        None => handle.spawn_blocking(f),
+    }
+}
+
+#[cfg(not(feature = "console"))]
+#[track_caller]
+fn spawn_blocking_on<F, R>(handle: &Handle, _name: Option<String>, f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    handle.spawn_blocking(f)
+}
+
+///
+/// If task instrumentation is enabled, returns a name for a task spawned on the given Pool, which
+/// includes the (optional) given name and the id of the workunit which spawned the task.
+///
+fn task_name(pool: Pool, name: impl FnOnce() -> Option<String>) -> Option<String> {
+    if !INSTRUMENT_TASKS.load(Ordering::Relaxed) {
+        return None;
+    }
+    let mut task_name = pool.name().to_owned();
+    if let Some(name) = name() {
+        task_name.push_str(&format!(": {name}"));
+    }
+    if let Some(parent_id) =
+        workunit_store::get_workunit_store_handle().and_then(|handle| handle.parent_id)
+    {
+        task_name.push_str(&format!(" (workunit {parent_id})"));
+    }
+    Some(task_name)
+}
+
 ///
 /// Executors come in two flavors:
 /// * "borrowed"
@@ -44,10 +181,116 @@ fn future_with_correct_context<F: Future>(future: F) -> impl Future<Output = F::
 ///       Additionally, the explicit shutdown methods can be used to shut down the Executor for all
 ///       clones.
 ///
+/// An owned Executor may additionally own dedicated Runtimes for network IO and for blocking
+/// filesystem IO (see `ExecutorOptions`), so that neither can starve the other (or `@rule` logic).
+/// When they are not configured, the corresponding methods use the main Runtime.
+///
 #[derive(Debug, Clone)]
 pub struct Executor {
-    runtime: Arc<Mutex<Option<Runtime>>>,
+    runtime: Arc<Mutex<Option<Runtimes>>>,
     handle: Handle,
+    io_handle: Handle,
+    fs_handle: Handle,
+    in_flight: Arc<InFlight>,
+}
+
+#[derive(Debug)]
+struct Runtimes {
+    main: Runtime,
+    io: Option<Runtime>,
+    fs: Option<Runtime>,
+}
+
+///
+/// Options for the dedicated Runtimes of an owned Executor.
+///
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExecutorOptions {
+    /// If set, the number of worker threads of a dedicated Runtime for network IO (such as gRPC
+    /// streams), which is used by `Executor::spawn_io`.
+    pub io_threads: Option<usize>,
+    /// If set, the maximum number of threads of a dedicated pool for blocking filesystem IO, which is
+    /// used by `Executor::spawn_blocking_fs`.
+    pub fs_blocking_threads: Option<usize>,
+}
+
+///
+/// The pools that an Executor spawns work onto.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Pool {
+    /// Async tasks: see `Executor::spawn`.
+    Async,
+    /// Blocking functions: see `Executor::spawn_blocking`.
+    Blocking,
+    /// Async network IO tasks: see `Executor::spawn_io`.
+    Io,
+    /// Blocking filesystem IO functions: see `Executor::spawn_blocking_fs`.
+    FsBlocking,
+}
+
+impl Pool {
+    pub fn all() -> [Pool; 4] {
+        [Pool::Async, Pool::Blocking, Pool::Io, Pool::FsBlocking]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pool::Async => "async",
+            Pool::Blocking => "blocking",
+            Pool::Io => "io",
+            Pool::FsBlocking => "fs_blocking",
+        }
+    }
+}
+
+///
+/// Counts of the tasks which have been spawned by an Executor (and its borrowed clones), but which
+/// have not yet completed: these include tasks which are queued, as well as those which are running.
+///
+#[derive(Debug, Default)]
+struct InFlight {
+    tasks: AtomicUsize,
+    blocking_tasks: AtomicUsize,
+    io_tasks: AtomicUsize,
+    fs_blocking_tasks: AtomicUsize,
+}
+
+///
+/// Decrements an in-flight count when the task which holds it completes (or is dropped).
+///
+struct InFlightGuard {
+    in_flight: Arc<InFlight>,
+    pool: Pool,
+}
+
+impl InFlightGuard {
+    fn new(in_flight: &Arc<InFlight>, pool: Pool) -> Self {
+        in_flight.counter(pool).fetch_add(1, Ordering::Relaxed);
+        Self {
+            in_flight: in_flight.clone(),
+            pool,
+        }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight
+            .counter(self.pool)
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl InFlight {
+    fn counter(&self, pool: Pool) -> &AtomicUsize {
+        match pool {
+            Pool::Async => &self.tasks,
+            Pool::Blocking => &self.blocking_tasks,
+            Pool::Io => &self.io_tasks,
+            Pool::FsBlocking => &self.fs_blocking_tasks,
+        }
+    }
 }
 
 impl Executor {
@@ -61,9 +304,13 @@ impl Executor {
     /// the scope of the tokio::{test, main} macros.
     ///
     pub fn new() -> Executor {
+        let handle = Handle::current();
         Self {
             runtime: Arc::new(Mutex::new(None)),
-            handle: Handle::current(),
+            io_handle: handle.clone(),
+            fs_handle: handle.clone(),
+            handle,
+            in_flight: Arc::default(),
         }
     }
 
@@ -80,6 +327,27 @@ impl Executor {
         max_threads: usize,
         on_thread_start: F,
     ) -> Result<Executor, String>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        Self::new_owned_with_options(
+            num_worker_threads,
+            max_threads,
+            ExecutorOptions::default(),
+            on_thread_start,
+        )
+    }
+
+    ///
+    /// As `Self::new_owned`, but additionally starts any dedicated Runtimes configured by the given
+    /// options.
+    ///
+    pub fn new_owned_with_options<F>(
+        num_worker_threads: usize,
+        max_threads: usize,
+        options: ExecutorOptions,
+        on_thread_start: F,
+    ) -> Result<Executor, String>
     where
         F: Fn() + Send + Sync + 'static,
     {
@@ -94,14 +362,52 @@ impl Executor {
             runtime_builder.on_thread_start(on_thread_start);
         };
 
+        if let Ok(address) = env::var(CONSOLE_ADDRESS_ENV_VAR) {
+            init_console(&address)?;
+        }
+
         let runtime = runtime_builder
             .build()
             .map_err(|e| format!("Failed to start the runtime: {e}"))?;
 
+        let io = options
+            .io_threads
+            .map(|io_threads| {
+                Builder::new_multi_thread()
+                    .worker_threads(io_threads)
+                    .thread_name("pants-io")
+                    .enable_all()
+                    .build()
+                    .map_err(|e| format!("Failed to start the IO runtime: {e}"))
+            })
+            .transpose()?;
+        let fs = options
+            .fs_blocking_threads
+            .map(|fs_blocking_threads| {
+                // Only the blocking pool of this Runtime is used, so it has a single worker thread.
+                Builder::new_multi_thread()
+                    .worker_threads(1)
+                    .max_blocking_threads(fs_blocking_threads)
+                    .thread_name("pants-fs")
+                    .enable_all()
+                    .build()
+                    .map_err(|e| format!("Failed to start the filesystem IO runtime: {e}"))
+            })
+            .transpose()?;
+
         let handle = runtime.handle().clone();
+        let io_handle = io.as_ref().map_or(&handle, |io| io.handle()).clone();
+        let fs_handle = fs.as_ref().map_or(&handle, |fs| fs.handle()).clone();
         Ok(Executor {
-            runtime: Arc::new(Mutex::new(Some(runtime))),
+            runtime: Arc::new(Mutex::new(Some(Runtimes {
+                main: runtime,
+                io,
+                fs,
+            }))),
             handle,
+            io_handle,
+            fs_handle,
+            in_flight: Arc::default(),
         })
     }
 
@@ -113,6 +419,9 @@ impl Executor {
         Self {
             runtime: Arc::new(Mutex::new(None)),
             handle: self.handle.clone(),
+            io_handle: self.io_handle.clone(),
+            fs_handle: self.fs_handle.clone(),
+            in_flight: self.in_flight.clone(),
         }
     }
 
@@ -128,6 +437,18 @@ impl Executor {
         f()
     }
 
+    ///
+    /// As `Self::enter`, but enters the runtime context used for network IO: resources (such as
+    /// servers) which are created in this context will have their IO driven by that Runtime.
+    ///
+    pub fn enter_io<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let _context = self.io_handle.enter();
+        f()
+    }
+
     ///
     /// Run a Future on a tokio Runtime as a new Task, and return a Future handle to it.
     ///
@@ -137,6 +458,7 @@ impl Executor {
     /// If the returned Future is dropped, the computation will still continue to completion: see
     /// <https://docs.rs/tokio/0.2.20/tokio/task/struct.JoinHandle.html>
     ///
+    #[track_caller]
     pub fn spawn<O: Send + 'static, F: Future<Output = O> + Send + 'static>(
         &self,
         future: F,
@@ -151,11 +473,53 @@ impl Executor {
     ///
     /// Run a Future on a tokio Runtime as a new Task, and return a JoinHandle.
     ///
+    #[track_caller]
     pub fn native_spawn<O: Send + 'static, F: Future<Output = O> + Send + 'static>(
         &self,
         future: F,
     ) -> JoinHandle<O> {
-        self.handle.spawn(future_with_correct_context(future))
+        self.native_spawn_on(&self.handle, Pool::Async, || None, future)
+    }
+
+    ///
+    /// As `Self::native_spawn`, but if task instrumentation is enabled (see `enable_console`), names
+    /// the Task using the given function, so that it can be identified while it is running.
+    ///
+    #[track_caller]
+    pub fn native_spawn_named<O: Send + 'static, F: Future<Output = O> + Send + 'static>(
+        &self,
+        name: impl FnOnce() -> String,
+        future: F,
+    ) -> JoinHandle<O> {
+        self.native_spawn_on(&self.handle, Pool::Async, || Some(name()), future)
+    }
+
+    ///
+    /// Run a Future which is dominated by network IO (such as a gRPC stream) as a new Task on the
+    /// Runtime reserved for network IO, and return a JoinHandle.
+    ///
+    #[track_caller]
+    pub fn native_spawn_io<O: Send + 'static, F: Future<Output = O> + Send + 'static>(
+        &self,
+        future: F,
+    ) -> JoinHandle<O> {
+        self.native_spawn_on(&self.io_handle, Pool::Io, || None, future)
+    }
+
+    #[track_caller]
+    fn native_spawn_on<O: Send + 'static, F: Future<Output = O> + Send + 'static>(
+        &self,
+        handle: &Handle,
+        pool: Pool,
+        name: impl FnOnce() -> Option<String>,
+        future: F,
+    ) -> JoinHandle<O> {
+        let guard = InFlightGuard::new(&self.in_flight, pool);
+        let future = future_with_correct_context(async move {
+            let _guard = guard;
+            future.await
+        });
+        spawn_on(handle, task_name(pool, name), future)
     }
 
     ///
@@ -184,6 +548,7 @@ impl Executor {
     /// If the returned Future is dropped, the computation will still continue to completion: see
     /// <https://docs.rs/tokio/0.2.20/tokio/task/struct.JoinHandle.html>
     ///
+    #[track_caller]
     pub fn spawn_blocking<F: FnOnce() -> R + Send + 'static, R: Send + 'static>(
         &self,
         f: F,
@@ -199,19 +564,73 @@ impl Executor {
     /// Spawn a Future on threads specifically reserved for I/O tasks which are allowed to be
     /// long-running and return a JoinHandle
     ///
+    #[track_caller]
     pub fn native_spawn_blocking<F: FnOnce() -> R + Send + 'static, R: Send + 'static>(
         &self,
         f: F,
+    ) -> JoinHandle<R> {
+        self.native_spawn_blocking_on(&self.handle, Pool::Blocking, f)
+    }
+
+    ///
+    /// As `Self::spawn_blocking`, but for functions which are dominated by filesystem IO (such as
+    /// capturing or materializing files), which run on the pool reserved for filesystem IO.
+    ///
+    #[track_caller]
+    pub fn spawn_blocking_fs<F: FnOnce() -> R + Send + 'static, R: Send + 'static>(
+        &self,
+        f: F,
+        rescue_join_error: impl FnOnce(JoinError) -> R,
+    ) -> impl Future<Output = R> {
+        self.native_spawn_blocking_on(&self.fs_handle, Pool::FsBlocking, f)
+            .map(|res| match res {
+                Ok(o) => o,
+                Err(e) => rescue_join_error(e),
+            })
+    }
+
+    #[track_caller]
+    fn native_spawn_blocking_on<F: FnOnce() -> R + Send + 'static, R: Send + 'static>(
+        &self,
+        handle: &Handle,
+        pool: Pool,
+        f: F,
     ) -> JoinHandle<R> {
         let stdio_destination = stdio::get_destination();
         let workunit_store_handle = workunit_store::get_workunit_store_handle();
         // NB: We unwrap here because the only thing that should cause an error in a spawned task is a
         // panic, in which case we want to propagate that.
-        self.handle.spawn_blocking(move || {
+        let guard = InFlightGuard::new(&self.in_flight, pool);
+        let name = task_name(pool, || None);
+        let f = move || {
+            let _guard = guard;
             stdio::set_thread_destination(stdio_destination);
             workunit_store::set_thread_workunit_store_handle(workunit_store_handle);
             f()
-        })
+        };
+        spawn_blocking_on(handle, name, f)
+    }
+
+    ///
+    /// The number of tasks spawned by `spawn` or `native_spawn` which have not yet completed.
+    ///
+    pub fn in_flight_tasks(&self) -> usize {
+        self.in_flight(Pool::Async)
+    }
+
+    ///
+    /// The number of functions spawned by `spawn_blocking` or `native_spawn_blocking` which have not
+    /// yet completed: when this exceeds the number of blocking threads, the excess are queued.
+    ///
+    pub fn in_flight_blocking_tasks(&self) -> usize {
+        self.in_flight(Pool::Blocking)
+    }
+
+    ///
+    /// The number of tasks or functions spawned onto the given pool which have not yet completed.
+    ///
+    pub fn in_flight(&self, pool: Pool) -> usize {
+        self.in_flight.counter(pool).load(Ordering::Relaxed)
     }
 
     /// Return a reference to this executor's runtime handle.
@@ -220,18 +639,24 @@ impl Executor {
     }
 
     ///
-    /// A blocking call to shut down the Runtime associated with this "owned" Executor. If tasks do
+    /// A blocking call to shut down the Runtimes associated with this "owned" Executor. If tasks do
     /// not shut down within the given timeout, they are leaked.
     ///
     /// This method has no effect for "borrowed" Executors: see the `Executor` rustdoc.
     ///
     pub fn shutdown(&self, timeout: Duration) {
-        let Some(runtime) = self.runtime.lock().take() else {
+        let Some(runtimes) = self.runtime.lock().take() else {
             return;
         };
 
         let start = Instant::now();
-        runtime.shutdown_timeout(timeout + Duration::from_millis(250));
+        for runtime in [Some(runtimes.main), runtimes.io, runtimes.fs]
+            .into_iter()
+            .flatten()
+        {
+            let remaining = timeout.saturating_sub(start.elapsed());
+            runtime.shutdown_timeout(remaining + Duration::from_millis(250));
+        }
         if start.elapsed() > timeout {
             // Leaked tasks could lead to panics in some cases (see #16105), so warn for them.
             log::warn!("Executor shutdown took unexpectedly long: tasks were likely leaked!");
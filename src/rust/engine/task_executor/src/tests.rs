@@ -0,0 +1,163 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Barrier};
+use std::time::Duration;
+
+use parking_lot::{const_mutex, Mutex, MutexGuard};
+use workunit_store::{SpanId, WorkunitStore, WorkunitStoreHandle};
+
+use crate::{init_console, task_name, Executor, ExecutorOptions, Pool, INSTRUMENT_TASKS};
+
+fn dedicated_executor() -> Executor {
+    Executor::new_owned_with_options(
+        1,
+        4,
+        ExecutorOptions {
+            io_threads: Some(1),
+            fs_blocking_threads: Some(1),
+        },
+        || (),
+    )
+    .unwrap()
+}
+
+///
+/// Enables task instrumentation until dropped, and then restores the previous value. Tests which
+/// enable instrumentation are serialized, so that one does not disable it while another runs.
+///
+struct InstrumentTasks {
+    previous: bool,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl InstrumentTasks {
+    fn enable() -> InstrumentTasks {
+        static LOCK: Mutex<()> = const_mutex(());
+        let _lock = LOCK.lock();
+        let previous = INSTRUMENT_TASKS.swap(true, Ordering::Relaxed);
+        InstrumentTasks { previous, _lock }
+    }
+}
+
+impl Drop for InstrumentTasks {
+    fn drop(&mut self) {
+        INSTRUMENT_TASKS.store(self.previous, Ordering::Relaxed);
+    }
+}
+
+fn thread_name() -> Option<String> {
+    std::thread::current().name().map(str::to_owned)
+}
+
+#[test]
+fn work_runs_on_dedicated_runtimes() {
+    let executor = dedicated_executor();
+
+    let (io, fs, blocking) = executor.block_on(async {
+        let io = executor
+            .native_spawn_io(async { thread_name() })
+            .await
+            .unwrap();
+        let fs = executor
+            .spawn_blocking_fs(thread_name, |e| panic!("Filesystem task failed: {e}"))
+            .await;
+        let blocking = executor
+            .spawn_blocking(thread_name, |e| panic!("Blocking task failed: {e}"))
+            .await;
+        (io, fs, blocking)
+    });
+    assert_eq!(io.as_deref(), Some("pants-io"));
+    assert_eq!(fs.as_deref(), Some("pants-fs"));
+    assert_ne!(blocking.as_deref(), Some("pants-io"));
+    assert_ne!(blocking.as_deref(), Some("pants-fs"));
+
+    executor.shutdown(Duration::from_secs(1));
+}
+
+#[test]
+fn in_flight_tasks_are_counted_per_pool() {
+    let executor = dedicated_executor();
+    let in_flight = |executor: &Executor| Pool::all().map(|pool| executor.in_flight(pool));
+
+    // Block one task on each pool until all of them have been counted.
+    let barrier = Arc::new(Barrier::new(4));
+    let io = {
+        let barrier = barrier.clone();
+        executor.native_spawn_io(async move {
+            tokio::task::spawn_blocking(move || barrier.wait())
+                .await
+                .unwrap();
+        })
+    };
+    let blocking = {
+        let barrier = barrier.clone();
+        executor.native_spawn_blocking(move || {
+            barrier.wait();
+        })
+    };
+    let fs = {
+        let barrier = barrier.clone();
+        executor.spawn_blocking_fs(
+            move || {
+                barrier.wait();
+            },
+            |e| panic!("Filesystem task failed: {e}"),
+        )
+    };
+    assert_eq!(in_flight(&executor), [0, 1, 1, 1]);
+
+    barrier.wait();
+    executor.block_on(async {
+        io.await.unwrap();
+        blocking.await.unwrap();
+        fs.await;
+    });
+    assert_eq!(in_flight(&executor), [0, 0, 0, 0]);
+
+    executor.shutdown(Duration::from_secs(1));
+}
+
+#[test]
+fn console_is_initialized_once() {
+    assert_eq!(init_console("127.0.0.1:0"), Ok(()));
+    // Later Executors reuse the outcome of the first, rather than installing another subscriber.
+    assert_eq!(init_console("not an address"), Ok(()));
+}
+
+#[test]
+fn task_names_include_pool_name_and_workunit() {
+    let _instrument_tasks = InstrumentTasks::enable();
+
+    workunit_store::set_thread_workunit_store_handle(None);
+    assert_eq!(
+        task_name(Pool::Blocking, || None),
+        Some("blocking".to_owned())
+    );
+
+    let parent_id = SpanId::new();
+    workunit_store::set_thread_workunit_store_handle(Some(WorkunitStoreHandle {
+        store: WorkunitStore::new(false, log::Level::Debug),
+        parent_id: Some(parent_id),
+    }));
+    assert_eq!(
+        task_name(Pool::Async, || Some("node Snapshot".to_owned())),
+        Some(format!("async: node Snapshot (workunit {parent_id})"))
+    );
+}
+
+#[tokio::test]
+async fn named_tasks_are_spawned() {
+    let _instrument_tasks = InstrumentTasks::enable();
+    let executor = Executor::new();
+
+    let result = executor
+        .native_spawn_named(|| "named".to_owned(), async { 1 })
+        .await
+        .unwrap();
+    assert_eq!(result, 1);
+    let result = executor
+        .spawn_blocking(|| 2, |e| panic!("Blocking task failed: {e}"))
+        .await;
+    assert_eq!(result, 2);
+}
@@ -0,0 +1,113 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use serde_derive::Deserialize;
+
+use crate::{TestCase, TestOutcome};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    #[serde(default)]
+    test_results: Vec<FileResult>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileResult {
+    name: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    assertion_results: Vec<AssertionResult>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AssertionResult {
+    #[serde(default)]
+    ancestor_titles: Vec<String>,
+    title: String,
+    full_name: Option<String>,
+    status: String,
+    // In milliseconds.
+    duration: Option<f64>,
+    #[serde(default)]
+    failure_messages: Vec<String>,
+}
+
+///
+/// Parses the test cases of a jest JSON report (as written by `jest --json`).
+///
+/// The suite of a test case is the path of its test file. A test file which failed to run (e.g.
+/// due to a syntax error) is reported as an errored test case with an empty name.
+///
+pub fn parse(content: &str) -> Result<Vec<TestCase>, String> {
+    let report: Report =
+        serde_json::from_str(content).map_err(|e| format!("Invalid jest JSON report: {e}"))?;
+    let mut cases = Vec::new();
+    for file in report.test_results {
+        let suite = sandbox_relative(&file.name);
+        if file.assertion_results.is_empty() && file.status == "failed" {
+            cases.push(TestCase {
+                suite,
+                name: String::new(),
+                outcome: TestOutcome::Errored,
+                duration: Duration::ZERO,
+                message: Some(file.message).filter(|m| !m.is_empty()),
+            });
+            continue;
+        }
+        for assertion in file.assertion_results {
+            let outcome = match assertion.status.as_str() {
+                "passed" => TestOutcome::Passed,
+                "failed" => TestOutcome::Failed,
+                // `pending`, `skipped`, `todo`, and `disabled`.
+                _ => TestOutcome::Skipped,
+            };
+            let name = assertion.full_name.unwrap_or_else(|| {
+                let mut titles = assertion.ancestor_titles;
+                titles.push(assertion.title);
+                titles.join(" ")
+            });
+            cases.push(TestCase {
+                suite: suite.clone(),
+                name,
+                outcome,
+                duration: assertion
+                    .duration
+                    .filter(|millis| millis.is_finite() && *millis >= 0.0)
+                    .map(|millis| Duration::from_micros((millis * 1000.0) as u64))
+                    .unwrap_or_default(),
+                message: Some(assertion.failure_messages.join("\n")).filter(|m| !m.is_empty()),
+            });
+        }
+    }
+    Ok(cases)
+}
+
+///
+/// Jest reports absolute paths, which for tests run by Pants are below a (randomly named) process
+/// sandbox. In order for the results of different attempts of a test to be merged, the path is
+/// made relative to the sandbox if possible.
+///
+fn sandbox_relative(path: &str) -> String {
+    let components = Path::new(path).components().collect::<Vec<_>>();
+    let is_sandbox = |component: &Component| match component {
+        Component::Normal(name) => name.to_string_lossy().starts_with("pants-sandbox-"),
+        _ => false,
+    };
+    let Some(sandbox) = components.iter().rposition(is_sandbox) else {
+        return path.to_owned();
+    };
+    components[sandbox + 1..]
+        .iter()
+        .collect::<PathBuf>()
+        .display()
+        .to_string()
+}
@@ -0,0 +1,149 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::time::Duration;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::{TestCase, TestOutcome};
+
+///
+/// Parses the test cases of a JUnit XML report: either a `<testsuites>` element containing
+/// (possibly nested) `<testsuite>` elements, or a single `<testsuite>`.
+///
+/// The suite of a test case is its `classname` if it has one, and otherwise the name of its
+/// innermost `<testsuite>`.
+///
+pub fn parse(content: &str) -> Result<Vec<TestCase>, String> {
+    let mut reader = Reader::from_str(content);
+    let mut cases = Vec::new();
+    // The names of the enclosing `<testsuite>` elements.
+    let mut suites: Vec<String> = Vec::new();
+    // The `<testcase>` which is open, if any.
+    let mut current: Option<TestCase> = None;
+    // True while a `<failure>`, `<error>` or `<skipped>` element is open, in which case its text is
+    // used as the message of the test case if it has no `message` attribute.
+    let mut in_outcome = false;
+    loop {
+        let event = reader.read_event().map_err(|e| {
+            format!(
+                "Invalid JUnit XML at position {}: {e}",
+                reader.buffer_position()
+            )
+        })?;
+        match event {
+            Event::Start(e) => match e.name().as_ref() {
+                b"testsuite" => suites.push(attribute(&e, b"name")?.unwrap_or_default()),
+                b"testcase" => current = Some(test_case(&e, &suites)?),
+                name => {
+                    if let Some(case) = current.as_mut() {
+                        in_outcome = outcome(case, name, &e)?;
+                    }
+                }
+            },
+            Event::Empty(e) => match e.name().as_ref() {
+                b"testcase" => cases.push(test_case(&e, &suites)?),
+                name => {
+                    if let Some(case) = current.as_mut() {
+                        outcome(case, name, &e)?;
+                    }
+                }
+            },
+            Event::End(e) => match e.name().as_ref() {
+                b"testsuite" => {
+                    suites.pop();
+                }
+                b"testcase" => cases.extend(current.take()),
+                _ => in_outcome = false,
+            },
+            Event::Text(text) if in_outcome => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| format!("Invalid JUnit XML text: {e}"))?;
+                set_message_from_text(current.as_mut(), &text);
+            }
+            Event::CData(text) if in_outcome => {
+                set_message_from_text(current.as_mut(), &String::from_utf8_lossy(&text));
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    Ok(cases)
+}
+
+fn test_case(element: &BytesStart, suites: &[String]) -> Result<TestCase, String> {
+    let suite = attribute(element, b"classname")?
+        .filter(|classname| !classname.is_empty())
+        .or_else(|| suites.last().cloned())
+        .unwrap_or_default();
+    Ok(TestCase {
+        suite,
+        name: attribute(element, b"name")?.unwrap_or_default(),
+        outcome: TestOutcome::Passed,
+        duration: attribute(element, b"time")?
+            .and_then(|time| parse_secs(&time))
+            .unwrap_or_default(),
+        message: None,
+    })
+}
+
+///
+/// Applies an element within a `<testcase>` to it, returning true if it was an outcome element.
+///
+fn outcome(case: &mut TestCase, name: &[u8], element: &BytesStart) -> Result<bool, String> {
+    let outcome = match name {
+        b"failure" => TestOutcome::Failed,
+        b"error" => TestOutcome::Errored,
+        b"skipped" => TestOutcome::Skipped,
+        _ => return Ok(false),
+    };
+    // A test case can have multiple outcome elements: e.g. a failure in the test, and an error in
+    // its teardown. The most severe of them is used.
+    case.outcome = if case.outcome == TestOutcome::Passed {
+        outcome
+    } else {
+        case.outcome.max(outcome)
+    };
+    if case.message.is_none() {
+        case.message = attribute(element, b"message")?.filter(|m| !m.is_empty());
+    }
+    Ok(true)
+}
+
+fn set_message_from_text(case: Option<&mut TestCase>, text: &str) {
+    let Some(case) = case else {
+        return;
+    };
+    let text = text.trim();
+    if case.message.is_none() && !text.is_empty() {
+        case.message = Some(text.to_owned());
+    }
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Result<Option<String>, String> {
+    for attr in element.attributes() {
+        let attr = attr.map_err(|e| format!("Invalid JUnit XML attribute: {e}"))?;
+        if attr.key.as_ref() == name {
+            let value = attr
+                .unescape_value()
+                .map_err(|e| format!("Invalid JUnit XML attribute: {e}"))?;
+            return Ok(Some(value.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+///
+/// Parses a duration in (possibly fractional) seconds. Some reporters include thousands
+/// separators, which are ignored.
+///
+fn parse_secs(secs: &str) -> Option<Duration> {
+    secs.trim()
+        .replace(',', "")
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
@@ -0,0 +1,195 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//! Parsing and aggregation of the result files written by test runners.
+//!
+//! Test runners write results in a handful of formats (JUnit XML, jest JSON), generally one file
+//! per test process. Large repositories may have thousands of such files per run, and a test may
+//! be split across shards or retried, so results are merged here by test case to produce a single
+//! summary of the run.
+
+use std::collections::{btree_map, BTreeMap};
+use std::path::Path;
+use std::time::Duration;
+
+pub mod jest;
+pub mod junit;
+
+#[cfg(test)]
+mod tests;
+
+///
+/// The outcome of a single attempt of a test case.
+///
+/// Outcomes are ordered by severity, which is used to choose between the outcomes of attempts
+/// which did not pass.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TestOutcome {
+    Skipped,
+    Passed,
+    Failed,
+    Errored,
+}
+
+impl TestOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TestOutcome::Skipped => "skipped",
+            TestOutcome::Passed => "passed",
+            TestOutcome::Failed => "failed",
+            TestOutcome::Errored => "errored",
+        }
+    }
+}
+
+///
+/// The result of a single attempt of a test case, as parsed from a result file.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestCase {
+    /// The suite (or class, or file) containing the test case.
+    pub suite: String,
+    pub name: String,
+    pub outcome: TestOutcome,
+    pub duration: Duration,
+    /// The failure, error or skip message, if any.
+    pub message: Option<String>,
+}
+
+///
+/// A format of test result file.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResultFormat {
+    JUnitXml,
+    JestJson,
+}
+
+impl ResultFormat {
+    ///
+    /// Detects the format of a result file from its extension, if it is a supported format.
+    ///
+    pub fn for_path(path: &Path) -> Option<ResultFormat> {
+        match path.extension()?.to_str()? {
+            "xml" => Some(ResultFormat::JUnitXml),
+            "json" => Some(ResultFormat::JestJson),
+            _ => None,
+        }
+    }
+
+    pub fn parse(self, content: &str) -> Result<Vec<TestCase>, String> {
+        match self {
+            ResultFormat::JUnitXml => junit::parse(content),
+            ResultFormat::JestJson => jest::parse(content),
+        }
+    }
+}
+
+///
+/// The result of a test case across all of its attempts.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregatedTestCase {
+    pub suite: String,
+    pub name: String,
+    /// Passed if any attempt passed, and otherwise the most severe outcome of any attempt.
+    pub outcome: TestOutcome,
+    /// The total duration of all attempts.
+    pub duration: Duration,
+    /// The message of the most recent attempt which did not pass.
+    pub message: Option<String>,
+    pub attempts: usize,
+    /// True if the test case both passed and failed (or errored) in different attempts.
+    pub flaky: bool,
+}
+
+///
+/// A summary of the results of all of the test cases of a run.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TestResultsSummary {
+    /// The aggregated test cases, ordered by suite and name.
+    pub cases: Vec<AggregatedTestCase>,
+    pub passed: usize,
+    pub failed: usize,
+    pub errored: usize,
+    pub skipped: usize,
+    pub flaky: usize,
+    /// The total duration of all attempts of all test cases.
+    pub duration: Duration,
+}
+
+///
+/// Merges the test cases of result files, which may come from different shards of a run (which
+/// contain disjoint test cases) or from different attempts of the same tests (which contain the
+/// same test cases).
+///
+#[derive(Default)]
+pub struct TestResultsAggregator {
+    cases: BTreeMap<(String, String), AggregatedTestCase>,
+}
+
+impl TestResultsAggregator {
+    ///
+    /// Adds the test cases of one result file. Files should be added in the order in which they
+    /// were produced, so that the most recent message of a test case is retained.
+    ///
+    pub fn add(&mut self, cases: impl IntoIterator<Item = TestCase>) {
+        for case in cases {
+            let aggregated = match self.cases.entry((case.suite, case.name)) {
+                btree_map::Entry::Vacant(entry) => {
+                    let (suite, name) = entry.key().clone();
+                    entry.insert(AggregatedTestCase {
+                        suite,
+                        name,
+                        outcome: case.outcome,
+                        duration: case.duration,
+                        message: case.message,
+                        attempts: 1,
+                        flaky: false,
+                    });
+                    continue;
+                }
+                btree_map::Entry::Occupied(entry) => entry.into_mut(),
+            };
+
+            aggregated.attempts += 1;
+            aggregated.duration += case.duration;
+            if case.outcome != TestOutcome::Passed && case.message.is_some() {
+                aggregated.message = case.message;
+            }
+            let failed = |outcome| matches!(outcome, TestOutcome::Failed | TestOutcome::Errored);
+            if (aggregated.outcome == TestOutcome::Passed && failed(case.outcome))
+                || (failed(aggregated.outcome) && case.outcome == TestOutcome::Passed)
+            {
+                aggregated.flaky = true;
+            }
+            aggregated.outcome = if aggregated.outcome == TestOutcome::Passed
+                || case.outcome == TestOutcome::Passed
+            {
+                TestOutcome::Passed
+            } else {
+                aggregated.outcome.max(case.outcome)
+            };
+        }
+    }
+
+    pub fn summary(self) -> TestResultsSummary {
+        let mut summary = TestResultsSummary::default();
+        for case in self.cases.into_values() {
+            match case.outcome {
+                TestOutcome::Passed => summary.passed += 1,
+                TestOutcome::Failed => summary.failed += 1,
+                TestOutcome::Errored => summary.errored += 1,
+                TestOutcome::Skipped => summary.skipped += 1,
+            }
+            if case.flaky {
+                summary.flaky += 1;
+            }
+            summary.duration += case.duration;
+            summary.cases.push(case);
+        }
+        summary
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{ResultFormat, TestCase, TestOutcome, TestResultsAggregator};
+
+const PYTEST_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<testsuites>
+  <testsuite name="pytest" errors="0" failures="1" skipped="1" tests="3" time="1.5">
+    <testcase classname="tests.test_app" name="test_ok" time="0.5" />
+    <testcase classname="tests.test_app" name="test_fails" time="1.0">
+      <failure message="assert 1 == 2">def test_fails():
+&gt;       assert 1 == 2</failure>
+      <system-out>some output</system-out>
+    </testcase>
+    <testcase classname="tests.test_app" name="test_skipped" time="0.000">
+      <skipped type="pytest.skip" message="not today" />
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+
+#[test]
+fn parse_junit_xml() {
+    let cases = ResultFormat::for_path(Path::new("reports/tests.test_app.xml"))
+        .unwrap()
+        .parse(PYTEST_XML)
+        .unwrap();
+    assert_eq!(
+        cases,
+        vec![
+            case("test_ok", TestOutcome::Passed, 500, None),
+            case(
+                "test_fails",
+                TestOutcome::Failed,
+                1000,
+                Some("assert 1 == 2")
+            ),
+            case("test_skipped", TestOutcome::Skipped, 0, Some("not today")),
+        ]
+    );
+}
+
+#[test]
+fn parse_junit_xml_message_from_text() {
+    let xml = r#"<testsuite name="Suite">
+      <testcase name="test_error"><error><![CDATA[boom]]></error></testcase>
+    </testsuite>"#;
+    let cases = crate::junit::parse(xml).unwrap();
+    assert_eq!(cases.len(), 1);
+    assert_eq!(cases[0].suite, "Suite");
+    assert_eq!(cases[0].outcome, TestOutcome::Errored);
+    assert_eq!(cases[0].message.as_deref(), Some("boom"));
+
+    assert!(crate::junit::parse("<testsuite><testcase></testsuite>").is_err());
+}
+
+#[test]
+fn parse_jest_json() {
+    let json = r#"{
+      "numTotalTests": 3,
+      "testResults": [
+        {
+          "name": "/tmp/pants-sandbox-abc123/src/js/app.test.js",
+          "status": "failed",
+          "message": "",
+          "assertionResults": [
+            {"ancestorTitles": ["app"], "title": "works", "fullName": "app works",
+             "status": "passed", "duration": 12, "failureMessages": []},
+            {"ancestorTitles": ["app"], "title": "breaks", "status": "failed",
+             "duration": null, "failureMessages": ["Expected 1", "Received 2"]},
+            {"ancestorTitles": [], "title": "later", "status": "todo", "failureMessages": []}
+          ]
+        },
+        {
+          "name": "/tmp/pants-sandbox-abc123/src/js/broken.test.js",
+          "status": "failed",
+          "message": "SyntaxError: Unexpected token",
+          "assertionResults": []
+        }
+      ]
+    }"#;
+    let cases = ResultFormat::JestJson.parse(json).unwrap();
+    let summary = cases
+        .iter()
+        .map(|c| {
+            (
+                c.suite.as_str(),
+                c.name.as_str(),
+                c.outcome,
+                c.message.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            ("src/js/app.test.js", "app works", TestOutcome::Passed, None),
+            (
+                "src/js/app.test.js",
+                "app breaks",
+                TestOutcome::Failed,
+                Some("Expected 1\nReceived 2")
+            ),
+            ("src/js/app.test.js", "later", TestOutcome::Skipped, None),
+            (
+                "src/js/broken.test.js",
+                "",
+                TestOutcome::Errored,
+                Some("SyntaxError: Unexpected token")
+            ),
+        ]
+    );
+    assert_eq!(cases[0].duration, Duration::from_millis(12));
+}
+
+#[test]
+fn aggregate_shards_and_attempts() {
+    let mut aggregator = TestResultsAggregator::default();
+    // A first shard, in which one test fails.
+    aggregator.add(vec![
+        case("a", TestOutcome::Passed, 100, None),
+        case("b", TestOutcome::Failed, 100, Some("first")),
+    ]);
+    // A second shard.
+    aggregator.add(vec![case("c", TestOutcome::Skipped, 0, None)]);
+    // A retry of the first shard, in which the failed test passes.
+    aggregator.add(vec![
+        case("a", TestOutcome::Passed, 100, None),
+        case("b", TestOutcome::Passed, 300, None),
+    ]);
+    // Another test which fails in every attempt.
+    aggregator.add(vec![case("d", TestOutcome::Failed, 10, Some("first"))]);
+    aggregator.add(vec![case("d", TestOutcome::Errored, 10, Some("second"))]);
+
+    let summary = aggregator.summary();
+    assert_eq!(
+        (
+            summary.passed,
+            summary.failed,
+            summary.errored,
+            summary.skipped,
+            summary.flaky
+        ),
+        (2, 0, 1, 1, 1)
+    );
+    assert_eq!(summary.duration, Duration::from_millis(620));
+
+    let b = &summary.cases[1];
+    assert_eq!(
+        (b.name.as_str(), b.outcome, b.attempts, b.flaky),
+        ("b", TestOutcome::Passed, 2, true)
+    );
+    assert_eq!(b.message.as_deref(), Some("first"));
+    let d = &summary.cases[3];
+    assert_eq!(
+        (d.outcome, d.flaky, d.message.as_deref()),
+        (TestOutcome::Errored, false, Some("second"))
+    );
+}
+
+fn case(name: &str, outcome: TestOutcome, millis: u64, message: Option<&str>) -> TestCase {
+    TestCase {
+        suite: "tests.test_app".to_owned(),
+        name: name.to_owned(),
+        outcome,
+        duration: Duration::from_millis(millis),
+        message: message.map(|m| m.to_owned()),
+    }
+}
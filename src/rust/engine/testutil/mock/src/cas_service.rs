@@ -8,6 +8,7 @@ use std::sync::Arc;
 use bytes::{Bytes, BytesMut};
 use futures::stream::StreamExt;
 use futures::Stream;
+use grpc_util::resource_name::{parse_read_resource_name, parse_write_resource_name};
 use hashing::{Digest, Fingerprint};
 use parking_lot::Mutex;
 use protos::gen::build::bazel::remote::execution::v2 as remexec;
@@ -77,108 +78,6 @@ macro_rules! check_instance_name {
     };
 }
 
-#[derive(Debug, Eq, PartialEq)]
-struct ParsedWriteResourceName<'a> {
-    instance_name: &'a str,
-    _uuid: &'a str,
-    hash: &'a str,
-    size: usize,
-}
-
-/// Parses a resource name of the form `{instance_name}/uploads/{uuid}/blobs/{hash}/{size}` into
-/// a struct with references to the individual components of the resource name. The
-/// `{instance_name}` may be blank (with no leading slash) as per REAPI specification.
-fn parse_write_resource_name(resource: &str) -> Result<ParsedWriteResourceName, String> {
-    if resource.is_empty() {
-        return Err("Missing resource name".to_owned());
-    }
-
-    // Parse the resource name into parts separated by slashes (/).
-    let parts: Vec<_> = resource.split('/').collect();
-
-    // Search for the `uploads` path component.
-    let uploads_index = match parts.iter().position(|p| *p == "uploads") {
-        Some(index) => index,
-        None => return Err("Malformed resource name: missing `uploads` component".to_owned()),
-    };
-    let instance_parts = &parts[0..uploads_index];
-
-    if (parts.len() - uploads_index) < 5 {
-        return Err(
-            "Malformed resource name: not enough path components after `uploads`".to_owned(),
-        );
-    }
-
-    if parts[uploads_index + 2] != "blobs" {
-        return Err("Malformed resource name: expected `blobs` component".to_owned());
-    }
-
-    let size = parts[uploads_index + 4]
-        .parse::<usize>()
-        .map_err(|_| "Malformed resource name: cannot parse size".to_owned())?;
-
-    let instance_name = if instance_parts.is_empty() {
-        ""
-    } else {
-        let last_instance_name_index =
-            instance_parts.iter().map(|x| (*x).len()).sum::<usize>() + instance_parts.len() - 1;
-        &resource[0..last_instance_name_index]
-    };
-
-    Ok(ParsedWriteResourceName {
-        instance_name,
-        _uuid: parts[uploads_index + 1],
-        hash: parts[uploads_index + 3],
-        size,
-    })
-}
-
-#[derive(Debug, Eq, PartialEq)]
-struct ParsedReadResourceName<'a> {
-    instance_name: &'a str,
-    hash: &'a str,
-    size: usize,
-}
-
-/// `"{instance_name}/blobs/{hash}/{size}"`
-fn parse_read_resource_name(resource: &str) -> Result<ParsedReadResourceName, String> {
-    if resource.is_empty() {
-        return Err("Missing resource name".to_owned());
-    }
-
-    // Parse the resource name into parts separated by slashes (/).
-    let parts: Vec<_> = resource.split('/').collect();
-
-    // Search for the `blobs` path component.
-    let blobs_index = match parts.iter().position(|p| *p == "blobs") {
-        Some(index) => index,
-        None => return Err("Malformed resource name: missing `blobs` component".to_owned()),
-    };
-    let instance_parts = &parts[0..blobs_index];
-
-    if (parts.len() - blobs_index) < 3 {
-        return Err("Malformed resource name: not enough path components after `blobs`".to_owned());
-    }
-
-    let size = parts[blobs_index + 2]
-        .parse::<usize>()
-        .map_err(|_| "Malformed resource name: cannot parse size".to_owned())?;
-
-    let instance_name = if instance_parts.is_empty() {
-        ""
-    } else {
-        let last_instance_name_index =
-            instance_parts.iter().map(|x| (*x).len()).sum::<usize>() + instance_parts.len() - 1;
-        &resource[0..last_instance_name_index]
-    };
-
-    Ok(ParsedReadResourceName {
-        instance_name,
-        hash: parts[blobs_index + 1],
-        size,
-    })
-}
-
 impl StubCASResponder {
     fn instance_name(&self) -> String {
         self.instance_name.clone().unwrap_or_default()
@@ -552,137 +451,3 @@ impl Capabilities for StubCASResponder {
         Ok(Response::new(response))
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::{
-        parse_read_resource_name, parse_write_resource_name, ParsedReadResourceName,
-        ParsedWriteResourceName,
-    };
-
-    #[test]
-    fn parse_write_resource_name_correctly() {
-        let result = parse_write_resource_name("main/uploads/uuid-12345/blobs/abc123/12").unwrap();
-        assert_eq!(
-            result,
-            ParsedWriteResourceName {
-                instance_name: "main",
-                _uuid: "uuid-12345",
-                hash: "abc123",
-                size: 12,
-            }
-        );
-
-        let result = parse_write_resource_name("uploads/uuid-12345/blobs/abc123/12").unwrap();
-        assert_eq!(
-            result,
-            ParsedWriteResourceName {
-                instance_name: "",
-                _uuid: "uuid-12345",
-                hash: "abc123",
-                size: 12,
-            }
-        );
-
-        let result = parse_write_resource_name("a/b/c/uploads/uuid-12345/blobs/abc123/12").unwrap();
-        assert_eq!(
-            result,
-            ParsedWriteResourceName {
-                instance_name: "a/b/c",
-                _uuid: "uuid-12345",
-                hash: "abc123",
-                size: 12,
-            }
-        );
-
-        // extra components after the size are accepted
-        let result =
-            parse_write_resource_name("a/b/c/uploads/uuid-12345/blobs/abc123/12/extra/stuff")
-                .unwrap();
-        assert_eq!(
-            result,
-            ParsedWriteResourceName {
-                instance_name: "a/b/c",
-                _uuid: "uuid-12345",
-                hash: "abc123",
-                size: 12,
-            }
-        );
-    }
-
-    #[test]
-    fn parse_write_resource_name_errors_as_expected() {
-        //
-        let err = parse_write_resource_name("").unwrap_err();
-        assert_eq!(err, "Missing resource name");
-
-        let err = parse_write_resource_name("main/uuid-12345/blobs/abc123/12").unwrap_err();
-        assert_eq!(err, "Malformed resource name: missing `uploads` component");
-
-        let err = parse_write_resource_name("main/uploads/uuid-12345/abc123/12").unwrap_err();
-        assert_eq!(
-            err,
-            "Malformed resource name: not enough path components after `uploads`"
-        );
-
-        let err = parse_write_resource_name("main/uploads/uuid-12345/abc123/12/foo").unwrap_err();
-        assert_eq!(err, "Malformed resource name: expected `blobs` component");
-
-        // negative size should be rejected
-        let err =
-            parse_write_resource_name("main/uploads/uuid-12345/blobs/abc123/-12").unwrap_err();
-        assert_eq!(err, "Malformed resource name: cannot parse size");
-    }
-
-    #[test]
-    fn parse_read_resource_name_correctly() {
-        let result = parse_read_resource_name("main/blobs/abc123/12").unwrap();
-        assert_eq!(
-            result,
-            ParsedReadResourceName {
-                instance_name: "main",
-                hash: "abc123",
-                size: 12,
-            }
-        );
-
-        let result = parse_read_resource_name("blobs/abc123/12").unwrap();
-        assert_eq!(
-            result,
-            ParsedReadResourceName {
-                instance_name: "",
-                hash: "abc123",
-                size: 12,
-            }
-        );
-
-        let result = parse_read_resource_name("a/b/c/blobs/abc123/12").unwrap();
-        assert_eq!(
-            result,
-            ParsedReadResourceName {
-                instance_name: "a/b/c",
-                hash: "abc123",
-                size: 12,
-            }
-        );
-    }
-
-    #[test]
-    fn parse_read_resource_name_errors_as_expected() {
-        let err = parse_read_resource_name("").unwrap_err();
-        assert_eq!(err, "Missing resource name");
-
-        let err = parse_read_resource_name("main/abc123/12").unwrap_err();
-        assert_eq!(err, "Malformed resource name: missing `blobs` component");
-
-        let err = parse_read_resource_name("main/blobs/12").unwrap_err();
-        assert_eq!(
-            err,
-            "Malformed resource name: not enough path components after `blobs`"
-        );
-
-        // negative size should be rejected
-        let err = parse_read_resource_name("main/blobs/abc123/-12").unwrap_err();
-        assert_eq!(err, "Malformed resource name: cannot parse size");
-    }
-}
@@ -7,7 +7,7 @@ use std::collections::HashSet;
 use std::time::SystemTime;
 use task_executor::Executor;
 use terminal_size::terminal_size_using_fd;
-use workunit_store::SpanId;
+use workunit_store::{ProgressEstimate, SpanId, TransferSnapshot};
 
 mod indicatif;
 mod prodash;
@@ -63,10 +63,19 @@ impl Instance {
     ///
     /// Update the rendering with new data.
     ///
-    pub fn render(&mut self, heavy_hitters: &HashMap<SpanId, (String, SystemTime)>) {
+    /// `transfers` contains the progress of any in-flight transfers, keyed by the heavy hitter
+    /// which they are running under. If an `estimate` of overall progress is available, it is
+    /// rendered above the heavy hitters.
+    ///
+    pub fn render(
+        &mut self,
+        heavy_hitters: &HashMap<SpanId, (String, SystemTime)>,
+        transfers: &HashMap<SpanId, TransferSnapshot>,
+        estimate: Option<ProgressEstimate>,
+    ) {
         match self {
-            Instance::Indicatif(indicatif) => indicatif.render(heavy_hitters),
-            Instance::Prodash(prodash) => prodash.render(heavy_hitters),
+            Instance::Indicatif(indicatif) => indicatif.render(heavy_hitters, transfers, estimate),
+            Instance::Prodash(prodash) => prodash.render(heavy_hitters, transfers, estimate),
         };
     }
 
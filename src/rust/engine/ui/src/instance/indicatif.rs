@@ -18,16 +18,22 @@ use indicatif::WeakProgressBar;
 use parking_lot::Mutex;
 
 use workunit_store::format_workunit_duration_ms;
+use workunit_store::ProgressEstimate;
 use workunit_store::SpanId;
+use workunit_store::TransferSnapshot;
 
 use super::TaskState;
 use crate::ConsoleUI;
 
+/// The resolution of the overall progress bar.
+const PROGRESS_BAR_LENGTH: u64 = 1000;
+
 pub struct IndicatifInstance {
     tasks_to_display: IndexSet<SpanId>,
-    // NB: Kept for Drop.
-    _multi_progress: MultiProgress,
+    multi_progress: MultiProgress,
     bars: Vec<ProgressBar>,
+    // A bar rendering the overall progress estimate, which is added once an estimate is available.
+    progress_bar: Option<ProgressBar>,
 }
 
 impl IndicatifInstance {
@@ -62,8 +68,9 @@ impl IndicatifInstance {
 
         Ok(IndicatifInstance {
             tasks_to_display: IndexSet::new(),
-            _multi_progress: multi_progress,
+            multi_progress,
             bars,
+            progress_bar: None,
         })
     }
 
@@ -74,7 +81,25 @@ impl IndicatifInstance {
         future::ready(()).boxed()
     }
 
-    pub fn render(&mut self, heavy_hitters: &HashMap<SpanId, (String, SystemTime)>) {
+    pub fn render(
+        &mut self,
+        heavy_hitters: &HashMap<SpanId, (String, SystemTime)>,
+        transfers: &HashMap<SpanId, TransferSnapshot>,
+        estimate: Option<ProgressEstimate>,
+    ) {
+        if let Some(estimate) = estimate {
+            let progress_bar = self.progress_bar.get_or_insert_with(|| {
+                let style = ProgressStyle::default_bar()
+                    .template("{bar:30} {wide_msg}")
+                    .expect("Valid template.");
+                self.multi_progress
+                    .insert(0, ProgressBar::new(PROGRESS_BAR_LENGTH).with_style(style))
+            });
+            progress_bar
+                .set_position((estimate.fraction_complete() * PROGRESS_BAR_LENGTH as f64) as u64);
+            progress_bar.set_message(estimate.to_string());
+        }
+
         let tasks_to_display = &mut self.tasks_to_display;
         super::classify_tasks(
             heavy_hitters,
@@ -102,7 +127,10 @@ impl IndicatifInstance {
                         format_workunit_duration_ms!((duration).as_millis()).to_string()
                     }
                 };
-                format!("{duration_label} {label}")
+                match transfers.get(span_id) {
+                    Some(transfer) => format!("{duration_label} {label} {transfer}"),
+                    None => format!("{duration_label} {label}"),
+                }
             });
 
             match maybe_label {
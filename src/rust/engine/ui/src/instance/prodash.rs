@@ -18,13 +18,19 @@ use prodash::TreeOptions;
 use logging::fatal_log;
 use task_executor::Executor;
 use workunit_store::format_workunit_duration_ms;
+use workunit_store::ProgressEstimate;
 use workunit_store::SpanId;
+use workunit_store::TransferSnapshot;
 
 use super::TaskState;
 use crate::ConsoleUI;
 
 pub struct ProdashInstance {
-    tasks_to_display: HashMap<SpanId, prodash::tree::Item>,
+    // Displayed items, with the description they were created with.
+    tasks_to_display: HashMap<SpanId, (prodash::tree::Item, String)>,
+    // An item rendering the overall progress estimate, which is added once an estimate is
+    // available.
+    progress_item: Option<prodash::tree::Item>,
     tree: prodash::Tree,
     handle: line::JoinHandle,
     terminal_width: u16,
@@ -88,6 +94,7 @@ impl ProdashInstance {
 
         Ok(ProdashInstance {
             tasks_to_display: HashMap::new(),
+            progress_item: None,
             tree,
             handle,
             terminal_width,
@@ -99,6 +106,7 @@ impl ProdashInstance {
         // Drop all tasks to clear the Tree. The call to shutdown will render a final "Tick" with the
         // empty Tree, which will clear the screen.
         self.tasks_to_display.clear();
+        self.progress_item = None;
         self.executor
             .clone()
             .spawn_blocking(
@@ -108,7 +116,22 @@ impl ProdashInstance {
             .boxed()
     }
 
-    pub fn render(&mut self, heavy_hitters: &HashMap<SpanId, (String, SystemTime)>) {
+    pub fn render(
+        &mut self,
+        heavy_hitters: &HashMap<SpanId, (String, SystemTime)>,
+        transfers: &HashMap<SpanId, TransferSnapshot>,
+        estimate: Option<ProgressEstimate>,
+    ) {
+        if let Some(estimate) = estimate {
+            let item = self.progress_item.get_or_insert_with(|| {
+                let mut item = self.tree.add_child("Progress");
+                item.init(Some(100), None);
+                item
+            });
+            item.set((estimate.fraction_complete() * 100.0) as Step);
+            item.set_name(estimate.to_string());
+        }
+
         let tasks_to_display = &mut self.tasks_to_display;
         super::classify_tasks(
             heavy_hitters,
@@ -118,34 +141,49 @@ impl ProdashInstance {
                     tasks_to_display.remove(&span_id);
                 }
                 TaskState::Update => {
+                    let (item, description) = tasks_to_display.get_mut(&span_id).unwrap();
                     // NB: `inc` moves the "worms" to help show ongoing progress.
-                    tasks_to_display.get_mut(&span_id).unwrap().inc();
+                    item.inc();
+                    // Reset the name once a transfer has ended, so that a stale transfer is not
+                    // displayed.
+                    let label = match transfers.get(&span_id) {
+                        Some(transfer) => format!("{description} {transfer}"),
+                        None => description.clone(),
+                    };
+                    item.set_name(truncate(&label, self.terminal_width));
                 }
                 TaskState::New => {
                     let (desc, start_time) = heavy_hitters.get(&span_id).unwrap();
-                    // NB: Allow a 8 char "buffer" to allow for timing and spaces.
-                    let max_len = (self.terminal_width as usize) - 8;
-                    let description: String = if desc.len() < max_len {
-                        desc.to_string()
-                    } else {
-                        desc.chars()
-                            .take(max_len - 3)
-                            .chain(std::iter::repeat('.').take(3))
-                            .collect()
-                    };
-                    let mut item = self.tree.add_child(description);
+                    let mut item = self.tree.add_child(truncate(desc, self.terminal_width));
                     item.init(
                         None,
                         Some(prodash::unit::dynamic(MillisAsFloatingPointSecs)),
                     );
                     item.set(MillisAsFloatingPointSecs::start_time_to_step(start_time));
-                    tasks_to_display.insert(span_id, item);
+                    tasks_to_display.insert(span_id, (item, desc.clone()));
                 }
             },
         )
     }
 }
 
+///
+/// Truncates the given label to fit on a line of the given width.
+///
+fn truncate(label: &str, terminal_width: u16) -> String {
+    // NB: Allow a 8 char "buffer" to allow for timing and spaces.
+    let max_len = (terminal_width as usize).saturating_sub(8);
+    if label.len() < max_len {
+        label.to_string()
+    } else {
+        label
+            .chars()
+            .take(max_len.saturating_sub(3))
+            .chain(std::iter::repeat('.').take(3))
+            .collect()
+    }
+}
+
 /// Renders a millis-since-epoch unit as floating point seconds.
 #[derive(Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Debug)]
 struct MillisAsFloatingPointSecs;
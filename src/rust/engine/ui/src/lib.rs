@@ -5,17 +5,29 @@ use futures::future::BoxFuture;
 use futures::future::FutureExt;
 use instance::Instance;
 use std::future::Future;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use task_executor::Executor;
 use workunit_store::WorkunitStore;
 mod instance;
+mod plain;
+#[cfg(test)]
+mod plain_tests;
+
+pub use plain::PlainOutputRenderer;
+
+///
+/// How long the UI must have been running before a progress estimate is rendered: short runs are
+/// adequately served by spinners, and their estimates would mostly be noise.
+///
+const PROGRESS_ESTIMATE_DELAY: Duration = Duration::from_secs(5);
 
 pub struct ConsoleUI {
     workunit_store: WorkunitStore,
     local_parallelism: usize,
     ui_use_prodash: bool,
-    // While the UI is running, there will be an Instance present.
-    instance: Option<Instance>,
+    // While the UI is running, there will be an Instance present, along with the time at which it
+    // started.
+    instance: Option<(Instance, Instant)>,
 }
 
 impl ConsoleUI {
@@ -59,12 +71,19 @@ impl ConsoleUI {
     /// drives rendering, while this method feeds it new data.
     ///
     pub fn render(&mut self) {
-        let Some(instance) = &mut self.instance else {
+        let Some((instance, started)) = &mut self.instance else {
             return;
         };
 
         let heavy_hitters = self.workunit_store.heavy_hitters(self.local_parallelism);
-        instance.render(&heavy_hitters)
+        let transfers = self.workunit_store.transfers_by_visible_parent();
+        let estimate = if started.elapsed() >= PROGRESS_ESTIMATE_DELAY {
+            self.workunit_store
+                .progress_estimate(self.local_parallelism)
+        } else {
+            None
+        };
+        instance.render(&heavy_hitters, &transfers, estimate)
     }
 
     ///
@@ -78,11 +97,10 @@ impl ConsoleUI {
             return Err("A ConsoleUI cannot render multiple UIs concurrently.".to_string());
         }
 
-        self.instance = Some(Instance::new(
-            self.ui_use_prodash,
-            self.local_parallelism,
-            executor,
-        )?);
+        self.instance = Some((
+            Instance::new(self.ui_use_prodash, self.local_parallelism, executor)?,
+            Instant::now(),
+        ));
 
         Ok(())
     }
@@ -94,7 +112,7 @@ impl ConsoleUI {
     /// outside of any UI locks.
     ///
     pub fn teardown(&mut self) -> BoxFuture<'static, ()> {
-        if let Some(instance) = self.instance.take() {
+        if let Some((instance, _)) = self.instance.take() {
             instance.teardown()
         } else {
             futures::future::ready(()).boxed()
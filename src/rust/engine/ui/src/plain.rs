@@ -0,0 +1,153 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use stdio::Destination;
+use workunit_store::{OutputStream, ProcessOutputSink, SpanId};
+
+/// The maximum number of characters of a process description to include in a line prefix.
+const MAX_PREFIX_DESCRIPTION_CHARS: usize = 32;
+
+/// The maximum number of bytes of output which will be buffered while waiting for a newline: a
+/// process which writes more than this without a newline has its output rendered in pieces.
+pub(crate) const MAX_PARTIAL_LINE_BYTES: usize = 64 * 1024;
+
+/// Output which was not terminated by a newline, by stream.
+type Remainders = Vec<(OutputStream, Vec<u8>)>;
+
+///
+/// A ProcessOutputSink which streams the output of running processes to stderr line by line,
+/// with each line prefixed by a stable short identifier for the process (similar to
+/// `docker-compose`).
+///
+/// Unlike the dynamic UI, this renderer only ever appends to its output, which makes it suitable
+/// for watching long running processes in CI logs.
+///
+pub struct PlainOutputRenderer {
+    destination: Arc<Destination>,
+    state: Mutex<PlainOutputState>,
+}
+
+#[derive(Default)]
+pub(crate) struct PlainOutputState {
+    next_id: usize,
+    // The prefix for each process which has produced output.
+    prefixes: HashMap<SpanId, String>,
+    // Output which has not yet been terminated by a newline.
+    partial_lines: HashMap<(SpanId, OutputStream), Vec<u8>>,
+}
+
+impl PlainOutputState {
+    ///
+    /// Buffers the given output, and returns the prefix for the process and any lines which are
+    /// ready to be rendered.
+    ///
+    pub(crate) fn output(
+        &mut self,
+        span_id: SpanId,
+        description: &str,
+        stream: OutputStream,
+        bytes: &[u8],
+    ) -> (String, Vec<Vec<u8>>) {
+        let next_id = &mut self.next_id;
+        let prefix = self
+            .prefixes
+            .entry(span_id)
+            .or_insert_with(|| {
+                *next_id += 1;
+                PlainOutputRenderer::prefix(*next_id, description)
+            })
+            .clone();
+
+        let partial = self.partial_lines.entry((span_id, stream)).or_default();
+        partial.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(newline) = partial.iter().position(|b| *b == b'\n') {
+            let mut line = partial.drain(..=newline).collect::<Vec<_>>();
+            line.pop();
+            lines.push(line);
+        }
+        if partial.len() >= MAX_PARTIAL_LINE_BYTES {
+            lines.push(std::mem::take(partial));
+        }
+        (prefix, lines)
+    }
+
+    ///
+    /// Forgets the given process, and returns its prefix and any output which was not terminated
+    /// by a newline, or None if it did not produce any output.
+    ///
+    pub(crate) fn completed(&mut self, span_id: SpanId) -> Option<(String, Remainders)> {
+        let prefix = self.prefixes.remove(&span_id)?;
+        let remainders = [OutputStream::Stdout, OutputStream::Stderr]
+            .into_iter()
+            .filter_map(|stream| {
+                let partial = self.partial_lines.remove(&(span_id, stream))?;
+                if partial.is_empty() {
+                    None
+                } else {
+                    Some((stream, partial))
+                }
+            })
+            .collect::<Vec<_>>();
+        Some((prefix, remainders))
+    }
+}
+
+impl PlainOutputRenderer {
+    pub fn new(destination: Arc<Destination>) -> PlainOutputRenderer {
+        PlainOutputRenderer {
+            destination,
+            state: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn prefix(id: usize, description: &str) -> String {
+        let truncated: String = if description.chars().count() > MAX_PREFIX_DESCRIPTION_CHARS {
+            description
+                .chars()
+                .take(MAX_PREFIX_DESCRIPTION_CHARS - 3)
+                .chain("...".chars())
+                .collect()
+        } else {
+            description.to_owned()
+        };
+        format!("[{id}] {truncated:<MAX_PREFIX_DESCRIPTION_CHARS$} | ")
+    }
+
+    fn write_line(&self, prefix: &str, stream: OutputStream, line: &[u8]) {
+        let mut rendered = Vec::with_capacity(prefix.len() + line.len() + 1);
+        rendered.extend_from_slice(prefix.as_bytes());
+        if stream == OutputStream::Stderr {
+            rendered.extend_from_slice(b"(stderr) ");
+        }
+        rendered.extend_from_slice(line);
+        rendered.push(b'\n');
+        self.destination.write_stderr(&rendered);
+    }
+}
+
+impl ProcessOutputSink for PlainOutputRenderer {
+    fn output(&self, span_id: SpanId, description: &str, stream: OutputStream, bytes: &[u8]) {
+        // Split complete lines out while holding the lock, but write them after releasing it.
+        let (prefix, lines) = self
+            .state
+            .lock()
+            .output(span_id, description, stream, bytes);
+        for line in lines {
+            self.write_line(&prefix, stream, &line);
+        }
+    }
+
+    fn completed(&self, span_id: SpanId) {
+        let Some((prefix, remainders)) = self.state.lock().completed(span_id) else {
+            return;
+        };
+        for (stream, line) in remainders {
+            self.write_line(&prefix, stream, &line);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use workunit_store::{OutputStream, SpanId};
+
+use crate::plain::{PlainOutputRenderer, PlainOutputState, MAX_PARTIAL_LINE_BYTES};
+
+#[test]
+fn splits_complete_lines() {
+    let mut state = PlainOutputState::default();
+    let span_id = SpanId::new();
+
+    let (prefix, lines) = state.output(span_id, "Run pytest", OutputStream::Stdout, b"one\ntw");
+    assert_eq!(prefix, PlainOutputRenderer::prefix(1, "Run pytest"));
+    assert_eq!(lines, vec![b"one".to_vec()]);
+
+    let (_, lines) = state.output(span_id, "Run pytest", OutputStream::Stdout, b"o\nthree\n");
+    assert_eq!(lines, vec![b"two".to_vec(), b"three".to_vec()]);
+}
+
+#[test]
+fn streams_and_processes_are_buffered_independently() {
+    let mut state = PlainOutputState::default();
+    let (first, second) = (SpanId::new(), SpanId::new());
+
+    let (_, lines) = state.output(first, "first", OutputStream::Stdout, b"out");
+    assert!(lines.is_empty());
+    let (_, lines) = state.output(first, "first", OutputStream::Stderr, b"err\n");
+    assert_eq!(lines, vec![b"err".to_vec()]);
+    let (prefix, lines) = state.output(second, "second", OutputStream::Stdout, b"\n");
+    assert_eq!(prefix, PlainOutputRenderer::prefix(2, "second"));
+    assert_eq!(lines, vec![Vec::<u8>::new()]);
+
+    let (_, lines) = state.output(first, "first", OutputStream::Stdout, b"put\n");
+    assert_eq!(lines, vec![b"output".to_vec()]);
+}
+
+#[test]
+fn long_partial_lines_are_flushed() {
+    let mut state = PlainOutputState::default();
+    let span_id = SpanId::new();
+
+    let (_, lines) = state.output(
+        span_id,
+        "Run pytest",
+        OutputStream::Stdout,
+        &vec![b'a'; MAX_PARTIAL_LINE_BYTES - 1],
+    );
+    assert!(lines.is_empty());
+    let (_, lines) = state.output(span_id, "Run pytest", OutputStream::Stdout, b"ab");
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].len(), MAX_PARTIAL_LINE_BYTES + 1);
+
+    // Nothing remains buffered once the process completes.
+    let (_, remainders) = state.completed(span_id).unwrap();
+    assert!(remainders.is_empty());
+}
+
+#[test]
+fn completion_flushes_remainders_and_forgets_process() {
+    let mut state = PlainOutputState::default();
+    let span_id = SpanId::new();
+
+    state.output(
+        span_id,
+        "Run pytest",
+        OutputStream::Stdout,
+        b"done\nno newline",
+    );
+    state.output(span_id, "Run pytest", OutputStream::Stderr, b"warning");
+    let (prefix, remainders) = state.completed(span_id).unwrap();
+    assert_eq!(prefix, PlainOutputRenderer::prefix(1, "Run pytest"));
+    assert_eq!(
+        remainders,
+        vec![
+            (OutputStream::Stdout, b"no newline".to_vec()),
+            (OutputStream::Stderr, b"warning".to_vec()),
+        ]
+    );
+
+    assert!(state.completed(span_id).is_none());
+}
+
+#[test]
+fn long_descriptions_are_truncated() {
+    assert_eq!(
+        PlainOutputRenderer::prefix(3, "Run a process with a very long description indeed"),
+        "[3] Run a process with a very lon... | "
+    );
+    assert_eq!(
+        PlainOutputRenderer::prefix(4, "short"),
+        format!("[4] {:<32} | ", "short")
+    );
+}
@@ -0,0 +1,140 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::{CriticalPath, Metric};
+
+///
+/// An end-of-run summary of the counters recorded by a WorkunitStore.
+///
+/// This aggregates raw counters into the figures that users generally care about (cache hit
+/// ratios, transfer volumes, and where processes ran), rather than requiring them to be computed
+/// from individual counters.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BuildStats {
+    pub local_cache_requests: u64,
+    pub local_cache_hits: u64,
+    pub remote_cache_requests: u64,
+    pub remote_cache_hits: u64,
+    pub cas_bytes_uploaded: u64,
+    pub cas_bytes_downloaded: u64,
+    pub processes_run_locally: u64,
+    pub processes_run_remotely: u64,
+    pub processes_run_in_docker: u64,
+    pub time_saved_by_local_cache_ms: u64,
+    pub time_saved_by_remote_cache_ms: u64,
+    pub critical_path_ms: u64,
+}
+
+impl BuildStats {
+    pub(crate) fn new(counters: &HashMap<Metric, u64>, critical_path: &CriticalPath) -> BuildStats {
+        let get = |metric: Metric| counters.get(&metric).copied().unwrap_or(0);
+        BuildStats {
+            local_cache_requests: get(Metric::LocalCacheRequests),
+            local_cache_hits: get(Metric::LocalCacheRequestsCached),
+            remote_cache_requests: get(Metric::RemoteCacheRequests),
+            remote_cache_hits: get(Metric::RemoteCacheRequestsCached),
+            cas_bytes_uploaded: get(Metric::RemoteStoreBytesUploaded),
+            cas_bytes_downloaded: get(Metric::RemoteStoreBytesDownloaded),
+            processes_run_locally: get(Metric::LocalExecutionRequests),
+            processes_run_remotely: get(Metric::RemoteExecutionRequests),
+            processes_run_in_docker: get(Metric::DockerExecutionRequests),
+            time_saved_by_local_cache_ms: get(Metric::LocalCacheTotalTimeSavedMs),
+            time_saved_by_remote_cache_ms: get(Metric::RemoteCacheTotalTimeSavedMs),
+            critical_path_ms: critical_path.duration().as_millis() as u64,
+        }
+    }
+
+    /// The fraction of local cache lookups which hit, or None if there were no lookups.
+    pub fn local_cache_hit_ratio(&self) -> Option<f64> {
+        Self::ratio(self.local_cache_hits, self.local_cache_requests)
+    }
+
+    /// The fraction of remote cache lookups which hit, or None if there were no lookups.
+    pub fn remote_cache_hit_ratio(&self) -> Option<f64> {
+        Self::ratio(self.remote_cache_hits, self.remote_cache_requests)
+    }
+
+    fn ratio(numerator: u64, denominator: u64) -> Option<f64> {
+        if denominator == 0 {
+            None
+        } else {
+            Some(numerator as f64 / denominator as f64)
+        }
+    }
+
+    ///
+    /// Returns the summary as a flat list of named values, in a stable order.
+    ///
+    pub fn entries(&self) -> Vec<(&'static str, BuildStatValue)> {
+        use BuildStatValue::*;
+        vec![
+            ("local_cache_requests", Count(self.local_cache_requests)),
+            ("local_cache_hits", Count(self.local_cache_hits)),
+            ("local_cache_hit_ratio", Ratio(self.local_cache_hit_ratio())),
+            ("remote_cache_requests", Count(self.remote_cache_requests)),
+            ("remote_cache_hits", Count(self.remote_cache_hits)),
+            (
+                "remote_cache_hit_ratio",
+                Ratio(self.remote_cache_hit_ratio()),
+            ),
+            ("cas_bytes_uploaded", Bytes(self.cas_bytes_uploaded)),
+            ("cas_bytes_downloaded", Bytes(self.cas_bytes_downloaded)),
+            ("processes_run_locally", Count(self.processes_run_locally)),
+            ("processes_run_remotely", Count(self.processes_run_remotely)),
+            (
+                "processes_run_in_docker",
+                Count(self.processes_run_in_docker),
+            ),
+            (
+                "time_saved_by_local_cache_ms",
+                Millis(self.time_saved_by_local_cache_ms),
+            ),
+            (
+                "time_saved_by_remote_cache_ms",
+                Millis(self.time_saved_by_remote_cache_ms),
+            ),
+            ("critical_path_ms", Millis(self.critical_path_ms)),
+        ]
+    }
+
+    ///
+    /// Renders the summary as a two column, human readable table.
+    ///
+    pub fn render_table(&self) -> String {
+        let entries = self.entries();
+        let width = entries
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        let mut table = String::new();
+        for (name, value) in entries {
+            let _ = writeln!(table, "  {name:<width$}  {value}");
+        }
+        table
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BuildStatValue {
+    Count(u64),
+    Bytes(u64),
+    Millis(u64),
+    Ratio(Option<f64>),
+}
+
+impl std::fmt::Display for BuildStatValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildStatValue::Count(c) => write!(f, "{c}"),
+            BuildStatValue::Bytes(b) => write!(f, "{b} bytes"),
+            BuildStatValue::Millis(ms) => write!(f, "{:.2}s", (*ms as f64) / 1000.0),
+            BuildStatValue::Ratio(Some(r)) => write!(f, "{:.1}%", r * 100.0),
+            BuildStatValue::Ratio(None) => write!(f, "n/a"),
+        }
+    }
+}
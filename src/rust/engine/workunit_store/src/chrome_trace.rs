@@ -0,0 +1,204 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use concrete_time::TimeSpan;
+use serde_json::{json, Value as JsonValue};
+
+use crate::{SpanId, Workunit};
+
+///
+/// The track (rendered as a "process" in the Chrome trace format) that a workunit is rendered on.
+///
+/// Separating tracks makes it possible to look at (for example) only process executions without
+/// the noise of the rules which requested them.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TraceTrack {
+    Rules = 1,
+    Processes = 2,
+    Intrinsics = 3,
+    CacheLookups = 4,
+}
+
+impl TraceTrack {
+    const ALL: [TraceTrack; 4] = [
+        TraceTrack::Rules,
+        TraceTrack::Processes,
+        TraceTrack::Intrinsics,
+        TraceTrack::CacheLookups,
+    ];
+
+    fn for_workunit_name(name: &str) -> TraceTrack {
+        match name {
+            "process"
+            | "run_local_process"
+            | "run_local_process_in_workspace"
+            | "run_local_process_via_docker"
+            | "run_nailgun_process"
+            | "run_remote_process"
+            | "run_execute_request"
+            | "interactive_process" => TraceTrack::Processes,
+            "local_cache_read"
+            | "local_cache_write"
+            | "check_action_cache"
+            | "remote_cache_read_speculation"
+            | "remote_cache_write"
+            | "eager_fetch_action_cache"
+            | "eager_validate_action_cache" => TraceTrack::CacheLookups,
+            "snapshot" | "digest_file" | "downloaded_file" | "read_link" | "scandir"
+            | "path_metadata" => TraceTrack::Intrinsics,
+            n if n.starts_with("pants.engine.intrinsics.") => TraceTrack::Intrinsics,
+            _ => TraceTrack::Rules,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            TraceTrack::Rules => "Rules",
+            TraceTrack::Processes => "Processes",
+            TraceTrack::Intrinsics => "Intrinsics",
+            TraceTrack::CacheLookups => "Cache lookups",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct TraceEvent {
+    track: TraceTrack,
+    name: String,
+    workunit_name: &'static str,
+    span_id: SpanId,
+    start: Duration,
+    duration: Duration,
+}
+
+///
+/// Records completed workunits in order to render them as a Chrome trace-event JSON file, which
+/// can be loaded in `about://tracing` or https://ui.perfetto.dev.
+///
+#[derive(Default)]
+pub struct ChromeTrace {
+    events: Vec<TraceEvent>,
+}
+
+impl ChromeTrace {
+    pub(crate) fn record(&mut self, workunit: &Workunit, time_span: TimeSpan) {
+        // Disabled workunits have no metadata, and are not rendered.
+        let metadata = if let Some(metadata) = workunit.metadata.as_ref() {
+            metadata
+        } else {
+            return;
+        };
+        self.events.push(TraceEvent {
+            track: TraceTrack::for_workunit_name(workunit.name),
+            name: metadata
+                .desc
+                .clone()
+                .unwrap_or_else(|| workunit.name.to_owned()),
+            workunit_name: workunit.name,
+            span_id: workunit.span_id,
+            start: time_span.start.into(),
+            duration: time_span.duration.into(),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    ///
+    /// Render the recorded events as a Chrome trace-event JSON value.
+    ///
+    /// Concurrent events cannot be rendered on the same thread in the trace format, so each event is
+    /// assigned to the first "lane" (rendered as a thread) of its track which is free at its start
+    /// time.
+    ///
+    pub fn to_json(&self) -> JsonValue {
+        let mut events = self.events.iter().collect::<Vec<_>>();
+        events.sort_by_key(|e| (e.track, e.start, std::cmp::Reverse(e.duration)));
+        let epoch = events.iter().map(|e| e.start).min().unwrap_or_default();
+
+        let mut trace_events = Vec::with_capacity(events.len() + TraceTrack::ALL.len());
+        for track in TraceTrack::ALL {
+            trace_events.push(json!({
+                "name": "process_name",
+                "ph": "M",
+                "pid": track as u32,
+                "args": {"name": track.label()},
+            }));
+            trace_events.push(json!({
+                "name": "process_sort_index",
+                "ph": "M",
+                "pid": track as u32,
+                "args": {"sort_index": track as u32},
+            }));
+        }
+
+        let mut lanes: Vec<(TraceTrack, Vec<Duration>)> = Vec::new();
+        for event in events {
+            let track_index = match lanes.iter().position(|(track, _)| *track == event.track) {
+                Some(track_index) => track_index,
+                None => {
+                    lanes.push((event.track, Vec::new()));
+                    lanes.len() - 1
+                }
+            };
+            let lane_ends = &mut lanes[track_index].1;
+            let end = event.start + event.duration;
+            let lane = match lane_ends
+                .iter()
+                .position(|lane_end| *lane_end <= event.start)
+            {
+                Some(lane) => {
+                    lane_ends[lane] = end;
+                    lane
+                }
+                None => {
+                    lane_ends.push(end);
+                    lane_ends.len() - 1
+                }
+            };
+            trace_events.push(json!({
+                "name": event.name,
+                "cat": event.workunit_name,
+                "ph": "X",
+                "ts": (event.start - epoch).as_micros() as u64,
+                "dur": event.duration.as_micros() as u64,
+                "pid": event.track as u32,
+                "tid": lane,
+                "args": {"span_id": event.span_id.to_string()},
+            }));
+        }
+
+        json!({
+            "traceEvents": trace_events,
+            "displayTimeUnit": "ms",
+        })
+    }
+
+    ///
+    /// Write the recorded events to the given path, replacing any existing file.
+    ///
+    pub fn write_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {e}", parent.display()))?;
+        }
+        let mut file = std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .map_err(|e| format!("Failed to create {}: {e}", path.display()))?,
+        );
+        serde_json::to_writer(&mut file, &self.to_json())
+            .map_err(|e| format!("Failed to write Chrome trace to {}: {e}", path.display()))?;
+        file.flush()
+            .map_err(|e| format!("Failed to write Chrome trace to {}: {e}", path.display()))
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use concrete_time::TimeSpan;
+
+use crate::{ParentIds, SpanId, Workunit};
+
+///
+/// The minimal information about a completed workunit which is needed to compute a critical path.
+///
+#[derive(Clone, Debug)]
+struct CompletedSpan {
+    name: &'static str,
+    desc: Option<String>,
+    parent_ids: ParentIds,
+    start: Duration,
+    end: Duration,
+}
+
+///
+/// One link in the chain of a critical path.
+///
+/// `self_duration` is the portion of the `duration` of the workunit which was not spent waiting
+/// for the next (child) segment of the critical path, and which is attributable to this workunit.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CriticalPathSegment {
+    pub span_id: SpanId,
+    pub name: &'static str,
+    pub description: String,
+    pub time_span: TimeSpan,
+    pub self_duration: Duration,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CriticalPath {
+    pub segments: Vec<CriticalPathSegment>,
+}
+
+impl CriticalPath {
+    /// The total wall-clock length of the critical path.
+    pub fn duration(&self) -> Duration {
+        self.segments.iter().map(|s| s.self_duration).sum()
+    }
+}
+
+///
+/// Records the completed workunits of a run in order to compute the critical path: the chain of
+/// blocking work which determined the wall-clock time of the run.
+///
+/// Disabled workunits are recorded (without a description) so that parent links are preserved,
+/// but their time is attributed to their closest enabled ancestor on the path.
+///
+#[derive(Default)]
+pub(crate) struct CompletedSpans {
+    spans: HashMap<SpanId, CompletedSpan>,
+    // The critical path of the current `spans`, which is cleared when another span is recorded.
+    computed: Option<CriticalPath>,
+}
+
+impl CompletedSpans {
+    pub(crate) fn record(&mut self, workunit: &Workunit, time_span: TimeSpan) {
+        let start: Duration = time_span.start.into();
+        let duration: Duration = time_span.duration.into();
+        self.computed = None;
+        self.spans.insert(
+            workunit.span_id,
+            CompletedSpan {
+                name: workunit.name,
+                desc: workunit
+                    .metadata
+                    .as_ref()
+                    .map(|m| m.desc.clone().unwrap_or_else(|| workunit.name.to_owned())),
+                parent_ids: workunit.parent_ids.clone(),
+                start,
+                end: start + duration,
+            },
+        );
+    }
+
+    ///
+    /// Computes the critical path of the recorded workunits.
+    ///
+    /// Starting from the longest running root, the path repeatedly follows the child which
+    /// completed last, since that is the child that its parent was blocked on for longest.
+    ///
+    pub(crate) fn critical_path(&mut self) -> CriticalPath {
+        self.computed
+            .get_or_insert_with(|| Self::compute(&self.spans))
+            .clone()
+    }
+
+    fn compute(spans: &HashMap<SpanId, CompletedSpan>) -> CriticalPath {
+        let mut children: HashMap<SpanId, Vec<SpanId>> = HashMap::new();
+        let mut roots = Vec::new();
+        for (span_id, span) in spans {
+            let mut has_parent = false;
+            for parent_id in &span.parent_ids {
+                if spans.contains_key(parent_id) {
+                    children.entry(*parent_id).or_default().push(*span_id);
+                    has_parent = true;
+                }
+            }
+            if !has_parent {
+                roots.push(*span_id);
+            }
+        }
+
+        let longest = |candidates: &[SpanId], key: &dyn Fn(&CompletedSpan) -> Duration| {
+            candidates
+                .iter()
+                .max_by_key(|span_id| (key(&spans[*span_id]), **span_id))
+                .copied()
+        };
+
+        let mut chain = Vec::new();
+        let mut current = longest(&roots, &|s| s.end - s.start);
+        while let Some(span_id) = current {
+            chain.push(span_id);
+            current = children
+                .get(&span_id)
+                .and_then(|children| longest(children, &|s| s.end));
+        }
+
+        // Compute the self time of each link in the chain, and then attribute the self time of
+        // disabled workunits to their closest enabled ancestor.
+        let mut segments: Vec<CriticalPathSegment> = Vec::new();
+        let mut orphaned_self_duration = Duration::ZERO;
+        for (i, span_id) in chain.iter().enumerate() {
+            let span = &spans[span_id];
+            let duration = span.end.saturating_sub(span.start);
+            let self_duration = match chain.get(i + 1) {
+                Some(child_id) => {
+                    // Subtract the portion of the child which overlapped this workunit.
+                    let child = &spans[child_id];
+                    let overlap = child
+                        .end
+                        .min(span.end)
+                        .saturating_sub(child.start.max(span.start));
+                    duration.saturating_sub(overlap)
+                }
+                None => duration,
+            };
+            if let Some(desc) = &span.desc {
+                segments.push(CriticalPathSegment {
+                    span_id: *span_id,
+                    name: span.name,
+                    description: desc.clone(),
+                    time_span: TimeSpan {
+                        start: span.start.into(),
+                        duration: duration.into(),
+                    },
+                    self_duration: self_duration + std::mem::take(&mut orphaned_self_duration),
+                });
+            } else if let Some(previous) = segments.last_mut() {
+                previous.self_duration += self_duration;
+            } else {
+                orphaned_self_duration += self_duration;
+            }
+        }
+        CriticalPath { segments }
+    }
+}
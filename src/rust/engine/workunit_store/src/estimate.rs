@@ -0,0 +1,249 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use crate::{HeavyHittersData, SpanId, Workunit};
+
+/// The maximum number of entries which are retained in a `TimingHistory`.
+const MAX_TIMING_ENTRIES: usize = 10_000;
+
+/// The minimum weight of a new observation in the moving average of a duration, which bounds how
+/// long it takes for the average to adapt when the duration of some work changes.
+const MIN_OBSERVATION_WEIGHT: f64 = 0.2;
+
+///
+/// The historical durations of visible workunits, which are used to estimate the progress of a
+/// run: see `WorkunitStore::progress_estimate`.
+///
+/// Durations are recorded both per workunit name and description (which generally identifies the
+/// work precisely, e.g. the test of a particular target), and per workunit name alone (which is
+/// used as a fallback for work which has not been observed before).
+///
+#[derive(Debug, Default)]
+pub struct TimingHistory {
+    entries: HashMap<String, TimingEntry>,
+    // Incremented for each observation, to allow for evicting the least recently observed entries.
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct TimingEntry {
+    mean: Duration,
+    samples: u64,
+    last_observed: u64,
+}
+
+impl TimingHistory {
+    ///
+    /// Creates a TimingHistory from entries (as returned by `TimingHistory::entries`), ordered from
+    /// least to most recently observed.
+    ///
+    pub fn from_entries(entries: impl IntoIterator<Item = (String, Duration, u64)>) -> Self {
+        let mut history = TimingHistory::default();
+        for (key, mean, samples) in entries {
+            history.generation += 1;
+            history.entries.insert(
+                key,
+                TimingEntry {
+                    mean,
+                    samples,
+                    last_observed: history.generation,
+                },
+            );
+        }
+        history.evict();
+        history
+    }
+
+    ///
+    /// The key, mean duration and sample count of each entry, ordered from least to most recently
+    /// observed.
+    ///
+    pub fn entries(&self) -> Vec<(String, Duration, u64)> {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(_, entry)| entry.last_observed);
+        entries
+            .into_iter()
+            .map(|(key, entry)| (key.clone(), entry.mean, entry.samples))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    ///
+    /// Records the duration of a completed workunit with the given name and description.
+    ///
+    pub fn record(&mut self, name: &str, desc: &str, duration: Duration) {
+        self.observe(Self::key(name, desc), duration);
+        self.observe(name.to_owned(), duration);
+        self.evict();
+    }
+
+    ///
+    /// The expected duration of a workunit with the given name and description, if any similar
+    /// work has been observed.
+    ///
+    pub fn expected(&self, name: &str, desc: &str) -> Option<Duration> {
+        self.entries
+            .get(&Self::key(name, desc))
+            .or_else(|| self.entries.get(name))
+            .map(|entry| entry.mean)
+    }
+
+    fn key(name: &str, desc: &str) -> String {
+        format!("{name}:{desc}")
+    }
+
+    fn observe(&mut self, key: String, duration: Duration) {
+        self.generation += 1;
+        let generation = self.generation;
+        let entry = self.entries.entry(key).or_insert(TimingEntry {
+            mean: duration,
+            samples: 0,
+            last_observed: generation,
+        });
+        entry.samples += 1;
+        // A cumulative mean for the first few samples, and then an exponential moving average.
+        let weight = (1.0 / entry.samples as f64).max(MIN_OBSERVATION_WEIGHT);
+        let mean = entry.mean.as_secs_f64();
+        entry.mean = Duration::from_secs_f64(mean + (duration.as_secs_f64() - mean) * weight);
+        entry.last_observed = generation;
+    }
+
+    fn evict(&mut self) {
+        // Evict in batches, to avoid sorting the entries on each observation once the history is full.
+        if self.entries.len() <= MAX_TIMING_ENTRIES + MAX_TIMING_ENTRIES / 10 {
+            return;
+        }
+        let mut generations = self
+            .entries
+            .values()
+            .map(|entry| entry.last_observed)
+            .collect::<Vec<_>>();
+        generations.sort_unstable();
+        let cutoff = generations[generations.len() - MAX_TIMING_ENTRIES];
+        self.entries
+            .retain(|_, entry| entry.last_observed >= cutoff);
+    }
+}
+
+///
+/// An estimate of the progress of the work which has been scheduled in a WorkunitStore, based on
+/// the historical durations of similar work: see `WorkunitStore::progress_estimate`.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProgressEstimate {
+    /// The total duration of the visible work which has completed.
+    pub completed: Duration,
+    /// The estimated total duration of the visible work which is running or waiting to run.
+    pub remaining: Duration,
+    /// The estimated wall-clock time until the running and waiting work completes, given the
+    /// available parallelism.
+    pub eta: Duration,
+}
+
+impl ProgressEstimate {
+    pub fn fraction_complete(&self) -> f64 {
+        let total = self.completed + self.remaining;
+        if total.is_zero() {
+            return 0.0;
+        }
+        self.completed.as_secs_f64() / total.as_secs_f64()
+    }
+}
+
+impl fmt::Display for ProgressEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let eta_secs = self.eta.as_secs();
+        write!(
+            f,
+            "{:.0}% complete, about ",
+            (self.fraction_complete() * 100.0).floor()
+        )?;
+        if eta_secs >= 60 {
+            write!(f, "{}m{:02}s", eta_secs / 60, eta_secs % 60)?;
+        } else {
+            write!(f, "{eta_secs}s")?;
+        }
+        write!(f, " remaining")
+    }
+}
+
+///
+/// Tracks the visible work of a WorkunitStore which has completed, in order to estimate progress.
+///
+/// Only "leaf-most" visible workunits are counted: the durations of visible workunits which have
+/// visible children (such as a rule which runs a process) are accounted for by their children.
+///
+#[derive(Default)]
+pub(crate) struct ProgressEstimator {
+    // Running visible workunits which have had visible children.
+    composite: HashSet<SpanId>,
+    // The total duration of the leaf-most visible workunits which have completed.
+    completed: Duration,
+}
+
+impl ProgressEstimator {
+    pub(crate) fn started(&mut self, visible_parent_ids: HashSet<SpanId>) {
+        self.composite.extend(visible_parent_ids);
+    }
+
+    pub(crate) fn completed(&mut self, workunit: &Workunit) {
+        let composite = self.composite.remove(&workunit.span_id);
+        if composite || !HeavyHittersData::is_visible(workunit.level, Some(workunit)) {
+            return;
+        }
+        if let Some(time_span) = workunit.time_span() {
+            self.completed += Duration::from(time_span.duration);
+        }
+    }
+
+    pub(crate) fn canceled(&mut self, span_id: SpanId) {
+        self.composite.remove(&span_id);
+    }
+
+    pub(crate) fn is_composite(&self, span_id: SpanId) -> bool {
+        self.composite.contains(&span_id)
+    }
+
+    ///
+    /// Estimates progress, given the expected remaining duration of each leaf-most visible workunit
+    /// which is running or waiting to run (or None if there is no history for it).
+    ///
+    pub(crate) fn estimate(
+        &self,
+        remaining: impl IntoIterator<Item = Option<Duration>>,
+        parallelism: usize,
+    ) -> Option<ProgressEstimate> {
+        let mut pending = 0;
+        let mut estimated = 0;
+        let mut estimated_remaining = Duration::ZERO;
+        for expected in remaining {
+            pending += 1;
+            if let Some(expected) = expected {
+                estimated += 1;
+                estimated_remaining += expected;
+            }
+        }
+        if estimated == 0 {
+            return None;
+        }
+        // Work without any history is assumed to take as long as the average of the work which has it.
+        let remaining = estimated_remaining.mul_f64(pending as f64 / estimated as f64);
+        let lanes = parallelism.min(pending).max(1) as u32;
+        Some(ProgressEstimate {
+            completed: self.completed,
+            remaining,
+            eta: remaining / lanes,
+        })
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::time::Duration;
+
+use petgraph::visit::{VisitMap, Visitable};
+
+use crate::{Level, RunningWorkunitGraph, SpanId, Workunit};
+
+///
+/// What a running workunit is waiting on, if anything: see `RunningWorkunit::waiting_on`.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum WaitingOn {
+    /// The results of other Nodes: for example, the dependencies or `Get`s of an `@rule`.
+    Dependencies = 1,
+    /// A semaphore or pool: for example, the limit on concurrent local processes.
+    Semaphore = 2,
+    /// The Python GIL.
+    Gil = 3,
+    /// A process which is executing.
+    ProcessExecution = 4,
+    /// An RPC to a remote cache, store, or execution service.
+    RemoteRpc = 5,
+}
+
+impl WaitingOn {
+    pub(crate) fn from_u8(value: u8) -> Option<WaitingOn> {
+        match value {
+            1 => Some(WaitingOn::Dependencies),
+            2 => Some(WaitingOn::Semaphore),
+            3 => Some(WaitingOn::Gil),
+            4 => Some(WaitingOn::ProcessExecution),
+            5 => Some(WaitingOn::RemoteRpc),
+            _ => None,
+        }
+    }
+
+    ///
+    /// True if a workunit which is waiting on this is idle (rather than waiting on work which is
+    /// being done on its behalf). Idle workunits are considered to be "blocked", and are not
+    /// rendered as running by the UI.
+    ///
+    pub fn is_idle(self) -> bool {
+        matches!(
+            self,
+            WaitingOn::Dependencies | WaitingOn::Semaphore | WaitingOn::Gil
+        )
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WaitingOn::Dependencies => "dependencies",
+            WaitingOn::Semaphore => "semaphore",
+            WaitingOn::Gil => "gil",
+            WaitingOn::ProcessExecution => "process_execution",
+            WaitingOn::RemoteRpc => "remote_rpc",
+        }
+    }
+}
+
+///
+/// A visible workunit which is currently running: see `WorkunitStore::running_workunits`.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunningWorkunitInfo {
+    pub span_id: SpanId,
+    pub name: &'static str,
+    pub description: String,
+    pub duration: Duration,
+    /// What the workunit (or its invisible children) is waiting on, or None if it is running.
+    pub waiting_on: Option<WaitingOn>,
+    /// The nearest visible parents of the workunit.
+    pub parent_ids: Vec<SpanId>,
+}
+
+impl RunningWorkunitGraph {
+    ///
+    /// Returns what the given workunit is waiting on. If the workunit has not marked itself as
+    /// waiting, its running children which are not visible are consulted, since the work that they
+    /// do is attributed to it.
+    ///
+    pub(crate) fn waiting_on(
+        &self,
+        span_id: SpanId,
+        is_visible: impl Fn(Level, Option<&Workunit>) -> bool,
+    ) -> Option<WaitingOn> {
+        let mut visited = self.graph.visit_map();
+        let mut to_visit = vec![span_id];
+        while let Some(current_span_id) = to_visit.pop() {
+            let Some((node, level, workunit)) = self.entries.get(&current_span_id) else {
+                continue;
+            };
+            if !visited.visit(*node) {
+                continue;
+            }
+            if current_span_id != span_id && is_visible(*level, workunit.as_ref()) {
+                continue;
+            }
+            if let Some(waiting_on) = workunit.as_ref().and_then(|wu| wu.state.waiting_on()) {
+                return Some(waiting_on);
+            }
+            to_visit.extend(
+                self.graph
+                    .neighbors_directed(*node, petgraph::Direction::Outgoing)
+                    .map(|child_node_id| self.graph[child_node_id]),
+            );
+        }
+        None
+    }
+}
+
+///
+/// Renders the given running workunits as a human readable table, for dumping the state of a run.
+///
+pub fn render_running_workunits(running: &[RunningWorkunitInfo]) -> String {
+    let mut lines = vec![format!("{} running workunit(s):", running.len())];
+    for workunit in running {
+        lines.push(format!(
+            "  {:>8.1}s  {:<17}  {}",
+            workunit.duration.as_secs_f64(),
+            workunit.waiting_on.map_or("running", WaitingOn::as_str),
+            workunit.description
+        ));
+    }
+    lines.join("\n")
+}
@@ -7,27 +7,54 @@ use std::cmp::Reverse;
 use std::collections::{hash_map, BinaryHeap, HashMap, HashSet};
 use std::fmt::Debug;
 use std::future::Future;
-use std::sync::atomic::{self, AtomicBool};
+use std::path::Path;
+use std::sync::atomic::{self, AtomicU8};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
+pub use build_stats::{BuildStatValue, BuildStats};
 use bytes::{BufMut, Bytes, BytesMut};
+pub use chrome_trace::ChromeTrace;
 use concrete_time::TimeSpan;
+use critical_path::CompletedSpans;
+pub use critical_path::{CriticalPath, CriticalPathSegment};
 use deepsize::DeepSizeOf;
+use estimate::ProgressEstimator;
+pub use estimate::{ProgressEstimate, TimingHistory};
 use hdrhistogram::serialization::Serializer;
+pub use introspection::{render_running_workunits, RunningWorkunitInfo, WaitingOn};
 use log::log;
 pub use log::Level;
 pub use metrics::{Metric, ObservationMetric};
 use parking_lot::Mutex;
 use petgraph::stable_graph::{NodeIndex, StableDiGraph};
 use petgraph::visit::{VisitMap, Visitable};
+pub use process_output::{OutputStream, ProcessOutputSink};
+pub use prometheus::{MetricsAccumulator, PrometheusText};
 use rand::thread_rng;
 use rand::Rng;
+use sampling::Sampler;
+pub use sampling::{SampledWorkunits, WorkunitSampling};
 use smallvec::SmallVec;
+use streaming::StreamingState;
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tokio::task_local;
-
+pub use transfer::{TransferDirection, TransferProgress, TransferSnapshot};
+use watchdog::ProgressTracker;
+pub use watchdog::StalledWorkunit;
+
+mod build_stats;
+mod chrome_trace;
+mod critical_path;
+mod estimate;
+mod introspection;
 mod metrics;
+mod process_output;
+mod prometheus;
+mod sampling;
+mod streaming;
+mod transfer;
+mod watchdog;
 
 ///
 /// A unique id for a single run or `--loop` iteration of Pants within a single Scheduler.
@@ -204,8 +231,8 @@ impl RunningWorkunitGraph {
 ///
 /// While running (the Started state), a copy of a Workunit is generally kept on the stack by the
 /// `in_workunit!` macro, while another copy of the same Workunit is recorded in the WorkunitStore.
-/// Most of the fields of the Workunit are immutable, but an atomic "blocked" flag can be set to
-/// temporarily mark the running Workunit as being in a blocked state.
+/// Most of the fields of the Workunit are immutable, but an atomic "waiting on" value can be set to
+/// temporarily mark the running Workunit as waiting (and possibly blocked): see `WaitingOn`.
 ///
 /// When the `in_workunit!` macro exits, the Workunit on the stack is completed by storing any
 /// local mutated values as the final value of the Workunit.
@@ -290,7 +317,7 @@ impl Workunit {
 pub enum WorkunitState {
     Started {
         start_time: SystemTime,
-        blocked: Arc<AtomicBool>,
+        waiting_on: Arc<AtomicU8>,
     },
     Completed {
         time_span: TimeSpan,
@@ -299,9 +326,19 @@ pub enum WorkunitState {
 
 impl WorkunitState {
     fn blocked(&self) -> bool {
+        self.waiting_on().map_or(false, WaitingOn::is_idle)
+    }
+
+    ///
+    /// What the workunit is currently waiting on, if it is running and has marked itself as
+    /// waiting.
+    ///
+    pub fn waiting_on(&self) -> Option<WaitingOn> {
         match self {
-            WorkunitState::Started { blocked, .. } => blocked.load(atomic::Ordering::Relaxed),
-            WorkunitState::Completed { .. } => false,
+            WorkunitState::Started { waiting_on, .. } => {
+                WaitingOn::from_u8(waiting_on.load(atomic::Ordering::Relaxed))
+            }
+            WorkunitState::Completed { .. } => None,
         }
     }
 }
@@ -357,20 +394,36 @@ pub struct WorkunitStore {
     max_level: Level,
     senders: [UnboundedSender<StoreMsg>; 2],
     streaming_workunit_data: Arc<Mutex<StreamingWorkunitData>>,
+    // Wakes the streaming consumer when workunits are available, and applies backpressure when it
+    // falls behind.
+    streaming: Arc<StreamingState>,
     heavy_hitters_data: Arc<Mutex<HeavyHittersData>>,
     metrics_data: Arc<MetricsData>,
+    chrome_trace: Option<Arc<Mutex<ChromeTrace>>>,
+    completed_spans: Option<Arc<Mutex<CompletedSpans>>>,
+    // In-flight transfers, keyed by the workunit which is running them.
+    transfers: Arc<Mutex<HashMap<SpanId, Vec<TransferProgress>>>>,
+    process_output_sink: Option<Arc<dyn ProcessOutputSink>>,
+    session_id: Option<Arc<str>>,
+    sampler: Option<Arc<Mutex<Sampler>>>,
+    timing_history: Option<Arc<Mutex<TimingHistory>>>,
 }
 
 struct StreamingWorkunitData {
     receiver: UnboundedReceiver<StoreMsg>,
     running_graph: RunningWorkunitGraph,
+    streaming: Arc<StreamingState>,
 }
 
 impl StreamingWorkunitData {
-    fn new(receiver: UnboundedReceiver<StoreMsg>) -> StreamingWorkunitData {
+    fn new(
+        receiver: UnboundedReceiver<StoreMsg>,
+        streaming: Arc<StreamingState>,
+    ) -> StreamingWorkunitData {
         StreamingWorkunitData {
             receiver,
             running_graph: RunningWorkunitGraph::default(),
+            streaming,
         }
     }
 
@@ -382,7 +435,9 @@ impl StreamingWorkunitData {
 
         let mut started_workunits = Vec::new();
         let mut completed_workunits = Vec::new();
+        let mut received = 0;
         while let Ok(msg) = self.receiver.try_recv() {
+            received += 1;
             match msg {
                 StoreMsg::Started(mut started) => {
                     self.running_graph.add(started.clone());
@@ -414,6 +469,7 @@ impl StreamingWorkunitData {
                 StoreMsg::Canceled(..) => (),
             }
         }
+        self.streaming.received(received);
 
         (started_workunits, completed_workunits)
     }
@@ -422,6 +478,8 @@ impl StreamingWorkunitData {
 struct HeavyHittersData {
     receiver: UnboundedReceiver<StoreMsg>,
     running_graph: RunningWorkunitGraph,
+    progress: ProgressTracker,
+    estimator: ProgressEstimator,
 }
 
 impl HeavyHittersData {
@@ -429,23 +487,57 @@ impl HeavyHittersData {
         HeavyHittersData {
             receiver,
             running_graph: RunningWorkunitGraph::default(),
+            progress: ProgressTracker::default(),
+            estimator: ProgressEstimator::default(),
         }
     }
 
     fn refresh_store(&mut self) {
         while let Ok(msg) = self.receiver.try_recv() {
             match msg {
-                StoreMsg::Started(started) => self.running_graph.add(started),
-                StoreMsg::Completed(span_id, _level, new_metadata, time) => {
-                    let _ = self.running_graph.complete(span_id, new_metadata, time);
+                StoreMsg::Started(started) => {
+                    if let WorkunitState::Started { start_time, .. } = started.state {
+                        for parent_id in &started.parent_ids {
+                            self.progress.record(*parent_id, start_time);
+                        }
+                    }
+                    if Self::is_visible(started.level, Some(&started)) {
+                        self.estimator
+                            .started(self.running_graph.first_matched_parents(
+                                started.parent_ids.iter().copied(),
+                                Self::is_visible,
+                            ));
+                    }
+                    self.running_graph.add(started)
+                }
+                StoreMsg::Completed(span_id, level, new_metadata, time) => {
+                    self.record_completion_progress(span_id, time);
+                    if let Some(mut workunit) =
+                        self.running_graph.complete(span_id, new_metadata, time)
+                    {
+                        workunit.level = level;
+                        self.estimator.completed(&workunit);
+                    }
                 }
                 StoreMsg::Canceled(span_id, time) => {
+                    self.record_completion_progress(span_id, time);
+                    self.estimator.canceled(span_id);
                     let _ = self.running_graph.complete(span_id, None, time);
                 }
             }
         }
     }
 
+    /// The completion of a workunit is progress for its parents.
+    fn record_completion_progress(&mut self, span_id: SpanId, time: SystemTime) {
+        if let Some(workunit) = self.running_graph.get(span_id) {
+            for parent_id in &workunit.parent_ids {
+                self.progress.record(*parent_id, time);
+            }
+        }
+        self.progress.remove(span_id);
+    }
+
     fn heavy_hitters(&mut self, k: usize) -> HashMap<SpanId, (String, SystemTime)> {
         self.refresh_store();
 
@@ -478,6 +570,65 @@ impl HeavyHittersData {
         res
     }
 
+    fn progress_estimate(
+        &mut self,
+        parallelism: usize,
+        history: &TimingHistory,
+    ) -> Option<ProgressEstimate> {
+        self.refresh_store();
+        let now = SystemTime::now();
+
+        // Partition the leaf-most visible workunits into those which are running, and those which
+        // are only waiting for a slot to run in (and so have not really started yet).
+        let mut running = HashSet::new();
+        let mut waiting = HashSet::new();
+        for leaf in self
+            .running_graph
+            .graph
+            .externals(petgraph::Direction::Outgoing)
+            .map(|node| self.running_graph.graph[node])
+        {
+            let leaf_waiting_on = self
+                .running_graph
+                .get(leaf)
+                .and_then(|workunit| workunit.state.waiting_on());
+            let visible_parents = self
+                .running_graph
+                .first_matched_parents([leaf], Self::is_visible);
+            if leaf_waiting_on == Some(WaitingOn::Semaphore) {
+                waiting.extend(visible_parents);
+            } else {
+                running.extend(visible_parents);
+            }
+        }
+
+        let remaining = running
+            .iter()
+            .map(|span_id| (*span_id, true))
+            .chain(
+                waiting
+                    .difference(&running)
+                    .map(|span_id| (*span_id, false)),
+            )
+            .filter(|(span_id, _)| !self.estimator.is_composite(*span_id))
+            .filter_map(|(span_id, started)| {
+                let workunit = self.running_graph.get(span_id)?;
+                let desc = workunit.metadata.as_ref()?.desc.as_ref()?;
+                let elapsed = if started {
+                    Self::duration_for(now, workunit).unwrap_or_default()
+                } else {
+                    Duration::ZERO
+                };
+                Some(
+                    history
+                        .expected(workunit.name, desc)
+                        .map(|expected| expected.saturating_sub(elapsed)),
+                )
+            })
+            .collect::<Vec<_>>();
+        self.estimator.estimate(remaining, parallelism)
+    }
+
     fn straggling_workunits(&mut self, duration_threshold: Duration) -> Vec<(Duration, String)> {
         self.refresh_store();
         let now = SystemTime::now();
@@ -519,6 +670,143 @@ impl HeavyHittersData {
         stragglers
     }
 
+    fn running_workunits(&mut self) -> Vec<RunningWorkunitInfo> {
+        self.refresh_store();
+        let now = SystemTime::now();
+
+        let mut running = self
+            .running_graph
+            .entries
+            .iter()
+            .filter_map(|(span_id, (node, level, workunit))| {
+                let workunit = workunit.as_ref()?;
+                if !Self::is_visible(*level, Some(workunit)) {
+                    return None;
+                }
+                let duration = Self::duration_for(now, workunit)?;
+                let parent_ids = self.running_graph.first_matched_parents(
+                    self.running_graph
+                        .graph
+                        .neighbors_directed(*node, petgraph::Direction::Incoming)
+                        .map(|parent_node_id| self.running_graph.graph[parent_node_id]),
+                    Self::is_visible,
+                );
+                let mut parent_ids = parent_ids.into_iter().collect::<Vec<_>>();
+                parent_ids.sort();
+                Some(RunningWorkunitInfo {
+                    span_id: *span_id,
+                    name: workunit.name,
+                    description: workunit.metadata.as_ref()?.desc.clone()?,
+                    duration,
+                    waiting_on: self.running_graph.waiting_on(*span_id, Self::is_visible),
+                    parent_ids,
+                })
+            })
+            .collect::<Vec<_>>();
+        // Longest running first.
+        running.sort_by(|a, b| b.duration.cmp(&a.duration).then(a.span_id.cmp(&b.span_id)));
+        running
+    }
+
+    fn stalled_workunits(
+        &mut self,
+        threshold: Duration,
+        transferred_bytes: HashMap<SpanId, u64>,
+    ) -> Vec<StalledWorkunit> {
+        self.refresh_store();
+        let now = SystemTime::now();
+        self.progress.observe_transfers(transferred_bytes, now);
+
+        // Consider the visible parents of running (non-blocked) leaves of the graph, in the same way
+        // as `straggling_workunits`.
+        let mut stalled = self
+            .running_graph
+            .first_matched_parents(self.running_graph.running_leaves(), Self::is_visible)
+            .into_iter()
+            .filter_map(|span_id| {
+                let stalled_for = self
+                    .progress
+                    .stalled_for(&self.running_graph, span_id, now)?;
+                if stalled_for < threshold {
+                    return None;
+                }
+                let workunit = self.running_graph.get(span_id)?;
+                Some(StalledWorkunit {
+                    span_id,
+                    name: workunit.name,
+                    description: workunit.metadata.as_ref()?.desc.clone()?,
+                    stalled_for,
+                    ancestors: self.visible_ancestors(span_id),
+                })
+            })
+            .collect::<Vec<_>>();
+        // Longest stalled first.
+        stalled.sort_by(|a, b| {
+            b.stalled_for
+                .cmp(&a.stalled_for)
+                .then(a.span_id.cmp(&b.span_id))
+        });
+        stalled
+    }
+
+    fn stalled_for(
+        &mut self,
+        span_id: SpanId,
+        transferred_bytes: HashMap<SpanId, u64>,
+    ) -> Option<Duration> {
+        self.refresh_store();
+        let now = SystemTime::now();
+        self.progress.observe_transfers(transferred_bytes, now);
+        self.progress.stalled_for(&self.running_graph, span_id, now)
+    }
+
+    /// The names and descriptions of one chain of visible ancestors of the given workunit.
+    fn visible_ancestors(&self, span_id: SpanId) -> Vec<(&'static str, String)> {
+        let mut ancestors = Vec::new();
+        let mut current = span_id;
+        loop {
+            let Some(parent_ids) = self.running_graph.get(current).map(|wu| &wu.parent_ids) else {
+                break;
+            };
+            let Some(parent_id) = self
+                .running_graph
+                .first_matched_parents(parent_ids.iter().copied(), Self::is_visible)
+                .into_iter()
+                .min()
+            else {
+                break;
+            };
+            let Some(parent) = self.running_graph.get(parent_id) else {
+                break;
+            };
+            if let Some(desc) = parent.metadata.as_ref().and_then(|m| m.desc.clone()) {
+                ancestors.push((parent.name, desc));
+            }
+            current = parent_id;
+        }
+        ancestors
+    }
+
+    fn transfers_by_visible_parent(
+        &mut self,
+        transfers: Vec<(SpanId, TransferSnapshot)>,
+    ) -> HashMap<SpanId, TransferSnapshot> {
+        self.refresh_store();
+
+        let mut res: HashMap<SpanId, TransferSnapshot> = HashMap::new();
+        for (span_id, snapshot) in transfers {
+            for parent_id in self
+                .running_graph
+                .first_matched_parents([span_id], Self::is_visible)
+            {
+                res.entry(parent_id)
+                    .and_modify(|s| *s = s.merge(snapshot))
+                    .or_insert(snapshot);
+            }
+        }
+        res
+    }
+
     fn is_visible(level: Level, workunit: Option<&Workunit>) -> bool {
         level <= Level::Debug
             && workunit
@@ -547,18 +835,149 @@ impl WorkunitStore {
         // affects the total number of messages that might be queued at any given time.
         let (sender1, receiver1) = mpsc::unbounded_channel();
         let (sender2, receiver2) = mpsc::unbounded_channel();
+        let streaming = Arc::new(StreamingState::default());
         WorkunitStore {
             log_starting_workunits,
             max_level,
             // TODO: Create one `StreamingWorkunitData` per subscriber, and zero if no subscribers are
             // installed.
             senders: [sender1, sender2],
-            streaming_workunit_data: Arc::new(Mutex::new(StreamingWorkunitData::new(receiver1))),
+            streaming_workunit_data: Arc::new(Mutex::new(StreamingWorkunitData::new(
+                receiver1,
+                streaming.clone(),
+            ))),
+            streaming,
             heavy_hitters_data: Arc::new(Mutex::new(HeavyHittersData::new(receiver2))),
             metrics_data: Arc::default(),
+            chrome_trace: None,
+            completed_spans: None,
+            transfers: Arc::default(),
+            process_output_sink: None,
+            session_id: None,
+            sampler: None,
+            timing_history: None,
         }
     }
 
+    ///
+    /// Limits the number of workunits with a single name which are recorded individually: see
+    /// `WorkunitSampling`.
+    ///
+    pub fn with_sampling(mut self, sampling: WorkunitSampling) -> WorkunitStore {
+        self.sampler = Some(Arc::new(Mutex::new(Sampler::new(sampling))));
+        self
+    }
+
+    ///
+    /// Returns the aggregate counts of the workunits which were not recorded individually due to
+    /// sampling, by name, in descending order by count.
+    ///
+    pub fn sampled_workunits(&self) -> Vec<(&'static str, SampledWorkunits)> {
+        self.sampler
+            .as_ref()
+            .map(|sampler| sampler.lock().aggregates())
+            .unwrap_or_default()
+    }
+
+    ///
+    /// Returns whether a starting workunit should be recorded individually, along with its nearest
+    /// recorded parent.
+    ///
+    fn sample_start(
+        &self,
+        name: &'static str,
+        span_id: SpanId,
+        parent_id: Option<SpanId>,
+    ) -> (bool, Option<SpanId>) {
+        match self.sampler.as_ref() {
+            Some(sampler) => sampler.lock().start(name, span_id, parent_id),
+            None => (true, parent_id),
+        }
+    }
+
+    ///
+    /// Returns true if the given workunit was not recorded individually, in which case it has been
+    /// aggregated instead.
+    ///
+    fn sample_complete(&self, workunit: &Workunit, end_time: SystemTime) -> bool {
+        let Some(sampler) = self.sampler.as_ref() else {
+            return false;
+        };
+        let duration = match workunit.state {
+            WorkunitState::Started { start_time, .. } => end_time.duration_since(start_time).ok(),
+            WorkunitState::Completed { .. } => None,
+        };
+        sampler
+            .lock()
+            .complete(workunit.name, workunit.span_id, duration)
+    }
+
+    ///
+    /// Enables recording of completed workunits for rendering as a Chrome trace: see
+    /// `write_chrome_trace`.
+    ///
+    pub fn with_chrome_trace(mut self) -> WorkunitStore {
+        self.chrome_trace = Some(Arc::default());
+        self
+    }
+
+    ///
+    /// Enables recording of completed workunits in order to compute the critical path of the run:
+    /// see `critical_path`.
+    ///
+    pub fn with_critical_path(mut self) -> WorkunitStore {
+        self.completed_spans = Some(Arc::default());
+        self
+    }
+
+    ///
+    /// Installs a sink which will receive the output of processes while they run.
+    ///
+    pub fn with_process_output_sink(mut self, sink: Arc<dyn ProcessOutputSink>) -> WorkunitStore {
+        self.process_output_sink = Some(sink);
+        self
+    }
+
+    pub fn process_output_sink(&self) -> Option<Arc<dyn ProcessOutputSink>> {
+        self.process_output_sink.clone()
+    }
+
+    ///
+    /// Records the durations of completed visible workunits in the given (possibly shared)
+    /// TimingHistory, and uses it to estimate progress: see `progress_estimate`.
+    ///
+    pub fn with_timing_history(mut self, history: Arc<Mutex<TimingHistory>>) -> WorkunitStore {
+        self.timing_history = Some(history);
+        self
+    }
+
+    ///
+    /// Estimates the progress of the visible work which is running or waiting to run, based on the
+    /// historical durations of similar work.
+    ///
+    /// Returns None if no TimingHistory was installed, or if it contains no observations of the
+    /// pending work.
+    ///
+    pub fn progress_estimate(&self, parallelism: usize) -> Option<ProgressEstimate> {
+        let history = self.timing_history.as_ref()?;
+        self.heavy_hitters_data
+            .lock()
+            .progress_estimate(parallelism, &history.lock())
+    }
+
+    ///
+    /// Records the id of the Session which owns this store, so that it can be attached to output
+    /// (such as structured logs) produced while it is active.
+    ///
+    pub fn with_session_id(mut self, session_id: String) -> WorkunitStore {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
     pub fn init_thread_state(&self, parent_id: Option<SpanId>) {
         set_thread_workunit_store_handle(Some(WorkunitStoreHandle {
             store: self.clone(),
@@ -588,6 +1007,74 @@ impl WorkunitStore {
         self.heavy_hitters_data.lock().heavy_hitters(k)
     }
 
+    ///
+    /// Return all visible workunits which are currently running, longest running first, along with
+    /// what each of them is waiting on (if anything).
+    ///
+    pub fn running_workunits(&self) -> Vec<RunningWorkunitInfo> {
+        self.heavy_hitters_data.lock().running_workunits()
+    }
+
+    ///
+    /// Return the visible running workunits which have made no observable progress (neither
+    /// starting or completing child workunits, nor transferring bytes) for at least the given
+    /// threshold, longest stalled first. Workunits which are blocked are never considered stalled.
+    ///
+    pub fn stalled_workunits(&self, threshold: Duration) -> Vec<StalledWorkunit> {
+        let transferred_bytes = self.transferred_bytes();
+        self.heavy_hitters_data
+            .lock()
+            .stalled_workunits(threshold, transferred_bytes)
+    }
+
+    ///
+    /// Return how long the given running workunit (including its descendants) has made no
+    /// observable progress, or None if it is not running or is blocked.
+    ///
+    pub fn stalled_for(&self, span_id: SpanId) -> Option<Duration> {
+        let transferred_bytes = self.transferred_bytes();
+        self.heavy_hitters_data
+            .lock()
+            .stalled_for(span_id, transferred_bytes)
+    }
+
+    fn transferred_bytes(&self) -> HashMap<SpanId, u64> {
+        self.transfers
+            .lock()
+            .iter()
+            .map(|(span_id, transfers)| {
+                let bytes = transfers
+                    .iter()
+                    .map(|transfer| transfer.snapshot().bytes_done)
+                    .sum();
+                (*span_id, bytes)
+            })
+            .collect()
+    }
+
+    ///
+    /// Return the progress of in-flight transfers, aggregated by the first visible parent of the
+    /// workunit running each transfer (which will generally be one of the `heavy_hitters`).
+    ///
+    pub fn transfers_by_visible_parent(&self) -> HashMap<SpanId, TransferSnapshot> {
+        let transfers = self
+            .transfers
+            .lock()
+            .iter()
+            .flat_map(|(span_id, transfers)| {
+                transfers
+                    .iter()
+                    .map(move |transfer| (*span_id, transfer.snapshot()))
+            })
+            .collect::<Vec<_>>();
+        if transfers.is_empty() {
+            return HashMap::new();
+        }
+        self.heavy_hitters_data
+            .lock()
+            .transfers_by_visible_parent(transfers)
+    }
+
     fn send(&self, msg: StoreMsg) {
         let send_inner = |sender: &UnboundedSender<StoreMsg>, msg: StoreMsg| {
             sender
@@ -595,10 +1082,26 @@ impl WorkunitStore {
                 .unwrap_or_else(|_| panic!("Receivers are static, and should always be present."));
         };
         // Send clones to the first N-1 senders, and the owned value to the final sender.
+        self.streaming.sending();
         for sender in &self.senders[0..self.senders.len() - 1] {
             send_inner(sender, msg.clone());
         }
         send_inner(&self.senders[self.senders.len() - 1], msg);
+        self.streaming.sent();
+    }
+
+    ///
+    /// If a streaming consumer has fallen too far behind (see `next_workunits`), returns a future
+    /// which waits for it to catch up.
+    ///
+    /// NB: Public for macro use. Use `in_workunit!` instead.
+    ///
+    pub fn _streaming_backpressure(&self) -> Option<impl Future<Output = ()> + Send + 'static> {
+        if !self.streaming.is_saturated() {
+            return None;
+        }
+        let streaming = self.streaming.clone();
+        Some(async move { streaming.wait_for_capacity().await })
     }
 
     ///
@@ -612,6 +1115,7 @@ impl WorkunitStore {
         parent_id: Option<SpanId>,
         metadata: Option<WorkunitMetadata>,
     ) -> Workunit {
+        let (record, parent_id) = self.sample_start(name, span_id, parent_id);
         let started = Workunit {
             name,
             level,
@@ -619,12 +1123,14 @@ impl WorkunitStore {
             parent_ids: parent_id.into_iter().collect(),
             state: WorkunitState::Started {
                 start_time: std::time::SystemTime::now(),
-                blocked: Arc::new(AtomicBool::new(false)),
+                waiting_on: Arc::new(AtomicU8::new(0)),
             },
             metadata,
         };
 
-        self.send(StoreMsg::Started(started.clone()));
+        if record {
+            self.send(StoreMsg::Started(started.clone()));
+        }
 
         if self.log_starting_workunits {
             started.log_workunit_state(false)
@@ -638,10 +1144,11 @@ impl WorkunitStore {
 
     fn cancel_workunit(&self, workunit: Workunit) {
         workunit.log_workunit_state(true);
-        self.send(StoreMsg::Canceled(
-            workunit.span_id,
-            std::time::SystemTime::now(),
-        ));
+        let end_time = std::time::SystemTime::now();
+        if self.sample_complete(&workunit, end_time) {
+            return;
+        }
+        self.send(StoreMsg::Canceled(workunit.span_id, end_time));
     }
 
     fn complete_workunit_impl(&self, mut workunit: Workunit, end_time: SystemTime) {
@@ -649,7 +1156,10 @@ impl WorkunitStore {
         let span_id = workunit.span_id;
         let new_metadata = workunit.metadata.clone();
 
-        self.send(StoreMsg::Completed(span_id, level, new_metadata, end_time));
+        let sampled_out = self.sample_complete(&workunit, end_time);
+        if !sampled_out {
+            self.send(StoreMsg::Completed(span_id, level, new_metadata, end_time));
+        }
 
         let start_time = match workunit.state {
             WorkunitState::Started { start_time, .. } => start_time,
@@ -659,6 +1169,24 @@ impl WorkunitStore {
             }
         };
         let time_span = TimeSpan::from_start_and_end_systemtime(&start_time, &end_time);
+        if let Some(completed_spans) = self.completed_spans.as_ref() {
+            completed_spans.lock().record(&workunit, time_span);
+        }
+        if let Some(chrome_trace) = self.chrome_trace.as_ref().filter(|_| !sampled_out) {
+            chrome_trace.lock().record(&workunit, time_span);
+        }
+        if let Some(timing_history) = self.timing_history.as_ref() {
+            if let Some(desc) = workunit
+                .metadata
+                .as_ref()
+                .and_then(|m| m.desc.as_ref())
+                .filter(|_| HeavyHittersData::is_visible(level, Some(&workunit)))
+            {
+                timing_history
+                    .lock()
+                    .record(workunit.name, desc, time_span.duration.into());
+            }
+        }
         let new_state = WorkunitState::Completed { time_span };
         workunit.state = new_state;
         workunit.log_workunit_state(false);
@@ -674,6 +1202,7 @@ impl WorkunitStore {
         metadata: WorkunitMetadata,
     ) {
         let span_id = SpanId::new();
+        let (record, parent_id) = self.sample_start(name, span_id, parent_id);
 
         let workunit = Workunit {
             name,
@@ -682,12 +1211,14 @@ impl WorkunitStore {
             parent_ids: parent_id.into_iter().collect(),
             state: WorkunitState::Started {
                 start_time,
-                blocked: Arc::new(AtomicBool::new(false)),
+                waiting_on: Arc::new(AtomicU8::new(0)),
             },
             metadata: Some(metadata),
         };
 
-        self.send(StoreMsg::Started(workunit.clone()));
+        if record {
+            self.send(StoreMsg::Started(workunit.clone()));
+        }
         self.complete_workunit_impl(workunit, end_time);
     }
 
@@ -697,6 +1228,51 @@ impl WorkunitStore {
             .latest_workunits(max_verbosity)
     }
 
+    ///
+    /// Waits until at least one workunit at or above the given verbosity has started or completed,
+    /// and then returns all of the workunits which are available, as in `latest_workunits`.
+    ///
+    /// Workunits which arrive while the consumer is handling a previous batch are coalesced into the
+    /// next batch. Once the consumer has called this method, it is considered to be attached until
+    /// `close_workunit_stream` is called: while it is attached and has fallen far behind, new
+    /// workunits briefly wait for it to catch up before running.
+    ///
+    /// NB: This method is cancel safe: workunits are only consumed when it returns.
+    ///
+    pub async fn next_workunits(
+        &self,
+        max_verbosity: log::Level,
+    ) -> (Vec<Workunit>, Vec<Workunit>) {
+        self.streaming.attach();
+        loop {
+            self.streaming.start_waiting();
+            let (started, completed) = self.latest_workunits(max_verbosity);
+            if !started.is_empty() || !completed.is_empty() {
+                self.streaming.stop_waiting();
+                return (started, completed);
+            }
+            self.streaming.wait_for_messages().await;
+        }
+    }
+
+    ///
+    /// Detaches the consumer of `next_workunits`, so that new workunits no longer wait for it.
+    ///
+    pub fn close_workunit_stream(&self) {
+        self.streaming.detach();
+    }
+
+    ///
+    /// If Chrome trace recording was enabled for this store, writes all workunits which have
+    /// completed so far to the given path as Chrome trace-event JSON.
+    ///
+    pub fn write_chrome_trace(&self, path: &Path) -> Result<(), String> {
+        match self.chrome_trace.as_ref() {
+            Some(chrome_trace) => chrome_trace.lock().write_to(path),
+            None => Err("Chrome trace recording was not enabled for this run.".to_owned()),
+        }
+    }
+
     pub fn increment_counter(&self, counter_name: Metric, change: u64) {
         self.metrics_data
             .counters
@@ -714,6 +1290,27 @@ impl WorkunitStore {
             .collect()
     }
 
+    ///
+    /// Aggregates the counters recorded so far into an end-of-run summary.
+    ///
+    pub fn build_stats(&self) -> BuildStats {
+        let critical_path = self.critical_path();
+        BuildStats::new(&self.metrics_data.counters.lock(), &critical_path)
+    }
+
+    ///
+    /// Computes the critical path of the workunits which have completed so far: the longest chain
+    /// of blocking work, with each segment attributed to the workunit which was running.
+    ///
+    /// Empty unless critical path recording was enabled for this store: see `with_critical_path`.
+    ///
+    pub fn critical_path(&self) -> CriticalPath {
+        self.completed_spans
+            .as_ref()
+            .map(|completed_spans| completed_spans.lock().critical_path())
+            .unwrap_or_default()
+    }
+
     ///
     /// Records an observation of a time-like metric into a histogram.
     ///
@@ -830,6 +1427,15 @@ pub fn record_observation_if_in_workunit(metric: ObservationMetric, value: u64)
     }
 }
 
+/// If this thread has a workunit set which is tracking a transfer (see
+/// `RunningWorkunit::track_transfer`), return the transfer so that its progress may be reported.
+pub fn transfer_progress_if_in_workunit() -> Option<TransferProgress> {
+    let handle = get_workunit_store_handle()?;
+    let span_id = handle.parent_id?;
+    let transfers = handle.store.transfers.lock();
+    transfers.get(&span_id)?.first().cloned()
+}
+
 /// Run the given async block. If the level given by the WorkunitMetadata is above a configured
 /// threshold, the block will run inside of a workunit recorded in the workunit store.
 ///
@@ -843,6 +1449,7 @@ macro_rules! in_workunit {
     use futures::future::FutureExt;
     let mut store_handle = $crate::expect_workunit_store_handle();
     let level: log::Level  = $workunit_level;
+    let backpressure = store_handle.store._streaming_backpressure();
     let mut $workunit = {
       let workunit_metadata =
         if store_handle.store.max_level() >= level {
@@ -864,6 +1471,9 @@ macro_rules! in_workunit {
       $crate::RunningWorkunit::new(store_handle.store.clone(), workunit)
     };
     $crate::scope_task_workunit_store_handle(Some(store_handle), async move {
+      if let Some(backpressure) = backpressure {
+        backpressure.await;
+      }
       let result = {
         let $workunit = &mut $workunit;
         $f
@@ -879,6 +1489,7 @@ macro_rules! in_workunit {
 pub struct RunningWorkunit {
     store: WorkunitStore,
     workunit: Option<Workunit>,
+    tracks_transfers: bool,
 }
 
 impl RunningWorkunit {
@@ -886,6 +1497,30 @@ impl RunningWorkunit {
         RunningWorkunit {
             store,
             workunit: Some(workunit),
+            tracks_transfers: false,
+        }
+    }
+
+    ///
+    /// Reports the progress of the given transfer as part of this workunit until it completes, so
+    /// that it may be rendered by the ConsoleUI.
+    ///
+    pub fn track_transfer(&mut self, progress: &TransferProgress) {
+        if let Some(ref workunit) = self.workunit {
+            self.store
+                .transfers
+                .lock()
+                .entry(workunit.span_id)
+                .or_default()
+                .push(progress.clone());
+            self.tracks_transfers = true;
+        }
+    }
+
+    fn untrack_transfers(&mut self, span_id: SpanId) {
+        if self.tracks_transfers {
+            self.store.transfers.lock().remove(&span_id);
+            self.tracks_transfers = false;
         }
     }
 
@@ -917,14 +1552,22 @@ impl RunningWorkunit {
     }
 
     ///
-    /// Marks the workunit as being blocked until the returned token is dropped.
+    /// Marks the workunit as being blocked on its dependencies until the returned token is dropped.
     ///
     pub fn blocking(&mut self) -> BlockingWorkunitToken {
+        self.waiting_on(WaitingOn::Dependencies)
+    }
+
+    ///
+    /// Marks the workunit as waiting on the given resource until the returned token is dropped, at
+    /// which point whatever it was previously waiting on (if anything) is restored.
+    ///
+    pub fn waiting_on(&mut self, resource: WaitingOn) -> BlockingWorkunitToken {
         let mut token = BlockingWorkunitToken(None);
         if let Some(ref mut workunit) = self.workunit {
-            if let WorkunitState::Started { blocked, .. } = &mut workunit.state {
-                blocked.store(true, atomic::Ordering::Relaxed);
-                token.0 = Some(blocked.clone());
+            if let WorkunitState::Started { waiting_on, .. } = &mut workunit.state {
+                let previous = waiting_on.swap(resource as u8, atomic::Ordering::Relaxed);
+                token.0 = Some((waiting_on.clone(), previous));
             }
         }
         token
@@ -932,6 +1575,7 @@ impl RunningWorkunit {
 
     pub fn complete(&mut self) {
         if let Some(workunit) = self.workunit.take() {
+            self.untrack_transfers(workunit.span_id);
             self.store.complete_workunit(workunit);
         }
     }
@@ -940,17 +1584,18 @@ impl RunningWorkunit {
 impl Drop for RunningWorkunit {
     fn drop(&mut self) {
         if let Some(workunit) = self.workunit.take() {
+            self.untrack_transfers(workunit.span_id);
             self.store.cancel_workunit(workunit);
         }
     }
 }
 
-pub struct BlockingWorkunitToken(Option<Arc<AtomicBool>>);
+pub struct BlockingWorkunitToken(Option<(Arc<AtomicU8>, u8)>);
 
 impl Drop for BlockingWorkunitToken {
     fn drop(&mut self) {
-        if let Some(blocked) = self.0.take() {
-            blocked.store(false, atomic::Ordering::Relaxed);
+        if let Some((waiting_on, previous)) = self.0.take() {
+            waiting_on.store(previous, atomic::Ordering::Relaxed);
         }
     }
 }
@@ -53,9 +53,15 @@ pub enum Metric {
     RemoteStoreReadCached,
     RemoteStoreReadUncached,
     RemoteStoreReadErrors,
+    /// Number of remote CAS reads whose content did not match the requested digest.
+    RemoteStoreReadMismatches,
     RemoteStoreWriteAttempts,
     RemoteStoreWriteSuccesses,
     RemoteStoreWriteErrors,
+    /// Total number of bytes of blobs uploaded to a remote CAS.
+    RemoteStoreBytesUploaded,
+    /// Total number of bytes of blobs downloaded from a remote CAS.
+    RemoteStoreBytesDownloaded,
     /// Number of network requests to check existance of digests, not the total number of digests
     /// that were checked (if bulk query)
     RemoteStoreExistsAttempts,
@@ -68,6 +74,18 @@ pub enum Metric {
     DockerExecutionRequests,
     DockerExecutionSuccesses,
     DockerExecutionErrors,
+    /// Number of requests which were run in a nailgun server.
+    NailgunRequests,
+    /// Number of nailgun servers which were started.
+    NailgunServersStarted,
+    /// Number of requests which were run in a persistent worker.
+    PersistentWorkerRequests,
+    /// Number of persistent workers which were started.
+    PersistentWorkersStarted,
+    /// Number of processes which were run twice to verify that they are deterministic.
+    DeterminismVerificationRuns,
+    /// Number of verified processes whose two runs produced different results.
+    NondeterministicProcesses,
 }
 
 impl Metric {
@@ -102,4 +120,7 @@ pub enum ObservationMetric {
     RemoteCacheGetActionResultTimeMicros,
     /// Remote cache timing (in microseconds) for GetActionResult calls (network timing only).
     RemoteCacheGetActionResultNetworkTimeMicros,
+    /// The number of requests handled by a nailgun server over its lifetime (recorded when it
+    /// exits).
+    NailgunServerRequests,
 }
@@ -0,0 +1,30 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use crate::SpanId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+///
+/// A consumer of the output of running processes, which receives output as it is produced rather
+/// than when the process completes.
+///
+/// Implementations are called concurrently for many processes, and should not block.
+///
+pub trait ProcessOutputSink: Send + Sync + 'static {
+    ///
+    /// Called with each chunk of output produced by the process running in the given workunit.
+    /// Chunks are not guaranteed to align with line boundaries.
+    ///
+    fn output(&self, span_id: SpanId, description: &str, stream: OutputStream, bytes: &[u8]);
+
+    ///
+    /// Called once the process running in the given workunit has exited, and will produce no more
+    /// output.
+    ///
+    fn completed(&self, span_id: SpanId);
+}
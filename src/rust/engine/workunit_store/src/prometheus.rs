@@ -0,0 +1,158 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+//!
+//! Rendering of metrics in the Prometheus text exposition format: see
+//! <https://prometheus.io/docs/instrumenting/exposition_formats/>.
+//!
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use parking_lot::Mutex;
+use strum::IntoEnumIterator;
+
+use crate::{Metric, ObservationMetric, WorkunitStore};
+
+/// The prefix of the names of all exported metrics.
+const PREFIX: &str = "pants";
+
+/// The quantiles which are exported for each histogram.
+const QUANTILES: [f64; 4] = [0.5, 0.9, 0.99, 1.0];
+
+///
+/// Accumulates the metrics of many WorkunitStores (generally: of all of the Sessions that a
+/// long-lived process has run), so that they can be exported as monotonic totals.
+///
+#[derive(Default)]
+pub struct MetricsAccumulator {
+    counters: Mutex<HashMap<Metric, u64>>,
+    observations: Mutex<HashMap<ObservationMetric, hdrhistogram::Histogram<u64>>>,
+}
+
+impl MetricsAccumulator {
+    ///
+    /// Adds the counters and observations recorded so far by the given store. Should be called once
+    /// per store, when it will not record any more metrics.
+    ///
+    pub fn add(&self, store: &WorkunitStore) {
+        {
+            let mut counters = self.counters.lock();
+            for (metric, value) in store.metrics_data.counters.lock().iter() {
+                *counters.entry(*metric).or_insert(0) += value;
+            }
+        }
+        let mut observations = self.observations.lock();
+        for (metric, histogram) in store.metrics_data.observations.lock().iter() {
+            match observations.get_mut(metric) {
+                Some(total) => {
+                    if let Err(e) = total.add(histogram) {
+                        log::debug!("Failed to accumulate observations for {metric:?}: {e}");
+                    }
+                }
+                None => {
+                    observations.insert(*metric, histogram.clone());
+                }
+            }
+        }
+    }
+
+    ///
+    /// Renders all counters (including those which have never been incremented) and observations.
+    ///
+    pub fn render(&self, text: &mut PrometheusText) {
+        {
+            let counters = self.counters.lock();
+            for metric in Metric::iter() {
+                let value = counters.get(&metric).copied().unwrap_or(0);
+                text.counter(metric.into(), value);
+            }
+        }
+        let observations = self.observations.lock();
+        let mut observations = observations.iter().collect::<Vec<_>>();
+        observations.sort_by_key(|(metric, _)| <&'static str>::from(*metric));
+        for (metric, histogram) in observations {
+            text.summary(metric.into(), histogram);
+        }
+    }
+}
+
+///
+/// A buffer of metrics in the Prometheus text exposition format.
+///
+#[derive(Default)]
+pub struct PrometheusText {
+    buffer: String,
+}
+
+impl PrometheusText {
+    ///
+    /// Adds a monotonically increasing counter. The conventional `_total` suffix is appended.
+    ///
+    pub fn counter(&mut self, name: &str, value: u64) {
+        let name = format!("{PREFIX}_{name}_total");
+        let _ = writeln!(self.buffer, "# TYPE {name} counter");
+        let _ = writeln!(self.buffer, "{name} {value}");
+    }
+
+    ///
+    /// Adds a gauge, which is a value that may go up or down, optionally with labels.
+    ///
+    pub fn gauge<'a>(
+        &mut self,
+        name: &str,
+        help: &str,
+        values: impl IntoIterator<Item = (Vec<(&'a str, &'a str)>, u64)>,
+    ) {
+        let name = format!("{PREFIX}_{name}");
+        let _ = writeln!(self.buffer, "# HELP {name} {help}");
+        let _ = writeln!(self.buffer, "# TYPE {name} gauge");
+        for (labels, value) in values {
+            let _ = writeln!(self.buffer, "{name}{} {value}", Self::labels(&labels));
+        }
+    }
+
+    ///
+    /// Adds a summary of the given histogram, as a series of quantiles and a count and sum.
+    ///
+    pub fn summary(&mut self, name: &str, histogram: &hdrhistogram::Histogram<u64>) {
+        let name = format!("{PREFIX}_{name}");
+        let _ = writeln!(self.buffer, "# TYPE {name} summary");
+        for quantile in QUANTILES {
+            let _ = writeln!(
+                self.buffer,
+                "{name}{{quantile=\"{quantile}\"}} {}",
+                histogram.value_at_quantile(quantile)
+            );
+        }
+        let sum = histogram
+            .iter_recorded()
+            .map(|v| v.value_iterated_to() * v.count_at_value())
+            .sum::<u64>();
+        let _ = writeln!(self.buffer, "{name}_sum {sum}");
+        let _ = writeln!(self.buffer, "{name}_count {}", histogram.len());
+    }
+
+    fn labels(labels: &[(&str, &str)]) -> String {
+        if labels.is_empty() {
+            return String::new();
+        }
+        let labels = labels
+            .iter()
+            .map(|(k, v)| format!("{k}=\"{}\"", Self::escape(v)))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{{{labels}}}")
+    }
+
+    fn escape(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    pub fn into_string(self) -> String {
+        self.buffer
+    }
+}
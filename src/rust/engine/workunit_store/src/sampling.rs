@@ -0,0 +1,116 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::SpanId;
+
+///
+/// Limits on the number of workunits with a single name which are recorded individually.
+///
+/// Once more than `threshold` workunits with a name have started, only one in every
+/// `sample_interval` of the remainder are recorded (or none, if the interval is zero). Workunits
+/// which are not recorded are not streamed to consumers or rendered by the UI: instead, they are
+/// counted in aggregate by name (see `WorkunitStore::sampled_workunits`).
+///
+#[derive(Clone, Copy, Debug)]
+pub struct WorkunitSampling {
+    pub threshold: usize,
+    pub sample_interval: usize,
+}
+
+///
+/// The aggregate count and total duration of the workunits with a name which were not recorded
+/// individually due to sampling.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SampledWorkunits {
+    pub count: u64,
+    pub duration: Duration,
+}
+
+pub(crate) struct Sampler {
+    limits: WorkunitSampling,
+    // The number of workunits with each name which have started.
+    started: HashMap<&'static str, usize>,
+    // The nearest recorded ancestors of running workunits which were not recorded, so that their
+    // children can be attached to the ancestor instead.
+    running: HashMap<SpanId, Option<SpanId>>,
+    aggregates: HashMap<&'static str, SampledWorkunits>,
+}
+
+impl Sampler {
+    pub(crate) fn new(limits: WorkunitSampling) -> Sampler {
+        Sampler {
+            limits,
+            started: HashMap::new(),
+            running: HashMap::new(),
+            aggregates: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Decides whether the given starting workunit should be recorded, and returns its nearest
+    /// recorded parent along with the decision.
+    ///
+    pub(crate) fn start(
+        &mut self,
+        name: &'static str,
+        span_id: SpanId,
+        parent_id: Option<SpanId>,
+    ) -> (bool, Option<SpanId>) {
+        let parent_id = parent_id.and_then(|parent_id| {
+            self.running
+                .get(&parent_id)
+                .copied()
+                .unwrap_or(Some(parent_id))
+        });
+
+        let started = self.started.entry(name).or_insert(0);
+        *started += 1;
+        let record = match started.checked_sub(self.limits.threshold) {
+            None | Some(0) => true,
+            Some(above) => {
+                self.limits.sample_interval > 0 && above % self.limits.sample_interval == 0
+            }
+        };
+        if !record {
+            self.running.insert(span_id, parent_id);
+        }
+        (record, parent_id)
+    }
+
+    ///
+    /// Records the completion (or cancellation) of the given workunit, and returns true if it was
+    /// not recorded individually (in which case it has been added to the aggregates).
+    ///
+    pub(crate) fn complete(
+        &mut self,
+        name: &'static str,
+        span_id: SpanId,
+        duration: Option<Duration>,
+    ) -> bool {
+        if self.running.remove(&span_id).is_none() {
+            return false;
+        }
+        let aggregate = self.aggregates.entry(name).or_default();
+        aggregate.count += 1;
+        aggregate.duration += duration.unwrap_or_default();
+        true
+    }
+
+    ///
+    /// The aggregates for workunits which were not recorded individually, in descending order by
+    /// count.
+    ///
+    pub(crate) fn aggregates(&self) -> Vec<(&'static str, SampledWorkunits)> {
+        let mut aggregates = self
+            .aggregates
+            .iter()
+            .map(|(name, aggregate)| (*name, aggregate.clone()))
+            .collect::<Vec<_>>();
+        aggregates
+            .sort_by(|(a_name, a), (b_name, b)| b.count.cmp(&a.count).then(a_name.cmp(b_name)));
+        aggregates
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::sync::atomic::{self, AtomicBool, AtomicUsize};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+///
+/// The number of messages which a streaming consumer may fall behind by before new workunits wait
+/// for it to catch up.
+///
+pub(crate) const BACKLOG_LIMIT: usize = 10_000;
+
+///
+/// The longest that a new workunit will wait for a streaming consumer to catch up.
+///
+/// NB: The wait is bounded because a consumer may itself request work from the engine (which
+/// produces more workunits) while handling a batch, and must not deadlock by doing so.
+///
+pub(crate) const MAX_STALL: Duration = Duration::from_millis(100);
+
+///
+/// Coordinates the producers of workunits with the (optional) consumer which streams them: the
+/// consumer is only woken when it is waiting for workunits, and producers are slowed down while the
+/// consumer has fallen more than `BACKLOG_LIMIT` messages behind.
+///
+#[derive(Default)]
+pub(crate) struct StreamingState {
+    // The number of messages which have been sent, but not yet received by the consumer.
+    backlog: AtomicUsize,
+    consumer_attached: AtomicBool,
+    consumer_waiting: AtomicBool,
+    // Notified when a message is sent while the consumer is waiting.
+    available: Notify,
+    // Notified when the consumer receives messages while saturated, or detaches.
+    drained: Notify,
+}
+
+impl StreamingState {
+    ///
+    /// Must be called before a message is sent, so that the backlog never undercounts.
+    ///
+    pub(crate) fn sending(&self) {
+        self.backlog.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    ///
+    /// Must be called after a message is sent, to wake the consumer if it is waiting.
+    ///
+    pub(crate) fn sent(&self) {
+        // Pairs with the fence in `start_waiting`: either the consumer observes the message, or we
+        // observe that it is waiting.
+        atomic::fence(atomic::Ordering::SeqCst);
+        if self.consumer_waiting.load(atomic::Ordering::Relaxed)
+            && self.consumer_waiting.swap(false, atomic::Ordering::Relaxed)
+        {
+            self.available.notify_one();
+        }
+    }
+
+    pub(crate) fn received(&self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let previous = self.backlog.fetch_sub(count, atomic::Ordering::Relaxed);
+        if previous >= BACKLOG_LIMIT {
+            self.drained.notify_waiters();
+        }
+    }
+
+    pub(crate) fn attach(&self) {
+        self.consumer_attached
+            .store(true, atomic::Ordering::Relaxed);
+    }
+
+    pub(crate) fn detach(&self) {
+        self.consumer_attached
+            .store(false, atomic::Ordering::Relaxed);
+        self.drained.notify_waiters();
+    }
+
+    ///
+    /// Marks the consumer as waiting: must be called before the consumer checks for messages for
+    /// the last time before calling `wait_for_messages`.
+    ///
+    pub(crate) fn start_waiting(&self) {
+        self.consumer_waiting.store(true, atomic::Ordering::Relaxed);
+        atomic::fence(atomic::Ordering::SeqCst);
+    }
+
+    pub(crate) fn stop_waiting(&self) {
+        self.consumer_waiting
+            .store(false, atomic::Ordering::Relaxed);
+    }
+
+    pub(crate) async fn wait_for_messages(&self) {
+        self.available.notified().await;
+    }
+
+    pub(crate) fn is_saturated(&self) -> bool {
+        self.consumer_attached.load(atomic::Ordering::Relaxed)
+            && self.backlog.load(atomic::Ordering::Relaxed) >= BACKLOG_LIMIT
+    }
+
+    ///
+    /// Waits (for at most `MAX_STALL`) until the consumer is no longer saturated.
+    ///
+    pub(crate) async fn wait_for_capacity(&self) {
+        let drained = async {
+            loop {
+                // NB: Created before checking, so that a notification sent after the check is not
+                // missed.
+                let notified = self.drained.notified();
+                if !self.is_saturated() {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        let _ = tokio::time::timeout(MAX_STALL, drained).await;
+    }
+}
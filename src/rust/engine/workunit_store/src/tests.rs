@@ -2,11 +2,18 @@
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 use std::collections::HashSet;
 use std::sync::atomic;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use internment::Intern;
+use parking_lot::Mutex;
 
-use crate::{Level, ParentIds, SpanId, WorkunitMetadata, WorkunitState, WorkunitStore};
+use crate::streaming::{BACKLOG_LIMIT, MAX_STALL};
+use crate::{
+    BuildStatValue, CriticalPath, Level, Metric, MetricsAccumulator, ObservationMetric, ParentIds,
+    ProgressEstimate, PrometheusText, SpanId, TimingHistory, TransferDirection, TransferProgress,
+    WaitingOn, WorkunitMetadata, WorkunitSampling, WorkunitState, WorkunitStore,
+};
 
 #[test]
 fn heavy_hitters_basic() {
@@ -60,6 +67,77 @@ fn straggling_workunits_blocked_path() {
     assert!(ws.straggling_workunits(Duration::from_secs(0)).is_empty());
 }
 
+#[test]
+fn running_workunits_waiting_on() {
+    let ws = create_store(vec![wu_root(0), wu(2, 0)], vec![wu(1, 0)], vec![wu(4, 2)]);
+    // An invisible child which is executing a process is attributed to its visible parent.
+    let mut invisible = ws._start_workunit(SpanId(3), "3", Level::Trace, Some(SpanId(2)), None);
+    match &mut invisible.state {
+        WorkunitState::Started { waiting_on, .. } => {
+            waiting_on.store(WaitingOn::ProcessExecution as u8, atomic::Ordering::Relaxed)
+        }
+        _ => unreachable!(),
+    }
+
+    let mut running = ws
+        .running_workunits()
+        .into_iter()
+        .map(|info| (info.span_id, info.waiting_on, info.parent_ids))
+        .collect::<Vec<_>>();
+    running.sort_by_key(|(span_id, _, _)| *span_id);
+    assert_eq!(
+        running,
+        vec![
+            (SpanId(0), None, vec![]),
+            (SpanId(1), Some(WaitingOn::Dependencies), vec![SpanId(0)]),
+            (
+                SpanId(2),
+                Some(WaitingOn::ProcessExecution),
+                vec![SpanId(0)]
+            ),
+        ]
+    );
+    // Waiting on a process is not idle, so the parent is still rendered by the UI.
+    assert!(ws.heavy_hitters(3).contains_key(&SpanId(2)));
+}
+
+#[test]
+fn stalled_workunits() {
+    let ws = create_store(vec![wu_root(0), wu(1, 0)], vec![], vec![]);
+    let stalled = ws.stalled_workunits(Duration::ZERO);
+    assert_eq!(
+        stalled
+            .iter()
+            .map(|s| (s.span_id, s.ancestors.clone()))
+            .collect::<Vec<_>>(),
+        vec![(SpanId(1), vec![("0", "0".to_owned())])]
+    );
+    assert!(ws.stalled_workunits(Duration::from_secs(3600)).is_empty());
+    assert!(ws.stalled_for(SpanId(0)).is_some());
+}
+
+#[test]
+fn stalled_workunits_blocked() {
+    // Neither a blocked workunit nor its parents are stalled, since they are waiting in a queue.
+    let ws = create_store(vec![wu_root(0), wu(1, 0)], vec![wu(2, 1)], vec![]);
+    assert!(ws.stalled_workunits(Duration::ZERO).is_empty());
+    assert_eq!(ws.stalled_for(SpanId(0)), None);
+    assert_eq!(ws.stalled_for(SpanId(2)), None);
+}
+
+#[test]
+fn stalled_for_observes_child_completion() {
+    let ws = create_store(vec![wu_root(0)], vec![], vec![]);
+    let started = ws.stalled_for(SpanId(0)).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(ws.stalled_for(SpanId(0)).unwrap() >= started + Duration::from_millis(50));
+
+    // Completing a child is progress.
+    let child = ws._start_workunit(SpanId(1), "1", Level::Info, Some(SpanId(0)), None);
+    ws.complete_workunit(child);
+    assert!(ws.stalled_for(SpanId(0)).unwrap() < Duration::from_millis(50));
+}
+
 #[tokio::test]
 async fn disabled_workunit_is_filtered() {
     // Create a chain of completed workunits like: Info -> Trace -> Info (where `Trace` is below the
@@ -126,6 +204,351 @@ async fn workunit_escalation_is_recorded() {
     );
 }
 
+#[tokio::test]
+async fn next_workunits_waits_for_visible_workunits() {
+    let ws = WorkunitStore::new(false, Level::Trace);
+    let waiter = {
+        let ws = ws.clone();
+        tokio::spawn(async move { ws.next_workunits(Level::Info).await })
+    };
+
+    // A workunit below the requested verbosity should not wake the waiter.
+    ws.add_completed_workunit(
+        "hidden",
+        Level::Debug,
+        SystemTime::now(),
+        SystemTime::now(),
+        None,
+        WorkunitMetadata::default(),
+    );
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!waiter.is_finished());
+
+    ws.add_completed_workunit(
+        "visible",
+        Level::Info,
+        SystemTime::now(),
+        SystemTime::now(),
+        None,
+        WorkunitMetadata::default(),
+    );
+    let (started, completed) = tokio::time::timeout(Duration::from_secs(5), waiter)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        vec!["visible"],
+        started.iter().map(|wu| wu.name).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["visible"],
+        completed.iter().map(|wu| wu.name).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn streaming_backpressure_waits_for_attached_consumer() {
+    let ws = WorkunitStore::new(false, Level::Debug);
+    let fill = |ws: &WorkunitStore| {
+        for _ in 0..BACKLOG_LIMIT {
+            ws._start_workunit(SpanId::new(), "wu", Level::Debug, None, None);
+        }
+    };
+
+    // Without an attached consumer, workunits never wait.
+    fill(&ws);
+    assert!(ws._streaming_backpressure().is_none());
+
+    // Once attached, a consumer which has fallen behind causes workunits to wait until it catches up.
+    ws.next_workunits(Level::Debug).await;
+    fill(&ws);
+    let mut backpressure = Box::pin(ws._streaming_backpressure().unwrap());
+    assert!(futures::poll!(&mut backpressure).is_pending());
+    ws.latest_workunits(Level::Debug);
+    assert!(futures::poll!(&mut backpressure).is_ready());
+    assert!(ws._streaming_backpressure().is_none());
+
+    // And closing the stream releases any waiters.
+    fill(&ws);
+    let mut backpressure = Box::pin(ws._streaming_backpressure().unwrap());
+    assert!(futures::poll!(&mut backpressure).is_pending());
+    ws.close_workunit_stream();
+    assert!(futures::poll!(&mut backpressure).is_ready());
+}
+
+#[tokio::test(start_paused = true)]
+async fn streaming_backpressure_stall_is_bounded() {
+    let ws = WorkunitStore::new(false, Level::Debug);
+    let fill = |ws: &WorkunitStore| {
+        for _ in 0..BACKLOG_LIMIT {
+            ws._start_workunit(SpanId::new(), "wu", Level::Debug, None, None);
+        }
+    };
+    fill(&ws);
+    ws.next_workunits(Level::Debug).await;
+
+    // A consumer which never catches up only stalls new workunits for a bounded amount of time.
+    fill(&ws);
+    let start = tokio::time::Instant::now();
+    ws._streaming_backpressure().unwrap().await;
+    assert!(start.elapsed() >= MAX_STALL);
+    assert!(ws._streaming_backpressure().is_some());
+}
+
+#[test]
+fn chrome_trace_tracks_and_lanes() {
+    let ws = WorkunitStore::new(false, Level::Debug).with_chrome_trace();
+    let start = SystemTime::now();
+    let at = |millis: u64| start + Duration::from_millis(millis);
+    let completed = |name: &'static str, desc: &str, start_ms: u64, end_ms: u64| {
+        ws.add_completed_workunit(
+            name,
+            Level::Info,
+            at(start_ms),
+            at(end_ms),
+            None,
+            WorkunitMetadata {
+                desc: Some(desc.to_owned()),
+                ..WorkunitMetadata::default()
+            },
+        )
+    };
+    completed("run_local_process", "Run pytest", 0, 10);
+    completed("run_local_process", "Run mypy", 5, 20);
+    completed("local_cache_read", "Check the cache", 0, 1);
+    completed("some.rule", "Rule", 0, 30);
+
+    let json = ws.chrome_trace.as_ref().unwrap().lock().to_json();
+    let events = json["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|e| e["ph"] == "X")
+        .map(|e| {
+            (
+                e["name"].as_str().unwrap().to_owned(),
+                e["pid"].as_u64().unwrap(),
+                e["tid"].as_u64().unwrap(),
+                e["ts"].as_u64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        vec![
+            ("Rule".to_owned(), 1, 0, 0),
+            ("Run pytest".to_owned(), 2, 0, 0),
+            // Overlaps with the first process, and so is rendered in a second lane.
+            ("Run mypy".to_owned(), 2, 1, 5000),
+            ("Check the cache".to_owned(), 4, 0, 0),
+        ]
+    );
+}
+
+#[test]
+fn chrome_trace_disabled() {
+    let ws = WorkunitStore::new(false, Level::Debug);
+    assert!(ws
+        .write_chrome_trace(std::path::Path::new("/dev/null"))
+        .is_err());
+}
+
+#[test]
+fn build_stats_aggregates_counters() {
+    let ws = WorkunitStore::new(false, Level::Debug);
+    ws.increment_counter(Metric::LocalCacheRequests, 4);
+    ws.increment_counter(Metric::LocalCacheRequestsCached, 3);
+    ws.increment_counter(Metric::RemoteStoreBytesUploaded, 100);
+    ws.increment_counter(Metric::RemoteStoreBytesUploaded, 28);
+    ws.increment_counter(Metric::LocalExecutionRequests, 1);
+
+    let stats = ws.build_stats();
+    assert_eq!(stats.local_cache_hit_ratio(), Some(0.75));
+    assert_eq!(stats.remote_cache_hit_ratio(), None);
+    assert_eq!(stats.cas_bytes_uploaded, 128);
+    assert_eq!(stats.processes_run_locally, 1);
+    assert_eq!(stats.local_cache_hits, 3);
+
+    let entries = stats.entries();
+    assert!(entries.contains(&("local_cache_hit_ratio", BuildStatValue::Ratio(Some(0.75)))));
+    let table = stats.render_table();
+    assert!(table.contains("local_cache_hit_ratio"));
+    assert!(table.contains("75.0%"));
+}
+
+#[test]
+fn critical_path_follows_last_completed_child() {
+    let ws = WorkunitStore::new(false, Level::Info).with_critical_path();
+    let start = SystemTime::now();
+    let complete =
+        |span_id: u64, parent_id: Option<u64>, level: Level, start_ms: u64, end_ms: u64| {
+            let mut workunit = ws._start_workunit(
+                SpanId(span_id),
+                "wu",
+                level,
+                parent_id.map(SpanId),
+                if level <= Level::Info {
+                    Some(WorkunitMetadata {
+                        desc: Some(format!("{span_id}")),
+                        ..WorkunitMetadata::default()
+                    })
+                } else {
+                    None
+                },
+            );
+            if let WorkunitState::Started { start_time, .. } = &mut workunit.state {
+                *start_time = start + Duration::from_millis(start_ms);
+            }
+            ws.complete_workunit_impl(workunit, start + Duration::from_millis(end_ms));
+        };
+
+    //   0: [0, 100]
+    //     1: [0, 40]
+    //     2: [10, 90] (disabled)
+    //       3: [20, 60]
+    //       4: [30, 80]
+    complete(1, Some(0), Level::Info, 0, 40);
+    complete(3, Some(2), Level::Info, 20, 60);
+    complete(4, Some(2), Level::Info, 30, 80);
+    complete(2, Some(0), Level::Debug, 10, 90);
+    complete(0, None, Level::Info, 0, 100);
+
+    let critical_path = ws.critical_path();
+    assert_eq!(
+        critical_path
+            .segments
+            .iter()
+            .map(|s| (s.description.as_str(), s.self_duration.as_millis()))
+            .collect::<Vec<_>>(),
+        // The self time of the disabled workunit is attributed to its enabled parent.
+        vec![("0", 50), ("4", 50)]
+    );
+    assert_eq!(critical_path.duration(), Duration::from_millis(100));
+    assert_eq!(ws.build_stats().critical_path_ms, 100);
+
+    // A longer root completing afterward replaces the previously computed path.
+    complete(5, None, Level::Info, 0, 200);
+    assert_eq!(ws.critical_path().duration(), Duration::from_millis(200));
+}
+
+#[test]
+fn critical_path_not_recorded_by_default() {
+    let ws = WorkunitStore::new(false, Level::Info);
+    let workunit = ws._start_workunit(
+        SpanId(0),
+        "wu",
+        Level::Info,
+        None,
+        Some(WorkunitMetadata::default()),
+    );
+    ws.complete_workunit(workunit);
+    assert_eq!(ws.critical_path(), CriticalPath::default());
+    assert_eq!(ws.build_stats().critical_path_ms, 0);
+}
+
+#[tokio::test]
+async fn transfers_are_reported_for_visible_parent() {
+    let ws = WorkunitStore::new(false, Level::Debug);
+    ws.init_thread_state(None);
+
+    in_workunit!(
+        "parent",
+        Level::Info,
+        desc = Some("Parent".to_owned()),
+        |_parent| async move {
+            let ws2 = ws.clone();
+            in_workunit!("load", Level::Trace, |workunit| async move {
+                let progress = TransferProgress::new(TransferDirection::Download, Some(100));
+                workunit.track_transfer(&progress);
+                progress.add_bytes(40);
+
+                let transfers = ws2.transfers_by_visible_parent();
+                assert_eq!(transfers.len(), 1);
+                let snapshot = transfers.values().next().unwrap();
+                assert_eq!(snapshot.bytes_done, 40);
+                assert_eq!(snapshot.bytes_total, Some(100));
+                assert_eq!(snapshot.direction, Some(TransferDirection::Download));
+            })
+            .await;
+
+            // Once the transferring workunit has completed, its transfer is no longer reported.
+            assert!(ws.transfers_by_visible_parent().is_empty());
+        }
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn transfer_progress_is_available_within_workunit() {
+    let ws = WorkunitStore::new(false, Level::Debug);
+    ws.init_thread_state(None);
+
+    in_workunit!("store", Level::Trace, |workunit| async move {
+        assert!(crate::transfer_progress_if_in_workunit().is_none());
+
+        let progress = TransferProgress::new(TransferDirection::Upload, Some(100));
+        workunit.track_transfer(&progress);
+        // Progress reported without access to the workunit is observed by the tracked transfer.
+        crate::transfer_progress_if_in_workunit()
+            .unwrap()
+            .add_bytes(40);
+        assert_eq!(progress.snapshot().bytes_done, 40);
+
+        // And completing the transfer accounts for the bytes which were not reported.
+        progress.complete();
+        assert_eq!(progress.snapshot().bytes_done, 100);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn high_cardinality_workunits_are_sampled() {
+    let ws = WorkunitStore::new(false, Level::Trace).with_sampling(WorkunitSampling {
+        threshold: 2,
+        sample_interval: 3,
+    });
+    ws.init_thread_state(None);
+
+    in_workunit!("parent", Level::Info, |_parent| async move {
+        for i in 0..8 {
+            in_workunit!("digest_file", Level::Info, |_workunit| async move {
+                if i == 3 {
+                    in_workunit!("child", Level::Info, |_child| async {}).await;
+                }
+            })
+            .await;
+        }
+    })
+    .await;
+
+    // The first two workunits are recorded, and then one in every three of the remainder.
+    let (_, completed) = ws.latest_workunits(Level::Info);
+    let recorded = |name: &str| {
+        completed
+            .iter()
+            .filter(|wu| wu.name == name)
+            .collect::<Vec<_>>()
+    };
+    let parent = recorded("parent")[0].span_id;
+    assert_eq!(recorded("digest_file").len(), 4);
+    assert!(recorded("digest_file")
+        .iter()
+        .all(|wu| wu.parent_ids.iter().eq([parent].iter())));
+
+    // The children of unrecorded workunits are attached to their nearest recorded ancestor.
+    let child = recorded("child");
+    assert_eq!(child.len(), 1);
+    assert!(child[0].parent_ids.iter().eq([parent].iter()));
+
+    assert_eq!(
+        ws.sampled_workunits()
+            .into_iter()
+            .map(|(name, aggregate)| (name, aggregate.count))
+            .collect::<Vec<_>>(),
+        vec![("digest_file", 4)]
+    );
+}
+
 #[test]
 fn workunit_span_id_has_16_digits_len_hex_format() {
     let number: u64 = 1;
@@ -149,6 +572,89 @@ fn hex_16_digit_string_actually_uses_input_number() {
     );
 }
 
+#[test]
+fn timing_history_falls_back_to_name() {
+    let mut history = TimingHistory::default();
+    history.record("test", "a", Duration::from_secs(10));
+    history.record("test", "a", Duration::from_secs(20));
+    history.record("lint", "a", Duration::from_secs(60));
+
+    assert_eq!(history.expected("test", "a"), Some(Duration::from_secs(15)));
+    assert_eq!(history.expected("lint", "b"), Some(Duration::from_secs(60)));
+    assert_eq!(history.expected("fmt", "a"), None);
+
+    // Entries roundtrip, and are ordered from least to most recently observed.
+    let entries = history.entries();
+    assert_eq!(
+        entries
+            .iter()
+            .map(|(key, _, _)| key.as_str())
+            .collect::<Vec<_>>(),
+        vec!["test:a", "test", "lint:a", "lint"]
+    );
+    let restored = TimingHistory::from_entries(entries);
+    assert_eq!(
+        restored.expected("test", "a"),
+        Some(Duration::from_secs(15))
+    );
+}
+
+#[test]
+fn progress_estimate() {
+    let history = Arc::new(Mutex::new(TimingHistory::default()));
+    history.lock().record("1", "1", Duration::from_secs(10));
+    history.lock().record("2", "2", Duration::from_secs(30));
+    let ws = WorkunitStore::new(false, Level::Debug).with_timing_history(history.clone());
+    let start = |span_id: u64, parent_id: Option<u64>| {
+        let (level, span_id, parent_id, metadata) = wu_level(span_id, parent_id, Level::Info);
+        let name = Intern::new(format!("{}", span_id.0)).as_ref();
+        ws._start_workunit(span_id, name, level, parent_id, Some(metadata))
+    };
+
+    // The root has visible children, so its own duration is not estimated.
+    let _root = start(0, None);
+    let running = start(1, Some(0));
+    // A workunit whose only child is waiting on a semaphore has not started yet, and so is expected
+    // to take its full duration.
+    let _waiting = start(2, Some(0));
+    let queued = ws._start_workunit(SpanId(3), "3", Level::Trace, Some(SpanId(2)), None);
+    match &queued.state {
+        WorkunitState::Started { waiting_on, .. } => {
+            waiting_on.store(WaitingOn::Semaphore as u8, atomic::Ordering::Relaxed)
+        }
+        _ => unreachable!(),
+    }
+
+    let estimate = ws.progress_estimate(4).unwrap();
+    assert_eq!(estimate.completed, Duration::ZERO);
+    assert!(estimate.remaining > Duration::from_secs(39));
+    assert!(estimate.remaining <= Duration::from_secs(40));
+    assert_eq!(estimate.eta, estimate.remaining / 2);
+
+    // Completing work counts its actual duration as complete, and records it in the history.
+    ws.complete_workunit(running);
+    let estimate = ws.progress_estimate(4).unwrap();
+    assert!(estimate.completed < Duration::from_secs(1));
+    assert_eq!(estimate.remaining, Duration::from_secs(30));
+    assert_eq!(estimate.eta, Duration::from_secs(30));
+    assert!(history.lock().expected("1", "1").unwrap() < Duration::from_secs(10));
+
+    // Without a history, there is no estimate.
+    let (ws, _) = WorkunitStore::setup_for_tests();
+    assert_eq!(ws.progress_estimate(4), None);
+}
+
+#[test]
+fn progress_estimate_display() {
+    let estimate = ProgressEstimate {
+        completed: Duration::from_secs(30),
+        remaining: Duration::from_secs(90),
+        eta: Duration::from_secs(75),
+    };
+    assert_eq!(estimate.fraction_complete(), 0.25);
+    assert_eq!(estimate.to_string(), "25% complete, about 1m15s remaining");
+}
+
 fn create_store(
     started: Vec<AnonymousWorkunit>,
     blocked: Vec<AnonymousWorkunit>,
@@ -193,8 +699,8 @@ fn create_store(
     for mut workunit in workunits {
         if blocked_ids.contains(&workunit.span_id) {
             match &mut workunit.state {
-                WorkunitState::Started { blocked, .. } => {
-                    blocked.store(true, atomic::Ordering::Relaxed)
+                WorkunitState::Started { waiting_on, .. } => {
+                    waiting_on.store(WaitingOn::Dependencies as u8, atomic::Ordering::Relaxed)
                 }
                 _ => unreachable!(),
             }
@@ -224,3 +730,28 @@ fn wu_level(span_id: u64, parent_id: Option<u64>, level: Level) -> AnonymousWork
     metadata.desc = Some(format!("{span_id}"));
     (level, SpanId(span_id), parent_id.map(SpanId), metadata)
 }
+
+#[test]
+fn metrics_accumulator_renders_totals() {
+    let accumulator = MetricsAccumulator::default();
+    for _ in 0..2 {
+        let ws = WorkunitStore::new(false, Level::Debug);
+        ws.increment_counter(Metric::LocalCacheRequests, 3);
+        ws.record_observation(ObservationMetric::LocalProcessTimeRunMs, 10);
+        accumulator.add(&ws);
+    }
+
+    let mut text = PrometheusText::default();
+    accumulator.render(&mut text);
+    text.gauge("graph_nodes", "Nodes.", [(vec![("kind", "a\"b")], 7)]);
+    let text = text.into_string();
+
+    assert!(text.contains(
+        "# TYPE pants_local_cache_requests_total counter\npants_local_cache_requests_total 6\n"
+    ));
+    // Counters which were never incremented are rendered as zero.
+    assert!(text.contains("pants_local_cache_requests_cached_total 0\n"));
+    assert!(text.contains("pants_local_process_time_run_ms_count 2\n"));
+    assert!(text.contains("pants_local_process_time_run_ms_sum 20\n"));
+    assert!(text.contains("pants_graph_nodes{kind=\"a\\\"b\"} 7\n"));
+}
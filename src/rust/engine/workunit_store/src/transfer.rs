@@ -0,0 +1,158 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferDirection {
+    Upload,
+    Download,
+}
+
+///
+/// A handle used to report the progress of a transfer of bytes (to or from a remote store, or
+/// for a file download) which is running within a workunit.
+///
+/// See `RunningWorkunit::track_transfer`.
+///
+#[derive(Clone, Debug)]
+pub struct TransferProgress(Arc<TransferState>);
+
+#[derive(Debug)]
+struct TransferState {
+    direction: TransferDirection,
+    started: Instant,
+    bytes_done: AtomicU64,
+    // Zero if the total is not known.
+    bytes_total: AtomicU64,
+}
+
+impl TransferProgress {
+    pub fn new(direction: TransferDirection, bytes_total: Option<u64>) -> TransferProgress {
+        TransferProgress(Arc::new(TransferState {
+            direction,
+            started: Instant::now(),
+            bytes_done: AtomicU64::new(0),
+            bytes_total: AtomicU64::new(bytes_total.unwrap_or(0)),
+        }))
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.0.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn set_total(&self, bytes_total: u64) {
+        self.0.bytes_total.store(bytes_total, Ordering::Relaxed);
+    }
+
+    ///
+    /// Resets the count of bytes done, for use when a transfer is retried from the beginning.
+    ///
+    pub fn reset(&self) {
+        self.0.bytes_done.store(0, Ordering::Relaxed);
+    }
+
+    ///
+    /// Marks all bytes of the transfer as done, for use when progress was not reported as the
+    /// transfer ran.
+    ///
+    pub fn complete(&self) {
+        let bytes_total = self.0.bytes_total.load(Ordering::Relaxed);
+        self.0.bytes_done.fetch_max(bytes_total, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TransferSnapshot {
+        let bytes_total = self.0.bytes_total.load(Ordering::Relaxed);
+        TransferSnapshot {
+            direction: Some(self.0.direction),
+            bytes_done: self.0.bytes_done.load(Ordering::Relaxed),
+            bytes_total: if bytes_total == 0 {
+                None
+            } else {
+                Some(bytes_total)
+            },
+            elapsed: self.0.started.elapsed(),
+        }
+    }
+}
+
+///
+/// A point-in-time view of one or more transfers.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransferSnapshot {
+    /// The direction of the transfer(s), or None if both uploads and downloads are included.
+    pub direction: Option<TransferDirection>,
+    pub bytes_done: u64,
+    /// The total size of the transfer(s), if it is known for all of them.
+    pub bytes_total: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl TransferSnapshot {
+    pub fn bytes_per_second(&self) -> Option<u64> {
+        let elapsed_ms = self.elapsed.as_millis() as u64;
+        if elapsed_ms == 0 {
+            None
+        } else {
+            Some(self.bytes_done * 1000 / elapsed_ms)
+        }
+    }
+
+    ///
+    /// Combines two snapshots of concurrent transfers.
+    ///
+    pub fn merge(self, other: TransferSnapshot) -> TransferSnapshot {
+        TransferSnapshot {
+            direction: if self.direction == other.direction {
+                self.direction
+            } else {
+                None
+            },
+            bytes_done: self.bytes_done + other.bytes_done,
+            bytes_total: self
+                .bytes_total
+                .and_then(|t| other.bytes_total.map(|o| t + o)),
+            elapsed: std::cmp::max(self.elapsed, other.elapsed),
+        }
+    }
+}
+
+impl fmt::Display for TransferSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arrow = match self.direction {
+            Some(TransferDirection::Upload) => "↑",
+            Some(TransferDirection::Download) => "↓",
+            None => "↕",
+        };
+        write!(f, "{arrow} {}", HumanBytes(self.bytes_done))?;
+        if let Some(bytes_total) = self.bytes_total {
+            write!(f, "/{}", HumanBytes(bytes_total))?;
+        }
+        if let Some(rate) = self.bytes_per_second() {
+            write!(f, " ({}/s)", HumanBytes(rate))?;
+        }
+        Ok(())
+    }
+}
+
+struct HumanBytes(u64);
+
+impl fmt::Display for HumanBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut value = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{value:.1} {}", UNITS[unit])
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use petgraph::visit::{VisitMap, Visitable};
+
+use crate::{RunningWorkunitGraph, SpanId, WorkunitState};
+
+///
+/// A visible running workunit which has made no observable progress for longer than a threshold:
+/// see `WorkunitStore::stalled_workunits`.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StalledWorkunit {
+    pub span_id: SpanId,
+    pub name: &'static str,
+    pub description: String,
+    pub stalled_for: Duration,
+    /// The names and descriptions of the visible ancestors of the workunit (such as the rule which
+    /// is running a process), nearest first.
+    pub ancestors: Vec<(&'static str, String)>,
+}
+
+///
+/// Records the last time at which each running workunit made observable progress: either one of
+/// its children started or completed, or the number of bytes that it had transferred changed.
+///
+#[derive(Default)]
+pub(crate) struct ProgressTracker {
+    last_progress: HashMap<SpanId, SystemTime>,
+    transferred_bytes: HashMap<SpanId, u64>,
+}
+
+impl ProgressTracker {
+    pub(crate) fn record(&mut self, span_id: SpanId, time: SystemTime) {
+        let last_progress = self.last_progress.entry(span_id).or_insert(time);
+        if *last_progress < time {
+            *last_progress = time;
+        }
+    }
+
+    pub(crate) fn remove(&mut self, span_id: SpanId) {
+        self.last_progress.remove(&span_id);
+        self.transferred_bytes.remove(&span_id);
+    }
+
+    ///
+    /// Records progress for each of the given workunits whose total transferred bytes have changed
+    /// since they were last observed.
+    ///
+    pub(crate) fn observe_transfers(
+        &mut self,
+        transferred_bytes: HashMap<SpanId, u64>,
+        now: SystemTime,
+    ) {
+        for (span_id, bytes) in transferred_bytes {
+            if self.transferred_bytes.insert(span_id, bytes) != Some(bytes) {
+                self.record(span_id, now);
+            }
+        }
+    }
+
+    ///
+    /// Returns how long the given running workunit and all of its running descendants have gone
+    /// without making progress.
+    ///
+    /// Returns None if the workunit is not running, or if it or any of its descendants is blocked
+    /// (on its dependencies or a semaphore, for example), since waiting in a queue is not a stall.
+    ///
+    pub(crate) fn stalled_for(
+        &self,
+        graph: &RunningWorkunitGraph,
+        span_id: SpanId,
+        now: SystemTime,
+    ) -> Option<Duration> {
+        graph.get(span_id)?;
+        let mut latest_progress: Option<SystemTime> = None;
+        let mut visited = graph.graph.visit_map();
+        let mut to_visit = vec![span_id];
+        while let Some(current_span_id) = to_visit.pop() {
+            let Some((node, _, workunit)) = graph.entries.get(&current_span_id) else {
+                continue;
+            };
+            if !visited.visit(*node) {
+                continue;
+            }
+            if let Some(workunit) = workunit {
+                if workunit.state.blocked() {
+                    return None;
+                }
+                if let WorkunitState::Started { start_time, .. } = workunit.state {
+                    latest_progress = latest_progress.max(Some(start_time));
+                }
+            }
+            if let Some(last_progress) = self.last_progress.get(&current_span_id) {
+                latest_progress = latest_progress.max(Some(*last_progress));
+            }
+            to_visit.extend(
+                graph
+                    .graph
+                    .neighbors_directed(*node, petgraph::Direction::Outgoing)
+                    .map(|child_node_id| graph.graph[child_node_id]),
+            );
+        }
+        now.duration_since(latest_progress?).ok()
+    }
+}